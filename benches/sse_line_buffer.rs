@@ -0,0 +1,42 @@
+//! SseLineBuffer throughput
+//!
+//! Feeds streams of many small SSE lines at increasing sizes and reports the
+//! time per line; with linear draining the per-line cost stays flat as the
+//! stream grows. Run with `cargo bench --bench sse_line_buffer`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use sentinel::streaming::SseLineBuffer;
+
+const LINE: &[u8] = b"data: {\"choices\":[{\"delta\":{\"content\":\"token\"}}]}\n\n";
+
+/// Bytes of `lines` SSE events, re-chunked at an awkward size so lines straddle chunks
+fn stream_chunks(lines: usize) -> Vec<Vec<u8>> {
+    let stream: Vec<u8> = LINE.iter().copied().cycle().take(LINE.len() * lines).collect();
+    stream.chunks(1000).map(<[u8]>::to_vec).collect()
+}
+
+fn feed_all(chunks: &[Vec<u8>]) -> Duration {
+    let mut buffer = SseLineBuffer::new();
+    let start = Instant::now();
+    for chunk in chunks {
+        black_box(buffer.feed(chunk).unwrap());
+    }
+    start.elapsed()
+}
+
+fn main() {
+    println!("{:>10} {:>12} {:>12}", "lines", "total", "per line");
+    for lines in [10_000, 40_000, 160_000, 640_000] {
+        let chunks = stream_chunks(lines);
+        // Best of three to damp scheduler noise
+        let elapsed = (0..3).map(|_| feed_all(&chunks)).min().unwrap();
+        println!(
+            "{:>10} {:>10.2}ms {:>10.0}ns",
+            lines,
+            elapsed.as_secs_f64() * 1e3,
+            elapsed.as_nanos() as f64 / lines as f64
+        );
+    }
+}
@@ -0,0 +1,39 @@
+//! OpenAI request translation throughput
+//!
+//! Translates native conversations of increasing length, including the
+//! 500-message fixture used for sizing, and reports the time per message.
+//! Run with `cargo bench --bench translate_request --features test-utils`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use sentinel::native::request::ChatCompletionRequest;
+use sentinel::native::translate::{MessageTranslator, OpenAITranslator};
+use sentinel::testing::long_conversation;
+
+/// Translations per timing, so short conversations still measure something
+const ROUNDS: usize = 20;
+
+fn translate_all(request: &ChatCompletionRequest) -> Duration {
+    let translator = OpenAITranslator::new();
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        black_box(translator.translate_request(black_box(request)).unwrap());
+    }
+    start.elapsed() / ROUNDS as u32
+}
+
+fn main() {
+    println!("{:>10} {:>12} {:>12}", "messages", "total", "per message");
+    for messages in [50, 500, 5_000] {
+        let request = long_conversation(messages);
+        // Best of three to damp scheduler noise
+        let elapsed = (0..3).map(|_| translate_all(&request)).min().unwrap();
+        println!(
+            "{:>10} {:>10.2}ms {:>10.0}ns",
+            messages,
+            elapsed.as_secs_f64() * 1e3,
+            elapsed.as_nanos() as f64 / messages as f64
+        );
+    }
+}
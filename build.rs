@@ -0,0 +1,42 @@
+//! Embeds build metadata for `/health` and `sentinel --version`
+//!
+//! - `SENTINEL_GIT_SHA`: `GIT_SHA` if set (Docker builds have no `.git`),
+//!   else `git rev-parse --short HEAD`, else `unknown`
+//! - `SENTINEL_BUILD_UNIX`: `SOURCE_DATE_EPOCH` if set, else the time this
+//!   script last ran
+
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=GIT_SHA");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
+
+    let git_sha = std::env::var("GIT_SHA")
+        .ok()
+        .filter(|sha| !sha.trim().is_empty())
+        .or_else(|| {
+            Command::new("git")
+                .args(["rev-parse", "--short", "HEAD"])
+                .output()
+                .ok()
+                .filter(|output| output.status.success())
+                .and_then(|output| String::from_utf8(output.stdout).ok())
+        })
+        .map(|sha| sha.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=SENTINEL_GIT_SHA={}", git_sha);
+
+    let built_at = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.trim().parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default()
+        });
+    println!("cargo:rustc-env=SENTINEL_BUILD_UNIX={}", built_at);
+}
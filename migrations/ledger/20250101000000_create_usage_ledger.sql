@@ -0,0 +1,16 @@
+-- Local usage ledger: one row per tracked request
+-- Kept portable between SQLite and Postgres (no autoincrement, integer timestamps)
+CREATE TABLE IF NOT EXISTS usage_ledger (
+    request_id TEXT PRIMARY KEY,
+    user_hash TEXT NOT NULL,
+    model TEXT,
+    input_tokens BIGINT NOT NULL,
+    output_tokens BIGINT NOT NULL,
+    requests BIGINT NOT NULL,
+    created_at BIGINT NOT NULL,
+    delivery_status TEXT NOT NULL,
+    updated_at BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_usage_ledger_created_at ON usage_ledger (created_at);
+CREATE INDEX IF NOT EXISTS idx_usage_ledger_delivery_status ON usage_ledger (delivery_status);
@@ -0,0 +1,2 @@
+-- The `user` value forwarded to the provider (a hash, see proxy::upstream_user)
+ALTER TABLE usage_ledger ADD COLUMN upstream_user TEXT;
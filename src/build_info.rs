@@ -0,0 +1,101 @@
+//! Build metadata
+//!
+//! The version, git SHA and build time embedded by `build.rs`, plus the
+//! optional features compiled in. Reported by `/health` and
+//! `sentinel --version` to confirm which build is live.
+
+use chrono::DateTime;
+use serde::Serialize;
+
+/// What this binary was built from
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct BuildInfo {
+    /// Crate version
+    pub version: &'static str,
+    /// Short git SHA (`unknown` when built outside a checkout without `GIT_SHA`)
+    pub git_sha: &'static str,
+    /// RFC 3339 build time
+    pub built_at: String,
+    /// Optional cargo features compiled in
+    pub features: Vec<&'static str>,
+}
+
+impl BuildInfo {
+    /// Metadata of the running binary
+    pub fn current() -> Self {
+        let built_at = env!("SENTINEL_BUILD_UNIX")
+            .parse::<i64>()
+            .ok()
+            .and_then(|unix| DateTime::from_timestamp(unix, 0))
+            .map(|time| time.to_rfc3339())
+            .unwrap_or_default();
+
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            git_sha: env!("SENTINEL_GIT_SHA"),
+            built_at,
+            features: compiled_features(),
+        }
+    }
+
+    /// One-line summary printed by `sentinel --version`
+    pub fn version_line(&self) -> String {
+        let features = if self.features.is_empty() {
+            "none".to_string()
+        } else {
+            self.features.join(", ")
+        };
+        format!(
+            "sentinel {} ({}, built {}, features: {})",
+            self.version, self.git_sha, self.built_at, features
+        )
+    }
+}
+
+/// Optional cargo features and whether they were compiled in
+const OPTIONAL_FEATURES: &[(&str, bool)] = &[
+    ("chaos", cfg!(feature = "chaos")),
+    ("ledger", cfg!(feature = "ledger")),
+    ("sigv4", cfg!(feature = "sigv4")),
+];
+
+fn compiled_features() -> Vec<&'static str> {
+    OPTIONAL_FEATURES
+        .iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| *name)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_current_build_info() {
+        let info = BuildInfo::current();
+        assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+        assert!(!info.git_sha.is_empty());
+        assert!(DateTime::parse_from_rfc3339(&info.built_at).is_ok());
+        assert_eq!(info.features.contains(&"sigv4"), cfg!(feature = "sigv4"));
+    }
+
+    #[test]
+    fn test_version_line() {
+        let info = BuildInfo {
+            version: "1.2.3",
+            git_sha: "abc1234",
+            built_at: "2026-10-15T12:00:00+00:00".to_string(),
+            features: vec!["ledger", "sigv4"],
+        };
+        assert_eq!(
+            info.version_line(),
+            "sentinel 1.2.3 (abc1234, built 2026-10-15T12:00:00+00:00, features: ledger, sigv4)"
+        );
+        let info = BuildInfo {
+            features: Vec::new(),
+            ..info
+        };
+        assert!(info.version_line().ends_with("features: none)"));
+    }
+}
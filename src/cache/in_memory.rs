@@ -9,6 +9,8 @@ use std::time::{Duration, Instant};
 
 use serde::{de::DeserializeOwned, Serialize};
 
+use crate::cache::schema::{self, Schema};
+use crate::clock::{system_clock, SharedClock};
 use crate::error::AppResult;
 
 /// Entry in the in-memory cache with expiration
@@ -18,8 +20,17 @@ struct CacheEntry {
 }
 
 impl CacheEntry {
-    fn is_expired(&self) -> bool {
-        self.expires_at.map(|exp| Instant::now() > exp).unwrap_or(false)
+    fn new(value: String, ttl_seconds: u64, now: Instant) -> Self {
+        let expires_at = if ttl_seconds > 0 {
+            Some(now + Duration::from_secs(ttl_seconds))
+        } else {
+            None
+        };
+        Self { value, expires_at }
+    }
+
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires_at.map(|exp| now > exp).unwrap_or(false)
     }
 }
 
@@ -34,6 +45,7 @@ impl CacheEntry {
 pub struct InMemoryCache {
     data: RwLock<HashMap<String, CacheEntry>>,
     default_ttl: u64,
+    clock: SharedClock,
 }
 
 impl InMemoryCache {
@@ -42,15 +54,26 @@ impl InMemoryCache {
         Self {
             data: RwLock::new(HashMap::new()),
             default_ttl,
+            clock: system_clock(),
         }
     }
 
+    /// Use the given clock for expiry, so tests can expire keys without sleeping
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn now(&self) -> Instant {
+        self.clock.instant_now()
+    }
+
     /// Get a value from cache
     pub async fn get<T: DeserializeOwned>(&self, key: &str) -> AppResult<Option<T>> {
         let data = self.data.read().unwrap();
 
         match data.get(key) {
-            Some(entry) if !entry.is_expired() => {
+            Some(entry) if !entry.is_expired(self.now()) => {
                 let parsed: T = serde_json::from_str(&entry.value)?;
                 Ok(Some(parsed))
             }
@@ -71,23 +94,138 @@ impl InMemoryCache {
         ttl_seconds: u64,
     ) -> AppResult<()> {
         let serialized = serde_json::to_string(value)?;
-        let expires_at = if ttl_seconds > 0 {
-            Some(Instant::now() + Duration::from_secs(ttl_seconds))
-        } else {
-            None
-        };
 
         let mut data = self.data.write().unwrap();
-        data.insert(
-            key.to_string(),
-            CacheEntry {
-                value: serialized,
-                expires_at,
-            },
-        );
+        data.insert(key.to_string(), CacheEntry::new(serialized, ttl_seconds, self.now()));
+        Ok(())
+    }
+
+    /// Set a value only if the key does not exist yet (or has expired)
+    ///
+    /// Returns false when the key already holds a value.
+    pub async fn set_if_absent<T: Serialize>(
+        &self,
+        key: &str,
+        value: &T,
+        ttl_seconds: u64,
+    ) -> AppResult<bool> {
+        let serialized = serde_json::to_string(value)?;
+        let mut data = self.data.write().unwrap();
+
+        if data.get(key).is_some_and(|entry| !entry.is_expired(self.now())) {
+            return Ok(false);
+        }
+
+        data.insert(key.to_string(), CacheEntry::new(serialized, ttl_seconds, self.now()));
+        Ok(true)
+    }
+
+    /// Get a value stored with its schema version
+    ///
+    /// Mirrors `RedisCache::get_versioned`.
+    pub async fn get_versioned<T: Schema>(&self, key: &str) -> AppResult<Option<T>> {
+        let data = self.data.read().unwrap();
+        Ok(data
+            .get(key)
+            .filter(|entry| !entry.is_expired(self.now()))
+            .and_then(|entry| schema::decode(&entry.value).ok()))
+    }
+
+    /// Set a value with its schema version and a custom TTL
+    pub async fn set_versioned<T: Schema>(
+        &self,
+        key: &str,
+        value: &T,
+        ttl_seconds: u64,
+    ) -> AppResult<()> {
+        self.set_raw(key, schema::encode(value)?, ttl_seconds);
         Ok(())
     }
 
+    /// Set a value with its schema version only if the key does not exist yet
+    pub async fn set_versioned_if_absent<T: Schema>(
+        &self,
+        key: &str,
+        value: &T,
+        ttl_seconds: u64,
+    ) -> AppResult<bool> {
+        let serialized = schema::encode(value)?;
+        let mut data = self.data.write().unwrap();
+
+        if data.get(key).is_some_and(|entry| !entry.is_expired(self.now())) {
+            return Ok(false);
+        }
+
+        data.insert(key.to_string(), CacheEntry::new(serialized, ttl_seconds, self.now()));
+        Ok(true)
+    }
+
+    /// Store a raw string as is, e.g. an entry as another release would write it
+    pub fn set_raw(&self, key: &str, value: String, ttl_seconds: u64) {
+        let mut data = self.data.write().unwrap();
+        data.insert(key.to_string(), CacheEntry::new(value, ttl_seconds, self.now()));
+    }
+
+    /// The raw string stored under a key
+    pub fn get_raw(&self, key: &str) -> Option<String> {
+        let data = self.data.read().unwrap();
+        data.get(key)
+            .filter(|entry| !entry.is_expired(self.now()))
+            .map(|entry| entry.value.clone())
+    }
+
+    /// Replace an entry with a versioned value only if it still holds `expected_raw`
+    ///
+    /// Mirrors `RedisCache::set_versioned_if_raw`.
+    pub async fn set_versioned_if_raw<T: Schema>(
+        &self,
+        key: &str,
+        expected_raw: &str,
+        value: &T,
+        ttl_seconds: u64,
+    ) -> AppResult<bool> {
+        let serialized = schema::encode(value)?;
+        let mut data = self.data.write().unwrap();
+
+        if !data
+            .get(key)
+            .is_some_and(|entry| !entry.is_expired(self.now()) && entry.value == expected_raw)
+        {
+            return Ok(false);
+        }
+        data.insert(key.to_string(), CacheEntry::new(serialized, ttl_seconds, self.now()));
+        Ok(true)
+    }
+
+    /// Replace a versioned JSON object only if its `version` field still matches
+    ///
+    /// Mirrors `RedisCache::set_if_version`: a missing `version` counts as 0,
+    /// and a missing key never matches.
+    pub async fn set_if_version<T: Schema>(
+        &self,
+        key: &str,
+        expected_version: u64,
+        value: &T,
+        ttl_seconds: u64,
+    ) -> AppResult<bool> {
+        let serialized = schema::encode(value)?;
+        let mut data = self.data.write().unwrap();
+
+        let current_version = match data.get(key).filter(|entry| !entry.is_expired(self.now())) {
+            Some(entry) => serde_json::from_str::<serde_json::Value>(schema::split(&entry.value).1)?
+                .get("version")
+                .and_then(|v| v.as_u64())
+                .unwrap_or(0),
+            None => return Ok(false),
+        };
+        if current_version != expected_version {
+            return Ok(false);
+        }
+
+        data.insert(key.to_string(), CacheEntry::new(serialized, ttl_seconds, self.now()));
+        Ok(true)
+    }
+
     /// Delete a key from cache
     pub async fn delete(&self, key: &str) -> AppResult<()> {
         let mut data = self.data.write().unwrap();
@@ -95,11 +233,26 @@ impl InMemoryCache {
         Ok(())
     }
 
+    /// Delete a key only if it still holds `value`
+    pub async fn delete_if_value<T: Serialize>(&self, key: &str, value: &T) -> AppResult<bool> {
+        let serialized = serde_json::to_string(value)?;
+        let mut data = self.data.write().unwrap();
+
+        if !data
+            .get(key)
+            .is_some_and(|entry| !entry.is_expired(self.now()) && entry.value == serialized)
+        {
+            return Ok(false);
+        }
+        data.remove(key);
+        Ok(true)
+    }
+
     /// Check if a key exists (and is not expired)
     pub async fn exists(&self, key: &str) -> AppResult<bool> {
         let data = self.data.read().unwrap();
         match data.get(key) {
-            Some(entry) => Ok(!entry.is_expired()),
+            Some(entry) => Ok(!entry.is_expired(self.now())),
             None => Ok(false),
         }
     }
@@ -110,7 +263,7 @@ impl InMemoryCache {
 
         let current: i64 = data
             .get(key)
-            .filter(|e| !e.is_expired())
+            .filter(|e| !e.is_expired(self.now()))
             .and_then(|e| e.value.parse().ok())
             .unwrap_or(0);
 
@@ -131,12 +284,63 @@ impl InMemoryCache {
         let mut data = self.data.write().unwrap();
 
         if let Some(entry) = data.get_mut(key) {
-            entry.expires_at = Some(Instant::now() + Duration::from_secs(seconds));
+            entry.expires_at = Some(self.now() + Duration::from_secs(seconds));
         }
 
         Ok(())
     }
 
+    /// Set expiry on a key only if it still holds `value`
+    pub async fn expire_if_value<T: Serialize>(
+        &self,
+        key: &str,
+        value: &T,
+        seconds: u64,
+    ) -> AppResult<bool> {
+        let serialized = serde_json::to_string(value)?;
+        let now = self.now();
+        let mut data = self.data.write().unwrap();
+
+        match data
+            .get_mut(key)
+            .filter(|entry| !entry.is_expired(now) && entry.value == serialized)
+        {
+            Some(entry) => {
+                entry.expires_at = Some(now + Duration::from_secs(seconds));
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Add a member to a set and (re)set the set's expiry
+    ///
+    /// Sets are stored as a JSON array of members.
+    pub async fn sadd(&self, key: &str, member: &str, ttl_seconds: u64) -> AppResult<()> {
+        let mut members = self.smembers(key).await?;
+        if !members.iter().any(|m| m == member) {
+            members.push(member.to_string());
+        }
+        self.set_with_ttl(key, &members, ttl_seconds).await
+    }
+
+    /// Members of a set (empty when the key doesn't exist)
+    pub async fn smembers(&self, key: &str) -> AppResult<Vec<String>> {
+        Ok(self.get::<Vec<String>>(key).await?.unwrap_or_default())
+    }
+
+    /// Remove members from a set, keeping its expiry
+    pub async fn srem(&self, key: &str, members: &[String]) -> AppResult<()> {
+        let mut data = self.data.write().unwrap();
+        let Some(entry) = data.get_mut(key).filter(|e| !e.is_expired(self.now())) else {
+            return Ok(());
+        };
+        let mut current: Vec<String> = serde_json::from_str(&entry.value)?;
+        current.retain(|m| !members.contains(m));
+        entry.value = serde_json::to_string(&current)?;
+        Ok(())
+    }
+
     /// Clear all entries (useful for test isolation)
     #[allow(dead_code)]
     pub fn clear(&self) {
@@ -235,4 +439,66 @@ mod tests {
         assert!(!cache.exists("key1").await.unwrap());
         assert!(!cache.exists("key2").await.unwrap());
     }
+
+    #[tokio::test]
+    async fn test_set_if_absent() {
+        let cache = InMemoryCache::new(60);
+
+        assert!(cache.set_if_absent("key1", &"first", 60).await.unwrap());
+        assert!(!cache.set_if_absent("key1", &"second", 60).await.unwrap());
+
+        let result: Option<String> = cache.get("key1").await.unwrap();
+        assert_eq!(result, Some("first".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_delete_and_expire_if_value() {
+        let cache = InMemoryCache::new(60);
+        cache.set("lock", &"owner-a").await.unwrap();
+
+        // Another value doesn't match
+        assert!(!cache.expire_if_value("lock", &"owner-b", 5).await.unwrap());
+        assert!(!cache.delete_if_value("lock", &"owner-b").await.unwrap());
+        assert!(cache.exists("lock").await.unwrap());
+
+        assert!(cache.expire_if_value("lock", &"owner-a", 5).await.unwrap());
+        assert!(cache.delete_if_value("lock", &"owner-a").await.unwrap());
+        assert!(!cache.exists("lock").await.unwrap());
+        assert!(!cache.delete_if_value("lock", &"owner-a").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_set_if_version() {
+        #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+        struct Doc {
+            name: String,
+            #[serde(default)]
+            version: u64,
+        }
+        impl Schema for Doc {
+            const NAME: &'static str = "doc";
+            const VERSION: u32 = 2;
+        }
+        let doc = |name: &str, version| Doc {
+            name: name.to_string(),
+            version,
+        };
+
+        let cache = InMemoryCache::new(60);
+
+        // Missing key never matches
+        assert!(!cache.set_if_version("key1", 0, &doc("b", 1), 60).await.unwrap());
+
+        // Missing version field counts as 0, unprefixed entries are read too
+        cache.set("key1", &serde_json::json!({"name": "a"})).await.unwrap();
+        assert!(cache.set_if_version("key1", 0, &doc("b", 1), 60).await.unwrap());
+        assert!(cache.get_raw("key1").unwrap().starts_with("v2|"));
+
+        // Stale version is rejected
+        assert!(!cache.set_if_version("key1", 0, &doc("c", 2), 60).await.unwrap());
+        assert!(cache.set_if_version("key1", 1, &doc("c", 2), 60).await.unwrap());
+
+        let result: Option<Doc> = cache.get_versioned("key1").await.unwrap();
+        assert_eq!(result, Some(doc("c", 2)));
+    }
 }
@@ -1,16 +1,20 @@
 //! Cache module
 //!
-//! Provides caching for user limits and JWT validation.
+//! Provides caching for user limits and JWT validation, and bulk warming of
+//! the limits cache.
 //! Supports Redis-based caching for production and in-memory caching for testing.
 
 pub mod redis;
+pub mod schema;
 pub mod subscription;
+pub mod warm;
 
 #[cfg(any(test, feature = "test-utils"))]
 mod in_memory;
 
 pub use self::redis::RedisCache;
 pub use self::subscription::SubscriptionCache;
+pub use self::warm::CacheWarmer;
 
 #[cfg(any(test, feature = "test-utils"))]
 pub use self::in_memory::InMemoryCache;
@@ -5,23 +5,100 @@
 use redis::AsyncCommands;
 use serde::{de::DeserializeOwned, Serialize};
 
+#[cfg(feature = "chaos")]
+use std::sync::Arc;
+
+use crate::cache::schema::{self, Schema};
+#[cfg(feature = "chaos")]
+use crate::chaos::{ChaosInjector, Target};
 use crate::error::AppResult;
 
+/// Compare-and-set on the `version` field of a JSON value
+///
+/// The stored value may carry a schema version prefix (`v2|{...}`).
+const SET_IF_VERSION_SCRIPT: &str = r#"
+local current = redis.call('GET', KEYS[1])
+if not current then
+    return 0
+end
+local json = string.gsub(current, '^v%d+|', '', 1)
+local version = cjson.decode(json).version or 0
+if tonumber(version) ~= tonumber(ARGV[1]) then
+    return 0
+end
+redis.call('SET', KEYS[1], ARGV[2], 'EX', ARGV[3])
+return 1
+"#;
+
+/// Delete a key only if it still holds the given value
+const DELETE_IF_VALUE_SCRIPT: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('DEL', KEYS[1])
+end
+return 0
+"#;
+
+/// Replace a key only if it still holds the given raw value
+const SET_IF_RAW_SCRIPT: &str = r#"
+if redis.call('GET', KEYS[1]) ~= ARGV[1] then
+    return 0
+end
+redis.call('SET', KEYS[1], ARGV[2], 'EX', ARGV[3])
+return 1
+"#;
+
+/// Set a key's expiry only if it still holds the given value
+const EXPIRE_IF_VALUE_SCRIPT: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('EXPIRE', KEYS[1], ARGV[2])
+end
+return 0
+"#;
+
 /// Redis cache wrapper
 pub struct RedisCache {
     conn: redis::aio::ConnectionManager,
     default_ttl: u64,
+    /// Fault rules consulted before each operation (`CHAOS_ENABLED`)
+    #[cfg(feature = "chaos")]
+    chaos: Option<Arc<ChaosInjector>>,
 }
 
 impl RedisCache {
     /// Create a new Redis cache
     pub fn new(conn: redis::aio::ConnectionManager, default_ttl: u64) -> Self {
-        Self { conn, default_ttl }
+        Self {
+            conn,
+            default_ttl,
+            #[cfg(feature = "chaos")]
+            chaos: None,
+        }
+    }
+
+    /// Run every operation through the `redis` fault rules of `chaos` first
+    #[cfg(feature = "chaos")]
+    pub fn with_chaos(mut self, chaos: Arc<ChaosInjector>) -> Self {
+        self.chaos = Some(chaos);
+        self
+    }
+
+    /// Connection for one operation, after the `redis` fault rules (`chaos` feature)
+    async fn connection(&self, operation: &str) -> AppResult<redis::aio::ConnectionManager> {
+        #[cfg(feature = "chaos")]
+        if let Some(chaos) = &self.chaos {
+            chaos
+                .intercept(Target::Redis, operation)
+                .await
+                .map_err(|injected| injected.redis_error())?;
+        }
+        #[cfg(not(feature = "chaos"))]
+        let _ = operation;
+        Ok(self.conn.clone())
     }
 
     /// Get a value from cache
     pub async fn get<T: DeserializeOwned>(&self, key: &str) -> AppResult<Option<T>> {
-        let mut conn = self.conn.clone();
+        let mut conn = self.connection("get").await?;
         let value: Option<String> = conn.get(key).await?;
 
         match value {
@@ -45,50 +122,231 @@ impl RedisCache {
         value: &T,
         ttl_seconds: u64,
     ) -> AppResult<()> {
-        let mut conn = self.conn.clone();
+        let mut conn = self.connection("set_with_ttl").await?;
         let serialized = serde_json::to_string(value)?;
         let _: () = conn.set_ex(key, serialized, ttl_seconds).await?;
         Ok(())
     }
 
+    /// Set a value only if the key does not exist yet (SET NX EX)
+    ///
+    /// Returns false when the key already holds a value.
+    pub async fn set_if_absent<T: Serialize>(
+        &self,
+        key: &str,
+        value: &T,
+        ttl_seconds: u64,
+    ) -> AppResult<bool> {
+        let mut conn = self.connection("set_if_absent").await?;
+        let serialized = serde_json::to_string(value)?;
+        let result: Option<String> = redis::cmd("SET")
+            .arg(key)
+            .arg(serialized)
+            .arg("NX")
+            .arg("EX")
+            .arg(ttl_seconds)
+            .query_async(&mut conn)
+            .await?;
+        Ok(result.is_some())
+    }
+
+    /// Get a value stored with its schema version (see `cache::schema`)
+    ///
+    /// Entries from older versions are migrated; entries from newer versions
+    /// or that don't parse read as a miss.
+    pub async fn get_versioned<T: Schema>(&self, key: &str) -> AppResult<Option<T>> {
+        let mut conn = self.connection("get_versioned").await?;
+        let value: Option<String> = conn.get(key).await?;
+        Ok(value.and_then(|raw| schema::decode(&raw).ok()))
+    }
+
+    /// Set a value with its schema version and a custom TTL
+    pub async fn set_versioned<T: Schema>(
+        &self,
+        key: &str,
+        value: &T,
+        ttl_seconds: u64,
+    ) -> AppResult<()> {
+        let mut conn = self.connection("set_versioned").await?;
+        let _: () = conn.set_ex(key, schema::encode(value)?, ttl_seconds).await?;
+        Ok(())
+    }
+
+    /// Set a value with its schema version only if the key does not exist yet
+    ///
+    /// Returns false when the key already holds a value.
+    pub async fn set_versioned_if_absent<T: Schema>(
+        &self,
+        key: &str,
+        value: &T,
+        ttl_seconds: u64,
+    ) -> AppResult<bool> {
+        let mut conn = self.connection("set_versioned_if_absent").await?;
+        let result: Option<String> = redis::cmd("SET")
+            .arg(key)
+            .arg(schema::encode(value)?)
+            .arg("NX")
+            .arg("EX")
+            .arg(ttl_seconds)
+            .query_async(&mut conn)
+            .await?;
+        Ok(result.is_some())
+    }
+
+    /// Replace a versioned JSON object only if its `version` field still matches
+    ///
+    /// The compare and the write run atomically in a Lua script. A missing
+    /// `version` field counts as 0. The value is written with its schema
+    /// version. Returns false when the key is gone or another writer got
+    /// there first.
+    pub async fn set_if_version<T: Schema>(
+        &self,
+        key: &str,
+        expected_version: u64,
+        value: &T,
+        ttl_seconds: u64,
+    ) -> AppResult<bool> {
+        let mut conn = self.connection("set_if_version").await?;
+        let serialized = schema::encode(value)?;
+        let swapped: i64 = redis::Script::new(SET_IF_VERSION_SCRIPT)
+            .key(key)
+            .arg(expected_version)
+            .arg(serialized)
+            .arg(ttl_seconds)
+            .invoke_async(&mut conn)
+            .await?;
+        Ok(swapped == 1)
+    }
+
+    /// The raw string stored under a key, schema prefix included
+    pub async fn get_raw(&self, key: &str) -> AppResult<Option<String>> {
+        let mut conn = self.connection("get_raw").await?;
+        let value: Option<String> = conn.get(key).await?;
+        Ok(value)
+    }
+
+    /// Replace an entry with a versioned value only if it still holds `expected_raw`
+    ///
+    /// For taking over an entry that doesn't parse, where there is no
+    /// `version` to compare. Returns false when the key is gone or changed.
+    pub async fn set_versioned_if_raw<T: Schema>(
+        &self,
+        key: &str,
+        expected_raw: &str,
+        value: &T,
+        ttl_seconds: u64,
+    ) -> AppResult<bool> {
+        let mut conn = self.connection("set_versioned_if_raw").await?;
+        let swapped: i64 = redis::Script::new(SET_IF_RAW_SCRIPT)
+            .key(key)
+            .arg(expected_raw)
+            .arg(schema::encode(value)?)
+            .arg(ttl_seconds)
+            .invoke_async(&mut conn)
+            .await?;
+        Ok(swapped == 1)
+    }
+
     /// Delete a key from cache
     pub async fn delete(&self, key: &str) -> AppResult<()> {
-        let mut conn = self.conn.clone();
+        let mut conn = self.connection("delete").await?;
         let _: () = conn.del(key).await?;
         Ok(())
     }
 
+    /// Delete a key only if it still holds `value` (e.g. a lock we own)
+    ///
+    /// Returns false when the key is gone or holds something else.
+    pub async fn delete_if_value<T: Serialize>(&self, key: &str, value: &T) -> AppResult<bool> {
+        let mut conn = self.connection("delete_if_value").await?;
+        let deleted: i64 = redis::Script::new(DELETE_IF_VALUE_SCRIPT)
+            .key(key)
+            .arg(serde_json::to_string(value)?)
+            .invoke_async(&mut conn)
+            .await?;
+        Ok(deleted == 1)
+    }
+
     /// Check if a key exists
     pub async fn exists(&self, key: &str) -> AppResult<bool> {
-        let mut conn = self.conn.clone();
+        let mut conn = self.connection("exists").await?;
         let exists: bool = conn.exists(key).await?;
         Ok(exists)
     }
 
     /// Increment a counter
     pub async fn incr(&self, key: &str, delta: i64) -> AppResult<i64> {
-        let mut conn = self.conn.clone();
+        let mut conn = self.connection("incr").await?;
         let value: i64 = conn.incr(key, delta).await?;
         Ok(value)
     }
 
     /// Set expiry on a key
     pub async fn expire(&self, key: &str, seconds: u64) -> AppResult<()> {
-        let mut conn = self.conn.clone();
+        let mut conn = self.connection("expire").await?;
         let _: () = conn.expire(key, seconds as i64).await?;
         Ok(())
     }
 
+    /// Set expiry on a key only if it still holds `value`
+    ///
+    /// Returns false when the key is gone or holds something else.
+    pub async fn expire_if_value<T: Serialize>(
+        &self,
+        key: &str,
+        value: &T,
+        seconds: u64,
+    ) -> AppResult<bool> {
+        let mut conn = self.connection("expire_if_value").await?;
+        let updated: i64 = redis::Script::new(EXPIRE_IF_VALUE_SCRIPT)
+            .key(key)
+            .arg(serde_json::to_string(value)?)
+            .arg(seconds)
+            .invoke_async(&mut conn)
+            .await?;
+        Ok(updated == 1)
+    }
+
+    /// Add a member to a set and (re)set the set's expiry
+    pub async fn sadd(&self, key: &str, member: &str, ttl_seconds: u64) -> AppResult<()> {
+        let mut conn = self.connection("sadd").await?;
+        let _: () = redis::pipe()
+            .sadd(key, member)
+            .ignore()
+            .expire(key, ttl_seconds as i64)
+            .ignore()
+            .query_async(&mut conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Members of a set (empty when the key doesn't exist)
+    pub async fn smembers(&self, key: &str) -> AppResult<Vec<String>> {
+        let mut conn = self.connection("smembers").await?;
+        let members: Vec<String> = conn.smembers(key).await?;
+        Ok(members)
+    }
+
+    /// Remove members from a set
+    pub async fn srem(&self, key: &str, members: &[String]) -> AppResult<()> {
+        if members.is_empty() {
+            return Ok(());
+        }
+        let mut conn = self.connection("srem").await?;
+        let _: () = conn.srem(key, members).await?;
+        Ok(())
+    }
+
     /// Get TTL remaining on a key (returns -2 if key doesn't exist, -1 if no TTL)
     pub async fn ttl(&self, key: &str) -> AppResult<i64> {
-        let mut conn = self.conn.clone();
+        let mut conn = self.connection("ttl").await?;
         let ttl: i64 = conn.ttl(key).await?;
         Ok(ttl)
     }
 
     /// Scan for keys matching a pattern (limited to first 100 matches for safety)
     pub async fn scan_keys(&self, pattern: &str) -> AppResult<Vec<String>> {
-        let mut conn = self.conn.clone();
+        let mut conn = self.connection("scan_keys").await?;
         let mut keys = Vec::new();
         let mut cursor = 0u64;
 
@@ -118,7 +376,7 @@ impl RedisCache {
 
     /// Check if Redis is connected and responsive
     pub async fn ping(&self) -> AppResult<bool> {
-        let mut conn = self.conn.clone();
+        let mut conn = self.connection("ping").await?;
         let result: String = redis::cmd("PING").query_async(&mut conn).await?;
         Ok(result == "PONG")
     }
@@ -141,15 +399,91 @@ pub mod keys {
         format!("sentinel:profile:{}", jwt_hash)
     }
 
+    /// External id last seen for a token, kept past its profile so both can
+    /// be fetched together when the profile expires
+    pub fn profile_hint(jwt_hash: &str) -> String {
+        format!("sentinel:profile-hint:{}", jwt_hash)
+    }
+
     /// Session cache key for provider stickiness
     pub fn session(conversation_id: &str) -> String {
         format!("sentinel:session:{}", conversation_id)
     }
 
+    /// Stored messages of a conversation (with session history enabled)
+    pub fn session_history(conversation_id: &str) -> String {
+        format!("sentinel:session-history:{}", conversation_id)
+    }
+
+    /// Lock held by the one stream running in a conversation
+    pub fn stream_lock(conversation_id: &str) -> String {
+        format!("sentinel:stream-lock:{}", conversation_id)
+    }
+
+    /// Set of a user's session (conversation) IDs
+    pub fn user_sessions(external_id: &str) -> String {
+        format!("sentinel:sessions:{}", external_id)
+    }
+
     /// Tier configuration cache key (global, not per-user)
     pub fn tier_config() -> &'static str {
         "sentinel:tiers:config"
     }
+
+    /// Last-known-good tier config, kept for Zion outages
+    pub fn tier_config_standby() -> &'static str {
+        "sentinel:tiers:config:standby"
+    }
+
+    /// Sighting of an upstream snapshot serving a requested model
+    pub fn model_snapshot(requested: &str, served: &str) -> String {
+        format!("sentinel:snapshot:{}:{}", requested, served)
+    }
+
+    /// Daily usage counter for a user (`field` is requests, input_tokens or output_tokens)
+    pub fn usage_daily(external_id: &str, date: &str, field: &str) -> String {
+        format!("sentinel:usage:daily:{}:{}:{}", external_id, date, field)
+    }
+
+    /// Accumulated counter field for a user's workflow
+    pub fn usage_workflow(external_id: &str, workflow_id: &str, field: &str) -> String {
+        format!("sentinel:usage:workflow:{}:{}:{}", external_id, workflow_id, field)
+    }
+
+    /// Daily counter for a model in a tier (`field` is requests, input_tokens or output_tokens)
+    pub fn usage_model_daily(date: &str, tier: &str, model: &str, field: &str) -> String {
+        format!("sentinel:usage:model:{}:{}:{}:{}", date, tier, model, field)
+    }
+
+    /// `{tier}:{model}` pairs with usage recorded on a day
+    pub fn usage_models(date: &str) -> String {
+        format!("sentinel:usage:models:{}", date)
+    }
+
+    /// External IDs with usage recorded on a day
+    pub fn usage_active(date: &str) -> String {
+        format!("sentinel:usage:active:{}", date)
+    }
+
+    /// Malformed-request counter for a user in a quarantine window
+    pub fn quarantine_strikes(external_id: &str, window: i64) -> String {
+        format!("sentinel:quarantine:strikes:{}:{}", external_id, window)
+    }
+
+    /// Quarantine marker for a user (value: Unix time the quarantine ends)
+    pub fn quarantine(external_id: &str) -> String {
+        format!("sentinel:quarantine:{}", external_id)
+    }
+
+    /// Runtime maintenance mode override (JSON `MaintenanceFlag`)
+    pub fn maintenance() -> String {
+        "sentinel:maintenance".to_string()
+    }
+
+    /// Runtime rate limit exemption list (JSON `ExemptIdsOverride`)
+    pub fn rate_limit_exempt_ids() -> String {
+        "sentinel:ratelimit:exempt-ids".to_string()
+    }
 }
 
 #[cfg(test)]
@@ -174,6 +508,25 @@ mod tests {
             keys::user_profile("abc123"),
             "sentinel:profile:abc123"
         );
+        assert_eq!(
+            keys::profile_hint("abc123"),
+            "sentinel:profile-hint:abc123"
+        );
+        assert_eq!(
+            keys::usage_daily("ext_1", "2024-01-31", "requests"),
+            "sentinel:usage:daily:ext_1:2024-01-31:requests"
+        );
+        assert_eq!(keys::usage_active("2024-01-31"), "sentinel:usage:active:2024-01-31");
+        assert_eq!(
+            keys::usage_model_daily("2024-01-31", "simple", "gpt-4o-mini", "output_tokens"),
+            "sentinel:usage:model:2024-01-31:simple:gpt-4o-mini:output_tokens"
+        );
+        assert_eq!(keys::usage_models("2024-01-31"), "sentinel:usage:models:2024-01-31");
+        assert_eq!(
+            keys::usage_workflow("ext_1", "wf-1", "input_tokens"),
+            "sentinel:usage:workflow:ext_1:wf-1:input_tokens"
+        );
+        assert_eq!(keys::quarantine("ext_1"), "sentinel:quarantine:ext_1");
     }
 
     #[test]
@@ -0,0 +1,281 @@
+//! Schema versions of state shared between replicas
+//!
+//! During a rolling deployment old and new replicas read and write the same
+//! Redis entries, so every shared value (cached limits, profiles and profile
+//! hints, native sessions and their history, failed usage increments, the
+//! tier config snapshot) carries the version of the schema it was written with:
+//! `v{N}|{json}`. Version 1 is written without a prefix, exactly as releases
+//! from before versioning wrote and read it, so those replicas keep reading
+//! entries during the rollout that introduces versioning. Bump a type past 1
+//! only once no such release is deployed.
+//!
+//! A reader migrates entries from older versions (see [`Schema::migrate`])
+//! and treats entries from newer versions, or that don't parse, as a cache
+//! miss rather than an error. Only callers that must not lose data (the
+//! failed increments queue) look at why an entry was unreadable.
+
+use metrics::counter;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use tracing::{info, warn};
+
+use crate::{
+    cache::subscription::ProfileHint,
+    error::AppResult,
+    native::session::{Session, SessionHistory},
+    tiers::StandbyTierConfig,
+    usage::batching::UsageIncrement,
+    zion::{models::TierConfigData, UserLimit, UserProfile},
+};
+
+/// Version of entries without a prefix (written without one)
+pub const UNVERSIONED: u32 = 1;
+
+/// A value stored in shared Redis state
+pub trait Schema: Serialize + DeserializeOwned {
+    /// Name used in logs and metrics
+    const NAME: &'static str;
+
+    /// Version this build writes
+    const VERSION: u32;
+
+    /// Bring a value written with an older `version` up to [`Self::VERSION`]
+    ///
+    /// Returns None when the value can't be upgraded. The default keeps the
+    /// value as is, for versions whose JSON shape didn't change.
+    fn migrate(version: u32, value: Value) -> Option<Value> {
+        let _ = version;
+        Some(value)
+    }
+}
+
+/// Why a stored entry couldn't be read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unreadable {
+    /// Written by a newer release
+    Newer(u32),
+    /// Not valid for any known version
+    Invalid,
+}
+
+/// Serialize a value with this build's version prefix (none for version 1)
+pub fn encode<T: Schema>(value: &T) -> AppResult<String> {
+    let json = serde_json::to_string(value)?;
+    if T::VERSION == UNVERSIONED {
+        return Ok(json);
+    }
+    Ok(format!("v{}|{}", T::VERSION, json))
+}
+
+/// Split a stored entry into its version and JSON
+pub fn split(raw: &str) -> (u32, &str) {
+    raw.strip_prefix('v')
+        .and_then(|rest| rest.split_once('|'))
+        .and_then(|(version, json)| Some((version.parse().ok()?, json)))
+        .unwrap_or((UNVERSIONED, raw))
+}
+
+/// Parse a stored entry, migrating it from an older version if needed
+pub fn decode<T: Schema>(raw: &str) -> Result<T, Unreadable> {
+    let (version, json) = split(raw);
+    if version > T::VERSION {
+        warn!(
+            schema = T::NAME,
+            version,
+            supported = T::VERSION,
+            "Ignoring entry written by a newer release"
+        );
+        mismatch::<T>("newer");
+        return Err(Unreadable::Newer(version));
+    }
+
+    let migrated = serde_json::from_str::<Value>(json)
+        .ok()
+        .and_then(|value| match version {
+            v if v == T::VERSION => Some(value),
+            _ => {
+                mismatch::<T>("migrated");
+                T::migrate(version, value)
+            }
+        })
+        .and_then(|value| serde_json::from_value(value).ok());
+    migrated.ok_or_else(|| {
+        warn!(schema = T::NAME, version, "Ignoring unreadable entry");
+        mismatch::<T>("invalid");
+        Unreadable::Invalid
+    })
+}
+
+fn mismatch<T: Schema>(outcome: &'static str) {
+    counter!(
+        "sentinel_cache_schema_mismatches_total",
+        "schema" => T::NAME,
+        "outcome" => outcome
+    )
+    .increment(1);
+}
+
+/// Schema versions this build reads and writes
+pub fn versions() -> [(&'static str, u32); 7] {
+    [
+        (<Vec<UserLimit>>::NAME, <Vec<UserLimit>>::VERSION),
+        (UserProfile::NAME, UserProfile::VERSION),
+        (ProfileHint::NAME, ProfileHint::VERSION),
+        (Session::NAME, Session::VERSION),
+        (UsageIncrement::NAME, UsageIncrement::VERSION),
+        (TierConfigData::NAME, TierConfigData::VERSION),
+        (StandbyTierConfig::NAME, StandbyTierConfig::VERSION),
+    ]
+}
+
+/// Log the schema versions at startup, to compare replicas during a rollout
+pub fn log_schema_versions() {
+    let summary = versions()
+        .iter()
+        .map(|(name, version)| format!("{}=v{}", name, version))
+        .collect::<Vec<_>>()
+        .join(" ");
+    info!(schemas = %summary, "Shared state schema versions");
+}
+
+impl Schema for Vec<UserLimit> {
+    const NAME: &'static str = "user_limits";
+    const VERSION: u32 = 1;
+}
+
+impl Schema for UserProfile {
+    const NAME: &'static str = "user_profile";
+    const VERSION: u32 = 1;
+}
+
+impl Schema for ProfileHint {
+    const NAME: &'static str = "profile_hint";
+    const VERSION: u32 = 1;
+}
+
+impl Schema for Session {
+    const NAME: &'static str = "session";
+    const VERSION: u32 = 1;
+}
+
+impl Schema for SessionHistory {
+    const NAME: &'static str = "session_history";
+    const VERSION: u32 = 1;
+}
+
+impl Schema for UsageIncrement {
+    const NAME: &'static str = "failed_increment";
+    const VERSION: u32 = 1;
+}
+
+impl Schema for TierConfigData {
+    const NAME: &'static str = "tier_config";
+    const VERSION: u32 = 1;
+}
+
+impl Schema for StandbyTierConfig {
+    const NAME: &'static str = "tier_config_standby";
+    const VERSION: u32 = 1;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Widget {
+        name: String,
+        size: u32,
+    }
+
+    impl Schema for Widget {
+        const NAME: &'static str = "widget";
+        const VERSION: u32 = 3;
+
+        // v2 called `size` `width`
+        fn migrate(version: u32, mut value: Value) -> Option<Value> {
+            if version < 3 {
+                let width = value.as_object_mut()?.remove("width")?;
+                value["size"] = width;
+            }
+            Some(value)
+        }
+    }
+
+    fn widget() -> Widget {
+        Widget {
+            name: "a".to_string(),
+            size: 4,
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let raw = encode(&widget()).unwrap();
+        assert_eq!(raw, r#"v3|{"name":"a","size":4}"#);
+        assert_eq!(decode::<Widget>(&raw), Ok(widget()));
+    }
+
+    #[test]
+    fn test_version_one_is_written_unprefixed() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Plain {
+            name: String,
+        }
+        impl Schema for Plain {
+            const NAME: &'static str = "plain";
+            const VERSION: u32 = 1;
+        }
+
+        let plain = Plain {
+            name: "a".to_string(),
+        };
+        let raw = encode(&plain).unwrap();
+        assert_eq!(raw, r#"{"name":"a"}"#);
+        assert_eq!(decode::<Plain>(&raw), Ok(plain));
+    }
+
+    #[test]
+    fn test_older_versions_are_migrated() {
+        assert_eq!(
+            decode::<Widget>(r#"v2|{"name":"a","width":4}"#),
+            Ok(widget())
+        );
+        // Unprefixed entries are version 1
+        assert_eq!(split(r#"{"name":"a"}"#), (UNVERSIONED, r#"{"name":"a"}"#));
+        assert_eq!(decode::<Widget>(r#"{"name":"a","width":4}"#), Ok(widget()));
+        assert_eq!(
+            decode::<Widget>(r#"v2|{"name":"a"}"#),
+            Err(Unreadable::Invalid)
+        );
+    }
+
+    #[test]
+    fn test_newer_and_malformed_entries_are_unreadable() {
+        assert_eq!(
+            decode::<Widget>(r#"v4|{"name":"a","size":4,"shape":"round"}"#),
+            Err(Unreadable::Newer(4))
+        );
+        assert_eq!(decode::<Widget>("v3|not json"), Err(Unreadable::Invalid));
+        assert_eq!(decode::<Widget>("vx|{}"), Err(Unreadable::Invalid));
+    }
+
+    #[test]
+    fn test_session_reads_legacy_entries() {
+        let legacy = json!({
+            "id": "conv-1",
+            "provider": "openai",
+            "model": "gpt-4o",
+            "tier": "simple",
+            "external_id": "user-1",
+            "created_at": 1700000000,
+            "version": 3
+        })
+        .to_string();
+        let session = decode::<Session>(&legacy).unwrap();
+        assert_eq!(session.version, 3);
+        assert_eq!(decode::<Session>(&encode(&session).unwrap()), Ok(session));
+    }
+}
@@ -4,18 +4,66 @@
 
 use std::sync::Arc;
 
-use serde::{de::DeserializeOwned, Serialize};
-use tracing::{debug, instrument};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument, warn};
 
 use crate::{
-    cache::redis::{keys, RedisCache},
-    error::AppResult,
+    cache::{
+        redis::{keys, RedisCache},
+        schema::Schema,
+    },
+    error::{AppError, AppResult},
+    usage::limits,
     zion::{IncrementUsageData, UserLimit, UserProfile, ZionClient},
 };
 
 #[cfg(any(test, feature = "test-utils"))]
 use crate::cache::InMemoryCache;
 
+/// How long a token's external id is remembered after its profile (1 day)
+const PROFILE_HINT_TTL_SECONDS: u64 = 24 * 60 * 60;
+
+/// External id a token last resolved to (`keys::profile_hint`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProfileHint {
+    pub external_id: String,
+}
+
+/// How [`SubscriptionCache::authenticate`] resolved a token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthLookup {
+    /// Profile from the cache, then the limits
+    Cached,
+    /// Profile miss for a known token: Zion validation and the limits
+    /// fetch ran concurrently
+    Joined,
+    /// Profile miss for an unknown token: validation, then the limits
+    Serial,
+    /// Token verified against Zion's JWKS, then the limits
+    Local,
+}
+
+impl AuthLookup {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuthLookup::Cached => "cached",
+            AuthLookup::Joined => "joined",
+            AuthLookup::Serial => "serial",
+            AuthLookup::Local => "local",
+        }
+    }
+}
+
+/// A validated token's profile and limits
+#[derive(Debug)]
+pub struct Authentication {
+    pub profile: UserProfile,
+    /// Limits of the profile's external id; a failure here never hides an
+    /// invalid token
+    pub limits: AppResult<Vec<UserLimit>>,
+    pub lookup: AuthLookup,
+}
+
 /// Cache backend abstraction for SubscriptionCache
 ///
 /// This enum allows SubscriptionCache to work with either Redis or in-memory
@@ -29,24 +77,24 @@ pub enum CacheBackend {
 }
 
 impl CacheBackend {
-    async fn get<T: DeserializeOwned>(&self, key: &str) -> AppResult<Option<T>> {
+    async fn get<T: Schema>(&self, key: &str) -> AppResult<Option<T>> {
         match self {
-            CacheBackend::Redis(cache) => cache.get(key).await,
+            CacheBackend::Redis(cache) => cache.get_versioned(key).await,
             #[cfg(any(test, feature = "test-utils"))]
-            CacheBackend::InMemory(cache) => cache.get(key).await,
+            CacheBackend::InMemory(cache) => cache.get_versioned(key).await,
         }
     }
 
-    async fn set_with_ttl<T: Serialize>(
+    async fn set_with_ttl<T: Schema>(
         &self,
         key: &str,
         value: &T,
         ttl_seconds: u64,
     ) -> AppResult<()> {
         match self {
-            CacheBackend::Redis(cache) => cache.set_with_ttl(key, value, ttl_seconds).await,
+            CacheBackend::Redis(cache) => cache.set_versioned(key, value, ttl_seconds).await,
             #[cfg(any(test, feature = "test-utils"))]
-            CacheBackend::InMemory(cache) => cache.set_with_ttl(key, value, ttl_seconds).await,
+            CacheBackend::InMemory(cache) => cache.set_versioned(key, value, ttl_seconds).await,
         }
     }
 
@@ -121,6 +169,9 @@ impl SubscriptionCache {
         // Fetch from Zion API
         let limits = self.zion_client.get_limits(external_id).await?;
 
+        // Runs once per user per TTL, since the result is cached below
+        warn_on_unexpected_shape(&limits);
+
         // Cache the result
         self.cache
             .set_with_ttl(&cache_key, &limits, self.limits_ttl)
@@ -129,6 +180,15 @@ impl SubscriptionCache {
         Ok(limits)
     }
 
+    /// Get cached user limits
+    ///
+    /// Returns None if not in cache (does not fetch from Zion).
+    #[instrument(skip(self), fields(external_id = %external_id))]
+    pub async fn get_cached_user_limits(&self, external_id: &str) -> AppResult<Option<Vec<UserLimit>>> {
+        let cache_key = keys::user_limits(external_id);
+        self.cache.get::<Vec<UserLimit>>(&cache_key).await
+    }
+
     /// Set user limits in cache
     ///
     /// Useful for updating cache after usage increment.
@@ -140,7 +200,7 @@ impl SubscriptionCache {
     ) -> AppResult<()> {
         let cache_key = keys::user_limits(external_id);
         self.cache
-            .set_with_ttl(&cache_key, &limits, self.limits_ttl)
+            .set_with_ttl(&cache_key, &limits.to_vec(), self.limits_ttl)
             .await
     }
 
@@ -177,8 +237,12 @@ impl SubscriptionCache {
         }
 
         debug!("Cache miss for JWT validation, validating with Zion");
+        self.validate_with_zion(jwt, jwt_hash).await
+    }
 
-        // Validate with Zion API
+    /// Validate JWT with Zion and cache the profile
+    async fn validate_with_zion(&self, jwt: &str, jwt_hash: &str) -> AppResult<UserProfile> {
+        let cache_key = keys::user_profile(jwt_hash);
         let profile = self.zion_client.validate_jwt(jwt).await?;
 
         debug!(
@@ -196,6 +260,80 @@ impl SubscriptionCache {
         Ok(profile)
     }
 
+    /// Validate JWT and load the user's limits
+    ///
+    /// With a cached profile the limits are fetched right after. On a miss
+    /// the external id the token resolved to before (kept a day, past the
+    /// profile) lets Zion validate the token while the limits are fetched;
+    /// if the token now belongs to someone else the limits are refetched.
+    /// A token Sentinel hasn't seen is validated first.
+    ///
+    /// A validation error is returned as soon as Zion answers, whatever the
+    /// concurrent limits fetch does: an invalid token beats missing limits.
+    #[instrument(skip(self, jwt), fields(jwt_hash = %jwt_hash))]
+    pub async fn authenticate(&self, jwt: &str, jwt_hash: &str) -> AppResult<Authentication> {
+        if let Some(profile) = self.get_cached_profile(jwt_hash).await? {
+            let limits = self.get_user_limits(&profile.effective_external_id()).await;
+            return Ok(Authentication {
+                profile,
+                limits,
+                lookup: AuthLookup::Cached,
+            });
+        }
+
+        let hint_key = keys::profile_hint(jwt_hash);
+        let hint = self
+            .cache
+            .get::<ProfileHint>(&hint_key)
+            .await
+            .unwrap_or_else(|e| {
+                debug!(error = %e, "Could not read profile hint");
+                None
+            });
+
+        let (profile, hinted_limits, lookup) = match &hint {
+            Some(hint) => {
+                debug!(external_id = %hint.external_id, "Validating JWT and fetching limits together");
+                let (profile, limits) = tokio::try_join!(
+                    self.validate_with_zion(jwt, jwt_hash),
+                    async { Ok::<_, AppError>(self.get_user_limits(&hint.external_id).await) },
+                )?;
+                (profile, Some(limits), AuthLookup::Joined)
+            }
+            None => (
+                self.validate_with_zion(jwt, jwt_hash).await?,
+                None,
+                AuthLookup::Serial,
+            ),
+        };
+
+        let external_id = profile.effective_external_id();
+        let limits = match hinted_limits {
+            Some(limits) if hint.as_ref().is_some_and(|hint| hint.external_id == external_id) => {
+                limits
+            }
+            _ => {
+                let hint = ProfileHint {
+                    external_id: external_id.clone(),
+                };
+                if let Err(e) = self
+                    .cache
+                    .set_with_ttl(&hint_key, &hint, PROFILE_HINT_TTL_SECONDS)
+                    .await
+                {
+                    debug!(error = %e, "Could not store profile hint");
+                }
+                self.get_user_limits(&external_id).await
+            }
+        };
+
+        Ok(Authentication {
+            profile,
+            limits,
+            lookup,
+        })
+    }
+
     /// Get cached user profile by JWT hash
     ///
     /// Returns None if not in cache (does not fetch from Zion).
@@ -239,7 +377,7 @@ impl SubscriptionCache {
         // Increment via Zion API
         let updated_limit = self
             .zion_client
-            .increment_usage(external_id, input_tokens, output_tokens, requests, model, timestamp)
+            .increment_usage(external_id, input_tokens, output_tokens, requests, model, None, timestamp)
             .await?;
 
         // Invalidate cached limits
@@ -249,10 +387,144 @@ impl SubscriptionCache {
     }
 }
 
+/// Log a structured warning when the limits payload lacks `ai_usage` or repeats it
+fn warn_on_unexpected_shape(user_limits: &[UserLimit]) {
+    let ai_usage_count = user_limits
+        .iter()
+        .filter(|l| l.name == limits::AI_USAGE)
+        .count();
+    if ai_usage_count == 1 {
+        return;
+    }
+
+    let shape: Vec<String> = user_limits
+        .iter()
+        .map(|l| match &l.reset_period {
+            Some(period) => format!("{}:{:?}", l.name, period),
+            None => l.name.clone(),
+        })
+        .collect();
+    warn!(
+        expected = limits::AI_USAGE,
+        found = ai_usage_count,
+        shape = ?shape,
+        "Unexpected Zion limits shape"
+    );
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::{test_config, zion_stub};
+    use crate::zion::{resolve_limit, MissingLimitPolicy};
+    use serde_json::json;
+    use wiremock::matchers::{method, path_regex};
+    use wiremock::{Mock, ResponseTemplate};
+
+    async fn cache_with_limits(
+        limits: serde_json::Value,
+    ) -> (SubscriptionCache, Arc<InMemoryCache>, wiremock::MockServer) {
+        let zion = zion_stub().await;
+        Mock::given(method("GET"))
+            .and(path_regex(r"^/api/v1/limits/external/.+$"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "success": true,
+                "data": {"userId": "user_123", "externalId": "ext_123", "limits": limits}
+            })))
+            .mount(&zion)
+            .await;
+
+        let config = test_config(&zion.uri(), "http://unused.invalid/v1");
+        let zion_client = Arc::new(ZionClient::new(reqwest::Client::new(), &config));
+        let memory = Arc::new(InMemoryCache::new(60));
+        let cache = SubscriptionCache::new_for_testing(memory.clone(), zion_client, 60, 60);
+        (cache, memory, zion)
+    }
+
+    #[tokio::test]
+    async fn test_missing_ai_usage_uses_policy() {
+        let (cache, _memory, _zion) = cache_with_limits(json!([{"name": "seats", "limit": 5}])).await;
+        let user_limits = cache.get_user_limits("ext_123").await.unwrap();
 
-    // Integration tests would require a mock Redis and Zion client
-    // For now, we just ensure the module compiles correctly
+        let unlimited = resolve_limit(&user_limits, limits::AI_USAGE, MissingLimitPolicy::Unlimited);
+        assert_eq!(unlimited.ai_requests.remaining, i64::MAX);
+
+        let zero = resolve_limit(&user_limits, limits::AI_USAGE, MissingLimitPolicy::Zero);
+        assert_eq!(zero.ai_requests.remaining, 0);
+    }
+
+    #[tokio::test]
+    async fn test_empty_limits_array() {
+        let (cache, _memory, _zion) = cache_with_limits(json!([])).await;
+        let user_limits = cache.get_user_limits("ext_123").await.unwrap();
+        assert!(user_limits.is_empty());
+
+        let limit = resolve_limit(&user_limits, limits::AI_USAGE, MissingLimitPolicy::Unlimited);
+        assert_eq!(limit.name, limits::AI_USAGE);
+        assert_eq!(limit.ai_input_tokens.limit, i64::MAX);
+    }
+
+    #[tokio::test]
+    async fn test_limits_written_by_other_releases() {
+        let (cache, memory, zion) = cache_with_limits(json!([{"name": "seats", "limit": 5}])).await;
+        let key = keys::user_limits("ext_123");
+
+        // An unversioned entry from an older replica is still a hit
+        let cached = resolve_limit(&[], "from-cache", MissingLimitPolicy::Zero);
+        memory.set_raw(&key, serde_json::to_string(&vec![cached]).unwrap(), 60);
+        let limits = cache.get_user_limits("ext_123").await.unwrap();
+        assert_eq!(limits[0].name, "from-cache");
+        assert!(zion.received_requests().await.unwrap_or_default().is_empty());
+
+        // A newer replica's entry is a miss, refetched and rewritten in the
+        // unprefixed v1 format older replicas read
+        memory.set_raw(&key, r#"v2|{"limits":[]}"#.to_string(), 60);
+        let limits = cache.get_user_limits("ext_123").await.unwrap();
+        assert!(limits.iter().all(|limit| limit.name != "from-cache"));
+        assert_eq!(zion.received_requests().await.unwrap().len(), 1);
+        assert!(memory.get_raw(&key).unwrap().starts_with('['));
+    }
+
+    #[tokio::test]
+    async fn test_authenticate_lookups_and_stale_hint() {
+        let (cache, memory, zion) = cache_with_limits(json!([{"name": "seats", "limit": 5}])).await;
+        let limits_paths = || async {
+            zion.received_requests()
+                .await
+                .unwrap_or_default()
+                .iter()
+                .map(|r| r.url.path().to_string())
+                .filter(|path| path.starts_with("/api/v1/limits/"))
+                .collect::<Vec<_>>()
+        };
+
+        let first = cache.authenticate("jwt", "hash").await.unwrap();
+        assert_eq!(first.lookup, AuthLookup::Serial);
+        assert!(first.limits.is_ok());
+        let cached = cache.authenticate("jwt", "hash").await.unwrap();
+        assert_eq!(cached.lookup, AuthLookup::Cached);
+
+        // The profile expired and the token remembered someone else: the
+        // limits fetched alongside are for the wrong user and refetched
+        let hint = ProfileHint {
+            external_id: "ext_previous".to_string(),
+        };
+        memory.set_versioned(&keys::profile_hint("hash"), &hint, 60).await.unwrap();
+        cache.invalidate_jwt("hash").await.unwrap();
+        let joined = cache.authenticate("jwt", "hash").await.unwrap();
+        assert_eq!(joined.lookup, AuthLookup::Joined);
+        let external_id = joined.profile.effective_external_id();
+        assert_eq!(
+            limits_paths().await,
+            vec![
+                format!("/api/v1/limits/external/{}", external_id),
+                "/api/v1/limits/external/ext_previous".to_string(),
+            ]
+        );
+        let hint = memory
+            .get_versioned::<ProfileHint>(&keys::profile_hint("hash"))
+            .await
+            .unwrap();
+        assert_eq!(hint.unwrap().external_id, external_id);
+    }
 }
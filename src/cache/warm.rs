@@ -0,0 +1,398 @@
+//! Bulk warming of the user limits cache
+//!
+//! Ahead of a known traffic spike (a partner's nightly batch job), operators
+//! can load many users' limits into the subscription cache so the first
+//! request per user doesn't wait on Zion. Jobs run in the background with
+//! bounded concurrency and a fetch rate shared by all jobs; progress is polled
+//! by job id.
+//!
+//! Jobs are idempotent: the id is derived from the set of external IDs, so
+//! submitting a list again while its job runs returns that job, and submitting
+//! it later (after a restart, or on another replica) runs it again with
+//! already-cached users reported as `cached` instead of being fetched. Job
+//! state lives in memory on the replica that accepted the job.
+
+use std::collections::{HashMap, HashSet};
+use std::num::NonZeroU32;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use futures::stream::{self, StreamExt};
+use governor::{DefaultDirectRateLimiter, Quota, RateLimiter};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tracing::info;
+
+use crate::{
+    cache::SubscriptionCache,
+    config::Config,
+    error::{AppError, AppResult},
+};
+
+/// Most external IDs accepted in one job
+pub const MAX_WARM_IDS: usize = 10_000;
+
+/// Finished jobs kept for polling before the oldest are dropped
+const MAX_RETAINED_JOBS: usize = 64;
+
+/// What warming did for one user
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarmOutcome {
+    /// Limits were fetched from Zion and cached
+    Warmed,
+    /// Limits were already cached; Zion was not called
+    Cached,
+    /// The Zion fetch failed
+    Failed,
+}
+
+/// Per-user result of a warm job
+#[derive(Debug, Clone, Serialize)]
+pub struct WarmResult {
+    pub external_id: String,
+    pub status: WarmOutcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Whether a warm job is still fetching
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarmJobStatus {
+    Running,
+    Completed,
+}
+
+/// Progress of a warm job
+#[derive(Debug, Clone, Serialize)]
+pub struct WarmJobReport {
+    pub job_id: String,
+    pub status: WarmJobStatus,
+    /// When this run of the job started (RFC 3339)
+    pub started_at: String,
+    /// When this run finished (RFC 3339)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<String>,
+    /// Distinct external IDs in the job
+    pub total: usize,
+    /// IDs processed so far
+    pub done: usize,
+    pub warmed: usize,
+    pub cached: usize,
+    pub failed: usize,
+    /// Results for the processed IDs, in submission order
+    pub results: Vec<WarmResult>,
+}
+
+/// One run of a warm job
+struct WarmJob {
+    id: String,
+    external_ids: Vec<String>,
+    started_at: String,
+    /// Start order, used to drop the oldest finished jobs
+    seq: u64,
+    progress: Mutex<WarmProgress>,
+}
+
+struct WarmProgress {
+    results: Vec<Option<WarmResult>>,
+    finished_at: Option<String>,
+}
+
+impl WarmJob {
+    fn is_running(&self) -> bool {
+        self.progress.lock().unwrap().finished_at.is_none()
+    }
+
+    fn report(&self) -> WarmJobReport {
+        let progress = self.progress.lock().unwrap();
+        let results: Vec<WarmResult> = progress.results.iter().flatten().cloned().collect();
+        let count = |outcome| results.iter().filter(|r| r.status == outcome).count();
+        WarmJobReport {
+            job_id: self.id.clone(),
+            status: match progress.finished_at {
+                Some(_) => WarmJobStatus::Completed,
+                None => WarmJobStatus::Running,
+            },
+            started_at: self.started_at.clone(),
+            finished_at: progress.finished_at.clone(),
+            total: self.external_ids.len(),
+            done: results.len(),
+            warmed: count(WarmOutcome::Warmed),
+            cached: count(WarmOutcome::Cached),
+            failed: count(WarmOutcome::Failed),
+            results,
+        }
+    }
+}
+
+/// Runs and tracks cache warm jobs
+pub struct CacheWarmer {
+    subscription_cache: Arc<SubscriptionCache>,
+    concurrency: usize,
+    /// Shared by all jobs so parallel jobs can't multiply the load on Zion
+    rate_limiter: Arc<DefaultDirectRateLimiter>,
+    jobs: Mutex<HashMap<String, Arc<WarmJob>>>,
+    next_seq: AtomicU64,
+}
+
+impl CacheWarmer {
+    /// Create a warmer filling `subscription_cache`, sized from the Zion config
+    pub fn new(subscription_cache: Arc<SubscriptionCache>, config: &Config) -> Self {
+        let rate =
+            NonZeroU32::new(config.zion.cache_warm_rate_per_second).unwrap_or(NonZeroU32::MIN);
+        Self {
+            subscription_cache,
+            concurrency: config.zion.cache_warm_concurrency.max(1),
+            rate_limiter: Arc::new(RateLimiter::direct(Quota::per_second(rate))),
+            jobs: Mutex::new(HashMap::new()),
+            next_seq: AtomicU64::new(0),
+        }
+    }
+
+    /// Start warming `external_ids`, or return the job already running for them
+    ///
+    /// IDs are trimmed and deduplicated; blank ones are ignored.
+    pub fn start(&self, external_ids: Vec<String>) -> AppResult<WarmJobReport> {
+        let external_ids = normalize_ids(external_ids);
+        if external_ids.is_empty() {
+            return Err(AppError::BadRequest("No external IDs to warm".to_string()));
+        }
+        if external_ids.len() > MAX_WARM_IDS {
+            return Err(AppError::BadRequest(format!(
+                "At most {} external IDs can be warmed per job, got {}",
+                MAX_WARM_IDS,
+                external_ids.len()
+            )));
+        }
+        let id = job_id(&external_ids);
+
+        let mut jobs = self.jobs.lock().unwrap();
+        if let Some(job) = jobs.get(&id).filter(|job| job.is_running()) {
+            return Ok(job.report());
+        }
+
+        let job = Arc::new(WarmJob {
+            id: id.clone(),
+            started_at: chrono::Utc::now().to_rfc3339(),
+            seq: self.next_seq.fetch_add(1, Ordering::Relaxed),
+            progress: Mutex::new(WarmProgress {
+                results: vec![None; external_ids.len()],
+                finished_at: None,
+            }),
+            external_ids,
+        });
+        jobs.insert(id, job.clone());
+        evict_finished(&mut jobs);
+        drop(jobs);
+
+        info!(job_id = %job.id, users = job.external_ids.len(), "Starting cache warm job");
+        tokio::spawn(run(
+            job.clone(),
+            self.subscription_cache.clone(),
+            self.rate_limiter.clone(),
+            self.concurrency,
+        ));
+        Ok(job.report())
+    }
+
+    /// Progress of a job started on this replica
+    pub fn job(&self, job_id: &str) -> Option<WarmJobReport> {
+        self.jobs
+            .lock()
+            .unwrap()
+            .get(job_id)
+            .map(|job| job.report())
+    }
+}
+
+/// Fetch every user's limits through the subscription cache
+async fn run(
+    job: Arc<WarmJob>,
+    subscription_cache: Arc<SubscriptionCache>,
+    rate_limiter: Arc<DefaultDirectRateLimiter>,
+    concurrency: usize,
+) {
+    stream::iter(job.external_ids.iter().enumerate())
+        .for_each_concurrent(concurrency, |(index, external_id)| {
+            let job = &job;
+            let subscription_cache = &subscription_cache;
+            let rate_limiter = &rate_limiter;
+            async move {
+                let result = warm_user(subscription_cache, rate_limiter, external_id).await;
+                job.progress.lock().unwrap().results[index] = Some(result);
+            }
+        })
+        .await;
+
+    job.progress.lock().unwrap().finished_at = Some(chrono::Utc::now().to_rfc3339());
+    let report = job.report();
+    info!(
+        job_id = %job.id,
+        warmed = report.warmed,
+        cached = report.cached,
+        failed = report.failed,
+        "Cache warm job completed"
+    );
+}
+
+/// Warm one user; cache hits don't count against the rate limit
+async fn warm_user(
+    subscription_cache: &SubscriptionCache,
+    rate_limiter: &DefaultDirectRateLimiter,
+    external_id: &str,
+) -> WarmResult {
+    let result = |status, error| WarmResult {
+        external_id: external_id.to_string(),
+        status,
+        error,
+    };
+
+    if let Ok(Some(_)) = subscription_cache.get_cached_user_limits(external_id).await {
+        return result(WarmOutcome::Cached, None);
+    }
+    rate_limiter.until_ready().await;
+    match subscription_cache.get_user_limits(external_id).await {
+        Ok(_) => result(WarmOutcome::Warmed, None),
+        Err(e) => result(WarmOutcome::Failed, Some(e.to_string())),
+    }
+}
+
+/// Trim, drop blanks and deduplicate, keeping first occurrences in order
+fn normalize_ids(external_ids: Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    external_ids
+        .into_iter()
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty() && seen.insert(id.clone()))
+        .collect()
+}
+
+/// Job id for a set of external IDs, independent of their order
+fn job_id(external_ids: &[String]) -> String {
+    let mut sorted: Vec<&str> = external_ids.iter().map(String::as_str).collect();
+    sorted.sort_unstable();
+    let digest = Sha256::digest(sorted.join("\n").as_bytes());
+    format!("warm_{}", &hex::encode(digest)[..16])
+}
+
+/// Drop the oldest finished jobs beyond `MAX_RETAINED_JOBS`
+fn evict_finished(jobs: &mut HashMap<String, Arc<WarmJob>>) {
+    while jobs.len() > MAX_RETAINED_JOBS {
+        let oldest = jobs
+            .values()
+            .filter(|job| !job.is_running())
+            .min_by_key(|job| job.seq)
+            .map(|job| job.id.clone());
+        match oldest {
+            Some(id) => jobs.remove(&id),
+            None => break,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::InMemoryCache;
+    use crate::testing::{test_config, zion_stub};
+    use crate::zion::ZionClient;
+    use std::time::Duration;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, ResponseTemplate};
+
+    fn ids(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    async fn wait_for_completion(warmer: &CacheWarmer, job_id: &str) -> WarmJobReport {
+        for _ in 0..100 {
+            let report = warmer.job(job_id).unwrap();
+            if report.status == WarmJobStatus::Completed {
+                return report;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("Warm job {} did not complete", job_id);
+    }
+
+    #[test]
+    fn test_normalize_ids() {
+        assert_eq!(
+            normalize_ids(ids(&[" a", "b", "", "a", "c ", "  "])),
+            vec!["a", "b", "c"]
+        );
+    }
+
+    #[test]
+    fn test_job_id_ignores_order() {
+        assert_eq!(job_id(&ids(&["a", "b"])), job_id(&ids(&["b", "a"])));
+        assert_ne!(job_id(&ids(&["a", "b"])), job_id(&ids(&["a", "c"])));
+        assert!(job_id(&ids(&["a"])).starts_with("warm_"));
+    }
+
+    #[tokio::test]
+    async fn test_warm_reports_per_id_outcomes() {
+        let zion = zion_stub().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/limits/external/missing"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&zion)
+            .await;
+        let config = test_config(&zion.uri(), "http://unused.invalid/v1");
+        let zion_client = Arc::new(ZionClient::new(reqwest::Client::new(), &config));
+        let cache = Arc::new(SubscriptionCache::new_for_testing(
+            Arc::new(InMemoryCache::new(60)),
+            zion_client,
+            60,
+            60,
+        ));
+        cache.get_user_limits("already").await.unwrap();
+        let warmer = CacheWarmer::new(cache, &config);
+
+        let started = warmer
+            .start(ids(&["fresh", "already", "missing", "fresh"]))
+            .unwrap();
+        assert_eq!(started.total, 3);
+
+        let report = wait_for_completion(&warmer, &started.job_id).await;
+        let outcomes: Vec<(&str, WarmOutcome)> = report
+            .results
+            .iter()
+            .map(|r| (r.external_id.as_str(), r.status))
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                ("fresh", WarmOutcome::Warmed),
+                ("already", WarmOutcome::Cached),
+                ("missing", WarmOutcome::Failed),
+            ]
+        );
+        assert!(report.results[2].error.is_some());
+
+        // Running the same set again only retries what isn't cached
+        let rerun = warmer.start(ids(&["missing", "already", "fresh"])).unwrap();
+        assert_eq!(rerun.job_id, started.job_id);
+        let report = wait_for_completion(&warmer, &rerun.job_id).await;
+        assert_eq!((report.warmed, report.cached, report.failed), (0, 2, 1));
+    }
+
+    #[test]
+    fn test_start_rejects_empty_list() {
+        let config = Config::for_tests();
+        let zion_client = Arc::new(ZionClient::new(reqwest::Client::new(), &config));
+        let cache = Arc::new(SubscriptionCache::new_for_testing(
+            Arc::new(InMemoryCache::new(60)),
+            zion_client,
+            60,
+            60,
+        ));
+        let warmer = CacheWarmer::new(cache, &config);
+        assert!(matches!(
+            warmer.start(ids(&[" ", ""])),
+            Err(AppError::BadRequest(_))
+        ));
+    }
+}
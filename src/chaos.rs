@@ -0,0 +1,597 @@
+//! Fault injection for resilience testing
+//!
+//! Compiled in with the `chaos` cargo feature and switched on with
+//! `CHAOS_ENABLED` (meant for staging only). Admins add rules through
+//! `/admin/chaos/faults`; a rule targets the AI provider, Zion or Redis and
+//! delays calls (`latency`), fails them with an error status (`error`) or
+//! fails them as a dropped connection (`reset`), for a share of calls
+//! (`rate`) until its TTL runs out.
+//!
+//! Calls consult the shared [`ChaosInjector`] before going out: provider calls
+//! through [`ChaosProvider`] (inside the upstream circuit breakers, so they
+//! count injected failures), Zion and Redis calls in [`ZionClient`] and
+//! [`RedisCache`]. Injected faults are logged with `chaos = true` and counted
+//! in `sentinel_chaos_faults_injected_total`, so they can be told apart from
+//! real failures.
+//!
+//! [`ZionClient`]: crate::zion::ZionClient
+//! [`RedisCache`]: crate::cache::RedisCache
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use axum::body::Body;
+use axum::http::{HeaderMap, Method, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::clock::{system_clock, SharedClock};
+use crate::error::{AppError, AppResult};
+use crate::proxy::provider::{AiProvider, ByteStream};
+
+/// Longest TTL a rule may have, so a forgotten rule can't outlive a test day
+pub const MAX_TTL_SECONDS: u64 = 24 * 3600;
+
+/// Longest delay a latency rule may add
+pub const MAX_LATENCY_MS: u64 = 120_000;
+
+/// Dependency a rule applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Target {
+    Provider,
+    Zion,
+    Redis,
+}
+
+impl Target {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Target::Provider => "provider",
+            Target::Zion => "zion",
+            Target::Redis => "redis",
+        }
+    }
+}
+
+/// What a rule does to the calls it hits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FaultKind {
+    /// Delay the call by `latency_ms`, then let it through
+    Latency,
+    /// Fail the call as if the dependency answered with `status`
+    Error,
+    /// Fail the call as if the connection was reset
+    Reset,
+}
+
+impl FaultKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FaultKind::Latency => "latency",
+            FaultKind::Error => "error",
+            FaultKind::Reset => "reset",
+        }
+    }
+}
+
+fn default_rate() -> f64 {
+    1.0
+}
+
+fn default_status() -> u16 {
+    503
+}
+
+/// A fault to inject, as posted to `/admin/chaos/faults`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FaultRule {
+    pub target: Target,
+    pub fault: FaultKind,
+    /// Share of calls hit, 0.0 to 1.0 (default 1.0)
+    #[serde(default = "default_rate")]
+    pub rate: f64,
+    /// Delay added by `latency` faults
+    #[serde(default)]
+    pub latency_ms: u64,
+    /// Status returned by `error` faults (default 503)
+    #[serde(default = "default_status")]
+    pub status: u16,
+    /// How long the rule stays active
+    pub ttl_seconds: u64,
+}
+
+impl FaultRule {
+    /// Check the rule's fields, describing the first problem found
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&self.rate) {
+            return Err("'rate' must be between 0.0 and 1.0".to_string());
+        }
+        if self.ttl_seconds == 0 || self.ttl_seconds > MAX_TTL_SECONDS {
+            return Err(format!(
+                "'ttl_seconds' must be between 1 and {}",
+                MAX_TTL_SECONDS
+            ));
+        }
+        match self.fault {
+            FaultKind::Latency if self.latency_ms == 0 || self.latency_ms > MAX_LATENCY_MS => {
+                Err(format!(
+                    "'latency_ms' must be between 1 and {} for latency faults",
+                    MAX_LATENCY_MS
+                ))
+            }
+            FaultKind::Error if !(400..=599).contains(&self.status) => {
+                Err("'status' must be a 4xx or 5xx status for error faults".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A rule in effect, as listed by `/admin/chaos/faults`
+#[derive(Debug, Clone, Serialize)]
+pub struct ActiveFault {
+    pub id: String,
+    #[serde(flatten)]
+    pub rule: FaultRule,
+    /// When the rule expires (Unix seconds)
+    pub expires_at: i64,
+    #[serde(skip)]
+    deadline: Instant,
+}
+
+/// How an intercepted call fails
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Injected {
+    Error(StatusCode),
+    Reset,
+}
+
+impl Injected {
+    /// The error an HTTP dependency (provider or Zion) fails with
+    ///
+    /// Worded like the clients' own errors (`"Zion API error 503 ..."`), so
+    /// breakers and retries classify it the same way. A reset counts as a
+    /// bad gateway, like the connection errors it stands for.
+    pub fn upstream_error(self, service: &str) -> AppError {
+        match self {
+            Injected::Error(status) => {
+                AppError::UpstreamError(format!("{} error {}: injected fault", service, status))
+            }
+            Injected::Reset => AppError::UpstreamError(format!(
+                "{} error {}: connection reset (injected fault)",
+                service,
+                StatusCode::BAD_GATEWAY
+            )),
+        }
+    }
+
+    /// The error a Redis operation fails with
+    pub fn redis_error(self) -> AppError {
+        let error = match self {
+            Injected::Error(_) => {
+                redis::RedisError::from((redis::ErrorKind::ResponseError, "injected fault"))
+            }
+            Injected::Reset => redis::RedisError::from(std::io::Error::new(
+                std::io::ErrorKind::ConnectionReset,
+                "connection reset (injected fault)",
+            )),
+        };
+        AppError::RedisError(error)
+    }
+}
+
+/// Active fault rules, consulted by every intercepted call
+pub struct ChaosInjector {
+    rules: Mutex<Vec<ActiveFault>>,
+    clock: SharedClock,
+}
+
+impl Default for ChaosInjector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChaosInjector {
+    pub fn new() -> Self {
+        Self {
+            rules: Mutex::new(Vec::new()),
+            clock: system_clock(),
+        }
+    }
+
+    /// Use the given clock for rule expiry
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Activate a rule (validate it first)
+    pub fn add(&self, rule: FaultRule) -> ActiveFault {
+        let fault = ActiveFault {
+            id: Uuid::new_v4().to_string(),
+            expires_at: self.clock.now_unix() + rule.ttl_seconds as i64,
+            deadline: self.clock.instant_now() + Duration::from_secs(rule.ttl_seconds),
+            rule,
+        };
+        warn!(
+            chaos = true,
+            id = %fault.id,
+            target = fault.rule.target.as_str(),
+            fault = fault.rule.fault.as_str(),
+            rate = fault.rule.rate,
+            ttl_seconds = fault.rule.ttl_seconds,
+            "Chaos fault rule activated"
+        );
+        let mut rules = self.rules.lock().unwrap();
+        self.prune(&mut rules);
+        rules.push(fault.clone());
+        fault
+    }
+
+    /// Rules still in effect, oldest first
+    pub fn list(&self) -> Vec<ActiveFault> {
+        let mut rules = self.rules.lock().unwrap();
+        self.prune(&mut rules);
+        rules.clone()
+    }
+
+    /// Deactivate one rule; false if it wasn't active
+    pub fn remove(&self, id: &str) -> bool {
+        let mut rules = self.rules.lock().unwrap();
+        let before = rules.len();
+        rules.retain(|fault| fault.id != id);
+        rules.len() < before
+    }
+
+    /// Deactivate every rule, returning how many were active
+    pub fn clear(&self) -> usize {
+        let mut rules = self.rules.lock().unwrap();
+        self.prune(&mut rules);
+        let count = rules.len();
+        rules.clear();
+        count
+    }
+
+    fn prune(&self, rules: &mut Vec<ActiveFault>) {
+        let now = self.clock.instant_now();
+        rules.retain(|fault| {
+            let active = fault.deadline > now;
+            if !active {
+                warn!(
+                    chaos = true,
+                    id = %fault.id,
+                    target = fault.rule.target.as_str(),
+                    fault = fault.rule.fault.as_str(),
+                    "Chaos fault rule expired"
+                );
+            }
+            active
+        });
+    }
+
+    /// Apply the rules for `target` to one call before it goes out
+    ///
+    /// Each matching rule hits with its `rate`: latency rules delay the call,
+    /// and the first error or reset rule that hits fails it.
+    pub async fn intercept(&self, target: Target, operation: &str) -> Result<(), Injected> {
+        let matching: Vec<FaultRule> = {
+            let mut rules = self.rules.lock().unwrap();
+            self.prune(&mut rules);
+            rules
+                .iter()
+                .filter(|fault| fault.rule.target == target)
+                .map(|fault| fault.rule.clone())
+                .collect()
+        };
+
+        for rule in matching {
+            if rule.rate < 1.0 && rand::random::<f64>() >= rule.rate {
+                continue;
+            }
+            warn!(
+                chaos = true,
+                target = target.as_str(),
+                operation = %operation,
+                fault = rule.fault.as_str(),
+                "Injecting chaos fault"
+            );
+            metrics::record_injected(target, rule.fault);
+            match rule.fault {
+                FaultKind::Latency => {
+                    tokio::time::sleep(Duration::from_millis(rule.latency_ms)).await;
+                }
+                FaultKind::Error => {
+                    let status = StatusCode::from_u16(rule.status)
+                        .unwrap_or(StatusCode::SERVICE_UNAVAILABLE);
+                    return Err(Injected::Error(status));
+                }
+                FaultKind::Reset => return Err(Injected::Reset),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An [`AiProvider`] whose calls go through the `provider` fault rules first
+pub struct ChaosProvider {
+    inner: Arc<dyn AiProvider>,
+    chaos: Arc<ChaosInjector>,
+}
+
+impl ChaosProvider {
+    pub fn new(inner: Arc<dyn AiProvider>, chaos: Arc<ChaosInjector>) -> Self {
+        Self { inner, chaos }
+    }
+
+    async fn intercept(&self, operation: &str) -> AppResult<()> {
+        self.chaos
+            .intercept(Target::Provider, operation)
+            .await
+            .map_err(|injected| injected.upstream_error(self.inner.name()))
+    }
+}
+
+#[async_trait]
+impl AiProvider for ChaosProvider {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    async fn chat_completions(
+        &self,
+        request: serde_json::Value,
+        incoming_headers: &HeaderMap,
+    ) -> AppResult<serde_json::Value> {
+        self.intercept("chat/completions").await?;
+        self.inner.chat_completions(request, incoming_headers).await
+    }
+
+    async fn chat_completions_stream(
+        &self,
+        request: serde_json::Value,
+        incoming_headers: &HeaderMap,
+    ) -> AppResult<ByteStream> {
+        self.intercept("chat/completions").await?;
+        self.inner
+            .chat_completions_stream(request, incoming_headers)
+            .await
+    }
+
+    async fn completions(
+        &self,
+        request: serde_json::Value,
+        incoming_headers: &HeaderMap,
+    ) -> AppResult<serde_json::Value> {
+        self.intercept("completions").await?;
+        self.inner.completions(request, incoming_headers).await
+    }
+
+    async fn completions_stream(
+        &self,
+        request: serde_json::Value,
+        incoming_headers: &HeaderMap,
+    ) -> AppResult<ByteStream> {
+        self.intercept("completions").await?;
+        self.inner.completions_stream(request, incoming_headers).await
+    }
+
+    async fn embeddings(
+        &self,
+        request: serde_json::Value,
+        incoming_headers: &HeaderMap,
+    ) -> AppResult<serde_json::Value> {
+        self.intercept("embeddings").await?;
+        self.inner.embeddings(request, incoming_headers).await
+    }
+
+    async fn list_models(&self) -> AppResult<serde_json::Value> {
+        self.intercept("models").await?;
+        self.inner.list_models().await
+    }
+
+    async fn get_model(&self, model_id: &str) -> AppResult<serde_json::Value> {
+        self.intercept("models").await?;
+        self.inner.get_model(model_id).await
+    }
+
+    async fn responses(
+        &self,
+        request: serde_json::Value,
+        incoming_headers: &HeaderMap,
+    ) -> AppResult<serde_json::Value> {
+        self.intercept("responses").await?;
+        self.inner.responses(request, incoming_headers).await
+    }
+
+    async fn responses_stream(
+        &self,
+        request: serde_json::Value,
+        incoming_headers: &HeaderMap,
+    ) -> AppResult<ByteStream> {
+        self.intercept("responses").await?;
+        self.inner.responses_stream(request, incoming_headers).await
+    }
+
+    async fn forward_raw(
+        &self,
+        method: Method,
+        path: &str,
+        incoming_headers: HeaderMap,
+        body: Body,
+    ) -> AppResult<Response<Body>> {
+        self.intercept(path).await?;
+        self.inner
+            .forward_raw(method, path, incoming_headers, body)
+            .await
+    }
+}
+
+/// Metrics for injected faults
+pub mod metrics {
+    use metrics::counter;
+
+    use super::{FaultKind, Target};
+
+    /// Record a fault injected into a call
+    pub fn record_injected(target: Target, fault: FaultKind) {
+        counter!(
+            "sentinel_chaos_faults_injected_total",
+            "target" => target.as_str(),
+            "fault" => fault.as_str()
+        )
+        .increment(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::TestClock;
+    use crate::proxy::breaker::is_breaker_failure;
+    use crate::testing::{MockAiProvider, MockEndpoint, MockReply};
+
+    fn rule(target: Target, fault: FaultKind, ttl_seconds: u64) -> FaultRule {
+        FaultRule {
+            target,
+            fault,
+            rate: 1.0,
+            latency_ms: 0,
+            status: 503,
+            ttl_seconds,
+        }
+    }
+
+    fn injector(clock: &Arc<TestClock>) -> ChaosInjector {
+        ChaosInjector::new().with_clock(clock.clone())
+    }
+
+    #[test]
+    fn test_rule_deserializes_with_defaults() {
+        let parsed: FaultRule = serde_json::from_value(serde_json::json!({
+            "target": "zion",
+            "fault": "error",
+            "ttl_seconds": 30
+        }))
+        .unwrap();
+        assert_eq!(parsed, rule(Target::Zion, FaultKind::Error, 30));
+        assert!(parsed.validate().is_ok());
+    }
+
+    #[test]
+    fn test_rule_validation() {
+        let mut bad_rate = rule(Target::Zion, FaultKind::Error, 30);
+        bad_rate.rate = 1.5;
+        assert!(bad_rate.validate().unwrap_err().contains("rate"));
+
+        assert!(rule(Target::Zion, FaultKind::Reset, 0)
+            .validate()
+            .unwrap_err()
+            .contains("ttl_seconds"));
+        assert!(rule(Target::Redis, FaultKind::Reset, MAX_TTL_SECONDS + 1)
+            .validate()
+            .is_err());
+
+        assert!(rule(Target::Provider, FaultKind::Latency, 30)
+            .validate()
+            .unwrap_err()
+            .contains("latency_ms"));
+
+        let mut bad_status = rule(Target::Provider, FaultKind::Error, 30);
+        bad_status.status = 200;
+        assert!(bad_status.validate().unwrap_err().contains("status"));
+    }
+
+    #[tokio::test]
+    async fn test_rules_hit_their_target_until_they_expire() {
+        let clock = TestClock::new(1_700_000_000);
+        let chaos = injector(&clock);
+        let fault = chaos.add(rule(Target::Zion, FaultKind::Error, 10));
+        assert_eq!(fault.expires_at, 1_700_000_010);
+
+        assert_eq!(
+            chaos.intercept(Target::Zion, "get_limits").await,
+            Err(Injected::Error(StatusCode::SERVICE_UNAVAILABLE))
+        );
+        assert_eq!(chaos.intercept(Target::Redis, "get").await, Ok(()));
+
+        clock.advance(Duration::from_secs(9));
+        assert!(chaos.intercept(Target::Zion, "get_limits").await.is_err());
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(chaos.intercept(Target::Zion, "get_limits").await, Ok(()));
+        assert!(chaos.list().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_zero_rate_never_hits() {
+        let clock = TestClock::new(1_700_000_000);
+        let chaos = injector(&clock);
+        let mut never = rule(Target::Redis, FaultKind::Reset, 10);
+        never.rate = 0.0;
+        chaos.add(never);
+        for _ in 0..20 {
+            assert_eq!(chaos.intercept(Target::Redis, "get").await, Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_remove_and_clear() {
+        let clock = TestClock::new(1_700_000_000);
+        let chaos = injector(&clock);
+        let first = chaos.add(rule(Target::Zion, FaultKind::Error, 10));
+        chaos.add(rule(Target::Redis, FaultKind::Reset, 10));
+
+        assert!(chaos.remove(&first.id));
+        assert!(!chaos.remove(&first.id));
+        assert_eq!(chaos.list().len(), 1);
+        assert_eq!(chaos.clear(), 1);
+        assert!(chaos.list().is_empty());
+    }
+
+    #[test]
+    fn test_injected_errors_look_like_real_failures() {
+        let error = Injected::Error(StatusCode::SERVICE_UNAVAILABLE).upstream_error("Zion API");
+        assert!(error.to_string().contains("Zion API error 503"));
+        assert!(is_breaker_failure(&error));
+        assert!(is_breaker_failure(&Injected::Reset.upstream_error("OpenAI")));
+        assert!(!is_breaker_failure(
+            &Injected::Error(StatusCode::TOO_MANY_REQUESTS).upstream_error("OpenAI")
+        ));
+
+        match Injected::Reset.redis_error() {
+            AppError::RedisError(e) => assert!(e.is_connection_dropped()),
+            other => panic!("expected a Redis error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_chaos_provider_fails_before_calling_inner() {
+        let clock = TestClock::new(1_700_000_000);
+        let chaos = Arc::new(injector(&clock));
+        let inner = Arc::new(MockAiProvider::new().with_reply(
+            MockEndpoint::ChatCompletions,
+            MockReply::chat_completion("gpt-4o-mini", "Hi!", 1, 1),
+        ));
+        let provider = ChaosProvider::new(inner.clone(), chaos.clone());
+
+        chaos.add(rule(Target::Provider, FaultKind::Reset, 10));
+        let error = provider
+            .chat_completions(serde_json::json!({}), &HeaderMap::new())
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("connection reset (injected fault)"));
+        assert!(inner.requests_for(MockEndpoint::ChatCompletions).is_empty());
+
+        chaos.clear();
+        provider
+            .chat_completions(serde_json::json!({}), &HeaderMap::new())
+            .await
+            .unwrap();
+        assert_eq!(inner.requests_for(MockEndpoint::ChatCompletions).len(), 1);
+    }
+}
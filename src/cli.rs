@@ -0,0 +1,223 @@
+//! Operator subcommands of the `sentinel` binary
+//!
+//! `sentinel usage-queue <command>` works on the failed usage increments
+//! queue in Redis (`usage::queue`), using the same `REDIS_URL`, `ZION_API_URL`
+//! and `ZION_API_KEY` as the server:
+//!
+//! - `stats` - length, oldest/newest timestamps and per-user totals (JSON)
+//! - `export [--format json|csv]` - all readable entries on stdout
+//! - `flush [--rate 10]` - send queued entries to Zion, at most `rate` per second
+//! - `purge --older-than 7d` - drop entries older than the age (`s`, `m`, `h`, `d`)
+//!
+//! `flush` and `purge` take the queue lock, so they fail fast instead of
+//! racing a running instance's retry loop. Ledger rows of flushed increments
+//! keep their `failed` status.
+
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{Parser, Subcommand};
+
+use crate::{
+    config::Config,
+    usage::{
+        queue::{export, ExportFormat, QueueLock},
+        FailedQueue, LedgerHandle,
+    },
+    zion::ZionClient,
+};
+
+/// `sentinel usage-queue` arguments
+#[derive(Debug, Parser)]
+#[command(
+    name = "usage-queue",
+    bin_name = "sentinel usage-queue",
+    about = "Inspect and drain the failed usage increments queue"
+)]
+struct UsageQueueArgs {
+    #[command(subcommand)]
+    command: UsageQueueCommand,
+}
+
+/// A parsed `sentinel usage-queue` command
+#[derive(Debug, Clone, PartialEq, Subcommand)]
+pub enum UsageQueueCommand {
+    /// Queue length, oldest/newest timestamps and per-user totals
+    Stats,
+    /// Print all queued increments
+    Export {
+        /// json or csv
+        #[arg(long, default_value = "json", value_parser = parse_format)]
+        format: ExportFormat,
+    },
+    /// Send queued increments to Zion
+    Flush {
+        /// Increments sent per second
+        #[arg(
+            long = "rate",
+            value_name = "PER_SECOND",
+            default_value_t = 10,
+            value_parser = clap::value_parser!(u32).range(1..)
+        )]
+        rate_per_second: u32,
+    },
+    /// Remove increments older than an age
+    Purge {
+        /// Age such as 90m, 12h or 7d
+        #[arg(long, value_name = "AGE", value_parser = parse_age)]
+        older_than: Duration,
+    },
+}
+
+impl UsageQueueCommand {
+    /// Parse the arguments following `usage-queue`
+    pub fn parse(args: &[String]) -> Result<Self, clap::Error> {
+        let args = std::iter::once("usage-queue").chain(args.iter().map(String::as_str));
+        Ok(UsageQueueArgs::try_parse_from(args)?.command)
+    }
+}
+
+/// Parse an `--format` value
+fn parse_format(value: &str) -> Result<ExportFormat> {
+    match value {
+        "json" => Ok(ExportFormat::Json),
+        "csv" => Ok(ExportFormat::Csv),
+        other => bail!("unknown export format '{}' (expected json or csv)", other),
+    }
+}
+
+/// Parse an age such as `30s`, `90m`, `12h` or `7d`
+fn parse_age(value: &str) -> Result<Duration> {
+    let invalid = || {
+        anyhow!(
+            "invalid age '{}' (expected a number followed by s, m, h or d)",
+            value
+        )
+    };
+    let unit = value.chars().last().ok_or_else(invalid)?;
+    let amount: u64 = value[..value.len() - unit.len_utf8()]
+        .parse()
+        .map_err(|_| invalid())?;
+    let unit_seconds = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 3600,
+        'd' => 86_400,
+        _ => return Err(invalid()),
+    };
+    let seconds = amount
+        .checked_mul(unit_seconds)
+        .ok_or_else(|| anyhow!("age '{}' is too large", value))?;
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Run `sentinel usage-queue` with the arguments following it
+///
+/// Arguments are checked before the configuration is loaded; invalid ones (and
+/// `--help`) print the usage text and exit.
+pub async fn run_usage_queue(args: &[String]) -> Result<()> {
+    let command = UsageQueueCommand::parse(args).unwrap_or_else(|e| e.exit());
+    let config = Config::from_env()?;
+
+    let redis_client = redis::Client::open(config.redis.url.as_str())?;
+    let redis = redis::aio::ConnectionManager::new(redis_client)
+        .await
+        .context("Failed to connect to Redis")?;
+    let queue = FailedQueue::new(redis);
+
+    match command {
+        UsageQueueCommand::Stats => {
+            println!("{}", serde_json::to_string_pretty(&queue.stats().await?)?);
+        }
+        UsageQueueCommand::Export { format } => {
+            print!("{}", export(&queue.entries().await?, format));
+        }
+        UsageQueueCommand::Flush { rate_per_second } => {
+            let lock = lock(&queue).await?;
+            let zion_client = ZionClient::new(reqwest::Client::new(), &config);
+            let report = queue
+                .flush(
+                    &lock,
+                    &zion_client,
+                    rate_per_second,
+                    &LedgerHandle::disabled(),
+                )
+                .await;
+            queue.unlock(lock).await?;
+            println!("{}", serde_json::to_string_pretty(&report?)?);
+        }
+        UsageQueueCommand::Purge { older_than } => {
+            let lock = lock(&queue).await?;
+            let purged = queue.purge_older_than(&lock, older_than).await;
+            queue.unlock(lock).await?;
+            println!("Purged {} increments", purged?);
+        }
+    }
+    Ok(())
+}
+
+/// Take the queue lock or explain who holds it
+async fn lock(queue: &FailedQueue) -> Result<QueueLock> {
+    queue.try_lock().await?.ok_or_else(|| {
+        anyhow!("the queue is locked by a running Sentinel retry or another flush/purge; try again shortly")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<UsageQueueCommand, clap::Error> {
+        UsageQueueCommand::parse(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(parse(&["stats"]).unwrap(), UsageQueueCommand::Stats);
+        assert_eq!(
+            parse(&["export"]).unwrap(),
+            UsageQueueCommand::Export {
+                format: ExportFormat::Json
+            }
+        );
+        assert_eq!(
+            parse(&["export", "--format", "csv"]).unwrap(),
+            UsageQueueCommand::Export {
+                format: ExportFormat::Csv
+            }
+        );
+        assert_eq!(
+            parse(&["flush", "--rate", "25"]).unwrap(),
+            UsageQueueCommand::Flush {
+                rate_per_second: 25
+            }
+        );
+        assert_eq!(
+            parse(&["purge", "--older-than", "7d"]).unwrap(),
+            UsageQueueCommand::Purge {
+                older_than: Duration::from_secs(7 * 86_400)
+            }
+        );
+
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["export", "--format", "xml"]).is_err());
+        assert!(parse(&["flush", "--rate", "0"]).is_err());
+        assert!(parse(&["flush", "--rate"]).is_err());
+        assert!(parse(&["purge"]).is_err());
+        assert!(parse(&["drain"]).is_err());
+        assert!(parse(&["purge", "--older-than", "999999999999999d"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(parse_age("30s").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_age("90m").unwrap(), Duration::from_secs(5400));
+        assert_eq!(parse_age("12h").unwrap(), Duration::from_secs(43_200));
+        assert!(parse_age("7").is_err());
+        assert!(parse_age("d").is_err());
+        assert!(parse_age("1w").is_err());
+        assert!(parse_age("").is_err());
+        assert!(parse_age("999999999999999d").is_err());
+        assert!(parse_age("18446744073709551615s").is_ok());
+    }
+}
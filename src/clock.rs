@@ -0,0 +1,111 @@
+//! Time source abstraction
+//!
+//! Rate-limit windows, circuit breaker resets, provider backoff and session
+//! timestamps read the time through [`Clock`] instead of calling
+//! `Utc::now()` / `Instant::now()` directly. Production code uses
+//! [`SystemClock`]; tests drive a [`TestClock`] forward explicitly, so
+//! boundary behavior can be checked without sleeping.
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Instant;
+
+#[cfg(any(test, feature = "test-utils"))]
+use std::{sync::Mutex, time::Duration};
+
+/// Source of wall-clock and monotonic time
+pub trait Clock: Send + Sync + fmt::Debug {
+    /// Current Unix time in seconds
+    fn now_unix(&self) -> i64;
+
+    /// Current monotonic instant (for measuring elapsed time)
+    fn instant_now(&self) -> Instant;
+}
+
+/// Clock shared between components
+pub type SharedClock = Arc<dyn Clock>;
+
+/// The real clock
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_unix(&self) -> i64 {
+        chrono::Utc::now().timestamp()
+    }
+
+    fn instant_now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Shared handle to the real clock
+pub fn system_clock() -> SharedClock {
+    Arc::new(SystemClock)
+}
+
+/// Manually advanced clock for tests
+///
+/// Starts at the given Unix time and only moves when [`TestClock::advance`]
+/// is called. Unix time and instants move together.
+#[cfg(any(test, feature = "test-utils"))]
+#[derive(Debug)]
+pub struct TestClock {
+    start_unix: i64,
+    start_instant: Instant,
+    elapsed: Mutex<Duration>,
+}
+
+#[cfg(any(test, feature = "test-utils"))]
+impl TestClock {
+    /// Create a clock frozen at `start_unix`
+    pub fn new(start_unix: i64) -> Arc<Self> {
+        Arc::new(Self {
+            start_unix,
+            start_instant: Instant::now(),
+            elapsed: Mutex::new(Duration::ZERO),
+        })
+    }
+
+    /// Move the clock forward
+    pub fn advance(&self, by: Duration) {
+        *self.elapsed.lock().unwrap() += by;
+    }
+
+    /// Move the clock forward to `unix` (which must not be in the clock's past)
+    pub fn set_unix(&self, unix: i64) {
+        let now = self.now_unix();
+        assert!(unix >= now, "TestClock cannot move backwards ({} -> {})", now, unix);
+        self.advance(Duration::from_secs((unix - now) as u64));
+    }
+}
+
+#[cfg(any(test, feature = "test-utils"))]
+impl Clock for TestClock {
+    fn now_unix(&self) -> i64 {
+        self.start_unix + self.elapsed.lock().unwrap().as_secs() as i64
+    }
+
+    fn instant_now(&self) -> Instant {
+        self.start_instant + *self.elapsed.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_test_clock_moves_only_when_advanced() {
+        let clock = TestClock::new(1_700_000_000);
+        let start = clock.instant_now();
+        assert_eq!(clock.now_unix(), 1_700_000_000);
+
+        clock.advance(Duration::from_millis(1500));
+        assert_eq!(clock.now_unix(), 1_700_000_001);
+        assert_eq!(clock.instant_now() - start, Duration::from_millis(1500));
+
+        clock.set_unix(1_700_000_060);
+        assert_eq!(clock.now_unix(), 1_700_000_060);
+    }
+}
@@ -1,147 +1,1510 @@
 //! Configuration management for Sentinel
 //!
-//! Configuration is loaded from environment variables.
+//! Configuration is loaded from environment variables into nested sections.
+//! Each field can be set as `SENTINEL_<SECTION>__<FIELD>` (for example
+//! `SENTINEL_SERVER__PORT` or `SENTINEL_RATE_LIMIT__ORG_MAX_REQUESTS`); the
+//! flat names used before the split (`SENTINEL_PORT`, `ORG_RATE_LIMIT_MAX_REQUESTS`,
+//! ...) keep working as aliases, see [`LEGACY_NAMES`]. When both are set the
+//! sectioned name wins.
 
 use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::HashMap;
 use std::env;
 
+use crate::injection::InjectionMode;
+use crate::middleware::compat::CompatMode;
+use crate::native::translate::ParamOutOfRange;
+use crate::proxy::capabilities::ProviderCheckMode;
+use crate::proxy::content_filter::ContentFilter;
+use crate::proxy::deprecated::DeprecatedParams;
+use crate::proxy::provider::ProviderKind;
+use crate::proxy::signing::AuthMode;
+use crate::proxy::static_models::{ModelsSource, StaticModels};
+use crate::proxy::stream_accept::StreamAcceptMismatch;
+use crate::proxy::upstream_user::UpstreamUserField;
+use crate::tokens::Encoding;
+use crate::usage::exact::ExactUsageMode;
+use crate::usage::weights::RequestWeightTable;
+use crate::zion::{MissingLimitPolicy, PayloadCase};
+
+/// Upstream response headers captured when `UPSTREAM_CAPTURE_HEADERS` is unset
+const DEFAULT_UPSTREAM_CAPTURE_HEADERS: &str = "x-request-id,openai-processing-ms,\
+x-ratelimit-remaining-requests,x-ratelimit-remaining-tokens,\
+x-ratelimit-reset-requests,x-ratelimit-reset-tokens";
+
+/// Message returned during maintenance when `MAINTENANCE_MESSAGE` is unset
+pub const DEFAULT_MAINTENANCE_MESSAGE: &str =
+    "Sentinel is undergoing scheduled maintenance. Please retry shortly.";
+
+/// Paths excluded from request logging when `QUIET_LOG_PATHS` is unset
+const DEFAULT_QUIET_LOG_PATHS: &[&str] = &["/health", "/health/ready", "/health/live"];
+
+/// Pre-section environment variable names: (name, section, field)
+pub const LEGACY_NAMES: &[(&str, &str, &str)] = &[
+    ("SENTINEL_HOST", "server", "host"),
+    ("SENTINEL_PORT", "server", "port"),
+    ("SENTINEL_DEBUG", "server", "debug_enabled"),
+    ("ADMIN_API_KEY", "server", "admin_api_key"),
+    ("AUTH_ALLOW_X_API_KEY", "server", "allow_x_api_key"),
+    ("AUTH_UNSCOPED_FULL_ACCESS", "server", "unscoped_full_access"),
+    ("MAINTENANCE_MODE", "server", "maintenance_mode"),
+    ("MAINTENANCE_MESSAGE", "server", "maintenance_message"),
+    ("MAINTENANCE_RETRY_AFTER_SECONDS", "server", "maintenance_retry_after_seconds"),
+    ("PAYLOAD_WARN_REQUEST_BYTES", "server", "payload_warn_request_bytes"),
+    ("PAYLOAD_WARN_RESPONSE_BYTES", "server", "payload_warn_response_bytes"),
+    ("MAX_REQUEST_BODY_BYTES", "server", "max_request_body_bytes"),
+    ("JSON_MAX_DEPTH", "server", "json_max_depth"),
+    ("JSON_MAX_KEYS", "server", "json_max_keys"),
+    ("JSON_MAX_STRING_BYTES", "server", "json_max_string_bytes"),
+    ("MIRROR_URL", "server", "mirror_url"),
+    ("MIRROR_AUTH_TOKEN", "server", "mirror_auth_token"),
+    ("MIRROR_SAMPLE_RATE", "server", "mirror_sample_rate"),
+    ("MIRROR_MAX_CONCURRENCY", "server", "mirror_max_concurrency"),
+    ("LOG_LEVEL_REVERT_SECONDS", "server", "log_level_revert_seconds"),
+    ("SENTINEL_REPLICA_ID", "server", "replica_id"),
+    ("METRICS_LABELS", "server", "metrics_labels"),
+    ("METRICS_TOKEN", "server", "metrics_token"),
+    ("QUIET_LOG_PATHS", "server", "quiet_log_paths"),
+    ("JSON_RESPONSE_CHARSET", "server", "json_response_charset"),
+    ("COMPAT_MODE", "server", "compat_mode"),
+    ("CHAOS_ENABLED", "server", "chaos_enabled"),
+    ("REDIS_URL", "redis", "url"),
+    ("ZION_API_URL", "zion", "api_url"),
+    ("ZION_API_KEY", "zion", "api_key"),
+    ("CACHE_TTL_SECONDS", "zion", "cache_ttl_seconds"),
+    ("JWT_CACHE_TTL_SECONDS", "zion", "jwt_cache_ttl_seconds"),
+    ("TIER_CONFIG_TTL_SECONDS", "zion", "tier_config_ttl_seconds"),
+    ("TIER_CONFIG_STANDBY_PATH", "zion", "tier_config_standby_path"),
+    ("TIER_CONFIG_MAX_STALENESS_HOURS", "zion", "tier_config_max_staleness_hours"),
+    ("ZION_META_TTL_SECONDS", "zion", "meta_ttl_seconds"),
+    ("MISSING_LIMIT_POLICY", "zion", "missing_limit_policy"),
+    ("ZION_PAYLOAD_CASE", "zion", "payload_case"),
+    ("CACHE_WARM_CONCURRENCY", "zion", "cache_warm_concurrency"),
+    ("CACHE_WARM_RATE_PER_SECOND", "zion", "cache_warm_rate_per_second"),
+    ("AUTH_PATH_BUDGET_MS", "zion", "auth_path_budget_ms"),
+    ("ZION_JWKS_URL", "zion", "jwks_url"),
+    ("ZION_JWT_ISSUER", "zion", "jwt_issuer"),
+    ("AI_PROVIDER", "provider", "ai_provider"),
+    ("OPENAI_API_URL", "provider", "openai_api_url"),
+    ("OPENAI_API_KEY", "provider", "openai_api_key"),
+    ("OPENAI_AUTH_MODE", "provider", "openai_auth_mode"),
+    ("OPENAI_SIGV4_REGION", "provider", "openai_sigv4_region"),
+    ("OPENAI_SIGV4_SERVICE", "provider", "openai_sigv4_service"),
+    ("OPENAI_SIGV4_ACCESS_KEY_ID", "provider", "openai_sigv4_access_key_id"),
+    ("OPENAI_SIGV4_SECRET_ACCESS_KEY", "provider", "openai_sigv4_secret_access_key"),
+    ("OPENAI_SIGV4_SESSION_TOKEN", "provider", "openai_sigv4_session_token"),
+    ("ANTHROPIC_API_URL", "provider", "anthropic_api_url"),
+    ("ANTHROPIC_API_KEY", "provider", "anthropic_api_key"),
+    ("FAILOVER_PROVIDERS", "provider", "failover_providers"),
+    ("FAILOVER_ATTEMPT_TIMEOUT_MS", "provider", "failover_attempt_timeout_ms"),
+    ("FAILOVER_MODEL_MAP", "provider", "failover_model_map"),
+    ("SESSION_TTL_SECONDS", "provider", "session_ttl_seconds"),
+    ("SESSION_HISTORY_MAX_MESSAGES", "provider", "session_history_max_messages"),
+    ("AFFINITY_SECRET", "provider", "affinity_secret"),
+    ("AFFINITY_LOCAL_TTL_SECONDS", "provider", "affinity_local_ttl_seconds"),
+    ("STREAM_LOCK_TTL_SECONDS", "provider", "stream_lock_ttl_seconds"),
+    ("STREAM_LOCK_WAIT_MS", "provider", "stream_lock_wait_ms"),
+    ("NATIVE_BATCH_MAX_ITEMS", "provider", "native_batch_max_items"),
+    ("NATIVE_BATCH_CONCURRENCY", "provider", "native_batch_concurrency"),
+    ("NATIVE_EMBEDDINGS_MAX_INPUT_TOKENS", "provider", "native_embeddings_max_input_tokens"),
+    ("SYSTEM_PROMPT_INJECTION", "provider", "system_prompt_injection"),
+    ("SYSTEM_PROMPT_INJECTION_MODE", "provider", "system_prompt_injection_mode"),
+    ("CONTENT_NORMALIZE_NFC", "provider", "content_normalize_nfc"),
+    ("PARAM_OUT_OF_RANGE", "provider", "param_out_of_range"),
+    ("DEPRECATED_PARAMS", "provider", "deprecated_params"),
+    ("STREAM_ACCEPT_MISMATCH", "provider", "stream_accept_mismatch"),
+    ("UPSTREAM_USER_FIELD", "provider", "upstream_user_field"),
+    ("UPSTREAM_USER_HASH_KEY", "provider", "upstream_user_hash_key"),
+    ("UPSTREAM_TIMEOUT_MIN_MS", "provider", "upstream_timeout_min_ms"),
+    ("UPSTREAM_TIMEOUT_MAX_MS", "provider", "upstream_timeout_max_ms"),
+    ("PROGRESS_INTERVAL_MS", "provider", "progress_interval_ms"),
+    ("SSE_MAX_LINE_BYTES", "provider", "sse_max_line_bytes"),
+    ("STREAM_USAGE_INJECTION", "provider", "stream_usage_injection"),
+    ("UPSTREAM_CAPTURE_HEADERS", "provider", "upstream_capture_headers"),
+    ("CONTEXT_FALLBACK", "provider", "context_fallback"),
+    ("TIER_LATENCY_WEIGHT", "provider", "tier_latency_weight"),
+    ("TIER_HEALTH_RETENTION_HOURS", "provider", "tier_health_retention_hours"),
+    ("TIER_HEALTH_PRUNE_INTERVAL_SECONDS", "provider", "tier_health_prune_interval_seconds"),
+    ("STARTUP_PROVIDER_CHECK", "provider", "startup_provider_check"),
+    ("MODELS_SOURCE", "provider", "models_source"),
+    ("STATIC_MODELS_JSON", "provider", "static_models"),
+    ("PROVIDER_CANARY_EXTERNAL_IDS", "provider", "canary_external_ids"),
+    ("RESPONSE_STRIP_TAGS", "provider", "response_strip_tags"),
+    ("RESPONSE_DROP_FIELDS", "provider", "response_drop_fields"),
+    ("RESPONSE_BLOCKLIST_JSON", "provider", "response_blocklist"),
+    ("FINISH_REASON_ALERT_PERCENT", "provider", "finish_reason_alert_percent"),
+    ("FINISH_REASON_WINDOW_SECONDS", "provider", "finish_reason_window_seconds"),
+    ("FINISH_REASON_MIN_SAMPLES", "provider", "finish_reason_min_samples"),
+    ("VALIDATE_UPSTREAM_RESPONSES", "provider", "validate_upstream_responses"),
+    ("UPSTREAM_CIRCUIT_BREAKER_THRESHOLD", "provider", "circuit_breaker_threshold"),
+    ("UPSTREAM_CIRCUIT_BREAKER_RESET_SECONDS", "provider", "circuit_breaker_reset_seconds"),
+    ("UPSTREAM_CIRCUIT_BREAKER_HALF_OPEN_PROBES", "provider", "circuit_breaker_half_open_probes"),
+    ("UPSTREAM_RESPONSE_MAX_HEADERS", "provider", "upstream_response_max_headers"),
+    ("UPSTREAM_RESPONSE_MAX_HEADER_BYTES", "provider", "upstream_response_max_header_bytes"),
+    ("UPSTREAM_ALLOW_SET_COOKIE", "provider", "upstream_allow_set_cookie"),
+    ("UPSTREAM_STREAM_POOL_MAX_IDLE", "provider", "upstream_stream_pool_max_idle"),
+    ("UPSTREAM_SHORT_POOL_MAX_IDLE", "provider", "upstream_short_pool_max_idle"),
+    ("UPSTREAM_SHORT_CONNECT_TIMEOUT_MS", "provider", "upstream_short_connect_timeout_ms"),
+    ("RATE_LIMIT_EXEMPT_IDS", "rate_limit", "exempt_ids"),
+    ("ORG_RATE_LIMIT_MAX_REQUESTS", "rate_limit", "org_max_requests"),
+    ("ORG_RATE_LIMIT_OVERRIDES", "rate_limit", "org_overrides"),
+    ("RATE_LIMIT_MAX_TOKENS_PER_WINDOW", "rate_limit", "max_tokens_per_window"),
+    ("QUARANTINE_MALFORMED_THRESHOLD", "rate_limit", "quarantine_malformed_threshold"),
+    ("QUARANTINE_WINDOW_SECONDS", "rate_limit", "quarantine_window_seconds"),
+    ("QUARANTINE_DURATION_SECONDS", "rate_limit", "quarantine_duration_seconds"),
+    ("USAGE_AGGREGATE_DAYS", "usage", "aggregate_days"),
+    ("USAGE_REPORT_DAYS", "usage", "report_days"),
+    ("USAGE_REPORT_MAX_GROUPS", "usage", "report_max_groups"),
+    ("LEDGER_DATABASE_URL", "usage", "ledger_database_url"),
+    ("IMAGE_DEFAULT_TOKENS", "usage", "image_default_tokens"),
+    ("TOKEN_FALLBACK_ENCODING", "usage", "token_fallback_encoding"),
+    ("MODEL_ENCODING_OVERRIDES", "usage", "model_encoding_overrides"),
+    ("USAGE_REQUEST_WEIGHTS_JSON", "usage", "request_weights"),
+    ("SYNTHETIC_EXTERNAL_IDS", "usage", "synthetic_external_ids"),
+    ("REQUIRE_EXACT_USAGE", "usage", "require_exact_usage"),
+    ("EXACT_USAGE_MODE", "usage", "exact_usage_mode"),
+    ("REPORT_ESTIMATED_USAGE", "usage", "report_estimated_usage"),
+];
+
 /// Application configuration
 #[derive(Debug, Clone)]
 pub struct Config {
+    pub server: ServerConfig,
+    pub redis: RedisConfig,
+    pub zion: ZionConfig,
+    pub provider: ProviderConfig,
+    pub rate_limit: RateLimitConfig,
+    pub usage: UsageConfig,
+}
+
+/// HTTP server, operator endpoints and maintenance (`SENTINEL_SERVER__*`)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
     /// Host to bind to
     pub host: String,
     /// Port to listen on
     pub port: u16,
 
+    /// Enable debug endpoints (development only)
+    #[serde(deserialize_with = "de::flag")]
+    pub debug_enabled: bool,
+
+    /// Key required in X-Admin-Key for /admin endpoints (None = admin endpoints disabled)
+    #[serde(deserialize_with = "de::non_blank")]
+    pub admin_api_key: Option<String>,
+    /// Also accept the Zion JWT in `X-Api-Key` (for clients that can't set Authorization)
+    #[serde(deserialize_with = "de::flag")]
+    pub allow_x_api_key: bool,
+    /// Tokens without a Zion `scopes` claim may call every route (default: true)
+    #[serde(deserialize_with = "de::flag")]
+    pub unscoped_full_access: bool,
+
+    /// Start in maintenance mode (model endpoints return 503; overridable via /admin/maintenance)
+    #[serde(deserialize_with = "de::flag")]
+    pub maintenance_mode: bool,
+    /// Message returned to clients during maintenance
+    #[serde(deserialize_with = "de::maintenance_message")]
+    pub maintenance_message: String,
+    /// Retry-After sent during maintenance (in seconds, default: 300)
+    pub maintenance_retry_after_seconds: u64,
+
+    /// Request body size (client or forwarded) that logs a payload warning (in bytes, default: 1 MiB)
+    pub payload_warn_request_bytes: u64,
+    /// Response body size (or streamed total) that logs a payload warning (in bytes, default: 2 MiB)
+    pub payload_warn_response_bytes: u64,
+    /// Largest request body a compressed upload may expand to (in bytes, default: 32 MiB)
+    pub max_request_body_bytes: usize,
+    /// Deepest array/object nesting accepted in JSON bodies (default: 64, 0 = unlimited)
+    pub json_max_depth: usize,
+    /// Object keys accepted in a JSON body (default: 10000, 0 = unlimited)
+    pub json_max_keys: usize,
+    /// Longest single string accepted in a JSON body (in bytes, default: 16 MiB, 0 = unlimited)
+    pub json_max_string_bytes: usize,
+
+    /// Staging Sentinel that receives a sample of requests (None = mirroring disabled)
+    #[serde(deserialize_with = "de::non_blank")]
+    pub mirror_url: Option<String>,
+    /// Token sent as the Bearer credential on mirrored requests
+    #[serde(deserialize_with = "de::non_blank")]
+    pub mirror_auth_token: Option<String>,
+    /// Fraction of requests mirrored (0.0-1.0, default: 0.01)
+    pub mirror_sample_rate: f64,
+    /// Mirrored requests in flight at once; extra samples are dropped (default: 8)
+    pub mirror_max_concurrency: usize,
+
+    /// Default delay before a `PUT /admin/log-level` change reverts (default: 900, 0 = never)
+    pub log_level_revert_seconds: u64,
+
+    /// Name of this replica for the usage retry lease (None = HOSTNAME, else random)
+    #[serde(deserialize_with = "de::non_blank")]
+    pub replica_id: Option<String>,
+
+    /// Constant labels added to every exported metric (`METRICS_LABELS=env=prod,region=eu`)
+    #[serde(deserialize_with = "de::metric_labels")]
+    pub metrics_labels: Vec<(String, String)>,
+    /// Bearer token required to scrape /metrics (None = public)
+    #[serde(deserialize_with = "de::non_blank")]
+    pub metrics_token: Option<String>,
+    /// Paths served without request logging, e.g. load balancer probes (default: the health endpoints)
+    #[serde(deserialize_with = "de::id_list")]
+    pub quiet_log_paths: Vec<String>,
+    /// Send `application/json; charset=utf-8` rather than bare `application/json` on JSON responses
+    #[serde(deserialize_with = "de::flag")]
+    pub json_response_charset: bool,
+    /// `/v1` compatibility: `standard` (default) or `strict`, which drops Sentinel's extensions
+    #[serde(deserialize_with = "de::parsed")]
+    pub compat_mode: CompatMode,
+    /// Serve the fault injection endpoints under /admin/chaos (needs the `chaos` feature; staging only)
+    #[serde(deserialize_with = "de::flag")]
+    pub chaos_enabled: bool,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            host: "0.0.0.0".to_string(),
+            port: 8080,
+            debug_enabled: false,
+            admin_api_key: None,
+            allow_x_api_key: false,
+            unscoped_full_access: true,
+            maintenance_mode: false,
+            maintenance_message: DEFAULT_MAINTENANCE_MESSAGE.to_string(),
+            maintenance_retry_after_seconds: 300,
+            payload_warn_request_bytes: 1_048_576,
+            payload_warn_response_bytes: 2_097_152,
+            max_request_body_bytes: 33_554_432,
+            json_max_depth: 64,
+            json_max_keys: 10_000,
+            json_max_string_bytes: 16_777_216,
+            mirror_url: None,
+            mirror_auth_token: None,
+            mirror_sample_rate: 0.01,
+            mirror_max_concurrency: 8,
+            log_level_revert_seconds: 900,
+            replica_id: None,
+            metrics_labels: Vec::new(),
+            metrics_token: None,
+            quiet_log_paths: DEFAULT_QUIET_LOG_PATHS.iter().map(|path| path.to_string()).collect(),
+            json_response_charset: false,
+            compat_mode: CompatMode::default(),
+            chaos_enabled: false,
+        }
+    }
+}
+
+/// Redis connection (`SENTINEL_REDIS__*`)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RedisConfig {
     /// Redis connection URL
-    pub redis_url: String,
+    pub url: String,
+}
 
-    /// Zion API base URL
-    pub zion_api_url: String,
-    /// Zion API key for external service authentication
-    pub zion_api_key: String,
+impl Default for RedisConfig {
+    fn default() -> Self {
+        Self {
+            url: "redis://localhost:6379".to_string(),
+        }
+    }
+}
 
-    /// OpenAI API URL
-    pub openai_api_url: String,
-    /// OpenAI API key (required for AI provider)
-    pub openai_api_key: Option<String>,
+/// Zion API access and the caches in front of it (`SENTINEL_ZION__*`)
+#[derive(Debug, Clone, Deserialize)]
+pub struct ZionConfig {
+    /// Zion API base URL (required)
+    pub api_url: String,
+    /// Zion API key for external service authentication (required)
+    pub api_key: String,
 
-    /// Cache TTL for user limits (in seconds)
+    /// Cache TTL for user limits (in seconds, default: 300)
+    #[serde(default = "de::default_cache_ttl")]
     pub cache_ttl_seconds: u64,
-    /// Cache TTL for JWT validation (in seconds)
+    /// Cache TTL for JWT validation (in seconds, default: 300)
+    #[serde(default = "de::default_cache_ttl")]
     pub jwt_cache_ttl_seconds: u64,
+    /// Cache TTL for tier configuration (in seconds, default: 30 minutes)
+    #[serde(default = "de::default_tier_config_ttl")]
+    pub tier_config_ttl_seconds: u64,
+    /// File the last-known-good tier config is also kept in (default: unset, Redis only)
+    #[serde(default, deserialize_with = "de::non_blank")]
+    pub tier_config_standby_path: Option<String>,
+    /// Oldest standby tier config served while Zion is unreachable (in hours, default: 72, 0 = never)
+    #[serde(default = "de::default_tier_config_max_staleness")]
+    pub tier_config_max_staleness_hours: u64,
+    /// How often Zion's advertised capabilities are re-read (in seconds, default: 300)
+    #[serde(default = "de::default_cache_ttl")]
+    pub meta_ttl_seconds: u64,
+
+    /// How to treat a Zion limits payload without the `ai_usage` entry (default: unlimited)
+    #[serde(default, deserialize_with = "de::parsed")]
+    pub missing_limit_policy: MissingLimitPolicy,
+    /// Field casing of usage increments sent to Zion (default: camel)
+    #[serde(default, deserialize_with = "de::parsed")]
+    pub payload_case: PayloadCase,
+
+    /// Limits fetched in parallel by a cache warm job (default: 8)
+    #[serde(default = "de::default_cache_warm_concurrency")]
+    pub cache_warm_concurrency: usize,
+    /// Limits fetches per second across all cache warm jobs (default: 20)
+    #[serde(default = "de::default_cache_warm_rate")]
+    pub cache_warm_rate_per_second: u32,
+
+    /// Longest a request waits for Zion to validate its token and load its
+    /// limits before a 503 (in milliseconds, default: 0 = no limit)
+    #[serde(default)]
+    pub auth_path_budget_ms: u64,
+    /// Zion's JWKS; tokens signed with its keys are verified locally (default: unset, always ask Zion)
+    #[serde(default, deserialize_with = "de::non_blank")]
+    pub jwks_url: Option<String>,
+    /// `iss` locally verified tokens must carry (default: unset, not checked)
+    #[serde(default, deserialize_with = "de::non_blank")]
+    pub jwt_issuer: Option<String>,
+}
+
+/// Upstream AI provider and what is sent to it (`SENTINEL_PROVIDER__*`)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ProviderConfig {
+    /// Upstream the proxy talks to: `openai` (default) or `anthropic`
+    #[serde(deserialize_with = "de::parsed")]
+    pub ai_provider: ProviderKind,
+    /// OpenAI API URL
+    pub openai_api_url: String,
+    /// OpenAI API key (required for AI provider with bearer auth)
+    pub openai_api_key: Option<String>,
+    /// How provider requests are authenticated: `bearer` (default) or `sigv4`
+    #[serde(deserialize_with = "de::parsed")]
+    pub openai_auth_mode: AuthMode,
+    /// SigV4 signing region (required with `sigv4`)
+    #[serde(deserialize_with = "de::non_blank")]
+    pub openai_sigv4_region: Option<String>,
+    /// SigV4 signing service name (default: execute-api)
+    pub openai_sigv4_service: String,
+    /// SigV4 access key id (required with `sigv4`)
+    #[serde(deserialize_with = "de::non_blank")]
+    pub openai_sigv4_access_key_id: Option<String>,
+    /// SigV4 secret access key (required with `sigv4`)
+    #[serde(deserialize_with = "de::non_blank")]
+    pub openai_sigv4_secret_access_key: Option<String>,
+    /// SigV4 session token for temporary credentials
+    #[serde(deserialize_with = "de::non_blank")]
+    pub openai_sigv4_session_token: Option<String>,
+    /// Anthropic API URL
+    pub anthropic_api_url: String,
+    /// Anthropic API key (required with `AI_PROVIDER=anthropic`)
+    #[serde(deserialize_with = "de::non_blank")]
+    pub anthropic_api_key: Option<String>,
+    /// Providers tried in order when `AI_PROVIDER` fails with a 5xx or connection error (empty = no failover)
+    #[serde(deserialize_with = "de::provider_list")]
+    pub failover_providers: Vec<ProviderKind>,
+    /// Time allowed per failover attempt, until the first chunk for streams
+    /// (in milliseconds, default: `UPSTREAM_TIMEOUT_MAX_MS`, 0 = unbounded)
+    pub failover_attempt_timeout_ms: Option<u64>,
+    /// Model the failover providers are asked for, per requested model (`gpt-4o=claude-sonnet-4-5`)
+    #[serde(deserialize_with = "de::model_map")]
+    pub failover_model_map: HashMap<String, String>,
 
     /// Session TTL for provider stickiness (in seconds, default: 24 hours)
     pub session_ttl_seconds: u64,
+    /// Messages of conversation history kept per session for export (default: 0 = no history)
+    pub session_history_max_messages: usize,
+    /// Key for `X-Sentinel-Affinity` routing hints (None = no hints, no local session copies)
+    #[serde(deserialize_with = "de::non_blank")]
+    pub affinity_secret: Option<String>,
+    /// How long a local session copy serves requests with a valid hint (in seconds, default: 30)
+    pub affinity_local_ttl_seconds: u64,
+    /// Expiry of a conversation's stream lock unless refreshed by a running stream (in seconds, default: 60)
+    pub stream_lock_ttl_seconds: u64,
+    /// How long a second stream in a conversation waits for the lock before a 409 (in milliseconds, default: 0)
+    pub stream_lock_wait_ms: u64,
+    /// Most requests in one `POST /native/v1/chat/completions/batch` (default: 50)
+    pub native_batch_max_items: usize,
+    /// Batch items run at the same time (default: 8)
+    pub native_batch_concurrency: usize,
+    /// Most input tokens in one `POST /native/v1/embeddings`, across all items (default: 300000)
+    pub native_embeddings_max_input_tokens: usize,
 
-    /// Cache TTL for tier configuration (in seconds, default: 30 minutes)
-    pub tier_config_ttl_seconds: u64,
+    /// System prompt injected into every chat conversation (None = disabled)
+    #[serde(deserialize_with = "de::non_blank")]
+    pub system_prompt_injection: Option<String>,
+    /// How the injected system prompt is applied (default: prepend)
+    #[serde(deserialize_with = "de::parsed")]
+    pub system_prompt_injection_mode: InjectionMode,
 
-    /// Enable debug endpoints (development only)
-    pub debug_enabled: bool,
+    /// Normalize chat message text to Unicode NFC before estimation and forwarding (default: false)
+    #[serde(deserialize_with = "de::flag")]
+    pub content_normalize_nfc: bool,
+
+    /// Native sampling parameters outside the provider's bounds: `reject` (default) or `clamp`
+    #[serde(deserialize_with = "de::parsed")]
+    pub param_out_of_range: ParamOutOfRange,
+
+    /// `/v1` requests using `functions`, `function_call` or a reasoning model's `max_tokens`: `warn` (default), `translate` or `reject`
+    #[serde(deserialize_with = "de::parsed")]
+    pub deprecated_params: DeprecatedParams,
+
+    /// Chat streams whose `Accept` header excludes `text/event-stream`: `reject` with a 406 (default) or `warn`
+    #[serde(deserialize_with = "de::parsed")]
+    pub stream_accept_mismatch: StreamAcceptMismatch,
+
+    /// `user` field forwarded upstream: `passthrough` (default), `hash`, `external_id_hash` or `omit`
+    #[serde(deserialize_with = "de::parsed")]
+    pub upstream_user_field: UpstreamUserField,
+    /// HMAC key of the hashing `UPSTREAM_USER_FIELD` modes (required by them)
+    #[serde(deserialize_with = "de::non_blank")]
+    pub upstream_user_hash_key: Option<String>,
+
+    /// Lower bound for client-requested upstream timeouts (in milliseconds, default: 1000)
+    pub upstream_timeout_min_ms: u64,
+    /// Upper bound for client-requested upstream timeouts (in milliseconds, default: 300000)
+    pub upstream_timeout_max_ms: u64,
+
+    /// Heartbeat interval of `X-Sentinel-Progress: sse` responses (in milliseconds, default: 5000)
+    pub progress_interval_ms: u64,
+
+    /// Longest upstream SSE line buffered before the stream is aborted
+    pub sse_max_line_bytes: usize,
+
+    /// Request a usage chunk on chat streams whose client didn't, consuming it before the client (default: true)
+    #[serde(deserialize_with = "de::flag")]
+    pub stream_usage_injection: bool,
+
+    /// Upstream response headers captured for logs and correlation (lowercase names)
+    #[serde(deserialize_with = "de::header_list")]
+    pub upstream_capture_headers: Vec<String>,
+
+    /// Retry native requests that exceed the model's context on the tier's long-context model
+    #[serde(deserialize_with = "de::flag")]
+    pub context_fallback: bool,
+
+    /// Share of tier selection weight given to live p95 latency over cost (0..=1, default: 0)
+    pub tier_latency_weight: f64,
+    /// Hours a model may be absent from the tier config before its health state is pruned (default: 24)
+    pub tier_health_retention_hours: u64,
+    /// Seconds between health prune passes (default: 600, 0 = never prune)
+    pub tier_health_prune_interval_seconds: u64,
+
+    /// Probe providers' `/models` against the tier config at startup (`off`, `warn` or `fail`)
+    #[serde(deserialize_with = "de::parsed")]
+    pub startup_provider_check: ProviderCheckMode,
+
+    /// Where `/v1/models` takes its list from (`upstream`, `static` or `merged`)
+    #[serde(deserialize_with = "de::parsed")]
+    pub models_source: ModelsSource,
+    /// Models in the OpenAI list format, inline or a file path (required for `static` and `merged`)
+    #[serde(deserialize_with = "de::static_models")]
+    pub static_models: Option<StaticModels>,
+
+    /// External IDs allowed to pick a provider per request with `X-Sentinel-Provider`
+    #[serde(deserialize_with = "de::id_list")]
+    pub canary_external_ids: Vec<String>,
+
+    /// Tags whose blocks are removed from responses of models flagged `stripReasoning`
+    #[serde(deserialize_with = "de::id_list")]
+    pub response_strip_tags: Vec<String>,
+
+    /// Message/delta fields dropped from responses of models flagged `stripReasoning`
+    #[serde(deserialize_with = "de::id_list")]
+    pub response_drop_fields: Vec<String>,
+
+    /// Regex blocklist for chat responses (None = no blocking)
+    #[serde(deserialize_with = "de::blocklist")]
+    pub response_blocklist: Option<ContentFilter>,
+
+    /// Share of `content_filter` finish reasons that logs a warning (in percent, default: 20, 0 = off)
+    pub finish_reason_alert_percent: f64,
+    /// Sliding window for the `content_filter` share (in seconds, default: 300)
+    pub finish_reason_window_seconds: u64,
+    /// Responses needed in the window before the share is judged (default: 50)
+    pub finish_reason_min_samples: u64,
+
+    /// Turn structurally broken non-streaming chat completions into a 502 (default: true)
+    #[serde(deserialize_with = "de::flag")]
+    pub validate_upstream_responses: bool,
+
+    /// Consecutive 5xx responses or timeouts that open a provider endpoint's circuit (0 = disabled, default: 5)
+    pub circuit_breaker_threshold: u32,
+    /// How long an open circuit fails fast before probing again (in seconds, default: 30)
+    pub circuit_breaker_reset_seconds: u64,
+    /// Probe requests let through at once while a circuit is half-open (default: 1)
+    pub circuit_breaker_half_open_probes: u32,
+
+    /// Most upstream response headers forwarded to clients on passthrough routes (default: 64)
+    pub upstream_response_max_headers: usize,
+    /// Most upstream response header bytes (names plus values) forwarded to clients (default: 16384)
+    pub upstream_response_max_header_bytes: usize,
+    /// Forward upstream `Set-Cookie` headers to clients (default: false)
+    #[serde(deserialize_with = "de::flag")]
+    pub upstream_allow_set_cookie: bool,
+
+    /// Idle connections kept per host by the streaming upstream client (default: 256)
+    pub upstream_stream_pool_max_idle: usize,
+    /// Idle connections kept per host by the client for non-streaming calls (default: 32)
+    pub upstream_short_pool_max_idle: usize,
+    /// Connect timeout of the client for non-streaming calls (in milliseconds, default: 5000)
+    pub upstream_short_connect_timeout_ms: u64,
+}
+
+impl Default for ProviderConfig {
+    fn default() -> Self {
+        Self {
+            ai_provider: ProviderKind::default(),
+            openai_api_url: "https://api.openai.com/v1".to_string(),
+            openai_api_key: None,
+            openai_auth_mode: AuthMode::default(),
+            openai_sigv4_region: None,
+            openai_sigv4_service: "execute-api".to_string(),
+            openai_sigv4_access_key_id: None,
+            openai_sigv4_secret_access_key: None,
+            openai_sigv4_session_token: None,
+            anthropic_api_url: "https://api.anthropic.com/v1".to_string(),
+            anthropic_api_key: None,
+            failover_providers: Vec::new(),
+            failover_attempt_timeout_ms: None,
+            failover_model_map: HashMap::new(),
+            session_ttl_seconds: 86400,
+            session_history_max_messages: 0,
+            affinity_secret: None,
+            affinity_local_ttl_seconds: 30,
+            stream_lock_ttl_seconds: 60,
+            stream_lock_wait_ms: 0,
+            native_batch_max_items: 50,
+            native_batch_concurrency: 8,
+            native_embeddings_max_input_tokens: 300_000,
+            system_prompt_injection: None,
+            system_prompt_injection_mode: InjectionMode::default(),
+            content_normalize_nfc: false,
+            param_out_of_range: ParamOutOfRange::default(),
+            deprecated_params: DeprecatedParams::default(),
+            stream_accept_mismatch: StreamAcceptMismatch::default(),
+            upstream_user_field: UpstreamUserField::default(),
+            upstream_user_hash_key: None,
+            upstream_timeout_min_ms: 1000,
+            upstream_timeout_max_ms: 300_000,
+            progress_interval_ms: 5000,
+            sse_max_line_bytes: 1_048_576,
+            stream_usage_injection: true,
+            upstream_capture_headers: de::parse_header_list(DEFAULT_UPSTREAM_CAPTURE_HEADERS),
+            context_fallback: false,
+            tier_latency_weight: 0.0,
+            tier_health_retention_hours: 24,
+            tier_health_prune_interval_seconds: 600,
+            startup_provider_check: ProviderCheckMode::default(),
+            models_source: ModelsSource::default(),
+            static_models: None,
+            canary_external_ids: Vec::new(),
+            response_strip_tags: vec!["thinking".to_string()],
+            response_drop_fields: vec!["reasoning_content".to_string()],
+            response_blocklist: None,
+            finish_reason_alert_percent: 20.0,
+            finish_reason_window_seconds: 300,
+            finish_reason_min_samples: 50,
+            validate_upstream_responses: true,
+            circuit_breaker_threshold: 5,
+            circuit_breaker_reset_seconds: 30,
+            circuit_breaker_half_open_probes: 1,
+            upstream_response_max_headers: 64,
+            upstream_response_max_header_bytes: 16_384,
+            upstream_allow_set_cookie: false,
+            upstream_stream_pool_max_idle: 256,
+            upstream_short_pool_max_idle: 32,
+            upstream_short_connect_timeout_ms: 5000,
+        }
+    }
+}
+
+/// Request rate limits, exemptions and quarantine (`SENTINEL_RATE_LIMIT__*`)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    /// External IDs exempt from request rate limiting (e.g. internal service accounts)
+    #[serde(deserialize_with = "de::id_list")]
+    pub exempt_ids: Vec<String>,
+
+    /// Default request ceiling per organization per rate-limit window (default: 1000)
+    pub org_max_requests: i64,
+    /// Per-organization request ceilings (`ORG_RATE_LIMIT_OVERRIDES=org_a=5000,org_b=200`)
+    #[serde(deserialize_with = "de::limit_overrides")]
+    pub org_overrides: HashMap<String, i64>,
+
+    /// Prompt and completion tokens per user per minute, lowered to Zion's
+    /// remaining `aiInputTokens` (0 = disabled, default: 0)
+    pub max_tokens_per_window: i64,
+
+    /// Malformed (400/413/422) responses per window that quarantine a user (0 = disabled, default: 300)
+    pub quarantine_malformed_threshold: i64,
+    /// Window over which malformed responses are counted (in seconds, default: 60)
+    pub quarantine_window_seconds: u64,
+    /// How long a quarantined user's requests are rejected (in seconds, default: 300)
+    pub quarantine_duration_seconds: u64,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            exempt_ids: Vec::new(),
+            org_max_requests: 1000,
+            org_overrides: HashMap::new(),
+            max_tokens_per_window: 0,
+            quarantine_malformed_threshold: 300,
+            quarantine_window_seconds: 60,
+            quarantine_duration_seconds: 300,
+        }
+    }
+}
+
+/// Usage accounting and token estimation (`SENTINEL_USAGE__*`)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct UsageConfig {
+    /// Days of local per-user usage aggregates kept in Redis
+    pub aggregate_days: u32,
+
+    /// Days of per-(model, tier) usage aggregates kept for `/admin/reports/usage`
+    pub report_days: u32,
+
+    /// (model, tier) pairs counted per day; later pairs are folded into `other`
+    pub report_max_groups: usize,
+
+    /// Usage ledger database (`sqlite:` or `postgres:` URL; requires the `ledger` feature)
+    #[serde(deserialize_with = "de::non_blank")]
+    pub ledger_database_url: Option<String>,
+
+    /// Token estimate for images whose size can't be read (remote URLs)
+    pub image_default_tokens: u64,
+
+    /// Encoding for models tiktoken doesn't know (default: o200k_base)
+    #[serde(deserialize_with = "de::parsed")]
+    pub token_fallback_encoding: Encoding,
+
+    /// Encodings of specific models ahead of tiktoken (`model=encoding` or `prefix*=encoding` pairs)
+    #[serde(deserialize_with = "de::encoding_overrides")]
+    pub model_encoding_overrides: HashMap<String, Encoding>,
+
+    /// Request weights of pass-through paths (JSON object, pattern → weight; see `usage::weights`)
+    #[serde(deserialize_with = "de::request_weights")]
+    pub request_weights: RequestWeightTable,
+
+    /// External IDs whose `X-Sentinel-Synthetic: true` requests are not tracked
+    #[serde(deserialize_with = "de::id_list")]
+    pub synthetic_external_ids: Vec<String>,
+
+    /// External or organization IDs billed from provider-reported usage only (see `usage::exact`)
+    #[serde(deserialize_with = "de::id_list")]
+    pub require_exact_usage: Vec<String>,
+
+    /// Streams of those accounts: `flag` estimated usage (default) or `strict` (refuse streaming)
+    #[serde(deserialize_with = "de::parsed")]
+    pub exact_usage_mode: ExactUsageMode,
+
+    /// Mark their estimated increments with `estimated: true` for Zion (default: false)
+    #[serde(deserialize_with = "de::flag")]
+    pub report_estimated_usage: bool,
+}
+
+impl Default for UsageConfig {
+    fn default() -> Self {
+        Self {
+            aggregate_days: 30,
+            report_days: 400,
+            report_max_groups: 200,
+            ledger_database_url: None,
+            image_default_tokens: 1445,
+            token_fallback_encoding: Encoding::default(),
+            model_encoding_overrides: HashMap::new(),
+            request_weights: RequestWeightTable::default(),
+            synthetic_external_ids: Vec::new(),
+            require_exact_usage: Vec::new(),
+            exact_usage_mode: ExactUsageMode::default(),
+            report_estimated_usage: false,
+        }
+    }
 }
 
 impl Config {
     /// Load configuration from environment variables
     pub fn from_env() -> Result<Self> {
+        Self::from_vars(env::vars())
+    }
+
+    /// Load configuration from the given variables (as if they were the environment)
+    pub fn from_vars(vars: impl IntoIterator<Item = (String, String)>) -> Result<Self> {
+        let vars: HashMap<String, String> = vars.into_iter().collect();
+
         Ok(Self {
-            host: env::var("SENTINEL_HOST").unwrap_or_else(|_| "0.0.0.0".to_string()),
-            port: env::var("SENTINEL_PORT")
-                .unwrap_or_else(|_| "8080".to_string())
-                .parse()
-                .context("Invalid SENTINEL_PORT")?,
-
-            redis_url: env::var("REDIS_URL")
-                .unwrap_or_else(|_| "redis://localhost:6379".to_string()),
-
-            zion_api_url: env::var("ZION_API_URL")
-                .context("ZION_API_URL must be set")?,
-            zion_api_key: env::var("ZION_API_KEY")
-                .context("ZION_API_KEY must be set")?,
-
-            openai_api_url: env::var("OPENAI_API_URL")
-                .unwrap_or_else(|_| "https://api.openai.com/v1".to_string()),
-            openai_api_key: env::var("OPENAI_API_KEY").ok(),
-
-            cache_ttl_seconds: env::var("CACHE_TTL_SECONDS")
-                .unwrap_or_else(|_| "300".to_string())
-                .parse()
-                .context("Invalid CACHE_TTL_SECONDS")?,
-            jwt_cache_ttl_seconds: env::var("JWT_CACHE_TTL_SECONDS")
-                .unwrap_or_else(|_| "300".to_string())
-                .parse()
-                .context("Invalid JWT_CACHE_TTL_SECONDS")?,
-            session_ttl_seconds: env::var("SESSION_TTL_SECONDS")
-                .unwrap_or_else(|_| "86400".to_string())
-                .parse()
-                .context("Invalid SESSION_TTL_SECONDS")?,
-
-            tier_config_ttl_seconds: env::var("TIER_CONFIG_TTL_SECONDS")
-                .unwrap_or_else(|_| "1800".to_string())
-                .parse()
-                .context("Invalid TIER_CONFIG_TTL_SECONDS")?,
-
-            debug_enabled: env::var("SENTINEL_DEBUG")
-                .map(|v| v == "true" || v == "1")
-                .unwrap_or(false),
+            server: section(&vars, "server")?,
+            redis: section(&vars, "redis")?,
+            zion: section(&vars, "zion").context("ZION_API_URL and ZION_API_KEY must be set")?,
+            provider: section(&vars, "provider")?,
+            rate_limit: section(&vars, "rate_limit")?,
+            usage: section(&vars, "usage")?,
         })
     }
+
+    /// Defaults for tests, pointing at placeholder Zion and provider URLs
+    ///
+    /// Callers override the URLs (and anything else) for their mock servers.
+    #[cfg(any(test, feature = "test-utils"))]
+    pub fn for_tests() -> Self {
+        use crate::testing::constants;
+
+        Self {
+            server: ServerConfig {
+                host: "127.0.0.1".to_string(),
+                port: 0,
+                maintenance_message: "Scheduled maintenance".to_string(),
+                ..Default::default()
+            },
+            redis: RedisConfig::default(), // Not used in test mode
+            zion: ZionConfig {
+                api_url: "http://zion.invalid".to_string(),
+                api_key: constants::TEST_ZION_API_KEY.to_string(),
+                cache_ttl_seconds: 60,
+                jwt_cache_ttl_seconds: 60,
+                tier_config_ttl_seconds: 60,
+                tier_config_standby_path: None,
+                tier_config_max_staleness_hours: 72,
+                meta_ttl_seconds: 60,
+                missing_limit_policy: MissingLimitPolicy::default(),
+                payload_case: PayloadCase::default(),
+                cache_warm_concurrency: 8,
+                cache_warm_rate_per_second: 1000,
+                auth_path_budget_ms: 0,
+                jwks_url: None,
+                jwt_issuer: None,
+            },
+            provider: ProviderConfig {
+                openai_api_url: "http://openai.invalid/v1".to_string(),
+                openai_api_key: Some(constants::TEST_OPENAI_API_KEY.to_string()),
+                upstream_capture_headers: vec![
+                    "x-request-id".to_string(),
+                    "openai-processing-ms".to_string(),
+                ],
+                ..Default::default()
+            },
+            rate_limit: RateLimitConfig::default(),
+            usage: UsageConfig::default(),
+        }
+    }
+}
+
+/// Deserialize one section from its legacy names and `SENTINEL_<SECTION>__*` variables
+fn section<T: DeserializeOwned>(vars: &HashMap<String, String>, name: &str) -> Result<T> {
+    let prefix = format!("SENTINEL_{}__", name.to_ascii_uppercase());
+
+    let mut values: HashMap<String, String> = LEGACY_NAMES
+        .iter()
+        .filter(|(_, section, _)| *section == name)
+        .filter_map(|(legacy, _, field)| {
+            vars.get(*legacy).map(|value| (field.to_string(), value.clone()))
+        })
+        .collect();
+    values.extend(vars.iter().filter_map(|(key, value)| {
+        key.strip_prefix(&prefix)
+            .map(|field| (field.to_ascii_lowercase(), value.clone()))
+    }));
+
+    envy::from_iter(values).with_context(|| format!("Invalid {} configuration ({}*)", name, prefix))
+}
+
+/// Field parsers for values that aren't plain strings or numbers
+mod de {
+    use std::collections::HashMap;
+    use std::fmt::Display;
+    use std::str::FromStr;
+
+    use serde::{de::Error, Deserialize, Deserializer};
+
+    pub fn default_cache_ttl() -> u64 {
+        300
+    }
+
+    pub fn default_tier_config_ttl() -> u64 {
+        1800
+    }
+
+    pub fn default_tier_config_max_staleness() -> u64 {
+        72
+    }
+
+    pub fn default_cache_warm_concurrency() -> usize {
+        8
+    }
+
+    pub fn default_cache_warm_rate() -> u32 {
+        20
+    }
+
+    /// `true` or `1` enable; anything else disables
+    pub fn flag<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        Ok(value == "true" || value == "1")
+    }
+
+    /// Blank values count as unset
+    pub fn non_blank<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        Ok(Some(value).filter(|v| !v.trim().is_empty()))
+    }
+
+    pub fn maintenance_message<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+        Ok(non_blank(deserializer)?.unwrap_or_else(|| super::DEFAULT_MAINTENANCE_MESSAGE.to_string()))
+    }
+
+    /// Any type with a `FromStr` impl (the mode enums)
+    pub fn parsed<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: FromStr,
+        T::Err: Display,
+    {
+        String::deserialize(deserializer)?.parse().map_err(D::Error::custom)
+    }
+
+    /// Blank values count as unset; anything else must be a valid blocklist
+    pub fn blocklist<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<super::ContentFilter>, D::Error> {
+        match non_blank(deserializer)? {
+            Some(json) => json.parse().map(Some).map_err(D::Error::custom),
+            None => Ok(None),
+        }
+    }
+
+    /// Blank values count as unset; anything else must be a valid model list or file
+    pub fn static_models<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<super::StaticModels>, D::Error> {
+        match non_blank(deserializer)? {
+            Some(value) => value.parse().map(Some).map_err(D::Error::custom),
+            None => Ok(None),
+        }
+    }
+
+    /// Blank values keep the default table; anything else must be a valid table
+    pub fn request_weights<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<super::RequestWeightTable, D::Error> {
+        match non_blank(deserializer)? {
+            Some(json) => json.parse().map_err(D::Error::custom),
+            None => Ok(super::RequestWeightTable::default()),
+        }
+    }
+
+    pub fn id_list<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+        Ok(parse_id_list(&String::deserialize(deserializer)?))
+    }
+
+    pub fn header_list<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+        Ok(parse_header_list(&String::deserialize(deserializer)?))
+    }
+
+    pub fn provider_list<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<super::ProviderKind>, D::Error> {
+        parse_id_list(&String::deserialize(deserializer)?)
+            .iter()
+            .map(|name| name.parse().map_err(D::Error::custom))
+            .collect()
+    }
+
+    pub fn limit_overrides<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<String, i64>, D::Error> {
+        parse_limit_overrides(&String::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+
+    pub fn model_map<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<String, String>, D::Error> {
+        parse_model_map(&String::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+
+    pub fn encoding_overrides<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<String, super::Encoding>, D::Error> {
+        parse_encoding_overrides(&String::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+
+    pub fn metric_labels<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<(String, String)>, D::Error> {
+        parse_metric_labels(&String::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+
+    /// Parse a comma-separated list of IDs, skipping blanks
+    pub fn parse_id_list(value: &str) -> Vec<String> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Parse a comma-separated list of header names, lowercased
+    pub fn parse_header_list(value: &str) -> Vec<String> {
+        parse_id_list(value)
+            .into_iter()
+            .map(|name| name.to_ascii_lowercase())
+            .collect()
+    }
+
+    /// Parse comma-separated `id=limit` pairs, skipping blanks
+    pub fn parse_limit_overrides(value: &str) -> Result<HashMap<String, i64>, String> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (id, limit) = pair
+                    .split_once('=')
+                    .ok_or_else(|| format!("expected 'id=limit', got '{}'", pair))?;
+                let limit = limit
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("invalid limit in '{}'", pair))?;
+                Ok((id.trim().to_string(), limit))
+            })
+            .collect()
+    }
+
+    /// Parse comma-separated `model=encoding` pairs, skipping blanks
+    pub fn parse_encoding_overrides(
+        value: &str,
+    ) -> Result<HashMap<String, super::Encoding>, String> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (model, encoding) = pair
+                    .split_once('=')
+                    .ok_or_else(|| format!("expected 'model=encoding', got '{}'", pair))?;
+                Ok((model.trim().to_string(), encoding.parse()?))
+            })
+            .collect()
+    }
+
+    /// Parse comma-separated `model=model` pairs, skipping blanks
+    pub fn parse_model_map(value: &str) -> Result<HashMap<String, String>, String> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (from, to) = pair
+                    .split_once('=')
+                    .map(|(from, to)| (from.trim(), to.trim()))
+                    .filter(|(from, to)| !from.is_empty() && !to.is_empty())
+                    .ok_or_else(|| format!("expected 'model=model', got '{}'", pair))?;
+                Ok((from.to_string(), to.to_string()))
+            })
+            .collect()
+    }
+
+    /// Parse comma-separated `name=value` Prometheus labels, skipping blanks
+    pub fn parse_metric_labels(value: &str) -> Result<Vec<(String, String)>, String> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (name, label_value) = pair
+                    .split_once('=')
+                    .ok_or_else(|| format!("expected 'name=value', got '{}'", pair))?;
+                let name = name.trim();
+                let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                    && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+                if !valid {
+                    return Err(format!("invalid metric label name '{}'", name));
+                }
+                Ok((name.to_string(), label_value.trim().to_string()))
+            })
+            .collect()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use super::de::{
+        parse_encoding_overrides, parse_id_list, parse_limit_overrides, parse_metric_labels,
+        parse_model_map,
+    };
+
+    /// The two variables without a default
+    fn required() -> Vec<(String, String)> {
+        vars(&[("ZION_API_URL", "http://localhost:3000"), ("ZION_API_KEY", "test-key")])
+    }
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
 
     #[test]
     fn test_default_values() {
-        // Set required env vars
-        env::set_var("ZION_API_URL", "http://localhost:3000");
-        env::set_var("ZION_API_KEY", "test-key");
+        let config = Config::from_vars(required()).unwrap();
 
-        let config = Config::from_env().unwrap();
+        assert_eq!(config.server.host, "0.0.0.0");
+        assert_eq!(config.server.port, 8080);
+        assert_eq!(config.redis.url, "redis://localhost:6379");
+        assert_eq!(config.provider.openai_api_url, "https://api.openai.com/v1");
+        assert_eq!(config.provider.ai_provider, ProviderKind::OpenAI);
+        assert_eq!(config.provider.anthropic_api_url, "https://api.anthropic.com/v1");
+        assert!(config.provider.failover_providers.is_empty());
+        assert_eq!(config.provider.failover_attempt_timeout_ms, None);
+        assert!(config.provider.failover_model_map.is_empty());
+        assert_eq!(config.provider.native_embeddings_max_input_tokens, 300_000);
+        assert_eq!(config.provider.openai_auth_mode, AuthMode::Bearer);
+        assert_eq!(config.zion.cache_ttl_seconds, 300);
+        assert_eq!(
+            config.provider.upstream_capture_headers,
+            de::parse_header_list(DEFAULT_UPSTREAM_CAPTURE_HEADERS)
+        );
+        assert_eq!(config.server.maintenance_message, DEFAULT_MAINTENANCE_MESSAGE);
+        assert_eq!(
+            config.server.quiet_log_paths,
+            vec!["/health", "/health/ready", "/health/live"]
+        );
+        assert!(!config.server.json_response_charset);
+        assert_eq!(config.server.compat_mode, CompatMode::Standard);
+        assert!(!config.server.chaos_enabled);
+        assert_eq!(config.rate_limit.max_tokens_per_window, 0);
+        assert_eq!(config.zion.auth_path_budget_ms, 0);
+        assert!(config.zion.jwks_url.is_none());
+        assert!(config.zion.jwt_issuer.is_none());
+        assert_eq!(config.provider.stream_accept_mismatch, StreamAcceptMismatch::Reject);
+    }
 
-        assert_eq!(config.host, "0.0.0.0");
-        assert_eq!(config.port, 8080);
-        assert_eq!(config.redis_url, "redis://localhost:6379");
-        assert_eq!(config.openai_api_url, "https://api.openai.com/v1");
-        assert_eq!(config.cache_ttl_seconds, 300);
+    #[test]
+    fn test_session_ttl_default() {
+        let config = Config::from_vars(required()).unwrap();
 
-        // Clean up
-        env::remove_var("ZION_API_URL");
-        env::remove_var("ZION_API_KEY");
+        // Default session TTL is 24 hours (86400 seconds)
+        assert_eq!(config.provider.session_ttl_seconds, 86400);
+        assert_eq!(config.provider.session_ttl_seconds, 24 * 60 * 60);
+        assert_eq!(config.provider.session_history_max_messages, 0);
+        assert_eq!(config.provider.upstream_user_field, UpstreamUserField::Passthrough);
+        assert!(config.provider.upstream_user_hash_key.is_none());
     }
 
     #[test]
-    fn test_session_ttl_default() {
-        // Set required env vars
-        env::set_var("ZION_API_URL", "http://localhost:3000");
-        env::set_var("ZION_API_KEY", "test-key");
+    fn test_tier_config_ttl_default() {
+        let config = Config::from_vars(required()).unwrap();
 
-        let config = Config::from_env().unwrap();
+        // Default tier config TTL is 30 minutes (1800 seconds)
+        assert_eq!(config.zion.tier_config_ttl_seconds, 1800);
+        assert_eq!(config.zion.tier_config_ttl_seconds, 30 * 60);
+        assert_eq!(config.zion.tier_config_standby_path, None);
+        assert_eq!(config.zion.tier_config_max_staleness_hours, 72);
+    }
 
-        // Default session TTL is 24 hours (86400 seconds)
-        assert_eq!(config.session_ttl_seconds, 86400);
-        assert_eq!(config.session_ttl_seconds, 24 * 60 * 60);
+    #[test]
+    fn test_system_prompt_injection_default() {
+        let config = Config::from_vars(required()).unwrap();
 
-        // Clean up
-        env::remove_var("ZION_API_URL");
-        env::remove_var("ZION_API_KEY");
+        // Injection is disabled unless SYSTEM_PROMPT_INJECTION is set
+        assert!(config.provider.system_prompt_injection.is_none());
+        assert_eq!(config.provider.system_prompt_injection_mode, InjectionMode::Prepend);
+        assert_eq!(config.zion.missing_limit_policy, MissingLimitPolicy::Unlimited);
+        assert_eq!(config.provider.startup_provider_check, ProviderCheckMode::Off);
+        assert_eq!(config.provider.models_source, ModelsSource::Upstream);
+        assert!(config.provider.static_models.is_none());
+        assert_eq!(config.usage.report_days, 400);
+        assert_eq!(config.usage.report_max_groups, 200);
     }
 
     #[test]
-    fn test_tier_config_ttl_default() {
-        // Set required env vars
-        env::set_var("ZION_API_URL", "http://localhost:3000");
-        env::set_var("ZION_API_KEY", "test-key");
+    fn test_zion_credentials_required() {
+        let error = Config::from_vars(vars(&[("ZION_API_URL", "http://localhost:3000")])).unwrap_err();
+        assert!(error.to_string().contains("ZION_API_URL and ZION_API_KEY must be set"));
+    }
 
-        let config = Config::from_env().unwrap();
+    #[test]
+    fn test_legacy_variable_names() {
+        let config = Config::from_vars(vars(&[
+            ("SENTINEL_HOST", "127.0.0.1"),
+            ("SENTINEL_PORT", "9090"),
+            ("SENTINEL_DEBUG", "1"),
+            ("ADMIN_API_KEY", "admin"),
+            ("AUTH_ALLOW_X_API_KEY", "true"),
+            ("AUTH_UNSCOPED_FULL_ACCESS", "false"),
+            ("MAINTENANCE_MODE", "true"),
+            ("MAINTENANCE_MESSAGE", "Back soon"),
+            ("MAINTENANCE_RETRY_AFTER_SECONDS", "60"),
+            ("PAYLOAD_WARN_REQUEST_BYTES", "10"),
+            ("PAYLOAD_WARN_RESPONSE_BYTES", "20"),
+            ("MAX_REQUEST_BODY_BYTES", "30"),
+            ("JSON_MAX_DEPTH", "31"),
+            ("JSON_MAX_KEYS", "32"),
+            ("JSON_MAX_STRING_BYTES", "33"),
+            ("MIRROR_URL", "http://staging:8080"),
+            ("MIRROR_AUTH_TOKEN", "staging-token"),
+            ("MIRROR_SAMPLE_RATE", "0.5"),
+            ("MIRROR_MAX_CONCURRENCY", "3"),
+            ("LOG_LEVEL_REVERT_SECONDS", "34"),
+            ("SENTINEL_REPLICA_ID", "replica-b"),
+            ("METRICS_LABELS", "env=prod,region=eu"),
+            ("METRICS_TOKEN", "scrape-token"),
+            ("QUIET_LOG_PATHS", "/health/live, /ping"),
+            ("JSON_RESPONSE_CHARSET", "true"),
+            ("COMPAT_MODE", "Strict"),
+            ("CHAOS_ENABLED", "true"),
+            ("REDIS_URL", "redis://cache:6379"),
+            ("ZION_API_URL", "http://zion:3000"),
+            ("ZION_API_KEY", "zion-key"),
+            ("CACHE_TTL_SECONDS", "11"),
+            ("JWT_CACHE_TTL_SECONDS", "12"),
+            ("TIER_CONFIG_TTL_SECONDS", "13"),
+            ("TIER_CONFIG_STANDBY_PATH", "/var/lib/sentinel/tiers.json"),
+            ("TIER_CONFIG_MAX_STALENESS_HOURS", "12"),
+            ("ZION_META_TTL_SECONDS", "25"),
+            ("MISSING_LIMIT_POLICY", "zero"),
+            ("ZION_PAYLOAD_CASE", "snake"),
+            ("CACHE_WARM_CONCURRENCY", "4"),
+            ("CACHE_WARM_RATE_PER_SECOND", "50"),
+            ("AUTH_PATH_BUDGET_MS", "250"),
+            ("ZION_JWKS_URL", "https://zion.example/.well-known/jwks.json"),
+            ("ZION_JWT_ISSUER", "https://zion.example"),
+            ("AI_PROVIDER", "Anthropic"),
+            ("OPENAI_API_URL", "http://gateway/v1"),
+            ("OPENAI_API_KEY", "sk-test"),
+            ("OPENAI_AUTH_MODE", "sigv4"),
+            ("OPENAI_SIGV4_REGION", "eu-west-1"),
+            ("OPENAI_SIGV4_SERVICE", "inference"),
+            ("OPENAI_SIGV4_ACCESS_KEY_ID", "AKID"),
+            ("OPENAI_SIGV4_SECRET_ACCESS_KEY", "secret"),
+            ("OPENAI_SIGV4_SESSION_TOKEN", "session"),
+            ("ANTHROPIC_API_URL", "http://anthropic/v1"),
+            ("ANTHROPIC_API_KEY", "sk-ant-test"),
+            ("FAILOVER_PROVIDERS", "openai"),
+            ("FAILOVER_ATTEMPT_TIMEOUT_MS", "8000"),
+            ("FAILOVER_MODEL_MAP", "gpt-4o=claude-sonnet-4-5"),
+            ("SESSION_TTL_SECONDS", "14"),
+            ("SESSION_HISTORY_MAX_MESSAGES", "200"),
+            ("AFFINITY_SECRET", "affinity-key"),
+            ("AFFINITY_LOCAL_TTL_SECONDS", "26"),
+            ("STREAM_LOCK_TTL_SECONDS", "28"),
+            ("STREAM_LOCK_WAIT_MS", "29"),
+            ("NATIVE_BATCH_MAX_ITEMS", "40"),
+            ("NATIVE_BATCH_CONCURRENCY", "6"),
+            ("NATIVE_EMBEDDINGS_MAX_INPUT_TOKENS", "1000"),
+            ("SYSTEM_PROMPT_INJECTION", "Be brief."),
+            ("SYSTEM_PROMPT_INJECTION_MODE", "replace_empty"),
+            ("PARAM_OUT_OF_RANGE", "clamp"),
+            ("DEPRECATED_PARAMS", "translate"),
+            ("STREAM_ACCEPT_MISMATCH", "warn"),
+            ("UPSTREAM_USER_FIELD", "external_id_hash"),
+            ("UPSTREAM_USER_HASH_KEY", "user-hash-key"),
+            ("UPSTREAM_TIMEOUT_MIN_MS", "15"),
+            ("UPSTREAM_TIMEOUT_MAX_MS", "16"),
+            ("PROGRESS_INTERVAL_MS", "27"),
+            ("SSE_MAX_LINE_BYTES", "17"),
+            ("CONTENT_NORMALIZE_NFC", "true"),
+            ("STREAM_USAGE_INJECTION", "false"),
+            ("UPSTREAM_CAPTURE_HEADERS", "X-Request-Id, cf-ray"),
+            ("CONTEXT_FALLBACK", "true"),
+            ("TIER_LATENCY_WEIGHT", "0.25"),
+            ("TIER_HEALTH_RETENTION_HOURS", "6"),
+            ("TIER_HEALTH_PRUNE_INTERVAL_SECONDS", "60"),
+            ("STARTUP_PROVIDER_CHECK", "fail"),
+            ("MODELS_SOURCE", "merged"),
+            ("STATIC_MODELS_JSON", r#"[{"id": "local-model"}]"#),
+            ("PROVIDER_CANARY_EXTERNAL_IDS", "canary-1"),
+            ("RESPONSE_STRIP_TAGS", "think, analysis"),
+            ("RESPONSE_DROP_FIELDS", "reasoning"),
+            ("RESPONSE_BLOCKLIST_JSON", r#"{"block": ["banned"]}"#),
+            ("FINISH_REASON_ALERT_PERCENT", "12.5"),
+            ("FINISH_REASON_WINDOW_SECONDS", "120"),
+            ("FINISH_REASON_MIN_SAMPLES", "10"),
+            ("VALIDATE_UPSTREAM_RESPONSES", "false"),
+            ("UPSTREAM_CIRCUIT_BREAKER_THRESHOLD", "35"),
+            ("UPSTREAM_CIRCUIT_BREAKER_RESET_SECONDS", "36"),
+            ("UPSTREAM_CIRCUIT_BREAKER_HALF_OPEN_PROBES", "37"),
+            ("UPSTREAM_RESPONSE_MAX_HEADERS", "38"),
+            ("UPSTREAM_RESPONSE_MAX_HEADER_BYTES", "39"),
+            ("UPSTREAM_ALLOW_SET_COOKIE", "true"),
+            ("UPSTREAM_STREAM_POOL_MAX_IDLE", "40"),
+            ("UPSTREAM_SHORT_POOL_MAX_IDLE", "41"),
+            ("UPSTREAM_SHORT_CONNECT_TIMEOUT_MS", "42"),
+            ("RATE_LIMIT_EXEMPT_IDS", "eval-runner, batch-worker"),
+            ("ORG_RATE_LIMIT_MAX_REQUESTS", "18"),
+            ("ORG_RATE_LIMIT_OVERRIDES", "org_a=19"),
+            ("RATE_LIMIT_MAX_TOKENS_PER_WINDOW", "19000"),
+            ("QUARANTINE_MALFORMED_THRESHOLD", "20"),
+            ("QUARANTINE_WINDOW_SECONDS", "21"),
+            ("QUARANTINE_DURATION_SECONDS", "22"),
+            ("USAGE_AGGREGATE_DAYS", "23"),
+            ("USAGE_REPORT_DAYS", "90"),
+            ("USAGE_REPORT_MAX_GROUPS", "12"),
+            ("LEDGER_DATABASE_URL", "sqlite::memory:"),
+            ("IMAGE_DEFAULT_TOKENS", "24"),
+            ("TOKEN_FALLBACK_ENCODING", "cl100k_base"),
+            ("MODEL_ENCODING_OVERRIDES", "gpt-7*=o200k_base"),
+            ("USAGE_REQUEST_WEIGHTS_JSON", r#"{"/audio": 2}"#),
+            ("SYNTHETIC_EXTERNAL_IDS", "probe-1, monitor-2"),
+            ("REQUIRE_EXACT_USAGE", "org_exact"),
+            ("EXACT_USAGE_MODE", "strict"),
+            ("REPORT_ESTIMATED_USAGE", "true"),
+        ]))
+        .unwrap();
 
-        // Default tier config TTL is 30 minutes (1800 seconds)
-        assert_eq!(config.tier_config_ttl_seconds, 1800);
-        assert_eq!(config.tier_config_ttl_seconds, 30 * 60);
+        assert_eq!(config.server.host, "127.0.0.1");
+        assert_eq!(config.server.port, 9090);
+        assert!(config.server.debug_enabled);
+        assert_eq!(config.server.admin_api_key.as_deref(), Some("admin"));
+        assert!(config.server.allow_x_api_key);
+        assert!(!config.server.unscoped_full_access);
+        assert!(config.server.maintenance_mode);
+        assert_eq!(config.server.maintenance_message, "Back soon");
+        assert_eq!(config.server.maintenance_retry_after_seconds, 60);
+        assert_eq!(config.server.payload_warn_request_bytes, 10);
+        assert_eq!(config.server.payload_warn_response_bytes, 20);
+        assert_eq!(config.server.max_request_body_bytes, 30);
+        assert_eq!(config.server.json_max_depth, 31);
+        assert_eq!(config.server.json_max_keys, 32);
+        assert_eq!(config.server.json_max_string_bytes, 33);
+        assert_eq!(config.server.mirror_url.as_deref(), Some("http://staging:8080"));
+        assert_eq!(config.server.mirror_auth_token.as_deref(), Some("staging-token"));
+        assert_eq!(config.server.mirror_sample_rate, 0.5);
+        assert_eq!(config.server.mirror_max_concurrency, 3);
+        assert_eq!(config.server.log_level_revert_seconds, 34);
+        assert_eq!(config.server.replica_id.as_deref(), Some("replica-b"));
+        assert_eq!(
+            config.server.metrics_labels,
+            vec![
+                ("env".to_string(), "prod".to_string()),
+                ("region".to_string(), "eu".to_string())
+            ]
+        );
+        assert_eq!(config.server.metrics_token.as_deref(), Some("scrape-token"));
+        assert_eq!(config.server.quiet_log_paths, vec!["/health/live", "/ping"]);
+        assert!(config.server.json_response_charset);
+        assert_eq!(config.server.compat_mode, CompatMode::Strict);
+        assert!(config.server.chaos_enabled);
+        assert_eq!(config.redis.url, "redis://cache:6379");
+        assert_eq!(config.zion.api_url, "http://zion:3000");
+        assert_eq!(config.zion.api_key, "zion-key");
+        assert_eq!(config.zion.cache_ttl_seconds, 11);
+        assert_eq!(config.zion.jwt_cache_ttl_seconds, 12);
+        assert_eq!(config.zion.tier_config_ttl_seconds, 13);
+        assert_eq!(
+            config.zion.tier_config_standby_path.as_deref(),
+            Some("/var/lib/sentinel/tiers.json")
+        );
+        assert_eq!(config.zion.tier_config_max_staleness_hours, 12);
+        assert_eq!(config.zion.meta_ttl_seconds, 25);
+        assert_eq!(config.zion.missing_limit_policy, MissingLimitPolicy::Zero);
+        assert_eq!(config.zion.payload_case, PayloadCase::Snake);
+        assert_eq!(config.zion.cache_warm_concurrency, 4);
+        assert_eq!(config.zion.cache_warm_rate_per_second, 50);
+        assert_eq!(config.zion.auth_path_budget_ms, 250);
+        assert_eq!(
+            config.zion.jwks_url.as_deref(),
+            Some("https://zion.example/.well-known/jwks.json")
+        );
+        assert_eq!(config.zion.jwt_issuer.as_deref(), Some("https://zion.example"));
+        assert_eq!(config.provider.ai_provider, ProviderKind::Anthropic);
+        assert_eq!(config.provider.openai_api_url, "http://gateway/v1");
+        assert_eq!(config.provider.openai_api_key.as_deref(), Some("sk-test"));
+        assert_eq!(config.provider.openai_auth_mode, AuthMode::Sigv4);
+        assert_eq!(config.provider.openai_sigv4_region.as_deref(), Some("eu-west-1"));
+        assert_eq!(config.provider.openai_sigv4_service, "inference");
+        assert_eq!(config.provider.openai_sigv4_access_key_id.as_deref(), Some("AKID"));
+        assert_eq!(config.provider.openai_sigv4_secret_access_key.as_deref(), Some("secret"));
+        assert_eq!(config.provider.openai_sigv4_session_token.as_deref(), Some("session"));
+        assert_eq!(config.provider.anthropic_api_url, "http://anthropic/v1");
+        assert_eq!(config.provider.anthropic_api_key.as_deref(), Some("sk-ant-test"));
+        assert_eq!(config.provider.failover_providers, vec![ProviderKind::OpenAI]);
+        assert_eq!(config.provider.failover_attempt_timeout_ms, Some(8000));
+        assert_eq!(
+            config.provider.failover_model_map,
+            HashMap::from([("gpt-4o".to_string(), "claude-sonnet-4-5".to_string())])
+        );
+        assert_eq!(config.provider.session_ttl_seconds, 14);
+        assert_eq!(config.provider.session_history_max_messages, 200);
+        assert_eq!(config.provider.affinity_secret.as_deref(), Some("affinity-key"));
+        assert_eq!(config.provider.affinity_local_ttl_seconds, 26);
+        assert_eq!(config.provider.stream_lock_ttl_seconds, 28);
+        assert_eq!(config.provider.stream_lock_wait_ms, 29);
+        assert_eq!(config.provider.native_batch_max_items, 40);
+        assert_eq!(config.provider.native_batch_concurrency, 6);
+        assert_eq!(config.provider.native_embeddings_max_input_tokens, 1000);
+        assert_eq!(config.provider.system_prompt_injection.as_deref(), Some("Be brief."));
+        assert_eq!(config.provider.system_prompt_injection_mode, InjectionMode::ReplaceEmpty);
+        assert_eq!(config.provider.param_out_of_range, ParamOutOfRange::Clamp);
+        assert_eq!(config.provider.deprecated_params, DeprecatedParams::Translate);
+        assert_eq!(config.provider.stream_accept_mismatch, StreamAcceptMismatch::Warn);
+        assert_eq!(config.provider.upstream_user_field, UpstreamUserField::ExternalIdHash);
+        assert_eq!(config.provider.upstream_user_hash_key.as_deref(), Some("user-hash-key"));
+        assert_eq!(config.provider.upstream_timeout_min_ms, 15);
+        assert_eq!(config.provider.upstream_timeout_max_ms, 16);
+        assert_eq!(config.provider.progress_interval_ms, 27);
+        assert_eq!(config.provider.sse_max_line_bytes, 17);
+        assert!(config.provider.content_normalize_nfc);
+        assert!(!config.provider.stream_usage_injection);
+        assert_eq!(config.provider.upstream_capture_headers, vec!["x-request-id", "cf-ray"]);
+        assert!(config.provider.context_fallback);
+        assert_eq!(config.provider.tier_latency_weight, 0.25);
+        assert_eq!(config.provider.tier_health_retention_hours, 6);
+        assert_eq!(config.provider.tier_health_prune_interval_seconds, 60);
+        assert_eq!(config.provider.startup_provider_check, ProviderCheckMode::Fail);
+        assert_eq!(config.provider.models_source, ModelsSource::Merged);
+        assert!(config.provider.static_models.unwrap().get("local-model").is_some());
+        assert_eq!(config.provider.canary_external_ids, vec!["canary-1"]);
+        assert_eq!(config.provider.response_strip_tags, vec!["think", "analysis"]);
+        assert_eq!(config.provider.response_drop_fields, vec!["reasoning"]);
+        assert!(config.provider.response_blocklist.unwrap().is_blocked("a banned word"));
+        assert_eq!(config.provider.finish_reason_alert_percent, 12.5);
+        assert_eq!(config.provider.finish_reason_window_seconds, 120);
+        assert_eq!(config.provider.finish_reason_min_samples, 10);
+        assert!(!config.provider.validate_upstream_responses);
+        assert_eq!(config.provider.circuit_breaker_threshold, 35);
+        assert_eq!(config.provider.circuit_breaker_reset_seconds, 36);
+        assert_eq!(config.provider.circuit_breaker_half_open_probes, 37);
+        assert_eq!(config.provider.upstream_response_max_headers, 38);
+        assert_eq!(config.provider.upstream_response_max_header_bytes, 39);
+        assert!(config.provider.upstream_allow_set_cookie);
+        assert_eq!(config.provider.upstream_stream_pool_max_idle, 40);
+        assert_eq!(config.provider.upstream_short_pool_max_idle, 41);
+        assert_eq!(config.provider.upstream_short_connect_timeout_ms, 42);
+        assert_eq!(config.rate_limit.exempt_ids, vec!["eval-runner", "batch-worker"]);
+        assert_eq!(config.rate_limit.org_max_requests, 18);
+        assert_eq!(config.rate_limit.org_overrides["org_a"], 19);
+        assert_eq!(config.rate_limit.max_tokens_per_window, 19000);
+        assert_eq!(config.rate_limit.quarantine_malformed_threshold, 20);
+        assert_eq!(config.rate_limit.quarantine_window_seconds, 21);
+        assert_eq!(config.rate_limit.quarantine_duration_seconds, 22);
+        assert_eq!(config.usage.aggregate_days, 23);
+        assert_eq!(config.usage.report_days, 90);
+        assert_eq!(config.usage.report_max_groups, 12);
+        assert_eq!(config.usage.ledger_database_url.as_deref(), Some("sqlite::memory:"));
+        assert_eq!(config.usage.image_default_tokens, 24);
+        assert_eq!(config.usage.token_fallback_encoding, Encoding::Cl100kBase);
+        assert_eq!(
+            config.usage.model_encoding_overrides,
+            HashMap::from([("gpt-7*".to_string(), Encoding::O200kBase)])
+        );
+        assert_eq!(config.usage.request_weights.weight_for("/audio/speech"), 2);
+        assert_eq!(config.usage.synthetic_external_ids, vec!["probe-1", "monitor-2"]);
+        assert_eq!(config.usage.require_exact_usage, vec!["org_exact"]);
+        assert_eq!(config.usage.exact_usage_mode, ExactUsageMode::Strict);
+        assert!(config.usage.report_estimated_usage);
+
+        // Every legacy name is covered above
+        assert_eq!(LEGACY_NAMES.len(), 123);
+    }
+
+    #[test]
+    fn test_sectioned_names_take_precedence() {
+        let mut env = required();
+        env.extend(vars(&[
+            ("SENTINEL_PORT", "9090"),
+            ("SENTINEL_SERVER__PORT", "9191"),
+            ("SENTINEL_RATE_LIMIT__ORG_MAX_REQUESTS", "50"),
+            ("SENTINEL_ZION__API_KEY", "sectioned-key"),
+        ]));
+        let config = Config::from_vars(env).unwrap();
+
+        assert_eq!(config.server.port, 9191);
+        assert_eq!(config.rate_limit.org_max_requests, 50);
+        assert_eq!(config.zion.api_key, "sectioned-key");
+    }
 
-        // Clean up
-        env::remove_var("ZION_API_URL");
-        env::remove_var("ZION_API_KEY");
+    #[test]
+    fn test_invalid_values_are_rejected() {
+        let mut with_bad_port = required();
+        with_bad_port.push(("SENTINEL_PORT".to_string(), "eighty".to_string()));
+        assert!(Config::from_vars(with_bad_port).is_err());
+
+        let mut with_bad_mode = required();
+        with_bad_mode.push(("STARTUP_PROVIDER_CHECK".to_string(), "strict".to_string()));
+        assert!(Config::from_vars(with_bad_mode).is_err());
+
+        let mut with_bad_models = required();
+        with_bad_models.push(("STATIC_MODELS_JSON".to_string(), r#"[{"object": "model"}]"#.to_string()));
+        assert!(Config::from_vars(with_bad_models).is_err());
+
+        let mut with_bad_weights = required();
+        with_bad_weights.push(("USAGE_REQUEST_WEIGHTS_JSON".to_string(), r#"{"/images": -1}"#.to_string()));
+        assert!(Config::from_vars(with_bad_weights).is_err());
+
+        let mut with_bad_failover = required();
+        with_bad_failover.push(("FAILOVER_PROVIDERS".to_string(), "openai, vercel".to_string()));
+        assert!(Config::from_vars(with_bad_failover).is_err());
+    }
+
+    #[test]
+    fn test_blank_values_count_as_unset() {
+        let mut env = required();
+        env.extend(vars(&[("ADMIN_API_KEY", " "), ("MAINTENANCE_MESSAGE", "")]));
+        let config = Config::from_vars(env).unwrap();
+
+        assert!(config.server.admin_api_key.is_none());
+        assert_eq!(config.server.maintenance_message, DEFAULT_MAINTENANCE_MESSAGE);
+    }
+
+    #[test]
+    fn test_parse_id_list() {
+        assert_eq!(
+            parse_id_list(" eval-runner, ,batch-worker,"),
+            vec!["eval-runner".to_string(), "batch-worker".to_string()]
+        );
+        assert!(parse_id_list("").is_empty());
+    }
+
+    #[test]
+    fn test_parse_limit_overrides() {
+        let overrides = parse_limit_overrides(" org_a=5000, ,org_b = 200,").unwrap();
+        assert_eq!(overrides.len(), 2);
+        assert_eq!(overrides["org_a"], 5000);
+        assert_eq!(overrides["org_b"], 200);
+
+        assert!(parse_limit_overrides("").unwrap().is_empty());
+        assert!(parse_limit_overrides("org_a").is_err());
+        assert!(parse_limit_overrides("org_a=lots").is_err());
+    }
+
+    #[test]
+    fn test_parse_encoding_overrides() {
+        let overrides = parse_encoding_overrides(" gpt-7 = o200k_base, ,ft:*=cl100k_base,").unwrap();
+        assert_eq!(overrides["gpt-7"], Encoding::O200kBase);
+        assert_eq!(overrides["ft:*"], Encoding::Cl100kBase);
+        assert!(parse_encoding_overrides("").unwrap().is_empty());
+        assert!(parse_encoding_overrides("gpt-7").is_err());
+        assert!(parse_encoding_overrides("gpt-7=o300k_base").is_err());
+    }
+
+    #[test]
+    fn test_parse_model_map() {
+        let map = parse_model_map(" gpt-4o = claude-sonnet-4-5, ,gpt-4o-mini=claude-haiku-4-5").unwrap();
+        assert_eq!(map["gpt-4o"], "claude-sonnet-4-5");
+        assert_eq!(map["gpt-4o-mini"], "claude-haiku-4-5");
+        assert!(parse_model_map("").unwrap().is_empty());
+        assert!(parse_model_map("gpt-4o").is_err());
+        assert!(parse_model_map("gpt-4o=").is_err());
+    }
+
+    #[test]
+    fn test_parse_metric_labels() {
+        assert_eq!(
+            parse_metric_labels(" env = prod, ,region=eu,").unwrap(),
+            vec![
+                ("env".to_string(), "prod".to_string()),
+                ("region".to_string(), "eu".to_string())
+            ]
+        );
+        assert!(parse_metric_labels("").unwrap().is_empty());
+        assert!(parse_metric_labels("env").is_err());
+        assert!(parse_metric_labels("1env=prod").is_err());
+        assert!(parse_metric_labels("deploy-env=prod").is_err());
     }
 }
@@ -9,14 +9,20 @@ use utoipa::{
 
 use crate::native::{
     error::{NativeError, NativeErrorResponse},
-    request::{ChatCompletionRequest, StopSequence},
+    request::{
+        ChatCompletionBatchRequest, ChatCompletionRequest, EmbeddingsInput, EmbeddingsRequest,
+        StopSequence, TokenizeRequest,
+    },
     response::{
-        ChatCompletionResponse, Choice, ChoiceMessage, Delta, StreamChoice, StreamChunk,
-        ToolCallDelta, ToolCallFunctionDelta, Usage,
+        BatchItemResult, ChatCompletionBatchResponse, ChatCompletionResponse, Choice,
+        ChoiceMessage, Delta, Embedding, EmbeddingsResponse, EmbeddingsUsage, SessionExport,
+        SessionHistoryPage, StreamChoice, StreamChunk, TokenizeResponse, ToolCallDelta,
+        ToolCallFunctionDelta, TruncationSuggestion, Usage,
     },
+    session::SessionUsage,
     types::{
-        Content, ContentPart, FunctionDefinition, ImageUrl, Message, Role, Tier, ToolCall,
-        ToolCallFunction, ToolChoice, ToolDefinition, ToolResult, ToolResultContent,
+        Content, ContentPart, FunctionDefinition, ImageDetail, ImageUrl, Message, Role, Tier,
+        ToolCall, ToolCallFunction, ToolChoice, ToolDefinition, ToolResult, ToolResultContent,
     },
 };
 
@@ -29,12 +35,17 @@ use crate::native::{
         description = "Native API for Sentinel AI Proxy - unified format with tier routing and session management"
     ),
     paths(
-        crate::native_routes::chat::native_chat_completions
+        crate::native_routes::chat::native_chat_completions,
+        crate::native_routes::batch::native_chat_completions_batch,
+        crate::native_routes::embeddings::native_embeddings,
+        crate::native_routes::sessions::native_get_session,
+        crate::native_routes::tokenize::native_tokenize
     ),
     components(
         schemas(
             // Types
             Role,
+            ImageDetail,
             ImageUrl,
             ContentPart,
             Content,
@@ -50,6 +61,10 @@ use crate::native::{
             // Request
             StopSequence,
             ChatCompletionRequest,
+            ChatCompletionBatchRequest,
+            EmbeddingsInput,
+            EmbeddingsRequest,
+            TokenizeRequest,
             // Response
             Usage,
             ChoiceMessage,
@@ -60,6 +75,16 @@ use crate::native::{
             Delta,
             StreamChoice,
             StreamChunk,
+            BatchItemResult,
+            ChatCompletionBatchResponse,
+            Embedding,
+            EmbeddingsUsage,
+            EmbeddingsResponse,
+            SessionUsage,
+            SessionHistoryPage,
+            SessionExport,
+            TokenizeResponse,
+            TruncationSuggestion,
             // Error
             NativeError,
             NativeErrorResponse,
@@ -67,7 +92,10 @@ use crate::native::{
     ),
     modifiers(&SecurityAddon),
     tags(
-        (name = "Chat", description = "Chat completion endpoints")
+        (name = "Chat", description = "Chat completion endpoints"),
+        (name = "Embeddings", description = "Embedding endpoints"),
+        (name = "Sessions", description = "Conversation session endpoints"),
+        (name = "Tokens", description = "Token counting endpoints")
     )
 )]
 pub struct NativeApiDoc;
@@ -5,7 +5,7 @@
 use std::time::Duration;
 
 use axum::{
-    http::{HeaderValue, StatusCode},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
@@ -21,9 +21,17 @@ pub enum AppError {
     #[error("Invalid authentication token")]
     InvalidToken,
 
+    /// Credentials header present but unusable; rejected before Zion is asked
+    #[error("{0}")]
+    AuthHeader(AuthHeaderError),
+
     #[error("Access forbidden")]
     Forbidden,
 
+    /// The requested model is outside the user's `allowedModels`
+    #[error("Model '{0}' is not available on this subscription")]
+    ModelNotAllowed(String),
+
     #[error("Resource not found: {0}")]
     NotFound(String),
 
@@ -41,11 +49,17 @@ pub enum AppError {
         message: String,
         limit: i64,
         used: i64,
+        /// Wait until the limit's period ends, when known
+        retry_after: Option<RetryAfter>,
     },
 
     #[error("Bad request: {0}")]
     BadRequest(String),
 
+    /// The response the request asks for is excluded by its `Accept` header
+    #[error("Not acceptable: {0}")]
+    NotAcceptable(String),
+
     /// Service temporarily unavailable (e.g., all providers in backoff)
     #[error("Service unavailable: {message}")]
     ServiceUnavailable {
@@ -53,9 +67,39 @@ pub enum AppError {
         retry_after: Option<Duration>,
     },
 
+    /// Zion didn't validate the token and load its limits within `AUTH_PATH_BUDGET_MS`
+    #[error("Authentication backend did not respond within {budget_ms}ms")]
+    AuthBackendSlow { budget_ms: u64 },
+
     #[error("Upstream error: {0}")]
     UpstreamError(String),
 
+    /// Upstream did not answer within the request's timeout
+    #[error("Upstream did not respond within {timeout_ms}ms")]
+    UpstreamTimeout { timeout_ms: u64 },
+
+    /// Provider endpoint's circuit breaker is open; the request was not sent
+    #[error(
+        "{provider} {endpoint} is failing; requests are paused for {}s",
+        RetryAfter::from_duration(*.retry_after)
+    )]
+    UpstreamUnavailable {
+        provider: String,
+        endpoint: String,
+        retry_after: Duration,
+    },
+
+    /// Response matched the configured blocklist
+    #[error("{}", crate::proxy::content_filter::CONTENT_BLOCKED_MESSAGE)]
+    ContentBlocked,
+
+    /// Upstream answered 200 with a body that isn't a usable response
+    #[error("Upstream returned an invalid response: {reason}{}", request_id_suffix(.request_id))]
+    UpstreamInvalidResponse {
+        reason: String,
+        request_id: Option<String>,
+    },
+
     #[error("Redis error: {0}")]
     RedisError(#[from] redis::RedisError),
 
@@ -65,10 +109,168 @@ pub enum AppError {
     #[error("JSON error: {0}")]
     JsonError(#[from] serde_json::Error),
 
+    #[cfg(feature = "ledger")]
+    #[error("Ledger error: {0}")]
+    LedgerError(#[from] sqlx::Error),
+
     #[error("Internal error: {0}")]
     Internal(#[from] anyhow::Error),
 }
 
+/// How long a client should wait before retrying a 429 or 503
+///
+/// Every `Retry-After` Sentinel sends is built here. The value is in whole
+/// seconds, rounded up and never below one: many clients read `0` as "retry
+/// now", which turns a rejection into a retry storm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RetryAfter(u64);
+
+impl RetryAfter {
+    /// Shortest wait ever sent
+    pub const MIN: RetryAfter = RetryAfter(1);
+
+    /// Wait `seconds` (at least one)
+    pub fn seconds(seconds: u64) -> Self {
+        Self(seconds.max(Self::MIN.0))
+    }
+
+    /// Wait `duration`, rounded up to whole seconds
+    pub fn from_duration(duration: Duration) -> Self {
+        Self::seconds(
+            duration
+                .as_secs()
+                .saturating_add(u64::from(duration.subsec_nanos() > 0)),
+        )
+    }
+
+    /// Wait until `reset_at` (Unix seconds), at most `window_seconds`
+    ///
+    /// For limits that reset at a known time: a rate limit window, a quota
+    /// period, a quarantine. A reset already past (the window turned over
+    /// while the request was checked) still waits one second, and a reset
+    /// further out than the window (clock skew) is capped at the window.
+    pub fn until(reset_at: i64, now: i64, window_seconds: u64) -> Self {
+        let remaining = u64::try_from(reset_at.saturating_sub(now)).unwrap_or(0);
+        Self::seconds(remaining.min(window_seconds))
+    }
+
+    /// Seconds to wait
+    pub fn as_secs(self) -> u64 {
+        self.0
+    }
+
+    /// Milliseconds to wait, for `retry_after_ms` in error bodies
+    pub fn as_millis(self) -> u64 {
+        self.0.saturating_mul(1000)
+    }
+
+    /// Unix time the client may retry, seen from `now`
+    pub fn retry_at(self, now: i64) -> i64 {
+        now.saturating_add(i64::try_from(self.0).unwrap_or(i64::MAX))
+    }
+
+    /// RFC 3339 retry time, for `details.reset_at` in error bodies
+    pub fn reset_at(self, now: i64) -> Option<String> {
+        chrono::DateTime::from_timestamp(self.retry_at(now), 0).map(|dt| dt.to_rfc3339())
+    }
+
+    /// `Retry-After` in the delta-seconds form
+    pub fn header_value(self) -> HeaderValue {
+        HeaderValue::from(self.0)
+    }
+
+    /// `Retry-After` in the HTTP-date form (RFC 9110 IMF-fixdate)
+    ///
+    /// Used where the wait ends at an absolute time a client may act on
+    /// long after receiving the response, such as the end of a quota period.
+    /// Falls back to delta-seconds for times chrono can't represent.
+    pub fn http_date_value(self, now: i64) -> HeaderValue {
+        chrono::DateTime::from_timestamp(self.retry_at(now), 0)
+            .and_then(|dt| {
+                HeaderValue::from_str(&dt.format("%a, %d %b %Y %H:%M:%S GMT").to_string()).ok()
+            })
+            .unwrap_or_else(|| self.header_value())
+    }
+
+    /// Set `Retry-After` (delta-seconds) on a response
+    pub fn apply(self, headers: &mut HeaderMap) {
+        headers.insert(header::RETRY_AFTER, self.header_value());
+    }
+}
+
+impl std::fmt::Display for RetryAfter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// ` (upstream request id ...)` when the provider's request id is known
+fn request_id_suffix(request_id: &Option<String>) -> String {
+    request_id
+        .as_ref()
+        .map(|id| format!(" (upstream request id {})", id))
+        .unwrap_or_default()
+}
+
+/// Ways the credentials header can be malformed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum AuthHeaderError {
+    #[error("Multiple credentials headers sent; send exactly one")]
+    MultipleHeaders,
+    #[error("Credentials header is not valid header text")]
+    Malformed,
+    #[error("Unsupported authorization scheme; use Bearer")]
+    UnsupportedScheme,
+    #[error("Bearer token is empty")]
+    EmptyToken,
+}
+
+impl AuthHeaderError {
+    fn status_and_code(self) -> (StatusCode, &'static str) {
+        match self {
+            AuthHeaderError::MultipleHeaders => {
+                (StatusCode::BAD_REQUEST, "MULTIPLE_AUTHORIZATION_HEADERS")
+            }
+            AuthHeaderError::Malformed => (StatusCode::BAD_REQUEST, "MALFORMED_AUTHORIZATION"),
+            AuthHeaderError::UnsupportedScheme => {
+                (StatusCode::UNAUTHORIZED, "UNSUPPORTED_AUTH_SCHEME")
+            }
+            AuthHeaderError::EmptyToken => (StatusCode::UNAUTHORIZED, "EMPTY_TOKEN"),
+        }
+    }
+}
+
+/// Whether retrying a request that failed with `code` can succeed
+///
+/// Sent as `retryable` in every error body and SSE error event, so clients
+/// can tell transient failures (rate limits, unavailable or slow upstreams,
+/// maintenance) from permanent ones (validation, auth, exhausted quotas,
+/// translation failures). `UPSTREAM_ERROR` and `provider_error` depend on
+/// the upstream status instead (see [`AppError::retryable`]); unknown codes
+/// are not retryable.
+pub fn is_retryable_code(code: &str) -> bool {
+    matches!(
+        code,
+        "RATE_LIMIT_EXCEEDED"
+            | "USER_RATE_LIMIT_EXCEEDED"
+            | "ORG_RATE_LIMIT_EXCEEDED"
+            | "TOKEN_RATE_LIMIT_EXCEEDED"
+            | "rate_limit_exceeded"
+            | "too_many_malformed_requests"
+            | "SERVICE_UNAVAILABLE"
+            | "service_unavailable"
+            | "auth_backend_slow"
+            | "maintenance"
+            | "upstream_timeout"
+            | "upstream_unavailable"
+            | "upstream_invalid_response"
+            | "connection_closed"
+            | "conversation_busy"
+            | "CACHE_ERROR"
+            | "LEDGER_ERROR"
+    )
+}
+
 /// Error response body
 #[derive(Debug, Serialize)]
 pub struct ErrorResponse {
@@ -80,6 +282,11 @@ pub struct ErrorResponse {
 pub struct ErrorBody {
     pub code: String,
     pub message: String,
+    /// Whether the same request may succeed later
+    pub retryable: bool,
+    /// How long to wait first, when known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after_ms: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub details: Option<ErrorDetails>,
 }
@@ -97,8 +304,57 @@ pub struct ErrorDetails {
     pub reset_at: Option<String>,
 }
 
+impl AppError {
+    /// Whether the same request may succeed later
+    ///
+    /// Upstream errors are retryable when the provider answered 429 or 5xx;
+    /// other upstream statuses and unparseable responses are not.
+    pub fn retryable(&self) -> bool {
+        match self {
+            AppError::RateLimitExceeded { .. }
+            | AppError::ServiceUnavailable { .. }
+            | AppError::AuthBackendSlow { .. }
+            | AppError::UpstreamTimeout { .. }
+            | AppError::UpstreamUnavailable { .. }
+            | AppError::UpstreamInvalidResponse { .. }
+            | AppError::RedisError(_)
+            | AppError::HttpError(_) => true,
+            #[cfg(feature = "ledger")]
+            AppError::LedgerError(_) => true,
+            AppError::UpstreamError(message) => crate::proxy::breaker::upstream_status(message)
+                .is_some_and(|status| status == 429 || (500..600).contains(&status)),
+            _ => false,
+        }
+    }
+
+    /// How long to wait before the request may succeed, seen from `now`
+    ///
+    /// Also set for an exhausted quota, which isn't retryable until its
+    /// period ends.
+    pub fn retry_after(&self, now: i64) -> Option<RetryAfter> {
+        match self {
+            AppError::RateLimitExceeded {
+                reset_at: Some(reset_at),
+                ..
+            } => chrono::DateTime::parse_from_rfc3339(reset_at)
+                .ok()
+                .map(|reset_at| RetryAfter::until(reset_at.timestamp(), now, u64::MAX)),
+            AppError::QuotaExceeded { retry_after, .. } => *retry_after,
+            AppError::ServiceUnavailable { retry_after, .. } => {
+                retry_after.map(RetryAfter::from_duration)
+            }
+            AppError::UpstreamUnavailable { retry_after, .. } => {
+                Some(RetryAfter::from_duration(*retry_after))
+            }
+            AppError::AuthBackendSlow { .. } => Some(RetryAfter::MIN),
+            _ => None,
+        }
+    }
+}
+
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
+        let now = chrono::Utc::now().timestamp();
         let (status, code, message, details) = match &self {
             AppError::Unauthorized => (
                 StatusCode::UNAUTHORIZED,
@@ -112,12 +368,22 @@ impl IntoResponse for AppError {
                 self.to_string(),
                 None,
             ),
+            AppError::AuthHeader(reason) => {
+                let (status, code) = reason.status_and_code();
+                (status, code, self.to_string(), None)
+            }
             AppError::Forbidden => (
                 StatusCode::FORBIDDEN,
                 "FORBIDDEN",
                 self.to_string(),
                 None,
             ),
+            AppError::ModelNotAllowed(_) => (
+                StatusCode::FORBIDDEN,
+                crate::middleware::model_access::MODEL_NOT_ALLOWED_CODE,
+                self.to_string(),
+                None,
+            ),
             AppError::NotFound(msg) => (
                 StatusCode::NOT_FOUND,
                 "NOT_FOUND",
@@ -141,7 +407,12 @@ impl IntoResponse for AppError {
                     reset_at: reset_at.clone(),
                 }),
             ),
-            AppError::QuotaExceeded { message, limit, used } => (
+            AppError::QuotaExceeded {
+                message,
+                limit,
+                used,
+                retry_after,
+            } => (
                 StatusCode::TOO_MANY_REQUESTS,
                 "QUOTA_EXCEEDED",
                 message.clone(),
@@ -149,7 +420,7 @@ impl IntoResponse for AppError {
                     limit: Some(*limit),
                     used: Some(*used),
                     remaining: None,
-                    reset_at: None,
+                    reset_at: retry_after.and_then(|wait| wait.reset_at(now)),
                 }),
             ),
             AppError::BadRequest(msg) => (
@@ -158,18 +429,59 @@ impl IntoResponse for AppError {
                 msg.clone(),
                 None,
             ),
+            AppError::NotAcceptable(msg) => (
+                StatusCode::NOT_ACCEPTABLE,
+                "not_acceptable",
+                msg.clone(),
+                None,
+            ),
             AppError::ServiceUnavailable { message, .. } => (
                 StatusCode::SERVICE_UNAVAILABLE,
                 "SERVICE_UNAVAILABLE",
                 message.clone(),
                 None,
             ),
+            AppError::AuthBackendSlow { .. } => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "auth_backend_slow",
+                self.to_string(),
+                None,
+            ),
             AppError::UpstreamError(msg) => (
                 StatusCode::BAD_GATEWAY,
                 "UPSTREAM_ERROR",
                 msg.clone(),
                 None,
             ),
+            AppError::UpstreamTimeout { .. } => (
+                StatusCode::GATEWAY_TIMEOUT,
+                "upstream_timeout",
+                self.to_string(),
+                None,
+            ),
+            AppError::UpstreamUnavailable { retry_after, .. } => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "upstream_unavailable",
+                self.to_string(),
+                Some(ErrorDetails {
+                    limit: None,
+                    used: None,
+                    remaining: None,
+                    reset_at: RetryAfter::from_duration(*retry_after).reset_at(now),
+                }),
+            ),
+            AppError::ContentBlocked => (
+                StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS,
+                crate::proxy::content_filter::CONTENT_BLOCKED_CODE,
+                self.to_string(),
+                None,
+            ),
+            AppError::UpstreamInvalidResponse { .. } => (
+                StatusCode::BAD_GATEWAY,
+                "upstream_invalid_response",
+                self.to_string(),
+                None,
+            ),
             AppError::RedisError(_) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "CACHE_ERROR",
@@ -188,6 +500,13 @@ impl IntoResponse for AppError {
                 "Invalid JSON in request".to_string(),
                 None,
             ),
+            #[cfg(feature = "ledger")]
+            AppError::LedgerError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "LEDGER_ERROR",
+                "Usage ledger error".to_string(),
+                None,
+            ),
             AppError::Internal(_) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "INTERNAL_ERROR",
@@ -199,18 +518,33 @@ impl IntoResponse for AppError {
         let body = ErrorResponse {
             error: ErrorBody {
                 code: code.to_string(),
-                message,
+                message: crate::proxy::redact::redact(&message).into_owned(),
+                retryable: self.retryable(),
+                retry_after_ms: self.retry_after(now).map(RetryAfter::as_millis),
                 details,
             },
         };
 
         let mut response = (status, Json(body)).into_response();
 
-        // Add Retry-After header for ServiceUnavailable errors
-        if let AppError::ServiceUnavailable { retry_after: Some(duration), .. } = &self {
-            if let Ok(value) = HeaderValue::from_str(&duration.as_secs().to_string()) {
-                response.headers_mut().insert("Retry-After", value);
+        let headers = response.headers_mut();
+        match &self {
+            AppError::ServiceUnavailable {
+                retry_after: Some(duration),
+                ..
+            }
+            | AppError::UpstreamUnavailable {
+                retry_after: duration,
+                ..
+            } => RetryAfter::from_duration(*duration).apply(headers),
+            AppError::AuthBackendSlow { .. } => RetryAfter::MIN.apply(headers),
+            AppError::QuotaExceeded {
+                retry_after: Some(wait),
+                ..
+            } => {
+                headers.insert(header::RETRY_AFTER, wait.http_date_value(now));
             }
+            _ => {}
         }
 
         response
@@ -219,3 +553,298 @@ impl IntoResponse for AppError {
 
 /// Result type alias for convenience
 pub type AppResult<T> = Result<T, AppError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic spread of values, extremes included
+    fn samples(seed: u64, count: usize) -> Vec<i64> {
+        let mut state = seed;
+        let mut values = vec![i64::MIN, -1, 0, 1, i64::MAX];
+        values.extend((0..count).map(|_| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 1) as i64 >> (state % 60)
+        }));
+        values
+    }
+
+    #[test]
+    fn test_retry_after_until_stays_within_window() {
+        let windows = [0, 1, 2, 59, 60, 61, 3600, 86_400, u64::MAX];
+        for now in samples(7, 200) {
+            for offset in samples(now as u64, 50) {
+                let reset_at = now.saturating_add(offset);
+                for window in windows {
+                    let wait = RetryAfter::until(reset_at, now, window).as_secs();
+                    assert!(wait >= 1, "reset_at={reset_at} now={now}");
+                    assert!(wait <= window.max(1), "window={window} wait={wait}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_retry_after_until_window_boundaries() {
+        // The window turned over between the check and the response
+        assert_eq!(RetryAfter::until(100, 100, 60), RetryAfter::MIN);
+        assert_eq!(RetryAfter::until(99, 100, 60), RetryAfter::MIN);
+        assert_eq!(RetryAfter::until(101, 100, 60).as_secs(), 1);
+        assert_eq!(RetryAfter::until(160, 100, 60).as_secs(), 60);
+        assert_eq!(RetryAfter::until(400, 100, 60).as_secs(), 60);
+    }
+
+    #[test]
+    fn test_retry_after_from_duration_rounds_up() {
+        for millis in samples(11, 500) {
+            let duration = Duration::from_millis(millis.unsigned_abs() >> 4);
+            let wait = RetryAfter::from_duration(duration).as_secs();
+            assert!(wait >= 1);
+            assert!(Duration::from_secs(wait) >= duration);
+            assert!(Duration::from_secs(wait) < duration + Duration::from_secs(1) || wait == 1);
+        }
+        assert_eq!(RetryAfter::from_duration(Duration::ZERO).as_secs(), 1);
+        assert_eq!(RetryAfter::from_duration(Duration::from_millis(1001)).as_secs(), 2);
+    }
+
+    #[test]
+    fn test_retry_after_http_date() {
+        let wait = RetryAfter::seconds(90);
+        assert_eq!(wait.http_date_value(0), "Thu, 01 Jan 1970 00:01:30 GMT");
+
+        let now = 1_767_225_600;
+        let date = wait.http_date_value(now);
+        let parsed = chrono::DateTime::parse_from_rfc2822(date.to_str().unwrap()).unwrap();
+        assert_eq!(parsed.timestamp(), now + 90);
+        // Unrepresentable times fall back to delta-seconds
+        assert_eq!(wait.http_date_value(i64::MAX), "90");
+    }
+
+    #[test]
+    fn test_error_responses_never_send_zero() {
+        let service = AppError::ServiceUnavailable {
+            message: "All models for tier simple are currently unavailable".to_string(),
+            retry_after: Some(Duration::from_millis(300)),
+        }
+        .into_response();
+        assert_eq!(service.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(service.headers()[header::RETRY_AFTER], "1");
+
+        let upstream = AppError::UpstreamUnavailable {
+            provider: "openai".to_string(),
+            endpoint: "chat".to_string(),
+            retry_after: Duration::ZERO,
+        }
+        .into_response();
+        assert_eq!(upstream.headers()[header::RETRY_AFTER], "1");
+    }
+
+    /// Every fixed error code Sentinel sends, with whether it is retryable
+    ///
+    /// Pinned so a change to a code's `retryable` is deliberate: SDKs decide
+    /// whether to retry from it.
+    const PINNED_CODES: &[(&str, bool)] = &[
+        ("UNAUTHORIZED", false),
+        ("INVALID_TOKEN", false),
+        ("MULTIPLE_AUTHORIZATION_HEADERS", false),
+        ("MALFORMED_AUTHORIZATION", false),
+        ("UNSUPPORTED_AUTH_SCHEME", false),
+        ("EMPTY_TOKEN", false),
+        ("FORBIDDEN", false),
+        ("insufficient_scope", false),
+        ("model_not_allowed", false),
+        ("provider_override_forbidden", false),
+        ("unknown_provider", false),
+        ("NOT_FOUND", false),
+        ("endpoint_not_found", false),
+        ("debug_disabled", false),
+        ("method_not_allowed", false),
+        ("BAD_REQUEST", false),
+        ("invalid_request", false),
+        ("INVALID_JSON", false),
+        ("invalid_json", false),
+        ("invalid_type", false),
+        ("duplicate_field", false),
+        ("unsupported_media_type", false),
+        ("not_acceptable", false),
+        ("json_too_deep", false),
+        ("json_too_many_keys", false),
+        ("json_string_too_long", false),
+        ("request_too_large", false),
+        ("unsupported_encoding", false),
+        ("invalid_encoding", false),
+        ("QUOTA_EXCEEDED", false),
+        ("quota_exceeded", false),
+        ("content_blocked", false),
+        ("sse_line_too_long", false),
+        ("parse_error", false),
+        ("INTERNAL_ERROR", false),
+        ("internal_error", false),
+        ("RATE_LIMIT_EXCEEDED", true),
+        ("USER_RATE_LIMIT_EXCEEDED", true),
+        ("ORG_RATE_LIMIT_EXCEEDED", true),
+        ("TOKEN_RATE_LIMIT_EXCEEDED", true),
+        ("rate_limit_exceeded", true),
+        ("too_many_malformed_requests", true),
+        ("SERVICE_UNAVAILABLE", true),
+        ("service_unavailable", true),
+        ("auth_backend_slow", true),
+        ("maintenance", true),
+        ("upstream_timeout", true),
+        ("upstream_unavailable", true),
+        ("upstream_invalid_response", true),
+        ("connection_closed", true),
+        ("conversation_busy", true),
+        ("CACHE_ERROR", true),
+        ("LEDGER_ERROR", true),
+    ];
+
+    fn pinned(code: &str) -> bool {
+        PINNED_CODES
+            .iter()
+            .find(|(pinned, _)| *pinned == code)
+            .unwrap_or_else(|| panic!("error code {code} is not pinned"))
+            .1
+    }
+
+    async fn error_body(error: AppError) -> serde_json::Value {
+        let body = axum::body::to_bytes(error.into_response().into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice::<serde_json::Value>(&body).unwrap()["error"].clone()
+    }
+
+    #[test]
+    fn test_retryable_codes_are_pinned() {
+        for (code, retryable) in PINNED_CODES {
+            assert_eq!(is_retryable_code(code), *retryable, "{code}");
+        }
+        assert!(!is_retryable_code("something_new"));
+    }
+
+    #[tokio::test]
+    async fn test_error_bodies_follow_pinned_codes() {
+        let errors = vec![
+            AppError::Unauthorized,
+            AppError::InvalidToken,
+            AppError::AuthHeader(AuthHeaderError::MultipleHeaders),
+            AppError::AuthHeader(AuthHeaderError::Malformed),
+            AppError::AuthHeader(AuthHeaderError::UnsupportedScheme),
+            AppError::AuthHeader(AuthHeaderError::EmptyToken),
+            AppError::Forbidden,
+            AppError::ModelNotAllowed("gpt-4o".to_string()),
+            AppError::NotFound("model".to_string()),
+            AppError::RateLimitExceeded {
+                message: "Slow down".to_string(),
+                limit: 10,
+                used: 10,
+                remaining: 0,
+                reset_at: None,
+            },
+            AppError::QuotaExceeded {
+                message: "Used up".to_string(),
+                limit: 10,
+                used: 10,
+                retry_after: None,
+            },
+            AppError::BadRequest("messages is empty".to_string()),
+            AppError::NotAcceptable("stream needs text/event-stream".to_string()),
+            AppError::ServiceUnavailable {
+                message: "All models in backoff".to_string(),
+                retry_after: None,
+            },
+            AppError::AuthBackendSlow { budget_ms: 250 },
+            AppError::UpstreamTimeout { timeout_ms: 1000 },
+            AppError::UpstreamUnavailable {
+                provider: "openai".to_string(),
+                endpoint: "chat".to_string(),
+                retry_after: Duration::from_secs(5),
+            },
+            AppError::ContentBlocked,
+            AppError::UpstreamInvalidResponse {
+                reason: "no choices".to_string(),
+                request_id: None,
+            },
+            AppError::RedisError(redis::RedisError::from((
+                redis::ErrorKind::IoError,
+                "connection refused",
+            ))),
+            AppError::JsonError(serde_json::from_str::<u8>("x").unwrap_err()),
+            AppError::Internal(anyhow::anyhow!("translation failed")),
+        ];
+        for error in errors {
+            let body = error_body(error).await;
+            let code = body["code"].as_str().unwrap().to_string();
+            assert_eq!(body["retryable"], pinned(&code), "{code}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upstream_errors_retryable_by_status() {
+        for (status, retryable) in [(429, true), (500, true), (503, true), (400, false), (404, false)] {
+            let error = AppError::UpstreamError(format!("OpenAI error {} Error: x", status));
+            assert_eq!(error.retryable(), retryable, "{status}");
+            let body = error_body(error).await;
+            assert_eq!(body["code"], "UPSTREAM_ERROR");
+            assert_eq!(body["retryable"], retryable, "{status}");
+        }
+        // Unparseable responses and redirect failures don't say
+        assert!(!AppError::UpstreamError("Failed to parse response".to_string()).retryable());
+    }
+
+    #[tokio::test]
+    async fn test_retry_after_ms_hints() {
+        let body = error_body(AppError::ServiceUnavailable {
+            message: "All models in backoff".to_string(),
+            retry_after: Some(Duration::from_millis(1500)),
+        })
+        .await;
+        assert_eq!(body["retryable"], true);
+        assert_eq!(body["retry_after_ms"], 2000);
+
+        let now = chrono::Utc::now().timestamp();
+        let body = error_body(AppError::RateLimitExceeded {
+            message: "Slow down".to_string(),
+            limit: 10,
+            used: 10,
+            remaining: 0,
+            reset_at: RetryAfter::seconds(30).reset_at(now),
+        })
+        .await;
+        let wait = body["retry_after_ms"].as_u64().unwrap();
+        assert!((29_000..=30_000).contains(&wait), "{wait}");
+
+        // Quotas say when they reset but aren't retryable until then
+        let body = error_body(AppError::QuotaExceeded {
+            message: "Used up".to_string(),
+            limit: 10,
+            used: 10,
+            retry_after: Some(RetryAfter::seconds(3600)),
+        })
+        .await;
+        assert_eq!(body["retryable"], false);
+        assert_eq!(body["retry_after_ms"], 3_600_000);
+
+        let body = error_body(AppError::BadRequest("bad".to_string())).await;
+        assert_eq!(body["retryable"], false);
+        assert!(body.get("retry_after_ms").is_none());
+    }
+
+    #[test]
+    fn test_quota_exceeded_retry_after_is_a_date() {
+        let response = AppError::QuotaExceeded {
+            message: "Monthly token quota used up".to_string(),
+            limit: 100,
+            used: 100,
+            retry_after: Some(RetryAfter::seconds(3600)),
+        }
+        .into_response();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let date = response.headers()[header::RETRY_AFTER].to_str().unwrap();
+        let retry_at = chrono::DateTime::parse_from_rfc2822(date).unwrap().timestamp();
+        let expected = chrono::Utc::now().timestamp() + 3600;
+        assert!((expected - 2..=expected).contains(&retry_at), "{date}");
+    }
+}
@@ -0,0 +1,198 @@
+//! System prompt injection
+//!
+//! Injects a mandatory system preamble (configured per deployment, optionally
+//! overridden per tier in the Zion tier config) into conversations before they
+//! are forwarded upstream. Injected messages are always placed first so the
+//! "system messages before everything else" ordering rule still holds.
+
+use std::str::FromStr;
+
+use crate::config::Config;
+use crate::native::types::{Content, Message, Role as NativeRole};
+use crate::routes::chat::{ChatMessage, Role as ChatRole};
+
+/// How the configured preamble is applied to a conversation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InjectionMode {
+    /// Always insert the preamble as the first message
+    #[default]
+    Prepend,
+    /// Only inject when the conversation has no non-empty system message
+    ReplaceEmpty,
+    /// Never inject
+    Off,
+}
+
+impl FromStr for InjectionMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "prepend" => Ok(InjectionMode::Prepend),
+            "replace_empty" => Ok(InjectionMode::ReplaceEmpty),
+            "off" => Ok(InjectionMode::Off),
+            other => Err(format!(
+                "unknown injection mode '{}' (expected prepend, replace_empty or off)",
+                other
+            )),
+        }
+    }
+}
+
+/// Resolve the preamble to inject for a request
+///
+/// A per-tier override takes precedence over the deployment-wide text.
+/// Returns `None` when injection is off or no non-empty text is configured.
+pub fn resolve_preamble<'a>(config: &'a Config, tier_override: Option<&'a str>) -> Option<&'a str> {
+    if config.provider.system_prompt_injection_mode == InjectionMode::Off {
+        return None;
+    }
+    tier_override
+        .or(config.provider.system_prompt_injection.as_deref())
+        .filter(|text| !text.trim().is_empty())
+}
+
+/// Inject the preamble into `/v1` chat messages
+///
+/// Returns true if the preamble was injected.
+pub fn inject_chat_messages(
+    messages: &mut Vec<ChatMessage>,
+    preamble: &str,
+    mode: InjectionMode,
+) -> bool {
+    let is_system = |m: &ChatMessage| matches!(m.role, ChatRole::System);
+    let is_empty = |m: &ChatMessage| m.content.as_deref().is_none_or(|c| c.trim().is_empty());
+
+    match mode {
+        InjectionMode::Off => return false,
+        InjectionMode::ReplaceEmpty => {
+            if messages.iter().any(|m| is_system(m) && !is_empty(m)) {
+                return false;
+            }
+            messages.retain(|m| !is_system(m));
+        }
+        InjectionMode::Prepend => {}
+    }
+
+    messages.insert(
+        0,
+        ChatMessage {
+            role: ChatRole::System,
+            content: Some(preamble.to_string()),
+            name: None,
+            tool_calls: None,
+            tool_call_id: None,
+        },
+    );
+    true
+}
+
+/// Inject the preamble into Native API messages
+///
+/// Returns true if the preamble was injected.
+pub fn inject_native_messages(
+    messages: &mut Vec<Message>,
+    preamble: &str,
+    mode: InjectionMode,
+) -> bool {
+    let is_system = |m: &Message| m.role == NativeRole::System;
+    let is_empty = |m: &Message| m.content.as_text().trim().is_empty();
+
+    match mode {
+        InjectionMode::Off => return false,
+        InjectionMode::ReplaceEmpty => {
+            if messages.iter().any(|m| is_system(m) && !is_empty(m)) {
+                return false;
+            }
+            messages.retain(|m| !is_system(m));
+        }
+        InjectionMode::Prepend => {}
+    }
+
+    messages.insert(
+        0,
+        Message {
+            role: NativeRole::System,
+            content: Content::Text(preamble.to_string()),
+            name: None,
+            tool_call_id: None,
+            tool_calls: None,
+        },
+    );
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chat(role: ChatRole, content: &str) -> ChatMessage {
+        ChatMessage {
+            role,
+            content: Some(content.to_string()),
+            name: None,
+            tool_calls: None,
+            tool_call_id: None,
+        }
+    }
+
+    fn native(role: NativeRole, content: &str) -> Message {
+        Message {
+            role,
+            content: Content::Text(content.to_string()),
+            name: None,
+            tool_call_id: None,
+            tool_calls: None,
+        }
+    }
+
+    #[test]
+    fn test_mode_from_str() {
+        assert_eq!("prepend".parse::<InjectionMode>().unwrap(), InjectionMode::Prepend);
+        assert_eq!(
+            "REPLACE_EMPTY".parse::<InjectionMode>().unwrap(),
+            InjectionMode::ReplaceEmpty
+        );
+        assert_eq!("off".parse::<InjectionMode>().unwrap(), InjectionMode::Off);
+        assert!("sometimes".parse::<InjectionMode>().is_err());
+    }
+
+    #[test]
+    fn test_prepend_always_first() {
+        let mut messages = vec![chat(ChatRole::System, "Be brief."), chat(ChatRole::User, "Hi")];
+        assert!(inject_chat_messages(&mut messages, "No legal advice.", InjectionMode::Prepend));
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0].content.as_deref(), Some("No legal advice."));
+        assert_eq!(messages[1].content.as_deref(), Some("Be brief."));
+    }
+
+    #[test]
+    fn test_replace_empty_keeps_client_system_prompt() {
+        let mut messages = vec![chat(ChatRole::System, "Be brief."), chat(ChatRole::User, "Hi")];
+        assert!(!inject_chat_messages(&mut messages, "No legal advice.", InjectionMode::ReplaceEmpty));
+        assert_eq!(messages.len(), 2);
+    }
+
+    #[test]
+    fn test_replace_empty_replaces_blank_system_prompt() {
+        let mut messages = vec![chat(ChatRole::System, "  "), chat(ChatRole::User, "Hi")];
+        assert!(inject_chat_messages(&mut messages, "No legal advice.", InjectionMode::ReplaceEmpty));
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].content.as_deref(), Some("No legal advice."));
+    }
+
+    #[test]
+    fn test_off_never_injects() {
+        let mut messages = vec![native(NativeRole::User, "Hi")];
+        assert!(!inject_native_messages(&mut messages, "No legal advice.", InjectionMode::Off));
+        assert_eq!(messages.len(), 1);
+    }
+
+    #[test]
+    fn test_native_prepend() {
+        let mut messages = vec![native(NativeRole::User, "Hi")];
+        assert!(inject_native_messages(&mut messages, "No legal advice.", InjectionMode::Prepend));
+        assert_eq!(messages[0].role, NativeRole::System);
+        assert_eq!(messages[0].content.as_text(), "No legal advice.");
+    }
+}
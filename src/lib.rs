@@ -4,50 +4,82 @@
 //! It handles AI request proxying with user authentication, rate limiting,
 //! and token tracking.
 
+pub mod build_info;
 pub mod cache;
+#[cfg(feature = "chaos")]
+pub mod chaos;
+pub mod cli;
+pub mod clock;
 pub mod config;
 pub mod docs;
 pub mod error;
+pub mod injection;
+pub mod log_level;
 pub mod middleware;
 pub mod native;
 pub mod native_routes;
 pub mod proxy;
 pub mod routes;
 pub mod streaming;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod testing;
 pub mod tiers;
 pub mod tokens;
 pub mod usage;
 pub mod zion;
 
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 
-pub use crate::cache::{RedisCache, SubscriptionCache};
+pub use crate::cache::{CacheWarmer, RedisCache, SubscriptionCache};
+pub use crate::clock::{Clock, SharedClock, SystemClock};
 pub use crate::config::Config;
+pub use crate::log_level::LogLevel;
+pub use crate::middleware::{
+    InFlightRegistry, MaintenanceMode, QuarantineTracker, RateLimitExemptIds, RejectionWindow,
+    RequestMirror,
+};
 pub use crate::native::SessionManager;
-pub use crate::proxy::{AiProvider, OpenAIProvider};
+pub use crate::proxy::{
+    breaker::{BreakerConfig, UpstreamBreakers},
+    capabilities::ProviderStatus, finish_reason::FinishReasonMonitor, registry::ProviderRegistry,
+    provider::ProviderKind, snapshot::ModelSnapshotTracker, AiProvider, AnthropicProvider,
+    OpenAIProvider,
+};
 pub use crate::tiers::{HealthConfig, ProviderHealthTracker, SelectedModel, TierConfigCache, TierRouter};
 pub use crate::tokens::SharedTokenCounter;
-pub use crate::usage::{BatchingUsageTracker, UsageTracker};
-pub use crate::zion::ZionClient;
+pub use crate::usage::{
+    BatchingConfig, BatchingUsageTracker, LedgerHandle, RecentUsageStore, RequestWeights, UsageTracker,
+};
+pub use crate::zion::{jwks::JwksVerifier, ZionClient};
 
 /// Application state shared across all request handlers
 pub struct AppState {
     pub config: Config,
+    /// Time source for rate-limit windows and other time-based decisions
+    pub clock: SharedClock,
     /// Redis connection - None in test mode with InMemoryCache
     pub redis: Option<redis::aio::ConnectionManager>,
     pub http_client: reqwest::Client,
     pub start_time: Instant,
     pub zion_client: Arc<ZionClient>,
     pub subscription_cache: Arc<SubscriptionCache>,
+    /// Local verification of Zion tokens (None unless ZION_JWKS_URL is set)
+    pub jwks: Option<Arc<JwksVerifier>>,
+    /// Bulk warm jobs for the limits cache
+    pub cache_warmer: Arc<CacheWarmer>,
     /// Synchronous usage tracker for immediate tracking (used for streaming)
     pub usage_tracker: Arc<UsageTracker>,
     /// Batching usage tracker for fire-and-forget tracking (protects Zion from floods)
     pub batching_tracker: Arc<BatchingUsageTracker>,
     /// AI provider for forwarding requests to LLM backends
     pub ai_provider: Arc<dyn AiProvider>,
+    /// Providers tried in order when `ai_provider` fails (`FAILOVER_PROVIDERS`)
+    pub failover_providers: Vec<Arc<dyn AiProvider>>,
+    /// Providers selectable by name for canary overrides
+    pub providers: Arc<ProviderRegistry>,
     /// Token counter for estimating token usage with tiktoken-rs
     pub token_counter: SharedTokenCounter,
     /// Session manager for conversation-based provider stickiness
@@ -56,15 +88,51 @@ pub struct AppState {
     pub tier_config_cache: Arc<TierConfigCache>,
     /// Provider health tracker for availability monitoring
     pub health_tracker: Arc<ProviderHealthTracker>,
+    /// Circuit breakers per provider endpoint, applied by [`AppState::provider`]
+    pub upstream_breakers: Arc<UpstreamBreakers>,
     /// Tier router for model selection
     pub tier_router: Arc<TierRouter>,
+    /// Upstream model snapshot tracker
+    pub model_snapshots: Arc<ModelSnapshotTracker>,
+    /// finish_reason counters and `content_filter` spike alerts
+    pub finish_reasons: Arc<FinishReasonMonitor>,
+    /// Quarantine for users sending malformed requests at high rate
+    pub quarantine: Arc<QuarantineTracker>,
+    /// Maintenance mode toggle (startup setting plus runtime override)
+    pub maintenance: Arc<MaintenanceMode>,
+    /// External IDs that bypass rate limiting (startup setting plus runtime override)
+    pub rate_limit_exempt_ids: Arc<RateLimitExemptIds>,
+    /// Copies sampled requests to a staging Sentinel
+    pub mirror: Arc<RequestMirror>,
+    /// Latest provider capability check report
+    pub provider_status: Arc<ProviderStatus>,
+    /// Runtime control of the log filter (`/admin/log-level`)
+    pub log_level: Arc<LogLevel>,
+    /// Request weights of pass-through usage (`/admin/usage/request-weights`)
+    pub request_weights: Arc<RequestWeights>,
+    /// Requests being handled right now (`/admin/snapshot`)
+    pub in_flight: Arc<InFlightRegistry>,
+    /// Rate limit checks and rejections over the last minute
+    pub rate_limit_rejections: Arc<RejectionWindow>,
+    /// Local usage ledger (None unless LEDGER_DATABASE_URL is set)
+    #[cfg(feature = "ledger")]
+    pub ledger: Option<Arc<usage::ledger::LedgerStore>>,
+    /// Fault injection rules (None unless CHAOS_ENABLED is set)
+    #[cfg(feature = "chaos")]
+    pub chaos: Option<Arc<chaos::ChaosInjector>>,
 }
 
 impl AppState {
     /// Create a new application state
-    pub async fn new(config: Config) -> Result<Self> {
+    ///
+    /// `log_level` controls the filter of the installed subscriber (see
+    /// [`log_level::reloadable`]).
+    pub async fn new(config: Config, log_level: LogLevel) -> Result<Self> {
+        proxy::upstream_user::validate(&config.provider)?;
+        let clock = clock::system_clock();
+
         // Initialize Redis connection
-        let redis_client = redis::Client::open(config.redis_url.as_str())?;
+        let redis_client = redis::Client::open(config.redis.url.as_str())?;
         let redis = redis::aio::ConnectionManager::new(redis_client).await?;
 
         // Initialize HTTP client with connection pooling
@@ -73,77 +141,241 @@ impl AppState {
             .timeout(std::time::Duration::from_secs(300))
             .build()?;
 
+        // Fault injection rules, consulted by the provider, Zion and Redis clients
+        #[cfg(feature = "chaos")]
+        let chaos = config.server.chaos_enabled.then(|| {
+            tracing::warn!("Chaos fault injection enabled (CHAOS_ENABLED); not for production");
+            Arc::new(chaos::ChaosInjector::new().with_clock(clock.clone()))
+        });
+        #[cfg(not(feature = "chaos"))]
+        if config.server.chaos_enabled {
+            tracing::warn!(
+                "CHAOS_ENABLED is set but Sentinel was built without the `chaos` feature; fault injection disabled"
+            );
+        }
+
         // Initialize Zion client
-        let zion_client = Arc::new(ZionClient::new(http_client.clone(), &config));
+        let zion_client = ZionClient::new(http_client.clone(), &config);
+        #[cfg(feature = "chaos")]
+        let zion_client = match &chaos {
+            Some(chaos) => zion_client.with_chaos(chaos.clone()),
+            None => zion_client,
+        };
+        let zion_client = Arc::new(zion_client);
 
         // Initialize Redis cache
-        let redis_cache = Arc::new(RedisCache::new(redis.clone(), config.cache_ttl_seconds));
+        let redis_cache = RedisCache::new(redis.clone(), config.zion.cache_ttl_seconds);
+        #[cfg(feature = "chaos")]
+        let redis_cache = match &chaos {
+            Some(chaos) => redis_cache.with_chaos(chaos.clone()),
+            None => redis_cache,
+        };
+        let redis_cache = Arc::new(redis_cache);
 
         // Initialize subscription cache
         let subscription_cache = Arc::new(SubscriptionCache::new(
             redis_cache.clone(),
             zion_client.clone(),
-            config.cache_ttl_seconds,
-            config.jwt_cache_ttl_seconds,
+            config.zion.cache_ttl_seconds,
+            config.zion.jwt_cache_ttl_seconds,
         ));
+        let cache_warmer = Arc::new(CacheWarmer::new(subscription_cache.clone(), &config));
+        let jwks = JwksVerifier::from_config(&http_client, &config.zion).map(Arc::new);
 
         // Initialize session manager for provider stickiness
-        let session_manager = Arc::new(SessionManager::new(
-            redis_cache.clone(),
-            config.session_ttl_seconds,
-        ));
+        let mut session_manager =
+            SessionManager::new(redis_cache.clone(), config.provider.session_ttl_seconds)
+                .with_clock(clock.clone());
+        if config.provider.affinity_secret.is_some() {
+            session_manager = session_manager.with_local_copies(Duration::from_secs(
+                config.provider.affinity_local_ttl_seconds,
+            ));
+        }
+        if config.provider.session_history_max_messages > 0 {
+            session_manager =
+                session_manager.with_history(config.provider.session_history_max_messages);
+        }
+        let session_manager = Arc::new(session_manager);
+
+        // Initialize upstream model snapshot tracker
+        let model_snapshots = Arc::new(ModelSnapshotTracker::new(redis_cache.clone()));
+        let finish_reasons = Arc::new(FinishReasonMonitor::new(&config.provider).with_clock(clock.clone()));
+
+        // Initialize malformed-request quarantine
+        let quarantine = Arc::new(QuarantineTracker::new(redis_cache.clone(), &config));
+
+        // Initialize maintenance mode toggle
+        let maintenance = Arc::new(MaintenanceMode::new(redis_cache.clone(), &config));
+
+        // Initialize rate limit exemption list
+        let rate_limit_exempt_ids = Arc::new(RateLimitExemptIds::new(redis_cache.clone(), &config));
+
+        let mirror = Arc::new(RequestMirror::new(&config.server));
 
         // Initialize tier configuration cache
-        let tier_config_cache = Arc::new(TierConfigCache::new(
-            redis_cache,
-            zion_client.clone(),
-            config.tier_config_ttl_seconds,
-        ));
+        let tier_config_cache = Arc::new(
+            TierConfigCache::new(
+                redis_cache,
+                zion_client.clone(),
+                config.zion.tier_config_ttl_seconds,
+            )
+            .with_standby(
+                config.zion.tier_config_standby_path.clone().map(Into::into),
+                Duration::from_secs(config.zion.tier_config_max_staleness_hours * 3600),
+            )
+            .with_clock(clock.clone()),
+        );
 
         // Initialize provider health tracker
-        let health_tracker = Arc::new(ProviderHealthTracker::new());
+        let health_tracker = Arc::new(ProviderHealthTracker::new().with_clock(clock.clone()));
+
+        // Initialize upstream circuit breakers (state is reported to the health tracker)
+        let upstream_breakers = Arc::new(
+            UpstreamBreakers::new(BreakerConfig::from(&config.provider), health_tracker.clone())
+                .with_clock(clock.clone()),
+        );
 
         // Initialize tier router
-        let tier_router = Arc::new(TierRouter::new(
-            tier_config_cache.clone(),
-            health_tracker.clone(),
-        ));
+        let tier_router = Arc::new(
+            TierRouter::new(tier_config_cache.clone(), health_tracker.clone())
+                .with_latency_weight(config.provider.tier_latency_weight),
+        );
 
         // Initialize usage tracker (synchronous, for streaming)
         let usage_tracker = Arc::new(UsageTracker::new(zion_client.clone()));
 
+        // Initialize the local usage ledger (optional, dual-written by the batching tracker)
+        #[cfg(feature = "ledger")]
+        let (ledger, ledger_handle) = match config.usage.ledger_database_url.as_deref() {
+            Some(url) => {
+                let store = Arc::new(usage::ledger::LedgerStore::connect(url).await?);
+                let handle = usage::ledger::spawn_ledger_writer(
+                    store.clone(),
+                    Some(redis.clone()),
+                    usage::ledger::LedgerWriterConfig::default(),
+                );
+                tracing::info!("Usage ledger enabled");
+                (Some(store), handle)
+            }
+            None => (None, LedgerHandle::disabled()),
+        };
+        #[cfg(not(feature = "ledger"))]
+        let ledger_handle = {
+            if config.usage.ledger_database_url.is_some() {
+                tracing::warn!(
+                    "LEDGER_DATABASE_URL is set but Sentinel was built without the `ledger` feature; ledger disabled"
+                );
+            }
+            LedgerHandle::disabled()
+        };
+
         // Initialize batching usage tracker (fire-and-forget, protects Zion)
-        let batching_tracker = Arc::new(BatchingUsageTracker::with_defaults(
+        let batching_tracker = Arc::new(BatchingUsageTracker::new_with_ledger(
             zion_client.clone(),
             redis.clone(),
+            BatchingConfig {
+                clock: clock.clone(),
+                replica_id: usage::retry_lease::resolve_replica_id(
+                    config.server.replica_id.as_deref(),
+                ),
+                ..Default::default()
+            },
+            ledger_handle,
+            Arc::new(
+                RecentUsageStore::new(redis.clone(), config.usage.aggregate_days)
+                    .with_report_limits(config.usage.report_days, config.usage.report_max_groups),
+            ),
         ));
 
-        // Initialize AI provider (OpenAI by default)
-        // Note: Will panic if OPENAI_API_KEY is not set - this is intentional
-        // as the proxy cannot function without an AI provider
-        let ai_provider: Arc<dyn AiProvider> = Arc::new(OpenAIProvider::new(
-            http_client.clone(),
-            &config,
-        ));
+        // Initialize AI provider (OpenAI by default, `AI_PROVIDER`) with its own
+        // streaming and short-call clients, which leave redirects to the provider
+        // Note: Will panic if OPENAI_API_KEY (bearer auth) or ANTHROPIC_API_KEY
+        // is not set - this is intentional as the proxy cannot function without
+        // an AI provider
+        let provider_clients = proxy::pool::ProviderClients::from_config(&config.provider)?;
+        // MODELS_SOURCE=static|merged needs STATIC_MODELS_JSON
+        proxy::static_models::static_list(&config.provider)?;
+        let build_provider = |kind: ProviderKind| -> Result<Arc<dyn AiProvider>> {
+            let clients = provider_clients.clone();
+            Ok(match kind {
+                ProviderKind::Anthropic => Arc::new(AnthropicProvider::new(clients, &config)),
+                ProviderKind::OpenAI => {
+                    match proxy::signing::signer_from_config(&config.provider, clock.clone())? {
+                        Some(signer) => {
+                            Arc::new(OpenAIProvider::with_signer(clients, &config, signer))
+                        }
+                        None => Arc::new(OpenAIProvider::new(clients, &config)),
+                    }
+                }
+            })
+        };
+        let ai_provider = build_provider(config.provider.ai_provider)?;
+
+        // Failover providers, each listed once and never the primary again
+        let mut failover_kinds: Vec<ProviderKind> = Vec::new();
+        for kind in &config.provider.failover_providers {
+            if *kind != config.provider.ai_provider && !failover_kinds.contains(kind) {
+                failover_kinds.push(*kind);
+            }
+        }
+        let failover_providers = failover_kinds
+            .into_iter()
+            .map(build_provider)
+            .collect::<Result<Vec<_>>>()?;
+        let providers = Arc::new(ProviderRegistry::new(ai_provider.clone()));
+        for provider in &failover_providers {
+            providers.register(provider.name(), provider.clone());
+        }
+
+        // Keep provider and Zion keys out of error bodies returned or logged
+        proxy::redact::install(proxy::redact::SecretRedactor::from_config(&config));
 
         // Initialize token counter for tiktoken-based token estimation
-        let token_counter = SharedTokenCounter::new();
+        let token_counter = SharedTokenCounter::with_encodings(
+            config.usage.token_fallback_encoding,
+            config.usage.model_encoding_overrides.clone(),
+        );
+
+        let request_weights = Arc::new(RequestWeights::new(config.usage.request_weights.clone()));
+        let in_flight = Arc::new(InFlightRegistry::new().with_clock(clock.clone()));
+        let rate_limit_rejections = Arc::new(RejectionWindow::new().with_clock(clock.clone()));
 
         Ok(Self {
             config,
+            clock,
             redis: Some(redis),
             http_client,
             start_time: Instant::now(),
             zion_client,
             subscription_cache,
+            jwks,
+            cache_warmer,
             usage_tracker,
             batching_tracker,
+            providers,
+            failover_providers,
             ai_provider,
             token_counter,
             session_manager,
             tier_config_cache,
             health_tracker,
+            upstream_breakers,
             tier_router,
+            model_snapshots,
+            finish_reasons,
+            quarantine,
+            maintenance,
+            rate_limit_exempt_ids,
+            mirror,
+            provider_status: Arc::new(ProviderStatus::new()),
+            log_level: Arc::new(log_level),
+            request_weights,
+            in_flight,
+            rate_limit_rejections,
+            #[cfg(feature = "ledger")]
+            ledger,
+            #[cfg(feature = "chaos")]
+            chaos,
         })
     }
 
@@ -163,12 +395,17 @@ impl AppState {
     ) -> Self {
         use crate::cache::InMemoryCache;
 
+        let clock = clock::system_clock();
         let http_client = reqwest::Client::new();
-        let token_counter = SharedTokenCounter::new();
+        proxy::redact::install(proxy::redact::SecretRedactor::from_config(&config));
+        let token_counter = SharedTokenCounter::with_encodings(
+            config.usage.token_fallback_encoding,
+            config.usage.model_encoding_overrides.clone(),
+        );
         let usage_tracker = Arc::new(UsageTracker::new(zion_client.clone()));
 
         // Create in-memory cache for testing (no Redis required)
-        let in_memory_cache = Arc::new(InMemoryCache::new(60));
+        let in_memory_cache = Arc::new(InMemoryCache::new(60).with_clock(clock.clone()));
 
         // Create subscription cache with in-memory backend
         let subscription_cache = Arc::new(SubscriptionCache::new_for_testing(
@@ -177,42 +414,161 @@ impl AppState {
             60, // 1 minute TTL for limits
             60, // 1 minute TTL for JWT
         ));
+        let cache_warmer = Arc::new(CacheWarmer::new(subscription_cache.clone(), &config));
+        let jwks = JwksVerifier::from_config(&http_client, &config.zion).map(Arc::new);
 
         // Create session manager with in-memory backend for testing
-        let session_manager = Arc::new(SessionManager::new_for_testing(
+        let mut session_manager =
+            SessionManager::new_for_testing(in_memory_cache.clone(), config.provider.session_ttl_seconds)
+                .with_clock(clock.clone());
+        if config.provider.affinity_secret.is_some() {
+            session_manager = session_manager.with_local_copies(Duration::from_secs(
+                config.provider.affinity_local_ttl_seconds,
+            ));
+        }
+        if config.provider.session_history_max_messages > 0 {
+            session_manager =
+                session_manager.with_history(config.provider.session_history_max_messages);
+        }
+        let session_manager = Arc::new(session_manager);
+
+        let model_snapshots = Arc::new(ModelSnapshotTracker::new_for_testing(in_memory_cache.clone()));
+        let finish_reasons = Arc::new(FinishReasonMonitor::new(&config.provider).with_clock(clock.clone()));
+
+        let quarantine = Arc::new(QuarantineTracker::new_for_testing(in_memory_cache.clone(), &config));
+
+        let maintenance = Arc::new(MaintenanceMode::new_for_testing(in_memory_cache.clone(), &config));
+
+        let rate_limit_exempt_ids = Arc::new(RateLimitExemptIds::new_for_testing(
             in_memory_cache.clone(),
-            config.session_ttl_seconds,
+            &config,
         ));
 
+        let mirror = Arc::new(RequestMirror::new(&config.server));
+
         // Create tier config cache with in-memory backend for testing
-        let tier_config_cache = Arc::new(TierConfigCache::new_for_testing(
-            in_memory_cache,
-            zion_client.clone(),
-            60, // 1 minute TTL for tests
-        ));
+        let tier_config_cache = Arc::new(
+            TierConfigCache::new_for_testing(
+                in_memory_cache,
+                zion_client.clone(),
+                60, // 1 minute TTL for tests
+            )
+            .with_standby(
+                config.zion.tier_config_standby_path.clone().map(Into::into),
+                Duration::from_secs(config.zion.tier_config_max_staleness_hours * 3600),
+            )
+            .with_clock(clock.clone()),
+        );
 
-        let health_tracker = Arc::new(ProviderHealthTracker::new());
+        let health_tracker = Arc::new(ProviderHealthTracker::new().with_clock(clock.clone()));
 
-        let tier_router = Arc::new(TierRouter::new(
-            tier_config_cache.clone(),
-            health_tracker.clone(),
-        ));
+        let upstream_breakers = Arc::new(
+            UpstreamBreakers::new(BreakerConfig::from(&config.provider), health_tracker.clone())
+                .with_clock(clock.clone()),
+        );
+
+        let tier_router = Arc::new(
+            TierRouter::new(tier_config_cache.clone(), health_tracker.clone())
+                .with_latency_weight(config.provider.tier_latency_weight),
+        );
+
+        let request_weights = Arc::new(RequestWeights::new(config.usage.request_weights.clone()));
+        let in_flight = Arc::new(InFlightRegistry::new().with_clock(clock.clone()));
+        let rate_limit_rejections = Arc::new(RejectionWindow::new().with_clock(clock.clone()));
+
+        // Share the Zion client's fault rules, so one admin call reaches both
+        #[cfg(feature = "chaos")]
+        let chaos = zion_client.chaos().cloned().or_else(|| {
+            config
+                .server
+                .chaos_enabled
+                .then(|| Arc::new(chaos::ChaosInjector::new()))
+        });
 
         Self {
             config,
+            clock,
             redis: None, // No Redis in test mode
             http_client,
             start_time: Instant::now(),
             zion_client,
             subscription_cache,
+            jwks,
+            cache_warmer,
             usage_tracker,
             batching_tracker,
+            providers: Arc::new(ProviderRegistry::new(ai_provider.clone())),
+            failover_providers: Vec::new(),
             ai_provider,
             token_counter,
             session_manager,
             tier_config_cache,
             health_tracker,
+            upstream_breakers,
             tier_router,
+            model_snapshots,
+            finish_reasons,
+            quarantine,
+            maintenance,
+            rate_limit_exempt_ids,
+            mirror,
+            provider_status: Arc::new(ProviderStatus::new()),
+            log_level: Arc::new(LogLevel::unmanaged()),
+            request_weights,
+            in_flight,
+            rate_limit_rejections,
+            #[cfg(feature = "ledger")]
+            ledger: None,
+            #[cfg(feature = "chaos")]
+            chaos,
         }
     }
+
+    /// Provider serving the current request
+    ///
+    /// The default provider, unless the canary override middleware swapped it
+    /// for this request. Calls go through the upstream circuit breakers unless
+    /// they are disabled, and without an override fail over to
+    /// `failover_providers` when any are configured. Chaos fault rules apply
+    /// inside the breakers, which count injected failures like real ones.
+    pub fn provider(&self) -> Arc<dyn AiProvider> {
+        let guarded = |provider: Arc<dyn AiProvider>| -> Arc<dyn AiProvider> {
+            #[cfg(feature = "chaos")]
+            let provider: Arc<dyn AiProvider> = match &self.chaos {
+                Some(chaos) => Arc::new(chaos::ChaosProvider::new(provider, chaos.clone())),
+                None => provider,
+            };
+            if !self.upstream_breakers.is_enabled() {
+                return provider;
+            }
+            Arc::new(proxy::breaker::CircuitBreakingProvider::new(
+                provider,
+                self.upstream_breakers.clone(),
+            ))
+        };
+        if let Some(provider) = proxy::registry::current_override() {
+            return guarded(provider);
+        }
+        if self.failover_providers.is_empty() {
+            return guarded(self.ai_provider.clone());
+        }
+
+        let chain = std::iter::once(&self.ai_provider)
+            .chain(&self.failover_providers)
+            .map(|provider| guarded(provider.clone()))
+            .collect();
+        // Unset, an attempt may take as long as any upstream call
+        let attempt_timeout = Some(
+            self.config
+                .provider
+                .failover_attempt_timeout_ms
+                .unwrap_or(self.config.provider.upstream_timeout_max_ms),
+        )
+        .filter(|ms| *ms > 0)
+        .map(Duration::from_millis);
+        Arc::new(
+            proxy::failover::FailoverProvider::new(chain, attempt_timeout)
+                .with_model_map(self.config.provider.failover_model_map.clone()),
+        )
+    }
 }
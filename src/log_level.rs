@@ -0,0 +1,266 @@
+//! Runtime log filter
+//!
+//! The `EnvFilter` built from `RUST_LOG` is installed behind a
+//! `tracing_subscriber::reload` layer on the registry, in front of every
+//! output layer, so `PUT /admin/log-level` can swap it without restarting
+//! the process (and losing the state being debugged). A change can revert to
+//! the startup filter after a delay (`LOG_LEVEL_REVERT_SECONDS`), so debug
+//! logging isn't left on by accident.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{SecondsFormat, Utc};
+use serde::Serialize;
+use tracing::info;
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+use crate::error::{AppError, AppResult};
+
+/// Filter used when `RUST_LOG` is unset or invalid
+pub const DEFAULT_LOG_FILTER: &str = "sentinel=info,tower_http=info";
+
+/// Reload handle of the registry-level filter
+type FilterHandle = reload::Handle<EnvFilter, Registry>;
+
+/// Current filter, as returned by `GET /admin/log-level`
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct LogLevelStatus {
+    /// Active filter directives
+    pub filter: String,
+    /// Filter the process started with (restored by the revert)
+    pub startup_filter: String,
+    /// When the active filter reverts to the startup filter (RFC 3339)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reverts_at: Option<String>,
+}
+
+/// Active filter and the pending revert
+#[derive(Debug)]
+struct FilterState {
+    filter: String,
+    reverts_at: Option<String>,
+    /// Bumped by every change, so a revert scheduled before a later change is skipped
+    generation: u64,
+}
+
+/// Runtime control of the log filter
+pub struct LogLevel {
+    handle: Option<FilterHandle>,
+    startup: String,
+    state: Mutex<FilterState>,
+}
+
+/// Wrap `filter` in a reload layer for the registry
+///
+/// Install the returned layer before any output layer so the filter applies
+/// to all of them; the `LogLevel` changes it later.
+pub fn reloadable(filter: EnvFilter) -> (reload::Layer<EnvFilter, Registry>, LogLevel) {
+    let startup = filter.to_string();
+    let (layer, handle) = reload::Layer::new(filter);
+    (layer, LogLevel::new(Some(handle), startup))
+}
+
+impl LogLevel {
+    fn new(handle: Option<FilterHandle>, startup: String) -> Self {
+        Self {
+            handle,
+            state: Mutex::new(FilterState {
+                filter: startup.clone(),
+                reverts_at: None,
+                generation: 0,
+            }),
+            startup,
+        }
+    }
+
+    /// Control for a process whose subscriber wasn't installed with [`reloadable`]
+    ///
+    /// Reports and changes nothing; the admin endpoints answer 404.
+    pub fn unmanaged() -> Self {
+        Self::new(None, String::new())
+    }
+
+    /// Whether the filter can be changed at runtime
+    pub fn is_reloadable(&self) -> bool {
+        self.handle.is_some()
+    }
+
+    /// Current filter
+    pub fn status(&self) -> AppResult<LogLevelStatus> {
+        self.require_reloadable()?;
+        let state = self.state.lock().unwrap();
+        Ok(LogLevelStatus {
+            filter: state.filter.clone(),
+            startup_filter: self.startup.clone(),
+            reverts_at: state.reverts_at.clone(),
+        })
+    }
+
+    /// Replace the filter, optionally reverting to the startup filter after `revert_after`
+    ///
+    /// The directives are parsed before anything changes; an invalid filter is
+    /// a `BadRequest` and leaves the current one in place.
+    pub fn set(
+        self: &Arc<Self>,
+        directives: &str,
+        revert_after: Option<Duration>,
+    ) -> AppResult<LogLevelStatus> {
+        self.require_reloadable()?;
+        if directives.trim().is_empty() {
+            return Err(AppError::BadRequest(
+                "Log filter must not be empty".to_string(),
+            ));
+        }
+        let filter = EnvFilter::try_new(directives).map_err(|e| {
+            AppError::BadRequest(format!("Invalid log filter '{}': {}", directives, e))
+        })?;
+        let filter_text = filter.to_string();
+
+        let generation = {
+            let mut state = self.state.lock().unwrap();
+            info!(
+                from = %state.filter,
+                to = %filter_text,
+                revert_after_seconds = revert_after.map(|d| d.as_secs()),
+                "Changing log filter"
+            );
+            self.reload(filter)?;
+            state.filter = filter_text;
+            state.reverts_at = revert_after
+                .map(|delay| (Utc::now() + delay).to_rfc3339_opts(SecondsFormat::Secs, true));
+            state.generation += 1;
+            state.generation
+        };
+
+        if let Some(delay) = revert_after {
+            let log_level = self.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(delay).await;
+                log_level.revert(generation);
+            });
+        }
+        self.status()
+    }
+
+    /// Restore the startup filter unless it was changed again since `generation`
+    fn revert(&self, generation: u64) {
+        let mut state = self.state.lock().unwrap();
+        if state.generation != generation {
+            return;
+        }
+        // The startup filter was parsed once already
+        let Ok(filter) = EnvFilter::try_new(&self.startup) else {
+            return;
+        };
+        if self.reload(filter).is_err() {
+            return;
+        }
+        let expired = std::mem::replace(&mut state.filter, self.startup.clone());
+        state.reverts_at = None;
+        state.generation += 1;
+        info!(from = %expired, to = %self.startup, "Log filter reverted to the startup filter");
+    }
+
+    fn reload(&self, filter: EnvFilter) -> AppResult<()> {
+        let handle = self.handle.as_ref().ok_or_else(not_reloadable)?;
+        handle
+            .reload(filter)
+            .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to apply log filter: {}", e)))
+    }
+
+    fn require_reloadable(&self) -> AppResult<()> {
+        if self.is_reloadable() {
+            Ok(())
+        } else {
+            Err(not_reloadable())
+        }
+    }
+}
+
+fn not_reloadable() -> AppError {
+    AppError::NotFound("Log level control is not enabled in this process".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::capture_logs as capture;
+    use tracing::debug;
+
+    #[tokio::test]
+    async fn test_change_applies_immediately() {
+        let (log_level, captured, _guard) = capture("sentinel=info");
+
+        debug!("hidden at info");
+        let status = log_level.set("sentinel=debug", None).unwrap();
+        assert_eq!(status.filter, "sentinel=debug");
+        assert_eq!(status.startup_filter, "sentinel=info");
+        assert!(status.reverts_at.is_none());
+        debug!("shown at debug");
+        log_level.set("sentinel=info", None).unwrap();
+        debug!("hidden again");
+
+        let output = captured.text();
+        assert!(!output.contains("hidden at info"));
+        assert!(output.contains("shown at debug"));
+        assert!(!output.contains("hidden again"));
+        // The change itself is logged
+        assert!(output.contains("Changing log filter"));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_filter_is_rejected() {
+        let (log_level, _captured, _guard) = capture("sentinel=info");
+
+        for invalid in ["", "  ", "sentinel=loud", "[unclosed"] {
+            let error = log_level.set(invalid, None).unwrap_err();
+            assert!(matches!(error, AppError::BadRequest(_)), "{}", invalid);
+        }
+        assert_eq!(log_level.status().unwrap().filter, "sentinel=info");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_reverts_after_delay() {
+        let (log_level, captured, _guard) = capture("sentinel=info");
+
+        let status = log_level
+            .set("sentinel=debug", Some(Duration::from_secs(60)))
+            .unwrap();
+        assert!(status.reverts_at.is_some());
+
+        tokio::time::sleep(Duration::from_secs(59)).await;
+        assert_eq!(log_level.status().unwrap().filter, "sentinel=debug");
+
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        let status = log_level.status().unwrap();
+        assert_eq!(status.filter, "sentinel=info");
+        assert!(status.reverts_at.is_none());
+        debug!("hidden after revert");
+        assert!(!captured.text().contains("hidden after revert"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_later_change_cancels_pending_revert() {
+        let (log_level, _captured, _guard) = capture("sentinel=info");
+
+        log_level
+            .set("sentinel=debug", Some(Duration::from_secs(60)))
+            .unwrap();
+        log_level.set("sentinel=trace", None).unwrap();
+
+        tokio::time::sleep(Duration::from_secs(120)).await;
+        assert_eq!(log_level.status().unwrap().filter, "sentinel=trace");
+    }
+
+    #[test]
+    fn test_unmanaged_is_not_found() {
+        let log_level = Arc::new(LogLevel::unmanaged());
+        assert!(!log_level.is_reloadable());
+        assert!(matches!(log_level.status(), Err(AppError::NotFound(_))));
+        assert!(matches!(
+            log_level.set("sentinel=debug", None),
+            Err(AppError::NotFound(_))
+        ));
+    }
+}
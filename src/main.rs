@@ -8,22 +8,35 @@ use std::sync::Arc;
 use anyhow::Result;
 use tokio::signal;
 use tracing::{info, warn};
+use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
-use sentinel::{routes, AppState, Config};
+use sentinel::{
+    build_info::BuildInfo, cache, cli, log_level, proxy::capabilities, routes, tiers, AppState, Config,
+};
 
 #[tokio::main]
 async fn main() -> Result<()> {
     // Load environment variables from .env file
     dotenvy::dotenv().ok();
 
+    // Operator subcommands (`sentinel usage-queue ...`) run instead of the server
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if matches!(args.first().map(String::as_str), Some("--version" | "-V")) {
+        println!("{}", BuildInfo::current().version_line());
+        return Ok(());
+    }
+    if args.first().map(String::as_str) == Some("usage-queue") {
+        return run_usage_queue(&args[1..]).await;
+    }
+
     // Initialize tracing
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "sentinel=info,tower_http=info".into()),
-        )
-        .with_target(true)
-        .with_thread_ids(true)
+    // Initialize tracing, with the filter reloadable via /admin/log-level
+    let (filter, log_level) = log_level::reloadable(
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| log_level::DEFAULT_LOG_FILTER.into()),
+    );
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().with_target(true).with_thread_ids(true))
         .init();
 
     info!("Starting Sentinel AI Proxy");
@@ -33,18 +46,37 @@ async fn main() -> Result<()> {
     info!("Configuration loaded successfully");
 
     // Initialize metrics
-    routes::metrics::init_metrics();
+    routes::metrics::init_metrics_with_labels(&config.server.metrics_labels);
     info!("Metrics initialized");
 
     // Initialize application state
-    let state = Arc::new(AppState::new(config.clone()).await?);
+    let state = Arc::new(AppState::new(config.clone(), log_level).await?);
     info!("Application state initialized");
 
+    // Replicas sharing Redis during a rollout should agree on (or migrate) these
+    cache::schema::log_schema_versions();
+
+    // Validate provider configuration (STARTUP_PROVIDER_CHECK)
+    capabilities::startup_check(&state).await?;
+
+    // Negotiate optional batch-increment fields with Zion
+    state.zion_client.refresh_capabilities().await;
+
+    // Load the tier config, falling back to the standby copy if Zion is down
+    tiers::standby::load_at_startup(state.tier_config_cache.clone()).await;
+
+    // Prune health state of models that left the tier config
+    tiers::prune::spawn_pruner(
+        state.tier_config_cache.clone(),
+        state.health_tracker.clone(),
+        (&config.provider).into(),
+    );
+
     // Build the router
     let app = routes::create_router(state.clone());
 
     // Bind to address
-    let addr: SocketAddr = format!("{}:{}", config.host, config.port).parse()?;
+    let addr: SocketAddr = format!("{}:{}", config.server.host, config.server.port).parse()?;
     info!("Listening on {}", addr);
 
     // Create listener
@@ -59,6 +91,19 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+/// Run a `sentinel usage-queue` command, logging to stderr so stdout stays clean
+async fn run_usage_queue(args: &[String]) -> Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "sentinel=warn".into()),
+        )
+        .with_writer(std::io::stderr)
+        .init();
+
+    cli::run_usage_queue(args).await
+}
+
 /// Handle graceful shutdown signals
 async fn shutdown_signal() {
     let ctrl_c = async {
@@ -1,19 +1,46 @@
 //! Authentication middleware
 //!
 //! Validates Zion JWTs and caches validation results.
+//!
+//! The token is read from `Authorization: Bearer <jwt>` (scheme matched
+//! case-insensitively, surrounding whitespace ignored) or, with
+//! `AUTH_ALLOW_X_API_KEY`, from `X-Api-Key: <jwt>`. Malformed headers are
+//! rejected here with a specific error code instead of being sent to Zion.
+//!
+//! The user's limits are loaded together with the profile (see
+//! [`SubscriptionCache::authenticate`]) and handed to the rate limiter, which
+//! records the time from here to the handler in
+//! `sentinel_auth_path_duration_seconds`. With `AUTH_PATH_BUDGET_MS` a lookup
+//! that takes longer is cut off with a 503 `auth_backend_slow`.
+//!
+//! With `ZION_JWKS_URL` tokens signed with Zion's keys are verified locally
+//! first (see [`crate::zion::jwks`]); the rest are still validated by Zion.
+//!
+//! [`SubscriptionCache::authenticate`]: crate::cache::subscription::SubscriptionCache::authenticate
 
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use axum::{
     extract::{Request, State},
-    http::header,
+    http::{header, HeaderMap},
     middleware::Next,
     response::Response,
 };
 use sha2::{Digest, Sha256};
 use tracing::{debug, instrument, warn};
 
-use crate::{error::AppError, AppState};
+use crate::{
+    cache::subscription::{AuthLookup, Authentication},
+    error::{AppError, AuthHeaderError},
+    middleware::{model_access::ModelAllowlist, scope::TokenScopes, token_limit::TokenCharge},
+    routes::metrics::record_auth_path,
+    zion::{jwks::Verification, UserLimit},
+    AppState,
+};
+
+/// Alternative credentials header, enabled by `AUTH_ALLOW_X_API_KEY`
+pub const API_KEY_HEADER: &str = "x-api-key";
 
 /// Extract user ID from request
 ///
@@ -23,17 +50,123 @@ pub struct AuthenticatedUser {
     pub user_id: String,
     pub external_id: String,
     pub email: String,
+    /// Zion organization, filled in by the rate limiter from the user's limits
+    pub organization_id: Option<String>,
+    /// Zion `loggingOptOut`, filled in by the rate limiter like `organization_id`
+    pub logging_opt_out: bool,
+    /// Zion `allowedModels`, filled in by the rate limiter and checked by the handlers
+    pub allowed_models: ModelAllowlist,
+    /// Allow-listed synthetic traffic, filled in by `synthetic_middleware`;
+    /// its usage is not tracked
+    pub synthetic: bool,
+    /// Scopes granted to the token, checked by `scope_middleware`
+    pub scopes: TokenScopes,
+    /// Estimated prompt tokens charged by the rate limiter, settled with the
+    /// actual usage when it is tracked
+    pub token_charge: Option<TokenCharge>,
+    /// The `user` value forwarded to the provider as the ledger records it,
+    /// filled in by the handlers (see `proxy::upstream_user::apply`)
+    pub upstream_user: Option<String>,
 }
 
-/// Extract the Authorization header and return the bearer token
-pub fn extract_bearer_token(auth_header: &str) -> Option<&str> {
-    if auth_header.starts_with("Bearer ") {
-        Some(&auth_header[7..])
-    } else {
-        None
+/// Limits loaded with the profile, and when authentication started
+///
+/// Added to the request extensions by `auth_middleware` for the rate limiter.
+#[derive(Debug, Clone)]
+pub struct AuthPath {
+    pub started: Instant,
+    pub lookup: AuthLookup,
+    /// None when the lookup failed, which means not exempt, no organization
+    /// scope and no quota check
+    pub limits: Option<Vec<UserLimit>>,
+}
+
+impl AuthPath {
+    /// Record the time since authentication started, as the request moves on
+    pub fn record(&self) {
+        record_auth_path(self.lookup.as_str(), self.started.elapsed().as_secs_f64());
+    }
+}
+
+/// Logged instead of the identifiers of users who opted out of request logging
+pub const OPTED_OUT_LOG_ID: &str = "[opted-out]";
+
+impl AuthenticatedUser {
+    /// User identifier for request logs
+    ///
+    /// The external id, or `OPTED_OUT_LOG_ID` for users who opted out of
+    /// request logging.
+    pub fn log_id(&self) -> &str {
+        if self.logging_opt_out {
+            OPTED_OUT_LOG_ID
+        } else {
+            &self.external_id
+        }
+    }
+
+    /// Email for request logs, hidden like `log_id`
+    pub fn log_email(&self) -> &str {
+        if self.logging_opt_out {
+            OPTED_OUT_LOG_ID
+        } else {
+            &self.email
+        }
     }
 }
 
+/// Parse an Authorization header value and return the bearer token
+pub fn extract_bearer_token(auth_header: &str) -> Result<&str, AuthHeaderError> {
+    let auth_header = auth_header.trim();
+    let (scheme, token) = auth_header
+        .split_once(|c: char| c.is_ascii_whitespace())
+        .unwrap_or((auth_header, ""));
+
+    if !scheme.eq_ignore_ascii_case("bearer") {
+        return Err(AuthHeaderError::UnsupportedScheme);
+    }
+    token_value(token)
+}
+
+/// Trim a raw token and reject empty ones or ones with embedded whitespace
+fn token_value(token: &str) -> Result<&str, AuthHeaderError> {
+    let token = token.trim();
+    if token.is_empty() {
+        return Err(AuthHeaderError::EmptyToken);
+    }
+    if token.contains(|c: char| c.is_ascii_whitespace()) {
+        return Err(AuthHeaderError::Malformed);
+    }
+    Ok(token)
+}
+
+/// The single value of `name`, if sent
+fn single_header<'a>(headers: &'a HeaderMap, name: &str) -> Result<Option<&'a str>, AuthHeaderError> {
+    let mut values = headers.get_all(name).iter();
+    let Some(value) = values.next() else {
+        return Ok(None);
+    };
+    if values.next().is_some() {
+        return Err(AuthHeaderError::MultipleHeaders);
+    }
+    value.to_str().map(Some).map_err(|_| AuthHeaderError::Malformed)
+}
+
+/// Find the request's token
+///
+/// `Authorization` wins over `X-Api-Key` when both are sent; `X-Api-Key` is
+/// only read when `allow_api_key` is set. `Ok(None)` means no credentials.
+pub fn request_token(headers: &HeaderMap, allow_api_key: bool) -> Result<Option<&str>, AuthHeaderError> {
+    if let Some(value) = single_header(headers, header::AUTHORIZATION.as_str())? {
+        return extract_bearer_token(value).map(Some);
+    }
+    if allow_api_key {
+        if let Some(value) = single_header(headers, API_KEY_HEADER)? {
+            return token_value(value).map(Some);
+        }
+    }
+    Ok(None)
+}
+
 /// Hash a JWT for cache key
 pub fn hash_jwt(jwt: &str) -> String {
     let mut hasher = Sha256::new();
@@ -45,41 +178,63 @@ pub fn hash_jwt(jwt: &str) -> String {
 ///
 /// This middleware:
 /// 1. Extracts JWT from Authorization header
-/// 2. Checks JWT cache (Redis) for existing validation
-/// 3. If not cached, validates with Zion API
-/// 4. Caches successful validation
-/// 5. Adds AuthenticatedUser to request extensions
+/// 2. With `ZION_JWKS_URL`, verifies the JWT locally when it can
+/// 3. Otherwise checks JWT cache (Redis) for existing validation
+/// 4. If not cached, validates with Zion API, fetching the limits alongside
+/// 5. Caches successful validation
+/// 6. Adds AuthenticatedUser and the limits (`AuthPath`) to request extensions
 #[instrument(skip_all, fields(path = %request.uri().path()))]
 pub async fn auth_middleware(
     State(state): State<Arc<AppState>>,
     mut request: Request,
     next: Next,
 ) -> Result<Response, AppError> {
-    // Extract Authorization header
-    let auth_header = request
-        .headers()
-        .get(header::AUTHORIZATION)
-        .and_then(|h| h.to_str().ok())
-        .ok_or(AppError::Unauthorized)?;
+    let started = Instant::now();
 
-    // Extract bearer token
-    let token = extract_bearer_token(auth_header).ok_or(AppError::InvalidToken)?;
+    // Extract the bearer token, rejecting malformed headers before Zion sees them
+    let token = match request_token(request.headers(), state.config.server.allow_x_api_key) {
+        Ok(Some(token)) => token,
+        Ok(None) => return Err(AppError::Unauthorized),
+        Err(reason) => {
+            debug!(reason = %reason, "Rejected malformed credentials header");
+            return Err(AppError::AuthHeader(reason));
+        }
+    };
 
     // Hash the token for cache lookup
     let token_hash = hash_jwt(token);
     debug!(token_hash = %token_hash, "Processing authentication request");
 
-    // Validate JWT using the subscription cache
-    let profile = match state.subscription_cache.validate_jwt(token, &token_hash).await {
-        Ok(profile) => profile,
+    // Validate JWT and load the limits using the subscription cache
+    let lookup = authenticate(&state, token, &token_hash);
+    let budget_ms = state.config.zion.auth_path_budget_ms;
+    let result = if budget_ms > 0 {
+        match tokio::time::timeout(Duration::from_millis(budget_ms), lookup).await {
+            Ok(result) => result,
+            Err(_) => {
+                warn!(budget_ms, "Authentication exceeded its budget");
+                record_auth_path("timeout", started.elapsed().as_secs_f64());
+                return Err(AppError::AuthBackendSlow { budget_ms });
+            }
+        }
+    } else {
+        lookup.await
+    };
+    let authentication = match result {
+        Ok(authentication) => authentication,
         Err(e) => {
             warn!(error = %e, "JWT validation failed");
             return Err(e);
         }
     };
+    let profile = authentication.profile;
+    let limits = authentication
+        .limits
+        .inspect_err(|e| debug!(error = %e, "Could not load user limits"))
+        .ok();
 
     // Extract external_id, defaulting to user_id if not set
-    let external_id = profile.external_id.clone().unwrap_or_else(|| profile.id.clone());
+    let external_id = profile.effective_external_id();
 
     // Warn if external_id is empty - this will cause usage tracking to fail
     if external_id.is_empty() {
@@ -96,6 +251,13 @@ pub async fn auth_middleware(
         user_id: profile.id,
         external_id,
         email: profile.email,
+        organization_id: None,
+        logging_opt_out: false,
+        allowed_models: ModelAllowlist::All,
+        synthetic: false,
+        scopes: TokenScopes::resolve(profile.scopes, state.config.server.unscoped_full_access),
+        token_charge: None,
+        upstream_user: None,
     };
 
     debug!(
@@ -105,25 +267,153 @@ pub async fn auth_middleware(
         "User authenticated successfully"
     );
 
-    // Add authenticated user to request extensions
+    // Add authenticated user and limits to request extensions
     request.extensions_mut().insert(user);
+    request.extensions_mut().insert(AuthPath {
+        started,
+        lookup: authentication.lookup,
+        limits,
+    });
 
     Ok(next.run(request).await)
 }
 
+/// Scopes explicitly claimed by the request's token, for routes outside `auth_middleware`
+///
+/// None without valid credentials. Tokens without a `scopes` claim get no
+/// scopes here, whatever `AUTH_UNSCOPED_FULL_ACCESS` says.
+pub async fn claimed_scopes(state: &AppState, headers: &HeaderMap) -> Option<TokenScopes> {
+    let token = request_token(headers, state.config.server.allow_x_api_key).ok()??;
+    match authenticate(state, token, &hash_jwt(token)).await {
+        Ok(authentication) => Some(TokenScopes::resolve(authentication.profile.scopes, false)),
+        Err(e) => {
+            debug!(error = %e, "Token rejected");
+            None
+        }
+    }
+}
+
+/// Verify the token locally if possible, validate it with Zion otherwise
+async fn authenticate(state: &AppState, token: &str, token_hash: &str) -> Result<Authentication, AppError> {
+    if let Some(jwks) = &state.jwks {
+        match jwks.verify(token).await {
+            Verification::Verified(profile) => {
+                let limits = state
+                    .subscription_cache
+                    .get_user_limits(&profile.effective_external_id())
+                    .await;
+                return Ok(Authentication {
+                    profile,
+                    limits,
+                    lookup: AuthLookup::Local,
+                });
+            }
+            Verification::Rejected(reason) => {
+                debug!(reason = %reason, "Token failed local verification");
+                return Err(AppError::InvalidToken);
+            }
+            Verification::Remote(reason) => {
+                debug!(reason = %reason, "Token not verifiable locally, asking Zion");
+            }
+        }
+    }
+    state.subscription_cache.authenticate(token, token_hash).await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use axum::http::HeaderValue;
+
     #[test]
     fn test_extract_bearer_token() {
+        assert_eq!(extract_bearer_token("Bearer abc123"), Ok("abc123"));
+        assert_eq!(extract_bearer_token("bearer abc123"), Ok("abc123"));
+        assert_eq!(extract_bearer_token("BEARER abc123"), Ok("abc123"));
+        assert_eq!(extract_bearer_token("  Bearer   abc123 \t"), Ok("abc123"));
+    }
+
+    #[test]
+    fn test_extract_bearer_token_rejections() {
+        use AuthHeaderError::*;
+        assert_eq!(extract_bearer_token("abc123"), Err(UnsupportedScheme));
+        assert_eq!(extract_bearer_token("Basic dXNlcjpwdw=="), Err(UnsupportedScheme));
+        assert_eq!(extract_bearer_token("Bearerabc123"), Err(UnsupportedScheme));
+        assert_eq!(extract_bearer_token(""), Err(UnsupportedScheme));
+        assert_eq!(extract_bearer_token("Bearer"), Err(EmptyToken));
+        assert_eq!(extract_bearer_token("Bearer    "), Err(EmptyToken));
+        assert_eq!(extract_bearer_token("Bearer abc 123"), Err(Malformed));
+    }
+
+    #[test]
+    fn test_log_id_hides_opted_out_users() {
+        let mut user = AuthenticatedUser {
+            user_id: "user_1".to_string(),
+            external_id: "ext_1".to_string(),
+            email: "user@example.com".to_string(),
+            organization_id: None,
+            logging_opt_out: false,
+            allowed_models: ModelAllowlist::All,
+            synthetic: false,
+            scopes: TokenScopes::All,
+            token_charge: None,
+            upstream_user: None,
+        };
+        assert_eq!(user.log_id(), "ext_1");
+        assert_eq!(user.log_email(), "user@example.com");
+        user.logging_opt_out = true;
+        assert_eq!(user.log_id(), OPTED_OUT_LOG_ID);
+        assert_eq!(user.log_email(), OPTED_OUT_LOG_ID);
+    }
+
+    fn headers(pairs: &[(&'static str, &[u8])]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(*name, HeaderValue::from_bytes(value).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_request_token_header_shapes() {
+        use AuthHeaderError::*;
+        assert_eq!(request_token(&HeaderMap::new(), true), Ok(None));
         assert_eq!(
-            extract_bearer_token("Bearer abc123"),
-            Some("abc123")
+            request_token(&headers(&[("authorization", b"bearer jwt")]), false),
+            Ok(Some("jwt"))
         );
-        assert_eq!(extract_bearer_token("bearer abc123"), None);
-        assert_eq!(extract_bearer_token("abc123"), None);
-        assert_eq!(extract_bearer_token(""), None);
+        assert_eq!(
+            request_token(
+                &headers(&[("authorization", b"Bearer a"), ("authorization", b"Bearer b")]),
+                false
+            ),
+            Err(MultipleHeaders)
+        );
+        assert_eq!(
+            request_token(&headers(&[("authorization", b"Bearer caf\xc3\xa9")]), false),
+            Err(Malformed)
+        );
+    }
+
+    #[test]
+    fn test_request_token_api_key_header() {
+        use AuthHeaderError::*;
+        let api_key = headers(&[("x-api-key", b" jwt ")]);
+        assert_eq!(request_token(&api_key, false), Ok(None));
+        assert_eq!(request_token(&api_key, true), Ok(Some("jwt")));
+        assert_eq!(
+            request_token(&headers(&[("x-api-key", b"")]), true),
+            Err(EmptyToken)
+        );
+        assert_eq!(
+            request_token(&headers(&[("x-api-key", b"a"), ("x-api-key", b"b")]), true),
+            Err(MultipleHeaders)
+        );
+
+        // Authorization takes precedence
+        let both = headers(&[("authorization", b"Bearer from-auth"), ("x-api-key", b"from-key")]);
+        assert_eq!(request_token(&both, true), Ok(Some("from-auth")));
     }
 
     #[test]
@@ -0,0 +1,241 @@
+//! Strict OpenAI compatibility for `/v1` (`COMPAT_MODE=strict`)
+//!
+//! Conformance suites run against `/v1` fail on Sentinel's additions. In
+//! strict mode the `/v1` routes drop them:
+//!
+//! - Response headers: every `X-Sentinel-*` header and Sentinel's own
+//!   `X-RateLimit-*` headers are removed. The provider's rate limit headers
+//!   (`x-ratelimit-remaining-requests`, ...) are OpenAI's and stay.
+//! - Request fields: extensions such as the `models` fallback array are
+//!   refused as OpenAI refuses unknown arguments (400, `Unrecognized request
+//!   argument supplied: models`).
+//! - Error bodies Sentinel builds are reduced to OpenAI's envelope,
+//!   `{"error": {"message", "type", "param", "code"}}`: no `retryable`,
+//!   `retry_after_ms` or `details`. Errors forwarded from the provider
+//!   already have that shape and are left alone.
+//!
+//! `/native` routes are Sentinel's own API and keep everything. The default
+//! `standard` mode changes nothing.
+
+use std::{str::FromStr, sync::Arc};
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderName, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use serde_json::{json, Map, Value};
+
+use crate::{
+    error::AppError, middleware::content_type::ForwardedResponse, proxy::fallback::MODELS_FIELD,
+    AppState,
+};
+
+/// Request fields Sentinel understands on `/v1` but OpenAI doesn't
+const EXTENSION_FIELDS: &[&str] = &[MODELS_FIELD];
+
+/// Rate limit headers OpenAI itself sends (forwarded from the provider)
+const OPENAI_RATE_LIMIT_HEADERS: &[&str] = &[
+    "x-ratelimit-limit-requests",
+    "x-ratelimit-limit-tokens",
+    "x-ratelimit-remaining-requests",
+    "x-ratelimit-remaining-tokens",
+    "x-ratelimit-reset-requests",
+    "x-ratelimit-reset-tokens",
+];
+
+/// How closely `/v1` follows the OpenAI API (`COMPAT_MODE`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CompatMode {
+    /// OpenAI's API plus Sentinel's headers, fields and error details
+    #[default]
+    Standard,
+    /// OpenAI's API only
+    Strict,
+}
+
+impl FromStr for CompatMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "standard" => Ok(CompatMode::Standard),
+            "strict" => Ok(CompatMode::Strict),
+            other => Err(format!(
+                "unknown compat mode '{}' (expected standard or strict)",
+                other
+            )),
+        }
+    }
+}
+
+/// Refuse Sentinel extension fields in strict mode, as OpenAI refuses unknown arguments
+pub fn reject_extensions(
+    mode: CompatMode,
+    extra: &Option<Map<String, Value>>,
+) -> Result<(), AppError> {
+    if mode != CompatMode::Strict {
+        return Ok(());
+    }
+    match EXTENSION_FIELDS
+        .iter()
+        .find(|field| extra.as_ref().is_some_and(|extra| extra.contains_key(**field)))
+    {
+        Some(field) => Err(AppError::BadRequest(format!(
+            "Unrecognized request argument supplied: {}",
+            field
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Strip Sentinel's headers and error details from `/v1` responses in strict mode
+pub async fn compat_middleware(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let mut response = next.run(request).await;
+    if state.config.server.compat_mode != CompatMode::Strict {
+        return response;
+    }
+
+    remove_extension_headers(response.headers_mut());
+    let status = response.status();
+    if !(status.is_client_error() || status.is_server_error())
+        || response.extensions().get::<ForwardedResponse>().is_some()
+        || !is_json(response.headers())
+    {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = axum::body::to_bytes(body, usize::MAX).await else {
+        return Response::from_parts(parts, Body::empty());
+    };
+    let body = match serde_json::from_slice::<Value>(&bytes) {
+        Ok(value) => match openai_error(status, &value) {
+            Some(error) => {
+                parts.headers.remove(header::CONTENT_LENGTH);
+                Body::from(error.to_string())
+            }
+            None => Body::from(bytes),
+        },
+        Err(_) => Body::from(bytes),
+    };
+    Response::from_parts(parts, body)
+}
+
+/// Remove `X-Sentinel-*` and Sentinel's `X-RateLimit-*` headers
+fn remove_extension_headers(headers: &mut HeaderMap) {
+    let extensions: Vec<HeaderName> = headers
+        .keys()
+        .filter(|name| is_extension_header(name.as_str()))
+        .cloned()
+        .collect();
+    for name in extensions {
+        headers.remove(name);
+    }
+}
+
+/// Whether a (lowercase) header name is one of Sentinel's
+fn is_extension_header(name: &str) -> bool {
+    name.starts_with("x-sentinel-")
+        || (name.starts_with("x-ratelimit-") && !OPENAI_RATE_LIMIT_HEADERS.contains(&name))
+}
+
+fn is_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value
+                .split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .eq_ignore_ascii_case("application/json")
+        })
+}
+
+/// A Sentinel error body in OpenAI's envelope (None: not a Sentinel error body)
+fn openai_error(status: StatusCode, body: &Value) -> Option<Value> {
+    let error = body.get("error")?;
+    let message = error.get("message")?.as_str()?;
+    let code = error.get("code")?.as_str()?;
+    Some(json!({
+        "error": {
+            "message": message,
+            "type": error_type(status),
+            "param": null,
+            "code": code,
+        }
+    }))
+}
+
+/// OpenAI's error `type` for a status
+fn error_type(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::UNAUTHORIZED => "authentication_error",
+        StatusCode::FORBIDDEN => "permission_error",
+        StatusCode::TOO_MANY_REQUESTS => "rate_limit_error",
+        status if status.is_server_error() => "server_error",
+        _ => "invalid_request_error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extension_headers() {
+        assert!(is_extension_header("x-sentinel-model"));
+        assert!(is_extension_header("x-ratelimit-limit"));
+        assert!(is_extension_header("x-ratelimit-org-remaining"));
+        assert!(is_extension_header("x-ratelimit-tokens-reset"));
+        assert!(!is_extension_header("x-ratelimit-remaining-requests"));
+        assert!(!is_extension_header("x-request-id"));
+        assert!(!is_extension_header("retry-after"));
+    }
+
+    #[test]
+    fn test_openai_error_envelope() {
+        let body = json!({
+            "error": {
+                "code": "RATE_LIMIT_EXCEEDED",
+                "message": "Rate limit exceeded",
+                "retryable": true,
+                "retry_after_ms": 1000,
+                "details": {"limit": 10, "used": 10}
+            }
+        });
+        let error = openai_error(StatusCode::TOO_MANY_REQUESTS, &body).unwrap();
+        assert_eq!(
+            error,
+            json!({
+                "error": {
+                    "message": "Rate limit exceeded",
+                    "type": "rate_limit_error",
+                    "param": null,
+                    "code": "RATE_LIMIT_EXCEEDED"
+                }
+            })
+        );
+        assert!(openai_error(StatusCode::BAD_REQUEST, &json!({"detail": "nope"})).is_none());
+    }
+
+    #[test]
+    fn test_reject_extensions() {
+        let mut extra = Map::new();
+        extra.insert(MODELS_FIELD.to_string(), json!(["gpt-4o"]));
+        let extra = Some(extra);
+
+        assert!(reject_extensions(CompatMode::Standard, &extra).is_ok());
+        assert!(reject_extensions(CompatMode::Strict, &None).is_ok());
+        let error = reject_extensions(CompatMode::Strict, &extra).unwrap_err();
+        assert_eq!(error.to_string(), "Bad request: Unrecognized request argument supplied: models");
+    }
+}
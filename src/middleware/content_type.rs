@@ -0,0 +1,130 @@
+//! Response `Content-Type` normalization
+//!
+//! Handlers build JSON through axum's `Json` and event streams through
+//! [`sse_response`](crate::streaming::sse_response); this layer gives every
+//! such response the same headers, whichever code path produced it:
+//!
+//! - JSON: `application/json`, or `application/json; charset=utf-8` with
+//!   `JSON_RESPONSE_CHARSET` (clients disagree on which one they accept)
+//! - SSE: `text/event-stream; charset=utf-8` plus `Cache-Control: no-cache`,
+//!   `Connection: keep-alive` and `X-Accel-Buffering: no`
+//!
+//! Pass-through responses are the provider's own and are left as they came.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::{streaming::insert_sse_headers, AppState};
+
+/// `Content-Type` of JSON responses by default
+pub const JSON_CONTENT_TYPE: &str = "application/json";
+
+/// `Content-Type` of JSON responses with `JSON_RESPONSE_CHARSET`
+pub const JSON_CHARSET_CONTENT_TYPE: &str = "application/json; charset=utf-8";
+
+/// Response extension marking a provider response forwarded as is
+#[derive(Debug, Clone, Copy)]
+pub struct ForwardedResponse;
+
+/// Normalize the `Content-Type` (and SSE headers) of responses Sentinel builds
+pub async fn content_type_middleware(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let mut response = next.run(request).await;
+    if response.extensions().get::<ForwardedResponse>().is_none() {
+        normalize(
+            response.headers_mut(),
+            state.config.server.json_response_charset,
+        );
+    }
+    response
+}
+
+/// Rewrite JSON and SSE content types to the canonical form
+///
+/// Other content types (Prometheus text, MessagePack, ...) are untouched.
+pub fn normalize(headers: &mut HeaderMap, json_charset: bool) {
+    let Some(essence) = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| {
+            value
+                .split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_ascii_lowercase()
+        })
+    else {
+        return;
+    };
+
+    match essence.as_str() {
+        "application/json" => {
+            let content_type = if json_charset {
+                JSON_CHARSET_CONTENT_TYPE
+            } else {
+                JSON_CONTENT_TYPE
+            };
+            headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+        }
+        "text/event-stream" => insert_sse_headers(headers),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::streaming::SSE_CONTENT_TYPE;
+
+    fn headers(content_type: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, content_type.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_json_charset_follows_config() {
+        for sent in ["application/json", "Application/JSON; charset=UTF-8"] {
+            let mut plain = headers(sent);
+            normalize(&mut plain, false);
+            assert_eq!(plain[header::CONTENT_TYPE], JSON_CONTENT_TYPE);
+
+            let mut charset = headers(sent);
+            normalize(&mut charset, true);
+            assert_eq!(charset[header::CONTENT_TYPE], JSON_CHARSET_CONTENT_TYPE);
+        }
+    }
+
+    #[test]
+    fn test_sse_gets_full_header_set() {
+        let mut sse = headers("text/event-stream");
+        normalize(&mut sse, false);
+        assert_eq!(sse[header::CONTENT_TYPE], SSE_CONTENT_TYPE);
+        assert_eq!(sse[header::CACHE_CONTROL], "no-cache");
+        assert_eq!(sse[header::CONNECTION], "keep-alive");
+        assert_eq!(sse["x-accel-buffering"], "no");
+    }
+
+    #[test]
+    fn test_other_content_types_untouched() {
+        for sent in ["text/plain; version=0.0.4", "application/msgpack"] {
+            let mut other = headers(sent);
+            normalize(&mut other, true);
+            assert_eq!(other[header::CONTENT_TYPE], sent);
+            assert_eq!(other.len(), 1);
+        }
+        let mut none = HeaderMap::new();
+        normalize(&mut none, true);
+        assert!(none.is_empty());
+    }
+}
@@ -0,0 +1,178 @@
+//! Request body decompression
+//!
+//! Clients may gzip large request bodies (`Content-Encoding: gzip`). The body
+//! is inflated right after authentication, before any other handler or
+//! middleware reads it, so unauthenticated requests are never inflated. The
+//! `Content-Encoding` header is removed so handlers, the pass-through proxy
+//! and the upstream all see plain JSON. Inflation stops as soon as the output
+//! passes `MAX_REQUEST_BODY_BYTES`, so a small body that expands enormously
+//! is rejected with a 413 without ever being held in full.
+//!
+//! Other encodings are rejected with a 415; `identity` passes through.
+
+use std::io::Read;
+use std::sync::Arc;
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use flate2::read::MultiGzDecoder;
+use tracing::{debug, warn};
+
+use crate::{error::AppError, routes::body::JsonBodyRejection, AppState};
+
+/// Error code for a body that expands past the size limit
+pub const REQUEST_TOO_LARGE_CODE: &str = "request_too_large";
+
+/// Error code for a `Content-Encoding` other than gzip or identity
+pub const UNSUPPORTED_ENCODING_CODE: &str = "unsupported_encoding";
+
+/// Error code for a body that isn't valid gzip
+pub const INVALID_ENCODING_CODE: &str = "invalid_encoding";
+
+/// Why a compressed body couldn't be inflated
+#[derive(Debug, PartialEq)]
+enum InflateError {
+    TooLarge,
+    Corrupt(String),
+}
+
+/// Inflate a gzip body, giving up once the output passes `limit` bytes
+fn gunzip(compressed: &[u8], limit: usize) -> Result<Vec<u8>, InflateError> {
+    let mut inflated = Vec::new();
+    MultiGzDecoder::new(compressed)
+        .take(limit as u64 + 1)
+        .read_to_end(&mut inflated)
+        .map_err(|e| InflateError::Corrupt(e.to_string()))?;
+    if inflated.len() > limit {
+        return Err(InflateError::TooLarge);
+    }
+    Ok(inflated)
+}
+
+fn rejection(status: StatusCode, code: &'static str, message: String) -> Response {
+    JsonBodyRejection {
+        status,
+        code,
+        message,
+        param: None,
+    }
+    .into_response()
+}
+
+fn too_large(limit: usize) -> Response {
+    rejection(
+        StatusCode::PAYLOAD_TOO_LARGE,
+        REQUEST_TOO_LARGE_CODE,
+        format!("Request body exceeds {} bytes once decompressed", limit),
+    )
+}
+
+/// Request decompression middleware (applied to the authenticated routers, after auth)
+pub async fn decompression_middleware(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(encoding) = request.headers().get(header::CONTENT_ENCODING) else {
+        return next.run(request).await;
+    };
+    let encoding = encoding
+        .to_str()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    match encoding.as_str() {
+        "identity" => return next.run(request).await,
+        "gzip" | "x-gzip" => {}
+        _ => {
+            return rejection(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                UNSUPPORTED_ENCODING_CODE,
+                format!(
+                    "Unsupported Content-Encoding '{}'; send gzip or an uncompressed body",
+                    encoding
+                ),
+            );
+        }
+    }
+
+    let limit = state.config.server.max_request_body_bytes;
+    let (mut parts, body) = request.into_parts();
+    let Ok(compressed) = axum::body::to_bytes(body, limit).await else {
+        return too_large(limit);
+    };
+
+    let inflated = match tokio::task::spawn_blocking(move || gunzip(&compressed, limit)).await {
+        Ok(Ok(inflated)) => inflated,
+        Ok(Err(InflateError::TooLarge)) => {
+            warn!(path = %parts.uri.path(), limit, "Rejecting request body that inflates past the limit");
+            return too_large(limit);
+        }
+        Ok(Err(InflateError::Corrupt(e))) => {
+            return rejection(
+                StatusCode::BAD_REQUEST,
+                INVALID_ENCODING_CODE,
+                format!("Failed to decompress gzip request body: {}", e),
+            );
+        }
+        Err(e) => {
+            return AppError::Internal(anyhow::anyhow!("Request decompression failed: {}", e))
+                .into_response();
+        }
+    };
+    debug!(
+        inflated_bytes = inflated.len(),
+        "Decompressed gzip request body"
+    );
+
+    parts.headers.remove(header::CONTENT_ENCODING);
+    parts
+        .headers
+        .insert(header::CONTENT_LENGTH, HeaderValue::from(inflated.len()));
+    next.run(Request::from_parts(
+        parts,
+        Body::from(Bytes::from(inflated)),
+    ))
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_gunzip_round_trip() {
+        let body = br#"{"model": "text-embedding-3-small", "input": "hello"}"#;
+        assert_eq!(gunzip(&gzip(body), 1024).unwrap(), body.to_vec());
+    }
+
+    #[test]
+    fn test_gunzip_stops_at_limit() {
+        let compressed = gzip(&vec![b'a'; 1_000_000]);
+        assert!(compressed.len() < 10_000);
+        assert_eq!(gunzip(&compressed, 4096), Err(InflateError::TooLarge));
+        // Exactly at the limit is fine
+        assert_eq!(gunzip(&gzip(&[b'a'; 4096]), 4096).unwrap().len(), 4096);
+    }
+
+    #[test]
+    fn test_gunzip_rejects_corrupt_input() {
+        assert!(matches!(
+            gunzip(b"not gzip", 1024),
+            Err(InflateError::Corrupt(_))
+        ));
+    }
+}
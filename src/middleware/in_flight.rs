@@ -0,0 +1,269 @@
+//! In-flight request registry
+//!
+//! Every admitted `/v1` and `/native` request is registered under a fresh
+//! request ID for as long as it runs, so `GET /admin/snapshot` can show what
+//! the replica is doing right now. Streaming responses stay registered until
+//! their body finishes or the client disconnects: the guard that removes the
+//! entry travels with the body. A registration is one map insert and one
+//! removal, so the overhead per request is negligible.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::{
+    body::Body,
+    extract::{MatchedPath, OriginalUri, Request, State},
+    http::header,
+    middleware::Next,
+    response::Response,
+};
+use dashmap::DashMap;
+use futures::StreamExt;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    clock::{system_clock, SharedClock},
+    middleware::auth::AuthenticatedUser,
+    usage::ledger::hash_user,
+    AppState,
+};
+
+/// Requests listed under `slowest` in a snapshot
+pub const SLOWEST_LIMIT: usize = 5;
+
+#[derive(Debug)]
+struct Entry {
+    endpoint: String,
+    user_hash: Option<String>,
+    started: Instant,
+    /// Set once the response turned out to be an event stream
+    streaming: AtomicBool,
+}
+
+/// A request in flight, as listed in a snapshot
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct InFlightRequest {
+    pub request_id: String,
+    /// Hashed user email (None for users who opted out of logging)
+    pub user_hash: Option<String>,
+    pub endpoint: String,
+    pub streaming: bool,
+    pub elapsed_ms: u64,
+}
+
+/// Requests in flight on this replica
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct InFlightSnapshot {
+    pub total: usize,
+    /// Requests per endpoint (route pattern, or path for pass-through)
+    pub by_endpoint: BTreeMap<String, usize>,
+    /// Streaming responses still being sent, oldest first
+    pub streams: Vec<InFlightRequest>,
+    /// Longest-running requests, slowest first
+    pub slowest: Vec<InFlightRequest>,
+}
+
+/// Registry of the requests currently being handled
+#[derive(Debug)]
+pub struct InFlightRegistry {
+    requests: DashMap<String, Entry>,
+    clock: SharedClock,
+}
+
+impl Default for InFlightRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InFlightRegistry {
+    pub fn new() -> Self {
+        Self {
+            requests: DashMap::new(),
+            clock: system_clock(),
+        }
+    }
+
+    /// Use the given clock for elapsed times
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Register a request; it is removed when the guard is dropped
+    pub fn register(
+        self: &Arc<Self>,
+        endpoint: String,
+        user_hash: Option<String>,
+    ) -> InFlightGuard {
+        let request_id = Uuid::new_v4().to_string();
+        self.requests.insert(
+            request_id.clone(),
+            Entry {
+                endpoint,
+                user_hash,
+                started: self.clock.instant_now(),
+                streaming: AtomicBool::new(false),
+            },
+        );
+        InFlightGuard {
+            registry: self.clone(),
+            request_id,
+        }
+    }
+
+    /// Number of requests in flight
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    /// Current requests, grouped by endpoint, with streams and the slowest listed
+    pub fn snapshot(&self) -> InFlightSnapshot {
+        let now = self.clock.instant_now();
+        let mut by_endpoint = BTreeMap::new();
+        let mut requests: Vec<InFlightRequest> = self
+            .requests
+            .iter()
+            .map(|entry| {
+                *by_endpoint.entry(entry.endpoint.clone()).or_insert(0) += 1;
+                InFlightRequest {
+                    request_id: entry.key().clone(),
+                    user_hash: entry.user_hash.clone(),
+                    endpoint: entry.endpoint.clone(),
+                    streaming: entry.streaming.load(Ordering::Relaxed),
+                    elapsed_ms: millis(now.saturating_duration_since(entry.started)),
+                }
+            })
+            .collect();
+        requests.sort_by_key(|request| std::cmp::Reverse(request.elapsed_ms));
+
+        InFlightSnapshot {
+            total: requests.len(),
+            by_endpoint,
+            streams: requests.iter().filter(|r| r.streaming).cloned().collect(),
+            slowest: requests.into_iter().take(SLOWEST_LIMIT).collect(),
+        }
+    }
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration.as_millis().try_into().unwrap_or(u64::MAX)
+}
+
+/// Keeps a request registered until dropped
+#[derive(Debug)]
+pub struct InFlightGuard {
+    registry: Arc<InFlightRegistry>,
+    request_id: String,
+}
+
+impl InFlightGuard {
+    /// Mark the request as a streaming response
+    pub fn set_streaming(&self) {
+        if let Some(entry) = self.registry.requests.get(&self.request_id) {
+            entry.streaming.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.registry.requests.remove(&self.request_id);
+    }
+}
+
+/// Register the request for its lifetime, including a streamed body
+///
+/// Runs after authentication and rate limiting, so only admitted requests
+/// are listed.
+pub async fn in_flight_middleware(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let endpoint = match request.extensions().get::<MatchedPath>() {
+        Some(matched) => matched.as_str().to_string(),
+        None => request
+            .extensions()
+            .get::<OriginalUri>()
+            .map_or_else(|| request.uri().path(), |uri| uri.path())
+            .to_string(),
+    };
+    let user_hash = request
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .filter(|user| !user.logging_opt_out)
+        .map(|user| hash_user(&user.email));
+    let guard = state.in_flight.register(endpoint, user_hash);
+
+    let response = next.run(request).await;
+    let streaming = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/event-stream"));
+    if !streaming {
+        return response;
+    }
+
+    guard.set_streaming();
+    response.map(|body| {
+        Body::from_stream(body.into_data_stream().map(move |chunk| {
+            let _registered = &guard;
+            chunk
+        }))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::TestClock;
+
+    #[test]
+    fn test_snapshot_groups_and_orders_requests() {
+        let clock = TestClock::new(1_700_000_000);
+        let registry = Arc::new(InFlightRegistry::new().with_clock(clock.clone()));
+
+        let oldest = registry.register("/v1/chat/completions".to_string(), Some("abc".to_string()));
+        oldest.set_streaming();
+        clock.advance(Duration::from_millis(300));
+        let _chat = registry.register("/v1/chat/completions".to_string(), None);
+        clock.advance(Duration::from_millis(200));
+        let embeddings = registry.register("/v1/embeddings".to_string(), None);
+
+        let snapshot = registry.snapshot();
+        assert_eq!(snapshot.total, 3);
+        assert_eq!(snapshot.by_endpoint["/v1/chat/completions"], 2);
+        assert_eq!(snapshot.by_endpoint["/v1/embeddings"], 1);
+        let elapsed: Vec<u64> = snapshot.slowest.iter().map(|r| r.elapsed_ms).collect();
+        assert_eq!(elapsed, vec![500, 200, 0]);
+        assert_eq!(snapshot.streams.len(), 1);
+        assert_eq!(snapshot.streams[0].user_hash.as_deref(), Some("abc"));
+
+        drop(embeddings);
+        drop(oldest);
+        let snapshot = registry.snapshot();
+        assert_eq!(snapshot.total, 1);
+        assert!(snapshot.streams.is_empty());
+    }
+
+    #[test]
+    fn test_slowest_is_capped() {
+        let registry = Arc::new(InFlightRegistry::new());
+        let _guards: Vec<_> = (0..SLOWEST_LIMIT + 3)
+            .map(|_| registry.register("/v1/models".to_string(), None))
+            .collect();
+
+        let snapshot = registry.snapshot();
+        assert_eq!(snapshot.total, SLOWEST_LIMIT + 3);
+        assert_eq!(snapshot.slowest.len(), SLOWEST_LIMIT);
+    }
+}
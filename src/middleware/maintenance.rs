@@ -0,0 +1,305 @@
+//! Maintenance mode
+//!
+//! During planned upstream maintenance the model endpoints return a branded
+//! 503 (`error.code = "maintenance"`) with `Retry-After`, while the health
+//! endpoints keep the instance in rotation. `MAINTENANCE_MODE` sets the state
+//! at startup; `PUT /admin/maintenance` stores an override in Redis so every
+//! replica picks it up. Replicas re-read the flag at most once per
+//! [`LOCAL_CACHE_TTL`], so toggling needs no restart.
+//!
+//! The check happens when a request arrives; requests already in flight
+//! (including open streams) finish normally.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::{debug, info, warn};
+
+use crate::{
+    cache::redis::{keys, RedisCache},
+    config::Config,
+    error::{AppResult, ErrorBody, ErrorResponse, RetryAfter},
+    streaming::sse_response,
+    AppState,
+};
+
+#[cfg(any(test, feature = "test-utils"))]
+use crate::cache::InMemoryCache;
+
+/// Error code returned while maintenance mode is on
+pub const MAINTENANCE_ERROR_CODE: &str = "maintenance";
+
+/// How long a replica trusts its last read of the Redis flag
+pub const LOCAL_CACHE_TTL: Duration = Duration::from_secs(2);
+
+/// How long a runtime override is kept (a forgotten flag lapses back to the env setting)
+const FLAG_TTL_SECONDS: u64 = 7 * 24 * 60 * 60;
+
+/// Runtime override stored in Redis
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MaintenanceFlag {
+    pub enabled: bool,
+    /// Replaces `MAINTENANCE_MESSAGE` while set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Replaces `MAINTENANCE_RETRY_AFTER_SECONDS` while set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_after_seconds: Option<u64>,
+}
+
+/// Effective maintenance state
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct MaintenanceStatus {
+    pub enabled: bool,
+    pub message: String,
+    pub retry_after_seconds: u64,
+    /// `config` (MAINTENANCE_MODE) or `override` (set via the admin endpoint)
+    pub source: &'static str,
+}
+
+/// Cache backend abstraction for MaintenanceMode
+enum MaintenanceBackend {
+    Redis(Arc<RedisCache>),
+    #[cfg(any(test, feature = "test-utils"))]
+    InMemory(Arc<InMemoryCache>),
+}
+
+impl MaintenanceBackend {
+    async fn get(&self) -> AppResult<Option<MaintenanceFlag>> {
+        match self {
+            MaintenanceBackend::Redis(cache) => cache.get(&keys::maintenance()).await,
+            #[cfg(any(test, feature = "test-utils"))]
+            MaintenanceBackend::InMemory(cache) => cache.get(&keys::maintenance()).await,
+        }
+    }
+
+    async fn set(&self, flag: &MaintenanceFlag) -> AppResult<()> {
+        match self {
+            MaintenanceBackend::Redis(cache) => {
+                cache.set_with_ttl(&keys::maintenance(), flag, FLAG_TTL_SECONDS).await
+            }
+            #[cfg(any(test, feature = "test-utils"))]
+            MaintenanceBackend::InMemory(cache) => {
+                cache.set_with_ttl(&keys::maintenance(), flag, FLAG_TTL_SECONDS).await
+            }
+        }
+    }
+
+    async fn delete(&self) -> AppResult<()> {
+        match self {
+            MaintenanceBackend::Redis(cache) => cache.delete(&keys::maintenance()).await,
+            #[cfg(any(test, feature = "test-utils"))]
+            MaintenanceBackend::InMemory(cache) => cache.delete(&keys::maintenance()).await,
+        }
+    }
+}
+
+/// Maintenance mode state shared by all handlers
+pub struct MaintenanceMode {
+    cache: MaintenanceBackend,
+    default_enabled: bool,
+    default_message: String,
+    default_retry_after_seconds: u64,
+    local: Mutex<Option<(Instant, MaintenanceStatus)>>,
+}
+
+impl MaintenanceMode {
+    /// Create maintenance mode state with Redis backend
+    pub fn new(cache: Arc<RedisCache>, config: &Config) -> Self {
+        Self::with_backend(MaintenanceBackend::Redis(cache), config)
+    }
+
+    /// Create maintenance mode state with in-memory backend for testing
+    #[cfg(any(test, feature = "test-utils"))]
+    pub fn new_for_testing(cache: Arc<InMemoryCache>, config: &Config) -> Self {
+        Self::with_backend(MaintenanceBackend::InMemory(cache), config)
+    }
+
+    fn with_backend(cache: MaintenanceBackend, config: &Config) -> Self {
+        Self {
+            cache,
+            default_enabled: config.server.maintenance_mode,
+            default_message: config.server.maintenance_message.clone(),
+            default_retry_after_seconds: config.server.maintenance_retry_after_seconds,
+            local: Mutex::new(None),
+        }
+    }
+
+    fn resolve(&self, flag: Option<MaintenanceFlag>) -> MaintenanceStatus {
+        match flag {
+            Some(flag) => MaintenanceStatus {
+                enabled: flag.enabled,
+                message: flag.message.unwrap_or_else(|| self.default_message.clone()),
+                retry_after_seconds: flag
+                    .retry_after_seconds
+                    .unwrap_or(self.default_retry_after_seconds),
+                source: "override",
+            },
+            None => MaintenanceStatus {
+                enabled: self.default_enabled,
+                message: self.default_message.clone(),
+                retry_after_seconds: self.default_retry_after_seconds,
+                source: "config",
+            },
+        }
+    }
+
+    fn remember(&self, status: &MaintenanceStatus) {
+        *self.local.lock().unwrap() = Some((Instant::now(), status.clone()));
+    }
+
+    /// Current maintenance state, re-reading Redis at most once per [`LOCAL_CACHE_TTL`]
+    ///
+    /// When Redis can't be read the startup setting applies.
+    pub async fn status(&self) -> MaintenanceStatus {
+        if let Some((read_at, status)) = self.local.lock().unwrap().as_ref() {
+            if read_at.elapsed() < LOCAL_CACHE_TTL {
+                return status.clone();
+            }
+        }
+
+        let flag = match self.cache.get().await {
+            Ok(flag) => flag,
+            Err(e) => {
+                warn!(error = %e, "Failed to read maintenance flag, using MAINTENANCE_MODE");
+                None
+            }
+        };
+        let status = self.resolve(flag);
+        self.remember(&status);
+        status
+    }
+
+    /// Store a runtime override for all replicas
+    pub async fn set_override(&self, flag: MaintenanceFlag) -> AppResult<MaintenanceStatus> {
+        self.cache.set(&flag).await?;
+        let status = self.resolve(Some(flag));
+        self.remember(&status);
+        info!(enabled = status.enabled, "Maintenance mode override set");
+        Ok(status)
+    }
+
+    /// Remove the runtime override, reverting to `MAINTENANCE_MODE`
+    pub async fn clear_override(&self) -> AppResult<MaintenanceStatus> {
+        self.cache.delete().await?;
+        let status = self.resolve(None);
+        self.remember(&status);
+        info!(enabled = status.enabled, "Maintenance mode override cleared");
+        Ok(status)
+    }
+}
+
+/// Whether a JSON request body asks for a streaming response
+fn wants_stream(body: &[u8]) -> bool {
+    serde_json::from_slice::<serde_json::Value>(body)
+        .ok()
+        .and_then(|value| value.get("stream").and_then(|stream| stream.as_bool()))
+        .unwrap_or(false)
+}
+
+/// Build the 503 returned while in maintenance
+///
+/// Streaming requests get a single SSE error event instead of a JSON body.
+pub fn maintenance_response(status: &MaintenanceStatus, streaming: bool) -> Response {
+    let retry_after = RetryAfter::seconds(status.retry_after_seconds);
+    let mut response = if streaming {
+        let event = json!({
+            "error": {
+                "message": status.message,
+                "type": "service_unavailable",
+                "code": MAINTENANCE_ERROR_CODE,
+                "retryable": true,
+                "retry_after_ms": retry_after.as_millis(),
+            }
+        });
+        sse_response(StatusCode::SERVICE_UNAVAILABLE)
+            .body(Body::from(format!("data: {}\n\n", event)))
+            .unwrap()
+    } else {
+        let error_response = ErrorResponse {
+            error: ErrorBody {
+                code: MAINTENANCE_ERROR_CODE.to_string(),
+                message: status.message.clone(),
+                retryable: true,
+                retry_after_ms: Some(retry_after.as_millis()),
+                details: None,
+            },
+        };
+        (StatusCode::SERVICE_UNAVAILABLE, Json(error_response)).into_response()
+    };
+
+    retry_after.apply(response.headers_mut());
+    response
+}
+
+/// Maintenance middleware for model endpoints
+///
+/// Passes requests through unless maintenance mode is on; the body is only
+/// read (to detect `stream: true`) when the request is being rejected.
+pub async fn maintenance_middleware(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let status = state.maintenance.status().await;
+    if !status.enabled {
+        return next.run(request).await;
+    }
+
+    let path = request.uri().path().to_string();
+    let body = axum::body::to_bytes(request.into_body(), usize::MAX)
+        .await
+        .unwrap_or_default();
+    debug!(path = %path, "Rejecting request during maintenance");
+
+    maintenance_response(&status, wants_stream(&body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+
+    fn mode(enabled: bool) -> MaintenanceMode {
+        let mut config = test_config("http://zion.invalid", "http://openai.invalid/v1");
+        config.server.maintenance_mode = enabled;
+        MaintenanceMode::new_for_testing(Arc::new(InMemoryCache::new(60)), &config)
+    }
+
+    #[tokio::test]
+    async fn test_override_takes_precedence_over_config() {
+        let mode = mode(true);
+        assert!(mode.status().await.enabled);
+        assert_eq!(mode.status().await.source, "config");
+
+        let status = mode
+            .set_override(MaintenanceFlag {
+                enabled: false,
+                message: None,
+                retry_after_seconds: None,
+            })
+            .await
+            .unwrap();
+        assert!(!status.enabled);
+        assert_eq!(mode.status().await.source, "override");
+
+        assert!(mode.clear_override().await.unwrap().enabled);
+    }
+
+    #[test]
+    fn test_wants_stream() {
+        assert!(wants_stream(br#"{"stream": true}"#));
+        assert!(!wants_stream(br#"{"stream": false}"#));
+        assert!(!wants_stream(b"not json"));
+    }
+}
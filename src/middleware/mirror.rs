@@ -0,0 +1,326 @@
+//! Request mirroring to a staging Sentinel
+//!
+//! Before upgrading Sentinel itself, a sample of production traffic can be
+//! replayed against a staging instance (`MIRROR_URL`) to compare status codes.
+//! Sampled requests (`MIRROR_SAMPLE_RATE`) are copied after authentication
+//! with their headers and body, the client's credentials replaced by
+//! `MIRROR_AUTH_TOKEN`, and sent in the background once the primary response
+//! is ready. Streaming requests are mirrored as non-streaming. A status that
+//! differs from the primary response is logged and counted in
+//! `sentinel_mirror_requests_total{result="mismatch"}`.
+//!
+//! Mirroring never delays the primary response beyond buffering the body of
+//! sampled requests, and the mirror's usage is tracked by the staging
+//! instance, not here. At most `MIRROR_MAX_CONCURRENCY` mirrored requests are
+//! in flight; samples beyond that are dropped rather than queued. Users who
+//! opted out of request logging (Zion `loggingOptOut`) are never mirrored.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{OriginalUri, Request, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode, Uri},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde_json::Value;
+use tokio::sync::Semaphore;
+use tracing::{debug, warn};
+
+use crate::{
+    config::ServerConfig,
+    error::AppError,
+    middleware::auth::{AuthenticatedUser, API_KEY_HEADER},
+    proxy::headers::is_hop_by_hop_header,
+    routes::metrics::record_mirror_request,
+    AppState,
+};
+
+/// Header marking requests sent by the mirror
+pub const MIRROR_HEADER: &str = "x-sentinel-mirror";
+
+/// Timeout for a mirrored request
+const MIRROR_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Copy of a sampled request, ready to send to the mirror
+#[derive(Debug)]
+struct MirroredRequest {
+    method: Method,
+    url: String,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+/// Sends sampled requests to the staging Sentinel
+pub struct RequestMirror {
+    base_url: Option<String>,
+    auth_token: String,
+    sample_rate: f64,
+    client: reqwest::Client,
+    permits: Arc<Semaphore>,
+}
+
+impl RequestMirror {
+    /// Create a mirror from the `MIRROR_*` settings
+    ///
+    /// Mirroring stays off without both `MIRROR_URL` and `MIRROR_AUTH_TOKEN`.
+    pub fn new(config: &ServerConfig) -> Self {
+        let base_url = match (&config.mirror_url, &config.mirror_auth_token) {
+            (Some(url), Some(_)) => Some(url.trim_end_matches('/').to_string()),
+            (Some(_), None) => {
+                warn!("MIRROR_URL is set without MIRROR_AUTH_TOKEN; request mirroring disabled");
+                None
+            }
+            _ => None,
+        };
+        let client = reqwest::Client::builder()
+            .timeout(MIRROR_TIMEOUT)
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap_or_default();
+
+        Self {
+            base_url,
+            auth_token: config.mirror_auth_token.clone().unwrap_or_default(),
+            sample_rate: config.mirror_sample_rate.clamp(0.0, 1.0),
+            client,
+            permits: Arc::new(Semaphore::new(config.mirror_max_concurrency.max(1))),
+        }
+    }
+
+    /// Whether a mirror is configured
+    pub fn is_enabled(&self) -> bool {
+        self.base_url.is_some() && self.sample_rate > 0.0
+    }
+
+    fn sampled(&self) -> bool {
+        self.sample_rate >= 1.0 || rand::random::<f64>() < self.sample_rate
+    }
+
+    /// Build the mirror's copy of a request
+    fn copy(
+        &self,
+        method: &Method,
+        uri: &Uri,
+        headers: &HeaderMap,
+        body: &Bytes,
+    ) -> Option<MirroredRequest> {
+        let base_url = self.base_url.as_ref()?;
+        let mut mirrored_headers = HeaderMap::new();
+        for (name, value) in headers {
+            if !is_forwarded(name) {
+                continue;
+            }
+            mirrored_headers.append(name.clone(), value.clone());
+        }
+        let authorization = HeaderValue::from_str(&format!("Bearer {}", self.auth_token)).ok()?;
+        mirrored_headers.insert(header::AUTHORIZATION, authorization);
+        mirrored_headers.insert(
+            HeaderName::from_static(MIRROR_HEADER),
+            HeaderValue::from_static("1"),
+        );
+
+        Some(MirroredRequest {
+            method: method.clone(),
+            url: format!("{}{}", base_url, non_streaming_path(uri)),
+            headers: mirrored_headers,
+            body: non_streaming_body(body),
+        })
+    }
+
+    /// Send a copy in the background and compare its status with the primary's
+    fn spawn(self: &Arc<Self>, request: MirroredRequest, primary: StatusCode) {
+        let Ok(permit) = self.permits.clone().try_acquire_owned() else {
+            debug!(url = %request.url, "Mirror at capacity; dropping sample");
+            record_mirror_request("dropped");
+            return;
+        };
+        let mirror = self.clone();
+        tokio::spawn(async move {
+            let _permit = permit;
+            let path = request.url.clone();
+            let result = mirror
+                .client
+                .request(request.method.clone(), &request.url)
+                .headers(request.headers)
+                .body(request.body)
+                .send()
+                .await;
+            match result {
+                Ok(response) if response.status().as_u16() == primary.as_u16() => {
+                    record_mirror_request("match");
+                }
+                Ok(response) => {
+                    warn!(
+                        method = %request.method,
+                        url = %path,
+                        primary_status = primary.as_u16(),
+                        mirror_status = response.status().as_u16(),
+                        "Mirrored request status differs from primary"
+                    );
+                    record_mirror_request("mismatch");
+                }
+                Err(e) => {
+                    warn!(method = %request.method, url = %path, error = %e, "Mirrored request failed");
+                    record_mirror_request("error");
+                }
+            }
+        });
+    }
+}
+
+/// Whether a client header is copied to the mirror
+fn is_forwarded(name: &HeaderName) -> bool {
+    !(is_hop_by_hop_header(name)
+        || name == header::AUTHORIZATION
+        || name == header::HOST
+        || name == header::CONTENT_LENGTH
+        || name.as_str() == API_KEY_HEADER)
+}
+
+/// Path and query with any `stream` query parameter removed
+fn non_streaming_path(uri: &Uri) -> String {
+    let path = uri.path();
+    let Some(query) = uri.query() else {
+        return path.to_string();
+    };
+    let kept: Vec<&str> = query
+        .split('&')
+        .filter(|pair| pair.split('=').next() != Some("stream"))
+        .collect();
+    if kept.is_empty() {
+        path.to_string()
+    } else {
+        format!("{}?{}", path, kept.join("&"))
+    }
+}
+
+/// The body with `"stream": true` turned off; other bodies are sent as-is
+fn non_streaming_body(body: &Bytes) -> Bytes {
+    let Ok(Value::Object(mut object)) = serde_json::from_slice::<Value>(body) else {
+        return body.clone();
+    };
+    if object.get("stream") != Some(&Value::Bool(true)) {
+        return body.clone();
+    }
+    object.insert("stream".to_string(), Value::Bool(false));
+    serde_json::to_vec(&object)
+        .map(Bytes::from)
+        .unwrap_or_else(|_| body.clone())
+}
+
+/// Mirroring middleware for model endpoints (runs after authentication)
+///
+/// Requests of users who opted out of request logging are never mirrored.
+pub async fn mirror_middleware(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let opted_out = request
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .is_some_and(|user| user.logging_opt_out);
+    if opted_out || !state.mirror.is_enabled() || !state.mirror.sampled() {
+        return next.run(request).await;
+    }
+
+    let (parts, body) = request.into_parts();
+    let body = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(e) => {
+            return AppError::BadRequest(format!("Failed to read request body: {}", e))
+                .into_response();
+        }
+    };
+    // Routers are nested under /v1 and /native; mirror the full path
+    let uri = parts
+        .extensions
+        .get::<OriginalUri>()
+        .map(|original| original.0.clone())
+        .unwrap_or_else(|| parts.uri.clone());
+    let mirrored = state
+        .mirror
+        .copy(&parts.method, &uri, &parts.headers, &body);
+
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+    if let Some(mirrored) = mirrored {
+        state.mirror.spawn(mirrored, response.status());
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mirror(url: Option<&str>, token: Option<&str>) -> RequestMirror {
+        RequestMirror::new(&ServerConfig {
+            mirror_url: url.map(str::to_string),
+            mirror_auth_token: token.map(str::to_string),
+            mirror_sample_rate: 1.0,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_enabled_needs_url_and_token() {
+        assert!(mirror(Some("http://staging"), Some("token")).is_enabled());
+        assert!(!mirror(Some("http://staging"), None).is_enabled());
+        assert!(!mirror(None, Some("token")).is_enabled());
+    }
+
+    #[test]
+    fn test_copy_replaces_credentials() {
+        let mirror = mirror(Some("http://staging/"), Some("staging-token"));
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer prod-jwt"),
+        );
+        headers.insert(API_KEY_HEADER, HeaderValue::from_static("prod-jwt"));
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        );
+        headers.insert(header::CONNECTION, HeaderValue::from_static("keep-alive"));
+        let uri: Uri = "/v1/chat/completions?stream=true&a=b".parse().unwrap();
+
+        let copy = mirror
+            .copy(&Method::POST, &uri, &headers, &Bytes::from_static(b"{}"))
+            .unwrap();
+        assert_eq!(copy.url, "http://staging/v1/chat/completions?a=b");
+        assert_eq!(copy.headers[header::AUTHORIZATION], "Bearer staging-token");
+        assert!(copy.headers.get(API_KEY_HEADER).is_none());
+        assert!(copy.headers.get(header::CONNECTION).is_none());
+        assert_eq!(copy.headers[header::CONTENT_TYPE], "application/json");
+        assert_eq!(copy.headers[MIRROR_HEADER], "1");
+    }
+
+    #[test]
+    fn test_non_streaming_body() {
+        let body = non_streaming_body(&Bytes::from_static(br#"{"model":"gpt-4o","stream":true}"#));
+        let value: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(value["stream"], false);
+        assert_eq!(value["model"], "gpt-4o");
+
+        let untouched = Bytes::from_static(br#"{"model":"gpt-4o"}"#);
+        assert_eq!(non_streaming_body(&untouched), untouched);
+        let binary = Bytes::from_static(b"\x00\x01");
+        assert_eq!(non_streaming_body(&binary), binary);
+    }
+
+    #[test]
+    fn test_non_streaming_path() {
+        assert_eq!(
+            non_streaming_path(&"/v1/models".parse().unwrap()),
+            "/v1/models"
+        );
+        assert_eq!(
+            non_streaming_path(&"/v1/chat/completions?stream=true".parse().unwrap()),
+            "/v1/chat/completions"
+        );
+    }
+}
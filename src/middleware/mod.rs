@@ -1,12 +1,42 @@
 //! Middleware module
 //!
-//! Contains Tower middleware for authentication and rate limiting.
+//! Contains Tower middleware for authentication, strict OpenAI compatibility, response content types, request decompression, in-flight tracking, maintenance mode, request mirroring, model allowlists, provider overrides, quarantine, rate limiting (requests and tokens) and its exemption list, request logging, synthetic traffic marking and token scopes.
 
 pub mod auth;
+pub mod compat;
+pub mod content_type;
+pub mod decompression;
+pub mod in_flight;
+pub mod maintenance;
+pub mod mirror;
+pub mod model_access;
+pub mod provider_override;
+pub mod quarantine;
+pub mod rate_limit_exempt;
 pub mod rate_limiter;
+pub mod request_log;
+pub mod scope;
+pub mod synthetic;
+pub mod token_limit;
 
 pub use auth::{auth_middleware, AuthenticatedUser};
+pub use compat::{compat_middleware, CompatMode};
+pub use content_type::content_type_middleware;
+pub use decompression::decompression_middleware;
+pub use in_flight::{in_flight_middleware, InFlightRegistry};
+pub use maintenance::{maintenance_middleware, MaintenanceMode};
+pub use mirror::{mirror_middleware, RequestMirror};
+pub use model_access::ModelAllowlist;
+pub use provider_override::{provider_override_middleware, ProviderOverride};
+pub use quarantine::{quarantine_middleware, QuarantineTracker};
+pub use rate_limit_exempt::RateLimitExemptIds;
 pub use rate_limiter::{
-    check_rate_limit, increment_rate_limit, rate_limit_exceeded_response, rate_limit_middleware,
-    RateLimitConfig, RateLimitResult,
+    check_rate_limit, increment_rate_limit, organization_rate_limit, rate_limit_exceeded_response,
+    rate_limit_exemption, rate_limit_middleware, scoped_rate_limit_exceeded_response,
+    RateLimitConfig, RateLimitExemption, RateLimitResult, RateLimitScope, RateLimitWeight,
+    RejectionWindow,
 };
+pub use request_log::request_log_middleware;
+pub use scope::{scope_middleware, TokenScopes};
+pub use synthetic::synthetic_middleware;
+pub use token_limit::{estimate_prompt_tokens, token_rate_limit, TokenCharge};
@@ -0,0 +1,136 @@
+//! Per-user model allowlists
+//!
+//! Zion limits may carry `allowedModels`, glob patterns of the models a
+//! subscription may request (e.g. `gpt-4o-mini*`; `*` matches any run of
+//! characters). The rate limiter copies them onto the request's
+//! `AuthenticatedUser`, and the typed `/v1` handlers and the pass-through
+//! handler refuse other models with 403 `model_not_allowed` before anything
+//! is forwarded.
+//!
+//! Limits without the field allow every model, so payloads from a Zion that
+//! doesn't send it keep working. When several limit entries carry patterns
+//! (e.g. daily and monthly periods) a model allowed by any of them is allowed.
+
+use metrics::counter;
+use tracing::warn;
+
+use crate::{error::AppError, middleware::auth::AuthenticatedUser, zion::UserLimit};
+
+/// Error code for models outside the user's allowlist
+pub const MODEL_NOT_ALLOWED_CODE: &str = "model_not_allowed";
+
+/// Models a request's user may call
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ModelAllowlist {
+    /// Every model (no `allowedModels` in the user's limits)
+    #[default]
+    All,
+    /// Only models matching one of the patterns
+    Only(Vec<String>),
+}
+
+impl ModelAllowlist {
+    /// Allowlist for a user's limits
+    pub fn from_limits(limits: &[UserLimit]) -> Self {
+        let mut patterns = limits
+            .iter()
+            .filter_map(|limit| limit.allowed_models.as_ref())
+            .peekable();
+        if patterns.peek().is_none() {
+            return ModelAllowlist::All;
+        }
+        ModelAllowlist::Only(patterns.flatten().cloned().collect())
+    }
+
+    /// Whether `model` may be requested
+    pub fn allows(&self, model: &str) -> bool {
+        match self {
+            ModelAllowlist::All => true,
+            ModelAllowlist::Only(patterns) => {
+                patterns.iter().any(|pattern| glob_matches(pattern, model))
+            }
+        }
+    }
+}
+
+/// Refuse `models` if the user's allowlist doesn't cover every one of them
+pub fn check(user: &AuthenticatedUser, endpoint: &'static str, models: &[&str]) -> Result<(), AppError> {
+    let Some(model) = models.iter().find(|model| !user.allowed_models.allows(model)) else {
+        return Ok(());
+    };
+    counter!("sentinel_model_not_allowed_total", "endpoint" => endpoint).increment(1);
+    warn!(
+        external_id = %user.log_id(),
+        endpoint = endpoint,
+        model = %model,
+        "Request rejected: model not in the user's allowlist"
+    );
+    Err(AppError::ModelNotAllowed(model.to_string()))
+}
+
+/// Match `value` against a pattern where `*` stands for any run of characters
+fn glob_matches(pattern: &str, value: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = value.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    // Without a `*` the whole value must match
+    let Some(last) = parts.pop() else {
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zion::models::MissingLimitPolicy;
+
+    fn limit(allowed_models: Option<&[&str]>) -> UserLimit {
+        let mut limit = UserLimit::not_configured("ai_usage", MissingLimitPolicy::Unlimited);
+        limit.allowed_models =
+            allowed_models.map(|patterns| patterns.iter().map(|p| p.to_string()).collect());
+        limit
+    }
+
+    #[test]
+    fn test_glob_matches() {
+        assert!(glob_matches("gpt-4o-mini*", "gpt-4o-mini"));
+        assert!(glob_matches("gpt-4o-mini*", "gpt-4o-mini-2024-07-18"));
+        assert!(!glob_matches("gpt-4o-mini*", "gpt-4o"));
+        assert!(glob_matches("gpt-4o", "gpt-4o"));
+        assert!(!glob_matches("gpt-4o", "gpt-4o-mini"));
+        assert!(glob_matches("*", "anything"));
+        assert!(glob_matches("text-embedding-*-small", "text-embedding-3-small"));
+        assert!(!glob_matches("text-embedding-*-small", "text-embedding-3-large"));
+        assert!(glob_matches("*mini*", "o4-mini-high"));
+        // A suffix can't reuse characters already matched by the prefix
+        assert!(!glob_matches("ab*ba", "aba"));
+    }
+
+    #[test]
+    fn test_allowlist_from_limits() {
+        assert_eq!(ModelAllowlist::from_limits(&[]), ModelAllowlist::All);
+        assert_eq!(ModelAllowlist::from_limits(&[limit(None)]), ModelAllowlist::All);
+
+        let allowlist = ModelAllowlist::from_limits(&[
+            limit(Some(&["gpt-4o-mini*"])),
+            limit(None),
+            limit(Some(&["text-embedding-3-small"])),
+        ]);
+        assert!(allowlist.allows("gpt-4o-mini"));
+        assert!(allowlist.allows("text-embedding-3-small"));
+        assert!(!allowlist.allows("gpt-4o"));
+
+        // An empty list allows nothing
+        assert!(!ModelAllowlist::from_limits(&[limit(Some(&[]))]).allows("gpt-4o-mini"));
+    }
+}
@@ -0,0 +1,127 @@
+//! Per-request provider override for canary testing
+//!
+//! Users listed in `PROVIDER_CANARY_EXTERNAL_IDS` may send
+//! `X-Sentinel-Provider: <name>` to have the request served by a registered
+//! provider other than the default. Everything else stays the same: tier
+//! routing still picks the model, and usage is tracked as usual. Applied
+//! overrides are audit-logged and echoed in the `X-Sentinel-Provider`
+//! response header.
+//!
+//! Anyone else sending the header gets 403 `provider_override_forbidden`
+//! rather than having it silently ignored, so a misconfigured canary client
+//! notices immediately.
+//!
+//! Requests without the header are served with provider failover when it is
+//! configured, and the provider that answered is named in the same response
+//! header.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::{HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use tracing::{info, warn};
+
+use crate::{
+    error::{ErrorBody, ErrorResponse},
+    middleware::auth::AuthenticatedUser,
+    proxy::{failover, registry},
+    AppState,
+};
+
+/// Request and response header naming the provider
+pub const PROVIDER_HEADER: &str = "x-sentinel-provider";
+
+/// Error code for users not on the canary allow-list
+pub const OVERRIDE_FORBIDDEN_CODE: &str = "provider_override_forbidden";
+
+/// Error code for a provider name that isn't registered
+pub const UNKNOWN_PROVIDER_CODE: &str = "unknown_provider";
+
+/// Provider chosen by `X-Sentinel-Provider`, stored in request extensions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProviderOverride(pub String);
+
+fn error_response(status: StatusCode, code: &str, message: String) -> Response {
+    let error_response = ErrorResponse {
+        error: ErrorBody {
+            code: code.to_string(),
+            message,
+            retryable: false,
+            retry_after_ms: None,
+            details: None,
+        },
+    };
+    (status, Json(error_response)).into_response()
+}
+
+/// Provider override middleware
+///
+/// Runs after auth, quarantine and rate limiting. Requests without the header
+/// pass through, only picking up the header if failover served them.
+pub async fn provider_override_middleware(
+    State(state): State<Arc<AppState>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let Some(requested) = request.headers().get(PROVIDER_HEADER) else {
+        let (mut response, served) = failover::serving(next.run(request)).await;
+        if let Some(provider) = served {
+            response
+                .headers_mut()
+                .insert(PROVIDER_HEADER, HeaderValue::from_static(provider));
+        }
+        return response;
+    };
+    let requested = requested.to_str().unwrap_or_default().trim().to_ascii_lowercase();
+
+    let (external_id, log_id) = request
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .map(|user| (user.external_id.clone(), user.log_id().to_string()))
+        .unwrap_or_default();
+    if !state.config.provider.canary_external_ids.contains(&external_id) {
+        warn!(
+            external_id = %log_id,
+            provider = %requested,
+            "Provider override rejected: user not on canary list"
+        );
+        return error_response(
+            StatusCode::FORBIDDEN,
+            OVERRIDE_FORBIDDEN_CODE,
+            "The X-Sentinel-Provider header is only available to canary accounts".to_string(),
+        );
+    }
+
+    let Some(provider) = state.providers.get(&requested) else {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            UNKNOWN_PROVIDER_CODE,
+            format!(
+                "Unknown provider '{}' (available: {})",
+                requested,
+                state.providers.names().join(", ")
+            ),
+        );
+    };
+
+    info!(
+        external_id = %log_id,
+        provider = %requested,
+        path = %request.uri().path(),
+        "Provider override applied"
+    );
+
+    request
+        .extensions_mut()
+        .insert(ProviderOverride(requested.clone()));
+    let mut response = registry::with_override(provider, next.run(request)).await;
+    if let Ok(value) = HeaderValue::from_str(&requested) {
+        response.headers_mut().insert(PROVIDER_HEADER, value);
+    }
+    response
+}
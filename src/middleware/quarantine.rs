@@ -0,0 +1,324 @@
+//! Quarantine for clients sending malformed requests
+//!
+//! A buggy client can send thousands of requests per minute that all end in a
+//! 400, each still costing auth, rate limiting and body validation. Malformed
+//! responses (400/413/422) are counted per user in a fixed window; a user who
+//! reaches `QUARANTINE_MALFORMED_THRESHOLD` is quarantined for
+//! `QUARANTINE_DURATION_SECONDS`, during which requests are rejected right
+//! after auth with 429 `too_many_malformed_requests`.
+//!
+//! The quarantine marker stores the Unix time it ends and outlives it by a
+//! grace period, so the first request after expiry can log and count the exit.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::Utc;
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    cache::redis::{keys, RedisCache},
+    config::Config,
+    error::{AppResult, ErrorBody, ErrorDetails, ErrorResponse, RetryAfter},
+    middleware::auth::AuthenticatedUser,
+    routes::metrics::record_quarantine_event,
+    AppState,
+};
+
+#[cfg(any(test, feature = "test-utils"))]
+use crate::cache::InMemoryCache;
+
+/// Error code returned to quarantined users
+pub const QUARANTINE_ERROR_CODE: &str = "too_many_malformed_requests";
+
+/// How long a lapsed quarantine marker is kept so its exit can be observed
+const EXIT_GRACE_SECONDS: u64 = 3600;
+
+/// Cache backend abstraction for QuarantineTracker
+enum QuarantineBackend {
+    Redis(Arc<RedisCache>),
+    #[cfg(any(test, feature = "test-utils"))]
+    InMemory(Arc<InMemoryCache>),
+}
+
+impl QuarantineBackend {
+    async fn get<T: DeserializeOwned>(&self, key: &str) -> AppResult<Option<T>> {
+        match self {
+            QuarantineBackend::Redis(cache) => cache.get(key).await,
+            #[cfg(any(test, feature = "test-utils"))]
+            QuarantineBackend::InMemory(cache) => cache.get(key).await,
+        }
+    }
+
+    async fn set_if_absent<T: Serialize>(&self, key: &str, value: &T, ttl_seconds: u64) -> AppResult<bool> {
+        match self {
+            QuarantineBackend::Redis(cache) => cache.set_if_absent(key, value, ttl_seconds).await,
+            #[cfg(any(test, feature = "test-utils"))]
+            QuarantineBackend::InMemory(cache) => cache.set_if_absent(key, value, ttl_seconds).await,
+        }
+    }
+
+    async fn incr(&self, key: &str, delta: i64) -> AppResult<i64> {
+        match self {
+            QuarantineBackend::Redis(cache) => cache.incr(key, delta).await,
+            #[cfg(any(test, feature = "test-utils"))]
+            QuarantineBackend::InMemory(cache) => cache.incr(key, delta).await,
+        }
+    }
+
+    async fn expire(&self, key: &str, seconds: u64) -> AppResult<()> {
+        match self {
+            QuarantineBackend::Redis(cache) => cache.expire(key, seconds).await,
+            #[cfg(any(test, feature = "test-utils"))]
+            QuarantineBackend::InMemory(cache) => cache.expire(key, seconds).await,
+        }
+    }
+
+    async fn delete(&self, key: &str) -> AppResult<()> {
+        match self {
+            QuarantineBackend::Redis(cache) => cache.delete(key).await,
+            #[cfg(any(test, feature = "test-utils"))]
+            QuarantineBackend::InMemory(cache) => cache.delete(key).await,
+        }
+    }
+}
+
+/// Per-user malformed-request counters and quarantine markers
+pub struct QuarantineTracker {
+    cache: QuarantineBackend,
+    threshold: i64,
+    window_seconds: u64,
+    duration_seconds: u64,
+}
+
+impl QuarantineTracker {
+    /// Create a tracker with Redis backend
+    pub fn new(cache: Arc<RedisCache>, config: &Config) -> Self {
+        Self::with_backend(QuarantineBackend::Redis(cache), config)
+    }
+
+    /// Create a tracker with in-memory backend for testing
+    #[cfg(any(test, feature = "test-utils"))]
+    pub fn new_for_testing(cache: Arc<InMemoryCache>, config: &Config) -> Self {
+        Self::with_backend(QuarantineBackend::InMemory(cache), config)
+    }
+
+    fn with_backend(cache: QuarantineBackend, config: &Config) -> Self {
+        Self {
+            cache,
+            threshold: config.rate_limit.quarantine_malformed_threshold,
+            window_seconds: config.rate_limit.quarantine_window_seconds.max(1),
+            duration_seconds: config.rate_limit.quarantine_duration_seconds.max(1),
+        }
+    }
+
+    /// Whether quarantine is enabled (`QUARANTINE_MALFORMED_THRESHOLD` > 0)
+    pub fn enabled(&self) -> bool {
+        self.threshold > 0
+    }
+
+    fn strikes_key(&self, external_id: &str, now: i64) -> String {
+        keys::quarantine_strikes(external_id, now / self.window_seconds as i64)
+    }
+
+    /// Wait until the user's quarantine ends, or None if not quarantined
+    ///
+    /// A lapsed marker is removed here and the exit is logged.
+    pub async fn remaining(&self, external_id: &str) -> AppResult<Option<RetryAfter>> {
+        let key = keys::quarantine(external_id);
+        let Some(until) = self.cache.get::<i64>(&key).await? else {
+            return Ok(None);
+        };
+
+        let now = Utc::now().timestamp();
+        if until > now {
+            return Ok(Some(RetryAfter::until(until, now, self.duration_seconds)));
+        }
+
+        self.cache.delete(&key).await?;
+        info!(external_id = %external_id, "Quarantine expired");
+        record_quarantine_event("expired");
+        Ok(None)
+    }
+
+    /// Count a malformed request, quarantining the user at the threshold
+    ///
+    /// Returns true when this request started a quarantine.
+    pub async fn record_malformed(&self, external_id: &str) -> AppResult<bool> {
+        let now = Utc::now().timestamp();
+        let strikes_key = self.strikes_key(external_id, now);
+        let strikes = self.cache.incr(&strikes_key, 1).await?;
+        if strikes == 1 {
+            self.cache.expire(&strikes_key, self.window_seconds * 2).await?;
+        }
+        if strikes < self.threshold {
+            return Ok(false);
+        }
+
+        let until = now + self.duration_seconds as i64;
+        let entered = self
+            .cache
+            .set_if_absent(
+                &keys::quarantine(external_id),
+                &until,
+                self.duration_seconds + EXIT_GRACE_SECONDS,
+            )
+            .await?;
+        if entered {
+            // Start the next window from zero once the quarantine ends
+            self.cache.delete(&strikes_key).await?;
+            warn!(
+                external_id = %external_id,
+                strikes = strikes,
+                window_seconds = self.window_seconds,
+                duration_seconds = self.duration_seconds,
+                "User quarantined for malformed requests"
+            );
+            record_quarantine_event("entered");
+        }
+        Ok(entered)
+    }
+
+    /// Lift a user's quarantine and reset their malformed-request count
+    ///
+    /// Returns true if the user was quarantined.
+    pub async fn clear(&self, external_id: &str) -> AppResult<bool> {
+        let key = keys::quarantine(external_id);
+        let now = Utc::now().timestamp();
+        let active = self
+            .cache
+            .get::<i64>(&key)
+            .await?
+            .is_some_and(|until| until > now);
+
+        self.cache.delete(&key).await?;
+        self.cache.delete(&self.strikes_key(external_id, now)).await?;
+        if active {
+            info!(external_id = %external_id, "Quarantine cleared");
+            record_quarantine_event("cleared");
+        }
+        Ok(active)
+    }
+}
+
+/// Whether a response status counts as a malformed request
+fn is_malformed(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::BAD_REQUEST | StatusCode::PAYLOAD_TOO_LARGE | StatusCode::UNPROCESSABLE_ENTITY
+    )
+}
+
+/// Build the 429 returned while a user is quarantined
+pub fn quarantined_response(retry_after: RetryAfter) -> Response {
+
+    let error_response = ErrorResponse {
+        error: ErrorBody {
+            code: QUARANTINE_ERROR_CODE.to_string(),
+            message: "Too many malformed requests. Fix the request payload and retry later."
+                .to_string(),
+            retryable: true,
+            retry_after_ms: Some(retry_after.as_millis()),
+            details: Some(ErrorDetails {
+                limit: None,
+                used: None,
+                remaining: None,
+                reset_at: retry_after.reset_at(Utc::now().timestamp()),
+            }),
+        },
+    };
+
+    let mut response = (StatusCode::TOO_MANY_REQUESTS, Json(error_response)).into_response();
+    retry_after.apply(response.headers_mut());
+    response
+}
+
+/// Quarantine middleware
+///
+/// Runs after auth and before rate limiting. Quarantined users are rejected
+/// without touching the body; everyone else's malformed responses are
+/// counted. Cache errors fail open.
+pub async fn quarantine_middleware(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let external_id = request
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .map(|user| user.external_id.clone());
+    let Some(external_id) = external_id.filter(|_| state.quarantine.enabled()) else {
+        return next.run(request).await;
+    };
+
+    match state.quarantine.remaining(&external_id).await {
+        Ok(Some(retry_after)) => {
+            record_quarantine_event("rejected");
+            return quarantined_response(retry_after);
+        }
+        Ok(None) => {}
+        Err(e) => warn!(error = %e, external_id = %external_id, "Quarantine check failed"),
+    }
+
+    let response = next.run(request).await;
+
+    if is_malformed(response.status()) {
+        if let Err(e) = state.quarantine.record_malformed(&external_id).await {
+            warn!(error = %e, external_id = %external_id, "Failed to record malformed request");
+        }
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+
+    fn tracker(threshold: i64) -> QuarantineTracker {
+        let mut config = test_config("http://zion.invalid", "http://openai.invalid/v1");
+        config.rate_limit.quarantine_malformed_threshold = threshold;
+        QuarantineTracker::new_for_testing(Arc::new(InMemoryCache::new(60)), &config)
+    }
+
+    #[test]
+    fn test_is_malformed() {
+        assert!(is_malformed(StatusCode::BAD_REQUEST));
+        assert!(is_malformed(StatusCode::PAYLOAD_TOO_LARGE));
+        assert!(is_malformed(StatusCode::UNPROCESSABLE_ENTITY));
+        assert!(!is_malformed(StatusCode::UNAUTHORIZED));
+        assert!(!is_malformed(StatusCode::TOO_MANY_REQUESTS));
+        assert!(!is_malformed(StatusCode::OK));
+    }
+
+    #[tokio::test]
+    async fn test_quarantine_starts_at_threshold_and_clears() {
+        let tracker = tracker(3);
+        assert!(!tracker.record_malformed("user-1").await.unwrap());
+        assert!(!tracker.record_malformed("user-1").await.unwrap());
+        assert_eq!(tracker.remaining("user-1").await.unwrap(), None);
+
+        assert!(tracker.record_malformed("user-1").await.unwrap());
+        assert!(tracker.remaining("user-1").await.unwrap().is_some());
+        assert_eq!(tracker.remaining("user-2").await.unwrap(), None);
+
+        assert!(tracker.clear("user-1").await.unwrap());
+        assert_eq!(tracker.remaining("user-1").await.unwrap(), None);
+        assert!(!tracker.clear("user-1").await.unwrap());
+    }
+
+    #[test]
+    fn test_quarantined_response_sets_retry_after() {
+        let response = quarantined_response(RetryAfter::seconds(0));
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[axum::http::header::RETRY_AFTER], "1");
+    }
+}
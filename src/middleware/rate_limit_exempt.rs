@@ -0,0 +1,191 @@
+//! Rate limit exemption list
+//!
+//! External IDs on the list bypass request rate limiting (usage is still
+//! tracked). `RATE_LIMIT_EXEMPT_IDS` sets the list at startup;
+//! `PUT /admin/rate-limit/exempt-ids` stores a replacement in Redis so every
+//! replica picks it up. Replicas re-read the list at most once per
+//! [`LOCAL_CACHE_TTL`], so changing it needs no restart.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    cache::redis::{keys, RedisCache},
+    config::Config,
+    error::AppResult,
+};
+
+#[cfg(any(test, feature = "test-utils"))]
+use crate::cache::InMemoryCache;
+
+/// How long a replica trusts its last read of the Redis list
+pub const LOCAL_CACHE_TTL: Duration = Duration::from_secs(2);
+
+/// How long a runtime list is kept (a forgotten list lapses back to the env setting)
+const LIST_TTL_SECONDS: u64 = 7 * 24 * 60 * 60;
+
+/// Runtime list stored in Redis
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ExemptIdsOverride {
+    /// Replaces `RATE_LIMIT_EXEMPT_IDS` while set
+    pub external_ids: Vec<String>,
+}
+
+/// Effective exemption list
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ExemptIdsStatus {
+    pub external_ids: Vec<String>,
+    /// `config` (RATE_LIMIT_EXEMPT_IDS) or `override` (set via the admin endpoint)
+    pub source: &'static str,
+}
+
+/// Cache backend abstraction for RateLimitExemptIds
+enum ExemptIdsBackend {
+    Redis(Arc<RedisCache>),
+    #[cfg(any(test, feature = "test-utils"))]
+    InMemory(Arc<InMemoryCache>),
+}
+
+impl ExemptIdsBackend {
+    async fn get(&self) -> AppResult<Option<ExemptIdsOverride>> {
+        match self {
+            ExemptIdsBackend::Redis(cache) => cache.get(&keys::rate_limit_exempt_ids()).await,
+            #[cfg(any(test, feature = "test-utils"))]
+            ExemptIdsBackend::InMemory(cache) => cache.get(&keys::rate_limit_exempt_ids()).await,
+        }
+    }
+
+    async fn set(&self, list: &ExemptIdsOverride) -> AppResult<()> {
+        match self {
+            ExemptIdsBackend::Redis(cache) => {
+                cache.set_with_ttl(&keys::rate_limit_exempt_ids(), list, LIST_TTL_SECONDS).await
+            }
+            #[cfg(any(test, feature = "test-utils"))]
+            ExemptIdsBackend::InMemory(cache) => {
+                cache.set_with_ttl(&keys::rate_limit_exempt_ids(), list, LIST_TTL_SECONDS).await
+            }
+        }
+    }
+
+    async fn delete(&self) -> AppResult<()> {
+        match self {
+            ExemptIdsBackend::Redis(cache) => cache.delete(&keys::rate_limit_exempt_ids()).await,
+            #[cfg(any(test, feature = "test-utils"))]
+            ExemptIdsBackend::InMemory(cache) => cache.delete(&keys::rate_limit_exempt_ids()).await,
+        }
+    }
+}
+
+/// Exemption list shared by all handlers (startup setting plus runtime override)
+pub struct RateLimitExemptIds {
+    cache: ExemptIdsBackend,
+    default_ids: Vec<String>,
+    local: Mutex<Option<(Instant, ExemptIdsStatus)>>,
+}
+
+impl RateLimitExemptIds {
+    /// Create the exemption list with Redis backend
+    pub fn new(cache: Arc<RedisCache>, config: &Config) -> Self {
+        Self::with_backend(ExemptIdsBackend::Redis(cache), config)
+    }
+
+    /// Create the exemption list with in-memory backend for testing
+    #[cfg(any(test, feature = "test-utils"))]
+    pub fn new_for_testing(cache: Arc<InMemoryCache>, config: &Config) -> Self {
+        Self::with_backend(ExemptIdsBackend::InMemory(cache), config)
+    }
+
+    fn with_backend(cache: ExemptIdsBackend, config: &Config) -> Self {
+        Self {
+            cache,
+            default_ids: config.rate_limit.exempt_ids.clone(),
+            local: Mutex::new(None),
+        }
+    }
+
+    fn resolve(&self, list: Option<ExemptIdsOverride>) -> ExemptIdsStatus {
+        match list {
+            Some(list) => ExemptIdsStatus {
+                external_ids: list.external_ids,
+                source: "override",
+            },
+            None => ExemptIdsStatus {
+                external_ids: self.default_ids.clone(),
+                source: "config",
+            },
+        }
+    }
+
+    fn remember(&self, status: &ExemptIdsStatus) {
+        *self.local.lock().unwrap() = Some((Instant::now(), status.clone()));
+    }
+
+    /// Current list, re-reading Redis at most once per [`LOCAL_CACHE_TTL`]
+    ///
+    /// When Redis can't be read the startup setting applies.
+    pub async fn status(&self) -> ExemptIdsStatus {
+        if let Some((read_at, status)) = self.local.lock().unwrap().as_ref() {
+            if read_at.elapsed() < LOCAL_CACHE_TTL {
+                return status.clone();
+            }
+        }
+
+        let list = match self.cache.get().await {
+            Ok(list) => list,
+            Err(e) => {
+                warn!(error = %e, "Failed to read rate limit exemptions, using RATE_LIMIT_EXEMPT_IDS");
+                None
+            }
+        };
+        let status = self.resolve(list);
+        self.remember(&status);
+        status
+    }
+
+    /// Store a replacement list for all replicas
+    pub async fn set_override(&self, list: ExemptIdsOverride) -> AppResult<ExemptIdsStatus> {
+        self.cache.set(&list).await?;
+        let status = self.resolve(Some(list));
+        self.remember(&status);
+        info!(count = status.external_ids.len(), "Rate limit exemption list replaced");
+        Ok(status)
+    }
+
+    /// Remove the runtime list, reverting to `RATE_LIMIT_EXEMPT_IDS`
+    pub async fn clear_override(&self) -> AppResult<ExemptIdsStatus> {
+        self.cache.delete().await?;
+        let status = self.resolve(None);
+        self.remember(&status);
+        info!(count = status.external_ids.len(), "Rate limit exemption list reset");
+        Ok(status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+
+    #[tokio::test]
+    async fn test_override_replaces_config_list() {
+        let mut config = test_config("http://zion.invalid", "http://openai.invalid/v1");
+        config.rate_limit.exempt_ids = vec!["eval-runner".to_string()];
+        let exempt = RateLimitExemptIds::new_for_testing(Arc::new(InMemoryCache::new(60)), &config);
+        assert_eq!(exempt.status().await.external_ids, ["eval-runner"]);
+        assert_eq!(exempt.status().await.source, "config");
+
+        let status = exempt
+            .set_override(ExemptIdsOverride {
+                external_ids: vec!["load-test".to_string()],
+            })
+            .await
+            .unwrap();
+        assert_eq!(status.external_ids, ["load-test"]);
+        assert_eq!(exempt.status().await.source, "override");
+
+        assert_eq!(exempt.clear_override().await.unwrap().external_ids, ["eval-runner"]);
+    }
+}
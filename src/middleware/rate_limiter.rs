@@ -3,20 +3,29 @@
 //! Implements sliding window rate limiting using Redis.
 //! Uses atomic MULTI/EXEC operations to ensure accuracy under concurrent load.
 
-use std::sync::Arc;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
 
 use axum::{
-    extract::{Request, State},
+    extract::{MatchedPath, Request, State},
     http::{header, HeaderValue, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
     Json,
 };
 use redis::AsyncCommands;
+use serde::Serialize;
 
 use crate::{
-    error::{AppError, ErrorBody, ErrorDetails, ErrorResponse},
-    middleware::auth::AuthenticatedUser,
+    clock::{system_clock, SharedClock},
+    config::Config,
+    error::{AppError, ErrorBody, ErrorDetails, ErrorResponse, RetryAfter},
+    middleware::auth::{AuthPath, AuthenticatedUser, OPTED_OUT_LOG_ID},
+    middleware::model_access::ModelAllowlist,
+    middleware::token_limit::{estimate_request, token_rate_limit, TokenCharge},
+    routes::metrics::record_rate_limit_exempt,
+    usage::limits::AI_USAGE,
+    zion::{resolve_limit, UserLimit},
     AppState,
 };
 
@@ -50,6 +59,17 @@ impl RateLimitConfig {
         }
     }
 
+    /// Create config for organization-wide AI request limits
+    ///
+    /// Shares the AI request window, so user and organization counters reset together.
+    pub fn for_org_requests(max_requests: i64) -> Self {
+        Self {
+            max_requests,
+            window_seconds: 60,
+            key_prefix: "sentinel:ratelimit:org".to_string(),
+        }
+    }
+
     /// Create config for token-based limits
     pub fn for_tokens(max_tokens: i64, window_seconds: u64) -> Self {
         Self {
@@ -83,44 +103,167 @@ pub struct RateLimitResult {
     pub reset_at: i64,
     /// Current request count
     pub current: i64,
+    /// Wait until the window resets, sent as `Retry-After` when not allowed
+    pub retry_after: RetryAfter,
 }
 
 impl RateLimitResult {
     /// Create rate limit headers for the response
     pub fn headers(&self) -> Vec<(header::HeaderName, HeaderValue)> {
+        self.scoped_headers(RateLimitScope::User)
+    }
+
+    /// Create rate limit headers for the given scope
+    ///
+    /// User limits use `X-RateLimit-*`, organization limits `X-RateLimit-Org-*`
+    /// and token limits `X-RateLimit-Tokens-*`.
+    pub fn scoped_headers(&self, scope: RateLimitScope) -> Vec<(header::HeaderName, HeaderValue)> {
+        let (limit, remaining, reset) = scope.header_names();
         let mut headers = vec![
             (
-                header::HeaderName::from_static("x-ratelimit-limit"),
+                header::HeaderName::from_static(limit),
                 HeaderValue::from_str(&self.limit.to_string()).unwrap(),
             ),
             (
-                header::HeaderName::from_static("x-ratelimit-remaining"),
+                header::HeaderName::from_static(remaining),
                 HeaderValue::from_str(&self.remaining.max(0).to_string()).unwrap(),
             ),
             (
-                header::HeaderName::from_static("x-ratelimit-reset"),
+                header::HeaderName::from_static(reset),
                 HeaderValue::from_str(&self.reset_at.to_string()).unwrap(),
             ),
         ];
 
         if !self.allowed {
-            // Add Retry-After header when rate limited
-            let retry_after = (self.reset_at - chrono::Utc::now().timestamp()).max(1);
-            headers.push((
-                header::RETRY_AFTER,
-                HeaderValue::from_str(&retry_after.to_string()).unwrap(),
-            ));
+            headers.push((header::RETRY_AFTER, self.retry_after.header_value()));
         }
 
         headers
     }
 }
 
+/// Scope a rate limit is enforced at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitScope {
+    /// Per-user limit keyed by external ID
+    User,
+    /// Shared limit keyed by Zion organization ID
+    Organization,
+    /// Per-user prompt and completion tokens keyed by external ID
+    Tokens,
+}
+
+impl RateLimitScope {
+    /// Label used in logs and the `X-RateLimit-Scope` header
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RateLimitScope::User => "user",
+            RateLimitScope::Organization => "org",
+            RateLimitScope::Tokens => "tokens",
+        }
+    }
+
+    /// Error code returned when this scope's limit is exceeded
+    pub fn error_code(&self) -> &'static str {
+        match self {
+            RateLimitScope::User => "USER_RATE_LIMIT_EXCEEDED",
+            RateLimitScope::Organization => "ORG_RATE_LIMIT_EXCEEDED",
+            RateLimitScope::Tokens => "TOKEN_RATE_LIMIT_EXCEEDED",
+        }
+    }
+
+    /// Limit, remaining and reset header names for this scope
+    fn header_names(&self) -> (&'static str, &'static str, &'static str) {
+        match self {
+            RateLimitScope::User => (
+                "x-ratelimit-limit",
+                "x-ratelimit-remaining",
+                "x-ratelimit-reset",
+            ),
+            RateLimitScope::Organization => (
+                "x-ratelimit-org-limit",
+                "x-ratelimit-org-remaining",
+                "x-ratelimit-org-reset",
+            ),
+            RateLimitScope::Tokens => (
+                "x-ratelimit-tokens-limit",
+                "x-ratelimit-tokens-remaining",
+                "x-ratelimit-tokens-reset",
+            ),
+        }
+    }
+}
+
 /// Generate Redis key for sliding window rate limiting
 fn rate_limit_key(prefix: &str, user_id: &str, window_start: i64) -> String {
     format!("{}:{}:{}", prefix, user_id, window_start)
 }
 
+/// Position of a request within the fixed windows backing the sliding window
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SlidingWindow {
+    /// Index of the window `now` falls in (`now / window_seconds`)
+    pub current: i64,
+    /// Unix time the current window started
+    pub start: i64,
+    /// Seconds elapsed since the current window started
+    pub elapsed: i64,
+    /// Window length in seconds
+    pub window_seconds: i64,
+}
+
+impl SlidingWindow {
+    /// Locate `now` (Unix seconds) in windows of `window_seconds`
+    pub fn at(now: i64, window_seconds: u64) -> Self {
+        let window_seconds = window_seconds.max(1) as i64;
+        let current = now.div_euclid(window_seconds);
+        let start = current * window_seconds;
+        Self {
+            current,
+            start,
+            elapsed: now - start,
+            window_seconds,
+        }
+    }
+
+    /// Index of the previous window
+    pub fn previous(&self) -> i64 {
+        self.current - 1
+    }
+
+    /// Weight of the previous window's count (1.0 at the start of a window, towards 0.0 at its end)
+    pub fn previous_weight(&self) -> f64 {
+        1.0 - (self.elapsed as f64 / self.window_seconds as f64)
+    }
+
+    /// Unix time the current window ends
+    pub fn reset_at(&self) -> i64 {
+        self.start + self.window_seconds
+    }
+
+    /// Combine the two window counters into a result
+    ///
+    /// The previous window is weighted by the portion of it still inside
+    /// the sliding window.
+    pub fn result(&self, max_requests: i64, previous_count: i64, current_count: i64) -> RateLimitResult {
+        let weighted_previous = (previous_count as f64 * self.previous_weight()) as i64;
+        let total_count = current_count + weighted_previous;
+
+        RateLimitResult {
+            allowed: total_count <= max_requests,
+            limit: max_requests,
+            remaining: max_requests - total_count,
+            reset_at: self.reset_at(),
+            current: total_count,
+            retry_after: RetryAfter::until(
+                self.reset_at(),
+                self.start + self.elapsed,
+                self.window_seconds as u64,
+            ),
+        }
+    }
+}
+
 /// Check rate limit for a user using sliding window algorithm
 ///
 /// Uses Redis MULTI/EXEC for atomic operations:
@@ -135,62 +278,18 @@ pub async fn check_rate_limit(
     config: &RateLimitConfig,
 ) -> Result<RateLimitResult, AppError> {
     // In test mode, Redis may not be configured - skip rate limiting
-    let Some(ref redis) = state.redis else {
-        let now = chrono::Utc::now().timestamp();
+    if state.redis.is_none() {
         return Ok(RateLimitResult {
             allowed: true,
             limit: config.max_requests,
             remaining: config.max_requests,
-            reset_at: now + config.window_seconds as i64,
+            reset_at: state.clock.now_unix() + config.window_seconds as i64,
             current: 0,
+            retry_after: RetryAfter::seconds(config.window_seconds),
         });
-    };
-
-    let mut conn = redis.clone();
-    let now = chrono::Utc::now().timestamp();
-
-    // Calculate window boundaries
-    let window_seconds = config.window_seconds as i64;
-    let current_window = now / window_seconds;
-    let previous_window = current_window - 1;
-    let window_start_time = current_window * window_seconds;
-    let elapsed_in_window = now - window_start_time;
-
-    // Keys for current and previous windows
-    let current_key = rate_limit_key(&config.key_prefix, user_id, current_window);
-    let previous_key = rate_limit_key(&config.key_prefix, user_id, previous_window);
-
-    // Get previous window count (may not exist)
-    let previous_count: i64 = conn.get(&previous_key).await.unwrap_or(0);
-
-    // Atomically increment current window and set expiry
-    // Use a pipeline for atomic operations
-    let (current_count,): (i64,) = redis::pipe()
-        .atomic()
-        .incr(&current_key, 1i64)
-        .expire(&current_key, (config.window_seconds * 2) as i64) // Keep for 2 windows
-        .ignore()
-        .query_async(&mut conn)
-        .await?;
+    }
 
-    // Calculate sliding window count
-    // Weight the previous window by the portion that hasn't elapsed
-    let weight = 1.0 - (elapsed_in_window as f64 / window_seconds as f64);
-    let weighted_previous = (previous_count as f64 * weight) as i64;
-    let total_count = current_count + weighted_previous;
-
-    // Check if limit exceeded
-    let allowed = total_count <= config.max_requests;
-    let remaining = config.max_requests - total_count;
-    let reset_at = window_start_time + window_seconds;
-
-    Ok(RateLimitResult {
-        allowed,
-        limit: config.max_requests,
-        remaining,
-        reset_at,
-        current: total_count,
-    })
+    increment_rate_limit(state, user_id, config, 1).await
 }
 
 /// Increment rate limit counter by a custom amount
@@ -203,68 +302,206 @@ pub async fn increment_rate_limit(
     config: &RateLimitConfig,
     amount: i64,
 ) -> Result<RateLimitResult, AppError> {
-    // In test mode, Redis may not be configured - skip rate limiting
+    let now = state.clock.now_unix();
+
+    // In test mode, Redis may not be configured - only an amount over the
+    // whole limit is rejected
     let Some(ref redis) = state.redis else {
-        let now = chrono::Utc::now().timestamp();
         return Ok(RateLimitResult {
-            allowed: true,
+            allowed: amount <= config.max_requests,
             limit: config.max_requests,
             remaining: config.max_requests - amount,
             reset_at: now + config.window_seconds as i64,
             current: amount,
+            retry_after: RetryAfter::seconds(config.window_seconds),
         });
     };
 
-    let mut conn = redis.clone();
-    let now = chrono::Utc::now().timestamp();
+    increment_window(redis, now, user_id, config, amount).await
+}
 
-    // Calculate window boundaries
-    let window_seconds = config.window_seconds as i64;
-    let current_window = now / window_seconds;
-    let previous_window = current_window - 1;
-    let window_start_time = current_window * window_seconds;
-    let elapsed_in_window = now - window_start_time;
+/// Add `amount` to a user's current window in Redis and read back the sliding count
+pub(crate) async fn increment_window(
+    redis: &redis::aio::ConnectionManager,
+    now: i64,
+    user_id: &str,
+    config: &RateLimitConfig,
+    amount: i64,
+) -> Result<RateLimitResult, AppError> {
+    let mut conn = redis.clone();
+    let window = SlidingWindow::at(now, config.window_seconds);
 
     // Keys for current and previous windows
-    let current_key = rate_limit_key(&config.key_prefix, user_id, current_window);
-    let previous_key = rate_limit_key(&config.key_prefix, user_id, previous_window);
+    let current_key = rate_limit_key(&config.key_prefix, user_id, window.current);
+    let previous_key = rate_limit_key(&config.key_prefix, user_id, window.previous());
 
-    // Get previous window count
+    // Get previous window count (may not exist)
     let previous_count: i64 = conn.get(&previous_key).await.unwrap_or(0);
 
-    // Atomically increment current window by amount
+    // Atomically increment current window and set expiry
     let (current_count,): (i64,) = redis::pipe()
         .atomic()
         .incr(&current_key, amount)
-        .expire(&current_key, (config.window_seconds * 2) as i64)
+        .expire(&current_key, (config.window_seconds * 2) as i64) // Keep for 2 windows
         .ignore()
         .query_async(&mut conn)
         .await?;
 
-    // Calculate sliding window count
-    let weight = 1.0 - (elapsed_in_window as f64 / window_seconds as f64);
-    let weighted_previous = (previous_count as f64 * weight) as i64;
-    let total_count = current_count + weighted_previous;
-
-    let allowed = total_count <= config.max_requests;
-    let remaining = config.max_requests - total_count;
-    let reset_at = window_start_time + window_seconds;
-
-    Ok(RateLimitResult {
-        allowed,
-        limit: config.max_requests,
-        remaining,
-        reset_at,
-        current: total_count,
+    Ok(window.result(config.max_requests, previous_count, current_count))
+}
+
+/// Number of requests a single HTTP request counts as (1 when absent)
+///
+/// Set in the request extensions by middleware running before rate limiting,
+/// for endpoints that carry several requests in one call (native chat batches).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitWeight(pub u32);
+
+/// Source of a rate limit exemption
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitExemption {
+    /// External ID on the exemption list (RATE_LIMIT_EXEMPT_IDS or its runtime override)
+    Config,
+    /// `rateLimitExempt` flag set in the user's Zion limits
+    Zion,
+}
+
+impl RateLimitExemption {
+    /// Label used in logs and metrics
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RateLimitExemption::Config => "config",
+            RateLimitExemption::Zion => "zion",
+        }
+    }
+}
+
+/// Determine whether a user is exempt from rate limiting
+///
+/// The config list is checked first; otherwise any Zion limit carrying the
+/// `rateLimitExempt` flag exempts the user.
+pub fn rate_limit_exemption(
+    exempt_ids: &[String],
+    external_id: &str,
+    limits: &[UserLimit],
+) -> Option<RateLimitExemption> {
+    if exempt_ids.iter().any(|id| id == external_id) {
+        Some(RateLimitExemption::Config)
+    } else if limits.iter().any(|limit| limit.rate_limit_exempt) {
+        Some(RateLimitExemption::Zion)
+    } else {
+        None
+    }
+}
+
+/// Resolve the organization-wide limit for a user, if they belong to one
+///
+/// The organization is taken from the first Zion limit carrying an
+/// `organizationId`. The ceiling is the Zion `organizationRateLimit` when
+/// present, then the ORG_RATE_LIMIT_OVERRIDES entry, then the global default.
+pub fn organization_rate_limit(
+    config: &Config,
+    limits: &[UserLimit],
+) -> Option<(String, RateLimitConfig)> {
+    let limit = limits.iter().find(|limit| limit.organization_id.is_some())?;
+    let organization_id = limit.organization_id.clone()?;
+
+    let max_requests = limit
+        .organization_rate_limit
+        .or_else(|| config.rate_limit.org_overrides.get(&organization_id).copied())
+        .unwrap_or(config.rate_limit.org_max_requests);
+
+    Some((organization_id, RateLimitConfig::for_org_requests(max_requests)))
+}
+
+/// Refuse a user whose `ai_usage` allowance is used up
+///
+/// A missing `ai_usage` entry follows `MISSING_LIMIT_POLICY`: `unlimited`
+/// admits the user, `zero` refuses them. `Retry-After` points at the end of
+/// the limit's period when Zion reports one.
+pub fn quota_exceeded(config: &Config, limits: &[UserLimit], now: i64) -> Option<AppError> {
+    let usage = resolve_limit(limits, AI_USAGE, config.zion.missing_limit_policy);
+    let metric = usage.exhausted_metric()?;
+    Some(AppError::QuotaExceeded {
+        message: format!("{} quota used up", usage.display_name),
+        limit: metric.limit,
+        used: metric.used,
+        retry_after: usage.retry_after(now),
     })
 }
 
+/// Fetch the Zion limits for an authenticated user
+///
+/// Limits come from the subscription cache, so flag and organization changes
+/// are picked up when the cached limits expire; `auth_middleware` normally
+/// loaded them already. None when there is no user or the lookup failed,
+/// which means not exempt, no organization scope and no quota check.
+async fn lookup_limits(
+    state: &Arc<AppState>,
+    user: Option<&AuthenticatedUser>,
+    path: Option<&AuthPath>,
+) -> Option<Vec<UserLimit>> {
+    let user = user?;
+    if let Some(path) = path {
+        return path.limits.clone();
+    }
+
+    state
+        .subscription_cache
+        .get_user_limits(&user.external_id)
+        .await
+        .ok()
+}
+
+/// Run an exempt request without rate limiting, marking the response
+async fn run_exempt(
+    request: Request,
+    next: Next,
+    user_id: &str,
+    exemption: RateLimitExemption,
+) -> Response {
+    tracing::debug!(
+        user_id = %user_id,
+        source = exemption.as_str(),
+        "Rate limit exemption applied"
+    );
+    record_rate_limit_exempt(exemption.as_str());
+    if let Some(path) = request.extensions().get::<AuthPath>() {
+        path.record();
+    }
+
+    let mut response = next.run(request).await;
+    response.headers_mut().insert(
+        header::HeaderName::from_static("x-ratelimit-exempt"),
+        HeaderValue::from_static("true"),
+    );
+    response
+}
+
 /// Build a 429 Too Many Requests response with rate limit headers
 pub fn rate_limit_exceeded_response(result: &RateLimitResult) -> Response {
+    scoped_rate_limit_exceeded_response(RateLimitScope::User, result)
+}
+
+/// Build a 429 Too Many Requests response for the scope whose limit was exceeded
+pub fn scoped_rate_limit_exceeded_response(
+    scope: RateLimitScope,
+    result: &RateLimitResult,
+) -> Response {
+    let message = match scope {
+        RateLimitScope::User => "Too many requests. Please slow down.",
+        RateLimitScope::Organization => {
+            "Too many requests from your organization. Please slow down."
+        }
+        RateLimitScope::Tokens => "Too many tokens requested. Please slow down.",
+    };
+
     let error_response = ErrorResponse {
         error: ErrorBody {
-            code: "RATE_LIMIT_EXCEEDED".to_string(),
-            message: "Too many requests. Please slow down.".to_string(),
+            code: scope.error_code().to_string(),
+            message: message.to_string(),
+            retryable: true,
+            retry_after_ms: Some(result.retry_after.as_millis()),
             details: Some(ErrorDetails {
                 limit: Some(result.limit),
                 used: Some(result.current),
@@ -282,102 +519,303 @@ pub fn rate_limit_exceeded_response(result: &RateLimitResult) -> Response {
 
     // Add rate limit headers
     let headers = response.headers_mut();
-    for (name, value) in result.headers() {
+    for (name, value) in result.scoped_headers(scope) {
         headers.insert(name, value);
     }
+    headers.insert(
+        header::HeaderName::from_static("x-ratelimit-scope"),
+        HeaderValue::from_static(scope.as_str()),
+    );
 
     response
 }
 
-/// Rate limiting middleware
+/// Check one rate limit scope, counting `weight` requests and failing open on errors
+async fn check_scope(
+    state: &Arc<AppState>,
+    scope: RateLimitScope,
+    id: &str,
+    config: &RateLimitConfig,
+    weight: i64,
+) -> Option<RateLimitResult> {
+    let result = if weight == 1 {
+        check_rate_limit(state, id, config).await
+    } else {
+        increment_rate_limit(state, id, config, weight).await
+    };
+    match result {
+        Ok(result) => Some(result),
+        Err(e) => {
+            // Log error but allow request through (fail open)
+            tracing::error!(error = %e, scope = scope.as_str(), "Rate limit check failed");
+            None
+        }
+    }
+}
+
+/// Enforce the user limit and, for organization members, the organization limit
 ///
-/// Checks rate limits before processing requests. Returns 429 if exceeded.
-/// Adds rate limit headers to all responses.
-pub async fn rate_limit_middleware(
-    State(state): State<Arc<AppState>>,
-    request: Request,
+/// Users whose `ai_usage` allowance is used up (see [`quota_exceeded`]) are
+/// refused first. Both limits must pass, and both count the request's [`RateLimitWeight`]. The organization ID, logging opt-out and model allowlist are recorded on
+/// the request's `AuthenticatedUser` so handlers can attribute usage to the
+/// organization, keep opted-out users out of their logs and refuse models
+/// outside the subscription. When token
+/// limiting is on, the prompt's estimated tokens must also fit the user's
+/// token window; the charge is recorded on the `AuthenticatedUser` too, to
+/// be settled once the actual usage is known.
+async fn enforce_rate_limits(
+    state: Arc<AppState>,
+    mut request: Request,
     next: Next,
+    config: &RateLimitConfig,
 ) -> Response {
     // Extract user ID from extensions (set by auth middleware)
-    let user_id = request
-        .extensions()
-        .get::<AuthenticatedUser>()
+    let user = request.extensions().get::<AuthenticatedUser>().cloned();
+    let user_id = user
+        .as_ref()
         .map(|u| u.external_id.clone())
         .unwrap_or_else(|| "anonymous".to_string());
 
-    // Default rate limit config
-    let config = RateLimitConfig::for_ai_requests();
-
-    // Check rate limit
-    match check_rate_limit(&state, &user_id, &config).await {
-        Ok(result) => {
-            if !result.allowed {
-                tracing::warn!(
-                    user_id = %user_id,
-                    limit = result.limit,
-                    current = result.current,
-                    "Rate limit exceeded"
-                );
-                return rate_limit_exceeded_response(&result);
-            }
+    let weight = request
+        .extensions()
+        .get::<RateLimitWeight>()
+        .map_or(1, |weight| i64::from(weight.0.max(1)));
+
+    let limits = lookup_limits(&state, user.as_ref(), request.extensions().get::<AuthPath>()).await;
+    let quota = limits
+        .as_deref()
+        .and_then(|limits| quota_exceeded(&state.config, limits, state.clock.now_unix()));
+    let limits = limits.unwrap_or_default();
+    let organization = organization_rate_limit(&state.config, &limits);
+    let logging_opt_out = limits.iter().any(|limit| limit.logging_opt_out);
+    if let Some(user) = request.extensions_mut().get_mut::<AuthenticatedUser>() {
+        user.organization_id = organization.as_ref().map(|(id, _)| id.clone());
+        user.logging_opt_out = logging_opt_out;
+        user.allowed_models = ModelAllowlist::from_limits(&limits);
+    }
+    let log_id = if logging_opt_out {
+        OPTED_OUT_LOG_ID
+    } else {
+        user_id.as_str()
+    };
+
+    if let Some(error) = quota {
+        tracing::warn!(user_id = %log_id, "AI usage quota used up");
+        return error.into_response();
+    }
 
-            // Process request
-            let mut response = next.run(request).await;
+    if user.is_some() {
+        let exempt_ids = state.rate_limit_exempt_ids.status().await.external_ids;
+        if let Some(exemption) = rate_limit_exemption(&exempt_ids, &user_id, &limits) {
+            return run_exempt(request, next, log_id, exemption).await;
+        }
+    }
 
-            // Add rate limit headers to successful response
-            let headers = response.headers_mut();
-            for (name, value) in result.headers() {
-                headers.insert(name, value);
+    let user_result = check_scope(&state, RateLimitScope::User, &user_id, config, weight).await;
+    if let Some(result) = user_result.as_ref().filter(|r| !r.allowed) {
+        state.rate_limit_rejections.record(true);
+        tracing::warn!(
+            user_id = %log_id,
+            limit = result.limit,
+            current = result.current,
+            "Rate limit exceeded"
+        );
+        return scoped_rate_limit_exceeded_response(RateLimitScope::User, result);
+    }
+
+    let org_result = match &organization {
+        Some((organization_id, org_config)) => {
+            check_scope(&state, RateLimitScope::Organization, organization_id, org_config, weight)
+                .await
+        }
+        None => None,
+    };
+    if let Some(result) = org_result.as_ref().filter(|r| !r.allowed) {
+        state.rate_limit_rejections.record(true);
+        tracing::warn!(
+            user_id = %log_id,
+            organization_id = organization.as_ref().map(|(id, _)| id.as_str()).unwrap_or_default(),
+            limit = result.limit,
+            current = result.current,
+            "Organization rate limit exceeded"
+        );
+        return scoped_rate_limit_exceeded_response(RateLimitScope::Organization, result);
+    }
+
+    let mut token_result = None;
+    // Pass-through requests (no matched route) aren't estimated: their bodies
+    // may be large uploads and are forwarded untouched
+    let passthrough = request.extensions().get::<MatchedPath>().is_none();
+    if let Some(token_config) = token_rate_limit(&state.config, &limits).filter(|_| !passthrough) {
+        let estimate;
+        let body_limit = state.config.server.max_request_body_bytes;
+        (request, estimate) = match estimate_request(&state.token_counter, request, body_limit).await
+        {
+            Ok(estimated) => estimated,
+            Err(response) => return response,
+        };
+        if estimate > 0 {
+            token_result =
+                check_scope(&state, RateLimitScope::Tokens, &user_id, &token_config, estimate)
+                    .await;
+        }
+        if let Some(result) = token_result.as_ref().filter(|r| !r.allowed) {
+            state.rate_limit_rejections.record(true);
+            tracing::warn!(
+                user_id = %log_id,
+                limit = result.limit,
+                current = result.current,
+                estimate,
+                "Token rate limit exceeded"
+            );
+            return scoped_rate_limit_exceeded_response(RateLimitScope::Tokens, result);
+        }
+        if let Some(user) = request.extensions_mut().get_mut::<AuthenticatedUser>() {
+            user.token_charge = Some(TokenCharge::new(&state, &user_id, token_config, estimate));
+        }
+    }
+
+    state.rate_limit_rejections.record(false);
+    if let Some(path) = request.extensions().get::<AuthPath>() {
+        path.record();
+    }
+
+    // Process request
+    let mut response = next.run(request).await;
+
+    // Add rate limit headers to successful response
+    let headers = response.headers_mut();
+    for (scope, result) in [
+        (RateLimitScope::User, user_result),
+        (RateLimitScope::Organization, org_result),
+        (RateLimitScope::Tokens, token_result),
+    ] {
+        for (name, value) in result.iter().flat_map(|r| r.scoped_headers(scope)) {
+            headers.insert(name, value);
+        }
+    }
+
+    response
+}
+
+/// Seconds covered by [`RejectionWindow`]
+pub const REJECTION_WINDOW_SECONDS: i64 = 60;
+
+/// Rate limit checks and rejections over the last minute, as in a snapshot
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+pub struct RejectionRate {
+    pub window_seconds: i64,
+    pub checked: u64,
+    pub rejected: u64,
+    /// Share of checks rejected (0 when nothing was checked)
+    pub rate: f64,
+}
+
+/// Sliding one-minute count of rate limit checks and rejections
+///
+/// Per replica and in memory, counted in one-second buckets.
+#[derive(Debug)]
+pub struct RejectionWindow {
+    /// (second, checked, rejected), oldest first
+    buckets: Mutex<VecDeque<(i64, u64, u64)>>,
+    clock: SharedClock,
+}
+
+impl Default for RejectionWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RejectionWindow {
+    pub fn new() -> Self {
+        Self {
+            buckets: Mutex::new(VecDeque::new()),
+            clock: system_clock(),
+        }
+    }
+
+    /// Use the given clock for the window
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Count a rate limit check
+    pub fn record(&self, rejected: bool) {
+        let now = self.clock.now_unix();
+        let mut buckets = self.buckets.lock().unwrap();
+        Self::expire(&mut buckets, now);
+        match buckets.back_mut() {
+            Some((second, checked, rejections)) if *second == now => {
+                *checked += 1;
+                *rejections += u64::from(rejected);
             }
+            _ => buckets.push_back((now, 1, u64::from(rejected))),
+        }
+    }
 
-            response
+    /// Checks and rejections in the last minute
+    pub fn rate(&self) -> RejectionRate {
+        let mut buckets = self.buckets.lock().unwrap();
+        Self::expire(&mut buckets, self.clock.now_unix());
+        let (checked, rejected) = buckets
+            .iter()
+            .fold((0, 0), |(checked, rejected), bucket| {
+                (checked + bucket.1, rejected + bucket.2)
+            });
+        RejectionRate {
+            window_seconds: REJECTION_WINDOW_SECONDS,
+            checked,
+            rejected,
+            rate: if checked == 0 {
+                0.0
+            } else {
+                rejected as f64 / checked as f64
+            },
         }
-        Err(e) => {
-            // Log error but allow request through (fail open)
-            tracing::error!(error = %e, "Rate limit check failed");
-            next.run(request).await
+    }
+
+    fn expire(buckets: &mut VecDeque<(i64, u64, u64)>, now: i64) {
+        while buckets
+            .front()
+            .is_some_and(|(second, _, _)| *second <= now - REJECTION_WINDOW_SECONDS)
+        {
+            buckets.pop_front();
         }
     }
 }
 
+/// Rate limiting middleware
+///
+/// Checks rate limits before processing requests. Returns 429 if exceeded.
+/// Users belonging to a Zion organization are also checked against the
+/// organization's shared limit. Adds rate limit headers to all responses.
+/// Exempt users skip the checks and get `X-RateLimit-Exempt: true` instead;
+/// their usage is still tracked.
+pub async fn rate_limit_middleware(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    enforce_rate_limits(state, request, next, &RateLimitConfig::for_ai_requests()).await
+}
+
 /// Create rate limit middleware layer with custom config
 pub fn rate_limit_layer(
     config: RateLimitConfig,
 ) -> impl Fn(State<Arc<AppState>>, Request, Next) -> std::pin::Pin<Box<dyn std::future::Future<Output = Response> + Send>> + Clone + Send {
-    move |state: State<Arc<AppState>>, request: Request, next: Next| {
+    move |State(state): State<Arc<AppState>>, request: Request, next: Next| {
         let config = config.clone();
-        Box::pin(async move {
-            let user_id = request
-                .extensions()
-                .get::<AuthenticatedUser>()
-                .map(|u| u.external_id.clone())
-                .unwrap_or_else(|| "anonymous".to_string());
-
-            match check_rate_limit(&state, &user_id, &config).await {
-                Ok(result) => {
-                    if !result.allowed {
-                        return rate_limit_exceeded_response(&result);
-                    }
-
-                    let mut response = next.run(request).await;
-                    let headers = response.headers_mut();
-                    for (name, value) in result.headers() {
-                        headers.insert(name, value);
-                    }
-                    response
-                }
-                Err(e) => {
-                    tracing::error!(error = %e, "Rate limit check failed");
-                    next.run(request).await
-                }
-            }
-        })
+        Box::pin(async move { enforce_rate_limits(state, request, next, &config).await })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clock::{Clock, TestClock};
+    use std::time::Duration;
 
     // ===========================================
     // RateLimitConfig Tests
@@ -517,6 +955,7 @@ mod tests {
             remaining: 95,
             reset_at: 1234567890,
             current: 5,
+            retry_after: RetryAfter::seconds(60),
         };
 
         assert!(result.allowed);
@@ -533,6 +972,7 @@ mod tests {
             remaining: -5,
             reset_at: 1234567890,
             current: 105,
+            retry_after: RetryAfter::seconds(60),
         };
 
         assert!(!result.allowed);
@@ -548,6 +988,7 @@ mod tests {
             remaining: 95,
             reset_at: 1234567890,
             current: 5,
+            retry_after: RetryAfter::seconds(60),
         };
 
         let headers = result.headers();
@@ -569,6 +1010,7 @@ mod tests {
             remaining: -5,
             reset_at: future_reset,
             current: 105,
+            retry_after: RetryAfter::seconds(60),
         };
 
         let headers = result.headers();
@@ -586,6 +1028,7 @@ mod tests {
             remaining: 50,
             reset_at: 1700000000,
             current: 50,
+            retry_after: RetryAfter::seconds(60),
         };
 
         let headers = result.headers();
@@ -609,6 +1052,7 @@ mod tests {
             remaining: -10,
             reset_at: chrono::Utc::now().timestamp() + 30,
             current: 110,
+            retry_after: RetryAfter::seconds(60),
         };
 
         let headers = result.headers();
@@ -628,6 +1072,7 @@ mod tests {
             remaining: 50,
             reset_at: 1234567890,
             current: 50,
+            retry_after: RetryAfter::seconds(60),
         };
 
         let cloned = result.clone();
@@ -646,6 +1091,7 @@ mod tests {
             remaining: 50,
             reset_at: 1234567890,
             current: 50,
+            retry_after: RetryAfter::seconds(60),
         };
 
         let debug_str = format!("{:?}", result);
@@ -657,85 +1103,97 @@ mod tests {
     // Window Calculation Tests
     // ===========================================
 
+    /// Unix time at the start of a 60-second window
+    const WINDOW_START: i64 = 1_700_000_040;
+
     #[test]
     fn test_window_calculation_boundaries() {
-        let window_seconds: i64 = 60;
-
-        // Test window boundaries
-        let timestamps = vec![
-            (0, 0),      // Start of epoch
-            (59, 0),     // End of first window
-            (60, 1),     // Start of second window
-            (119, 1),    // End of second window
-            (120, 2),    // Start of third window
-        ];
+        let clock = TestClock::new(0);
 
-        for (timestamp, expected_window) in timestamps {
-            let window = timestamp / window_seconds;
-            assert_eq!(window, expected_window, "Failed for timestamp {}", timestamp);
+        // (seconds since epoch, expected window)
+        for (timestamp, expected_window) in [(0, 0), (59, 0), (60, 1), (119, 1), (120, 2)] {
+            clock.set_unix(timestamp);
+            let window = SlidingWindow::at(clock.now_unix(), 60);
+            assert_eq!(window.current, expected_window, "Failed for timestamp {}", timestamp);
         }
     }
 
     #[test]
     fn test_window_start_calculation() {
-        let now: i64 = 1700000000;
-        let window_seconds: i64 = 60;
-        let current_window = now / window_seconds;
-        let window_start_time = current_window * window_seconds;
+        let window = SlidingWindow::at(1700000000, 60);
 
         // Window start should be at or before current time
-        assert!(window_start_time <= now);
+        assert!(window.start <= 1700000000);
         // Window start should be within one window of current time
-        assert!(now - window_start_time < window_seconds);
+        assert!(1700000000 - window.start < 60);
     }
 
     #[test]
     fn test_elapsed_in_window_calculation() {
-        let window_seconds: i64 = 60;
+        let clock = TestClock::new(WINDOW_START);
 
-        // Test various points within a window
         for offset in 0..60 {
-            let now = 1700000000 + offset;
-            let current_window = now / window_seconds;
-            let window_start_time = current_window * window_seconds;
-            let elapsed = now - window_start_time;
-
-            assert!(elapsed >= 0 && elapsed < window_seconds);
+            let window = SlidingWindow::at(clock.now_unix(), 60);
+            assert_eq!(window.elapsed, offset);
+            assert_eq!(window.start, WINDOW_START);
+            clock.advance(Duration::from_secs(1));
         }
     }
 
     #[test]
     fn test_sliding_window_weight_calculation() {
-        let window_seconds: i64 = 60;
-
         // At the start of a window, previous window has full weight
-        let elapsed_start: f64 = 0.0;
-        let weight_start = 1.0 - (elapsed_start / window_seconds as f64);
-        assert!((weight_start - 1.0).abs() < f64::EPSILON);
+        let start = SlidingWindow::at(WINDOW_START, 60);
+        assert!((start.previous_weight() - 1.0).abs() < f64::EPSILON);
 
-        // At the end of a window, previous window has zero weight
-        let elapsed_end: f64 = 59.0;
-        let weight_end = 1.0 - (elapsed_end / window_seconds as f64);
-        assert!(weight_end > 0.0 && weight_end < 0.1);
+        // At the end of a window, previous window has almost no weight
+        let end = SlidingWindow::at(WINDOW_START + 59, 60);
+        assert!(end.previous_weight() > 0.0 && end.previous_weight() < 0.1);
 
         // At the middle of a window, weight is 0.5
-        let elapsed_mid: f64 = 30.0;
-        let weight_mid = 1.0 - (elapsed_mid / window_seconds as f64);
-        assert!((weight_mid - 0.5).abs() < f64::EPSILON);
+        let mid = SlidingWindow::at(WINDOW_START + 30, 60);
+        assert!((mid.previous_weight() - 0.5).abs() < f64::EPSILON);
     }
 
     #[test]
     fn test_reset_at_calculation() {
         let now: i64 = 1700000000;
-        let window_seconds: i64 = 60;
-        let current_window = now / window_seconds;
-        let window_start_time = current_window * window_seconds;
-        let reset_at = window_start_time + window_seconds;
+        let window = SlidingWindow::at(now, 60);
 
         // Reset should be in the future
-        assert!(reset_at > now);
+        assert!(window.reset_at() > now);
         // Reset should be at most window_seconds away
-        assert!(reset_at - now <= window_seconds);
+        assert!(window.reset_at() - now <= 60);
+    }
+
+    #[test]
+    fn test_request_exactly_at_window_rollover() {
+        let clock = TestClock::new(WINDOW_START + 59);
+
+        // Last second of the window: the 100th request fits, the 101st doesn't
+        let last_second = SlidingWindow::at(clock.now_unix(), 60);
+        assert!(last_second.result(100, 0, 100).allowed);
+        assert!(!last_second.result(100, 0, 101).allowed);
+        assert_eq!(last_second.reset_at(), WINDOW_START + 60);
+
+        // First second of the next window: those 101 requests now count in
+        // full as the previous window, so rolling over doesn't reset the budget
+        clock.advance(Duration::from_secs(1));
+        let rollover = SlidingWindow::at(clock.now_unix(), 60);
+        assert_eq!(rollover.current, last_second.current + 1);
+        assert_eq!(rollover.previous(), last_second.current);
+        assert_eq!(rollover.elapsed, 0);
+        assert_eq!(rollover.reset_at(), WINDOW_START + 120);
+        let result = rollover.result(100, 101, 1);
+        assert!(!result.allowed);
+        assert_eq!(result.current, 102);
+
+        // Halfway through, the previous window counts half
+        clock.advance(Duration::from_secs(30));
+        let result = SlidingWindow::at(clock.now_unix(), 60).result(100, 101, 1);
+        assert!(result.allowed);
+        assert_eq!(result.current, 51);
+        assert_eq!(result.remaining, 49);
     }
 
     // ===========================================
@@ -750,6 +1208,7 @@ mod tests {
             remaining: 0,
             reset_at: 1234567890,
             current: 100,
+            retry_after: RetryAfter::seconds(60),
         };
 
         assert!(result.allowed);
@@ -764,6 +1223,7 @@ mod tests {
             remaining: -1,
             reset_at: 1234567890,
             current: 101,
+            retry_after: RetryAfter::seconds(60),
         };
 
         assert!(!result.allowed);
@@ -778,6 +1238,7 @@ mod tests {
             remaining: -1000,
             reset_at: chrono::Utc::now().timestamp() + 30,
             current: 1100,
+            retry_after: RetryAfter::seconds(60),
         };
 
         let headers = result.headers();
@@ -791,22 +1252,18 @@ mod tests {
     }
 
     #[test]
-    fn test_retry_after_minimum_value() {
-        // Reset time in the past should result in retry-after of at least 1
-        let past_reset = chrono::Utc::now().timestamp() - 10;
-        let result = RateLimitResult {
-            allowed: false,
-            limit: 100,
-            remaining: -5,
-            reset_at: past_reset,
-            current: 105,
-        };
-
-        let headers = result.headers();
-        for (name, value) in &headers {
-            if name.as_str() == "retry-after" {
-                let retry_after: i64 = value.to_str().unwrap().parse().unwrap();
-                assert!(retry_after >= 1);
+    fn test_retry_after_within_window() {
+        // Every second of the window, including the last one before it turns over
+        for window_seconds in [1, 2, 60, 3600] {
+            for now in 1_700_000_000..1_700_000_000 + window_seconds as i64 * 2 {
+                let window = SlidingWindow::at(now, window_seconds);
+                let result = window.result(10, 0, 11);
+                let retry_after = result.retry_after.as_secs();
+                assert!(
+                    (1..=window_seconds).contains(&retry_after),
+                    "window={window_seconds} now={now} retry_after={retry_after}"
+                );
+                assert_eq!(now + retry_after as i64, result.reset_at);
             }
         }
     }
@@ -852,31 +1309,235 @@ mod tests {
 
     #[test]
     fn test_window_at_epoch() {
-        let now: i64 = 0;
-        let window_seconds: i64 = 60;
-        let current_window = now / window_seconds;
+        let window = SlidingWindow::at(0, 60);
 
-        assert_eq!(current_window, 0);
+        assert_eq!(window.current, 0);
+        assert_eq!(window.start, 0);
     }
 
     #[test]
     fn test_window_large_timestamp() {
         let now: i64 = 2000000000; // Year 2033
-        let window_seconds: i64 = 60;
-        let current_window = now / window_seconds;
+        let window = SlidingWindow::at(now, 60);
 
-        assert!(current_window > 0);
-        assert_eq!(current_window, now / window_seconds);
+        assert!(window.current > 0);
+        assert_eq!(window.current, now / 60);
     }
 
     #[test]
     fn test_previous_window_calculation() {
-        let now: i64 = 1700000000;
-        let window_seconds: i64 = 60;
-        let current_window = now / window_seconds;
-        let previous_window = current_window - 1;
+        let window = SlidingWindow::at(1700000000, 60);
+
+        assert_eq!(window.previous(), window.current - 1);
+        assert!(window.previous() >= 0);
+    }
+
+    // ===========================================
+    // Rate Limit Exemption Tests
+    // ===========================================
+
+    fn limit(rate_limit_exempt: bool) -> UserLimit {
+        let metric = crate::zion::LimitMetric {
+            limit: 1000,
+            used: 0,
+            remaining: 1000,
+        };
+        UserLimit {
+            name: "ai_usage".to_string(),
+            display_name: "AI Usage".to_string(),
+            description: None,
+            unit: None,
+            ai_input_tokens: metric.clone(),
+            ai_output_tokens: metric.clone(),
+            ai_requests: metric,
+            reset_period: None,
+            period_start: None,
+            period_end: None,
+            rate_limit_exempt,
+            organization_id: None,
+            organization_rate_limit: None,
+            logging_opt_out: false,
+            allowed_models: None,
+        }
+    }
 
-        assert_eq!(previous_window, current_window - 1);
-        assert!(previous_window >= 0 || current_window == 0);
+    fn org_limit(organization_id: &str, organization_rate_limit: Option<i64>) -> UserLimit {
+        UserLimit {
+            organization_id: Some(organization_id.to_string()),
+            organization_rate_limit,
+            ..limit(false)
+        }
+    }
+
+    #[test]
+    fn test_exemption_from_config() {
+        let exempt_ids = vec!["eval-runner".to_string()];
+        assert_eq!(
+            rate_limit_exemption(&exempt_ids, "eval-runner", &[limit(false)]),
+            Some(RateLimitExemption::Config)
+        );
+    }
+
+    #[test]
+    fn test_exemption_from_zion_flag() {
+        assert_eq!(
+            rate_limit_exemption(&[], "ext_123", &[limit(false), limit(true)]),
+            Some(RateLimitExemption::Zion)
+        );
+    }
+
+    #[test]
+    fn test_no_exemption_for_normal_user() {
+        let exempt_ids = vec!["eval-runner".to_string()];
+        assert_eq!(rate_limit_exemption(&exempt_ids, "ext_123", &[limit(false)]), None);
+        assert_eq!(rate_limit_exemption(&exempt_ids, "ext_123", &[]), None);
+    }
+
+    #[test]
+    fn test_exemption_labels() {
+        assert_eq!(RateLimitExemption::Config.as_str(), "config");
+        assert_eq!(RateLimitExemption::Zion.as_str(), "zion");
+    }
+
+    #[test]
+    fn test_quota_follows_missing_limit_policy() {
+        let mut config = crate::testing::test_config("http://zion.invalid", "http://openai.invalid/v1");
+        let other = UserLimit {
+            name: "seats".to_string(),
+            ..limit(false)
+        };
+        assert!(quota_exceeded(&config, &[limit(false)], 0).is_none());
+        assert!(quota_exceeded(&config, std::slice::from_ref(&other), 0).is_none());
+
+        config.zion.missing_limit_policy = crate::zion::MissingLimitPolicy::Zero;
+        assert!(quota_exceeded(&config, &[limit(false)], 0).is_none());
+        assert!(matches!(
+            quota_exceeded(&config, &[other], 0),
+            Some(AppError::QuotaExceeded { limit: 0, .. })
+        ));
+
+        // An ai_usage entry that is used up is refused either way
+        let mut used_up = limit(false);
+        used_up.ai_input_tokens.remaining = 0;
+        assert!(quota_exceeded(&config, &[used_up], 0).is_some());
+    }
+
+    // ===========================================
+    // Organization Scope Tests
+    // ===========================================
+
+    #[test]
+    fn test_org_requests_config() {
+        let config = RateLimitConfig::for_org_requests(250);
+        assert_eq!(config.max_requests, 250);
+        assert_eq!(config.window_seconds, 60);
+        assert_eq!(config.key_prefix, "sentinel:ratelimit:org");
+    }
+
+    #[test]
+    fn test_scope_codes_and_labels() {
+        assert_eq!(RateLimitScope::User.error_code(), "USER_RATE_LIMIT_EXCEEDED");
+        assert_eq!(RateLimitScope::Organization.error_code(), "ORG_RATE_LIMIT_EXCEEDED");
+        assert_eq!(RateLimitScope::User.as_str(), "user");
+        assert_eq!(RateLimitScope::Organization.as_str(), "org");
+        assert_eq!(RateLimitScope::Tokens.error_code(), "TOKEN_RATE_LIMIT_EXCEEDED");
+        assert_eq!(RateLimitScope::Tokens.as_str(), "tokens");
+    }
+
+    #[test]
+    fn test_org_scoped_headers() {
+        let result = RateLimitResult {
+            allowed: true,
+            limit: 500,
+            remaining: 499,
+            reset_at: 1700000060,
+            current: 1,
+            retry_after: RetryAfter::seconds(60),
+        };
+        let names: Vec<String> = result
+            .scoped_headers(RateLimitScope::Organization)
+            .iter()
+            .map(|(name, _)| name.to_string())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "x-ratelimit-org-limit",
+                "x-ratelimit-org-remaining",
+                "x-ratelimit-org-reset"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scoped_exceeded_response() {
+        let result = RateLimitResult {
+            allowed: false,
+            limit: 5,
+            remaining: -1,
+            reset_at: chrono::Utc::now().timestamp() + 30,
+            current: 6,
+            retry_after: RetryAfter::seconds(60),
+        };
+        let response = scoped_rate_limit_exceeded_response(RateLimitScope::Organization, &result);
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let headers = response.headers();
+        assert_eq!(headers["x-ratelimit-scope"], "org");
+        assert_eq!(headers["x-ratelimit-org-limit"], "5");
+        assert_eq!(headers["x-ratelimit-org-remaining"], "0");
+        assert!(headers.contains_key(header::RETRY_AFTER));
+        assert!(!headers.contains_key("x-ratelimit-limit"));
+    }
+
+    fn org_config() -> Config {
+        let mut config = crate::testing::test_config("http://zion.invalid", "http://openai.invalid/v1");
+        config.rate_limit.org_max_requests = 1000;
+        config.rate_limit.org_overrides.insert("org_big".to_string(), 5000);
+        config
+    }
+
+    #[test]
+    fn test_no_org_scope_without_organization() {
+        assert!(organization_rate_limit(&org_config(), &[limit(false)]).is_none());
+        assert!(organization_rate_limit(&org_config(), &[]).is_none());
+    }
+
+    #[test]
+    fn test_org_limit_resolution_order() {
+        let config = org_config();
+
+        // Global default
+        let (id, org) = organization_rate_limit(&config, &[limit(false), org_limit("org_1", None)]).unwrap();
+        assert_eq!(id, "org_1");
+        assert_eq!(org.max_requests, 1000);
+
+        // Config override
+        let (_, org) = organization_rate_limit(&config, &[org_limit("org_big", None)]).unwrap();
+        assert_eq!(org.max_requests, 5000);
+
+        // Zion override wins over config
+        let (_, org) = organization_rate_limit(&config, &[org_limit("org_big", Some(42))]).unwrap();
+        assert_eq!(org.max_requests, 42);
+    }
+
+    #[test]
+    fn test_rejection_window_covers_last_minute() {
+        let clock = TestClock::new(1_700_000_000);
+        let window = RejectionWindow::new().with_clock(clock.clone());
+        assert_eq!(window.rate().rate, 0.0);
+
+        window.record(true);
+        window.record(false);
+        clock.advance(Duration::from_secs(30));
+        window.record(false);
+        window.record(false);
+        let rate = window.rate();
+        assert_eq!((rate.checked, rate.rejected), (4, 1));
+        assert_eq!(rate.rate, 0.25);
+
+        // The first second's checks fall out of the window
+        clock.advance(Duration::from_secs(30));
+        let rate = window.rate();
+        assert_eq!((rate.checked, rate.rejected), (2, 0));
     }
 }
@@ -0,0 +1,54 @@
+//! Request logging
+//!
+//! Wraps every request in tower-http's `TraceLayer`, except requests to
+//! `QUIET_LOG_PATHS` (the health endpoints by default). Load balancer probes
+//! hit those several times a second per replica, so they are served as usual
+//! but without a span or request/response events, and only counted in
+//! `sentinel_quiet_requests_total`.
+
+use std::future::poll_fn;
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use tower::{Layer, Service};
+use tower_http::trace::TraceLayer;
+
+use crate::{routes::metrics::record_quiet_request, AppState};
+
+/// Request logging middleware
+///
+/// Outermost layer apart from CORS, so the span covers decompression and
+/// response compression like the global `TraceLayer` it replaces.
+pub async fn request_log_middleware(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path();
+    if state
+        .config
+        .server
+        .quiet_log_paths
+        .iter()
+        .any(|quiet| quiet == path)
+    {
+        record_quiet_request();
+        return next.run(request).await;
+    }
+
+    // `Next` is always ready and never fails
+    let mut traced = TraceLayer::new_for_http().layer(next);
+    let response = match poll_fn(|cx| traced.poll_ready(cx)).await {
+        Ok(()) => traced.call(request).await,
+        Err(never) => match never {},
+    };
+    match response {
+        Ok(response) => response.map(Body::new),
+        Err(never) => match never {},
+    }
+}
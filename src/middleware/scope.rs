@@ -0,0 +1,155 @@
+//! Per-route token scopes
+//!
+//! Zion profiles carry the token's `scopes` (e.g. `chat`, `embeddings`). Routes
+//! that need one are registered with [`scope_middleware`] and the scope as its
+//! state; a token without that scope (or `*`) gets 403 `insufficient_scope`
+//! naming the scope. Routes registered without a scope accept any token.
+//! The `/v1` pass-through endpoints (images, audio, files, ...) need
+//! `passthrough`.
+//!
+//! Admin endpoints take a token with the `admin` scope as an alternative to
+//! `X-Admin-Key` (see `routes::admin::admin_auth_middleware`); that scope
+//! must be claimed explicitly.
+//!
+//! Tokens whose profile has no `scopes` field predate scoping and keep full
+//! access, except to admin endpoints, unless `AUTH_UNSCOPED_FULL_ACCESS=false`.
+
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use tracing::warn;
+
+use crate::{
+    error::{ErrorBody, ErrorResponse},
+    middleware::auth::AuthenticatedUser,
+};
+
+/// Error code for tokens missing the route's scope
+pub const INSUFFICIENT_SCOPE_CODE: &str = "insufficient_scope";
+
+/// Scope granting every other scope
+pub const WILDCARD_SCOPE: &str = "*";
+
+/// Chat, completions and responses (including native chat)
+pub const CHAT_SCOPE: &str = "chat";
+
+/// Embeddings
+pub const EMBEDDINGS_SCOPE: &str = "embeddings";
+
+/// `/v1` endpoints forwarded without parsing (images, audio, files, ...)
+pub const PASSTHROUGH_SCOPE: &str = "passthrough";
+
+/// Admin endpoints
+pub const ADMIN_SCOPE: &str = "admin";
+
+/// Scopes granted to a request's token
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenScopes {
+    /// Every scope (an unscoped legacy token)
+    All,
+    /// Only the listed scopes
+    Only(Vec<String>),
+}
+
+impl TokenScopes {
+    /// Scopes for a profile's `scopes` claim
+    ///
+    /// A missing claim grants everything when `unscoped_full_access` is set
+    /// and nothing otherwise.
+    pub fn resolve(claimed: Option<Vec<String>>, unscoped_full_access: bool) -> Self {
+        match claimed {
+            Some(scopes) => TokenScopes::Only(scopes),
+            None if unscoped_full_access => TokenScopes::All,
+            None => TokenScopes::Only(Vec::new()),
+        }
+    }
+
+    /// Whether the token may call a route requiring `scope`
+    pub fn allows(&self, scope: &str) -> bool {
+        match self {
+            TokenScopes::All => true,
+            TokenScopes::Only(scopes) => scopes
+                .iter()
+                .any(|granted| granted == scope || granted == WILDCARD_SCOPE),
+        }
+    }
+}
+
+/// 403 `insufficient_scope` naming the required scope
+pub fn insufficient_scope(required: &str) -> Response {
+    let error_response = ErrorResponse {
+        error: ErrorBody {
+            code: INSUFFICIENT_SCOPE_CODE.to_string(),
+            message: format!("This token lacks the '{}' scope required here", required),
+            retryable: false,
+            retry_after_ms: None,
+            details: None,
+        },
+    };
+    (StatusCode::FORBIDDEN, Json(error_response)).into_response()
+}
+
+/// Scope check middleware, registered per route with the required scope
+///
+/// Runs after auth (route layers run inside the router's middleware).
+pub async fn scope_middleware(
+    State(required): State<&'static str>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(user) = request.extensions().get::<AuthenticatedUser>() else {
+        return next.run(request).await;
+    };
+    if !user.scopes.allows(required) {
+        warn!(
+            external_id = %user.log_id(),
+            required_scope = required,
+            path = %request.uri().path(),
+            "Request rejected: token lacks the route's scope"
+        );
+        return insufficient_scope(required);
+    }
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn only(scopes: &[&str]) -> TokenScopes {
+        TokenScopes::Only(scopes.iter().map(|scope| scope.to_string()).collect())
+    }
+
+    #[test]
+    fn test_resolve_unscoped_tokens() {
+        assert_eq!(TokenScopes::resolve(None, true), TokenScopes::All);
+        assert_eq!(TokenScopes::resolve(None, false), only(&[]));
+        assert_eq!(
+            TokenScopes::resolve(Some(vec!["chat".to_string()]), true),
+            only(&["chat"])
+        );
+    }
+
+    #[test]
+    fn test_allows() {
+        assert!(TokenScopes::All.allows(CHAT_SCOPE));
+        assert!(only(&["embeddings"]).allows(EMBEDDINGS_SCOPE));
+        assert!(!only(&["embeddings"]).allows(CHAT_SCOPE));
+        assert!(only(&["*"]).allows(CHAT_SCOPE));
+        // An explicitly empty claim grants nothing
+        assert!(!only(&[]).allows(CHAT_SCOPE));
+    }
+
+    #[test]
+    fn test_admin_needs_an_explicit_claim() {
+        // What `auth::claimed_scopes` resolves an unscoped token to
+        assert!(!TokenScopes::resolve(None, false).allows(ADMIN_SCOPE));
+        assert!(!only(&["chat", "embeddings"]).allows(ADMIN_SCOPE));
+        assert!(only(&["admin"]).allows(ADMIN_SCOPE));
+        assert!(only(&["*"]).allows(ADMIN_SCOPE));
+    }
+}
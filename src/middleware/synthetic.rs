@@ -0,0 +1,82 @@
+//! Synthetic traffic marking
+//!
+//! Synthetic monitors and health probes call the public endpoints like any
+//! client. Users listed in `SYNTHETIC_EXTERNAL_IDS` may send
+//! `X-Sentinel-Synthetic: true` to keep those requests out of Zion usage
+//! increments, the local daily aggregates and the ledger. They are still
+//! rate-limited and logged: the request runs in a `synthetic` span and is
+//! counted in `sentinel_synthetic_requests_total`.
+//!
+//! Anyone else sending the header is served and billed as usual; the
+//! attempt is logged as a warning and counted with `result="ignored"`.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use tracing::{info, info_span, warn, Instrument};
+
+use crate::{
+    middleware::auth::AuthenticatedUser, routes::metrics::record_synthetic_request, AppState,
+};
+
+/// Request header marking synthetic traffic
+pub const SYNTHETIC_HEADER: &str = "x-sentinel-synthetic";
+
+/// Whether the request carries `X-Sentinel-Synthetic: true`
+pub fn requested(request: &Request) -> bool {
+    request
+        .headers()
+        .get(SYNTHETIC_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.trim().eq_ignore_ascii_case("true"))
+}
+
+/// Synthetic traffic middleware
+///
+/// Runs right after auth. Requests without the header pass through untouched.
+pub async fn synthetic_middleware(
+    State(state): State<Arc<AppState>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if !requested(&request) {
+        return next.run(request).await;
+    }
+
+    let path = request.uri().path().to_string();
+    let allowed_list = &state.config.usage.synthetic_external_ids;
+    let Some(user) = request
+        .extensions_mut()
+        .get_mut::<AuthenticatedUser>()
+        .filter(|user| allowed_list.contains(&user.external_id))
+    else {
+        let external_id = request
+            .extensions()
+            .get::<AuthenticatedUser>()
+            .map(|user| user.external_id.clone())
+            .unwrap_or_default();
+        warn!(
+            external_id = %external_id,
+            path = %path,
+            "X-Sentinel-Synthetic ignored: user not on the synthetic allow-list"
+        );
+        record_synthetic_request("ignored");
+        return next.run(request).await;
+    };
+
+    user.synthetic = true;
+    let external_id = user.external_id.clone();
+    info!(
+        external_id = %external_id,
+        path = %path,
+        "Synthetic request: usage is not tracked"
+    );
+    record_synthetic_request("excluded");
+    next.run(request)
+        .instrument(info_span!("synthetic", synthetic = true, external_id = %external_id))
+        .await
+}
@@ -0,0 +1,351 @@
+//! Token-based rate limiting
+//!
+//! Runs inside the rate limiting middleware, next to the request counts: a
+//! request's prompt tokens are estimated from its body before it is
+//! forwarded and charged to the user's token window (same sliding-window
+//! scheme, keyed `sentinel:ratelimit:tokens:{external_id}:{window}`). The
+//! charge travels on the request's [`AuthenticatedUser`] and is settled with
+//! the provider's input and output counts when the usage is tracked, which
+//! for streams is after the stream has completed.
+//!
+//! [`AuthenticatedUser`]: super::auth::AuthenticatedUser
+
+use std::fmt;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    extract::Request,
+    response::{IntoResponse, Response},
+};
+use serde_json::Value;
+
+use crate::{
+    clock::SharedClock,
+    config::Config,
+    middleware::rate_limiter::{increment_window, RateLimitConfig},
+    routes::body::JsonBodyRejection,
+    tokens::SharedTokenCounter,
+    usage::limits::AI_USAGE,
+    zion::{resolve_limit, UserLimit},
+    AppState,
+};
+
+/// Length of a token window in seconds (tokens per minute)
+pub const TOKEN_WINDOW_SECONDS: u64 = 60;
+
+/// Model whose encoding counts requests that don't name one (native requests)
+const DEFAULT_COUNTING_MODEL: &str = "gpt-4o";
+
+/// Body fields whose text is sent to the model as prompt
+const PROMPT_FIELDS: &[&str] = &["messages", "prompt", "input", "instructions", "system"];
+
+/// Token window of a user, or None when token limiting is off
+///
+/// The ceiling is `RATE_LIMIT_MAX_TOKENS_PER_WINDOW`, lowered to the
+/// `aiInputTokens` remaining on the `ai_usage` limit when Zion reports less.
+/// Other limits don't count; a missing `ai_usage` entry follows
+/// `MISSING_LIMIT_POLICY`.
+pub fn token_rate_limit(config: &Config, limits: &[UserLimit]) -> Option<RateLimitConfig> {
+    let default = config.rate_limit.max_tokens_per_window;
+    if default <= 0 {
+        return None;
+    }
+    let usage = resolve_limit(limits, AI_USAGE, config.zion.missing_limit_policy);
+    let max_tokens = default.min(usage.ai_input_tokens.remaining.max(0));
+    Some(RateLimitConfig::for_tokens(max_tokens, TOKEN_WINDOW_SECONDS))
+}
+
+/// Estimate the prompt tokens of a JSON request body
+///
+/// Counts the text of messages, prompts, inputs and instructions (of every
+/// item of a native batch), with the encoding of the requested model.
+/// Images and other non-text parts are not counted.
+pub fn estimate_prompt_tokens(counter: &SharedTokenCounter, body: &Value) -> i64 {
+    let mut text = String::new();
+    collect_prompt_text(body, &mut text);
+    if text.is_empty() {
+        return 0;
+    }
+    let model = body
+        .get("model")
+        .and_then(Value::as_str)
+        .unwrap_or(DEFAULT_COUNTING_MODEL);
+    let tokens = counter
+        .count_tokens(model, &text)
+        .unwrap_or(text.len() / 4);
+    i64::try_from(tokens).unwrap_or(i64::MAX)
+}
+
+fn collect_prompt_text(body: &Value, out: &mut String) {
+    for field in PROMPT_FIELDS {
+        if let Some(value) = body.get(field) {
+            collect_text(value, out);
+        }
+    }
+    if let Some(items) = body.get("requests").and_then(Value::as_array) {
+        for item in items {
+            collect_prompt_text(item, out);
+        }
+    }
+}
+
+/// Text of a string, a list, or a message or content part (`content` / `text`)
+fn collect_text(value: &Value, out: &mut String) {
+    match value {
+        Value::String(text) => {
+            out.push_str(text);
+            out.push('\n');
+        }
+        Value::Array(items) => items.iter().for_each(|item| collect_text(item, out)),
+        Value::Object(object) => {
+            for key in ["content", "text"] {
+                if let Some(value) = object.get(key) {
+                    collect_text(value, out);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Estimate a request's prompt tokens, handing the request back with its body
+///
+/// Bodies that aren't JSON count as 0 tokens; bodies over `limit` bytes
+/// (`MAX_REQUEST_BODY_BYTES`) are refused with a 413.
+pub async fn estimate_request(
+    counter: &SharedTokenCounter,
+    request: Request,
+    limit: usize,
+) -> Result<(Request, i64), Response> {
+    let (parts, body) = request.into_parts();
+    let body = axum::body::to_bytes(body, limit)
+        .await
+        .map_err(|_| JsonBodyRejection::too_large(limit).into_response())?;
+    let estimate = serde_json::from_slice::<Value>(&body)
+        .map(|value| estimate_prompt_tokens(counter, &value))
+        .unwrap_or(0);
+    Ok((Request::from_parts(parts, Body::from(body)), estimate))
+}
+
+/// Prompt tokens charged to a user's token window before forwarding
+///
+/// Cloned along with the request's `AuthenticatedUser`; every clone settles
+/// against the same estimate, so it is credited back exactly once.
+#[derive(Clone)]
+pub struct TokenCharge {
+    redis: Option<redis::aio::ConnectionManager>,
+    clock: SharedClock,
+    user_id: String,
+    config: RateLimitConfig,
+    /// Estimated prompt tokens not yet replaced by actual counts
+    outstanding: Arc<AtomicI64>,
+}
+
+impl fmt::Debug for TokenCharge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenCharge")
+            .field("user_id", &self.user_id)
+            .field("limit", &self.config.max_requests)
+            .field("outstanding", &self.outstanding.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+impl TokenCharge {
+    pub fn new(state: &AppState, user_id: &str, config: RateLimitConfig, estimate: i64) -> Self {
+        Self {
+            redis: state.redis.clone(),
+            clock: state.clock.clone(),
+            user_id: user_id.to_string(),
+            config,
+            outstanding: Arc::new(AtomicI64::new(estimate)),
+        }
+    }
+
+    /// Tokens to add to the window for a tracked response: its actual input
+    /// and output, less the estimate charged up front. The first response
+    /// takes the whole estimate (negative when it was too high); later items
+    /// of a native batch are charged in full.
+    pub fn adjustment(&self, input_tokens: u64, output_tokens: u64) -> i64 {
+        let input = i64::try_from(input_tokens).unwrap_or(i64::MAX);
+        let output = i64::try_from(output_tokens).unwrap_or(i64::MAX);
+        let credited = self.outstanding.swap(0, Ordering::Relaxed);
+        input.saturating_add(output) - credited
+    }
+
+    /// Replace the estimate with the actual counts (fire-and-forget)
+    pub fn settle(&self, input_tokens: u64, output_tokens: u64) {
+        let amount = self.adjustment(input_tokens, output_tokens);
+        let Some(redis) = self.redis.clone() else {
+            return;
+        };
+        if amount == 0 {
+            return;
+        }
+        let (clock, user_id, config) = (self.clock.clone(), self.user_id.clone(), self.config.clone());
+        tokio::spawn(async move {
+            if let Err(e) = increment_window(&redis, clock.now_unix(), &user_id, &config, amount).await {
+                tracing::warn!(error = %e, "Failed to settle token rate limit usage");
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::system_clock;
+    use crate::zion::{LimitMetric, MissingLimitPolicy};
+    use serde_json::json;
+
+    fn config(max_tokens_per_window: i64) -> Config {
+        let mut config = crate::testing::test_config("http://zion.invalid", "http://openai.invalid/v1");
+        config.rate_limit.max_tokens_per_window = max_tokens_per_window;
+        config
+    }
+
+    fn limit(input_remaining: i64) -> UserLimit {
+        UserLimit {
+            name: "ai_usage".to_string(),
+            display_name: "AI Usage".to_string(),
+            description: None,
+            unit: None,
+            ai_input_tokens: LimitMetric {
+                limit: 100_000,
+                used: 100_000 - input_remaining,
+                remaining: input_remaining,
+            },
+            ai_output_tokens: LimitMetric::unlimited(),
+            ai_requests: LimitMetric::unlimited(),
+            reset_period: None,
+            period_start: None,
+            period_end: None,
+            rate_limit_exempt: false,
+            organization_id: None,
+            organization_rate_limit: None,
+            logging_opt_out: false,
+            allowed_models: None,
+        }
+    }
+
+    fn charge(estimate: i64) -> TokenCharge {
+        TokenCharge {
+            redis: None,
+            clock: system_clock(),
+            user_id: "ext_1".to_string(),
+            config: RateLimitConfig::for_tokens(1000, TOKEN_WINDOW_SECONDS),
+            outstanding: Arc::new(AtomicI64::new(estimate)),
+        }
+    }
+
+    #[test]
+    fn test_token_limit_off_by_default() {
+        assert!(token_rate_limit(&config(0), &[limit(500)]).is_none());
+    }
+
+    #[test]
+    fn test_token_limit_lowered_to_zion_remaining() {
+        let window = token_rate_limit(&config(10_000), &[]).unwrap();
+        assert_eq!(window.max_requests, 10_000);
+        assert_eq!(window.window_seconds, 60);
+        assert_eq!(window.key_prefix, "sentinel:ratelimit:tokens");
+
+        let window = token_rate_limit(&config(10_000), &[limit(50_000), limit(2_500)]).unwrap();
+        assert_eq!(window.max_requests, 2_500);
+
+        // Overdrawn periods allow nothing
+        let window = token_rate_limit(&config(10_000), &[limit(-20)]).unwrap();
+        assert_eq!(window.max_requests, 0);
+
+        let unlimited = UserLimit {
+            ai_input_tokens: LimitMetric::unlimited(),
+            ..limit(0)
+        };
+        let window = token_rate_limit(&config(10_000), &[unlimited]).unwrap();
+        assert_eq!(window.max_requests, 10_000);
+    }
+
+    #[test]
+    fn test_token_limit_reads_ai_usage_only() {
+        let other = UserLimit {
+            name: "image_generation".to_string(),
+            ..limit(0)
+        };
+        let window = token_rate_limit(&config(10_000), &[other.clone(), limit(2_500)]).unwrap();
+        assert_eq!(window.max_requests, 2_500);
+
+        // Without an ai_usage entry, MISSING_LIMIT_POLICY decides
+        let window = token_rate_limit(&config(10_000), std::slice::from_ref(&other)).unwrap();
+        assert_eq!(window.max_requests, 10_000);
+        let mut zero = config(10_000);
+        zero.zion.missing_limit_policy = MissingLimitPolicy::Zero;
+        let window = token_rate_limit(&zero, &[other]).unwrap();
+        assert_eq!(window.max_requests, 0);
+    }
+
+    #[test]
+    fn test_estimate_counts_prompt_text_only() {
+        let counter = SharedTokenCounter::new();
+        let text = "The quick brown fox jumps over the lazy dog";
+        let plain = estimate_prompt_tokens(
+            &counter,
+            &json!({"model": "gpt-4o", "messages": [{"role": "user", "content": text}]}),
+        );
+        assert!(plain > 5, "estimate was {}", plain);
+
+        // Content parts: text counts, images don't
+        let parts = estimate_prompt_tokens(
+            &counter,
+            &json!({"model": "gpt-4o", "messages": [{"role": "user", "content": [
+                {"type": "text", "text": text},
+                {"type": "image_url", "image_url": {"url": "https://example.com/a-very-long-image-url.png"}}
+            ]}]}),
+        );
+        assert_eq!(parts, plain);
+
+        // Parameters aren't prompt
+        let with_params = estimate_prompt_tokens(
+            &counter,
+            &json!({"model": "gpt-4o", "temperature": 0.5, "user": "someone",
+                    "messages": [{"role": "user", "content": text}]}),
+        );
+        assert_eq!(with_params, plain);
+
+        assert_eq!(estimate_prompt_tokens(&counter, &json!({"model": "gpt-4o"})), 0);
+    }
+
+    #[test]
+    fn test_estimate_covers_other_request_shapes() {
+        let counter = SharedTokenCounter::new();
+        for body in [
+            json!({"model": "gpt-4o", "input": "Summarize this document"}),
+            json!({"model": "gpt-3.5-turbo-instruct", "prompt": ["first", "second"]}),
+            json!({"tier": "simple", "messages": [{"role": "user", "content": "Hello"}]}),
+        ] {
+            assert!(estimate_prompt_tokens(&counter, &body) > 0, "{}", body);
+        }
+
+        // Native batches count every item
+        let item = json!({"tier": "simple", "messages": [{"role": "user", "content": "Hello there"}]});
+        let single = estimate_prompt_tokens(&counter, &item);
+        let batch = estimate_prompt_tokens(&counter, &json!({"requests": [item.clone(), item]}));
+        assert_eq!(batch, single * 2);
+    }
+
+    #[test]
+    fn test_charge_settles_estimate_once() {
+        // Underestimate: the difference and the output are added
+        assert_eq!(charge(100).adjustment(120, 30), 50);
+
+        // Overestimate: the excess is given back
+        assert_eq!(charge(100).adjustment(60, 10), -30);
+
+        // Batch items share one estimate
+        let batch = charge(100);
+        let item = batch.clone();
+        assert_eq!(batch.adjustment(45, 5), -50);
+        assert_eq!(item.adjustment(55, 5), 60);
+    }
+}
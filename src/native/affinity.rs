@@ -0,0 +1,92 @@
+//! Sticky routing hints for load-balanced replicas
+//!
+//! With `AFFINITY_SECRET` set, native responses in a conversation carry an
+//! `X-Sentinel-Affinity` header: an HMAC-SHA256 of the `conversation_id`. A
+//! load balancer can hash on it (or a client echo it back) so a conversation
+//! keeps hitting the same replica. When a request presents a valid hint, the
+//! replica serves the session from its local copy if that copy is fresh
+//! (`AFFINITY_LOCAL_TTL_SECONDS`) instead of reading it from Redis again.
+//!
+//! Redis stays the source of truth: every session write goes there first, so
+//! a missing, stale or forged hint only costs the Redis read.
+
+use sha2::{Digest, Sha256};
+
+/// Header carrying the routing hint
+pub const AFFINITY_HEADER: &str = "X-Sentinel-Affinity";
+
+/// SHA-256 block size in bytes
+const BLOCK_SIZE: usize = 64;
+
+/// Routing hint for a conversation (lowercase hex)
+pub fn hint(secret: &str, conversation_id: &str) -> String {
+    hex::encode(hmac_sha256(secret.as_bytes(), conversation_id.as_bytes()))
+}
+
+/// Whether `presented` is the hint for this conversation
+///
+/// Compared in constant time, so a forged hint can't be guessed byte by byte.
+pub fn verify(secret: &str, conversation_id: &str, presented: &str) -> bool {
+    let expected = hint(secret, conversation_id);
+    let presented = presented.trim().to_ascii_lowercase();
+    expected.len() == presented.len()
+        && expected
+            .bytes()
+            .zip(presented.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// HMAC-SHA256 (RFC 2104)
+pub(crate) fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let pad = |byte: u8| block.map(|k| k ^ byte);
+    let inner = Sha256::new()
+        .chain_update(pad(0x36))
+        .chain_update(message)
+        .finalize();
+    Sha256::new()
+        .chain_update(pad(0x5c))
+        .chain_update(inner)
+        .finalize()
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hmac_matches_rfc_4231() {
+        // Test case 2
+        assert_eq!(
+            hint("Jefe", "what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        // Test case 6: key longer than the block size
+        assert_eq!(
+            hex::encode(hmac_sha256(
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            )),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+    }
+
+    #[test]
+    fn test_verify() {
+        let hint = hint("secret", "conv-1");
+        assert!(verify("secret", "conv-1", &hint));
+        assert!(verify("secret", "conv-1", &hint.to_ascii_uppercase()));
+        assert!(!verify("secret", "conv-2", &hint));
+        assert!(!verify("other", "conv-1", &hint));
+        assert!(!verify("secret", "conv-1", &hint[..63]));
+        assert!(!verify("secret", "conv-1", ""));
+    }
+}
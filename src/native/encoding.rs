@@ -0,0 +1,202 @@
+//! Response encodings for the native API
+//!
+//! Non-streaming native chat responses, and the errors of those requests, are
+//! MessagePack instead of JSON when the client prefers `application/msgpack`
+//! in its `Accept` header. Maps keep their field names (`to_vec_named`), so a
+//! MessagePack body decodes into the same structs as the JSON one. Anything
+//! else, including `application/x-protobuf` (no generated types exist for the
+//! native API), gets JSON. Streaming responses are always SSE with JSON events.
+
+use axum::{
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use serde_json::json;
+use tracing::error;
+
+/// Content type of MessagePack responses
+pub const MSGPACK_CONTENT_TYPE: &str = "application/msgpack";
+
+/// Media types accepted as a request for MessagePack
+const MSGPACK_TYPES: &[&str] = &[
+    MSGPACK_CONTENT_TYPE,
+    "application/x-msgpack",
+    "application/vnd.msgpack",
+];
+
+/// Media types that JSON satisfies
+const JSON_TYPES: &[&str] = &["application/json", "application/*", "*/*"];
+
+/// Body encoding of a native response
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResponseFormat {
+    #[default]
+    Json,
+    MessagePack,
+}
+
+impl ResponseFormat {
+    /// Format preferred by the request's `Accept` header
+    ///
+    /// The highest `q` wins, ties go to the type listed first. JSON is used
+    /// when MessagePack isn't preferred or the header is missing.
+    pub fn negotiate(headers: &HeaderMap) -> Self {
+        let mut best: Option<(f32, Self)> = None;
+        let ranges = headers
+            .get_all(header::ACCEPT)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','));
+
+        for range in ranges {
+            let mut parts = range.split(';').map(str::trim);
+            let media_type = parts.next().unwrap_or_default().to_ascii_lowercase();
+            let format = if MSGPACK_TYPES.contains(&media_type.as_str()) {
+                Self::MessagePack
+            } else if JSON_TYPES.contains(&media_type.as_str()) {
+                Self::Json
+            } else {
+                continue;
+            };
+            let q = parts
+                .filter_map(|param| param.strip_prefix("q="))
+                .find_map(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            if q > 0.0 && best.is_none_or(|(best_q, _)| q > best_q) {
+                best = Some((q, format));
+            }
+        }
+
+        best.map_or(Self::Json, |(_, format)| format)
+    }
+
+    /// `Content-Type` of responses in this format
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::MessagePack => MSGPACK_CONTENT_TYPE,
+        }
+    }
+
+    /// Serialize `body` as a response in this format
+    pub fn response<T: Serialize>(self, status: StatusCode, body: &T) -> Response {
+        match self {
+            Self::Json => (status, Json(body)).into_response(),
+            Self::MessagePack => match rmp_serde::to_vec_named(body) {
+                Ok(bytes) => (
+                    status,
+                    [(
+                        header::CONTENT_TYPE,
+                        HeaderValue::from_static(MSGPACK_CONTENT_TYPE),
+                    )],
+                    bytes,
+                )
+                    .into_response(),
+                Err(e) => {
+                    error!(error = %e, "Failed to encode MessagePack response");
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(json!({
+                            "error": {
+                                "message": "Failed to encode response",
+                                "type": "server_error",
+                                "code": "internal_error",
+                                "retryable": false
+                            }
+                        })),
+                    )
+                        .into_response()
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::native::response::{ChatCompletionResponse, Choice, ChoiceMessage, Usage};
+    use crate::native::types::Role;
+    use axum::body::to_bytes;
+
+    fn accept(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_negotiate() {
+        assert_eq!(
+            ResponseFormat::negotiate(&HeaderMap::new()),
+            ResponseFormat::Json
+        );
+        assert_eq!(
+            ResponseFormat::negotiate(&accept("application/msgpack")),
+            ResponseFormat::MessagePack
+        );
+        assert_eq!(
+            ResponseFormat::negotiate(&accept("application/x-msgpack, application/json")),
+            ResponseFormat::MessagePack
+        );
+        assert_eq!(
+            ResponseFormat::negotiate(&accept("application/json, application/msgpack")),
+            ResponseFormat::Json
+        );
+        assert_eq!(
+            ResponseFormat::negotiate(&accept("application/json;q=0.5, application/msgpack;q=0.9")),
+            ResponseFormat::MessagePack
+        );
+        assert_eq!(
+            ResponseFormat::negotiate(&accept("*/*, application/msgpack;q=0.1")),
+            ResponseFormat::Json
+        );
+        assert_eq!(
+            ResponseFormat::negotiate(&accept("application/msgpack;q=0")),
+            ResponseFormat::Json
+        );
+        // Unknown or unsupported types fall back to JSON
+        for value in ["application/x-protobuf", "text/html", "garbage;;"] {
+            assert_eq!(
+                ResponseFormat::negotiate(&accept(value)),
+                ResponseFormat::Json
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_msgpack_round_trip() {
+        let body = ChatCompletionResponse {
+            id: "chatcmpl-1".to_string(),
+            object: "chat.completion".to_string(),
+            created: 1_700_000_000,
+            model: "gpt-4o-mini".to_string(),
+            system_fingerprint: Some("fp_1".to_string()),
+            choices: vec![Choice {
+                index: 0,
+                message: ChoiceMessage {
+                    role: Role::Assistant,
+                    content: Some("Hello".to_string()),
+                    tool_calls: None,
+                },
+                finish_reason: Some("stop".to_string()),
+            }],
+            usage: Usage {
+                prompt_tokens: 10,
+                completion_tokens: 5,
+                total_tokens: 15,
+            },
+        };
+
+        let response = ResponseFormat::MessagePack.response(StatusCode::OK, &body);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            MSGPACK_CONTENT_TYPE
+        );
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let decoded: ChatCompletionResponse = rmp_serde::from_slice(&bytes).unwrap();
+        assert_eq!(decoded, body);
+    }
+}
@@ -3,14 +3,19 @@
 //! Provides OpenAI-compatible error responses for all Native API endpoints.
 //! Errors are wrapped in a consistent JSON format that matches OpenAI's error structure.
 
+use std::time::Duration;
+
 use axum::{
-    http::{HeaderMap, HeaderValue, StatusCode},
+    http::{HeaderMap, StatusCode},
     response::{IntoResponse, Response},
-    Json,
 };
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
+use super::encoding::ResponseFormat;
+use crate::error::RetryAfter;
+use crate::proxy::content_filter::{CONTENT_BLOCKED_CODE, CONTENT_BLOCKED_MESSAGE};
+
 /// Native API error with OpenAI-compatible structure
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
 pub struct NativeError {
@@ -28,6 +33,14 @@ pub struct NativeError {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[schema(example = "openai")]
     pub provider: Option<String>,
+    /// Whether the same request may succeed later
+    #[serde(default)]
+    #[schema(example = false)]
+    pub retryable: bool,
+    /// How long to wait before retrying, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = 30000)]
+    pub retry_after_ms: Option<u64>,
 }
 
 /// Wrapper for error responses matching OpenAI's format
@@ -35,17 +48,6 @@ pub struct NativeError {
 pub struct NativeErrorResponse {
     /// The error details
     pub error: NativeError,
-    /// Rate limit information (internal use, not serialized in response body)
-    #[serde(skip)]
-    #[schema(ignore)]
-    pub rate_limit_info: Option<RateLimitInfo>,
-}
-
-/// Rate limit information for 429 responses
-#[derive(Debug, Clone, PartialEq)]
-pub struct RateLimitInfo {
-    /// Seconds until retry is allowed
-    pub retry_after: Option<u64>,
 }
 
 impl NativeErrorResponse {
@@ -59,38 +61,77 @@ impl NativeErrorResponse {
                 error_type: "invalid_request_error".to_string(),
                 code: "invalid_request".to_string(),
                 provider: None,
+                retryable: false,
+                retry_after_ms: None,
             },
-            rate_limit_info: None,
         }
     }
 
     /// Create a provider error (502 Bad Gateway)
     ///
-    /// Use when an upstream provider returns an error. Includes provider hint.
-    pub fn provider_error(message: impl Into<String>, provider: &str) -> Self {
+    /// Use when an upstream provider returns an error. Includes provider hint;
+    /// `retryable` comes from the upstream failure ([`AppError::retryable`]).
+    ///
+    /// [`AppError::retryable`]: crate::error::AppError::retryable
+    pub fn provider_error(message: impl Into<String>, provider: &str, retryable: bool) -> Self {
         Self {
             error: NativeError {
                 message: message.into(),
                 error_type: "upstream_error".to_string(),
                 code: "provider_error".to_string(),
                 provider: Some(provider.to_string()),
+                retryable,
+                retry_after_ms: None,
             },
-            rate_limit_info: None,
         }
     }
 
     /// Create a rate limit error (429 Too Many Requests)
     ///
     /// Use when request rate exceeds limits. Optionally includes Retry-After header.
-    pub fn rate_limited(message: impl Into<String>, retry_after: Option<u64>) -> Self {
+    pub fn rate_limited(message: impl Into<String>, retry_after: Option<RetryAfter>) -> Self {
         Self {
             error: NativeError {
                 message: message.into(),
                 error_type: "rate_limit_error".to_string(),
                 code: "rate_limit_exceeded".to_string(),
                 provider: None,
+                retryable: true,
+                retry_after_ms: retry_after.map(RetryAfter::as_millis),
+            },
+        }
+    }
+
+    /// Create a quota exceeded error (429 Too Many Requests)
+    ///
+    /// Use when a usage quota is used up. Not retryable until the quota's
+    /// period ends, which `retry_after` gives when known.
+    pub fn quota_exceeded(message: impl Into<String>, retry_after: Option<RetryAfter>) -> Self {
+        Self {
+            error: NativeError {
+                message: message.into(),
+                error_type: "rate_limit_error".to_string(),
+                code: "quota_exceeded".to_string(),
+                provider: None,
+                retryable: false,
+                retry_after_ms: retry_after.map(RetryAfter::as_millis),
+            },
+        }
+    }
+
+    /// Create a not acceptable error (406 Not Acceptable)
+    ///
+    /// Use when the `Accept` header excludes the response the request asks for.
+    pub fn not_acceptable(message: impl Into<String>) -> Self {
+        Self {
+            error: NativeError {
+                message: message.into(),
+                error_type: "not_acceptable_error".to_string(),
+                code: "not_acceptable".to_string(),
+                provider: None,
+                retryable: false,
+                retry_after_ms: None,
             },
-            rate_limit_info: Some(RateLimitInfo { retry_after }),
         }
     }
 
@@ -104,8 +145,25 @@ impl NativeErrorResponse {
                 error_type: "server_error".to_string(),
                 code: "internal_error".to_string(),
                 provider: None,
+                retryable: false,
+                retry_after_ms: None,
+            },
+        }
+    }
+
+    /// Create a content filter error (451 Unavailable For Legal Reasons)
+    ///
+    /// Use when the response matched the configured blocklist.
+    pub fn content_blocked() -> Self {
+        Self {
+            error: NativeError {
+                message: CONTENT_BLOCKED_MESSAGE.to_string(),
+                error_type: "content_filter_error".to_string(),
+                code: CONTENT_BLOCKED_CODE.to_string(),
+                provider: None,
+                retryable: false,
+                retry_after_ms: None,
             },
-            rate_limit_info: None,
         }
     }
 
@@ -119,8 +177,114 @@ impl NativeErrorResponse {
                 error_type: "service_unavailable".to_string(),
                 code: "service_unavailable".to_string(),
                 provider: None,
+                retryable: true,
+                retry_after_ms: None,
+            },
+        }
+    }
+
+    /// Create an upstream unavailable error (503 Service Unavailable)
+    ///
+    /// Use when the provider endpoint's circuit breaker is open. Includes the
+    /// provider hint and a Retry-After header.
+    pub fn upstream_unavailable(
+        message: impl Into<String>,
+        provider: &str,
+        retry_after: RetryAfter,
+    ) -> Self {
+        Self {
+            error: NativeError {
+                message: message.into(),
+                error_type: "service_unavailable".to_string(),
+                code: "upstream_unavailable".to_string(),
+                provider: Some(provider.to_string()),
+                retryable: true,
+                retry_after_ms: Some(retry_after.as_millis()),
+            },
+        }
+    }
+
+    /// Create an upstream timeout error (504 Gateway Timeout)
+    ///
+    /// Use when the provider did not answer within the request's timeout.
+    pub fn upstream_timeout(message: impl Into<String>) -> Self {
+        Self {
+            error: NativeError {
+                message: message.into(),
+                error_type: "timeout_error".to_string(),
+                code: "upstream_timeout".to_string(),
+                provider: None,
+                retryable: true,
+                retry_after_ms: None,
+            },
+        }
+    }
+
+    /// Create a conversation busy error (409 Conflict)
+    ///
+    /// Use when another stream in the same conversation is still running.
+    pub fn conversation_busy(conversation_id: &str) -> Self {
+        Self {
+            error: NativeError {
+                message: format!(
+                    "Another streaming request for conversation '{}' is still in progress",
+                    conversation_id
+                ),
+                error_type: "conflict_error".to_string(),
+                code: "conversation_busy".to_string(),
+                provider: None,
+                retryable: true,
+                retry_after_ms: None,
+            },
+        }
+    }
+
+    /// Create an endpoint not found error (404 Not Found)
+    ///
+    /// Use for paths under `/native` that no route serves.
+    pub fn endpoint_not_found(message: impl Into<String>) -> Self {
+        Self {
+            error: NativeError {
+                message: message.into(),
+                error_type: "not_found_error".to_string(),
+                code: "endpoint_not_found".to_string(),
+                provider: None,
+                retryable: false,
+                retry_after_ms: None,
+            },
+        }
+    }
+
+    /// Create a session not found error (404 Not Found)
+    ///
+    /// The same for sessions that don't exist and sessions of other users, so
+    /// conversation IDs can't be probed.
+    pub fn session_not_found(conversation_id: &str) -> Self {
+        Self {
+            error: NativeError {
+                message: format!("Session '{}' not found", conversation_id),
+                error_type: "not_found_error".to_string(),
+                code: "session_not_found".to_string(),
+                provider: None,
+                retryable: false,
+                retry_after_ms: None,
+            },
+        }
+    }
+
+    /// Create a method not allowed error (405 Method Not Allowed)
+    ///
+    /// Use when a native route exists but doesn't accept the request's method.
+    pub fn method_not_allowed(message: impl Into<String>) -> Self {
+        Self {
+            error: NativeError {
+                message: message.into(),
+                error_type: "method_not_allowed_error".to_string(),
+                code: "method_not_allowed".to_string(),
+                provider: None,
+                retryable: false,
+                retry_after_ms: None,
             },
-            rate_limit_info: None,
         }
     }
 
@@ -128,46 +292,76 @@ impl NativeErrorResponse {
     pub fn from_app_error(err: crate::error::AppError) -> Self {
         use crate::error::AppError;
         match err {
-            AppError::ServiceUnavailable { message, .. } => Self::service_unavailable(&message),
+            AppError::ServiceUnavailable {
+                message,
+                retry_after,
+            } => {
+                let mut response = Self::service_unavailable(message);
+                response.error.retry_after_ms = retry_after
+                    .map(RetryAfter::from_duration)
+                    .map(RetryAfter::as_millis);
+                response
+            }
+            AppError::QuotaExceeded { retry_after, .. } => {
+                Self::quota_exceeded(err.to_string(), retry_after)
+            }
             AppError::BadRequest(msg) => Self::validation(msg),
+            AppError::NotAcceptable(msg) => Self::not_acceptable(msg),
             AppError::NotFound(msg) => Self::validation(msg),
+            AppError::UpstreamTimeout { .. } => Self::upstream_timeout(err.to_string()),
+            AppError::UpstreamUnavailable {
+                ref provider,
+                ref retry_after,
+                ..
+            } => Self::upstream_unavailable(
+                err.to_string(),
+                provider,
+                RetryAfter::from_duration(*retry_after),
+            ),
+            AppError::ContentBlocked => Self::content_blocked(),
             _ => Self::internal(err.to_string()),
         }
     }
 
     /// Get the HTTP status code for this error
-    fn status_code(&self) -> StatusCode {
+    pub fn status_code(&self) -> StatusCode {
         match self.error.error_type.as_str() {
             "invalid_request_error" => StatusCode::BAD_REQUEST,
             "upstream_error" => StatusCode::BAD_GATEWAY,
             "rate_limit_error" => StatusCode::TOO_MANY_REQUESTS,
             "server_error" => StatusCode::INTERNAL_SERVER_ERROR,
             "service_unavailable" => StatusCode::SERVICE_UNAVAILABLE,
+            "timeout_error" => StatusCode::GATEWAY_TIMEOUT,
+            "content_filter_error" => StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS,
+            "conflict_error" => StatusCode::CONFLICT,
+            "not_found_error" => StatusCode::NOT_FOUND,
+            "method_not_allowed_error" => StatusCode::METHOD_NOT_ALLOWED,
+            "not_acceptable_error" => StatusCode::NOT_ACCEPTABLE,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 }
 
-impl IntoResponse for NativeErrorResponse {
-    fn into_response(self) -> Response {
+impl NativeErrorResponse {
+    /// Build the response with the body in `format` (negotiated from `Accept`)
+    pub fn into_response_as(self, format: ResponseFormat) -> Response {
         let status = self.status_code();
 
         // Build headers
         let mut headers = HeaderMap::new();
 
-        // Add Retry-After header for rate limit errors
-        if let Some(ref rate_limit_info) = self.rate_limit_info {
-            if let Some(retry_after) = rate_limit_info.retry_after {
-                if let Ok(value) = HeaderValue::from_str(&retry_after.to_string()) {
-                    headers.insert("Retry-After", value);
-                }
-            }
+        // Add Retry-After header when the body says how long to wait
+        if let Some(retry_after_ms) = self.error.retry_after_ms {
+            RetryAfter::from_duration(Duration::from_millis(retry_after_ms)).apply(&mut headers);
         }
 
-        // Create response body (excludes rate_limit_info due to #[serde(skip)])
-        let body = Json(&self);
+        (headers, format.response(status, &self)).into_response()
+    }
+}
 
-        (status, headers, body).into_response()
+impl IntoResponse for NativeErrorResponse {
+    fn into_response(self) -> Response {
+        self.into_response_as(ResponseFormat::Json)
     }
 }
 
@@ -175,6 +369,7 @@ impl IntoResponse for NativeErrorResponse {
 mod tests {
     use super::*;
     use axum::body::to_bytes;
+    use axum::http::HeaderValue;
 
     #[test]
     fn test_validation_error_json() {
@@ -191,7 +386,7 @@ mod tests {
 
     #[test]
     fn test_provider_error_includes_hint() {
-        let error = NativeErrorResponse::provider_error("Model overloaded", "openai");
+        let error = NativeErrorResponse::provider_error("Model overloaded", "openai", true);
         let json = serde_json::to_string(&error).unwrap();
 
         let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
@@ -202,15 +397,54 @@ mod tests {
 
     #[test]
     fn test_rate_limit_error_status() {
-        let error = NativeErrorResponse::rate_limited("Too many requests", Some(60));
+        let error = NativeErrorResponse::rate_limited("Too many requests", Some(RetryAfter::seconds(60)));
         let response = error.into_response();
 
         assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
     }
 
+    #[test]
+    fn test_content_blocked_from_app_error() {
+        let error = NativeErrorResponse::from_app_error(crate::error::AppError::ContentBlocked);
+        assert_eq!(error.error.code, "content_blocked");
+        assert_eq!(
+            error.into_response().status(),
+            StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS
+        );
+    }
+
+    #[test]
+    fn test_conversation_busy_status() {
+        let error = NativeErrorResponse::conversation_busy("conv-1");
+        assert_eq!(error.error.code, "conversation_busy");
+        assert!(error.error.message.contains("conv-1"));
+        assert_eq!(error.into_response().status(), StatusCode::CONFLICT);
+    }
+
+    #[test]
+    fn test_routing_error_statuses() {
+        let not_found = NativeErrorResponse::endpoint_not_found("No such endpoint");
+        assert_eq!(not_found.error.code, "endpoint_not_found");
+        assert_eq!(not_found.into_response().status(), StatusCode::NOT_FOUND);
+
+        let wrong_method = NativeErrorResponse::method_not_allowed("Use POST");
+        assert_eq!(wrong_method.error.code, "method_not_allowed");
+        assert_eq!(
+            wrong_method.into_response().status(),
+            StatusCode::METHOD_NOT_ALLOWED
+        );
+
+        let not_acceptable = NativeErrorResponse::not_acceptable("Accept excludes SSE");
+        assert_eq!(not_acceptable.error.code, "not_acceptable");
+        assert_eq!(
+            not_acceptable.into_response().status(),
+            StatusCode::NOT_ACCEPTABLE
+        );
+    }
+
     #[tokio::test]
     async fn test_rate_limit_error_includes_retry_after_header() {
-        let error = NativeErrorResponse::rate_limited("Too many requests", Some(30));
+        let error = NativeErrorResponse::rate_limited("Too many requests", Some(RetryAfter::seconds(30)));
         let response = error.into_response();
 
         assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
@@ -220,6 +454,60 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_retryable_follows_code() {
+        let errors = [
+            NativeErrorResponse::validation("bad"),
+            NativeErrorResponse::rate_limited("slow down", None),
+            NativeErrorResponse::quota_exceeded("used up", None),
+            NativeErrorResponse::internal("translation failed"),
+            NativeErrorResponse::content_blocked(),
+            NativeErrorResponse::service_unavailable("backoff"),
+            NativeErrorResponse::upstream_unavailable("open", "openai", RetryAfter::seconds(5)),
+            NativeErrorResponse::upstream_timeout("slow"),
+            NativeErrorResponse::conversation_busy("conv-1"),
+            NativeErrorResponse::endpoint_not_found("nope"),
+            NativeErrorResponse::method_not_allowed("Use POST"),
+            NativeErrorResponse::not_acceptable("no SSE"),
+        ];
+        for error in errors {
+            assert_eq!(
+                error.error.retryable,
+                crate::error::is_retryable_code(&error.error.code),
+                "{}",
+                error.error.code
+            );
+        }
+
+        let overloaded = NativeErrorResponse::provider_error("overloaded", "openai", true);
+        assert!(overloaded.error.retryable);
+        let rejected = NativeErrorResponse::provider_error("bad request", "openai", false);
+        assert!(!rejected.error.retryable);
+    }
+
+    #[test]
+    fn test_retry_after_ms_in_body() {
+        let error = NativeErrorResponse::rate_limited("slow down", Some(RetryAfter::seconds(30)));
+        let parsed = serde_json::to_value(&error).unwrap();
+        assert_eq!(parsed["error"]["retryable"], true);
+        assert_eq!(parsed["error"]["retry_after_ms"], 30_000);
+
+        let quota = NativeErrorResponse::from_app_error(crate::error::AppError::QuotaExceeded {
+            message: "Monthly quota used up".to_string(),
+            limit: 100,
+            used: 100,
+            retry_after: Some(RetryAfter::seconds(3600)),
+        });
+        assert_eq!(quota.error.code, "quota_exceeded");
+        assert!(!quota.error.retryable);
+        assert_eq!(quota.error.retry_after_ms, Some(3_600_000));
+        assert_eq!(quota.into_response().status(), StatusCode::TOO_MANY_REQUESTS);
+
+        let parsed = serde_json::to_value(NativeErrorResponse::validation("bad")).unwrap();
+        assert_eq!(parsed["error"]["retryable"], false);
+        assert!(parsed["error"].get("retry_after_ms").is_none());
+    }
+
     #[test]
     fn test_internal_error_status() {
         let error = NativeErrorResponse::internal("Database connection failed");
@@ -238,12 +526,23 @@ mod tests {
 
     #[test]
     fn test_provider_error_status() {
-        let error = NativeErrorResponse::provider_error("Upstream timeout", "anthropic");
+        let error = NativeErrorResponse::provider_error("Upstream timeout", "anthropic", true);
         let response = error.into_response();
 
         assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
     }
 
+    #[test]
+    fn test_upstream_timeout_status() {
+        let error = NativeErrorResponse::from_app_error(crate::error::AppError::UpstreamTimeout {
+            timeout_ms: 1500,
+        });
+        assert_eq!(error.error.code, "upstream_timeout");
+
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+    }
+
     #[tokio::test]
     async fn test_error_response_body_format() {
         let error = NativeErrorResponse::validation("Test error");
@@ -3,6 +3,8 @@
 //! This module defines the canonical message format that all providers translate to/from.
 //! Types are designed to be OpenAI-compatible for seamless integration with existing clients.
 
+pub mod affinity;
+pub mod encoding;
 pub mod error;
 pub mod request;
 pub mod response;
@@ -12,13 +14,16 @@ pub mod translate;
 pub mod types;
 
 // Re-export key types for convenience
-pub use request::{ChatCompletionRequest, StopSequence};
+pub use request::{
+    max_stop_sequences, validate_stop_sequences, validate_stop_value, ChatCompletionRequest,
+    StopSequence,
+};
 pub use response::{
     ChatCompletionResponse, Choice, ChoiceMessage, Delta, StreamChoice, StreamChunk,
     ToolCallDelta, ToolCallFunctionDelta, Usage,
 };
 pub use session::{Session, SessionManager};
 pub use types::{
-    Content, ContentPart, FunctionDefinition, ImageUrl, Message, Role, ToolCall,
+    Content, ContentPart, FunctionDefinition, ImageDetail, ImageUrl, Message, Role, ToolCall,
     ToolCallFunction, ToolChoice, ToolDefinition, ToolResult, ToolResultContent,
 };
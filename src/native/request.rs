@@ -7,9 +7,77 @@ use utoipa::ToSchema;
 
 use super::types::{Message, Tier, ToolChoice, ToolDefinition};
 
+/// Maximum stop sequences accepted before the provider is known (OpenAI's limit)
+pub const DEFAULT_MAX_STOP_SEQUENCES: usize = 4;
+
+/// Maximum length of a single stop sequence, in characters
+pub const MAX_STOP_SEQUENCE_CHARS: usize = 256;
+
+/// Maximum stop sequences accepted by a provider
+///
+/// Providers without a known limit get the conservative default.
+pub fn max_stop_sequences(provider: &str) -> usize {
+    match provider {
+        "openai" => 4,
+        _ => DEFAULT_MAX_STOP_SEQUENCES,
+    }
+}
+
+/// Validate stop sequences: at most `max_entries`, each non-empty and within the length cap
+///
+/// Shared by native request parsing and the `/v1` body validation so both
+/// reject the same payloads. Errors name the offending index.
+pub fn validate_stop_sequences<S: AsRef<str>>(stops: &[S], max_entries: usize) -> Result<(), String> {
+    if stops.len() > max_entries {
+        return Err(format!(
+            "stop accepts at most {} sequences, got {}",
+            max_entries,
+            stops.len()
+        ));
+    }
+    for (idx, stop) in stops.iter().enumerate() {
+        let stop = stop.as_ref();
+        if stop.is_empty() {
+            return Err(format!("stop[{}] must not be empty", idx));
+        }
+        if stop.chars().count() > MAX_STOP_SEQUENCE_CHARS {
+            return Err(format!(
+                "stop[{}] exceeds the maximum length of {} characters",
+                idx, MAX_STOP_SEQUENCE_CHARS
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Validate a raw `stop` value from a `/v1` request body
+///
+/// Accepts null, a string or an array of strings.
+pub fn validate_stop_value(value: &serde_json::Value, max_entries: usize) -> Result<(), String> {
+    match value {
+        serde_json::Value::Null => Ok(()),
+        serde_json::Value::String(stop) => validate_stop_sequences(&[stop], max_entries),
+        serde_json::Value::Array(items) => {
+            let stops = items
+                .iter()
+                .enumerate()
+                .map(|(idx, item)| {
+                    item.as_str()
+                        .ok_or_else(|| format!("stop[{}] must be a string", idx))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            validate_stop_sequences(&stops, max_entries)
+        }
+        _ => Err("stop must be a string or an array of strings".to_string()),
+    }
+}
+
 /// Stop sequence - can be a single string or array of strings
+///
+/// Validated on deserialization against the default limits; call
+/// [`StopSequence::validate`] again once the provider is known.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
-#[serde(untagged)]
+#[serde(untagged, try_from = "RawStopSequence")]
 pub enum StopSequence {
     /// Single stop sequence
     #[schema(example = "END")]
@@ -19,6 +87,42 @@ pub enum StopSequence {
     Multiple(Vec<String>),
 }
 
+impl StopSequence {
+    /// The stop sequences as a slice
+    pub fn sequences(&self) -> &[String] {
+        match self {
+            StopSequence::Single(stop) => std::slice::from_ref(stop),
+            StopSequence::Multiple(stops) => stops,
+        }
+    }
+
+    /// Validate against a provider's maximum number of entries
+    pub fn validate(&self, max_entries: usize) -> Result<(), String> {
+        validate_stop_sequences(self.sequences(), max_entries)
+    }
+}
+
+/// Unvalidated wire form of [`StopSequence`]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawStopSequence {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl TryFrom<RawStopSequence> for StopSequence {
+    type Error = String;
+
+    fn try_from(raw: RawStopSequence) -> Result<Self, Self::Error> {
+        let stop = match raw {
+            RawStopSequence::Single(stop) => StopSequence::Single(stop),
+            RawStopSequence::Multiple(stops) => StopSequence::Multiple(stops),
+        };
+        stop.validate(DEFAULT_MAX_STOP_SEQUENCES)?;
+        Ok(stop)
+    }
+}
+
 /// Chat completion request
 ///
 /// Uses `deny_unknown_fields` to ensure strict validation - requests with
@@ -52,6 +156,14 @@ pub struct ChatCompletionRequest {
     /// Stop sequences
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stop: Option<StopSequence>,
+    /// Seed for best-effort deterministic sampling
+    ///
+    /// Forwarded to providers that support it. Compare `system_fingerprint`
+    /// across responses to tell when the backend changed; without a
+    /// `conversation_id` tier routing may also pick a different model.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = 42)]
+    pub seed: Option<i64>,
     /// Whether to stream the response
     #[serde(default)]
     #[schema(example = false)]
@@ -62,12 +174,108 @@ pub struct ChatCompletionRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[schema(example = "conv-550e8400-e29b-41d4-a716-446655440000")]
     pub conversation_id: Option<String>,
+    /// Agent workflow this request belongs to (optional)
+    /// Usage is also accumulated per workflow, readable via `GET /v1/usage/workflows/{id}`.
+    /// Takes precedence over the `X-Sentinel-Workflow-Id` header.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "wf-build-1234")]
+    pub workflow_id: Option<String>,
     /// Tool definitions available to the model
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tools: Option<Vec<ToolDefinition>>,
     /// How the model should use the provided tools
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_choice: Option<ToolChoice>,
+    /// Upstream timeout in milliseconds (optional, clamped to the server's limits)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = 30000)]
+    pub timeout_ms: Option<u64>,
+    /// End-user identifier for the provider's abuse monitoring (optional)
+    /// Forwarded, hashed or dropped according to the server's `UPSTREAM_USER_FIELD` policy.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "user-1234")]
+    pub user: Option<String>,
+}
+
+/// Batch of independent chat completion requests
+///
+/// Items are kept as raw JSON so that one invalid item fails on its own
+/// instead of rejecting the whole batch.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ChatCompletionBatchRequest {
+    /// Non-streaming chat completion requests, answered in the same order
+    #[schema(value_type = Vec<ChatCompletionRequest>)]
+    pub requests: Vec<serde_json::Value>,
+}
+
+/// Token count request
+///
+/// Exactly one of `messages` and `text` must be set. Tokens are counted with
+/// `model`'s tokenizer, or with that of the first model configured for `tier`
+/// (simple by default); `model` and `tier` can't be combined.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TokenizeRequest {
+    /// Model whose tokenizer to use
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "gpt-4o-mini")]
+    pub model: Option<String>,
+    /// Tier whose first model's tokenizer to use
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "simple")]
+    pub tier: Option<Tier>,
+    /// Conversation to count, message by message
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub messages: Option<Vec<Message>>,
+    /// Plain text to count
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "How many tokens is this?")]
+    pub text: Option<String>,
+    /// Token budget to suggest a truncation for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(minimum = 1, example = 4000)]
+    pub max_tokens: Option<u64>,
+}
+
+/// Text to embed: one string or a list of strings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(untagged)]
+pub enum EmbeddingsInput {
+    /// A single text
+    Text(String),
+    /// Several texts, embedded in order
+    Texts(Vec<String>),
+}
+
+impl EmbeddingsInput {
+    /// The texts to embed, in order
+    pub fn texts(&self) -> Vec<&str> {
+        match self {
+            Self::Text(text) => vec![text.as_str()],
+            Self::Texts(texts) => texts.iter().map(String::as_str).collect(),
+        }
+    }
+}
+
+/// Embeddings request
+///
+/// The embedding model is the one the tier config sets for `tier` (simple by
+/// default).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct EmbeddingsRequest {
+    /// Text or texts to embed
+    #[schema(example = json!(["The quick brown fox", "jumps over the lazy dog"]))]
+    pub input: EmbeddingsInput,
+    /// Tier whose embedding model to use
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "simple")]
+    pub tier: Option<Tier>,
+    /// Number of dimensions of the returned vectors, for models that support it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(minimum = 1, example = 512)]
+    pub dimensions: Option<u32>,
 }
 
 #[cfg(test)]
@@ -205,6 +413,53 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_stop_sequence_too_many_entries_rejected() {
+        let json = r#"{"messages": [], "stop": ["a", "b", "c", "d", "e"]}"#;
+        let err = serde_json::from_str::<ChatCompletionRequest>(json).unwrap_err();
+        assert!(err.to_string().contains("at most 4 sequences, got 5"));
+    }
+
+    #[test]
+    fn test_stop_sequence_empty_entry_names_index() {
+        let json = r#"{"messages": [], "stop": ["STOP", ""]}"#;
+        let err = serde_json::from_str::<ChatCompletionRequest>(json).unwrap_err();
+        assert!(err.to_string().contains("stop[1] must not be empty"));
+    }
+
+    #[test]
+    fn test_stop_sequence_length_cap() {
+        let long = "x".repeat(MAX_STOP_SEQUENCE_CHARS + 1);
+        let err = validate_stop_sequences(&["ok", long.as_str()], 4).unwrap_err();
+        assert!(err.contains("stop[1] exceeds"));
+
+        let at_cap = "é".repeat(MAX_STOP_SEQUENCE_CHARS);
+        assert!(validate_stop_sequences(&[at_cap.as_str()], 4).is_ok());
+    }
+
+    #[test]
+    fn test_stop_sequence_provider_maximum() {
+        let stop = StopSequence::Multiple(vec!["a".into(), "b".into(), "c".into()]);
+        assert!(stop.validate(max_stop_sequences("openai")).is_ok());
+        assert!(stop.validate(2).unwrap_err().contains("at most 2"));
+    }
+
+    #[test]
+    fn test_validate_stop_value_shapes() {
+        assert!(validate_stop_value(&serde_json::json!(null), 4).is_ok());
+        assert!(validate_stop_value(&serde_json::json!("END"), 4).is_ok());
+        assert!(validate_stop_value(&serde_json::json!(["END", "STOP"]), 4).is_ok());
+        assert_eq!(
+            validate_stop_value(&serde_json::json!(["END", 5]), 4).unwrap_err(),
+            "stop[1] must be a string"
+        );
+        assert!(validate_stop_value(&serde_json::json!({"a": 1}), 4).is_err());
+        assert_eq!(
+            validate_stop_value(&serde_json::json!(""), 4).unwrap_err(),
+            "stop[0] must not be empty"
+        );
+    }
+
     #[test]
     fn test_minimal_request() {
         let json = r#"{"messages": []}"#;
@@ -235,8 +490,12 @@ mod tests {
             stop: Some(StopSequence::Single("END".to_string())),
             stream: true,
             conversation_id: None,
+            workflow_id: None,
             tools: None,
             tool_choice: None,
+            timeout_ms: None,
+            user: None,
+            seed: None,
         };
         let json = serde_json::to_string(&request).unwrap();
         let deserialized: ChatCompletionRequest = serde_json::from_str(&json).unwrap();
@@ -254,8 +513,12 @@ mod tests {
             stop: None,
             stream: false,
             conversation_id: None,
+            workflow_id: None,
             tools: None,
             tool_choice: None,
+            timeout_ms: None,
+            user: None,
+            seed: None,
         };
         let json = serde_json::to_string(&request).unwrap();
         // tier should not appear in serialized output when None
@@ -273,8 +536,12 @@ mod tests {
             stop: None,
             stream: false,
             conversation_id: None,
+            workflow_id: None,
             tools: None,
             tool_choice: None,
+            timeout_ms: None,
+            user: None,
+            seed: None,
         };
         let json = serde_json::to_string(&request).unwrap();
         assert!(json.contains("\"tier\":\"complex\""));
@@ -311,6 +578,18 @@ mod tests {
         assert_eq!(request.tier, Some(Tier::Moderate));
     }
 
+    #[test]
+    fn test_request_with_timeout_ms_deserializes() {
+        let json = r#"{
+            "messages": [
+                {"role": "user", "content": "Hello!"}
+            ],
+            "timeout_ms": 2500
+        }"#;
+        let request: ChatCompletionRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(request.timeout_ms, Some(2500));
+    }
+
     #[test]
     fn test_conversation_id_omitted_from_serialization_when_none() {
         let request = ChatCompletionRequest {
@@ -328,8 +607,12 @@ mod tests {
             stop: None,
             stream: false,
             conversation_id: None,
+            workflow_id: None,
             tools: None,
             tool_choice: None,
+            timeout_ms: None,
+            user: None,
+            seed: None,
         };
         let json = serde_json::to_string(&request).unwrap();
         // conversation_id should not appear in serialized output when None
@@ -353,8 +636,12 @@ mod tests {
             stop: None,
             stream: false,
             conversation_id: Some("conv-uuid-123".to_string()),
+            workflow_id: None,
             tools: None,
             tool_choice: None,
+            timeout_ms: None,
+            user: None,
+            seed: None,
         };
         let json = serde_json::to_string(&request).unwrap();
         assert!(json.contains("conversation_id"));
@@ -475,8 +762,12 @@ mod tests {
             stop: None,
             stream: false,
             conversation_id: None,
+            workflow_id: None,
             tools: None,
             tool_choice: None,
+            timeout_ms: None,
+            user: None,
+            seed: None,
         };
         let json = serde_json::to_string(&request).unwrap();
         assert!(!json.contains("tools"));
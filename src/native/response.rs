@@ -5,7 +5,9 @@
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
-use super::types::{Role, ToolCall};
+use super::error::NativeError;
+use super::session::SessionUsage;
+use super::types::{Message, Role, Tier, ToolCall};
 
 /// Token usage statistics
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
@@ -64,6 +66,13 @@ pub struct ChatCompletionResponse {
     /// Model used for completion
     #[schema(example = "gpt-4o-mini")]
     pub model: String,
+    /// Backend configuration that served the completion, when the provider reports it
+    ///
+    /// Requests sent with the same `seed` are only expected to be reproducible
+    /// while this stays the same.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = "fp_44709d6fcb")]
+    pub system_fingerprint: Option<String>,
     /// List of completion choices
     pub choices: Vec<Choice>,
     /// Token usage statistics
@@ -146,6 +155,13 @@ pub struct StreamChunk {
     /// Model used for completion
     #[schema(example = "gpt-4o-mini")]
     pub model: String,
+    /// Backend configuration that served the completion, when the provider reports it
+    ///
+    /// Requests sent with the same `seed` are only expected to be reproducible
+    /// while this stays the same.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = "fp_44709d6fcb")]
+    pub system_fingerprint: Option<String>,
     /// List of choices with delta content
     pub choices: Vec<StreamChoice>,
     /// Token usage (only in final chunk when requested)
@@ -153,6 +169,147 @@ pub struct StreamChunk {
     pub usage: Option<Usage>,
 }
 
+/// Outcome of one item of a batch
+///
+/// Exactly one of `response` and `error` is set.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct BatchItemResult {
+    /// HTTP status the item would have gotten as a single request
+    #[schema(example = 200)]
+    pub status: u16,
+    /// The completion, when the item succeeded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<ChatCompletionResponse>,
+    /// The error, when the item failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<NativeError>,
+}
+
+/// Response to a chat completion batch
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct ChatCompletionBatchResponse {
+    /// One result per request, in request order
+    pub results: Vec<BatchItemResult>,
+}
+
+/// Token counts for a tokenize request
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct TokenizeResponse {
+    /// Model whose tokenizer was used
+    #[schema(example = "gpt-4o-mini")]
+    pub model: String,
+    /// Prompt tokens of the whole input, including per-request overhead
+    #[schema(example = 42)]
+    pub total_tokens: u64,
+    /// Tokens of each message, in request order (for `messages` input)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_tokens: Option<Vec<u64>>,
+    /// How to fit `max_tokens`, when it was given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub truncation: Option<TruncationSuggestion>,
+}
+
+/// Embedding vectors for an embeddings request (OpenAI's embeddings format)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct EmbeddingsResponse {
+    /// Object type, always "list"
+    #[schema(example = "list")]
+    pub object: String,
+    /// One embedding per input text, in input order
+    pub data: Vec<Embedding>,
+    /// Model that produced the embeddings
+    #[schema(example = "text-embedding-3-small")]
+    pub model: String,
+    /// Token usage
+    pub usage: EmbeddingsUsage,
+}
+
+/// Embedding of one input text
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct Embedding {
+    /// Object type, always "embedding"
+    #[schema(example = "embedding")]
+    pub object: String,
+    /// Position of the text in the input
+    #[schema(example = 0)]
+    pub index: u32,
+    /// The embedding vector
+    pub embedding: Vec<f32>,
+}
+
+/// Token usage of an embeddings request (input tokens only)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+pub struct EmbeddingsUsage {
+    /// Number of input tokens
+    #[schema(example = 12)]
+    pub prompt_tokens: u32,
+    /// Total tokens used (same as prompt_tokens)
+    #[schema(example = 12)]
+    pub total_tokens: u32,
+}
+
+/// A stored conversation session, as exported by `GET /native/v1/sessions/{conversation_id}`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct SessionExport {
+    /// The session's conversation ID
+    #[schema(example = "conv-123")]
+    pub conversation_id: String,
+    /// Tier the conversation is bound to
+    pub tier: Tier,
+    /// Model the conversation is bound to
+    #[schema(example = "gpt-4o-mini")]
+    pub model: String,
+    /// Unix timestamp of the session's creation
+    #[schema(example = 1700000000)]
+    pub created_at: i64,
+    /// Unix timestamp of the last completed request
+    #[schema(example = 1700000600)]
+    pub last_used_at: i64,
+    /// Tokens used by the conversation's completed requests
+    pub usage: SessionUsage,
+    /// Stored messages, when session history is enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub history: Option<SessionHistoryPage>,
+}
+
+/// One page of a session's stored messages
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct SessionHistoryPage {
+    /// Messages in Native format, oldest first
+    pub messages: Vec<Message>,
+    /// Index of the first message of the page
+    #[schema(example = 0)]
+    pub first_index: usize,
+    /// Stored messages in total
+    #[schema(example = 12)]
+    pub total_messages: usize,
+    /// Older messages were dropped to stay within the history cap
+    #[schema(example = false)]
+    pub truncated: bool,
+    /// `after` for the next page (absent on the last page)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = 9)]
+    pub next_after: Option<usize>,
+}
+
+/// Messages to drop so a conversation fits a token budget
+///
+/// Messages are dropped oldest first. System messages and the last message
+/// are always kept, so `fits` can be false even after dropping.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct TruncationSuggestion {
+    /// The requested budget
+    #[schema(example = 4000)]
+    pub max_tokens: u64,
+    /// Indices of the messages to drop, ascending
+    pub drop: Vec<usize>,
+    /// Prompt tokens left after dropping them
+    #[schema(example = 3850)]
+    pub total_tokens: u64,
+    /// Whether the remaining conversation fits `max_tokens`
+    pub fits: bool,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -316,4 +473,46 @@ mod tests {
         let deserialized: ToolCallDelta = serde_json::from_str(&json).unwrap();
         assert_eq!(delta, deserialized);
     }
+
+    // =============================================================================
+    // System Fingerprint Tests
+    // =============================================================================
+
+    fn stream_chunk(system_fingerprint: Option<&str>) -> StreamChunk {
+        StreamChunk {
+            id: "chatcmpl-abc".to_string(),
+            object: "chat.completion.chunk".to_string(),
+            created: 1700000000,
+            model: "gpt-4o-2024-08-06".to_string(),
+            system_fingerprint: system_fingerprint.map(str::to_string),
+            choices: vec![StreamChoice {
+                index: 0,
+                delta: Delta {
+                    role: None,
+                    content: Some("Hi".to_string()),
+                    tool_calls: None,
+                },
+                finish_reason: None,
+            }],
+            usage: None,
+        }
+    }
+
+    #[test]
+    fn test_stream_chunk_system_fingerprint_roundtrip() {
+        let chunk = stream_chunk(Some("fp_44709d6fcb"));
+        let json = serde_json::to_string(&chunk).unwrap();
+        assert!(json.contains("\"system_fingerprint\":\"fp_44709d6fcb\""));
+        let deserialized: StreamChunk = serde_json::from_str(&json).unwrap();
+        assert_eq!(chunk, deserialized);
+    }
+
+    #[test]
+    fn test_missing_system_fingerprint_is_omitted() {
+        let chunk = stream_chunk(None);
+        let json = serde_json::to_string(&chunk).unwrap();
+        assert!(!json.contains("system_fingerprint"));
+        let deserialized: StreamChunk = serde_json::from_str(&json).unwrap();
+        assert_eq!(chunk, deserialized);
+    }
 }
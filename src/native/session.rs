@@ -3,17 +3,48 @@
 //! This module provides session storage to ensure consistent provider/model
 //! selection within a conversation. Sessions are stored in Redis with TTL-based
 //! expiration that refreshes on activity.
+//!
+//! Concurrent requests in one conversation (e.g. client retries) race on the
+//! same session, so writes are compare-and-set on a version counter: a stale
+//! write is rejected and retried against the fresh state.
+//!
+//! Each user's conversation IDs are also kept in a Redis set so all of a
+//! user's sessions can be found (and deleted) without scanning the keyspace.
+//! Set members aren't expired individually; entries whose session has expired
+//! are pruned whenever the set is read.
+//!
+//! With affinity hints on (`native::affinity`), the manager also keeps a local
+//! copy of every session it reads or writes. Requests carrying a valid hint
+//! may use a copy younger than the configured age instead of reading Redis;
+//! writes always go to Redis first.
+//!
+//! Every completed request updates the session's last-used time and usage
+//! totals. With history enabled (`SESSION_HISTORY_MAX_MESSAGES`), the
+//! conversation as of the last completed request is stored next to the
+//! session, capped to the newest messages, so it can be exported.
+//!
+//! Streaming requests in a conversation are serialized with a stream lock
+//! (`SET NX` with a TTL, owner token as value). The holder refreshes it while
+//! chunks flow and releases it when the stream ends, fails or is dropped; a
+//! replica that dies mid-stream leaves it to expire.
 
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use chrono::Utc;
-use serde::{de::DeserializeOwned, Deserialize, Serialize};
-use tracing::{debug, instrument};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument, warn};
+use utoipa::ToSchema;
 
 use crate::{
-    cache::redis::{keys, RedisCache},
-    error::AppResult,
-    native::types::Tier,
+    cache::{
+        redis::{keys, RedisCache},
+        schema::{self, Schema},
+    },
+    clock::{system_clock, SharedClock},
+    error::{AppError, AppResult},
+    native::types::{Message, Tier},
 };
 
 #[cfg(any(test, feature = "test-utils"))]
@@ -32,24 +63,95 @@ pub enum SessionCacheBackend {
 }
 
 impl SessionCacheBackend {
-    async fn get<T: DeserializeOwned>(&self, key: &str) -> AppResult<Option<T>> {
+    async fn get<T: Schema>(&self, key: &str) -> AppResult<Option<T>> {
         match self {
-            SessionCacheBackend::Redis(cache) => cache.get(key).await,
+            SessionCacheBackend::Redis(cache) => cache.get_versioned(key).await,
             #[cfg(any(test, feature = "test-utils"))]
-            SessionCacheBackend::InMemory(cache) => cache.get(key).await,
+            SessionCacheBackend::InMemory(cache) => cache.get_versioned(key).await,
         }
     }
 
-    async fn set_with_ttl<T: Serialize>(
+    async fn set<T: Schema>(&self, key: &str, value: &T, ttl_seconds: u64) -> AppResult<()> {
+        match self {
+            SessionCacheBackend::Redis(cache) => cache.set_versioned(key, value, ttl_seconds).await,
+            #[cfg(any(test, feature = "test-utils"))]
+            SessionCacheBackend::InMemory(cache) => {
+                cache.set_versioned(key, value, ttl_seconds).await
+            }
+        }
+    }
+
+    async fn set_versioned_if_absent<T: Schema>(
         &self,
         key: &str,
         value: &T,
         ttl_seconds: u64,
-    ) -> AppResult<()> {
+    ) -> AppResult<bool> {
+        match self {
+            SessionCacheBackend::Redis(cache) => {
+                cache.set_versioned_if_absent(key, value, ttl_seconds).await
+            }
+            #[cfg(any(test, feature = "test-utils"))]
+            SessionCacheBackend::InMemory(cache) => {
+                cache.set_versioned_if_absent(key, value, ttl_seconds).await
+            }
+        }
+    }
+
+    async fn get_raw(&self, key: &str) -> AppResult<Option<String>> {
+        match self {
+            SessionCacheBackend::Redis(cache) => cache.get_raw(key).await,
+            #[cfg(any(test, feature = "test-utils"))]
+            SessionCacheBackend::InMemory(cache) => Ok(cache.get_raw(key)),
+        }
+    }
+
+    async fn set_versioned_if_raw<T: Schema>(
+        &self,
+        key: &str,
+        expected_raw: &str,
+        value: &T,
+        ttl_seconds: u64,
+    ) -> AppResult<bool> {
+        match self {
+            SessionCacheBackend::Redis(cache) => {
+                cache.set_versioned_if_raw(key, expected_raw, value, ttl_seconds).await
+            }
+            #[cfg(any(test, feature = "test-utils"))]
+            SessionCacheBackend::InMemory(cache) => {
+                cache.set_versioned_if_raw(key, expected_raw, value, ttl_seconds).await
+            }
+        }
+    }
+
+    async fn set_if_absent<T: Serialize>(
+        &self,
+        key: &str,
+        value: &T,
+        ttl_seconds: u64,
+    ) -> AppResult<bool> {
         match self {
-            SessionCacheBackend::Redis(cache) => cache.set_with_ttl(key, value, ttl_seconds).await,
+            SessionCacheBackend::Redis(cache) => cache.set_if_absent(key, value, ttl_seconds).await,
             #[cfg(any(test, feature = "test-utils"))]
-            SessionCacheBackend::InMemory(cache) => cache.set_with_ttl(key, value, ttl_seconds).await,
+            SessionCacheBackend::InMemory(cache) => cache.set_if_absent(key, value, ttl_seconds).await,
+        }
+    }
+
+    async fn set_if_version<T: Schema>(
+        &self,
+        key: &str,
+        expected_version: u64,
+        value: &T,
+        ttl_seconds: u64,
+    ) -> AppResult<bool> {
+        match self {
+            SessionCacheBackend::Redis(cache) => {
+                cache.set_if_version(key, expected_version, value, ttl_seconds).await
+            }
+            #[cfg(any(test, feature = "test-utils"))]
+            SessionCacheBackend::InMemory(cache) => {
+                cache.set_if_version(key, expected_version, value, ttl_seconds).await
+            }
         }
     }
 
@@ -60,6 +162,56 @@ impl SessionCacheBackend {
             SessionCacheBackend::InMemory(cache) => cache.expire(key, seconds).await,
         }
     }
+
+    async fn delete(&self, key: &str) -> AppResult<()> {
+        match self {
+            SessionCacheBackend::Redis(cache) => cache.delete(key).await,
+            #[cfg(any(test, feature = "test-utils"))]
+            SessionCacheBackend::InMemory(cache) => cache.delete(key).await,
+        }
+    }
+
+    async fn delete_if_value(&self, key: &str, value: &str) -> AppResult<bool> {
+        match self {
+            SessionCacheBackend::Redis(cache) => cache.delete_if_value(key, &value).await,
+            #[cfg(any(test, feature = "test-utils"))]
+            SessionCacheBackend::InMemory(cache) => cache.delete_if_value(key, &value).await,
+        }
+    }
+
+    async fn expire_if_value(&self, key: &str, value: &str, seconds: u64) -> AppResult<bool> {
+        match self {
+            SessionCacheBackend::Redis(cache) => cache.expire_if_value(key, &value, seconds).await,
+            #[cfg(any(test, feature = "test-utils"))]
+            SessionCacheBackend::InMemory(cache) => {
+                cache.expire_if_value(key, &value, seconds).await
+            }
+        }
+    }
+
+    async fn sadd(&self, key: &str, member: &str, ttl_seconds: u64) -> AppResult<()> {
+        match self {
+            SessionCacheBackend::Redis(cache) => cache.sadd(key, member, ttl_seconds).await,
+            #[cfg(any(test, feature = "test-utils"))]
+            SessionCacheBackend::InMemory(cache) => cache.sadd(key, member, ttl_seconds).await,
+        }
+    }
+
+    async fn smembers(&self, key: &str) -> AppResult<Vec<String>> {
+        match self {
+            SessionCacheBackend::Redis(cache) => cache.smembers(key).await,
+            #[cfg(any(test, feature = "test-utils"))]
+            SessionCacheBackend::InMemory(cache) => cache.smembers(key).await,
+        }
+    }
+
+    async fn srem(&self, key: &str, members: &[String]) -> AppResult<()> {
+        match self {
+            SessionCacheBackend::Redis(cache) => cache.srem(key, members).await,
+            #[cfg(any(test, feature = "test-utils"))]
+            SessionCacheBackend::InMemory(cache) => cache.srem(key, members).await,
+        }
+    }
 }
 
 /// Session data stored in Redis
@@ -81,6 +233,53 @@ pub struct Session {
     pub external_id: String,
     /// Unix timestamp when session was created
     pub created_at: i64,
+    /// Unix timestamp of the last completed request (None for sessions stored before it was kept)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<i64>,
+    /// Tokens used by the conversation's completed requests
+    #[serde(default)]
+    pub usage: SessionUsage,
+    /// Incremented on every update; writes only succeed against the version they read
+    #[serde(default)]
+    pub version: u64,
+}
+
+/// Usage accumulated over a session's completed requests
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+pub struct SessionUsage {
+    /// Completed requests
+    #[schema(example = 3)]
+    pub requests: u64,
+    /// Input tokens across those requests
+    #[schema(example = 1200)]
+    pub input_tokens: u64,
+    /// Output tokens across those requests
+    #[schema(example = 450)]
+    pub output_tokens: u64,
+}
+
+/// A conversation's messages as of its last completed request
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SessionHistory {
+    /// Messages, oldest first, ending with the last assistant reply
+    pub messages: Vec<Message>,
+    /// Older messages were dropped to stay within the history cap
+    pub truncated: bool,
+}
+
+/// Maximum compare-and-set attempts for a session update
+const MAX_UPDATE_ATTEMPTS: u32 = 5;
+
+/// Most local session copies kept at once
+const MAX_LOCAL_SESSIONS: usize = 10_000;
+
+/// How often a request waiting for a stream lock retries it
+const STREAM_LOCK_POLL: Duration = Duration::from_millis(50);
+
+/// Local session copies for requests with a valid affinity hint
+struct LocalSessions {
+    max_age: Duration,
+    entries: Mutex<HashMap<String, (Session, Instant)>>,
 }
 
 /// Session manager for provider stickiness
@@ -90,6 +289,12 @@ pub struct Session {
 pub struct SessionManager {
     cache: SessionCacheBackend,
     session_ttl: u64,
+    clock: SharedClock,
+    /// None unless affinity hints are enabled
+    local: Option<LocalSessions>,
+    local_hits: AtomicU64,
+    /// Most history messages kept per session (None = no history)
+    history_max_messages: Option<usize>,
 }
 
 impl SessionManager {
@@ -102,6 +307,10 @@ impl SessionManager {
         Self {
             cache: SessionCacheBackend::Redis(cache),
             session_ttl,
+            clock: system_clock(),
+            local: None,
+            local_hits: AtomicU64::new(0),
+            history_max_messages: None,
         }
     }
 
@@ -111,6 +320,79 @@ impl SessionManager {
         Self {
             cache: SessionCacheBackend::InMemory(cache),
             session_ttl,
+            clock: system_clock(),
+            local: None,
+            local_hits: AtomicU64::new(0),
+            history_max_messages: None,
+        }
+    }
+
+    /// Use the given clock for session timestamps
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Keep local copies of sessions, usable for `max_age` after they were read or written
+    pub fn with_local_copies(mut self, max_age: Duration) -> Self {
+        self.local = Some(LocalSessions {
+            max_age,
+            entries: Mutex::new(HashMap::new()),
+        });
+        self
+    }
+
+    /// Store conversation history, keeping the newest `max_messages` messages
+    pub fn with_history(mut self, max_messages: usize) -> Self {
+        self.history_max_messages = Some(max_messages);
+        self
+    }
+
+    /// Whether conversation history is stored
+    pub fn history_enabled(&self) -> bool {
+        self.history_max_messages.is_some()
+    }
+
+    /// Fresh local copy of a session, without going to Redis
+    ///
+    /// Only for requests that presented a valid affinity hint; None when
+    /// there is no copy, it is too old, or local copies are disabled.
+    pub fn local(&self, conversation_id: &str) -> Option<Session> {
+        let local = self.local.as_ref()?;
+        let entries = local.entries.lock().unwrap();
+        let (session, stored_at) = entries.get(conversation_id)?;
+        if self.clock.instant_now().duration_since(*stored_at) >= local.max_age {
+            return None;
+        }
+        self.local_hits.fetch_add(1, Ordering::Relaxed);
+        Some(session.clone())
+    }
+
+    /// Number of lookups served from a local copy
+    pub fn local_hits(&self) -> u64 {
+        self.local_hits.load(Ordering::Relaxed)
+    }
+
+    /// Store a local copy of a session just read from or written to Redis
+    fn remember(&self, session: &Session) {
+        let Some(local) = &self.local else {
+            return;
+        };
+        let now = self.clock.instant_now();
+        let mut entries = local.entries.lock().unwrap();
+        if entries.len() >= MAX_LOCAL_SESSIONS && !entries.contains_key(&session.id) {
+            entries.retain(|_, (_, stored_at)| now.duration_since(*stored_at) < local.max_age);
+            if entries.len() >= MAX_LOCAL_SESSIONS {
+                return;
+            }
+        }
+        entries.insert(session.id.clone(), (session.clone(), now));
+    }
+
+    /// Drop the local copy of a session
+    fn forget(&self, conversation_id: &str) {
+        if let Some(local) = &self.local {
+            local.entries.lock().unwrap().remove(conversation_id);
         }
     }
 
@@ -123,7 +405,8 @@ impl SessionManager {
         let key = keys::session(conversation_id);
         let result = self.cache.get::<Session>(&key).await?;
 
-        if result.is_some() {
+        if let Some(ref session) = result {
+            self.remember(session);
             debug!("Session cache hit");
         } else {
             debug!("Session cache miss");
@@ -136,6 +419,11 @@ impl SessionManager {
     ///
     /// Stores the provider/model/tier binding in Redis with TTL.
     /// The TTL resets on each activity via `touch()`.
+    ///
+    /// If a concurrent request created the session first, the requested tier
+    /// is merged into it via `upgrade_tier()` instead, so the returned session
+    /// is the one actually stored and may differ from the arguments. A session
+    /// another user created is left alone and refused with `Forbidden`.
     #[instrument(skip(self), fields(conversation_id = %conversation_id, provider = %provider, model = %model, tier = ?tier))]
     pub async fn create(
         &self,
@@ -151,68 +439,402 @@ impl SessionManager {
             model: model.to_string(),
             tier,
             external_id: external_id.to_string(),
-            created_at: Utc::now().timestamp(),
+            created_at: self.clock.now_unix(),
+            last_used_at: None,
+            usage: SessionUsage::default(),
+            version: 0,
         };
 
         let key = keys::session(conversation_id);
-        self.cache
-            .set_with_ttl(&key, &session, self.session_ttl)
-            .await?;
+        for attempt in 1..=MAX_UPDATE_ATTEMPTS {
+            if self
+                .cache
+                .set_versioned_if_absent(&key, &session, self.session_ttl)
+                .await?
+            {
+                self.cache
+                    .sadd(&keys::user_sessions(external_id), conversation_id, self.session_ttl)
+                    .await?;
+                self.remember(&session);
+                debug!("Session created");
+                return Ok(session);
+            }
+
+            // Expired since: try creating it again
+            let Some(raw) = self.cache.get_raw(&key).await? else {
+                continue;
+            };
+            match schema::decode::<Session>(&raw) {
+                Ok(existing) if existing.external_id != external_id => {
+                    warn!("Session created concurrently by another user");
+                    return Err(AppError::Forbidden);
+                }
+                Ok(_) => {
+                    debug!("Session created concurrently, merging requested tier");
+                    return self
+                        .upgrade_tier(conversation_id, external_id, provider, model, tier)
+                        .await;
+                }
+                // Written by a release that can't be read: take it over,
+                // unless it changed in between
+                Err(_) => {
+                    if self
+                        .cache
+                        .set_versioned_if_raw(&key, &raw, &session, self.session_ttl)
+                        .await?
+                    {
+                        warn!("Replaced unreadable session");
+                        self.cache
+                            .sadd(&keys::user_sessions(external_id), conversation_id, self.session_ttl)
+                            .await?;
+                        self.remember(&session);
+                        return Ok(session);
+                    }
+                }
+            }
+            debug!(attempt, "Session changed concurrently, retrying create");
+        }
 
-        debug!("Session created");
-        Ok(session)
+        warn!(
+            attempts = MAX_UPDATE_ATTEMPTS,
+            "Session create kept conflicting, giving up"
+        );
+        Err(AppError::Internal(anyhow::anyhow!(
+            "Session create conflict for {} after {} attempts",
+            conversation_id,
+            MAX_UPDATE_ATTEMPTS
+        )))
     }
 
     /// Update session tier, provider, and model for tier upgrade
     ///
     /// Called when a request in an existing session requests a higher tier.
-    /// Only upgrades are allowed (simple -> moderate -> complex).
+    /// Only upgrades are allowed (simple -> moderate -> complex); the rule is
+    /// checked against the freshest stored state, and a write that loses a
+    /// race is retried (up to `MAX_UPDATE_ATTEMPTS`). Returns the stored
+    /// session, which keeps its own binding if it is already at or above
+    /// `new_tier`. A session that belongs to another user than `external_id`
+    /// is never changed and is refused with `Forbidden`.
     #[instrument(skip(self), fields(conversation_id = %conversation_id, new_tier = ?new_tier))]
     pub async fn upgrade_tier(
         &self,
         conversation_id: &str,
+        external_id: &str,
         provider: &str,
         model: &str,
         new_tier: Tier,
-    ) -> AppResult<()> {
+    ) -> AppResult<Session> {
         let key = keys::session(conversation_id);
 
-        // Get existing session
-        let mut session: Session = self.cache.get::<Session>(&key).await?.ok_or_else(|| {
-            crate::error::AppError::NotFound(format!("Session not found: {}", conversation_id))
-        })?;
-
-        // Update tier, provider, model
-        session.tier = new_tier;
-        session.provider = provider.to_string();
-        session.model = model.to_string();
-
-        // Save back with TTL refresh
-        self.cache
-            .set_with_ttl(&key, &session, self.session_ttl)
-            .await?;
+        for attempt in 1..=MAX_UPDATE_ATTEMPTS {
+            // Get the freshest session
+            let mut session: Session = self.cache.get::<Session>(&key).await?.ok_or_else(|| {
+                AppError::NotFound(format!("Session not found: {}", conversation_id))
+            })?;
+            if session.external_id != external_id {
+                warn!("Session belongs to another user, tier not upgraded");
+                return Err(AppError::Forbidden);
+            }
+
+            // Never downgrade (or rebind at the same tier)
+            if new_tier <= session.tier {
+                debug!(session_tier = ?session.tier, "Session already at or above requested tier");
+                self.remember(&session);
+                return Ok(session);
+            }
+
+            // Update tier, provider, model
+            let expected_version = session.version;
+            session.tier = new_tier;
+            session.provider = provider.to_string();
+            session.model = model.to_string();
+            session.version = expected_version + 1;
+
+            // Save back with TTL refresh, only if nobody else wrote in between
+            if self
+                .cache
+                .set_if_version(&key, expected_version, &session, self.session_ttl)
+                .await?
+            {
+                self.remember(&session);
+                debug!("Session tier upgraded");
+                return Ok(session);
+            }
+
+            debug!(attempt, "Session changed concurrently, retrying upgrade");
+        }
 
-        debug!("Session tier upgraded");
-        Ok(())
+        warn!(
+            attempts = MAX_UPDATE_ATTEMPTS,
+            "Session upgrade kept conflicting, giving up"
+        );
+        Err(AppError::Internal(anyhow::anyhow!(
+            "Session update conflict for {} after {} attempts",
+            conversation_id,
+            MAX_UPDATE_ATTEMPTS
+        )))
     }
 
     /// Refresh session TTL on activity
     ///
     /// Called on each request to implement activity-based expiration.
     /// The 24-hour (or configured) TTL resets from the last activity,
-    /// not from session creation.
+    /// not from session creation. The owner's session index is extended
+    /// with it, so it never expires before the sessions it lists.
     #[instrument(skip(self), fields(conversation_id = %conversation_id))]
-    pub async fn touch(&self, conversation_id: &str) -> AppResult<()> {
+    pub async fn touch(&self, conversation_id: &str, external_id: &str) -> AppResult<()> {
         let key = keys::session(conversation_id);
         self.cache.expire(&key, self.session_ttl).await?;
+        if self.history_enabled() {
+            self.cache
+                .expire(&keys::session_history(conversation_id), self.session_ttl)
+                .await?;
+        }
+        self.cache
+            .expire(&keys::user_sessions(external_id), self.session_ttl)
+            .await?;
         debug!("Session TTL refreshed");
         Ok(())
     }
+
+    /// Live sessions belonging to a user
+    ///
+    /// Index entries whose session has expired, or whose conversation ID has
+    /// since been reused by another user, are removed from the index.
+    #[instrument(skip(self), fields(external_id = %external_id))]
+    pub async fn list_for_user(&self, external_id: &str) -> AppResult<Vec<Session>> {
+        let index = keys::user_sessions(external_id);
+        let mut sessions = Vec::new();
+        let mut stale = Vec::new();
+
+        for conversation_id in self.cache.smembers(&index).await? {
+            match self.get(&conversation_id).await? {
+                Some(session) if session.external_id == external_id => sessions.push(session),
+                _ => stale.push(conversation_id),
+            }
+        }
+
+        if !stale.is_empty() {
+            debug!(stale = stale.len(), "Pruning expired sessions from index");
+            self.cache.srem(&index, &stale).await?;
+        }
+        Ok(sessions)
+    }
+
+    /// Delete all of a user's sessions, returning how many were removed
+    ///
+    /// Idempotent: a second call finds nothing and returns 0.
+    #[instrument(skip(self), fields(external_id = %external_id))]
+    pub async fn delete_for_user(&self, external_id: &str) -> AppResult<usize> {
+        let sessions = self.list_for_user(external_id).await?;
+        for session in &sessions {
+            self.cache.delete(&keys::session(&session.id)).await?;
+            self.cache.delete(&keys::session_history(&session.id)).await?;
+            self.forget(&session.id);
+        }
+        self.cache.delete(&keys::user_sessions(external_id)).await?;
+
+        debug!(deleted = sessions.len(), "Deleted user sessions");
+        Ok(sessions.len())
+    }
+
+    /// Wipe the stored history of a user's sessions, keeping the sessions
+    ///
+    /// The model binding, timestamps and usage totals stay, so conversations
+    /// carry on without their earlier messages. Returns how many histories
+    /// were removed; a second call finds none and returns 0.
+    #[instrument(skip(self), fields(external_id = %external_id))]
+    pub async fn redact_for_user(&self, external_id: &str) -> AppResult<usize> {
+        let mut redacted = 0;
+        for session in self.list_for_user(external_id).await? {
+            let key = keys::session_history(&session.id);
+            if self.cache.get_raw(&key).await?.is_some() {
+                self.cache.delete(&key).await?;
+                redacted += 1;
+            }
+        }
+
+        debug!(redacted, "Redacted user session histories");
+        Ok(redacted)
+    }
+
+    /// Record a completed request: last-used time, usage and, with history
+    /// enabled, the conversation including the reply
+    ///
+    /// `messages` are stored as the session's history, without the oldest
+    /// ones beyond the cap. Does nothing if the session has expired or
+    /// belongs to another user than `external_id`.
+    #[instrument(skip(self, messages), fields(conversation_id = %conversation_id))]
+    pub async fn record_turn(
+        &self,
+        conversation_id: &str,
+        external_id: &str,
+        input_tokens: u64,
+        output_tokens: u64,
+        messages: Option<Vec<Message>>,
+    ) -> AppResult<()> {
+        let key = keys::session(conversation_id);
+        let mut recorded = false;
+        for attempt in 1..=MAX_UPDATE_ATTEMPTS {
+            let Some(mut session) = self.cache.get::<Session>(&key).await? else {
+                debug!("Session expired before the turn was recorded");
+                return Ok(());
+            };
+            if session.external_id != external_id {
+                warn!("Session belongs to another user, turn not recorded");
+                return Ok(());
+            }
+            let expected_version = session.version;
+            session.last_used_at = Some(self.clock.now_unix());
+            session.usage.requests += 1;
+            session.usage.input_tokens += input_tokens;
+            session.usage.output_tokens += output_tokens;
+            session.version = expected_version + 1;
+            if self
+                .cache
+                .set_if_version(&key, expected_version, &session, self.session_ttl)
+                .await?
+            {
+                self.remember(&session);
+                recorded = true;
+                break;
+            }
+            debug!(attempt, "Session changed concurrently, retrying turn");
+        }
+        if !recorded {
+            warn!(
+                attempts = MAX_UPDATE_ATTEMPTS,
+                "Session turn kept conflicting, usage not recorded"
+            );
+        }
+
+        if let (Some(max_messages), Some(mut messages)) = (self.history_max_messages, messages) {
+            let dropped = messages.len().saturating_sub(max_messages);
+            messages.drain(..dropped);
+            let history = SessionHistory {
+                messages,
+                truncated: dropped > 0,
+            };
+            self.cache
+                .set(&keys::session_history(conversation_id), &history, self.session_ttl)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Stored history of a conversation (None when there is none or history is off)
+    #[instrument(skip(self), fields(conversation_id = %conversation_id))]
+    pub async fn history(&self, conversation_id: &str) -> AppResult<Option<SessionHistory>> {
+        if !self.history_enabled() {
+            return Ok(None);
+        }
+        self.cache
+            .get::<SessionHistory>(&keys::session_history(conversation_id))
+            .await
+    }
+
+    /// Take the conversation's stream lock, waiting up to `wait` for it
+    ///
+    /// Returns None when another stream still holds it after `wait`. The lock
+    /// expires after `ttl_seconds` unless refreshed by its holder.
+    #[instrument(skip(self), fields(conversation_id = %conversation_id))]
+    pub async fn lock_stream(
+        self: &Arc<Self>,
+        conversation_id: &str,
+        ttl_seconds: u64,
+        wait: Duration,
+    ) -> AppResult<Option<StreamLock>> {
+        let key = keys::stream_lock(conversation_id);
+        let token = uuid::Uuid::new_v4().to_string();
+        let deadline = tokio::time::Instant::now() + wait;
+
+        loop {
+            if self.cache.set_if_absent(&key, &token, ttl_seconds).await? {
+                debug!("Stream lock acquired");
+                return Ok(Some(StreamLock {
+                    manager: self.clone(),
+                    key,
+                    token,
+                    ttl_seconds,
+                    refreshed_at: self.clock.instant_now(),
+                    released: false,
+                }));
+            }
+            if tokio::time::Instant::now() >= deadline {
+                debug!("Stream lock held by another request");
+                return Ok(None);
+            }
+            tokio::time::sleep(STREAM_LOCK_POLL.min(deadline - tokio::time::Instant::now())).await;
+        }
+    }
+}
+
+/// Hold on a conversation's stream lock
+///
+/// Released with [`StreamLock::release`] or, if the stream is dropped
+/// first, in a background task on drop.
+pub struct StreamLock {
+    manager: Arc<SessionManager>,
+    key: String,
+    token: String,
+    ttl_seconds: u64,
+    refreshed_at: Instant,
+    released: bool,
+}
+
+impl StreamLock {
+    /// Extend the lock's expiry once a third of its TTL has passed since the last refresh
+    pub async fn refresh(&mut self) {
+        let now = self.manager.clock.instant_now();
+        if now.duration_since(self.refreshed_at) < Duration::from_secs(self.ttl_seconds) / 3 {
+            return;
+        }
+        self.refreshed_at = now;
+        match self
+            .manager
+            .cache
+            .expire_if_value(&self.key, &self.token, self.ttl_seconds)
+            .await
+        {
+            Ok(true) => {}
+            Ok(false) => warn!(key = %self.key, "Stream lock expired while the stream was running"),
+            Err(e) => warn!(key = %self.key, error = %e, "Failed to refresh stream lock"),
+        }
+    }
+
+    /// Release the lock (a no-op if it already expired)
+    pub async fn release(mut self) {
+        self.released = true;
+        if let Err(e) = self.manager.cache.delete_if_value(&self.key, &self.token).await {
+            warn!(key = %self.key, error = %e, "Failed to release stream lock");
+        }
+    }
+}
+
+impl Drop for StreamLock {
+    fn drop(&mut self) {
+        if self.released {
+            return;
+        }
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let manager = self.manager.clone();
+        let key = std::mem::take(&mut self.key);
+        let token = std::mem::take(&mut self.token);
+        runtime.spawn(async move {
+            if let Err(e) = manager.cache.delete_if_value(&key, &token).await {
+                warn!(key = %key, error = %e, "Failed to release dropped stream lock");
+            }
+        });
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clock::TestClock;
+    use std::time::Duration;
 
     // ===========================================
     // Session Struct Tests
@@ -227,6 +849,9 @@ mod tests {
             tier: Tier::Moderate,
             external_id: "user-456".to_string(),
             created_at: 1700000000,
+            last_used_at: None,
+            usage: SessionUsage::default(),
+            version: 0,
         };
 
         // Serialize to JSON
@@ -247,6 +872,9 @@ mod tests {
             tier: Tier::Complex,
             external_id: "ext-123".to_string(),
             created_at: 1700000000,
+            last_used_at: None,
+            usage: SessionUsage::default(),
+            version: 0,
         };
 
         let json = serde_json::to_string(&session).unwrap();
@@ -279,6 +907,8 @@ mod tests {
         assert_eq!(session.tier, Tier::Moderate);
         assert_eq!(session.external_id, "user-xyz");
         assert_eq!(session.created_at, 1700000000);
+        // Sessions stored before versioning start at version 0
+        assert_eq!(session.version, 0);
     }
 
     #[test]
@@ -290,6 +920,9 @@ mod tests {
             tier: Tier::Simple,
             external_id: "user-1".to_string(),
             created_at: 1700000000,
+            last_used_at: None,
+            usage: SessionUsage::default(),
+            version: 0,
         };
 
         let cloned = session.clone();
@@ -306,6 +939,9 @@ mod tests {
             tier: Tier::Simple,
             external_id: "user-1".to_string(),
             created_at: 1700000000,
+            last_used_at: None,
+            usage: SessionUsage::default(),
+            version: 0,
         };
 
         let debug_str = format!("{:?}", session);
@@ -346,6 +982,428 @@ mod tests {
         assert_eq!(one_week, 7 * 24 * 60 * 60);
     }
 
+    // ===========================================
+    // Compare-and-Set Tests (in-memory backend)
+    // ===========================================
+
+    fn test_manager() -> Arc<SessionManager> {
+        Arc::new(SessionManager::new_for_testing(Arc::new(InMemoryCache::new(60)), 60))
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_bumps_version_and_never_downgrades() {
+        let manager = test_manager();
+        manager
+            .create("conv-1", "openai", "gpt-4o-mini", Tier::Simple, "user-1")
+            .await
+            .unwrap();
+
+        let upgraded = manager
+            .upgrade_tier("conv-1", "user-1", "openai", "gpt-4o", Tier::Complex)
+            .await
+            .unwrap();
+        assert_eq!(upgraded.tier, Tier::Complex);
+        assert_eq!(upgraded.version, 1);
+
+        // A stale request for a lower tier gets the stored binding back
+        let kept = manager
+            .upgrade_tier("conv-1", "user-1", "openai", "gpt-4o-mini", Tier::Moderate)
+            .await
+            .unwrap();
+        assert_eq!(kept, upgraded);
+        assert_eq!(manager.get("conv-1").await.unwrap(), Some(upgraded));
+    }
+
+    #[tokio::test]
+    async fn test_record_turn_accumulates_usage_and_caps_history() {
+        let clock = TestClock::new(1_700_000_000);
+        let manager = SessionManager::new_for_testing(Arc::new(InMemoryCache::new(60)), 60)
+            .with_clock(clock.clone())
+            .with_history(3);
+        manager
+            .create("conv-1", "openai", "gpt-4o-mini", Tier::Simple, "user-1")
+            .await
+            .unwrap();
+
+        let message = |text: &str| Message {
+            role: crate::native::types::Role::User,
+            content: crate::native::types::Content::Text(text.to_string()),
+            name: None,
+            tool_call_id: None,
+            tool_calls: None,
+        };
+        manager
+            .record_turn("conv-1", "user-1", 10, 5, Some(vec![message("a"), message("b")]))
+            .await
+            .unwrap();
+        let history = manager.history("conv-1").await.unwrap().unwrap();
+        assert_eq!(history.messages.len(), 2);
+        assert!(!history.truncated);
+
+        clock.advance(Duration::from_secs(30));
+        let messages = ["a", "b", "c", "d"].map(message).to_vec();
+        manager
+            .record_turn("conv-1", "user-1", 20, 7, Some(messages))
+            .await
+            .unwrap();
+        let history = manager.history("conv-1").await.unwrap().unwrap();
+        assert_eq!(history.messages, ["b", "c", "d"].map(message).to_vec());
+        assert!(history.truncated);
+
+        let session = manager.get("conv-1").await.unwrap().unwrap();
+        assert_eq!(
+            session.usage,
+            SessionUsage {
+                requests: 2,
+                input_tokens: 30,
+                output_tokens: 12
+            }
+        );
+        assert_eq!(session.last_used_at, Some(session.created_at + 30));
+
+        // Expired sessions are left alone
+        manager.record_turn("conv-gone", "user-1", 1, 1, None).await.unwrap();
+        assert!(manager.get("conv-gone").await.unwrap().is_none());
+
+        // Another user's turn touches neither the usage nor the history
+        manager
+            .record_turn("conv-1", "user-2", 99, 99, Some(vec![message("intruder")]))
+            .await
+            .unwrap();
+        assert_eq!(manager.get("conv-1").await.unwrap().unwrap().usage.requests, 2);
+        let history = manager.history("conv-1").await.unwrap().unwrap();
+        assert_eq!(history.messages, ["b", "c", "d"].map(message).to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_create_merges_into_existing_session() {
+        let manager = test_manager();
+        manager
+            .create("conv-1", "openai", "gpt-4o", Tier::Moderate, "user-1")
+            .await
+            .unwrap();
+
+        // Losing the create race must not overwrite the higher tier
+        let session = manager
+            .create("conv-1", "openai", "gpt-4o-mini", Tier::Simple, "user-1")
+            .await
+            .unwrap();
+        assert_eq!(session.tier, Tier::Moderate);
+        assert_eq!(session.model, "gpt-4o");
+    }
+
+    #[tokio::test]
+    async fn test_other_users_never_change_a_session() {
+        let manager = test_manager();
+        let owned = manager
+            .create("conv-1", "openai", "gpt-4o-mini", Tier::Simple, "user-1")
+            .await
+            .unwrap();
+
+        let created = manager
+            .create("conv-1", "openai", "o3", Tier::Complex, "user-2")
+            .await;
+        assert!(matches!(created, Err(AppError::Forbidden)));
+        let upgraded = manager
+            .upgrade_tier("conv-1", "user-2", "openai", "o3", Tier::Complex)
+            .await;
+        assert!(matches!(upgraded, Err(AppError::Forbidden)));
+
+        assert_eq!(manager.get("conv-1").await.unwrap(), Some(owned));
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_session_is_replaced_only_if_unchanged() {
+        let cache = Arc::new(InMemoryCache::new(60));
+        let key = keys::session("conv-new");
+        let unreadable = r#"v9|{"id":"conv-new"}"#;
+        cache.set_raw(&key, unreadable.to_string(), 60);
+
+        let session = SessionManager::new_for_testing(cache.clone(), 60)
+            .create("conv-new", "openai", "gpt-4o", Tier::Moderate, "user-1")
+            .await
+            .unwrap();
+        assert!(!cache
+            .set_versioned_if_raw(&key, unreadable, &session, 60)
+            .await
+            .unwrap());
+        assert!(cache.get_raw(&key).unwrap().contains("user-1"));
+    }
+
+    #[tokio::test]
+    async fn test_session_expires_after_ttl_and_touch_extends_it() {
+        let clock = TestClock::new(1_700_000_000);
+        let cache = Arc::new(InMemoryCache::new(60).with_clock(clock.clone()));
+        let manager = SessionManager::new_for_testing(cache, 60).with_clock(clock.clone());
+
+        let session = manager
+            .create("conv-1", "openai", "gpt-4o", Tier::Simple, "user-1")
+            .await
+            .unwrap();
+        assert_eq!(session.created_at, 1_700_000_000);
+
+        // Activity just before expiry restarts the TTL
+        clock.advance(Duration::from_secs(59));
+        manager.touch("conv-1", "user-1").await.unwrap();
+        clock.advance(Duration::from_secs(60));
+        assert!(manager.get("conv-1").await.unwrap().is_some());
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(manager.get("conv-1").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_delete_for_user_removes_only_their_sessions() {
+        let manager = test_manager();
+        for (conversation_id, user) in [("conv-1", "user-1"), ("conv-2", "user-1"), ("conv-3", "user-2")] {
+            manager
+                .create(conversation_id, "openai", "gpt-4o", Tier::Simple, user)
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(manager.list_for_user("user-1").await.unwrap().len(), 2);
+        assert_eq!(manager.delete_for_user("user-1").await.unwrap(), 2);
+        assert_eq!(manager.get("conv-1").await.unwrap(), None);
+        assert!(manager.get("conv-3").await.unwrap().is_some());
+
+        // Deleting again is a no-op
+        assert_eq!(manager.delete_for_user("user-1").await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_redact_for_user_keeps_sessions() {
+        let manager = SessionManager::new_for_testing(Arc::new(InMemoryCache::new(60)), 60)
+            .with_history(10);
+        let hello = Message {
+            role: crate::native::types::Role::User,
+            content: crate::native::types::Content::Text("Hello".to_string()),
+            name: None,
+            tool_call_id: None,
+            tool_calls: None,
+        };
+        for (conversation_id, user) in [("conv-1", "user-1"), ("conv-2", "user-2")] {
+            manager
+                .create(conversation_id, "openai", "gpt-4o", Tier::Simple, user)
+                .await
+                .unwrap();
+            manager
+                .record_turn(conversation_id, user, 10, 5, Some(vec![hello.clone()]))
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(manager.redact_for_user("user-1").await.unwrap(), 1);
+        assert_eq!(manager.history("conv-1").await.unwrap(), None);
+        let session = manager.get("conv-1").await.unwrap().unwrap();
+        assert_eq!(session.tier, Tier::Simple);
+        assert_eq!(session.usage.input_tokens, 10);
+        assert!(manager.history("conv-2").await.unwrap().is_some());
+
+        // Redacting again is a no-op
+        assert_eq!(manager.redact_for_user("user-1").await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_index_prunes_expired_and_reused_sessions() {
+        let clock = TestClock::new(1_700_000_000);
+        let cache = Arc::new(InMemoryCache::new(60).with_clock(clock.clone()));
+        let manager = SessionManager::new_for_testing(cache.clone(), 60).with_clock(clock.clone());
+
+        manager
+            .create("conv-old", "openai", "gpt-4o", Tier::Simple, "user-1")
+            .await
+            .unwrap();
+        manager
+            .create("conv-reused", "openai", "gpt-4o", Tier::Simple, "user-1")
+            .await
+            .unwrap();
+
+        // conv-old expires while conv-reused stays active
+        clock.advance(Duration::from_secs(30));
+        manager.touch("conv-reused", "user-1").await.unwrap();
+        clock.advance(Duration::from_secs(31));
+        let sessions = manager.list_for_user("user-1").await.unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].id, "conv-reused");
+        let index = cache.smembers(&keys::user_sessions("user-1")).await.unwrap();
+        assert_eq!(index, vec!["conv-reused"]);
+
+        // After conv-reused expires another user takes the same conversation ID
+        clock.advance(Duration::from_secs(61));
+        manager
+            .create("conv-reused", "openai", "gpt-4o", Tier::Simple, "user-2")
+            .await
+            .unwrap();
+        // user-1's index still lists it, e.g. kept alive by another session
+        cache
+            .sadd(&keys::user_sessions("user-1"), "conv-reused", 60)
+            .await
+            .unwrap();
+        assert_eq!(manager.delete_for_user("user-1").await.unwrap(), 0);
+        assert!(manager.get("conv-reused").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_local_copies_expire_and_follow_writes() {
+        let clock = TestClock::new(1_700_000_000);
+        let cache = Arc::new(InMemoryCache::new(60).with_clock(clock.clone()));
+        let manager = SessionManager::new_for_testing(cache, 60)
+            .with_clock(clock.clone())
+            .with_local_copies(Duration::from_secs(10));
+
+        manager
+            .create("conv-1", "openai", "gpt-4o-mini", Tier::Simple, "user-1")
+            .await
+            .unwrap();
+        assert_eq!(manager.local("conv-1").unwrap().model, "gpt-4o-mini");
+
+        // Upgrades replace the copy
+        manager
+            .upgrade_tier("conv-1", "user-1", "openai", "gpt-4o", Tier::Complex)
+            .await
+            .unwrap();
+        assert_eq!(manager.local("conv-1").unwrap().model, "gpt-4o");
+        assert_eq!(manager.local_hits(), 2);
+
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(manager.local("conv-1"), None);
+        // Reading Redis refreshes it
+        manager.get("conv-1").await.unwrap();
+        assert!(manager.local("conv-1").is_some());
+
+        manager.delete_for_user("user-1").await.unwrap();
+        assert_eq!(manager.local("conv-1"), None);
+        assert_eq!(manager.local_hits(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_local_copies_disabled_by_default() {
+        let manager = test_manager();
+        manager
+            .create("conv-1", "openai", "gpt-4o", Tier::Simple, "user-1")
+            .await
+            .unwrap();
+        assert_eq!(manager.local("conv-1"), None);
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_missing_session_is_not_found() {
+        let manager = test_manager();
+        let result = manager
+            .upgrade_tier("missing", "user-1", "openai", "gpt-4o", Tier::Complex)
+            .await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_sessions_written_by_other_releases() {
+        let cache = Arc::new(InMemoryCache::new(60));
+        let manager = SessionManager::new_for_testing(cache.clone(), 60);
+
+        // An unversioned session from an older replica is read and upgraded in
+        // place, still in the format that replica reads
+        let legacy = serde_json::json!({
+            "id": "conv-old", "provider": "openai", "model": "gpt-4o-mini",
+            "tier": "simple", "external_id": "user-1", "created_at": 1, "version": 2
+        });
+        cache.set_raw(&keys::session("conv-old"), legacy.to_string(), 60);
+        assert_eq!(manager.get("conv-old").await.unwrap().unwrap().version, 2);
+        let upgraded = manager
+            .upgrade_tier("conv-old", "user-1", "openai", "gpt-4o", Tier::Complex)
+            .await
+            .unwrap();
+        assert_eq!(upgraded.version, 3);
+        assert!(cache
+            .get_raw(&keys::session("conv-old"))
+            .unwrap()
+            .starts_with('{'));
+
+        // A newer replica's session is a miss, and creating over it replaces it
+        cache.set_raw(&keys::session("conv-new"), r#"v9|{"id":"conv-new"}"#.to_string(), 60);
+        assert_eq!(manager.get("conv-new").await.unwrap(), None);
+        let session = manager
+            .create("conv-new", "openai", "gpt-4o", Tier::Moderate, "user-1")
+            .await
+            .unwrap();
+        assert_eq!(manager.get("conv-new").await.unwrap(), Some(session));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_updates_keep_highest_tier() {
+        let manager = test_manager();
+        let tiers = [Tier::Simple, Tier::Complex, Tier::Moderate, Tier::Simple];
+
+        let handles: Vec<_> = (0..32)
+            .map(|i| {
+                let manager = manager.clone();
+                let tier = tiers[i % tiers.len()];
+                tokio::spawn(async move {
+                    let model = format!("model-{:?}", tier);
+                    match manager.get("conv-race").await.unwrap() {
+                        Some(_) => manager.upgrade_tier("conv-race", "user-1", "openai", &model, tier).await,
+                        None => manager.create("conv-race", "openai", &model, tier, "user-1").await,
+                    }
+                    .unwrap()
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        let session = manager.get("conv-race").await.unwrap().unwrap();
+        assert_eq!(session.tier, Tier::Complex);
+        assert_eq!(session.model, "model-Complex");
+    }
+
+    #[tokio::test]
+    async fn test_stream_lock_is_exclusive_until_released() {
+        let manager = test_manager();
+        let lock = manager.lock_stream("conv-1", 60, Duration::ZERO).await.unwrap().unwrap();
+        assert!(manager.lock_stream("conv-1", 60, Duration::ZERO).await.unwrap().is_none());
+        // Other conversations are unaffected
+        assert!(manager.lock_stream("conv-2", 60, Duration::ZERO).await.unwrap().is_some());
+
+        lock.release().await;
+        assert!(manager.lock_stream("conv-1", 60, Duration::ZERO).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_dropped_stream_lock_is_released() {
+        let manager = test_manager();
+        drop(manager.lock_stream("conv-1", 60, Duration::ZERO).await.unwrap());
+
+        // A waiting request gets the lock once the release task has run
+        let lock = manager
+            .lock_stream("conv-1", 60, Duration::from_secs(1))
+            .await
+            .unwrap();
+        assert!(lock.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_stream_lock_expires_and_refresh_extends_it() {
+        let clock = TestClock::new(1_700_000_000);
+        let cache = Arc::new(InMemoryCache::new(60).with_clock(clock.clone()));
+        let manager = Arc::new(SessionManager::new_for_testing(cache, 60).with_clock(clock.clone()));
+
+        // A crashed replica never releases its lock
+        std::mem::forget(manager.lock_stream("conv-1", 30, Duration::ZERO).await.unwrap());
+        clock.advance(Duration::from_secs(30));
+        assert!(manager.lock_stream("conv-1", 30, Duration::ZERO).await.unwrap().is_none());
+        clock.advance(Duration::from_secs(1));
+        let mut lock = manager.lock_stream("conv-1", 30, Duration::ZERO).await.unwrap().unwrap();
+
+        // A running stream keeps its lock past the TTL by refreshing it
+        for _ in 0..4 {
+            clock.advance(Duration::from_secs(20));
+            lock.refresh().await;
+        }
+        assert!(manager.lock_stream("conv-1", 30, Duration::ZERO).await.unwrap().is_none());
+        lock.release().await;
+    }
+
     // ===========================================
     // Edge Case Tests
     // ===========================================
@@ -360,6 +1418,9 @@ mod tests {
             tier: Tier::Moderate,
             external_id: "user@example.com".to_string(),
             created_at: 1700000000,
+            last_used_at: None,
+            usage: SessionUsage::default(),
+            version: 0,
         };
 
         let json = serde_json::to_string(&session).unwrap();
@@ -378,6 +1439,9 @@ mod tests {
             tier: Tier::Simple,
             external_id: "".to_string(),
             created_at: 0,
+            last_used_at: None,
+            usage: SessionUsage::default(),
+            version: 0,
         };
 
         let json = serde_json::to_string(&session).unwrap();
@@ -396,6 +1460,9 @@ mod tests {
             tier: Tier::Complex,
             external_id: "user-unicode".to_string(),
             created_at: 1700000000,
+            last_used_at: None,
+            usage: SessionUsage::default(),
+            version: 0,
         };
 
         let json = serde_json::to_string(&session).unwrap();
@@ -414,6 +1481,9 @@ mod tests {
             tier: Tier::Simple,
             external_id: "user".to_string(),
             created_at: 0,
+            last_used_at: None,
+            usage: SessionUsage::default(),
+            version: 0,
         };
 
         // Far future timestamp
@@ -424,6 +1494,9 @@ mod tests {
             tier: Tier::Simple,
             external_id: "user".to_string(),
             created_at: i64::MAX,
+            last_used_at: None,
+            usage: SessionUsage::default(),
+            version: 0,
         };
 
         // Both should serialize/deserialize correctly
@@ -445,6 +1518,9 @@ mod tests {
                 tier,
                 external_id: "user-1".to_string(),
                 created_at: 1700000000,
+                last_used_at: None,
+                usage: SessionUsage::default(),
+                version: 0,
             };
 
             let json = serde_json::to_string(&session).unwrap();
@@ -10,7 +10,9 @@ use std::collections::HashMap;
 use thiserror::Error;
 
 use super::response::{Delta, StreamChoice, StreamChunk, ToolCallDelta, Usage};
+use super::translate::ToolCallIdMapping;
 use super::types::{ToolCall, ToolCallFunction};
+use crate::error::is_retryable_code;
 
 /// Metadata cached across streaming chunks for consistent response generation.
 ///
@@ -24,6 +26,8 @@ pub struct StreamMetadata {
     pub model: String,
     /// Unix timestamp of creation
     pub created: u64,
+    /// Provider backend fingerprint, when reported
+    pub system_fingerprint: Option<String>,
 }
 
 /// State accumulated during stream processing.
@@ -89,6 +93,23 @@ pub fn format_sse_done() -> Bytes {
     Bytes::from_static(b"data: [DONE]\n\n")
 }
 
+/// Re-encode complete SSE lines (as returned by `SseLineBuffer::feed`).
+///
+/// Each `data:` line ends its event; other lines (`event:`, comments) are
+/// kept in front of the data line they belong to.
+pub fn format_sse_lines(lines: &[String]) -> Bytes {
+    let mut output = String::new();
+    for line in lines {
+        output.push_str(line);
+        if line.starts_with("data:") {
+            output.push_str("\n\n");
+        } else {
+            output.push('\n');
+        }
+    }
+    Bytes::from(output)
+}
+
 /// Create a stream chunk with consistent metadata.
 ///
 /// Factory function to create a StreamChunk with metadata from the cached state,
@@ -113,6 +134,7 @@ pub fn create_chunk_with_metadata(
         object: "chat.completion.chunk".to_string(),
         created: metadata.created,
         model: metadata.model.clone(),
+        system_fingerprint: metadata.system_fingerprint.clone(),
         choices: vec![StreamChoice {
             index: 0,
             delta,
@@ -135,12 +157,14 @@ struct SseErrorDetails {
     error_type: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     code: Option<String>,
+    retryable: bool,
 }
 
 /// Format an error as an SSE error event.
 ///
 /// Creates a structured error JSON and wraps it in SSE format.
 /// This allows clients to receive error information before the stream closes.
+/// `retryable` follows the code (see [`is_retryable_code`]).
 ///
 /// # Arguments
 /// * `message` - Error message
@@ -151,9 +175,10 @@ struct SseErrorDetails {
 pub fn format_error_event(message: &str, code: Option<&str>) -> Bytes {
     let event = SseErrorEvent {
         error: SseErrorDetails {
-            message: message.to_string(),
+            message: crate::proxy::redact::redact(message).into_owned(),
             error_type: "stream_error".to_string(),
             code: code.map(|c| c.to_string()),
+            retryable: code.is_some_and(is_retryable_code),
         },
     };
     let json = serde_json::to_string(&event).expect("SseErrorEvent should always serialize");
@@ -346,6 +371,78 @@ impl ToolCallAccumulator {
     }
 }
 
+/// Rewrites the tool call deltas of a provider stream into the native format.
+///
+/// Each provider tool call ID is replaced with a Sentinel ID (`call_{uuid}`)
+/// the first time it appears, argument fragments are forwarded as they arrive,
+/// and a stream that called tools always finishes with `"tool_calls"`.
+#[derive(Debug, Default)]
+pub struct ToolCallStreamTranslator {
+    mapping: ToolCallIdMapping,
+    accumulator: ToolCallAccumulator,
+}
+
+impl ToolCallStreamTranslator {
+    /// Create a translator for one stream
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Translate a chunk in place, returning whether anything changed.
+    pub fn translate(&mut self, chunk: &mut StreamChunk) -> bool {
+        let mut changed = false;
+        for choice in &mut chunk.choices {
+            for delta in choice.delta.tool_calls.iter_mut().flatten() {
+                self.accumulator.accumulate(delta);
+                if let Some(provider_id) = delta.id.take() {
+                    let sentinel_id = match self.mapping.get_sentinel_id(&provider_id) {
+                        Some(id) => id.clone(),
+                        None => self.mapping.generate_sentinel_id(&provider_id),
+                    };
+                    delta.id = Some(sentinel_id);
+                    changed = true;
+                }
+            }
+            // Anthropic's `tool_use` (or a provider reporting `stop`) still means tool calls
+            if self.accumulator.has_tool_calls()
+                && matches!(choice.finish_reason.as_deref(), Some("stop" | "tool_use"))
+            {
+                choice.finish_reason = Some("tool_calls".to_string());
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Whether the stream has called any tools so far
+    pub fn has_tool_calls(&self) -> bool {
+        self.accumulator.has_tool_calls()
+    }
+
+    /// The provider <-> Sentinel ID mapping built so far
+    pub fn mapping(&self) -> &ToolCallIdMapping {
+        &self.mapping
+    }
+
+    /// Finalize the streamed tool calls, carrying their Sentinel IDs.
+    ///
+    /// Returns an empty list when the stream called no tools.
+    pub fn finish(self) -> Result<Vec<ToolCall>, StreamError> {
+        let mapping = self.mapping;
+        Ok(self
+            .accumulator
+            .finalize()?
+            .into_iter()
+            .map(|(provider_id, mut tool_call)| {
+                if let Some(sentinel_id) = mapping.get_sentinel_id(&provider_id) {
+                    tool_call.id = sentinel_id.clone();
+                }
+                tool_call
+            })
+            .collect())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -358,6 +455,7 @@ mod tests {
             object: "chat.completion.chunk".to_string(),
             created: 1234567890,
             model: "gpt-4".to_string(),
+            system_fingerprint: None,
             choices: vec![StreamChoice {
                 index: 0,
                 delta: Delta {
@@ -422,6 +520,7 @@ mod tests {
             id: "test-id".to_string(),
             model: "gpt-4".to_string(),
             created: 12345,
+            system_fingerprint: None,
         };
         state.set_metadata(meta);
 
@@ -437,6 +536,7 @@ mod tests {
             id: "chatcmpl-abc123".to_string(),
             model: "gpt-4-turbo".to_string(),
             created: 1700000000,
+            system_fingerprint: Some("fp_abc123".to_string()),
         };
 
         let delta = Delta {
@@ -452,6 +552,7 @@ mod tests {
         assert_eq!(chunk.model, "gpt-4-turbo");
         assert_eq!(chunk.created, 1700000000);
         assert_eq!(chunk.object, "chat.completion.chunk");
+        assert_eq!(chunk.system_fingerprint.as_deref(), Some("fp_abc123"));
 
         // Verify choice structure
         assert_eq!(chunk.choices.len(), 1);
@@ -471,6 +572,7 @@ mod tests {
             id: "chatcmpl-xyz".to_string(),
             model: "gpt-4".to_string(),
             created: 1234567890,
+            system_fingerprint: None,
         };
 
         let delta = Delta::default();
@@ -575,6 +677,7 @@ mod tests {
 
         assert_eq!(parsed["error"]["message"], "Invalid JSON at position 42");
         assert_eq!(parsed["error"]["code"], "parse_error");
+        assert_eq!(parsed["error"]["retryable"], false);
     }
 
     #[test]
@@ -591,6 +694,7 @@ mod tests {
             "Stream connection closed unexpectedly"
         );
         assert_eq!(parsed["error"]["code"], "connection_closed");
+        assert_eq!(parsed["error"]["retryable"], true);
     }
 
     #[test]
@@ -835,4 +939,92 @@ mod tests {
         assert_eq!(result[0].1.function.arguments, serde_json::json!({"a": 1}));
         assert_eq!(result[1].1.function.arguments, serde_json::json!({"b": 2}));
     }
+
+    // =============================================================================
+    // ToolCallStreamTranslator Tests
+    // =============================================================================
+
+    use super::ToolCallStreamTranslator;
+
+    fn tool_call_chunk(delta: ToolCallDelta, finish_reason: Option<&str>) -> StreamChunk {
+        let mut chunk = make_test_chunk("");
+        chunk.choices[0].delta = Delta {
+            role: None,
+            content: None,
+            tool_calls: Some(vec![delta]),
+        };
+        chunk.choices[0].finish_reason = finish_reason.map(str::to_string);
+        chunk
+    }
+
+    #[test]
+    fn test_tool_call_stream_translator_rewrites_ids() {
+        let mut translator = ToolCallStreamTranslator::new();
+
+        let mut first = tool_call_chunk(
+            ToolCallDelta {
+                index: 0,
+                id: Some("toolu_01A".to_string()),
+                call_type: Some("function".to_string()),
+                function: Some(ToolCallFunctionDelta {
+                    name: Some("get_weather".to_string()),
+                    arguments: Some("{\"city\":".to_string()),
+                }),
+            },
+            None,
+        );
+        assert!(translator.translate(&mut first));
+        let sentinel_id = first.choices[0].delta.tool_calls.as_ref().unwrap()[0]
+            .id
+            .clone()
+            .unwrap();
+        assert!(sentinel_id.starts_with("call_"));
+        assert_eq!(
+            translator.mapping().get_provider_id(&sentinel_id).unwrap(),
+            "toolu_01A"
+        );
+
+        // Argument fragments are forwarded untouched
+        let mut fragment = tool_call_chunk(
+            ToolCallDelta {
+                index: 0,
+                id: None,
+                call_type: None,
+                function: Some(ToolCallFunctionDelta {
+                    name: None,
+                    arguments: Some("\"Paris\"}".to_string()),
+                }),
+            },
+            None,
+        );
+        let original = fragment.clone();
+        assert!(!translator.translate(&mut fragment));
+        assert_eq!(fragment, original);
+
+        let mut last = make_test_chunk("");
+        last.choices[0].delta = Delta::default();
+        last.choices[0].finish_reason = Some("tool_use".to_string());
+        assert!(translator.translate(&mut last));
+        assert_eq!(last.choices[0].finish_reason.as_deref(), Some("tool_calls"));
+
+        let tool_calls = translator.finish().unwrap();
+        assert_eq!(tool_calls.len(), 1);
+        assert_eq!(tool_calls[0].id, sentinel_id);
+        assert_eq!(
+            tool_calls[0].function.arguments,
+            serde_json::json!({"city": "Paris"})
+        );
+    }
+
+    #[test]
+    fn test_tool_call_stream_translator_leaves_text_streams_alone() {
+        let mut translator = ToolCallStreamTranslator::new();
+        let mut chunk = make_test_chunk("Hello");
+        chunk.choices[0].finish_reason = Some("stop".to_string());
+        let original = chunk.clone();
+
+        assert!(!translator.translate(&mut chunk));
+        assert_eq!(chunk, original);
+        assert!(translator.finish().unwrap().is_empty());
+    }
 }
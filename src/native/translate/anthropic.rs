@@ -3,13 +3,18 @@
 //! Provides bidirectional translation between Native API format and Anthropic's API format.
 //! Handles Anthropic's strict message alternation requirements and system prompt extraction.
 //!
-//! Note: This is a scaffold for v2. The actual translation is not yet implemented,
-//! but validation logic is in place to ensure type design handles Anthropic's constraints.
+//! The translated request has no `model` or `stream`; the caller
+//! ([`crate::proxy::anthropic::AnthropicProvider`]) adds them.
 
-use super::{MessageTranslator, TranslationError};
+use serde_json::{json, Map, Value};
+
+use super::params::{normalize_params, param_bounds, ParamOutOfRange};
+use super::{MessageTranslator, ToolCallIdMapping, TranslationError};
 use crate::native::request::ChatCompletionRequest;
-use crate::native::response::ChatCompletionResponse;
-use crate::native::types::{Message, Role};
+use crate::native::response::{ChatCompletionResponse, Choice, ChoiceMessage, Usage};
+use crate::native::types::{
+    Content, ContentPart, Message, Role, ToolCall, ToolCallFunction, ToolChoice,
+};
 
 /// Anthropic API translator
 ///
@@ -19,12 +24,21 @@ use crate::native::types::{Message, Role};
 /// - Messages must strictly alternate between user and assistant
 /// - First non-system message must be from user
 #[derive(Debug, Clone, Default)]
-pub struct AnthropicTranslator;
+pub struct AnthropicTranslator {
+    /// Handling of sampling parameters outside Anthropic's bounds
+    param_mode: ParamOutOfRange,
+}
 
 impl AnthropicTranslator {
     /// Create a new Anthropic translator
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    /// Clamp or reject out-of-range sampling parameters (default: reject)
+    pub fn with_param_mode(mut self, mode: ParamOutOfRange) -> Self {
+        self.param_mode = mode;
+        self
     }
 }
 
@@ -36,6 +50,9 @@ impl AnthropicTranslator {
 /// 3. Messages must strictly alternate between user and assistant
 ///
 /// System messages are handled separately (extracted to `system` field in Anthropic API).
+/// Tool results are sent as user messages, so a tool message takes the user's
+/// turn and may be followed by more tool results or a user message, which
+/// are merged into the same turn.
 pub fn validate_anthropic_alternation(messages: &[Message]) -> Result<(), TranslationError> {
     // Filter out system messages - they go to a separate field in Anthropic API
     let non_system_messages: Vec<&Message> = messages
@@ -53,22 +70,17 @@ pub fn validate_anthropic_alternation(messages: &[Message]) -> Result<(), Transl
         return Err(TranslationError::FirstMustBeUser);
     }
 
-    // Check strict alternation
-    let mut expect_user = true;
-    for message in &non_system_messages {
-        let is_user = message.role == Role::User;
-
-        if expect_user && !is_user {
-            return Err(TranslationError::MustAlternate);
-        }
-        if !expect_user && is_user {
+    // Check strict alternation; tool results follow the assistant's tool calls
+    for pair in non_system_messages.windows(2) {
+        let alternates = matches!(
+            (&pair[0].role, &pair[1].role),
+            (Role::User, Role::Assistant)
+                | (Role::Assistant, Role::User | Role::Tool)
+                | (Role::Tool, Role::Assistant | Role::User | Role::Tool)
+        );
+        if !alternates {
             return Err(TranslationError::MustAlternate);
         }
-
-        // Toggle expected role (but Tool messages don't toggle)
-        if message.role != Role::Tool {
-            expect_user = !expect_user;
-        }
     }
 
     Ok(())
@@ -110,6 +122,121 @@ pub fn extract_system_prompt(messages: &[Message]) -> (Option<String>, Vec<&Mess
     (system_prompt, non_system_messages)
 }
 
+/// Anthropic content block for one content part
+fn content_part_block(part: &ContentPart) -> Value {
+    match part {
+        ContentPart::Text { text } => json!({"type": "text", "text": text}),
+        ContentPart::ImageUrl { image_url } => {
+            // `data:image/png;base64,...` is sent inline, anything else by URL
+            let inline = image_url
+                .url
+                .strip_prefix("data:")
+                .and_then(|rest| rest.split_once(";base64,"));
+            let source = match inline {
+                Some((media_type, data)) => {
+                    json!({"type": "base64", "media_type": media_type, "data": data})
+                }
+                None => json!({"type": "url", "url": image_url.url}),
+            };
+            json!({"type": "image", "source": source})
+        }
+    }
+}
+
+/// Content blocks of a user or assistant message
+fn content_blocks(content: &Content) -> Vec<Value> {
+    match content {
+        Content::Text(text) if text.is_empty() => Vec::new(),
+        Content::Text(text) => vec![json!({"type": "text", "text": text})],
+        Content::Parts(parts) => parts.iter().map(content_part_block).collect(),
+    }
+}
+
+/// A tool call's arguments as the object Anthropic expects in `input`
+///
+/// Arguments arriving as the JSON text OpenAI uses are parsed first.
+fn tool_input(call: &ToolCall) -> Result<Value, TranslationError> {
+    let input = match &call.function.arguments {
+        Value::String(text) if text.trim().is_empty() => json!({}),
+        Value::String(text) => serde_json::from_str(text)
+            .map_err(|e| TranslationError::MalformedArguments(format!("{}: {}", call.id, e)))?,
+        other => other.clone(),
+    };
+    if !input.is_object() {
+        return Err(TranslationError::MalformedArguments(format!(
+            "{}: arguments must be a JSON object",
+            call.id
+        )));
+    }
+    Ok(input)
+}
+
+/// Translate non-system messages to Anthropic `messages`
+///
+/// Assistant tool calls become `tool_use` blocks and tool messages
+/// `tool_result` blocks of a user turn, merged with the user text that
+/// follows them.
+fn translate_messages(messages: &[&Message]) -> Result<Vec<Value>, TranslationError> {
+    let mut translated: Vec<Value> = Vec::with_capacity(messages.len());
+    for message in messages {
+        let (role, blocks) = match message.role {
+            Role::User => ("user", content_blocks(&message.content)),
+            Role::Assistant => {
+                let mut blocks = content_blocks(&message.content);
+                for call in message.tool_calls.iter().flatten() {
+                    blocks.push(json!({
+                        "type": "tool_use",
+                        "id": call.id,
+                        "name": call.function.name,
+                        "input": tool_input(call)?,
+                    }));
+                }
+                ("assistant", blocks)
+            }
+            Role::Tool => {
+                let tool_use_id = message.tool_call_id.as_deref().ok_or_else(|| {
+                    TranslationError::MissingRequiredField("tool_call_id".to_string())
+                })?;
+                let block = json!({
+                    "type": "tool_result",
+                    "tool_use_id": tool_use_id,
+                    "content": message.content.as_text(),
+                });
+                ("user", vec![block])
+            }
+            Role::System => continue,
+        };
+
+        match translated.last_mut() {
+            Some(previous) if previous["role"] == role => {
+                if let Some(content) = previous["content"].as_array_mut() {
+                    content.extend(blocks);
+                }
+            }
+            _ => translated.push(json!({"role": role, "content": blocks})),
+        }
+    }
+    Ok(translated)
+}
+
+/// Anthropic `tool_choice` for a Native one
+fn translate_tool_choice(choice: &ToolChoice) -> Value {
+    match choice {
+        ToolChoice::Auto => json!({"type": "auto"}),
+        ToolChoice::None => json!({"type": "none"}),
+        ToolChoice::Required => json!({"type": "any"}),
+        ToolChoice::Function { name } => json!({"type": "tool", "name": name}),
+    }
+}
+
+/// Field of an Anthropic response, or a `MissingRequiredField` error
+fn required<'a>(value: &'a Value, field: &str) -> Result<&'a Value, TranslationError> {
+    value
+        .get(field)
+        .filter(|value| !value.is_null())
+        .ok_or_else(|| TranslationError::MissingRequiredField(field.to_string()))
+}
+
 impl MessageTranslator for AnthropicTranslator {
     fn translate_request(
         &self,
@@ -117,21 +244,131 @@ impl MessageTranslator for AnthropicTranslator {
     ) -> Result<serde_json::Value, TranslationError> {
         // Validate Anthropic-specific requirements
         validate_anthropic_alternation(&request.messages)?;
+        let params = normalize_params(request, &param_bounds("anthropic"), self.param_mode)?;
 
-        // Actual translation not implemented yet - this is a scaffold for v2
-        Err(TranslationError::NotImplemented(
-            "Anthropic request translation".to_string(),
-        ))
+        let (system, messages) = extract_system_prompt(&request.messages);
+        let mut body = Map::new();
+        body.insert(
+            "messages".to_string(),
+            Value::Array(translate_messages(&messages)?),
+        );
+        if let Some(system) = system {
+            body.insert("system".to_string(), json!(system));
+        }
+        body.insert("max_tokens".to_string(), json!(params.max_tokens));
+        if let Some(temperature) = params.temperature {
+            body.insert("temperature".to_string(), json!(temperature));
+        }
+        if let Some(top_p) = params.top_p {
+            body.insert("top_p".to_string(), json!(top_p));
+        }
+        if let Some(ref stop) = request.stop {
+            body.insert("stop_sequences".to_string(), json!(stop.sequences()));
+        }
+        if let Some(ref tools) = request.tools {
+            let tools: Vec<Value> = tools
+                .iter()
+                .map(|tool| {
+                    json!({
+                        "name": tool.function.name,
+                        "description": tool.function.description,
+                        "input_schema": tool.function.parameters,
+                    })
+                })
+                .collect();
+            body.insert("tools".to_string(), Value::Array(tools));
+        }
+        if let Some(ref choice) = request.tool_choice {
+            body.insert("tool_choice".to_string(), translate_tool_choice(choice));
+        }
+        if let Some(ref user) = request.user {
+            body.insert("metadata".to_string(), json!({"user_id": user}));
+        }
+
+        Ok(Value::Object(body))
     }
 
     fn translate_response(
         &self,
-        _response: serde_json::Value,
-    ) -> Result<(ChatCompletionResponse, super::ToolCallIdMapping), TranslationError> {
-        // Actual translation not implemented yet - this is a scaffold for v2
-        Err(TranslationError::NotImplemented(
-            "Anthropic response translation".to_string(),
-        ))
+        response: serde_json::Value,
+    ) -> Result<(ChatCompletionResponse, ToolCallIdMapping), TranslationError> {
+        let mut id_mapping = ToolCallIdMapping::new();
+
+        let id = required(&response, "id")?
+            .as_str()
+            .ok_or_else(|| {
+                TranslationError::InvalidMessageFormat("id is not a string".to_string())
+            })?
+            .to_string();
+        let model = required(&response, "model")?
+            .as_str()
+            .ok_or_else(|| {
+                TranslationError::InvalidMessageFormat("model is not a string".to_string())
+            })?
+            .to_string();
+        let blocks = required(&response, "content")?.as_array().ok_or_else(|| {
+            TranslationError::InvalidMessageFormat("content is not an array".to_string())
+        })?;
+
+        let mut text = String::new();
+        let mut tool_calls = Vec::new();
+        for block in blocks {
+            match block.get("type").and_then(Value::as_str) {
+                Some("text") => text.push_str(block["text"].as_str().unwrap_or_default()),
+                Some("tool_use") => {
+                    let provider_id = required(block, "id")?.as_str().unwrap_or_default();
+                    let name = required(block, "name")?.as_str().unwrap_or_default();
+                    tool_calls.push(ToolCall {
+                        id: id_mapping.generate_sentinel_id(provider_id),
+                        call_type: "function".to_string(),
+                        function: ToolCallFunction {
+                            name: name.to_string(),
+                            arguments: block.get("input").cloned().unwrap_or_else(|| json!({})),
+                        },
+                    });
+                }
+                // Thinking and other blocks aren't part of the unified response
+                _ => {}
+            }
+        }
+
+        let usage = response.get("usage");
+        let tokens = |field: &str| {
+            usage
+                .and_then(|usage| usage.get(field))
+                .and_then(Value::as_u64)
+                .unwrap_or(0) as u32
+        };
+        let (prompt_tokens, completion_tokens) = (tokens("input_tokens"), tokens("output_tokens"));
+
+        let finish_reason = response
+            .get("stop_reason")
+            .and_then(Value::as_str)
+            .map(|reason| self.translate_stop_reason(reason));
+
+        let response = ChatCompletionResponse {
+            id,
+            object: "chat.completion".to_string(),
+            created: chrono::Utc::now().timestamp() as u64,
+            model,
+            system_fingerprint: None,
+            choices: vec![Choice {
+                index: 0,
+                message: ChoiceMessage {
+                    role: Role::Assistant,
+                    content: (!text.is_empty() || tool_calls.is_empty()).then_some(text),
+                    tool_calls: (!tool_calls.is_empty()).then_some(tool_calls),
+                },
+                finish_reason,
+            }],
+            usage: Usage {
+                prompt_tokens,
+                completion_tokens,
+                total_tokens: prompt_tokens + completion_tokens,
+            },
+        };
+
+        Ok((response, id_mapping))
     }
 
     fn translate_stop_reason(&self, reason: &str) -> String {
@@ -151,7 +388,8 @@ impl MessageTranslator for AnthropicTranslator {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::native::types::Content;
+    use crate::native::request::StopSequence;
+    use crate::native::types::{FunctionDefinition, ImageUrl, ToolDefinition};
 
     fn make_message(role: Role, text: &str) -> Message {
         Message {
@@ -249,6 +487,227 @@ mod tests {
         assert_eq!(remaining.len(), 2);
     }
 
+    fn request_with_params(temperature: Option<f64>, top_p: Option<f64>) -> ChatCompletionRequest {
+        ChatCompletionRequest {
+            tier: None,
+            messages: vec![make_message(Role::User, "Hello")],
+            temperature,
+            max_tokens: None,
+            top_p,
+            stop: None,
+            stream: false,
+            conversation_id: None,
+            workflow_id: None,
+            tools: None,
+            tool_choice: None,
+            timeout_ms: None,
+            user: None,
+            seed: None,
+        }
+    }
+
+    #[test]
+    fn test_param_bounds_rejected() {
+        let translator = AnthropicTranslator::new();
+        // OpenAI accepts 1.5, Anthropic stops at 1
+        let result = translator.translate_request(&request_with_params(Some(1.5), None));
+        assert!(
+            matches!(result, Err(TranslationError::InvalidParameter(ref msg)) if msg.contains("temperature"))
+        );
+        let result = translator.translate_request(&request_with_params(None, Some(1.2)));
+        assert!(
+            matches!(result, Err(TranslationError::InvalidParameter(ref msg)) if msg.contains("top_p"))
+        );
+        let result = translator.translate_request(&request_with_params(Some(f64::NAN), None));
+        assert!(matches!(result, Err(TranslationError::InvalidParameter(_))));
+    }
+
+    #[test]
+    fn test_param_bounds_clamped() {
+        let translator = AnthropicTranslator::new().with_param_mode(ParamOutOfRange::Clamp);
+        let body = translator
+            .translate_request(&request_with_params(Some(1.5), Some(1.2)))
+            .unwrap();
+        assert_eq!(body["temperature"], 1.0);
+        assert_eq!(body["top_p"], 1.0);
+        let result = translator.translate_request(&request_with_params(Some(f64::INFINITY), None));
+        assert!(matches!(result, Err(TranslationError::InvalidParameter(_))));
+    }
+
+    #[test]
+    fn test_alternation_with_tool_results() {
+        let mut tool = make_message(Role::Tool, "18C");
+        tool.tool_call_id = Some("toolu_1".to_string());
+        let messages = vec![
+            make_message(Role::User, "Weather in Paris?"),
+            make_message(Role::Assistant, ""),
+            tool.clone(),
+            tool,
+            make_message(Role::User, "And tomorrow?"),
+        ];
+        assert!(validate_anthropic_alternation(&messages).is_ok());
+
+        let messages = vec![
+            make_message(Role::User, "Hello"),
+            make_message(Role::Tool, "18C"),
+        ];
+        assert!(matches!(
+            validate_anthropic_alternation(&messages),
+            Err(TranslationError::MustAlternate)
+        ));
+    }
+
+    #[test]
+    fn test_translate_request() {
+        let mut request = request_with_params(Some(0.5), None);
+        request
+            .messages
+            .insert(0, make_message(Role::System, "Be brief."));
+        request.max_tokens = Some(100);
+        request.stop = Some(StopSequence::Single("END".to_string()));
+        request.tools = Some(vec![ToolDefinition {
+            tool_type: "function".to_string(),
+            function: FunctionDefinition {
+                name: "get_weather".to_string(),
+                description: "Current weather".to_string(),
+                parameters: json!({"type": "object"}),
+            },
+        }]);
+        request.tool_choice = Some(ToolChoice::Required);
+        request.user = Some("user-1234".to_string());
+
+        let body = AnthropicTranslator::new()
+            .translate_request(&request)
+            .unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "system": "Be brief.",
+                "messages": [{"role": "user", "content": [{"type": "text", "text": "Hello"}]}],
+                "max_tokens": 100,
+                "temperature": 0.5,
+                "stop_sequences": ["END"],
+                "tools": [{
+                    "name": "get_weather",
+                    "description": "Current weather",
+                    "input_schema": {"type": "object"}
+                }],
+                "tool_choice": {"type": "any"},
+                "metadata": {"user_id": "user-1234"}
+            })
+        );
+
+        // Anthropic requires max_tokens
+        let body = AnthropicTranslator::new()
+            .translate_request(&request_with_params(None, None))
+            .unwrap();
+        assert_eq!(body["max_tokens"], 4096);
+    }
+
+    #[test]
+    fn test_translate_tool_round_trip() {
+        let mut assistant = make_message(Role::Assistant, "Checking.");
+        assistant.tool_calls = Some(vec![ToolCall {
+            id: "call_1".to_string(),
+            call_type: "function".to_string(),
+            function: ToolCallFunction {
+                name: "get_weather".to_string(),
+                arguments: json!("{\"city\":\"Paris\"}"),
+            },
+        }]);
+        let mut tool = make_message(Role::Tool, "18C");
+        tool.tool_call_id = Some("call_1".to_string());
+        let mut request = request_with_params(None, None);
+        request
+            .messages
+            .extend([assistant, tool, make_message(Role::User, "Thanks")]);
+
+        let body = AnthropicTranslator::new()
+            .translate_request(&request)
+            .unwrap();
+        let messages = body["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(
+            messages[1]["content"][1],
+            json!({"type": "tool_use", "id": "call_1", "name": "get_weather", "input": {"city": "Paris"}})
+        );
+        // The tool result and the next user text share one user turn
+        assert_eq!(messages[2]["role"], "user");
+        assert_eq!(
+            messages[2]["content"],
+            json!([
+                {"type": "tool_result", "tool_use_id": "call_1", "content": "18C"},
+                {"type": "text", "text": "Thanks"}
+            ])
+        );
+    }
+
+    #[test]
+    fn test_translate_image_parts() {
+        let mut request = request_with_params(None, None);
+        request.messages[0].content = Content::Parts(vec![
+            ContentPart::ImageUrl {
+                image_url: ImageUrl {
+                    url: "data:image/png;base64,iVBORw0=".to_string(),
+                    detail: None,
+                },
+            },
+            ContentPart::ImageUrl {
+                image_url: ImageUrl {
+                    url: "https://example.com/cat.jpg".to_string(),
+                    detail: None,
+                },
+            },
+        ]);
+
+        let body = AnthropicTranslator::new()
+            .translate_request(&request)
+            .unwrap();
+        let content = &body["messages"][0]["content"];
+        assert_eq!(
+            content[0]["source"],
+            json!({"type": "base64", "media_type": "image/png", "data": "iVBORw0="})
+        );
+        assert_eq!(
+            content[1]["source"],
+            json!({"type": "url", "url": "https://example.com/cat.jpg"})
+        );
+    }
+
+    #[test]
+    fn test_translate_response() {
+        let (response, mapping) = AnthropicTranslator::new()
+            .translate_response(json!({
+                "id": "msg_1",
+                "type": "message",
+                "role": "assistant",
+                "model": "claude-sonnet-4-5",
+                "content": [
+                    {"type": "text", "text": "Let me check."},
+                    {"type": "tool_use", "id": "toolu_1", "name": "get_weather", "input": {"city": "Paris"}}
+                ],
+                "stop_reason": "tool_use",
+                "usage": {"input_tokens": 12, "output_tokens": 8}
+            }))
+            .unwrap();
+
+        assert_eq!(response.id, "msg_1");
+        assert_eq!(response.model, "claude-sonnet-4-5");
+        let choice = &response.choices[0];
+        assert_eq!(choice.message.content.as_deref(), Some("Let me check."));
+        assert_eq!(choice.finish_reason.as_deref(), Some("tool_calls"));
+        let call = &choice.message.tool_calls.as_ref().unwrap()[0];
+        assert_eq!(mapping.get_provider_id(&call.id).unwrap(), "toolu_1");
+        assert_eq!(call.function.arguments, json!({"city": "Paris"}));
+        assert_eq!(response.usage.total_tokens, 20);
+
+        let result = AnthropicTranslator::new().translate_response(json!({"id": "msg_1"}));
+        assert!(matches!(
+            result,
+            Err(TranslationError::MissingRequiredField(_))
+        ));
+    }
+
     #[test]
     fn test_stop_reason_end_turn() {
         let translator = AnthropicTranslator::new();
@@ -6,6 +6,7 @@
 
 pub mod anthropic;
 pub mod openai;
+pub mod params;
 
 use std::collections::HashMap;
 use thiserror::Error;
@@ -111,6 +112,10 @@ pub enum TranslationError {
     /// Tool call ID not found in conversation history
     #[error("No tool call found in history for tool_call_id: {0}")]
     MissingToolCallInHistory(String),
+
+    /// Sampling parameter outside the target provider's bounds, or not finite
+    #[error("Invalid parameter: {0}")]
+    InvalidParameter(String),
 }
 
 /// Trait for translating between Native API format and provider-specific formats
@@ -172,6 +177,7 @@ pub trait MessageTranslator {
 // Re-export key types for convenience
 pub use anthropic::AnthropicTranslator;
 pub use openai::OpenAITranslator;
+pub use params::{param_bounds, ParamBounds, ParamOutOfRange};
 
 #[cfg(test)]
 mod tests {
@@ -3,8 +3,12 @@
 //! Provides bidirectional translation between Native API format and OpenAI's API format.
 //! Since the Native API is designed to be OpenAI-compatible, translation is minimal.
 
-use serde_json::json;
+use std::collections::HashMap;
 
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use super::params::{normalize_params, param_bounds, ParamOutOfRange};
 use super::{MessageTranslator, ToolCallIdMapping, TranslationError};
 use crate::native::request::ChatCompletionRequest;
 use crate::native::response::{ChatCompletionResponse, Choice, ChoiceMessage, Usage};
@@ -18,12 +22,47 @@ use crate::native::types::{
 /// Since the Native API is designed to be OpenAI-compatible, most fields pass
 /// through unchanged, with validation ensuring message ordering requirements.
 #[derive(Debug, Clone, Default)]
-pub struct OpenAITranslator;
+pub struct OpenAITranslator {
+    /// Target is a reasoning model: system messages become `developer`,
+    /// sampling parameters are dropped and `max_tokens` is sent as
+    /// `max_completion_tokens`
+    reasoning: bool,
+    /// Handling of sampling parameters outside OpenAI's bounds
+    param_mode: ParamOutOfRange,
+}
 
 impl OpenAITranslator {
     /// Create a new OpenAI translator
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    /// Create a translator for a reasoning model (o1/o3 family)
+    pub fn for_reasoning_model() -> Self {
+        Self {
+            reasoning: true,
+            ..Self::default()
+        }
+    }
+
+    /// Clamp or reject out-of-range sampling parameters (default: reject)
+    pub fn with_param_mode(mut self, mode: ParamOutOfRange) -> Self {
+        self.param_mode = mode;
+        self
+    }
+
+    /// Parameters set on `request` that this translator will not forward
+    pub fn unsupported_params(&self, request: &ChatCompletionRequest) -> Vec<&'static str> {
+        if !self.reasoning {
+            return Vec::new();
+        }
+        [
+            ("temperature", request.temperature.is_some()),
+            ("top_p", request.top_p.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(param, set)| set.then_some(param))
+        .collect()
     }
 }
 
@@ -50,33 +89,51 @@ fn validate_message_order(messages: &[Message]) -> Result<(), TranslationError>
     Ok(())
 }
 
-/// Find function name for a tool_call_id by searching conversation history.
+/// A message in OpenAI's format, borrowing from the native request
 ///
-/// Searches backwards through messages for an assistant message containing
-/// tool_calls with a matching tool_call_id, then extracts the function name
-/// from that tool_call.
+/// The whole list is serialized into the request body in one pass, so large
+/// conversations are not cloned or converted message by message.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum OpenAIMessage<'a> {
+    /// Native messages are already OpenAI-compatible
+    Native(&'a Message),
+    /// A message that needed rewriting (system messages for reasoning models)
+    Rewritten(Value),
+    /// Tool result with the `name` OpenAI requires
+    ToolResult {
+        role: &'static str,
+        tool_call_id: &'a str,
+        name: &'a str,
+        content: String,
+    },
+}
+
+/// Function names of the tool calls made so far, by tool call id
 ///
-/// This is needed because OpenAI's tool message format requires a `name` field,
-/// but our unified format doesn't store the name on tool result messages.
-fn find_function_name_for_tool_call(
-    messages: &[Message],
-    tool_call_id: &str,
-    current_index: usize,
-) -> Option<String> {
-    // Search backwards from current_index
-    for i in (0..current_index).rev() {
-        let msg = &messages[i];
-        if msg.role == Role::Assistant {
-            if let Some(ref tool_calls) = msg.tool_calls {
-                for tc in tool_calls {
-                    if tc.id == tool_call_id {
-                        return Some(tc.function.name.clone());
-                    }
-                }
+/// OpenAI's tool message format requires a `name` field, but our unified
+/// format doesn't store the name on tool result messages. A result takes the
+/// name from the nearest preceding assistant call with its id, and within one
+/// message from the first call with that id.
+#[derive(Default)]
+struct ToolCallNames<'a> {
+    names: HashMap<&'a str, &'a str>,
+}
+
+impl<'a> ToolCallNames<'a> {
+    /// Remember the calls of an assistant message, shadowing earlier ones
+    fn record(&mut self, message: &'a Message) {
+        if let Some(ref tool_calls) = message.tool_calls {
+            // Reversed, so the first call with a repeated id wins
+            for tc in tool_calls.iter().rev() {
+                self.names.insert(tc.id.as_str(), tc.function.name.as_str());
             }
         }
     }
-    None
+
+    fn get(&self, tool_call_id: &str) -> Option<&'a str> {
+        self.names.get(tool_call_id).copied()
+    }
 }
 
 impl MessageTranslator for OpenAITranslator {
@@ -84,57 +141,64 @@ impl MessageTranslator for OpenAITranslator {
         &self,
         request: &ChatCompletionRequest,
     ) -> Result<serde_json::Value, TranslationError> {
-        // Validate message ordering
+        // Validate message ordering and parameter ranges
         validate_message_order(&request.messages)?;
+        let params = normalize_params(request, &param_bounds("openai"), self.param_mode)?;
 
         // Transform messages for OpenAI format
         // Most messages serialize directly, but Tool messages need function name lookup
         let mut translated_messages = Vec::with_capacity(request.messages.len());
-        for (idx, msg) in request.messages.iter().enumerate() {
-            if msg.role == Role::Tool {
-                // Tool message needs function name from history
-                let tool_call_id = msg.tool_call_id.as_ref().ok_or_else(|| {
-                    TranslationError::MissingRequiredField(
-                        "tool_call_id is required for tool messages".to_string(),
-                    )
-                })?;
+        let mut tool_call_names = ToolCallNames::default();
+        for msg in &request.messages {
+            match msg.role {
+                Role::Tool => {
+                    // Tool message needs function name from history
+                    let tool_call_id = msg.tool_call_id.as_ref().ok_or_else(|| {
+                        TranslationError::MissingRequiredField(
+                            "tool_call_id is required for tool messages".to_string(),
+                        )
+                    })?;
 
-                let function_name =
-                    find_function_name_for_tool_call(&request.messages, tool_call_id, idx)
-                        .ok_or_else(|| {
-                            TranslationError::MissingToolCallInHistory(tool_call_id.clone())
-                        })?;
+                    let name = tool_call_names.get(tool_call_id).ok_or_else(|| {
+                        TranslationError::MissingToolCallInHistory(tool_call_id.clone())
+                    })?;
 
-                // Build OpenAI tool message format with name field
-                let content_str = msg.content.as_text();
-                translated_messages.push(json!({
-                    "role": "tool",
-                    "tool_call_id": tool_call_id,
-                    "name": function_name,
-                    "content": content_str
-                }));
-            } else {
-                // Other message types serialize directly
-                translated_messages.push(serde_json::to_value(msg)?);
+                    translated_messages.push(OpenAIMessage::ToolResult {
+                        role: "tool",
+                        tool_call_id,
+                        name,
+                        content: msg.content.as_text(),
+                    });
+                }
+                Role::System if self.reasoning => {
+                    let mut value = serde_json::to_value(msg)?;
+                    value["role"] = json!("developer");
+                    translated_messages.push(OpenAIMessage::Rewritten(value));
+                }
+                Role::Assistant => {
+                    tool_call_names.record(msg);
+                    translated_messages.push(OpenAIMessage::Native(msg));
+                }
+                _ => translated_messages.push(OpenAIMessage::Native(msg)),
             }
         }
 
-        // Build the request JSON
+        // Build the request JSON; the messages are serialized once, straight from the request
         // Note: model is not included here - it's injected by the handler after tier routing
-        let mut obj = json!({
-            "messages": translated_messages,
-        });
+        let mut obj = Value::Object(Map::new());
+        obj["messages"] = serde_json::to_value(&translated_messages)?;
 
         // Add optional fields if present
-        if let Some(temperature) = request.temperature {
+        if let Some(temperature) = params.temperature.filter(|_| !self.reasoning) {
             obj["temperature"] = json!(temperature);
         }
 
-        if let Some(max_tokens) = request.max_tokens {
-            obj["max_tokens"] = json!(max_tokens);
+        if let Some(max_tokens) = params.max_tokens {
+            let field = if self.reasoning { "max_completion_tokens" } else { "max_tokens" };
+            obj[field] = json!(max_tokens);
         }
 
-        if let Some(top_p) = request.top_p {
+        if let Some(top_p) = params.top_p.filter(|_| !self.reasoning) {
             obj["top_p"] = json!(top_p);
         }
 
@@ -142,10 +206,18 @@ impl MessageTranslator for OpenAITranslator {
             obj["stop"] = serde_json::to_value(stop)?;
         }
 
+        if let Some(seed) = request.seed {
+            obj["seed"] = json!(seed);
+        }
+
         if request.stream {
             obj["stream"] = json!(true);
         }
 
+        if let Some(ref user) = request.user {
+            obj["user"] = json!(user);
+        }
+
         // Add tools if present and non-empty
         if let Some(ref tools) = request.tools {
             if !tools.is_empty() {
@@ -222,6 +294,12 @@ impl MessageTranslator for OpenAITranslator {
             .ok_or_else(|| TranslationError::MissingRequiredField("model".to_string()))?
             .to_string();
 
+        // Optional: lets seeded clients notice backend changes
+        let system_fingerprint = response
+            .get("system_fingerprint")
+            .and_then(|v| v.as_str())
+            .map(str::to_string);
+
         // Parse choices
         let choices_value = response
             .get("choices")
@@ -374,6 +452,7 @@ impl MessageTranslator for OpenAITranslator {
                 object,
                 created,
                 model,
+                system_fingerprint,
                 choices,
                 usage: Usage {
                     prompt_tokens,
@@ -428,8 +507,12 @@ mod tests {
             stop: None,
             stream: false,
             conversation_id: None,
+            workflow_id: None,
             tools: None,
             tool_choice: None,
+            timeout_ms: None,
+            user: None,
+            seed: None,
         };
 
         let result = translator.translate_request(&request).unwrap();
@@ -458,8 +541,12 @@ mod tests {
             stop: None,
             stream: true,
             conversation_id: None,
+            workflow_id: None,
             tools: None,
             tool_choice: None,
+            timeout_ms: None,
+            user: Some("user-1234".to_string()),
+            seed: Some(42),
         };
 
         let result = translator.translate_request(&request).unwrap();
@@ -470,6 +557,106 @@ mod tests {
         assert_eq!(result.get("max_tokens").unwrap(), 500);
         assert_eq!(result.get("top_p").unwrap(), 0.95);
         assert_eq!(result.get("stream").unwrap(), true);
+        assert_eq!(result.get("seed").unwrap(), 42);
+        assert_eq!(result.get("user").unwrap(), "user-1234");
+    }
+
+    fn request_with_params(
+        temperature: Option<f64>,
+        top_p: Option<f64>,
+        max_tokens: Option<u32>,
+    ) -> ChatCompletionRequest {
+        ChatCompletionRequest {
+            tier: None,
+            messages: vec![make_message(Role::User, "Hi")],
+            temperature,
+            max_tokens,
+            top_p,
+            stop: None,
+            stream: false,
+            conversation_id: None,
+            workflow_id: None,
+            tools: None,
+            tool_choice: None,
+            timeout_ms: None,
+            user: None,
+            seed: None,
+        }
+    }
+
+    #[test]
+    fn test_param_bounds_rejected() {
+        let translator = OpenAITranslator::new();
+        for (request, name) in [
+            (request_with_params(Some(2.5), None, None), "temperature"),
+            (request_with_params(Some(-0.5), None, None), "temperature"),
+            (request_with_params(None, Some(1.5), None), "top_p"),
+            (request_with_params(None, None, Some(0)), "max_tokens"),
+            (request_with_params(Some(f64::NAN), None, None), "temperature"),
+        ] {
+            let result = translator.translate_request(&request);
+            assert!(
+                matches!(result, Err(TranslationError::InvalidParameter(ref msg)) if msg.contains(name)),
+                "{:?}",
+                result
+            );
+        }
+        // Anthropic's temperature limit doesn't apply here
+        let result = translator
+            .translate_request(&request_with_params(Some(1.5), None, None))
+            .unwrap();
+        assert_eq!(result["temperature"], 1.5);
+        // max_tokens stays optional
+        assert!(result.get("max_tokens").is_none());
+    }
+
+    #[test]
+    fn test_param_bounds_clamped() {
+        let translator = OpenAITranslator::new().with_param_mode(ParamOutOfRange::Clamp);
+        let result = translator
+            .translate_request(&request_with_params(Some(2.5), Some(-1.0), Some(0)))
+            .unwrap();
+        assert_eq!(result["temperature"], 2.0);
+        assert_eq!(result["top_p"], 0.0);
+        assert_eq!(result["max_tokens"], 1);
+
+        let result =
+            translator.translate_request(&request_with_params(Some(f64::INFINITY), None, None));
+        assert!(matches!(result, Err(TranslationError::InvalidParameter(_))));
+    }
+
+    #[test]
+    fn test_translate_request_for_reasoning_model() {
+        let translator = OpenAITranslator::for_reasoning_model();
+        let request = ChatCompletionRequest {
+            tier: None,
+            messages: vec![
+                make_message(Role::System, "Think carefully."),
+                make_message(Role::User, "Hi"),
+            ],
+            temperature: Some(0.7),
+            max_tokens: Some(500),
+            top_p: None,
+            stop: None,
+            stream: false,
+            conversation_id: None,
+            workflow_id: None,
+            tools: None,
+            tool_choice: None,
+            timeout_ms: None,
+            user: None,
+            seed: None,
+        };
+
+        assert_eq!(translator.unsupported_params(&request), vec!["temperature"]);
+        assert!(OpenAITranslator::new().unsupported_params(&request).is_empty());
+
+        let result = translator.translate_request(&request).unwrap();
+        assert_eq!(result["messages"][0]["role"], "developer");
+        assert_eq!(result["messages"][1]["role"], "user");
+        assert!(result.get("temperature").is_none());
+        assert!(result.get("max_tokens").is_none());
+        assert_eq!(result["max_completion_tokens"], 500);
     }
 
     #[test]
@@ -487,8 +674,12 @@ mod tests {
             stop: None,
             stream: false,
             conversation_id: None,
+            workflow_id: None,
             tools: None,
             tool_choice: None,
+            timeout_ms: None,
+            user: None,
+            seed: None,
         };
 
         let result = translator.translate_request(&request);
@@ -538,10 +729,38 @@ mod tests {
         assert_eq!(result.usage.prompt_tokens, 9);
         assert_eq!(result.usage.completion_tokens, 12);
         assert_eq!(result.usage.total_tokens, 21);
+        assert!(result.system_fingerprint.is_none());
         // No tool calls, so mapping should be empty
         assert!(mapping.is_empty());
     }
 
+    #[test]
+    fn test_translate_response_keeps_system_fingerprint() {
+        let translator = OpenAITranslator::new();
+        let response = json!({
+            "id": "chatcmpl-123",
+            "object": "chat.completion",
+            "created": 1677652288,
+            "model": "gpt-4o-2024-08-06",
+            "system_fingerprint": "fp_44709d6fcb",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Hi"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 5, "completion_tokens": 1, "total_tokens": 6}
+        });
+
+        let (result, _) = translator.translate_response(response).unwrap();
+        assert_eq!(result.system_fingerprint.as_deref(), Some("fp_44709d6fcb"));
+
+        // Serializes back to the OpenAI field
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["system_fingerprint"], "fp_44709d6fcb");
+        let roundtrip: ChatCompletionResponse = serde_json::from_value(json).unwrap();
+        assert_eq!(roundtrip, result);
+    }
+
     #[test]
     fn test_translate_stop_reason() {
         let translator = OpenAITranslator::new();
@@ -572,8 +791,12 @@ mod tests {
             stop: None,
             stream: false,
             conversation_id: None,
+            workflow_id: None,
             tools: None,
             tool_choice: None,
+            timeout_ms: None,
+            user: None,
+            seed: None,
         };
 
         // Empty messages should translate without error
@@ -602,8 +825,12 @@ mod tests {
             stop: None,
             stream: false,
             conversation_id: None,
+            workflow_id: None,
             tools: None,
             tool_choice: None,
+            timeout_ms: None,
+            user: None,
+            seed: None,
         };
 
         // Multiple system messages at start should be valid
@@ -682,6 +909,7 @@ mod tests {
             stop: None,
             stream: false,
             conversation_id: None,
+            workflow_id: None,
             tools: Some(vec![ToolDefinition {
                 tool_type: "function".to_string(),
                 function: FunctionDefinition {
@@ -697,6 +925,9 @@ mod tests {
                 },
             }]),
             tool_choice: None,
+            timeout_ms: None,
+            user: None,
+            seed: None,
         };
 
         let result = translator.translate_request(&request).unwrap();
@@ -720,6 +951,7 @@ mod tests {
             stop: None,
             stream: false,
             conversation_id: None,
+            workflow_id: None,
             tools: Some(vec![ToolDefinition {
                 tool_type: "function".to_string(),
                 function: FunctionDefinition {
@@ -729,6 +961,9 @@ mod tests {
                 },
             }]),
             tool_choice: None,
+            timeout_ms: None,
+            user: None,
+            seed: None,
         };
 
         let result = translator.translate_request(&request);
@@ -752,6 +987,7 @@ mod tests {
             stop: None,
             stream: false,
             conversation_id: None,
+            workflow_id: None,
             tools: Some(vec![ToolDefinition {
                 tool_type: "function".to_string(),
                 function: FunctionDefinition {
@@ -761,6 +997,9 @@ mod tests {
                 },
             }]),
             tool_choice: None,
+            timeout_ms: None,
+            user: None,
+            seed: None,
         };
 
         let result = translator.translate_request(&request);
@@ -784,6 +1023,7 @@ mod tests {
             stop: None,
             stream: false,
             conversation_id: None,
+            workflow_id: None,
             tools: Some(vec![ToolDefinition {
                 tool_type: "function".to_string(),
                 function: FunctionDefinition {
@@ -793,6 +1033,9 @@ mod tests {
                 },
             }]),
             tool_choice: None,
+            timeout_ms: None,
+            user: None,
+            seed: None,
         };
 
         let result = translator.translate_request(&request);
@@ -814,8 +1057,12 @@ mod tests {
             stop: None,
             stream: false,
             conversation_id: None,
+            workflow_id: None,
             tools: Some(vec![]),
             tool_choice: None,
+            timeout_ms: None,
+            user: None,
+            seed: None,
         };
 
         let result = translator.translate_request(&request).unwrap();
@@ -835,8 +1082,12 @@ mod tests {
             stop: None,
             stream: false,
             conversation_id: None,
+            workflow_id: None,
             tools: None,
             tool_choice: Some(ToolChoice::Auto),
+            timeout_ms: None,
+            user: None,
+            seed: None,
         };
 
         let result = translator.translate_request(&request).unwrap();
@@ -855,8 +1106,12 @@ mod tests {
             stop: None,
             stream: false,
             conversation_id: None,
+            workflow_id: None,
             tools: None,
             tool_choice: Some(ToolChoice::None),
+            timeout_ms: None,
+            user: None,
+            seed: None,
         };
 
         let result = translator.translate_request(&request).unwrap();
@@ -875,8 +1130,12 @@ mod tests {
             stop: None,
             stream: false,
             conversation_id: None,
+            workflow_id: None,
             tools: None,
             tool_choice: Some(ToolChoice::Required),
+            timeout_ms: None,
+            user: None,
+            seed: None,
         };
 
         let result = translator.translate_request(&request).unwrap();
@@ -895,10 +1154,14 @@ mod tests {
             stop: None,
             stream: false,
             conversation_id: None,
+            workflow_id: None,
             tools: None,
             tool_choice: Some(ToolChoice::Function {
                 name: "get_weather".to_string(),
             }),
+            timeout_ms: None,
+            user: None,
+            seed: None,
         };
 
         let result = translator.translate_request(&request).unwrap();
@@ -1187,8 +1450,12 @@ mod tests {
             stop: None,
             stream: false,
             conversation_id: None,
+            workflow_id: None,
             tools: None,
             tool_choice: None,
+            timeout_ms: None,
+            user: None,
+            seed: None,
         };
 
         let result = translator.translate_request(&request).unwrap();
@@ -1225,8 +1492,12 @@ mod tests {
             stop: None,
             stream: false,
             conversation_id: None,
+            workflow_id: None,
             tools: None,
             tool_choice: None,
+            timeout_ms: None,
+            user: None,
+            seed: None,
         };
 
         let result = translator.translate_request(&request);
@@ -1257,8 +1528,12 @@ mod tests {
             stop: None,
             stream: false,
             conversation_id: None,
+            workflow_id: None,
             tools: None,
             tool_choice: None,
+            timeout_ms: None,
+            user: None,
+            seed: None,
         };
 
         let result = translator.translate_request(&request);
@@ -1320,8 +1595,12 @@ mod tests {
             stop: None,
             stream: false,
             conversation_id: None,
+            workflow_id: None,
             tools: None,
             tool_choice: None,
+            timeout_ms: None,
+            user: None,
+            seed: None,
         };
 
         let result = translator.translate_request(&request).unwrap();
@@ -1383,8 +1662,12 @@ mod tests {
             stop: None,
             stream: false,
             conversation_id: None,
+            workflow_id: None,
             tools: None,
             tool_choice: None,
+            timeout_ms: None,
+            user: None,
+            seed: None,
         };
 
         let result = translator.translate_request(&request).unwrap();
@@ -1394,4 +1677,34 @@ mod tests {
         assert_eq!(tool_msg["name"], "search");
         assert_eq!(tool_msg["content"], "Result: found it!");
     }
+
+    // =============================================================================
+    // Golden Output
+    // =============================================================================
+
+    /// Translations of a 40-message conversation covering every message kind
+    fn long_conversation_translations() -> serde_json::Value {
+        let request = crate::testing::long_conversation(40);
+        json!({
+            "default": OpenAITranslator::new().translate_request(&request).unwrap(),
+            "reasoning": OpenAITranslator::for_reasoning_model().translate_request(&request).unwrap(),
+        })
+    }
+
+    #[test]
+    fn test_long_conversation_matches_golden() {
+        let translated = serde_json::to_string_pretty(&long_conversation_translations()).unwrap();
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::write(
+                concat!(env!("CARGO_MANIFEST_DIR"), "/src/native/translate/testdata/openai_long_conversation.json"),
+                format!("{}\n", translated),
+            )
+            .unwrap();
+        }
+        // Compared as text: the forwarded body must stay byte-identical
+        assert_eq!(
+            format!("{}\n", translated),
+            include_str!("testdata/openai_long_conversation.json")
+        );
+    }
 }
@@ -0,0 +1,342 @@
+//! Sampling parameter bounds per provider
+//!
+//! Providers accept different ranges (OpenAI `temperature` 0-2, Anthropic
+//! 0-1) and Anthropic requires `max_tokens`. Translators run
+//! `normalize_params` with their provider's [`ParamBounds`] before building
+//! the upstream request, so a bad value gets the same error whichever
+//! provider serves it instead of an upstream-specific 400. Values outside the
+//! bounds are clamped or rejected depending on `PARAM_OUT_OF_RANGE`;
+//! non-finite numbers are always rejected.
+
+use std::str::FromStr;
+
+use tracing::warn;
+
+use super::TranslationError;
+use crate::native::request::ChatCompletionRequest;
+
+/// What to do with a parameter outside the provider's bounds (`PARAM_OUT_OF_RANGE`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParamOutOfRange {
+    /// Reject the request with a 400 naming the parameter
+    #[default]
+    Reject,
+    /// Move the value to the nearest bound and log a warning
+    Clamp,
+}
+
+impl FromStr for ParamOutOfRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "reject" => Ok(ParamOutOfRange::Reject),
+            "clamp" => Ok(ParamOutOfRange::Clamp),
+            other => Err(format!(
+                "unknown out-of-range policy '{}' (expected clamp or reject)",
+                other
+            )),
+        }
+    }
+}
+
+/// Accepted parameter ranges of one provider (inclusive)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParamBounds {
+    pub temperature: (f64, f64),
+    pub top_p: (f64, f64),
+    pub min_max_tokens: u32,
+    /// `max_tokens` sent when the client didn't set one (None = optional upstream)
+    pub default_max_tokens: Option<u32>,
+}
+
+/// OpenAI bounds, also used for providers without a known table
+pub const OPENAI_BOUNDS: ParamBounds = ParamBounds {
+    temperature: (0.0, 2.0),
+    top_p: (0.0, 1.0),
+    min_max_tokens: 1,
+    default_max_tokens: None,
+};
+
+/// Anthropic bounds: narrower temperature, `max_tokens` required
+pub const ANTHROPIC_BOUNDS: ParamBounds = ParamBounds {
+    temperature: (0.0, 1.0),
+    top_p: (0.0, 1.0),
+    min_max_tokens: 1,
+    default_max_tokens: Some(4096),
+};
+
+/// Parameter bounds of a provider
+pub fn param_bounds(provider: &str) -> ParamBounds {
+    match provider {
+        "anthropic" => ANTHROPIC_BOUNDS,
+        _ => OPENAI_BOUNDS,
+    }
+}
+
+/// Sampling parameters to send upstream
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SamplingParams {
+    pub temperature: Option<f64>,
+    pub top_p: Option<f64>,
+    pub max_tokens: Option<u32>,
+}
+
+/// Check a request's sampling parameters against `bounds`
+///
+/// Required defaults (Anthropic `max_tokens`) are filled in with a warning.
+pub fn normalize_params(
+    request: &ChatCompletionRequest,
+    bounds: &ParamBounds,
+    mode: ParamOutOfRange,
+) -> Result<SamplingParams, TranslationError> {
+    let max_tokens = match (request.max_tokens, bounds.default_max_tokens) {
+        (Some(max_tokens), _) if max_tokens < bounds.min_max_tokens => Some(out_of_range(
+            "max_tokens",
+            max_tokens,
+            bounds.min_max_tokens,
+            format!("at least {}", bounds.min_max_tokens),
+            mode,
+        )?),
+        (Some(max_tokens), _) => Some(max_tokens),
+        (None, Some(default)) => {
+            warn!(
+                max_tokens = default,
+                "Request has no max_tokens, which the provider requires; using the default"
+            );
+            Some(default)
+        }
+        (None, None) => None,
+    };
+
+    Ok(SamplingParams {
+        temperature: request
+            .temperature
+            .map(|value| check_range("temperature", value, bounds.temperature, mode))
+            .transpose()?,
+        top_p: request
+            .top_p
+            .map(|value| check_range("top_p", value, bounds.top_p, mode))
+            .transpose()?,
+        max_tokens,
+    })
+}
+
+/// Check a float parameter against an inclusive range
+fn check_range(
+    name: &str,
+    value: f64,
+    (min, max): (f64, f64),
+    mode: ParamOutOfRange,
+) -> Result<f64, TranslationError> {
+    if !value.is_finite() {
+        return Err(TranslationError::InvalidParameter(format!(
+            "{} must be a finite number",
+            name
+        )));
+    }
+    if value < min || value > max {
+        let clamped = value.clamp(min, max);
+        return out_of_range(
+            name,
+            value,
+            clamped,
+            format!("between {} and {}", min, max),
+            mode,
+        );
+    }
+    Ok(value)
+}
+
+/// Reject an out-of-range value, or return its clamped replacement
+fn out_of_range<T: std::fmt::Display + Copy>(
+    name: &str,
+    value: T,
+    clamped: T,
+    expected: String,
+    mode: ParamOutOfRange,
+) -> Result<T, TranslationError> {
+    match mode {
+        ParamOutOfRange::Reject => Err(TranslationError::InvalidParameter(format!(
+            "{} must be {} for this provider, got {}",
+            name, expected, value
+        ))),
+        ParamOutOfRange::Clamp => {
+            warn!(param = name, value = %value, clamped = %clamped, "Clamped out-of-range parameter");
+            Ok(clamped)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::native::types::{Content, Message, Role};
+
+    fn request(
+        temperature: Option<f64>,
+        top_p: Option<f64>,
+        max_tokens: Option<u32>,
+    ) -> ChatCompletionRequest {
+        ChatCompletionRequest {
+            tier: None,
+            messages: vec![Message {
+                role: Role::User,
+                content: Content::Text("Hi".to_string()),
+                name: None,
+                tool_call_id: None,
+                tool_calls: None,
+            }],
+            temperature,
+            max_tokens,
+            top_p,
+            stop: None,
+            stream: false,
+            conversation_id: None,
+            workflow_id: None,
+            tools: None,
+            tool_choice: None,
+            timeout_ms: None,
+            user: None,
+            seed: None,
+        }
+    }
+
+    #[test]
+    fn test_mode_from_str() {
+        assert_eq!(
+            "clamp".parse::<ParamOutOfRange>(),
+            Ok(ParamOutOfRange::Clamp)
+        );
+        assert_eq!(
+            " REJECT ".parse::<ParamOutOfRange>(),
+            Ok(ParamOutOfRange::Reject)
+        );
+        assert!("ignore".parse::<ParamOutOfRange>().is_err());
+    }
+
+    #[test]
+    fn test_bounds_table() {
+        assert_eq!(param_bounds("openai"), OPENAI_BOUNDS);
+        assert_eq!(param_bounds("anthropic"), ANTHROPIC_BOUNDS);
+        assert_eq!(param_bounds("mock"), OPENAI_BOUNDS);
+    }
+
+    #[test]
+    fn test_values_within_bounds_pass_through() {
+        let params = normalize_params(
+            &request(Some(2.0), Some(0.0), Some(1)),
+            &OPENAI_BOUNDS,
+            ParamOutOfRange::Reject,
+        )
+        .unwrap();
+        assert_eq!(
+            params,
+            SamplingParams {
+                temperature: Some(2.0),
+                top_p: Some(0.0),
+                max_tokens: Some(1)
+            }
+        );
+        assert_eq!(
+            normalize_params(
+                &request(None, None, None),
+                &OPENAI_BOUNDS,
+                ParamOutOfRange::Reject
+            )
+            .unwrap(),
+            SamplingParams::default()
+        );
+    }
+
+    #[test]
+    fn test_reject_names_the_parameter() {
+        let reject = |request: ChatCompletionRequest, bounds: &ParamBounds| {
+            normalize_params(&request, bounds, ParamOutOfRange::Reject)
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(reject(request(Some(2.5), None, None), &OPENAI_BOUNDS)
+            .contains("temperature must be between 0 and 2"));
+        assert!(reject(request(Some(-0.1), None, None), &OPENAI_BOUNDS).contains("temperature"));
+        assert!(
+            reject(request(Some(1.5), None, None), &ANTHROPIC_BOUNDS).contains("between 0 and 1")
+        );
+        assert!(reject(request(None, Some(1.01), None), &OPENAI_BOUNDS).contains("top_p"));
+        assert!(reject(request(None, None, Some(0)), &ANTHROPIC_BOUNDS)
+            .contains("max_tokens must be at least 1"));
+    }
+
+    #[test]
+    fn test_clamp_moves_to_nearest_bound() {
+        let clamp = |request: ChatCompletionRequest, bounds: &ParamBounds| {
+            normalize_params(&request, bounds, ParamOutOfRange::Clamp).unwrap()
+        };
+        assert_eq!(
+            clamp(request(Some(2.5), None, None), &OPENAI_BOUNDS).temperature,
+            Some(2.0)
+        );
+        assert_eq!(
+            clamp(request(Some(1.5), None, None), &ANTHROPIC_BOUNDS).temperature,
+            Some(1.0)
+        );
+        assert_eq!(
+            clamp(request(Some(-1.0), None, None), &ANTHROPIC_BOUNDS).temperature,
+            Some(0.0)
+        );
+        assert_eq!(
+            clamp(request(None, Some(3.0), None), &OPENAI_BOUNDS).top_p,
+            Some(1.0)
+        );
+        assert_eq!(
+            clamp(request(None, None, Some(0)), &OPENAI_BOUNDS).max_tokens,
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_non_finite_always_rejected() {
+        for mode in [ParamOutOfRange::Reject, ParamOutOfRange::Clamp] {
+            for value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+                let error =
+                    normalize_params(&request(Some(value), None, None), &OPENAI_BOUNDS, mode)
+                        .unwrap_err();
+                assert!(error
+                    .to_string()
+                    .contains("temperature must be a finite number"));
+                assert!(normalize_params(
+                    &request(None, Some(value), None),
+                    &ANTHROPIC_BOUNDS,
+                    mode
+                )
+                .is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn test_required_max_tokens_default() {
+        let anthropic = normalize_params(
+            &request(None, None, None),
+            &ANTHROPIC_BOUNDS,
+            ParamOutOfRange::Reject,
+        )
+        .unwrap();
+        assert_eq!(anthropic.max_tokens, Some(4096));
+        let openai = normalize_params(
+            &request(None, None, None),
+            &OPENAI_BOUNDS,
+            ParamOutOfRange::Reject,
+        )
+        .unwrap();
+        assert_eq!(openai.max_tokens, None);
+        // A client value is kept
+        let explicit = normalize_params(
+            &request(None, None, Some(50)),
+            &ANTHROPIC_BOUNDS,
+            ParamOutOfRange::Reject,
+        )
+        .unwrap();
+        assert_eq!(explicit.max_tokens, Some(50));
+    }
+}
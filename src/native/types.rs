@@ -13,7 +13,8 @@ use utoipa::ToSchema;
 #[serde(rename_all = "lowercase")]
 #[schema(example = "user")]
 pub enum Role {
-    /// System message providing instructions or context
+    /// System message providing instructions or context (`developer` is accepted as an alias)
+    #[serde(alias = "developer")]
     System,
     /// User message from the human
     User,
@@ -23,6 +24,22 @@ pub enum Role {
     Tool,
 }
 
+/// Image detail level for vision input
+///
+/// `low` is billed at a flat rate; `high` is billed per 512px tile of the
+/// resized image. `auto` lets the provider choose and is estimated as `high`.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageDetail {
+    /// Provider picks the detail level
+    #[default]
+    Auto,
+    /// Fixed low-resolution rendering
+    Low,
+    /// Tiled high-resolution rendering
+    High,
+}
+
 /// Image URL reference for multimodal content
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
 pub struct ImageUrl {
@@ -32,7 +49,7 @@ pub struct ImageUrl {
     /// Image detail level: "auto", "low", or "high"
     #[serde(skip_serializing_if = "Option::is_none")]
     #[schema(example = "auto")]
-    pub detail: Option<String>,
+    pub detail: Option<ImageDetail>,
 }
 
 /// A part of multimodal content
@@ -81,6 +98,20 @@ impl Content {
                 .join(""),
         }
     }
+
+    /// Mutable text of either variant, for in-place rewrites
+    pub fn texts_mut(&mut self) -> Vec<&mut String> {
+        match self {
+            Content::Text(text) => vec![text],
+            Content::Parts(parts) => parts
+                .iter_mut()
+                .filter_map(|part| match part {
+                    ContentPart::Text { text } => Some(text),
+                    ContentPart::ImageUrl { .. } => None,
+                })
+                .collect(),
+        }
+    }
 }
 
 /// A chat message with role and content
@@ -407,7 +438,7 @@ mod tests {
             ContentPart::ImageUrl {
                 image_url: ImageUrl {
                     url: "https://example.com/image.png".to_string(),
-                    detail: Some("high".to_string()),
+                    detail: Some(ImageDetail::High),
                 },
             },
         ]);
@@ -456,6 +487,23 @@ mod tests {
         assert_eq!(content.as_text(), "Hello world!");
     }
 
+    #[test]
+    fn test_image_detail_restricted_to_known_levels() {
+        let part: ContentPart = serde_json::from_str(
+            r#"{"type":"image_url","image_url":{"url":"https://example.com/a.png","detail":"low"}}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            part,
+            ContentPart::ImageUrl { image_url: ImageUrl { detail: Some(ImageDetail::Low), .. } }
+        ));
+
+        let err = serde_json::from_str::<ContentPart>(
+            r#"{"type":"image_url","image_url":{"url":"https://example.com/a.png","detail":"ultra"}}"#,
+        );
+        assert!(err.is_err());
+    }
+
     // =============================================================================
     // Tier Tests
     // =============================================================================
@@ -0,0 +1,212 @@
+//! Native API chat completion batches
+//!
+//! `POST /native/v1/chat/completions/batch` runs many independent,
+//! non-streaming chat completions in one HTTP call, so auth and the rest of
+//! the middleware run once per batch instead of once per prompt. Each item
+//! goes through the same pipeline as a single request (tier routing, retries,
+//! usage tracking per item) with at most `NATIVE_BATCH_CONCURRENCY` running at
+//! once. Results come back in request order, each either a completion or the
+//! error the item would have gotten on its own, so one bad item doesn't fail
+//! the batch.
+//!
+//! For rate limiting a batch counts as one request per item:
+//! [`batch_weight_middleware`] reads the item count before the rate limiter
+//! runs.
+
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, HeaderMap, Method},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use futures::StreamExt;
+use serde::de::IgnoredAny;
+use serde::Deserialize;
+use tracing::info;
+
+use crate::{
+    middleware::{auth::AuthenticatedUser, ProviderOverride, RateLimitWeight},
+    native::{
+        error::NativeErrorResponse,
+        request::{ChatCompletionBatchRequest, ChatCompletionRequest},
+        response::{BatchItemResult, ChatCompletionBatchResponse, ChatCompletionResponse},
+    },
+    proxy::progress::PROGRESS_HEADER,
+    routes::body::SentinelJson,
+    AppState,
+};
+
+use super::chat::handle_chat_completion;
+
+/// Path of the batch route inside the native router
+pub const BATCH_PATH: &str = "/v1/chat/completions/batch";
+
+/// Just enough of a batch body to count its items
+#[derive(Deserialize)]
+struct BatchItems {
+    requests: Vec<IgnoredAny>,
+}
+
+/// Weighs batch requests for rate limiting by their number of items
+///
+/// Runs on every native route right before rate limiting, but only reads the
+/// body of batch requests. A body that can't be counted keeps the default
+/// weight and is rejected by the handler.
+pub async fn batch_weight_middleware(request: Request, next: Next) -> Response {
+    if request.method() != Method::POST || request.uri().path() != BATCH_PATH {
+        return next.run(request).await;
+    }
+
+    let (mut parts, body) = request.into_parts();
+    let body = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(e) => {
+            return NativeErrorResponse::validation(format!("Failed to read request body: {}", e))
+                .into_response();
+        }
+    };
+    if let Ok(batch) = serde_json::from_slice::<BatchItems>(&body) {
+        let weight = u32::try_from(batch.requests.len()).unwrap_or(u32::MAX);
+        parts.extensions.insert(RateLimitWeight(weight));
+    }
+    next.run(Request::from_parts(parts, Body::from(body))).await
+}
+
+/// Handle a batch of native chat completion requests
+#[utoipa::path(
+    post,
+    path = "/native/v1/chat/completions/batch",
+    tag = "Chat",
+    operation_id = "createNativeChatCompletionBatch",
+    description = "Run several independent non-streaming chat completions in one call.
+
+Each item of `requests` is a chat completion request as accepted by `/native/v1/chat/completions`. Items run concurrently and are tracked for usage individually; `results` holds one entry per item, in request order, with the HTTP `status` the item would have gotten on its own and either its `response` or its `error`.
+
+Items with `stream: true` fail with a 400. The batch itself fails with a 400 when `requests` is empty or longer than the server's limit. For rate limiting, the batch counts as one request per item.",
+    request_body(
+        content = ChatCompletionBatchRequest,
+        description = "Chat completion requests to run",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Per-item results, in request order", body = ChatCompletionBatchResponse),
+        (status = 400, description = "Invalid batch - malformed body, no items or too many", body = NativeErrorResponse),
+        (status = 401, description = "Missing or invalid JWT in Authorization header"),
+        (status = 403, description = "Insufficient permissions"),
+        (status = 429, description = "Rate limit exceeded (counting every item)")
+    ),
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn native_chat_completions_batch(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Extension(user): Extension<AuthenticatedUser>,
+    provider_override: Option<Extension<ProviderOverride>>,
+    SentinelJson(batch, _): SentinelJson<ChatCompletionBatchRequest>,
+) -> Result<Json<ChatCompletionBatchResponse>, NativeErrorResponse> {
+    let max_items = state.config.provider.native_batch_max_items;
+    if batch.requests.is_empty() {
+        return Err(NativeErrorResponse::validation(
+            "requests must contain at least one chat completion request",
+        ));
+    }
+    if batch.requests.len() > max_items {
+        return Err(NativeErrorResponse::validation(format!(
+            "requests accepts at most {} items, got {}",
+            max_items,
+            batch.requests.len()
+        )));
+    }
+
+    // Every item is answered as JSON inside the batch response
+    let mut item_headers = headers;
+    item_headers.remove(header::ACCEPT);
+    item_headers.remove(PROGRESS_HEADER);
+
+    let items = batch.requests.len();
+    let concurrency = state.config.provider.native_batch_concurrency.max(1);
+    let results: Vec<BatchItemResult> = futures::stream::iter(batch.requests)
+        .map(|item| {
+            run_item(
+                state.clone(),
+                item_headers.clone(),
+                user.clone(),
+                provider_override.clone(),
+                item,
+            )
+        })
+        .buffered(concurrency)
+        .collect()
+        .await;
+
+    let failed = results
+        .iter()
+        .filter(|result| result.error.is_some())
+        .count();
+    info!(
+        items = items,
+        failed = failed,
+        concurrency = concurrency,
+        external_id = %user.log_id(),
+        "Native chat completion batch completed"
+    );
+
+    Ok(Json(ChatCompletionBatchResponse { results }))
+}
+
+/// Run one batch item through the single-request pipeline
+async fn run_item(
+    state: Arc<AppState>,
+    headers: HeaderMap,
+    user: AuthenticatedUser,
+    provider_override: Option<Extension<ProviderOverride>>,
+    item: serde_json::Value,
+) -> BatchItemResult {
+    match complete_item(state, headers, user, provider_override, item).await {
+        Ok(response) => BatchItemResult {
+            status: 200,
+            response: Some(response),
+            error: None,
+        },
+        Err(e) => BatchItemResult {
+            status: e.status_code().as_u16(),
+            response: None,
+            error: Some(e.error),
+        },
+    }
+}
+
+async fn complete_item(
+    state: Arc<AppState>,
+    headers: HeaderMap,
+    user: AuthenticatedUser,
+    provider_override: Option<Extension<ProviderOverride>>,
+    item: serde_json::Value,
+) -> Result<ChatCompletionResponse, NativeErrorResponse> {
+    let request = serde_json::from_value::<ChatCompletionRequest>(item)
+        .map_err(|e| NativeErrorResponse::validation(format!("Invalid request body: {}", e)))?;
+    if request.stream {
+        return Err(NativeErrorResponse::validation(
+            "Streaming is not supported in batches; set \"stream\": false or omit it",
+        ));
+    }
+    let response = handle_chat_completion(state, headers, user, provider_override, request).await?;
+    read_completion(response).await
+}
+
+/// Completion carried by a successful single-request response
+async fn read_completion(
+    response: Response,
+) -> Result<ChatCompletionResponse, NativeErrorResponse> {
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .map_err(|e| NativeErrorResponse::internal(format!("Failed to read completion: {}", e)))?;
+    serde_json::from_slice(&body)
+        .map_err(|e| NativeErrorResponse::internal(format!("Failed to read completion: {}", e)))
+}
@@ -5,41 +5,52 @@
 //! Uses tier routing for model selection based on complexity.
 
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use axum::{
     body::Body,
     extract::State,
-    http::{header, HeaderMap, HeaderValue, StatusCode},
+    http::{HeaderMap, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
-    Json,
+    Extension,
 };
 use futures::StreamExt;
 use serde_json::json;
 use tracing::{debug, info, warn};
 
 use crate::{
-    middleware::auth::AuthenticatedUser,
+    error::AppError,
+    middleware::{auth::AuthenticatedUser, ProviderOverride},
     native::{
+        affinity,
+        encoding::ResponseFormat,
         error::NativeErrorResponse,
-        request::ChatCompletionRequest,
-        response::ChatCompletionResponse,
+        request::{max_stop_sequences, ChatCompletionRequest},
+        response::{self, ChatCompletionResponse},
+        session::{SessionManager, StreamLock},
+        streaming::{format_error_event, format_sse_lines, ToolCallStreamTranslator},
         translate::{MessageTranslator, OpenAITranslator},
-        types::Tier,
+        types::{Content, ContentPart, Message, Role, Tier, ToolCall},
     },
-    streaming::SseLineBuffer,
+    injection,
+    proxy::{
+        complexity::RequestComplexity,
+        content_filter::{CONTENT_BLOCKED_CODE, CONTENT_BLOCKED_MESSAGE},
+        progress, reasoning,
+        response_filter::ResponseFilter,
+        sanitize::{self, SanitizeReport},
+        stream_accept::{self, Negotiation},
+        timeout, upstream_user,
+    },
+    routes::{
+        body::SentinelJson,
+        metrics::{record_content_blocked, record_estimated_usage, record_session_affinity},
+    },
+    streaming::{sse_response, SseLineBuffer},
+    usage::{exact, validate_workflow_id, workflow_id_from_headers},
     AppState,
 };
 
-// Note: These types are imported for documentation/future use.
-// - ToolCallAccumulator: For future streaming tool call ID translation
-//   (v1 streaming passes through provider IDs as documented limitation)
-// - ToolCallIdMapping: Used internally by translate_response, will be used
-//   for streaming ID translation in future versions
-#[allow(unused_imports)]
-use crate::native::streaming::ToolCallAccumulator;
-#[allow(unused_imports)]
-use crate::native::translate::ToolCallIdMapping;
-
 /// Usage statistics from stream chunks
 #[derive(Debug, Clone, serde::Deserialize, Default)]
 struct StreamUsage {
@@ -65,6 +76,8 @@ struct StreamChunk {
 struct StreamChoice {
     #[serde(default)]
     delta: StreamDelta,
+    #[serde(default)]
+    finish_reason: Option<String>,
 }
 
 /// Streaming delta content
@@ -79,6 +92,109 @@ struct ModelSelection {
     provider: String,
     model: String,
     tier: Tier,
+    /// Long-context model to retry on after a context-length error (CONTEXT_FALLBACK)
+    long_context_model: Option<String>,
+    /// Whether the conversation's session fixes the model for later requests too
+    pinned: bool,
+}
+
+/// Header naming why a response was served by a fallback model
+const FALLBACK_REASON_HEADER: &str = "X-Sentinel-Fallback-Reason";
+
+/// A request in a conversation, recorded on its session once it completes
+struct SessionTurn {
+    conversation_id: String,
+    /// The session's owner; turns are only recorded on their own sessions
+    external_id: String,
+    /// The client's messages, kept only when session history is stored
+    messages: Option<Vec<Message>>,
+}
+
+impl SessionTurn {
+    fn new(
+        sessions: &SessionManager,
+        request: &ChatCompletionRequest,
+        user: &AuthenticatedUser,
+    ) -> Option<Self> {
+        let conversation_id = request.conversation_id.clone()?;
+        Some(Self {
+            conversation_id,
+            external_id: user.external_id.clone(),
+            messages: sessions.history_enabled().then(|| request.messages.clone()),
+        })
+    }
+
+    /// Add the usage (and, with history, the reply) to the session; failures are only logged
+    async fn record(
+        self,
+        sessions: &SessionManager,
+        input_tokens: u64,
+        output_tokens: u64,
+        reply: impl FnOnce() -> Message,
+    ) {
+        let messages = self.messages.map(|mut messages| {
+            messages.push(reply());
+            messages
+        });
+        if let Err(e) = sessions
+            .record_turn(
+                &self.conversation_id,
+                &self.external_id,
+                input_tokens,
+                output_tokens,
+                messages,
+            )
+            .await
+        {
+            warn!(conversation_id = %self.conversation_id, error = %e, "Failed to record session turn");
+        }
+    }
+}
+
+/// Refusal of a conversation ID whose session belongs to another user
+///
+/// Answered like a missing session, the same as the owner-only session routes.
+fn foreign_session(conversation_id: &str, user: &AuthenticatedUser) -> NativeErrorResponse {
+    warn!(
+        conversation_id = %conversation_id,
+        external_id = %user.log_id(),
+        "Conversation belongs to another user, request refused"
+    );
+    NativeErrorResponse::session_not_found(conversation_id)
+}
+
+/// Assistant message for session history
+fn assistant_message(content: Option<String>, tool_calls: Option<Vec<ToolCall>>) -> Message {
+    Message {
+        role: Role::Assistant,
+        content: Content::Text(content.unwrap_or_default()),
+        name: None,
+        tool_call_id: None,
+        tool_calls,
+    }
+}
+
+/// Function names and argument text of streamed tool calls, for output estimation
+fn tool_calls_text(tool_calls: &[ToolCall]) -> String {
+    tool_calls
+        .iter()
+        .map(|call| format!("{}{}", call.function.name, call.function.arguments))
+        .collect()
+}
+
+/// Fallback reason for requests that exceeded the selected model's context
+const CONTEXT_LENGTH_REASON: &str = "context_length";
+
+/// Whether an upstream error reports that the prompt exceeded the model's context window
+fn is_context_length_error(error: &AppError) -> bool {
+    matches!(error, AppError::UpstreamError(message) if message.contains("context_length_exceeded"))
+}
+
+/// Copy of a provider request targeting a different model
+fn request_for_model(provider_request: &serde_json::Value, model: &str) -> serde_json::Value {
+    let mut request = provider_request.clone();
+    request["model"] = json!(model);
+    request
 }
 
 /// Handle native chat completion requests
@@ -116,9 +232,9 @@ struct ModelSelection {
 
 ## Request Modes
 
-**Non-streaming (default):** Returns complete response as JSON when `stream: false` or omitted.
+**Non-streaming (default):** Returns complete response as JSON when `stream: false` or omitted. Clients preferring `application/msgpack` in `Accept` get the same response (and errors) as MessagePack with named fields instead; other `Accept` values get JSON.
 
-**Streaming:** When `stream: true`, returns Server-Sent Events (SSE) with incremental chunks. Each chunk is prefixed with `data: ` and the stream ends with `data: [DONE]`.
+**Streaming:** When `stream: true`, returns Server-Sent Events (SSE) with incremental chunks. Each chunk is prefixed with `data: ` and the stream ends with `data: [DONE]`. An `Accept` header that rules out `text/event-stream` gets a 406 instead (unless `STREAM_ACCEPT_MISMATCH=warn`); non-streaming requests accepting only `text/event-stream` get an `X-Sentinel-Stream-Hint` header.
 
 ## Tier Selection
 
@@ -142,9 +258,11 @@ Supports OpenAI-compatible tool calling:
 
 ## Error Handling
 
-- **400**: Invalid request body, missing required fields, or validation errors
+- **400**: Invalid request body, missing required fields, or validation errors. Body parse errors set `code` to `invalid_json` (syntax) or `invalid_type` (schema mismatch) and `param` to the JSON path of the offending field
 - **401**: Missing or invalid JWT in Authorization header
 - **403**: User lacks permission or has exceeded quota
+- **406**: `stream: true` with an `Accept` header that excludes `text/event-stream`
+- **415**: Missing or non-JSON `Content-Type`
 - **429**: Rate limit exceeded (check X-RateLimit-* headers)
 - **500**: Internal server error
 - **502**: Upstream AI provider error (provider field indicates source)
@@ -155,11 +273,15 @@ Supports OpenAI-compatible tool calling:
         content_type = "application/json"
     ),
     responses(
-        (status = 200, description = "Successful completion", body = ChatCompletionResponse,
-            content_type = "application/json"),
+        (status = 200, description = "Successful completion", content(
+            (ChatCompletionResponse = "application/json"),
+            (ChatCompletionResponse = "application/msgpack")
+        )),
         (status = 400, description = "Invalid request - malformed JSON or validation error", body = NativeErrorResponse),
         (status = 401, description = "Missing or invalid JWT in Authorization header"),
         (status = 403, description = "Insufficient permissions or quota exceeded"),
+        (status = 406, description = "Streaming requested, but Accept excludes text/event-stream", body = NativeErrorResponse),
+        (status = 415, description = "Content-Type is missing or not application/json"),
         (status = 429, description = "Rate limit exceeded", body = NativeErrorResponse),
         (status = 500, description = "Internal server error", body = NativeErrorResponse),
         (status = 502, description = "Provider error - upstream AI provider failed", body = NativeErrorResponse),
@@ -172,35 +294,167 @@ Supports OpenAI-compatible tool calling:
 pub async fn native_chat_completions(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
-    request: axum::extract::Request,
+    Extension(user): Extension<AuthenticatedUser>,
+    provider_override: Option<Extension<ProviderOverride>>,
+    SentinelJson(native_request, _): SentinelJson<ChatCompletionRequest>,
 ) -> Result<Response, NativeErrorResponse> {
-    // Extract authenticated user from request extensions (set by auth middleware)
-    let user = request
-        .extensions()
-        .get::<AuthenticatedUser>()
-        .cloned()
-        .ok_or_else(|| {
-            NativeErrorResponse::internal("AuthenticatedUser not found in request extensions")
-        })?;
+    // Errors are encoded like the response body would have been
+    let format = response_format(&headers, native_request.stream);
+    let result =
+        handle_chat_completion(state, headers, user, provider_override, native_request).await;
+    match format {
+        ResponseFormat::Json => result,
+        format => Ok(result.unwrap_or_else(|e| e.into_response_as(format))),
+    }
+}
 
-    // Read request body
-    let body = axum::body::to_bytes(request.into_body(), usize::MAX)
-        .await
-        .map_err(|e| NativeErrorResponse::internal(format!("Failed to read request body: {}", e)))?;
+/// Body encoding for a native chat response
+///
+/// Streams, and progress SSE that carries the body in an event, stay JSON.
+fn response_format(headers: &HeaderMap, stream: bool) -> ResponseFormat {
+    if stream || progress::requested(headers) {
+        ResponseFormat::Json
+    } else {
+        ResponseFormat::negotiate(headers)
+    }
+}
 
-    // Parse as ChatCompletionRequest
-    let native_request: ChatCompletionRequest = serde_json::from_slice(&body).map_err(|e| {
-        NativeErrorResponse::validation(format!("Invalid request body: {}", e))
-    })?;
+/// Chat completion behind `native_chat_completions`, errors not yet encoded
+pub(crate) async fn handle_chat_completion(
+    state: Arc<AppState>,
+    headers: HeaderMap,
+    mut user: AuthenticatedUser,
+    provider_override: Option<Extension<ProviderOverride>>,
+    mut native_request: ChatCompletionRequest,
+) -> Result<Response, NativeErrorResponse> {
+    // Normalized before token estimation so both see the same text
+    if state.config.provider.content_normalize_nfc {
+        let normalized = sanitize::normalize_texts(
+            native_request
+                .messages
+                .iter_mut()
+                .flat_map(|message| message.content.texts_mut()),
+        );
+        SanitizeReport { normalized, ..Default::default() }.record("/native/v1/chat/completions");
+    }
+
+    // Counted on the client's messages, before any injected system prompt
+    let complexity = request_complexity(&native_request);
+
+    // A stream the Accept header rules out is refused or served per STREAM_ACCEPT_MISMATCH
+    let negotiation = Negotiation::check(&headers, native_request.stream);
+    let accept_mode = state.config.provider.stream_accept_mismatch;
+    negotiation.record(accept_mode, "/native/v1/chat/completions", &headers);
+    if negotiation.rejected(accept_mode) {
+        return Err(NativeErrorResponse::not_acceptable(
+            stream_accept::NOT_ACCEPTABLE_MESSAGE,
+        ));
+    }
+
+    // Streams can't guarantee provider-reported usage
+    if native_request.stream && exact::refuses_stream(&state.config.usage, &user) {
+        return Err(NativeErrorResponse::validation(exact::STREAMING_REFUSED));
+    }
 
     // Determine tier from request (default to Simple)
     let requested_tier = native_request.tier.unwrap_or_default();
 
+    // A `workflow_id` in the body takes precedence over the header
+    let workflow_id = match native_request.workflow_id.clone() {
+        Some(workflow_id) => {
+            validate_workflow_id(&workflow_id).map_err(NativeErrorResponse::validation)?;
+            Some(workflow_id)
+        }
+        None => workflow_id_from_headers(&headers).map_err(NativeErrorResponse::validation)?,
+    };
+
+    // A valid affinity hint lets the session come from this replica's local copy
+    let affinity = state
+        .config
+        .provider
+        .affinity_secret
+        .as_deref()
+        .zip(native_request.conversation_id.as_deref());
+    let affinity_hint =
+        affinity.map(|(secret, conversation_id)| affinity::hint(secret, conversation_id));
+    let use_local_session = match (affinity, headers.get(affinity::AFFINITY_HEADER)) {
+        (Some((secret, conversation_id)), Some(presented)) => {
+            let valid = presented
+                .to_str()
+                .is_ok_and(|presented| affinity::verify(secret, conversation_id, presented));
+            if !valid {
+                debug!(conversation_id = %conversation_id, "Ignoring invalid affinity hint");
+                record_session_affinity("invalid");
+            }
+            valid
+        }
+        _ => false,
+    };
+
     // Resolve model selection based on session and tier
-    let selection = resolve_model_selection(&state, &native_request, requested_tier, &user)
-        .await?;
+    let mut selection =
+        resolve_model_selection(&state, &native_request, requested_tier, &user, use_local_session)
+            .await?;
+
+    // One stream at a time per conversation, so retries don't interleave or double-bill;
+    // taken once the session is known to be the caller's, so others can't hold it
+    let stream_lock = match native_request.conversation_id.as_deref() {
+        Some(conversation_id) if native_request.stream => {
+            Some(lock_conversation_stream(&state, conversation_id).await?)
+        }
+        _ => None,
+    };
+
+    // A canary override swaps the provider but keeps the tier's model
+    if let Some(Extension(ProviderOverride(provider))) = provider_override {
+        selection.provider = provider;
+        selection.pinned = false;
+    }
+    complexity.record("/native/v1/chat/completions", &selection.tier.to_string());
+
+    // Stop sequences were checked against the default limit on parse; apply the provider's
+    if let Some(ref stop) = native_request.stop {
+        stop.validate(max_stop_sequences(&selection.provider))
+            .map_err(NativeErrorResponse::validation)?;
+    }
 
     let is_streaming = native_request.stream;
+    let timeout = timeout::effective_timeout(&state.config, native_request.timeout_ms);
+
+    // The client's conversation, before any injected system prompt
+    let turn = SessionTurn::new(&state.session_manager, &native_request, &user);
+
+    // Inject the configured system prompt (per-tier override first) ahead of translation,
+    // so it stays first in the conversation and is part of the token estimate
+    let tier_config = state.tier_config_cache.get_config().await.ok();
+    let tier_prompt = tier_config
+        .as_ref()
+        .and_then(|config| config.system_prompt_for_tier(selection.tier));
+    if state.config.provider.context_fallback {
+        selection.long_context_model = tier_config
+            .as_ref()
+            .and_then(|config| config.long_context_model_for_tier(selection.tier))
+            .filter(|model| *model != selection.model)
+            .map(str::to_string);
+    }
+    let system_prompt_injected = injection::resolve_preamble(&state.config, tier_prompt)
+        .map(|preamble| {
+            injection::inject_native_messages(
+                &mut native_request.messages,
+                preamble,
+                state.config.provider.system_prompt_injection_mode,
+            )
+        })
+        .unwrap_or(false);
+
+    // Pre-count input tokens (fallback if the provider doesn't return usage), images included
+    let image_tokens = estimate_image_tokens(&state, &native_request.messages);
+    let estimated_input_tokens =
+        estimate_input_tokens(&state, &selection.model, &native_request.messages) + image_tokens;
+
+    // The provider only sees the `user` value UPSTREAM_USER_FIELD allows
+    native_request.user =
+        upstream_user::apply(&state.config.provider, &mut user, native_request.user.as_deref());
 
     info!(
         model = %selection.model,
@@ -208,28 +462,203 @@ pub async fn native_chat_completions(
         tier = %selection.tier,
         stream = %is_streaming,
         messages = %native_request.messages.len(),
-        external_id = %user.external_id,
+        estimated_input_tokens = estimated_input_tokens,
+        image_tokens = image_tokens,
+        system_prompt_injected = system_prompt_injected,
+        external_id = %user.log_id(),
         conversation_id = ?native_request.conversation_id,
+        workflow_id = ?workflow_id,
         "Processing native chat completion request"
     );
 
-    // Translate request using OpenAI translator
-    let translator = OpenAITranslator::new();
-    let provider_request = translator
+    // Translate request using OpenAI translator; reasoning models get their own dialect
+    let translator = if tier_config
+        .as_ref()
+        .is_some_and(|config| config.is_reasoning_model(&selection.model))
+    {
+        OpenAITranslator::for_reasoning_model()
+    } else {
+        OpenAITranslator::new()
+    }
+    .with_param_mode(state.config.provider.param_out_of_range);
+    reasoning::warn_stripped(&selection.model, &translator.unsupported_params(&native_request));
+    // A seed only reproduces on the same backend, which tier routing doesn't promise
+    if let Some(seed) = native_request.seed.filter(|_| !selection.pinned) {
+        warn!(
+            seed = seed,
+            model = %selection.model,
+            tier = %selection.tier,
+            "Seeded request without a pinned model; the same seed may be served by another model"
+        );
+    }
+    let mut provider_request = translator
         .translate_request(&native_request)
         .map_err(|e| NativeErrorResponse::validation(e.to_string()))?;
-
-    if is_streaming {
-        handle_streaming(state, &headers, provider_request, selection, user).await
+    provider_request["model"] = json!(selection.model);
+
+    let result = if is_streaming {
+        handle_streaming(
+            state,
+            &headers,
+            provider_request,
+            selection,
+            user,
+            workflow_id,
+            complexity,
+            estimated_input_tokens,
+            timeout,
+            stream_lock,
+            turn,
+        )
+        .await
+    } else if progress::requested(&headers) {
+        // Same non-streaming handling, with heartbeats while the upstream call runs
+        let interval = Duration::from_millis(state.config.provider.progress_interval_ms);
+        let request = async move {
+            handle_non_streaming(
+                state,
+                &headers,
+                provider_request,
+                selection,
+                user,
+                workflow_id,
+                complexity,
+                translator,
+                timeout,
+                turn,
+            )
+            .await
+        };
+        Ok(progress::progress_response(request, interval))
     } else {
-        handle_non_streaming(state, &headers, provider_request, selection, user, translator).await
+        handle_non_streaming(
+            state,
+            &headers,
+            provider_request,
+            selection,
+            user,
+            workflow_id,
+            complexity,
+            translator,
+            timeout,
+            turn,
+        )
+        .await
+    };
+
+    let result = with_affinity_header(timeout::with_timeout_header(result, timeout), affinity_hint);
+    negotiation.with_hint(result)
+}
+
+/// Take the conversation's stream lock, or 409 `conversation_busy` if another stream holds it
+async fn lock_conversation_stream(
+    state: &Arc<AppState>,
+    conversation_id: &str,
+) -> Result<StreamLock, NativeErrorResponse> {
+    let config = &state.config.provider;
+    state
+        .session_manager
+        .lock_stream(
+            conversation_id,
+            config.stream_lock_ttl_seconds,
+            Duration::from_millis(config.stream_lock_wait_ms),
+        )
+        .await
+        .map_err(|e| NativeErrorResponse::internal(format!("Stream lock failed: {}", e)))?
+        .ok_or_else(|| {
+            warn!(conversation_id = %conversation_id, "Rejecting concurrent stream in conversation");
+            NativeErrorResponse::conversation_busy(conversation_id)
+        })
+}
+
+/// Add the conversation's `X-Sentinel-Affinity` hint to the response
+fn with_affinity_header(
+    result: Result<Response, NativeErrorResponse>,
+    hint: Option<String>,
+) -> Result<Response, NativeErrorResponse> {
+    let Some(hint) = hint else {
+        return result;
+    };
+
+    let mut response = result.unwrap_or_else(IntoResponse::into_response);
+    if let Ok(value) = HeaderValue::from_str(&hint) {
+        response.headers_mut().insert(affinity::AFFINITY_HEADER, value);
+    }
+    Ok(response)
+}
+
+/// Message count, content size and images of a native chat request
+fn request_complexity(request: &ChatCompletionRequest) -> RequestComplexity {
+    let mut complexity = RequestComplexity::new(request.stream)
+        .with_tools(request.tools.as_ref().is_some_and(|tools| !tools.is_empty()));
+    for message in &request.messages {
+        match &message.content {
+            Content::Text(text) => complexity.add_message([text.as_str()]),
+            Content::Parts(parts) => {
+                complexity.add_message(parts.iter().filter_map(|part| match part {
+                    ContentPart::Text { text } => Some(text.as_str()),
+                    ContentPart::ImageUrl { .. } => None,
+                }));
+                complexity.add_images(
+                    parts
+                        .iter()
+                        .filter(|part| matches!(part, ContentPart::ImageUrl { .. }))
+                        .count(),
+                );
+            }
+        }
     }
+    complexity
+}
+
+/// Estimate prompt tokens for native messages with tiktoken
+fn estimate_input_tokens(state: &AppState, model: &str, messages: &[Message]) -> u64 {
+    let tuples: Vec<(String, String, Option<String>)> = messages
+        .iter()
+        .map(|m| {
+            let role = match m.role {
+                Role::System => "system",
+                Role::User => "user",
+                Role::Assistant => "assistant",
+                Role::Tool => "tool",
+            };
+            (role.to_string(), m.content.as_text(), m.name.clone())
+        })
+        .collect();
+
+    state
+        .token_counter
+        .count_chat_messages(model, &tuples)
+        .unwrap_or(0) as u64
+}
+
+/// Estimate tokens for the image parts of native messages
+///
+/// Images whose size can't be read (remote URLs) cost `IMAGE_DEFAULT_TOKENS`.
+fn estimate_image_tokens(state: &AppState, messages: &[Message]) -> u64 {
+    messages
+        .iter()
+        .filter_map(|m| match &m.content {
+            Content::Parts(parts) => Some(parts),
+            Content::Text(_) => None,
+        })
+        .flatten()
+        .filter_map(|part| match part {
+            ContentPart::ImageUrl { image_url } => Some(state.token_counter.count_image_tokens(
+                &image_url.url,
+                image_url.detail.unwrap_or_default(),
+                state.config.usage.image_default_tokens,
+            )),
+            ContentPart::Text { .. } => None,
+        })
+        .sum()
 }
 
 /// Resolve model selection based on session and tier
 ///
 /// Handles:
-/// - Existing session lookup with tier upgrade logic
+/// - Existing session lookup with tier upgrade logic (from the local copy
+///   when `use_local_session` is set and one is fresh)
 /// - New session creation with tier routing
 /// - Stateless mode (no session)
 async fn resolve_model_selection(
@@ -237,14 +666,30 @@ async fn resolve_model_selection(
     request: &ChatCompletionRequest,
     requested_tier: Tier,
     user: &AuthenticatedUser,
+    use_local_session: bool,
 ) -> Result<ModelSelection, NativeErrorResponse> {
     if let Some(ref conv_id) = request.conversation_id {
-        // Try to get existing session
-        if let Some(session) = state.session_manager.get(conv_id).await.map_err(|e| {
-            NativeErrorResponse::internal(format!("Session lookup failed: {}", e))
-        })? {
+        // Try to get existing session, skipping Redis if this replica has a fresh copy
+        let local = use_local_session
+            .then(|| state.session_manager.local(conv_id))
+            .flatten();
+        if use_local_session {
+            record_session_affinity(if local.is_some() { "hit" } else { "miss" });
+        }
+        let session = match local {
+            Some(session) => Some(session),
+            None => state.session_manager.get(conv_id).await.map_err(|e| {
+                NativeErrorResponse::internal(format!("Session lookup failed: {}", e))
+            })?,
+        };
+        if let Some(session) = session {
+            // Conversation IDs are chosen by clients; another user's session is never used
+            if session.external_id != user.external_id {
+                return Err(foreign_session(conv_id, user));
+            }
+
             // Refresh TTL on activity (fire-and-forget, log errors)
-            if let Err(e) = state.session_manager.touch(conv_id).await {
+            if let Err(e) = state.session_manager.touch(conv_id, &session.external_id).await {
                 warn!(conversation_id = %conv_id, error = %e, "Failed to refresh session TTL");
             }
 
@@ -257,27 +702,37 @@ async fn resolve_model_selection(
                     .await
                     .map_err(NativeErrorResponse::from_app_error)?;
 
-                // Update session with new tier/model
-                state
+                // Update session with new tier/model; a concurrent request may
+                // have upgraded further, so use whatever binding was stored
+                let upgraded = state
                     .session_manager
-                    .upgrade_tier(conv_id, &selected.provider, &selected.model, requested_tier)
+                    .upgrade_tier(
+                        conv_id,
+                        &user.external_id,
+                        &selected.provider,
+                        &selected.model,
+                        requested_tier,
+                    )
                     .await
-                    .map_err(|e| {
-                        NativeErrorResponse::internal(format!("Session upgrade failed: {}", e))
+                    .map_err(|e| match e {
+                        AppError::Forbidden => foreign_session(conv_id, user),
+                        e => NativeErrorResponse::internal(format!("Session upgrade failed: {}", e)),
                     })?;
 
                 info!(
                     conversation_id = %conv_id,
                     old_tier = %session.tier,
-                    new_tier = %requested_tier,
-                    model = %selected.model,
+                    new_tier = %upgraded.tier,
+                    model = %upgraded.model,
                     "Session tier upgraded"
                 );
 
                 return Ok(ModelSelection {
-                    provider: selected.provider,
-                    model: selected.model,
-                    tier: requested_tier,
+                    provider: upgraded.provider,
+                    model: upgraded.model,
+                    tier: upgraded.tier,
+                    long_context_model: None,
+                    pinned: false,
                 });
             }
 
@@ -294,6 +749,8 @@ async fn resolve_model_selection(
                 provider: session.provider,
                 model: session.model,
                 tier: session.tier,
+                long_context_model: None,
+                pinned: true,
             });
         }
 
@@ -304,8 +761,8 @@ async fn resolve_model_selection(
             .await
             .map_err(NativeErrorResponse::from_app_error)?;
 
-        // Store new session
-        state
+        // Store new session (merged into a concurrently created one if needed)
+        let session = state
             .session_manager
             .create(
                 conv_id,
@@ -315,19 +772,25 @@ async fn resolve_model_selection(
                 &user.external_id,
             )
             .await
-            .map_err(|e| NativeErrorResponse::internal(format!("Session creation failed: {}", e)))?;
+            .map_err(|e| match e {
+                // Another user created it concurrently
+                AppError::Forbidden => foreign_session(conv_id, user),
+                e => NativeErrorResponse::internal(format!("Session creation failed: {}", e)),
+            })?;
 
         info!(
             conversation_id = %conv_id,
-            model = %selected.model,
-            tier = %requested_tier,
+            model = %session.model,
+            tier = %session.tier,
             "Created new session with tier routing"
         );
 
         return Ok(ModelSelection {
-            provider: selected.provider,
-            model: selected.model,
-            tier: requested_tier,
+            provider: session.provider,
+            model: session.model,
+            tier: session.tier,
+            long_context_model: None,
+            pinned: true,
         });
     }
 
@@ -348,28 +811,36 @@ async fn resolve_model_selection(
         provider: selected.provider,
         model: selected.model,
         tier: requested_tier,
+        long_context_model: None,
+        pinned: false,
     })
 }
 
 /// Handle non-streaming chat completion
+#[allow(clippy::too_many_arguments)]
 async fn handle_non_streaming(
     state: Arc<AppState>,
     headers: &HeaderMap,
     provider_request: serde_json::Value,
     selection: ModelSelection,
     user: AuthenticatedUser,
+    workflow_id: Option<String>,
+    complexity: RequestComplexity,
     translator: OpenAITranslator,
+    timeout: Option<Duration>,
+    turn: Option<SessionTurn>,
 ) -> Result<Response, NativeErrorResponse> {
-    // Try primary request with retry on failure
-    let (native_response, final_model, _final_provider) = match execute_with_retry(
-        &state,
-        headers,
-        provider_request,
-        &selection,
-        &translator,
-    )
-    .await
-    {
+    // Try primary request with retry on failure; the timeout covers both attempts
+    let attempt = execute_with_retry(&state, headers, provider_request, &selection, &translator);
+    let result = match timeout {
+        Some(limit) => tokio::time::timeout(limit, attempt).await.map_err(|_| {
+            NativeErrorResponse::from_app_error(AppError::UpstreamTimeout {
+                timeout_ms: limit.as_millis() as u64,
+            })
+        })?,
+        None => attempt.await,
+    };
+    let (mut native_response, final_model, _final_provider, fallback_reason) = match result {
         Ok(result) => result,
         Err(e) => return Err(e),
     };
@@ -378,8 +849,10 @@ async fn handle_non_streaming(
     let input_tokens = native_response.usage.prompt_tokens as u64;
     let output_tokens = native_response.usage.completion_tokens as u64;
 
-    state.batching_tracker.track(
-        user.email.clone(),
+    state.batching_tracker.track_user_in_tier(
+        &user,
+        selection.tier,
+        workflow_id.clone(),
         input_tokens,
         output_tokens,
         Some(final_model.clone()),
@@ -390,47 +863,125 @@ async fn handle_non_streaming(
         if let Some(ref tool_calls) = choice.message.tool_calls {
             debug!(
                 tool_count = tool_calls.len(),
-                external_id = %user.external_id,
+                external_id = %user.log_id(),
                 "Response contains tool calls"
             );
         }
     }
 
+    let finish_reason = native_response
+        .choices
+        .first()
+        .and_then(|c| c.finish_reason.clone());
+    state
+        .finish_reasons
+        .observe("native_chat", &final_model, finish_reason.as_deref());
+
     info!(
         model = %final_model,
         input_tokens = input_tokens,
         output_tokens = output_tokens,
-        external_id = %user.external_id,
+        finish_reason = %finish_reason.as_deref().unwrap_or("unknown"),
+        messages = complexity.messages,
+        content_chars = complexity.content_chars,
+        images = complexity.images,
+        tools = complexity.tools,
+        external_id = %user.log_id(),
+        workflow_id = ?workflow_id,
         "Native chat completion completed"
     );
 
-    // Build response with custom headers
-    let mut response = Json(native_response).into_response();
+    // Usage above was taken from the unfiltered response
+    if let Some(filter) = response_filter(&state, &final_model).await {
+        for choice in &mut native_response.choices {
+            if let Some(ref mut content) = choice.message.content {
+                *content = filter.strip_text(content);
+            }
+        }
+    }
+
+    // Checked after tracking: the upstream tokens were spent either way
+    if let Some(ref blocklist) = state.config.provider.response_blocklist {
+        let blocked = native_response
+            .choices
+            .iter()
+            .filter_map(|c| c.message.content.as_deref())
+            .any(|content| blocklist.is_blocked(content));
+        if blocked {
+            warn!(model = %final_model, external_id = %user.log_id(), "Response matched the blocklist");
+            record_content_blocked("native_chat");
+            return Err(NativeErrorResponse::content_blocked());
+        }
+    }
+
+    // History keeps the reply as the client receives it
+    if let Some(turn) = turn {
+        let reply = native_response.choices.first().map(|choice| &choice.message);
+        turn.record(&state.session_manager, input_tokens, output_tokens, || {
+            assistant_message(
+                reply.and_then(|message| message.content.clone()),
+                reply.and_then(|message| message.tool_calls.clone()),
+            )
+        })
+        .await;
+    }
+
+    // Build response with custom headers, MessagePack if the client prefers it
+    let mut response =
+        response_format(headers, false).response(StatusCode::OK, &native_response);
     add_sentinel_headers(response.headers_mut(), &final_model, selection.tier);
+    if let Some(reason) = fallback_reason {
+        response
+            .headers_mut()
+            .insert(FALLBACK_REASON_HEADER, HeaderValue::from_static(reason));
+    }
 
     Ok(response)
 }
 
+/// Reasoning filter for the model that actually served the request
+///
+/// Looked up after execution since a context fallback may have switched models.
+async fn response_filter(state: &AppState, model: &str) -> Option<ResponseFilter> {
+    let tier_config = state.tier_config_cache.get_config().await.ok();
+    ResponseFilter::for_model(tier_config.as_ref(), &state.config.provider, model)
+}
+
+/// Response, serving model, serving provider and fallback reason of an executed request
+type ExecutionResult = (
+    crate::native::response::ChatCompletionResponse,
+    String,
+    String,
+    Option<&'static str>,
+);
+
 /// Execute request with single retry on provider failure
+///
+/// Context-length errors are retried on the tier's long-context model instead
+/// when one is configured.
 async fn execute_with_retry(
     state: &Arc<AppState>,
     headers: &HeaderMap,
     provider_request: serde_json::Value,
     selection: &ModelSelection,
     translator: &OpenAITranslator,
-) -> Result<(crate::native::response::ChatCompletionResponse, String, String), NativeErrorResponse>
+) -> Result<ExecutionResult, NativeErrorResponse>
 {
     // Try primary model
+    let started = Instant::now();
     match state
-        .ai_provider
+        .provider()
         .chat_completions(provider_request.clone(), headers)
         .await
     {
         Ok(provider_response) => {
-            // Record success
+            // Record success and latency (feeds latency-aware tier selection)
             state
                 .tier_router
                 .record_success(&selection.provider, &selection.model);
+            state
+                .tier_router
+                .record_latency(&selection.provider, &selection.model, started.elapsed());
 
             let (native_response, _id_mapping) = translator
                 .translate_response(provider_response)
@@ -442,8 +993,26 @@ async fn execute_with_retry(
                 native_response,
                 selection.model.clone(),
                 selection.provider.clone(),
+                None,
             ));
         }
+        Err(e) if is_context_length_error(&e) && selection.long_context_model.is_some() => {
+            // The prompt is too large for this model, not a provider fault
+            let fallback_model = selection.long_context_model.as_deref().unwrap_or_default();
+            execute_context_fallback(
+                state,
+                headers,
+                &provider_request,
+                selection,
+                fallback_model,
+                translator,
+            )
+            .await
+        }
+        Err(e @ AppError::UpstreamUnavailable { .. }) => {
+            // The circuit is open, so a retry would fail fast the same way
+            Err(NativeErrorResponse::from_app_error(e))
+        }
         Err(e) => {
             // Record failure
             state
@@ -473,8 +1042,9 @@ async fn execute_with_retry(
                     );
 
                     // Retry with alternative model
+                    let started = Instant::now();
                     match state
-                        .ai_provider
+                        .provider()
                         .chat_completions(provider_request, headers)
                         .await
                     {
@@ -482,6 +1052,11 @@ async fn execute_with_retry(
                             state
                                 .tier_router
                                 .record_success(&alternative.provider, &alternative.model);
+                            state.tier_router.record_latency(
+                                &alternative.provider,
+                                &alternative.model,
+                                started.elapsed(),
+                            );
 
                             let (native_response, _id_mapping) = translator
                                 .translate_response(provider_response)
@@ -496,8 +1071,13 @@ async fn execute_with_retry(
                                 native_response,
                                 alternative.model.clone(),
                                 alternative.provider.clone(),
+                                None,
                             ));
                         }
+                        Err(retry_err @ AppError::UpstreamUnavailable { .. }) => {
+                            // The primary's failure opened the circuit
+                            Err(NativeErrorResponse::from_app_error(retry_err))
+                        }
                         Err(retry_err) => {
                             state
                                 .tier_router
@@ -512,6 +1092,7 @@ async fn execute_with_retry(
                             return Err(NativeErrorResponse::provider_error(
                                 format!("All models failed: {}", retry_err),
                                 &alternative.provider,
+                                retry_err.retryable(),
                             ));
                         }
                     }
@@ -521,6 +1102,7 @@ async fn execute_with_retry(
                     return Err(NativeErrorResponse::provider_error(
                         e.to_string(),
                         &selection.provider,
+                        e.retryable(),
                     ));
                 }
             }
@@ -528,8 +1110,64 @@ async fn execute_with_retry(
     }
 }
 
+/// Re-issue a request that exceeded the selected model's context on the long-context model
+///
+/// Only one attempt is made; a second failure (including another context
+/// error) is returned to the client.
+async fn execute_context_fallback(
+    state: &Arc<AppState>,
+    headers: &HeaderMap,
+    provider_request: &serde_json::Value,
+    selection: &ModelSelection,
+    fallback_model: &str,
+    translator: &OpenAITranslator,
+) -> Result<ExecutionResult, NativeErrorResponse> {
+    info!(
+        original_model = %selection.model,
+        fallback_model = %fallback_model,
+        "Context length exceeded, retrying with long-context model"
+    );
+
+    match state
+        .provider()
+        .chat_completions(request_for_model(provider_request, fallback_model), headers)
+        .await
+    {
+        Ok(provider_response) => {
+            state
+                .tier_router
+                .record_success(&selection.provider, fallback_model);
+
+            let (native_response, _id_mapping) = translator
+                .translate_response(provider_response)
+                .map_err(|e| {
+                    NativeErrorResponse::internal(format!("Response translation failed: {}", e))
+                })?;
+
+            Ok((
+                native_response,
+                fallback_model.to_string(),
+                selection.provider.clone(),
+                Some(CONTEXT_LENGTH_REASON),
+            ))
+        }
+        Err(e) => {
+            warn!(
+                fallback_model = %fallback_model,
+                error = %e,
+                "Long-context fallback also failed"
+            );
+            Err(NativeErrorResponse::provider_error(
+                e.to_string(),
+                &selection.provider,
+                e.retryable(),
+            ))
+        }
+    }
+}
+
 /// Add X-Sentinel-Model and X-Sentinel-Tier headers to response
-fn add_sentinel_headers(headers: &mut HeaderMap, model: &str, tier: Tier) {
+pub(crate) fn add_sentinel_headers(headers: &mut HeaderMap, model: &str, tier: Tier) {
     if let Ok(value) = HeaderValue::from_str(model) {
         headers.insert("X-Sentinel-Model", value);
     }
@@ -542,12 +1180,22 @@ fn add_sentinel_headers(headers: &mut HeaderMap, model: &str, tier: Tier) {
 ///
 /// Note: For streaming, retry is only possible BEFORE any chunks are sent.
 /// Once streaming starts, we fail fast without retry.
+///
+/// The conversation's stream lock, if any, is refreshed as chunks arrive and
+/// released when the upstream stream ends (or on drop if the client leaves).
+#[allow(clippy::too_many_arguments)]
 async fn handle_streaming(
     state: Arc<AppState>,
     headers: &HeaderMap,
     mut provider_request: serde_json::Value,
-    selection: ModelSelection,
+    mut selection: ModelSelection,
     user: AuthenticatedUser,
+    workflow_id: Option<String>,
+    complexity: RequestComplexity,
+    estimated_input_tokens: u64,
+    timeout: Option<Duration>,
+    mut stream_lock: Option<StreamLock>,
+    turn: Option<SessionTurn>,
 ) -> Result<Response, NativeErrorResponse> {
     // Inject stream_options.include_usage: true to get token counts from OpenAI
     // This is critical for accurate usage tracking
@@ -557,10 +1205,14 @@ async fn handle_streaming(
 
     // Forward streaming request to provider
     // Note: No retry after streaming starts - would cause duplicate partial responses
-    let stream = match state
-        .ai_provider
-        .chat_completions_stream(provider_request.clone(), headers)
-        .await
+    let mut fallback_reason = None;
+    let stream = match timeout::stream_with_timeout(
+        timeout,
+        state
+            .provider()
+            .chat_completions_stream(provider_request.clone(), headers),
+    )
+    .await
     {
         Ok(stream) => {
             state
@@ -568,6 +1220,46 @@ async fn handle_streaming(
                 .record_success(&selection.provider, &selection.model);
             stream
         }
+        Err(e) if is_context_length_error(&e) && selection.long_context_model.is_some() => {
+            // The stream failed to open, so nothing has been sent and it can be re-issued once
+            let fallback_model = selection.long_context_model.take().unwrap_or_default();
+            info!(
+                original_model = %selection.model,
+                fallback_model = %fallback_model,
+                "Context length exceeded, retrying stream with long-context model"
+            );
+
+            let fallback_request = request_for_model(&provider_request, &fallback_model);
+            let stream = timeout::stream_with_timeout(
+                timeout,
+                state
+                    .provider()
+                    .chat_completions_stream(fallback_request, headers),
+            )
+            .await
+            .map_err(|e| {
+                warn!(
+                    fallback_model = %fallback_model,
+                    error = %e,
+                    "Long-context streaming fallback also failed"
+                );
+                NativeErrorResponse::provider_error(
+                    e.to_string(),
+                    &selection.provider,
+                    e.retryable(),
+                )
+            })?;
+
+            state
+                .tier_router
+                .record_success(&selection.provider, &fallback_model);
+            selection.model = fallback_model;
+            fallback_reason = Some(CONTEXT_LENGTH_REASON);
+            stream
+        }
+        Err(e @ AppError::UpstreamUnavailable { .. }) => {
+            return Err(NativeErrorResponse::from_app_error(e));
+        }
         Err(e) => {
             state
                 .tier_router
@@ -583,6 +1275,7 @@ async fn handle_streaming(
             return Err(NativeErrorResponse::provider_error(
                 e.to_string(),
                 &selection.provider,
+                e.retryable(),
             ));
         }
     };
@@ -590,7 +1283,7 @@ async fn handle_streaming(
     // Clone values for the stream closure
     let model_clone = selection.model.clone();
     let tracker = state.batching_tracker.clone();
-    let user_email = user.email.clone();
+    let user_email = user.log_email().to_string();
     let token_counter = state.token_counter.clone();
 
     // Track accumulated usage from stream
@@ -601,31 +1294,91 @@ async fn handle_streaming(
     let content_accumulator = std::sync::Arc::new(std::sync::Mutex::new(String::new()));
     let content_for_stream = content_accumulator.clone();
 
+    // Track the last finish_reason seen for the audit log
+    let finish_reason_accumulator = std::sync::Arc::new(std::sync::Mutex::new(None::<String>));
+    let finish_reason_for_stream = finish_reason_accumulator.clone();
+
     // Buffer for accumulating incomplete SSE lines across chunk boundaries
-    let line_buffer = std::sync::Arc::new(std::sync::Mutex::new(SseLineBuffer::new()));
+    let line_buffer = std::sync::Arc::new(std::sync::Mutex::new(
+        SseLineBuffer::with_max_line_bytes(state.config.provider.sse_max_line_bytes),
+    ));
     let line_buffer_for_stream = line_buffer.clone();
 
+    // Set when the upstream sends an oversized line; the stream ends after the error event
+    let aborted = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let aborted_for_stream = aborted.clone();
+
     // Clone model for metrics in stream closure
     let model_for_parse_error = selection.model.clone();
 
+    // Filtered chunks are re-encoded from the parsed lines; accounting still sees the originals
+    let mut stream_filter = response_filter(&state, &selection.model)
+        .await
+        .map(|filter| filter.stream());
+
+    // Provider tool call IDs are replaced with Sentinel IDs as the deltas arrive
+    let tool_calls = std::sync::Arc::new(std::sync::Mutex::new(ToolCallStreamTranslator::new()));
+    let tool_calls_for_stream = tool_calls.clone();
+
+    // Deltas are scanned across chunk boundaries; a match ends the stream
+    let mut scanner = state
+        .config
+        .provider
+        .response_blocklist
+        .as_ref()
+        .map(|blocklist| blocklist.scanner());
+
     // Wrap the stream to extract content and usage from chunks
-    // Since our Native API format is OpenAI-compatible, chunks pass through with minimal transformation
+    // Since our Native API format is OpenAI-compatible, only tool call chunks are rewritten
     let tracked_stream = stream.map(move |chunk| {
         match chunk {
             Ok(bytes) => {
                 // Use line buffer to handle chunks split across network boundaries
-                let complete_lines = line_buffer_for_stream.lock().unwrap().feed(&bytes);
+                let mut complete_lines = match line_buffer_for_stream.lock().unwrap().feed(&bytes) {
+                    Ok(lines) => lines,
+                    Err(e) => {
+                        warn!(model = %model_clone, error = %e, "Aborting stream with oversized SSE line");
+                        aborted_for_stream.store(true, std::sync::atomic::Ordering::Relaxed);
+                        return Ok(format_error_event(&e.to_string(), Some("sse_line_too_long")));
+                    }
+                };
 
-                for line in complete_lines {
+                for line in complete_lines.iter_mut() {
+                    let mut translated = None;
                     if let Some(json_str) = line.strip_prefix("data: ") {
                         let json_str = json_str.trim();
                         if json_str != "[DONE]" {
                             match serde_json::from_str::<StreamChunk>(json_str) {
                                 Ok(chunk) => {
+                                    let mut translator = tool_calls_for_stream.lock().unwrap();
+                                    let finishing = chunk.choices.iter().any(|c| c.finish_reason.is_some());
+                                    if json_str.contains("\"tool_calls\"") || (finishing && translator.has_tool_calls()) {
+                                        if let Ok(mut native) = serde_json::from_str::<response::StreamChunk>(json_str) {
+                                            if translator.translate(&mut native) {
+                                                translated = Some(format!(
+                                                    "data: {}",
+                                                    serde_json::to_string(&native).expect("StreamChunk should always serialize")
+                                                ));
+                                            }
+                                        }
+                                    }
+                                    drop(translator);
                                     // Accumulate content from delta
                                     if let Some(choice) = chunk.choices.first() {
+                                        if let Some(ref reason) = choice.finish_reason {
+                                            *finish_reason_for_stream.lock().unwrap() = Some(reason.clone());
+                                        }
                                         if let Some(ref content) = choice.delta.content {
                                             content_for_stream.lock().unwrap().push_str(content);
+                                            if scanner.as_mut().is_some_and(|s| s.scan(content)) {
+                                                warn!(model = %model_clone, "Aborting stream matching the blocklist");
+                                                record_content_blocked("native_chat");
+                                                aborted_for_stream.store(true, std::sync::atomic::Ordering::Relaxed);
+                                                return Ok(format_error_event(
+                                                    CONTENT_BLOCKED_MESSAGE,
+                                                    Some(CONTENT_BLOCKED_CODE),
+                                                ));
+                                            }
                                         }
                                     }
                                     // Capture usage if provided (usually in final chunk)
@@ -648,8 +1401,16 @@ async fn handle_streaming(
                             }
                         }
                     }
+                    if let Some(translated) = translated {
+                        *line = translated;
+                    }
+                }
+                // Lines are always re-encoded: a tool call split across network
+                // chunks must not be half sent before its ID is rewritten
+                match stream_filter {
+                    Some(ref mut filter) => Ok(filter.rewrite_lines(&complete_lines)),
+                    None => Ok(format_sse_lines(&complete_lines)),
                 }
-                Ok(bytes)
             }
             Err(e) => {
                 warn!(model = %model_clone, error = %e, "Stream error in native chat");
@@ -660,24 +1421,55 @@ async fn handle_streaming(
 
     // Create a stream that tracks usage after completion
     let user_email_final = user_email.clone();
+    let user_final = user.clone();
     let model_for_metrics = selection.model.clone();
+    let tier_final = selection.tier;
     let model_for_counting = selection.model.clone();
     let usage_final = usage_accumulator.clone();
     let content_final = content_accumulator.clone();
+    let finish_reason_final = finish_reason_accumulator.clone();
+    let finish_reasons_final = state.finish_reasons.clone();
     let tracker_final = tracker.clone();
+    let sessions_final = state.session_manager.clone();
+    let tool_calls_final = tool_calls.clone();
+    // Accounts that require exact usage get estimates flagged rather than silently billed
+    let exact_usage = exact::required(&state.config.usage, &user);
+    let report_estimated = state.config.usage.report_estimated_usage;
+
+    let aborted_final = aborted.clone();
 
     let final_stream = async_stream::stream! {
-        futures::pin_mut!(tracked_stream);
-        while let Some(item) = tracked_stream.next().await {
-            yield item;
+        {
+            futures::pin_mut!(tracked_stream);
+            while let Some(item) = tracked_stream.next().await {
+                yield item;
+                if aborted_final.load(std::sync::atomic::Ordering::Relaxed) {
+                    break;
+                }
+                if let Some(lock) = stream_lock.as_mut() {
+                    lock.refresh().await;
+                }
+            }
+            // Dropping the tracked stream here closes the upstream connection
+        }
+
+        // The next stream in this conversation may start
+        if let Some(lock) = stream_lock.take() {
+            lock.release().await;
         }
 
         // Stream completed - determine token counts
         let openai_usage = usage_final.lock().unwrap().clone();
         let accumulated_content = content_final.lock().unwrap().clone();
+        let translator = std::mem::take(&mut *tool_calls_final.lock().unwrap());
+        let streamed_tool_calls = translator.finish().unwrap_or_else(|e| {
+            warn!(model = %model_for_metrics, error = %e, "Streamed tool calls could not be finalized");
+            Vec::new()
+        });
 
         // Prefer OpenAI usage if available, otherwise estimate
-        let (input_tokens, output_tokens) = if openai_usage.prompt_tokens > 0 || openai_usage.completion_tokens > 0 {
+        let estimated_usage = openai_usage.prompt_tokens == 0 && openai_usage.completion_tokens == 0;
+        let (input_tokens, output_tokens) = if !estimated_usage {
             debug!(
                 actual_input = openai_usage.prompt_tokens,
                 actual_output = openai_usage.completion_tokens,
@@ -687,26 +1479,75 @@ async fn handle_streaming(
             (openai_usage.prompt_tokens as u64, openai_usage.completion_tokens as u64)
         } else {
             // Fallback to estimation - OpenAI didn't return usage field
+            // Tool call turns usually stream no content, only the calls
+            let output_text = accumulated_content.clone() + &tool_calls_text(&streamed_tool_calls);
             let estimated_output = token_counter
-                .count_tokens(&model_for_counting, &accumulated_content)
+                .count_tokens(&model_for_counting, &output_text)
                 .unwrap_or(0) as u64;
             warn!(
                 model = %model_for_counting,
+                estimated_input = estimated_input_tokens,
                 estimated_output = estimated_output,
                 content_len = accumulated_content.len(),
                 "Using estimated token counts - OpenAI didn't return usage field"
             );
-            (0, estimated_output)
+            (estimated_input_tokens, estimated_output)
         };
 
         // Track usage in Zion (fire-and-forget)
-        tracker_final.track(user_email_final.clone(), input_tokens, output_tokens, Some(model_for_metrics.clone()));
+        if estimated_usage && exact_usage {
+            record_estimated_usage("/native/v1/chat/completions");
+            warn!(
+                model = %model_for_metrics,
+                external_id = %user_final.log_id(),
+                "Exact usage required but the stream ended without provider usage"
+            );
+        }
+        if estimated_usage && exact_usage && report_estimated {
+            tracker_final.track_user_estimated(
+                &user_final,
+                tier_final,
+                workflow_id.clone(),
+                input_tokens,
+                output_tokens,
+                Some(model_for_metrics.clone()),
+            );
+        } else {
+            tracker_final.track_user_in_tier(
+                &user_final,
+                tier_final,
+                workflow_id.clone(),
+                input_tokens,
+                output_tokens,
+                Some(model_for_metrics.clone()),
+            );
+        }
+
+        let finish_reason = finish_reason_final.lock().unwrap().clone();
+        finish_reasons_final.observe("native_chat", &model_for_metrics, finish_reason.as_deref());
+
+        if let Some(turn) = turn {
+            turn.record(&sessions_final, input_tokens, output_tokens, || {
+                assistant_message(
+                    Some(accumulated_content),
+                    Some(streamed_tool_calls).filter(|calls| !calls.is_empty()),
+                )
+            })
+            .await;
+        }
 
         info!(
             model = %model_for_metrics,
             input_tokens = input_tokens,
             output_tokens = output_tokens,
+            finish_reason = %finish_reason.as_deref().unwrap_or("unknown"),
+            messages = complexity.messages,
+            content_chars = complexity.content_chars,
+            images = complexity.images,
+            tools = complexity.tools,
+            estimated_usage = estimated_usage,
             email = %user_email_final,
+            workflow_id = ?workflow_id,
             "Native streaming usage tracked"
         );
     };
@@ -714,21 +1555,20 @@ async fn handle_streaming(
     // Build SSE response with custom headers
     let body = Body::from_stream(final_stream);
 
-    let response = Response::builder()
-        .status(StatusCode::OK)
-        .header(header::CONTENT_TYPE, "text/event-stream")
-        .header(header::CACHE_CONTROL, "no-cache")
-        .header(header::CONNECTION, "keep-alive")
-        .header("X-Accel-Buffering", "no")
+    let mut builder = sse_response(StatusCode::OK)
         .header("X-Sentinel-Model", &selection.model)
-        .header("X-Sentinel-Tier", selection.tier.to_string())
+        .header("X-Sentinel-Tier", selection.tier.to_string());
+    if let Some(reason) = fallback_reason {
+        builder = builder.header(FALLBACK_REASON_HEADER, reason);
+    }
+    let response = builder
         .body(body)
         .map_err(|e| NativeErrorResponse::internal(format!("Failed to build response: {}", e)))?;
 
     info!(
         model = %selection.model,
         tier = %selection.tier,
-        external_id = %user.external_id,
+        external_id = %user.log_id(),
         "Native streaming chat started"
     );
 
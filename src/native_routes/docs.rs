@@ -245,6 +245,10 @@ mod tests {
                 .unwrap()
                 .contains("Sentinel"));
             assert!(spec["paths"]["/native/v1/chat/completions"].is_object());
+            assert!(spec["paths"]["/native/v1/chat/completions/batch"].is_object());
+            assert!(spec["paths"]["/native/v1/embeddings"].is_object());
+            assert!(spec["paths"]["/native/v1/sessions/{conversation_id}"].is_object());
+            assert!(spec["paths"]["/native/v1/tokenize"].is_object());
             assert!(spec["components"]["schemas"]["ChatCompletionRequest"].is_object());
             assert!(spec["components"]["securitySchemes"]["bearer_auth"].is_object());
         })
@@ -0,0 +1,192 @@
+//! Native API embeddings
+//!
+//! `POST /native/v1/embeddings` embeds one text or a list of texts with the
+//! embedding model the tier config sets for the request's tier (simple by
+//! default). Every text is counted with the proxy's tokenizer before the
+//! provider is called, so a request above `NATIVE_EMBEDDINGS_MAX_INPUT_TOKENS`
+//! is refused without spending anything. Input tokens are tracked like the
+//! `/v1/embeddings` endpoint does; the provider's count wins over the estimate.
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use axum::{
+    extract::State,
+    http::HeaderMap,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde_json::json;
+use tracing::{debug, info};
+
+use crate::{
+    error::AppError,
+    middleware::auth::AuthenticatedUser,
+    native::{
+        error::NativeErrorResponse, request::EmbeddingsRequest, response::EmbeddingsResponse,
+        types::Tier,
+    },
+    native_routes::chat::add_sentinel_headers,
+    routes::{
+        body::SentinelJson,
+        metrics::{record_request, record_tokens},
+    },
+    AppState,
+};
+
+/// Path of the embeddings route inside the native router
+pub const EMBEDDINGS_PATH: &str = "/v1/embeddings";
+
+/// Create embeddings for text
+#[utoipa::path(
+    post,
+    path = "/native/v1/embeddings",
+    tag = "Embeddings",
+    operation_id = "createEmbeddings",
+    description = "Embed one text or a list of texts.
+
+The model is the embedding model the tier config sets for `tier` (simple when not given); it is returned in the `X-Sentinel-Model` header, and the tier in `X-Sentinel-Tier`. The response follows OpenAI's embeddings format, one vector per text in input order.
+
+Texts are counted with Sentinel's tokenizer before the provider is called. A request whose texts add up to more than the configured maximum (`NATIVE_EMBEDDINGS_MAX_INPUT_TOKENS`) is refused with a 400. Input tokens are charged as usage; there are no output tokens.",
+    request_body(
+        content = EmbeddingsRequest,
+        description = "Texts to embed",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Embeddings, in input order", body = EmbeddingsResponse),
+        (status = 400, description = "Invalid request - empty input, an empty text or too many input tokens", body = NativeErrorResponse),
+        (status = 401, description = "Missing or invalid JWT in Authorization header"),
+        (status = 403, description = "Insufficient permissions or quota exceeded"),
+        (status = 429, description = "Rate limit exceeded", body = NativeErrorResponse),
+        (status = 502, description = "Provider error - upstream AI provider failed", body = NativeErrorResponse),
+        (status = 503, description = "Tier config unavailable or no embedding model configured for the tier", body = NativeErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn native_embeddings(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Extension(user): Extension<AuthenticatedUser>,
+    SentinelJson(request, _): SentinelJson<EmbeddingsRequest>,
+) -> Result<Response, NativeErrorResponse> {
+    let start_time = Instant::now();
+    let tier = request.tier.unwrap_or_default();
+
+    let texts = request.input.texts();
+    if texts.is_empty() {
+        return Err(NativeErrorResponse::validation(
+            "input must contain at least one text",
+        ));
+    }
+    if let Some(index) = texts.iter().position(|text| text.is_empty()) {
+        return Err(NativeErrorResponse::validation(format!(
+            "input[{}] must not be empty",
+            index
+        )));
+    }
+
+    let model = resolve_model(&state, tier).await?;
+
+    // Counted per text so thousands of texts stay cheap and the limit is
+    // enforced before the provider sees anything
+    let counts = state
+        .token_counter
+        .count_tokens_each(&model, &texts)
+        .map_err(NativeErrorResponse::from_app_error)?;
+    let input_tokens: usize = counts.iter().sum();
+    let max_tokens = state.config.provider.native_embeddings_max_input_tokens;
+    if input_tokens > max_tokens {
+        return Err(NativeErrorResponse::validation(format!(
+            "input has {} tokens across {} texts, more than the maximum of {}",
+            input_tokens,
+            texts.len(),
+            max_tokens
+        )));
+    }
+
+    debug!(
+        model = %model,
+        tier = %tier,
+        texts = texts.len(),
+        input_tokens,
+        external_id = %user.log_id(),
+        "Processing native embeddings request"
+    );
+
+    let mut provider_request = json!({
+        "model": model,
+        "input": request.input,
+    });
+    if let Some(dimensions) = request.dimensions {
+        provider_request["dimensions"] = json!(dimensions);
+    }
+
+    let provider = state.provider();
+    let mut response_value = provider
+        .embeddings(provider_request, &headers)
+        .await
+        .map_err(|e| match e {
+            AppError::UpstreamTimeout { .. } | AppError::UpstreamUnavailable { .. } => {
+                NativeErrorResponse::from_app_error(e)
+            }
+            e => NativeErrorResponse::provider_error(e.to_string(), provider.name(), e.retryable()),
+        })?;
+
+    // Providers that don't report usage are charged the estimate
+    if response_value
+        .get("usage")
+        .is_none_or(|usage| usage.is_null())
+    {
+        response_value["usage"] = json!({
+            "prompt_tokens": input_tokens,
+            "total_tokens": input_tokens,
+        });
+    }
+    let response: EmbeddingsResponse = serde_json::from_value(response_value).map_err(|e| {
+        NativeErrorResponse::internal(format!("Failed to parse embeddings response: {}", e))
+    })?;
+
+    let duration = start_time.elapsed().as_secs_f64();
+    let prompt_tokens = response.usage.prompt_tokens as u64;
+    record_request("success", &model, duration);
+    record_tokens("prompt", prompt_tokens, &model);
+
+    // Embeddings only have input tokens
+    state
+        .batching_tracker
+        .track_user_in_tier(&user, tier, None, prompt_tokens, 0, Some(model.clone()));
+
+    info!(
+        model = %model,
+        tier = %tier,
+        texts = response.data.len(),
+        prompt_tokens,
+        estimated_tokens = input_tokens,
+        duration_ms = %format!("{:.2}", duration * 1000.0),
+        external_id = %user.log_id(),
+        "Native embeddings request completed"
+    );
+
+    let mut response = Json(response).into_response();
+    add_sentinel_headers(response.headers_mut(), &model, tier);
+    Ok(response)
+}
+
+/// Embedding model the tier config sets for `tier`
+async fn resolve_model(state: &AppState, tier: Tier) -> Result<String, NativeErrorResponse> {
+    let config = state.tier_config_cache.get_config().await.map_err(|e| {
+        NativeErrorResponse::service_unavailable(format!("Tier config unavailable: {}", e))
+    })?;
+    config
+        .embedding_model_for_tier(tier)
+        .map(str::to_string)
+        .ok_or_else(|| {
+            NativeErrorResponse::service_unavailable(format!(
+                "No embedding model configured for tier {}",
+                tier
+            ))
+        })
+}
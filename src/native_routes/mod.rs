@@ -4,41 +4,223 @@
 //! These endpoints accept the unified Native API format and translate to
 //! provider-specific formats internally.
 
+pub mod batch;
 pub mod chat;
 pub mod docs;
+pub mod embeddings;
+pub mod sessions;
+pub mod tokenize;
 
 pub use docs::create_docs_router;
 
 use std::sync::Arc;
 
-use axum::{middleware, routing::post, Router};
+use axum::{
+    extract::OriginalUri,
+    http::{header, HeaderValue, Method},
+    middleware,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Router,
+};
+use tracing::warn;
 
 use crate::{
-    middleware::{auth::auth_middleware, rate_limiter::rate_limit_middleware},
+    middleware::{
+        auth::auth_middleware, decompression::decompression_middleware,
+        in_flight::in_flight_middleware, maintenance::maintenance_middleware, mirror::mirror_middleware,
+        provider_override::provider_override_middleware, quarantine::quarantine_middleware,
+        rate_limiter::rate_limit_middleware,
+        scope::{scope_middleware, CHAT_SCOPE, EMBEDDINGS_SCOPE},
+        synthetic::synthetic_middleware,
+    },
+    native::error::NativeErrorResponse,
     AppState,
 };
 
+/// Endpoints served under `/native`, listed by the fallback's 404
+const NATIVE_ENDPOINTS: &[&str] = &[
+    "POST /native/v1/chat/completions",
+    "POST /native/v1/chat/completions/batch",
+    "POST /native/v1/embeddings",
+    "GET /native/v1/sessions/{conversation_id}",
+    "POST /native/v1/tokenize",
+];
+
+/// `Allow` header of the native POST routes (`OPTIONS` is answered by the CORS layer)
+const CHAT_ALLOWED_METHODS: &str = "OPTIONS, POST";
+
+/// `Allow` header of the session export route
+const SESSION_ALLOWED_METHODS: &str = "GET, HEAD, OPTIONS";
+
 /// Create the native API router
 ///
 /// Routes:
 /// - POST /v1/chat/completions - Chat completions (streaming + non-streaming)
+/// - POST /v1/chat/completions/batch - Batches of non-streaming chat completions
+/// - POST /v1/embeddings - Embeddings with the tier's embedding model
+/// - GET /v1/sessions/:conversation_id - Export of the caller's session (owner only)
+/// - POST /v1/tokenize - Token counts and truncation suggestions (no provider call)
+///
+/// Other methods on these routes get a 405 and other paths a 404, both as
+/// `NativeErrorResponse`. `OPTIONS` never reaches this router: the global
+/// CORS layer answers it according to the CORS policy.
 ///
-/// All routes require authentication and rate limiting.
+/// All routes, including the fallback, require authentication and rate
+/// limiting, the same as the `/v1` router and its pass-through fallback.
 /// Middleware is applied in reverse order (last applied runs first):
 /// - auth_middleware runs first
-/// - rate_limit_middleware runs second
+/// - synthetic_middleware runs second (X-Sentinel-Synthetic for allow-listed monitors)
+/// - quarantine_middleware runs third
+/// - batch_weight_middleware runs fourth (a batch counts once per item for rate limiting)
+/// - rate_limit_middleware runs fifth
+/// - provider_override_middleware runs sixth (X-Sentinel-Provider for canary accounts)
+/// - mirror_middleware runs seventh (copies sampled requests to `MIRROR_URL`)
+/// - in_flight_middleware runs eighth (lists the request in `/admin/snapshot`)
+/// - scope_middleware runs ninth (per route, 403 without the `chat` or `embeddings` scope)
+/// - maintenance_middleware runs last (per route, 503 while in maintenance)
 ///
 /// Returns a `Router<Arc<AppState>>` to be nested into the main router.
 /// Do not call `.with_state()` on the returned router - the parent router
 /// will provide the state.
 pub fn create_native_router(state: Arc<AppState>) -> Router<Arc<AppState>> {
     Router::new()
-        .route("/v1/chat/completions", post(chat::native_chat_completions))
-        // Apply rate limiting (runs after auth)
+        .route(
+            "/v1/chat/completions",
+            post(chat::native_chat_completions)
+                .layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    maintenance_middleware,
+                ))
+                .layer(middleware::from_fn_with_state(CHAT_SCOPE, scope_middleware))
+                .fallback(chat_method_not_allowed),
+        )
+        .route(
+            batch::BATCH_PATH,
+            post(batch::native_chat_completions_batch)
+                .layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    maintenance_middleware,
+                ))
+                .layer(middleware::from_fn_with_state(CHAT_SCOPE, scope_middleware))
+                .fallback(batch_method_not_allowed),
+        )
+        .route(
+            embeddings::EMBEDDINGS_PATH,
+            post(embeddings::native_embeddings)
+                .layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    maintenance_middleware,
+                ))
+                .layer(middleware::from_fn_with_state(
+                    EMBEDDINGS_SCOPE,
+                    scope_middleware,
+                ))
+                .fallback(embeddings_method_not_allowed),
+        )
+        .route(
+            sessions::SESSION_PATH,
+            get(sessions::native_get_session)
+                .layer(middleware::from_fn_with_state(CHAT_SCOPE, scope_middleware))
+                .fallback(session_method_not_allowed),
+        )
+        .route(
+            tokenize::TOKENIZE_PATH,
+            post(tokenize::native_tokenize).fallback(tokenize_method_not_allowed),
+        )
+        // Native-format 404 for anything else under /native
+        .fallback(native_fallback)
+        // List the request in `/admin/snapshot` while it runs (runs after the mirror)
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            in_flight_middleware,
+        ))
+        // Copy a sample of requests to the staging mirror (runs after the canary override)
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            mirror_middleware,
+        ))
+        // Honor X-Sentinel-Provider for canary accounts (runs after rate limiting)
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            provider_override_middleware,
+        ))
+        // Apply rate limiting (runs after batch weighing)
         .layer(middleware::from_fn_with_state(
             state.clone(),
             rate_limit_middleware,
         ))
+        // Count batch items for rate limiting (runs after quarantine)
+        .layer(middleware::from_fn(batch::batch_weight_middleware))
+        // Reject quarantined users before any further work (runs after synthetic marking)
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            quarantine_middleware,
+        ))
+        // Mark allow-listed X-Sentinel-Synthetic requests (runs after decompression)
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            synthetic_middleware,
+        ))
+        // Inflate gzip request bodies, for authenticated callers only (runs after auth)
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            decompression_middleware,
+        ))
         // Apply authentication (runs first)
         .layer(middleware::from_fn_with_state(state, auth_middleware))
 }
+
+/// 405 for methods the chat route doesn't accept
+async fn chat_method_not_allowed(method: Method) -> Response {
+    method_not_allowed(method, "/native/v1/chat/completions", CHAT_ALLOWED_METHODS)
+}
+
+/// 405 for methods the batch route doesn't accept
+async fn batch_method_not_allowed(method: Method) -> Response {
+    method_not_allowed(method, "/native/v1/chat/completions/batch", CHAT_ALLOWED_METHODS)
+}
+
+/// 405 for methods the embeddings route doesn't accept
+async fn embeddings_method_not_allowed(method: Method) -> Response {
+    method_not_allowed(method, "/native/v1/embeddings", CHAT_ALLOWED_METHODS)
+}
+
+/// 405 for methods the session export route doesn't accept
+async fn session_method_not_allowed(method: Method) -> Response {
+    method_not_allowed(
+        method,
+        "/native/v1/sessions/{conversation_id}",
+        SESSION_ALLOWED_METHODS,
+    )
+}
+
+/// 405 for methods the tokenize route doesn't accept
+async fn tokenize_method_not_allowed(method: Method) -> Response {
+    method_not_allowed(method, "/native/v1/tokenize", CHAT_ALLOWED_METHODS)
+}
+
+fn method_not_allowed(method: Method, path: &str, allowed: &'static str) -> Response {
+    let mut response = NativeErrorResponse::method_not_allowed(format!(
+        "Method {} is not allowed on {} (allowed: {})",
+        method, path, allowed
+    ))
+    .into_response();
+    response
+        .headers_mut()
+        .insert(header::ALLOW, HeaderValue::from_static(allowed));
+    response
+}
+
+/// 404 for paths under `/native` without a route
+///
+/// Unlike the global fallback, points at the native endpoints instead of `/v1/`.
+async fn native_fallback(method: Method, OriginalUri(uri): OriginalUri) -> NativeErrorResponse {
+    warn!(method = %method, path = %uri.path(), "Unmatched native route");
+    NativeErrorResponse::endpoint_not_found(format!(
+        "Endpoint {} {} not found. Native API endpoints: {}",
+        method,
+        uri.path(),
+        NATIVE_ENDPOINTS.join(", ")
+    ))
+}
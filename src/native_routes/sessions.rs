@@ -0,0 +1,149 @@
+//! Native API session export
+//!
+//! `GET /native/v1/sessions/{conversation_id}` returns the caller's stored
+//! session: its tier and model binding, timestamps and accumulated usage, and
+//! with session history enabled the stored messages, a page at a time. A
+//! session of another user gets the same 404 as one that doesn't exist, so
+//! conversation IDs can't be probed.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{rejection::QueryRejection, Path, Query, State},
+    Extension, Json,
+};
+use serde::Deserialize;
+use tracing::info;
+use utoipa::IntoParams;
+
+use crate::{
+    middleware::auth::AuthenticatedUser,
+    native::{
+        error::NativeErrorResponse,
+        response::{SessionExport, SessionHistoryPage},
+        session::SessionHistory,
+    },
+    AppState,
+};
+
+/// Path of the session export route inside the native router
+pub const SESSION_PATH: &str = "/v1/sessions/:conversation_id";
+
+/// Messages per page when `limit` isn't given
+const DEFAULT_PAGE_LIMIT: usize = 100;
+
+/// Most messages per page
+const MAX_PAGE_LIMIT: usize = 1000;
+
+/// Pagination of a session's messages
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SessionHistoryQuery {
+    /// Return messages after this index (the previous page's `next_after`)
+    pub after: Option<usize>,
+    /// Messages per page (default 100, at most 1000)
+    pub limit: Option<usize>,
+}
+
+/// Export a conversation session
+#[utoipa::path(
+    get,
+    path = "/native/v1/sessions/{conversation_id}",
+    tag = "Sessions",
+    operation_id = "getSession",
+    description = "Export one of the caller's conversation sessions.
+
+Returns the tier and model the conversation is bound to, when it was created and last used, and the tokens its completed requests used. When the deployment stores session history, `history` holds the conversation as of its last completed request in Native message format. Long histories are paged: pass the previous page's `next_after` as `after`. `truncated` is true when older messages were dropped to stay within the history cap.
+
+Sessions that don't exist, have expired or belong to someone else all get the same 404.",
+    params(
+        ("conversation_id" = String, Path, description = "Conversation ID the session was created with"),
+        SessionHistoryQuery
+    ),
+    responses(
+        (status = 200, description = "The session", body = SessionExport),
+        (status = 400, description = "Invalid pagination parameters", body = NativeErrorResponse),
+        (status = 401, description = "Missing or invalid JWT in Authorization header"),
+        (status = 404, description = "No such session for the caller", body = NativeErrorResponse),
+        (status = 429, description = "Rate limit exceeded", body = NativeErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn native_get_session(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(conversation_id): Path<String>,
+    query: Result<Query<SessionHistoryQuery>, QueryRejection>,
+) -> Result<Json<SessionExport>, NativeErrorResponse> {
+    let Query(query) = query.map_err(|e| NativeErrorResponse::validation(e.body_text()))?;
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_LIMIT);
+    if !(1..=MAX_PAGE_LIMIT).contains(&limit) {
+        return Err(NativeErrorResponse::validation(format!(
+            "limit must be between 1 and {}",
+            MAX_PAGE_LIMIT
+        )));
+    }
+
+    let session = state
+        .session_manager
+        .get(&conversation_id)
+        .await
+        .map_err(|e| NativeErrorResponse::internal(format!("Session lookup failed: {}", e)))?
+        .filter(|session| session.external_id == user.external_id)
+        .ok_or_else(|| NativeErrorResponse::session_not_found(&conversation_id))?;
+
+    let history = if state.session_manager.history_enabled() {
+        let stored = state
+            .session_manager
+            .history(&conversation_id)
+            .await
+            .map_err(|e| NativeErrorResponse::internal(format!("History lookup failed: {}", e)))?
+            .unwrap_or(SessionHistory {
+                messages: Vec::new(),
+                truncated: false,
+            });
+        Some(page(stored, query.after, limit))
+    } else {
+        None
+    };
+
+    info!(
+        conversation_id = %conversation_id,
+        messages = history.as_ref().map_or(0, |page| page.messages.len()),
+        external_id = %user.log_id(),
+        "Session exported"
+    );
+    Ok(Json(SessionExport {
+        conversation_id,
+        tier: session.tier,
+        model: session.model,
+        created_at: session.created_at,
+        last_used_at: session.last_used_at.unwrap_or(session.created_at),
+        usage: session.usage,
+        history,
+    }))
+}
+
+/// Up to `limit` messages following index `after`
+fn page(history: SessionHistory, after: Option<usize>, limit: usize) -> SessionHistoryPage {
+    let total_messages = history.messages.len();
+    let first_index = after
+        .map_or(0, |after| after.saturating_add(1))
+        .min(total_messages);
+    let messages: Vec<_> = history
+        .messages
+        .into_iter()
+        .skip(first_index)
+        .take(limit)
+        .collect();
+    let last_index = first_index + messages.len();
+    SessionHistoryPage {
+        next_after: (last_index < total_messages).then(|| last_index - 1),
+        messages,
+        first_index,
+        total_messages,
+        truncated: history.truncated,
+    }
+}
@@ -0,0 +1,196 @@
+//! Native API token counting
+//!
+//! `POST /native/v1/tokenize` counts tokens the way the proxy does for its own
+//! estimates (same `TokenCounter`, per-message overhead and image costs), so
+//! clients can trim prompts to fit a budget without guessing. With
+//! `max_tokens` it also suggests which messages to drop (see
+//! [`plan_truncation`]). No provider call is made and no usage is tracked;
+//! the request counts once for rate limiting like any other.
+
+use std::sync::Arc;
+
+use axum::{extract::State, Extension, Json};
+use tracing::info;
+
+use crate::{
+    middleware::auth::AuthenticatedUser,
+    native::{
+        error::NativeErrorResponse,
+        request::TokenizeRequest,
+        response::{TokenizeResponse, TruncationSuggestion},
+        types::{Content, ContentPart, Message, Role},
+    },
+    routes::body::SentinelJson,
+    tokens::{counter::REPLY_PRIMING_TOKENS, truncation::plan_truncation},
+    AppState,
+};
+
+/// Path of the tokenize route inside the native router
+pub const TOKENIZE_PATH: &str = "/v1/tokenize";
+
+/// Count tokens for messages or text
+#[utoipa::path(
+    post,
+    path = "/native/v1/tokenize",
+    tag = "Tokens",
+    operation_id = "tokenize",
+    description = "Count prompt tokens with the tokenizer Sentinel uses for its own estimates.
+
+Send either `messages` (counted per message, plus the fixed per-request overhead) or `text`. The tokenizer is `model`'s, or that of the first model configured for `tier` (simple when neither is given).
+
+With `max_tokens`, `truncation` lists the messages to drop, oldest first, so the conversation fits. System messages and the last message are never dropped, and tool results go with the assistant turn they answer; `fits` is false when the budget can't be met even so.
+
+No provider is called and no usage is charged. The request counts as one request for rate limiting.",
+    request_body(
+        content = TokenizeRequest,
+        description = "Messages or text to count",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Token counts", body = TokenizeResponse),
+        (status = 400, description = "Invalid request - both or neither of messages and text, or both model and tier", body = NativeErrorResponse),
+        (status = 401, description = "Missing or invalid JWT in Authorization header"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 503, description = "Tier config unavailable for a tier lookup", body = NativeErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn native_tokenize(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    SentinelJson(request, _): SentinelJson<TokenizeRequest>,
+) -> Result<Json<TokenizeResponse>, NativeErrorResponse> {
+    let model = resolve_model(&state, &request).await?;
+
+    let response = match (request.messages, request.text) {
+        (Some(messages), None) => {
+            if messages.is_empty() {
+                return Err(NativeErrorResponse::validation(
+                    "messages must contain at least one message",
+                ));
+            }
+            let counts: Vec<u64> = messages
+                .iter()
+                .map(|message| message_tokens(&state, &model, message))
+                .collect();
+            let overhead = REPLY_PRIMING_TOKENS as u64;
+            let truncation = request.max_tokens.map(|max_tokens| {
+                let weighted: Vec<(Role, u64)> = messages
+                    .iter()
+                    .zip(&counts)
+                    .map(|(message, &tokens)| (message.role.clone(), tokens))
+                    .collect();
+                let plan = plan_truncation(&weighted, overhead, max_tokens);
+                TruncationSuggestion {
+                    max_tokens,
+                    drop: plan.drop,
+                    total_tokens: plan.total_tokens,
+                    fits: plan.fits,
+                }
+            });
+            TokenizeResponse {
+                total_tokens: overhead + counts.iter().sum::<u64>(),
+                message_tokens: Some(counts),
+                truncation,
+                model,
+            }
+        }
+        (None, Some(text)) => {
+            let total_tokens = state.token_counter.count_tokens(&model, &text).unwrap_or(0) as u64;
+            let truncation = request.max_tokens.map(|max_tokens| TruncationSuggestion {
+                max_tokens,
+                drop: Vec::new(),
+                total_tokens,
+                fits: total_tokens <= max_tokens,
+            });
+            TokenizeResponse {
+                total_tokens,
+                message_tokens: None,
+                truncation,
+                model,
+            }
+        }
+        _ => {
+            return Err(NativeErrorResponse::validation(
+                "Exactly one of messages and text is required",
+            ));
+        }
+    };
+
+    info!(
+        model = %response.model,
+        total_tokens = response.total_tokens,
+        messages = response.message_tokens.as_ref().map_or(0, Vec::len),
+        dropped = response.truncation.as_ref().map_or(0, |t| t.drop.len()),
+        external_id = %user.log_id(),
+        "Tokenize request completed"
+    );
+    Ok(Json(response))
+}
+
+/// Model whose tokenizer counts the request
+async fn resolve_model(
+    state: &AppState,
+    request: &TokenizeRequest,
+) -> Result<String, NativeErrorResponse> {
+    match (&request.model, request.tier) {
+        (Some(_), Some(_)) => Err(NativeErrorResponse::validation(
+            "Set either model or tier, not both",
+        )),
+        (Some(model), None) => Ok(model.clone()),
+        (None, tier) => {
+            let tier = tier.unwrap_or_default();
+            let config = state.tier_config_cache.get_config().await.map_err(|e| {
+                NativeErrorResponse::service_unavailable(format!("Tier config unavailable: {}", e))
+            })?;
+            config
+                .models_for_tier(tier)
+                .first()
+                .map(|config| config.model.clone())
+                .ok_or_else(|| {
+                    NativeErrorResponse::service_unavailable(format!(
+                        "No models configured for tier {}",
+                        tier
+                    ))
+                })
+        }
+    }
+}
+
+/// Prompt tokens of one message: its text, role and name, plus any images
+fn message_tokens(state: &AppState, model: &str, message: &Message) -> u64 {
+    let role = match message.role {
+        Role::System => "system",
+        Role::User => "user",
+        Role::Assistant => "assistant",
+        Role::Tool => "tool",
+    };
+    let text = state
+        .token_counter
+        .count_message_tokens(
+            model,
+            role,
+            &message.content.as_text(),
+            message.name.as_deref(),
+        )
+        .unwrap_or(0) as u64;
+    let images: u64 = match &message.content {
+        Content::Parts(parts) => parts
+            .iter()
+            .filter_map(|part| match part {
+                ContentPart::ImageUrl { image_url } => {
+                    Some(state.token_counter.count_image_tokens(
+                        &image_url.url,
+                        image_url.detail.unwrap_or_default(),
+                        state.config.usage.image_default_tokens,
+                    ))
+                }
+                ContentPart::Text { .. } => None,
+            })
+            .sum(),
+        Content::Text(_) => 0,
+    };
+    text + images
+}
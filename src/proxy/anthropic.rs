@@ -0,0 +1,909 @@
+//! Anthropic provider implementation
+//!
+//! Serves the OpenAI-format requests the rest of the proxy builds from
+//! Anthropic's Messages API (`AI_PROVIDER=anthropic`). Requests and responses
+//! go through [`AnthropicTranslator`]; streamed Messages events are rewritten
+//! to `chat.completion.chunk`s, and Anthropic error bodies to the OpenAI error
+//! shape, so routes, usage tracking and clients see the same payloads as with
+//! [`super::OpenAIProvider`].
+
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use axum::body::Body;
+use axum::http::{header, HeaderValue, Method, Response, StatusCode};
+use bytes::Bytes;
+use futures::StreamExt;
+use http_body_util::BodyExt;
+use reqwest::header::{HeaderMap, CONTENT_TYPE};
+use serde_json::{json, Map, Value};
+use tracing::{debug, instrument};
+
+use crate::config::Config;
+use crate::error::{AppError, AppResult};
+use crate::native::response::{
+    Delta, StreamChoice, StreamChunk, ToolCallDelta, ToolCallFunctionDelta, Usage,
+};
+use crate::native::translate::{
+    AnthropicTranslator, MessageTranslator, ParamOutOfRange, TranslationError,
+};
+use crate::native::types::Role;
+use crate::native::ChatCompletionRequest;
+use crate::proxy::capture::{self, UpstreamHeaders};
+use crate::proxy::headers::{filter_response_headers, ResponseHeaderLimits};
+use crate::proxy::logging::RequestContext;
+use crate::proxy::openai::leased_stream;
+use crate::proxy::pool::{self, ProviderClients, UpstreamPool};
+use crate::proxy::provider::{AiProvider, ByteStream};
+use crate::proxy::redact;
+use crate::streaming::SseLineBuffer;
+
+/// Messages API version sent in `anthropic-version`
+pub const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// Anthropic Messages API provider
+///
+/// Only chat completions and models are available; the other OpenAI
+/// endpoints have no Anthropic equivalent and are refused with a 400.
+pub struct AnthropicProvider {
+    clients: ProviderClients,
+    base_url: String,
+    api_key: String,
+    param_mode: ParamOutOfRange,
+    /// Largest upstream SSE line accepted (`SSE_MAX_LINE_BYTES`)
+    sse_max_line_bytes: usize,
+    /// Upstream response headers kept for logs (`UPSTREAM_CAPTURE_HEADERS`)
+    capture_headers: Vec<String>,
+    /// Limits on the upstream headers forwarded by `forward_raw`
+    response_header_limits: ResponseHeaderLimits,
+}
+
+impl AnthropicProvider {
+    /// Create a new Anthropic provider
+    ///
+    /// Uses the same streaming and short-call clients as the OpenAI provider
+    /// (see [`pool`]).
+    ///
+    /// # Panics
+    ///
+    /// Panics if ANTHROPIC_API_KEY is not configured.
+    pub fn new(clients: impl Into<ProviderClients>, config: &Config) -> Self {
+        let api_key = config
+            .provider
+            .anthropic_api_key
+            .clone()
+            .expect("ANTHROPIC_API_KEY must be configured");
+
+        Self {
+            clients: clients.into(),
+            base_url: config.provider.anthropic_api_url.clone(),
+            api_key,
+            param_mode: config.provider.param_out_of_range,
+            sse_max_line_bytes: config.provider.sse_max_line_bytes,
+            capture_headers: config.provider.upstream_capture_headers.clone(),
+            response_header_limits: ResponseHeaderLimits::from_config(&config.provider),
+        }
+    }
+
+    /// Headers sent with every request
+    fn default_headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Ok(mut api_key) = HeaderValue::from_str(&self.api_key) {
+            api_key.set_sensitive(true);
+            headers.insert("x-api-key", api_key);
+        }
+        headers.insert(
+            "anthropic-version",
+            HeaderValue::from_static(ANTHROPIC_VERSION),
+        );
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        headers
+    }
+
+    /// Send a request through the client of `pool`
+    ///
+    /// The response's allow-listed headers are captured into `ctx` and
+    /// published to any enclosing [`capture::capture`] scope.
+    async fn send(
+        &self,
+        method: reqwest::Method,
+        url: &str,
+        body: Option<Bytes>,
+        pool: UpstreamPool,
+        ctx: &RequestContext,
+    ) -> AppResult<reqwest::Response> {
+        let headers = self.default_headers();
+        ctx.log_headers_prepared(headers.len());
+        ctx.log_upstream_request(url, body.as_ref().map(Bytes::len));
+
+        let mut request = self
+            .clients
+            .client(pool)
+            .request(method, url)
+            .headers(headers);
+        if let Some(body) = body {
+            request = request.body(body);
+        }
+        let response = request
+            .send()
+            .await
+            .inspect_err(|e| ctx.log_connection_error(&e.to_string(), url))?;
+
+        let upstream = UpstreamHeaders::from_response(response.headers(), &self.capture_headers);
+        capture::publish(&upstream);
+        ctx.record_upstream_headers(upstream);
+        Ok(response)
+    }
+
+    /// Fail with an `UpstreamError` carrying the OpenAI-style error body
+    async fn check_status(
+        response: reqwest::Response,
+        ctx: &RequestContext,
+    ) -> AppResult<reqwest::Response> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let text = redact::redact(&response.text().await.unwrap_or_default()).into_owned();
+        ctx.log_error(&format!("Anthropic error {}: {}", status, text));
+        Err(AppError::UpstreamError(format!(
+            "Anthropic error {}: {}",
+            status,
+            openai_error_body(status.as_u16(), &text)
+        )))
+    }
+
+    /// Read a successful response as JSON
+    async fn json(response: reqwest::Response, ctx: &RequestContext) -> AppResult<Value> {
+        let body_text = response.text().await?;
+        debug!(
+            trace_id = %ctx.trace_id,
+            body_len = body_text.len(),
+            "Response body received"
+        );
+        serde_json::from_str(&body_text).map_err(|e| {
+            ctx.log_parse_failure(&e.to_string(), &body_text);
+            AppError::UpstreamError(format!("Failed to parse response: {}", e))
+        })
+    }
+
+    /// Translate an OpenAI chat request into a Messages request
+    fn messages_request(&self, request: &Value, stream: bool) -> AppResult<Value> {
+        let model = request
+            .get("model")
+            .and_then(Value::as_str)
+            .ok_or_else(|| AppError::BadRequest("model is required".to_string()))?;
+        let native = native_request(request)?;
+        let mut body = AnthropicTranslator::new()
+            .with_param_mode(self.param_mode)
+            .translate_request(&native)
+            .map_err(translation_error)?;
+        body["model"] = json!(model);
+        if stream {
+            body["stream"] = json!(true);
+        }
+        Ok(body)
+    }
+
+    /// GET a models endpoint
+    async fn get(&self, endpoint: &str, ctx: &RequestContext) -> AppResult<Value> {
+        let url = format!("{}{}", self.base_url, endpoint);
+        let _lease = self.clients.lease(UpstreamPool::Short);
+        let response = self
+            .send(reqwest::Method::GET, &url, None, UpstreamPool::Short, ctx)
+            .await?;
+        ctx.log_upstream_response(response.status().as_u16(), response.content_length());
+
+        let response = Self::check_status(response, ctx).await?;
+        let result = Self::json(response, ctx).await?;
+        ctx.log_request_complete(None);
+        Ok(result)
+    }
+}
+
+/// The native request equivalent to an OpenAI chat request
+///
+/// Fields the Messages API has no use for (`n`, `logprobs`, ...) are dropped;
+/// `max_completion_tokens` takes precedence over `max_tokens`.
+fn native_request(request: &Value) -> AppResult<ChatCompletionRequest> {
+    let mut native = Map::new();
+    for field in [
+        "messages",
+        "temperature",
+        "top_p",
+        "stop",
+        "seed",
+        "tools",
+        "tool_choice",
+        "user",
+    ] {
+        if let Some(value) = request.get(field).filter(|value| !value.is_null()) {
+            native.insert(field.to_string(), value.clone());
+        }
+    }
+    if let Some(max_tokens) = ["max_completion_tokens", "max_tokens"]
+        .iter()
+        .find_map(|field| request.get(*field).filter(|value| !value.is_null()))
+    {
+        native.insert("max_tokens".to_string(), max_tokens.clone());
+    }
+
+    // Assistant messages with only tool calls have null content
+    if let Some(messages) = native.get_mut("messages").and_then(Value::as_array_mut) {
+        for message in messages.iter_mut().filter_map(Value::as_object_mut) {
+            if message.get("content").is_none_or(Value::is_null) {
+                message.insert("content".to_string(), json!(""));
+            }
+        }
+    }
+    // Descriptions are optional for OpenAI, parameters default to no arguments
+    if let Some(tools) = native.get_mut("tools").and_then(Value::as_array_mut) {
+        for function in tools.iter_mut().filter_map(|tool| tool.get_mut("function")) {
+            if let Some(function) = function.as_object_mut() {
+                function.entry("description").or_insert_with(|| json!(""));
+                function
+                    .entry("parameters")
+                    .or_insert_with(|| json!({"type": "object", "properties": {}}));
+            }
+        }
+    }
+
+    serde_json::from_value(Value::Object(native))
+        .map_err(|e| AppError::BadRequest(format!("Unsupported request for Anthropic: {}", e)))
+}
+
+/// Client error for a request the translator refused
+fn translation_error(error: TranslationError) -> AppError {
+    AppError::BadRequest(format!("Unsupported request for Anthropic: {}", error))
+}
+
+/// Translate a Messages response into an OpenAI chat completion
+///
+/// Tool calls keep Anthropic's ids, which are sent back unchanged with the
+/// results, and their arguments become JSON text as in OpenAI responses.
+fn chat_completion(response: Value) -> AppResult<Value> {
+    let (mut completion, mapping) = AnthropicTranslator::new()
+        .translate_response(response)
+        .map_err(|e| AppError::UpstreamError(format!("Failed to parse response: {}", e)))?;
+    for choice in &mut completion.choices {
+        for call in choice.message.tool_calls.iter_mut().flatten() {
+            if let Some(provider_id) = mapping.get_provider_id(&call.id) {
+                call.id = provider_id.clone();
+            }
+            call.function.arguments = json!(call.function.arguments.to_string());
+        }
+    }
+    Ok(serde_json::to_value(completion)?)
+}
+
+/// OpenAI-style `{"error": {...}}` body for an Anthropic error response
+///
+/// Anthropic answers `{"type": "error", "error": {"type", "message"}}`; its
+/// error type is kept as the `code`. Bodies that aren't Anthropic errors are
+/// passed on as the message.
+pub fn openai_error_body(status: u16, body: &str) -> Value {
+    let parsed: Option<Value> = serde_json::from_str(body).ok();
+    let error = parsed.as_ref().and_then(|body| body.get("error"));
+    let kind = error
+        .and_then(|error| error.get("type"))
+        .and_then(Value::as_str);
+    let message = error
+        .and_then(|error| error.get("message"))
+        .and_then(Value::as_str)
+        .unwrap_or(body);
+
+    let error_type = match (kind, status) {
+        (Some("rate_limit_error"), _) | (None, 429) => "rate_limit_error",
+        (Some("authentication_error"), _) | (None, 401) => "authentication_error",
+        (Some("permission_error"), _) | (None, 403) => "permission_error",
+        (Some("not_found_error"), _) | (None, 404) => "not_found_error",
+        (Some("api_error" | "overloaded_error"), _) => "server_error",
+        (None, status) if status >= 500 => "server_error",
+        _ => "invalid_request_error",
+    };
+    json!({
+        "error": {
+            "message": message,
+            "type": error_type,
+            "param": null,
+            "code": kind,
+        }
+    })
+}
+
+/// OpenAI model object for an Anthropic one
+fn openai_model(model: &Value) -> Value {
+    let created = model
+        .get("created_at")
+        .and_then(Value::as_str)
+        .and_then(|created| chrono::DateTime::parse_from_rfc3339(created).ok())
+        .map_or(0, |created| created.timestamp());
+    json!({
+        "id": model.get("id").cloned().unwrap_or_default(),
+        "object": "model",
+        "created": created,
+        "owned_by": "anthropic",
+    })
+}
+
+/// Rewrites a Messages event stream as OpenAI `chat.completion.chunk`s
+///
+/// Usage is sent in a final chunk without choices when the client asked for
+/// it with `stream_options.include_usage`, as OpenAI does.
+struct ChunkTranslator {
+    include_usage: bool,
+    id: String,
+    model: String,
+    created: u64,
+    prompt_tokens: u32,
+    completion_tokens: u32,
+    finish_reason: Option<String>,
+    /// Tool call index of each `tool_use` content block
+    tool_calls: HashMap<u64, u32>,
+    done: bool,
+}
+
+impl ChunkTranslator {
+    fn new(include_usage: bool) -> Self {
+        Self {
+            include_usage,
+            id: String::new(),
+            model: String::new(),
+            created: chrono::Utc::now().timestamp() as u64,
+            prompt_tokens: 0,
+            completion_tokens: 0,
+            finish_reason: None,
+            tool_calls: HashMap::new(),
+            done: false,
+        }
+    }
+
+    /// Translate the `data:` lines among `lines` into client SSE bytes
+    fn translate_lines(&mut self, lines: &[String]) -> Bytes {
+        let mut output = String::new();
+        for line in lines {
+            let Some(data) = line.strip_prefix("data:") else {
+                continue;
+            };
+            if self.done {
+                break;
+            }
+            match serde_json::from_str::<Value>(data.trim()) {
+                Ok(event) => {
+                    for data in self.translate_event(&event) {
+                        output.push_str(&format!("data: {}\n\n", data));
+                    }
+                }
+                Err(e) => debug!(error = %e, "Skipping unparseable Anthropic stream event"),
+            }
+        }
+        Bytes::from(output)
+    }
+
+    /// OpenAI stream payloads for one Messages event
+    fn translate_event(&mut self, event: &Value) -> Vec<String> {
+        let usage = |event: &Value, field: &str| {
+            event
+                .get("usage")
+                .and_then(|usage| usage.get(field))
+                .and_then(Value::as_u64)
+                .map(|tokens| tokens as u32)
+        };
+
+        match event.get("type").and_then(Value::as_str) {
+            Some("message_start") => {
+                let message = &event["message"];
+                self.id = message["id"].as_str().unwrap_or_default().to_string();
+                self.model = message["model"].as_str().unwrap_or_default().to_string();
+                self.prompt_tokens = usage(message, "input_tokens").unwrap_or(0);
+                self.completion_tokens = usage(message, "output_tokens").unwrap_or(0);
+                vec![self.chunk(Delta {
+                    role: Some(Role::Assistant),
+                    content: Some(String::new()),
+                    tool_calls: None,
+                })]
+            }
+            Some("content_block_start") => {
+                let block = &event["content_block"];
+                match block["type"].as_str() {
+                    Some("tool_use") => {
+                        let index = self.tool_calls.len() as u32;
+                        self.tool_calls
+                            .insert(event["index"].as_u64().unwrap_or_default(), index);
+                        vec![self.tool_call_chunk(ToolCallDelta {
+                            index,
+                            id: block["id"].as_str().map(str::to_string),
+                            call_type: Some("function".to_string()),
+                            function: Some(ToolCallFunctionDelta {
+                                name: block["name"].as_str().map(str::to_string),
+                                arguments: Some(String::new()),
+                            }),
+                        })]
+                    }
+                    Some("text") => block["text"]
+                        .as_str()
+                        .filter(|text| !text.is_empty())
+                        .map(|text| self.text_chunk(text))
+                        .into_iter()
+                        .collect(),
+                    _ => Vec::new(),
+                }
+            }
+            Some("content_block_delta") => {
+                let delta = &event["delta"];
+                match delta["type"].as_str() {
+                    Some("text_delta") => {
+                        vec![self.text_chunk(delta["text"].as_str().unwrap_or_default())]
+                    }
+                    Some("input_json_delta") => {
+                        let block = event["index"].as_u64().unwrap_or_default();
+                        let Some(&index) = self.tool_calls.get(&block) else {
+                            return Vec::new();
+                        };
+                        vec![self.tool_call_chunk(ToolCallDelta {
+                            index,
+                            id: None,
+                            call_type: None,
+                            function: Some(ToolCallFunctionDelta {
+                                name: None,
+                                arguments: delta["partial_json"].as_str().map(str::to_string),
+                            }),
+                        })]
+                    }
+                    _ => Vec::new(),
+                }
+            }
+            Some("message_delta") => {
+                if let Some(reason) = event["delta"]["stop_reason"].as_str() {
+                    self.finish_reason =
+                        Some(AnthropicTranslator::new().translate_stop_reason(reason));
+                }
+                if let Some(tokens) = usage(event, "input_tokens") {
+                    self.prompt_tokens = tokens;
+                }
+                if let Some(tokens) = usage(event, "output_tokens") {
+                    self.completion_tokens = tokens;
+                }
+                Vec::new()
+            }
+            Some("message_stop") => {
+                self.done = true;
+                let mut payloads = vec![self.finish_chunk()];
+                if self.include_usage {
+                    payloads.push(self.usage_chunk());
+                }
+                payloads.push("[DONE]".to_string());
+                payloads
+            }
+            Some("error") => {
+                self.done = true;
+                let status = match event["error"]["type"].as_str() {
+                    Some("rate_limit_error") => 429,
+                    Some("overloaded_error") => 529,
+                    _ => 500,
+                };
+                vec![openai_error_body(status, &redact::redact(&event.to_string())).to_string()]
+            }
+            // `ping` and block stops carry nothing for the client
+            _ => Vec::new(),
+        }
+    }
+
+    fn stream_chunk(&self, choices: Vec<StreamChoice>, usage: Option<Usage>) -> String {
+        let chunk = StreamChunk {
+            id: self.id.clone(),
+            object: "chat.completion.chunk".to_string(),
+            created: self.created,
+            model: self.model.clone(),
+            system_fingerprint: None,
+            choices,
+            usage,
+        };
+        serde_json::to_string(&chunk).unwrap_or_default()
+    }
+
+    fn chunk(&self, delta: Delta) -> String {
+        let choice = StreamChoice {
+            index: 0,
+            delta,
+            finish_reason: None,
+        };
+        self.stream_chunk(vec![choice], None)
+    }
+
+    fn text_chunk(&self, text: &str) -> String {
+        self.chunk(Delta {
+            content: Some(text.to_string()),
+            ..Default::default()
+        })
+    }
+
+    fn tool_call_chunk(&self, tool_call: ToolCallDelta) -> String {
+        self.chunk(Delta {
+            tool_calls: Some(vec![tool_call]),
+            ..Default::default()
+        })
+    }
+
+    fn finish_chunk(&self) -> String {
+        let choice = StreamChoice {
+            index: 0,
+            delta: Delta::default(),
+            finish_reason: Some(
+                self.finish_reason
+                    .clone()
+                    .unwrap_or_else(|| "stop".to_string()),
+            ),
+        };
+        self.stream_chunk(vec![choice], None)
+    }
+
+    fn usage_chunk(&self) -> String {
+        let usage = Usage {
+            prompt_tokens: self.prompt_tokens,
+            completion_tokens: self.completion_tokens,
+            total_tokens: self.prompt_tokens + self.completion_tokens,
+        };
+        self.stream_chunk(Vec::new(), Some(usage))
+    }
+}
+
+#[async_trait]
+impl AiProvider for AnthropicProvider {
+    fn name(&self) -> &'static str {
+        "anthropic"
+    }
+
+    #[instrument(
+        skip(self, request, _incoming_headers),
+        fields(provider = "anthropic", endpoint = "messages")
+    )]
+    async fn chat_completions(
+        &self,
+        request: Value,
+        _incoming_headers: &HeaderMap,
+    ) -> AppResult<Value> {
+        let ctx = RequestContext::new(self.name(), "/v1/chat/completions");
+        ctx.log_request_start();
+
+        let body = Bytes::from(serde_json::to_vec(
+            &self.messages_request(&request, false)?,
+        )?);
+        let url = format!("{}/messages", self.base_url);
+        let _lease = self.clients.lease(UpstreamPool::Short);
+        let response = self
+            .send(
+                reqwest::Method::POST,
+                &url,
+                Some(body),
+                UpstreamPool::Short,
+                &ctx,
+            )
+            .await?;
+        ctx.log_upstream_response(response.status().as_u16(), response.content_length());
+
+        let response = Self::check_status(response, &ctx).await?;
+        let result = chat_completion(Self::json(response, &ctx).await?)?;
+        ctx.log_request_complete(None);
+        Ok(result)
+    }
+
+    #[instrument(
+        skip(self, request, _incoming_headers),
+        fields(provider = "anthropic", endpoint = "messages", streaming = true)
+    )]
+    async fn chat_completions_stream(
+        &self,
+        request: Value,
+        _incoming_headers: &HeaderMap,
+    ) -> AppResult<ByteStream> {
+        let ctx = RequestContext::new(self.name(), "/v1/chat/completions").with_streaming(true);
+        ctx.log_request_start();
+
+        let include_usage = request["stream_options"]["include_usage"]
+            .as_bool()
+            .unwrap_or(false);
+        let body = Bytes::from(serde_json::to_vec(&self.messages_request(&request, true)?)?);
+        let url = format!("{}/messages", self.base_url);
+        let lease = self.clients.lease(UpstreamPool::Streaming);
+        let response = self
+            .send(
+                reqwest::Method::POST,
+                &url,
+                Some(body),
+                UpstreamPool::Streaming,
+                &ctx,
+            )
+            .await?;
+        ctx.log_upstream_response(response.status().as_u16(), None);
+
+        let response = Self::check_status(response, &ctx).await?;
+        ctx.log_stream_started();
+
+        let mut buffer = SseLineBuffer::with_max_line_bytes(self.sse_max_line_bytes);
+        let mut translator = ChunkTranslator::new(include_usage);
+        let stream = leased_stream(response, lease).map(move |chunk| {
+            let chunk = chunk?;
+            if translator.done {
+                return Ok(Bytes::new());
+            }
+            match buffer.feed(&chunk) {
+                Ok(lines) => Ok(translator.translate_lines(&lines)),
+                Err(e) => {
+                    translator.done = true;
+                    Ok(e.to_event())
+                }
+            }
+        });
+        Ok(Box::pin(stream))
+    }
+
+    async fn completions(
+        &self,
+        _request: Value,
+        _incoming_headers: &HeaderMap,
+    ) -> AppResult<Value> {
+        Err(unsupported("/v1/completions"))
+    }
+
+    async fn completions_stream(
+        &self,
+        _request: Value,
+        _incoming_headers: &HeaderMap,
+    ) -> AppResult<ByteStream> {
+        Err(unsupported("/v1/completions"))
+    }
+
+    async fn embeddings(&self, _request: Value, _incoming_headers: &HeaderMap) -> AppResult<Value> {
+        Err(unsupported("/v1/embeddings"))
+    }
+
+    #[instrument(skip(self), fields(provider = "anthropic", endpoint = "models"))]
+    async fn list_models(&self) -> AppResult<Value> {
+        let ctx = RequestContext::new(self.name(), "/v1/models");
+        ctx.log_request_start();
+        let models = self.get("/models", &ctx).await?;
+        let data: Vec<Value> = models["data"]
+            .as_array()
+            .map(|models| models.iter().map(openai_model).collect())
+            .unwrap_or_default();
+        Ok(json!({"object": "list", "data": data}))
+    }
+
+    #[instrument(skip(self), fields(provider = "anthropic", endpoint = "models"))]
+    async fn get_model(&self, model_id: &str) -> AppResult<Value> {
+        let ctx = RequestContext::new(self.name(), &format!("/v1/models/{}", model_id));
+        ctx.log_request_start();
+        let model = self.get(&format!("/models/{}", model_id), &ctx).await?;
+        Ok(openai_model(&model))
+    }
+
+    async fn responses(&self, _request: Value, _incoming_headers: &HeaderMap) -> AppResult<Value> {
+        Err(unsupported("/v1/responses"))
+    }
+
+    async fn responses_stream(
+        &self,
+        _request: Value,
+        _incoming_headers: &HeaderMap,
+    ) -> AppResult<ByteStream> {
+        Err(unsupported("/v1/responses"))
+    }
+
+    /// Forward a request to the same path under `ANTHROPIC_API_URL`
+    ///
+    /// Error bodies are rewritten to the OpenAI error shape.
+    #[instrument(skip(self, _incoming_headers, body), fields(provider = "anthropic", method = %method, path = %path))]
+    async fn forward_raw(
+        &self,
+        method: Method,
+        path: &str,
+        _incoming_headers: HeaderMap,
+        body: Body,
+    ) -> AppResult<Response<Body>> {
+        let ctx = RequestContext::new(self.name(), path);
+        ctx.log_request_start();
+
+        let url = format!("{}{}", self.base_url, path);
+        let body_bytes = body
+            .collect()
+            .await
+            .map_err(|e| {
+                ctx.log_error(&format!("Failed to read request body: {}", e));
+                AppError::Internal(anyhow::anyhow!("Failed to read request body: {}", e))
+            })?
+            .to_bytes();
+        let body = (method != Method::GET && method != Method::HEAD).then_some(body_bytes);
+        let upstream_pool =
+            UpstreamPool::for_request(body.as_deref().is_some_and(pool::requests_stream));
+        let lease = self.clients.lease(upstream_pool);
+        let response = self
+            .send(
+                reqwest::Method::from_bytes(method.as_str().as_bytes())
+                    .unwrap_or(reqwest::Method::POST),
+                &url,
+                body,
+                upstream_pool,
+                &ctx,
+            )
+            .await?;
+
+        let status = StatusCode::from_u16(response.status().as_u16())
+            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        ctx.log_upstream_response(status.as_u16(), response.content_length());
+        let mut headers = filter_response_headers(response.headers(), &self.response_header_limits);
+
+        let body = if status.is_client_error() || status.is_server_error() {
+            let text = redact::redact(&response.text().await.unwrap_or_default()).into_owned();
+            ctx.log_upstream_error_body(status.as_u16(), &text);
+            let error = openai_error_body(status.as_u16(), &text).to_string();
+            headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+            headers.insert(header::CONTENT_LENGTH, HeaderValue::from(error.len()));
+            Body::from(error)
+        } else {
+            Body::from_stream(leased_stream(response, lease))
+        };
+
+        let mut axum_response = Response::builder()
+            .status(status)
+            .body(body)
+            .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to build response: {}", e)))?;
+        *axum_response.headers_mut() = headers;
+        ctx.log_request_complete(None);
+        Ok(axum_response)
+    }
+}
+
+/// Client error for an endpoint Anthropic doesn't offer
+fn unsupported(endpoint: &str) -> AppError {
+    AppError::BadRequest(format!(
+        "{} is not supported by the Anthropic provider",
+        endpoint
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events(events: &[Value]) -> String {
+        let lines: Vec<String> = events
+            .iter()
+            .flat_map(|event| {
+                [
+                    format!("event: {}", event["type"].as_str().unwrap()),
+                    format!("data: {}", event),
+                ]
+            })
+            .collect();
+        let output = ChunkTranslator::new(true).translate_lines(&lines);
+        String::from_utf8(output.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_native_request_from_openai() {
+        let request = native_request(&json!({
+            "model": "claude-sonnet-4-5",
+            "messages": [
+                {"role": "user", "content": "Weather?"},
+                {"role": "assistant", "content": null, "tool_calls": [{
+                    "id": "toolu_1", "type": "function",
+                    "function": {"name": "get_weather", "arguments": "{\"city\":\"Paris\"}"}
+                }]},
+                {"role": "tool", "tool_call_id": "toolu_1", "content": "18C"}
+            ],
+            "max_tokens": 50,
+            "max_completion_tokens": 100,
+            "tools": [{"type": "function", "function": {"name": "get_weather", "strict": true}}],
+            "n": 1,
+            "stream_options": {"include_usage": true}
+        }))
+        .unwrap();
+
+        assert_eq!(request.max_tokens, Some(100));
+        assert_eq!(request.messages[1].content.as_text(), "");
+        let tool = &request.tools.as_ref().unwrap()[0].function;
+        assert_eq!(tool.description, "");
+        assert_eq!(tool.parameters["type"], "object");
+
+        let body = AnthropicTranslator::new()
+            .translate_request(&request)
+            .unwrap();
+        assert_eq!(
+            body["messages"][1]["content"][0]["input"],
+            json!({"city": "Paris"})
+        );
+        assert_eq!(body["messages"][2]["content"][0]["tool_use_id"], "toolu_1");
+    }
+
+    #[test]
+    fn test_chat_completion_keeps_tool_ids() {
+        let completion = chat_completion(json!({
+            "id": "msg_1",
+            "model": "claude-sonnet-4-5",
+            "content": [{"type": "tool_use", "id": "toolu_1", "name": "get_weather", "input": {"city": "Paris"}}],
+            "stop_reason": "tool_use",
+            "usage": {"input_tokens": 10, "output_tokens": 5}
+        }))
+        .unwrap();
+
+        let call = &completion["choices"][0]["message"]["tool_calls"][0];
+        assert_eq!(call["id"], "toolu_1");
+        assert_eq!(call["function"]["arguments"], "{\"city\":\"Paris\"}");
+        assert_eq!(completion["choices"][0]["finish_reason"], "tool_calls");
+        assert_eq!(completion["usage"]["total_tokens"], 15);
+    }
+
+    #[test]
+    fn test_openai_error_body() {
+        let body = r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#;
+        assert_eq!(
+            openai_error_body(529, body),
+            json!({"error": {
+                "message": "Overloaded", "type": "server_error", "param": null, "code": "overloaded_error"
+            }})
+        );
+        let error = openai_error_body(429, "slow down");
+        assert_eq!(error["error"]["type"], "rate_limit_error");
+        assert_eq!(error["error"]["message"], "slow down");
+        assert!(error["error"]["code"].is_null());
+    }
+
+    #[test]
+    fn test_stream_translation() {
+        let output = events(&[
+            json!({"type": "message_start", "message": {
+                "id": "msg_1", "model": "claude-sonnet-4-5", "usage": {"input_tokens": 12, "output_tokens": 1}
+            }}),
+            json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+            json!({"type": "ping"}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Hi"}}),
+            json!({"type": "content_block_start", "index": 1, "content_block": {
+                "type": "tool_use", "id": "toolu_1", "name": "get_weather", "input": {}
+            }}),
+            json!({"type": "content_block_delta", "index": 1, "delta": {
+                "type": "input_json_delta", "partial_json": "{\"city\":"
+            }}),
+            json!({"type": "message_delta", "delta": {"stop_reason": "tool_use"}, "usage": {"output_tokens": 7}}),
+            json!({"type": "message_stop"}),
+        ]);
+
+        let payloads: Vec<&str> = output
+            .split("\n\n")
+            .filter_map(|event| event.strip_prefix("data: "))
+            .collect();
+        assert_eq!(payloads.len(), 7);
+        let chunks: Vec<Value> = payloads[..6]
+            .iter()
+            .map(|payload| serde_json::from_str(payload).unwrap())
+            .collect();
+        assert_eq!(chunks[0]["choices"][0]["delta"]["role"], "assistant");
+        assert_eq!(chunks[0]["object"], "chat.completion.chunk");
+        assert_eq!(chunks[1]["choices"][0]["delta"]["content"], "Hi");
+        let tool_call = &chunks[2]["choices"][0]["delta"]["tool_calls"][0];
+        assert_eq!(tool_call["id"], "toolu_1");
+        assert_eq!(tool_call["function"]["name"], "get_weather");
+        assert_eq!(
+            chunks[3]["choices"][0]["delta"]["tool_calls"][0]["function"]["arguments"],
+            "{\"city\":"
+        );
+        assert_eq!(chunks[4]["choices"][0]["finish_reason"], "tool_calls");
+        assert_eq!(
+            chunks[5]["usage"],
+            json!({"prompt_tokens": 12, "completion_tokens": 7, "total_tokens": 19})
+        );
+        assert_eq!(payloads[6], "[DONE]");
+    }
+
+    #[test]
+    fn test_stream_error_event() {
+        let output = events(&[json!({
+            "type": "error",
+            "error": {"type": "overloaded_error", "message": "Overloaded"}
+        })]);
+        let error: Value =
+            serde_json::from_str(output.trim().strip_prefix("data: ").unwrap()).unwrap();
+        assert_eq!(error["error"]["type"], "server_error");
+        assert_eq!(error["error"]["message"], "Overloaded");
+    }
+}
@@ -0,0 +1,656 @@
+//! Upstream circuit breakers
+//!
+//! One breaker per (provider, endpoint), independent of the breaker guarding
+//! Zion in the batching usage tracker. Consecutive upstream 5xx responses,
+//! connection failures and timeouts open it; while open, requests fail fast
+//! with a 503 `upstream_unavailable` instead of waiting on a provider that is
+//! down. Once the reset period has elapsed the breaker is half-open and lets a
+//! few probe requests through: a success closes it, a failure opens it again.
+//!
+//! [`CircuitBreakingProvider`] applies the breakers to every call of a wrapped
+//! [`AiProvider`]. State changes are pushed to the [`ProviderHealthTracker`]
+//! (which `/health/ready` reports) and to the `sentinel_upstream_circuit_*`
+//! metrics.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use axum::body::Body;
+use axum::http::{HeaderMap, Method, Response};
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::clock::{system_clock, SharedClock};
+use crate::config::ProviderConfig;
+use crate::error::{AppError, AppResult};
+use crate::proxy::provider::{AiProvider, ByteStream};
+use crate::tiers::ProviderHealthTracker;
+
+/// Retry hint while a half-open breaker's probes are still in flight
+const PROBE_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// Circuit breaker state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+impl BreakerState {
+    /// Gauge value, matching `sentinel_usage_circuit_state` (0=closed, 1=half-open, 2=open)
+    fn gauge_value(self) -> u8 {
+        match self {
+            BreakerState::Closed => 0,
+            BreakerState::HalfOpen => 1,
+            BreakerState::Open => 2,
+        }
+    }
+}
+
+/// Configuration for the upstream circuit breakers
+#[derive(Debug, Clone)]
+pub struct BreakerConfig {
+    /// Number of consecutive failures before the circuit opens (0 = disabled)
+    pub circuit_breaker_threshold: u32,
+    /// Time to fail fast before probing the endpoint again
+    pub circuit_breaker_reset: Duration,
+    /// Probe requests let through at once while half-open
+    pub half_open_probes: u32,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        Self {
+            circuit_breaker_threshold: 5,
+            circuit_breaker_reset: Duration::from_secs(30),
+            half_open_probes: 1,
+        }
+    }
+}
+
+impl From<&ProviderConfig> for BreakerConfig {
+    fn from(config: &ProviderConfig) -> Self {
+        Self {
+            circuit_breaker_threshold: config.circuit_breaker_threshold,
+            circuit_breaker_reset: Duration::from_secs(config.circuit_breaker_reset_seconds),
+            half_open_probes: config.circuit_breaker_half_open_probes.max(1),
+        }
+    }
+}
+
+/// Breaker for one provider endpoint
+#[derive(Debug)]
+struct Breaker {
+    state: BreakerState,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    probes_in_flight: u32,
+}
+
+impl Default for Breaker {
+    fn default() -> Self {
+        Self {
+            state: BreakerState::Closed,
+            consecutive_failures: 0,
+            opened_at: None,
+            probes_in_flight: 0,
+        }
+    }
+}
+
+/// Permission to send one request, returned by [`UpstreamBreakers::admit`]
+///
+/// Report the outcome with [`Admission::finish`]. An admission dropped
+/// without an outcome (the caller gave up on the request) frees its probe
+/// slot without counting as a success or a failure.
+pub struct Admission<'a> {
+    breakers: &'a UpstreamBreakers,
+    provider: &'a str,
+    endpoint: &'a str,
+    probe: bool,
+    finished: bool,
+}
+
+impl Admission<'_> {
+    /// Record whether the upstream failed (5xx, connection error or timeout)
+    pub fn finish(mut self, failed: bool) {
+        self.finished = true;
+        self.breakers
+            .record(self.provider, self.endpoint, self.probe, failed);
+    }
+}
+
+impl Drop for Admission<'_> {
+    fn drop(&mut self) {
+        if !self.finished && self.probe {
+            self.breakers.release_probe(self.provider, self.endpoint);
+        }
+    }
+}
+
+/// Circuit breakers of all provider endpoints seen so far
+///
+/// State is kept in-memory per instance, like [`ProviderHealthTracker`].
+pub struct UpstreamBreakers {
+    breakers: Mutex<HashMap<(String, String), Breaker>>,
+    config: BreakerConfig,
+    health_tracker: Arc<ProviderHealthTracker>,
+    clock: SharedClock,
+}
+
+impl UpstreamBreakers {
+    /// Create breakers reporting their state to `health_tracker`
+    pub fn new(config: BreakerConfig, health_tracker: Arc<ProviderHealthTracker>) -> Self {
+        Self {
+            breakers: Mutex::new(HashMap::new()),
+            config,
+            health_tracker,
+            clock: system_clock(),
+        }
+    }
+
+    /// Use the given clock for reset timing
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Whether breakers are configured at all (threshold above 0)
+    pub fn is_enabled(&self) -> bool {
+        self.config.circuit_breaker_threshold > 0
+    }
+
+    /// Current state of a provider endpoint (closed if never seen)
+    pub fn state(&self, provider: &str, endpoint: &str) -> BreakerState {
+        self.breakers
+            .lock()
+            .unwrap()
+            .get(&(provider.to_string(), endpoint.to_string()))
+            .map_or(BreakerState::Closed, |breaker| breaker.state)
+    }
+
+    /// Admit a request to a provider endpoint, or fail fast
+    ///
+    /// An open breaker whose reset period has elapsed turns half-open and
+    /// admits up to `half_open_probes` requests at a time. Rejections carry
+    /// the time until the breaker will let requests through again.
+    pub fn admit<'a>(&'a self, provider: &'a str, endpoint: &'a str) -> AppResult<Admission<'a>> {
+        let mut breakers = self.breakers.lock().unwrap();
+        let breaker = breakers
+            .entry((provider.to_string(), endpoint.to_string()))
+            .or_default();
+
+        if breaker.state == BreakerState::Open {
+            let elapsed =
+                breaker
+                    .opened_at
+                    .map_or(self.config.circuit_breaker_reset, |opened_at| {
+                        self.clock
+                            .instant_now()
+                            .saturating_duration_since(opened_at)
+                    });
+            if elapsed < self.config.circuit_breaker_reset {
+                return Err(self.reject(
+                    provider,
+                    endpoint,
+                    self.config.circuit_breaker_reset - elapsed,
+                ));
+            }
+            info!(
+                provider = %provider,
+                endpoint = %endpoint,
+                "Upstream circuit half-open, probing"
+            );
+            self.transition(provider, endpoint, breaker, BreakerState::HalfOpen);
+        }
+
+        let probe = breaker.state == BreakerState::HalfOpen;
+        if probe {
+            if breaker.probes_in_flight >= self.config.half_open_probes {
+                return Err(self.reject(provider, endpoint, PROBE_RETRY_AFTER));
+            }
+            breaker.probes_in_flight += 1;
+        }
+
+        Ok(Admission {
+            breakers: self,
+            provider,
+            endpoint,
+            probe,
+            finished: false,
+        })
+    }
+
+    /// Count the outcome of an admitted request
+    fn record(&self, provider: &str, endpoint: &str, probe: bool, failed: bool) {
+        let mut breakers = self.breakers.lock().unwrap();
+        let breaker = breakers
+            .entry((provider.to_string(), endpoint.to_string()))
+            .or_default();
+        if probe {
+            breaker.probes_in_flight = breaker.probes_in_flight.saturating_sub(1);
+        }
+
+        if !failed {
+            breaker.consecutive_failures = 0;
+            if breaker.state != BreakerState::Closed {
+                info!(
+                    provider = %provider,
+                    endpoint = %endpoint,
+                    "Upstream circuit closing after successful probe"
+                );
+                breaker.opened_at = None;
+                self.transition(provider, endpoint, breaker, BreakerState::Closed);
+            }
+            return;
+        }
+
+        breaker.consecutive_failures += 1;
+        let trips = match breaker.state {
+            BreakerState::Closed => {
+                breaker.consecutive_failures >= self.config.circuit_breaker_threshold
+            }
+            BreakerState::HalfOpen => true,
+            // A request admitted before the circuit opened
+            BreakerState::Open => false,
+        };
+        if trips {
+            warn!(
+                provider = %provider,
+                endpoint = %endpoint,
+                consecutive_failures = breaker.consecutive_failures,
+                reset_seconds = self.config.circuit_breaker_reset.as_secs(),
+                "Upstream circuit opening due to consecutive failures"
+            );
+            breaker.opened_at = Some(self.clock.instant_now());
+            self.transition(provider, endpoint, breaker, BreakerState::Open);
+        }
+    }
+
+    /// Free the probe slot of a request that never reported an outcome
+    fn release_probe(&self, provider: &str, endpoint: &str) {
+        let mut breakers = self.breakers.lock().unwrap();
+        if let Some(breaker) = breakers.get_mut(&(provider.to_string(), endpoint.to_string())) {
+            breaker.probes_in_flight = breaker.probes_in_flight.saturating_sub(1);
+        }
+    }
+
+    fn transition(
+        &self,
+        provider: &str,
+        endpoint: &str,
+        breaker: &mut Breaker,
+        state: BreakerState,
+    ) {
+        breaker.state = state;
+        let retry_at = breaker
+            .opened_at
+            .filter(|_| state == BreakerState::Open)
+            .map(|opened_at| opened_at + self.config.circuit_breaker_reset);
+        self.health_tracker
+            .record_breaker_state(provider, endpoint, state, retry_at);
+        metrics::set_circuit_state(provider, endpoint, state);
+    }
+
+    fn reject(&self, provider: &str, endpoint: &str, retry_after: Duration) -> AppError {
+        metrics::record_rejected(provider, endpoint);
+        AppError::UpstreamUnavailable {
+            provider: provider.to_string(),
+            endpoint: endpoint.to_string(),
+            retry_after,
+        }
+    }
+}
+
+/// Whether an upstream error counts against the endpoint's breaker
+///
+/// Server errors (5xx), connection failures and timeouts do; client errors
+/// and unparseable bodies mean the provider is answering.
+pub fn is_breaker_failure(error: &AppError) -> bool {
+    match error {
+        AppError::UpstreamError(message) => {
+            upstream_status(message).is_some_and(|status| (500..600).contains(&status))
+        }
+        AppError::HttpError(_) | AppError::UpstreamTimeout { .. } => true,
+        _ => false,
+    }
+}
+
+/// Status code in an upstream error message (`"OpenAI error 503 ..."`)
+pub(crate) fn upstream_status(message: &str) -> Option<u16> {
+    let (_, rest) = message.split_once("error ")?;
+    rest.get(..3)?.parse().ok()
+}
+
+/// Breaker key for a pass-through path: its first segment (`/files/file-1` -> `files`)
+fn raw_endpoint(path: &str) -> &str {
+    path.trim_start_matches('/')
+        .split(['/', '?'])
+        .next()
+        .unwrap_or_default()
+}
+
+/// An [`AiProvider`] whose calls go through the upstream circuit breakers
+///
+/// Streaming and non-streaming calls to the same upstream endpoint share a
+/// breaker. A stream counts as a success once it has opened.
+pub struct CircuitBreakingProvider {
+    inner: Arc<dyn AiProvider>,
+    breakers: Arc<UpstreamBreakers>,
+}
+
+impl CircuitBreakingProvider {
+    pub fn new(inner: Arc<dyn AiProvider>, breakers: Arc<UpstreamBreakers>) -> Self {
+        Self { inner, breakers }
+    }
+
+    async fn guarded<T>(
+        &self,
+        endpoint: &str,
+        call: impl Future<Output = AppResult<T>>,
+    ) -> AppResult<T> {
+        let admission = self.breakers.admit(self.inner.name(), endpoint)?;
+        let result = call.await;
+        admission.finish(result.as_ref().is_err_and(is_breaker_failure));
+        result
+    }
+}
+
+#[async_trait]
+impl AiProvider for CircuitBreakingProvider {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    async fn chat_completions(
+        &self,
+        request: serde_json::Value,
+        incoming_headers: &HeaderMap,
+    ) -> AppResult<serde_json::Value> {
+        self.guarded(
+            "chat/completions",
+            self.inner.chat_completions(request, incoming_headers),
+        )
+        .await
+    }
+
+    async fn chat_completions_stream(
+        &self,
+        request: serde_json::Value,
+        incoming_headers: &HeaderMap,
+    ) -> AppResult<ByteStream> {
+        self.guarded(
+            "chat/completions",
+            self.inner
+                .chat_completions_stream(request, incoming_headers),
+        )
+        .await
+    }
+
+    async fn completions(
+        &self,
+        request: serde_json::Value,
+        incoming_headers: &HeaderMap,
+    ) -> AppResult<serde_json::Value> {
+        self.guarded(
+            "completions",
+            self.inner.completions(request, incoming_headers),
+        )
+        .await
+    }
+
+    async fn completions_stream(
+        &self,
+        request: serde_json::Value,
+        incoming_headers: &HeaderMap,
+    ) -> AppResult<ByteStream> {
+        self.guarded(
+            "completions",
+            self.inner.completions_stream(request, incoming_headers),
+        )
+        .await
+    }
+
+    async fn embeddings(
+        &self,
+        request: serde_json::Value,
+        incoming_headers: &HeaderMap,
+    ) -> AppResult<serde_json::Value> {
+        self.guarded(
+            "embeddings",
+            self.inner.embeddings(request, incoming_headers),
+        )
+        .await
+    }
+
+    async fn list_models(&self) -> AppResult<serde_json::Value> {
+        self.guarded("models", self.inner.list_models()).await
+    }
+
+    async fn get_model(&self, model_id: &str) -> AppResult<serde_json::Value> {
+        self.guarded("models", self.inner.get_model(model_id)).await
+    }
+
+    async fn responses(
+        &self,
+        request: serde_json::Value,
+        incoming_headers: &HeaderMap,
+    ) -> AppResult<serde_json::Value> {
+        self.guarded("responses", self.inner.responses(request, incoming_headers))
+            .await
+    }
+
+    async fn responses_stream(
+        &self,
+        request: serde_json::Value,
+        incoming_headers: &HeaderMap,
+    ) -> AppResult<ByteStream> {
+        self.guarded(
+            "responses",
+            self.inner.responses_stream(request, incoming_headers),
+        )
+        .await
+    }
+
+    async fn forward_raw(
+        &self,
+        method: Method,
+        path: &str,
+        incoming_headers: HeaderMap,
+        body: Body,
+    ) -> AppResult<Response<Body>> {
+        // Error statuses come back as responses here, not as errors
+        let admission = self.breakers.admit(self.inner.name(), raw_endpoint(path))?;
+        let result = self
+            .inner
+            .forward_raw(method, path, incoming_headers, body)
+            .await;
+        let failed = match &result {
+            Ok(response) => response.status().is_server_error(),
+            Err(e) => is_breaker_failure(e),
+        };
+        admission.finish(failed);
+        result
+    }
+}
+
+/// Metrics for the upstream circuit breakers
+pub mod metrics {
+    use metrics::{counter, gauge};
+
+    use super::BreakerState;
+
+    /// Set a provider endpoint's circuit state (0=closed, 1=half-open, 2=open)
+    pub fn set_circuit_state(provider: &str, endpoint: &str, state: BreakerState) {
+        gauge!(
+            "sentinel_upstream_circuit_state",
+            "provider" => provider.to_string(),
+            "endpoint" => endpoint.to_string()
+        )
+        .set(state.gauge_value() as f64);
+    }
+
+    /// Record a request failed fast by an open circuit
+    pub fn record_rejected(provider: &str, endpoint: &str) {
+        counter!(
+            "sentinel_upstream_circuit_rejected_total",
+            "provider" => provider.to_string(),
+            "endpoint" => endpoint.to_string()
+        )
+        .increment(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::TestClock;
+    use crate::testing::{MockAiProvider, MockEndpoint, MockReply};
+
+    fn breakers(clock: &Arc<TestClock>) -> (UpstreamBreakers, Arc<ProviderHealthTracker>) {
+        let health_tracker = Arc::new(ProviderHealthTracker::new().with_clock(clock.clone()));
+        let config = BreakerConfig {
+            circuit_breaker_threshold: 3,
+            circuit_breaker_reset: Duration::from_secs(30),
+            half_open_probes: 1,
+        };
+        let breakers =
+            UpstreamBreakers::new(config, health_tracker.clone()).with_clock(clock.clone());
+        (breakers, health_tracker)
+    }
+
+    fn fail(breakers: &UpstreamBreakers, times: usize) {
+        for _ in 0..times {
+            breakers
+                .admit("openai", "chat/completions")
+                .unwrap()
+                .finish(true);
+        }
+    }
+
+    #[test]
+    fn test_opens_after_consecutive_failures() {
+        let clock = TestClock::new(0);
+        let (breakers, _) = breakers(&clock);
+
+        fail(&breakers, 2);
+        // A success in between resets the count
+        breakers
+            .admit("openai", "chat/completions")
+            .unwrap()
+            .finish(false);
+        fail(&breakers, 2);
+        assert_eq!(
+            breakers.state("openai", "chat/completions"),
+            BreakerState::Closed
+        );
+
+        fail(&breakers, 1);
+        assert_eq!(
+            breakers.state("openai", "chat/completions"),
+            BreakerState::Open
+        );
+        // Other endpoints are unaffected
+        assert!(breakers.admit("openai", "embeddings").is_ok());
+
+        clock.advance(Duration::from_secs(10));
+        let Err(AppError::UpstreamUnavailable { retry_after, .. }) =
+            breakers.admit("openai", "chat/completions")
+        else {
+            panic!("expected a fast failure");
+        };
+        assert_eq!(retry_after, Duration::from_secs(20));
+    }
+
+    #[test]
+    fn test_half_open_probe_closes_or_reopens() {
+        let clock = TestClock::new(0);
+        let (breakers, health_tracker) = breakers(&clock);
+        fail(&breakers, 3);
+        assert_eq!(health_tracker.tripped_endpoints().len(), 1);
+
+        clock.advance(Duration::from_secs(30));
+        let probe = breakers.admit("openai", "chat/completions").unwrap();
+        assert_eq!(
+            breakers.state("openai", "chat/completions"),
+            BreakerState::HalfOpen
+        );
+        // Only one probe at a time
+        assert!(breakers.admit("openai", "chat/completions").is_err());
+        probe.finish(true);
+        assert_eq!(
+            breakers.state("openai", "chat/completions"),
+            BreakerState::Open
+        );
+
+        clock.advance(Duration::from_secs(30));
+        breakers
+            .admit("openai", "chat/completions")
+            .unwrap()
+            .finish(false);
+        assert_eq!(
+            breakers.state("openai", "chat/completions"),
+            BreakerState::Closed
+        );
+        assert!(health_tracker.tripped_endpoints().is_empty());
+    }
+
+    #[test]
+    fn test_abandoned_probe_frees_its_slot() {
+        let clock = TestClock::new(0);
+        let (breakers, _) = breakers(&clock);
+        fail(&breakers, 3);
+        clock.advance(Duration::from_secs(30));
+
+        drop(breakers.admit("openai", "chat/completions").unwrap());
+        assert_eq!(
+            breakers.state("openai", "chat/completions"),
+            BreakerState::HalfOpen
+        );
+        assert!(breakers.admit("openai", "chat/completions").is_ok());
+    }
+
+    #[test]
+    fn test_is_breaker_failure() {
+        assert!(is_breaker_failure(&AppError::UpstreamError(
+            "OpenAI error 503 Service Unavailable: overloaded".to_string()
+        )));
+        assert!(is_breaker_failure(&AppError::UpstreamTimeout {
+            timeout_ms: 100
+        }));
+        assert!(!is_breaker_failure(&AppError::UpstreamError(
+            "OpenAI error 400 Bad Request: context_length_exceeded".to_string()
+        )));
+        assert!(!is_breaker_failure(&AppError::UpstreamError(
+            "Failed to parse response: EOF".to_string()
+        )));
+        assert_eq!(raw_endpoint("/files/file-1?purpose=x"), "files");
+    }
+
+    #[tokio::test]
+    async fn test_provider_fails_fast_without_calling_upstream() {
+        let clock = TestClock::new(0);
+        let (breakers, _) = breakers(&clock);
+        let mock = Arc::new(MockAiProvider::new().with_reply(
+            MockEndpoint::ChatCompletions,
+            MockReply::Error {
+                status: 500,
+                message: "boom".to_string(),
+            },
+        ));
+        let provider = CircuitBreakingProvider::new(mock.clone(), Arc::new(breakers));
+
+        for _ in 0..5 {
+            let _ = provider
+                .chat_completions(serde_json::json!({}), &HeaderMap::new())
+                .await;
+        }
+        assert_eq!(mock.requests_for(MockEndpoint::ChatCompletions).len(), 3);
+        assert_eq!(provider.name(), "mock");
+    }
+}
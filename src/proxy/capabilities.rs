@@ -0,0 +1,336 @@
+//! Startup provider capability check
+//!
+//! A wrong `OPENAI_API_URL` or a gateway key without access to the tier
+//! config's models otherwise only shows up once traffic arrives. With
+//! `STARTUP_PROVIDER_CHECK=warn|fail` Sentinel calls `/models` on each
+//! configured provider at startup, verifies that auth works and that every
+//! model referenced in the tier config is listed, and logs the problems
+//! (`warn`) or refuses to start (`fail`). The latest report is served at
+//! `GET /admin/providers/status`.
+//!
+//! A tier config that can't be fetched from Zion is reported but never fails
+//! startup; that is a Zion outage, not provider misconfiguration.
+//!
+//! With `MODELS_SOURCE=static` the tier config is checked against
+//! `STATIC_MODELS_JSON` and the provider isn't called; with `merged` the
+//! static models count as listed next to the provider's.
+
+use std::collections::BTreeSet;
+use std::str::FromStr;
+use std::sync::RwLock;
+
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::error::AppError;
+use crate::proxy::static_models::{static_list, ModelsSource, StaticModels};
+use crate::proxy::AiProvider;
+use crate::AppState;
+
+/// What to do with the startup capability check (`STARTUP_PROVIDER_CHECK`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderCheckMode {
+    /// Don't probe providers at startup
+    #[default]
+    Off,
+    /// Probe and log problems, but start anyway
+    Warn,
+    /// Probe and refuse to start on auth failures or missing models
+    Fail,
+}
+
+impl FromStr for ProviderCheckMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "off" => Ok(ProviderCheckMode::Off),
+            "warn" => Ok(ProviderCheckMode::Warn),
+            "fail" => Ok(ProviderCheckMode::Fail),
+            other => Err(format!(
+                "unknown provider check mode '{}' (expected warn, fail or off)",
+                other
+            )),
+        }
+    }
+}
+
+/// Capability check result for a single provider
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ProviderCapabilities {
+    pub provider: String,
+    /// Whether `/models` answered at all
+    pub reachable: bool,
+    /// False when the provider rejected our credentials (401/403)
+    pub auth_ok: bool,
+    /// Number of models the provider listed
+    pub model_count: usize,
+    /// Tier config models the provider did not list
+    pub missing_models: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ProviderCapabilities {
+    /// Whether this provider can serve every configured model
+    pub fn is_healthy(&self) -> bool {
+        self.reachable && self.auth_ok && self.missing_models.is_empty()
+    }
+}
+
+/// Result of a capability check across all providers
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ProviderStatusReport {
+    pub mode: ProviderCheckMode,
+    /// When the check ran (RFC 3339)
+    pub checked_at: String,
+    /// True when every provider is reachable, authenticated and lists all models
+    pub ok: bool,
+    /// Version of the tier config the models were taken from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tier_config_version: Option<String>,
+    /// Why the tier config couldn't be read (models were not verified)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tier_config_error: Option<String>,
+    pub providers: Vec<ProviderCapabilities>,
+}
+
+impl ProviderStatusReport {
+    /// One line per problem, for logs and the startup error
+    pub fn problems(&self) -> Vec<String> {
+        self.providers
+            .iter()
+            .flat_map(|provider| {
+                let mut problems = Vec::new();
+                if !provider.auth_ok {
+                    problems.push(format!("{}: authentication failed", provider.provider));
+                } else if !provider.reachable {
+                    problems.push(format!(
+                        "{}: /models unavailable ({})",
+                        provider.provider,
+                        provider.error.as_deref().unwrap_or("unknown error")
+                    ));
+                }
+                if !provider.missing_models.is_empty() {
+                    problems.push(format!(
+                        "{}: missing models {}",
+                        provider.provider,
+                        provider.missing_models.join(", ")
+                    ));
+                }
+                problems
+            })
+            .collect()
+    }
+}
+
+/// Latest capability report, shared with the admin endpoint
+#[derive(Debug, Default)]
+pub struct ProviderStatus {
+    latest: RwLock<Option<ProviderStatusReport>>,
+}
+
+impl ProviderStatus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The most recent report, if a check has run
+    pub fn latest(&self) -> Option<ProviderStatusReport> {
+        self.latest.read().unwrap().clone()
+    }
+
+    fn record(&self, report: &ProviderStatusReport) {
+        *self.latest.write().unwrap() = Some(report.clone());
+    }
+}
+
+/// Whether an upstream error means the provider rejected our credentials
+fn is_auth_failure(error: &AppError) -> bool {
+    match error {
+        AppError::UpstreamError(message) => {
+            message.contains(" 401") || message.contains(" 403")
+        }
+        _ => false,
+    }
+}
+
+/// Model IDs in an OpenAI-style `/models` response
+fn listed_models(response: &serde_json::Value) -> BTreeSet<String> {
+    response["data"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|model| model["id"].as_str())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Probe one provider against the expected models
+///
+/// `configured` (the static list of `MODELS_SOURCE=merged`) counts as listed too.
+async fn probe_provider(
+    provider: &dyn AiProvider,
+    expected: &BTreeSet<String>,
+    configured: Option<&StaticModels>,
+) -> ProviderCapabilities {
+    let configured = configured.map(StaticModels::ids).unwrap_or_default();
+    match provider.list_models().await {
+        Ok(response) => {
+            let mut listed = listed_models(&response);
+            listed.extend(configured);
+            ProviderCapabilities {
+                provider: provider.name().to_string(),
+                reachable: true,
+                auth_ok: true,
+                model_count: listed.len(),
+                missing_models: expected.difference(&listed).cloned().collect(),
+                error: None,
+            }
+        }
+        Err(e) => ProviderCapabilities {
+            provider: provider.name().to_string(),
+            reachable: false,
+            auth_ok: !is_auth_failure(&e),
+            model_count: 0,
+            // Nothing could be verified
+            missing_models: Vec::new(),
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+/// Check the expected models against `STATIC_MODELS_JSON` alone (`MODELS_SOURCE=static`)
+fn check_static(
+    provider: &dyn AiProvider,
+    expected: &BTreeSet<String>,
+    configured: &StaticModels,
+) -> ProviderCapabilities {
+    let listed = configured.ids();
+    ProviderCapabilities {
+        provider: provider.name().to_string(),
+        reachable: true,
+        auth_ok: true,
+        model_count: listed.len(),
+        missing_models: expected.difference(&listed).cloned().collect(),
+        error: None,
+    }
+}
+
+/// Run the capability check and store the report
+///
+/// Runs regardless of the configured mode, so the admin endpoint can probe
+/// on demand.
+pub async fn check_providers(state: &AppState) -> ProviderStatusReport {
+    let (expected, tier_config_version, tier_config_error) =
+        match state.tier_config_cache.get_config().await {
+            Ok(config) => {
+                let models = [&config.tiers.simple, &config.tiers.moderate, &config.tiers.complex]
+                    .into_iter()
+                    .flatten()
+                    .map(|model| model.model.clone())
+                    .chain(
+                        config
+                            .long_context_models
+                            .iter()
+                            .flat_map(|models| [&models.simple, &models.moderate, &models.complex])
+                            .flatten()
+                            .cloned(),
+                    )
+                    .chain(
+                        config
+                            .embedding_models
+                            .iter()
+                            .flat_map(|models| [&models.simple, &models.moderate, &models.complex])
+                            .flatten()
+                            .cloned(),
+                    )
+                    .collect();
+                (models, Some(config.version), None)
+            }
+            Err(e) => (BTreeSet::new(), None, Some(e.to_string())),
+        };
+
+    let configured = static_list(&state.config.provider).ok().flatten();
+    let provider = state.ai_provider.as_ref();
+    let providers = vec![match (state.config.provider.models_source, configured) {
+        (ModelsSource::Static, Some(configured)) => check_static(provider, &expected, configured),
+        _ => probe_provider(provider, &expected, configured).await,
+    }];
+
+    let report = ProviderStatusReport {
+        mode: state.config.provider.startup_provider_check,
+        checked_at: chrono::Utc::now().to_rfc3339(),
+        ok: providers.iter().all(ProviderCapabilities::is_healthy),
+        tier_config_version,
+        tier_config_error,
+        providers,
+    };
+    state.provider_status.record(&report);
+    report
+}
+
+/// Startup check according to `STARTUP_PROVIDER_CHECK`
+///
+/// Returns an error only in `fail` mode, when a provider can't be reached,
+/// rejects our credentials, or lacks a configured model.
+pub async fn startup_check(state: &AppState) -> anyhow::Result<()> {
+    let mode = state.config.provider.startup_provider_check;
+    if mode == ProviderCheckMode::Off {
+        return Ok(());
+    }
+
+    let report = check_providers(state).await;
+    if let Some(error) = &report.tier_config_error {
+        warn!(error = %error, "Could not load tier config, configured models not verified");
+    }
+
+    let problems = report.problems();
+    if problems.is_empty() {
+        info!(
+            providers = report.providers.len(),
+            "Provider capability check passed"
+        );
+        return Ok(());
+    }
+
+    for problem in &problems {
+        warn!(problem = %problem, "Provider capability check problem");
+    }
+    if mode == ProviderCheckMode::Fail {
+        anyhow::bail!("Provider capability check failed: {}", problems.join("; "));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_provider_check_mode_parsing() {
+        assert_eq!("warn".parse::<ProviderCheckMode>().unwrap(), ProviderCheckMode::Warn);
+        assert_eq!(" FAIL ".parse::<ProviderCheckMode>().unwrap(), ProviderCheckMode::Fail);
+        assert_eq!("off".parse::<ProviderCheckMode>().unwrap(), ProviderCheckMode::Off);
+        assert!("strict".parse::<ProviderCheckMode>().is_err());
+    }
+
+    #[test]
+    fn test_auth_failure_detection() {
+        assert!(is_auth_failure(&AppError::UpstreamError(
+            "OpenAI error 401 Unauthorized: invalid key".to_string()
+        )));
+        assert!(!is_auth_failure(&AppError::UpstreamError(
+            "OpenAI error 404 Not Found: ".to_string()
+        )));
+    }
+
+    #[test]
+    fn test_listed_models() {
+        let listed = listed_models(&json!({"data": [{"id": "gpt-4o"}, {"id": "o3-mini"}]}));
+        assert_eq!(listed.into_iter().collect::<Vec<_>>(), vec!["gpt-4o", "o3-mini"]);
+        assert!(listed_models(&json!({"error": "nope"})).is_empty());
+    }
+}
@@ -0,0 +1,149 @@
+//! Upstream response header capture
+//!
+//! Providers keep an allow-listed subset of upstream response headers
+//! (`UPSTREAM_CAPTURE_HEADERS`) so slow or failed requests can be correlated
+//! with provider tickets. The `AiProvider` trait only returns bodies, so
+//! handlers wrap provider calls in [`capture`]; headers published by the
+//! provider inside that scope are handed back alongside the result. Headers
+//! arrive before the body, so this works for buffered and streaming calls.
+
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use axum::http::{HeaderMap, HeaderValue};
+
+/// Response header exposing the upstream request id to clients
+pub const UPSTREAM_REQUEST_ID_HEADER: &str = "x-upstream-request-id";
+
+/// Upstream header carrying the provider's request id
+const REQUEST_ID_SOURCE: &str = "x-request-id";
+
+tokio::task_local! {
+    static CAPTURED: Arc<Mutex<UpstreamHeaders>>;
+}
+
+/// Allow-listed upstream response headers, in allow-list order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UpstreamHeaders(Vec<(String, String)>);
+
+impl UpstreamHeaders {
+    /// Keep the headers named in `allow_list` (lowercase names)
+    pub fn from_response(headers: &HeaderMap, allow_list: &[String]) -> Self {
+        Self(
+            allow_list
+                .iter()
+                .filter_map(|name| {
+                    let value = headers.get(name.as_str())?.to_str().ok()?;
+                    Some((name.clone(), value.to_string()))
+                })
+                .collect(),
+        )
+    }
+
+    /// Value of a captured header
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(captured, _)| captured.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The provider's request id, if it was captured
+    pub fn request_id(&self) -> Option<&str> {
+        self.get(REQUEST_ID_SOURCE)
+    }
+
+    /// Whether nothing was captured
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Add `X-Upstream-Request-Id` to a response when the request id was captured
+    pub fn insert_request_id_header(&self, headers: &mut HeaderMap) {
+        if let Some(value) = self.request_id().and_then(|id| HeaderValue::from_str(id).ok()) {
+            headers.insert(UPSTREAM_REQUEST_ID_HEADER, value);
+        }
+    }
+}
+
+impl fmt::Display for UpstreamHeaders {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, (name, value)) in self.0.iter().enumerate() {
+            if idx > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}={}", name, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Run a provider call, returning its result and the headers it published
+///
+/// When the call makes several upstream requests (redirects, retries), the
+/// last published headers win.
+pub async fn capture<F: Future>(fut: F) -> (F::Output, UpstreamHeaders) {
+    let slot = Arc::new(Mutex::new(UpstreamHeaders::default()));
+    let output = CAPTURED.scope(slot.clone(), fut).await;
+    let headers = slot.lock().unwrap().clone();
+    (output, headers)
+}
+
+/// Publish captured headers to the enclosing [`capture`] scope, if any
+pub fn publish(headers: &UpstreamHeaders) {
+    let _ = CAPTURED.try_with(|slot| *slot.lock().unwrap() = headers.clone());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allow_list() -> Vec<String> {
+        vec!["x-request-id".to_string(), "openai-processing-ms".to_string()]
+    }
+
+    #[test]
+    fn test_only_allow_listed_headers_captured() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-request-id", HeaderValue::from_static("req_123"));
+        headers.insert("openai-processing-ms", HeaderValue::from_static("845"));
+        headers.insert("set-cookie", HeaderValue::from_static("secret"));
+
+        let captured = UpstreamHeaders::from_response(&headers, &allow_list());
+        assert_eq!(captured.request_id(), Some("req_123"));
+        assert_eq!(captured.get("OpenAI-Processing-Ms"), Some("845"));
+        assert_eq!(captured.get("set-cookie"), None);
+    }
+
+    #[tokio::test]
+    async fn test_capture_returns_published_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-request-id", HeaderValue::from_static("req_456"));
+        let upstream = UpstreamHeaders::from_response(&headers, &allow_list());
+
+        let (output, captured) = capture(async {
+            publish(&upstream);
+            42
+        })
+        .await;
+        assert_eq!(output, 42);
+        assert_eq!(captured, upstream);
+
+        // Publishing outside a scope is a no-op
+        publish(&upstream);
+    }
+
+    #[test]
+    fn test_request_id_header_inserted_only_when_captured() {
+        let mut response_headers = HeaderMap::new();
+        UpstreamHeaders::default().insert_request_id_header(&mut response_headers);
+        assert!(response_headers.is_empty());
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-request-id", HeaderValue::from_static("req_789"));
+        UpstreamHeaders::from_response(&headers, &allow_list())
+            .insert_request_id_header(&mut response_headers);
+        assert_eq!(response_headers[UPSTREAM_REQUEST_ID_HEADER], "req_789");
+    }
+}
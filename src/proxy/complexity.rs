@@ -0,0 +1,98 @@
+//! Request complexity metrics
+//!
+//! How heavy a request is, without running the tokenizer: message count,
+//! characters of message content, images, whether tools are offered and
+//! whether the response streams. Handlers count these on the request they
+//! have already parsed (no extra copy of the body), export them per endpoint
+//! and tier, and log them with the request's completion line.
+
+use crate::routes::metrics::record_request_complexity;
+
+/// Tier label for endpoints without tier routing
+pub const NO_TIER: &str = "none";
+
+/// Cheap size and shape of a chat request
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RequestComplexity {
+    /// Messages (or prompts, for legacy completions)
+    pub messages: usize,
+    /// Characters of text content across all messages
+    pub content_chars: usize,
+    /// Image parts across all messages
+    pub images: usize,
+    /// The request offers tools to the model
+    pub tools: bool,
+    /// The response is streamed
+    pub streaming: bool,
+}
+
+impl RequestComplexity {
+    /// Empty counts for a request that does or doesn't stream
+    pub fn new(streaming: bool) -> Self {
+        Self {
+            streaming,
+            ..Self::default()
+        }
+    }
+
+    /// Count one message with the given text content
+    pub fn add_message<'a>(&mut self, texts: impl IntoIterator<Item = &'a str>) {
+        self.messages += 1;
+        self.content_chars += texts
+            .into_iter()
+            .map(|text| text.chars().count())
+            .sum::<usize>();
+    }
+
+    /// Count image parts
+    pub fn add_images(&mut self, images: usize) {
+        self.images += images;
+    }
+
+    /// Mark whether tools are offered
+    pub fn with_tools(mut self, tools: bool) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    /// Export the counts for `endpoint` and `tier` ([`NO_TIER`] without tier routing)
+    pub fn record(&self, endpoint: &str, tier: &str) {
+        record_request_complexity(endpoint, tier, self);
+    }
+}
+
+/// Whether a `tools` value offers at least one tool
+pub fn offers_tools(tools: Option<&serde_json::Value>) -> bool {
+    tools
+        .and_then(|tools| tools.as_array())
+        .is_some_and(|tools| !tools.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_counts_messages_and_characters() {
+        let mut complexity = RequestComplexity::new(true);
+        complexity.add_message(["Hello"]);
+        // Characters, not bytes
+        complexity.add_message(["héllo ", "wörld"]);
+        complexity.add_images(2);
+
+        assert_eq!(complexity.messages, 2);
+        assert_eq!(complexity.content_chars, 16);
+        assert_eq!(complexity.images, 2);
+        assert!(complexity.streaming);
+        assert!(!complexity.tools);
+    }
+
+    #[test]
+    fn test_offers_tools() {
+        assert!(offers_tools(Some(&json!([{"type": "function"}]))));
+        assert!(!offers_tools(Some(&json!([]))));
+        assert!(!offers_tools(Some(&json!(null))));
+        assert!(!offers_tools(None));
+    }
+}
@@ -0,0 +1,196 @@
+//! Response blocklist for regulated terms
+//!
+//! Deployments that must never return certain terms configure regexes in
+//! `RESPONSE_BLOCKLIST_JSON`:
+//!
+//! ```json
+//! {"block": ["(?i)\\bmiracle cure\\b"], "allow": ["(?i)no miracle cure exists"], "window_bytes": 256}
+//! ```
+//!
+//! A `block` match is ignored when an `allow` match covers it, so known-safe
+//! contexts don't trip the filter. Non-streaming responses are checked whole.
+//! Streams are scanned incrementally: each content delta is appended to a
+//! sliding window holding the tail of what came before, so a term split
+//! across chunks is still caught as long as it (and its allow context) fits
+//! in `window_bytes`. Only matches ending in the new delta count, so a term
+//! is reported once. An allow context that continues past the blocked term
+//! only helps if its end arrives in the same delta as the term's end.
+//!
+//! Blocking ends a stream with a `content_blocked` error event. The chunk
+//! carrying the match is not forwarded, but earlier chunks already were.
+//! Usage is tracked from everything received up to the match.
+
+use std::str::FromStr;
+
+use bytes::Bytes;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::json;
+
+/// Error code for blocked responses and stream events
+pub const CONTENT_BLOCKED_CODE: &str = "content_blocked";
+
+/// Message returned in place of a blocked response
+pub const CONTENT_BLOCKED_MESSAGE: &str = "Response blocked by content filter";
+
+/// Window kept between stream chunks when `window_bytes` is not set
+const DEFAULT_WINDOW_BYTES: usize = 256;
+
+/// `RESPONSE_BLOCKLIST_JSON` as written by operators
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BlocklistSpec {
+    block: Vec<String>,
+    #[serde(default)]
+    allow: Vec<String>,
+    window_bytes: Option<usize>,
+}
+
+/// Compiled blocklist applied to response content
+#[derive(Debug, Clone)]
+pub struct ContentFilter {
+    block: Vec<Regex>,
+    allow: Vec<Regex>,
+    window_bytes: usize,
+}
+
+impl FromStr for ContentFilter {
+    type Err = String;
+
+    fn from_str(json: &str) -> Result<Self, Self::Err> {
+        let spec: BlocklistSpec =
+            serde_json::from_str(json).map_err(|e| format!("invalid blocklist JSON: {}", e))?;
+        let compile = |patterns: Vec<String>| {
+            patterns
+                .iter()
+                .map(|pattern| {
+                    Regex::new(pattern).map_err(|e| format!("invalid pattern '{}': {}", pattern, e))
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(Self {
+            block: compile(spec.block)?,
+            allow: compile(spec.allow)?,
+            window_bytes: spec.window_bytes.unwrap_or(DEFAULT_WINDOW_BYTES).max(1),
+        })
+    }
+}
+
+impl ContentFilter {
+    /// Whether complete text contains a blocked term outside an allowed context
+    pub fn is_blocked(&self, text: &str) -> bool {
+        self.blocked_after(text, 0)
+    }
+
+    /// Incremental scanner for one streamed response
+    pub fn scanner(&self) -> ContentScanner {
+        ContentScanner {
+            filter: self.clone(),
+            window: String::new(),
+        }
+    }
+
+    /// Whether a block match ending after byte `from` isn't covered by an allow match
+    fn blocked_after(&self, text: &str, from: usize) -> bool {
+        self.block
+            .iter()
+            .flat_map(|pattern| pattern.find_iter(text))
+            .filter(|found| found.end() > from)
+            .any(|found| {
+                !self
+                    .allow
+                    .iter()
+                    .flat_map(|pattern| pattern.find_iter(text))
+                    .any(|allowed| allowed.start() <= found.start() && allowed.end() >= found.end())
+            })
+    }
+}
+
+/// Streaming counterpart of [`ContentFilter`]
+#[derive(Debug)]
+pub struct ContentScanner {
+    filter: ContentFilter,
+    /// Tail of the content seen so far, at most `window_bytes` between calls
+    window: String,
+}
+
+impl ContentScanner {
+    /// Add the next content delta; true when it completes a blocked term
+    pub fn scan(&mut self, delta: &str) -> bool {
+        let from = self.window.len();
+        self.window.push_str(delta);
+        let blocked = self.filter.blocked_after(&self.window, from);
+
+        if self.window.len() > self.filter.window_bytes {
+            let mut cut = self.window.len() - self.filter.window_bytes;
+            while !self.window.is_char_boundary(cut) {
+                cut += 1;
+            }
+            self.window.drain(..cut);
+        }
+        blocked
+    }
+}
+
+/// SSE error event ending a blocked stream
+pub fn blocked_event() -> Bytes {
+    let event = json!({
+        "error": {
+            "message": CONTENT_BLOCKED_MESSAGE,
+            "type": "content_filter_error",
+            "code": CONTENT_BLOCKED_CODE,
+            "retryable": false,
+        }
+    });
+    Bytes::from(format!("data: {}\n\n", event))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(json: &str) -> ContentFilter {
+        json.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_config() {
+        assert!("not json".parse::<ContentFilter>().is_err());
+        assert!(r#"{"block": ["("]}"#.parse::<ContentFilter>().is_err());
+        assert!(r#"{"block": [], "unknown": 1}"#.parse::<ContentFilter>().is_err());
+    }
+
+    #[test]
+    fn test_is_blocked_honours_allow_contexts() {
+        let filter =
+            filter(r#"{"block": ["(?i)miracle cure"], "allow": ["(?i)no miracle cure exists"]}"#);
+        assert!(filter.is_blocked("Try this Miracle Cure today"));
+        assert!(!filter.is_blocked("Sadly, no miracle cure exists."));
+        assert!(filter.is_blocked("No miracle cure exists, but this miracle cure works"));
+        assert!(!filter.is_blocked("Nothing to see here"));
+    }
+
+    #[test]
+    fn test_scanner_catches_term_split_across_chunks() {
+        let mut scanner = filter(r#"{"block": ["miracle cure"]}"#).scanner();
+        assert!(!scanner.scan("This is a mira"));
+        assert!(scanner.scan("cle cure for you"));
+    }
+
+    #[test]
+    fn test_scanner_reports_each_match_once_and_slides() {
+        let mut scanner = filter(r#"{"block": ["bad"], "window_bytes": 8}"#).scanner();
+        assert!(scanner.scan("bad"));
+        // The earlier match is still in the window but was already reported
+        assert!(!scanner.scan(" fine"));
+        assert!(!scanner.scan(" and more text than the window holds"));
+        assert!(scanner.window.len() <= 8);
+    }
+
+    #[test]
+    fn test_scanner_allow_context_split_across_chunks() {
+        let mut scanner = filter(r#"{"block": ["cure"], "allow": ["no cure exists"]}"#).scanner();
+        assert!(!scanner.scan("sadly no cu"));
+        assert!(!scanner.scan("re exists"));
+    }
+}
@@ -0,0 +1,344 @@
+//! Deprecated OpenAI request parameters on `/v1/chat/completions`
+//!
+//! OpenAI replaced `functions`/`function_call` with `tools`/`tool_choice`,
+//! and reasoning models take `max_completion_tokens` instead of `max_tokens`.
+//! Requests still using them are flagged in `X-Sentinel-Deprecated` (e.g.
+//! `functions->tools`) and counted per parameter; `DEPRECATED_PARAMS` decides
+//! whether they are forwarded as sent (`warn`), rewritten to the modern
+//! equivalents (`translate`) or refused with a 400 (`reject`). A warning is
+//! logged at most once an hour per user and parameter, so affected customers
+//! can be found without flooding the logs.
+//!
+//! `max_tokens` on a reasoning model is rewritten in every mode but `reject`,
+//! as before (see [`super::reasoning`]).
+
+use std::num::NonZeroU32;
+use std::str::FromStr;
+
+use axum::{
+    http::HeaderValue,
+    response::{IntoResponse, Response},
+};
+use governor::{DefaultKeyedRateLimiter, Quota, RateLimiter};
+use metrics::counter;
+use once_cell::sync::Lazy;
+use serde_json::{json, Map, Value};
+use tracing::warn;
+
+use crate::error::AppError;
+
+/// Response header listing the deprecated parameters a request used
+pub const DEPRECATED_HEADER: &str = "X-Sentinel-Deprecated";
+
+/// Tracked users before the warning limiter forgets idle ones
+const WARNING_KEYS_MAX: usize = 10_000;
+
+/// One warning per user and parameter per hour
+static WARNINGS: Lazy<DefaultKeyedRateLimiter<(String, &'static str)>> =
+    Lazy::new(|| RateLimiter::keyed(Quota::per_hour(NonZeroU32::MIN)));
+
+/// What to do with deprecated parameters (`DEPRECATED_PARAMS`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DeprecatedParams {
+    /// Forward them as sent, with the header and metric
+    #[default]
+    Warn,
+    /// Rewrite them to the modern parameters before forwarding
+    Translate,
+    /// Refuse the request with a 400 naming the replacements
+    Reject,
+}
+
+impl DeprecatedParams {
+    fn as_str(self) -> &'static str {
+        match self {
+            DeprecatedParams::Warn => "warn",
+            DeprecatedParams::Translate => "translate",
+            DeprecatedParams::Reject => "reject",
+        }
+    }
+}
+
+impl FromStr for DeprecatedParams {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "warn" => Ok(DeprecatedParams::Warn),
+            "translate" => Ok(DeprecatedParams::Translate),
+            "reject" => Ok(DeprecatedParams::Reject),
+            other => Err(format!(
+                "unknown deprecated parameter policy '{}' (expected warn, translate or reject)",
+                other
+            )),
+        }
+    }
+}
+
+/// A deprecated parameter and what replaces it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deprecation {
+    pub param: &'static str,
+    pub replacement: &'static str,
+}
+
+pub const FUNCTIONS: Deprecation = Deprecation {
+    param: "functions",
+    replacement: "tools",
+};
+
+pub const FUNCTION_CALL: Deprecation = Deprecation {
+    param: "function_call",
+    replacement: "tool_choice",
+};
+
+pub const MAX_TOKENS: Deprecation = Deprecation {
+    param: "max_tokens",
+    replacement: "max_completion_tokens",
+};
+
+/// Deprecated parameters of a chat request
+///
+/// `extra` holds the request fields Sentinel doesn't model, where
+/// `functions` and `function_call` end up; `max_tokens_for_reasoning` is
+/// whether it sets `max_tokens` for a model that needs
+/// `max_completion_tokens`.
+pub fn detect(
+    extra: Option<&Map<String, Value>>,
+    max_tokens_for_reasoning: bool,
+) -> Vec<Deprecation> {
+    let mut found: Vec<Deprecation> = [FUNCTIONS, FUNCTION_CALL]
+        .into_iter()
+        .filter(|deprecation| extra.is_some_and(|extra| extra.contains_key(deprecation.param)))
+        .collect();
+    if max_tokens_for_reasoning {
+        found.push(MAX_TOKENS);
+    }
+    found
+}
+
+/// Count and (rate-limited) log the deprecated parameters of a request
+pub fn record(
+    deprecations: &[Deprecation],
+    mode: DeprecatedParams,
+    external_id: &str,
+    model: &str,
+) {
+    for deprecation in deprecations {
+        counter!(
+            "sentinel_deprecated_params_total",
+            "param" => deprecation.param,
+            "mode" => mode.as_str()
+        )
+        .increment(1);
+
+        if WARNINGS
+            .check_key(&(external_id.to_string(), deprecation.param))
+            .is_ok()
+        {
+            warn!(
+                external_id = %external_id,
+                model = %model,
+                param = deprecation.param,
+                replacement = deprecation.replacement,
+                mode = mode.as_str(),
+                "Client sent a deprecated parameter"
+            );
+        }
+    }
+    if WARNINGS.len() > WARNING_KEYS_MAX {
+        WARNINGS.retain_recent();
+    }
+}
+
+/// The 400 returned in `reject` mode
+pub fn rejection(deprecations: &[Deprecation]) -> AppError {
+    let params: Vec<String> = deprecations
+        .iter()
+        .map(|d| format!("{} (use {})", d.param, d.replacement))
+        .collect();
+    AppError::BadRequest(format!(
+        "Deprecated parameters are not accepted: {}",
+        params.join(", ")
+    ))
+}
+
+/// Rewrite the detected deprecated parameters to their modern equivalents
+///
+/// `functions` become `function` tools (after any `tools` already sent, with
+/// names already offered as tools skipped) and `function_call` becomes the
+/// matching `tool_choice` unless one is set. Returns an error message when a
+/// deprecated parameter has a shape OpenAI never accepted.
+pub fn translate(request: &mut Value, deprecations: &[Deprecation]) -> Result<(), String> {
+    let Some(obj) = request.as_object_mut() else {
+        return Ok(());
+    };
+    let mut take = |deprecation: Deprecation| {
+        deprecations
+            .contains(&deprecation)
+            .then(|| obj.remove(deprecation.param))
+            .flatten()
+    };
+    let functions = take(FUNCTIONS);
+    let function_call = take(FUNCTION_CALL);
+    let max_tokens = take(MAX_TOKENS);
+
+    if let Some(functions) = functions {
+        let Value::Array(functions) = functions else {
+            return Err("functions must be an array".to_string());
+        };
+        let tools = obj
+            .entry("tools")
+            .or_insert_with(|| Value::Array(Vec::new()));
+        let Some(tools) = tools.as_array_mut() else {
+            return Err("tools must be an array".to_string());
+        };
+        for function in functions {
+            let name = function.get("name").cloned();
+            let offered = tools
+                .iter()
+                .any(|tool| name.is_some() && tool["function"].get("name") == name.as_ref());
+            if !offered {
+                tools.push(json!({"type": "function", "function": function}));
+            }
+        }
+    }
+
+    if let Some(function_call) = function_call {
+        let tool_choice = match function_call {
+            Value::String(mode) if mode == "none" || mode == "auto" => Value::String(mode),
+            Value::Object(call) if call.get("name").is_some_and(Value::is_string) => {
+                json!({"type": "function", "function": {"name": call["name"]}})
+            }
+            _ => {
+                return Err(
+                    "function_call must be \"none\", \"auto\" or {\"name\": ...}".to_string(),
+                )
+            }
+        };
+        obj.entry("tool_choice").or_insert(tool_choice);
+    }
+
+    if let Some(max_tokens) = max_tokens {
+        obj.entry("max_completion_tokens").or_insert(max_tokens);
+    }
+
+    Ok(())
+}
+
+/// Name the deprecated parameters of a request in `X-Sentinel-Deprecated`
+///
+/// Like `timeout::with_timeout_header`, error responses get the header too.
+pub fn with_header<E: IntoResponse>(
+    result: Result<Response, E>,
+    deprecations: &[Deprecation],
+) -> Result<Response, E> {
+    if deprecations.is_empty() {
+        return result;
+    }
+
+    let value = deprecations
+        .iter()
+        .map(|d| format!("{}->{}", d.param, d.replacement))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut response = result.unwrap_or_else(IntoResponse::into_response);
+    if let Ok(value) = HeaderValue::from_str(&value) {
+        response.headers_mut().insert(DEPRECATED_HEADER, value);
+    }
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extra(value: Value) -> Map<String, Value> {
+        value.as_object().cloned().unwrap()
+    }
+
+    #[test]
+    fn test_parse_mode() {
+        assert_eq!("warn".parse(), Ok(DeprecatedParams::Warn));
+        assert_eq!(" Translate ".parse(), Ok(DeprecatedParams::Translate));
+        assert_eq!("reject".parse(), Ok(DeprecatedParams::Reject));
+        assert!("ignore".parse::<DeprecatedParams>().is_err());
+    }
+
+    #[test]
+    fn test_detect() {
+        let fields = extra(json!({"functions": [], "function_call": "auto", "custom": 1}));
+        assert_eq!(detect(Some(&fields), false), vec![FUNCTIONS, FUNCTION_CALL]);
+        assert_eq!(detect(None, true), vec![MAX_TOKENS]);
+        assert!(detect(Some(&extra(json!({"custom": 1}))), false).is_empty());
+    }
+
+    #[test]
+    fn test_translate_functions_and_function_call() {
+        let weather = json!({"name": "get_weather", "parameters": {"type": "object"}});
+        let mut request = json!({
+            "model": "gpt-4o",
+            "max_tokens": 100,
+            "functions": [weather],
+            "function_call": {"name": "get_weather"}
+        });
+        translate(&mut request, &[FUNCTIONS, FUNCTION_CALL]).unwrap();
+        assert_eq!(
+            request,
+            json!({
+                "model": "gpt-4o",
+                "max_tokens": 100,
+                "tools": [{"type": "function", "function": weather}],
+                "tool_choice": {"type": "function", "function": {"name": "get_weather"}}
+            })
+        );
+
+        for mode in ["none", "auto"] {
+            let mut request = json!({"function_call": mode});
+            translate(&mut request, &[FUNCTION_CALL]).unwrap();
+            assert_eq!(request, json!({"tool_choice": mode}));
+        }
+    }
+
+    #[test]
+    fn test_translate_keeps_modern_parameters() {
+        let existing = json!({"type": "function", "function": {"name": "get_weather"}});
+        let mut request = json!({
+            "tools": [existing],
+            "tool_choice": "required",
+            "functions": [{"name": "get_weather"}, {"name": "get_time"}],
+            "function_call": "none",
+            "max_tokens": 100,
+            "max_completion_tokens": 500
+        });
+        translate(&mut request, &[FUNCTIONS, FUNCTION_CALL, MAX_TOKENS]).unwrap();
+        assert_eq!(request["tools"].as_array().unwrap().len(), 2);
+        assert_eq!(request["tools"][0], existing);
+        assert_eq!(request["tools"][1]["function"]["name"], "get_time");
+        assert_eq!(request["tool_choice"], "required");
+        assert_eq!(request["max_completion_tokens"], 500);
+        assert!(request.get("max_tokens").is_none());
+    }
+
+    #[test]
+    fn test_translate_rejects_malformed_parameters() {
+        let all = [FUNCTIONS, FUNCTION_CALL];
+        assert!(translate(&mut json!({"functions": {"name": "f"}}), &all).is_err());
+        assert!(translate(&mut json!({"function_call": "required"}), &all).is_err());
+        assert!(translate(&mut json!({"function_call": {"arguments": "{}"}}), &all).is_err());
+    }
+
+    #[test]
+    fn test_header_lists_replacements() {
+        let response = with_header::<AppError>(
+            Err(rejection(&[FUNCTIONS, FUNCTION_CALL])),
+            &[FUNCTIONS, FUNCTION_CALL],
+        )
+        .unwrap();
+        assert_eq!(response.status(), 400);
+        assert_eq!(
+            response.headers()[DEPRECATED_HEADER],
+            "functions->tools, function_call->tool_choice"
+        );
+    }
+}
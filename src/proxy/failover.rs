@@ -0,0 +1,475 @@
+//! Provider failover
+//!
+//! With `FAILOVER_PROVIDERS` set, requests that the primary provider fails
+//! with 500, 502, 503 or 529, a connection error, an open circuit or a
+//! per-attempt timeout (`FAILOVER_ATTEMPT_TIMEOUT_MS`) are re-sent to the next
+//! provider in the list. Streams can only move on before their first chunk;
+//! once bytes have reached the client the stream belongs to the provider that
+//! sent them. Pass-through requests stay with the primary, since their body
+//! can only be sent once.
+//!
+//! Failover providers are another kind than the primary, so they know the
+//! requested model under another name: `FAILOVER_MODEL_MAP` names the model
+//! they serve instead. Requests for models without an entry stay with the
+//! primary. When no failover provider answers, the client gets the
+//! primary's error rather than a failover provider's.
+//!
+//! The provider that answered is counted per provider and named in the
+//! `X-Sentinel-Provider` response header (see [`serving`]).
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use axum::body::Body;
+use axum::http::{HeaderMap, Method, Response};
+use futures::StreamExt;
+use metrics::counter;
+use serde_json::Value;
+use tracing::{debug, warn};
+
+use crate::{
+    error::{AppError, AppResult},
+    proxy::{breaker::upstream_status, AiProvider, ByteStream},
+};
+
+/// Upstream statuses worth trying another provider for (529 = Anthropic overloaded)
+const FAILOVER_STATUSES: [u16; 4] = [500, 502, 503, 529];
+
+tokio::task_local! {
+    static SERVED: Arc<Mutex<Option<&'static str>>>;
+}
+
+/// Run a request, returning its output and the provider failover settled on
+///
+/// `None` when no failover chain handled a provider call inside the scope.
+pub async fn serving<F: Future>(fut: F) -> (F::Output, Option<&'static str>) {
+    let slot = Arc::new(Mutex::new(None));
+    let output = SERVED.scope(slot.clone(), fut).await;
+    let served = *slot.lock().unwrap();
+    (output, served)
+}
+
+fn publish_served(provider: &'static str) {
+    let _ = SERVED.try_with(|slot| *slot.lock().unwrap() = Some(provider));
+}
+
+/// Whether another provider might succeed where this error came from
+pub fn is_failover_error(error: &AppError) -> bool {
+    match error {
+        AppError::UpstreamError(message) => {
+            upstream_status(message).is_some_and(|status| FAILOVER_STATUSES.contains(&status))
+        }
+        AppError::HttpError(_)
+        | AppError::UpstreamTimeout { .. }
+        | AppError::UpstreamUnavailable { .. } => true,
+        _ => false,
+    }
+}
+
+/// An [`AiProvider`] trying an ordered list of providers until one answers
+pub struct FailoverProvider {
+    providers: Vec<Arc<dyn AiProvider>>,
+    attempt_timeout: Option<Duration>,
+    /// Requested model => model the failover providers serve instead
+    model_map: HashMap<String, String>,
+}
+
+impl FailoverProvider {
+    /// Try `providers` in order, the first being the primary
+    ///
+    /// `attempt_timeout` bounds each non-streaming call, and each stream until
+    /// its first chunk.
+    pub fn new(providers: Vec<Arc<dyn AiProvider>>, attempt_timeout: Option<Duration>) -> Self {
+        assert!(
+            !providers.is_empty(),
+            "failover needs at least one provider"
+        );
+        Self {
+            providers,
+            attempt_timeout,
+            model_map: HashMap::new(),
+        }
+    }
+
+    /// Models to request from the failover providers (unmapped models don't fail over)
+    pub fn with_model_map(mut self, model_map: HashMap<String, String>) -> Self {
+        self.model_map = model_map;
+        self
+    }
+
+    fn primary(&self) -> &Arc<dyn AiProvider> {
+        &self.providers[0]
+    }
+
+    async fn attempt<T>(&self, call: impl Future<Output = AppResult<T>>) -> AppResult<T> {
+        match self.attempt_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, call).await.map_err(|_| {
+                AppError::UpstreamTimeout {
+                    timeout_ms: timeout.as_millis() as u64,
+                }
+            })?,
+            None => call.await,
+        }
+    }
+
+    /// Open a stream and wait for its first chunk, so a failure surfaces here
+    async fn attempt_stream(
+        &self,
+        open: impl Future<Output = AppResult<ByteStream>>,
+    ) -> AppResult<ByteStream> {
+        self.attempt(async {
+            let mut stream = open.await?;
+            match stream.next().await {
+                Some(Err(e)) => Err(AppError::HttpError(e)),
+                first => Ok(Box::pin(futures::stream::iter(first).chain(stream)) as ByteStream),
+            }
+        })
+        .await
+    }
+
+    /// Call each provider in turn until one succeeds or fails for good
+    ///
+    /// `model` is the requested model, if any: `call` gets the model a
+    /// failover provider should be asked for instead (None: as requested).
+    async fn failover<'a, T, F, Fut>(
+        &'a self,
+        endpoint: &str,
+        model: Option<&str>,
+        call: F,
+    ) -> AppResult<T>
+    where
+        F: Fn(&'a Arc<dyn AiProvider>, Option<&'a str>) -> Fut,
+        Fut: Future<Output = AppResult<T>>,
+    {
+        let primary = self.primary();
+        let primary_error = match call(primary, None).await {
+            Ok(output) => {
+                record_served(primary.name());
+                return Ok(output);
+            }
+            Err(e) if is_failover_error(&e) => e,
+            Err(e) => return Err(e),
+        };
+
+        let mapped = match model {
+            Some(model) => match self.model_map.get(model) {
+                Some(mapped) => Some(mapped.as_str()),
+                None => {
+                    debug!(model = %model, endpoint = %endpoint, "No failover model mapped, not failing over");
+                    return Err(primary_error);
+                }
+            },
+            None => None,
+        };
+        warn!(
+            provider = primary.name(),
+            endpoint = %endpoint,
+            failover_model = mapped.unwrap_or_default(),
+            error = %primary_error,
+            "Provider failed, failing over"
+        );
+        for provider in &self.providers[1..] {
+            match call(provider, mapped).await {
+                Ok(output) => {
+                    record_served(provider.name());
+                    return Ok(output);
+                }
+                Err(e) if is_failover_error(&e) => {
+                    warn!(provider = provider.name(), endpoint = %endpoint, error = %e, "Failover provider failed too");
+                }
+                Err(e) => {
+                    warn!(provider = provider.name(), endpoint = %endpoint, error = %e, "Failover provider refused the request");
+                    break;
+                }
+            }
+        }
+        Err(primary_error)
+    }
+}
+
+/// The request's `model`, if it names one
+fn requested_model(request: &Value) -> Option<&str> {
+    request.get("model").and_then(Value::as_str)
+}
+
+/// `request` with `model` swapped in, when there is one
+fn with_model(request: &Value, model: Option<&str>) -> Value {
+    let mut request = request.clone();
+    if let (Some(model), Some(fields)) = (model, request.as_object_mut()) {
+        fields.insert("model".to_string(), Value::String(model.to_string()));
+    }
+    request
+}
+
+/// Count and publish the provider that answered
+fn record_served(provider: &'static str) {
+    counter!("sentinel_failover_served_total", "provider" => provider).increment(1);
+    publish_served(provider);
+}
+
+#[async_trait]
+impl AiProvider for FailoverProvider {
+    fn name(&self) -> &'static str {
+        self.primary().name()
+    }
+
+    async fn chat_completions(
+        &self,
+        request: serde_json::Value,
+        incoming_headers: &HeaderMap,
+    ) -> AppResult<serde_json::Value> {
+        self.failover("chat/completions", requested_model(&request), |provider, model| {
+            self.attempt(provider.chat_completions(with_model(&request, model), incoming_headers))
+        })
+        .await
+    }
+
+    async fn chat_completions_stream(
+        &self,
+        request: serde_json::Value,
+        incoming_headers: &HeaderMap,
+    ) -> AppResult<ByteStream> {
+        self.failover("chat/completions", requested_model(&request), |provider, model| {
+            self.attempt_stream(provider.chat_completions_stream(with_model(&request, model), incoming_headers))
+        })
+        .await
+    }
+
+    async fn completions(
+        &self,
+        request: serde_json::Value,
+        incoming_headers: &HeaderMap,
+    ) -> AppResult<serde_json::Value> {
+        self.failover("completions", requested_model(&request), |provider, model| {
+            self.attempt(provider.completions(with_model(&request, model), incoming_headers))
+        })
+        .await
+    }
+
+    async fn completions_stream(
+        &self,
+        request: serde_json::Value,
+        incoming_headers: &HeaderMap,
+    ) -> AppResult<ByteStream> {
+        self.failover("completions", requested_model(&request), |provider, model| {
+            self.attempt_stream(provider.completions_stream(with_model(&request, model), incoming_headers))
+        })
+        .await
+    }
+
+    async fn embeddings(
+        &self,
+        request: serde_json::Value,
+        incoming_headers: &HeaderMap,
+    ) -> AppResult<serde_json::Value> {
+        self.failover("embeddings", requested_model(&request), |provider, model| {
+            self.attempt(provider.embeddings(with_model(&request, model), incoming_headers))
+        })
+        .await
+    }
+
+    async fn list_models(&self) -> AppResult<serde_json::Value> {
+        self.failover("models", None, |provider, _| self.attempt(provider.list_models()))
+            .await
+    }
+
+    async fn get_model(&self, model_id: &str) -> AppResult<serde_json::Value> {
+        self.failover("models", Some(model_id), |provider, model| {
+            self.attempt(provider.get_model(model.unwrap_or(model_id)))
+        })
+        .await
+    }
+
+    async fn responses(
+        &self,
+        request: serde_json::Value,
+        incoming_headers: &HeaderMap,
+    ) -> AppResult<serde_json::Value> {
+        self.failover("responses", requested_model(&request), |provider, model| {
+            self.attempt(provider.responses(with_model(&request, model), incoming_headers))
+        })
+        .await
+    }
+
+    async fn responses_stream(
+        &self,
+        request: serde_json::Value,
+        incoming_headers: &HeaderMap,
+    ) -> AppResult<ByteStream> {
+        self.failover("responses", requested_model(&request), |provider, model| {
+            self.attempt_stream(provider.responses_stream(with_model(&request, model), incoming_headers))
+        })
+        .await
+    }
+
+    async fn forward_raw(
+        &self,
+        method: Method,
+        path: &str,
+        incoming_headers: HeaderMap,
+        body: Body,
+    ) -> AppResult<Response<Body>> {
+        let response = self
+            .primary()
+            .forward_raw(method, path, incoming_headers, body)
+            .await?;
+        record_served(self.primary().name());
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockAiProvider, MockEndpoint, MockReply};
+
+    fn provider(name: &'static str, reply: MockReply) -> Arc<MockAiProvider> {
+        Arc::new(
+            MockAiProvider::new()
+                .with_name(name)
+                .with_reply(MockEndpoint::ChatCompletions, reply),
+        )
+    }
+
+    fn error(status: u16) -> MockReply {
+        MockReply::Error {
+            status,
+            message: "upstream trouble".to_string(),
+        }
+    }
+
+    fn chain(providers: &[&Arc<MockAiProvider>]) -> FailoverProvider {
+        FailoverProvider::new(
+            providers
+                .iter()
+                .map(|p| Arc::clone(p) as Arc<dyn AiProvider>)
+                .collect(),
+            Some(Duration::from_millis(200)),
+        )
+        .with_model_map(HashMap::from([(
+            "gpt-4o".to_string(),
+            "claude-sonnet-4-5".to_string(),
+        )]))
+    }
+
+    fn request() -> serde_json::Value {
+        serde_json::json!({"model": "gpt-4o", "messages": []})
+    }
+
+    #[test]
+    fn test_failover_errors() {
+        for status in FAILOVER_STATUSES {
+            let error = AppError::UpstreamError(format!("OpenAI error {}: down", status));
+            assert!(is_failover_error(&error), "{}", status);
+        }
+        for status in [400, 429, 504] {
+            let error = AppError::UpstreamError(format!("OpenAI error {}: no", status));
+            assert!(!is_failover_error(&error), "{}", status);
+        }
+        assert!(is_failover_error(&AppError::UpstreamTimeout {
+            timeout_ms: 1
+        }));
+        assert!(!is_failover_error(&AppError::BadRequest("bad".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_non_streaming_fails_over_on_5xx() {
+        let primary = provider("primary", error(503));
+        let secondary = provider(
+            "secondary",
+            MockReply::chat_completion("gpt-4o", "Hello!", 10, 5),
+        );
+        let failover = chain(&[&primary, &secondary]);
+        assert_eq!(failover.name(), "primary");
+
+        let (result, served) =
+            serving(failover.chat_completions(request(), &HeaderMap::new())).await;
+        assert_eq!(
+            result.unwrap()["choices"][0]["message"]["content"],
+            "Hello!"
+        );
+        assert_eq!(served, Some("secondary"));
+        assert_eq!(primary.requests().len(), 1);
+        // The failover provider is asked for its own model
+        assert_eq!(primary.requests()[0].body["model"], "gpt-4o");
+        assert_eq!(secondary.requests()[0].body["model"], "claude-sonnet-4-5");
+    }
+
+    #[tokio::test]
+    async fn test_unmapped_models_stay_with_primary() {
+        let primary = provider("primary", error(503));
+        let secondary = provider("secondary", MockReply::chat_completion("gpt-4o-mini", "Hi", 1, 1));
+        let failover = chain(&[&primary, &secondary]);
+
+        let request = serde_json::json!({"model": "gpt-4o-mini", "messages": []});
+        let (result, served) = serving(failover.chat_completions(request, &HeaderMap::new())).await;
+        assert!(result.unwrap_err().to_string().contains("503"));
+        assert_eq!(served, None);
+        assert!(secondary.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_client_errors_and_primary_failure_are_returned() {
+        let primary = provider("primary", error(400));
+        let secondary = provider("secondary", error(503));
+        let failover = chain(&[&primary, &secondary]);
+        let err = failover
+            .chat_completions(request(), &HeaderMap::new())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("400"));
+        assert!(secondary.requests().is_empty());
+
+        // Whatever the failover provider answers, the client gets the primary's error
+        for secondary_reply in [error(503), error(404)] {
+            let primary = provider("primary", error(502));
+            let secondary = provider("secondary", secondary_reply);
+            let failover = chain(&[&primary, &secondary]);
+            let (result, served) =
+                serving(failover.chat_completions(request(), &HeaderMap::new())).await;
+            assert!(result.unwrap_err().to_string().contains("502"));
+            assert_eq!(served, None);
+            assert_eq!(secondary.requests().len(), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slow_attempt_fails_over() {
+        let primary = Arc::new(
+            MockAiProvider::new()
+                .with_name("primary")
+                .with_chunk_delay(Duration::from_secs(5))
+                .with_reply(
+                    MockEndpoint::ChatCompletions,
+                    MockReply::chat_stream("gpt-4o", "slow", None),
+                ),
+        );
+        let secondary = provider("secondary", MockReply::chat_stream("gpt-4o", "fast", None));
+        let failover = chain(&[&primary, &secondary]);
+
+        let (result, served) =
+            serving(failover.chat_completions_stream(request(), &HeaderMap::new())).await;
+        let chunks: Vec<_> = result.unwrap().collect().await;
+        let body: String = chunks
+            .into_iter()
+            .map(|chunk| String::from_utf8(chunk.unwrap().to_vec()).unwrap())
+            .collect();
+        assert!(body.contains("fast"));
+        assert_eq!(served, Some("secondary"));
+    }
+
+    #[tokio::test]
+    async fn test_stream_errors_fail_over_before_first_chunk() {
+        let primary = provider("primary", error(529));
+        let secondary = provider("secondary", MockReply::chat_stream("gpt-4o", "Hi", None));
+        let failover = chain(&[&primary, &secondary]);
+
+        let (result, served) =
+            serving(failover.chat_completions_stream(request(), &HeaderMap::new())).await;
+        assert!(result.is_ok());
+        assert_eq!(served, Some("secondary"));
+    }
+}
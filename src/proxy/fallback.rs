@@ -0,0 +1,287 @@
+//! Client-driven model fallback for `/v1/chat/completions`
+//!
+//! Clients may send a `models` array next to `model` (a Sentinel extension,
+//! never forwarded upstream). The request goes to `model` first; when the
+//! provider fails in a way another model might not (429, 5xx, connection
+//! errors, timeouts) it is re-issued with the next entry of `models`, in
+//! order. Every entry must be a model from the tier config. Failed attempts
+//! are recorded in the provider health tracker, and the model that answered
+//! is returned in `X-Sentinel-Model`.
+//!
+//! Streams can only move on before they open; once bytes have reached the
+//! client the stream belongs to the model that sent them.
+
+use std::future::Future;
+
+use axum::http::{HeaderMap, HeaderValue};
+use serde_json::{json, Map, Value};
+use tracing::warn;
+
+use crate::{
+    error::AppError,
+    proxy::{breaker::upstream_status, complexity::NO_TIER},
+    routes::metrics::record_model_retry,
+    tiers::{config::TierConfig, router::TierRouter},
+};
+
+/// Request field listing fallback models
+pub const MODELS_FIELD: &str = "models";
+
+/// Response header naming the model that served the request
+pub const MODEL_HEADER: &str = "X-Sentinel-Model";
+
+/// One model to try, with the provider it is tracked under
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FallbackModel {
+    pub provider: String,
+    pub model: String,
+}
+
+/// Models to try for a request, in order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FallbackModels {
+    models: Vec<FallbackModel>,
+}
+
+/// Remove the `models` extension from a request's extra fields
+///
+/// Returns the listed model names, or an error message when the field is not
+/// a non-empty array of non-empty strings.
+pub fn take_models(extra: &mut Option<Map<String, Value>>) -> Result<Option<Vec<String>>, String> {
+    let Some(value) = extra.as_mut().and_then(|extra| extra.remove(MODELS_FIELD)) else {
+        return Ok(None);
+    };
+    let invalid = || "models must be a non-empty array of model names".to_string();
+    let entries = value
+        .as_array()
+        .filter(|entries| !entries.is_empty())
+        .ok_or_else(invalid)?;
+    entries
+        .iter()
+        .map(|entry| {
+            entry
+                .as_str()
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(str::to_string)
+                .ok_or_else(invalid)
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(Some)
+}
+
+impl FallbackModels {
+    /// Attempt order for `model` followed by the client's `models`
+    ///
+    /// Entries repeating an earlier model are skipped. Every `models` entry
+    /// must be configured in the tier config; `model` itself is accepted as
+    /// the client sent it, as without fallbacks, and tracked under
+    /// `default_provider` when the tier config doesn't list it.
+    pub fn resolve(
+        model: &str,
+        models: Vec<String>,
+        tier_config: Option<&TierConfig>,
+        default_provider: &str,
+    ) -> Result<Self, AppError> {
+        let tier_config = tier_config.ok_or_else(|| AppError::ServiceUnavailable {
+            message: "Fallback models can't be checked right now (tier config unavailable)"
+                .to_string(),
+            retry_after: None,
+        })?;
+
+        let mut resolved = vec![FallbackModel {
+            provider: tier_config
+                .model_config(model)
+                .map_or(default_provider, |config| config.provider.as_str())
+                .to_string(),
+            model: model.to_string(),
+        }];
+        for name in models {
+            let config = tier_config.model_config(&name).ok_or_else(|| {
+                AppError::BadRequest(format!("models: '{}' is not an available model", name))
+            })?;
+            if resolved.iter().all(|existing| existing.model != name) {
+                resolved.push(FallbackModel {
+                    provider: config.provider.clone(),
+                    model: name,
+                });
+            }
+        }
+        Ok(Self { models: resolved })
+    }
+
+    /// Models in attempt order
+    pub fn models(&self) -> &[FallbackModel] {
+        &self.models
+    }
+
+    /// Run `attempt` for each model until one succeeds or fails for good
+    ///
+    /// `request` is re-targeted at each model before it is passed on. Returns
+    /// the last attempt's result with the model it was made for.
+    pub async fn run<T, F, Fut>(
+        &self,
+        router: &TierRouter,
+        request: &Value,
+        mut attempt: F,
+    ) -> (Result<T, AppError>, &FallbackModel)
+    where
+        F: FnMut(Value) -> Fut,
+        Fut: Future<Output = Result<T, AppError>>,
+    {
+        let mut models = self.models.iter().peekable();
+        loop {
+            let current = models.next().expect("fallback list is never empty");
+            let mut targeted = request.clone();
+            targeted["model"] = json!(current.model);
+            let result = attempt(targeted).await;
+
+            match result {
+                Ok(value) => {
+                    router.record_success(&current.provider, &current.model);
+                    return (Ok(value), current);
+                }
+                Err(e) if is_retryable(&e) => {
+                    router.record_failure(&current.provider, &current.model);
+                    let Some(next) = models.peek() else {
+                        return (Err(e), current);
+                    };
+                    warn!(
+                        model = %current.model,
+                        next_model = %next.model,
+                        error = %e,
+                        "Model failed, trying the next fallback model"
+                    );
+                    record_model_retry(NO_TIER, &current.model, &next.model);
+                }
+                Err(e) => return (Err(e), current),
+            }
+        }
+    }
+}
+
+/// Whether another model might succeed where this error failed
+///
+/// Rate limits, upstream 5xx, connection failures and timeouts qualify. An
+/// open circuit breaker doesn't: it covers the provider endpoint, not the model.
+pub fn is_retryable(error: &AppError) -> bool {
+    match error {
+        AppError::UpstreamError(message) => upstream_status(message)
+            .is_some_and(|status| status == 429 || (500..600).contains(&status)),
+        AppError::HttpError(_) | AppError::UpstreamTimeout { .. } => true,
+        _ => false,
+    }
+}
+
+/// Name the model that served a request in `X-Sentinel-Model`
+pub fn insert_model_header(headers: &mut HeaderMap, served: &FallbackModel) {
+    if let Ok(value) = HeaderValue::from_str(&served.model) {
+        headers.insert(MODEL_HEADER, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zion::models::TierConfigData;
+
+    fn tier_config() -> TierConfig {
+        serde_json::from_value::<TierConfigData>(json!({
+            "version": "1.0.0",
+            "updatedAt": "2024-01-01T00:00:00Z",
+            "tiers": {
+                "simple": [{"provider": "openai", "model": "gpt-4o-mini", "relativeCost": 1, "inputPricePerMillion": 1.0, "outputPricePerMillion": 1.0}],
+                "moderate": [{"provider": "openai", "model": "gpt-4o", "relativeCost": 5, "inputPricePerMillion": 1.0, "outputPricePerMillion": 1.0}],
+                "complex": [{"provider": "openai", "model": "gpt-4o", "relativeCost": 5, "inputPricePerMillion": 1.0, "outputPricePerMillion": 1.0}]
+            }
+        }))
+        .unwrap()
+    }
+
+    fn extra(value: Value) -> Option<Map<String, Value>> {
+        value.as_object().cloned()
+    }
+
+    #[test]
+    fn test_take_models_strips_the_field() {
+        let mut fields = extra(json!({"models": ["gpt-4o", " gpt-4o-mini "], "custom": 1}));
+        assert_eq!(
+            take_models(&mut fields).unwrap(),
+            Some(vec!["gpt-4o".to_string(), "gpt-4o-mini".to_string()])
+        );
+        assert_eq!(fields, extra(json!({"custom": 1})));
+
+        assert_eq!(take_models(&mut None).unwrap(), None);
+    }
+
+    #[test]
+    fn test_take_models_rejects_malformed_lists() {
+        for value in [
+            json!([]),
+            json!("gpt-4o"),
+            json!(["gpt-4o", 3]),
+            json!([" "]),
+        ] {
+            let mut fields = extra(json!({ "models": value }));
+            assert!(take_models(&mut fields).is_err(), "accepted {}", value);
+        }
+    }
+
+    #[test]
+    fn test_resolve_orders_and_dedupes() {
+        let config = tier_config();
+        let models = FallbackModels::resolve(
+            "gpt-4o",
+            vec!["gpt-4o".to_string(), "gpt-4o-mini".to_string()],
+            Some(&config),
+            "mock",
+        )
+        .unwrap();
+        let names: Vec<&str> = models.models().iter().map(|m| m.model.as_str()).collect();
+        assert_eq!(names, vec!["gpt-4o", "gpt-4o-mini"]);
+        assert_eq!(models.models()[0].provider, "openai");
+
+        // The primary model isn't checked, only tracked under the default provider
+        let models = FallbackModels::resolve(
+            "custom-model",
+            vec!["gpt-4o".to_string()],
+            Some(&config),
+            "mock",
+        )
+        .unwrap();
+        assert_eq!(models.models()[0].provider, "mock");
+    }
+
+    #[test]
+    fn test_resolve_rejects_unknown_models() {
+        let config = tier_config();
+        let err = FallbackModels::resolve(
+            "gpt-4o",
+            vec!["gpt-5-turbo".to_string()],
+            Some(&config),
+            "mock",
+        )
+        .unwrap_err();
+        assert!(matches!(err, AppError::BadRequest(ref m) if m.contains("gpt-5-turbo")));
+
+        let err = FallbackModels::resolve("gpt-4o", vec!["gpt-4o".to_string()], None, "mock")
+            .unwrap_err();
+        assert!(matches!(err, AppError::ServiceUnavailable { .. }));
+    }
+
+    #[test]
+    fn test_is_retryable() {
+        let upstream =
+            |status: u16| AppError::UpstreamError(format!("OpenAI error {} Error: x", status));
+        assert!(is_retryable(&upstream(429)));
+        assert!(is_retryable(&upstream(503)));
+        assert!(is_retryable(&AppError::UpstreamTimeout { timeout_ms: 10 }));
+        assert!(!is_retryable(&upstream(400)));
+        assert!(!is_retryable(&AppError::BadRequest("bad".to_string())));
+        assert!(!is_retryable(&AppError::UpstreamUnavailable {
+            provider: "openai".to_string(),
+            endpoint: "chat".to_string(),
+            retry_after: std::time::Duration::from_secs(5),
+        }));
+    }
+}
@@ -0,0 +1,236 @@
+//! finish_reason monitoring
+//!
+//! Every completed chat or completion response has its `finish_reason`
+//! counted in `sentinel_finish_reasons_total` (by endpoint, model and reason).
+//! A jump in `content_filter` or `length` usually means something changed
+//! upstream or a client shipped a bad prompt template, so the share of
+//! `content_filter` is also watched over a sliding window: once it passes
+//! `FINISH_REASON_ALERT_PERCENT` (with at least `FINISH_REASON_MIN_SAMPLES`
+//! responses in the window) a warn event is logged. The alert fires again
+//! only after the share has dropped back below the threshold.
+//!
+//! The window is per replica and in memory; responses are counted in
+//! one-second buckets so its size doesn't grow with traffic.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use tracing::warn;
+
+use crate::{
+    clock::{system_clock, SharedClock},
+    config::ProviderConfig,
+    routes::metrics::record_finish_reason,
+};
+
+/// Reason whose share is watched
+pub const CONTENT_FILTER: &str = "content_filter";
+
+/// Reasons reported under their own label; anything else is `other`
+const KNOWN_REASONS: [&str; 5] = [
+    "stop",
+    "length",
+    "content_filter",
+    "tool_calls",
+    "function_call",
+];
+
+/// Metric label for a finish_reason (`unknown` when none was sent)
+pub fn label(reason: Option<&str>) -> &'static str {
+    match reason {
+        None => "unknown",
+        Some(reason) => KNOWN_REASONS
+            .iter()
+            .find(|known| **known == reason)
+            .copied()
+            .unwrap_or("other"),
+    }
+}
+
+/// Responses seen during one second
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    second: i64,
+    total: u64,
+    filtered: u64,
+}
+
+#[derive(Debug, Default)]
+struct Window {
+    buckets: VecDeque<Bucket>,
+    total: u64,
+    filtered: u64,
+    alerting: bool,
+}
+
+/// Counts finish reasons and warns on `content_filter` spikes
+#[derive(Debug)]
+pub struct FinishReasonMonitor {
+    alert_percent: f64,
+    window_seconds: i64,
+    min_samples: u64,
+    clock: SharedClock,
+    window: Mutex<Window>,
+}
+
+impl FinishReasonMonitor {
+    /// Create a monitor using the `FINISH_REASON_*` settings
+    pub fn new(config: &ProviderConfig) -> Self {
+        Self {
+            alert_percent: config.finish_reason_alert_percent,
+            window_seconds: config.finish_reason_window_seconds.max(1) as i64,
+            min_samples: config.finish_reason_min_samples.max(1),
+            clock: system_clock(),
+            window: Mutex::new(Window::default()),
+        }
+    }
+
+    /// Use the given clock for the sliding window
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Whether the `content_filter` share is currently over the threshold
+    pub fn is_alerting(&self) -> bool {
+        self.window.lock().unwrap().alerting
+    }
+
+    /// Count a completed response's finish_reason
+    ///
+    /// Returns true when this response pushed the `content_filter` share over
+    /// the threshold (and the warn event was logged).
+    pub fn observe(&self, endpoint: &str, model: &str, reason: Option<&str>) -> bool {
+        let label = label(reason);
+        record_finish_reason(endpoint, model, label);
+        if self.alert_percent <= 0.0 {
+            return false;
+        }
+
+        let now = self.clock.now_unix();
+        let filtered = u64::from(label == CONTENT_FILTER);
+        let mut window = self.window.lock().unwrap();
+
+        while let Some(oldest) = window.buckets.front().copied() {
+            if oldest.second > now - self.window_seconds {
+                break;
+            }
+            window.buckets.pop_front();
+            window.total -= oldest.total;
+            window.filtered -= oldest.filtered;
+        }
+        match window.buckets.back_mut() {
+            Some(bucket) if bucket.second == now => {
+                bucket.total += 1;
+                bucket.filtered += filtered;
+            }
+            _ => window.buckets.push_back(Bucket {
+                second: now,
+                total: 1,
+                filtered,
+            }),
+        }
+        window.total += 1;
+        window.filtered += filtered;
+
+        let share = window.filtered as f64 * 100.0 / window.total as f64;
+        let above = window.total >= self.min_samples && share > self.alert_percent;
+        if !above {
+            window.alerting = false;
+            return false;
+        }
+        if window.alerting {
+            return false;
+        }
+        window.alerting = true;
+        warn!(
+            share_percent = %format!("{:.1}", share),
+            threshold_percent = self.alert_percent,
+            content_filter = window.filtered,
+            responses = window.total,
+            window_seconds = self.window_seconds,
+            endpoint = %endpoint,
+            model = %model,
+            "content_filter finish_reason share above threshold"
+        );
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::TestClock;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn monitor(clock: Arc<TestClock>) -> FinishReasonMonitor {
+        let config = ProviderConfig {
+            finish_reason_alert_percent: 25.0,
+            finish_reason_window_seconds: 60,
+            finish_reason_min_samples: 8,
+            ..Default::default()
+        };
+        FinishReasonMonitor::new(&config).with_clock(clock)
+    }
+
+    #[test]
+    fn test_label_normalizes_reasons() {
+        assert_eq!(label(Some("stop")), "stop");
+        assert_eq!(label(Some("content_filter")), "content_filter");
+        assert_eq!(label(Some("something_new")), "other");
+        assert_eq!(label(None), "unknown");
+    }
+
+    #[test]
+    fn test_alert_needs_min_samples_and_fires_once() {
+        let clock = TestClock::new(1_700_000_000);
+        let monitor = monitor(clock.clone());
+
+        // 2 of 3 filtered is above 25%, but below the sample minimum
+        assert!(!monitor.observe("chat", "gpt-4o", Some("content_filter")));
+        assert!(!monitor.observe("chat", "gpt-4o", Some("content_filter")));
+        assert!(!monitor.observe("chat", "gpt-4o", Some("stop")));
+        for _ in 0..4 {
+            assert!(!monitor.observe("chat", "gpt-4o", Some("stop")));
+        }
+        // 8th response: 3 of 8 filtered (37.5%)
+        assert!(monitor.observe("chat", "gpt-4o", Some("content_filter")));
+        // Still above: no repeat
+        assert!(!monitor.observe("chat", "gpt-4o", Some("content_filter")));
+    }
+
+    #[test]
+    fn test_window_slides_and_alert_rearms() {
+        let clock = TestClock::new(1_700_000_000);
+        let monitor = monitor(clock.clone());
+
+        for _ in 0..8 {
+            monitor.observe("chat", "gpt-4o", Some("content_filter"));
+        }
+        // Old responses leave the window, and the share drops below the threshold
+        clock.advance(Duration::from_secs(61));
+        for _ in 0..8 {
+            assert!(!monitor.observe("chat", "gpt-4o", Some("stop")));
+        }
+        assert_eq!(monitor.window.lock().unwrap().total, 8);
+
+        // Rising again fires a new alert
+        let fired = (0..4)
+            .map(|_| monitor.observe("chat", "gpt-4o", Some("content_filter")))
+            .filter(|fired| *fired)
+            .count();
+        assert_eq!(fired, 1);
+    }
+
+    #[test]
+    fn test_zero_threshold_disables_alerts() {
+        let config = ProviderConfig {
+            finish_reason_alert_percent: 0.0,
+            finish_reason_min_samples: 1,
+            ..Default::default()
+        };
+        let monitor = FinishReasonMonitor::new(&config);
+        assert!(!monitor.observe("chat", "gpt-4o", Some("content_filter")));
+    }
+}
@@ -1,13 +1,21 @@
 //! Header utilities for AI provider proxying
 //!
 //! Provides secure header filtering to ensure internal authentication tokens
-//! are never forwarded to external AI providers.
+//! are never forwarded to external AI providers, and strips headers from
+//! provider responses that only describe the upstream connection, set
+//! cookies, or exceed the configured count and size limits.
 
 use axum::http::header::{self, HeaderName};
 use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use tracing::warn;
+
+use crate::config::ProviderConfig;
+
+/// Response header marking that upstream headers were dropped by the limits
+pub const HEADERS_TRUNCATED_HEADER: &str = "x-sentinel-headers-truncated";
 
 /// Hop-by-hop headers that must never be forwarded
-const HOP_BY_HOP_HEADERS: &[HeaderName] = &[
+static HOP_BY_HOP_HEADERS: [HeaderName; 9] = [
     header::CONNECTION,
     header::PROXY_AUTHENTICATE,
     header::PROXY_AUTHORIZATION,
@@ -15,8 +23,37 @@ const HOP_BY_HOP_HEADERS: &[HeaderName] = &[
     header::TRAILER,
     header::TRANSFER_ENCODING,
     header::UPGRADE,
+    HeaderName::from_static("keep-alive"),
+    HeaderName::from_static("proxy-connection"),
 ];
 
+/// Limits on the upstream response headers forwarded to clients
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResponseHeaderLimits {
+    /// Most headers forwarded (each value of a repeated header counts)
+    pub max_headers: usize,
+    /// Most bytes forwarded, counting names and values
+    pub max_bytes: usize,
+    /// Whether `Set-Cookie` is forwarded (Sentinel is not a cookie-bearing origin)
+    pub allow_set_cookie: bool,
+}
+
+impl ResponseHeaderLimits {
+    pub fn from_config(config: &ProviderConfig) -> Self {
+        Self {
+            max_headers: config.upstream_response_max_headers,
+            max_bytes: config.upstream_response_max_header_bytes,
+            allow_set_cookie: config.upstream_allow_set_cookie,
+        }
+    }
+}
+
+impl Default for ResponseHeaderLimits {
+    fn default() -> Self {
+        Self::from_config(&ProviderConfig::default())
+    }
+}
+
 /// Build default headers for AI provider requests
 ///
 /// This function creates a minimal set of headers for all requests to AI providers.
@@ -39,16 +76,63 @@ pub fn is_hop_by_hop_header(name: &HeaderName) -> bool {
     HOP_BY_HOP_HEADERS.contains(name)
 }
 
-/// Filter hop-by-hop headers from a response
+/// Headers nominated as hop-by-hop by the message's `Connection` header
+fn connection_nominated(headers: &HeaderMap) -> Vec<HeaderName> {
+    headers
+        .get_all(header::CONNECTION)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|token| HeaderName::from_bytes(token.trim().as_bytes()).ok())
+        .collect()
+}
+
+/// Filter a provider response's headers for the response sent to the client
 ///
-/// Used when converting provider responses back to client responses.
-pub fn filter_response_headers(response_headers: &HeaderMap) -> HeaderMap {
+/// Drops hop-by-hop headers, including any nominated by `Connection`, and
+/// `Content-Length`: the body is re-chunked (or was decoded) on the way
+/// through, so the upstream length no longer describes it. Callers that
+/// buffer the whole body set a fresh `Content-Length` themselves.
+///
+/// `Set-Cookie` is dropped unless `limits` allow it. Headers past the count
+/// or byte limit are dropped with a warning and the response is marked with
+/// `X-Sentinel-Headers-Truncated: true`; `Content-Type` is always kept so the
+/// client can still read the body.
+pub fn filter_response_headers(
+    response_headers: &HeaderMap,
+    limits: &ResponseHeaderLimits,
+) -> HeaderMap {
+    let nominated = connection_nominated(response_headers);
     let mut filtered = HeaderMap::new();
+    let mut bytes = 0;
+    let mut dropped = 0;
 
     for (name, value) in response_headers {
-        if !is_hop_by_hop_header(name) {
-            filtered.insert(name.clone(), value.clone());
+        if is_hop_by_hop_header(name) || nominated.contains(name) || name == header::CONTENT_LENGTH {
+            continue;
+        }
+        if name == header::SET_COOKIE && !limits.allow_set_cookie {
+            continue;
         }
+        let size = name.as_str().len() + value.len();
+        let within_limits = filtered.len() < limits.max_headers && bytes + size <= limits.max_bytes;
+        if !within_limits && name != header::CONTENT_TYPE {
+            dropped += 1;
+            continue;
+        }
+        bytes += size;
+        filtered.append(name.clone(), value.clone());
+    }
+
+    if dropped > 0 {
+        warn!(
+            dropped = dropped,
+            forwarded = filtered.len(),
+            max_headers = limits.max_headers,
+            max_bytes = limits.max_bytes,
+            "Upstream response headers over the limits were dropped"
+        );
+        filtered.insert(HEADERS_TRUNCATED_HEADER, HeaderValue::from_static("true"));
     }
 
     filtered
@@ -80,5 +164,84 @@ mod tests {
         assert!(is_hop_by_hop_header(&header::TRANSFER_ENCODING));
         assert!(!is_hop_by_hop_header(&header::CONTENT_TYPE));
         assert!(!is_hop_by_hop_header(&header::ACCEPT));
+        assert!(is_hop_by_hop_header(&HeaderName::from_static("keep-alive")));
+    }
+
+    #[test]
+    fn test_filter_response_headers_strips_connection_nominated_and_length() {
+        let mut upstream = HeaderMap::new();
+        upstream.insert(header::CONNECTION, HeaderValue::from_static("keep-alive, X-Custom-Hop"));
+        upstream.insert("keep-alive", HeaderValue::from_static("timeout=5"));
+        upstream.insert("x-custom-hop", HeaderValue::from_static("internal"));
+        upstream.insert(header::TRANSFER_ENCODING, HeaderValue::from_static("chunked"));
+        upstream.insert(header::CONTENT_LENGTH, HeaderValue::from_static("999"));
+        upstream.insert(header::CONTENT_TYPE, HeaderValue::from_static("audio/mpeg"));
+        upstream.append(header::SET_COOKIE, HeaderValue::from_static("a=1"));
+        upstream.append(header::SET_COOKIE, HeaderValue::from_static("b=2"));
+
+        let limits = ResponseHeaderLimits {
+            allow_set_cookie: true,
+            ..Default::default()
+        };
+        let filtered = filter_response_headers(&upstream, &limits);
+
+        let mut names: Vec<&str> = filtered.keys().map(HeaderName::as_str).collect();
+        names.sort();
+        assert_eq!(names, vec!["content-type", "set-cookie"]);
+        // Repeated headers are kept
+        assert_eq!(filtered.get_all(header::SET_COOKIE).iter().count(), 2);
+    }
+
+    #[test]
+    fn test_filter_response_headers_drops_set_cookie_by_default() {
+        let mut upstream = HeaderMap::new();
+        upstream.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        upstream.append(header::SET_COOKIE, HeaderValue::from_static("session=abc"));
+        upstream.append(header::SET_COOKIE, HeaderValue::from_static("__cf_bm=xyz"));
+
+        let filtered = filter_response_headers(&upstream, &ResponseHeaderLimits::default());
+
+        assert!(filtered.get(header::SET_COOKIE).is_none());
+        assert_eq!(filtered.len(), 1);
+        assert!(filtered.get(HEADERS_TRUNCATED_HEADER).is_none());
+    }
+
+    #[test]
+    fn test_filter_response_headers_caps_header_count() {
+        let mut upstream = HeaderMap::new();
+        for i in 0..300 {
+            upstream.append("x-tracking", HeaderValue::from_str(&format!("t{}", i)).unwrap());
+        }
+        upstream.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        let limits = ResponseHeaderLimits {
+            max_headers: 10,
+            ..Default::default()
+        };
+
+        let filtered = filter_response_headers(&upstream, &limits);
+
+        assert_eq!(filtered.get_all("x-tracking").iter().count(), 10);
+        assert_eq!(filtered[header::CONTENT_TYPE], "application/json");
+        assert_eq!(filtered[HEADERS_TRUNCATED_HEADER], "true");
+    }
+
+    #[test]
+    fn test_filter_response_headers_caps_header_bytes() {
+        let mut upstream = HeaderMap::new();
+        upstream.insert("x-small", HeaderValue::from_static("1"));
+        upstream.insert("x-large", HeaderValue::from_str(&"a".repeat(200)).unwrap());
+        upstream.insert("x-after", HeaderValue::from_static("2"));
+        let limits = ResponseHeaderLimits {
+            max_bytes: 100,
+            ..Default::default()
+        };
+
+        let filtered = filter_response_headers(&upstream, &limits);
+
+        // Only the header that would overflow is dropped
+        assert!(filtered.get("x-large").is_none());
+        assert_eq!(filtered["x-small"], "1");
+        assert_eq!(filtered["x-after"], "2");
+        assert_eq!(filtered[HEADERS_TRUNCATED_HEADER], "true");
     }
 }
@@ -3,12 +3,20 @@
 //! Provides structured logging with correlation IDs for tracing requests
 //! through the system, especially useful for debugging desktop app integration.
 
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Instant;
+
 use tracing::{debug, error, info, warn, Span};
 use uuid::Uuid;
 
+use crate::proxy::capture::UpstreamHeaders;
+use crate::proxy::complexity::RequestComplexity;
+use crate::routes::metrics;
+
 /// Truncate a string to at most `max_bytes` bytes, ensuring we don't split UTF-8 characters.
-fn truncate_utf8(s: &str, max_bytes: usize) -> &str {
+pub(crate) fn truncate_utf8(s: &str, max_bytes: usize) -> &str {
     if s.len() <= max_bytes {
         return s;
     }
@@ -20,6 +28,28 @@ fn truncate_utf8(s: &str, max_bytes: usize) -> &str {
     &s[..end]
 }
 
+/// Writer that only counts the bytes written to it
+struct ByteCounter(u64);
+
+impl io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Serialized size of a JSON value in bytes, without allocating the output
+pub fn json_len(value: &serde_json::Value) -> u64 {
+    let mut counter = ByteCounter(0);
+    // Writing to a counter cannot fail
+    let _ = serde_json::to_writer(&mut counter, value);
+    counter.0
+}
+
 /// Context for tracking a request through the system
 ///
 /// Provides correlation IDs and timing information for debugging
@@ -40,6 +70,20 @@ pub struct RequestContext {
     pub streaming: bool,
     /// User's external ID (for correlation with Zion)
     pub external_id: Option<String>,
+    /// Hashed user email (for payload warnings without logging the address)
+    pub user_hash: Option<String>,
+    /// Agent workflow the request belongs to (`X-Sentinel-Workflow-Id`)
+    pub workflow_id: Option<String>,
+    /// Size of the client's request body in bytes
+    pub request_bytes: u64,
+    /// Size of the body forwarded upstream after translation and injection
+    pub forwarded_bytes: u64,
+    /// Message count and content size of the client's request
+    pub complexity: RequestComplexity,
+    /// Response bytes sent to the client; shared by clones so streams can count as they forward
+    response_bytes: Arc<AtomicU64>,
+    /// Allow-listed upstream response headers; shared by clones like `response_bytes`
+    upstream_headers: Arc<Mutex<UpstreamHeaders>>,
 }
 
 impl RequestContext {
@@ -53,6 +97,13 @@ impl RequestContext {
             model: None,
             streaming: false,
             external_id: None,
+            user_hash: None,
+            workflow_id: None,
+            request_bytes: 0,
+            forwarded_bytes: 0,
+            complexity: RequestComplexity::default(),
+            response_bytes: Arc::new(AtomicU64::new(0)),
+            upstream_headers: Arc::new(Mutex::new(UpstreamHeaders::default())),
         }
     }
 
@@ -74,6 +125,93 @@ impl RequestContext {
         self
     }
 
+    /// Set the hashed user email
+    pub fn with_user_hash(mut self, user_hash: impl Into<String>) -> Self {
+        self.user_hash = Some(user_hash.into());
+        self
+    }
+
+    /// Set the workflow the request belongs to
+    pub fn with_workflow_id(mut self, workflow_id: Option<String>) -> Self {
+        self.workflow_id = workflow_id;
+        self
+    }
+
+    /// Set the size of the client's request body
+    pub fn with_request_bytes(mut self, bytes: u64) -> Self {
+        self.request_bytes = bytes;
+        self
+    }
+
+    /// Set the size of the body forwarded upstream
+    pub fn with_forwarded_bytes(mut self, bytes: u64) -> Self {
+        self.forwarded_bytes = bytes;
+        self
+    }
+
+    /// Set the complexity of the client's request
+    pub fn with_complexity(mut self, complexity: RequestComplexity) -> Self {
+        self.complexity = complexity;
+        self
+    }
+
+    /// Add bytes sent to the client
+    pub fn add_response_bytes(&self, bytes: u64) {
+        self.response_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Total bytes sent to the client so far
+    pub fn response_bytes(&self) -> u64 {
+        self.response_bytes.load(Ordering::Relaxed)
+    }
+
+    /// Store the captured upstream response headers
+    pub fn record_upstream_headers(&self, headers: UpstreamHeaders) {
+        *self.upstream_headers.lock().unwrap() = headers;
+    }
+
+    /// Captured upstream response headers
+    pub fn upstream_headers(&self) -> UpstreamHeaders {
+        self.upstream_headers.lock().unwrap().clone()
+    }
+
+    /// Export payload sizes and warn when they exceed the thresholds
+    ///
+    /// Call once the response is complete. Returns true if a warning was logged.
+    pub fn record_payload_sizes(&self, max_request_bytes: u64, max_response_bytes: u64) -> bool {
+        let response_bytes = self.response_bytes();
+        metrics::record_request_bytes(&self.endpoint, "client", self.request_bytes);
+        metrics::record_request_bytes(&self.endpoint, "forwarded", self.forwarded_bytes);
+        metrics::record_response_bytes(&self.endpoint, response_bytes);
+
+        let request_exceeded = self.request_bytes.max(self.forwarded_bytes) > max_request_bytes;
+        let response_exceeded = response_bytes > max_response_bytes;
+        if request_exceeded {
+            metrics::record_payload_warning(&self.endpoint, "request");
+        }
+        if response_exceeded {
+            metrics::record_payload_warning(&self.endpoint, "response");
+        }
+        if !request_exceeded && !response_exceeded {
+            return false;
+        }
+
+        warn!(
+            trace_id = %self.trace_id,
+            endpoint = %self.endpoint,
+            model = ?self.model,
+            streaming = %self.streaming,
+            user_hash = ?self.user_hash,
+            request_bytes = self.request_bytes,
+            forwarded_bytes = self.forwarded_bytes,
+            response_bytes = response_bytes,
+            max_request_bytes = max_request_bytes,
+            max_response_bytes = max_response_bytes,
+            "Payload size exceeded threshold"
+        );
+        true
+    }
+
     /// Get elapsed time in milliseconds
     pub fn elapsed_ms(&self) -> u128 {
         self.start_time.elapsed().as_millis()
@@ -88,6 +226,7 @@ impl RequestContext {
             model = ?self.model,
             streaming = %self.streaming,
             external_id = ?self.external_id,
+            workflow_id = ?self.workflow_id,
             "Request started"
         );
     }
@@ -190,6 +329,8 @@ impl RequestContext {
             tokens = ?tokens,
             elapsed_ms = %self.elapsed_ms(),
             external_id = ?self.external_id,
+            workflow_id = ?self.workflow_id,
+            upstream_headers = %self.upstream_headers(),
             "Request completed successfully"
         );
     }
@@ -201,6 +342,7 @@ impl RequestContext {
             provider = %self.provider,
             endpoint = %self.endpoint,
             elapsed_ms = %self.elapsed_ms(),
+            upstream_headers = %self.upstream_headers(),
             "Streaming response started"
         );
     }
@@ -256,6 +398,19 @@ impl RequestContext {
         );
     }
 
+    /// Log a followed upstream redirect
+    pub fn log_redirect(&self, status: u16, target_host: &str, hop: usize) {
+        info!(
+            trace_id = %self.trace_id,
+            provider = %self.provider,
+            endpoint = %self.endpoint,
+            status = %status,
+            target_host = %target_host,
+            hop = %hop,
+            "Following upstream redirect"
+        );
+    }
+
     /// Log timeout
     pub fn log_timeout(&self, timeout_ms: u64) {
         error!(
@@ -326,6 +481,33 @@ mod tests {
         assert!(ctx.elapsed_ms() >= 10);
     }
 
+    #[test]
+    fn test_response_bytes_shared_across_clones() {
+        let ctx = RequestContext::new("openai", "/v1/chat/completions");
+        let for_stream = ctx.clone();
+        for_stream.add_response_bytes(100);
+        for_stream.add_response_bytes(50);
+        assert_eq!(ctx.response_bytes(), 150);
+    }
+
+    #[test]
+    fn test_payload_warning_thresholds() {
+        let ctx = RequestContext::new("openai", "/v1/chat/completions").with_request_bytes(100)
+            .with_forwarded_bytes(120);
+        ctx.add_response_bytes(500);
+        assert!(!ctx.record_payload_sizes(1000, 1000));
+
+        // The forwarded body counts against the request threshold too
+        assert!(ctx.record_payload_sizes(110, 1000));
+        assert!(ctx.record_payload_sizes(1000, 400));
+    }
+
+    #[test]
+    fn test_json_len_matches_serialized_size() {
+        let value = serde_json::json!({"model": "gpt-4", "messages": [{"role": "user", "content": "héllo"}]});
+        assert_eq!(json_len(&value), serde_json::to_vec(&value).unwrap().len() as u64);
+    }
+
     #[test]
     fn test_truncate_utf8_short_string() {
         // String shorter than limit should be returned unchanged
@@ -5,12 +5,38 @@
 //! This module provides a generic abstraction layer for AI providers,
 //! allowing easy switching between different backends (OpenAI, Anthropic, etc.)
 
+pub mod anthropic;
+pub mod breaker;
+pub mod capabilities;
+pub mod capture;
+pub mod complexity;
+pub mod content_filter;
+pub mod deprecated;
+pub mod failover;
+pub mod fallback;
+pub mod finish_reason;
 pub mod headers;
 pub mod logging;
 pub mod openai;
+pub mod pool;
+pub mod progress;
 pub mod provider;
+pub mod reasoning;
+pub mod redact;
+pub mod redirect;
+pub mod registry;
+pub mod response_filter;
+pub mod sanitize;
+pub mod signing;
+pub mod snapshot;
+pub mod static_models;
+pub mod stream_accept;
+pub mod timeout;
+pub mod upstream_user;
+pub mod validation;
 
 pub use headers::{build_default_headers, is_hop_by_hop_header};
 pub use logging::RequestContext;
+pub use anthropic::AnthropicProvider;
 pub use openai::{OpenAIClient, OpenAIProvider};
 pub use provider::{AiProvider, ByteStream};
@@ -3,45 +3,103 @@
 //! Handles request forwarding to OpenAI's API with comprehensive logging
 //! and secure header handling.
 
+use std::sync::Arc;
+
 use async_trait::async_trait;
 use axum::body::Body;
-use axum::http::{HeaderName, Method, Response, StatusCode};
+use axum::http::{header, HeaderValue, Method, Response, StatusCode};
+use bytes::Bytes;
+use futures::StreamExt;
 use http_body_util::BodyExt;
-use reqwest::header::HeaderMap;
+use reqwest::header::{HeaderMap, CONTENT_TYPE, LOCATION};
+use reqwest::Url;
 use tracing::{debug, instrument};
 
 use crate::config::Config;
 use crate::error::{AppError, AppResult};
-use crate::proxy::headers::{build_default_headers, is_hop_by_hop_header};
+use crate::proxy::capture::{self, UpstreamHeaders};
+use crate::proxy::headers::{build_default_headers, filter_response_headers, ResponseHeaderLimits};
 use crate::proxy::logging::RequestContext;
+use crate::proxy::pool::{self, PoolLease, ProviderClients, UpstreamPool};
 use crate::proxy::provider::{AiProvider, ByteStream};
+use crate::proxy::{redact, redirect};
+use crate::proxy::signing::{self, RequestSigner};
+
+/// How requests are authenticated upstream
+enum UpstreamAuth {
+    /// `Authorization: Bearer` with the API key
+    Bearer(String),
+    /// Headers from a signer, computed per request
+    Signed(Arc<dyn RequestSigner>),
+}
 
 /// OpenAI API provider
 ///
 /// Implements the AiProvider trait for OpenAI's API, handling all communication
 /// with proper logging and secure header filtering.
 pub struct OpenAIProvider {
-    client: reqwest::Client,
+    clients: ProviderClients,
     base_url: String,
-    api_key: String,
+    auth: UpstreamAuth,
+    /// Upstream response headers kept for logs (`UPSTREAM_CAPTURE_HEADERS`)
+    capture_headers: Vec<String>,
+    /// Limits on the upstream headers forwarded by `forward_raw`
+    response_header_limits: ResponseHeaderLimits,
 }
 
 impl OpenAIProvider {
     /// Create a new OpenAI provider
     ///
+    /// Streaming requests are sent through the streaming client and everything
+    /// else through the short one (see [`pool`]); a single `reqwest::Client`
+    /// serves both. The clients should not follow redirects themselves (see
+    /// [`redirect::provider_client`]); the provider follows them manually.
+    ///
     /// # Panics
     ///
     /// Panics if OPENAI_API_KEY is not configured.
-    pub fn new(client: reqwest::Client, config: &Config) -> Self {
+    pub fn new(clients: impl Into<ProviderClients>, config: &Config) -> Self {
         let api_key = config
+            .provider
             .openai_api_key
             .clone()
             .expect("OPENAI_API_KEY must be configured");
 
         Self {
-            client,
-            base_url: config.openai_api_url.clone(),
-            api_key,
+            clients: clients.into(),
+            base_url: config.provider.openai_api_url.clone(),
+            auth: UpstreamAuth::Bearer(api_key),
+            capture_headers: config.provider.upstream_capture_headers.clone(),
+            response_header_limits: ResponseHeaderLimits::from_config(&config.provider),
+        }
+    }
+
+    /// Create a provider that signs its requests instead of sending an API key
+    ///
+    /// See [`signing::signer_from_config`].
+    pub fn with_signer(
+        clients: impl Into<ProviderClients>,
+        config: &Config,
+        signer: Arc<dyn RequestSigner>,
+    ) -> Self {
+        Self {
+            clients: clients.into(),
+            base_url: config.provider.openai_api_url.clone(),
+            auth: UpstreamAuth::Signed(signer),
+            capture_headers: config.provider.upstream_capture_headers.clone(),
+            response_header_limits: ResponseHeaderLimits::from_config(&config.provider),
+        }
+    }
+
+    /// Headers sent with every request (signature headers are added in `send`)
+    fn default_headers(&self) -> HeaderMap {
+        match &self.auth {
+            UpstreamAuth::Bearer(api_key) => build_default_headers(api_key),
+            UpstreamAuth::Signed(_) => {
+                let mut headers = HeaderMap::new();
+                headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+                headers
+            }
         }
     }
 
@@ -54,6 +112,72 @@ impl OpenAIProvider {
         true
     }
 
+    /// Send a request, following same-origin 307/308 redirects
+    ///
+    /// The auth headers are re-attached on every hop, and signed requests are
+    /// re-signed for the hop's URL over the buffered body. Streaming requests are
+    /// never redirected; refused redirects become an `UpstreamError`. The final
+    /// response's allow-listed headers are captured into `ctx` and published
+    /// to any enclosing [`capture::capture`] scope. The request goes through
+    /// the client of `pool`; callers hold a [`PoolLease`] while it runs.
+    async fn send(
+        &self,
+        method: reqwest::Method,
+        url: &str,
+        headers: HeaderMap,
+        body: Option<Bytes>,
+        pool: UpstreamPool,
+        ctx: &RequestContext,
+    ) -> AppResult<reqwest::Response> {
+        let mut url = Url::parse(url).map_err(|e| {
+            AppError::Internal(anyhow::anyhow!("Invalid upstream URL '{}': {}", url, e))
+        })?;
+        let mut hops = 0;
+        let body_sha256 = match self.auth {
+            UpstreamAuth::Signed(_) => signing::body_sha256(body.as_deref()),
+            UpstreamAuth::Bearer(_) => String::new(),
+        };
+
+        loop {
+            let mut hop_headers = headers.clone();
+            if let UpstreamAuth::Signed(signer) = &self.auth {
+                hop_headers.extend(signer.sign(&method, &url, &headers, &body_sha256)?);
+            }
+            let mut request = self
+                .clients
+                .client(pool)
+                .request(method.clone(), url.clone())
+                .headers(hop_headers);
+            if let Some(ref body) = body {
+                request = request.body(body.clone());
+            }
+
+            let response = request
+                .send()
+                .await
+                .inspect_err(|e| ctx.log_connection_error(&e.to_string(), url.as_str()))?;
+
+            let status = response.status();
+            if !redirect::is_followable_redirect(status) {
+                let upstream = UpstreamHeaders::from_response(response.headers(), &self.capture_headers);
+                capture::publish(&upstream);
+                ctx.record_upstream_headers(upstream);
+                return Ok(response);
+            }
+
+            let location = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|value| value.to_str().ok());
+            let target = redirect::redirect_target(&url, location, hops, ctx.streaming)
+                .inspect_err(|e| ctx.log_error(&e.to_string()))?;
+
+            hops += 1;
+            ctx.log_redirect(status.as_u16(), target.host_str().unwrap_or("unknown"), hops);
+            url = target;
+        }
+    }
+
     /// Make a POST request (non-streaming)
     async fn post(
         &self,
@@ -63,28 +187,22 @@ impl OpenAIProvider {
     ) -> AppResult<serde_json::Value> {
         let url = format!("{}{}", self.base_url, endpoint);
 
-        let headers = build_default_headers(&self.api_key);
+        let headers = self.default_headers();
         ctx.log_headers_prepared(headers.len());
         ctx.log_upstream_request(&url, None);
 
+        let body = Bytes::from(serde_json::to_vec(body)?);
+        let _lease = self.clients.lease(UpstreamPool::Short);
         let response = self
-            .client
-            .post(&url)
-            .headers(headers)
-            .json(body)
-            .send()
-            .await
-            .map_err(|e| {
-                ctx.log_connection_error(&e.to_string(), &url);
-                e
-            })?;
+            .send(reqwest::Method::POST, &url, headers, Some(body), UpstreamPool::Short, ctx)
+            .await?;
 
         let status = response.status();
         let content_length = response.content_length();
         ctx.log_upstream_response(status.as_u16(), content_length);
 
         if !status.is_success() {
-            let text = response.text().await.unwrap_or_default();
+            let text = redact::redact(&response.text().await.unwrap_or_default()).into_owned();
             ctx.log_error(&format!("OpenAI error {}: {}", status, text));
             return Err(AppError::UpstreamError(format!(
                 "OpenAI error {}: {}",
@@ -117,27 +235,21 @@ impl OpenAIProvider {
     ) -> AppResult<ByteStream> {
         let url = format!("{}{}", self.base_url, endpoint);
 
-        let headers = build_default_headers(&self.api_key);
+        let headers = self.default_headers();
         ctx.log_headers_prepared(headers.len());
         ctx.log_upstream_request(&url, None);
 
+        let body = Bytes::from(serde_json::to_vec(body)?);
+        let lease = self.clients.lease(UpstreamPool::Streaming);
         let response = self
-            .client
-            .post(&url)
-            .headers(headers)
-            .json(body)
-            .send()
-            .await
-            .map_err(|e| {
-                ctx.log_connection_error(&e.to_string(), &url);
-                e
-            })?;
+            .send(reqwest::Method::POST, &url, headers, Some(body), UpstreamPool::Streaming, ctx)
+            .await?;
 
         let status = response.status();
         ctx.log_upstream_response(status.as_u16(), None);
 
         if !status.is_success() {
-            let text = response.text().await.unwrap_or_default();
+            let text = redact::redact(&response.text().await.unwrap_or_default()).into_owned();
             ctx.log_error(&format!("OpenAI error {}: {}", status, text));
             return Err(AppError::UpstreamError(format!(
                 "OpenAI error {}: {}",
@@ -146,34 +258,30 @@ impl OpenAIProvider {
         }
 
         ctx.log_stream_started();
-        Ok(Box::pin(response.bytes_stream()))
+        Ok(Box::pin(
+            leased_stream(response, lease).map(|chunk| chunk.map(redact::redact_error_chunk)),
+        ))
     }
 
     /// Make a GET request
     async fn get(&self, endpoint: &str, ctx: &RequestContext) -> AppResult<serde_json::Value> {
         let url = format!("{}{}", self.base_url, endpoint);
 
-        let headers = build_default_headers(&self.api_key);
+        let headers = self.default_headers();
         ctx.log_headers_prepared(headers.len());
         ctx.log_upstream_request(&url, None);
 
+        let _lease = self.clients.lease(UpstreamPool::Short);
         let response = self
-            .client
-            .get(&url)
-            .headers(headers)
-            .send()
-            .await
-            .map_err(|e| {
-                ctx.log_connection_error(&e.to_string(), &url);
-                e
-            })?;
+            .send(reqwest::Method::GET, &url, headers, None, UpstreamPool::Short, ctx)
+            .await?;
 
         let status = response.status();
         let content_length = response.content_length();
         ctx.log_upstream_response(status.as_u16(), content_length);
 
         if !status.is_success() {
-            let text = response.text().await.unwrap_or_default();
+            let text = redact::redact(&response.text().await.unwrap_or_default()).into_owned();
             ctx.log_error(&format!("OpenAI error {}: {}", status, text));
             return Err(AppError::UpstreamError(format!(
                 "OpenAI error {}: {}",
@@ -192,29 +300,28 @@ impl OpenAIProvider {
     }
 
     /// Convert reqwest Response to axum Response
-    async fn convert_response(&self, response: reqwest::Response) -> AppResult<Response<Body>> {
+    ///
+    /// The body is re-streamed, so the upstream `Content-Length` is dropped
+    /// along with hop-by-hop headers and the client response is chunked.
+    /// The pool lease is released once the body is done.
+    async fn convert_response(
+        &self,
+        response: reqwest::Response,
+        lease: PoolLease,
+    ) -> AppResult<Response<Body>> {
         let status = StatusCode::from_u16(response.status().as_u16())
             .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
-        let response_headers = response.headers().clone();
+        let response_headers = filter_response_headers(response.headers(), &self.response_header_limits);
 
         // Stream the response body
-        let body = Body::from_stream(response.bytes_stream());
-
-        // Build axum response
-        let mut builder = Response::builder().status(status);
-
-        // Copy headers, filtering out hop-by-hop headers
-        for (name, value) in response_headers.iter() {
-            if !is_hop_by_hop_header(name) {
-                if let Ok(header_name) = HeaderName::from_bytes(name.as_ref()) {
-                    builder = builder.header(header_name, value.as_bytes());
-                }
-            }
-        }
+        let body = Body::from_stream(leased_stream(response, lease));
 
-        builder
+        let mut axum_response = Response::builder()
+            .status(status)
             .body(body)
-            .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to build response: {}", e)))
+            .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to build response: {}", e)))?;
+        *axum_response.headers_mut() = response_headers;
+        Ok(axum_response)
     }
 }
 
@@ -329,7 +436,7 @@ impl AiProvider for OpenAIProvider {
         let url = format!("{}{}", self.base_url, path);
 
         // Build headers using the secure filtering
-        let headers = build_default_headers(&self.api_key);
+        let headers = self.default_headers();
         ctx.log_headers_prepared(headers.len());
 
         // Convert axum Body to bytes for reqwest
@@ -344,23 +451,22 @@ impl AiProvider for OpenAIProvider {
 
         ctx.log_upstream_request(&url, Some(body_bytes.len()));
 
-        // Build the request
-        let mut request_builder = self.client.request(
-            reqwest::Method::from_bytes(method.as_str().as_bytes())
-                .unwrap_or(reqwest::Method::POST),
-            &url,
-        );
-        request_builder = request_builder.headers(headers);
-
         // Only add body for methods that support it
-        if method != Method::GET && method != Method::HEAD {
-            request_builder = request_builder.body(body_bytes.to_vec());
-        }
-
-        let response = request_builder.send().await.map_err(|e| {
-            ctx.log_connection_error(&e.to_string(), &url);
-            e
-        })?;
+        let body = (method != Method::GET && method != Method::HEAD).then_some(body_bytes);
+        let upstream_pool =
+            UpstreamPool::for_request(body.as_deref().is_some_and(pool::requests_stream));
+        let lease = self.clients.lease(upstream_pool);
+        let response = self
+            .send(
+                reqwest::Method::from_bytes(method.as_str().as_bytes())
+                    .unwrap_or(reqwest::Method::POST),
+                &url,
+                headers,
+                body,
+                upstream_pool,
+                &ctx,
+            )
+            .await?;
 
         let status = response.status();
         let content_length = response.content_length();
@@ -368,27 +474,21 @@ impl AiProvider for OpenAIProvider {
 
         // If error status, log the error body and forward it to client
         if status.is_client_error() || status.is_server_error() {
-            let response_headers = response.headers().clone();
-
-            // Read the error body to log it
-            let error_body = response.text().await.unwrap_or_else(|_| "Failed to read error body".to_string());
-            ctx.log_upstream_error_body(status.as_u16(), &error_body);
+            let response_headers = filter_response_headers(response.headers(), &self.response_header_limits);
+
+            // Read the error body to log it, keeping the exact bytes (minus secrets) for the client
+            let error_body = redact::redact_bytes(
+                response
+                    .bytes()
+                    .await
+                    .unwrap_or_else(|_| Bytes::from_static(b"Failed to read error body")),
+            );
+            ctx.log_upstream_error_body(status.as_u16(), &String::from_utf8_lossy(&error_body));
 
             // Reconstruct the response with the body we already read, preserving relevant headers
             let axum_status = StatusCode::from_u16(status.as_u16())
                 .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
 
-            let mut builder = Response::builder().status(axum_status);
-
-            // Copy relevant headers from the upstream error response
-            for (name, value) in response_headers.iter() {
-                if !is_hop_by_hop_header(name) {
-                    if let Ok(header_name) = HeaderName::from_bytes(name.as_ref()) {
-                        builder = builder.header(header_name, value.as_bytes());
-                    }
-                }
-            }
-
             debug!(
                 trace_id = %ctx.trace_id,
                 status = %axum_status,
@@ -396,21 +496,39 @@ impl AiProvider for OpenAIProvider {
                 "Forwarding error response to client"
             );
 
-            let axum_response = builder
+            // The body is fully buffered, so its length is known
+            let body_len = error_body.len();
+            let mut axum_response = Response::builder()
+                .status(axum_status)
                 .body(Body::from(error_body))
                 .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to build error response: {}", e)))?;
+            *axum_response.headers_mut() = response_headers;
+            axum_response
+                .headers_mut()
+                .insert(header::CONTENT_LENGTH, HeaderValue::from(body_len));
 
             return Ok(axum_response);
         }
 
         // Convert and return the response
-        let axum_response = self.convert_response(response).await?;
+        let axum_response = self.convert_response(response, lease).await?;
         ctx.log_request_complete(None);
 
         Ok(axum_response)
     }
 }
 
+/// Body of `response`, holding `lease` until the body is done or dropped
+pub(crate) fn leased_stream(
+    response: reqwest::Response,
+    lease: PoolLease,
+) -> impl futures::Stream<Item = reqwest::Result<Bytes>> + Send {
+    response.bytes_stream().map(move |chunk| {
+        let _leased = &lease;
+        chunk
+    })
+}
+
 // Keep the old OpenAIClient for backwards compatibility during migration
 // TODO: Remove after all routes are migrated to use AiProvider trait
 pub use OpenAIProvider as OpenAIClient;
@@ -0,0 +1,259 @@
+//! Separate upstream connection pools for streaming and short calls
+//!
+//! Streaming responses hold their upstream connection for minutes. With one
+//! shared client, a burst of streams occupies the pooled connections (and,
+//! over HTTP/2, the concurrent stream slots of each connection) that
+//! embeddings and non-streaming completions are waiting for. The provider
+//! therefore sends through two clients: a streaming one that keeps many
+//! connections alive for long, and a short one with a small pool and tight
+//! connect/idle timeouts. Both are built from [`base_builder`], so proxy, TLS
+//! and redirect settings are identical.
+//!
+//! Requests in flight per pool are exported as
+//! `sentinel_upstream_pool_in_flight`, next to the pool size in
+//! `sentinel_upstream_pool_max_idle`.
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::config::ProviderConfig;
+use crate::routes::metrics::{record_upstream_pool_in_flight, record_upstream_pool_max_idle};
+
+/// Overall timeout of both clients; client-requested timeouts are shorter
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Which client an upstream request is sent through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpstreamPool {
+    /// Streaming responses
+    Streaming,
+    /// Everything else: non-streaming completions, embeddings, models
+    Short,
+}
+
+impl UpstreamPool {
+    /// Pool for a request that does or doesn't stream its response
+    pub fn for_request(streaming: bool) -> Self {
+        if streaming {
+            Self::Streaming
+        } else {
+            Self::Short
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Streaming => "streaming",
+            Self::Short => "short",
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            Self::Streaming => 0,
+            Self::Short => 1,
+        }
+    }
+}
+
+/// Connection pool tuning of one client
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolSettings {
+    pub max_idle_per_host: usize,
+    pub idle_timeout: Duration,
+    pub connect_timeout: Duration,
+}
+
+impl PoolSettings {
+    /// Many connections, kept alive long between streams
+    pub fn streaming(config: &ProviderConfig) -> Self {
+        Self {
+            max_idle_per_host: config.upstream_stream_pool_max_idle,
+            idle_timeout: Duration::from_secs(300),
+            connect_timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// A small pool that gives up quickly on unreachable hosts
+    pub fn short(config: &ProviderConfig) -> Self {
+        Self {
+            max_idle_per_host: config.upstream_short_pool_max_idle,
+            idle_timeout: Duration::from_secs(30),
+            connect_timeout: Duration::from_millis(config.upstream_short_connect_timeout_ms),
+        }
+    }
+}
+
+/// Builder shared by both clients
+///
+/// Proxy and TLS settings come from reqwest's defaults (including the
+/// `HTTPS_PROXY` environment); anything configured here applies to both
+/// pools. Redirects are left to the provider (see [`super::redirect`]).
+pub fn base_builder() -> reqwest::ClientBuilder {
+    reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none())
+}
+
+fn build_client(settings: PoolSettings) -> reqwest::Result<reqwest::Client> {
+    base_builder()
+        .pool_max_idle_per_host(settings.max_idle_per_host)
+        .pool_idle_timeout(settings.idle_timeout)
+        .connect_timeout(settings.connect_timeout)
+        .tcp_keepalive(Duration::from_secs(30))
+        .build()
+}
+
+#[derive(Debug, Default)]
+struct PoolUsage {
+    in_flight: AtomicUsize,
+    requests: AtomicU64,
+}
+
+/// The provider's upstream clients, one per [`UpstreamPool`]
+#[derive(Debug, Clone)]
+pub struct ProviderClients {
+    streaming: reqwest::Client,
+    short: reqwest::Client,
+    usage: Arc<[PoolUsage; 2]>,
+}
+
+impl ProviderClients {
+    /// Build both clients with the pool settings from `config`
+    pub fn from_config(config: &ProviderConfig) -> reqwest::Result<Self> {
+        let streaming = PoolSettings::streaming(config);
+        let short = PoolSettings::short(config);
+        record_upstream_pool_max_idle(
+            UpstreamPool::Streaming.as_str(),
+            streaming.max_idle_per_host,
+        );
+        record_upstream_pool_max_idle(UpstreamPool::Short.as_str(), short.max_idle_per_host);
+        Ok(Self {
+            streaming: build_client(streaming)?,
+            short: build_client(short)?,
+            usage: Arc::default(),
+        })
+    }
+
+    /// Client for requests in `pool`
+    pub fn client(&self, pool: UpstreamPool) -> &reqwest::Client {
+        match pool {
+            UpstreamPool::Streaming => &self.streaming,
+            UpstreamPool::Short => &self.short,
+        }
+    }
+
+    /// Count a request in `pool` until the lease is dropped
+    ///
+    /// For streams the lease should travel with the response body.
+    pub fn lease(&self, pool: UpstreamPool) -> PoolLease {
+        let usage = &self.usage[pool.index()];
+        usage.requests.fetch_add(1, Ordering::Relaxed);
+        let in_flight = usage.in_flight.fetch_add(1, Ordering::Relaxed) + 1;
+        record_upstream_pool_in_flight(pool.as_str(), in_flight);
+        PoolLease {
+            usage: self.usage.clone(),
+            pool,
+        }
+    }
+
+    /// Requests in flight in `pool`
+    pub fn in_flight(&self, pool: UpstreamPool) -> usize {
+        self.usage[pool.index()].in_flight.load(Ordering::Relaxed)
+    }
+
+    /// Requests sent through `pool` so far
+    pub fn requests(&self, pool: UpstreamPool) -> u64 {
+        self.usage[pool.index()].requests.load(Ordering::Relaxed)
+    }
+}
+
+/// Use one client for both pools (tests and single-client setups)
+///
+/// The client should not follow redirects (see [`base_builder`]).
+impl From<reqwest::Client> for ProviderClients {
+    fn from(client: reqwest::Client) -> Self {
+        Self {
+            streaming: client.clone(),
+            short: client,
+            usage: Arc::default(),
+        }
+    }
+}
+
+/// A request counted as in flight in its pool
+#[derive(Debug)]
+pub struct PoolLease {
+    usage: Arc<[PoolUsage; 2]>,
+    pool: UpstreamPool,
+}
+
+impl Drop for PoolLease {
+    fn drop(&mut self) {
+        let in_flight = self.usage[self.pool.index()]
+            .in_flight
+            .fetch_sub(1, Ordering::Relaxed)
+            - 1;
+        record_upstream_pool_in_flight(self.pool.as_str(), in_flight);
+    }
+}
+
+/// Whether a raw JSON request body asks for a streaming response
+///
+/// Pass-through bodies are forwarded as-is, so only the `stream` flag is
+/// read; anything unparseable counts as non-streaming.
+pub fn requests_stream(body: &[u8]) -> bool {
+    #[derive(Deserialize)]
+    struct StreamFlag {
+        #[serde(default)]
+        stream: Option<bool>,
+    }
+    serde_json::from_slice::<StreamFlag>(body)
+        .ok()
+        .and_then(|flag| flag.stream)
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool_settings_from_config() {
+        let config = ProviderConfig::default();
+        let streaming = PoolSettings::streaming(&config);
+        let short = PoolSettings::short(&config);
+        assert!(streaming.max_idle_per_host > short.max_idle_per_host);
+        assert!(streaming.idle_timeout > short.idle_timeout);
+        assert_eq!(short.connect_timeout, Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_leases_count_per_pool() {
+        let clients = ProviderClients::from_config(&ProviderConfig::default()).unwrap();
+        let stream = clients.lease(UpstreamPool::Streaming);
+        let short = clients.lease(UpstreamPool::Short);
+        let _second = clients.lease(UpstreamPool::Short);
+        assert_eq!(clients.in_flight(UpstreamPool::Streaming), 1);
+        assert_eq!(clients.in_flight(UpstreamPool::Short), 2);
+
+        drop(stream);
+        drop(short);
+        assert_eq!(clients.in_flight(UpstreamPool::Streaming), 0);
+        assert_eq!(clients.in_flight(UpstreamPool::Short), 1);
+        assert_eq!(clients.requests(UpstreamPool::Short), 2);
+    }
+
+    #[test]
+    fn test_requests_stream() {
+        assert!(requests_stream(br#"{"model": "gpt-4o", "stream": true}"#));
+        assert!(!requests_stream(br#"{"model": "gpt-4o", "stream": false}"#));
+        assert!(!requests_stream(br#"{"input": "text"}"#));
+        assert!(!requests_stream(br#"{"stream": null}"#));
+        assert!(!requests_stream(b"not json"));
+        assert!(!requests_stream(b""));
+    }
+}
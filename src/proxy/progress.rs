@@ -0,0 +1,179 @@
+//! Progress SSE for long non-streaming requests
+//!
+//! Complex-tier completions can take over a minute, longer than some client
+//! gateways keep an idle connection open. A non-streaming chat request sent
+//! with `X-Sentinel-Progress: sse` is answered at once with an SSE stream:
+//!
+//! ```text
+//! event: progress
+//! data: {"elapsed_ms":5000}
+//!
+//! event: result
+//! data: {...the non-streaming response body...}
+//!
+//! data: [DONE]
+//! ```
+//!
+//! A `progress` heartbeat is sent every `PROGRESS_INTERVAL_MS` while the
+//! upstream call is in flight. The request itself runs through the regular
+//! non-streaming handler in a background task, so usage is tracked the same
+//! way (even if the client goes away). A failed request ends with
+//! `event: error` carrying the usual JSON error envelope instead of `result`.
+//! Headers that are only known once the upstream answered
+//! (`X-Sentinel-Upstream-Model`, `X-Upstream-Request-Id`) can't be sent this way.
+
+use std::convert::Infallible;
+use std::future::Future;
+use std::time::Duration;
+
+use axum::{
+    body::{to_bytes, Body, Bytes},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde_json::json;
+use tokio::time::{Instant, MissedTickBehavior};
+use tracing::warn;
+
+use crate::streaming::sse_response;
+
+/// Request header that turns on progress SSE
+pub const PROGRESS_HEADER: &str = "X-Sentinel-Progress";
+
+/// Shortest heartbeat interval, whatever is configured
+const MIN_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Whether the client asked for progress SSE (`X-Sentinel-Progress: sse`)
+pub fn requested(headers: &HeaderMap) -> bool {
+    headers
+        .get(PROGRESS_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.trim().eq_ignore_ascii_case("sse"))
+}
+
+/// Run a non-streaming request in the background and stream its progress
+///
+/// Heartbeats are sent every `interval` until `request` finishes; its
+/// response body then becomes the `result` (2xx) or `error` event.
+pub fn progress_response<F, E>(request: F, interval: Duration) -> Response
+where
+    F: Future<Output = Result<Response, E>> + Send + 'static,
+    E: IntoResponse + Send + 'static,
+{
+    let interval = interval.max(MIN_INTERVAL);
+    let start = Instant::now();
+    let mut task =
+        tokio::spawn(async move { request.await.unwrap_or_else(IntoResponse::into_response) });
+
+    let stream = async_stream::stream! {
+        let mut heartbeat = tokio::time::interval_at(start + interval, interval);
+        heartbeat.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let response = loop {
+            tokio::select! {
+                joined = &mut task => break joined,
+                _ = heartbeat.tick() => {
+                    let elapsed_ms = start.elapsed().as_millis() as u64;
+                    yield Ok::<_, Infallible>(event("progress", &json!({"elapsed_ms": elapsed_ms}).to_string()));
+                }
+            }
+        };
+
+        let (name, body) = match response {
+            Ok(response) => {
+                let name = if response.status().is_success() { "result" } else { "error" };
+                match to_bytes(response.into_body(), usize::MAX).await {
+                    Ok(body) => (name, String::from_utf8_lossy(&body).into_owned()),
+                    Err(e) => ("error", internal_error(&format!("Failed to read response: {}", e))),
+                }
+            }
+            Err(e) => {
+                warn!(error = %e, "Progress SSE request task failed");
+                ("error", internal_error("Request failed"))
+            }
+        };
+        yield Ok(event(name, &body));
+        yield Ok(Bytes::from_static(b"data: [DONE]\n\n"));
+    };
+
+    sse_response(StatusCode::OK)
+        .body(Body::from_stream(stream))
+        .unwrap()
+}
+
+/// One named SSE event; `data` must be a single line (compact JSON)
+fn event(name: &str, data: &str) -> Bytes {
+    Bytes::from(format!("event: {}\ndata: {}\n\n", name, data))
+}
+
+/// Error envelope for failures outside the request handler
+fn internal_error(message: &str) -> String {
+    json!({
+        "error": {
+            "message": message,
+            "type": "internal_error",
+            "code": "internal_error",
+            "retryable": false,
+        }
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::streaming::SSE_CONTENT_TYPE;
+    use axum::{http::header, Json};
+
+    async fn collect(response: Response) -> String {
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_requested() {
+        let mut headers = HeaderMap::new();
+        assert!(!requested(&headers));
+        headers.insert(PROGRESS_HEADER, "SSE".parse().unwrap());
+        assert!(requested(&headers));
+        headers.insert(PROGRESS_HEADER, "json".parse().unwrap());
+        assert!(!requested(&headers));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_heartbeats_then_result() {
+        let request = async {
+            tokio::time::sleep(Duration::from_millis(250)).await;
+            Ok::<_, Infallible>(Json(json!({"id": "chatcmpl-1"})).into_response())
+        };
+        let response = progress_response(request, Duration::from_millis(100));
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            SSE_CONTENT_TYPE
+        );
+
+        assert_eq!(
+            collect(response).await,
+            concat!(
+                "event: progress\ndata: {\"elapsed_ms\":100}\n\n",
+                "event: progress\ndata: {\"elapsed_ms\":200}\n\n",
+                "event: result\ndata: {\"id\":\"chatcmpl-1\"}\n\n",
+                "data: [DONE]\n\n",
+            )
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_error_event() {
+        let request = async {
+            Err::<Response, _>((
+                StatusCode::BAD_GATEWAY,
+                Json(json!({"error": {"code": "upstream_error"}})),
+            ))
+        };
+        let body = collect(progress_response(request, Duration::from_secs(5))).await;
+        assert_eq!(
+            body,
+            "event: error\ndata: {\"error\":{\"code\":\"upstream_error\"}}\n\ndata: [DONE]\n\n"
+        );
+    }
+}
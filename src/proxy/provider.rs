@@ -9,12 +9,38 @@ use axum::http::{HeaderMap, Method, Response};
 use bytes::Bytes;
 use futures::Stream;
 use std::pin::Pin;
+use std::str::FromStr;
 
 use crate::error::AppResult;
 
 /// Stream type for streaming responses from AI providers
 pub type ByteStream = Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>> + Send>>;
 
+/// Which upstream the proxy talks to (`AI_PROVIDER`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProviderKind {
+    /// OpenAI or an OpenAI-compatible gateway at `OPENAI_API_URL`
+    #[default]
+    OpenAI,
+    /// Anthropic's Messages API at `ANTHROPIC_API_URL`
+    Anthropic,
+}
+
+impl FromStr for ProviderKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "openai" => Ok(ProviderKind::OpenAI),
+            "anthropic" => Ok(ProviderKind::Anthropic),
+            other => Err(format!(
+                "unknown AI provider '{}' (expected openai or anthropic)",
+                other
+            )),
+        }
+    }
+}
+
 /// Trait defining the interface for AI providers
 ///
 /// Implementations of this trait handle communication with specific AI backends
@@ -0,0 +1,104 @@
+//! Request adaptation for reasoning models
+//!
+//! o1/o3-style models reject `system` messages (they take `developer`),
+//! sampling parameters such as `temperature`, and `max_tokens` (replaced by
+//! `max_completion_tokens`). Models flagged `reasoning` in the tier config get
+//! their requests rewritten here before forwarding; stripped parameters are
+//! logged so callers can tell why their settings had no effect.
+
+use serde_json::Value;
+use tracing::warn;
+
+/// Sampling parameters reasoning models reject
+pub const UNSUPPORTED_PARAMS: &[&str] = &[
+    "temperature",
+    "top_p",
+    "presence_penalty",
+    "frequency_penalty",
+    "logprobs",
+    "top_logprobs",
+    "logit_bias",
+];
+
+/// Rewrite an OpenAI chat completion request for a reasoning model
+///
+/// Returns the names of the parameters that were stripped.
+pub fn adapt_chat_request(request: &mut Value) -> Vec<&'static str> {
+    let Some(obj) = request.as_object_mut() else {
+        return Vec::new();
+    };
+
+    if let Some(messages) = obj.get_mut("messages").and_then(Value::as_array_mut) {
+        for message in messages {
+            if message.get("role").and_then(Value::as_str) == Some("system") {
+                message["role"] = Value::from("developer");
+            }
+        }
+    }
+
+    let stripped: Vec<&'static str> = UNSUPPORTED_PARAMS
+        .iter()
+        .copied()
+        .filter(|param| obj.remove(*param).is_some())
+        .collect();
+
+    if let Some(max_tokens) = obj.remove("max_tokens") {
+        obj.entry("max_completion_tokens").or_insert(max_tokens);
+    }
+
+    stripped
+}
+
+/// Log the translation warning for stripped parameters, if any
+pub fn warn_stripped(model: &str, stripped: &[&str]) {
+    if !stripped.is_empty() {
+        warn!(
+            model = %model,
+            stripped = ?stripped,
+            "Stripped parameters unsupported by reasoning model"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_adapt_rewrites_roles_and_parameters() {
+        let mut request = json!({
+            "model": "o3-mini",
+            "messages": [
+                {"role": "system", "content": "Be brief"},
+                {"role": "user", "content": "Hi"}
+            ],
+            "temperature": 0.2,
+            "top_p": 0.9,
+            "max_tokens": 256,
+            "stream": true
+        });
+
+        let stripped = adapt_chat_request(&mut request);
+        assert_eq!(stripped, vec!["temperature", "top_p"]);
+        assert_eq!(request["messages"][0]["role"], "developer");
+        assert_eq!(request["messages"][1]["role"], "user");
+        assert_eq!(request["max_completion_tokens"], 256);
+        assert!(request.get("max_tokens").is_none());
+        assert!(request.get("temperature").is_none());
+        assert_eq!(request["stream"], true);
+    }
+
+    #[test]
+    fn test_explicit_max_completion_tokens_wins() {
+        let mut request = json!({
+            "messages": [],
+            "max_tokens": 100,
+            "max_completion_tokens": 500
+        });
+
+        assert!(adapt_chat_request(&mut request).is_empty());
+        assert_eq!(request["max_completion_tokens"], 500);
+        assert!(request.get("max_tokens").is_none());
+    }
+}
@@ -0,0 +1,248 @@
+//! Secret redaction for upstream error bodies
+//!
+//! Providers sometimes echo credentials back in error bodies (an OpenAI 401
+//! once quoted part of our API key). Before an upstream error body is logged
+//! or returned, occurrences of the configured provider keys, the Zion key and
+//! anything shaped like an `sk-` API key are replaced with
+//! `[redacted:<fingerprint>]`, the first 8 hex digits of the secret's SHA-256,
+//! so operators can tell which key leaked without seeing it.
+//!
+//! Scrubbing covers typed handler errors (when the `UpstreamError` is built
+//! and again when any error is rendered), pass-through error bodies and SSE
+//! error events. The redactor built from the config is installed process-wide
+//! by `AppState`; until then only the `sk-` pattern applies. Every redaction
+//! is counted in `sentinel_secrets_redacted_total` by `kind` (`configured`,
+//! `pattern`).
+
+use std::borrow::Cow;
+use std::sync::{Arc, RwLock};
+
+use bytes::Bytes;
+use metrics::counter;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use crate::config::Config;
+
+/// Configured secrets shorter than this are not redacted (too likely to
+/// match ordinary text)
+const MIN_SECRET_LEN: usize = 8;
+
+/// API keys in OpenAI's shape: `sk-` and 20+ alphanumerics, optionally after
+/// dash-separated prefixes (`sk-proj-...`, `sk-ant-api03-...`)
+static KEY_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\bsk-(?:[A-Za-z0-9]+-)*[A-Za-z0-9]{20,}").unwrap());
+
+static INSTALLED: Lazy<RwLock<Arc<SecretRedactor>>> = Lazy::new(RwLock::default);
+
+/// Replaces known secrets and key-shaped strings in text
+#[derive(Debug, Clone, Default)]
+pub struct SecretRedactor {
+    /// Secrets and their replacements, longest first
+    secrets: Vec<(String, String)>,
+}
+
+impl SecretRedactor {
+    /// Redact the given secrets (blank and short values are ignored)
+    pub fn new<I, S>(secrets: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut secrets: Vec<(String, String)> = secrets
+            .into_iter()
+            .map(|secret| secret.as_ref().trim().to_string())
+            .filter(|secret| secret.len() >= MIN_SECRET_LEN)
+            .map(|secret| {
+                let replacement = replacement(&secret);
+                (secret, replacement)
+            })
+            .collect();
+        secrets.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        secrets.dedup_by(|(a, _), (b, _)| a == b);
+        Self { secrets }
+    }
+
+    /// Redactor for the provider credentials and Zion key in `config`
+    pub fn from_config(config: &Config) -> Self {
+        let provider = &config.provider;
+        Self::new(
+            [
+                provider.openai_api_key.as_deref(),
+                provider.anthropic_api_key.as_deref(),
+                provider.openai_sigv4_secret_access_key.as_deref(),
+                provider.openai_sigv4_session_token.as_deref(),
+                Some(config.zion.api_key.as_str()),
+            ]
+            .into_iter()
+            .flatten(),
+        )
+    }
+
+    /// `text` with every secret replaced, borrowed when there was none
+    pub fn redact<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut text = Cow::Borrowed(text);
+        for (secret, replacement) in &self.secrets {
+            if text.contains(secret.as_str()) {
+                record_redacted("configured", replacement);
+                text = Cow::Owned(text.replace(secret.as_str(), replacement));
+            }
+        }
+        if KEY_PATTERN.is_match(&text) {
+            let redacted = KEY_PATTERN.replace_all(&text, |captures: &regex::Captures| {
+                let replacement = replacement(&captures[0]);
+                record_redacted("pattern", &replacement);
+                replacement
+            });
+            text = Cow::Owned(redacted.into_owned());
+        }
+        text
+    }
+}
+
+/// `[redacted:<fingerprint>]` for a secret
+fn replacement(secret: &str) -> String {
+    let digest = Sha256::digest(secret.as_bytes());
+    format!("[redacted:{}]", &hex::encode(digest)[..8])
+}
+
+fn record_redacted(kind: &'static str, replacement: &str) {
+    counter!("sentinel_secrets_redacted_total", "kind" => kind).increment(1);
+    warn!(kind, secret = %replacement, "Redacted a secret from an upstream error body");
+}
+
+/// Make `redactor` the one used by [`redact`]
+pub fn install(redactor: SecretRedactor) {
+    *INSTALLED.write().unwrap() = Arc::new(redactor);
+}
+
+/// Redact `text` with the installed redactor
+pub fn redact(text: &str) -> Cow<'_, str> {
+    let redactor = INSTALLED.read().unwrap().clone();
+    redactor.redact(text)
+}
+
+/// Redact a UTF-8 body with the installed redactor (other bodies pass unchanged)
+pub fn redact_bytes(body: Bytes) -> Bytes {
+    let Ok(text) = std::str::from_utf8(&body) else {
+        return body;
+    };
+    match redact(text) {
+        Cow::Borrowed(_) => body,
+        Cow::Owned(redacted) => Bytes::from(redacted),
+    }
+}
+
+/// Redact an SSE chunk when it carries an error event
+///
+/// Only chunks mentioning `"error"` are scanned, so model output that merely
+/// looks like a key is left alone.
+pub fn redact_error_chunk(chunk: Bytes) -> Bytes {
+    if !chunk.windows(7).any(|window| window == b"\"error\"") {
+        return chunk;
+    }
+    redact_bytes(chunk)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+
+    const OPENAI_KEY: &str = "sk-proj-Abc123Def456Ghi789Jkl012Mno345";
+    const ANTHROPIC_KEY: &str = "sk-ant-REDACTED";
+    const ZION_KEY: &str = "zion_live_6f1c2e9a";
+
+    fn redactor() -> SecretRedactor {
+        let mut config = test_config("http://zion.invalid", "http://provider.invalid/v1");
+        config.provider.openai_api_key = Some(OPENAI_KEY.to_string());
+        config.provider.anthropic_api_key = Some(ANTHROPIC_KEY.to_string());
+        config.provider.openai_sigv4_secret_access_key = Some("wJalrXUtnFEMI/K7MDENG".to_string());
+        config.zion.api_key = ZION_KEY.to_string();
+        SecretRedactor::from_config(&config)
+    }
+
+    #[test]
+    fn test_configured_secrets_are_redacted() {
+        let redactor = redactor();
+        for secret in [OPENAI_KEY, ANTHROPIC_KEY, ZION_KEY, "wJalrXUtnFEMI/K7MDENG"] {
+            let body = format!(
+                r#"{{"error": {{"message": "Incorrect API key provided: {}."}}}}"#,
+                secret
+            );
+            let redacted = redactor.redact(&body);
+            assert!(
+                !redacted.contains(secret),
+                "{} leaked: {}",
+                secret,
+                redacted
+            );
+            assert!(redacted.contains(&replacement(secret)));
+            assert!(redacted.ends_with(r#"."}}"#));
+        }
+    }
+
+    #[test]
+    fn test_key_shaped_strings_are_redacted() {
+        let redactor = SecretRedactor::default();
+        let leaked = "sk-abcdefghijklmnopqrstuvwxyz0123";
+        let partial = "sk-proj-Abc123Def456Ghi789Jk";
+        let body = format!("key {} and prefix {}", leaked, partial);
+        assert_eq!(
+            redactor.redact(&body),
+            format!(
+                "key {} and prefix {}",
+                replacement(leaked),
+                replacement(partial)
+            )
+        );
+    }
+
+    #[test]
+    fn test_near_misses_are_kept() {
+        let redactor = redactor();
+        for text in [
+            "sk-short",
+            "sk-this-is-not-a-real-key-at-all",
+            "task-abcdefghijklmnopqrstuvwxyz0123",
+            "ask-ABCDEFGHIJKLMNOPQRSTUVWXYZ",
+            "sk_abcdefghijklmnopqrstuvwxyz0123",
+            "zion_live_6f1c2e9",
+            "Incorrect API key provided: sk-proj-********************Mno345",
+        ] {
+            assert!(
+                matches!(redactor.redact(text), Cow::Borrowed(_)),
+                "{} was redacted",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn test_short_secrets_are_ignored() {
+        let redactor = SecretRedactor::new(["test", "", "  "]);
+        assert_eq!(redactor.redact("test body"), "test body");
+    }
+
+    #[test]
+    fn test_error_chunks_only() {
+        let key = "sk-abcdefghijklmnopqrstuvwxyz0123";
+        let content = Bytes::from(format!(
+            "data: {{\"choices\": [{{\"delta\": {{\"content\": \"{}\"}}}}]}}\n\n",
+            key
+        ));
+        assert_eq!(redact_error_chunk(content.clone()), content);
+
+        let error = Bytes::from(format!(
+            "data: {{\"error\": {{\"message\": \"{}\"}}}}\n\n",
+            key
+        ));
+        let redacted = redact_error_chunk(error);
+        assert!(!String::from_utf8_lossy(&redacted).contains(key));
+
+        let binary = Bytes::from_static(b"\xff\"error\" sk-abcdefghijklmnopqrstuvwxyz0123");
+        assert_eq!(redact_error_chunk(binary.clone()), binary);
+    }
+}
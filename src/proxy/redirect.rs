@@ -0,0 +1,112 @@
+//! Manual redirect handling for upstream calls
+//!
+//! Automatic redirect following drops the Authorization header and breaks
+//! streaming bodies, so the provider client never follows redirects itself.
+//! Instead 307/308 responses are followed by the provider under strict rules:
+//! same origin only, at most `MAX_REDIRECTS` hops, and never for streaming
+//! requests. Anything else surfaces as a descriptive upstream error (502).
+
+use reqwest::{StatusCode, Url};
+
+use crate::error::{AppError, AppResult};
+
+/// Maximum redirects followed for one upstream request
+pub const MAX_REDIRECTS: usize = 3;
+
+/// Build a single HTTP client for AI providers
+///
+/// Pooled like the shared client, but with automatic redirects disabled.
+/// Production uses separate streaming and short clients instead (see
+/// [`super::pool::ProviderClients`]).
+pub fn provider_client() -> reqwest::Result<reqwest::Client> {
+    super::pool::base_builder().pool_max_idle_per_host(100).build()
+}
+
+/// Whether a status is a redirect that preserves method and body
+pub fn is_followable_redirect(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TEMPORARY_REDIRECT | StatusCode::PERMANENT_REDIRECT
+    )
+}
+
+/// Resolve where a redirect from `current` should go, or why it must not be followed
+///
+/// `hops` is the number of redirects already followed for this request.
+pub fn redirect_target(
+    current: &Url,
+    location: Option<&str>,
+    hops: usize,
+    streaming: bool,
+) -> AppResult<Url> {
+    let location = location.ok_or_else(|| {
+        AppError::UpstreamError("Upstream redirect is missing a Location header".to_string())
+    })?;
+    let target = current.join(location).map_err(|e| {
+        AppError::UpstreamError(format!("Upstream redirect has an invalid Location '{}': {}", location, e))
+    })?;
+
+    if streaming {
+        return Err(AppError::UpstreamError(format!(
+            "Upstream redirected a streaming request to {}; point OPENAI_API_URL at the final URL",
+            target
+        )));
+    }
+    if target.origin() != current.origin() {
+        return Err(AppError::UpstreamError(format!(
+            "Upstream redirected to a different origin ({}); refusing to follow",
+            target.host_str().unwrap_or("unknown")
+        )));
+    }
+    if hops >= MAX_REDIRECTS {
+        return Err(AppError::UpstreamError(format!(
+            "Upstream exceeded {} redirects",
+            MAX_REDIRECTS
+        )));
+    }
+
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_only_307_and_308_are_followable() {
+        assert!(is_followable_redirect(StatusCode::TEMPORARY_REDIRECT));
+        assert!(is_followable_redirect(StatusCode::PERMANENT_REDIRECT));
+        assert!(!is_followable_redirect(StatusCode::FOUND));
+        assert!(!is_followable_redirect(StatusCode::OK));
+    }
+
+    #[test]
+    fn test_same_origin_relative_location_followed() {
+        let current = url("http://gateway.internal:8080/v1/chat/completions");
+        let target = redirect_target(&current, Some("/v2/chat/completions"), 0, false).unwrap();
+        assert_eq!(target.as_str(), "http://gateway.internal:8080/v2/chat/completions");
+    }
+
+    #[test]
+    fn test_cross_origin_refused() {
+        let current = url("http://gateway.internal:8080/v1/chat/completions");
+        let err = redirect_target(&current, Some("https://elsewhere.example/v1"), 0, false)
+            .unwrap_err();
+        assert!(err.to_string().contains("elsewhere.example"));
+
+        // A different port is a different origin
+        assert!(redirect_target(&current, Some("http://gateway.internal:9090/v1"), 0, false).is_err());
+    }
+
+    #[test]
+    fn test_streaming_and_hop_limit_refused() {
+        let current = url("http://gateway.internal/v1/chat/completions");
+        assert!(redirect_target(&current, Some("/v2/chat"), 0, true).is_err());
+        assert!(redirect_target(&current, Some("/v2/chat"), MAX_REDIRECTS, false).is_err());
+        assert!(redirect_target(&current, None, 0, false).is_err());
+    }
+}
@@ -0,0 +1,87 @@
+//! Named AI providers
+//!
+//! Handlers reach the upstream through [`crate::AppState::provider`], which
+//! returns the default provider unless the request runs inside a
+//! [`with_override`] scope. The canary override middleware opens that scope
+//! for allow-listed users who ask for a specific provider with
+//! `X-Sentinel-Provider`, so everything downstream (tier routing, usage
+//! tracking, logging) runs unchanged against the swapped provider.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+
+use crate::proxy::AiProvider;
+
+tokio::task_local! {
+    static OVERRIDE: Arc<dyn AiProvider>;
+}
+
+/// Providers selectable by name
+pub struct ProviderRegistry {
+    providers: RwLock<HashMap<String, Arc<dyn AiProvider>>>,
+}
+
+impl ProviderRegistry {
+    /// Create a registry holding the default provider under its own name
+    pub fn new(default: Arc<dyn AiProvider>) -> Self {
+        let registry = Self {
+            providers: RwLock::new(HashMap::new()),
+        };
+        registry.register(default.name(), default);
+        registry
+    }
+
+    /// Make a provider selectable as `name` (case-insensitive)
+    pub fn register(&self, name: &str, provider: Arc<dyn AiProvider>) {
+        self.providers
+            .write()
+            .unwrap()
+            .insert(name.to_ascii_lowercase(), provider);
+    }
+
+    /// Look up a provider by name (case-insensitive)
+    pub fn get(&self, name: &str) -> Option<Arc<dyn AiProvider>> {
+        self.providers
+            .read()
+            .unwrap()
+            .get(&name.to_ascii_lowercase())
+            .cloned()
+    }
+
+    /// Registered provider names, sorted
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.providers.read().unwrap().keys().cloned().collect();
+        names.sort();
+        names
+    }
+}
+
+/// Run `future` with `provider` replacing the default provider
+pub async fn with_override<F: Future>(provider: Arc<dyn AiProvider>, future: F) -> F::Output {
+    OVERRIDE.scope(provider, future).await
+}
+
+/// Provider override for the current request, if any
+pub fn current_override() -> Option<Arc<dyn AiProvider>> {
+    OVERRIDE.try_with(Arc::clone).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockAiProvider;
+
+    #[tokio::test]
+    async fn test_override_applies_only_inside_scope() {
+        let registry = ProviderRegistry::new(Arc::new(MockAiProvider::new()));
+        registry.register("Anthropic", Arc::new(MockAiProvider::new()));
+        assert_eq!(registry.names(), vec!["anthropic", "mock"]);
+
+        let canary = registry.get("ANTHROPIC").unwrap();
+        assert!(current_override().is_none());
+        let inside = with_override(canary.clone(), async { current_override() }).await;
+        assert!(Arc::ptr_eq(&inside.unwrap(), &canary));
+        assert!(current_override().is_none());
+    }
+}
@@ -0,0 +1,351 @@
+//! Reasoning content filtering for chat responses
+//!
+//! Some models return their chain of thought inline (`<thinking>...</thinking>`
+//! in the message content) or in a side field such as `reasoning_content`.
+//! Models flagged `stripReasoning` in the tier config have those removed
+//! before the response reaches the client. Token accounting always uses the
+//! unfiltered response: the provider bills for the reasoning either way.
+//!
+//! Streams are filtered per choice with a small state machine, so a tag split
+//! across deltas (`<thin` + `king>`) is still recognised. Text that could be
+//! the start of a tag is held back until the next delta settles it.
+
+use std::collections::HashMap;
+
+use bytes::Bytes;
+use serde_json::Value;
+
+use crate::config::ProviderConfig;
+use crate::tiers::TierConfig;
+
+/// Removes configured tag blocks and fields from chat responses
+#[derive(Debug, Clone)]
+pub struct ResponseFilter {
+    /// (open, close) pairs, e.g. (`<thinking>`, `</thinking>`)
+    tags: Vec<(String, String)>,
+    drop_fields: Vec<String>,
+}
+
+impl ResponseFilter {
+    /// Filter stripping `tags` blocks from content and dropping `drop_fields`
+    pub fn new(tags: &[String], drop_fields: &[String]) -> Self {
+        Self {
+            tags: tags
+                .iter()
+                .map(|tag| (format!("<{}>", tag), format!("</{}>", tag)))
+                .collect(),
+            drop_fields: drop_fields.to_vec(),
+        }
+    }
+
+    /// Filter for `model`, if the tier config flags it and anything is configured
+    pub fn for_model(
+        tier_config: Option<&TierConfig>,
+        config: &ProviderConfig,
+        model: &str,
+    ) -> Option<Self> {
+        let enabled = tier_config.is_some_and(|tiers| tiers.strips_reasoning(model));
+        let configured =
+            !config.response_strip_tags.is_empty() || !config.response_drop_fields.is_empty();
+        (enabled && configured)
+            .then(|| Self::new(&config.response_strip_tags, &config.response_drop_fields))
+    }
+
+    /// Strip tag blocks from complete text; an unterminated block runs to the end
+    pub fn strip_text(&self, text: &str) -> String {
+        let mut stripper = TagStripper::default();
+        let mut output = stripper.feed(&self.tags, text);
+        output.push_str(&stripper.finish());
+        output
+    }
+
+    /// Filter a non-streaming chat completion (`choices[].message`)
+    pub fn apply_to_response(&self, response: &mut Value) {
+        for message in choices_mut(response).filter_map(|choice| choice.get_mut("message")) {
+            self.drop_fields_from(message);
+            if let Some(Value::String(content)) = message.get_mut("content") {
+                *content = self.strip_text(content);
+            }
+        }
+    }
+
+    /// Stateful filter for one streamed response
+    pub fn stream(&self) -> StreamFilter {
+        StreamFilter {
+            filter: self.clone(),
+            strippers: HashMap::new(),
+        }
+    }
+
+    fn drop_fields_from(&self, object: &mut Value) {
+        if let Some(object) = object.as_object_mut() {
+            for field in &self.drop_fields {
+                object.remove(field);
+            }
+        }
+    }
+}
+
+/// Streaming counterpart of [`ResponseFilter`], one tag state per choice
+#[derive(Debug)]
+pub struct StreamFilter {
+    filter: ResponseFilter,
+    strippers: HashMap<u64, TagStripper>,
+}
+
+impl StreamFilter {
+    /// Filter one chunk (`choices[].delta`)
+    ///
+    /// Held-back text is released on the chunk carrying the choice's
+    /// `finish_reason`.
+    pub fn apply_to_chunk(&mut self, chunk: &mut Value) {
+        for choice in choices_mut(chunk) {
+            let index = choice.get("index").and_then(Value::as_u64).unwrap_or(0);
+            let finished = choice
+                .get("finish_reason")
+                .is_some_and(|reason| !reason.is_null());
+            let Some(delta) = choice.get_mut("delta").filter(|delta| delta.is_object()) else {
+                continue;
+            };
+            self.filter.drop_fields_from(delta);
+
+            let stripper = self.strippers.entry(index).or_default();
+            let mut content = match delta.get("content") {
+                Some(Value::String(content)) => Some(stripper.feed(&self.filter.tags, content)),
+                _ => None,
+            };
+            if finished {
+                let tail = stripper.finish();
+                if !tail.is_empty() {
+                    content.get_or_insert_with(String::new).push_str(&tail);
+                }
+            }
+            if let Some(content) = content {
+                delta["content"] = Value::String(content);
+            }
+        }
+    }
+
+    /// Re-encode complete SSE lines with each `data:` chunk filtered
+    ///
+    /// Lines that aren't JSON chunks (`[DONE]`, comments, unparseable data)
+    /// pass through unchanged.
+    pub fn rewrite_lines(&mut self, lines: &[String]) -> Bytes {
+        let mut output = String::new();
+        for line in lines {
+            match line.strip_prefix("data: ") {
+                Some(data) => {
+                    match serde_json::from_str::<Value>(data.trim()) {
+                        Ok(mut chunk) => {
+                            self.apply_to_chunk(&mut chunk);
+                            output.push_str("data: ");
+                            output.push_str(&chunk.to_string());
+                        }
+                        Err(_) => output.push_str(line),
+                    }
+                    // A data line ends its event
+                    output.push_str("\n\n");
+                }
+                None => {
+                    output.push_str(line);
+                    output.push('\n');
+                }
+            }
+        }
+        Bytes::from(output)
+    }
+}
+
+fn choices_mut(value: &mut Value) -> impl Iterator<Item = &mut Value> {
+    value
+        .get_mut("choices")
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+}
+
+/// Incremental tag block remover
+#[derive(Debug, Default)]
+struct TagStripper {
+    /// Index of the tag whose block we're inside
+    inside: Option<usize>,
+    /// Text held back because it may be the start of a tag
+    pending: String,
+}
+
+impl TagStripper {
+    /// Feed the next piece of text, returning what can be emitted now
+    fn feed(&mut self, tags: &[(String, String)], text: &str) -> String {
+        let input = std::mem::take(&mut self.pending) + text;
+        let mut rest = input.as_str();
+        let mut output = String::new();
+        loop {
+            match self.inside {
+                Some(tag) => {
+                    let close = tags[tag].1.as_str();
+                    match rest.find(close) {
+                        Some(pos) => {
+                            rest = &rest[pos + close.len()..];
+                            self.inside = None;
+                        }
+                        None => {
+                            let keep = partial_suffix_len(rest, [close]);
+                            self.pending = rest[rest.len() - keep..].to_string();
+                            return output;
+                        }
+                    }
+                }
+                None => {
+                    let next = tags
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(tag, (open, _))| {
+                            rest.find(open.as_str()).map(|pos| (pos, tag))
+                        })
+                        .min();
+                    match next {
+                        Some((pos, tag)) => {
+                            output.push_str(&rest[..pos]);
+                            rest = &rest[pos + tags[tag].0.len()..];
+                            self.inside = Some(tag);
+                        }
+                        None => {
+                            let keep = partial_suffix_len(
+                                rest,
+                                tags.iter().map(|(open, _)| open.as_str()),
+                            );
+                            let (emit, hold) = rest.split_at(rest.len() - keep);
+                            output.push_str(emit);
+                            self.pending = hold.to_string();
+                            return output;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// End of the text: release held-back text, or drop an unterminated block
+    fn finish(&mut self) -> String {
+        let pending = std::mem::take(&mut self.pending);
+        if self.inside.take().is_some() {
+            String::new()
+        } else {
+            pending
+        }
+    }
+}
+
+/// Length of the longest suffix of `text` that is a proper prefix of a pattern
+fn partial_suffix_len<'a>(text: &str, patterns: impl IntoIterator<Item = &'a str>) -> usize {
+    patterns
+        .into_iter()
+        .filter_map(|pattern| {
+            (1..pattern.len().min(text.len() + 1)).rev().find(|&len| {
+                pattern.is_char_boundary(len)
+                    && text.is_char_boundary(text.len() - len)
+                    && text.ends_with(&pattern[..len])
+            })
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn filter() -> ResponseFilter {
+        ResponseFilter::new(
+            &["thinking".to_string()],
+            &["reasoning_content".to_string()],
+        )
+    }
+
+    fn delta(content: &str) -> Value {
+        json!({"choices": [{"index": 0, "delta": {"content": content}, "finish_reason": null}]})
+    }
+
+    #[test]
+    fn test_strip_text() {
+        let filter = filter();
+        assert_eq!(
+            filter.strip_text("<thinking>plan</thinking>Answer"),
+            "Answer"
+        );
+        assert_eq!(
+            filter.strip_text("a<thinking>x</thinking>b<thinking>y</thinking>c"),
+            "abc"
+        );
+        assert_eq!(
+            filter.strip_text("1 < 2 and <b>bold</b>"),
+            "1 < 2 and <b>bold</b>"
+        );
+        assert_eq!(filter.strip_text("Answer<thinking>never closed"), "Answer");
+        assert_eq!(filter.strip_text("ends with <think"), "ends with <think");
+    }
+
+    #[test]
+    fn test_tag_split_across_three_chunks() {
+        let mut stream = filter().stream();
+        let mut output = String::new();
+        for piece in ["Hi <thin", "king>secret</thinki", "ng> there"] {
+            let mut chunk = delta(piece);
+            stream.apply_to_chunk(&mut chunk);
+            output.push_str(chunk["choices"][0]["delta"]["content"].as_str().unwrap());
+        }
+        assert_eq!(output, "Hi  there");
+    }
+
+    #[test]
+    fn test_non_ascii_tag_split_mid_character() {
+        let filter = ResponseFilter::new(&["思考".to_string()], &[]);
+        let mut stream = filter.stream();
+        let mut output = String::new();
+        for piece in ["Hi <思", "考>secret</思考> there"] {
+            let mut chunk = delta(piece);
+            stream.apply_to_chunk(&mut chunk);
+            output.push_str(chunk["choices"][0]["delta"]["content"].as_str().unwrap());
+        }
+        assert_eq!(output, "Hi  there");
+    }
+
+    #[test]
+    fn test_held_text_released_on_finish() {
+        let mut stream = filter().stream();
+        let mut chunk = delta("3 <");
+        stream.apply_to_chunk(&mut chunk);
+        assert_eq!(chunk["choices"][0]["delta"]["content"], "3 ");
+
+        let mut last = json!({"choices": [{"index": 0, "delta": {}, "finish_reason": "stop"}]});
+        stream.apply_to_chunk(&mut last);
+        assert_eq!(last["choices"][0]["delta"]["content"], "<");
+    }
+
+    #[test]
+    fn test_drops_fields_and_rewrites_lines() {
+        let mut stream = filter().stream();
+        let lines = vec![
+            r#"data: {"choices":[{"index":0,"delta":{"reasoning_content":"hmm","content":"ok"}}]}"#
+                .to_string(),
+            "data: [DONE]".to_string(),
+        ];
+        let output = String::from_utf8(stream.rewrite_lines(&lines).to_vec()).unwrap();
+        assert_eq!(
+            output,
+            "data: {\"choices\":[{\"delta\":{\"content\":\"ok\"},\"index\":0}]}\n\ndata: [DONE]\n\n"
+        );
+
+        let mut response = json!({"choices": [{"message": {
+            "role": "assistant",
+            "content": "<thinking>x</thinking>Done",
+            "reasoning_content": "x"
+        }}]});
+        filter().apply_to_response(&mut response);
+        assert_eq!(
+            response["choices"][0]["message"],
+            json!({"role": "assistant", "content": "Done"})
+        );
+    }
+}
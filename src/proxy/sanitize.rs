@@ -0,0 +1,238 @@
+//! Request content sanitization
+//!
+//! Badly encoded client data can carry NUL bytes and lone UTF-16 surrogate
+//! escapes (`"\ud800"`). serde_json refuses the latter, OpenAI answers both
+//! with unhelpful 400s, and a NUL in a logged string corrupts the log line.
+//! [`sanitize_json_text`] cleans the raw body before it is parsed: NUL bytes
+//! and `\u0000` escapes are removed, and surrogate escapes that aren't part
+//! of a valid pair become `\ufffd` (U+FFFD). With `CONTENT_NORMALIZE_NFC`,
+//! chat message text is also normalized to NFC after parsing.
+//!
+//! Token estimation and forwarding both see the sanitized content. Every
+//! change is counted in `sentinel_content_sanitized_total` and logged as a
+//! warning.
+
+use std::borrow::Cow;
+
+use serde::Serialize;
+use tracing::warn;
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+
+use crate::routes::metrics::record_content_sanitized;
+
+/// Replacement for lone surrogate escapes
+const REPLACEMENT_ESCAPE: &[u8] = br"\ufffd";
+
+/// Changes made to a request's content
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct SanitizeReport {
+    /// NUL bytes and `\u0000` escapes removed
+    pub nul_bytes: usize,
+    /// Lone surrogate escapes replaced with U+FFFD
+    pub lone_surrogates: usize,
+    /// Strings rewritten by NFC normalization
+    pub normalized: usize,
+}
+
+impl SanitizeReport {
+    /// Whether nothing was changed
+    pub fn is_clean(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Count the changes and log the translation warning, if any
+    pub fn record(&self, endpoint: &str) {
+        if self.is_clean() {
+            return;
+        }
+        for (kind, count) in [
+            ("nul", self.nul_bytes),
+            ("surrogate", self.lone_surrogates),
+            ("nfc", self.normalized),
+        ] {
+            record_content_sanitized(endpoint, kind, count);
+        }
+        warn!(
+            endpoint = %endpoint,
+            nul_bytes = self.nul_bytes,
+            lone_surrogates = self.lone_surrogates,
+            normalized = self.normalized,
+            "Sanitized request content before forwarding"
+        );
+    }
+}
+
+/// Remove NULs and replace lone surrogate escapes in a raw JSON body
+///
+/// Only string contents are rewritten, apart from stray NUL bytes, which are
+/// dropped wherever they appear. Malformed JSON is passed through for the
+/// parse to report. Borrows the body when there is nothing to change.
+pub fn sanitize_json_text(body: &[u8]) -> (Cow<'_, [u8]>, SanitizeReport) {
+    let mut report = SanitizeReport::default();
+    if !body.contains(&0) && !body.windows(2).any(|pair| pair == br"\u") {
+        return (Cow::Borrowed(body), report);
+    }
+
+    let mut output = Vec::with_capacity(body.len());
+    let mut in_string = false;
+    let mut i = 0;
+    while i < body.len() {
+        let byte = body[i];
+        if byte == 0 {
+            report.nul_bytes += 1;
+            i += 1;
+            continue;
+        }
+        if !in_string {
+            in_string = byte == b'"';
+            output.push(byte);
+            i += 1;
+            continue;
+        }
+        match byte {
+            b'"' => {
+                in_string = false;
+                output.push(byte);
+                i += 1;
+            }
+            b'\\' => match unicode_escape(body, i) {
+                Some(0) => {
+                    report.nul_bytes += 1;
+                    i += 6;
+                }
+                Some(0xD800..=0xDBFF)
+                    if matches!(unicode_escape(body, i + 6), Some(0xDC00..=0xDFFF)) =>
+                {
+                    output.extend_from_slice(&body[i..i + 12]);
+                    i += 12;
+                }
+                Some(0xD800..=0xDFFF) => {
+                    report.lone_surrogates += 1;
+                    output.extend_from_slice(REPLACEMENT_ESCAPE);
+                    i += 6;
+                }
+                Some(_) => {
+                    output.extend_from_slice(&body[i..i + 6]);
+                    i += 6;
+                }
+                // Any other escape is two bytes; a trailing backslash is left to the parse
+                None => {
+                    let end = (i + 2).min(body.len());
+                    output.extend_from_slice(&body[i..end]);
+                    i = end;
+                }
+            },
+            _ => {
+                output.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    if report.is_clean() {
+        (Cow::Borrowed(body), report)
+    } else {
+        (Cow::Owned(output), report)
+    }
+}
+
+/// Code unit of a `\uXXXX` escape starting at `at`
+fn unicode_escape(body: &[u8], at: usize) -> Option<u32> {
+    let escape = body.get(at..at + 6)?;
+    if &escape[..2] != br"\u" {
+        return None;
+    }
+    if !escape[2..].iter().all(u8::is_ascii_hexdigit) {
+        return None;
+    }
+    let hex = std::str::from_utf8(&escape[2..]).ok()?;
+    u32::from_str_radix(hex, 16).ok()
+}
+
+/// Normalize `text` to NFC, returning whether it changed
+pub fn normalize_nfc(text: &mut String) -> bool {
+    if is_nfc_quick(text.chars()) == IsNormalized::Yes {
+        return false;
+    }
+    let normalized: String = text.nfc().collect();
+    if normalized == *text {
+        return false;
+    }
+    *text = normalized;
+    true
+}
+
+/// Normalize each text to NFC, returning how many changed
+pub fn normalize_texts<'a>(texts: impl IntoIterator<Item = &'a mut String>) -> usize {
+    texts
+        .into_iter()
+        .map(normalize_nfc)
+        .filter(|&changed| changed)
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sanitize a fixture and parse the result
+    fn clean(body: &[u8]) -> (serde_json::Value, SanitizeReport) {
+        let (sanitized, report) = sanitize_json_text(body);
+        (serde_json::from_slice(&sanitized).unwrap(), report)
+    }
+
+    #[test]
+    fn test_clean_body_is_borrowed() {
+        let body = br#"{"messages": [{"role": "user", "content": "caf\u00e9 \ud83d\ude00"}]}"#;
+        let (sanitized, report) = sanitize_json_text(body);
+        assert!(matches!(sanitized, Cow::Borrowed(_)));
+        assert!(report.is_clean());
+    }
+
+    #[test]
+    fn test_nul_escapes_and_bytes_are_removed() {
+        let (value, report) = clean(b"{\"content\": \"a\\u0000b\x00c\"}\x00");
+        assert_eq!(value["content"], "abc");
+        assert_eq!(report.nul_bytes, 3);
+        assert_eq!(report.lone_surrogates, 0);
+    }
+
+    #[test]
+    fn test_lone_surrogates_are_replaced() {
+        let body = br#"{"a": "x\ud800y", "b": "\uDC00", "c": "\ud83d", "d": "\ud83d\ude00"}"#;
+        assert!(serde_json::from_slice::<serde_json::Value>(body).is_err());
+
+        let (value, report) = clean(body);
+        assert_eq!(value["a"], "x\u{FFFD}y");
+        assert_eq!(value["b"], "\u{FFFD}");
+        assert_eq!(value["c"], "\u{FFFD}");
+        assert_eq!(value["d"], "\u{1F600}");
+        assert_eq!(report.lone_surrogates, 3);
+    }
+
+    #[test]
+    fn test_escaped_backslashes_are_not_escapes() {
+        // `\\u0000` is a backslash followed by the text "u0000"
+        let body = br#"{"path": "C:\\u0000\\ud800", "key\u0000": 1}"#;
+        let (value, report) = clean(body);
+        assert_eq!(value["path"], r"C:\u0000\ud800");
+        assert_eq!(value["key"], 1);
+        assert_eq!(report.nul_bytes, 1);
+        assert_eq!(report.lone_surrogates, 0);
+    }
+
+    #[test]
+    fn test_malformed_json_is_left_to_the_parse() {
+        let (sanitized, _) = sanitize_json_text(br#"{"a": "\u12"#);
+        assert_eq!(&*sanitized, br#"{"a": "\u12"#);
+        assert!(serde_json::from_slice::<serde_json::Value>(&sanitized).is_err());
+    }
+
+    #[test]
+    fn test_normalize_nfc() {
+        let mut decomposed = "Cafe\u{301}".to_string();
+        assert!(normalize_nfc(&mut decomposed));
+        assert_eq!(decomposed, "Caf\u{e9}");
+        assert!(!normalize_nfc(&mut decomposed));
+    }
+}
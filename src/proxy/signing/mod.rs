@@ -0,0 +1,154 @@
+//! Upstream request signing
+//!
+//! Providers authenticate with a bearer key by default. Gateways that expect
+//! signed requests instead get a [`RequestSigner`], which the provider asks
+//! for extra headers on every hop (redirects change the URL being signed).
+//! Request bodies are always fully buffered before sending, streaming
+//! requests included, so the signature covers the whole body.
+//!
+//! The SigV4 signer lives behind the `sigv4` feature.
+
+#[cfg(feature = "sigv4")]
+mod sigv4;
+
+#[cfg(feature = "sigv4")]
+pub use sigv4::{SigV4Credentials, SigV4Signer};
+
+use std::str::FromStr;
+use std::sync::Arc;
+
+use reqwest::header::HeaderMap;
+use reqwest::{Method, Url};
+use sha2::{Digest, Sha256};
+
+use crate::clock::SharedClock;
+use crate::config::ProviderConfig;
+use crate::error::AppResult;
+
+/// How requests to the provider are authenticated (`OPENAI_AUTH_MODE`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AuthMode {
+    /// `Authorization: Bearer <OPENAI_API_KEY>`
+    #[default]
+    Bearer,
+    /// AWS Signature Version 4 (requires the `sigv4` feature)
+    Sigv4,
+}
+
+impl FromStr for AuthMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "bearer" => Ok(AuthMode::Bearer),
+            "sigv4" => Ok(AuthMode::Sigv4),
+            other => Err(format!(
+                "unknown auth mode '{}' (expected bearer or sigv4)",
+                other
+            )),
+        }
+    }
+}
+
+/// Produces authentication headers for an upstream request
+pub trait RequestSigner: Send + Sync {
+    /// Headers to add to a request with the given method, URL and headers
+    ///
+    /// `body_sha256` is the lowercase hex SHA-256 of the body (of the empty
+    /// string when there is none).
+    fn sign(
+        &self,
+        method: &Method,
+        url: &Url,
+        headers: &HeaderMap,
+        body_sha256: &str,
+    ) -> AppResult<HeaderMap>;
+}
+
+/// Lowercase hex SHA-256 of a request body
+pub fn body_sha256(body: Option<&[u8]>) -> String {
+    hex::encode(Sha256::digest(body.unwrap_or_default()))
+}
+
+/// Signer for the configured auth mode (None for bearer keys)
+///
+/// Fails when SigV4 is requested without its region and credentials, or in a
+/// build without the `sigv4` feature.
+pub fn signer_from_config(
+    config: &ProviderConfig,
+    clock: SharedClock,
+) -> anyhow::Result<Option<Arc<dyn RequestSigner>>> {
+    match config.openai_auth_mode {
+        AuthMode::Bearer => Ok(None),
+        #[cfg(feature = "sigv4")]
+        AuthMode::Sigv4 => {
+            let required = |value: &Option<String>, name: &str| {
+                value.clone().ok_or_else(|| {
+                    anyhow::anyhow!("{} must be set when OPENAI_AUTH_MODE=sigv4", name)
+                })
+            };
+            let credentials = SigV4Credentials {
+                access_key_id: required(
+                    &config.openai_sigv4_access_key_id,
+                    "OPENAI_SIGV4_ACCESS_KEY_ID",
+                )?,
+                secret_access_key: required(
+                    &config.openai_sigv4_secret_access_key,
+                    "OPENAI_SIGV4_SECRET_ACCESS_KEY",
+                )?,
+                session_token: config.openai_sigv4_session_token.clone(),
+            };
+            let region = required(&config.openai_sigv4_region, "OPENAI_SIGV4_REGION")?;
+            Ok(Some(Arc::new(SigV4Signer::new(
+                credentials,
+                region,
+                config.openai_sigv4_service.clone(),
+                clock,
+            ))))
+        }
+        #[cfg(not(feature = "sigv4"))]
+        AuthMode::Sigv4 => {
+            let _ = clock;
+            anyhow::bail!(
+                "OPENAI_AUTH_MODE=sigv4 requires Sentinel to be built with the `sigv4` feature"
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::system_clock;
+
+    #[test]
+    fn test_auth_mode_from_str() {
+        assert_eq!("bearer".parse::<AuthMode>(), Ok(AuthMode::Bearer));
+        assert_eq!(" SigV4 ".parse::<AuthMode>(), Ok(AuthMode::Sigv4));
+        assert!("basic".parse::<AuthMode>().is_err());
+    }
+
+    #[test]
+    fn test_body_sha256() {
+        assert_eq!(
+            body_sha256(None),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(body_sha256(Some(b"")), body_sha256(None));
+    }
+
+    #[test]
+    fn test_signer_from_config() {
+        let mut config = ProviderConfig::default();
+        assert!(signer_from_config(&config, system_clock())
+            .unwrap()
+            .is_none());
+
+        config.openai_auth_mode = AuthMode::Sigv4;
+        let error = signer_from_config(&config, system_clock()).err().unwrap();
+        #[cfg(feature = "sigv4")]
+        assert!(error.to_string().contains("OPENAI_SIGV4_ACCESS_KEY_ID"));
+        #[cfg(not(feature = "sigv4"))]
+        assert!(error.to_string().contains("`sigv4` feature"));
+    }
+}
@@ -0,0 +1,368 @@
+//! AWS Signature Version 4
+//!
+//! Signs the method, canonical URI and query, the request's headers plus
+//! `host` and `x-amz-date` (and `x-amz-security-token` for temporary
+//! credentials), and the body hash. The result is sent as `Authorization`
+//! next to `X-Amz-Date`. Paths are encoded twice, as every service except S3
+//! expects.
+
+use chrono::DateTime;
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use reqwest::{Method, Url};
+use sha2::{Digest, Sha256};
+
+use crate::clock::SharedClock;
+use crate::error::{AppError, AppResult};
+use crate::proxy::signing::RequestSigner;
+
+const ALGORITHM: &str = "AWS4-HMAC-SHA256";
+const X_AMZ_DATE: &str = "x-amz-date";
+const X_AMZ_SECURITY_TOKEN: &str = "x-amz-security-token";
+
+/// Static (or temporary, with a session token) AWS credentials
+#[derive(Clone)]
+pub struct SigV4Credentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+}
+
+impl std::fmt::Debug for SigV4Credentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SigV4Credentials")
+            .field("access_key_id", &self.access_key_id)
+            .field("secret_access_key", &"[redacted]")
+            .field(
+                "session_token",
+                &self.session_token.as_ref().map(|_| "[redacted]"),
+            )
+            .finish()
+    }
+}
+
+/// Signs requests for one region and service
+#[derive(Debug)]
+pub struct SigV4Signer {
+    credentials: SigV4Credentials,
+    region: String,
+    service: String,
+    clock: SharedClock,
+}
+
+impl SigV4Signer {
+    pub fn new(
+        credentials: SigV4Credentials,
+        region: impl Into<String>,
+        service: impl Into<String>,
+        clock: SharedClock,
+    ) -> Self {
+        Self {
+            credentials,
+            region: region.into(),
+            service: service.into(),
+            clock,
+        }
+    }
+
+    /// `Authorization` value for the canonical request at `amz_date`
+    fn signature(
+        &self,
+        method: &Method,
+        url: &Url,
+        headers: &[(String, String)],
+        body_sha256: &str,
+        amz_date: &str,
+    ) -> String {
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>()
+            .join(";");
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{}:{}\n", name, value))
+            .collect();
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            method.as_str(),
+            canonical_uri(url),
+            canonical_query(url),
+            canonical_headers,
+            signed_headers,
+            body_sha256
+        );
+
+        let date = &amz_date[..8];
+        let scope = format!("{}/{}/{}/aws4_request", date, self.region, self.service);
+        let string_to_sign = format!(
+            "{}\n{}\n{}\n{}",
+            ALGORITHM,
+            amz_date,
+            scope,
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let key = [date, &self.region, &self.service, "aws4_request"]
+            .iter()
+            .fold(
+                format!("AWS4{}", self.credentials.secret_access_key).into_bytes(),
+                |key, part| hmac_sha256(&key, part.as_bytes()),
+            );
+        let signature = hex::encode(hmac_sha256(&key, string_to_sign.as_bytes()));
+
+        format!(
+            "{} Credential={}/{}, SignedHeaders={}, Signature={}",
+            ALGORITHM, self.credentials.access_key_id, scope, signed_headers, signature
+        )
+    }
+}
+
+impl RequestSigner for SigV4Signer {
+    fn sign(
+        &self,
+        method: &Method,
+        url: &Url,
+        headers: &HeaderMap,
+        body_sha256: &str,
+    ) -> AppResult<HeaderMap> {
+        let amz_date = DateTime::from_timestamp(self.clock.now_unix(), 0)
+            .ok_or_else(|| AppError::Internal(anyhow::anyhow!("clock out of range for SigV4")))?
+            .format("%Y%m%dT%H%M%SZ")
+            .to_string();
+
+        let mut added = HeaderMap::new();
+        added.insert(X_AMZ_DATE, header_value(&amz_date)?);
+        if let Some(token) = &self.credentials.session_token {
+            added.insert(X_AMZ_SECURITY_TOKEN, header_value(token)?);
+        }
+
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+        let mut canonical = vec![("host".to_string(), host)];
+        for name in headers.keys().chain(added.keys()) {
+            if name == AUTHORIZATION || name.as_str() == "host" {
+                continue;
+            }
+            let values: Vec<String> = headers
+                .get_all(name)
+                .iter()
+                .chain(added.get_all(name).iter())
+                .map(|value| collapse_whitespace(&String::from_utf8_lossy(value.as_bytes())))
+                .collect();
+            canonical.push((name.as_str().to_string(), values.join(",")));
+        }
+        canonical.sort();
+        canonical.dedup_by(|a, b| a.0 == b.0);
+
+        let authorization = self.signature(method, url, &canonical, body_sha256, &amz_date);
+        added.insert(AUTHORIZATION, header_value(&authorization)?);
+        Ok(added)
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn header_value(value: &str) -> AppResult<HeaderValue> {
+    HeaderValue::from_str(value)
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("invalid SigV4 header value: {}", e)))
+}
+
+/// Trimmed, with runs of spaces collapsed to one
+fn collapse_whitespace(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// URI-encode everything but the unreserved characters (and `/` if asked)
+fn uri_encode(input: &str, keep_slash: bool) -> String {
+    let mut encoded = String::with_capacity(input.len());
+    for byte in input.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b'/' if keep_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// The URL's (already percent-encoded) path, encoded once more
+fn canonical_uri(url: &Url) -> String {
+    match url.path() {
+        "" => "/".to_string(),
+        path => uri_encode(path, true),
+    }
+}
+
+/// Query parameters sorted by name, then value, each encoded once
+fn canonical_query(url: &Url) -> String {
+    let mut pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(name, value)| (uri_encode(&name, false), uri_encode(&value, false)))
+        .collect();
+    pairs.sort();
+    pairs
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::TestClock;
+    use crate::proxy::signing::body_sha256;
+    use reqwest::header::CONTENT_TYPE;
+
+    /// 2015-08-30T12:36:00Z, the date of the AWS SigV4 test suite
+    const SUITE_TIME: i64 = 1_440_938_160;
+
+    fn suite_signer(service: &str) -> SigV4Signer {
+        SigV4Signer::new(
+            SigV4Credentials {
+                access_key_id: "AKIDEXAMPLE".to_string(),
+                secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+                session_token: None,
+            },
+            "us-east-1",
+            service,
+            TestClock::new(SUITE_TIME),
+        )
+    }
+
+    fn authorization(
+        signer: &SigV4Signer,
+        method: Method,
+        url: &str,
+        headers: HeaderMap,
+        body: Option<&[u8]>,
+    ) -> String {
+        let added = signer
+            .sign(
+                &method,
+                &Url::parse(url).unwrap(),
+                &headers,
+                &body_sha256(body),
+            )
+            .unwrap();
+        assert_eq!(added[X_AMZ_DATE], "20150830T123600Z");
+        added[AUTHORIZATION].to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_get_vanilla() {
+        let signer = suite_signer("service");
+        assert_eq!(
+            authorization(
+                &signer,
+                Method::GET,
+                "https://example.amazonaws.com/",
+                HeaderMap::new(),
+                None
+            ),
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+             SignedHeaders=host;x-amz-date, \
+             Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+    }
+
+    #[test]
+    fn test_post_vanilla() {
+        let signer = suite_signer("service");
+        let authorization = authorization(
+            &signer,
+            Method::POST,
+            "https://example.amazonaws.com/",
+            HeaderMap::new(),
+            None,
+        );
+        assert!(authorization.ends_with(
+            "Signature=5da7c1a2acd57cee7505fc6676e4e544621c30862966e37dddb68e92efbe5d6b"
+        ));
+    }
+
+    #[test]
+    fn test_get_vanilla_query_order() {
+        let signer = suite_signer("service");
+        let authorization = authorization(
+            &signer,
+            Method::GET,
+            "https://example.amazonaws.com/?Param2=value2&Param1=value1",
+            HeaderMap::new(),
+            None,
+        );
+        assert!(authorization.ends_with(
+            "Signature=b97d918cfa904a5beff61c982a1b6f458b799221646efd99d3219ec94cdf2500"
+        ));
+    }
+
+    #[test]
+    fn test_post_form_body() {
+        let signer = suite_signer("service");
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/x-www-form-urlencoded"),
+        );
+        let authorization = authorization(
+            &signer,
+            Method::POST,
+            "https://example.amazonaws.com/",
+            headers,
+            Some(b"Param1=value1"),
+        );
+        assert!(authorization.contains("SignedHeaders=content-type;host;x-amz-date,"));
+        assert!(authorization.ends_with(
+            "Signature=ff11897932ad3f4e8b18135d722051e5ac45fc38421b1da7b9d196a0fe09473a"
+        ));
+    }
+
+    #[test]
+    fn test_iam_list_users() {
+        let signer = suite_signer("iam");
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/x-www-form-urlencoded; charset=utf-8"),
+        );
+        let authorization = authorization(
+            &signer,
+            Method::GET,
+            "https://iam.amazonaws.com/?Action=ListUsers&Version=2010-05-08",
+            headers,
+            None,
+        );
+        assert!(authorization.ends_with(
+            "Signature=5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d7"
+        ));
+    }
+
+    #[test]
+    fn test_session_token_is_sent_and_signed() {
+        let mut signer = suite_signer("execute-api");
+        signer.credentials.session_token = Some("token-123".to_string());
+        let added = signer
+            .sign(
+                &Method::POST,
+                &Url::parse("http://gateway.internal:8443/v1/chat/completions").unwrap(),
+                &HeaderMap::new(),
+                &body_sha256(Some(b"{}")),
+            )
+            .unwrap();
+        assert_eq!(added[X_AMZ_SECURITY_TOKEN], "token-123");
+        assert!(added[AUTHORIZATION]
+            .to_str()
+            .unwrap()
+            .contains("SignedHeaders=host;x-amz-date;x-amz-security-token,"));
+        assert!(!format!("{:?}", signer.credentials).contains("EXAMPLEKEY"));
+    }
+}
@@ -0,0 +1,155 @@
+//! Upstream model snapshot tracking
+//!
+//! Providers resolve model aliases to dated snapshots (`gpt-4o` →
+//! `gpt-4o-2024-11-20`) and swap them without notice. Every response's served
+//! model is counted against the requested one, and the first sighting of each
+//! requested/served pair is logged. Sightings are remembered in Redis with a
+//! TTL so replicas and restarts don't log the same snapshot again.
+
+use std::sync::Arc;
+
+use axum::http::{HeaderMap, HeaderValue};
+use chrono::Utc;
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::{
+    cache::redis::{keys, RedisCache},
+    error::AppResult,
+    routes::metrics::record_model_snapshot,
+};
+
+#[cfg(any(test, feature = "test-utils"))]
+use crate::cache::InMemoryCache;
+
+/// Response header carrying the model snapshot that served the request
+pub const UPSTREAM_MODEL_HEADER: &str = "x-sentinel-upstream-model";
+
+/// How long a sighting is remembered (7 days)
+const SNAPSHOT_TTL_SECONDS: u64 = 7 * 24 * 60 * 60;
+
+/// Cache backend abstraction for ModelSnapshotTracker
+pub enum SnapshotCacheBackend {
+    /// Redis-based cache for production use
+    Redis(Arc<RedisCache>),
+    /// In-memory cache for testing (only available with test-utils feature)
+    #[cfg(any(test, feature = "test-utils"))]
+    InMemory(Arc<InMemoryCache>),
+}
+
+impl SnapshotCacheBackend {
+    async fn set_if_absent<T: Serialize>(
+        &self,
+        key: &str,
+        value: &T,
+        ttl_seconds: u64,
+    ) -> AppResult<bool> {
+        match self {
+            SnapshotCacheBackend::Redis(cache) => cache.set_if_absent(key, value, ttl_seconds).await,
+            #[cfg(any(test, feature = "test-utils"))]
+            SnapshotCacheBackend::InMemory(cache) => cache.set_if_absent(key, value, ttl_seconds).await,
+        }
+    }
+}
+
+/// Tracks which upstream snapshots serve each requested model
+pub struct ModelSnapshotTracker {
+    cache: SnapshotCacheBackend,
+}
+
+impl ModelSnapshotTracker {
+    /// Create a tracker with Redis backend
+    pub fn new(cache: Arc<RedisCache>) -> Self {
+        Self {
+            cache: SnapshotCacheBackend::Redis(cache),
+        }
+    }
+
+    /// Create a tracker with in-memory backend for testing
+    #[cfg(any(test, feature = "test-utils"))]
+    pub fn new_for_testing(cache: Arc<InMemoryCache>) -> Self {
+        Self {
+            cache: SnapshotCacheBackend::InMemory(cache),
+        }
+    }
+
+    /// Record the model that served a request for `requested`
+    ///
+    /// Returns true the first time a snapshot other than the requested model
+    /// is seen for it. Cache errors are logged and treated as already seen.
+    pub async fn observe(&self, requested: &str, served: &str) -> bool {
+        record_model_snapshot(requested, served);
+        if served == requested {
+            return false;
+        }
+
+        let key = keys::model_snapshot(requested, served);
+        match self
+            .cache
+            .set_if_absent(&key, &Utc::now().timestamp(), SNAPSHOT_TTL_SECONDS)
+            .await
+        {
+            Ok(true) => {
+                info!(
+                    requested = %requested,
+                    served = %served,
+                    "New upstream model snapshot observed"
+                );
+                true
+            }
+            Ok(false) => false,
+            Err(e) => {
+                warn!(error = %e, requested = %requested, served = %served, "Failed to record model snapshot");
+                false
+            }
+        }
+    }
+}
+
+/// Model to attribute usage to: the served snapshot when the provider reported one
+pub fn attributed_model(requested: &str, served: Option<&str>) -> String {
+    served
+        .filter(|served| !served.is_empty())
+        .unwrap_or(requested)
+        .to_string()
+}
+
+/// Set `X-Sentinel-Upstream-Model` on a response
+pub fn insert_upstream_model_header(headers: &mut HeaderMap, served: &str) {
+    if let Ok(value) = HeaderValue::from_str(served) {
+        headers.insert(UPSTREAM_MODEL_HEADER, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker() -> ModelSnapshotTracker {
+        ModelSnapshotTracker::new_for_testing(Arc::new(InMemoryCache::new(60)))
+    }
+
+    #[tokio::test]
+    async fn test_new_snapshot_reported_once() {
+        let tracker = tracker();
+
+        assert!(tracker.observe("gpt-4o", "gpt-4o-2024-08-06").await);
+        assert!(!tracker.observe("gpt-4o", "gpt-4o-2024-08-06").await);
+
+        // A swap to another snapshot is a new sighting
+        assert!(tracker.observe("gpt-4o", "gpt-4o-2024-11-20").await);
+    }
+
+    #[tokio::test]
+    async fn test_matching_model_is_not_a_snapshot() {
+        let tracker = tracker();
+        assert!(!tracker.observe("gpt-4o-2024-11-20", "gpt-4o-2024-11-20").await);
+    }
+
+    #[test]
+    fn test_attributed_model_prefers_served() {
+        assert_eq!(attributed_model("gpt-4o", Some("gpt-4o-2024-11-20")), "gpt-4o-2024-11-20");
+        assert_eq!(attributed_model("gpt-4o", Some("")), "gpt-4o");
+        assert_eq!(attributed_model("gpt-4o", None), "gpt-4o");
+    }
+}
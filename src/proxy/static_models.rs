@@ -0,0 +1,271 @@
+//! Static model list (`MODELS_SOURCE`, `STATIC_MODELS_JSON`)
+//!
+//! Some upstreams (a local vLLM, say) have no `/models` endpoint, and client
+//! SDKs that list models at startup refuse to run against them. With
+//! `MODELS_SOURCE=static` `/v1/models` serves the list from
+//! `STATIC_MODELS_JSON` without calling the provider; `merged` overlays it on
+//! the provider's list, replacing entries with the same id. The startup
+//! capability check verifies the tier config against the same list.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::ProviderConfig;
+use crate::routes::models::Model;
+
+/// Where `/v1/models` takes its list from (`MODELS_SOURCE`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelsSource {
+    /// The provider's `/models` (built-in list when it fails)
+    #[default]
+    Upstream,
+    /// `STATIC_MODELS_JSON` only; the provider is never asked
+    Static,
+    /// The provider's list with `STATIC_MODELS_JSON` laid over it
+    Merged,
+}
+
+impl ModelsSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModelsSource::Upstream => "upstream",
+            ModelsSource::Static => "static",
+            ModelsSource::Merged => "merged",
+        }
+    }
+}
+
+impl FromStr for ModelsSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "upstream" => Ok(ModelsSource::Upstream),
+            "static" => Ok(ModelsSource::Static),
+            "merged" => Ok(ModelsSource::Merged),
+            other => Err(format!(
+                "unknown models source '{}' (expected upstream, static or merged)",
+                other
+            )),
+        }
+    }
+}
+
+/// Entry of `STATIC_MODELS_JSON`; only `id` is required
+#[derive(Deserialize)]
+struct StaticModel {
+    id: String,
+    #[serde(default = "default_object")]
+    object: String,
+    #[serde(default)]
+    created: i64,
+    #[serde(default = "default_owned_by")]
+    owned_by: String,
+    #[serde(default)]
+    root: Option<String>,
+    #[serde(default)]
+    parent: Option<String>,
+}
+
+fn default_object() -> String {
+    "model".to_string()
+}
+
+fn default_owned_by() -> String {
+    "system".to_string()
+}
+
+/// `{"object": "list", "data": [...]}` as `/models` returns it, or just the array
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StaticModelList {
+    List { data: Vec<StaticModel> },
+    Models(Vec<StaticModel>),
+}
+
+/// Models configured with `STATIC_MODELS_JSON`
+#[derive(Clone, PartialEq)]
+pub struct StaticModels {
+    models: Vec<Model>,
+}
+
+impl fmt::Debug for StaticModels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.models.iter().map(|m| &m.id)).finish()
+    }
+}
+
+impl FromStr for StaticModels {
+    type Err = String;
+
+    /// Inline JSON when the value starts with `{` or `[`, a file path otherwise
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value = s.trim();
+        let json = if value.starts_with('{') || value.starts_with('[') {
+            value.to_string()
+        } else {
+            std::fs::read_to_string(value)
+                .map_err(|e| format!("cannot read static models file '{}': {}", value, e))?
+        };
+        let list: StaticModelList =
+            serde_json::from_str(&json).map_err(|e| format!("invalid static models: {}", e))?;
+        let entries = match list {
+            StaticModelList::List { data } => data,
+            StaticModelList::Models(models) => models,
+        };
+
+        let mut models: Vec<Model> = Vec::with_capacity(entries.len());
+        for entry in entries {
+            if entry.id.trim().is_empty() {
+                return Err("invalid static models: empty model id".to_string());
+            }
+            if models.iter().any(|model| model.id == entry.id) {
+                return Err(format!("invalid static models: duplicate model id '{}'", entry.id));
+            }
+            models.push(Model {
+                id: entry.id,
+                object: entry.object,
+                created: entry.created,
+                owned_by: entry.owned_by,
+                permission: None,
+                root: entry.root,
+                parent: entry.parent,
+            });
+        }
+        Ok(Self { models })
+    }
+}
+
+impl StaticModels {
+    pub fn models(&self) -> &[Model] {
+        &self.models
+    }
+
+    /// The configured model with this id
+    pub fn get(&self, id: &str) -> Option<&Model> {
+        self.models.iter().find(|model| model.id == id)
+    }
+
+    pub fn ids(&self) -> BTreeSet<String> {
+        self.models.iter().map(|model| model.id.clone()).collect()
+    }
+
+    /// `upstream` with configured models replacing same-id entries and the rest appended
+    pub fn overlay(&self, upstream: Vec<Model>) -> Vec<Model> {
+        let mut models: Vec<Model> = upstream
+            .into_iter()
+            .map(|model| self.get(&model.id).cloned().unwrap_or(model))
+            .collect();
+        for model in &self.models {
+            if !models.iter().any(|m| m.id == model.id) {
+                models.push(model.clone());
+            }
+        }
+        models
+    }
+}
+
+/// The static list `MODELS_SOURCE` calls for, if any
+///
+/// Fails when the source is `static` or `merged` but `STATIC_MODELS_JSON` is
+/// unset; checked once when the app state is built.
+pub fn static_list(config: &ProviderConfig) -> anyhow::Result<Option<&StaticModels>> {
+    match config.models_source {
+        ModelsSource::Upstream => Ok(None),
+        ModelsSource::Static | ModelsSource::Merged => config
+            .static_models
+            .as_ref()
+            .map(Some)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "STATIC_MODELS_JSON must be set when MODELS_SOURCE={}",
+                    config.models_source.as_str()
+                )
+            }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model(id: &str, owned_by: &str) -> Model {
+        Model {
+            id: id.to_string(),
+            object: "model".to_string(),
+            created: 0,
+            owned_by: owned_by.to_string(),
+            permission: None,
+            root: None,
+            parent: None,
+        }
+    }
+
+    #[test]
+    fn test_models_source_parsing() {
+        assert_eq!("static".parse::<ModelsSource>().unwrap(), ModelsSource::Static);
+        assert_eq!(" Merged ".parse::<ModelsSource>().unwrap(), ModelsSource::Merged);
+        assert_eq!("upstream".parse::<ModelsSource>().unwrap(), ModelsSource::Upstream);
+        assert!("local".parse::<ModelsSource>().is_err());
+    }
+
+    #[test]
+    fn test_static_models_inline_formats() {
+        let list: StaticModels =
+            r#"{"object": "list", "data": [{"id": "llama-3-70b", "owned_by": "vllm", "created": 42}]}"#
+                .parse()
+                .unwrap();
+        assert_eq!(list.models(), &[Model { created: 42, ..model("llama-3-70b", "vllm") }]);
+
+        let bare: StaticModels = r#"[{"id": "llama-3-70b"}, {"id": "qwen-2"}]"#.parse().unwrap();
+        assert_eq!(bare.get("qwen-2"), Some(&model("qwen-2", "system")));
+        assert_eq!(bare.ids().into_iter().collect::<Vec<_>>(), vec!["llama-3-70b", "qwen-2"]);
+
+        assert!(r#"[{"id": ""}]"#.parse::<StaticModels>().is_err());
+        assert!(r#"[{"id": "a"}, {"id": "a"}]"#.parse::<StaticModels>().is_err());
+        assert!(r#"[{"owned_by": "vllm"}]"#.parse::<StaticModels>().is_err());
+    }
+
+    #[test]
+    fn test_static_models_from_file() {
+        let path = std::env::temp_dir().join(format!("static-models-{}.json", uuid::Uuid::new_v4()));
+        std::fs::write(&path, r#"{"data": [{"id": "local-model"}]}"#).unwrap();
+        let list: StaticModels = path.to_str().unwrap().parse().unwrap();
+        assert!(list.get("local-model").is_some());
+        std::fs::remove_file(&path).unwrap();
+
+        let err = "/nonexistent/models.json".parse::<StaticModels>().unwrap_err();
+        assert!(err.contains("/nonexistent/models.json"));
+    }
+
+    #[test]
+    fn test_overlay_replaces_and_appends() {
+        let list: StaticModels =
+            r#"[{"id": "gpt-4o", "owned_by": "on-prem"}, {"id": "local-model"}]"#.parse().unwrap();
+        let merged = list.overlay(vec![model("gpt-4o", "openai"), model("gpt-4o-mini", "openai")]);
+        let ids: Vec<_> = merged.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, vec!["gpt-4o", "gpt-4o-mini", "local-model"]);
+        assert_eq!(merged[0].owned_by, "on-prem");
+    }
+
+    #[test]
+    fn test_static_list_requires_models_for_static_sources() {
+        let mut config = ProviderConfig::default();
+        assert!(static_list(&config).unwrap().is_none());
+
+        config.models_source = ModelsSource::Static;
+        let err = static_list(&config).unwrap_err().to_string();
+        assert!(err.contains("MODELS_SOURCE=static"), "{}", err);
+
+        config.static_models = Some(r#"[{"id": "local-model"}]"#.parse().unwrap());
+        assert!(static_list(&config).unwrap().is_some());
+
+        // Configured but unused
+        config.models_source = ModelsSource::Upstream;
+        assert!(static_list(&config).unwrap().is_none());
+    }
+}
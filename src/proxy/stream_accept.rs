@@ -0,0 +1,222 @@
+//! `Accept` header checks for chat streams
+//!
+//! A client that sends `stream: true` with `Accept: application/json` gets
+//! SSE it can't parse, and some client libraries hang on it instead of
+//! failing. When the `Accept` header is present and accepts none of
+//! `text/event-stream`, `text/*` or `*/*`, `STREAM_ACCEPT_MISMATCH` decides
+//! whether the request is refused with a 406 (`reject`) or logged and served
+//! anyway (`warn`). A missing `Accept` header accepts anything.
+//!
+//! The opposite mistake, `Accept: text/event-stream` on a request without
+//! `stream: true`, is never an error: the JSON response carries
+//! `X-Sentinel-Stream-Hint` pointing at the `stream` flag. Progress SSE
+//! (`X-Sentinel-Progress: sse`) already answers such a request with SSE, so
+//! it gets no hint.
+
+use std::str::FromStr;
+
+use axum::{
+    http::{header, HeaderMap, HeaderValue},
+    response::{IntoResponse, Response},
+};
+use metrics::counter;
+use tracing::warn;
+
+use super::progress;
+
+/// Response header sent to clients that accept only SSE but didn't ask for a stream
+pub const STREAM_HINT_HEADER: &str = "X-Sentinel-Stream-Hint";
+
+const STREAM_HINT: &str = "set \"stream\": true to receive text/event-stream";
+
+/// Error message of a stream the client can't accept
+pub const NOT_ACCEPTABLE_MESSAGE: &str =
+    "stream: true responds with text/event-stream, which the Accept header does not allow";
+
+/// What to do with streams the client's `Accept` header rules out (`STREAM_ACCEPT_MISMATCH`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StreamAcceptMismatch {
+    /// Refuse the request with a 406
+    #[default]
+    Reject,
+    /// Log and count it, then stream anyway
+    Warn,
+}
+
+impl StreamAcceptMismatch {
+    fn as_str(self) -> &'static str {
+        match self {
+            StreamAcceptMismatch::Reject => "reject",
+            StreamAcceptMismatch::Warn => "warn",
+        }
+    }
+}
+
+impl FromStr for StreamAcceptMismatch {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "reject" => Ok(StreamAcceptMismatch::Reject),
+            "warn" => Ok(StreamAcceptMismatch::Warn),
+            other => Err(format!(
+                "unknown stream accept mismatch policy '{}' (expected reject or warn)",
+                other
+            )),
+        }
+    }
+}
+
+/// How a chat request's `Accept` header fits its `stream` flag
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Negotiation {
+    /// Nothing to report
+    Acceptable,
+    /// `stream: true`, but `Accept` rules out `text/event-stream`
+    StreamNotAccepted,
+    /// `Accept` is only `text/event-stream`, but the request isn't a stream
+    StreamNotRequested,
+}
+
+impl Negotiation {
+    /// Check the request's `Accept` header against its `stream` flag
+    pub fn check(headers: &HeaderMap, stream: bool) -> Self {
+        let ranges = media_ranges(headers);
+        if stream {
+            let accepts_sse = ranges.is_empty()
+                || ranges.iter().any(|(media_type, q)| {
+                    *q > 0.0 && matches!(media_type.as_str(), "text/event-stream" | "text/*" | "*/*")
+                });
+            if !accepts_sse {
+                return Negotiation::StreamNotAccepted;
+            }
+        } else if !ranges.is_empty()
+            && ranges.iter().all(|(media_type, _)| media_type == "text/event-stream")
+            && !progress::requested(headers)
+        {
+            return Negotiation::StreamNotRequested;
+        }
+        Negotiation::Acceptable
+    }
+
+    /// Whether `mode` refuses this request
+    pub fn rejected(self, mode: StreamAcceptMismatch) -> bool {
+        self == Negotiation::StreamNotAccepted && mode == StreamAcceptMismatch::Reject
+    }
+
+    /// Count (and for served mismatched streams, log) the outcome
+    pub fn record(self, mode: StreamAcceptMismatch, endpoint: &'static str, headers: &HeaderMap) {
+        let kind = match self {
+            Negotiation::Acceptable => return,
+            Negotiation::StreamNotAccepted => "stream_not_accepted",
+            Negotiation::StreamNotRequested => "stream_not_requested",
+        };
+        counter!(
+            "sentinel_stream_accept_mismatches_total",
+            "endpoint" => endpoint,
+            "kind" => kind,
+            "mode" => mode.as_str()
+        )
+        .increment(1);
+
+        if self == Negotiation::StreamNotAccepted && mode == StreamAcceptMismatch::Warn {
+            let accept = headers
+                .get(header::ACCEPT)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default();
+            warn!(
+                endpoint = endpoint,
+                accept = %accept,
+                "Streaming to a client whose Accept header excludes text/event-stream"
+            );
+        }
+    }
+
+    /// Add `X-Sentinel-Stream-Hint` to a request that didn't ask for the stream it accepts
+    ///
+    /// Like `deprecated::with_header`, error responses get the header too.
+    pub fn with_hint<E: IntoResponse>(self, result: Result<Response, E>) -> Result<Response, E> {
+        if self != Negotiation::StreamNotRequested {
+            return result;
+        }
+        let mut response = result.unwrap_or_else(IntoResponse::into_response);
+        response
+            .headers_mut()
+            .insert(STREAM_HINT_HEADER, HeaderValue::from_static(STREAM_HINT));
+        Ok(response)
+    }
+}
+
+/// Lowercased media types of the `Accept` header, with their `q`
+fn media_ranges(headers: &HeaderMap) -> Vec<(String, f32)> {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|range| {
+            let mut parts = range.split(';').map(str::trim);
+            let media_type = parts.next().unwrap_or_default().to_ascii_lowercase();
+            let q = parts
+                .filter_map(|param| param.strip_prefix("q="))
+                .find_map(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            (!media_type.is_empty()).then_some((media_type, q))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accept(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_streams_need_an_accept_header_allowing_sse() {
+        for allowed in [
+            "text/event-stream",
+            "application/json, text/event-stream",
+            "*/*",
+            "text/*;q=0.5",
+            "Text/Event-Stream; charset=utf-8",
+        ] {
+            assert_eq!(Negotiation::check(&accept(allowed), true), Negotiation::Acceptable, "{allowed}");
+        }
+        assert_eq!(Negotiation::check(&HeaderMap::new(), true), Negotiation::Acceptable);
+
+        for refused in ["application/json", "application/msgpack", "text/event-stream;q=0"] {
+            assert_eq!(
+                Negotiation::check(&accept(refused), true),
+                Negotiation::StreamNotAccepted,
+                "{refused}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_only_sse_without_stream_is_hinted() {
+        assert_eq!(
+            Negotiation::check(&accept("text/event-stream"), false),
+            Negotiation::StreamNotRequested
+        );
+        for other in ["text/event-stream, application/json", "*/*", "application/json"] {
+            assert_eq!(Negotiation::check(&accept(other), false), Negotiation::Acceptable, "{other}");
+        }
+
+        let mut progress = accept("text/event-stream");
+        progress.insert(progress::PROGRESS_HEADER, "sse".parse().unwrap());
+        assert_eq!(Negotiation::check(&progress, false), Negotiation::Acceptable);
+    }
+
+    #[test]
+    fn test_mismatch_policy_parsing() {
+        assert_eq!("warn".parse::<StreamAcceptMismatch>().unwrap(), StreamAcceptMismatch::Warn);
+        assert_eq!(" REJECT ".parse::<StreamAcceptMismatch>().unwrap(), StreamAcceptMismatch::Reject);
+        assert!("allow".parse::<StreamAcceptMismatch>().is_err());
+    }
+}
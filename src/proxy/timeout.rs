@@ -0,0 +1,239 @@
+//! Per-request upstream timeouts
+//!
+//! Clients can ask for a tighter deadline than the global HTTP client timeout,
+//! via `timeout_ms` on native requests or the `X-Sentinel-Timeout-Ms` header on
+//! `/v1`. The requested value is clamped to `UPSTREAM_TIMEOUT_MIN_MS` and
+//! `UPSTREAM_TIMEOUT_MAX_MS`, and the effective value is echoed back in the same
+//! header. Non-streaming calls fail with a 504; streams are only bounded until
+//! their first chunk, and end with an SSE error event if it never arrives.
+
+use std::future::Future;
+use std::time::Duration;
+
+use axum::{
+    http::{HeaderMap, HeaderValue},
+    response::{IntoResponse, Response},
+};
+use bytes::Bytes;
+use futures::StreamExt;
+use serde_json::json;
+
+use crate::{
+    config::Config,
+    error::{AppError, AppResult},
+    proxy::ByteStream,
+};
+
+/// Request header carrying the client's timeout, echoed back with the effective value
+pub const TIMEOUT_HEADER: &str = "x-sentinel-timeout-ms";
+
+/// Clamp a requested timeout to the configured bounds
+///
+/// Returns `None` when the client did not ask for one, leaving the global HTTP
+/// client timeout in charge.
+pub fn effective_timeout(config: &Config, requested_ms: Option<u64>) -> Option<Duration> {
+    requested_ms.map(|ms| {
+        let ms = ms
+            .max(config.provider.upstream_timeout_min_ms)
+            .min(config.provider.upstream_timeout_max_ms);
+        Duration::from_millis(ms)
+    })
+}
+
+/// Read and clamp the timeout from the `X-Sentinel-Timeout-Ms` header
+pub fn timeout_from_headers(config: &Config, headers: &HeaderMap) -> AppResult<Option<Duration>> {
+    let requested = headers
+        .get(TIMEOUT_HEADER)
+        .map(|value| {
+            value
+                .to_str()
+                .ok()
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .ok_or_else(|| {
+                    AppError::BadRequest(
+                        "X-Sentinel-Timeout-Ms must be a whole number of milliseconds".to_string(),
+                    )
+                })
+        })
+        .transpose()?;
+
+    Ok(effective_timeout(config, requested))
+}
+
+/// Run a non-streaming upstream call, failing with `UpstreamTimeout` on expiry
+pub async fn call_with_timeout<T>(
+    timeout: Option<Duration>,
+    call: impl Future<Output = AppResult<T>>,
+) -> AppResult<T> {
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, call)
+            .await
+            .map_err(|_| AppError::UpstreamTimeout {
+                timeout_ms: timeout.as_millis() as u64,
+            })?,
+        None => call.await,
+    }
+}
+
+/// Open an upstream stream, bounding the wait for its first chunk
+///
+/// If the deadline passes before the provider connects or sends its first
+/// chunk, the returned stream yields a single SSE error event and ends. Once
+/// the first chunk has arrived the deadline no longer applies.
+pub async fn stream_with_timeout(
+    timeout: Option<Duration>,
+    open: impl Future<Output = AppResult<ByteStream>>,
+) -> AppResult<ByteStream> {
+    let Some(timeout) = timeout else {
+        return open.await;
+    };
+    let deadline = tokio::time::Instant::now() + timeout;
+
+    let mut stream = match tokio::time::timeout_at(deadline, open).await {
+        Ok(result) => result?,
+        Err(_) => return Ok(timeout_event_stream(timeout)),
+    };
+
+    match tokio::time::timeout_at(deadline, stream.next()).await {
+        Ok(first) => Ok(Box::pin(futures::stream::iter(first).chain(stream))),
+        Err(_) => Ok(timeout_event_stream(timeout)),
+    }
+}
+
+/// Echo the effective timeout on a handler's response, including error responses
+pub fn with_timeout_header<E: IntoResponse>(
+    result: Result<Response, E>,
+    timeout: Option<Duration>,
+) -> Result<Response, E> {
+    let Some(timeout) = timeout else {
+        return result;
+    };
+
+    let mut response = result.unwrap_or_else(IntoResponse::into_response);
+    if let Ok(value) = HeaderValue::from_str(&timeout.as_millis().to_string()) {
+        response.headers_mut().insert(TIMEOUT_HEADER, value);
+    }
+    Ok(response)
+}
+
+/// A stream consisting of a single `upstream_timeout` SSE error event
+fn timeout_event_stream(timeout: Duration) -> ByteStream {
+    let error = AppError::UpstreamTimeout {
+        timeout_ms: timeout.as_millis() as u64,
+    };
+    let event = json!({
+        "error": {
+            "message": error.to_string(),
+            "type": "timeout_error",
+            "code": "upstream_timeout",
+            "retryable": true,
+        }
+    });
+    let chunk = Bytes::from(format!("data: {}\n\n", event));
+
+    Box::pin(futures::stream::once(async move { Ok(chunk) }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_config;
+
+    fn config() -> Config {
+        let mut config = test_config("http://zion.invalid", "http://provider.invalid/v1");
+        config.provider.upstream_timeout_min_ms = 100;
+        config.provider.upstream_timeout_max_ms = 5_000;
+        config
+    }
+
+    fn slow_stream(delay: Duration) -> ByteStream {
+        Box::pin(futures::stream::once(async move {
+            tokio::time::sleep(delay).await;
+            Ok(Bytes::from_static(b"data: {}\n\n"))
+        }))
+    }
+
+    async fn collect(stream: ByteStream) -> String {
+        let chunks: Vec<_> = stream.collect().await;
+        chunks
+            .into_iter()
+            .map(|chunk| String::from_utf8(chunk.unwrap().to_vec()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_effective_timeout_clamps_to_bounds() {
+        let config = config();
+        assert_eq!(effective_timeout(&config, None), None);
+        assert_eq!(effective_timeout(&config, Some(10)), Some(Duration::from_millis(100)));
+        assert_eq!(effective_timeout(&config, Some(2_000)), Some(Duration::from_millis(2_000)));
+        assert_eq!(effective_timeout(&config, Some(60_000)), Some(Duration::from_millis(5_000)));
+    }
+
+    #[test]
+    fn test_timeout_from_headers() {
+        let config = config();
+        let mut headers = HeaderMap::new();
+        assert_eq!(timeout_from_headers(&config, &headers).unwrap(), None);
+
+        headers.insert(TIMEOUT_HEADER, HeaderValue::from_static("250"));
+        assert_eq!(
+            timeout_from_headers(&config, &headers).unwrap(),
+            Some(Duration::from_millis(250))
+        );
+
+        headers.insert(TIMEOUT_HEADER, HeaderValue::from_static("soon"));
+        assert!(matches!(
+            timeout_from_headers(&config, &headers),
+            Err(AppError::BadRequest(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_call_with_timeout_expires() {
+        let result = call_with_timeout(Some(Duration::from_millis(20)), async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(())
+        })
+        .await;
+        assert!(matches!(result, Err(AppError::UpstreamTimeout { timeout_ms: 20 })));
+
+        let result = call_with_timeout(Some(Duration::from_secs(5)), async { Ok(7) }).await;
+        assert_eq!(result.unwrap(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_stream_without_first_chunk_ends_with_error_event() {
+        let stream = stream_with_timeout(Some(Duration::from_millis(20)), async {
+            Ok(slow_stream(Duration::from_secs(5)))
+        })
+        .await
+        .unwrap();
+
+        let body = collect(stream).await;
+        assert!(body.starts_with("data: "));
+        assert!(body.contains("\"code\":\"upstream_timeout\""));
+    }
+
+    #[tokio::test]
+    async fn test_stream_deadline_ignored_after_first_chunk() {
+        let stream: ByteStream = Box::pin(
+            futures::stream::iter(vec![Ok(Bytes::from_static(b"data: first\n\n"))])
+                .chain(slow_stream(Duration::from_millis(60))),
+        );
+        let stream = stream_with_timeout(Some(Duration::from_millis(20)), async { Ok(stream) })
+            .await
+            .unwrap();
+
+        assert_eq!(collect(stream).await, "data: first\n\ndata: {}\n\n");
+    }
+
+    #[test]
+    fn test_with_timeout_header_echoes_on_errors() {
+        let result: Result<Response, AppError> = Err(AppError::UpstreamTimeout { timeout_ms: 250 });
+        let response = with_timeout_header(result, Some(Duration::from_millis(250))).unwrap();
+
+        assert_eq!(response.status(), axum::http::StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(response.headers()[TIMEOUT_HEADER], "250");
+    }
+}
@@ -0,0 +1,306 @@
+//! The `user` field forwarded to providers for abuse attribution
+//!
+//! OpenAI asks for an end-user identifier in `user` (Anthropic takes it as
+//! `metadata.user_id`). `UPSTREAM_USER_FIELD` decides what Sentinel sends on
+//! `/v1` chat, completions, embeddings and responses, on JSON pass-through
+//! bodies and on native chat:
+//!
+//! - `passthrough` (default): whatever the client sent, or nothing
+//! - `hash`: a keyed hash of the client's value, so an email or name the
+//!   client put there never reaches the provider
+//! - `external_id_hash`: a keyed hash of the authenticated user's external ID,
+//!   whatever the client sent
+//! - `omit`: never send the field
+//!
+//! Hashes are HMAC-SHA256 with `UPSTREAM_USER_HASH_KEY` (lowercase hex), so
+//! the provider can't reverse them but support can recompute one to match a
+//! provider's abuse report. The forwarded value is recorded in the request's
+//! usage ledger row (see [`recorded_value`]), never in logs.
+
+use std::str::FromStr;
+
+use anyhow::{ensure, Result};
+
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+
+use crate::config::ProviderConfig;
+use crate::middleware::auth::AuthenticatedUser;
+use crate::native::affinity::hmac_sha256;
+
+/// Request body field holding the end-user identifier
+pub const USER_FIELD: &str = "user";
+
+/// What to forward in the `user` field (`UPSTREAM_USER_FIELD`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UpstreamUserField {
+    /// Forward the client's value as sent
+    #[default]
+    Passthrough,
+    /// Replace the client's value with its keyed hash
+    Hash,
+    /// Always send the keyed hash of the authenticated external ID
+    ExternalIdHash,
+    /// Never send the field
+    Omit,
+}
+
+impl UpstreamUserField {
+    /// Whether this mode sends hashes (and so needs `UPSTREAM_USER_HASH_KEY`)
+    pub fn hashes(self) -> bool {
+        matches!(
+            self,
+            UpstreamUserField::Hash | UpstreamUserField::ExternalIdHash
+        )
+    }
+}
+
+impl FromStr for UpstreamUserField {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "passthrough" => Ok(UpstreamUserField::Passthrough),
+            "hash" => Ok(UpstreamUserField::Hash),
+            "external_id_hash" => Ok(UpstreamUserField::ExternalIdHash),
+            "omit" => Ok(UpstreamUserField::Omit),
+            other => Err(format!(
+                "unknown upstream user field policy '{}' (expected passthrough, hash, external_id_hash or omit)",
+                other
+            )),
+        }
+    }
+}
+
+/// Refuse a hashing mode without a key to hash with
+pub fn validate(config: &ProviderConfig) -> Result<()> {
+    ensure!(
+        !config.upstream_user_field.hashes() || config.upstream_user_hash_key.is_some(),
+        "UPSTREAM_USER_HASH_KEY must be set when UPSTREAM_USER_FIELD hashes the user"
+    );
+    Ok(())
+}
+
+/// Keyed hash of a `user` value (lowercase hex)
+pub fn hash(key: &str, value: &str) -> String {
+    hex::encode(hmac_sha256(key.as_bytes(), value.as_bytes()))
+}
+
+/// The `user` value to forward for a request whose client sent `client_user`
+///
+/// Hashing modes without a key omit the field rather than send the raw value.
+pub fn resolve(
+    config: &ProviderConfig,
+    user: &AuthenticatedUser,
+    client_user: Option<&str>,
+) -> Option<String> {
+    let hash = |value: &str| {
+        config
+            .upstream_user_hash_key
+            .as_deref()
+            .map(|key| hash(key, value))
+    };
+    match config.upstream_user_field {
+        UpstreamUserField::Passthrough => client_user.map(str::to_string),
+        UpstreamUserField::Hash => client_user.and_then(hash),
+        UpstreamUserField::ExternalIdHash => hash(&user.external_id),
+        UpstreamUserField::Omit => None,
+    }
+}
+
+/// The forwarded value as the ledger records it
+///
+/// Hashing modes already forward a hash. A value passed through as the
+/// client sent it may be an email, so it is recorded as its keyed hash (plain
+/// SHA-256 without `UPSTREAM_USER_HASH_KEY`); support hashes the value from a
+/// provider's report the same way to find the row.
+pub fn recorded_value(config: &ProviderConfig, forwarded: Option<&str>) -> Option<String> {
+    let forwarded = forwarded?;
+    match config.upstream_user_field {
+        UpstreamUserField::Hash | UpstreamUserField::ExternalIdHash => Some(forwarded.to_string()),
+        UpstreamUserField::Passthrough | UpstreamUserField::Omit => {
+            Some(match config.upstream_user_hash_key.as_deref() {
+                Some(key) => hash(key, forwarded),
+                None => hex::encode(Sha256::digest(forwarded.as_bytes())),
+            })
+        }
+    }
+}
+
+/// Resolve the value to forward and keep its recorded form on the request's user
+pub fn apply(
+    config: &ProviderConfig,
+    user: &mut AuthenticatedUser,
+    client_user: Option<&str>,
+) -> Option<String> {
+    let forwarded = resolve(config, user, client_user);
+    user.upstream_user = recorded_value(config, forwarded.as_deref());
+    forwarded
+}
+
+/// [`apply`] to the `user` field of a JSON object (`/v1/responses` extras,
+/// pass-through bodies); returns whether the object changed
+pub fn apply_to_object(
+    config: &ProviderConfig,
+    user: &mut AuthenticatedUser,
+    object: &mut Map<String, Value>,
+) -> bool {
+    let client_user = object.get(USER_FIELD).and_then(Value::as_str).map(str::to_string);
+    let forwarded = apply(config, user, client_user.as_deref());
+    // Passthrough leaves the field as sent, even when it isn't a string
+    if forwarded == client_user
+        && (client_user.is_some() || config.upstream_user_field == UpstreamUserField::Passthrough)
+    {
+        return false;
+    }
+    if forwarded.is_none() && !object.contains_key(USER_FIELD) {
+        return false;
+    }
+    match forwarded {
+        Some(forwarded) => object.insert(USER_FIELD.to_string(), Value::String(forwarded)),
+        None => object.remove(USER_FIELD),
+    };
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::{model_access::ModelAllowlist, scope::TokenScopes};
+
+    fn test_user() -> AuthenticatedUser {
+        AuthenticatedUser {
+            user_id: "user_1".to_string(),
+            external_id: "ext_123".to_string(),
+            email: "user@example.com".to_string(),
+            organization_id: None,
+            logging_opt_out: false,
+            allowed_models: ModelAllowlist::All,
+            synthetic: false,
+            scopes: TokenScopes::All,
+            token_charge: None,
+            upstream_user: None,
+        }
+    }
+
+    fn config(mode: UpstreamUserField) -> ProviderConfig {
+        ProviderConfig {
+            upstream_user_field: mode,
+            upstream_user_hash_key: Some("user-hash-key".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_resolve_per_mode() {
+        let user = test_user();
+        let email = Some("jane@example.com");
+
+        let passthrough = config(UpstreamUserField::Passthrough);
+        assert_eq!(
+            resolve(&passthrough, &user, email).as_deref(),
+            Some("jane@example.com")
+        );
+        assert_eq!(resolve(&passthrough, &user, None), None);
+
+        let hashed = resolve(&config(UpstreamUserField::Hash), &user, email).unwrap();
+        assert_eq!(
+            hashed,
+            hex::encode(hmac_sha256(b"user-hash-key", b"jane@example.com"))
+        );
+        assert_eq!(hashed, hash("user-hash-key", "jane@example.com"));
+        assert_eq!(resolve(&config(UpstreamUserField::Hash), &user, None), None);
+
+        let external = config(UpstreamUserField::ExternalIdHash);
+        assert_eq!(
+            resolve(&external, &user, email).unwrap(),
+            hex::encode(hmac_sha256(b"user-hash-key", user.external_id.as_bytes()))
+        );
+        assert_eq!(
+            resolve(&external, &user, email),
+            resolve(&external, &user, None)
+        );
+
+        assert_eq!(
+            resolve(&config(UpstreamUserField::Omit), &user, email),
+            None
+        );
+    }
+
+    #[test]
+    fn test_recorded_value_is_always_hashed() {
+        let passthrough = config(UpstreamUserField::Passthrough);
+        assert_eq!(
+            recorded_value(&passthrough, Some("jane@example.com")),
+            Some(hash("user-hash-key", "jane@example.com"))
+        );
+        let unkeyed = ProviderConfig {
+            upstream_user_hash_key: None,
+            ..passthrough
+        };
+        assert_eq!(
+            recorded_value(&unkeyed, Some("jane@example.com")),
+            Some(hex::encode(Sha256::digest(b"jane@example.com")))
+        );
+        assert_eq!(recorded_value(&unkeyed, None), None);
+
+        // Hashing modes forward a hash already
+        assert_eq!(
+            recorded_value(&config(UpstreamUserField::Hash), Some("5e8f")).as_deref(),
+            Some("5e8f")
+        );
+    }
+
+    #[test]
+    fn test_apply_to_object() {
+        let mut user = test_user();
+        let mut object = Map::new();
+        object.insert(USER_FIELD.to_string(), Value::from("jane@example.com"));
+
+        let passthrough = config(UpstreamUserField::Passthrough);
+        assert!(!apply_to_object(&passthrough, &mut user, &mut object.clone()));
+        assert_eq!(user.upstream_user, Some(hash("user-hash-key", "jane@example.com")));
+
+        let hashed = config(UpstreamUserField::Hash);
+        let mut rewritten = object.clone();
+        assert!(apply_to_object(&hashed, &mut user, &mut rewritten));
+        assert_eq!(rewritten[USER_FIELD], hash("user-hash-key", "jane@example.com"));
+
+        let omit = config(UpstreamUserField::Omit);
+        let mut dropped = object.clone();
+        assert!(apply_to_object(&omit, &mut user, &mut dropped));
+        assert!(!dropped.contains_key(USER_FIELD));
+        assert_eq!(user.upstream_user, None);
+        assert!(!apply_to_object(&omit, &mut user, &mut Map::new()));
+
+        // A non-string value is dropped unless passed through
+        object.insert(USER_FIELD.to_string(), Value::from(42));
+        assert!(!apply_to_object(&passthrough, &mut user, &mut object.clone()));
+        assert!(apply_to_object(&hashed, &mut user, &mut object));
+        assert!(!object.contains_key(USER_FIELD));
+    }
+
+    #[test]
+    fn test_hash_modes_need_a_key() {
+        let mut config = config(UpstreamUserField::Hash);
+        assert!(validate(&config).is_ok());
+        config.upstream_user_hash_key = None;
+        assert!(validate(&config).is_err());
+        assert_eq!(
+            resolve(&config, &test_user(), Some("jane@example.com")),
+            None
+        );
+
+        config.upstream_user_field = UpstreamUserField::Omit;
+        assert!(validate(&config).is_ok());
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "External_ID_Hash".parse::<UpstreamUserField>(),
+            Ok(UpstreamUserField::ExternalIdHash)
+        );
+        assert!("anonymize".parse::<UpstreamUserField>().is_err());
+    }
+}
@@ -0,0 +1,140 @@
+//! Upstream response validation
+//!
+//! Upstreams occasionally answer 200 with a body that isn't a usable chat
+//! completion (an empty `choices` array, no `usage`), and SDKs crash on it.
+//! With `VALIDATE_UPSTREAM_RESPONSES` on (the default), non-streaming
+//! `/v1/chat/completions` responses are checked before they reach the client
+//! and turned into a 502 `upstream_invalid_response` instead. Fields the
+//! OpenAI API documents as nullable (`message.content`, `finish_reason`) may
+//! be null, but must be present.
+
+use serde_json::Value;
+
+/// Bytes of the offending body logged with a validation failure
+pub const LOGGED_BODY_BYTES: usize = 1024;
+
+/// Check the structure of a non-streaming chat completion
+///
+/// Returns what is wrong with the first problem found.
+pub fn validate_chat_completion(body: &Value) -> Result<(), String> {
+    let Some(body) = body.as_object() else {
+        return Err("body is not a JSON object".to_string());
+    };
+
+    let choices = match body.get("choices") {
+        Some(Value::Array(choices)) => choices,
+        Some(_) => return Err("choices is not an array".to_string()),
+        None => return Err("choices is missing".to_string()),
+    };
+    if choices.is_empty() {
+        return Err("choices is empty".to_string());
+    }
+    for (index, choice) in choices.iter().enumerate() {
+        let field = |name: &str| format!("choices[{}].{}", index, name);
+        let message = match choice.get("message") {
+            Some(Value::Object(message)) => message,
+            Some(_) => return Err(format!("{} is not an object", field("message"))),
+            None => return Err(format!("{} is missing", field("message"))),
+        };
+        match message.get("content") {
+            Some(Value::String(_) | Value::Null) => {}
+            Some(_) => return Err(format!("{} is not a string", field("message.content"))),
+            None => return Err(format!("{} is missing", field("message.content"))),
+        }
+        match choice.get("finish_reason") {
+            Some(Value::String(_) | Value::Null) => {}
+            Some(_) => return Err(format!("{} is not a string", field("finish_reason"))),
+            None => return Err(format!("{} is missing", field("finish_reason"))),
+        }
+    }
+
+    let usage = match body.get("usage") {
+        Some(Value::Object(usage)) => usage,
+        Some(_) => return Err("usage is not an object".to_string()),
+        None => return Err("usage is missing".to_string()),
+    };
+    for name in ["prompt_tokens", "completion_tokens"] {
+        if !usage.get(name).is_some_and(Value::is_u64) {
+            return Err(format!("usage.{} is not a non-negative integer", name));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn completion() -> Value {
+        json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Hi"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 5, "completion_tokens": 1, "total_tokens": 6}
+        })
+    }
+
+    #[test]
+    fn test_valid_completion_and_nullables() {
+        assert_eq!(validate_chat_completion(&completion()), Ok(()));
+
+        // Tool-call turns have null content; finish_reason is nullable
+        let mut body = completion();
+        body["choices"][0]["message"]["content"] = Value::Null;
+        body["choices"][0]["finish_reason"] = Value::Null;
+        assert_eq!(validate_chat_completion(&body), Ok(()));
+    }
+
+    #[test]
+    fn test_broken_completions() {
+        let broken = |edit: fn(&mut Value)| {
+            let mut body = completion();
+            edit(&mut body);
+            validate_chat_completion(&body).unwrap_err()
+        };
+
+        assert_eq!(broken(|b| b["choices"] = json!([])), "choices is empty");
+        assert_eq!(
+            broken(|b| {
+                b.as_object_mut().unwrap().remove("choices");
+            }),
+            "choices is missing"
+        );
+        assert_eq!(
+            broken(|b| {
+                b["choices"][0].as_object_mut().unwrap().remove("message");
+            }),
+            "choices[0].message is missing"
+        );
+        assert_eq!(
+            broken(|b| b["choices"][0]["message"]["content"] = json!(5)),
+            "choices[0].message.content is not a string"
+        );
+        assert_eq!(
+            broken(|b| {
+                b["choices"][0].as_object_mut().unwrap().remove("finish_reason");
+            }),
+            "choices[0].finish_reason is missing"
+        );
+        assert_eq!(
+            broken(|b| {
+                b.as_object_mut().unwrap().remove("usage");
+            }),
+            "usage is missing"
+        );
+        assert_eq!(
+            broken(|b| b["usage"]["prompt_tokens"] = json!("5")),
+            "usage.prompt_tokens is not a non-negative integer"
+        );
+        assert_eq!(
+            validate_chat_completion(&json!([])).unwrap_err(),
+            "body is not a JSON object"
+        );
+    }
+}
@@ -0,0 +1,719 @@
+//! Admin endpoints for operators
+//!
+//! Protected by the X-Admin-Key header matching ADMIN_API_KEY, or by a token
+//! whose Zion profile claims the `admin` scope. Unlike the docs endpoints
+//! these fail closed: without either every request gets a 404, and a wrong
+//! key or token is indistinguishable from a missing endpoint. A valid token
+//! without the scope gets 403 `insufficient_scope`.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    extract::{Path, Query, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{
+    cache::warm::WarmJobReport,
+    error::{AppError, AppResult},
+    log_level::LogLevelStatus,
+    middleware::{
+        auth,
+        in_flight::InFlightSnapshot,
+        maintenance::{MaintenanceFlag, MaintenanceStatus},
+        rate_limit_exempt::{ExemptIdsOverride, ExemptIdsStatus},
+        rate_limiter::RejectionRate,
+        scope::{insufficient_scope, ADMIN_SCOPE},
+    },
+    proxy::capabilities::{self, ProviderStatusReport},
+    routes::{
+        health::{self, DependencyCheck, HealthStatus},
+        sessions::{self, DeleteSessionsQuery, SessionsDeletedResponse},
+    },
+    tiers::{prune, TierStateReport, TrippedEndpoint},
+    usage::{
+        report::{self, GroupBy, UsageReport},
+        RecentUsage, RequestWeightTable, RequestWeightsStatus, RetryLeaseStatus,
+        UsageTrackerStatus,
+    },
+    zion::ZionCapabilities,
+    AppState,
+};
+
+/// Days of recent usage returned when `days` is not given
+pub const DEFAULT_RECENT_USAGE_DAYS: u32 = 7;
+
+/// Query parameters for recent usage lookups
+#[derive(Debug, Deserialize)]
+pub struct RecentUsageQuery {
+    /// Days to include, counting today (default 7, capped at USAGE_AGGREGATE_DAYS)
+    pub days: Option<u32>,
+}
+
+/// Middleware to protect admin endpoints with the admin API key or the `admin` scope
+pub async fn admin_auth_middleware(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, Response> {
+    let provided_key = request
+        .headers()
+        .get("X-Admin-Key")
+        .and_then(|v| v.to_str().ok());
+
+    match (state.config.server.admin_api_key.as_deref(), provided_key) {
+        (Some(expected), Some(provided)) if expected == provided => {
+            return Ok(next.run(request).await)
+        }
+        (_, Some(_)) => return Err(StatusCode::NOT_FOUND.into_response()),
+        _ => {}
+    }
+
+    match auth::claimed_scopes(&state, request.headers()).await {
+        Some(scopes) if scopes.allows(ADMIN_SCOPE) => Ok(next.run(request).await),
+        Some(_) => {
+            warn!(
+                path = %request.uri().path(),
+                "Admin request rejected: token lacks the admin scope"
+            );
+            Err(insufficient_scope(ADMIN_SCOPE))
+        }
+        None => Err(StatusCode::NOT_FOUND.into_response()),
+    }
+}
+
+/// GET /admin/users/:external_id/usage - local daily usage aggregates for a user
+///
+/// Served from Sentinel's own counters, so it reflects usage flushed by this
+/// deployment without a round-trip to Zion's reporting API.
+pub async fn user_usage(
+    State(state): State<Arc<AppState>>,
+    Path(external_id): Path<String>,
+    Query(query): Query<RecentUsageQuery>,
+) -> AppResult<Json<RecentUsage>> {
+    let days = query.days.unwrap_or(DEFAULT_RECENT_USAGE_DAYS);
+    let usage = state
+        .batching_tracker
+        .recent_usage()
+        .recent(&external_id, days)
+        .await?;
+    Ok(Json(usage))
+}
+
+/// Response for clearing a user's throttling state
+#[derive(Debug, Serialize)]
+pub struct ThrottleClearedResponse {
+    pub external_id: String,
+    /// Whether the user was quarantined for malformed requests
+    pub quarantine_cleared: bool,
+}
+
+/// DELETE /admin/users/:external_id/throttle - lift a malformed-request quarantine
+///
+/// Also resets the user's malformed-request count for the current window.
+pub async fn clear_throttle(
+    State(state): State<Arc<AppState>>,
+    Path(external_id): Path<String>,
+) -> AppResult<Json<ThrottleClearedResponse>> {
+    let quarantine_cleared = state.quarantine.clear(&external_id).await?;
+    Ok(Json(ThrottleClearedResponse {
+        external_id,
+        quarantine_cleared,
+    }))
+}
+
+/// DELETE /admin/users/:external_id/sessions - delete (or redact) all of a user's sessions
+pub async fn delete_user_sessions(
+    State(state): State<Arc<AppState>>,
+    Path(external_id): Path<String>,
+    Query(query): Query<DeleteSessionsQuery>,
+) -> AppResult<Json<SessionsDeletedResponse>> {
+    Ok(Json(sessions::remove_sessions(&state, external_id, query.mode).await?))
+}
+
+/// Hours of recent usage used to pick users when `hours` is not given
+pub const DEFAULT_WARM_RECENT_HOURS: u32 = 24;
+
+/// Where a cache warm job takes its users from
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WarmSource {
+    /// Users with local usage aggregates in the last `hours`
+    Recent,
+}
+
+/// Body for starting a cache warm job
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum CacheWarmRequest {
+    /// An explicit list of users
+    Ids { external_ids: Vec<String> },
+    /// Users with recent usage
+    Source { source: WarmSource, hours: Option<u32> },
+}
+
+/// POST /admin/cache/warm - load users' limits into the cache ahead of traffic
+///
+/// Returns 202 with the job's progress; poll `GET /admin/cache/warm/:job_id`.
+/// Submitting the same set of users again returns the running job, or reruns
+/// it once finished (cached users are not fetched again).
+pub async fn start_cache_warm(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<CacheWarmRequest>,
+) -> AppResult<(StatusCode, Json<WarmJobReport>)> {
+    let external_ids = match request {
+        CacheWarmRequest::Ids { external_ids } => external_ids,
+        CacheWarmRequest::Source {
+            source: WarmSource::Recent,
+            hours,
+        } => {
+            // Aggregates are per UTC day, so the window is rounded out to whole days
+            let hours = hours.unwrap_or(DEFAULT_WARM_RECENT_HOURS);
+            state
+                .batching_tracker
+                .recent_usage()
+                .active_users(hours.div_ceil(24) + 1)
+                .await?
+        }
+    };
+    let report = state.cache_warmer.start(external_ids)?;
+    Ok((StatusCode::ACCEPTED, Json(report)))
+}
+
+/// GET /admin/cache/warm/:job_id - progress and per-user results of a warm job
+///
+/// Jobs are tracked by the replica that accepted them.
+pub async fn cache_warm_status(
+    State(state): State<Arc<AppState>>,
+    Path(job_id): Path<String>,
+) -> AppResult<Json<WarmJobReport>> {
+    state
+        .cache_warmer
+        .job(&job_id)
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound(format!("Cache warm job not found: {}", job_id)))
+}
+
+/// GET /admin/maintenance - effective maintenance mode state
+pub async fn get_maintenance(State(state): State<Arc<AppState>>) -> Json<MaintenanceStatus> {
+    Json(state.maintenance.status().await)
+}
+
+/// PUT /admin/maintenance - turn maintenance mode on or off for all replicas
+///
+/// Other replicas pick up the change within a couple of seconds.
+pub async fn set_maintenance(
+    State(state): State<Arc<AppState>>,
+    Json(flag): Json<MaintenanceFlag>,
+) -> AppResult<Json<MaintenanceStatus>> {
+    Ok(Json(state.maintenance.set_override(flag).await?))
+}
+
+/// DELETE /admin/maintenance - drop the runtime override, reverting to MAINTENANCE_MODE
+pub async fn clear_maintenance(
+    State(state): State<Arc<AppState>>,
+) -> AppResult<Json<MaintenanceStatus>> {
+    Ok(Json(state.maintenance.clear_override().await?))
+}
+
+/// GET /admin/rate-limit/exempt-ids - external IDs currently exempt from rate limiting
+pub async fn get_rate_limit_exempt_ids(
+    State(state): State<Arc<AppState>>,
+) -> Json<ExemptIdsStatus> {
+    Json(state.rate_limit_exempt_ids.status().await)
+}
+
+/// PUT /admin/rate-limit/exempt-ids - replace the exemption list for all replicas
+///
+/// Other replicas pick up the change within a couple of seconds.
+pub async fn set_rate_limit_exempt_ids(
+    State(state): State<Arc<AppState>>,
+    Json(list): Json<ExemptIdsOverride>,
+) -> AppResult<Json<ExemptIdsStatus>> {
+    Ok(Json(state.rate_limit_exempt_ids.set_override(list).await?))
+}
+
+/// DELETE /admin/rate-limit/exempt-ids - drop the runtime list, reverting to RATE_LIMIT_EXEMPT_IDS
+pub async fn clear_rate_limit_exempt_ids(
+    State(state): State<Arc<AppState>>,
+) -> AppResult<Json<ExemptIdsStatus>> {
+    Ok(Json(state.rate_limit_exempt_ids.clear_override().await?))
+}
+
+/// Body for changing the log filter
+#[derive(Debug, Deserialize)]
+pub struct LogLevelChange {
+    /// `RUST_LOG`-style directives, e.g. `sentinel=debug,tower_http=info`
+    pub filter: String,
+    /// Seconds until the startup filter is restored (default: LOG_LEVEL_REVERT_SECONDS, 0 = never)
+    pub revert_after_seconds: Option<u64>,
+}
+
+/// GET /admin/log-level - active log filter of this replica
+pub async fn get_log_level(State(state): State<Arc<AppState>>) -> AppResult<Json<LogLevelStatus>> {
+    Ok(Json(state.log_level.status()?))
+}
+
+/// PUT /admin/log-level - replace this replica's log filter without a restart
+///
+/// Invalid directives are rejected with 400 before anything changes. Applies
+/// only to the replica that receives the request.
+pub async fn set_log_level(
+    State(state): State<Arc<AppState>>,
+    Json(change): Json<LogLevelChange>,
+) -> AppResult<Json<LogLevelStatus>> {
+    let revert_after = change
+        .revert_after_seconds
+        .unwrap_or(state.config.server.log_level_revert_seconds);
+    let revert_after = (revert_after > 0).then(|| Duration::from_secs(revert_after));
+    Ok(Json(state.log_level.set(&change.filter, revert_after)?))
+}
+
+/// GET /admin/tiers/state - cached tier config, per-model health and prune counters of this replica
+///
+/// Reads the tier config from the cache only; `config_version` is null when
+/// nothing is cached.
+pub async fn tier_state(State(state): State<Arc<AppState>>) -> Json<TierStateReport> {
+    Json(
+        prune::report(
+            &state.tier_config_cache,
+            &state.health_tracker,
+            (&state.config.provider).into(),
+        )
+        .await,
+    )
+}
+
+/// What this replica is doing right now, as returned by `GET /admin/snapshot`
+#[derive(Debug, Serialize)]
+pub struct TrafficSnapshot {
+    /// Unix time the snapshot was taken
+    pub taken_at: i64,
+    /// Admitted `/v1` and `/native` requests still running, streams included
+    pub in_flight: InFlightSnapshot,
+    /// Rate limit checks and rejections over the last minute
+    pub rate_limit: RejectionRate,
+    /// Usage increments queued for Zion and the circuit guarding it
+    pub usage_tracker: UsageTrackerStatus,
+    /// Provider endpoints whose circuit is open or half-open
+    pub upstream_circuits: Vec<TrippedEndpoint>,
+    /// Whether Redis failed to answer a PING
+    pub redis_degraded: bool,
+    pub redis: DependencyCheck,
+}
+
+/// GET /admin/snapshot - in-flight requests, streams and degradation state of this replica
+///
+/// Everything but the Redis PING is read from memory, so the call stays
+/// cheap during an incident.
+pub async fn snapshot(State(state): State<Arc<AppState>>) -> Json<TrafficSnapshot> {
+    let redis = health::check_redis(&state).await;
+    Json(TrafficSnapshot {
+        taken_at: state.clock.now_unix(),
+        in_flight: state.in_flight.snapshot(),
+        rate_limit: state.rate_limit_rejections.rate(),
+        usage_tracker: state.batching_tracker.status(),
+        upstream_circuits: state.health_tracker.tripped_endpoints(),
+        redis_degraded: redis.status != HealthStatus::Healthy,
+        redis,
+    })
+}
+
+/// Body for replacing the request weight table
+#[derive(Debug, Deserialize)]
+pub struct RequestWeightsChange {
+    /// Pattern → weight pairs, e.g. `{"/images/generations": 5, "/models": 0}`
+    pub weights: BTreeMap<String, u32>,
+}
+
+/// GET /admin/usage/request-weights - active pass-through request weights of this replica
+pub async fn get_request_weights(State(state): State<Arc<AppState>>) -> Json<RequestWeightsStatus> {
+    Json(state.request_weights.status())
+}
+
+/// PUT /admin/usage/request-weights - replace this replica's weight table without a restart
+///
+/// The whole table is replaced; invalid patterns are rejected with 400 before
+/// anything changes. Applies only to the replica that receives the request.
+pub async fn set_request_weights(
+    State(state): State<Arc<AppState>>,
+    Json(change): Json<RequestWeightsChange>,
+) -> AppResult<Json<RequestWeightsStatus>> {
+    let table = RequestWeightTable::new(change.weights).map_err(AppError::BadRequest)?;
+    Ok(Json(state.request_weights.set(table)))
+}
+
+/// DELETE /admin/usage/request-weights - restore the table this replica started with
+pub async fn reset_request_weights(State(state): State<Arc<AppState>>) -> Json<RequestWeightsStatus> {
+    Json(state.request_weights.reset())
+}
+
+/// Query parameters for the provider status endpoint
+#[derive(Debug, Deserialize)]
+pub struct ProviderStatusQuery {
+    /// Re-run the capability check instead of returning the last report
+    #[serde(default)]
+    pub refresh: bool,
+}
+
+/// GET /admin/providers/status - provider capability check report
+///
+/// Returns the report from startup (STARTUP_PROVIDER_CHECK); the check runs
+/// now when it hasn't run yet or `refresh=true` is given.
+pub async fn provider_status(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ProviderStatusQuery>,
+) -> Json<ProviderStatusReport> {
+    let report = match state.provider_status.latest() {
+        Some(report) if !query.refresh => report,
+        _ => capabilities::check_providers(&state).await,
+    };
+    Json(report)
+}
+
+/// GET /admin/usage/retry - which replica retries failed usage increments
+///
+/// Only the holder of the retry lease drains `sentinel:usage:failed`; the
+/// others skip their retry cycles until its lease expires or is released.
+pub async fn usage_retry_status(
+    State(state): State<Arc<AppState>>,
+) -> AppResult<Json<RetryLeaseStatus>> {
+    Ok(Json(state.batching_tracker.retry_status().await?))
+}
+
+/// Query parameters for the Zion capabilities endpoint
+#[derive(Debug, Deserialize)]
+pub struct ZionCapabilitiesQuery {
+    /// Re-read Zion's meta endpoint instead of returning the cached set
+    #[serde(default)]
+    pub refresh: bool,
+}
+
+/// GET /admin/zion/capabilities - batch payload fields negotiated with Zion
+///
+/// Returns the capabilities read from Zion's meta endpoint at startup (or on
+/// the last periodic refresh); `refresh=true` re-reads them now.
+pub async fn zion_capabilities(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ZionCapabilitiesQuery>,
+) -> Json<ZionCapabilities> {
+    let capabilities = match state.zion_client.capabilities() {
+        Some(capabilities) if !query.refresh => capabilities,
+        _ => state.zion_client.refresh_capabilities().await,
+    };
+    Json(capabilities.as_ref().clone())
+}
+
+/// Query parameters for the usage report
+#[derive(Debug, Deserialize)]
+pub struct UsageReportQuery {
+    /// First UTC day (YYYY-MM-DD, inclusive)
+    pub from: String,
+    /// Last UTC day (YYYY-MM-DD, inclusive)
+    pub to: String,
+    /// `model` (default), `tier` or `day`
+    #[serde(default)]
+    pub group_by: GroupBy,
+}
+
+/// GET /admin/reports/usage - tokens, requests and estimated cost per model, tier or day
+///
+/// Served from the per-(day, model, tier) counters the usage batcher keeps
+/// for USAGE_REPORT_DAYS, across all replicas. Returns CSV when the `Accept`
+/// header prefers `text/csv`.
+pub async fn usage_report(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<UsageReportQuery>,
+    headers: HeaderMap,
+) -> AppResult<Response> {
+    let from = parse_day("from", &query.from)?;
+    let to = parse_day("to", &query.to)?;
+    if from > to {
+        return Err(AppError::BadRequest("'from' must not be after 'to'".to_string()));
+    }
+
+    let rows = state.batching_tracker.recent_usage().model_usage(from, to).await?;
+    // Prices are optional: without a tier config the report just has no costs
+    let tier_config = state.tier_config_cache.get_config().await.ok();
+    let report = UsageReport::build(query.from, query.to, query.group_by, &rows, tier_config.as_ref());
+
+    if report::wants_csv(&headers) {
+        Ok((
+            [
+                (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+                (header::CONTENT_DISPOSITION, "attachment; filename=\"usage-report.csv\""),
+            ],
+            report_csv(&report),
+        )
+            .into_response())
+    } else {
+        Ok(Json(report).into_response())
+    }
+}
+
+/// Parse a UTC day given as YYYY-MM-DD
+fn parse_day(name: &str, value: &str) -> AppResult<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| AppError::BadRequest(format!("'{}' must be a date (YYYY-MM-DD)", name)))
+}
+
+/// Render report groups as CSV, headed by the grouping
+fn report_csv(report: &UsageReport) -> String {
+    let mut out = format!(
+        "{},requests,input_tokens,output_tokens,estimated_cost\n",
+        report.group_by.as_str()
+    );
+    for group in &report.groups {
+        let totals = &group.totals;
+        out.push_str(&format!(
+            "{},{},{},{},{}\n",
+            csv_field(&group.group),
+            totals.usage.requests,
+            totals.usage.input_tokens,
+            totals.usage.output_tokens,
+            totals.estimated_cost.map(|cost| cost.to_string()).unwrap_or_default(),
+        ));
+    }
+    out
+}
+
+/// Quote a CSV field if it contains a delimiter, quote or newline
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(feature = "ledger")]
+pub use ledger_export::export_ledger;
+
+#[cfg(feature = "ledger")]
+mod ledger_export {
+    use std::sync::Arc;
+
+    use axum::{
+        extract::{Query, State},
+        http::header,
+        response::{IntoResponse, Response},
+        Json,
+    };
+    use chrono::DateTime;
+    use serde::Deserialize;
+
+    use super::csv_field;
+    use crate::{
+        error::{AppError, AppResult},
+        usage::ledger::LedgerEntry,
+        AppState,
+    };
+
+    /// Default and maximum number of rows per export
+    const DEFAULT_EXPORT_LIMIT: i64 = 10_000;
+    const MAX_EXPORT_LIMIT: i64 = 100_000;
+
+    /// Query parameters for the ledger export
+    #[derive(Debug, Deserialize)]
+    pub struct LedgerExportQuery {
+        /// Start of the range (RFC 3339, inclusive)
+        pub from: String,
+        /// End of the range (RFC 3339, exclusive)
+        pub to: String,
+        /// `json` (default) or `csv`
+        pub format: Option<String>,
+        /// Maximum rows to return (default 10,000, max 100,000)
+        pub limit: Option<i64>,
+    }
+
+    /// GET /admin/ledger/export - export ledger rows for a time range
+    pub async fn export_ledger(
+        State(state): State<Arc<AppState>>,
+        Query(query): Query<LedgerExportQuery>,
+    ) -> AppResult<Response> {
+        let store = state
+            .ledger
+            .as_ref()
+            .ok_or_else(|| AppError::NotFound("Usage ledger is not enabled".to_string()))?;
+
+        let from = parse_timestamp("from", &query.from)?;
+        let to = parse_timestamp("to", &query.to)?;
+        if from >= to {
+            return Err(AppError::BadRequest("'from' must be before 'to'".to_string()));
+        }
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_EXPORT_LIMIT)
+            .clamp(1, MAX_EXPORT_LIMIT);
+
+        let entries = store.export(from, to, limit).await?;
+
+        match query.format.as_deref().unwrap_or("json") {
+            "json" => Ok(Json(entries).into_response()),
+            "csv" => Ok((
+                [
+                    (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+                    (
+                        header::CONTENT_DISPOSITION,
+                        "attachment; filename=\"usage-ledger.csv\"",
+                    ),
+                ],
+                to_csv(&entries),
+            )
+                .into_response()),
+            other => Err(AppError::BadRequest(format!(
+                "Unsupported format '{}', expected 'json' or 'csv'",
+                other
+            ))),
+        }
+    }
+
+    /// Parse an RFC 3339 timestamp into Unix milliseconds
+    fn parse_timestamp(name: &str, value: &str) -> AppResult<i64> {
+        DateTime::parse_from_rfc3339(value)
+            .map(|dt| dt.timestamp_millis())
+            .map_err(|_| AppError::BadRequest(format!("'{}' must be an RFC 3339 timestamp", name)))
+    }
+
+    /// Render ledger rows as CSV with a header line
+    pub(super) fn to_csv(entries: &[LedgerEntry]) -> String {
+        let mut out = String::from(
+            "request_id,user_hash,model,input_tokens,output_tokens,requests,created_at,delivery_status,upstream_user\n",
+        );
+        for entry in entries {
+            out.push_str(&format!(
+                "{},{},{},{},{},{},{},{},{}\n",
+                csv_field(&entry.request_id),
+                csv_field(&entry.user_hash),
+                csv_field(entry.model.as_deref().unwrap_or("")),
+                entry.input_tokens,
+                entry.output_tokens,
+                entry.requests,
+                entry.created_at,
+                entry.delivery_status.as_str(),
+                csv_field(entry.upstream_user.as_deref().unwrap_or("")),
+            ));
+        }
+        out
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::usage::ledger::DeliveryStatus;
+
+        #[test]
+        fn test_to_csv_escapes_fields() {
+            let entries = vec![LedgerEntry {
+                request_id: "req-1".to_string(),
+                user_hash: "abc".to_string(),
+                model: Some("gpt-4o, \"mini\"".to_string()),
+                input_tokens: 10,
+                output_tokens: 5,
+                requests: 1,
+                created_at: 1_700_000_000_000,
+                delivery_status: DeliveryStatus::Delivered,
+                upstream_user: None,
+            }];
+
+            let csv = to_csv(&entries);
+            let lines: Vec<&str> = csv.lines().collect();
+            assert_eq!(lines.len(), 2);
+            assert!(lines[0].starts_with("request_id,user_hash,model"));
+            assert_eq!(
+                lines[1],
+                "req-1,abc,\"gpt-4o, \"\"mini\"\"\",10,5,1,1700000000000,delivered,"
+            );
+        }
+
+        #[test]
+        fn test_parse_timestamp() {
+            assert_eq!(
+                parse_timestamp("from", "2023-11-14T22:13:20Z").unwrap(),
+                1_700_000_000_000
+            );
+            assert!(parse_timestamp("from", "yesterday").is_err());
+        }
+    }
+}
+
+#[cfg(feature = "chaos")]
+pub use chaos_faults::{add_chaos_fault, clear_chaos_faults, delete_chaos_fault, list_chaos_faults};
+
+#[cfg(feature = "chaos")]
+mod chaos_faults {
+    use std::sync::Arc;
+
+    use axum::{
+        extract::{Path, State},
+        http::StatusCode,
+        Json,
+    };
+    use serde::Serialize;
+
+    use crate::{
+        chaos::{ActiveFault, ChaosInjector, FaultRule},
+        error::{AppError, AppResult},
+        AppState,
+    };
+
+    /// Response for deleting fault rules
+    #[derive(Debug, Serialize)]
+    pub struct FaultsRemoved {
+        pub removed: usize,
+    }
+
+    fn injector(state: &AppState) -> AppResult<&Arc<ChaosInjector>> {
+        state
+            .chaos
+            .as_ref()
+            .ok_or_else(|| AppError::NotFound("Chaos fault injection is not enabled".to_string()))
+    }
+
+    /// GET /admin/chaos/faults - fault rules in effect on this replica
+    pub async fn list_chaos_faults(
+        State(state): State<Arc<AppState>>,
+    ) -> AppResult<Json<Vec<ActiveFault>>> {
+        Ok(Json(injector(&state)?.list()))
+    }
+
+    /// POST /admin/chaos/faults - inject a fault until the rule's TTL runs out
+    ///
+    /// Applies only to the replica that receives the request.
+    pub async fn add_chaos_fault(
+        State(state): State<Arc<AppState>>,
+        Json(rule): Json<FaultRule>,
+    ) -> AppResult<(StatusCode, Json<ActiveFault>)> {
+        let chaos = injector(&state)?;
+        rule.validate().map_err(AppError::BadRequest)?;
+        Ok((StatusCode::CREATED, Json(chaos.add(rule))))
+    }
+
+    /// DELETE /admin/chaos/faults/:id - end one fault rule early
+    pub async fn delete_chaos_fault(
+        State(state): State<Arc<AppState>>,
+        Path(id): Path<String>,
+    ) -> AppResult<Json<FaultsRemoved>> {
+        if !injector(&state)?.remove(&id) {
+            return Err(AppError::NotFound(format!("No active fault rule '{}'", id)));
+        }
+        Ok(Json(FaultsRemoved { removed: 1 }))
+    }
+
+    /// DELETE /admin/chaos/faults - end every fault rule
+    pub async fn clear_chaos_faults(
+        State(state): State<Arc<AppState>>,
+    ) -> AppResult<Json<FaultsRemoved>> {
+        Ok(Json(FaultsRemoved {
+            removed: injector(&state)?.clear(),
+        }))
+    }
+}
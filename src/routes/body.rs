@@ -0,0 +1,523 @@
+//! Request body parsing shared by the typed `/v1` handlers and the native API
+//!
+//! [`SentinelJson`] replaces axum's `Json` extractor, whose plain-text
+//! rejections OpenAI SDKs can't parse. Bodies are rejected with the OpenAI
+//! error envelope instead: `error.code` tells syntax errors (`invalid_json`)
+//! from schema mismatches (`invalid_type`), and `error.param` holds the JSON
+//! path of the offending field. A missing or non-JSON `Content-Type` is a 415.
+//!
+//! serde_json keeps the last value when an object repeats a key, so a
+//! hand-built body with `messages` twice would silently drop the first list.
+//! Bodies are checked for duplicate top-level keys before the typed parse and
+//! rejected with a 400 naming the key.
+//!
+//! Before any parsing, the raw body is scanned once (without recursion) for
+//! its nesting depth, total number of object keys and longest string, so a
+//! body with thousands of nested arrays or a huge string is rejected with a
+//! 400 before serde spends time on it. The limits come from `JSON_MAX_*`.
+//!
+//! Bodies are read up to `MAX_REQUEST_BODY_BYTES`; a longer one is a 413
+//! `request_too_large`.
+//!
+//! NUL bytes and lone surrogate escapes are removed from the raw body before
+//! that (see [`crate::proxy::sanitize`]), so badly encoded client text is
+//! cleaned rather than rejected.
+//!
+//! Some SDKs also send `?stream=true` in the query while the body says
+//! otherwise. When the query carries `stream` it takes precedence over the
+//! body.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+
+use axum::{
+    async_trait,
+    extract::{FromRef, FromRequest, Request},
+    http::{header, StatusCode, Uri},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::de::{DeserializeOwned, Deserializer, IgnoredAny, MapAccess, Visitor};
+use serde_json::json;
+
+use crate::{
+    config::ServerConfig, error::AppError, middleware::decompression::REQUEST_TOO_LARGE_CODE,
+    proxy::sanitize, AppState,
+};
+
+/// Error code for bodies that aren't well-formed JSON
+pub const INVALID_JSON_CODE: &str = "invalid_json";
+
+/// Error code for JSON that doesn't match the request schema
+pub const INVALID_TYPE_CODE: &str = "invalid_type";
+
+/// Error code for a top-level key given twice
+pub const DUPLICATE_FIELD_CODE: &str = "duplicate_field";
+
+/// Error code for a missing or non-JSON `Content-Type`
+pub const UNSUPPORTED_MEDIA_TYPE_CODE: &str = "unsupported_media_type";
+
+/// Error code for arrays and objects nested deeper than `JSON_MAX_DEPTH`
+pub const JSON_TOO_DEEP_CODE: &str = "json_too_deep";
+
+/// Error code for bodies with more than `JSON_MAX_KEYS` object keys
+pub const JSON_TOO_MANY_KEYS_CODE: &str = "json_too_many_keys";
+
+/// Error code for a string longer than `JSON_MAX_STRING_BYTES`
+pub const JSON_STRING_TOO_LONG_CODE: &str = "json_string_too_long";
+
+/// Structural limits checked before a body is parsed (0 disables a limit)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JsonLimits {
+    /// Deepest nesting of arrays and objects
+    pub max_depth: usize,
+    /// Object keys in the whole body
+    pub max_keys: usize,
+    /// Bytes in any single string, as sent (escapes are not decoded)
+    pub max_string_bytes: usize,
+}
+
+impl JsonLimits {
+    /// No limits
+    pub const UNLIMITED: Self = Self {
+        max_depth: 0,
+        max_keys: 0,
+        max_string_bytes: 0,
+    };
+}
+
+impl From<&ServerConfig> for JsonLimits {
+    fn from(config: &ServerConfig) -> Self {
+        Self {
+            max_depth: config.json_max_depth,
+            max_keys: config.json_max_keys,
+            max_string_bytes: config.json_max_string_bytes,
+        }
+    }
+}
+
+/// JSON request body extractor with OpenAI-style rejections
+///
+/// Holds the parsed body and the size of the raw body in bytes, for payload
+/// metrics.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SentinelJson<T>(pub T, pub usize);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for SentinelJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+    Arc<AppState>: FromRef<S>,
+{
+    type Rejection = JsonBodyRejection;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        if !is_json_content_type(request.headers().get(header::CONTENT_TYPE)) {
+            return Err(JsonBodyRejection {
+                status: StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                code: UNSUPPORTED_MEDIA_TYPE_CODE,
+                message: "Expected request with `Content-Type: application/json`".to_string(),
+                param: None,
+            });
+        }
+
+        let endpoint = request.uri().path().to_string();
+        let server = &Arc::<AppState>::from_ref(state).config.server;
+        let body = axum::body::to_bytes(request.into_body(), server.max_request_body_bytes)
+            .await
+            .map_err(|_| JsonBodyRejection::too_large(server.max_request_body_bytes))?;
+        let limits = JsonLimits::from(server);
+        let (sanitized, report) = sanitize::sanitize_json_text(&body);
+        report.record(&endpoint);
+        parse_json_body(&sanitized, &limits).map(|value| SentinelJson(value, body.len()))
+    }
+}
+
+/// A request body that couldn't be parsed, rendered as an OpenAI error
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonBodyRejection {
+    pub status: StatusCode,
+    pub code: &'static str,
+    pub message: String,
+    /// JSON path of the offending field, when known
+    pub param: Option<String>,
+}
+
+impl JsonBodyRejection {
+    /// A body longer than `limit` bytes (`MAX_REQUEST_BODY_BYTES`)
+    pub fn too_large(limit: usize) -> Self {
+        Self {
+            status: StatusCode::PAYLOAD_TOO_LARGE,
+            code: REQUEST_TOO_LARGE_CODE,
+            message: format!("Request body exceeds {} bytes", limit),
+            param: None,
+        }
+    }
+
+    fn bad_request(code: &'static str, message: String, param: Option<String>) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            code,
+            message,
+            param,
+        }
+    }
+}
+
+impl IntoResponse for JsonBodyRejection {
+    fn into_response(self) -> Response {
+        let body = json!({
+            "error": {
+                "message": self.message,
+                "type": "invalid_request_error",
+                "param": self.param,
+                "code": self.code,
+                "retryable": false,
+            }
+        });
+        (self.status, Json(body)).into_response()
+    }
+}
+
+/// Whether a `Content-Type` is `application/json` or an `application/*+json` type
+pub(crate) fn is_json_content_type(value: Option<&header::HeaderValue>) -> bool {
+    let Some(value) = value.and_then(|value| value.to_str().ok()) else {
+        return false;
+    };
+    let essence = value.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+    essence == "application/json"
+        || (essence.starts_with("application/") && essence.ends_with("+json"))
+}
+
+/// Parse a JSON request body, rejecting oversized structures and duplicate top-level keys
+pub fn parse_json_body<T: DeserializeOwned>(
+    body: &[u8],
+    limits: &JsonLimits,
+) -> Result<T, JsonBodyRejection> {
+    check_structure(body, limits)?;
+    if let Some(key) = duplicate_top_level_key(body) {
+        return Err(JsonBodyRejection::bad_request(
+            DUPLICATE_FIELD_CODE,
+            format!("Invalid request body: duplicate field `{}`", key),
+            Some(key),
+        ));
+    }
+
+    let deserializer = &mut serde_json::Deserializer::from_slice(body);
+    serde_path_to_error::deserialize(deserializer).map_err(|e| {
+        // serde_json reports some type mismatches (e.g. a number for an enum)
+        // as syntax errors, so check whether the body itself is well-formed
+        let schema_mismatch =
+            e.inner().is_data() || serde_json::from_slice::<IgnoredAny>(body).is_ok();
+        if !schema_mismatch {
+            return JsonBodyRejection::bad_request(
+                INVALID_JSON_CODE,
+                format!("Invalid request body: {}", e.inner()),
+                None,
+            );
+        }
+        JsonBodyRejection::bad_request(
+            INVALID_TYPE_CODE,
+            format!("Invalid request body: {}", e.inner()),
+            error_param(e.path(), e.inner()),
+        )
+    })
+}
+
+/// Check a body against the structural limits in a single pass
+///
+/// Only tracks whether the cursor is inside a string, so the cost is linear
+/// in the body size whatever its shape. Object keys are counted by their `:`
+/// separators. Malformed JSON that passes is left to the parse to report.
+fn check_structure(body: &[u8], limits: &JsonLimits) -> Result<(), JsonBodyRejection> {
+    let exceeded = |limit: usize, value: usize| limit > 0 && value > limit;
+    let mut depth = 0usize;
+    let mut keys = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut string_bytes = 0usize;
+
+    for &byte in body {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if byte == b'\\' {
+                escaped = true;
+            } else if byte == b'"' {
+                in_string = false;
+                continue;
+            }
+            string_bytes += 1;
+            if exceeded(limits.max_string_bytes, string_bytes) {
+                return Err(JsonBodyRejection::bad_request(
+                    JSON_STRING_TOO_LONG_CODE,
+                    format!(
+                        "Invalid request body: string longer than {} bytes",
+                        limits.max_string_bytes
+                    ),
+                    None,
+                ));
+            }
+            continue;
+        }
+        match byte {
+            b'"' => {
+                in_string = true;
+                string_bytes = 0;
+            }
+            b'[' | b'{' => {
+                depth += 1;
+                if exceeded(limits.max_depth, depth) {
+                    return Err(JsonBodyRejection::bad_request(
+                        JSON_TOO_DEEP_CODE,
+                        format!(
+                            "Invalid request body: nested deeper than {} levels",
+                            limits.max_depth
+                        ),
+                        None,
+                    ));
+                }
+            }
+            b']' | b'}' => depth = depth.saturating_sub(1),
+            b':' => {
+                keys += 1;
+                if exceeded(limits.max_keys, keys) {
+                    return Err(JsonBodyRejection::bad_request(
+                        JSON_TOO_MANY_KEYS_CODE,
+                        format!(
+                            "Invalid request body: more than {} object keys",
+                            limits.max_keys
+                        ),
+                        None,
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// JSON path of a schema mismatch, as OpenAI's `param`
+///
+/// A missing field is reported at its parent object, so the field name is
+/// appended. Root-level failures have no path.
+fn error_param(path: &serde_path_to_error::Path, error: &serde_json::Error) -> Option<String> {
+    let path = path.to_string();
+    let missing = error
+        .to_string()
+        .strip_prefix("missing field `")
+        .and_then(|rest| rest.split('`').next())
+        .map(str::to_string);
+
+    match (path.as_str(), missing) {
+        (".", None) => None,
+        (".", Some(field)) => Some(field),
+        (parent, Some(field)) => Some(format!("{}.{}", parent, field)),
+        (path, None) => Some(path.to_string()),
+    }
+}
+
+/// First top-level key that appears twice, if the body is a JSON object
+///
+/// Bodies that aren't JSON objects (or aren't valid JSON) return None and are
+/// left to the typed parse to report.
+fn duplicate_top_level_key(body: &[u8]) -> Option<String> {
+    struct DuplicateKey(Option<String>);
+
+    impl<'de> serde::Deserialize<'de> for DuplicateKey {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_any(DuplicateKeyVisitor)
+        }
+    }
+
+    struct DuplicateKeyVisitor;
+
+    impl<'de> Visitor<'de> for DuplicateKeyVisitor {
+        type Value = DuplicateKey;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a JSON object")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            // Keep consuming after a hit: serde_json rejects a partially read object
+            let mut seen = HashSet::new();
+            let mut duplicate = None;
+            while let Some(key) = map.next_key::<String>()? {
+                if duplicate.is_none() && !seen.insert(key.clone()) {
+                    duplicate = Some(key);
+                }
+                map.next_value::<IgnoredAny>()?;
+            }
+            Ok(DuplicateKey(duplicate))
+        }
+    }
+
+    serde_json::from_slice::<DuplicateKey>(body).ok().and_then(|found| found.0)
+}
+
+/// `stream` query parameter, when present
+///
+/// Accepts `true`/`false` and `1`/`0`; a bare `?stream` means true.
+pub fn stream_override(uri: &Uri) -> Result<Option<bool>, AppError> {
+    let Some(query) = uri.query() else {
+        return Ok(None);
+    };
+
+    let mut value = None;
+    for pair in query.split('&') {
+        let (name, raw) = pair.split_once('=').unwrap_or((pair, "true"));
+        if name != "stream" {
+            continue;
+        }
+        value = Some(match raw {
+            "true" | "1" | "" => true,
+            "false" | "0" => false,
+            other => {
+                return Err(AppError::BadRequest(format!(
+                    "Invalid stream query parameter: {} (expected true or false)",
+                    other
+                )))
+            }
+        });
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duplicate_top_level_key_detection() {
+        assert_eq!(
+            duplicate_top_level_key(br#"{"messages": [], "model": "m", "messages": []}"#),
+            Some("messages".to_string())
+        );
+        // Escaped spellings of the same key are still duplicates
+        assert_eq!(
+            duplicate_top_level_key(br#"{"stream": true, "str\u0065am": false}"#),
+            Some("stream".to_string())
+        );
+        // Nested objects may reuse top-level names
+        assert_eq!(
+            duplicate_top_level_key(br#"{"messages": [{"role": "user", "content": "x"}], "metadata": {"messages": 1}}"#),
+            None
+        );
+        assert_eq!(duplicate_top_level_key(b"{not json"), None);
+        assert_eq!(duplicate_top_level_key(b"[1, 2]"), None);
+    }
+
+    #[test]
+    fn test_parse_errors_point_at_the_field() {
+        #[derive(Debug, serde::Deserialize)]
+        struct Message {
+            #[allow(dead_code)]
+            content: String,
+        }
+        #[derive(Debug, serde::Deserialize)]
+        struct Body {
+            #[allow(dead_code)]
+            messages: Vec<Message>,
+        }
+
+        let error = parse_json_body::<Body>(br#"{"messages": [{"content": 5}]}"#, &JsonLimits::UNLIMITED).unwrap_err();
+        assert_eq!(error.code, INVALID_TYPE_CODE);
+        assert_eq!(error.param.as_deref(), Some("messages[0].content"));
+
+        let error = parse_json_body::<Body>(br#"{"messages": [{}]}"#, &JsonLimits::UNLIMITED).unwrap_err();
+        assert_eq!(error.param.as_deref(), Some("messages[0].content"));
+
+        let error = parse_json_body::<Body>(b"{}", &JsonLimits::UNLIMITED).unwrap_err();
+        assert_eq!(error.param.as_deref(), Some("messages"));
+
+        // serde_json calls an enum given a number a syntax error; it's a type error
+        #[derive(Debug, serde::Deserialize)]
+        #[serde(rename_all = "lowercase")]
+        enum Role {
+            User,
+        }
+        #[derive(Debug, serde::Deserialize)]
+        struct WithRole {
+            #[allow(dead_code)]
+            role: Role,
+        }
+        let error = parse_json_body::<WithRole>(br#"{"role": 5}"#, &JsonLimits::UNLIMITED).unwrap_err();
+        assert_eq!(error.code, INVALID_TYPE_CODE);
+        assert_eq!(error.param.as_deref(), Some("role"));
+
+        let error = parse_json_body::<Body>(br#"{"messages": [}"#, &JsonLimits::UNLIMITED).unwrap_err();
+        assert_eq!(error.code, INVALID_JSON_CODE);
+        assert_eq!(error.param, None);
+    }
+
+    #[test]
+    fn test_structural_limits() {
+        let limits = JsonLimits {
+            max_depth: 3,
+            max_keys: 2,
+            max_string_bytes: 5,
+        };
+        let check = |body: &str| check_structure(body.as_bytes(), &limits).map_err(|e| e.code);
+
+        assert_eq!(check(r#"{"a": [[1]], "b": "hello"}"#), Ok(()));
+        assert_eq!(check(r#"{"a": [[[1]]]}"#), Err(JSON_TOO_DEEP_CODE));
+        assert_eq!(check(r#"{"a": 1, "b": 2, "c": 3}"#), Err(JSON_TOO_MANY_KEYS_CODE));
+        assert_eq!(check(r#"{"a": "hello!"}"#), Err(JSON_STRING_TOO_LONG_CODE));
+        // Brackets, colons and escaped quotes inside strings don't count
+        assert_eq!(check(r#"["[[{", "a:b", "\"}"]"#), Ok(()));
+        assert_eq!(check_structure(&[b'['; 1000], &JsonLimits::UNLIMITED), Ok(()));
+    }
+
+    #[test]
+    fn test_adversarial_bodies_are_rejected_quickly() {
+        let limits = JsonLimits {
+            max_depth: 64,
+            max_keys: 10_000,
+            max_string_bytes: 1_048_576,
+        };
+        let deep = format!("{{\"tools\": {}{}}}", "[".repeat(100_000), "]".repeat(100_000));
+        let keys = format!(
+            "{{{}}}",
+            (0..100_000).map(|i| format!("\"k{}\": 0", i)).collect::<Vec<_>>().join(",")
+        );
+        let long = format!("{{\"prompt\": \"{}\"}}", "a".repeat(8 * 1_048_576));
+
+        let started = std::time::Instant::now();
+        for (body, code) in [
+            (deep, JSON_TOO_DEEP_CODE),
+            (keys, JSON_TOO_MANY_KEYS_CODE),
+            (long, JSON_STRING_TOO_LONG_CODE),
+        ] {
+            let error = parse_json_body::<serde_json::Value>(body.as_bytes(), &limits).unwrap_err();
+            assert_eq!(error.code, code);
+        }
+        assert!(started.elapsed() < std::time::Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_json_content_types() {
+        let check = |value: &str| is_json_content_type(Some(&header::HeaderValue::from_str(value).unwrap()));
+        assert!(check("application/json"));
+        assert!(check("Application/JSON; charset=utf-8"));
+        assert!(check("application/merge-patch+json"));
+        assert!(!check("text/plain"));
+        assert!(!check("application/x-www-form-urlencoded"));
+        assert!(!is_json_content_type(None));
+    }
+
+    #[test]
+    fn test_stream_override() {
+        let parse = |uri: &str| stream_override(&uri.parse::<Uri>().unwrap());
+
+        assert_eq!(parse("/v1/chat/completions").unwrap(), None);
+        assert_eq!(parse("/v1/chat/completions?api-version=1").unwrap(), None);
+        assert_eq!(parse("/v1/chat/completions?stream=true").unwrap(), Some(true));
+        assert_eq!(parse("/v1/chat/completions?stream=0").unwrap(), Some(false));
+        assert_eq!(parse("/v1/chat/completions?x=1&stream").unwrap(), Some(true));
+        assert!(parse("/v1/chat/completions?stream=yes").is_err());
+    }
+}
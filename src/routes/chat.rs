@@ -4,14 +4,14 @@
 //! Handles both streaming and non-streaming responses.
 
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::Duration;
 
 use axum::{
-    body::Body,
+    body::{Body, HttpBody},
     extract::State,
-    http::{header, HeaderMap, StatusCode},
+    http::{header, HeaderMap, StatusCode, Uri},
     response::{IntoResponse, Response},
-    Json,
+    Extension, Json,
 };
 use futures::StreamExt;
 use serde::{Deserialize, Serialize};
@@ -19,12 +19,32 @@ use tracing::{debug, info, warn};
 
 use crate::{
     error::AppError,
-    middleware::auth::AuthenticatedUser,
-    routes::metrics::{
-        record_fallback_estimation, record_request, record_sse_parse_error,
-        record_token_estimation_diff, record_tokens,
+    injection,
+    middleware::{auth::AuthenticatedUser, compat, model_access},
+    native::{max_stop_sequences, validate_stop_value},
+    proxy::{
+        capture,
+        complexity::{self, RequestComplexity, NO_TIER},
+        content_filter,
+        deprecated::{self, DeprecatedParams},
+        fallback::{self, FallbackModels},
+        logging::{json_len, truncate_utf8},
+        progress, reasoning,
+        response_filter::ResponseFilter,
+        sanitize::{self, SanitizeReport},
+        snapshot,
+        stream_accept::{self, Negotiation},
+        timeout, upstream_user, validation, RequestContext,
     },
-    streaming::SseLineBuffer,
+    routes::{
+        body::{self, SentinelJson},
+        metrics::{
+            record_content_blocked, record_fallback_estimation, record_request, record_sse_parse_error,
+            record_token_estimation_diff, record_tokens, record_upstream_invalid_response,
+        },
+    },
+    streaming::{encode_lines, sse_response, SseLineBuffer},
+    usage::{ledger::hash_user, workflow_id_from_headers},
     AppState,
 };
 
@@ -35,6 +55,7 @@ fn messages_to_tuples(messages: &[ChatMessage]) -> Vec<(String, String, Option<S
         .map(|msg| {
             let role = match msg.role {
                 Role::System => "system",
+                Role::Developer => "developer",
                 Role::User => "user",
                 Role::Assistant => "assistant",
                 Role::Tool => "tool",
@@ -49,11 +70,43 @@ fn messages_to_tuples(messages: &[ChatMessage]) -> Vec<(String, String, Option<S
         .collect()
 }
 
+/// Message count and content size of a chat request
+fn request_complexity(request: &ChatCompletionRequest) -> RequestComplexity {
+    let mut complexity = RequestComplexity::new(request.stream)
+        .with_tools(complexity::offers_tools(request.tools.as_ref()));
+    for message in &request.messages {
+        complexity.add_message(message.content.as_deref());
+    }
+    complexity
+}
+
+/// Concatenate function names and argument strings from a `tool_calls` array
+///
+/// Used for output token estimation when a response carries tool calls
+/// (often with null content) and the provider didn't report usage.
+fn tool_calls_text(tool_calls: &serde_json::Value) -> String {
+    let mut text = String::new();
+    if let Some(calls) = tool_calls.as_array() {
+        for call in calls {
+            if let Some(function) = call.get("function") {
+                if let Some(name) = function.get("name").and_then(|n| n.as_str()) {
+                    text.push_str(name);
+                }
+                if let Some(arguments) = function.get("arguments").and_then(|a| a.as_str()) {
+                    text.push_str(arguments);
+                }
+            }
+        }
+    }
+    text
+}
+
 /// Chat message role
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum Role {
     System,
+    Developer,
     User,
     Assistant,
     Tool,
@@ -74,10 +127,50 @@ pub struct ChatMessage {
 }
 
 /// Stream options for including usage in streaming responses
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Options Sentinel doesn't interpret (`include_obfuscation` and any added
+/// later) are forwarded as the client sent them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct StreamOptions {
-    #[serde(default)]
-    pub include_usage: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub include_usage: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub include_obfuscation: Option<bool>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Who a chat stream's final usage chunk is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsageChunk {
+    /// The client set `include_usage`: forwarded exactly as received
+    Client,
+    /// Sentinel set `include_usage` for its own accounting: consumed before the client
+    Sentinel,
+    /// Nobody asked for it; output tokens are estimated from the content
+    NotRequested,
+}
+
+impl StreamOptions {
+    /// Options to send upstream for the client's `stream_options`
+    ///
+    /// With `inject_usage`, `include_usage` is turned on when the client
+    /// didn't, and the usage chunk is Sentinel's to consume.
+    pub fn for_upstream(client: Option<Self>, inject_usage: bool) -> (Option<Self>, UsageChunk) {
+        let requested = client
+            .as_ref()
+            .and_then(|options| options.include_usage)
+            .unwrap_or(false);
+        if requested {
+            (client, UsageChunk::Client)
+        } else if inject_usage {
+            let mut options = client.unwrap_or_default();
+            options.include_usage = Some(true);
+            (Some(options), UsageChunk::Sentinel)
+        } else {
+            (client, UsageChunk::NotRequested)
+        }
+    }
 }
 
 /// Chat completion request
@@ -164,30 +257,124 @@ fn extract_bearer_token(headers: &HeaderMap) -> Option<String> {
 pub async fn chat_completions(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
-    request: axum::extract::Request,
+    uri: Uri,
+    Extension(mut user): Extension<AuthenticatedUser>,
+    SentinelJson(mut chat_request, body_len): SentinelJson<ChatCompletionRequest>,
 ) -> Result<Response, AppError> {
-    let start_time = Instant::now();
-
-    // Extract authenticated user from request extensions (set by auth middleware)
-    let user = request
-        .extensions()
-        .get::<AuthenticatedUser>()
-        .cloned()
-        .ok_or_else(|| {
-            warn!("AuthenticatedUser not found in request extensions");
-            AppError::Unauthorized
-        })?;
-
-    // Parse the request body
-    let body = axum::body::to_bytes(request.into_body(), usize::MAX)
-        .await
-        .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to read request body: {}", e)))?;
+    let ctx = RequestContext::new(state.provider().name(), "/v1/chat/completions");
+
+    // A `stream` query parameter overrides the body's flag
+    let stream_override = body::stream_override(&uri)?;
+    if let Some(stream) = stream_override {
+        chat_request.stream = stream;
+    }
+
+    // A stream the Accept header rules out is refused or served per STREAM_ACCEPT_MISMATCH
+    let negotiation = Negotiation::check(&headers, chat_request.stream);
+    let accept_mode = state.config.provider.stream_accept_mismatch;
+    negotiation.record(accept_mode, "/v1/chat/completions", &headers);
+    if negotiation.rejected(accept_mode) {
+        return Err(AppError::NotAcceptable(
+            stream_accept::NOT_ACCEPTABLE_MESSAGE.to_string(),
+        ));
+    }
+
+    // Normalized before token estimation so both see the same text
+    if state.config.provider.content_normalize_nfc {
+        let normalized = sanitize::normalize_texts(
+            chat_request
+                .messages
+                .iter_mut()
+                .filter_map(|message| message.content.as_mut()),
+        );
+        SanitizeReport { normalized, ..Default::default() }.record("/v1/chat/completions");
+    }
+
+    // The provider only sees the `user` value UPSTREAM_USER_FIELD allows
+    chat_request.user =
+        upstream_user::apply(&state.config.provider, &mut user, chat_request.user.as_deref());
 
-    let chat_request: ChatCompletionRequest = serde_json::from_slice(&body)
-        .map_err(|e| AppError::BadRequest(format!("Invalid request body: {}", e)))?;
+    // Counted on the client's messages, before any injected system prompt
+    let complexity = request_complexity(&chat_request);
+    complexity.record("/v1/chat/completions", NO_TIER);
+
+    // Reject stop sequences the provider would refuse, with the same rules as the native API
+    if let Some(ref stop) = chat_request.stop {
+        validate_stop_value(stop, max_stop_sequences(state.provider().name()))
+            .map_err(AppError::BadRequest)?;
+    }
+
+    // Inject the configured system prompt before token estimation so it is counted
+    let system_prompt_injected = injection::resolve_preamble(&state.config, None)
+        .map(|preamble| {
+            injection::inject_chat_messages(
+                &mut chat_request.messages,
+                preamble,
+                state.config.provider.system_prompt_injection_mode,
+            )
+        })
+        .unwrap_or(false);
 
     let model = chat_request.model.clone();
+
+    // Reasoning models take `developer` messages and reject sampling parameters
+    let tier_config = state.tier_config_cache.get_config().await.ok();
+    let reasoning_model = tier_config
+        .as_ref()
+        .is_some_and(|config| config.is_reasoning_model(&model));
+    let filter = ResponseFilter::for_model(tier_config.as_ref(), &state.config.provider, &model);
+
+    // Fallback models are a Sentinel extension (refused in strict mode); the provider never sees them
+    compat::reject_extensions(state.config.server.compat_mode, &chat_request.extra)?;
+    let fallback = fallback::take_models(&mut chat_request.extra)
+        .map_err(AppError::BadRequest)?
+        .map(|models| {
+            FallbackModels::resolve(&model, models, tier_config.as_ref(), state.provider().name())
+        })
+        .transpose()?;
+
+    // Subscriptions may be limited to some models (Zion `allowedModels`), fallbacks included
+    let requested: Vec<&str> = match &fallback {
+        Some(fallback) => fallback.models().iter().map(|f| f.model.as_str()).collect(),
+        None => vec![model.as_str()],
+    };
+    model_access::check(&user, "/v1/chat/completions", &requested)?;
+
+    // Deprecated OpenAI parameters are flagged, and rewritten or refused per DEPRECATED_PARAMS
+    let deprecations = deprecated::detect(
+        chat_request.extra.as_ref(),
+        reasoning_model && chat_request.max_tokens.is_some(),
+    );
+    let deprecated_mode = state.config.provider.deprecated_params;
+    deprecated::record(&deprecations, deprecated_mode, user.log_id(), &model);
+    match deprecated_mode {
+        DeprecatedParams::Reject if !deprecations.is_empty() => {
+            return deprecated::with_header(
+                Err(deprecated::rejection(&deprecations)),
+                &deprecations,
+            );
+        }
+        DeprecatedParams::Translate if !deprecations.is_empty() => {
+            chat_request = translate_deprecated(chat_request, &deprecations)?;
+        }
+        _ => {}
+    }
+
+    if reasoning_model {
+        chat_request = adapt_for_reasoning_model(chat_request)?;
+    }
+
     let is_streaming = chat_request.stream;
+    let timeout = timeout::timeout_from_headers(&state.config, &headers)?;
+    let workflow_id = workflow_id_from_headers(&headers).map_err(AppError::BadRequest)?;
+    let ctx = ctx
+        .with_model(model.clone())
+        .with_streaming(is_streaming)
+        .with_external_id(user.log_id())
+        .with_user_hash(hash_user(&user.email))
+        .with_workflow_id(workflow_id)
+        .with_request_bytes(body_len as u64)
+        .with_complexity(complexity);
 
     // Extract authorization token (kept for potential future use)
     let _token = extract_bearer_token(&headers);
@@ -196,27 +383,100 @@ pub async fn chat_completions(
         model = %model,
         stream = %is_streaming,
         messages = %chat_request.messages.len(),
-        external_id = %user.external_id,
+        system_prompt_injected = system_prompt_injected,
+        reasoning_model = reasoning_model,
+        fallback_models = fallback.as_ref().map_or(0, |f| f.models().len() - 1),
+        external_id = %user.log_id(),
+        workflow_id = ?ctx.workflow_id,
         "Processing chat completion request"
     );
 
-    if is_streaming {
+    let result = if is_streaming {
         // Handle streaming response
-        handle_streaming_chat(state, &headers, chat_request, model, start_time, user).await
+        handle_streaming_chat(
+            state,
+            &headers,
+            chat_request,
+            model,
+            ctx,
+            user,
+            timeout,
+            filter,
+            fallback,
+        )
+        .await
+    } else if progress::requested(&headers) {
+        // Same non-streaming handling, with heartbeats while the upstream call runs
+        let interval = Duration::from_millis(state.config.provider.progress_interval_ms);
+        let request = async move {
+            handle_non_streaming_chat(
+                state,
+                &headers,
+                chat_request,
+                model,
+                ctx,
+                user,
+                timeout,
+                filter,
+                fallback,
+            )
+            .await
+        };
+        Ok(progress::progress_response(request, interval))
     } else {
         // Handle non-streaming response
-        handle_non_streaming_chat(state, &headers, chat_request, model, start_time, user).await
-    }
+        handle_non_streaming_chat(
+            state,
+            &headers,
+            chat_request,
+            model,
+            ctx,
+            user,
+            timeout,
+            filter,
+            fallback,
+        )
+        .await
+    };
+
+    let result = deprecated::with_header(timeout::with_timeout_header(result, timeout), &deprecations);
+    negotiation.with_hint(result)
+}
+
+/// Rewrite deprecated parameters (see [`deprecated::translate`])
+fn translate_deprecated(
+    request: ChatCompletionRequest,
+    deprecations: &[deprecated::Deprecation],
+) -> Result<ChatCompletionRequest, AppError> {
+    let mut value = serde_json::to_value(&request)
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to serialize request: {}", e)))?;
+    deprecated::translate(&mut value, deprecations).map_err(AppError::BadRequest)?;
+    serde_json::from_value(value)
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to translate request: {}", e)))
+}
+
+/// Rewrite a request for a reasoning model (see [`reasoning::adapt_chat_request`])
+fn adapt_for_reasoning_model(request: ChatCompletionRequest) -> Result<ChatCompletionRequest, AppError> {
+    let mut value = serde_json::to_value(&request)
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to serialize request: {}", e)))?;
+    let stripped = reasoning::adapt_chat_request(&mut value);
+    reasoning::warn_stripped(&request.model, &stripped);
+    serde_json::from_value(value)
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to adapt request: {}", e)))
 }
 
 /// Handle non-streaming chat completion
+#[allow(clippy::too_many_arguments)]
 async fn handle_non_streaming_chat(
     state: Arc<AppState>,
     headers: &HeaderMap,
     request: ChatCompletionRequest,
     model: String,
-    start_time: Instant,
+    ctx: RequestContext,
     user: AuthenticatedUser,
+    timeout: Option<Duration>,
+    filter: Option<ResponseFilter>,
+    fallback: Option<FallbackModels>,
 ) -> Result<Response, AppError> {
     // Pre-count input tokens using tiktoken (for fallback if OpenAI doesn't return usage)
     let message_tuples = messages_to_tuples(&request.messages);
@@ -228,18 +488,71 @@ async fn handle_non_streaming_chat(
     // Convert request to Value for the provider
     let request_value = serde_json::to_value(&request)
         .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to serialize request: {}", e)))?;
+    let ctx = ctx.with_forwarded_bytes(json_len(&request_value));
 
-    let response_value = state
-        .ai_provider
-        .chat_completions(request_value, headers)
-        .await?;
+    let provider = state.provider();
+    let call = |request| timeout::call_with_timeout(timeout, provider.chat_completions(request, headers));
+    let ((response_value, fallback_served), upstream) = capture::capture(async {
+        match fallback {
+            Some(ref fallback) => {
+                let (result, served) = fallback.run(&state.tier_router, &request_value, call).await;
+                (result, Some(served.clone()))
+            }
+            None => (call(request_value).await, None),
+        }
+    })
+    .await;
+    ctx.record_upstream_headers(upstream);
+    let mut response_value = response_value?;
+    // Everything below is about the model that answered
+    let model = fallback_served.as_ref().map_or(model, |served| served.model.clone());
 
     // Parse the response
-    let response: ChatCompletionResponse = serde_json::from_value(response_value.clone())
+    let parsed = serde_json::from_value::<ChatCompletionResponse>(response_value.clone());
+
+    // A 200 that clients can't use becomes a 502 (VALIDATE_UPSTREAM_RESPONSES)
+    if state.config.provider.validate_upstream_responses {
+        let invalid = validation::validate_chat_completion(&response_value)
+            .err()
+            .or_else(|| parsed.as_ref().err().map(|e| e.to_string()));
+        if let Some(reason) = invalid {
+            let provider = state.provider().name();
+            let upstream_headers = ctx.upstream_headers();
+            let body = response_value.to_string();
+            warn!(
+                provider = provider,
+                model = %model,
+                reason = %reason,
+                upstream_headers = %upstream_headers,
+                body = %truncate_utf8(&body, validation::LOGGED_BODY_BYTES),
+                "Upstream returned an invalid chat completion"
+            );
+            record_upstream_invalid_response(provider);
+            return Err(AppError::UpstreamInvalidResponse {
+                reason,
+                request_id: upstream_headers.request_id().map(str::to_string),
+            });
+        }
+    }
+    let response = parsed
         .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to parse response: {}", e)))?;
 
+    // Usage below is counted on the unfiltered response; the client gets the filtered one
+    let client_response = match filter {
+        Some(ref filter) => {
+            filter.apply_to_response(&mut response_value);
+            serde_json::from_value(response_value)
+                .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to parse response: {}", e)))?
+        }
+        None => response.clone(),
+    };
+
+    // Usage is attributed to the snapshot the provider actually served
+    let served_model = snapshot::attributed_model(&model, Some(&response.model));
+    state.model_snapshots.observe(&model, &served_model).await;
+
     // Record metrics
-    let duration = start_time.elapsed().as_secs_f64();
+    let duration = ctx.start_time.elapsed().as_secs_f64();
     record_request("success", &model, duration);
 
     // Get token counts: prefer OpenAI usage, fallback to estimation
@@ -267,15 +580,21 @@ async fn handle_non_streaming_chat(
         (usage.prompt_tokens as u64, usage.completion_tokens as u64)
     } else {
         // Fallback: use estimated input tokens, estimate output from response text
+        // and any tool call arguments (tool-call turns usually have null content)
         let output_text = response
             .choices
             .first()
-            .and_then(|c| c.message.content.as_ref())
-            .map(|s| s.as_str())
-            .unwrap_or("");
+            .map(|c| {
+                let mut text = c.message.content.clone().unwrap_or_default();
+                if let Some(ref tool_calls) = c.message.tool_calls {
+                    text.push_str(&tool_calls_text(tool_calls));
+                }
+                text
+            })
+            .unwrap_or_default();
         let estimated_output = state
             .token_counter
-            .count_tokens(&model, output_text)
+            .count_tokens(&model, &output_text)
             .unwrap_or(0) as u64;
         debug!(
             estimated_input = estimated_input_tokens,
@@ -289,23 +608,69 @@ async fn handle_non_streaming_chat(
     record_tokens("completion", output_tokens, &model);
 
     // Track usage in Zion (fire-and-forget, never blocks)
-    state.batching_tracker.track(user.email.clone(), input_tokens, output_tokens, Some(model.clone()));
+    state.batching_tracker.track_user_in_workflow(
+        &user,
+        ctx.workflow_id.clone(),
+        input_tokens,
+        output_tokens,
+        Some(served_model.clone()),
+    );
+
+    let finish_reason = response
+        .choices
+        .first()
+        .and_then(|c| c.finish_reason.as_deref());
+    state.finish_reasons.observe("chat", &model, finish_reason);
+    let finish_reason = finish_reason.unwrap_or("unknown");
+
+    // Checked after tracking: the upstream tokens were spent either way
+    if let Some(ref blocklist) = state.config.provider.response_blocklist {
+        let blocked = client_response
+            .choices
+            .iter()
+            .filter_map(|c| c.message.content.as_deref())
+            .any(|content| blocklist.is_blocked(content));
+        if blocked {
+            warn!(model = %model, external_id = %user.log_id(), "Response matched the blocklist");
+            record_content_blocked("chat");
+            return Err(AppError::ContentBlocked);
+        }
+    }
 
     info!(
         model = %model,
         duration_ms = %format!("{:.2}", duration * 1000.0),
         input_tokens = input_tokens,
         output_tokens = output_tokens,
-        external_id = %user.external_id,
+        finish_reason = %finish_reason,
+        messages = ctx.complexity.messages,
+        content_chars = ctx.complexity.content_chars,
+        tools = ctx.complexity.tools,
+        external_id = %user.log_id(),
+        workflow_id = ?ctx.workflow_id,
+        upstream_headers = %ctx.upstream_headers(),
         "Chat completion request completed"
     );
 
-    Ok((StatusCode::OK, Json(response)).into_response())
+    let mut response = (StatusCode::OK, Json(client_response)).into_response();
+    ctx.add_response_bytes(response.body().size_hint().exact().unwrap_or(0));
+    ctx.record_payload_sizes(
+        state.config.server.payload_warn_request_bytes,
+        state.config.server.payload_warn_response_bytes,
+    );
+    snapshot::insert_upstream_model_header(response.headers_mut(), &served_model);
+    if let Some(ref served) = fallback_served {
+        fallback::insert_model_header(response.headers_mut(), served);
+    }
+    ctx.upstream_headers().insert_request_id_header(response.headers_mut());
+    Ok(response)
 }
 
 /// Streaming chunk for parsing content and usage
 #[derive(Debug, Clone, Deserialize)]
 struct StreamChunk {
+    #[serde(default)]
+    model: Option<String>,
     #[serde(default)]
     choices: Vec<StreamChoice>,
     #[serde(default)]
@@ -317,6 +682,8 @@ struct StreamChunk {
 struct StreamChoice {
     #[serde(default)]
     delta: StreamDelta,
+    #[serde(default)]
+    finish_reason: Option<String>,
 }
 
 /// Streaming delta content
@@ -324,16 +691,38 @@ struct StreamChoice {
 struct StreamDelta {
     #[serde(default)]
     content: Option<String>,
+    #[serde(default)]
+    tool_calls: Option<Vec<StreamToolCallDelta>>,
+}
+
+/// Streaming tool call fragment
+#[derive(Debug, Clone, Deserialize, Default)]
+struct StreamToolCallDelta {
+    #[serde(default)]
+    function: Option<StreamFunctionDelta>,
+}
+
+/// Streaming function name/arguments fragment
+#[derive(Debug, Clone, Deserialize, Default)]
+struct StreamFunctionDelta {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    arguments: Option<String>,
 }
 
 /// Handle streaming chat completion
+#[allow(clippy::too_many_arguments)]
 async fn handle_streaming_chat(
     state: Arc<AppState>,
     headers: &HeaderMap,
     mut request: ChatCompletionRequest,
     model: String,
-    start_time: Instant,
+    ctx: RequestContext,
     user: AuthenticatedUser,
+    timeout: Option<Duration>,
+    filter: Option<ResponseFilter>,
+    fallback: Option<FallbackModels>,
 ) -> Result<Response, AppError> {
     // Pre-count input tokens using tiktoken
     let message_tuples = messages_to_tuples(&request.messages);
@@ -342,23 +731,40 @@ async fn handle_streaming_chat(
         .count_chat_messages(&model, &message_tuples)
         .unwrap_or(0) as u64;
 
-    // Ensure stream_options.include_usage is set to get token counts from OpenAI
-    request.stream_options = Some(StreamOptions { include_usage: true });
+    // Ask for the usage chunk to get token counts from OpenAI, unless the client already did
+    let (stream_options, usage_chunk) = StreamOptions::for_upstream(
+        request.stream_options.take(),
+        state.config.provider.stream_usage_injection,
+    );
+    request.stream_options = stream_options;
 
     // Convert request to Value for the provider
     let request_value = serde_json::to_value(&request)
         .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to serialize request: {}", e)))?;
+    let ctx = ctx.with_forwarded_bytes(json_len(&request_value));
 
-    // Forward streaming request to provider
-    let stream = state
-        .ai_provider
-        .chat_completions_stream(request_value, headers)
-        .await?;
+    // Forward streaming request to provider; fallback models are only tried until a stream opens
+    let provider = state.provider();
+    let open =
+        |request| timeout::stream_with_timeout(timeout, provider.chat_completions_stream(request, headers));
+    let ((stream, fallback_served), upstream) = capture::capture(async {
+        match fallback {
+            Some(ref fallback) => {
+                let (result, served) = fallback.run(&state.tier_router, &request_value, open).await;
+                (result, Some(served.clone()))
+            }
+            None => (open(request_value).await, None),
+        }
+    })
+    .await;
+    ctx.record_upstream_headers(upstream);
+    let stream = stream?;
+    let model = fallback_served.as_ref().map_or(model, |served| served.model.clone());
 
     // Clone values for the stream closure
     let model_clone = model.clone();
     let tracker = state.batching_tracker.clone();
-    let user_email = user.email.clone();
+    let user_email = user.log_email().to_string();
     let token_counter = state.token_counter.clone();
 
     // Track accumulated usage from stream (if OpenAI provides it)
@@ -369,31 +775,99 @@ async fn handle_streaming_chat(
     let content_accumulator = std::sync::Arc::new(std::sync::Mutex::new(String::new()));
     let content_for_stream = content_accumulator.clone();
 
+    // Track the last finish_reason seen for the audit log
+    let finish_reason_accumulator = std::sync::Arc::new(std::sync::Mutex::new(None::<String>));
+    let finish_reason_for_stream = finish_reason_accumulator.clone();
+
+    // Track the model snapshot reported by the chunks
+    let served_accumulator = std::sync::Arc::new(std::sync::Mutex::new(None::<String>));
+    let served_for_stream = served_accumulator.clone();
+
     // Buffer for accumulating incomplete SSE lines across chunk boundaries
-    let line_buffer = std::sync::Arc::new(std::sync::Mutex::new(SseLineBuffer::new()));
+    let line_buffer = std::sync::Arc::new(std::sync::Mutex::new(
+        SseLineBuffer::with_max_line_bytes(state.config.provider.sse_max_line_bytes),
+    ));
     let line_buffer_for_stream = line_buffer.clone();
 
+    // Set when the upstream sends an oversized line; the stream ends after the error event
+    let aborted = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let aborted_for_stream = aborted.clone();
+
     // Clone model for metrics in stream closure
     let model_for_parse_error = model.clone();
 
+    // Deltas are scanned across chunk boundaries; a match ends the stream
+    let mut scanner = state
+        .config
+        .provider
+        .response_blocklist
+        .as_ref()
+        .map(|blocklist| blocklist.scanner());
+
+    // Filtered chunks are re-encoded from the parsed lines; accounting still sees the originals
+    let mut stream_filter = filter.map(|filter| filter.stream());
+
     // Wrap the stream to extract content and usage from chunks
+    // Count forwarded bytes as they pass; chunks are not copied
+    let ctx_for_stream = ctx.clone();
+
     let tracked_stream = stream.map(move |chunk| {
         match chunk {
             Ok(bytes) => {
+                ctx_for_stream.add_response_bytes(bytes.len() as u64);
+
                 // Use line buffer to handle chunks split across network boundaries
-                let complete_lines = line_buffer_for_stream.lock().unwrap().feed(&bytes);
+                let complete_lines = match line_buffer_for_stream.lock().unwrap().feed(&bytes) {
+                    Ok(lines) => lines,
+                    Err(e) => {
+                        warn!(model = %model_clone, error = %e, "Aborting stream with oversized SSE line");
+                        aborted_for_stream.store(true, std::sync::atomic::Ordering::Relaxed);
+                        return Ok(e.to_event());
+                    }
+                };
 
-                for line in complete_lines {
+                // Lines of usage chunks the client didn't ask for
+                let mut consumed = Vec::new();
+                for (index, line) in complete_lines.iter().enumerate() {
                     if let Some(json_str) = line.strip_prefix("data: ") {
                         let json_str = json_str.trim();
                         if json_str != "[DONE]" {
                             match serde_json::from_str::<StreamChunk>(json_str) {
                                 Ok(chunk) => {
-                                    // Accumulate content from delta
+                                    if let Some(served) = chunk.model {
+                                        *served_for_stream.lock().unwrap() = Some(served);
+                                    }
+                                    // Accumulate content and tool call arguments from delta
                                     if let Some(choice) = chunk.choices.first() {
                                         if let Some(ref content) = choice.delta.content {
                                             content_for_stream.lock().unwrap().push_str(content);
+                                            if scanner.as_mut().is_some_and(|s| s.scan(content)) {
+                                                warn!(model = %model_clone, "Aborting stream matching the blocklist");
+                                                record_content_blocked("chat");
+                                                aborted_for_stream.store(true, std::sync::atomic::Ordering::Relaxed);
+                                                return Ok(content_filter::blocked_event());
+                                            }
+                                        }
+                                        if let Some(ref tool_calls) = choice.delta.tool_calls {
+                                            let mut acc = content_for_stream.lock().unwrap();
+                                            for function in tool_calls.iter().filter_map(|tc| tc.function.as_ref()) {
+                                                if let Some(ref name) = function.name {
+                                                    acc.push_str(name);
+                                                }
+                                                if let Some(ref arguments) = function.arguments {
+                                                    acc.push_str(arguments);
+                                                }
+                                            }
                                         }
+                                        if let Some(ref reason) = choice.finish_reason {
+                                            *finish_reason_for_stream.lock().unwrap() = Some(reason.clone());
+                                        }
+                                    }
+                                    if usage_chunk == UsageChunk::Sentinel
+                                        && chunk.choices.is_empty()
+                                        && chunk.usage.is_some()
+                                    {
+                                        consumed.push(index);
                                     }
                                     // Capture usage if provided (usually in final chunk)
                                     if let Some(usage) = chunk.usage {
@@ -417,7 +891,23 @@ async fn handle_streaming_chat(
                         }
                     }
                 }
-                Ok(bytes)
+                if usage_chunk == UsageChunk::Sentinel {
+                    // Re-framed from complete lines, so a consumed chunk split across reads can't leak
+                    let lines: Vec<String> = complete_lines
+                        .into_iter()
+                        .enumerate()
+                        .filter(|(index, _)| !consumed.contains(index))
+                        .map(|(_, line)| line)
+                        .collect();
+                    return Ok(match stream_filter {
+                        Some(ref mut filter) => filter.rewrite_lines(&lines),
+                        None => encode_lines(&lines),
+                    });
+                }
+                match stream_filter {
+                    Some(ref mut filter) => Ok(filter.rewrite_lines(&complete_lines)),
+                    None => Ok(bytes),
+                }
             }
             Err(e) => {
                 warn!(model = %model_clone, error = %e, "Stream error");
@@ -428,17 +918,34 @@ async fn handle_streaming_chat(
 
     // Create a stream that tracks usage after completion
     let user_email_final = user_email.clone();
+    let user_final = user.clone();
     let model_for_metrics = model.clone();
     let model_for_counting = model.clone();
     let usage_final = usage_accumulator.clone();
     let content_final = content_accumulator.clone();
+    let finish_reason_final = finish_reason_accumulator.clone();
+    let served_final = served_accumulator.clone();
+    let snapshots_final = state.model_snapshots.clone();
+    let finish_reasons_final = state.finish_reasons.clone();
     let tracker_final = tracker.clone();
+    let ctx_final = ctx.clone();
+    let max_request_bytes = state.config.server.payload_warn_request_bytes;
+    let max_response_bytes = state.config.server.payload_warn_response_bytes;
+
+    let aborted_final = aborted.clone();
 
     let final_stream = async_stream::stream! {
-        futures::pin_mut!(tracked_stream);
-        while let Some(item) = tracked_stream.next().await {
-            yield item;
+        {
+            futures::pin_mut!(tracked_stream);
+            while let Some(item) = tracked_stream.next().await {
+                yield item;
+                if aborted_final.load(std::sync::atomic::Ordering::Relaxed) {
+                    break;
+                }
+            }
+            // Dropping the tracked stream here closes the upstream connection
         }
+        ctx_final.record_payload_sizes(max_request_bytes, max_response_bytes);
 
         // Stream completed - determine token counts
         let openai_usage = usage_final.lock().unwrap().clone();
@@ -487,33 +994,127 @@ async fn handle_streaming_chat(
         record_tokens("prompt", input_tokens, &model_for_metrics);
         record_tokens("completion", output_tokens, &model_for_metrics);
 
+        let served_model = snapshot::attributed_model(
+            &model_for_metrics,
+            served_final.lock().unwrap().as_deref(),
+        );
+        snapshots_final.observe(&model_for_metrics, &served_model).await;
+
         // ALWAYS track usage in Zion (fire-and-forget)
-        tracker_final.track(user_email_final.clone(), input_tokens, output_tokens, Some(model_for_metrics.clone()));
+        tracker_final.track_user_in_workflow(
+            &user_final,
+            ctx_final.workflow_id.clone(),
+            input_tokens,
+            output_tokens,
+            Some(served_model),
+        );
+
+        let finish_reason = finish_reason_final.lock().unwrap().clone();
+        finish_reasons_final.observe("chat", &model_for_metrics, finish_reason.as_deref());
+        let finish_reason = finish_reason.unwrap_or_else(|| "unknown".to_string());
 
         info!(
             model = %model_for_metrics,
             input_tokens = input_tokens,
             output_tokens = output_tokens,
+            finish_reason = %finish_reason,
+            messages = ctx_final.complexity.messages,
+            content_chars = ctx_final.complexity.content_chars,
+            tools = ctx_final.complexity.tools,
             email = %user_email_final,
+            workflow_id = ?ctx_final.workflow_id,
+            upstream_headers = %ctx_final.upstream_headers(),
             "Streaming usage tracked"
         );
     };
 
     // Record that we started streaming
-    let duration = start_time.elapsed().as_secs_f64();
+    let duration = ctx.start_time.elapsed().as_secs_f64();
     record_request("streaming", &model, duration);
 
     // Build SSE response
     let body = Body::from_stream(final_stream);
 
-    let response = Response::builder()
-        .status(StatusCode::OK)
-        .header(header::CONTENT_TYPE, "text/event-stream")
-        .header(header::CACHE_CONTROL, "no-cache")
-        .header(header::CONNECTION, "keep-alive")
-        .header("X-Accel-Buffering", "no")
+    let mut response = sse_response(StatusCode::OK)
         .body(body)
         .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to build response: {}", e)))?;
+    if let Some(ref served) = fallback_served {
+        fallback::insert_model_header(response.headers_mut(), served);
+    }
+    ctx.upstream_headers().insert_request_id_header(response.headers_mut());
 
     Ok(response)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_tool_calls_text_concatenates_names_and_arguments() {
+        let tool_calls = json!([
+            {"id": "call_1", "type": "function", "function": {"name": "get_weather", "arguments": "{\"city\":\"Paris\"}"}},
+            {"id": "call_2", "type": "function", "function": {"name": "get_time", "arguments": "{}"}}
+        ]);
+
+        assert_eq!(
+            tool_calls_text(&tool_calls),
+            "get_weather{\"city\":\"Paris\"}get_time{}"
+        );
+    }
+
+    #[test]
+    fn test_tool_calls_text_ignores_non_arrays() {
+        assert_eq!(tool_calls_text(&json!(null)), "");
+        assert_eq!(tool_calls_text(&json!({"function": {"name": "x"}})), "");
+    }
+
+    #[test]
+    fn test_stream_chunk_parses_tool_call_delta() {
+        let json_str = r#"{"choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"id":"call_1","type":"function","function":{"name":"get_weather","arguments":"{\"ci"}}]},"finish_reason":null}]}"#;
+        let chunk: StreamChunk = serde_json::from_str(json_str).unwrap();
+
+        let tool_calls = chunk.choices[0].delta.tool_calls.as_ref().unwrap();
+        let function = tool_calls[0].function.as_ref().unwrap();
+        assert_eq!(function.name.as_deref(), Some("get_weather"));
+        assert_eq!(function.arguments.as_deref(), Some("{\"ci"));
+    }
+
+    #[test]
+    fn test_stream_chunk_parses_finish_reason() {
+        let json_str = r#"{"choices":[{"index":0,"delta":{},"finish_reason":"tool_calls"}]}"#;
+        let chunk: StreamChunk = serde_json::from_str(json_str).unwrap();
+        assert_eq!(chunk.choices[0].finish_reason.as_deref(), Some("tool_calls"));
+    }
+
+    #[test]
+    fn test_stream_options_for_upstream() {
+        let options = |value: serde_json::Value| -> Option<StreamOptions> {
+            serde_json::from_value(value).unwrap()
+        };
+
+        // Client asked: forwarded unchanged, usage chunk is theirs
+        let client = options(json!({"include_usage": true, "include_obfuscation": false}));
+        let (upstream, usage) = StreamOptions::for_upstream(client.clone(), true);
+        assert_eq!((upstream, usage), (client, UsageChunk::Client));
+
+        // Client didn't ask: injected, other options kept
+        let client = options(json!({"include_obfuscation": false, "future": 1}));
+        let (upstream, usage) = StreamOptions::for_upstream(client, true);
+        assert_eq!(usage, UsageChunk::Sentinel);
+        assert_eq!(
+            serde_json::to_value(upstream).unwrap(),
+            json!({"include_usage": true, "include_obfuscation": false, "future": 1})
+        );
+
+        // Injection off: forwarded as sent, or still absent
+        let client = options(json!({"include_usage": false}));
+        let (upstream, usage) = StreamOptions::for_upstream(client.clone(), false);
+        assert_eq!((upstream, usage), (client, UsageChunk::NotRequested));
+        assert_eq!(
+            StreamOptions::for_upstream(None, false),
+            (None, UsageChunk::NotRequested)
+        );
+    }
+}
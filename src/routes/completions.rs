@@ -4,14 +4,14 @@
 //! Most modern applications should use chat completions instead.
 
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::Duration;
 
 use axum::{
-    body::Body,
+    body::{Body, HttpBody},
     extract::State,
-    http::{header, HeaderMap, StatusCode},
+    http::{header, HeaderMap, StatusCode, Uri},
     response::{IntoResponse, Response},
-    Json,
+    Extension, Json,
 };
 use futures::StreamExt;
 use serde::{Deserialize, Serialize};
@@ -19,12 +19,23 @@ use tracing::{debug, info, warn};
 
 use crate::{
     error::AppError,
-    middleware::auth::AuthenticatedUser,
-    routes::metrics::{
-        record_fallback_estimation, record_request, record_sse_parse_error,
-        record_token_estimation_diff, record_tokens,
+    middleware::{auth::AuthenticatedUser, model_access},
+    native::{max_stop_sequences, validate_stop_value},
+    proxy::{
+        capture,
+        complexity::{RequestComplexity, NO_TIER},
+        logging::json_len,
+        snapshot, timeout, upstream_user, RequestContext,
     },
-    streaming::SseLineBuffer,
+    routes::{
+        body::{self, SentinelJson},
+        metrics::{
+            record_fallback_estimation, record_request, record_sse_parse_error,
+            record_token_estimation_diff, record_tokens,
+        },
+    },
+    streaming::{sse_response, SseLineBuffer},
+    usage::{ledger::hash_user, workflow_id_from_headers},
     AppState,
 };
 
@@ -127,30 +138,44 @@ fn extract_bearer_token(headers: &HeaderMap) -> Option<String> {
 pub async fn completions(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
-    request: axum::extract::Request,
+    uri: Uri,
+    Extension(mut user): Extension<AuthenticatedUser>,
+    SentinelJson(mut completion_request, body_len): SentinelJson<CompletionRequest>,
 ) -> Result<Response, AppError> {
-    let start_time = Instant::now();
-
-    // Extract authenticated user from request extensions (set by auth middleware)
-    let user = request
-        .extensions()
-        .get::<AuthenticatedUser>()
-        .cloned()
-        .ok_or_else(|| {
-            warn!("AuthenticatedUser not found in request extensions");
-            AppError::Unauthorized
-        })?;
-
-    // Parse the request body
-    let body = axum::body::to_bytes(request.into_body(), usize::MAX)
-        .await
-        .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to read request body: {}", e)))?;
-
-    let completion_request: CompletionRequest = serde_json::from_slice(&body)
-        .map_err(|e| AppError::BadRequest(format!("Invalid request body: {}", e)))?;
+    let ctx = RequestContext::new(state.provider().name(), "/v1/completions");
+
+    // A `stream` query parameter overrides the body's flag
+    let stream_override = body::stream_override(&uri)?;
+    if let Some(stream) = stream_override {
+        completion_request.stream = stream;
+    }
+
+    // The provider only sees the `user` value UPSTREAM_USER_FIELD allows
+    completion_request.user =
+        upstream_user::apply(&state.config.provider, &mut user, completion_request.user.as_deref());
+
+    let complexity = request_complexity(&completion_request);
+    complexity.record("/v1/completions", NO_TIER);
+
+    // Reject stop sequences the provider would refuse, with the same rules as the native API
+    if let Some(ref stop) = completion_request.stop {
+        validate_stop_value(stop, max_stop_sequences(state.provider().name()))
+            .map_err(AppError::BadRequest)?;
+    }
 
     let model = completion_request.model.clone();
+    model_access::check(&user, "/v1/completions", &[&model])?;
     let is_streaming = completion_request.stream;
+    let timeout = timeout::timeout_from_headers(&state.config, &headers)?;
+    let workflow_id = workflow_id_from_headers(&headers).map_err(AppError::BadRequest)?;
+    let ctx = ctx
+        .with_model(model.clone())
+        .with_streaming(is_streaming)
+        .with_external_id(user.log_id())
+        .with_user_hash(hash_user(&user.email))
+        .with_workflow_id(workflow_id)
+        .with_request_bytes(body_len as u64)
+        .with_complexity(complexity);
 
     // Extract authorization token (kept for potential future use)
     let _token = extract_bearer_token(&headers);
@@ -158,17 +183,35 @@ pub async fn completions(
     info!(
         model = %model,
         stream = %is_streaming,
-        external_id = %user.external_id,
+        external_id = %user.log_id(),
+        workflow_id = ?ctx.workflow_id,
         "Processing completion request"
     );
 
-    if is_streaming {
+    let result = if is_streaming {
         // Handle streaming response
-        handle_streaming_completion(state, &headers, completion_request, model, start_time, user).await
+        handle_streaming_completion(state, &headers, completion_request, model, ctx, user, timeout).await
     } else {
         // Handle non-streaming response
-        handle_non_streaming_completion(state, &headers, completion_request, model, start_time, user).await
+        handle_non_streaming_completion(state, &headers, completion_request, model, ctx, user, timeout).await
+    };
+
+    timeout::with_timeout_header(result, timeout)
+}
+
+/// Prompt count and size of a completion request (`prompt` is a string or an array of them)
+fn request_complexity(request: &CompletionRequest) -> RequestComplexity {
+    let mut complexity = RequestComplexity::new(request.stream);
+    match &request.prompt {
+        serde_json::Value::String(prompt) => complexity.add_message([prompt.as_str()]),
+        serde_json::Value::Array(prompts) => {
+            for prompt in prompts {
+                complexity.add_message(prompt.as_str());
+            }
+        }
+        _ => {}
     }
+    complexity
 }
 
 /// Handle non-streaming completion
@@ -177,8 +220,9 @@ async fn handle_non_streaming_completion(
     headers: &HeaderMap,
     request: CompletionRequest,
     model: String,
-    start_time: Instant,
+    ctx: RequestContext,
     user: AuthenticatedUser,
+    timeout: Option<Duration>,
 ) -> Result<Response, AppError> {
     // Pre-count input tokens using tiktoken (for fallback if OpenAI doesn't return usage)
     let prompt_text = extract_prompt_text(&request.prompt);
@@ -190,18 +234,26 @@ async fn handle_non_streaming_completion(
     // Convert request to Value for the provider
     let request_value = serde_json::to_value(&request)
         .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to serialize request: {}", e)))?;
+    let ctx = ctx.with_forwarded_bytes(json_len(&request_value));
 
-    let response_value = state
-        .ai_provider
-        .completions(request_value, headers)
-        .await?;
+    let (response_value, upstream) = capture::capture(timeout::call_with_timeout(
+        timeout,
+        state.provider().completions(request_value, headers),
+    ))
+    .await;
+    ctx.record_upstream_headers(upstream);
+    let response_value = response_value?;
 
     // Parse the response
     let response: CompletionResponse = serde_json::from_value(response_value.clone())
         .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to parse response: {}", e)))?;
 
+    // Usage is attributed to the snapshot the provider actually served
+    let served_model = snapshot::attributed_model(&model, Some(&response.model));
+    state.model_snapshots.observe(&model, &served_model).await;
+
     // Record metrics
-    let duration = start_time.elapsed().as_secs_f64();
+    let duration = ctx.start_time.elapsed().as_secs_f64();
     record_request("success", &model, duration);
 
     // Get token counts: prefer OpenAI usage, fallback to estimation
@@ -248,23 +300,50 @@ async fn handle_non_streaming_completion(
     record_tokens("completion", output_tokens, &model);
 
     // Track usage in Zion (fire-and-forget, never blocks)
-    state.batching_tracker.track(user.email.clone(), input_tokens, output_tokens, Some(model.clone()));
+    state.batching_tracker.track_user_in_workflow(
+        &user,
+        ctx.workflow_id.clone(),
+        input_tokens,
+        output_tokens,
+        Some(served_model.clone()),
+    );
+
+    let finish_reason = response
+        .choices
+        .first()
+        .and_then(|c| c.finish_reason.as_deref());
+    state.finish_reasons.observe("completions", &model, finish_reason);
 
     info!(
         model = %model,
         duration_ms = %format!("{:.2}", duration * 1000.0),
         input_tokens = input_tokens,
         output_tokens = output_tokens,
-        external_id = %user.external_id,
+        finish_reason = %finish_reason.unwrap_or("unknown"),
+        messages = ctx.complexity.messages,
+        content_chars = ctx.complexity.content_chars,
+        external_id = %user.log_id(),
+        workflow_id = ?ctx.workflow_id,
+        upstream_headers = %ctx.upstream_headers(),
         "Completion request completed"
     );
 
-    Ok((StatusCode::OK, Json(response)).into_response())
+    let mut response = (StatusCode::OK, Json(response)).into_response();
+    ctx.add_response_bytes(response.body().size_hint().exact().unwrap_or(0));
+    ctx.record_payload_sizes(
+        state.config.server.payload_warn_request_bytes,
+        state.config.server.payload_warn_response_bytes,
+    );
+    snapshot::insert_upstream_model_header(response.headers_mut(), &served_model);
+    ctx.upstream_headers().insert_request_id_header(response.headers_mut());
+    Ok(response)
 }
 
 /// Streaming chunk for parsing content and usage
 #[derive(Debug, Clone, Deserialize)]
 struct StreamChunk {
+    #[serde(default)]
+    model: Option<String>,
     #[serde(default)]
     choices: Vec<StreamChoice>,
     #[serde(default)]
@@ -276,6 +355,8 @@ struct StreamChunk {
 struct StreamChoice {
     #[serde(default)]
     text: Option<String>,
+    #[serde(default)]
+    finish_reason: Option<String>,
 }
 
 /// Handle streaming completion
@@ -284,8 +365,9 @@ async fn handle_streaming_completion(
     headers: &HeaderMap,
     request: CompletionRequest,
     model: String,
-    start_time: Instant,
+    ctx: RequestContext,
     user: AuthenticatedUser,
+    timeout: Option<Duration>,
 ) -> Result<Response, AppError> {
     // Pre-count input tokens using tiktoken
     let prompt_text = extract_prompt_text(&request.prompt);
@@ -297,17 +379,21 @@ async fn handle_streaming_completion(
     // Convert request to Value for the provider
     let request_value = serde_json::to_value(&request)
         .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to serialize request: {}", e)))?;
+    let ctx = ctx.with_forwarded_bytes(json_len(&request_value));
 
     // Forward streaming request to provider
-    let stream = state
-        .ai_provider
-        .completions_stream(request_value, headers)
-        .await?;
+    let (stream, upstream) = capture::capture(timeout::stream_with_timeout(
+        timeout,
+        state.provider().completions_stream(request_value, headers),
+    ))
+    .await;
+    ctx.record_upstream_headers(upstream);
+    let stream = stream?;
 
     // Clone values for the stream closure
     let model_clone = model.clone();
     let tracker = state.batching_tracker.clone();
-    let user_email = user.email.clone();
+    let user_email = user.log_email().to_string();
     let token_counter = state.token_counter.clone();
 
     // Track accumulated usage from stream (if OpenAI provides it)
@@ -318,19 +404,45 @@ async fn handle_streaming_completion(
     let content_accumulator = std::sync::Arc::new(std::sync::Mutex::new(String::new()));
     let content_for_stream = content_accumulator.clone();
 
+    // Track the last finish_reason seen for the audit log
+    let finish_reason_accumulator = std::sync::Arc::new(std::sync::Mutex::new(None::<String>));
+    let finish_reason_for_stream = finish_reason_accumulator.clone();
+
+    // Track the model snapshot reported by the chunks
+    let served_accumulator = std::sync::Arc::new(std::sync::Mutex::new(None::<String>));
+    let served_for_stream = served_accumulator.clone();
+
     // Buffer for accumulating incomplete SSE lines across chunk boundaries
-    let line_buffer = std::sync::Arc::new(std::sync::Mutex::new(SseLineBuffer::new()));
+    let line_buffer = std::sync::Arc::new(std::sync::Mutex::new(
+        SseLineBuffer::with_max_line_bytes(state.config.provider.sse_max_line_bytes),
+    ));
     let line_buffer_for_stream = line_buffer.clone();
 
+    // Set when the upstream sends an oversized line; the stream ends after the error event
+    let aborted = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let aborted_for_stream = aborted.clone();
+
     // Clone model for metrics in stream closure
     let model_for_parse_error = model.clone();
 
     // Wrap the stream to extract content and usage from chunks
+    // Count forwarded bytes as they pass; chunks are not copied
+    let ctx_for_stream = ctx.clone();
+
     let tracked_stream = stream.map(move |chunk| {
         match chunk {
             Ok(bytes) => {
+                ctx_for_stream.add_response_bytes(bytes.len() as u64);
+
                 // Use line buffer to handle chunks split across network boundaries
-                let complete_lines = line_buffer_for_stream.lock().unwrap().feed(&bytes);
+                let complete_lines = match line_buffer_for_stream.lock().unwrap().feed(&bytes) {
+                    Ok(lines) => lines,
+                    Err(e) => {
+                        warn!(model = %model_clone, error = %e, "Aborting stream with oversized SSE line");
+                        aborted_for_stream.store(true, std::sync::atomic::Ordering::Relaxed);
+                        return Ok(e.to_event());
+                    }
+                };
 
                 for line in complete_lines {
                     if let Some(json_str) = line.strip_prefix("data: ") {
@@ -338,11 +450,17 @@ async fn handle_streaming_completion(
                         if json_str != "[DONE]" {
                             match serde_json::from_str::<StreamChunk>(json_str) {
                                 Ok(chunk) => {
+                                    if let Some(served) = chunk.model {
+                                        *served_for_stream.lock().unwrap() = Some(served);
+                                    }
                                     // Accumulate text from choices
                                     if let Some(choice) = chunk.choices.first() {
                                         if let Some(ref text) = choice.text {
                                             content_for_stream.lock().unwrap().push_str(text);
                                         }
+                                        if let Some(ref reason) = choice.finish_reason {
+                                            *finish_reason_for_stream.lock().unwrap() = Some(reason.clone());
+                                        }
                                     }
                                     // Capture usage if provided
                                     if let Some(usage) = chunk.usage {
@@ -377,17 +495,34 @@ async fn handle_streaming_completion(
 
     // Create a stream that tracks usage after completion
     let user_email_final = user_email.clone();
+    let user_final = user.clone();
     let model_for_metrics = model.clone();
     let model_for_counting = model.clone();
     let usage_final = usage_accumulator.clone();
     let content_final = content_accumulator.clone();
+    let finish_reason_final = finish_reason_accumulator.clone();
+    let served_final = served_accumulator.clone();
+    let snapshots_final = state.model_snapshots.clone();
+    let finish_reasons_final = state.finish_reasons.clone();
     let tracker_final = tracker.clone();
+    let ctx_final = ctx.clone();
+    let max_request_bytes = state.config.server.payload_warn_request_bytes;
+    let max_response_bytes = state.config.server.payload_warn_response_bytes;
+
+    let aborted_final = aborted.clone();
 
     let final_stream = async_stream::stream! {
-        futures::pin_mut!(tracked_stream);
-        while let Some(item) = tracked_stream.next().await {
-            yield item;
+        {
+            futures::pin_mut!(tracked_stream);
+            while let Some(item) = tracked_stream.next().await {
+                yield item;
+                if aborted_final.load(std::sync::atomic::Ordering::Relaxed) {
+                    break;
+                }
+            }
+            // Dropping the tracked stream here closes the upstream connection
         }
+        ctx_final.record_payload_sizes(max_request_bytes, max_response_bytes);
 
         // Stream completed - determine token counts
         let openai_usage = usage_final.lock().unwrap().clone();
@@ -434,33 +569,49 @@ async fn handle_streaming_completion(
         record_tokens("prompt", input_tokens, &model_for_metrics);
         record_tokens("completion", output_tokens, &model_for_metrics);
 
+        let served_model = snapshot::attributed_model(
+            &model_for_metrics,
+            served_final.lock().unwrap().as_deref(),
+        );
+        snapshots_final.observe(&model_for_metrics, &served_model).await;
+
         // ALWAYS track usage in Zion (fire-and-forget)
-        tracker_final.track(user_email_final.clone(), input_tokens, output_tokens, Some(model_for_metrics.clone()));
+        tracker_final.track_user_in_workflow(
+            &user_final,
+            ctx_final.workflow_id.clone(),
+            input_tokens,
+            output_tokens,
+            Some(served_model),
+        );
+
+        let finish_reason = finish_reason_final.lock().unwrap().clone();
+        finish_reasons_final.observe("completions", &model_for_metrics, finish_reason.as_deref());
 
         info!(
             model = %model_for_metrics,
             input_tokens = input_tokens,
             output_tokens = output_tokens,
+            finish_reason = %finish_reason.as_deref().unwrap_or("unknown"),
+            messages = ctx_final.complexity.messages,
+            content_chars = ctx_final.complexity.content_chars,
             email = %user_email_final,
+            workflow_id = ?ctx_final.workflow_id,
+            upstream_headers = %ctx_final.upstream_headers(),
             "Streaming completion usage tracked"
         );
     };
 
     // Record that we started streaming
-    let duration = start_time.elapsed().as_secs_f64();
+    let duration = ctx.start_time.elapsed().as_secs_f64();
     record_request("streaming", &model, duration);
 
     // Build SSE response
     let body = Body::from_stream(final_stream);
 
-    let response = Response::builder()
-        .status(StatusCode::OK)
-        .header(header::CONTENT_TYPE, "text/event-stream")
-        .header(header::CACHE_CONTROL, "no-cache")
-        .header(header::CONNECTION, "keep-alive")
-        .header("X-Accel-Buffering", "no")
+    let mut response = sse_response(StatusCode::OK)
         .body(body)
         .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to build response: {}", e)))?;
+    ctx.upstream_headers().insert_request_id_header(response.headers_mut());
 
     Ok(response)
 }
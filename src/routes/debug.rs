@@ -86,7 +86,7 @@ async fn scan_keys(conn: &mut redis::aio::ConnectionManager, pattern: &str) -> V
 pub async fn cache_overview(
     State(state): State<Arc<AppState>>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    if !state.config.debug_enabled {
+    if !state.config.server.debug_enabled {
         return Err(debug_disabled_error());
     }
 
@@ -129,7 +129,7 @@ pub async fn user_auth_state(
     State(state): State<Arc<AppState>>,
     Path(external_id): Path<String>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    if !state.config.debug_enabled {
+    if !state.config.server.debug_enabled {
         return Err(debug_disabled_error());
     }
 
@@ -165,7 +165,7 @@ pub async fn user_auth_state(
 pub async fn config_info(
     State(state): State<Arc<AppState>>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    if !state.config.debug_enabled {
+    if !state.config.server.debug_enabled {
         return Err(debug_disabled_error());
     }
 
@@ -179,12 +179,12 @@ pub async fn config_info(
     };
 
     let response = ConfigInfo {
-        zion_api_url: state.config.zion_api_url.clone(),
-        openai_api_url: state.config.openai_api_url.clone(),
-        cache_ttl_seconds: state.config.cache_ttl_seconds,
-        jwt_cache_ttl_seconds: state.config.jwt_cache_ttl_seconds,
+        zion_api_url: state.config.zion.api_url.clone(),
+        openai_api_url: state.config.provider.openai_api_url.clone(),
+        cache_ttl_seconds: state.config.zion.cache_ttl_seconds,
+        jwt_cache_ttl_seconds: state.config.zion.jwt_cache_ttl_seconds,
         redis_connected,
-        debug_enabled: state.config.debug_enabled,
+        debug_enabled: state.config.server.debug_enabled,
     };
 
     Ok(Json(response))
@@ -198,7 +198,8 @@ fn debug_disabled_error() -> (StatusCode, Json<serde_json::Value>) {
             "error": {
                 "message": "Debug endpoints are disabled. Set SENTINEL_DEBUG=true to enable.",
                 "type": "not_found_error",
-                "code": "debug_disabled"
+                "code": "debug_disabled",
+                "retryable": false
             }
         })),
     )
@@ -17,8 +17,12 @@ use tracing::{debug, info};
 
 use crate::{
     error::AppError,
-    middleware::auth::AuthenticatedUser,
-    routes::metrics::{record_request, record_tokens},
+    middleware::{auth::AuthenticatedUser, model_access},
+    proxy::upstream_user,
+    routes::{
+        body::SentinelJson,
+        metrics::{record_request, record_tokens},
+    },
     AppState,
 };
 
@@ -99,15 +103,20 @@ pub struct EmbeddingResponse {
 pub async fn embeddings(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
-    Extension(user): Extension<AuthenticatedUser>,
-    Json(request): Json<EmbeddingRequest>,
+    Extension(mut user): Extension<AuthenticatedUser>,
+    SentinelJson(mut request, _): SentinelJson<EmbeddingRequest>,
 ) -> Result<Response, AppError> {
     let start_time = Instant::now();
     let model = request.model.clone();
+    model_access::check(&user, "/v1/embeddings", &[&model])?;
+
+    // The provider only sees the `user` value UPSTREAM_USER_FIELD allows
+    request.user =
+        upstream_user::apply(&state.config.provider, &mut user, request.user.as_deref());
 
     debug!(
         model = %model,
-        external_id = %user.external_id,
+        external_id = %user.log_id(),
         "Processing embeddings request"
     );
 
@@ -117,7 +126,7 @@ pub async fn embeddings(
 
     // Forward request to provider
     let response_value = state
-        .ai_provider
+        .provider()
         .embeddings(request_value, &headers)
         .await?;
 
@@ -132,8 +141,8 @@ pub async fn embeddings(
 
     // Track usage in Zion (fire-and-forget)
     // Embeddings only have input tokens, no output tokens
-    state.batching_tracker.track(
-        user.email.clone(),
+    state.batching_tracker.track_user(
+        &user,
         response.usage.prompt_tokens as u64,
         0, // No output tokens for embeddings
         Some(model.clone()),
@@ -143,7 +152,7 @@ pub async fn embeddings(
         model = %model,
         prompt_tokens = response.usage.prompt_tokens,
         duration_ms = %format!("{:.2}", duration * 1000.0),
-        external_id = %user.external_id,
+        external_id = %user.log_id(),
         "Embeddings request completed"
     );
 
@@ -1,7 +1,7 @@
 //! Health check endpoints
 //!
 //! Provides endpoints for monitoring and container orchestration:
-//! - `/health` - Full health check with dependency status
+//! - `/health` - Full health check with dependency status and build metadata
 //! - `/health/ready` - Readiness probe
 //! - `/health/live` - Liveness probe
 
@@ -12,6 +12,8 @@ use axum::{extract::State, http::StatusCode, Json};
 use redis::AsyncCommands;
 use serde::Serialize;
 
+use crate::build_info::BuildInfo;
+use crate::tiers::{StandbyStatus, TrippedEndpoint};
 use crate::AppState;
 
 /// Health status enum
@@ -21,6 +23,8 @@ pub enum HealthStatus {
     Healthy,
     Degraded,
     Unhealthy,
+    /// Ready, but model endpoints are returning 503 for planned maintenance
+    Maintenance,
 }
 
 /// Individual dependency check result
@@ -53,16 +57,30 @@ pub struct HealthResponse {
     pub timestamp: String,
     pub checks: DependencyChecks,
     pub stats: HealthStats,
+    /// Whether maintenance mode is on
+    pub maintenance: bool,
+    /// Version, git SHA, build time and features of the running binary
+    pub build: BuildInfo,
+    /// Name of the default AI provider
+    pub provider: &'static str,
+    /// Version of the cached Zion tier config (None until first fetched)
+    pub tier_config_version: Option<String>,
 }
 
 /// Simple health response for liveness/readiness
 #[derive(Debug, Serialize)]
 pub struct SimpleHealthResponse {
     pub status: HealthStatus,
+    /// Provider endpoints whose circuit breaker is open or half-open
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub upstream_circuits: Vec<TrippedEndpoint>,
+    /// Standby tier config served while Zion is unreachable
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tier_config_standby: Option<StandbyStatus>,
 }
 
 /// Check Redis connectivity
-async fn check_redis(state: &AppState) -> DependencyCheck {
+pub(crate) async fn check_redis(state: &AppState) -> DependencyCheck {
     let start = Instant::now();
 
     // In test mode, Redis may not be configured
@@ -101,6 +119,7 @@ async fn check_redis(state: &AppState) -> DependencyCheck {
 /// - Uptime
 /// - Dependency checks (Redis)
 /// - Application stats
+/// - Build metadata, the default provider and the cached tier config version
 pub async fn health_check(
     State(state): State<Arc<AppState>>,
 ) -> (StatusCode, Json<HealthResponse>) {
@@ -116,21 +135,26 @@ pub async fn health_check(
     };
 
     let uptime = state.start_time.elapsed().as_secs();
+    let build = BuildInfo::current();
 
     let response = HealthResponse {
         status: overall_status.clone(),
-        version: env!("CARGO_PKG_VERSION").to_string(),
+        version: build.version.to_string(),
         uptime_seconds: uptime,
         timestamp: chrono::Utc::now().to_rfc3339(),
         checks: DependencyChecks { redis: redis_check },
         stats: HealthStats {
             uptime_seconds: uptime,
         },
+        maintenance: state.maintenance.status().await.enabled,
+        build,
+        provider: state.ai_provider.name(),
+        tier_config_version: state.tier_config_cache.cached_version().await,
     };
 
     let status_code = match overall_status {
         HealthStatus::Healthy => StatusCode::OK,
-        HealthStatus::Degraded => StatusCode::OK,
+        HealthStatus::Degraded | HealthStatus::Maintenance => StatusCode::OK,
         HealthStatus::Unhealthy => StatusCode::SERVICE_UNAVAILABLE,
     };
 
@@ -140,25 +164,45 @@ pub async fn health_check(
 /// Readiness probe endpoint
 ///
 /// Returns 200 OK if the application is ready to receive traffic.
-/// Used by Kubernetes readiness probes.
+/// Used by Kubernetes readiness probes. During maintenance the status is
+/// `maintenance` but the code stays 200, so the instance keeps serving the
+/// maintenance response instead of dropping out of rotation. Open upstream
+/// circuits are listed under `upstream_circuits` and make the status
+/// `degraded`, also with a 200: every instance shares the same providers, so
+/// taking this one out of rotation would not help. The same goes for a
+/// standby tier config served while Zion is down (`tier_config_standby`).
 pub async fn readiness_check(
     State(state): State<Arc<AppState>>,
 ) -> (StatusCode, Json<SimpleHealthResponse>) {
     let redis_check = check_redis(&state).await;
+    let upstream_circuits = state.health_tracker.tripped_endpoints();
+    let tier_config_standby = state.tier_config_cache.standby_status();
 
     if redis_check.status == HealthStatus::Unhealthy {
         return (
             StatusCode::SERVICE_UNAVAILABLE,
             Json(SimpleHealthResponse {
                 status: HealthStatus::Unhealthy,
+                upstream_circuits,
+                tier_config_standby,
             }),
         );
     }
 
+    let status = if state.maintenance.status().await.enabled {
+        HealthStatus::Maintenance
+    } else if !upstream_circuits.is_empty() || tier_config_standby.is_some() {
+        HealthStatus::Degraded
+    } else {
+        HealthStatus::Healthy
+    };
+
     (
         StatusCode::OK,
         Json(SimpleHealthResponse {
-            status: HealthStatus::Healthy,
+            status,
+            upstream_circuits,
+            tier_config_standby,
         }),
     )
 }
@@ -172,6 +216,8 @@ pub async fn liveness_check() -> (StatusCode, Json<SimpleHealthResponse>) {
         StatusCode::OK,
         Json(SimpleHealthResponse {
             status: HealthStatus::Healthy,
+            upstream_circuits: Vec::new(),
+            tier_config_standby: None,
         }),
     )
 }
@@ -194,5 +240,9 @@ mod tests {
             serde_json::to_string(&HealthStatus::Unhealthy).unwrap(),
             "\"unhealthy\""
         );
+        assert_eq!(
+            serde_json::to_string(&HealthStatus::Maintenance).unwrap(),
+            "\"maintenance\""
+        );
     }
 }
@@ -1,22 +1,100 @@
 //! Prometheus metrics endpoint
 //!
 //! Exposes application metrics in Prometheus format for monitoring.
+//! `METRICS_LABELS` adds constant labels to every series so several
+//! deployments can share one Prometheus, and `METRICS_TOKEN` requires a
+//! bearer token to scrape.
 
-use axum::response::IntoResponse;
-use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
-use once_cell::sync::Lazy;
+use std::sync::Arc;
 
-/// Global Prometheus handle for metrics export
-static PROMETHEUS_HANDLE: Lazy<PrometheusHandle> = Lazy::new(|| {
-    PrometheusBuilder::new()
-        .install_recorder()
-        .expect("Failed to install Prometheus recorder")
-});
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use once_cell::sync::OnceCell;
+
+use crate::proxy::complexity::RequestComplexity;
+use crate::AppState;
+
+/// Bucket bounds for payload size histograms (1 KiB .. 16 MiB)
+const PAYLOAD_BYTE_BUCKETS: &[f64] = &[
+    1024.0, 4096.0, 16384.0, 65536.0, 262144.0, 1048576.0, 4194304.0, 16777216.0,
+];
+
+/// Bucket bounds for the messages-per-request histogram
+const MESSAGE_COUNT_BUCKETS: &[f64] = &[1.0, 2.0, 4.0, 8.0, 16.0, 32.0, 64.0, 128.0, 256.0];
+
+/// Bucket bounds for the content characters histogram (100 .. 1M)
+const CONTENT_CHAR_BUCKETS: &[f64] = &[
+    100.0, 1000.0, 4000.0, 16000.0, 64000.0, 256000.0, 1000000.0,
+];
+
+/// Bucket bounds for the auth critical path histogram (in seconds, 5ms .. 5s)
+const AUTH_PATH_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+];
+
+/// Global Prometheus handle for metrics export, installed by the first initialization
+static PROMETHEUS_HANDLE: OnceCell<PrometheusHandle> = OnceCell::new();
+
+/// Exporter with Sentinel's histogram buckets and constant labels
+fn prometheus_builder(labels: &[(String, String)]) -> PrometheusBuilder {
+    let builder = PrometheusBuilder::new()
+        .set_buckets_for_metric(
+            Matcher::Full("sentinel_request_bytes".to_string()),
+            PAYLOAD_BYTE_BUCKETS,
+        )
+        .and_then(|builder| {
+            builder.set_buckets_for_metric(
+                Matcher::Full("sentinel_response_bytes".to_string()),
+                PAYLOAD_BYTE_BUCKETS,
+            )
+        })
+        .and_then(|builder| {
+            builder.set_buckets_for_metric(
+                Matcher::Full("sentinel_request_messages".to_string()),
+                MESSAGE_COUNT_BUCKETS,
+            )
+        })
+        .and_then(|builder| {
+            builder.set_buckets_for_metric(
+                Matcher::Full("sentinel_request_content_chars".to_string()),
+                CONTENT_CHAR_BUCKETS,
+            )
+        })
+        .and_then(|builder| {
+            builder.set_buckets_for_metric(
+                Matcher::Full("sentinel_auth_path_duration_seconds".to_string()),
+                AUTH_PATH_BUCKETS,
+            )
+        })
+        .expect("Invalid histogram buckets");
+    labels.iter().fold(builder, |builder, (name, value)| {
+        builder.add_global_label(name, value)
+    })
+}
+
+/// The installed handle, installing a recorder without labels if needed
+fn handle(labels: &[(String, String)]) -> &'static PrometheusHandle {
+    PROMETHEUS_HANDLE.get_or_init(|| {
+        prometheus_builder(labels)
+            .install_recorder()
+            .expect("Failed to install Prometheus recorder")
+    })
+}
 
 /// Initialize metrics (call once at startup)
 pub fn init_metrics() {
-    // Force initialization of the lazy static
-    let _ = &*PROMETHEUS_HANDLE;
+    init_metrics_with_labels(&[]);
+}
+
+/// Initialize metrics with constant labels on every series (`METRICS_LABELS`)
+///
+/// The recorder is process-wide, so only the first initialization sets the labels.
+pub fn init_metrics_with_labels(labels: &[(String, String)]) {
+    handle(labels);
 
     // Register custom metrics
     register_metrics();
@@ -41,6 +119,10 @@ fn register_metrics() {
         "sentinel_request_duration_seconds",
         "Request duration in seconds"
     );
+    metrics::describe_histogram!(
+        "sentinel_auth_path_duration_seconds",
+        "Time from authentication to the handler (token validation, limits, rate limits) by lookup"
+    );
     metrics::describe_gauge!(
         "sentinel_active_connections",
         "Number of active connections"
@@ -61,6 +143,30 @@ fn register_metrics() {
         "sentinel_token_estimation_fallback_total",
         "Times token counting fell back to estimation (OpenAI didn't return usage)"
     );
+    metrics::describe_histogram!(
+        "sentinel_request_bytes",
+        "Request body size in bytes by endpoint and stage (client or forwarded)"
+    );
+    metrics::describe_histogram!(
+        "sentinel_response_bytes",
+        "Response body size in bytes by endpoint (streamed bytes for streams)"
+    );
+    metrics::describe_counter!(
+        "sentinel_payload_warnings_total",
+        "Requests whose payload exceeded the configured size thresholds"
+    );
+    metrics::describe_histogram!(
+        "sentinel_request_messages",
+        "Messages per chat request by endpoint and tier"
+    );
+    metrics::describe_histogram!(
+        "sentinel_request_content_chars",
+        "Characters of message content per chat request by endpoint and tier"
+    );
+    metrics::describe_counter!(
+        "sentinel_request_features_total",
+        "Chat requests with images, tools or streaming, by endpoint, tier and feature"
+    );
 
     // Tier routing metrics
     metrics::describe_counter!(
@@ -79,17 +185,143 @@ fn register_metrics() {
         "sentinel_model_retries_total",
         "Model retry attempts after initial failure"
     );
+    metrics::describe_counter!(
+        "sentinel_model_snapshot",
+        "Responses by requested model and the upstream snapshot that served them"
+    );
+    metrics::describe_counter!(
+        "sentinel_rate_limit_exempt_requests_total",
+        "Requests that bypassed rate limiting via an exemption"
+    );
+    metrics::describe_counter!(
+        "sentinel_finish_reasons_total",
+        "Completed responses by endpoint, model and finish_reason"
+    );
+    metrics::describe_counter!(
+        "sentinel_mirror_requests_total",
+        "Requests mirrored to staging by result (match, mismatch, error, dropped)"
+    );
+    metrics::describe_counter!(
+        "sentinel_content_blocked_total",
+        "Responses blocked by the content filter"
+    );
+    metrics::describe_counter!(
+        "sentinel_content_sanitized_total",
+        "NUL bytes, lone surrogates and NFC rewrites removed from request content"
+    );
+    metrics::describe_counter!(
+        "sentinel_upstream_invalid_responses_total",
+        "Upstream 200 responses rejected as structurally invalid"
+    );
+    metrics::describe_counter!(
+        "sentinel_session_affinity_total",
+        "Affinity hints by result (hit = local session copy used, miss, invalid)"
+    );
+    metrics::describe_gauge!(
+        "sentinel_usage_retry_leader",
+        "Whether this replica holds the usage retry lease (1) or not (0), by replica"
+    );
     metrics::describe_gauge!(
         "sentinel_provider_health",
         "Provider health status (1=healthy, 0=in backoff)"
     );
+    metrics::describe_gauge!(
+        "sentinel_tier_health_entries",
+        "Provider/model entries held by the tier health tracker, by kind (health, latency, breaker)"
+    );
+    metrics::describe_gauge!(
+        "sentinel_tier_health_bytes",
+        "Approximate memory held by the tier health tracker"
+    );
+    metrics::describe_counter!(
+        "sentinel_tier_health_pruned_total",
+        "Health entries pruned for models absent from the tier config"
+    );
+    metrics::describe_gauge!(
+        "sentinel_tier_config_models",
+        "Provider/models in the cached tier config"
+    );
+    metrics::describe_gauge!(
+        "sentinel_tier_config_bytes",
+        "Serialized size of the cached tier config"
+    );
+    metrics::describe_gauge!(
+        "sentinel_upstream_circuit_state",
+        "Upstream circuit breaker state by provider and endpoint (0=closed, 1=half-open, 2=open)"
+    );
+    metrics::describe_counter!(
+        "sentinel_upstream_circuit_rejected_total",
+        "Requests failed fast with upstream_unavailable by provider and endpoint"
+    );
+    metrics::describe_counter!(
+        "sentinel_secrets_redacted_total",
+        "Secrets redacted from upstream error bodies by kind (configured, pattern)"
+    );
+    metrics::describe_counter!(
+        "sentinel_failover_served_total",
+        "Requests answered through provider failover by the provider that served them"
+    );
+    metrics::describe_counter!(
+        "sentinel_synthetic_requests_total",
+        "Requests carrying X-Sentinel-Synthetic by result (excluded from usage, ignored for non-allow-listed users)"
+    );
+    metrics::describe_counter!(
+        "sentinel_estimated_usage_total",
+        "Responses of REQUIRE_EXACT_USAGE accounts tracked with estimated token counts, by endpoint"
+    );
+    metrics::describe_counter!(
+        "sentinel_quiet_requests_total",
+        "Requests to QUIET_LOG_PATHS, served without request logging"
+    );
+    metrics::describe_gauge!(
+        "sentinel_upstream_pool_in_flight",
+        "Upstream requests in flight by client pool (streaming, short)"
+    );
+    metrics::describe_gauge!(
+        "sentinel_upstream_pool_max_idle",
+        "Idle connections kept per host by client pool (streaming, short)"
+    );
+    metrics::describe_counter!(
+        "sentinel_cache_schema_mismatches_total",
+        "Shared Redis entries written by another schema version, by schema and outcome (migrated, newer, invalid)"
+    );
+    metrics::describe_counter!(
+        "sentinel_deprecated_params_total",
+        "/v1 chat requests using deprecated OpenAI parameters, by param and DEPRECATED_PARAMS mode"
+    );
+    metrics::describe_counter!(
+        "sentinel_model_not_allowed_total",
+        "Requests refused because the model is outside the user's allowedModels, by endpoint"
+    );
+    metrics::describe_counter!(
+        "sentinel_stream_accept_mismatches_total",
+        "Chat requests whose Accept header doesn't fit their stream flag, by endpoint, kind and STREAM_ACCEPT_MISMATCH mode"
+    );
+    metrics::describe_counter!(
+        "sentinel_token_encoding_fallbacks_total",
+        "Models without a tiktoken encoding, counted by TOKEN_FALLBACK_ENCODING when first seen"
+    );
+    metrics::describe_counter!(
+        "sentinel_chaos_faults_injected_total",
+        "Faults injected by chaos rules (CHAOS_ENABLED), by target and fault"
+    );
 }
 
 /// Prometheus metrics endpoint handler
 ///
-/// Returns metrics in Prometheus text format for scraping.
-pub async fn prometheus_metrics() -> impl IntoResponse {
-    PROMETHEUS_HANDLE.render()
+/// Returns metrics in Prometheus text format for scraping. With
+/// `METRICS_TOKEN` set, requests without `Authorization: Bearer <token>` get a 401.
+pub async fn prometheus_metrics(State(state): State<Arc<AppState>>, headers: HeaderMap) -> Response {
+    if let Some(expected) = state.config.server.metrics_token.as_deref() {
+        let provided = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "));
+        if provided != Some(expected) {
+            return (StatusCode::UNAUTHORIZED, [(header::WWW_AUTHENTICATE, "Bearer")]).into_response();
+        }
+    }
+    handle(&[]).render().into_response()
 }
 
 /// Record a request
@@ -100,6 +332,15 @@ pub fn record_request(status: &str, model: &str, duration_secs: f64) {
         .record(duration_secs);
 }
 
+/// Record the pre-upstream critical path of a request
+///
+/// `lookup` is how the profile and limits were found (`cached`, `joined` or
+/// `serial`), or `timeout` for requests cut off by `AUTH_PATH_BUDGET_MS`.
+pub fn record_auth_path(lookup: &str, duration_secs: f64) {
+    metrics::histogram!("sentinel_auth_path_duration_seconds", "lookup" => lookup.to_string())
+        .record(duration_secs);
+}
+
 /// Record tokens processed
 pub fn record_tokens(token_type: &str, count: u64, model: &str) {
     metrics::counter!(
@@ -175,6 +416,173 @@ pub fn record_fallback_estimation(model: &str) {
     .increment(1);
 }
 
+/// Record a request body size (`stage` is `client` or `forwarded`)
+pub fn record_request_bytes(endpoint: &str, stage: &str, bytes: u64) {
+    metrics::histogram!(
+        "sentinel_request_bytes",
+        "endpoint" => endpoint.to_string(),
+        "stage" => stage.to_string()
+    )
+    .record(bytes as f64);
+}
+
+/// Record a response body size
+pub fn record_response_bytes(endpoint: &str, bytes: u64) {
+    metrics::histogram!("sentinel_response_bytes", "endpoint" => endpoint.to_string())
+        .record(bytes as f64);
+}
+
+/// Record a payload that exceeded its size threshold (`direction` is `request` or `response`)
+pub fn record_payload_warning(endpoint: &str, direction: &str) {
+    metrics::counter!(
+        "sentinel_payload_warnings_total",
+        "endpoint" => endpoint.to_string(),
+        "direction" => direction.to_string()
+    )
+    .increment(1);
+}
+
+/// Record the size and shape of a chat request
+pub fn record_request_complexity(endpoint: &str, tier: &str, complexity: &RequestComplexity) {
+    let labels = [
+        ("endpoint", endpoint.to_string()),
+        ("tier", tier.to_string()),
+    ];
+    metrics::histogram!("sentinel_request_messages", &labels).record(complexity.messages as f64);
+    metrics::histogram!("sentinel_request_content_chars", &labels)
+        .record(complexity.content_chars as f64);
+    for (feature, present) in [
+        ("images", complexity.images > 0),
+        ("tools", complexity.tools),
+        ("stream", complexity.streaming),
+    ] {
+        if present {
+            metrics::counter!(
+                "sentinel_request_features_total",
+                "endpoint" => endpoint.to_string(),
+                "tier" => tier.to_string(),
+                "feature" => feature
+            )
+            .increment(1);
+        }
+    }
+}
+
+/// Record the upstream snapshot that served a requested model
+pub fn record_model_snapshot(requested: &str, served: &str) {
+    metrics::counter!(
+        "sentinel_model_snapshot",
+        "requested" => requested.to_string(),
+        "served" => served.to_string()
+    )
+    .increment(1);
+}
+
+/// Record a request that bypassed rate limiting
+///
+/// Labelled by exemption source only; which users were exempt is in the debug
+/// log, so the label set stays bounded and carries no identifiers.
+pub fn record_rate_limit_exempt(source: &str) {
+    metrics::counter!(
+        "sentinel_rate_limit_exempt_requests_total",
+        "source" => source.to_string()
+    )
+    .increment(1);
+}
+
+/// Record a quarantine event (`entered`, `rejected`, `expired` or `cleared`)
+pub fn record_quarantine_event(event: &str) {
+    metrics::counter!(
+        "sentinel_quarantine_events_total",
+        "event" => event.to_string()
+    )
+    .increment(1);
+}
+
+/// Record the finish_reason of a completed response
+pub fn record_finish_reason(endpoint: &str, model: &str, reason: &str) {
+    metrics::counter!(
+        "sentinel_finish_reasons_total",
+        "endpoint" => endpoint.to_string(),
+        "model" => model.to_string(),
+        "reason" => reason.to_string()
+    )
+    .increment(1);
+}
+
+/// Record a mirrored request (`match`, `mismatch`, `error` or `dropped`)
+pub fn record_mirror_request(result: &str) {
+    metrics::counter!(
+        "sentinel_mirror_requests_total",
+        "result" => result.to_string()
+    )
+    .increment(1);
+}
+
+/// Record a response blocked by the content filter
+pub fn record_content_blocked(endpoint: &str) {
+    metrics::counter!(
+        "sentinel_content_blocked_total",
+        "endpoint" => endpoint.to_string()
+    )
+    .increment(1);
+}
+
+/// Record request content changes made by sanitization
+pub fn record_content_sanitized(endpoint: &str, kind: &'static str, count: usize) {
+    if count > 0 {
+        metrics::counter!(
+            "sentinel_content_sanitized_total",
+            "endpoint" => endpoint.to_string(),
+            "kind" => kind
+        )
+        .increment(count as u64);
+    }
+}
+
+/// Record an upstream response that failed validation
+pub fn record_upstream_invalid_response(provider: &str) {
+    metrics::counter!(
+        "sentinel_upstream_invalid_responses_total",
+        "provider" => provider.to_string()
+    )
+    .increment(1);
+}
+
+/// Record a request carrying `X-Sentinel-Synthetic` (`excluded` or `ignored`)
+pub fn record_synthetic_request(result: &'static str) {
+    metrics::counter!("sentinel_synthetic_requests_total", "result" => result).increment(1);
+}
+
+/// Record a request to a quiet path (served without request logging)
+pub fn record_quiet_request() {
+    metrics::counter!("sentinel_quiet_requests_total").increment(1);
+}
+
+/// Record a response of an exact-usage account whose usage had to be estimated
+pub fn record_estimated_usage(endpoint: &'static str) {
+    metrics::counter!("sentinel_estimated_usage_total", "endpoint" => endpoint).increment(1);
+}
+
+/// Record the upstream requests in flight in a client pool
+pub fn record_upstream_pool_in_flight(pool: &'static str, in_flight: usize) {
+    metrics::gauge!("sentinel_upstream_pool_in_flight", "pool" => pool).set(in_flight as f64);
+}
+
+/// Record the idle connection limit of a client pool
+pub fn record_upstream_pool_max_idle(pool: &'static str, max_idle: usize) {
+    metrics::gauge!("sentinel_upstream_pool_max_idle", "pool" => pool).set(max_idle as f64);
+}
+
+/// Record how a request's affinity hint was used
+pub fn record_session_affinity(result: &str) {
+    metrics::counter!(
+        "sentinel_session_affinity_total",
+        "result" => result.to_string()
+    )
+    .increment(1);
+}
+
 // =============================================================================
 // Tier Routing Metrics
 // =============================================================================
@@ -239,4 +647,29 @@ mod tests {
         // This should not panic
         init_metrics();
     }
+
+    #[test]
+    fn test_constant_labels_on_every_series() {
+        let labels = vec![
+            ("env".to_string(), "prod".to_string()),
+            ("region".to_string(), "eu".to_string()),
+        ];
+        let recorder = prometheus_builder(&labels).build_recorder();
+        let handle = recorder.handle();
+        metrics::with_local_recorder(&recorder, || {
+            record_request("200", "gpt-4o-mini", 0.5);
+            record_tokens("input", 10, "gpt-4o-mini");
+        });
+
+        let rendered = handle.render();
+        let series: Vec<&str> = rendered
+            .lines()
+            .filter(|line| line.starts_with("sentinel_"))
+            .collect();
+        assert!(!series.is_empty());
+        for line in series {
+            assert!(line.contains("env=\"prod\""), "missing env label: {}", line);
+            assert!(line.contains("region=\"eu\""), "missing region label: {}", line);
+        }
+    }
 }
@@ -8,6 +8,8 @@
 //! - **Typed handlers** for endpoints that need token tracking (chat, completions, embeddings)
 //! - **Pass-through handler** for all other /v1/* endpoints (audio, images, moderations, etc.)
 
+pub mod admin;
+pub mod body;
 pub mod chat;
 pub mod completions;
 pub mod debug;
@@ -17,27 +19,38 @@ pub mod metrics;
 pub mod models;
 pub mod passthrough;
 pub mod responses;
+pub mod sessions;
+pub mod usage;
 
 use std::sync::Arc;
 
 use axum::{
     body::Body,
+    handler::Handler,
     http::{Request, StatusCode},
     middleware,
     response::IntoResponse,
-    routing::{get, post},
+    routing::{delete, get, post},
     Json, Router,
 };
 use serde_json::json;
 use tower_http::{
     compression::CompressionLayer,
     cors::{Any, CorsLayer},
-    trace::TraceLayer,
 };
 use tracing::warn;
 
 use crate::{
-    middleware::{auth::auth_middleware, rate_limiter::rate_limit_middleware},
+    middleware::{
+        auth::auth_middleware, compat::compat_middleware, content_type::content_type_middleware,
+        in_flight::in_flight_middleware, decompression::decompression_middleware,
+        maintenance::maintenance_middleware, mirror::mirror_middleware,
+        provider_override::provider_override_middleware, quarantine::quarantine_middleware,
+        rate_limiter::rate_limit_middleware,
+        request_log::request_log_middleware,
+        scope::{scope_middleware, CHAT_SCOPE, EMBEDDINGS_SCOPE, PASSTHROUGH_SCOPE},
+        synthetic::synthetic_middleware,
+    },
     native_routes::{self, create_docs_router},
     AppState,
 };
@@ -52,31 +65,105 @@ pub fn create_router(state: Arc<AppState>) -> Router {
 
     // Routes that require authentication and rate limiting
     // Middleware is applied in reverse order (last applied runs first)
-    // So: auth runs first, then rate limiting
+    // So: the compat layer wraps everything, auth runs first, then synthetic marking, then quarantine, then rate
+    // limiting, then the canary provider override
     //
     // Using nest() so that the fallback works correctly for /v1/* routes.
     // Routes are defined without /v1 prefix since nest() adds it.
+    //
+    // Model endpoints answer 503 while maintenance mode is on (checked after
+    // auth and rate limiting, so rejected requests still need a valid token).
+    //
+    // Model endpoints and the pass-through fallback also declare the token
+    // scope they require (checked before maintenance); the other routes
+    // accept any token.
+    let maintenance = middleware::from_fn_with_state(state.clone(), maintenance_middleware);
+    let chat_scope = middleware::from_fn_with_state(CHAT_SCOPE, scope_middleware);
     let protected_routes = Router::new()
         // Typed handlers with token tracking
-        .route("/chat/completions", post(chat::chat_completions))
-        .route("/completions", post(completions::completions))
-        .route("/embeddings", post(embeddings::embeddings))
+        .route(
+            "/chat/completions",
+            post(chat::chat_completions)
+                .layer(maintenance.clone())
+                .layer(chat_scope.clone()),
+        )
+        .route(
+            "/completions",
+            post(completions::completions)
+                .layer(maintenance.clone())
+                .layer(chat_scope.clone()),
+        )
+        .route(
+            "/embeddings",
+            post(embeddings::embeddings)
+                .layer(maintenance.clone())
+                .layer(middleware::from_fn_with_state(EMBEDDINGS_SCOPE, scope_middleware)),
+        )
         .route("/models", get(models::list_models))
-        .route("/models/{model_id}", get(models::get_model))
+        .route("/models/:model_id", get(models::get_model))
         // OpenAI Responses API - routes directly to OpenAI (not supported by Vercel AI Gateway)
-        .route("/responses", post(responses::responses_handler))
+        .route(
+            "/responses",
+            post(responses::responses_handler)
+                .layer(maintenance)
+                .layer(chat_scope),
+        )
+        // Caller's limits and recent local usage
+        .route("/usage", get(usage::get_usage))
+        .route("/usage/workflows/:workflow_id", get(usage::get_workflow_usage))
+        // Delete the caller's native API sessions
+        .route("/sessions", delete(sessions::delete_sessions))
         // Pass-through handler for all other /v1/* endpoints
         // Handles: audio, images, moderations, assistants, etc.
-        .fallback(passthrough::passthrough_handler)
-        // Apply rate limiting (runs after auth)
+        .fallback(passthrough::passthrough_handler.layer(middleware::from_fn_with_state(
+            PASSTHROUGH_SCOPE,
+            scope_middleware,
+        )))
+        // List the request in `/admin/snapshot` while it runs (runs after the mirror)
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            in_flight_middleware,
+        ))
+        // Copy a sample of requests to the staging mirror (runs after the canary override)
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            mirror_middleware,
+        ))
+        // Honor X-Sentinel-Provider for canary accounts (runs after rate limiting)
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            provider_override_middleware,
+        ))
+        // Apply rate limiting (runs after quarantine)
         .layer(middleware::from_fn_with_state(
             state.clone(),
             rate_limit_middleware,
         ))
-        // Apply authentication (runs first)
+        // Reject quarantined users before any further work (runs after synthetic marking)
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            quarantine_middleware,
+        ))
+        // Mark allow-listed X-Sentinel-Synthetic requests (runs after decompression)
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            synthetic_middleware,
+        ))
+        // Inflate gzip request bodies before anything reads them, for
+        // authenticated callers only (runs after auth)
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            decompression_middleware,
+        ))
+        // Apply authentication (runs after the compat layer)
         .layer(middleware::from_fn_with_state(
             state.clone(),
             auth_middleware,
+        ))
+        // COMPAT_MODE=strict: strip Sentinel headers and error details (wraps everything above)
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            compat_middleware,
         ));
 
     // Public routes (health checks, metrics) - no auth required
@@ -92,21 +179,89 @@ pub fn create_router(state: Arc<AppState>) -> Router {
         .route("/debug/auth/:external_id", get(debug::user_auth_state))
         .route("/debug/config", get(debug::config_info));
 
+    // Admin routes (X-Admin-Key or `admin` scope protected, hidden otherwise;
+    // gzip bodies are inflated once the caller is checked)
+    let admin_routes = Router::new()
+        .route("/admin/users/:external_id/usage", get(admin::user_usage))
+        .route("/admin/users/:external_id/throttle", delete(admin::clear_throttle))
+        .route("/admin/users/:external_id/sessions", delete(admin::delete_user_sessions))
+        .route("/admin/providers/status", get(admin::provider_status))
+        .route("/admin/zion/capabilities", get(admin::zion_capabilities))
+        .route("/admin/tiers/state", get(admin::tier_state))
+        .route("/admin/snapshot", get(admin::snapshot))
+        .route("/admin/usage/retry", get(admin::usage_retry_status))
+        .route("/admin/reports/usage", get(admin::usage_report))
+        .route(
+            "/admin/usage/request-weights",
+            get(admin::get_request_weights)
+                .put(admin::set_request_weights)
+                .delete(admin::reset_request_weights),
+        )
+        .route("/admin/cache/warm", post(admin::start_cache_warm))
+        .route("/admin/cache/warm/:job_id", get(admin::cache_warm_status))
+        .route(
+            "/admin/maintenance",
+            get(admin::get_maintenance)
+                .put(admin::set_maintenance)
+                .delete(admin::clear_maintenance),
+        )
+        .route(
+            "/admin/rate-limit/exempt-ids",
+            get(admin::get_rate_limit_exempt_ids)
+                .put(admin::set_rate_limit_exempt_ids)
+                .delete(admin::clear_rate_limit_exempt_ids),
+        )
+        .route(
+            "/admin/log-level",
+            get(admin::get_log_level).put(admin::set_log_level),
+        );
+    #[cfg(feature = "ledger")]
+    let admin_routes = admin_routes.route("/admin/ledger/export", get(admin::export_ledger));
+    #[cfg(feature = "chaos")]
+    let admin_routes = admin_routes
+        .route(
+            "/admin/chaos/faults",
+            get(admin::list_chaos_faults)
+                .post(admin::add_chaos_fault)
+                .delete(admin::clear_chaos_faults),
+        )
+        .route("/admin/chaos/faults/:id", delete(admin::delete_chaos_fault));
+    let admin_routes = admin_routes
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            decompression_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            admin::admin_auth_middleware,
+        ));
+
     Router::new()
         .merge(public_routes)
         .merge(debug_routes)
+        .merge(admin_routes)
         // Docs router - API key protected, no auth/rate-limit middleware
         // Must be merged before fallback since it handles /native/docs paths
         .merge(create_docs_router())
         // Nest protected routes under /v1 - this makes fallback work correctly
         .nest("/v1", protected_routes)
         // Nest native API routes under /native - unified format with translation
+        // (with its own native-format fallback, behind the same middleware as /v1)
         .nest("/native", native_routes::create_native_router(state.clone()))
         // Fallback for non-/v1 routes
         .fallback(fallback_handler)
         // Global middleware (applied to all routes)
+        // Consistent JSON and SSE response headers, whichever layer built the response
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            content_type_middleware,
+        ))
         .layer(CompressionLayer::new())
-        .layer(TraceLayer::new_for_http())
+        // Log requests, except the QUIET_LOG_PATHS probes
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            request_log_middleware,
+        ))
         .layer(cors)
         .with_state(state)
 }
@@ -134,7 +289,8 @@ async fn fallback_handler(request: Request<Body>) -> impl IntoResponse {
             "error": {
                 "message": format!("Endpoint {} {} not found. API endpoints are under /v1/ and /native/", method, path),
                 "type": "not_found_error",
-                "code": "endpoint_not_found"
+                "code": "endpoint_not_found",
+                "retryable": false
             }
         })),
     )
@@ -1,6 +1,7 @@
 //! Models endpoint
 //!
-//! Lists available models through the proxy.
+//! Lists available models through the proxy, or from `STATIC_MODELS_JSON`
+//! (see [`crate::proxy::static_models`]).
 
 use std::sync::Arc;
 
@@ -8,10 +9,14 @@ use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 use serde::{Deserialize, Serialize};
 use tracing::{info, warn};
 
-use crate::{error::AppError, AppState};
+use crate::{
+    error::AppError,
+    proxy::static_models::{static_list, ModelsSource},
+    AppState,
+};
 
 /// Model information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Model {
     pub id: String,
     pub object: String,
@@ -110,38 +115,47 @@ fn get_static_models() -> Vec<Model> {
     ]
 }
 
-/// List available models
-///
-/// Attempts to fetch models from the AI provider, falls back to static list on error.
-pub async fn list_models(
-    State(state): State<Arc<AppState>>,
-) -> Result<impl IntoResponse, AppError> {
-    info!("Fetching available models");
-
-    // Try to fetch from provider, fall back to static list
-    let response = match state.ai_provider.list_models().await {
+/// Models listed by the provider, or the built-in list when that fails
+async fn upstream_models(state: &AppState) -> Vec<Model> {
+    match state.provider().list_models().await {
         Ok(response_value) => {
             match serde_json::from_value::<ModelsResponse>(response_value) {
                 Ok(models) => {
                     info!(count = %models.data.len(), "Fetched models from provider");
-                    models
+                    models.data
                 }
                 Err(e) => {
                     warn!(error = %e, "Failed to parse models response, using static list");
-                    ModelsResponse {
-                        object: "list".to_string(),
-                        data: get_static_models(),
-                    }
+                    get_static_models()
                 }
             }
         }
         Err(e) => {
             warn!(error = %e, "Failed to fetch models from provider, using static list");
-            ModelsResponse {
-                object: "list".to_string(),
-                data: get_static_models(),
-            }
+            get_static_models()
         }
+    }
+}
+
+/// List available models
+///
+/// Attempts to fetch models from the AI provider, falls back to static list on error.
+/// `MODELS_SOURCE=static` serves `STATIC_MODELS_JSON` instead, `merged` lays it
+/// over the provider's list.
+pub async fn list_models(
+    State(state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Fetching available models");
+
+    let configured = static_list(&state.config.provider)?;
+    let data = match (state.config.provider.models_source, configured) {
+        (ModelsSource::Static, Some(configured)) => configured.models().to_vec(),
+        (ModelsSource::Merged, Some(configured)) => configured.overlay(upstream_models(&state).await),
+        _ => upstream_models(&state).await,
+    };
+    let response = ModelsResponse {
+        object: "list".to_string(),
+        data,
     };
 
     Ok((StatusCode::OK, Json(response)))
@@ -149,15 +163,26 @@ pub async fn list_models(
 
 /// Get a specific model by ID
 ///
-/// Returns model details if found.
+/// Returns model details if found. Configured static models are resolved
+/// without asking the provider; in `static` mode any other id is a 404.
 pub async fn get_model(
     State(state): State<Arc<AppState>>,
     axum::extract::Path(model_id): axum::extract::Path<String>,
 ) -> Result<impl IntoResponse, AppError> {
     info!(model_id = %model_id, "Fetching model details");
 
+    let not_found = || AppError::NotFound(format!("Model '{}' not found", model_id));
+    if let Some(configured) = static_list(&state.config.provider)? {
+        if let Some(model) = configured.get(&model_id) {
+            return Ok((StatusCode::OK, Json(model.clone())));
+        }
+        if state.config.provider.models_source == ModelsSource::Static {
+            return Err(not_found());
+        }
+    }
+
     // Try to fetch model from provider
-    let model = match state.ai_provider.get_model(&model_id).await {
+    let model = match state.provider().get_model(&model_id).await {
         Ok(response_value) => {
             match serde_json::from_value::<Model>(response_value) {
                 Ok(model) => model,
@@ -166,7 +191,7 @@ pub async fn get_model(
                     get_static_models()
                         .into_iter()
                         .find(|m| m.id == model_id)
-                        .ok_or_else(|| AppError::NotFound(format!("Model '{}' not found", model_id)))?
+                        .ok_or_else(not_found)?
                 }
             }
         }
@@ -175,7 +200,7 @@ pub async fn get_model(
             get_static_models()
                 .into_iter()
                 .find(|m| m.id == model_id)
-                .ok_or_else(|| AppError::NotFound(format!("Model '{}' not found", model_id)))?
+                .ok_or_else(not_found)?
         }
     };
 
@@ -2,23 +2,29 @@
 //!
 //! Generic handler that forwards all unmatched /v1/* requests to the AI provider
 //! without parsing the request body. Used for endpoints that don't require token tracking
-//! (audio, images, moderations, etc.).
+//! (audio, images, moderations, etc.). The one exception: JSON bodies are read
+//! to check their `model` against the user's allowlist and to apply
+//! `UPSTREAM_USER_FIELD` to their `user`; they are forwarded byte for byte
+//! unless the `user` field changes.
 
 use std::sync::Arc;
 use std::time::Instant;
 
 use axum::{
+    body::Body,
     extract::{OriginalUri, State},
-    http::{header::HeaderMap, Method},
+    http::{header::{self, HeaderMap}, Method},
     response::Response,
     Extension,
 };
+use serde_json::Value;
 use tracing::info;
 
 use crate::{
     error::AppError,
-    middleware::auth::AuthenticatedUser,
-    routes::metrics::record_request,
+    middleware::{auth::AuthenticatedUser, content_type::ForwardedResponse, model_access},
+    proxy::upstream_user,
+    routes::{body::is_json_content_type, metrics::record_request},
     AppState,
 };
 
@@ -28,13 +34,13 @@ use crate::{
 /// 1. Authenticates the user (via middleware)
 /// 2. Forwards the request body unchanged to the AI provider
 /// 3. Streams the response back to the client
-/// 4. Tracks usage (request count only, no token tracking)
+/// 4. Tracks usage (request count weighted by path, no token tracking)
 pub async fn passthrough_handler(
     State(state): State<Arc<AppState>>,
     OriginalUri(uri): OriginalUri,
     method: Method,
     headers: HeaderMap,
-    Extension(user): Extension<AuthenticatedUser>,
+    Extension(mut user): Extension<AuthenticatedUser>,
     request: axum::extract::Request,
 ) -> Result<Response, AppError> {
     let start_time = Instant::now();
@@ -50,18 +56,40 @@ pub async fn passthrough_handler(
         method = %method,
         path = %path,
         forward_path = %forward_path,
-        external_id = %user.external_id,
+        external_id = %user.log_id(),
         "Processing pass-through request"
     );
 
-    // Extract body from request
-    let body = request.into_body();
+    // Extract body from request; JSON bodies are read first to check their
+    // `model` and rewrite their `user`
+    let body = if is_json_content_type(headers.get(header::CONTENT_TYPE)) {
+        let body = axum::body::to_bytes(request.into_body(), usize::MAX)
+            .await
+            .map_err(|e| AppError::BadRequest(format!("Failed to read request body: {}", e)))?;
+        match serde_json::from_slice::<Value>(&body) {
+            Ok(Value::Object(mut object)) => {
+                if let Some(model) = object.get("model").and_then(Value::as_str) {
+                    model_access::check(&user, "passthrough", &[model])?;
+                }
+                if upstream_user::apply_to_object(&state.config.provider, &mut user, &mut object) {
+                    Body::from(Value::Object(object).to_string())
+                } else {
+                    Body::from(body)
+                }
+            }
+            _ => Body::from(body),
+        }
+    } else {
+        request.into_body()
+    };
 
     // Forward the request using the AI provider
-    let response = state
-        .ai_provider
+    let mut response = state
+        .provider()
         .forward_raw(method.clone(), &forward_path, headers, body)
         .await?;
+    // Keep the provider's own Content-Type
+    response.extensions_mut().insert(ForwardedResponse);
 
     // Record metrics
     let duration = start_time.elapsed().as_secs_f64();
@@ -72,18 +100,18 @@ pub async fn passthrough_handler(
     };
     record_request(status_label, &path, duration);
 
-    // Track request count only (no token tracking for pass-through endpoints)
-    // No model available for pass-through requests
-    state
-        .batching_tracker
-        .track_request_only(user.email.clone(), None);
+    // Track request count only (no token tracking for pass-through endpoints),
+    // weighted by path; no model available for pass-through requests
+    let weight = state.request_weights.weight_for(&forward_path);
+    state.batching_tracker.track_user_requests(&user, weight);
 
     info!(
         method = %method,
         path = %path,
         status = %response.status(),
+        request_weight = weight,
         duration_ms = %format!("{:.2}", duration * 1000.0),
-        external_id = %user.external_id,
+        external_id = %user.log_id(),
         "Pass-through request completed"
     );
 
@@ -2,16 +2,21 @@
 //!
 //! Routes /v1/responses to OpenAI with full token tracking.
 //! The Responses API uses `input` array (similar to chat messages) instead of `messages`.
+//!
+//! Streams are forwarded to the client byte-for-byte. The Responses event set
+//! (`response.output_item.added`, `response.function_call_arguments.delta`, ...)
+//! is observed out-of-band: `response.completed` supplies exact usage and
+//! `response.failed`/`error` events are reported to the health tracker.
 
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::Duration;
 
 use axum::{
-    body::Body,
+    body::{Body, HttpBody},
     extract::State,
-    http::{header, HeaderMap, StatusCode},
+    http::{HeaderMap, StatusCode, Uri},
     response::{IntoResponse, Response},
-    Json,
+    Extension, Json,
 };
 use futures::StreamExt;
 use serde::{Deserialize, Serialize};
@@ -19,12 +24,17 @@ use tracing::{debug, info, warn};
 
 use crate::{
     error::AppError,
-    middleware::auth::AuthenticatedUser,
-    routes::metrics::{
-        record_fallback_estimation, record_request, record_sse_parse_error,
-        record_token_estimation_diff, record_tokens,
+    middleware::{auth::AuthenticatedUser, model_access},
+    proxy::{capture, logging::json_len, snapshot, timeout, upstream_user, RequestContext},
+    routes::{
+        body::{self, SentinelJson},
+        metrics::{
+            record_fallback_estimation, record_provider_failure, record_request,
+            record_sse_parse_error, record_token_estimation_diff, record_tokens,
+        },
     },
-    streaming::SseLineBuffer,
+    streaming::{sse_response, SseLineBuffer},
+    usage::{ledger::hash_user, workflow_id_from_headers},
     AppState,
 };
 
@@ -57,6 +67,9 @@ pub struct Usage {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResponsesResponse {
     pub id: String,
+    /// Model snapshot that served the request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
     #[serde(default)]
     pub output: Vec<serde_json::Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -118,44 +131,51 @@ fn extract_output_text(output: &[serde_json::Value]) -> String {
 pub async fn responses_handler(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
-    request: axum::extract::Request,
+    uri: Uri,
+    Extension(mut user): Extension<AuthenticatedUser>,
+    SentinelJson(mut responses_request, body_len): SentinelJson<ResponsesRequest>,
 ) -> Result<Response, AppError> {
-    let start_time = Instant::now();
-
-    // Extract authenticated user from request extensions (set by auth middleware)
-    let user = request
-        .extensions()
-        .get::<AuthenticatedUser>()
-        .cloned()
-        .ok_or_else(|| {
-            warn!("AuthenticatedUser not found in request extensions");
-            AppError::Unauthorized
-        })?;
-
-    // Parse the request body
-    let body = axum::body::to_bytes(request.into_body(), usize::MAX)
-        .await
-        .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to read request body: {}", e)))?;
-
-    let responses_request: ResponsesRequest = serde_json::from_slice(&body)
-        .map_err(|e| AppError::BadRequest(format!("Invalid request body: {}", e)))?;
+    let ctx = RequestContext::new(state.provider().name(), "/v1/responses");
+
+    // A `stream` query parameter overrides the body's flag
+    let stream_override = body::stream_override(&uri)?;
+    if let Some(stream) = stream_override {
+        responses_request.stream = stream;
+    }
 
     let model = responses_request.model.clone();
+    model_access::check(&user, "/v1/responses", &[&model])?;
+
+    // The provider only sees the `user` value UPSTREAM_USER_FIELD allows
+    let extra = responses_request.extra.get_or_insert_with(Default::default);
+    upstream_user::apply_to_object(&state.config.provider, &mut user, extra);
     let is_streaming = responses_request.stream;
+    let timeout = timeout::timeout_from_headers(&state.config, &headers)?;
+    let workflow_id = workflow_id_from_headers(&headers).map_err(AppError::BadRequest)?;
+    let ctx = ctx
+        .with_model(model.clone())
+        .with_streaming(is_streaming)
+        .with_external_id(user.log_id())
+        .with_user_hash(hash_user(&user.email))
+        .with_workflow_id(workflow_id)
+        .with_request_bytes(body_len as u64);
 
     info!(
         model = %model,
         stream = %is_streaming,
         input_items = %responses_request.input.len(),
-        external_id = %user.external_id,
+        external_id = %user.log_id(),
+        workflow_id = ?ctx.workflow_id,
         "Processing responses API request"
     );
 
-    if is_streaming {
-        handle_streaming_responses(state, &headers, responses_request, model, start_time, user).await
+    let result = if is_streaming {
+        handle_streaming_responses(state, &headers, responses_request, model, ctx, user, timeout).await
     } else {
-        handle_non_streaming_responses(state, &headers, responses_request, model, start_time, user).await
-    }
+        handle_non_streaming_responses(state, &headers, responses_request, model, ctx, user, timeout).await
+    };
+
+    timeout::with_timeout_header(result, timeout)
 }
 
 /// Handle non-streaming responses
@@ -164,8 +184,9 @@ async fn handle_non_streaming_responses(
     headers: &HeaderMap,
     request: ResponsesRequest,
     model: String,
-    start_time: Instant,
+    ctx: RequestContext,
     user: AuthenticatedUser,
+    timeout: Option<Duration>,
 ) -> Result<Response, AppError> {
     // Pre-count input tokens using tiktoken
     let input_tuples = input_to_tuples(&request.input);
@@ -177,18 +198,26 @@ async fn handle_non_streaming_responses(
     // Convert request to Value for the provider
     let request_value = serde_json::to_value(&request)
         .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to serialize request: {}", e)))?;
+    let ctx = ctx.with_forwarded_bytes(json_len(&request_value));
 
-    let response_value = state
-        .ai_provider
-        .responses(request_value, headers)
-        .await?;
+    let (response_value, upstream) = capture::capture(timeout::call_with_timeout(
+        timeout,
+        state.provider().responses(request_value, headers),
+    ))
+    .await;
+    ctx.record_upstream_headers(upstream);
+    let response_value = response_value?;
 
     // Parse the response
     let response: ResponsesResponse = serde_json::from_value(response_value.clone())
         .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to parse response: {}", e)))?;
 
+    // Usage is attributed to the snapshot the provider actually served
+    let served_model = snapshot::attributed_model(&model, response.model.as_deref());
+    state.model_snapshots.observe(&model, &served_model).await;
+
     // Record metrics
-    let duration = start_time.elapsed().as_secs_f64();
+    let duration = ctx.start_time.elapsed().as_secs_f64();
     record_request("success", &model, duration);
 
     // Get token counts: prefer OpenAI usage, fallback to estimation
@@ -233,43 +262,71 @@ async fn handle_non_streaming_responses(
     record_tokens("completion", output_tokens, &model);
 
     // Track usage in Zion (fire-and-forget, never blocks)
-    state.batching_tracker.track(user.email.clone(), input_tokens, output_tokens, Some(model.clone()));
+    state.batching_tracker.track_user_in_workflow(
+        &user,
+        ctx.workflow_id.clone(),
+        input_tokens,
+        output_tokens,
+        Some(served_model.clone()),
+    );
 
     info!(
         model = %model,
         duration_ms = %format!("{:.2}", duration * 1000.0),
         input_tokens = input_tokens,
         output_tokens = output_tokens,
-        external_id = %user.external_id,
+        external_id = %user.log_id(),
+        workflow_id = ?ctx.workflow_id,
+        upstream_headers = %ctx.upstream_headers(),
         "Responses API request completed"
     );
 
-    Ok((StatusCode::OK, Json(response)).into_response())
+    let mut response = (StatusCode::OK, Json(response)).into_response();
+    ctx.add_response_bytes(response.body().size_hint().exact().unwrap_or(0));
+    ctx.record_payload_sizes(
+        state.config.server.payload_warn_request_bytes,
+        state.config.server.payload_warn_response_bytes,
+    );
+    snapshot::insert_upstream_model_header(response.headers_mut(), &served_model);
+    ctx.upstream_headers().insert_request_id_header(response.headers_mut());
+    Ok(response)
 }
 
-/// Response object inside response.completed events
+/// Response object inside response.* lifecycle events
 #[derive(Debug, Clone, Deserialize, Default)]
 struct ResponseObject {
+    #[serde(default)]
+    model: Option<String>,
     #[serde(default)]
     usage: Option<Usage>,
+    /// Error details on response.failed
+    #[serde(default)]
+    error: Option<ResponseError>,
     // Note: output field exists in the API but we don't need it for token counting
 }
 
-/// Streaming chunk for parsing content and usage
-/// Handles both Chat Completions-style and Responses API-style events
+/// Error object carried by response.failed and error events
+#[derive(Debug, Clone, Deserialize, Default)]
+struct ResponseError {
+    #[serde(default)]
+    code: Option<String>,
+    #[serde(default)]
+    message: Option<String>,
+}
+
+/// Streaming event for parsing content and usage
+/// Handles both Responses API events and legacy untyped chunks
 #[derive(Debug, Clone, Deserialize)]
 struct StreamChunk {
     /// Event type (e.g., "response.output_text.delta", "response.completed")
-    /// Used in tests to verify correct parsing; available for production debugging if needed
     #[serde(default, rename = "type")]
-    #[allow(dead_code)]
     event_type: Option<String>,
 
-    /// Delta content - can be a string (response.output_text.delta) or object
+    /// Delta content - can be a string (response.*.delta) or object
     #[serde(default)]
     delta: Option<serde_json::Value>,
 
-    /// Response object inside response.completed events - contains usage
+    /// Response object inside response.* lifecycle events - contains usage
     #[serde(default)]
     response: Option<ResponseObject>,
 
@@ -280,6 +337,112 @@ struct StreamChunk {
     /// Output array (for non-streaming or different event types)
     #[serde(default)]
     output: Vec<serde_json::Value>,
+
+    /// Error code on top-level error events
+    #[serde(default)]
+    code: Option<String>,
+
+    /// Error message on top-level error events
+    #[serde(default)]
+    message: Option<String>,
+}
+
+/// Usage and outcome observed while forwarding a Responses stream
+#[derive(Debug, Clone, Default)]
+struct ResponsesStreamState {
+    /// Exact usage from response.completed / response.incomplete / response.failed
+    usage: Usage,
+    /// Text and tool-call arguments for fallback estimation
+    content: String,
+    /// Terminal event seen ("completed", "incomplete", "failed")
+    status: Option<String>,
+    /// Error reported by response.failed or an error event
+    failure: Option<String>,
+    /// Model snapshot reported by the lifecycle events
+    model: Option<String>,
+}
+
+impl ResponsesStreamState {
+    /// Update state from one parsed stream event
+    fn observe(&mut self, chunk: StreamChunk) {
+        if let Some(model) = chunk.response.as_ref().and_then(|r| r.model.clone()) {
+            self.model = Some(model);
+        }
+
+        match chunk.event_type.as_deref() {
+            Some(event @ ("response.completed" | "response.incomplete" | "response.failed")) => {
+                let status = event.trim_start_matches("response.");
+                self.status = Some(status.to_string());
+                if let Some(response) = chunk.response {
+                    if let Some(usage) = response.usage {
+                        self.usage = usage;
+                    }
+                    if status == "failed" {
+                        let error = response.error.unwrap_or_default();
+                        self.failure = Some(describe_error(error.code, error.message));
+                    }
+                }
+                if status == "failed" && self.failure.is_none() {
+                    self.failure = Some(describe_error(None, None));
+                }
+            }
+            Some("error") => {
+                self.failure = Some(describe_error(chunk.code, chunk.message));
+            }
+            // Incremental text, refusal, reasoning and function-call argument fragments
+            Some(event) if event.ends_with(".delta") => {
+                if let Some(ref delta) = chunk.delta {
+                    self.push_delta(delta);
+                }
+            }
+            // Lifecycle and *.done snapshots repeat what the deltas already carried
+            Some(_) => {}
+            // Untyped chunks (legacy/other formats)
+            None => {
+                if !chunk.output.is_empty() {
+                    self.content.push_str(&extract_output_text(&chunk.output));
+                }
+                if let Some(ref delta) = chunk.delta {
+                    self.push_delta(delta);
+                }
+            }
+        }
+
+        // Direct usage field (legacy/other formats)
+        if let Some(usage) = chunk.usage {
+            self.usage = usage;
+        }
+    }
+
+    fn push_delta(&mut self, delta: &serde_json::Value) {
+        // For response.*.delta events, delta is a string
+        if let Some(text) = delta.as_str() {
+            self.content.push_str(text);
+        }
+        // Some event types use an object with a text/content field
+        if let Some(obj) = delta.as_object() {
+            if let Some(text) = obj
+                .get("text")
+                .or_else(|| obj.get("content"))
+                .and_then(|t| t.as_str())
+            {
+                self.content.push_str(text);
+            }
+        }
+    }
+
+    fn has_usage(&self) -> bool {
+        self.usage.input_tokens > 0 || self.usage.output_tokens > 0
+    }
+}
+
+fn describe_error(code: Option<String>, message: Option<String>) -> String {
+    match (code, message) {
+        (Some(code), Some(message)) => format!("{}: {}", code, message),
+        (Some(code), None) => code,
+        (None, Some(message)) => message,
+        (None, None) => "response failed".to_string(),
+    }
 }
 
 /// Handle streaming responses
@@ -288,8 +451,9 @@ async fn handle_streaming_responses(
     headers: &HeaderMap,
     request: ResponsesRequest,
     model: String,
-    start_time: Instant,
+    ctx: RequestContext,
     user: AuthenticatedUser,
+    timeout: Option<Duration>,
 ) -> Result<Response, AppError> {
     // Pre-count input tokens using tiktoken
     let input_tuples = input_to_tuples(&request.input);
@@ -301,40 +465,60 @@ async fn handle_streaming_responses(
     // Convert request to Value for the provider
     let request_value = serde_json::to_value(&request)
         .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to serialize request: {}", e)))?;
+    let ctx = ctx.with_forwarded_bytes(json_len(&request_value));
 
     // Forward streaming request to provider
-    let stream = state
-        .ai_provider
-        .responses_stream(request_value, headers)
-        .await?;
+    let (stream, upstream) = capture::capture(timeout::stream_with_timeout(
+        timeout,
+        state.provider().responses_stream(request_value, headers),
+    ))
+    .await;
+    ctx.record_upstream_headers(upstream);
+    let stream = stream?;
 
     // Clone values for the stream closure
     let model_clone = model.clone();
     let tracker = state.batching_tracker.clone();
-    let user_email = user.email.clone();
+    let user_email = user.log_email().to_string();
     let token_counter = state.token_counter.clone();
+    let health_tracker = state.health_tracker.clone();
+    let provider_name = state.provider().name();
 
-    // Track accumulated usage from stream (if OpenAI provides it)
-    let usage_accumulator = std::sync::Arc::new(std::sync::Mutex::new(Usage::default()));
-    let usage_for_stream = usage_accumulator.clone();
-
-    // Track accumulated content for token counting fallback
-    let content_accumulator = std::sync::Arc::new(std::sync::Mutex::new(String::new()));
-    let content_for_stream = content_accumulator.clone();
+    // Track usage, content and outcome observed in the stream
+    let stream_state = std::sync::Arc::new(std::sync::Mutex::new(ResponsesStreamState::default()));
+    let state_for_stream = stream_state.clone();
 
     // Buffer for accumulating incomplete SSE lines across chunk boundaries
-    let line_buffer = std::sync::Arc::new(std::sync::Mutex::new(SseLineBuffer::new()));
+    let line_buffer = std::sync::Arc::new(std::sync::Mutex::new(
+        SseLineBuffer::with_max_line_bytes(state.config.provider.sse_max_line_bytes),
+    ));
     let line_buffer_for_stream = line_buffer.clone();
 
+    // Set when the upstream sends an oversized line; the stream ends after the error event
+    let aborted = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let aborted_for_stream = aborted.clone();
+
     // Clone model for metrics in stream closure
     let model_for_parse_error = model.clone();
 
     // Wrap the stream to extract content and usage from chunks
+    // Count forwarded bytes as they pass; chunks are not copied
+    let ctx_for_stream = ctx.clone();
+
     let tracked_stream = stream.map(move |chunk| {
         match chunk {
             Ok(bytes) => {
+                ctx_for_stream.add_response_bytes(bytes.len() as u64);
+
                 // Use line buffer to handle chunks split across network boundaries
-                let complete_lines = line_buffer_for_stream.lock().unwrap().feed(&bytes);
+                let complete_lines = match line_buffer_for_stream.lock().unwrap().feed(&bytes) {
+                    Ok(lines) => lines,
+                    Err(e) => {
+                        warn!(model = %model_clone, error = %e, "Aborting stream with oversized SSE line");
+                        aborted_for_stream.store(true, std::sync::atomic::Ordering::Relaxed);
+                        return Ok(e.to_event());
+                    }
+                };
 
                 for line in complete_lines {
                     if let Some(json_str) = line.strip_prefix("data: ") {
@@ -342,46 +526,7 @@ async fn handle_streaming_responses(
                         if json_str != "[DONE]" {
                             match serde_json::from_str::<StreamChunk>(json_str) {
                                 Ok(chunk) => {
-                                    // Accumulate content from output array (non-streaming or some event types)
-                                    if !chunk.output.is_empty() {
-                                        let output_text = extract_output_text(&chunk.output);
-                                        content_for_stream.lock().unwrap().push_str(&output_text);
-                                    }
-
-                                    // Accumulate content from delta field
-                                    if let Some(ref delta) = chunk.delta {
-                                        // For response.output_text.delta events, delta is a string
-                                        if let Some(text) = delta.as_str() {
-                                            content_for_stream.lock().unwrap().push_str(text);
-                                        }
-                                        // For other event types, delta may be an object with text/content fields
-                                        if let Some(obj) = delta.as_object() {
-                                            if let Some(text) = obj.get("text").and_then(|t| t.as_str()) {
-                                                content_for_stream.lock().unwrap().push_str(text);
-                                            }
-                                            if let Some(content) = obj.get("content").and_then(|c| c.as_str()) {
-                                                content_for_stream.lock().unwrap().push_str(content);
-                                            }
-                                        }
-                                    }
-
-                                    // Extract usage from response.completed event (nested in response object)
-                                    if let Some(ref response) = chunk.response {
-                                        if let Some(ref usage) = response.usage {
-                                            let mut acc = usage_for_stream.lock().unwrap();
-                                            acc.input_tokens = usage.input_tokens;
-                                            acc.output_tokens = usage.output_tokens;
-                                            acc.total_tokens = usage.total_tokens;
-                                        }
-                                    }
-
-                                    // Fallback: direct usage field (legacy/other formats)
-                                    if let Some(usage) = chunk.usage {
-                                        let mut acc = usage_for_stream.lock().unwrap();
-                                        acc.input_tokens = usage.input_tokens;
-                                        acc.output_tokens = usage.output_tokens;
-                                        acc.total_tokens = usage.total_tokens;
-                                    }
+                                    state_for_stream.lock().unwrap().observe(chunk);
                                 }
                                 Err(e) => {
                                     // Log and record metric for parse failures on complete lines
@@ -408,24 +553,56 @@ async fn handle_streaming_responses(
 
     // Create a stream that tracks usage after completion
     let user_email_final = user_email.clone();
+    let user_final = user.clone();
     let model_for_metrics = model.clone();
     let model_for_counting = model.clone();
-    let usage_final = usage_accumulator.clone();
-    let content_final = content_accumulator.clone();
+    let state_final = stream_state.clone();
+    let snapshots_final = state.model_snapshots.clone();
     let tracker_final = tracker.clone();
+    let ctx_final = ctx.clone();
+    let max_request_bytes = state.config.server.payload_warn_request_bytes;
+    let max_response_bytes = state.config.server.payload_warn_response_bytes;
+
+    let aborted_final = aborted.clone();
 
     let final_stream = async_stream::stream! {
-        futures::pin_mut!(tracked_stream);
-        while let Some(item) = tracked_stream.next().await {
-            yield item;
+        {
+            futures::pin_mut!(tracked_stream);
+            while let Some(item) = tracked_stream.next().await {
+                yield item;
+                if aborted_final.load(std::sync::atomic::Ordering::Relaxed) {
+                    break;
+                }
+            }
+            // Dropping the tracked stream here closes the upstream connection
+        }
+        ctx_final.record_payload_sizes(max_request_bytes, max_response_bytes);
+
+        // Stream completed - determine outcome and token counts
+        let observed = state_final.lock().unwrap().clone();
+        let openai_usage = observed.usage.clone();
+        let accumulated_content = observed.content.clone();
+
+        // Feed the health tracker with the stream outcome
+        match observed.failure {
+            Some(ref error) => {
+                warn!(
+                    model = %model_for_metrics,
+                    provider = provider_name,
+                    error = %error,
+                    "Responses stream reported failure"
+                );
+                health_tracker.record_failure(provider_name, &model_for_metrics);
+                record_provider_failure(provider_name, &model_for_metrics);
+            }
+            None if observed.status.is_some() => {
+                health_tracker.record_success(provider_name, &model_for_metrics);
+            }
+            None => {}
         }
-
-        // Stream completed - determine token counts
-        let openai_usage = usage_final.lock().unwrap().clone();
-        let accumulated_content = content_final.lock().unwrap().clone();
 
         // Prefer OpenAI usage if available, otherwise estimate
-        let (input_tokens, output_tokens) = if openai_usage.input_tokens > 0 || openai_usage.output_tokens > 0 {
+        let (input_tokens, output_tokens) = if observed.has_usage() {
             // Log comparison between estimated and actual
             let input_diff = (openai_usage.input_tokens as i64) - (estimated_input_tokens as i64);
             let input_diff_pct = if estimated_input_tokens > 0 {
@@ -467,33 +644,41 @@ async fn handle_streaming_responses(
         record_tokens("prompt", input_tokens, &model_for_metrics);
         record_tokens("completion", output_tokens, &model_for_metrics);
 
+        let served_model = snapshot::attributed_model(&model_for_metrics, observed.model.as_deref());
+        snapshots_final.observe(&model_for_metrics, &served_model).await;
+
         // ALWAYS track usage in Zion (fire-and-forget)
-        tracker_final.track(user_email_final.clone(), input_tokens, output_tokens, Some(model_for_metrics.clone()));
+        tracker_final.track_user_in_workflow(
+            &user_final,
+            ctx_final.workflow_id.clone(),
+            input_tokens,
+            output_tokens,
+            Some(served_model),
+        );
 
         info!(
             model = %model_for_metrics,
             input_tokens = input_tokens,
             output_tokens = output_tokens,
+            status = %observed.status.as_deref().unwrap_or("unknown"),
             email = %user_email_final,
+            workflow_id = ?ctx_final.workflow_id,
+            upstream_headers = %ctx_final.upstream_headers(),
             "Streaming responses usage tracked"
         );
     };
 
     // Record that we started streaming
-    let duration = start_time.elapsed().as_secs_f64();
+    let duration = ctx.start_time.elapsed().as_secs_f64();
     record_request("streaming", &model, duration);
 
     // Build SSE response
     let body = Body::from_stream(final_stream);
 
-    let response = Response::builder()
-        .status(StatusCode::OK)
-        .header(header::CONTENT_TYPE, "text/event-stream")
-        .header(header::CACHE_CONTROL, "no-cache")
-        .header(header::CONNECTION, "keep-alive")
-        .header("X-Accel-Buffering", "no")
+    let mut response = sse_response(StatusCode::OK)
         .body(body)
         .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to build response: {}", e)))?;
+    ctx.upstream_headers().insert_request_id_header(response.headers_mut());
 
     Ok(response)
 }
@@ -742,4 +927,100 @@ mod tests {
 
         assert_eq!(chunk.event_type, Some("response.output_text.delta".to_string()));
     }
+
+    // ==========================================
+    // Responses stream state tests
+    // ==========================================
+
+    /// Observe a sequence of `data:` payloads
+    fn observe_all(events: &[&str]) -> ResponsesStreamState {
+        let mut state = ResponsesStreamState::default();
+        for json_str in events {
+            state.observe(serde_json::from_str(json_str).unwrap());
+        }
+        state
+    }
+
+    #[test]
+    fn test_stream_state_tool_call_transcript() {
+        let state = observe_all(&[
+            r#"{"type":"response.created","response":{"id":"resp_1","status":"in_progress","usage":null}}"#,
+            r#"{"type":"response.output_item.added","output_index":0,"item":{"type":"function_call","name":"get_weather","arguments":""}}"#,
+            r#"{"type":"response.function_call_arguments.delta","output_index":0,"delta":"{\"city\":"}"#,
+            r#"{"type":"response.function_call_arguments.delta","output_index":0,"delta":"\"Paris\"}"}"#,
+            r#"{"type":"response.function_call_arguments.done","output_index":0,"arguments":"{\"city\":\"Paris\"}"}"#,
+            r#"{"type":"response.output_item.done","output_index":0,"item":{"type":"function_call","arguments":"{\"city\":\"Paris\"}"}}"#,
+            r#"{"type":"response.completed","response":{"id":"resp_1","status":"completed","usage":{"input_tokens":42,"output_tokens":17,"total_tokens":59}}}"#,
+        ]);
+
+        // Arguments are counted once - *.done snapshots are not re-accumulated
+        assert_eq!(state.content, "{\"city\":\"Paris\"}");
+        assert_eq!(state.usage.input_tokens, 42);
+        assert_eq!(state.usage.output_tokens, 17);
+        assert_eq!(state.status.as_deref(), Some("completed"));
+        assert!(state.failure.is_none());
+    }
+
+    #[test]
+    fn test_stream_state_ignores_in_progress_usage() {
+        let state = observe_all(&[
+            r#"{"type":"response.in_progress","response":{"usage":{"input_tokens":1,"output_tokens":1,"total_tokens":2}}}"#,
+            r#"{"type":"response.output_text.delta","delta":"Hi"}"#,
+        ]);
+
+        assert!(!state.has_usage());
+        assert!(state.status.is_none());
+        assert_eq!(state.content, "Hi");
+    }
+
+    #[test]
+    fn test_stream_state_captures_served_model() {
+        let state = observe_all(&[
+            r#"{"type":"response.created","response":{"id":"resp_1","model":"gpt-4o-2024-11-20","usage":null}}"#,
+            r#"{"type":"response.output_text.delta","delta":"Hi"}"#,
+            r#"{"type":"response.completed","response":{"id":"resp_1","usage":{"input_tokens":5,"output_tokens":1,"total_tokens":6}}}"#,
+        ]);
+
+        assert_eq!(state.model.as_deref(), Some("gpt-4o-2024-11-20"));
+    }
+
+    #[test]
+    fn test_stream_state_failed_event() {
+        let state = observe_all(&[
+            r#"{"type":"response.output_text.delta","delta":"Partial"}"#,
+            r#"{"type":"response.failed","response":{"status":"failed","error":{"code":"server_error","message":"The model crashed"},"usage":null}}"#,
+        ]);
+
+        assert_eq!(state.status.as_deref(), Some("failed"));
+        assert_eq!(
+            state.failure.as_deref(),
+            Some("server_error: The model crashed")
+        );
+        assert!(!state.has_usage());
+        assert_eq!(state.content, "Partial");
+    }
+
+    #[test]
+    fn test_stream_state_error_event() {
+        let state = observe_all(&[
+            r#"{"type":"error","code":"rate_limit_exceeded","message":"Slow down","param":null}"#,
+        ]);
+
+        assert_eq!(
+            state.failure.as_deref(),
+            Some("rate_limit_exceeded: Slow down")
+        );
+        assert!(state.status.is_none());
+    }
+
+    #[test]
+    fn test_stream_state_incomplete_keeps_usage() {
+        let state = observe_all(&[
+            r#"{"type":"response.incomplete","response":{"status":"incomplete","incomplete_details":{"reason":"max_output_tokens"},"usage":{"input_tokens":10,"output_tokens":100,"total_tokens":110}}}"#,
+        ]);
+
+        assert_eq!(state.status.as_deref(), Some("incomplete"));
+        assert_eq!(state.usage.output_tokens, 100);
+        assert!(state.failure.is_none());
+    }
 }
@@ -0,0 +1,74 @@
+//! Session deletion endpoint
+//!
+//! Removes the caller's stored native API sessions (the provider/model/tier
+//! binding per `conversation_id`), e.g. to honor a data deletion request. With
+//! `?mode=redact` only the stored conversation history is wiped; the sessions,
+//! their timestamps and usage totals stay. The admin variant lives at
+//! `DELETE /admin/users/:external_id/sessions`.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{error::AppResult, middleware::auth::AuthenticatedUser, AppState};
+
+/// What a session deletion removes
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeleteMode {
+    /// The sessions and their history
+    #[default]
+    Delete,
+    /// Only the history (message content)
+    Redact,
+}
+
+/// Query parameters for session deletion
+#[derive(Debug, Default, Deserialize)]
+pub struct DeleteSessionsQuery {
+    #[serde(default)]
+    pub mode: DeleteMode,
+}
+
+/// Response for session deletion
+#[derive(Debug, Serialize)]
+pub struct SessionsDeletedResponse {
+    pub external_id: String,
+    /// Number of sessions removed (0 when there was nothing left to delete)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted: Option<usize>,
+    /// Number of session histories wiped, with `mode=redact`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redacted: Option<usize>,
+}
+
+/// Delete or redact all of a user's sessions
+pub async fn remove_sessions(
+    state: &AppState,
+    external_id: String,
+    mode: DeleteMode,
+) -> AppResult<SessionsDeletedResponse> {
+    let sessions = &state.session_manager;
+    let (deleted, redacted) = match mode {
+        DeleteMode::Delete => (Some(sessions.delete_for_user(&external_id).await?), None),
+        DeleteMode::Redact => (None, Some(sessions.redact_for_user(&external_id).await?)),
+    };
+    Ok(SessionsDeletedResponse {
+        external_id,
+        deleted,
+        redacted,
+    })
+}
+
+/// DELETE /v1/sessions - delete (or redact) all of the caller's sessions
+pub async fn delete_sessions(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Query(query): Query<DeleteSessionsQuery>,
+) -> AppResult<Json<SessionsDeletedResponse>> {
+    Ok(Json(remove_sessions(&state, user.external_id, query.mode).await?))
+}
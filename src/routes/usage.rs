@@ -0,0 +1,80 @@
+//! Usage endpoint
+//!
+//! Lets a caller see their own limits and recent consumption. The `recent`
+//! section comes from Sentinel's local daily aggregates rather than Zion's
+//! reporting API, so it is cheap enough to poll.
+//!
+//! `GET /v1/usage/workflows/{id}` returns what the caller's requests tagged
+//! with that workflow id consumed, from the same local aggregates.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    Extension, Json,
+};
+use serde::Serialize;
+
+use crate::{
+    error::{AppError, AppResult},
+    middleware::auth::AuthenticatedUser,
+    routes::admin::{RecentUsageQuery, DEFAULT_RECENT_USAGE_DAYS},
+    usage::{recent::UsageCounts, validate_workflow_id, RecentUsage},
+    zion::UserLimit,
+    AppState,
+};
+
+/// Response for GET /v1/usage
+#[derive(Debug, Serialize)]
+pub struct UsageResponse {
+    /// Current limits and usage as reported by Zion
+    pub limits: Vec<UserLimit>,
+    /// Local daily aggregates, newest day first
+    pub recent: RecentUsage,
+}
+
+/// GET /v1/usage - the caller's limits and recent usage
+pub async fn get_usage(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Query(query): Query<RecentUsageQuery>,
+) -> AppResult<Json<UsageResponse>> {
+    let limits = state
+        .subscription_cache
+        .get_user_limits(&user.external_id)
+        .await?;
+    let recent = state
+        .batching_tracker
+        .recent_usage()
+        .recent(&user.external_id, query.days.unwrap_or(DEFAULT_RECENT_USAGE_DAYS))
+        .await?;
+
+    Ok(Json(UsageResponse { limits, recent }))
+}
+
+/// Response for GET /v1/usage/workflows/{id}
+#[derive(Debug, Serialize)]
+pub struct WorkflowUsageResponse {
+    pub workflow_id: String,
+    /// Accumulated over the caller's requests tagged with the workflow
+    #[serde(flatten)]
+    pub usage: UsageCounts,
+}
+
+/// GET /v1/usage/workflows/{id} - the caller's accumulated usage in a workflow
+///
+/// Unknown workflows report zero usage, like days without requests.
+pub async fn get_workflow_usage(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(workflow_id): Path<String>,
+) -> AppResult<Json<WorkflowUsageResponse>> {
+    validate_workflow_id(&workflow_id).map_err(AppError::BadRequest)?;
+    let usage = state
+        .batching_tracker
+        .recent_usage()
+        .workflow(&user.external_id, &workflow_id)
+        .await?;
+
+    Ok(Json(WorkflowUsageResponse { workflow_id, usage }))
+}
@@ -3,12 +3,72 @@
 //! Provides buffering and parsing helpers for processing SSE streams
 //! from AI providers like OpenAI.
 
+use std::borrow::Cow;
+
+use axum::http::{header, response::Builder, HeaderMap, HeaderValue, Response, StatusCode};
+use bytes::Bytes;
+use serde_json::json;
+
+/// Default cap on a single buffered SSE line (1 MiB)
+pub const DEFAULT_MAX_LINE_BYTES: usize = 1024 * 1024;
+
+/// `Content-Type` of every event stream Sentinel sends
+pub const SSE_CONTENT_TYPE: &str = "text/event-stream; charset=utf-8";
+
+/// Set the headers an event stream needs to reach the client unbuffered
+///
+/// `X-Accel-Buffering: no` stops nginx from holding events back.
+pub fn insert_sse_headers(headers: &mut HeaderMap) {
+    headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(SSE_CONTENT_TYPE));
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    headers.insert(header::CONNECTION, HeaderValue::from_static("keep-alive"));
+    headers.insert("x-accel-buffering", HeaderValue::from_static("no"));
+}
+
+/// Response builder for an event stream, with the SSE headers already set
+pub fn sse_response(status: StatusCode) -> Builder {
+    let mut builder = Response::builder().status(status);
+    if let Some(headers) = builder.headers_mut() {
+        insert_sse_headers(headers);
+    }
+    builder
+}
+
+/// Errors raised while splitting an upstream SSE stream into lines
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SseBufferError {
+    /// A line grew past the configured limit without a terminating newline
+    #[error("Upstream SSE line exceeded {max_line_bytes} bytes without a newline")]
+    LineTooLong { max_line_bytes: usize },
+}
+
+impl SseBufferError {
+    /// Client-facing SSE error event for an aborted stream
+    pub fn to_event(&self) -> Bytes {
+        let event = json!({
+            "error": {
+                "message": self.to_string(),
+                "type": "upstream_error",
+                "code": "sse_line_too_long",
+                "retryable": false,
+            }
+        });
+        Bytes::from(format!("data: {}\n\n", event))
+    }
+}
+
 /// Buffer for accumulating incomplete SSE lines across chunk boundaries.
 ///
 /// SSE data arrives as byte chunks that may not align with line boundaries.
 /// This buffer accumulates incomplete lines until a complete line (ending with \n)
 /// is available for processing.
 ///
+/// Bytes are kept raw until a line completes, so multi-byte UTF-8 characters
+/// split across chunks survive intact. Complete lines are consumed through a
+/// cursor and the incomplete tail is shifted once per feed, keeping the work
+/// linear in the bytes received. A line longer than the configured limit
+/// fails with [`SseBufferError::LineTooLong`] instead of growing unbounded.
+///
 /// # Example
 /// ```
 /// use sentinel::streaming::SseLineBuffer;
@@ -16,24 +76,41 @@
 /// let mut buffer = SseLineBuffer::new();
 ///
 /// // First chunk contains partial line
-/// let lines1 = buffer.feed(b"data: {\"content\":\"hel");
+/// let lines1 = buffer.feed(b"data: {\"content\":\"hel").unwrap();
 /// assert!(lines1.is_empty()); // No complete lines yet
 ///
 /// // Second chunk completes the line
-/// let lines2 = buffer.feed(b"lo\"}\n");
+/// let lines2 = buffer.feed(b"lo\"}\n").unwrap();
 /// assert_eq!(lines2, vec!["data: {\"content\":\"hello\"}"]);
 /// ```
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct SseLineBuffer {
-    /// Accumulated incomplete line data
-    incomplete: String,
+    /// Bytes of the incomplete trailing line
+    pending: Vec<u8>,
+    /// Prefix of `pending` already known to contain no newline
+    scanned: usize,
+    /// Largest line accepted before the stream is rejected
+    max_line_bytes: usize,
+}
+
+impl Default for SseLineBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SseLineBuffer {
-    /// Create a new empty buffer
+    /// Create a new empty buffer with the default line limit
     pub fn new() -> Self {
+        Self::with_max_line_bytes(DEFAULT_MAX_LINE_BYTES)
+    }
+
+    /// Create a new empty buffer rejecting lines longer than `max_line_bytes`
+    pub fn with_max_line_bytes(max_line_bytes: usize) -> Self {
         Self {
-            incomplete: String::new(),
+            pending: Vec::new(),
+            scanned: 0,
+            max_line_bytes,
         }
     }
 
@@ -47,46 +124,80 @@ impl SseLineBuffer {
     /// * `bytes` - Raw bytes from the SSE stream chunk
     ///
     /// # Returns
-    /// Vector of complete lines (without trailing newlines)
-    pub fn feed(&mut self, bytes: &[u8]) -> Vec<String> {
-        // Convert bytes to string, replacing invalid UTF-8 with replacement char
-        let text = String::from_utf8_lossy(bytes);
-
-        // Append to any incomplete data from previous chunk
-        self.incomplete.push_str(&text);
+    /// Vector of complete lines (without trailing newlines), or
+    /// `LineTooLong` once a line exceeds the limit. The buffer is cleared on
+    /// error; callers should abort the stream.
+    pub fn feed(&mut self, bytes: &[u8]) -> Result<Vec<String>, SseBufferError> {
+        self.pending.extend_from_slice(bytes);
 
-        // Split into lines and collect complete ones
         let mut complete_lines = Vec::new();
+        let mut line_start = 0;
+        let mut cursor = self.scanned;
 
         // Find complete lines (those followed by \n)
-        while let Some(newline_pos) = self.incomplete.find('\n') {
-            let line = self.incomplete[..newline_pos].to_string();
-            self.incomplete = self.incomplete[newline_pos + 1..].to_string();
+        while let Some(offset) = self.pending[cursor..].iter().position(|&b| b == b'\n') {
+            let newline_pos = cursor + offset;
+            let line = &self.pending[line_start..newline_pos];
+            if line.len() > self.max_line_bytes {
+                return Err(self.overflow());
+            }
 
             // Skip empty lines (SSE uses double newlines as separators)
             if !line.is_empty() {
-                complete_lines.push(line);
+                // Replace invalid UTF-8 with the replacement char
+                complete_lines.push(String::from_utf8_lossy(line).into_owned());
             }
+            line_start = newline_pos + 1;
+            cursor = line_start;
         }
 
-        complete_lines
+        // Shift the incomplete tail to the front once, rather than per line
+        self.pending.drain(..line_start);
+        self.scanned = self.pending.len();
+
+        if self.pending.len() > self.max_line_bytes {
+            return Err(self.overflow());
+        }
+
+        Ok(complete_lines)
     }
 
     /// Check if there's any incomplete data remaining in the buffer.
     ///
     /// Useful for detecting truncated streams at end of response.
     pub fn has_incomplete(&self) -> bool {
-        !self.incomplete.is_empty()
+        !self.pending.is_empty()
     }
 
     /// Get any remaining incomplete data.
     ///
     /// Call this at end of stream to check for truncated data.
-    pub fn remaining(&self) -> &str {
-        &self.incomplete
+    pub fn remaining(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.pending)
+    }
+
+    /// Drop buffered data and build the overflow error
+    fn overflow(&mut self) -> SseBufferError {
+        self.pending = Vec::new();
+        self.scanned = 0;
+        SseBufferError::LineTooLong {
+            max_line_bytes: self.max_line_bytes,
+        }
     }
 }
 
+/// Re-frame complete lines from [`SseLineBuffer::feed`] for the client.
+///
+/// Lines are written unchanged; a `data:` line ends its event.
+pub fn encode_lines<'a>(lines: impl IntoIterator<Item = &'a String>) -> Bytes {
+    let mut output = String::new();
+    for line in lines {
+        output.push_str(line);
+        output.push_str(if line.starts_with("data:") { "\n\n" } else { "\n" });
+    }
+    Bytes::from(output)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -94,7 +205,7 @@ mod tests {
     #[test]
     fn test_empty_input() {
         let mut buffer = SseLineBuffer::new();
-        let lines = buffer.feed(b"");
+        let lines = buffer.feed(b"").unwrap();
         assert!(lines.is_empty());
         assert!(!buffer.has_incomplete());
     }
@@ -102,7 +213,7 @@ mod tests {
     #[test]
     fn test_single_complete_line() {
         let mut buffer = SseLineBuffer::new();
-        let lines = buffer.feed(b"data: hello\n");
+        let lines = buffer.feed(b"data: hello\n").unwrap();
         assert_eq!(lines, vec!["data: hello"]);
         assert!(!buffer.has_incomplete());
     }
@@ -110,7 +221,7 @@ mod tests {
     #[test]
     fn test_multiple_complete_lines() {
         let mut buffer = SseLineBuffer::new();
-        let lines = buffer.feed(b"data: first\ndata: second\n");
+        let lines = buffer.feed(b"data: first\ndata: second\n").unwrap();
         assert_eq!(lines, vec!["data: first", "data: second"]);
         assert!(!buffer.has_incomplete());
     }
@@ -118,7 +229,7 @@ mod tests {
     #[test]
     fn test_incomplete_line_buffered() {
         let mut buffer = SseLineBuffer::new();
-        let lines = buffer.feed(b"data: incomp");
+        let lines = buffer.feed(b"data: incomp").unwrap();
         assert!(lines.is_empty());
         assert!(buffer.has_incomplete());
         assert_eq!(buffer.remaining(), "data: incomp");
@@ -129,12 +240,12 @@ mod tests {
         let mut buffer = SseLineBuffer::new();
 
         // First chunk: partial line
-        let lines1 = buffer.feed(b"data: {\"content\":\"hel");
+        let lines1 = buffer.feed(b"data: {\"content\":\"hel").unwrap();
         assert!(lines1.is_empty());
         assert!(buffer.has_incomplete());
 
         // Second chunk: completes the line
-        let lines2 = buffer.feed(b"lo\"}\n");
+        let lines2 = buffer.feed(b"lo\"}\n").unwrap();
         assert_eq!(lines2, vec!["data: {\"content\":\"hello\"}"]);
         assert!(!buffer.has_incomplete());
     }
@@ -144,11 +255,11 @@ mod tests {
         let mut buffer = SseLineBuffer::new();
 
         // Chunk ends right before newline
-        let lines1 = buffer.feed(b"data: test");
+        let lines1 = buffer.feed(b"data: test").unwrap();
         assert!(lines1.is_empty());
 
         // Next chunk starts with newline
-        let lines2 = buffer.feed(b"\ndata: next\n");
+        let lines2 = buffer.feed(b"\ndata: next\n").unwrap();
         assert_eq!(lines2, vec!["data: test", "data: next"]);
     }
 
@@ -156,7 +267,7 @@ mod tests {
     fn test_sse_double_newline_separator() {
         let mut buffer = SseLineBuffer::new();
         // SSE uses \n\n between events - empty lines should be skipped
-        let lines = buffer.feed(b"data: first\n\ndata: second\n");
+        let lines = buffer.feed(b"data: first\n\ndata: second\n").unwrap();
         assert_eq!(lines, vec!["data: first", "data: second"]);
     }
 
@@ -166,7 +277,7 @@ mod tests {
 
         // Simulate realistic OpenAI SSE chunks
         let chunk1 = b"data: {\"choices\":[{\"delta\":{\"content\":\"Hello\"}}]}\n\n";
-        let lines1 = buffer.feed(chunk1);
+        let lines1 = buffer.feed(chunk1).unwrap();
         assert_eq!(
             lines1,
             vec!["data: {\"choices\":[{\"delta\":{\"content\":\"Hello\"}}]}"]
@@ -174,11 +285,11 @@ mod tests {
 
         // Split chunk
         let chunk2 = b"data: {\"choices\":[{\"delta\":{\"con";
-        let lines2 = buffer.feed(chunk2);
+        let lines2 = buffer.feed(chunk2).unwrap();
         assert!(lines2.is_empty());
 
         let chunk3 = b"tent\":\" world\"}}]}\n\n";
-        let lines3 = buffer.feed(chunk3);
+        let lines3 = buffer.feed(chunk3).unwrap();
         assert_eq!(
             lines3,
             vec!["data: {\"choices\":[{\"delta\":{\"content\":\" world\"}}]}"]
@@ -186,7 +297,7 @@ mod tests {
 
         // Done marker
         let chunk4 = b"data: [DONE]\n\n";
-        let lines4 = buffer.feed(chunk4);
+        let lines4 = buffer.feed(chunk4).unwrap();
         assert_eq!(lines4, vec!["data: [DONE]"]);
     }
 
@@ -194,7 +305,7 @@ mod tests {
     fn test_carriage_return_handling() {
         let mut buffer = SseLineBuffer::new();
         // Some systems send \r\n - we only split on \n, \r remains in line
-        let lines = buffer.feed(b"data: test\r\n");
+        let lines = buffer.feed(b"data: test\r\n").unwrap();
         assert_eq!(lines, vec!["data: test\r"]);
     }
 
@@ -203,9 +314,65 @@ mod tests {
         let mut buffer = SseLineBuffer::new();
         // Invalid UTF-8 bytes should be replaced with replacement character
         let invalid_utf8 = b"data: hello \xff world\n";
-        let lines = buffer.feed(invalid_utf8);
+        let lines = buffer.feed(invalid_utf8).unwrap();
         assert_eq!(lines.len(), 1);
         assert!(lines[0].contains("hello"));
         assert!(lines[0].contains("world"));
     }
+
+    #[test]
+    fn test_multibyte_character_split_across_chunks() {
+        let mut buffer = SseLineBuffer::new();
+        let text = "data: caf\u{e9}\n".as_bytes();
+        // Split inside the two-byte 'é'
+        assert!(buffer.feed(&text[..10]).unwrap().is_empty());
+        assert_eq!(buffer.feed(&text[10..]).unwrap(), vec!["data: caf\u{e9}"]);
+    }
+
+    #[test]
+    fn test_newline_free_stream_rejected_at_limit() {
+        let mut buffer = SseLineBuffer::new();
+        let chunk = vec![b'x'; 64 * 1024];
+
+        // 10 MB without a newline, in 64 KiB chunks
+        let results: Vec<_> = (0..160).map(|_| buffer.feed(&chunk)).collect();
+        let first_error = results.iter().position(Result::is_err).unwrap();
+
+        // Rejected on the first chunk that crosses 1 MiB
+        assert_eq!(first_error, DEFAULT_MAX_LINE_BYTES / chunk.len());
+        assert_eq!(
+            results[first_error],
+            Err(SseBufferError::LineTooLong {
+                max_line_bytes: DEFAULT_MAX_LINE_BYTES
+            })
+        );
+        assert!(results[..first_error].iter().all(|r| r.as_ref().unwrap().is_empty()));
+        // Buffered data never exceeds the limit plus one chunk
+        assert!(buffer.remaining().len() <= DEFAULT_MAX_LINE_BYTES + chunk.len());
+    }
+
+    #[test]
+    fn test_complete_line_over_limit_rejected() {
+        let mut buffer = SseLineBuffer::with_max_line_bytes(8);
+        assert_eq!(buffer.feed(b"data: ok\n").unwrap(), vec!["data: ok"]);
+        assert!(buffer.feed(b"data: too long\n").is_err());
+    }
+
+    #[test]
+    fn test_error_event_format() {
+        let err = SseBufferError::LineTooLong { max_line_bytes: 8 };
+        let event = String::from_utf8(err.to_event().to_vec()).unwrap();
+        assert!(event.starts_with("data: {"));
+        assert!(event.ends_with("\n\n"));
+        assert!(event.contains("\"code\":\"sse_line_too_long\""));
+        assert!(event.contains("\"retryable\":false"));
+    }
+
+    #[test]
+    fn test_encode_lines_round_trips() {
+        let mut buffer = SseLineBuffer::new();
+        let input = b"event: message\ndata: {\"a\":1}\n\ndata: [DONE]\n\n";
+        let lines = buffer.feed(input).unwrap();
+        assert_eq!(encode_lines(&lines), Bytes::from_static(input));
+    }
 }
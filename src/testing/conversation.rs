@@ -0,0 +1,112 @@
+//! Long native conversations
+//!
+//! `long_conversation(n)` builds a deterministic native chat request with `n`
+//! messages that goes through every translation path: a system prompt, plain
+//! and multimodal user turns, assistant tool calls and their results. Tool
+//! call ids repeat every few turns with a different function name, so the
+//! name looked up for a tool result must come from the nearest preceding call.
+
+use serde_json::{json, Value};
+
+use crate::native::request::ChatCompletionRequest;
+
+/// Distinct tool call ids before they repeat
+const TOOL_CALL_IDS: usize = 7;
+
+/// Native chat request with `messages` messages (at least 1)
+pub fn long_conversation(messages: usize) -> ChatCompletionRequest {
+    let mut history = vec![json!({
+        "role": "system",
+        "content": "You are a careful assistant. Use tools when they help."
+    })];
+    let mut turn = 0;
+    while history.len() < messages {
+        history.extend(turn_messages(turn));
+        turn += 1;
+    }
+    history.truncate(messages.max(1));
+    // Never end on a tool call whose result was cut off
+    if history
+        .last()
+        .is_some_and(|m| m.get("tool_calls").is_some())
+    {
+        history.pop();
+        history.push(json!({"role": "user", "content": "Continue."}));
+    }
+
+    serde_json::from_value(json!({
+        "tier": "complex",
+        "messages": history,
+        "temperature": 0.4,
+        "max_tokens": 1024,
+        "stop": ["\n\nUser:"],
+        "tools": [{
+            "type": "function",
+            "function": {
+                "name": "lookup_0",
+                "description": "Look up a record",
+                "parameters": {
+                    "type": "object",
+                    "properties": {"key": {"type": "string"}},
+                    "required": ["key"]
+                }
+            }
+        }],
+        "tool_choice": "auto"
+    }))
+    .expect("fixture is a valid native request")
+}
+
+/// One turn: a question, a tool call and its result, an image, and an answer
+fn turn_messages(turn: usize) -> [Value; 5] {
+    let call_id = format!("call_{}", turn % TOOL_CALL_IDS);
+    let function = format!("lookup_{}", turn);
+    [
+        json!({
+            "role": "user",
+            "content": format!("Question {}: what does record {} say about \"quotes\" and ümlauts?", turn, turn)
+        }),
+        json!({
+            "role": "assistant",
+            "content": "",
+            "tool_calls": [{
+                "id": call_id,
+                "type": "function",
+                "function": {"name": function, "arguments": {"key": format!("record-{}", turn)}}
+            }]
+        }),
+        json!({
+            "role": "tool",
+            "tool_call_id": call_id,
+            "content": format!("Record {}: {}", turn, "lorem ipsum dolor sit amet ".repeat(8))
+        }),
+        json!({
+            "role": "user",
+            "content": [
+                {"type": "text", "text": format!("Compare with this chart ({})", turn)},
+                {"type": "image_url", "image_url": {"url": format!("https://example.com/chart-{}.png", turn), "detail": "low"}}
+            ]
+        }),
+        json!({
+            "role": "assistant",
+            "content": format!("Record {} matches the chart.", turn),
+            "name": "analyst"
+        }),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::native::types::Role;
+
+    #[test]
+    fn test_exact_length_and_no_dangling_tool_call() {
+        for messages in [1, 2, 3, 40, 500] {
+            let request = long_conversation(messages);
+            assert_eq!(request.messages.len(), messages);
+            assert!(request.messages.last().unwrap().tool_calls.is_none());
+        }
+        assert_eq!(long_conversation(500).messages[3].role, Role::Tool);
+    }
+}
@@ -0,0 +1,121 @@
+//! End-to-end test harness
+//!
+//! Wires a full `AppState` (in-memory caches, test batching tracker) to a Zion
+//! stub and an AI provider so the real router can be exercised in-process.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::Router;
+use wiremock::MockServer;
+
+use super::{batch_increment_requests, zion_stub, MockAiProvider};
+use crate::{config::Config, proxy::AiProvider, routes, AppState, BatchingUsageTracker, ZionClient};
+
+/// Build a `Config` pointing at mock Zion and provider URLs
+///
+/// The provider URL should include the `/v1` suffix, matching the real API.
+pub fn test_config(zion_url: &str, openai_url: &str) -> Config {
+    let mut config = Config::for_tests();
+    config.zion.api_url = zion_url.to_string();
+    config.provider.openai_api_url = openai_url.to_string();
+    config
+}
+
+/// Build a test `AppState` for the given config and provider
+///
+/// Uses in-memory caches and the test batching tracker, so no Redis is required.
+/// With `chaos_enabled` the Zion client consults the state's fault rules.
+pub async fn test_state(config: Config, ai_provider: Arc<dyn AiProvider>) -> Arc<AppState> {
+    let zion_client = ZionClient::new(reqwest::Client::new(), &config);
+    #[cfg(feature = "chaos")]
+    let zion_client = if config.server.chaos_enabled {
+        zion_client.with_chaos(Arc::new(crate::chaos::ChaosInjector::new()))
+    } else {
+        zion_client
+    };
+    let zion_client = Arc::new(zion_client);
+    let batching_tracker = Arc::new(BatchingUsageTracker::new_for_testing(zion_client.clone()));
+
+    Arc::new(AppState::new_for_testing(config, zion_client, ai_provider, batching_tracker).await)
+}
+
+/// Complete in-process test environment
+///
+/// # Example
+///
+/// ```ignore
+/// let harness = TestHarness::new().await;
+/// harness.provider.push_reply(
+///     MockEndpoint::ChatCompletions,
+///     MockReply::chat_completion("gpt-4o-mini", "Hello!", 10, 5),
+/// );
+///
+/// let server = axum_test::TestServer::new(harness.router()).unwrap();
+/// // ... send requests with `Bearer {constants::TEST_JWT_TOKEN}` ...
+///
+/// let requests = harness.wait_for_batch_requests(1, Duration::from_secs(2)).await;
+/// ```
+pub struct TestHarness {
+    pub state: Arc<AppState>,
+    pub provider: Arc<MockAiProvider>,
+    pub zion: MockServer,
+}
+
+impl TestHarness {
+    /// Create a harness with a fresh `MockAiProvider` and `zion_stub()`
+    pub async fn new() -> Self {
+        Self::with_provider(Arc::new(MockAiProvider::new())).await
+    }
+
+    /// Create a harness around a preconfigured `MockAiProvider`
+    pub async fn with_provider(provider: Arc<MockAiProvider>) -> Self {
+        Self::with_config(provider, |_| {}).await
+    }
+
+    /// Create a harness, adjusting the default test `Config` before the state is built
+    pub async fn with_config(
+        provider: Arc<MockAiProvider>,
+        configure: impl FnOnce(&mut Config),
+    ) -> Self {
+        let zion = zion_stub().await;
+        let mut config = test_config(&zion.uri(), "http://mock-provider.invalid/v1");
+        configure(&mut config);
+        let state = test_state(config, provider.clone()).await;
+
+        Self { state, provider, zion }
+    }
+
+    /// Build the full application router
+    pub fn router(&self) -> Router {
+        routes::create_router(self.state.clone())
+    }
+
+    /// Wait for batch-increment requests to arrive at the Zion stub
+    ///
+    /// Polls until at least `min_count` requests arrive or the timeout elapses,
+    /// returning whatever was received.
+    pub async fn wait_for_batch_requests(
+        &self,
+        min_count: usize,
+        timeout: Duration,
+    ) -> Vec<wiremock::Request> {
+        wait_for_batch_requests(&self.zion, min_count, timeout).await
+    }
+}
+
+/// Wait for batch-increment requests on any Zion mock server
+pub async fn wait_for_batch_requests(
+    server: &MockServer,
+    min_count: usize,
+    timeout: Duration,
+) -> Vec<wiremock::Request> {
+    let start = Instant::now();
+    loop {
+        let requests = batch_increment_requests(server).await;
+        if requests.len() >= min_count || start.elapsed() > timeout {
+            return requests;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
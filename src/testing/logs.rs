@@ -0,0 +1,53 @@
+//! Captured log output
+//!
+//! `capture_logs()` installs a reloadable subscriber that writes formatted
+//! events to memory as the current thread's default, so tests can change the
+//! filter through `LogLevel` (or `/admin/log-level`) and check which events
+//! were written.
+
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use tracing::subscriber::DefaultGuard;
+use tracing_subscriber::{prelude::*, EnvFilter};
+
+use crate::log_level::{self, LogLevel};
+
+/// Formatted events written so far
+#[derive(Debug, Clone, Default)]
+pub struct CapturedLogs(Arc<Mutex<Vec<u8>>>);
+
+impl CapturedLogs {
+    /// Everything written so far
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.0.lock().unwrap()).into_owned()
+    }
+}
+
+impl io::Write for CapturedLogs {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Capture this thread's events, filtered by a reloadable `startup` filter
+///
+/// Events are captured until the guard is dropped. Use a current-thread
+/// runtime (the `#[tokio::test]` default) so spawned tasks log here too.
+pub fn capture_logs(startup: &str) -> (Arc<LogLevel>, CapturedLogs, DefaultGuard) {
+    let (filter, log_level) = log_level::reloadable(EnvFilter::new(startup));
+    let captured = CapturedLogs::default();
+    let writer = captured.clone();
+    let subscriber = tracing_subscriber::registry().with(filter).with(
+        tracing_subscriber::fmt::layer()
+            .with_ansi(false)
+            .with_writer(move || writer.clone()),
+    );
+    let guard = tracing::subscriber::set_default(subscriber);
+    (Arc::new(log_level), captured, guard)
+}
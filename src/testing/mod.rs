@@ -0,0 +1,47 @@
+//! Test utilities for crates embedding Sentinel
+//!
+//! Only compiled with the `test-utils` feature. Provides everything needed to
+//! run Sentinel end-to-end in a test without network access to real services:
+//! - `MockAiProvider` - in-process `AiProvider` with programmable replies
+//! - `zion_stub()` - wiremock Zion with profile/limits/batch endpoints pre-mocked
+//! - `jwks::sign_token()` - RS256 tokens verifiable with `jwks::jwks_body()` (`ZION_JWKS_URL`)
+//! - `TestHarness` - ready-to-use `AppState` wired to the two mocks above
+//! - `TestClock` - manually advanced clock for time-dependent components
+//! - `capture_logs()` - in-memory log output with a reloadable filter
+//! - `StreamScript` - deterministic streaming chat completions with configurable chunking
+//! - `long_conversation()` - large native chat requests covering every translation path
+
+pub mod conversation;
+pub mod harness;
+pub mod jwks;
+pub mod logs;
+pub mod provider;
+pub mod stream;
+pub mod zion;
+
+pub use crate::clock::TestClock;
+pub use conversation::long_conversation;
+pub use harness::{test_config, test_state, wait_for_batch_requests, TestHarness};
+pub use logs::{capture_logs, CapturedLogs};
+pub use provider::{MockAiProvider, MockEndpoint, MockReply, RecordedRequest};
+pub use stream::{sse_events, Chunking, StreamScript};
+pub use zion::{
+    batch_increment_requests, extract_token_counts, parse_batch_payload, zion_stub,
+    STUB_PRIORITY,
+};
+
+/// Test constants shared by the stubs and harness
+pub mod constants {
+    /// Default test API key for Zion
+    pub const TEST_ZION_API_KEY: &str = "test-zion-api-key";
+    /// Default test API key for OpenAI
+    pub const TEST_OPENAI_API_KEY: &str = "test-openai-api-key";
+    /// Default test JWT token
+    pub const TEST_JWT_TOKEN: &str = "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9.eyJzdWIiOiJ1c2VyXzEyMyIsImVtYWlsIjoidGVzdEB0ZXN0LmNvbSJ9.test";
+    /// Test user ID
+    pub const TEST_USER_ID: &str = "user_123";
+    /// Test external ID
+    pub const TEST_EXTERNAL_ID: &str = "ext_123";
+    /// Test email
+    pub const TEST_EMAIL: &str = "test@test.com";
+}
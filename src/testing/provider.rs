@@ -0,0 +1,429 @@
+//! Programmable in-process AI provider
+//!
+//! `MockAiProvider` implements `AiProvider` without touching the network.
+//! Replies are queued per endpoint and consumed in order; the last reply in a
+//! queue is reused once everything before it has been consumed, so simple
+//! tests only need to configure a single response.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use axum::body::Body;
+use axum::http::{header, HeaderMap, Method, Response, StatusCode};
+use bytes::Bytes;
+use futures::StreamExt;
+use serde_json::json;
+
+use crate::error::{AppError, AppResult};
+use crate::proxy::{AiProvider, ByteStream};
+
+use super::stream::StreamScript;
+
+/// Provider endpoint a canned reply applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MockEndpoint {
+    ChatCompletions,
+    Completions,
+    Embeddings,
+    Models,
+    Responses,
+    Passthrough,
+}
+
+/// Canned reply returned by `MockAiProvider`
+#[derive(Debug, Clone)]
+pub enum MockReply {
+    /// Non-streaming JSON body
+    Json(serde_json::Value),
+    /// Streaming body, each element is yielded as one chunk
+    Stream(Vec<Bytes>),
+    /// Upstream failure, surfaced as `AppError::UpstreamError`
+    Error { status: u16, message: String },
+}
+
+impl MockReply {
+    /// Build a stream reply from SSE `data:` payloads, terminated by `[DONE]`
+    pub fn sse(events: Vec<serde_json::Value>) -> Self {
+        let mut chunks: Vec<Bytes> = events
+            .into_iter()
+            .map(|event| Bytes::from(format!("data: {}\n\n", event)))
+            .collect();
+        chunks.push(Bytes::from_static(b"data: [DONE]\n\n"));
+        MockReply::Stream(chunks)
+    }
+
+    /// Chat completion response with the given content and usage
+    pub fn chat_completion(
+        model: &str,
+        content: &str,
+        prompt_tokens: u32,
+        completion_tokens: u32,
+    ) -> Self {
+        MockReply::Json(json!({
+            "id": "chatcmpl-mock",
+            "object": "chat.completion",
+            "created": 1700000000,
+            "model": model,
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": content},
+                "finish_reason": "stop"
+            }],
+            "usage": {
+                "prompt_tokens": prompt_tokens,
+                "completion_tokens": completion_tokens,
+                "total_tokens": prompt_tokens + completion_tokens
+            }
+        }))
+    }
+
+    /// Streaming chat completion, one word per chunk
+    ///
+    /// When `usage` is set, a final usage-only chunk is emitted the same way
+    /// OpenAI does with `stream_options.include_usage`. Use [`StreamScript`]
+    /// for tool calls, other finish reasons or other chunking.
+    pub fn chat_stream(model: &str, content: &str, usage: Option<(u32, u32)>) -> Self {
+        let script = StreamScript::new(model).text(content);
+        match usage {
+            Some((prompt_tokens, completion_tokens)) => {
+                script.usage(prompt_tokens, completion_tokens).reply()
+            }
+            None => script.reply(),
+        }
+    }
+}
+
+/// A request captured by `MockAiProvider`
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub endpoint: MockEndpoint,
+    pub body: serde_json::Value,
+}
+
+/// In-process AI provider with programmable canned replies
+///
+/// # Example
+///
+/// ```ignore
+/// let provider = MockAiProvider::new().with_reply(
+///     MockEndpoint::ChatCompletions,
+///     MockReply::chat_completion("gpt-4o-mini", "Hello!", 10, 5),
+/// );
+/// ```
+#[derive(Default)]
+pub struct MockAiProvider {
+    replies: Mutex<HashMap<MockEndpoint, VecDeque<MockReply>>>,
+    requests: Mutex<Vec<RecordedRequest>>,
+    /// Pause before each chunk of a stream reply
+    chunk_delay: Option<Duration>,
+    /// Provider name, `mock` unless set
+    name: Option<&'static str>,
+}
+
+impl MockAiProvider {
+    /// Create a provider with no replies configured
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builder-style variant of `push_reply`
+    pub fn with_reply(self, endpoint: MockEndpoint, reply: MockReply) -> Self {
+        self.push_reply(endpoint, reply);
+        self
+    }
+
+    /// Pause before every chunk of stream replies, to keep streams open
+    pub fn with_chunk_delay(mut self, delay: Duration) -> Self {
+        self.chunk_delay = Some(delay);
+        self
+    }
+
+    /// Report a different provider name, to tell several mocks apart
+    pub fn with_name(mut self, name: &'static str) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// Queue a reply for an endpoint
+    pub fn push_reply(&self, endpoint: MockEndpoint, reply: MockReply) {
+        self.replies
+            .lock()
+            .unwrap()
+            .entry(endpoint)
+            .or_default()
+            .push_back(reply);
+    }
+
+    /// All requests received so far, in arrival order
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Request bodies received for a single endpoint
+    pub fn requests_for(&self, endpoint: MockEndpoint) -> Vec<serde_json::Value> {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|r| r.endpoint == endpoint)
+            .map(|r| r.body.clone())
+            .collect()
+    }
+
+    fn record(&self, endpoint: MockEndpoint, body: serde_json::Value) {
+        self.requests
+            .lock()
+            .unwrap()
+            .push(RecordedRequest { endpoint, body });
+    }
+
+    fn next_reply(&self, endpoint: MockEndpoint) -> AppResult<MockReply> {
+        let mut replies = self.replies.lock().unwrap();
+        let queue = replies.get_mut(&endpoint).filter(|q| !q.is_empty()).ok_or_else(|| {
+            AppError::UpstreamError(format!(
+                "Mock provider has no reply configured for {:?}",
+                endpoint
+            ))
+        })?;
+
+        if queue.len() > 1 {
+            Ok(queue.pop_front().unwrap())
+        } else {
+            Ok(queue.front().unwrap().clone())
+        }
+    }
+
+    fn json_reply(
+        &self,
+        endpoint: MockEndpoint,
+        request: serde_json::Value,
+    ) -> AppResult<serde_json::Value> {
+        self.record(endpoint, request);
+        match self.next_reply(endpoint)? {
+            MockReply::Json(value) => Ok(value),
+            MockReply::Error { status, message } => Err(upstream_error(status, &message)),
+            MockReply::Stream(_) => Err(AppError::UpstreamError(format!(
+                "Mock provider has a stream reply configured for non-streaming {:?}",
+                endpoint
+            ))),
+        }
+    }
+
+    fn stream_reply(
+        &self,
+        endpoint: MockEndpoint,
+        request: serde_json::Value,
+    ) -> AppResult<ByteStream> {
+        self.record(endpoint, request);
+        match self.next_reply(endpoint)? {
+            MockReply::Stream(chunks) => {
+                let delay = self.chunk_delay;
+                Ok(Box::pin(futures::stream::iter(chunks).then(move |chunk| async move {
+                    if let Some(delay) = delay {
+                        tokio::time::sleep(delay).await;
+                    }
+                    Ok(chunk)
+                })))
+            }
+            MockReply::Error { status, message } => Err(upstream_error(status, &message)),
+            MockReply::Json(_) => Err(AppError::UpstreamError(format!(
+                "Mock provider has a JSON reply configured for streaming {:?}",
+                endpoint
+            ))),
+        }
+    }
+}
+
+fn upstream_error(status: u16, message: &str) -> AppError {
+    AppError::UpstreamError(format!("Mock provider error {}: {}", status, message))
+}
+
+#[async_trait]
+impl AiProvider for MockAiProvider {
+    fn name(&self) -> &'static str {
+        self.name.unwrap_or("mock")
+    }
+
+    async fn chat_completions(
+        &self,
+        request: serde_json::Value,
+        _incoming_headers: &HeaderMap,
+    ) -> AppResult<serde_json::Value> {
+        self.json_reply(MockEndpoint::ChatCompletions, request)
+    }
+
+    async fn chat_completions_stream(
+        &self,
+        request: serde_json::Value,
+        _incoming_headers: &HeaderMap,
+    ) -> AppResult<ByteStream> {
+        self.stream_reply(MockEndpoint::ChatCompletions, request)
+    }
+
+    async fn completions(
+        &self,
+        request: serde_json::Value,
+        _incoming_headers: &HeaderMap,
+    ) -> AppResult<serde_json::Value> {
+        self.json_reply(MockEndpoint::Completions, request)
+    }
+
+    async fn completions_stream(
+        &self,
+        request: serde_json::Value,
+        _incoming_headers: &HeaderMap,
+    ) -> AppResult<ByteStream> {
+        self.stream_reply(MockEndpoint::Completions, request)
+    }
+
+    async fn embeddings(
+        &self,
+        request: serde_json::Value,
+        _incoming_headers: &HeaderMap,
+    ) -> AppResult<serde_json::Value> {
+        self.json_reply(MockEndpoint::Embeddings, request)
+    }
+
+    async fn list_models(&self) -> AppResult<serde_json::Value> {
+        self.json_reply(MockEndpoint::Models, serde_json::Value::Null)
+    }
+
+    async fn get_model(&self, model_id: &str) -> AppResult<serde_json::Value> {
+        self.json_reply(MockEndpoint::Models, json!({ "model": model_id }))
+    }
+
+    async fn responses(
+        &self,
+        request: serde_json::Value,
+        _incoming_headers: &HeaderMap,
+    ) -> AppResult<serde_json::Value> {
+        self.json_reply(MockEndpoint::Responses, request)
+    }
+
+    async fn responses_stream(
+        &self,
+        request: serde_json::Value,
+        _incoming_headers: &HeaderMap,
+    ) -> AppResult<ByteStream> {
+        self.stream_reply(MockEndpoint::Responses, request)
+    }
+
+    async fn forward_raw(
+        &self,
+        method: Method,
+        path: &str,
+        _incoming_headers: HeaderMap,
+        body: Body,
+    ) -> AppResult<Response<Body>> {
+        let bytes = axum::body::to_bytes(body, usize::MAX)
+            .await
+            .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to read body: {}", e)))?;
+        let body_value = serde_json::from_slice(&bytes).unwrap_or(serde_json::Value::Null);
+        self.record(
+            MockEndpoint::Passthrough,
+            json!({ "method": method.as_str(), "path": path, "body": body_value }),
+        );
+
+        // Errors are passed through as responses, mirroring OpenAIProvider::forward_raw
+        let (status, content_type, body) = match self.next_reply(MockEndpoint::Passthrough)? {
+            MockReply::Json(value) => (StatusCode::OK, "application/json", Body::from(value.to_string())),
+            MockReply::Stream(chunks) => (
+                StatusCode::OK,
+                "text/event-stream",
+                Body::from_stream(futures::stream::iter(
+                    chunks.into_iter().map(Ok::<_, std::io::Error>),
+                )),
+            ),
+            MockReply::Error { status, message } => (
+                StatusCode::from_u16(status).unwrap_or(StatusCode::BAD_GATEWAY),
+                "application/json",
+                Body::from(json!({ "error": { "message": message } }).to_string()),
+            ),
+        };
+
+        Response::builder()
+            .status(status)
+            .header(header::CONTENT_TYPE, content_type)
+            .body(body)
+            .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to build response: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_last_reply_is_reused() {
+        let provider = MockAiProvider::new()
+            .with_reply(
+                MockEndpoint::ChatCompletions,
+                MockReply::chat_completion("gpt-4o", "first", 1, 1),
+            )
+            .with_reply(
+                MockEndpoint::ChatCompletions,
+                MockReply::chat_completion("gpt-4o", "second", 1, 1),
+            );
+        let headers = HeaderMap::new();
+
+        let contents: Vec<String> = {
+            let mut out = Vec::new();
+            for _ in 0..3 {
+                let value = provider
+                    .chat_completions(json!({"model": "gpt-4o"}), &headers)
+                    .await
+                    .unwrap();
+                out.push(value["choices"][0]["message"]["content"].as_str().unwrap().to_string());
+            }
+            out
+        };
+
+        assert_eq!(contents, vec!["first", "second", "second"]);
+        assert_eq!(provider.requests_for(MockEndpoint::ChatCompletions).len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_missing_reply_is_upstream_error() {
+        let provider = MockAiProvider::new();
+        let result = provider.embeddings(json!({}), &HeaderMap::new()).await;
+        assert!(matches!(result, Err(AppError::UpstreamError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_error_reply() {
+        let provider = MockAiProvider::new().with_reply(
+            MockEndpoint::ChatCompletions,
+            MockReply::Error { status: 500, message: "boom".to_string() },
+        );
+        let err = provider
+            .chat_completions(json!({}), &HeaderMap::new())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("500"));
+    }
+
+    #[tokio::test]
+    async fn test_chat_stream_ends_with_done_and_usage() {
+        let provider = MockAiProvider::new().with_reply(
+            MockEndpoint::ChatCompletions,
+            MockReply::chat_stream("gpt-4o", "Hello world", Some((7, 2))),
+        );
+        let stream = provider
+            .chat_completions_stream(json!({}), &HeaderMap::new())
+            .await
+            .unwrap();
+        let chunks: Vec<Bytes> = stream.map(|c| c.unwrap()).collect().await;
+        let text: String = chunks
+            .iter()
+            .map(|c| String::from_utf8_lossy(c).into_owned())
+            .collect();
+
+        assert!(text.contains("\"content\":\"Hello\""));
+        assert!(text.contains("\"content\":\" world\""));
+        assert!(text.contains("\"prompt_tokens\":7"));
+        assert!(text.ends_with("data: [DONE]\n\n"));
+    }
+}
@@ -0,0 +1,386 @@
+//! Scripted streaming chat completions
+//!
+//! `StreamScript` describes an upstream chat completion stream (text, tool
+//! calls, finish reason, usage) and renders it three ways: the SSE body for a
+//! wiremock `ResponseTemplate`, a chunked `MockReply::Stream` for
+//! `MockAiProvider`, and the chunk events a client should receive. Sentinel
+//! passes OpenAI-format chunks through unchanged on both `/v1` and
+//! `/native/v1`, so the same events are expected from either API.
+//!
+//! The chunking strategy only changes where the byte stream is cut, never the
+//! events, so a test can assert the same output for every strategy.
+
+use bytes::Bytes;
+use serde_json::{json, Value};
+use wiremock::ResponseTemplate;
+
+use super::provider::MockReply;
+
+/// Chunk id used in every scripted event
+const SCRIPT_ID: &str = "chatcmpl-mock";
+
+/// Characters of tool call arguments per delta
+const ARGUMENT_PIECE_CHARS: usize = 8;
+
+/// Where the scripted byte stream is cut into chunks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Chunking {
+    /// One SSE event per chunk, each content delta carrying one token
+    #[default]
+    PerToken,
+    /// Chunks of this many bytes regardless of event boundaries
+    FixedBytes(usize),
+    /// Every event cut inside its JSON (within the first multi-byte character
+    /// if there is one, else halfway) and again between its two newlines
+    MidJson,
+}
+
+/// Tool call emitted by the script
+#[derive(Debug, Clone)]
+struct ScriptedToolCall {
+    id: String,
+    name: String,
+    arguments: String,
+}
+
+/// Builder for a deterministic streaming chat completion
+///
+/// # Example
+///
+/// ```ignore
+/// let script = StreamScript::new("gpt-4o-mini")
+///     .text("Hello there")
+///     .usage(10, 5)
+///     .chunking(Chunking::MidJson);
+/// let provider = MockAiProvider::new()
+///     .with_reply(MockEndpoint::ChatCompletions, script.reply());
+/// // ... send a streaming request ...
+/// assert_eq!(sse_events(&response.text()), script.events());
+/// ```
+#[derive(Debug, Clone)]
+pub struct StreamScript {
+    model: String,
+    text: Option<String>,
+    tool_calls: Vec<ScriptedToolCall>,
+    finish_reason: Option<String>,
+    usage: Option<(u32, u32)>,
+    chunking: Chunking,
+}
+
+impl StreamScript {
+    /// Empty stream from `model`: a role chunk, a `stop` chunk and `[DONE]`
+    pub fn new(model: &str) -> Self {
+        Self {
+            model: model.to_string(),
+            text: None,
+            tool_calls: Vec::new(),
+            finish_reason: None,
+            usage: None,
+            chunking: Chunking::default(),
+        }
+    }
+
+    /// Response text, streamed one space-delimited token per delta
+    pub fn text(mut self, text: &str) -> Self {
+        self.text = Some(text.to_string());
+        self
+    }
+
+    /// Tool call whose arguments are streamed in small pieces after the text
+    ///
+    /// The finish reason becomes `tool_calls` unless set explicitly.
+    pub fn tool_call(mut self, id: &str, name: &str, arguments: &str) -> Self {
+        self.tool_calls.push(ScriptedToolCall {
+            id: id.to_string(),
+            name: name.to_string(),
+            arguments: arguments.to_string(),
+        });
+        self
+    }
+
+    /// Finish reason of the last choice chunk
+    pub fn finish_reason(mut self, finish_reason: &str) -> Self {
+        self.finish_reason = Some(finish_reason.to_string());
+        self
+    }
+
+    /// Final usage-only chunk, as sent with `stream_options.include_usage`
+    pub fn usage(mut self, prompt_tokens: u32, completion_tokens: u32) -> Self {
+        self.usage = Some((prompt_tokens, completion_tokens));
+        self
+    }
+
+    /// How `chunks()` and `reply()` cut the byte stream
+    pub fn chunking(mut self, chunking: Chunking) -> Self {
+        self.chunking = chunking;
+        self
+    }
+
+    /// Chunk events in order, without `[DONE]`
+    pub fn events(&self) -> Vec<Value> {
+        let mut role = json!({"role": "assistant"});
+        if self.text.is_none() {
+            role["content"] = Value::Null;
+        }
+        let mut events = vec![self.chunk(role, Value::Null)];
+
+        for token in self.tokens() {
+            events.push(self.chunk(json!({"content": token}), Value::Null));
+        }
+
+        for (index, call) in self.tool_calls.iter().enumerate() {
+            events.push(self.chunk(
+                json!({"tool_calls": [{
+                    "index": index,
+                    "id": call.id,
+                    "type": "function",
+                    "function": {"name": call.name, "arguments": ""}
+                }]}),
+                Value::Null,
+            ));
+            for piece in argument_pieces(&call.arguments) {
+                events.push(self.chunk(
+                    json!({"tool_calls": [{"index": index, "function": {"arguments": piece}}]}),
+                    Value::Null,
+                ));
+            }
+        }
+
+        events.push(self.chunk(json!({}), json!(self.effective_finish_reason())));
+
+        if let Some((prompt_tokens, completion_tokens)) = self.usage {
+            events.push(json!({
+                "id": SCRIPT_ID,
+                "object": "chat.completion.chunk",
+                "created": 1700000000,
+                "model": self.model,
+                "choices": [],
+                "usage": {
+                    "prompt_tokens": prompt_tokens,
+                    "completion_tokens": completion_tokens,
+                    "total_tokens": prompt_tokens + completion_tokens
+                }
+            }));
+        }
+
+        events
+    }
+
+    /// Text the deltas add up to
+    pub fn content(&self) -> String {
+        self.text.clone().unwrap_or_default()
+    }
+
+    /// Complete SSE body, terminated by `data: [DONE]`
+    pub fn body(&self) -> String {
+        self.sse_events().concat()
+    }
+
+    /// Body cut according to the chunking strategy
+    pub fn chunks(&self) -> Vec<Bytes> {
+        match self.chunking {
+            Chunking::PerToken => self.sse_events().into_iter().map(Bytes::from).collect(),
+            Chunking::FixedBytes(size) => {
+                assert!(size > 0, "FixedBytes chunk size must be positive");
+                Bytes::from(self.body())
+                    .chunks(size)
+                    .map(Bytes::copy_from_slice)
+                    .collect()
+            }
+            Chunking::MidJson => self
+                .sse_events()
+                .into_iter()
+                .flat_map(|event| {
+                    let event = Bytes::from(event);
+                    let payload_start = b"data: ".len();
+                    let payload_end = event.len() - 2;
+                    // Inside the first multi-byte character, else halfway through the JSON
+                    let cut = event[payload_start..payload_end]
+                        .iter()
+                        .position(|b| !b.is_ascii())
+                        .map(|i| payload_start + i + 1)
+                        .unwrap_or((payload_start + payload_end) / 2);
+                    vec![
+                        event.slice(..cut),
+                        event.slice(cut..payload_end + 1),
+                        event.slice(payload_end + 1..),
+                    ]
+                })
+                .collect(),
+        }
+    }
+
+    /// Stream reply for `MockAiProvider`, one element per chunk
+    pub fn reply(&self) -> MockReply {
+        MockReply::Stream(self.chunks())
+    }
+
+    /// `text/event-stream` response with the whole body, for wiremock upstreams
+    pub fn response(&self) -> ResponseTemplate {
+        ResponseTemplate::new(200)
+            .insert_header("cache-control", "no-cache")
+            .set_body_raw(self.body(), "text/event-stream")
+    }
+
+    fn chunk(&self, delta: Value, finish_reason: Value) -> Value {
+        json!({
+            "id": SCRIPT_ID,
+            "object": "chat.completion.chunk",
+            "created": 1700000000,
+            "model": self.model,
+            "choices": [{"index": 0, "delta": delta, "finish_reason": finish_reason}]
+        })
+    }
+
+    /// Content deltas: the first word, then each following word with its leading space
+    fn tokens(&self) -> Vec<String> {
+        let Some(text) = self.text.as_deref() else {
+            return Vec::new();
+        };
+        text.split(' ')
+            .enumerate()
+            .map(|(i, word)| {
+                if i == 0 {
+                    word.to_string()
+                } else {
+                    format!(" {}", word)
+                }
+            })
+            .collect()
+    }
+
+    fn effective_finish_reason(&self) -> &str {
+        match self.finish_reason.as_deref() {
+            Some(reason) => reason,
+            None if self.tool_calls.is_empty() => "stop",
+            None => "tool_calls",
+        }
+    }
+
+    fn sse_events(&self) -> Vec<String> {
+        let mut events: Vec<String> = self
+            .events()
+            .iter()
+            .map(|event| format!("data: {}\n\n", event))
+            .collect();
+        events.push("data: [DONE]\n\n".to_string());
+        events
+    }
+}
+
+/// Split tool call arguments into pieces of a few characters
+fn argument_pieces(arguments: &str) -> Vec<String> {
+    let chars: Vec<char> = arguments.chars().collect();
+    chars
+        .chunks(ARGUMENT_PIECE_CHARS)
+        .map(|piece| piece.iter().collect())
+        .collect()
+}
+
+/// Parse the `data:` events of an SSE body, skipping `[DONE]` and comments
+///
+/// Panics on a `data:` line that isn't JSON, which is what a test wants when
+/// a stream was re-chunked incorrectly.
+pub fn sse_events(body: &str) -> Vec<Value> {
+    body.lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .map(str::trim)
+        .filter(|data| *data != "[DONE]")
+        .map(|data| {
+            serde_json::from_str(data)
+                .unwrap_or_else(|e| panic!("Invalid SSE data line {:?}: {}", data, e))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn script() -> StreamScript {
+        StreamScript::new("gpt-4o")
+            .text("Grüße from the café")
+            .tool_call("call_1", "get_weather", r#"{"city":"Paris"}"#)
+            .usage(7, 3)
+    }
+
+    fn joined(chunks: &[Bytes]) -> Vec<u8> {
+        chunks.iter().flat_map(|c| c.iter().copied()).collect()
+    }
+
+    #[test]
+    fn test_every_chunking_yields_the_same_body() {
+        let body = script().body();
+        for chunking in [
+            Chunking::PerToken,
+            Chunking::FixedBytes(1),
+            Chunking::FixedBytes(7),
+            Chunking::MidJson,
+        ] {
+            let chunks = script().chunking(chunking).chunks();
+            assert_eq!(joined(&chunks), body.as_bytes(), "{:?}", chunking);
+        }
+    }
+
+    #[test]
+    fn test_events_round_trip_through_body() {
+        let script = script();
+        assert_eq!(sse_events(&script.body()), script.events());
+
+        let text: String = script
+            .events()
+            .iter()
+            .filter_map(|e| {
+                e["choices"][0]["delta"]["content"]
+                    .as_str()
+                    .map(String::from)
+            })
+            .collect();
+        assert_eq!(text, script.content());
+
+        let arguments: String = script
+            .events()
+            .iter()
+            .filter_map(|e| {
+                e["choices"][0]["delta"]["tool_calls"][0]["function"]["arguments"]
+                    .as_str()
+                    .map(String::from)
+            })
+            .collect();
+        assert_eq!(arguments, r#"{"city":"Paris"}"#);
+        let events = script.events();
+        assert_eq!(
+            events[events.len() - 2]["choices"][0]["finish_reason"],
+            "tool_calls"
+        );
+        assert_eq!(events[events.len() - 1]["usage"]["total_tokens"], 10);
+    }
+
+    #[test]
+    fn test_mid_json_chunks_split_inside_payloads() {
+        let chunks = script().chunking(Chunking::MidJson).chunks();
+        let events = script().events().len() + 1;
+        assert_eq!(chunks.len(), events * 3);
+
+        for triple in chunks.chunks(3) {
+            assert!(!triple[0].ends_with(b"\n"));
+            assert_eq!(&triple[2][..], b"\n");
+        }
+        // "Grüße" and "café" are cut inside their multi-byte characters
+        assert!(chunks.iter().any(|c| std::str::from_utf8(c).is_err()));
+    }
+
+    #[test]
+    fn test_default_finish_reason_and_tool_only_role() {
+        let events = StreamScript::new("gpt-4o").text("Hi").events();
+        assert_eq!(
+            events.last().unwrap()["choices"][0]["finish_reason"],
+            "stop"
+        );
+
+        let events = StreamScript::new("gpt-4o")
+            .tool_call("call_1", "noop", "{}")
+            .events();
+        assert!(events[0]["choices"][0]["delta"]["content"].is_null());
+    }
+}
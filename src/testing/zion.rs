@@ -0,0 +1,247 @@
+//! Wiremock-backed Zion stub
+//!
+//! `zion_stub()` starts a wiremock server with the endpoints Sentinel calls on
+//! every request already mocked. Stub mocks are mounted at `STUB_PRIORITY`, so
+//! any mock a test mounts afterwards with the default priority takes precedence.
+
+use serde_json::{json, Value};
+use wiremock::matchers::{header_exists, method, path, path_regex};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use super::constants;
+use crate::zion::negotiation::{BATCH_ESTIMATED, BATCH_MODEL, BATCH_ORGANIZATION, BATCH_TIMESTAMP};
+
+/// Priority used for the pre-mounted stub mocks (wiremock default is 5)
+pub const STUB_PRIORITY: u8 = 10;
+
+/// Path of the batch increment endpoint
+const BATCH_INCREMENT_PATH: &str = "/api/v1/usage/external/batch-increment";
+
+/// Start a Zion stub with the standard endpoints pre-mocked
+///
+/// - `GET /api/v1/users/me` - profile for `constants::TEST_EMAIL`
+/// - `GET /api/v1/limits/external/{id}` - generous `ai_usage` limits
+/// - `POST /api/v1/usage/external/increment` - success
+/// - `POST /api/v1/usage/external/batch-increment` - success
+/// - `GET /api/v1/tiers/config` - gpt-4o-mini / gpt-4o tier mapping
+/// - `GET /api/v1/meta` - every optional batch field advertised
+pub async fn zion_stub() -> MockServer {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/v1/users/me"))
+        .and(header_exists("Authorization"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(profile_body()))
+        .with_priority(STUB_PRIORITY)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/api/v1/limits/external/.+$"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(limits_body()))
+        .with_priority(STUB_PRIORITY)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/api/v1/usage/external/increment"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(increment_body()))
+        .with_priority(STUB_PRIORITY)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path(BATCH_INCREMENT_PATH))
+        .respond_with(ResponseTemplate::new(200).set_body_json(batch_increment_body()))
+        .with_priority(STUB_PRIORITY)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/v1/tiers/config"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(tier_config_body()))
+        .with_priority(STUB_PRIORITY)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/v1/meta"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(meta_body()))
+        .with_priority(STUB_PRIORITY)
+        .mount(&server)
+        .await;
+
+    server
+}
+
+// Bodies are also the fixtures for the `zion::models` deserialization tests
+
+/// Profile served by `GET /api/v1/users/me`
+pub fn profile_body() -> Value {
+    json!({
+        "success": true,
+        "data": {
+            "id": constants::TEST_USER_ID,
+            "email": constants::TEST_EMAIL,
+            "name": "Test User",
+            "externalId": constants::TEST_EXTERNAL_ID,
+            "emailVerified": true,
+            "createdAt": "2024-01-01T00:00:00Z",
+            "lastLoginAt": "2024-01-15T12:00:00Z"
+        }
+    })
+}
+
+/// Limits served by `GET /api/v1/limits/external/{id}`
+pub fn limits_body() -> Value {
+    json!({
+        "success": true,
+        "data": {
+            "userId": constants::TEST_USER_ID,
+            "externalId": constants::TEST_EXTERNAL_ID,
+            "limits": [{
+                "name": "ai_usage",
+                "displayName": "AI Usage",
+                "aiInputTokens": {"limit": 1000000, "used": 0, "remaining": 1000000},
+                "aiOutputTokens": {"limit": 1000000, "used": 0, "remaining": 1000000},
+                "aiRequests": {"limit": 10000, "used": 0, "remaining": 10000},
+                "resetPeriod": "MONTHLY",
+                "periodStart": "2024-01-01T00:00:00Z",
+                "periodEnd": "2024-01-31T23:59:59Z"
+            }]
+        }
+    })
+}
+
+/// Response of `POST /api/v1/usage/external/increment`
+pub fn increment_body() -> Value {
+    json!({
+        "success": true,
+        "data": {
+            "canUse": true,
+            "aiInputTokens": {"limit": 1000000, "used": 0, "remaining": 1000000},
+            "aiOutputTokens": {"limit": 1000000, "used": 0, "remaining": 1000000},
+            "aiRequests": {"limit": 10000, "used": 0, "remaining": 10000}
+        }
+    })
+}
+
+/// Response of `POST /api/v1/usage/external/batch-increment`
+pub fn batch_increment_body() -> Value {
+    json!({
+        "success": true,
+        "data": {"processed": 1, "failed": 0, "results": []}
+    })
+}
+
+/// Tier config served by `GET /api/v1/tiers/config`
+pub fn tier_config_body() -> Value {
+    json!({
+        "success": true,
+        "data": {
+            "version": "1.0.0",
+            "updatedAt": "2024-01-01T00:00:00Z",
+            "tiers": {
+                "simple": [{
+                    "provider": "openai",
+                    "model": "gpt-4o-mini",
+                    "relativeCost": 1,
+                    "inputPricePerMillion": 0.15,
+                    "outputPricePerMillion": 0.60
+                }],
+                "moderate": [{
+                    "provider": "openai",
+                    "model": "gpt-4o",
+                    "relativeCost": 5,
+                    "inputPricePerMillion": 2.50,
+                    "outputPricePerMillion": 10.0
+                }],
+                "complex": [{
+                    "provider": "openai",
+                    "model": "gpt-4o",
+                    "relativeCost": 5,
+                    "inputPricePerMillion": 2.50,
+                    "outputPricePerMillion": 10.0
+                }]
+            }
+        }
+    })
+}
+
+/// Metadata served by `GET /api/v1/meta`
+pub fn meta_body() -> Value {
+    json!({
+        "success": true,
+        "data": {
+            "apiVersion": "1.0.0",
+            "capabilities": [BATCH_MODEL, BATCH_TIMESTAMP, BATCH_ORGANIZATION, BATCH_ESTIMATED]
+        }
+    })
+}
+
+/// Batch increment requests received by a Zion mock server
+pub async fn batch_increment_requests(server: &MockServer) -> Vec<wiremock::Request> {
+    server
+        .received_requests()
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|r| r.url.path() == BATCH_INCREMENT_PATH)
+        .collect()
+}
+
+/// Parse the increments array from a batch increment request
+pub fn parse_batch_payload(request: &wiremock::Request) -> Vec<serde_json::Value> {
+    let body: serde_json::Value = serde_json::from_slice(&request.body)
+        .expect("Failed to parse batch increment request body");
+    body["increments"].as_array().cloned().unwrap_or_default()
+}
+
+/// Extract token counts from a batch increment item
+///
+/// Returns (input_tokens, output_tokens, requests)
+pub fn extract_token_counts(item: &serde_json::Value) -> (i64, i64, i64) {
+    let input = item["aiInputTokens"].as_i64().unwrap_or(0);
+    let output = item["aiOutputTokens"].as_i64().unwrap_or(0);
+    let requests = item["aiRequests"].as_i64().unwrap_or(0);
+    (input, output, requests)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zion::ZionClient;
+
+    fn client_for(server: &MockServer) -> ZionClient {
+        let config = crate::testing::test_config(&server.uri(), "http://unused/v1");
+        ZionClient::new(reqwest::Client::new(), &config)
+    }
+
+    #[tokio::test]
+    async fn test_stub_serves_profile_limits_and_tier_config() {
+        let server = zion_stub().await;
+        let client = client_for(&server);
+
+        let profile = client.validate_jwt(constants::TEST_JWT_TOKEN).await.unwrap();
+        assert_eq!(profile.email, constants::TEST_EMAIL);
+
+        let limits = client.get_limits(constants::TEST_EXTERNAL_ID).await.unwrap();
+        assert_eq!(limits[0].name, "ai_usage");
+
+        let tiers = client.get_tier_config().await.unwrap();
+        assert_eq!(tiers.tiers.simple[0].model, "gpt-4o-mini");
+    }
+
+    #[tokio::test]
+    async fn test_stub_can_be_overridden() {
+        let server = zion_stub().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/users/me"))
+            .respond_with(ResponseTemplate::new(401))
+            .mount(&server)
+            .await;
+
+        let client = client_for(&server);
+        assert!(client.validate_jwt("anything").await.is_err());
+    }
+}
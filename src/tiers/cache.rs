@@ -1,17 +1,29 @@
 //! Tier configuration cache
 //!
-//! Caches tier configuration from Zion with TTL.
+//! Caches tier configuration from Zion with TTL, and falls back to the
+//! last-known-good copy (see [`super::standby`]) when Zion is unreachable.
 
-use std::sync::Arc;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
-use tracing::{debug, instrument};
+use tracing::{debug, error, instrument, warn};
 
 use crate::{
-    cache::redis::{keys, RedisCache},
+    cache::{
+        redis::{keys, RedisCache},
+        schema::Schema,
+    },
+    clock::{system_clock, SharedClock},
     error::AppResult,
     zion::{models::TierConfigData, ZionClient},
 };
 
+use super::standby::{self, StandbySource, StandbyStatus, StandbyTierConfig};
+
+/// How long a served standby config is cached before Zion is asked again
+const STANDBY_RECHECK_SECONDS: u64 = 30;
+
 #[cfg(any(test, feature = "test-utils"))]
 use crate::cache::InMemoryCache;
 
@@ -27,25 +39,25 @@ pub enum TierConfigCacheBackend {
 }
 
 impl TierConfigCacheBackend {
-    async fn get(&self, key: &str) -> AppResult<Option<TierConfigData>> {
+    async fn get<T: Schema>(&self, key: &str) -> AppResult<Option<T>> {
         match self {
-            TierConfigCacheBackend::Redis(cache) => cache.get(key).await,
+            TierConfigCacheBackend::Redis(cache) => cache.get_versioned(key).await,
             #[cfg(any(test, feature = "test-utils"))]
-            TierConfigCacheBackend::InMemory(cache) => cache.get(key).await,
+            TierConfigCacheBackend::InMemory(cache) => cache.get_versioned(key).await,
         }
     }
 
-    async fn set_with_ttl(
+    async fn set_with_ttl<T: Schema>(
         &self,
         key: &str,
-        value: &TierConfigData,
+        value: &T,
         ttl_seconds: u64,
     ) -> AppResult<()> {
         match self {
-            TierConfigCacheBackend::Redis(cache) => cache.set_with_ttl(key, value, ttl_seconds).await,
+            TierConfigCacheBackend::Redis(cache) => cache.set_versioned(key, value, ttl_seconds).await,
             #[cfg(any(test, feature = "test-utils"))]
             TierConfigCacheBackend::InMemory(cache) => {
-                cache.set_with_ttl(key, value, ttl_seconds).await
+                cache.set_versioned(key, value, ttl_seconds).await
             }
         }
     }
@@ -59,18 +71,47 @@ pub struct TierConfigCache {
     cache: TierConfigCacheBackend,
     zion_client: Arc<ZionClient>,
     ttl: u64,
+    /// File the standby copy is also kept in
+    standby_path: Option<PathBuf>,
+    /// Oldest standby copy that may be served (zero = never serve one)
+    max_staleness: Duration,
+    /// Standby copy being served, if any: where it came from and its version and fetch time
+    serving_standby: RwLock<Option<(StandbySource, String, i64)>>,
+    clock: SharedClock,
 }
 
 impl TierConfigCache {
     /// Create a new tier config cache with Redis backend
     pub fn new(cache: Arc<RedisCache>, zion_client: Arc<ZionClient>, ttl: u64) -> Self {
+        Self::with_backend(TierConfigCacheBackend::Redis(cache), zion_client, ttl)
+    }
+
+    fn with_backend(cache: TierConfigCacheBackend, zion_client: Arc<ZionClient>, ttl: u64) -> Self {
         Self {
-            cache: TierConfigCacheBackend::Redis(cache),
+            cache,
             zion_client,
             ttl,
+            standby_path: None,
+            max_staleness: Duration::ZERO,
+            serving_standby: RwLock::new(None),
+            clock: system_clock(),
         }
     }
 
+    /// Keep a standby copy (in Redis, and in `path` if given) and serve it
+    /// while Zion is unreachable, up to `max_staleness` old
+    pub fn with_standby(mut self, path: Option<PathBuf>, max_staleness: Duration) -> Self {
+        self.standby_path = path;
+        self.max_staleness = max_staleness;
+        self
+    }
+
+    /// Use the given clock for standby ages
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
     /// Create for testing with in-memory backend
     #[cfg(any(test, feature = "test-utils"))]
     pub fn new_for_testing(
@@ -78,49 +119,199 @@ impl TierConfigCache {
         zion_client: Arc<ZionClient>,
         ttl: u64,
     ) -> Self {
-        Self {
-            cache: TierConfigCacheBackend::InMemory(cache),
-            zion_client,
-            ttl,
-        }
+        Self::with_backend(TierConfigCacheBackend::InMemory(cache), zion_client, ttl)
     }
 
     /// Get tier configuration, using cache if available
     ///
     /// Returns cached config if present, otherwise fetches from Zion
-    /// and caches the result with configured TTL.
+    /// and caches the result with configured TTL. If Zion can't be reached,
+    /// a standby copy young enough is served instead.
     #[instrument(skip(self))]
     pub async fn get_config(&self) -> AppResult<TierConfigData> {
-        let cache_key = keys::tier_config();
-
         // Try cache first
-        if let Some(config) = self.cache.get(cache_key).await? {
+        if let Some(config) = self.cache.get::<TierConfigData>(keys::tier_config()).await? {
             debug!(version = %config.version, "Tier config cache hit");
             return Ok(config);
         }
 
         debug!("Tier config cache miss, fetching from Zion");
+        match self.refresh().await {
+            Ok(config) => Ok(config),
+            Err(e) => match self.load_standby().await {
+                Some(config) => Ok(config),
+                None => Err(e),
+            },
+        }
+    }
 
-        // Fetch from Zion
+    /// Fetch the tier config from Zion, bypassing the cache
+    ///
+    /// The result is cached and kept as the standby copy.
+    pub async fn refresh(&self) -> AppResult<TierConfigData> {
         let config = self.zion_client.get_tier_config().await?;
 
-        // Cache the result
         self.cache
-            .set_with_ttl(cache_key, &config, self.ttl)
+            .set_with_ttl(keys::tier_config(), &config, self.ttl)
             .await?;
-
         debug!(version = %config.version, "Tier config cached");
+
+        *self.serving_standby.write().unwrap() = None;
+        self.store_standby(&StandbyTierConfig {
+            config: config.clone(),
+            fetched_at: self.clock.now_unix(),
+        })
+        .await;
         Ok(config)
     }
+
+    /// Keep `standby` as the last-known-good copy
+    ///
+    /// Failures are logged: the fresh config is still served.
+    pub async fn store_standby(&self, standby: &StandbyTierConfig) {
+        if self.max_staleness.is_zero() {
+            return;
+        }
+        if let Err(e) = self
+            .cache
+            .set_with_ttl(keys::tier_config_standby(), standby, self.max_staleness.as_secs())
+            .await
+        {
+            warn!(error = %e, "Failed to store the standby tier config");
+        }
+        if let Some(path) = &self.standby_path {
+            if let Err(e) = standby::write_file(path, standby).await {
+                warn!(path = %path.display(), error = %e, "Failed to write the standby tier config file");
+            }
+        }
+    }
+
+    /// Serve the standby copy while Zion is unreachable
+    ///
+    /// Redis is preferred over the file. The copy is cached briefly, so Zion
+    /// is asked again every `STANDBY_RECHECK_SECONDS`; a copy older than the
+    /// maximum staleness is refused.
+    async fn load_standby(&self) -> Option<TierConfigData> {
+        if self.max_staleness.is_zero() {
+            return None;
+        }
+        let from_redis = match self
+            .cache
+            .get::<StandbyTierConfig>(keys::tier_config_standby())
+            .await
+        {
+            Ok(standby) => standby,
+            Err(e) => {
+                debug!(error = %e, "Failed to read the standby tier config");
+                None
+            }
+        };
+        let (source, standby) = match from_redis {
+            Some(standby) => (StandbySource::Redis, standby),
+            None => match &self.standby_path {
+                Some(path) => (StandbySource::File, standby::read_file(path).await?),
+                None => return None,
+            },
+        };
+
+        let age = self.age(standby.fetched_at);
+        if age > self.max_staleness.as_secs() {
+            error!(
+                version = %standby.config.version,
+                age_seconds = age,
+                max_staleness_seconds = self.max_staleness.as_secs(),
+                "Standby tier config is too old to serve"
+            );
+            *self.serving_standby.write().unwrap() = None;
+            return None;
+        }
+
+        let recheck = STANDBY_RECHECK_SECONDS.min(self.ttl).max(1);
+        if let Err(e) = self
+            .cache
+            .set_with_ttl(keys::tier_config(), &standby.config, recheck)
+            .await
+        {
+            debug!(error = %e, "Failed to cache the standby tier config");
+        }
+        warn!(
+            version = %standby.config.version,
+            age_seconds = age,
+            source = ?source,
+            "Zion unreachable; serving the standby tier config"
+        );
+        *self.serving_standby.write().unwrap() =
+            Some((source, standby.config.version.clone(), standby.fetched_at));
+        Some(standby.config)
+    }
+
+    /// Standby copy being served instead of a fresh config, if any
+    pub fn standby_status(&self) -> Option<StandbyStatus> {
+        let serving = self.serving_standby.read().unwrap().clone();
+        serving.map(|(source, version, fetched_at)| StandbyStatus {
+            source,
+            version,
+            fetched_at,
+            age_seconds: self.age(fetched_at),
+        })
+    }
+
+    /// Seconds since `fetched_at`
+    fn age(&self, fetched_at: i64) -> u64 {
+        u64::try_from(self.clock.now_unix() - fetched_at).unwrap_or(0)
+    }
+
+    /// Cached tier config, without fetching from Zion
+    ///
+    /// None when nothing is cached or the cache can't be read.
+    pub async fn cached_config(&self) -> Option<TierConfigData> {
+        match self.cache.get(keys::tier_config()).await {
+            Ok(config) => config,
+            Err(e) => {
+                debug!(error = %e, "Failed to read cached tier config");
+                None
+            }
+        }
+    }
+
+    /// Version of the cached tier config, without fetching from Zion
+    ///
+    /// None when nothing is cached or the cache can't be read.
+    pub async fn cached_version(&self) -> Option<String> {
+        self.cached_config().await.map(|config| config.version)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::{test_config, zion::tier_config_body, zion_stub};
 
     #[test]
     fn test_tier_config_cache_backend_enum() {
         // Compile-time check that enum variants exist
         fn _type_check(_backend: TierConfigCacheBackend) {}
     }
+
+    #[tokio::test]
+    async fn test_configs_written_by_other_releases() {
+        let zion = zion_stub().await;
+        let config = test_config(&zion.uri(), "http://unused.invalid/v1");
+        let zion_client = Arc::new(ZionClient::new(reqwest::Client::new(), &config));
+        let memory = Arc::new(InMemoryCache::new(60));
+        let cache = TierConfigCache::new_for_testing(memory.clone(), zion_client, 60);
+
+        // An unversioned config from an older replica is served from the cache
+        let mut legacy = tier_config_body()["data"].clone();
+        legacy["version"] = "legacy".into();
+        memory.set_raw(keys::tier_config(), legacy.to_string(), 60);
+        assert_eq!(cache.get_config().await.unwrap().version, "legacy");
+
+        // A newer replica's config is a miss: refetched from Zion, not an error
+        memory.set_raw(keys::tier_config(), r#"v2|{"tiers":{}}"#.to_string(), 60);
+        assert_eq!(cache.cached_version().await, None);
+        let fetched = cache.get_config().await.unwrap();
+        assert_eq!(fetched.version, tier_config_body()["data"]["version"]);
+        assert!(memory.get_raw(keys::tier_config()).unwrap().starts_with('{'));
+    }
 }
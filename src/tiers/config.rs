@@ -17,4 +17,52 @@ impl TierConfig {
             Tier::Complex => &self.tiers.complex,
         }
     }
+
+    /// Get the system prompt override for a specific tier, if configured
+    pub fn system_prompt_for_tier(&self, tier: Tier) -> Option<&str> {
+        let prompts = self.system_prompts.as_ref()?;
+        match tier {
+            Tier::Simple => prompts.simple.as_deref(),
+            Tier::Moderate => prompts.moderate.as_deref(),
+            Tier::Complex => prompts.complex.as_deref(),
+        }
+    }
+
+    /// Find a model's configuration in any tier
+    pub fn model_config(&self, model: &str) -> Option<&ModelConfig> {
+        [&self.tiers.simple, &self.tiers.moderate, &self.tiers.complex]
+            .into_iter()
+            .flatten()
+            .find(|config| config.model == model)
+    }
+
+    /// Whether the tier config flags `model` as a reasoning model
+    pub fn is_reasoning_model(&self, model: &str) -> bool {
+        self.model_config(model).is_some_and(|config| config.reasoning)
+    }
+
+    /// Whether `model`'s responses should have reasoning content stripped
+    pub fn strips_reasoning(&self, model: &str) -> bool {
+        self.model_config(model).is_some_and(|config| config.strip_reasoning)
+    }
+
+    /// Get the long-context fallback model for a specific tier, if configured
+    pub fn long_context_model_for_tier(&self, tier: Tier) -> Option<&str> {
+        let models = self.long_context_models.as_ref()?;
+        match tier {
+            Tier::Simple => models.simple.as_deref(),
+            Tier::Moderate => models.moderate.as_deref(),
+            Tier::Complex => models.complex.as_deref(),
+        }
+    }
+
+    /// Get the embedding model for a specific tier, if configured
+    pub fn embedding_model_for_tier(&self, tier: Tier) -> Option<&str> {
+        let models = self.embedding_models.as_ref()?;
+        match tier {
+            Tier::Simple => models.simple.as_deref(),
+            Tier::Moderate => models.moderate.as_deref(),
+            Tier::Complex => models.complex.as_deref(),
+        }
+    }
 }
@@ -2,13 +2,29 @@
 //!
 //! Tracks provider/model availability and implements exponential backoff
 //! when failures occur. This enables graceful degradation during outages.
+//! Also keeps a window of recent response latencies per provider/model,
+//! whose p95 feeds latency-aware tier selection, and the state of the
+//! upstream circuit breakers per provider endpoint.
+//!
+//! Entries of models that left the tier config are pruned once they have
+//! been absent for the retention window (see [`super::prune`]).
 
-use std::collections::HashMap;
-use std::sync::RwLock;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Mutex, RwLock};
 use std::time::{Duration, Instant};
 
+use serde::Serialize;
 use tracing::{debug, info, warn};
 
+use crate::clock::{system_clock, SharedClock};
+use crate::proxy::breaker::BreakerState;
+
+/// Latency samples kept per provider/model
+const LATENCY_WINDOW: usize = 100;
+
+/// Samples needed before a p95 latency is reported
+const MIN_LATENCY_SAMPLES: usize = 5;
+
 /// Configuration for health tracking
 #[derive(Debug, Clone)]
 pub struct HealthConfig {
@@ -54,6 +70,74 @@ impl Default for HealthState {
     }
 }
 
+/// Provider endpoint whose circuit breaker is not closed
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TrippedEndpoint {
+    pub provider: String,
+    pub endpoint: String,
+    pub state: BreakerState,
+    /// Seconds until an open circuit starts probing again
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after_seconds: Option<u64>,
+}
+
+/// Breaker state and, for an open breaker, when it starts probing
+type BreakerEntry = (BreakerState, Option<Instant>);
+
+/// Provider/model pair
+type ModelKey = (String, String);
+
+/// Health of one provider/model, as returned by `GET /admin/tiers/state`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ModelHealthSnapshot {
+    pub provider: String,
+    pub model: String,
+    /// Available for selection now (healthy or backoff elapsed)
+    pub available: bool,
+    pub consecutive_failures: u32,
+    /// Seconds of backoff left
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backoff_remaining_seconds: Option<u64>,
+    /// Latency samples held for this model
+    pub latency_samples: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub p95_ms: Option<u64>,
+    /// Seconds since the model was last seen in the tier config (None = configured)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub absent_for_seconds: Option<u64>,
+}
+
+/// Entry counts and approximate memory held by the tracker
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct HealthFootprint {
+    /// Provider/models with a health state
+    pub health_entries: usize,
+    /// Provider/models with latency samples
+    pub latency_entries: usize,
+    /// Latency samples across all models
+    pub latency_samples: usize,
+    /// Provider endpoints with a tripped breaker
+    pub breaker_entries: usize,
+    /// Estimate of the bytes held by keys, states and sample buffers
+    pub approx_bytes: usize,
+}
+
+/// Pruning counters, as returned by `GET /admin/tiers/state`
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PruneStats {
+    /// Prune passes run
+    pub runs: u64,
+    /// Unix time of the last pass
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_run_unix: Option<i64>,
+    /// Provider/models removed by the last pass
+    pub last_pruned: usize,
+    /// Provider/models removed since startup
+    pub total_pruned: u64,
+    /// Absent provider/models still inside the retention window
+    pub pending: usize,
+}
+
 /// Tracks health of provider/model combinations
 ///
 /// Uses exponential backoff to avoid hammering unhealthy providers.
@@ -61,7 +145,16 @@ impl Default for HealthState {
 /// independently tracks health based on its own observations).
 pub struct ProviderHealthTracker {
     states: RwLock<HashMap<(String, String), HealthState>>,
+    /// Most recent latencies per provider/model (oldest first)
+    latencies: RwLock<HashMap<(String, String), VecDeque<Duration>>>,
+    /// Upstream circuit breakers that are not closed, by provider/endpoint,
+    /// with the time an open one starts probing
+    breakers: RwLock<HashMap<(String, String), BreakerEntry>>,
+    /// When each tracked provider/model was first seen missing from the tier config
+    absent_since: Mutex<HashMap<ModelKey, Instant>>,
+    prune_stats: Mutex<PruneStats>,
     config: HealthConfig,
+    clock: SharedClock,
 }
 
 impl ProviderHealthTracker {
@@ -74,10 +167,25 @@ impl ProviderHealthTracker {
     pub fn with_config(config: HealthConfig) -> Self {
         Self {
             states: RwLock::new(HashMap::new()),
+            latencies: RwLock::new(HashMap::new()),
+            breakers: RwLock::new(HashMap::new()),
+            absent_since: Mutex::new(HashMap::new()),
+            prune_stats: Mutex::new(PruneStats::default()),
             config,
+            clock: system_clock(),
         }
     }
 
+    /// Use the given clock for backoff timing
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn elapsed_since(&self, instant: Instant) -> Duration {
+        self.clock.instant_now().saturating_duration_since(instant)
+    }
+
     /// Check if a provider/model is currently available
     ///
     /// Returns true if:
@@ -96,7 +204,7 @@ impl ProviderHealthTracker {
                 }
                 // Check if backoff period has elapsed
                 if let Some(last_failure) = state.last_failure {
-                    if last_failure.elapsed() >= state.backoff_duration {
+                    if self.elapsed_since(last_failure) >= state.backoff_duration {
                         debug!(
                             provider = %provider,
                             model = %model,
@@ -121,7 +229,7 @@ impl ProviderHealthTracker {
                 return None;
             }
             state.last_failure.map(|last| {
-                let elapsed = last.elapsed();
+                let elapsed = self.elapsed_since(last);
                 if elapsed >= state.backoff_duration {
                     Duration::ZERO
                 } else {
@@ -157,7 +265,7 @@ impl ProviderHealthTracker {
 
         let state = states.entry(key).or_default();
         state.available = false;
-        state.last_failure = Some(Instant::now());
+        state.last_failure = Some(self.clock.instant_now());
         state.consecutive_failures += 1;
 
         // Calculate exponential backoff: initial * multiplier^(failures-1)
@@ -178,6 +286,204 @@ impl ProviderHealthTracker {
         );
     }
 
+    /// Record how long a successful request took
+    pub fn record_latency(&self, provider: &str, model: &str, latency: Duration) {
+        let key = (provider.to_string(), model.to_string());
+        let mut latencies = self.latencies.write().unwrap();
+
+        let samples = latencies.entry(key).or_default();
+        if samples.len() == LATENCY_WINDOW {
+            samples.pop_front();
+        }
+        samples.push_back(latency);
+    }
+
+    /// p95 of the recent latencies of a provider/model
+    ///
+    /// None until enough samples have been recorded to be meaningful.
+    pub fn p95_latency(&self, provider: &str, model: &str) -> Option<Duration> {
+        let key = (provider.to_string(), model.to_string());
+        let latencies = self.latencies.read().unwrap();
+
+        let samples = latencies.get(&key)?;
+        if samples.len() < MIN_LATENCY_SAMPLES {
+            return None;
+        }
+        let mut sorted: Vec<Duration> = samples.iter().copied().collect();
+        sorted.sort_unstable();
+        // Nearest-rank percentile
+        let rank = (sorted.len() as f64 * 0.95).ceil() as usize;
+        Some(sorted[rank.max(1) - 1])
+    }
+
+    /// Record the state of a provider endpoint's circuit breaker
+    ///
+    /// Called by [`crate::proxy::breaker::UpstreamBreakers`] on every
+    /// transition; `retry_at` is when an open circuit starts probing.
+    pub fn record_breaker_state(
+        &self,
+        provider: &str,
+        endpoint: &str,
+        state: BreakerState,
+        retry_at: Option<Instant>,
+    ) {
+        let key = (provider.to_string(), endpoint.to_string());
+        let mut breakers = self.breakers.write().unwrap();
+        if state == BreakerState::Closed {
+            breakers.remove(&key);
+        } else {
+            breakers.insert(key, (state, retry_at));
+        }
+    }
+
+    /// Provider endpoints whose circuit is open or half-open, sorted
+    pub fn tripped_endpoints(&self) -> Vec<TrippedEndpoint> {
+        let breakers = self.breakers.read().unwrap();
+        let mut tripped: Vec<TrippedEndpoint> = breakers
+            .iter()
+            .map(|((provider, endpoint), (state, retry_at))| TrippedEndpoint {
+                provider: provider.clone(),
+                endpoint: endpoint.clone(),
+                state: *state,
+                retry_after_seconds: retry_at.map(|at| {
+                    at.saturating_duration_since(self.clock.instant_now())
+                        .as_secs_f64()
+                        .ceil() as u64
+                }),
+            })
+            .collect();
+        tripped.sort_by(|a, b| (&a.provider, &a.endpoint).cmp(&(&b.provider, &b.endpoint)));
+        tripped
+    }
+
+    /// Remove entries of models absent from the tier config for `retention`
+    ///
+    /// `configured` holds the provider/models of the current config. Models
+    /// missing from it start their absence now; those absent for at least
+    /// `retention` lose their health state and latencies. Both maps are
+    /// write-locked (states first, like every other path) for the whole pass,
+    /// so a concurrent selection sees a model either tracked or unknown,
+    /// which is treated as available.
+    pub fn prune_absent(&self, configured: &HashSet<ModelKey>, retention: Duration) -> PruneStats {
+        let now = self.clock.instant_now();
+        let mut states = self.states.write().unwrap();
+        let mut latencies = self.latencies.write().unwrap();
+        let mut absent_since = self.absent_since.lock().unwrap();
+
+        let tracked: HashSet<ModelKey> = states.keys().chain(latencies.keys()).cloned().collect();
+        absent_since.retain(|key, _| tracked.contains(key) && !configured.contains(key));
+
+        let mut pruned = 0;
+        for key in tracked.difference(configured) {
+            let since = *absent_since.entry(key.clone()).or_insert(now);
+            if now.saturating_duration_since(since) >= retention {
+                states.remove(key);
+                latencies.remove(key);
+                absent_since.remove(key);
+                pruned += 1;
+                info!(
+                    provider = %key.0,
+                    model = %key.1,
+                    "Pruned health state of a model absent from the tier config"
+                );
+            }
+        }
+
+        let mut stats = self.prune_stats.lock().unwrap();
+        stats.runs += 1;
+        stats.last_run_unix = Some(self.clock.now_unix());
+        stats.last_pruned = pruned;
+        stats.total_pruned += pruned as u64;
+        stats.pending = absent_since.len();
+        stats.clone()
+    }
+
+    /// Counters of the prune passes so far
+    pub fn prune_stats(&self) -> PruneStats {
+        self.prune_stats.lock().unwrap().clone()
+    }
+
+    /// Health of every tracked provider/model, sorted
+    pub fn snapshots(&self) -> Vec<ModelHealthSnapshot> {
+        let now = self.clock.instant_now();
+        let keys: HashSet<ModelKey> = {
+            let states = self.states.read().unwrap();
+            let latencies = self.latencies.read().unwrap();
+            states.keys().chain(latencies.keys()).cloned().collect()
+        };
+        let absent_since = self.absent_since.lock().unwrap().clone();
+
+        let mut snapshots: Vec<ModelHealthSnapshot> = keys
+            .into_iter()
+            .map(|key| {
+                let absent_for = absent_since
+                    .get(&key)
+                    .map(|since| now.saturating_duration_since(*since));
+                self.snapshot(key, absent_for)
+            })
+            .collect();
+        snapshots.sort_by(|a, b| (&a.provider, &a.model).cmp(&(&b.provider, &b.model)));
+        snapshots
+    }
+
+    fn snapshot(&self, key: ModelKey, absent_for: Option<Duration>) -> ModelHealthSnapshot {
+        let consecutive_failures = self
+            .states
+            .read()
+            .unwrap()
+            .get(&key)
+            .map_or(0, |state| state.consecutive_failures);
+        let latency_samples = self.latencies.read().unwrap().get(&key).map_or(0, VecDeque::len);
+        let (provider, model) = key;
+        ModelHealthSnapshot {
+            available: self.is_available(&provider, &model),
+            consecutive_failures,
+            backoff_remaining_seconds: self
+                .backoff_remaining(&provider, &model)
+                .map(|remaining| remaining.as_secs_f64().ceil() as u64),
+            latency_samples,
+            p95_ms: self.p95_latency(&provider, &model).map(|p95| p95.as_millis() as u64),
+            absent_for_seconds: absent_for.map(|absent| absent.as_secs()),
+            provider,
+            model,
+        }
+    }
+
+    /// Entry counts and approximate memory of the tracked state
+    pub fn footprint(&self) -> HealthFootprint {
+        let key_bytes = |(provider, model): &ModelKey| {
+            2 * std::mem::size_of::<String>() + provider.capacity() + model.capacity()
+        };
+        let states = self.states.read().unwrap();
+        let latencies = self.latencies.read().unwrap();
+        let breakers = self.breakers.read().unwrap();
+
+        let state_bytes: usize = states
+            .keys()
+            .map(|key| key_bytes(key) + std::mem::size_of::<HealthState>())
+            .sum();
+        let latency_bytes: usize = latencies
+            .iter()
+            .map(|(key, samples)| {
+                key_bytes(key)
+                    + std::mem::size_of::<VecDeque<Duration>>()
+                    + samples.capacity() * std::mem::size_of::<Duration>()
+            })
+            .sum();
+        let breaker_bytes: usize = breakers
+            .keys()
+            .map(|key| key_bytes(key) + std::mem::size_of::<BreakerEntry>())
+            .sum();
+
+        HealthFootprint {
+            health_entries: states.len(),
+            latency_entries: latencies.len(),
+            latency_samples: latencies.values().map(VecDeque::len).sum(),
+            breaker_entries: breakers.len(),
+            approx_bytes: state_bytes + latency_bytes + breaker_bytes,
+        }
+    }
+
     /// Get current state summary for debugging/metrics
     pub fn get_unavailable_providers(&self) -> Vec<(String, String, u32)> {
         let states = self.states.read().unwrap();
@@ -200,7 +506,7 @@ impl Default for ProviderHealthTracker {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::thread::sleep;
+    use crate::clock::TestClock;
 
     #[test]
     fn test_new_provider_is_available() {
@@ -232,14 +538,23 @@ mod tests {
             max_backoff: Duration::from_secs(1),
             backoff_multiplier: 2.0,
         };
-        let tracker = ProviderHealthTracker::with_config(config);
+        let clock = TestClock::new(0);
+        let tracker = ProviderHealthTracker::with_config(config).with_clock(clock.clone());
 
         tracker.record_failure("openai", "gpt-4o");
         assert!(!tracker.is_available("openai", "gpt-4o"));
 
-        // Wait for backoff to elapse
-        sleep(Duration::from_millis(60));
+        clock.advance(Duration::from_millis(49));
+        assert!(!tracker.is_available("openai", "gpt-4o"));
+        assert_eq!(
+            tracker.backoff_remaining("openai", "gpt-4o"),
+            Some(Duration::from_millis(1))
+        );
+
+        // Available again exactly when the backoff ends
+        clock.advance(Duration::from_millis(1));
         assert!(tracker.is_available("openai", "gpt-4o"));
+        assert_eq!(tracker.backoff_remaining("openai", "gpt-4o"), Some(Duration::ZERO));
     }
 
     #[test]
@@ -303,4 +618,141 @@ mod tests {
             ("openai".to_string(), "gpt-4o".to_string(), 2)
         );
     }
+
+    #[test]
+    fn test_p95_latency_needs_samples() {
+        let tracker = ProviderHealthTracker::new();
+        assert_eq!(tracker.p95_latency("openai", "gpt-4o"), None);
+
+        for _ in 0..MIN_LATENCY_SAMPLES - 1 {
+            tracker.record_latency("openai", "gpt-4o", Duration::from_millis(100));
+        }
+        assert_eq!(tracker.p95_latency("openai", "gpt-4o"), None);
+
+        tracker.record_latency("openai", "gpt-4o", Duration::from_millis(100));
+        assert_eq!(
+            tracker.p95_latency("openai", "gpt-4o"),
+            Some(Duration::from_millis(100))
+        );
+    }
+
+    #[test]
+    fn test_p95_latency_nearest_rank() {
+        let tracker = ProviderHealthTracker::new();
+        // 1..=100 ms: the 95th smallest sample is the p95
+        for ms in (1..=100).rev() {
+            tracker.record_latency("openai", "gpt-4o", Duration::from_millis(ms));
+        }
+        assert_eq!(
+            tracker.p95_latency("openai", "gpt-4o"),
+            Some(Duration::from_millis(95))
+        );
+        assert_eq!(tracker.p95_latency("openai", "gpt-4o-mini"), None);
+    }
+
+    #[test]
+    fn test_latency_window_drops_oldest() {
+        let tracker = ProviderHealthTracker::new();
+        for _ in 0..LATENCY_WINDOW {
+            tracker.record_latency("openai", "gpt-4o", Duration::from_secs(10));
+        }
+        // A full window of fast responses replaces the slow period
+        for _ in 0..LATENCY_WINDOW {
+            tracker.record_latency("openai", "gpt-4o", Duration::from_millis(200));
+        }
+        assert_eq!(
+            tracker.p95_latency("openai", "gpt-4o"),
+            Some(Duration::from_millis(200))
+        );
+    }
+
+    #[test]
+    fn test_success_keeps_latencies() {
+        let tracker = ProviderHealthTracker::new();
+        for _ in 0..MIN_LATENCY_SAMPLES {
+            tracker.record_latency("openai", "gpt-4o", Duration::from_millis(300));
+        }
+        tracker.record_failure("openai", "gpt-4o");
+        tracker.record_success("openai", "gpt-4o");
+        assert_eq!(
+            tracker.p95_latency("openai", "gpt-4o"),
+            Some(Duration::from_millis(300))
+        );
+    }
+
+    fn configured(models: &[&str]) -> HashSet<ModelKey> {
+        models
+            .iter()
+            .map(|model| ("openai".to_string(), model.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_prune_removes_models_absent_past_retention() {
+        let clock = TestClock::new(0);
+        let tracker = ProviderHealthTracker::new().with_clock(clock.clone());
+        let retention = Duration::from_secs(3600);
+        tracker.record_failure("openai", "gpt-4o");
+        tracker.record_latency("openai", "gpt-4o", Duration::from_millis(100));
+        tracker.record_failure("openai", "gpt-4o-mini");
+
+        // Both models configured: nothing to prune
+        let stats = tracker.prune_absent(&configured(&["gpt-4o", "gpt-4o-mini"]), retention);
+        assert_eq!((stats.last_pruned, stats.pending), (0, 0));
+
+        // gpt-4o leaves the config; its absence starts now
+        let without_gpt4o = configured(&["gpt-4o-mini"]);
+        let stats = tracker.prune_absent(&without_gpt4o, retention);
+        assert_eq!((stats.last_pruned, stats.pending), (0, 1));
+
+        clock.advance(Duration::from_secs(3599));
+        assert_eq!(tracker.prune_absent(&without_gpt4o, retention).last_pruned, 0);
+        let snapshot = tracker
+            .snapshots()
+            .into_iter()
+            .find(|snapshot| snapshot.model == "gpt-4o")
+            .unwrap();
+        assert_eq!(snapshot.absent_for_seconds, Some(3599));
+
+        clock.advance(Duration::from_secs(1));
+        let stats = tracker.prune_absent(&without_gpt4o, retention);
+        assert_eq!((stats.last_pruned, stats.pending, stats.total_pruned), (1, 0, 1));
+        assert_eq!(stats.runs, 4);
+        let models: Vec<String> = tracker.snapshots().into_iter().map(|s| s.model).collect();
+        assert_eq!(models, vec!["gpt-4o-mini"]);
+        assert_eq!(tracker.footprint().latency_entries, 0);
+    }
+
+    #[test]
+    fn test_prune_forgets_absence_when_model_returns() {
+        let clock = TestClock::new(0);
+        let tracker = ProviderHealthTracker::new().with_clock(clock.clone());
+        let retention = Duration::from_secs(60);
+        tracker.record_failure("openai", "gpt-4o");
+
+        tracker.prune_absent(&configured(&[]), retention);
+        clock.advance(Duration::from_secs(59));
+        // Back in the config before the window ends, then removed again
+        tracker.prune_absent(&configured(&["gpt-4o"]), retention);
+        clock.advance(Duration::from_secs(30));
+        let stats = tracker.prune_absent(&configured(&[]), retention);
+        assert_eq!((stats.last_pruned, stats.pending), (0, 1));
+        assert_eq!(tracker.footprint().health_entries, 1);
+    }
+
+    #[test]
+    fn test_footprint_counts_entries() {
+        let tracker = ProviderHealthTracker::new();
+        assert_eq!(tracker.footprint(), HealthFootprint::default());
+
+        tracker.record_failure("openai", "gpt-4o");
+        for _ in 0..3 {
+            tracker.record_latency("openai", "gpt-4o-mini", Duration::from_millis(10));
+        }
+        let footprint = tracker.footprint();
+        assert_eq!(footprint.health_entries, 1);
+        assert_eq!(footprint.latency_entries, 1);
+        assert_eq!(footprint.latency_samples, 3);
+        assert!(footprint.approx_bytes > 0);
+    }
 }
@@ -1,14 +1,21 @@
 //! Tier routing module
 //!
 //! Handles mapping complexity tiers to AI models based on configuration from Zion.
-//! Uses cost-weighted selection with health-aware filtering.
+//! Uses cost-weighted selection (optionally blended with live latency) with
+//! health-aware filtering.
 
 pub mod cache;
 pub mod config;
 pub mod health;
+pub mod prune;
 pub mod router;
+pub mod standby;
 
 pub use cache::TierConfigCache;
 pub use config::TierConfig;
-pub use health::{HealthConfig, ProviderHealthTracker};
-pub use router::{SelectedModel, TierRouter};
+pub use health::{
+    HealthConfig, HealthFootprint, ModelHealthSnapshot, ProviderHealthTracker, PruneStats, TrippedEndpoint,
+};
+pub use prune::{PruneConfig, TierStateReport};
+pub use router::{blend_weights, RoutingCandidate, SelectedModel, TierRouter};
+pub use standby::{StandbySource, StandbyStatus, StandbyTierConfig};
@@ -0,0 +1,244 @@
+//! Pruning and size reporting of the in-memory tier state
+//!
+//! The health tracker keeps an entry for every provider/model it has seen,
+//! including models dropped from the tier config long ago. A background task
+//! compares the tracked models with the current config every
+//! `TIER_HEALTH_PRUNE_INTERVAL_SECONDS` and prunes those absent for
+//! `TIER_HEALTH_RETENTION_HOURS`, then publishes the entry counts and
+//! approximate sizes of the tracker and the cached tier config as gauges.
+//! `GET /admin/tiers/state` reports the same alongside per-model health.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+use crate::config::ProviderConfig;
+use crate::zion::models::TierConfigData;
+
+use super::cache::TierConfigCache;
+use super::health::{HealthFootprint, ModelHealthSnapshot, ProviderHealthTracker, PruneStats};
+
+/// Prune schedule
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PruneConfig {
+    /// Time between passes (zero = never prune)
+    pub interval: Duration,
+    /// How long a model may be absent from the tier config
+    pub retention: Duration,
+}
+
+impl From<&ProviderConfig> for PruneConfig {
+    fn from(config: &ProviderConfig) -> Self {
+        Self {
+            interval: Duration::from_secs(config.tier_health_prune_interval_seconds),
+            retention: Duration::from_secs(config.tier_health_retention_hours * 3600),
+        }
+    }
+}
+
+/// Provider/models routed by a tier config
+pub fn configured_models(config: &TierConfigData) -> HashSet<(String, String)> {
+    [
+        &config.tiers.simple,
+        &config.tiers.moderate,
+        &config.tiers.complex,
+    ]
+    .into_iter()
+    .flatten()
+    .map(|model| (model.provider.clone(), model.model.clone()))
+    .collect()
+}
+
+/// Models and approximate size of a tier config
+fn config_footprint(config: &TierConfigData) -> (usize, usize) {
+    let bytes = serde_json::to_vec(config).map_or(0, |json| json.len());
+    (configured_models(config).len(), bytes)
+}
+
+/// Run one prune pass against the current tier config
+///
+/// Skipped (None) when the config can't be loaded: pruning against a missing
+/// config would treat every model as removed.
+pub async fn prune_once(
+    config_cache: &TierConfigCache,
+    health_tracker: &ProviderHealthTracker,
+    retention: Duration,
+) -> Option<PruneStats> {
+    let config = match config_cache.get_config().await {
+        Ok(config) => config,
+        Err(e) => {
+            warn!(error = %e, "Skipping tier health prune: tier config unavailable");
+            return None;
+        }
+    };
+
+    let stats = health_tracker.prune_absent(&configured_models(&config), retention);
+    debug!(
+        version = %config.version,
+        pruned = stats.last_pruned,
+        pending = stats.pending,
+        "Tier health prune pass complete"
+    );
+
+    let (models, bytes) = config_footprint(&config);
+    metrics::record_prune(stats.last_pruned);
+    metrics::set_config_size(models, bytes);
+    metrics::set_health_size(&health_tracker.footprint());
+    Some(stats)
+}
+
+/// Prune on `config.interval` until the returned task is aborted
+///
+/// Returns None when pruning is disabled.
+pub fn spawn_pruner(
+    config_cache: Arc<TierConfigCache>,
+    health_tracker: Arc<ProviderHealthTracker>,
+    config: PruneConfig,
+) -> Option<JoinHandle<()>> {
+    if config.interval.is_zero() {
+        return None;
+    }
+    Some(tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(config.interval);
+        // The first tick completes immediately; the config is usually not loaded yet
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            prune_once(&config_cache, &health_tracker, config.retention).await;
+        }
+    }))
+}
+
+/// In-memory tier state, as returned by `GET /admin/tiers/state`
+#[derive(Debug, Clone, Serialize)]
+pub struct TierStateReport {
+    /// Version of the cached tier config (None = not cached)
+    pub config_version: Option<String>,
+    /// Provider/models in the cached tier config
+    pub config_models: usize,
+    /// Serialized size of the cached tier config
+    pub config_bytes: usize,
+    /// Entry counts and approximate size of the health tracker
+    pub health: HealthFootprint,
+    /// Health of every tracked provider/model
+    pub models: Vec<ModelHealthSnapshot>,
+    /// Prune schedule and counters
+    pub prune: PruneReport,
+}
+
+/// Prune schedule and counters of a [`TierStateReport`]
+#[derive(Debug, Clone, Serialize)]
+pub struct PruneReport {
+    pub interval_seconds: u64,
+    pub retention_hours: u64,
+    #[serde(flatten)]
+    pub stats: PruneStats,
+}
+
+/// Current tier state, reading the config from the cache only
+pub async fn report(
+    config_cache: &TierConfigCache,
+    health_tracker: &ProviderHealthTracker,
+    config: PruneConfig,
+) -> TierStateReport {
+    let cached = config_cache.cached_config().await;
+    let (config_models, config_bytes) = cached.as_ref().map_or((0, 0), config_footprint);
+    TierStateReport {
+        config_version: cached.map(|config| config.version),
+        config_models,
+        config_bytes,
+        health: health_tracker.footprint(),
+        models: health_tracker.snapshots(),
+        prune: PruneReport {
+            interval_seconds: config.interval.as_secs(),
+            retention_hours: config.retention.as_secs() / 3600,
+            stats: health_tracker.prune_stats(),
+        },
+    }
+}
+
+/// Gauges for the in-memory tier state
+pub mod metrics {
+    use metrics::{counter, gauge};
+
+    use super::HealthFootprint;
+
+    /// Record the entries removed by a prune pass
+    pub fn record_prune(pruned: usize) {
+        counter!("sentinel_tier_health_pruned_total").increment(pruned as u64);
+    }
+
+    /// Set the model count and serialized size of the cached tier config
+    pub fn set_config_size(models: usize, bytes: usize) {
+        gauge!("sentinel_tier_config_models").set(models as f64);
+        gauge!("sentinel_tier_config_bytes").set(bytes as f64);
+    }
+
+    /// Set the entry counts and approximate size of the health tracker
+    pub fn set_health_size(footprint: &HealthFootprint) {
+        for (kind, entries) in [
+            ("health", footprint.health_entries),
+            ("latency", footprint.latency_entries),
+            ("breaker", footprint.breaker_entries),
+        ] {
+            gauge!("sentinel_tier_health_entries", "kind" => kind).set(entries as f64);
+        }
+        gauge!("sentinel_tier_health_bytes").set(footprint.approx_bytes as f64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zion::models::{ModelConfig, TierMapping};
+
+    fn model(provider: &str, model: &str) -> ModelConfig {
+        ModelConfig {
+            provider: provider.to_string(),
+            model: model.to_string(),
+            relative_cost: 1,
+            input_price_per_million: 0.0,
+            output_price_per_million: 0.0,
+            reasoning: false,
+            strip_reasoning: false,
+        }
+    }
+
+    #[test]
+    fn test_configured_models_spans_all_tiers() {
+        let config = TierConfigData {
+            version: "1".to_string(),
+            updated_at: String::new(),
+            tiers: TierMapping {
+                simple: vec![model("openai", "gpt-4o-mini")],
+                moderate: vec![model("openai", "gpt-4o-mini"), model("openai", "gpt-4o")],
+                complex: vec![model("anthropic", "claude-3")],
+            },
+            system_prompts: None,
+            long_context_models: None,
+            embedding_models: None,
+        };
+        let models = configured_models(&config);
+        assert_eq!(models.len(), 3);
+        assert!(models.contains(&("anthropic".to_string(), "claude-3".to_string())));
+        assert!(config_footprint(&config).1 > 0);
+    }
+
+    #[test]
+    fn test_prune_config_from_provider_config() {
+        let mut provider = crate::config::Config::for_tests().provider;
+        provider.tier_health_retention_hours = 2;
+        provider.tier_health_prune_interval_seconds = 30;
+        assert_eq!(
+            PruneConfig::from(&provider),
+            PruneConfig {
+                interval: Duration::from_secs(30),
+                retention: Duration::from_secs(7200),
+            }
+        );
+    }
+}
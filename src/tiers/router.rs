@@ -1,13 +1,17 @@
 //! Tier-based model routing
 //!
 //! Selects models for tiers using cost-weighted probabilistic selection
-//! with health-aware filtering.
+//! with health-aware filtering. With `TIER_LATENCY_WEIGHT` above 0 the cost
+//! weights are blended with each model's live p95 latency, so persistently
+//! slow models get proportionally less traffic without being excluded.
 
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use rand::distr::weighted::WeightedIndex;
 use rand::prelude::*;
 use rand::rng;
+use rand::rngs::StdRng;
 use tracing::{debug, info, warn};
 
 use crate::{
@@ -29,15 +33,97 @@ pub struct SelectedModel {
     pub tier: Tier,
 }
 
+/// Selection inputs for one candidate model, logged with each routing decision
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoutingCandidate {
+    pub provider: String,
+    pub model: String,
+    /// Share of the cost weights (1 / relative_cost)
+    pub cost_share: f64,
+    /// Live p95 latency (None = not enough samples yet)
+    pub p95_ms: Option<u64>,
+    /// Share of the latency weights (1 / p95)
+    pub latency_share: f64,
+    /// Blended selection weight
+    pub weight: f64,
+}
+
+/// Blend cost and latency shares into selection weights
+///
+/// `weight = (1 - latency_weight) * cost_share + latency_weight * latency_share`,
+/// where each share is the model's part of the tier total (1 / relative_cost
+/// and 1 / p95 respectively). Models without a p95 yet are given the mean of
+/// the known latency weights; if no model has one, latency shares equal cost
+/// shares. `latency_weight` is clamped to 0..=1 (0 = cost only).
+///
+/// Returns `(cost_share, latency_share, weight)` per model.
+pub fn blend_weights(
+    costs: &[u8],
+    p95s: &[Option<Duration>],
+    latency_weight: f64,
+) -> Vec<(f64, f64, f64)> {
+    let cost_shares = shares(
+        &costs
+            .iter()
+            .map(|&cost| 1.0 / cost.max(1) as f64)
+            .collect::<Vec<_>>(),
+    );
+
+    let known: Vec<f64> = p95s
+        .iter()
+        .flatten()
+        .map(|p95| 1.0 / p95.as_secs_f64().max(0.001))
+        .collect();
+    let latency_shares = if known.is_empty() {
+        cost_shares.clone()
+    } else {
+        let mean = known.iter().sum::<f64>() / known.len() as f64;
+        shares(
+            &p95s
+                .iter()
+                .map(|p95| p95.map_or(mean, |p95| 1.0 / p95.as_secs_f64().max(0.001)))
+                .collect::<Vec<_>>(),
+        )
+    };
+
+    let latency_weight = if latency_weight.is_finite() {
+        latency_weight.clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    cost_shares
+        .iter()
+        .zip(&latency_shares)
+        .map(|(&cost, &latency)| {
+            (
+                cost,
+                latency,
+                (1.0 - latency_weight) * cost + latency_weight * latency,
+            )
+        })
+        .collect()
+}
+
+/// Normalize weights so they sum to 1
+fn shares(weights: &[f64]) -> Vec<f64> {
+    let total: f64 = weights.iter().sum();
+    weights.iter().map(|weight| weight / total).collect()
+}
+
 /// Tier-based model router
 ///
 /// Selects models for complexity tiers using:
 /// 1. Health-aware filtering (skip unavailable providers)
-/// 2. Cost-weighted probabilistic selection (favor cheaper options)
+/// 2. Cost-weighted probabilistic selection (favor cheaper options),
+///    optionally blended with live latency
 /// 3. Single retry with next model on failure
 pub struct TierRouter {
     config_cache: Arc<TierConfigCache>,
     health_tracker: Arc<ProviderHealthTracker>,
+    /// Share of the selection weight given to latency (0 = cost only)
+    latency_weight: f64,
+    /// Seeded RNG for reproducible selection (None = thread RNG)
+    seeded_rng: Option<Mutex<StdRng>>,
 }
 
 impl TierRouter {
@@ -49,9 +135,23 @@ impl TierRouter {
         Self {
             config_cache,
             health_tracker,
+            latency_weight: 0.0,
+            seeded_rng: None,
         }
     }
 
+    /// Blend live p95 latency into the cost weights (`TIER_LATENCY_WEIGHT`, 0..=1)
+    pub fn with_latency_weight(mut self, latency_weight: f64) -> Self {
+        self.latency_weight = latency_weight;
+        self
+    }
+
+    /// Draw selections from an RNG seeded with `seed` (reproducible in tests)
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seeded_rng = Some(Mutex::new(StdRng::seed_from_u64(seed)));
+        self
+    }
+
     /// Select a model for the given tier
     ///
     /// Returns the selected model considering health and cost.
@@ -126,7 +226,7 @@ impl TierRouter {
         }
 
         // Cost-weighted selection
-        let selected = self.select_weighted(&healthy_models)?;
+        let selected = self.select_weighted(tier, &healthy_models)?;
 
         info!(
             tier = %tier,
@@ -147,8 +247,13 @@ impl TierRouter {
     /// Select a model using cost-weighted random selection
     ///
     /// Lower relative_cost = higher probability of selection.
-    /// Weight = 1 / relative_cost
-    fn select_weighted<'a>(&self, models: &[&'a ModelConfig]) -> AppResult<&'a ModelConfig> {
+    /// Weight = 1 / relative_cost, blended with 1 / p95 latency when a
+    /// latency weight is configured (see [`blend_weights`]).
+    fn select_weighted<'a>(
+        &self,
+        tier: Tier,
+        models: &[&'a ModelConfig],
+    ) -> AppResult<&'a ModelConfig> {
         if models.is_empty() {
             return Err(AppError::BadRequest("No models available".to_string()));
         }
@@ -157,15 +262,8 @@ impl TierRouter {
             return Ok(models[0]);
         }
 
-        // Calculate weights: inverse of cost (lower cost = higher weight)
-        // Validate relative_cost >= 1 to avoid division by zero
-        let weights: Vec<f64> = models
-            .iter()
-            .map(|m| {
-                let cost = m.relative_cost.max(1) as f64;
-                1.0 / cost
-            })
-            .collect();
+        let candidates = self.candidates(models);
+        let weights: Vec<f64> = candidates.iter().map(|c| c.weight).collect();
 
         let dist = WeightedIndex::new(&weights).map_err(|e| {
             AppError::Internal(anyhow::anyhow!(
@@ -174,12 +272,46 @@ impl TierRouter {
             ))
         })?;
 
-        let mut rng = rng();
-        let index = dist.sample(&mut rng);
+        let index = match &self.seeded_rng {
+            Some(seeded) => dist.sample(&mut *seeded.lock().unwrap()),
+            None => dist.sample(&mut rng()),
+        };
+
+        debug!(
+            tier = %tier,
+            latency_weight = self.latency_weight,
+            selected = %models[index].model,
+            candidates = ?candidates,
+            "Routing decision"
+        );
 
         Ok(models[index])
     }
 
+    /// Blend inputs and weight of each candidate model
+    pub fn candidates(&self, models: &[&ModelConfig]) -> Vec<RoutingCandidate> {
+        let costs: Vec<u8> = models.iter().map(|m| m.relative_cost).collect();
+        let p95s: Vec<Option<Duration>> = models
+            .iter()
+            .map(|m| self.health_tracker.p95_latency(&m.provider, &m.model))
+            .collect();
+
+        blend_weights(&costs, &p95s, self.latency_weight)
+            .into_iter()
+            .zip(models.iter().zip(&p95s))
+            .map(
+                |((cost_share, latency_share, weight), (model, p95))| RoutingCandidate {
+                    provider: model.provider.clone(),
+                    model: model.model.clone(),
+                    cost_share,
+                    p95_ms: p95.map(|p95| p95.as_millis() as u64),
+                    latency_share,
+                    weight,
+                },
+            )
+            .collect()
+    }
+
     /// Get an alternative model for retry after failure
     ///
     /// Returns a different model from the same tier if available.
@@ -209,7 +341,7 @@ impl TierRouter {
             return Ok(None);
         }
 
-        let selected = self.select_weighted(&alternatives)?;
+        let selected = self.select_weighted(tier, &alternatives)?;
 
         info!(
             tier = %tier,
@@ -231,6 +363,11 @@ impl TierRouter {
         self.health_tracker.record_success(provider, model);
     }
 
+    /// Record how long a successful request to a model took
+    pub fn record_latency(&self, provider: &str, model: &str, latency: Duration) {
+        self.health_tracker.record_latency(provider, model, latency);
+    }
+
     /// Record a failed request for a model
     pub fn record_failure(&self, provider: &str, model: &str) {
         self.health_tracker.record_failure(provider, model);
@@ -240,6 +377,180 @@ impl TierRouter {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cache::InMemoryCache;
+    use crate::config::Config;
+    use crate::zion::ZionClient;
+
+    fn model(name: &str, relative_cost: u8) -> ModelConfig {
+        ModelConfig {
+            provider: "openai".to_string(),
+            model: name.to_string(),
+            relative_cost,
+            input_price_per_million: 1.0,
+            output_price_per_million: 1.0,
+            reasoning: false,
+            strip_reasoning: false,
+        }
+    }
+
+    /// Router whose selections are drawn from a seeded RNG (the config cache is never read)
+    fn router(latency_weight: f64, seed: u64) -> TierRouter {
+        let config = Config::for_tests();
+        let zion = Arc::new(ZionClient::new(reqwest::Client::new(), &config));
+        let cache = Arc::new(TierConfigCache::new_for_testing(
+            Arc::new(InMemoryCache::new(60)),
+            zion,
+            60,
+        ));
+        TierRouter::new(cache, Arc::new(ProviderHealthTracker::new()))
+            .with_latency_weight(latency_weight)
+            .with_seed(seed)
+    }
+
+    /// Feed a model `count` latencies cycling through `pattern_ms`
+    fn feed(router: &TierRouter, model: &str, pattern_ms: &[u64], count: usize) {
+        for ms in pattern_ms.iter().cycle().take(count) {
+            router.record_latency("openai", model, Duration::from_millis(*ms));
+        }
+    }
+
+    /// Share of `draws` selections that picked `name`
+    fn share_of(router: &TierRouter, models: &[ModelConfig], name: &str, draws: usize) -> f64 {
+        let refs: Vec<&ModelConfig> = models.iter().collect();
+        let hits = (0..draws)
+            .filter(|_| router.select_weighted(Tier::Simple, &refs).unwrap().model == name)
+            .count();
+        hits as f64 / draws as f64
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 0.02,
+            "expected ~{:.3}, got {:.3}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn test_blend_weights_cost_only_by_default() {
+        let p95s = [
+            Some(Duration::from_secs(8)),
+            Some(Duration::from_millis(500)),
+        ];
+        for latency_weight in [0.0, -1.0, f64::NAN] {
+            let weights = blend_weights(&[1, 2], &p95s, latency_weight);
+            assert!((weights[0].2 - 2.0 / 3.0).abs() < 1e-9);
+            assert!((weights[1].2 - 1.0 / 3.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_blend_weights_latency_shares() {
+        // 1/4s and 1/1s: the fast model gets 80% of the latency share
+        let p95s = [Some(Duration::from_secs(4)), Some(Duration::from_secs(1))];
+        let weights = blend_weights(&[1, 2], &p95s, 0.5);
+        assert!((weights[0].1 - 0.2).abs() < 1e-9);
+        assert!((weights[1].1 - 0.8).abs() < 1e-9);
+        assert!((weights[0].2 - (0.5 * 2.0 / 3.0 + 0.5 * 0.2)).abs() < 1e-9);
+        assert!((weights.iter().map(|w| w.2).sum::<f64>() - 1.0).abs() < 1e-9);
+
+        // Above 1 is latency only
+        let weights = blend_weights(&[1, 2], &p95s, 3.0);
+        assert!((weights[0].2 - 0.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_blend_weights_missing_latency_is_neutral() {
+        // No samples anywhere: latency shares follow cost
+        let weights = blend_weights(&[1, 4], &[None, None], 1.0);
+        assert!((weights[0].2 - 0.8).abs() < 1e-9);
+
+        // A model without samples gets the mean of the known latency weights
+        let p95s = [
+            Some(Duration::from_secs(1)),
+            None,
+            Some(Duration::from_millis(500)),
+        ];
+        let weights = blend_weights(&[1, 1, 1], &p95s, 1.0);
+        // 1/s weights 1, 1.5 (mean) and 2
+        assert!((weights[1].1 - 1.5 / 4.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_seeded_selection_is_reproducible() {
+        let models = [model("gpt-4o-mini", 1), model("gpt-4o", 2), model("o3", 5)];
+        let refs: Vec<&ModelConfig> = models.iter().collect();
+        let draw = |router: &TierRouter| -> Vec<String> {
+            (0..50)
+                .map(|_| {
+                    router
+                        .select_weighted(Tier::Simple, &refs)
+                        .unwrap()
+                        .model
+                        .clone()
+                })
+                .collect()
+        };
+        assert_eq!(draw(&router(0.5, 7)), draw(&router(0.5, 7)));
+        assert_ne!(draw(&router(0.5, 7)), draw(&router(0.5, 8)));
+    }
+
+    #[test]
+    fn test_simulation_zero_weight_ignores_latency() {
+        let router = router(0.0, 1);
+        let models = [model("cheap", 1), model("fast", 2)];
+        feed(&router, "cheap", &[8000], 100);
+        feed(&router, "fast", &[300], 100);
+        assert_close(share_of(&router, &models, "cheap", 20_000), 2.0 / 3.0);
+    }
+
+    #[test]
+    fn test_simulation_slowdown_shifts_traffic() {
+        let models = [model("cheap", 1), model("fast", 2)];
+        // Cheap model: mostly fine, but a slow tail puts its p95 at 4s
+        let cheap_pattern = [400, 500, 600, 450, 550, 480, 520, 4000, 4000, 4000];
+        let fast_pattern = [900, 1000, 950, 1000, 980];
+
+        let mut previous = 1.0;
+        for latency_weight in [0.0, 0.25, 0.5, 0.75, 1.0] {
+            let router = router(latency_weight, 42);
+            feed(&router, "cheap", &cheap_pattern, 100);
+            feed(&router, "fast", &fast_pattern, 100);
+
+            let candidates = router.candidates(&models.iter().collect::<Vec<_>>());
+            assert_eq!(candidates[0].p95_ms, Some(4000));
+            assert_eq!(candidates[1].p95_ms, Some(1000));
+
+            // Cost shares 2/3 : 1/3, latency shares 0.2 : 0.8
+            let expected = (1.0 - latency_weight) * 2.0 / 3.0 + latency_weight * 0.2;
+            let share = share_of(&router, &models, "cheap", 20_000);
+            assert_close(share, expected);
+            assert!(
+                share < previous,
+                "traffic didn't shift at {}",
+                latency_weight
+            );
+            // Slow, but never excluded
+            assert!(share > 0.1);
+            previous = share;
+        }
+    }
+
+    #[test]
+    fn test_simulation_recovery_restores_cost_split() {
+        let router = router(0.5, 3);
+        let models = [model("cheap", 1), model("fast", 2)];
+        feed(&router, "cheap", &[5000], 100);
+        feed(&router, "fast", &[1000], 100);
+        let slow = share_of(&router, &models, "cheap", 10_000);
+
+        // Once the slowdown is over, equal latencies leave the cost split
+        feed(&router, "cheap", &[1000], 100);
+        let recovered = share_of(&router, &models, "cheap", 10_000);
+        assert!(recovered > slow);
+        assert_close(recovered, 0.5 * 2.0 / 3.0 + 0.5 * 0.5);
+    }
 
     // Note: Full tests require mocking TierConfigCache.
     // These tests verify the weight calculation logic.
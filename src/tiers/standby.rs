@@ -0,0 +1,144 @@
+//! Last-known-good tier config
+//!
+//! Every tier config fetched from Zion is also kept as a standby copy in
+//! Redis and, with `TIER_CONFIG_STANDBY_PATH`, in a local file. When nothing
+//! is cached and Zion can't be reached, `TierConfigCache` serves the standby
+//! copy as long as it is younger than `TIER_CONFIG_MAX_STALENESS_HOURS`, so a
+//! pod started during a Zion outage can still route native requests. The
+//! copy's age is reported on `/health/ready`, and Zion is retried in the
+//! background until it answers.
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use crate::zion::models::TierConfigData;
+
+use super::cache::TierConfigCache;
+
+/// Time between background Zion retries while the standby copy is served
+pub const RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A tier config as last fetched from Zion
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StandbyTierConfig {
+    pub config: TierConfigData,
+    /// Unix time the config was fetched
+    pub fetched_at: i64,
+}
+
+/// Where a served standby copy was read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StandbySource {
+    Redis,
+    File,
+}
+
+/// Standby tier config currently being served
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StandbyStatus {
+    pub source: StandbySource,
+    pub version: String,
+    /// Unix time the config was fetched from Zion
+    pub fetched_at: i64,
+    pub age_seconds: u64,
+}
+
+/// Read a standby copy from `path`
+///
+/// None when the file is missing or unreadable; a corrupt file is logged.
+pub async fn read_file(path: &Path) -> Option<StandbyTierConfig> {
+    let bytes = match tokio::fs::read(path).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            debug!(path = %path.display(), error = %e, "No standby tier config file");
+            return None;
+        }
+    };
+    match serde_json::from_slice(&bytes) {
+        Ok(standby) => Some(standby),
+        Err(e) => {
+            warn!(path = %path.display(), error = %e, "Ignoring unreadable standby tier config file");
+            None
+        }
+    }
+}
+
+/// Write a standby copy to `path`, replacing the previous one atomically
+pub async fn write_file(path: &Path, standby: &StandbyTierConfig) -> std::io::Result<()> {
+    let staging = path.with_extension("tmp");
+    tokio::fs::write(&staging, serde_json::to_vec(standby)?).await?;
+    tokio::fs::rename(&staging, path).await
+}
+
+/// Load the tier config once at startup
+///
+/// If Zion can't be reached (whether or not a standby copy is served), Zion
+/// is retried every `RETRY_INTERVAL` in the background until it answers.
+pub async fn load_at_startup(cache: Arc<TierConfigCache>) -> Option<JoinHandle<()>> {
+    let loaded = cache.get_config().await;
+    match (&loaded, cache.standby_status()) {
+        (Ok(config), None) => {
+            info!(version = %config.version, "Tier config loaded");
+            return None;
+        }
+        (Ok(_), Some(standby)) => warn!(
+            version = %standby.version,
+            age_seconds = standby.age_seconds,
+            source = ?standby.source,
+            "Zion unreachable at startup; serving the standby tier config"
+        ),
+        (Err(e), _) => warn!(
+            error = %e,
+            "Tier config unavailable at startup; native requests fail until Zion answers"
+        ),
+    }
+    Some(spawn_retry(cache, RETRY_INTERVAL))
+}
+
+/// Retry Zion every `interval` until a fresh tier config is fetched
+pub fn spawn_retry(cache: Arc<TierConfigCache>, interval: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(interval).await;
+            match cache.refresh().await {
+                Ok(config) => {
+                    info!(version = %config.version, "Fetched the tier config from Zion");
+                    return;
+                }
+                Err(e) => debug!(error = %e, "Zion still unreachable for the tier config"),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::zion::tier_config_body;
+
+    #[tokio::test]
+    async fn test_file_round_trip() {
+        let path =
+            std::env::temp_dir().join(format!("sentinel-tiers-{}.json", uuid::Uuid::new_v4()));
+        assert_eq!(read_file(&path).await, None);
+
+        let config: TierConfigData =
+            serde_json::from_value(tier_config_body()["data"].clone()).unwrap();
+        let standby = StandbyTierConfig {
+            config,
+            fetched_at: 1_700_000_000,
+        };
+        write_file(&path, &standby).await.unwrap();
+        assert_eq!(read_file(&path).await, Some(standby));
+
+        tokio::fs::write(&path, b"{\"config\":").await.unwrap();
+        assert_eq!(read_file(&path).await, None);
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+}
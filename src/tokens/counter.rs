@@ -2,53 +2,214 @@
 //!
 //! Uses tiktoken-rs for accurate token counting compatible with OpenAI models.
 //! Provides both a basic TokenCounter and a thread-safe SharedTokenCounter.
-
+//!
+//! A model's encoding comes from `MODEL_ENCODING_OVERRIDES`, then from
+//! tiktoken-rs; models it doesn't know yet (a new OpenAI family, other
+//! providers) use `TOKEN_FALLBACK_ENCODING`, with a warning the first time
+//! each is seen. Counting never fails: if an encoder can't be loaded, text is
+//! estimated at four bytes per token.
+
+use std::collections::HashMap;
+use std::str::FromStr;
 use std::sync::{Arc, RwLock};
 
-use tiktoken_rs::{get_bpe_from_model, CoreBPE};
+use metrics::counter;
+use tiktoken_rs::{
+    get_bpe_from_tokenizer, tokenizer::get_tokenizer, tokenizer::Tokenizer, CoreBPE,
+};
+
+use crate::{error::AppResult, native::types::ImageDetail};
+
+use super::image;
+
+/// Tokens every chat request adds for priming the reply (`<|start|>assistant<|message|>`)
+pub const REPLY_PRIMING_TOKENS: usize = 3;
+
+/// Models whose encoding is remembered before the cache is cleared
+const MODEL_CACHE_MAX: usize = 1024;
+
+/// Characters of a model name kept in logs
+const MODEL_LOG_CHARS: usize = 64;
+
+/// A tiktoken encoding
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// GPT-4o and later
+    #[default]
+    O200kBase,
+    /// GPT-4, GPT-3.5 and the v3 embeddings
+    Cl100kBase,
+    /// Codex and `text-davinci-002/003`
+    P50kBase,
+    /// The edit models
+    P50kEdit,
+    /// GPT-3 (`gpt2` is an alias)
+    R50kBase,
+}
 
-use crate::error::AppResult;
+impl Encoding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Encoding::O200kBase => "o200k_base",
+            Encoding::Cl100kBase => "cl100k_base",
+            Encoding::P50kBase => "p50k_base",
+            Encoding::P50kEdit => "p50k_edit",
+            Encoding::R50kBase => "r50k_base",
+        }
+    }
+
+    fn tokenizer(self) -> Tokenizer {
+        match self {
+            Encoding::O200kBase => Tokenizer::O200kBase,
+            Encoding::Cl100kBase => Tokenizer::Cl100kBase,
+            Encoding::P50kBase => Tokenizer::P50kBase,
+            Encoding::P50kEdit => Tokenizer::P50kEdit,
+            Encoding::R50kBase => Tokenizer::R50kBase,
+        }
+    }
+
+    fn from_tokenizer(tokenizer: Tokenizer) -> Self {
+        match tokenizer {
+            Tokenizer::O200kBase => Encoding::O200kBase,
+            Tokenizer::Cl100kBase => Encoding::Cl100kBase,
+            Tokenizer::P50kBase => Encoding::P50kBase,
+            Tokenizer::P50kEdit => Encoding::P50kEdit,
+            Tokenizer::R50kBase | Tokenizer::Gpt2 => Encoding::R50kBase,
+        }
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "o200k_base" => Ok(Encoding::O200kBase),
+            "cl100k_base" => Ok(Encoding::Cl100kBase),
+            "p50k_base" => Ok(Encoding::P50kBase),
+            "p50k_edit" => Ok(Encoding::P50kEdit),
+            "r50k_base" | "gpt2" => Ok(Encoding::R50kBase),
+            other => Err(format!(
+                "unknown encoding '{}' (expected o200k_base, cl100k_base, p50k_base, p50k_edit or r50k_base)",
+                other
+            )),
+        }
+    }
+}
 
 /// Token counter for various models
 pub struct TokenCounter {
-    /// Cached encoders for different models
-    encoders: std::collections::HashMap<String, CoreBPE>,
+    /// Encoding for models tiktoken-rs doesn't know
+    fallback: Encoding,
+    /// Model names (or `prefix*` patterns) mapped ahead of tiktoken-rs
+    overrides: HashMap<String, Encoding>,
+    /// Encoding each model resolved to
+    models: HashMap<String, Encoding>,
+    /// Loaded encoders; None when an encoding failed to load
+    encoders: HashMap<Encoding, Option<CoreBPE>>,
 }
 
 impl TokenCounter {
-    /// Create a new token counter
+    /// Create a new token counter, falling back to `o200k_base`
     pub fn new() -> Self {
+        Self::with_encodings(Encoding::default(), HashMap::new())
+    }
+
+    /// Create a token counter with a fallback encoding and model overrides
+    ///
+    /// Override keys are model names, or prefixes ending in `*`
+    /// (`gpt-5*`); an exact name wins over a prefix, a longer prefix over a
+    /// shorter one.
+    pub fn with_encodings(fallback: Encoding, overrides: HashMap<String, Encoding>) -> Self {
         Self {
-            encoders: std::collections::HashMap::new(),
+            fallback,
+            overrides,
+            models: HashMap::new(),
+            encoders: HashMap::new(),
         }
     }
 
-    /// Get or create an encoder for a model
-    fn get_encoder(&mut self, model: &str) -> &CoreBPE {
-        if !self.encoders.contains_key(model) {
-            let encoder = match get_bpe_from_model(model) {
-                Ok(e) => e,
-                Err(e) => {
-                    // Fall back to gpt-4 encoder for unknown models
-                    tracing::warn!(
-                        "Unknown model '{}', falling back to gpt-4 encoder: {}",
-                        model,
-                        e
-                    );
-                    get_bpe_from_model("gpt-4").expect("gpt-4 encoder should exist")
-                }
-            };
+    /// Encoding used for a model
+    pub fn encoding_for_model(&mut self, model: &str) -> Encoding {
+        if let Some(&encoding) = self.models.get(model) {
+            return encoding;
+        }
 
-            self.encoders.insert(model.to_string(), encoder);
+        let encoding = self.lookup(model).unwrap_or_else(|| {
+            tracing::warn!(
+                model = %model.chars().take(MODEL_LOG_CHARS).collect::<String>(),
+                fallback = self.fallback.as_str(),
+                "No tiktoken encoding for model, using the fallback encoding"
+            );
+            counter!(
+                "sentinel_token_encoding_fallbacks_total",
+                "encoding" => self.fallback.as_str()
+            )
+            .increment(1);
+            self.fallback
+        });
+
+        if self.models.len() >= MODEL_CACHE_MAX {
+            self.models.clear();
         }
+        self.models.insert(model.to_string(), encoding);
+        encoding
+    }
 
-        self.encoders.get(model).unwrap()
+    /// Encoding from the overrides or tiktoken-rs, if either knows the model
+    fn lookup(&self, model: &str) -> Option<Encoding> {
+        if let Some(&encoding) = self.overrides.get(model) {
+            return Some(encoding);
+        }
+        let prefixed = self
+            .overrides
+            .iter()
+            .filter_map(|(pattern, &encoding)| {
+                let prefix = pattern.strip_suffix('*')?;
+                model
+                    .starts_with(prefix)
+                    .then_some((prefix.len(), encoding))
+            })
+            .max_by_key(|(len, _)| *len)
+            .map(|(_, encoding)| encoding);
+        prefixed.or_else(|| get_tokenizer(model).map(Encoding::from_tokenizer))
+    }
+
+    /// Get or load the encoder for a model
+    ///
+    /// Falls back to the fallback encoding's encoder if the model's can't be
+    /// loaded; None if neither can.
+    fn get_encoder(&mut self, model: &str) -> Option<&CoreBPE> {
+        let mut encoding = self.encoding_for_model(model);
+        if !self.load(encoding) {
+            encoding = self.fallback;
+            self.load(encoding);
+        }
+        self.encoders.get(&encoding).and_then(Option::as_ref)
+    }
+
+    /// Load an encoding's encoder once, returning whether it is available
+    fn load(&mut self, encoding: Encoding) -> bool {
+        self.encoders
+            .entry(encoding)
+            .or_insert_with(|| match get_bpe_from_tokenizer(encoding.tokenizer()) {
+                Ok(encoder) => Some(encoder),
+                Err(e) => {
+                    tracing::error!(
+                        encoding = encoding.as_str(),
+                        error = %e,
+                        "Failed to load tiktoken encoding, estimating tokens from text length"
+                    );
+                    None
+                }
+            })
+            .is_some()
     }
 
     /// Count tokens in a text string
     pub fn count_tokens(&mut self, model: &str, text: &str) -> usize {
         let encoder = self.get_encoder(model);
-        encoder.encode_with_special_tokens(text).len()
+        encoded_len(encoder, text)
     }
 
     /// Count tokens in a chat message
@@ -67,11 +228,11 @@ impl TokenCounter {
         let tokens_per_name = 1;
 
         let mut count = tokens_per_message;
-        count += encoder.encode_with_special_tokens(role).len();
-        count += encoder.encode_with_special_tokens(content).len();
+        count += encoded_len(encoder, role);
+        count += encoded_len(encoder, content);
 
         if let Some(n) = name {
-            count += encoder.encode_with_special_tokens(n).len();
+            count += encoded_len(encoder, n);
             count += tokens_per_name;
         }
 
@@ -91,10 +252,17 @@ impl TokenCounter {
         }
 
         // Add reply priming tokens (every reply is primed with <|start|>assistant<|message|>)
-        total += 3;
+        total += REPLY_PRIMING_TOKENS;
 
         total
     }
+
+    /// Count tokens for an image part (see [`image::image_tokens`])
+    ///
+    /// `unknown_tokens` is used when the image size can't be read from the URL.
+    pub fn count_image_tokens(&self, url: &str, detail: ImageDetail, unknown_tokens: u64) -> u64 {
+        image::image_tokens(url, detail, unknown_tokens)
+    }
 }
 
 impl Default for TokenCounter {
@@ -103,6 +271,14 @@ impl Default for TokenCounter {
     }
 }
 
+/// Tokens in `text`, or a four-bytes-per-token estimate without an encoder
+fn encoded_len(encoder: Option<&CoreBPE>, text: &str) -> usize {
+    match encoder {
+        Some(encoder) => encoder.encode_with_special_tokens(text).len(),
+        None => text.len().div_ceil(4),
+    }
+}
+
 /// Thread-safe token counter wrapper
 ///
 /// Uses a RwLock to allow concurrent reads while protecting writes.
@@ -121,6 +297,17 @@ impl SharedTokenCounter {
         }
     }
 
+    /// Create a shared token counter with a fallback encoding and model overrides
+    ///
+    /// See [`TokenCounter::with_encodings`].
+    pub fn with_encodings(fallback: Encoding, overrides: HashMap<String, Encoding>) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(TokenCounter::with_encodings(
+                fallback, overrides,
+            ))),
+        }
+    }
+
     /// Count tokens in a text string
     pub fn count_tokens(&self, model: &str, text: &str) -> AppResult<usize> {
         let mut counter = self
@@ -130,6 +317,25 @@ impl SharedTokenCounter {
         Ok(counter.count_tokens(model, text))
     }
 
+    /// Count tokens in each of `texts`, in order
+    ///
+    /// Takes the lock once, so long lists don't contend with other requests
+    /// item by item.
+    pub fn count_tokens_each<S: AsRef<str>>(
+        &self,
+        model: &str,
+        texts: &[S],
+    ) -> AppResult<Vec<usize>> {
+        let mut counter = self
+            .inner
+            .write()
+            .map_err(|e| anyhow::anyhow!("Failed to acquire token counter lock: {}", e))?;
+        Ok(texts
+            .iter()
+            .map(|text| counter.count_tokens(model, text.as_ref()))
+            .collect())
+    }
+
     /// Count tokens in a chat message
     pub fn count_message_tokens(
         &self,
@@ -174,6 +380,13 @@ impl SharedTokenCounter {
             .collect();
         self.count_chat_request_tokens(model, &refs)
     }
+
+    /// Count tokens for an image part
+    ///
+    /// Needs no encoder, so no lock is taken.
+    pub fn count_image_tokens(&self, url: &str, detail: ImageDetail, unknown_tokens: u64) -> u64 {
+        image::image_tokens(url, detail, unknown_tokens)
+    }
 }
 
 impl Default for SharedTokenCounter {
@@ -220,6 +433,20 @@ mod tests {
         assert!(count > 0);
     }
 
+    #[test]
+    fn test_shared_counter_each() {
+        let counter = SharedTokenCounter::new();
+        let counts = counter
+            .count_tokens_each("gpt-4", &["Hello, world!", "", "Hi"])
+            .unwrap();
+        assert_eq!(counts.len(), 3);
+        assert_eq!(
+            counts[0],
+            counter.count_tokens("gpt-4", "Hello, world!").unwrap()
+        );
+        assert_eq!(counts[1], 0);
+    }
+
     #[test]
     fn test_shared_counter_message_tokens() {
         let counter = SharedTokenCounter::new();
@@ -274,14 +501,64 @@ mod tests {
     #[test]
     fn test_count_tokens_claude_fallback() {
         let mut counter = TokenCounter::new();
-        // Claude models should fall back to gpt-4 encoder
+        // Claude models should fall back to the o200k_base encoder
         let text = "Testing Claude model tokenization.";
         let count = counter.count_tokens("claude-3-opus-20240229", text);
         assert!(count > 0, "Claude fallback should return tokens");
 
-        // Verify it matches gpt-4 (since it falls back)
-        let gpt4_count = counter.count_tokens("gpt-4", text);
-        assert_eq!(count, gpt4_count, "Claude should use GPT-4 encoder as fallback");
+        // Verify it matches gpt-4o (since it falls back)
+        let gpt4o_count = counter.count_tokens("gpt-4o", text);
+        assert_eq!(count, gpt4o_count, "Claude should use the o200k_base encoder as fallback");
+    }
+
+    #[test]
+    fn test_encoding_resolution() {
+        let mut counter = TokenCounter::new();
+        assert_eq!(counter.encoding_for_model("gpt-4"), Encoding::Cl100kBase);
+        assert_eq!(counter.encoding_for_model("gpt-4o-mini"), Encoding::O200kBase);
+        // Unknown and empty names use the fallback
+        assert_eq!(counter.encoding_for_model("gpt-7-turbo"), Encoding::O200kBase);
+        assert_eq!(counter.encoding_for_model(""), Encoding::O200kBase);
+
+        let mut counter = TokenCounter::with_encodings(Encoding::Cl100kBase, HashMap::new());
+        assert_eq!(counter.encoding_for_model("gpt-7-turbo"), Encoding::Cl100kBase);
+    }
+
+    #[test]
+    fn test_encoding_overrides() {
+        let overrides = HashMap::from([
+            ("gpt-4".to_string(), Encoding::O200kBase),
+            ("gpt-7*".to_string(), Encoding::P50kBase),
+            ("gpt-7-mini*".to_string(), Encoding::Cl100kBase),
+        ]);
+        let mut counter = TokenCounter::with_encodings(Encoding::O200kBase, overrides);
+        assert_eq!(counter.encoding_for_model("gpt-4"), Encoding::O200kBase);
+        assert_eq!(counter.encoding_for_model("gpt-4-32k"), Encoding::Cl100kBase);
+        assert_eq!(counter.encoding_for_model("gpt-7"), Encoding::P50kBase);
+        assert_eq!(counter.encoding_for_model("gpt-7-mini-2026"), Encoding::Cl100kBase);
+    }
+
+    #[test]
+    fn test_unusual_model_names_never_panic() {
+        let counter = SharedTokenCounter::new();
+        let long_model = "x".repeat(100_000);
+        for model in ["", " ", "\0", "模型", long_model.as_str()] {
+            assert!(counter.count_tokens(model, "Hello, world!").unwrap() > 0);
+            assert!(counter.count_message_tokens(model, "user", "Hi", Some("n")).unwrap() > 0);
+        }
+        // Distinct unknown models don't grow the cache without bound
+        let mut counter = TokenCounter::new();
+        for i in 0..MODEL_CACHE_MAX + 10 {
+            counter.encoding_for_model(&format!("model-{}", i));
+        }
+        assert!(counter.models.len() <= MODEL_CACHE_MAX);
+    }
+
+    #[test]
+    fn test_parse_encoding() {
+        assert_eq!(" O200K_BASE ".parse(), Ok(Encoding::O200kBase));
+        assert_eq!("gpt2".parse(), Ok(Encoding::R50kBase));
+        assert!("o300k_base".parse::<Encoding>().is_err());
     }
 
     #[test]
@@ -449,7 +726,7 @@ mod tests {
         assert_eq!(count1, count2);
 
         // Verify encoder is cached
-        assert!(counter.encoders.contains_key("gpt-4"));
+        assert!(counter.encoders.contains_key(&Encoding::Cl100kBase));
     }
 
     #[test]
@@ -459,10 +736,11 @@ mod tests {
         // Create encoders for different models
         counter.count_tokens("gpt-4", "Hello");
         counter.count_tokens("gpt-3.5-turbo", "Hello");
+        counter.count_tokens("gpt-4o", "Hello");
 
-        // Both should be cached
-        assert!(counter.encoders.contains_key("gpt-4"));
-        assert!(counter.encoders.contains_key("gpt-3.5-turbo"));
+        // Models sharing an encoding share its encoder
+        assert!(counter.encoders.contains_key(&Encoding::Cl100kBase));
+        assert!(counter.encoders.contains_key(&Encoding::O200kBase));
         assert_eq!(counter.encoders.len(), 2);
     }
 
@@ -470,11 +748,11 @@ mod tests {
     fn test_unknown_model_encoder_caching() {
         let mut counter = TokenCounter::new();
 
-        // Unknown model should fall back to gpt-4 but cache under its own name
+        // Unknown model should fall back to o200k_base, remembered under its own name
         counter.count_tokens("my-custom-model", "Hello");
 
-        // Should be cached under the unknown model name
-        assert!(counter.encoders.contains_key("my-custom-model"));
+        assert_eq!(counter.models["my-custom-model"], Encoding::O200kBase);
+        assert!(counter.encoders.contains_key(&Encoding::O200kBase));
     }
 
     // ===========================================
@@ -0,0 +1,221 @@
+//! Image token estimation for vision requests
+//!
+//! Follows OpenAI's published formula: `low` detail costs a flat 85 tokens;
+//! `high` detail first fits the image within 2048x2048, then scales it so the
+//! shortest side is at most 768px, and costs 85 tokens plus 170 per 512px tile.
+//! Dimensions are read from data URLs by parsing the PNG, JPEG or WebP header;
+//! remote URLs are never fetched, so their size is unknown.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::native::types::ImageDetail;
+
+/// Flat cost of a low-detail image (also the base cost of a high-detail one)
+pub const BASE_IMAGE_TOKENS: u64 = 85;
+
+/// Cost of each 512px tile of a high-detail image
+const TILE_TOKENS: u64 = 170;
+
+const TILE_SIZE: f64 = 512.0;
+const MAX_SIDE: f64 = 2048.0;
+const MAX_SHORT_SIDE: f64 = 768.0;
+
+/// Estimate for images of unknown size: the largest possible high-detail cost
+/// (a 2048x768 image after resizing, 8 tiles)
+pub const DEFAULT_UNKNOWN_IMAGE_TOKENS: u64 = 1445;
+
+/// Base64 characters decoded when sniffing a data URL (enough for JPEG EXIF blocks)
+const SNIFF_BASE64_CHARS: usize = 256 * 1024;
+
+/// Tokens for a high-detail image of the given size
+pub fn high_detail_tokens(width: u32, height: u32) -> u64 {
+    if width == 0 || height == 0 {
+        return BASE_IMAGE_TOKENS;
+    }
+
+    let (mut width, mut height) = (width as f64, height as f64);
+    let longest = width.max(height);
+    if longest > MAX_SIDE {
+        let scale = MAX_SIDE / longest;
+        width *= scale;
+        height *= scale;
+    }
+    let shortest = width.min(height);
+    if shortest > MAX_SHORT_SIDE {
+        let scale = MAX_SHORT_SIDE / shortest;
+        width *= scale;
+        height *= scale;
+    }
+
+    let tiles = (width.round() / TILE_SIZE).ceil() as u64 * (height.round() / TILE_SIZE).ceil() as u64;
+    BASE_IMAGE_TOKENS + TILE_TOKENS * tiles
+}
+
+/// Tokens for an image part
+///
+/// `auto` is estimated as `high`; images whose size can't be read cost
+/// `unknown_tokens` unless they are low detail.
+pub fn image_tokens(url: &str, detail: ImageDetail, unknown_tokens: u64) -> u64 {
+    if detail == ImageDetail::Low {
+        return BASE_IMAGE_TOKENS;
+    }
+    match image_dimensions(url) {
+        Some((width, height)) => high_detail_tokens(width, height),
+        None => unknown_tokens,
+    }
+}
+
+/// Width and height of a base64 data URL image (PNG, JPEG or WebP)
+pub fn image_dimensions(url: &str) -> Option<(u32, u32)> {
+    let rest = url.strip_prefix("data:")?;
+    let (meta, payload) = rest.split_once(',')?;
+    if !meta.ends_with(";base64") {
+        return None;
+    }
+
+    // Only the header is needed; decode a prefix on a 4-character boundary
+    let prefix = &payload[..payload.len().min(SNIFF_BASE64_CHARS)];
+    let prefix = &prefix[..prefix.len() - prefix.len() % 4];
+    let bytes = STANDARD.decode(prefix).ok()?;
+
+    png_dimensions(&bytes)
+        .or_else(|| jpeg_dimensions(&bytes))
+        .or_else(|| webp_dimensions(&bytes))
+}
+
+fn be_u16(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u16::from_be_bytes(bytes.get(at..at + 2)?.try_into().ok()?) as u32)
+}
+
+fn le_u16(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?) as u32)
+}
+
+fn le_u24(bytes: &[u8], at: usize) -> Option<u32> {
+    let b = bytes.get(at..at + 3)?;
+    Some(b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16)
+}
+
+/// PNG: signature, then the IHDR chunk with big-endian width and height
+fn png_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    if !bytes.starts_with(b"\x89PNG\r\n\x1a\n") || bytes.get(12..16)? != b"IHDR" {
+        return None;
+    }
+    let width = u32::from_be_bytes(bytes.get(16..20)?.try_into().ok()?);
+    let height = u32::from_be_bytes(bytes.get(20..24)?.try_into().ok()?);
+    Some((width, height))
+}
+
+/// JPEG: walk the segments up to the first start-of-frame marker
+fn jpeg_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    if !bytes.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let mut pos = 2;
+    loop {
+        if *bytes.get(pos)? != 0xFF {
+            return None;
+        }
+        let marker = *bytes.get(pos + 1)?;
+        match marker {
+            // Fill bytes and standalone markers carry no length
+            0xFF => pos += 1,
+            0x01 | 0xD0..=0xD7 => pos += 2,
+            // SOF0-SOF15, except DHT (C4), JPG (C8) and DAC (CC)
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                let height = be_u16(bytes, pos + 5)?;
+                let width = be_u16(bytes, pos + 7)?;
+                return Some((width, height));
+            }
+            _ => pos += 2 + be_u16(bytes, pos + 2)? as usize,
+        }
+    }
+}
+
+/// WebP: RIFF container with a lossy (VP8), lossless (VP8L) or extended (VP8X) chunk
+fn webp_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    if bytes.get(0..4)? != b"RIFF" || bytes.get(8..12)? != b"WEBP" {
+        return None;
+    }
+    match bytes.get(12..16)? {
+        b"VP8 " => Some((le_u16(bytes, 26)? & 0x3FFF, le_u16(bytes, 28)? & 0x3FFF)),
+        b"VP8L" => {
+            let bits = u32::from_le_bytes(bytes.get(21..25)?.try_into().ok()?);
+            Some(((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1))
+        }
+        b"VP8X" => Some((le_u24(bytes, 24)? + 1, le_u24(bytes, 27)? + 1)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data_url(mime: &str, bytes: &[u8]) -> String {
+        format!("data:{};base64,{}", mime, STANDARD.encode(bytes))
+    }
+
+    /// Minimal PNG: signature and IHDR chunk
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = b"\x89PNG\r\n\x1a\n".to_vec();
+        bytes.extend_from_slice(&13u32.to_be_bytes());
+        bytes.extend_from_slice(b"IHDR");
+        bytes.extend_from_slice(&width.to_be_bytes());
+        bytes.extend_from_slice(&height.to_be_bytes());
+        bytes.extend_from_slice(&[8, 6, 0, 0, 0, 0, 0, 0, 0]);
+        bytes
+    }
+
+    /// Minimal JPEG: SOI, an APP0 segment, then SOF0
+    fn jpeg(width: u16, height: u16) -> Vec<u8> {
+        let mut bytes = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10];
+        bytes.extend_from_slice(b"JFIF\0\x01\x01\0\0\x01\0\x01\0\0");
+        bytes.extend_from_slice(&[0xFF, 0xC0, 0x00, 0x11, 0x08]);
+        bytes.extend_from_slice(&height.to_be_bytes());
+        bytes.extend_from_slice(&width.to_be_bytes());
+        bytes.extend_from_slice(&[0x03, 0x01, 0x22, 0x00, 0x02, 0x11, 0x01, 0x03, 0x11, 0x01]);
+        bytes
+    }
+
+    /// Minimal extended WebP: RIFF header and VP8X chunk
+    fn webp(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = b"RIFF\x1e\0\0\0WEBPVP8X\x0a\0\0\0\0\0\0\0".to_vec();
+        bytes.extend_from_slice(&(width - 1).to_le_bytes()[..3]);
+        bytes.extend_from_slice(&(height - 1).to_le_bytes()[..3]);
+        bytes
+    }
+
+    #[test]
+    fn test_dimensions_from_data_urls() {
+        assert_eq!(image_dimensions(&data_url("image/png", &png(1024, 768))), Some((1024, 768)));
+        assert_eq!(image_dimensions(&data_url("image/jpeg", &jpeg(640, 480))), Some((640, 480)));
+        assert_eq!(image_dimensions(&data_url("image/webp", &webp(4000, 3000))), Some((4000, 3000)));
+        assert_eq!(image_dimensions("https://example.com/cat.png"), None);
+        assert_eq!(image_dimensions("data:image/png;base64,not-an-image"), None);
+    }
+
+    #[test]
+    fn test_high_detail_formula_matches_documented_examples() {
+        // 1024x1024 -> 768x768 -> 2x2 tiles
+        assert_eq!(high_detail_tokens(1024, 1024), 765);
+        // 2048x4096 -> 1024x2048 -> 768x1536 -> 2x3 tiles
+        assert_eq!(high_detail_tokens(2048, 4096), 1105);
+        // Small images are not upscaled: 512x512 is one tile
+        assert_eq!(high_detail_tokens(512, 512), 255);
+        // 4000x3000 -> 2048x1536 -> 1024x768 -> 2x2 tiles
+        assert_eq!(high_detail_tokens(4000, 3000), 765);
+    }
+
+    #[test]
+    fn test_image_tokens_by_detail() {
+        let url = data_url("image/png", &png(2048, 4096));
+        assert_eq!(image_tokens(&url, ImageDetail::Low, DEFAULT_UNKNOWN_IMAGE_TOKENS), 85);
+        assert_eq!(image_tokens(&url, ImageDetail::High, DEFAULT_UNKNOWN_IMAGE_TOKENS), 1105);
+        assert_eq!(image_tokens(&url, ImageDetail::Auto, DEFAULT_UNKNOWN_IMAGE_TOKENS), 1105);
+
+        let remote = "https://example.com/cat.png";
+        assert_eq!(image_tokens(remote, ImageDetail::High, 1000), 1000);
+        assert_eq!(image_tokens(remote, ImageDetail::Low, 1000), 85);
+    }
+}
@@ -1,7 +1,10 @@
 //! Token counting module
 //!
-//! Provides token counting functionality using tiktoken-rs.
+//! Provides token counting functionality using tiktoken-rs, plus image token
+//! estimation for vision input.
 
 pub mod counter;
+pub mod image;
+pub mod truncation;
 
-pub use counter::{SharedTokenCounter, TokenCounter};
+pub use counter::{Encoding, SharedTokenCounter, TokenCounter};
@@ -0,0 +1,147 @@
+//! Conversation truncation suggestions
+//!
+//! Given per-message token counts, works out which messages to drop so a
+//! conversation fits a token budget: oldest first, never a system message and
+//! never the last message (the one being answered). Tool results left without
+//! the assistant turn that requested them are dropped along with it.
+
+use crate::native::types::Role;
+
+/// Messages to drop to fit a token budget
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TruncationPlan {
+    /// Indices of the messages to drop, ascending
+    pub drop: Vec<usize>,
+    /// Prompt tokens left after dropping them
+    pub total_tokens: u64,
+    /// Whether the remaining conversation fits the budget
+    pub fits: bool,
+}
+
+/// Plan which messages to drop so `messages` fits `max_tokens`
+///
+/// `messages` holds each message's role and token count; `overhead` is the
+/// per-request cost outside any message (reply priming). When even the
+/// messages that can't be dropped exceed the budget, everything droppable is
+/// dropped and `fits` is false.
+pub fn plan_truncation(messages: &[(Role, u64)], overhead: u64, max_tokens: u64) -> TruncationPlan {
+    let mut total = overhead + messages.iter().map(|(_, tokens)| tokens).sum::<u64>();
+    let mut drop = Vec::new();
+
+    let last = messages.len().saturating_sub(1);
+    let mut candidates = messages
+        .iter()
+        .enumerate()
+        .take(last)
+        .filter(|(_, (role, _))| !matches!(role, Role::System))
+        .peekable();
+    while total > max_tokens {
+        let Some((index, (_, tokens))) = candidates.next() else {
+            break;
+        };
+        drop.push(index);
+        total -= tokens;
+    }
+    // Tool results answer the assistant turn before them; don't leave them dangling
+    if !drop.is_empty() {
+        while let Some((index, (_, tokens))) =
+            candidates.next_if(|(_, (role, _))| matches!(role, Role::Tool))
+        {
+            drop.push(index);
+            total -= tokens;
+        }
+    }
+
+    TruncationPlan {
+        drop,
+        total_tokens: total,
+        fits: total <= max_tokens,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fitting_conversation_is_kept() {
+        let messages = [(Role::System, 10), (Role::User, 20)];
+        assert_eq!(
+            plan_truncation(&messages, 3, 33),
+            TruncationPlan {
+                drop: vec![],
+                total_tokens: 33,
+                fits: true
+            }
+        );
+    }
+
+    #[test]
+    fn test_drops_oldest_non_system_first() {
+        let messages = [
+            (Role::System, 10),
+            (Role::User, 20),
+            (Role::Assistant, 30),
+            (Role::User, 5),
+        ];
+        assert_eq!(
+            plan_truncation(&messages, 3, 40),
+            TruncationPlan {
+                drop: vec![1, 2],
+                total_tokens: 18,
+                fits: true
+            }
+        );
+        assert_eq!(plan_truncation(&messages, 3, 60).drop, vec![1]);
+    }
+
+    #[test]
+    fn test_orphaned_tool_results_are_dropped() {
+        let messages = [
+            (Role::User, 10),
+            (Role::Assistant, 10),
+            (Role::Tool, 10),
+            (Role::Tool, 10),
+            (Role::User, 10),
+        ];
+        let plan = plan_truncation(&messages, 0, 35);
+        assert_eq!(plan.drop, vec![0, 1, 2, 3]);
+        assert_eq!(plan.total_tokens, 10);
+    }
+
+    #[test]
+    fn test_system_only_overflow_cannot_fit() {
+        let messages = [(Role::System, 100), (Role::System, 50)];
+        assert_eq!(
+            plan_truncation(&messages, 3, 20),
+            TruncationPlan {
+                drop: vec![],
+                total_tokens: 153,
+                fits: false
+            }
+        );
+
+        // Everything else goes, the system prompt stays
+        let messages = [(Role::System, 100), (Role::User, 5), (Role::User, 5)];
+        let plan = plan_truncation(&messages, 3, 20);
+        assert_eq!(plan.drop, vec![1]);
+        assert!(!plan.fits);
+    }
+
+    #[test]
+    fn test_single_huge_message_is_kept() {
+        let plan = plan_truncation(&[(Role::User, 5000)], 3, 100);
+        assert_eq!(plan.drop, Vec::<usize>::new());
+        assert_eq!(plan.total_tokens, 5003);
+        assert!(!plan.fits);
+
+        assert_eq!(
+            plan_truncation(&[], 3, 100),
+            TruncationPlan {
+                drop: vec![],
+                total_tokens: 3,
+                fits: true
+            }
+        );
+    }
+}
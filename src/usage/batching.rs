@@ -10,24 +10,36 @@
 //! - Uses batch-increment API for efficiency (up to 1000 items per request)
 //! - Rate limits Zion API calls (default: 20 req/s)
 //! - Circuit breaker for graceful degradation
-//! - Redis persistence for failed increments with retry
+//! - Redis persistence for failed increments with retry, by one replica at a
+//!   time (see `retry_lease`)
+//! - Optional local ledger dual-write with per-request delivery status
+//! - Local daily per-user aggregates updated on each flush
+//! - Local per-workflow aggregates for usage tagged with a workflow id
+//! - Local daily per-(model, tier) aggregates for usage reports
 
 use std::collections::HashMap;
 use std::num::NonZeroU32;
+use std::sync::atomic::{AtomicU8, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use chrono::Utc;
 use governor::{Quota, RateLimiter};
-use redis::AsyncCommands;
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
 use tracing::{debug, error, info, warn};
 
-use crate::zion::{BatchIncrementItem, ZionClient};
-
-/// Redis key prefix for failed usage increments
-const REDIS_FAILED_INCREMENTS_KEY: &str = "sentinel:usage:failed";
+use super::ledger::{hash_user, DeliveryStatus, LedgerEntry, LedgerHandle};
+use super::queue::{FailedQueue, REDIS_FAILED_INCREMENTS_KEY};
+use super::recent::{RecentUsageStore, UsageCounts, DEFAULT_RETENTION_DAYS, NO_MODEL};
+use super::retry_lease::{resolve_replica_id, RetryLease, RetryLeaseStatus};
+use crate::cache::schema::{self, Unreadable};
+use crate::clock::{system_clock, SharedClock};
+use crate::error::AppResult;
+use crate::middleware::auth::AuthenticatedUser;
+use crate::native::types::Tier;
+use crate::proxy::complexity::NO_TIER;
+use crate::zion::{BatchIncrementItem, IncrementUsageData, ZionClient};
 
 /// Configuration for the batching usage tracker
 #[derive(Debug, Clone)]
@@ -48,6 +60,15 @@ pub struct BatchingConfig {
     pub retry_interval: Duration,
     /// Maximum number of failed increments to retry per cycle
     pub max_retry_batch: usize,
+    /// How long the retry lease outlives its last renewal, i.e. how soon
+    /// another replica takes over the retry loop from one that died
+    pub retry_lease_ttl: Duration,
+    /// This replica's name in the retry lease
+    pub replica_id: String,
+    /// Redis list of failed increments (and prefix of its lock and lease keys)
+    pub failed_queue_key: String,
+    /// Time source for the circuit breaker
+    pub clock: SharedClock,
 }
 
 impl Default for BatchingConfig {
@@ -61,19 +82,60 @@ impl Default for BatchingConfig {
             circuit_breaker_reset: Duration::from_secs(30),
             retry_interval: Duration::from_secs(60),
             max_retry_batch: 50,
+            retry_lease_ttl: Duration::from_secs(150),
+            replica_id: resolve_replica_id(None),
+            failed_queue_key: REDIS_FAILED_INCREMENTS_KEY.to_string(),
+            clock: system_clock(),
         }
     }
 }
 
 /// A single usage increment to be batched (unified format)
+///
+/// Also the format of entries in the failed increments queue.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-struct UsageIncrement {
-    email: String,
-    input_tokens: i64,
-    output_tokens: i64,
-    requests: i64,
-    model: Option<String>,
-    timestamp: String,
+pub struct UsageIncrement {
+    pub(crate) email: String,
+    pub(crate) input_tokens: i64,
+    pub(crate) output_tokens: i64,
+    pub(crate) requests: i64,
+    pub(crate) model: Option<String>,
+    pub(crate) timestamp: String,
+    /// Ledger request ids covered by this increment (empty when the ledger is off)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) request_ids: Vec<String>,
+    /// Zion organization the user belongs to, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) organization_id: Option<String>,
+    /// User's external id, keying the local daily aggregates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) external_id: Option<String>,
+    /// Workflow the request was tagged with, for the local workflow aggregates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) workflow_id: Option<String>,
+    /// Native routing tier that served the request, for the local model aggregates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) tier: Option<String>,
+    /// Token counts were estimated rather than reported by the provider
+    #[serde(default)]
+    pub(crate) estimated: bool,
+}
+
+impl UsageIncrement {
+    /// Send this increment on its own through Zion's single increment API
+    pub(crate) async fn deliver(&self, zion_client: &ZionClient) -> AppResult<IncrementUsageData> {
+        zion_client
+            .increment_usage(
+                &self.email,
+                self.input_tokens,
+                self.output_tokens,
+                self.requests,
+                self.model.as_deref(),
+                self.organization_id.as_deref(),
+                Some(&self.timestamp),
+            )
+            .await
+    }
 }
 
 /// Aggregated usage for a user and model
@@ -83,6 +145,15 @@ struct AggregatedUsage {
     output_tokens: i64,
     requests: i64,
     timestamp: Option<String>,
+    request_ids: Vec<String>,
+    organization_id: Option<String>,
+    external_id: Option<String>,
+    /// Share of the totals tagged with each workflow id
+    workflows: HashMap<String, UsageCounts>,
+    /// Share of the totals served by each tier (`none` outside native routing)
+    tiers: HashMap<String, UsageCounts>,
+    /// Some of the merged increments carry estimated token counts
+    estimated: bool,
 }
 
 impl AggregatedUsage {
@@ -90,6 +161,24 @@ impl AggregatedUsage {
         self.input_tokens += other.input_tokens;
         self.output_tokens += other.output_tokens;
         self.requests += other.requests;
+        let counts = UsageCounts {
+            requests: other.requests,
+            input_tokens: other.input_tokens,
+            output_tokens: other.output_tokens,
+        };
+        if let Some(workflow_id) = &other.workflow_id {
+            self.workflows.entry(workflow_id.clone()).or_default().add(&counts);
+        }
+        let tier = other.tier.as_deref().unwrap_or(NO_TIER);
+        self.tiers.entry(tier.to_string()).or_default().add(&counts);
+        self.request_ids.extend(other.request_ids.iter().cloned());
+        if other.organization_id.is_some() {
+            self.organization_id = other.organization_id.clone();
+        }
+        if other.external_id.is_some() {
+            self.external_id = other.external_id.clone();
+        }
+        self.estimated |= other.estimated;
         // Track the earliest timestamp in the aggregation
         match &self.timestamp {
             None => self.timestamp = Some(other.timestamp.clone()),
@@ -114,6 +203,121 @@ enum CircuitState {
     HalfOpen,
 }
 
+impl CircuitState {
+    /// Value of `sentinel_usage_circuit_state` (0=closed, 1=half-open, 2=open)
+    fn code(self) -> u8 {
+        match self {
+            CircuitState::Closed => 0,
+            CircuitState::HalfOpen => 1,
+            CircuitState::Open => 2,
+        }
+    }
+
+    fn from_code(code: u8) -> Self {
+        match code {
+            1 => CircuitState::HalfOpen,
+            2 => CircuitState::Open,
+            _ => CircuitState::Closed,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            CircuitState::Closed => "closed",
+            CircuitState::HalfOpen => "half_open",
+            CircuitState::Open => "open",
+        }
+    }
+}
+
+/// Circuit breaker guarding calls to Zion
+///
+/// Opens after `circuit_breaker_threshold` consecutive failures. Once
+/// `circuit_breaker_reset` has elapsed the next flush goes through half-open;
+/// success closes the circuit, failure opens it again.
+#[derive(Debug)]
+struct CircuitBreaker {
+    state: CircuitState,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    threshold: u32,
+    reset: Duration,
+    clock: SharedClock,
+    /// State as seen outside the worker (see [`BatchingUsageTracker::status`])
+    published: Arc<AtomicU8>,
+}
+
+impl CircuitBreaker {
+    fn new(config: &BatchingConfig) -> Self {
+        Self {
+            state: CircuitState::Closed,
+            consecutive_failures: 0,
+            opened_at: None,
+            threshold: config.circuit_breaker_threshold,
+            reset: config.circuit_breaker_reset,
+            clock: config.clock.clone(),
+            published: Arc::new(AtomicU8::new(CircuitState::Closed.code())),
+        }
+    }
+
+    /// Publish state changes to `published` as well as the gauge
+    fn publishing_to(mut self, published: Arc<AtomicU8>) -> Self {
+        published.store(self.state.code(), Ordering::Relaxed);
+        self.published = published;
+        self
+    }
+
+    fn set_state(&mut self, state: CircuitState) {
+        self.state = state;
+        self.published.store(state.code(), Ordering::Relaxed);
+        metrics::set_circuit_state(state.code());
+    }
+
+    fn state(&self) -> CircuitState {
+        self.state
+    }
+
+    fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures
+    }
+
+    /// Whether a call may go through, moving an open circuit to half-open
+    /// once the reset period has elapsed
+    fn allow(&mut self) -> bool {
+        if self.state != CircuitState::Open {
+            return true;
+        }
+        match self.opened_at {
+            Some(opened_at) if self.clock.instant_now() - opened_at < self.reset => false,
+            _ => {
+                debug!("Circuit breaker transitioning to half-open");
+                self.set_state(CircuitState::HalfOpen);
+                true
+            }
+        }
+    }
+
+    fn record_success(&mut self) {
+        if self.state == CircuitState::HalfOpen {
+            debug!("Circuit breaker closing after successful request");
+            self.set_state(CircuitState::Closed);
+        }
+        self.consecutive_failures = 0;
+        self.opened_at = None;
+    }
+
+    /// Count a failure; returns true when it opened the circuit
+    fn record_failure(&mut self) -> bool {
+        self.consecutive_failures += 1;
+        if self.consecutive_failures < self.threshold {
+            return false;
+        }
+        self.set_state(CircuitState::Open);
+        self.opened_at = Some(self.clock.instant_now());
+        true
+    }
+}
+
 /// Batching usage tracker that protects Zion API from request floods.
 ///
 /// Features:
@@ -124,6 +328,24 @@ enum CircuitState {
 /// - Redis persistence for failed increments with retry
 pub struct BatchingUsageTracker {
     sender: mpsc::Sender<UsageIncrement>,
+    ledger: LedgerHandle,
+    recent: Arc<RecentUsageStore>,
+    replica_id: String,
+    /// None for test trackers, which never retry
+    retry_lease: Option<Arc<RetryLease>>,
+    /// Circuit state published by the worker
+    circuit: Arc<AtomicU8>,
+}
+
+/// Queue depth and circuit state, as in `GET /admin/snapshot`
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct UsageTrackerStatus {
+    /// Increments waiting in the channel for the worker
+    pub queued: usize,
+    /// Channel capacity; `track` drops increments once it is full
+    pub capacity: usize,
+    /// Circuit guarding Zion: `closed`, `half_open` or `open`
+    pub circuit: &'static str,
 }
 
 impl BatchingUsageTracker {
@@ -134,13 +356,79 @@ impl BatchingUsageTracker {
         zion_client: Arc<ZionClient>,
         redis: redis::aio::ConnectionManager,
         config: BatchingConfig,
+    ) -> Self {
+        let recent = Arc::new(RecentUsageStore::new(redis.clone(), DEFAULT_RETENTION_DAYS));
+        Self::new_with_ledger(zion_client, redis, config, LedgerHandle::disabled(), recent)
+    }
+
+    /// Create a tracker that also records every request in the usage ledger
+    /// and flushes per-user daily aggregates into `recent`
+    pub fn new_with_ledger(
+        zion_client: Arc<ZionClient>,
+        redis: redis::aio::ConnectionManager,
+        config: BatchingConfig,
+        ledger: LedgerHandle,
+        recent: Arc<RecentUsageStore>,
     ) -> Self {
         let (sender, receiver) = mpsc::channel(config.channel_buffer);
+        let replica_id = config.replica_id.clone();
+        let retry_lease = Arc::new(RetryLease::new(
+            redis.clone(),
+            &config.failed_queue_key,
+            &config.replica_id,
+            config.retry_lease_ttl,
+        ));
+
+        let circuit = Arc::new(AtomicU8::new(CircuitState::Closed.code()));
 
         // Spawn background worker
-        tokio::spawn(Self::background_worker(zion_client, redis, receiver, config));
+        tokio::spawn(Self::background_worker(
+            zion_client,
+            redis,
+            receiver,
+            config,
+            ledger.clone(),
+            recent.clone(),
+            retry_lease.clone(),
+            circuit.clone(),
+        ));
+
+        Self {
+            sender,
+            ledger,
+            recent,
+            replica_id,
+            retry_lease: Some(retry_lease),
+            circuit,
+        }
+    }
+
+    /// Channel depth and circuit state
+    pub fn status(&self) -> UsageTrackerStatus {
+        let capacity = self.sender.max_capacity();
+        UsageTrackerStatus {
+            queued: capacity - self.sender.capacity(),
+            capacity,
+            circuit: CircuitState::from_code(self.circuit.load(Ordering::Relaxed)).as_str(),
+        }
+    }
+
+    /// Which replica runs the retry loop for the failed increments queue
+    pub async fn retry_status(&self) -> AppResult<RetryLeaseStatus> {
+        match &self.retry_lease {
+            Some(lease) => lease.status().await,
+            None => Ok(RetryLeaseStatus {
+                replica_id: self.replica_id.clone(),
+                leader: None,
+                is_leader: false,
+                expires_in_ms: None,
+            }),
+        }
+    }
 
-        Self { sender }
+    /// Local daily aggregates maintained by the flush path
+    pub fn recent_usage(&self) -> &Arc<RecentUsageStore> {
+        &self.recent
     }
 
     /// Create with default configuration
@@ -156,6 +444,176 @@ impl BatchingUsageTracker {
     /// This method never blocks and never fails. If the channel is full,
     /// the increment is dropped and logged.
     pub fn track(&self, email: String, input_tokens: u64, output_tokens: u64, model: Option<String>) {
+        self.track_with_org(email, None, input_tokens, output_tokens, model);
+    }
+
+    /// Track AI usage attributed to the user's organization - fire-and-forget
+    ///
+    /// Same as `track`, but the increment carries the Zion organization ID
+    /// when the user belongs to one.
+    pub fn track_with_org(
+        &self,
+        email: String,
+        organization_id: Option<String>,
+        input_tokens: u64,
+        output_tokens: u64,
+        model: Option<String>,
+    ) {
+        self.track_increment(
+            email,
+            None,
+            organization_id,
+            None,
+            None,
+            input_tokens,
+            output_tokens,
+            1,
+            model,
+            false,
+            false,
+            None,
+        );
+    }
+
+    /// Track AI usage for an authenticated user - fire-and-forget
+    ///
+    /// Carries the user's organization and external id, so the usage also
+    /// lands in the local daily aggregates. Users who opted out of request
+    /// logging are billed the same, but skip the daily aggregates and their
+    /// ledger entries keep only the hashed user and token totals. Synthetic
+    /// requests (see [`crate::middleware::synthetic`]) are not tracked at all.
+    pub fn track_user(
+        &self,
+        user: &AuthenticatedUser,
+        input_tokens: u64,
+        output_tokens: u64,
+        model: Option<String>,
+    ) {
+        self.track_user_in_workflow(user, None, input_tokens, output_tokens, model);
+    }
+
+    /// Track AI usage for an authenticated user, tagged with a workflow - fire-and-forget
+    ///
+    /// Same as `track_user`; the usage is additionally added to the user's
+    /// local aggregates for `workflow_id`. Zion increments stay per user.
+    pub fn track_user_in_workflow(
+        &self,
+        user: &AuthenticatedUser,
+        workflow_id: Option<String>,
+        input_tokens: u64,
+        output_tokens: u64,
+        model: Option<String>,
+    ) {
+        self.track_user_usage(user, None, workflow_id, input_tokens, output_tokens, model, false);
+    }
+
+    /// Track AI usage served by a native routing tier - fire-and-forget
+    ///
+    /// Same as `track_user_in_workflow`; the local model aggregates count the
+    /// usage under `tier` instead of `none`.
+    pub fn track_user_in_tier(
+        &self,
+        user: &AuthenticatedUser,
+        tier: Tier,
+        workflow_id: Option<String>,
+        input_tokens: u64,
+        output_tokens: u64,
+        model: Option<String>,
+    ) {
+        self.track_user_usage(user, Some(tier), workflow_id, input_tokens, output_tokens, model, false);
+    }
+
+    /// Track estimated AI usage for an authenticated user - fire-and-forget
+    ///
+    /// Same as `track_user_in_tier`, for token counts Sentinel estimated
+    /// because the provider didn't report usage. The batch item carries
+    /// `estimated: true` when Zion advertises `batch.estimated`.
+    pub fn track_user_estimated(
+        &self,
+        user: &AuthenticatedUser,
+        tier: Tier,
+        workflow_id: Option<String>,
+        input_tokens: u64,
+        output_tokens: u64,
+        model: Option<String>,
+    ) {
+        self.track_user_usage(user, Some(tier), workflow_id, input_tokens, output_tokens, model, true);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn track_user_usage(
+        &self,
+        user: &AuthenticatedUser,
+        tier: Option<Tier>,
+        workflow_id: Option<String>,
+        input_tokens: u64,
+        output_tokens: u64,
+        model: Option<String>,
+        estimated: bool,
+    ) {
+        if let Some(charge) = &user.token_charge {
+            charge.settle(input_tokens, output_tokens);
+        }
+        if user.synthetic {
+            debug!(input_tokens, output_tokens, "Synthetic request, usage not tracked");
+            return;
+        }
+        self.track_increment(
+            user.email.clone(),
+            Some(user.external_id.clone()).filter(|_| !user.logging_opt_out),
+            user.organization_id.clone(),
+            workflow_id,
+            tier.map(|tier| tier.to_string()),
+            input_tokens,
+            output_tokens,
+            1,
+            model,
+            user.logging_opt_out,
+            estimated,
+            user.upstream_user.clone(),
+        );
+    }
+
+    /// Track requests without token usage for an authenticated user - fire-and-forget
+    ///
+    /// `requests` is the request's weight (see [`super::weights`]); a weight
+    /// of 0 isn't tracked at all, and neither are synthetic requests.
+    pub fn track_user_requests(&self, user: &AuthenticatedUser, requests: u32) {
+        if requests == 0 || user.synthetic {
+            return;
+        }
+        self.track_increment(
+            user.email.clone(),
+            Some(user.external_id.clone()).filter(|_| !user.logging_opt_out),
+            user.organization_id.clone(),
+            None,
+            None,
+            0,
+            0,
+            i64::from(requests),
+            None,
+            user.logging_opt_out,
+            false,
+            user.upstream_user.clone(),
+        );
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn track_increment(
+        &self,
+        email: String,
+        external_id: Option<String>,
+        organization_id: Option<String>,
+        workflow_id: Option<String>,
+        tier: Option<String>,
+        input_tokens: u64,
+        output_tokens: u64,
+        requests: i64,
+        model: Option<String>,
+        logging_opt_out: bool,
+        estimated: bool,
+        upstream_user: Option<String>,
+    ) {
         // Warn if email is empty - this will cause Zion API to reject the request
         if email.is_empty() {
             warn!(
@@ -165,16 +623,41 @@ impl BatchingUsageTracker {
             );
         }
 
-        let timestamp = Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+        let now = Utc::now();
+        let timestamp = now.to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+
+        // Record the request in the ledger before it enters the batch
+        let mut request_ids = Vec::new();
+        if self.ledger.is_enabled() {
+            let request_id = uuid::Uuid::new_v4().to_string();
+            self.ledger.append(LedgerEntry {
+                request_id: request_id.clone(),
+                user_hash: hash_user(&email),
+                model: model.clone().filter(|_| !logging_opt_out),
+                input_tokens: input_tokens as i64,
+                output_tokens: output_tokens as i64,
+                requests,
+                created_at: now.timestamp_millis(),
+                delivery_status: DeliveryStatus::Pending,
+                upstream_user: upstream_user.filter(|_| !logging_opt_out),
+            });
+            request_ids.push(request_id);
+        }
 
         // Send unified usage increment
         self.send_increment(UsageIncrement {
             email,
             input_tokens: input_tokens as i64,
             output_tokens: output_tokens as i64,
-            requests: 1,
+            requests,
             model,
             timestamp,
+            request_ids,
+            organization_id,
+            external_id,
+            workflow_id,
+            tier,
+            estimated,
         });
     }
 
@@ -202,6 +685,7 @@ impl BatchingUsageTracker {
                         requests = inc.requests,
                         "Usage tracking channel full, dropping increment"
                     );
+                    self.ledger.mark(inc.request_ids, DeliveryStatus::Failed);
                 }
                 mpsc::error::TrySendError::Closed(inc) => {
                     error!(
@@ -210,23 +694,30 @@ impl BatchingUsageTracker {
                         output_tokens = inc.output_tokens,
                         "Usage tracking channel closed, dropping increment"
                     );
+                    self.ledger.mark(inc.request_ids, DeliveryStatus::Failed);
                 }
             }
         }
     }
 
     /// Background worker that processes increments
+    #[allow(clippy::too_many_arguments)]
     async fn background_worker(
         zion_client: Arc<ZionClient>,
         redis: redis::aio::ConnectionManager,
         mut receiver: mpsc::Receiver<UsageIncrement>,
         config: BatchingConfig,
+        ledger: LedgerHandle,
+        recent: Arc<RecentUsageStore>,
+        retry_lease: Arc<RetryLease>,
+        circuit: Arc<AtomicU8>,
     ) {
         info!(
             batch_size = config.max_batch_size,
             flush_interval_ms = config.flush_interval.as_millis(),
             rate_limit = config.rate_limit_per_second,
             retry_interval_s = config.retry_interval.as_secs(),
+            replica_id = %config.replica_id,
             "Starting batching usage tracker worker"
         );
 
@@ -235,10 +726,7 @@ impl BatchingUsageTracker {
             NonZeroU32::new(config.rate_limit_per_second).unwrap(),
         ));
 
-        // Circuit breaker state
-        let mut circuit_state = CircuitState::Closed;
-        let mut consecutive_failures: u32 = 0;
-        let mut circuit_opened_at: Option<std::time::Instant> = None;
+        let mut breaker = CircuitBreaker::new(&config).publishing_to(circuit);
 
         // Aggregation buffer - keyed by (email, model)
         let mut buffer: HashMap<(String, Option<String>), AggregatedUsage> = HashMap::new();
@@ -274,10 +762,10 @@ impl BatchingUsageTracker {
                                     &redis,
                                     &rate_limiter,
                                     &mut buffer,
-                                    &mut circuit_state,
-                                    &mut consecutive_failures,
-                                    &mut circuit_opened_at,
+                                    &mut breaker,
                                     &config,
+                                    &ledger,
+                                    &recent,
                                 ).await;
                                 last_flush = std::time::Instant::now();
                             }
@@ -290,12 +778,17 @@ impl BatchingUsageTracker {
                                     &redis,
                                     &rate_limiter,
                                     &mut buffer,
-                                    &mut circuit_state,
-                                    &mut consecutive_failures,
-                                    &mut circuit_opened_at,
+                                    &mut breaker,
                                     &config,
+                                    &ledger,
+                                    &recent,
                                 ).await;
                             }
+                            // Let another replica take over the retry loop right away
+                            if let Err(e) = retry_lease.release().await {
+                                warn!(error = %e, "Failed to release the retry lease");
+                            }
+                            metrics::set_retry_leader(retry_lease.replica_id(), false);
                             info!("Batching usage tracker shutting down");
                             break;
                         }
@@ -309,26 +802,29 @@ impl BatchingUsageTracker {
                             &redis,
                             &rate_limiter,
                             &mut buffer,
-                            &mut circuit_state,
-                            &mut consecutive_failures,
-                            &mut circuit_opened_at,
+                            &mut breaker,
                             &config,
+                            &ledger,
+                            &recent,
                         ).await;
                         last_flush = std::time::Instant::now();
                     }
                 }
                 // Timer for retrying failed increments
                 _ = tokio::time::sleep(time_until_retry) => {
-                    // Only retry when circuit is closed
-                    if circuit_state == CircuitState::Closed {
+                    // Only retry when circuit is closed, and only on the replica
+                    // holding the retry lease
+                    if breaker.state() == CircuitState::Closed
+                        && Self::lead_retry_cycle(&retry_lease).await
+                    {
                         Self::retry_failed_increments(
                             &zion_client,
                             &redis,
                             &rate_limiter,
-                            &mut circuit_state,
-                            &mut consecutive_failures,
-                            &mut circuit_opened_at,
+                            &mut breaker,
                             &config,
+                            &ledger,
+                            &retry_lease,
                         ).await;
                     }
                     last_retry = std::time::Instant::now();
@@ -337,7 +833,24 @@ impl BatchingUsageTracker {
         }
     }
 
+    /// Take or renew the retry lease; true when this replica should retry
+    async fn lead_retry_cycle(retry_lease: &RetryLease) -> bool {
+        let leading = match retry_lease.acquire().await {
+            Ok(leading) => leading,
+            Err(e) => {
+                warn!(error = %e, "Failed to take the retry lease, skipping retry");
+                false
+            }
+        };
+        if !leading {
+            debug!("Another replica holds the retry lease, skipping retry");
+        }
+        metrics::set_retry_leader(retry_lease.replica_id(), leading);
+        leading
+    }
+
     /// Flush the aggregation buffer to Zion using batch-increment API
+    #[allow(clippy::too_many_arguments)]
     async fn flush_buffer(
         zion_client: &Arc<ZionClient>,
         redis: &redis::aio::ConnectionManager,
@@ -347,34 +860,24 @@ impl BatchingUsageTracker {
             governor::clock::DefaultClock,
         >,
         buffer: &mut HashMap<(String, Option<String>), AggregatedUsage>,
-        circuit_state: &mut CircuitState,
-        consecutive_failures: &mut u32,
-        circuit_opened_at: &mut Option<std::time::Instant>,
+        breaker: &mut CircuitBreaker,
         config: &BatchingConfig,
+        ledger: &LedgerHandle,
+        recent: &RecentUsageStore,
     ) {
-        // Check circuit breaker state
-        match *circuit_state {
-            CircuitState::Open => {
-                // Check if we should try half-open
-                if let Some(opened_at) = *circuit_opened_at {
-                    if opened_at.elapsed() >= config.circuit_breaker_reset {
-                        debug!("Circuit breaker transitioning to half-open");
-                        *circuit_state = CircuitState::HalfOpen;
-                    } else {
-                        // Still open, drop increments
-                        let count = buffer.len();
-                        buffer.clear();
-                        warn!(
-                            dropped_count = count,
-                            "Circuit breaker open, dropping usage increments"
-                        );
-                        return;
-                    }
-                }
-            }
-            CircuitState::Closed | CircuitState::HalfOpen => {
-                // Continue processing
-            }
+        // Circuit still open: drop increments
+        if !breaker.allow() {
+            let count = buffer.len();
+            let dropped_ids: Vec<String> = buffer
+                .drain()
+                .flat_map(|(_, usage)| usage.request_ids)
+                .collect();
+            ledger.mark(dropped_ids, DeliveryStatus::Failed);
+            warn!(
+                dropped_count = count,
+                "Circuit breaker open, dropping usage increments"
+            );
+            return;
         }
 
         // Drain buffer and filter out empty entries
@@ -393,6 +896,12 @@ impl BatchingUsageTracker {
             "Flushing usage increments to Zion"
         );
 
+        // One pipelined write per flush keeps the local aggregates off the request path
+        let today = Utc::now().date_naive();
+        recent.record(today, &recent_totals(&increments)).await;
+        recent.record_workflows(&workflow_totals(&increments)).await;
+        recent.record_models(today, &model_totals(&increments)).await;
+
         // Wait for rate limiter
         rate_limiter.until_ready().await;
 
@@ -419,6 +928,8 @@ impl BatchingUsageTracker {
                 },
                 model: model.clone(),
                 timestamp: usage.timestamp.clone(),
+                organization_id: usage.organization_id.clone(),
+                estimated: usage.estimated.then_some(true),
             })
             .collect();
 
@@ -426,12 +937,18 @@ impl BatchingUsageTracker {
         match zion_client.batch_increment(batch_items).await {
             Ok(result) => {
                 // Reset failure count on success
-                if *circuit_state == CircuitState::HalfOpen {
-                    debug!("Circuit breaker closing after successful request");
-                    *circuit_state = CircuitState::Closed;
-                }
-                *consecutive_failures = 0;
-                *circuit_opened_at = None;
+                breaker.record_success();
+
+                // Everything Zion didn't reject is now delivered
+                let failed_emails: Vec<&str> = result
+                    .results
+                    .iter()
+                    .filter(|r| !r.success)
+                    .map(|r| r.email.as_str())
+                    .collect();
+                let (failed_ids, delivered_ids) = partition_request_ids(&increments, &failed_emails);
+                ledger.mark(delivered_ids, DeliveryStatus::Delivered);
+                ledger.mark(failed_ids, DeliveryStatus::Failed);
 
                 if result.failed > 0 {
                     warn!(
@@ -453,9 +970,16 @@ impl BatchingUsageTracker {
                                 requests: usage.requests,
                                 model: model.clone(),
                                 timestamp: usage.timestamp.clone().unwrap_or_default(),
+                                request_ids: usage.request_ids.clone(),
+                                organization_id: usage.organization_id.clone(),
+                                external_id: usage.external_id.clone(),
+                                // Already in the workflow and model aggregates; retries only go to Zion
+                                workflow_id: None,
+                                tier: None,
+                                estimated: usage.estimated,
                             };
                             if let Err(redis_err) =
-                                Self::persist_failed_increment(redis, &increment).await
+                                Self::persist_failed_increment(redis, config, &increment).await
                             {
                                 error!(
                                     error = %redis_err,
@@ -473,15 +997,23 @@ impl BatchingUsageTracker {
                 }
             }
             Err(e) => {
-                *consecutive_failures += 1;
+                let opened = breaker.record_failure();
 
                 warn!(
                     user_count = total_increments,
                     error = %e,
-                    consecutive_failures = *consecutive_failures,
+                    consecutive_failures = breaker.consecutive_failures(),
                     "Failed to batch increment usage"
                 );
 
+                ledger.mark(
+                    increments
+                        .iter()
+                        .flat_map(|(_, usage)| usage.request_ids.iter().cloned())
+                        .collect(),
+                    DeliveryStatus::Failed,
+                );
+
                 // Persist all to Redis for retry
                 for ((email, model), usage) in &increments {
                     let increment = UsageIncrement {
@@ -491,8 +1023,14 @@ impl BatchingUsageTracker {
                         requests: usage.requests,
                         model: model.clone(),
                         timestamp: usage.timestamp.clone().unwrap_or_default(),
+                        request_ids: usage.request_ids.clone(),
+                        organization_id: usage.organization_id.clone(),
+                        external_id: usage.external_id.clone(),
+                        workflow_id: None,
+                        tier: None,
+                        estimated: usage.estimated,
                     };
-                    if let Err(redis_err) = Self::persist_failed_increment(redis, &increment).await
+                    if let Err(redis_err) = Self::persist_failed_increment(redis, config, &increment).await
                     {
                         error!(
                             error = %redis_err,
@@ -502,15 +1040,12 @@ impl BatchingUsageTracker {
                     }
                 }
 
-                // Check if we should open circuit
-                if *consecutive_failures >= config.circuit_breaker_threshold {
+                if opened {
                     error!(
                         threshold = config.circuit_breaker_threshold,
                         reset_seconds = config.circuit_breaker_reset.as_secs(),
                         "Circuit breaker opening due to consecutive failures"
                     );
-                    *circuit_state = CircuitState::Open;
-                    *circuit_opened_at = Some(std::time::Instant::now());
                 }
             }
         }
@@ -519,19 +1054,11 @@ impl BatchingUsageTracker {
     /// Persist a failed increment to Redis for later retry
     async fn persist_failed_increment(
         redis: &redis::aio::ConnectionManager,
+        config: &BatchingConfig,
         increment: &UsageIncrement,
-    ) -> Result<(), redis::RedisError> {
-        let mut conn = redis.clone();
-        let json = serde_json::to_string(increment).map_err(|e| {
-            redis::RedisError::from((
-                redis::ErrorKind::IoError,
-                "JSON serialization error",
-                e.to_string(),
-            ))
-        })?;
-
-        // Use RPUSH to add to a list (FIFO queue)
-        conn.rpush::<_, _, ()>(REDIS_FAILED_INCREMENTS_KEY, json)
+    ) -> AppResult<()> {
+        FailedQueue::with_key(redis.clone(), &config.failed_queue_key)
+            .push(increment)
             .await?;
 
         debug!(
@@ -548,7 +1075,10 @@ impl BatchingUsageTracker {
     /// Retry failed increments from Redis
     ///
     /// Uses single increment API for retries since these are typically
-    /// smaller numbers of items that failed previously.
+    /// smaller numbers of items that failed previously. The caller holds the
+    /// retry lease; it is renewed after every increment and the cycle stops
+    /// if it was lost.
+    #[allow(clippy::too_many_arguments)]
     async fn retry_failed_increments(
         zion_client: &Arc<ZionClient>,
         redis: &redis::aio::ConnectionManager,
@@ -557,15 +1087,15 @@ impl BatchingUsageTracker {
             governor::state::InMemoryState,
             governor::clock::DefaultClock,
         >,
-        circuit_state: &mut CircuitState,
-        consecutive_failures: &mut u32,
-        circuit_opened_at: &mut Option<std::time::Instant>,
+        breaker: &mut CircuitBreaker,
         config: &BatchingConfig,
+        ledger: &LedgerHandle,
+        retry_lease: &RetryLease,
     ) {
-        let mut conn = redis.clone();
+        let queue = FailedQueue::with_key(redis.clone(), &config.failed_queue_key);
 
         // Get the number of failed increments
-        let len: usize = match conn.llen(REDIS_FAILED_INCREMENTS_KEY).await {
+        let len: usize = match queue.len().await {
             Ok(l) => l,
             Err(e) => {
                 warn!(error = %e, "Failed to get failed increments count from Redis");
@@ -577,6 +1107,19 @@ impl BatchingUsageTracker {
             return;
         }
 
+        // Skip this cycle while another replica or a manual flush/purge works the queue
+        let lock = match queue.try_lock().await {
+            Ok(Some(lock)) => lock,
+            Ok(None) => {
+                debug!("Failed increments queue is locked elsewhere, skipping retry");
+                return;
+            }
+            Err(e) => {
+                warn!(error = %e, "Failed to lock failed increments queue");
+                return;
+            }
+        };
+
         let batch_size = len.min(config.max_retry_batch);
         info!(
             total_pending = len,
@@ -589,7 +1132,7 @@ impl BatchingUsageTracker {
 
         for _ in 0..batch_size {
             // Pop from the front of the list (FIFO)
-            let json: Option<String> = match conn.lpop(REDIS_FAILED_INCREMENTS_KEY, None).await {
+            let json: Option<String> = match queue.pop().await {
                 Ok(j) => j,
                 Err(e) => {
                     warn!(error = %e, "Failed to pop from Redis queue");
@@ -601,10 +1144,17 @@ impl BatchingUsageTracker {
                 break;
             };
 
-            let increment: UsageIncrement = match serde_json::from_str(&json) {
+            let increment: UsageIncrement = match schema::decode(&json) {
                 Ok(i) => i,
-                Err(e) => {
-                    error!(error = %e, json = %json, "Failed to deserialize increment");
+                Err(Unreadable::Newer(version)) => {
+                    // Leave it for a replica that can read it
+                    if let Err(e) = queue.requeue(&json).await {
+                        warn!(error = %e, version, "Failed to re-queue increment from a newer release");
+                    }
+                    continue;
+                }
+                Err(Unreadable::Invalid) => {
+                    error!(json = %json, "Failed to deserialize increment");
                     continue;
                 }
             };
@@ -613,20 +1163,11 @@ impl BatchingUsageTracker {
             rate_limiter.until_ready().await;
 
             // Try to send to Zion using unified increment API
-            match zion_client
-                .increment_usage(
-                    &increment.email,
-                    increment.input_tokens,
-                    increment.output_tokens,
-                    increment.requests,
-                    increment.model.as_deref(),
-                    Some(&increment.timestamp),
-                )
-                .await
-            {
+            match increment.deliver(zion_client).await {
                 Ok(_) => {
                     success_count += 1;
-                    *consecutive_failures = 0;
+                    breaker.record_success();
+                    ledger.mark(increment.request_ids.clone(), DeliveryStatus::Delivered);
                     debug!(
                         email = %increment.email,
                         input_tokens = increment.input_tokens,
@@ -637,7 +1178,7 @@ impl BatchingUsageTracker {
                 }
                 Err(e) => {
                     failure_count += 1;
-                    *consecutive_failures += 1;
+                    let opened = breaker.record_failure();
 
                     warn!(
                         email = %increment.email,
@@ -649,7 +1190,7 @@ impl BatchingUsageTracker {
 
                     // Re-queue the failed increment
                     if let Err(redis_err) =
-                        Self::persist_failed_increment(redis, &increment).await
+                        Self::persist_failed_increment(redis, config, &increment).await
                     {
                         error!(
                             error = %redis_err,
@@ -657,18 +1198,34 @@ impl BatchingUsageTracker {
                         );
                     }
 
-                    // Check if we should open circuit
-                    if *consecutive_failures >= config.circuit_breaker_threshold {
+                    if opened {
                         error!(
                             threshold = config.circuit_breaker_threshold,
                             "Circuit breaker opening during retry"
                         );
-                        *circuit_state = CircuitState::Open;
-                        *circuit_opened_at = Some(std::time::Instant::now());
                         break;
                     }
                 }
             }
+
+            // A long drain keeps the lease; if it lapsed anyway, another
+            // replica may be retrying by now
+            match retry_lease.renew().await {
+                Ok(true) => {}
+                Ok(false) => {
+                    warn!("Lost the retry lease during a retry cycle, stopping");
+                    metrics::set_retry_leader(retry_lease.replica_id(), false);
+                    break;
+                }
+                Err(e) => {
+                    warn!(error = %e, "Failed to renew the retry lease, stopping");
+                    break;
+                }
+            }
+        }
+
+        if let Err(e) = queue.unlock(lock).await {
+            warn!(error = %e, "Failed to unlock failed increments queue");
         }
 
         if success_count > 0 || failure_count > 0 {
@@ -704,19 +1261,65 @@ impl BatchingUsageTracker {
     /// // Usage will be sent to Zion mock after ~10ms
     /// ```
     pub fn new_for_testing(zion_client: Arc<ZionClient>) -> Self {
-        let config = BatchingConfig {
+        Self::new_for_testing_with_ledger(zion_client, LedgerHandle::disabled())
+    }
+
+    /// Create a test tracker that records requests in the given ledger
+    pub fn new_for_testing_with_ledger(zion_client: Arc<ZionClient>, ledger: LedgerHandle) -> Self {
+        Self::spawn_for_testing(zion_client, Self::testing_config(), ledger)
+    }
+
+    /// Create a test tracker with the given flush and circuit breaker settings
+    ///
+    /// Unlike the other test trackers, whose breaker never opens, this one
+    /// opens after `circuit_breaker_threshold` failed flushes and drops
+    /// increments until `circuit_breaker_reset` has elapsed, like the real one.
+    pub fn new_for_testing_with_config(zion_client: Arc<ZionClient>, config: BatchingConfig) -> Self {
+        Self::spawn_for_testing(zion_client, config, LedgerHandle::disabled())
+    }
+
+    /// Settings of the test trackers: fast flushes, small batches, no breaker
+    pub fn testing_config() -> BatchingConfig {
+        BatchingConfig {
             flush_interval: Duration::from_millis(10), // Fast flush for tests
             max_batch_size: 10,                        // Small batch for tests
             channel_buffer: 1000,                      // Smaller buffer for tests
+            circuit_breaker_threshold: u32::MAX,       // Zion failures never open it
             ..Default::default()
-        };
+        }
+    }
 
+    fn spawn_for_testing(
+        zion_client: Arc<ZionClient>,
+        config: BatchingConfig,
+        ledger: LedgerHandle,
+    ) -> Self {
+        let recent = Arc::new(RecentUsageStore::new_for_testing(
+            Arc::new(crate::cache::InMemoryCache::new(60)),
+            DEFAULT_RETENTION_DAYS,
+        ));
         let (sender, receiver) = mpsc::channel(config.channel_buffer);
+        let replica_id = config.replica_id.clone();
+        let circuit = Arc::new(AtomicU8::new(CircuitState::Closed.code()));
 
         // Spawn minimal worker without Redis retry
-        tokio::spawn(Self::test_background_worker(zion_client, receiver, config));
+        tokio::spawn(Self::test_background_worker(
+            zion_client,
+            receiver,
+            config,
+            ledger.clone(),
+            recent.clone(),
+            circuit.clone(),
+        ));
 
-        Self { sender }
+        Self {
+            sender,
+            ledger,
+            recent,
+            replica_id,
+            retry_lease: None,
+            circuit,
+        }
     }
 
     /// Simplified background worker for testing (no Redis, no retry)
@@ -724,6 +1327,9 @@ impl BatchingUsageTracker {
         zion_client: Arc<ZionClient>,
         mut receiver: mpsc::Receiver<UsageIncrement>,
         config: BatchingConfig,
+        ledger: LedgerHandle,
+        recent: Arc<RecentUsageStore>,
+        circuit: Arc<AtomicU8>,
     ) {
         use std::num::NonZeroU32;
 
@@ -738,6 +1344,8 @@ impl BatchingUsageTracker {
             NonZeroU32::new(config.rate_limit_per_second).unwrap(),
         ));
 
+        let mut breaker = CircuitBreaker::new(&config).publishing_to(circuit);
+
         // Aggregation buffer - keyed by (email, model)
         let mut buffer: HashMap<(String, Option<String>), AggregatedUsage> = HashMap::new();
         let mut last_flush = std::time::Instant::now();
@@ -758,14 +1366,14 @@ impl BatchingUsageTracker {
 
                             // Flush if batch is full
                             if buffer.len() >= config.max_batch_size {
-                                Self::test_flush_buffer(&zion_client, &rate_limiter, &mut buffer).await;
+                                Self::test_flush_buffer(&zion_client, &rate_limiter, &mut buffer, &mut breaker, &ledger, &recent).await;
                                 last_flush = std::time::Instant::now();
                             }
                         }
                         None => {
                             // Channel closed, flush remaining and exit
                             if !buffer.is_empty() {
-                                Self::test_flush_buffer(&zion_client, &rate_limiter, &mut buffer).await;
+                                Self::test_flush_buffer(&zion_client, &rate_limiter, &mut buffer, &mut breaker, &ledger, &recent).await;
                             }
                             info!("Test usage tracker shutting down");
                             return;
@@ -774,7 +1382,7 @@ impl BatchingUsageTracker {
                 }
                 _ = tokio::time::sleep(time_until_flush) => {
                     if !buffer.is_empty() {
-                        Self::test_flush_buffer(&zion_client, &rate_limiter, &mut buffer).await;
+                        Self::test_flush_buffer(&zion_client, &rate_limiter, &mut buffer, &mut breaker, &ledger, &recent).await;
                         last_flush = std::time::Instant::now();
                     }
                 }
@@ -782,7 +1390,7 @@ impl BatchingUsageTracker {
         }
     }
 
-    /// Simplified flush for testing (no circuit breaker, no Redis persistence)
+    /// Simplified flush for testing (no Redis persistence)
     async fn test_flush_buffer(
         zion_client: &Arc<ZionClient>,
         rate_limiter: &RateLimiter<
@@ -791,7 +1399,20 @@ impl BatchingUsageTracker {
             governor::clock::DefaultClock,
         >,
         buffer: &mut HashMap<(String, Option<String>), AggregatedUsage>,
+        breaker: &mut CircuitBreaker,
+        ledger: &LedgerHandle,
+        recent: &RecentUsageStore,
     ) {
+        if !breaker.allow() {
+            let dropped_ids: Vec<String> = buffer
+                .drain()
+                .flat_map(|(_, usage)| usage.request_ids)
+                .collect();
+            ledger.mark(dropped_ids, DeliveryStatus::Failed);
+            warn!("TEST: Circuit breaker open, dropping usage increments");
+            return;
+        }
+
         let increments: Vec<((String, Option<String>), AggregatedUsage)> = buffer
             .drain()
             .filter(|(_, usage)| !usage.is_empty())
@@ -806,6 +1427,11 @@ impl BatchingUsageTracker {
             "TEST: Flushing usage increments to Zion"
         );
 
+        let today = Utc::now().date_naive();
+        recent.record(today, &recent_totals(&increments)).await;
+        recent.record_workflows(&workflow_totals(&increments)).await;
+        recent.record_models(today, &model_totals(&increments)).await;
+
         rate_limiter.until_ready().await;
 
         let batch_items: Vec<BatchIncrementItem> = increments
@@ -829,24 +1455,126 @@ impl BatchingUsageTracker {
                 },
                 model: model.clone(),
                 timestamp: usage.timestamp.clone(),
+                organization_id: usage.organization_id.clone(),
+                estimated: usage.estimated.then_some(true),
             })
             .collect();
 
         match zion_client.batch_increment(batch_items).await {
             Ok(result) => {
+                breaker.record_success();
                 debug!(
                     processed = result.processed,
                     failed = result.failed,
                     "TEST: Batch increment completed"
                 );
+                let failed_emails: Vec<&str> = result
+                    .results
+                    .iter()
+                    .filter(|r| !r.success)
+                    .map(|r| r.email.as_str())
+                    .collect();
+                let (failed_ids, delivered_ids) = partition_request_ids(&increments, &failed_emails);
+                ledger.mark(delivered_ids, DeliveryStatus::Delivered);
+                ledger.mark(failed_ids, DeliveryStatus::Failed);
             }
             Err(e) => {
+                if breaker.record_failure() {
+                    warn!("TEST: Circuit breaker opening due to consecutive failures");
+                }
                 warn!(error = %e, "TEST: Batch increment failed (no retry in test mode)");
+                ledger.mark(
+                    increments
+                        .iter()
+                        .flat_map(|(_, usage)| usage.request_ids.iter().cloned())
+                        .collect(),
+                    DeliveryStatus::Failed,
+                );
             }
         }
     }
 }
 
+/// Sum flushed increments per external id for the local daily aggregates
+///
+/// Increments tracked without an external id are left out.
+fn recent_totals(increments: &[((String, Option<String>), AggregatedUsage)]) -> Vec<(String, UsageCounts)> {
+    let mut totals: HashMap<String, UsageCounts> = HashMap::new();
+    for (_, usage) in increments {
+        if let Some(external_id) = &usage.external_id {
+            totals.entry(external_id.clone()).or_default().add(&UsageCounts {
+                requests: usage.requests,
+                input_tokens: usage.input_tokens,
+                output_tokens: usage.output_tokens,
+            });
+        }
+    }
+    totals.into_iter().collect()
+}
+
+/// Sum flushed increments per (external id, workflow id) for the local workflow aggregates
+///
+/// Like `recent_totals`, increments without an external id are left out.
+fn workflow_totals(
+    increments: &[((String, Option<String>), AggregatedUsage)],
+) -> Vec<(String, String, UsageCounts)> {
+    let mut totals: HashMap<(String, String), UsageCounts> = HashMap::new();
+    for (_, usage) in increments {
+        let Some(external_id) = &usage.external_id else {
+            continue;
+        };
+        for (workflow_id, counts) in &usage.workflows {
+            totals
+                .entry((external_id.clone(), workflow_id.clone()))
+                .or_default()
+                .add(counts);
+        }
+    }
+    totals
+        .into_iter()
+        .map(|((external_id, workflow_id), counts)| (external_id, workflow_id, counts))
+        .collect()
+}
+
+/// Sum flushed increments per (model, tier) for the local model aggregates
+///
+/// Unlike `recent_totals`, every increment counts: the model aggregates
+/// carry no user identity.
+fn model_totals(
+    increments: &[((String, Option<String>), AggregatedUsage)],
+) -> Vec<(String, String, UsageCounts)> {
+    let mut totals: HashMap<(String, String), UsageCounts> = HashMap::new();
+    for ((_, model), usage) in increments {
+        let model = model.as_deref().unwrap_or(NO_MODEL);
+        for (tier, counts) in &usage.tiers {
+            totals.entry((model.to_string(), tier.clone())).or_default().add(counts);
+        }
+    }
+    totals
+        .into_iter()
+        .map(|((model, tier), counts)| (model, tier, counts))
+        .collect()
+}
+
+/// Split ledger request ids by whether Zion rejected the user's increment
+///
+/// Returns `(failed, delivered)`.
+fn partition_request_ids(
+    increments: &[((String, Option<String>), AggregatedUsage)],
+    failed_emails: &[&str],
+) -> (Vec<String>, Vec<String>) {
+    let mut failed = Vec::new();
+    let mut delivered = Vec::new();
+    for ((email, _), usage) in increments {
+        if failed_emails.contains(&email.as_str()) {
+            failed.extend(usage.request_ids.iter().cloned());
+        } else {
+            delivered.extend(usage.request_ids.iter().cloned());
+        }
+    }
+    (failed, delivered)
+}
+
 /// Metrics for the batching tracker
 pub mod metrics {
     use metrics::{counter, gauge};
@@ -870,6 +1598,12 @@ pub mod metrics {
     pub fn set_circuit_state(state: u8) {
         gauge!("sentinel_usage_circuit_state").set(state as f64);
     }
+
+    /// Set whether this replica holds the retry lease (1) or not (0)
+    pub fn set_retry_leader(replica: &str, leading: bool) {
+        gauge!("sentinel_usage_retry_leader", "replica" => replica.to_string())
+            .set(if leading { 1.0 } else { 0.0 });
+    }
 }
 
 #[cfg(test)]
@@ -887,6 +1621,8 @@ mod tests {
         assert_eq!(config.circuit_breaker_reset, Duration::from_secs(30));
         assert_eq!(config.retry_interval, Duration::from_secs(60));
         assert_eq!(config.max_retry_batch, 50);
+        assert_eq!(config.retry_lease_ttl, Duration::from_secs(150));
+        assert_eq!(config.failed_queue_key, REDIS_FAILED_INCREMENTS_KEY);
     }
 
     #[test]
@@ -908,7 +1644,13 @@ mod tests {
             output_tokens: 50,
             requests: 1,
             model: Some("gpt-4o".to_string()),
+            request_ids: Vec::new(),
+            organization_id: None,
+            external_id: None,
+            workflow_id: None,
+            tier: None,
             timestamp: "2024-01-15T10:30:00.000Z".to_string(),
+            estimated: false,
         };
 
         usage.add(&increment);
@@ -925,7 +1667,13 @@ mod tests {
             output_tokens: 50,
             requests: 1,
             model: Some("gpt-4o".to_string()),
+            request_ids: Vec::new(),
+            organization_id: None,
+            external_id: None,
+            workflow_id: None,
+            tier: None,
             timestamp: "2024-01-15T10:31:00.000Z".to_string(),
+            estimated: false,
         };
         usage.add(&increment2);
         assert_eq!(usage.input_tokens, 200);
@@ -946,7 +1694,13 @@ mod tests {
             output_tokens: 50,
             requests: 1,
             model: None,
+            request_ids: Vec::new(),
+            organization_id: None,
+            external_id: None,
+            workflow_id: None,
+            tier: None,
             timestamp: "2024-01-15T10:31:00.000Z".to_string(),
+            estimated: false,
         };
         usage.add(&increment1);
         assert_eq!(usage.timestamp, Some("2024-01-15T10:31:00.000Z".to_string()));
@@ -958,7 +1712,13 @@ mod tests {
             output_tokens: 50,
             requests: 1,
             model: None,
+            request_ids: Vec::new(),
+            organization_id: None,
+            external_id: None,
+            workflow_id: None,
+            tier: None,
             timestamp: "2024-01-15T10:30:00.000Z".to_string(),
+            estimated: false,
         };
         usage.add(&increment2);
         assert_eq!(usage.timestamp, Some("2024-01-15T10:30:00.000Z".to_string()));
@@ -974,6 +1734,12 @@ mod tests {
             output_tokens: 0,
             requests: 0,
             timestamp: None,
+            request_ids: Vec::new(),
+            organization_id: None,
+            external_id: None,
+            workflows: HashMap::new(),
+            tiers: HashMap::new(),
+            estimated: false,
         };
         assert!(!with_input.is_empty());
 
@@ -982,6 +1748,12 @@ mod tests {
             output_tokens: 1,
             requests: 0,
             timestamp: None,
+            request_ids: Vec::new(),
+            organization_id: None,
+            external_id: None,
+            workflows: HashMap::new(),
+            tiers: HashMap::new(),
+            estimated: false,
         };
         assert!(!with_output.is_empty());
 
@@ -990,6 +1762,12 @@ mod tests {
             output_tokens: 0,
             requests: 1,
             timestamp: None,
+            request_ids: Vec::new(),
+            organization_id: None,
+            external_id: None,
+            workflows: HashMap::new(),
+            tiers: HashMap::new(),
+            estimated: false,
         };
         assert!(!with_request.is_empty());
     }
@@ -1002,7 +1780,13 @@ mod tests {
             output_tokens: 50,
             requests: 1,
             model: Some("gpt-4o".to_string()),
+            request_ids: Vec::new(),
+            organization_id: None,
+            external_id: None,
+            workflow_id: None,
+            tier: None,
             timestamp: "2024-01-15T10:30:00.000Z".to_string(),
+            estimated: false,
         };
 
         let json = serde_json::to_string(&increment).unwrap();
@@ -1024,7 +1808,13 @@ mod tests {
             output_tokens: 50,
             requests: 1,
             model: None,
+            request_ids: Vec::new(),
+            organization_id: None,
+            external_id: None,
+            workflow_id: None,
+            tier: None,
             timestamp: "2024-01-15T10:30:00.000Z".to_string(),
+            estimated: false,
         };
 
         let json = serde_json::to_string(&increment).unwrap();
@@ -1049,6 +1839,94 @@ mod tests {
         assert_eq!(state, CircuitState::Closed);
     }
 
+    fn test_breaker(clock: &Arc<crate::clock::TestClock>) -> CircuitBreaker {
+        CircuitBreaker::new(&BatchingConfig {
+            circuit_breaker_threshold: 3,
+            circuit_breaker_reset: Duration::from_secs(30),
+            clock: clock.clone(),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_circuit_breaker_opens_at_threshold() {
+        let clock = crate::clock::TestClock::new(0);
+        let mut breaker = test_breaker(&clock);
+
+        assert!(!breaker.record_failure());
+        assert!(!breaker.record_failure());
+        assert!(breaker.allow());
+        assert!(breaker.record_failure());
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(!breaker.allow());
+
+        // A success before the threshold resets the count
+        let mut breaker = test_breaker(&clock);
+        breaker.record_failure();
+        breaker.record_failure();
+        breaker.record_success();
+        assert!(!breaker.record_failure());
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_circuit_breaker_half_opens_exactly_at_reset() {
+        let clock = crate::clock::TestClock::new(0);
+        let mut breaker = test_breaker(&clock);
+        for _ in 0..3 {
+            breaker.record_failure();
+        }
+
+        clock.advance(Duration::from_secs(30) - Duration::from_millis(1));
+        assert!(!breaker.allow());
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        clock.advance(Duration::from_millis(1));
+        assert!(breaker.allow());
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+
+        breaker.record_success();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert_eq!(breaker.consecutive_failures(), 0);
+    }
+
+    #[test]
+    fn test_circuit_breaker_publishes_state() {
+        let clock = crate::clock::TestClock::new(0);
+        let published = Arc::new(AtomicU8::new(0));
+        let mut breaker = test_breaker(&clock).publishing_to(published.clone());
+        let state = || CircuitState::from_code(published.load(Ordering::Relaxed)).as_str();
+
+        for _ in 0..3 {
+            breaker.record_failure();
+        }
+        assert_eq!(state(), "open");
+        clock.advance(Duration::from_secs(30));
+        breaker.allow();
+        assert_eq!(state(), "half_open");
+        breaker.record_success();
+        assert_eq!(state(), "closed");
+    }
+
+    #[test]
+    fn test_circuit_breaker_half_open_failure_reopens() {
+        let clock = crate::clock::TestClock::new(0);
+        let mut breaker = test_breaker(&clock);
+        for _ in 0..3 {
+            breaker.record_failure();
+        }
+        clock.advance(Duration::from_secs(30));
+        assert!(breaker.allow());
+
+        // The trial call fails: open again for a full reset period
+        assert!(breaker.record_failure());
+        assert_eq!(breaker.state(), CircuitState::Open);
+        clock.advance(Duration::from_secs(29));
+        assert!(!breaker.allow());
+        clock.advance(Duration::from_secs(1));
+        assert!(breaker.allow());
+    }
+
     #[test]
     fn test_aggregation_by_email_and_model() {
         use std::collections::HashMap;
@@ -1062,7 +1940,13 @@ mod tests {
             output_tokens: 50,
             requests: 1,
             model: Some("gpt-4o".to_string()),
+            request_ids: Vec::new(),
+            organization_id: None,
+            external_id: None,
+            workflow_id: None,
+            tier: None,
             timestamp: "2024-01-15T10:30:00.000Z".to_string(),
+            estimated: false,
         };
         buffer.entry((inc1.email.clone(), inc1.model.clone())).or_default().add(&inc1);
 
@@ -1073,7 +1957,13 @@ mod tests {
             output_tokens: 100,
             requests: 1,
             model: Some("gpt-4o".to_string()),
+            request_ids: Vec::new(),
+            organization_id: None,
+            external_id: None,
+            workflow_id: None,
+            tier: None,
             timestamp: "2024-01-15T10:31:00.000Z".to_string(),
+            estimated: false,
         };
         buffer.entry((inc2.email.clone(), inc2.model.clone())).or_default().add(&inc2);
 
@@ -1084,7 +1974,13 @@ mod tests {
             output_tokens: 25,
             requests: 1,
             model: Some("gpt-3.5-turbo".to_string()),
+            request_ids: Vec::new(),
+            organization_id: None,
+            external_id: None,
+            workflow_id: None,
+            tier: None,
             timestamp: "2024-01-15T10:32:00.000Z".to_string(),
+            estimated: false,
         };
         buffer.entry((inc3.email.clone(), inc3.model.clone())).or_default().add(&inc3);
 
@@ -1095,7 +1991,13 @@ mod tests {
             output_tokens: 25,
             requests: 1,
             model: Some("gpt-4o".to_string()),
+            request_ids: Vec::new(),
+            organization_id: None,
+            external_id: None,
+            workflow_id: None,
+            tier: None,
             timestamp: "2024-01-15T10:33:00.000Z".to_string(),
+            estimated: false,
         };
         buffer.entry((inc4.email.clone(), inc4.model.clone())).or_default().add(&inc4);
 
@@ -1133,7 +2035,13 @@ mod tests {
             output_tokens: 50,
             requests: 1,
             model: None,
+            request_ids: Vec::new(),
+            organization_id: None,
+            external_id: None,
+            workflow_id: None,
+            tier: None,
             timestamp: "2024-01-15T10:30:00.000Z".to_string(),
+            estimated: false,
         };
         buffer.entry((inc1.email.clone(), inc1.model.clone())).or_default().add(&inc1);
 
@@ -1144,7 +2052,13 @@ mod tests {
             output_tokens: 50,
             requests: 1,
             model: None,
+            request_ids: Vec::new(),
+            organization_id: None,
+            external_id: None,
+            workflow_id: None,
+            tier: None,
             timestamp: "2024-01-15T10:31:00.000Z".to_string(),
+            estimated: false,
         };
         buffer.entry((inc2.email.clone(), inc2.model.clone())).or_default().add(&inc2);
 
@@ -1156,4 +2070,163 @@ mod tests {
         assert_eq!(user1_no_model.output_tokens, 100);
         assert_eq!(user1_no_model.requests, 2);
     }
+
+    #[test]
+    fn test_aggregation_keeps_organization() {
+        let with_org = UsageIncrement {
+            email: "user1@example.com".to_string(),
+            input_tokens: 100,
+            output_tokens: 50,
+            requests: 1,
+            model: None,
+            request_ids: Vec::new(),
+            organization_id: Some("org_acme".to_string()),
+            external_id: None,
+            workflow_id: None,
+            tier: None,
+            timestamp: "2024-01-15T10:30:00.000Z".to_string(),
+            estimated: false,
+        };
+        let without_org = UsageIncrement {
+            organization_id: None,
+            ..with_org.clone()
+        };
+
+        let mut usage = AggregatedUsage::default();
+        usage.add(&with_org);
+        usage.add(&without_org);
+        assert_eq!(usage.organization_id.as_deref(), Some("org_acme"));
+
+        // Persisted increments round-trip the organization and omit it when unknown
+        let json = serde_json::to_string(&with_org).unwrap();
+        let parsed: UsageIncrement = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.organization_id.as_deref(), Some("org_acme"));
+        assert!(!serde_json::to_string(&without_org).unwrap().contains("organization_id"));
+    }
+
+    #[test]
+    fn test_aggregation_flags_estimated_usage() {
+        let exact = UsageIncrement {
+            email: "user1@example.com".to_string(),
+            input_tokens: 100,
+            output_tokens: 50,
+            requests: 1,
+            model: Some("gpt-4o".to_string()),
+            request_ids: Vec::new(),
+            organization_id: None,
+            external_id: None,
+            workflow_id: None,
+            tier: None,
+            timestamp: "2024-01-15T10:30:00.000Z".to_string(),
+            estimated: false,
+        };
+        let estimated = UsageIncrement {
+            estimated: true,
+            ..exact.clone()
+        };
+
+        // One estimated increment flags the whole item
+        let mut usage = AggregatedUsage::default();
+        usage.add(&estimated);
+        usage.add(&exact);
+        assert!(usage.estimated);
+        assert_eq!(usage.requests, 2);
+
+        // Failed-queue entries written before the flag existed read as exact
+        let legacy: UsageIncrement = serde_json::from_str(
+            r#"{"email":"a@b.c","input_tokens":1,"output_tokens":1,"requests":1,"model":null,"timestamp":"t"}"#,
+        )
+        .unwrap();
+        assert!(!legacy.estimated);
+    }
+
+    #[test]
+    fn test_recent_totals_sum_models_per_external_id() {
+        let increment = |email: &str, model: &str, external_id: Option<&str>| UsageIncrement {
+            email: email.to_string(),
+            input_tokens: 100,
+            output_tokens: 50,
+            requests: 1,
+            model: Some(model.to_string()),
+            request_ids: Vec::new(),
+            organization_id: None,
+            external_id: external_id.map(str::to_string),
+            workflow_id: None,
+            tier: None,
+            timestamp: "2024-01-15T10:30:00.000Z".to_string(),
+            estimated: false,
+        };
+
+        let mut buffer: HashMap<(String, Option<String>), AggregatedUsage> = HashMap::new();
+        for inc in [
+            increment("user1@example.com", "gpt-4o", Some("ext_1")),
+            increment("user1@example.com", "gpt-4o-mini", Some("ext_1")),
+            increment("user2@example.com", "gpt-4o", None),
+        ] {
+            buffer
+                .entry((inc.email.clone(), inc.model.clone()))
+                .or_default()
+                .add(&inc);
+        }
+        let increments: Vec<_> = buffer.into_iter().collect();
+
+        let totals = recent_totals(&increments);
+        assert_eq!(
+            totals,
+            vec![(
+                "ext_1".to_string(),
+                UsageCounts {
+                    requests: 2,
+                    input_tokens: 200,
+                    output_tokens: 100,
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn test_workflow_totals_split_tagged_usage() {
+        let increment = |model: &str, workflow_id: Option<&str>| UsageIncrement {
+            email: "user1@example.com".to_string(),
+            input_tokens: 100,
+            output_tokens: 50,
+            requests: 1,
+            model: Some(model.to_string()),
+            request_ids: Vec::new(),
+            organization_id: None,
+            external_id: Some("ext_1".to_string()),
+            workflow_id: workflow_id.map(str::to_string),
+            tier: None,
+            timestamp: "2024-01-15T10:30:00.000Z".to_string(),
+            estimated: false,
+        };
+
+        let mut buffer: HashMap<(String, Option<String>), AggregatedUsage> = HashMap::new();
+        for inc in [
+            increment("gpt-4o", Some("wf-1")),
+            increment("gpt-4o", None),
+            increment("gpt-4o-mini", Some("wf-1")),
+        ] {
+            buffer
+                .entry((inc.email.clone(), inc.model.clone()))
+                .or_default()
+                .add(&inc);
+        }
+        let increments: Vec<_> = buffer.into_iter().collect();
+
+        // Zion still gets one item per (email, model)
+        assert_eq!(increments.len(), 2);
+        assert_eq!(
+            workflow_totals(&increments),
+            vec![(
+                "ext_1".to_string(),
+                "wf-1".to_string(),
+                UsageCounts {
+                    requests: 2,
+                    input_tokens: 200,
+                    output_tokens: 100,
+                }
+            )]
+        );
+    }
 }
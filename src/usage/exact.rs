@@ -0,0 +1,130 @@
+//! Exact usage accounting
+//!
+//! Accounts listed in `REQUIRE_EXACT_USAGE` (external or organization IDs)
+//! are meant to be billed from provider-reported token counts only. Native
+//! non-streaming responses always carry usage (the translator rejects one
+//! without it), and native streams ask for a usage chunk with
+//! `stream_options.include_usage`, but a provider that ignores the option
+//! leaves Sentinel with an estimate. `EXACT_USAGE_MODE` decides what then:
+//!
+//! - `flag` (default): the stream is served. If it ends without usage, the
+//!   estimate is counted in `sentinel_estimated_usage_total`, logged with
+//!   `estimated_usage = true`, and with `REPORT_ESTIMATED_USAGE` the Zion
+//!   increment carries `estimated: true`
+//! - `strict`: streaming is refused with a 400 before the provider is called
+
+use std::str::FromStr;
+
+use crate::config::UsageConfig;
+use crate::middleware::auth::AuthenticatedUser;
+
+/// What to do with streams of accounts that require exact usage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExactUsageMode {
+    /// Serve the stream; flag the usage if it had to be estimated
+    #[default]
+    Flag,
+    /// Refuse streaming requests
+    Strict,
+}
+
+impl FromStr for ExactUsageMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "flag" => Ok(ExactUsageMode::Flag),
+            "strict" => Ok(ExactUsageMode::Strict),
+            other => Err(format!(
+                "unknown exact usage mode '{}' (expected flag or strict)",
+                other
+            )),
+        }
+    }
+}
+
+/// Message of the 400 returned for streams in strict mode
+pub const STREAMING_REFUSED: &str = "Streaming is not available for this account: it requires \
+    exact usage accounting, and streamed responses may end without provider-reported token \
+    usage. Retry with \"stream\": false.";
+
+/// Whether the user's external ID or organization requires exact usage
+pub fn required(config: &UsageConfig, user: &AuthenticatedUser) -> bool {
+    config
+        .require_exact_usage
+        .iter()
+        .any(|id| *id == user.external_id || user.organization_id.as_deref() == Some(id.as_str()))
+}
+
+/// Whether a streaming request from `user` must be refused
+pub fn refuses_stream(config: &UsageConfig, user: &AuthenticatedUser) -> bool {
+    config.exact_usage_mode == ExactUsageMode::Strict && required(config, user)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::{model_access::ModelAllowlist, scope::TokenScopes};
+
+    fn user(external_id: &str, organization_id: Option<&str>) -> AuthenticatedUser {
+        AuthenticatedUser {
+            user_id: "user_1".to_string(),
+            external_id: external_id.to_string(),
+            email: "user@example.com".to_string(),
+            organization_id: organization_id.map(str::to_string),
+            logging_opt_out: false,
+            allowed_models: ModelAllowlist::All,
+            synthetic: false,
+            scopes: TokenScopes::All,
+            token_charge: None,
+            upstream_user: None,
+        }
+    }
+
+    fn config(ids: &[&str], mode: ExactUsageMode) -> UsageConfig {
+        UsageConfig {
+            require_exact_usage: ids.iter().map(|id| id.to_string()).collect(),
+            exact_usage_mode: mode,
+            ..UsageConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_mode_from_str() {
+        assert_eq!(
+            " Strict ".parse::<ExactUsageMode>(),
+            Ok(ExactUsageMode::Strict)
+        );
+        assert_eq!("flag".parse::<ExactUsageMode>(), Ok(ExactUsageMode::Flag));
+        assert!("refuse".parse::<ExactUsageMode>().is_err());
+    }
+
+    #[test]
+    fn test_required_by_external_or_organization_id() {
+        let config = config(&["ext_1", "org_1"], ExactUsageMode::Flag);
+        assert!(required(&config, &user("ext_1", None)));
+        assert!(required(&config, &user("ext_2", Some("org_1"))));
+        assert!(!required(&config, &user("ext_2", Some("org_2"))));
+        assert!(!required(
+            &UsageConfig::default(),
+            &user("ext_1", Some("org_1"))
+        ));
+    }
+
+    #[test]
+    fn test_only_strict_mode_refuses_streams() {
+        let exact = user("ext_1", None);
+        assert!(!refuses_stream(
+            &config(&["ext_1"], ExactUsageMode::Flag),
+            &exact
+        ));
+        assert!(refuses_stream(
+            &config(&["ext_1"], ExactUsageMode::Strict),
+            &exact
+        ));
+        assert!(!refuses_stream(
+            &config(&["ext_1"], ExactUsageMode::Strict),
+            &user("ext_2", None)
+        ));
+    }
+}
@@ -0,0 +1,232 @@
+//! Local usage ledger
+//!
+//! An authoritative local record of every tracked request, written alongside
+//! the Zion increments so usage can be reconciled if Zion loses data.
+//!
+//! The `LedgerHandle` is always compiled so the batching tracker can report to
+//! it unconditionally; with the ledger disabled every call is a no-op. The
+//! SQL-backed store and its writer task live behind the `ledger` feature.
+
+#[cfg(feature = "ledger")]
+mod store;
+
+#[cfg(feature = "ledger")]
+pub use store::{spawn_ledger_writer, LedgerStore, LedgerWriterConfig};
+
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::mpsc;
+use tracing::warn;
+
+/// Delivery status of a ledger row to Zion
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    /// Queued in the batching tracker, not yet sent
+    Pending,
+    /// Accepted by Zion
+    Delivered,
+    /// Rejected or not sent (queued for retry or dropped)
+    Failed,
+}
+
+impl DeliveryStatus {
+    /// Value stored in the `delivery_status` column
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::Failed => "failed",
+        }
+    }
+}
+
+impl FromStr for DeliveryStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(DeliveryStatus::Pending),
+            "delivered" => Ok(DeliveryStatus::Delivered),
+            "failed" => Ok(DeliveryStatus::Failed),
+            other => Err(format!("unknown delivery status '{}'", other)),
+        }
+    }
+}
+
+/// A single ledger row
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    pub request_id: String,
+    /// SHA-256 of the user's email (the ledger never stores raw emails)
+    pub user_hash: String,
+    pub model: Option<String>,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub requests: i64,
+    /// Unix timestamp in milliseconds
+    pub created_at: i64,
+    pub delivery_status: DeliveryStatus,
+    /// The `user` value forwarded to the provider, hashed (see
+    /// `proxy::upstream_user::recorded_value`), to match provider abuse reports
+    #[serde(default)]
+    pub upstream_user: Option<String>,
+}
+
+/// A write queued for the ledger
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum LedgerOp {
+    /// Insert a new row
+    Append(LedgerEntry),
+    /// Update the delivery status of existing rows
+    MarkDelivery {
+        request_ids: Vec<String>,
+        status: DeliveryStatus,
+    },
+}
+
+/// Hash a user's email for storage in the ledger
+pub fn hash_user(email: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(email.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// Fire-and-forget handle for queueing ledger writes
+///
+/// Never blocks: when the writer's queue is full the write is dropped and
+/// logged, so the ledger can never slow down request handling.
+#[derive(Debug, Clone, Default)]
+pub struct LedgerHandle {
+    sender: Option<mpsc::Sender<LedgerOp>>,
+}
+
+impl LedgerHandle {
+    /// Handle that discards all writes
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    /// Handle feeding the given writer queue
+    pub fn new(sender: mpsc::Sender<LedgerOp>) -> Self {
+        Self {
+            sender: Some(sender),
+        }
+    }
+
+    /// Whether writes go anywhere
+    pub fn is_enabled(&self) -> bool {
+        self.sender.is_some()
+    }
+
+    /// Queue a new ledger row
+    pub fn append(&self, entry: LedgerEntry) {
+        self.send(LedgerOp::Append(entry));
+    }
+
+    /// Queue a delivery status update
+    pub fn mark(&self, request_ids: Vec<String>, status: DeliveryStatus) {
+        if request_ids.is_empty() {
+            return;
+        }
+        self.send(LedgerOp::MarkDelivery {
+            request_ids,
+            status,
+        });
+    }
+
+    fn send(&self, op: LedgerOp) {
+        let Some(ref sender) = self.sender else {
+            return;
+        };
+
+        if let Err(e) = sender.try_send(op) {
+            metrics::counter!("sentinel_ledger_dropped_total").increment(1);
+            match e {
+                mpsc::error::TrySendError::Full(_) => {
+                    warn!("Usage ledger queue full, dropping write");
+                }
+                mpsc::error::TrySendError::Closed(_) => {
+                    warn!("Usage ledger writer stopped, dropping write");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(request_id: &str) -> LedgerEntry {
+        LedgerEntry {
+            request_id: request_id.to_string(),
+            user_hash: hash_user("test@test.com"),
+            model: Some("gpt-4o".to_string()),
+            input_tokens: 10,
+            output_tokens: 5,
+            requests: 1,
+            created_at: 1_700_000_000_000,
+            delivery_status: DeliveryStatus::Pending,
+            upstream_user: None,
+        }
+    }
+
+    #[test]
+    fn test_delivery_status_roundtrip() {
+        for status in [
+            DeliveryStatus::Pending,
+            DeliveryStatus::Delivered,
+            DeliveryStatus::Failed,
+        ] {
+            assert_eq!(status.as_str().parse::<DeliveryStatus>().unwrap(), status);
+        }
+        assert!("lost".parse::<DeliveryStatus>().is_err());
+    }
+
+    #[test]
+    fn test_hash_user_is_stable_and_not_raw() {
+        let hash = hash_user("test@test.com");
+        assert_eq!(hash, hash_user("test@test.com"));
+        assert_eq!(hash.len(), 64);
+        assert!(!hash.contains("test@test.com"));
+    }
+
+    #[tokio::test]
+    async fn test_handle_queues_ops() {
+        let (sender, mut receiver) = mpsc::channel(4);
+        let handle = LedgerHandle::new(sender);
+
+        handle.append(entry("req-1"));
+        handle.mark(vec!["req-1".to_string()], DeliveryStatus::Delivered);
+        handle.mark(Vec::new(), DeliveryStatus::Failed); // ignored
+
+        assert_eq!(receiver.recv().await, Some(LedgerOp::Append(entry("req-1"))));
+        assert_eq!(
+            receiver.recv().await,
+            Some(LedgerOp::MarkDelivery {
+                request_ids: vec!["req-1".to_string()],
+                status: DeliveryStatus::Delivered,
+            })
+        );
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_full_queue_drops_without_blocking() {
+        let (sender, _receiver) = mpsc::channel(1);
+        let handle = LedgerHandle::new(sender);
+
+        handle.append(entry("req-1"));
+        handle.append(entry("req-2")); // dropped, must not block
+    }
+
+    #[test]
+    fn test_disabled_handle_is_noop() {
+        let handle = LedgerHandle::disabled();
+        assert!(!handle.is_enabled());
+        handle.append(entry("req-1"));
+    }
+}
@@ -0,0 +1,420 @@
+//! SQL-backed ledger store and batched writer
+//!
+//! Uses sqlx's `Any` driver so the same queries run on SQLite and Postgres.
+//! Writes go through a background task that batches them into transactions;
+//! when the database is unavailable the batch is parked in a Redis list and
+//! replayed later, mirroring the batching tracker's failed-increment queue.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use redis::AsyncCommands;
+use sqlx::any::{install_default_drivers, AnyPoolOptions};
+use sqlx::{AnyPool, Row};
+use tokio::sync::mpsc;
+use tracing::{debug, error, info, warn};
+
+use super::{DeliveryStatus, LedgerEntry, LedgerHandle, LedgerOp};
+use crate::error::AppResult;
+
+/// Redis key for ledger writes that couldn't reach the database
+const REDIS_FAILED_LEDGER_KEY: &str = "sentinel:ledger:failed";
+
+/// Ledger database access
+pub struct LedgerStore {
+    pool: AnyPool,
+}
+
+impl LedgerStore {
+    /// Connect to `LEDGER_DATABASE_URL` and run pending migrations
+    ///
+    /// Accepts `sqlite:` and `postgres:` URLs. In-memory SQLite databases are
+    /// pinned to a single long-lived connection so the data survives.
+    pub async fn connect(url: &str) -> AppResult<Self> {
+        install_default_drivers();
+
+        let in_memory = url.contains(":memory:") || url.contains("mode=memory");
+        let mut options = AnyPoolOptions::new().max_connections(if in_memory { 1 } else { 5 });
+        if in_memory {
+            options = options.idle_timeout(None).max_lifetime(None);
+        }
+
+        let pool = options.connect(url).await?;
+        sqlx::migrate!("./migrations/ledger")
+            .run(&pool)
+            .await
+            .map_err(sqlx::Error::from)?;
+
+        Ok(Self { pool })
+    }
+
+    /// Apply a batch of writes in a single transaction
+    pub async fn apply(&self, ops: &[LedgerOp]) -> AppResult<()> {
+        let now = chrono::Utc::now().timestamp_millis();
+        let mut tx = self.pool.begin().await?;
+
+        for op in ops {
+            match op {
+                LedgerOp::Append(entry) => {
+                    sqlx::query(
+                        "INSERT INTO usage_ledger \
+                         (request_id, user_hash, model, input_tokens, output_tokens, requests, \
+                          created_at, delivery_status, updated_at, upstream_user) \
+                         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) \
+                         ON CONFLICT (request_id) DO NOTHING",
+                    )
+                    .bind(entry.request_id.clone())
+                    .bind(entry.user_hash.clone())
+                    .bind(entry.model.clone())
+                    .bind(entry.input_tokens)
+                    .bind(entry.output_tokens)
+                    .bind(entry.requests)
+                    .bind(entry.created_at)
+                    .bind(entry.delivery_status.as_str())
+                    .bind(now)
+                    .bind(entry.upstream_user.clone())
+                    .execute(&mut *tx)
+                    .await?;
+                }
+                LedgerOp::MarkDelivery {
+                    request_ids,
+                    status,
+                } => {
+                    for request_id in request_ids {
+                        sqlx::query(
+                            "UPDATE usage_ledger SET delivery_status = $1, updated_at = $2 \
+                             WHERE request_id = $3",
+                        )
+                        .bind(status.as_str())
+                        .bind(now)
+                        .bind(request_id.clone())
+                        .execute(&mut *tx)
+                        .await?;
+                    }
+                }
+            }
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Export rows created in `[from_ms, to_ms)`, oldest first
+    pub async fn export(&self, from_ms: i64, to_ms: i64, limit: i64) -> AppResult<Vec<LedgerEntry>> {
+        let rows = sqlx::query(
+            "SELECT request_id, user_hash, COALESCE(model, '') AS model, input_tokens, output_tokens, requests, \
+             created_at, delivery_status, COALESCE(upstream_user, '') AS upstream_user \
+             FROM usage_ledger WHERE created_at >= $1 AND created_at < $2 \
+             ORDER BY created_at, request_id LIMIT $3",
+        )
+        .bind(from_ms)
+        .bind(to_ms)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut entries = Vec::with_capacity(rows.len());
+        for row in rows {
+            let status: String = row.try_get("delivery_status")?;
+            // The Any driver can't decode NULL into Option, so these are COALESCEd
+            let model: String = row.try_get("model")?;
+            let upstream_user: String = row.try_get("upstream_user")?;
+            entries.push(LedgerEntry {
+                request_id: row.try_get("request_id")?,
+                user_hash: row.try_get("user_hash")?,
+                model: Some(model).filter(|m| !m.is_empty()),
+                input_tokens: row.try_get("input_tokens")?,
+                output_tokens: row.try_get("output_tokens")?,
+                requests: row.try_get("requests")?,
+                created_at: row.try_get("created_at")?,
+                delivery_status: status.parse().unwrap_or(DeliveryStatus::Failed),
+                upstream_user: Some(upstream_user).filter(|u| !u.is_empty()),
+            });
+        }
+
+        Ok(entries)
+    }
+}
+
+/// Configuration for the ledger writer task
+#[derive(Debug, Clone)]
+pub struct LedgerWriterConfig {
+    /// Maximum number of writes per transaction
+    pub max_batch_size: usize,
+    /// Maximum time to wait before flushing a batch
+    pub flush_interval: Duration,
+    /// Queue size between request handlers and the writer
+    pub channel_buffer: usize,
+    /// Time between replays of writes parked in Redis
+    pub retry_interval: Duration,
+    /// Maximum number of parked writes replayed per cycle
+    pub max_retry_batch: usize,
+}
+
+impl Default for LedgerWriterConfig {
+    fn default() -> Self {
+        Self {
+            max_batch_size: 200,
+            flush_interval: Duration::from_millis(250),
+            channel_buffer: 10_000,
+            retry_interval: Duration::from_secs(30),
+            max_retry_batch: 500,
+        }
+    }
+}
+
+/// Spawn the background writer and return a handle feeding it
+pub fn spawn_ledger_writer(
+    store: Arc<LedgerStore>,
+    redis: Option<redis::aio::ConnectionManager>,
+    config: LedgerWriterConfig,
+) -> LedgerHandle {
+    let (sender, receiver) = mpsc::channel(config.channel_buffer);
+    tokio::spawn(writer_loop(store, redis, receiver, config));
+    LedgerHandle::new(sender)
+}
+
+async fn writer_loop(
+    store: Arc<LedgerStore>,
+    redis: Option<redis::aio::ConnectionManager>,
+    mut receiver: mpsc::Receiver<LedgerOp>,
+    config: LedgerWriterConfig,
+) {
+    info!(
+        batch_size = config.max_batch_size,
+        flush_interval_ms = config.flush_interval.as_millis(),
+        "Starting usage ledger writer"
+    );
+
+    let mut batch: Vec<LedgerOp> = Vec::new();
+    let mut last_flush = Instant::now();
+    let mut last_retry = Instant::now();
+
+    loop {
+        let time_until_flush = config.flush_interval.saturating_sub(last_flush.elapsed());
+        let time_until_retry = config.retry_interval.saturating_sub(last_retry.elapsed());
+
+        tokio::select! {
+            maybe_op = receiver.recv() => {
+                match maybe_op {
+                    Some(op) => {
+                        batch.push(op);
+                        if batch.len() >= config.max_batch_size {
+                            flush(&store, redis.as_ref(), &mut batch).await;
+                            last_flush = Instant::now();
+                        }
+                    }
+                    None => {
+                        flush(&store, redis.as_ref(), &mut batch).await;
+                        info!("Usage ledger writer shutting down");
+                        break;
+                    }
+                }
+            }
+            _ = tokio::time::sleep(time_until_flush) => {
+                flush(&store, redis.as_ref(), &mut batch).await;
+                last_flush = Instant::now();
+            }
+            _ = tokio::time::sleep(time_until_retry) => {
+                if let Some(ref redis) = redis {
+                    replay_failed(&store, redis, config.max_retry_batch).await;
+                }
+                last_retry = Instant::now();
+            }
+        }
+    }
+}
+
+/// Write the batch, parking it in Redis if the database is unavailable
+async fn flush(
+    store: &LedgerStore,
+    redis: Option<&redis::aio::ConnectionManager>,
+    batch: &mut Vec<LedgerOp>,
+) {
+    if batch.is_empty() {
+        return;
+    }
+    let ops = std::mem::take(batch);
+
+    match store.apply(&ops).await {
+        Ok(()) => debug!(count = ops.len(), "Usage ledger batch written"),
+        Err(e) => {
+            warn!(error = %e, count = ops.len(), "Usage ledger write failed, parking in Redis");
+            match redis {
+                Some(redis) => park_failed(redis, &ops).await,
+                None => error!(count = ops.len(), "No Redis available, dropping ledger writes"),
+            }
+        }
+    }
+}
+
+/// Append writes to the Redis failed-ledger queue
+async fn park_failed(redis: &redis::aio::ConnectionManager, ops: &[LedgerOp]) {
+    let mut conn = redis.clone();
+    for op in ops {
+        let json = match serde_json::to_string(op) {
+            Ok(json) => json,
+            Err(e) => {
+                error!(error = %e, "Failed to serialize ledger write");
+                continue;
+            }
+        };
+        if let Err(e) = conn.rpush::<_, _, ()>(REDIS_FAILED_LEDGER_KEY, json).await {
+            error!(error = %e, "Failed to park ledger write in Redis");
+        }
+    }
+}
+
+/// Replay parked writes from Redis (FIFO), re-parking them on failure
+async fn replay_failed(store: &LedgerStore, redis: &redis::aio::ConnectionManager, max_batch: usize) {
+    let mut conn = redis.clone();
+
+    let mut ops = Vec::new();
+    for _ in 0..max_batch {
+        let json: Option<String> = match conn.lpop(REDIS_FAILED_LEDGER_KEY, None).await {
+            Ok(json) => json,
+            Err(e) => {
+                warn!(error = %e, "Failed to pop from ledger retry queue");
+                break;
+            }
+        };
+        let Some(json) = json else {
+            break;
+        };
+        match serde_json::from_str::<LedgerOp>(&json) {
+            Ok(op) => ops.push(op),
+            Err(e) => error!(error = %e, json = %json, "Failed to deserialize parked ledger write"),
+        }
+    }
+
+    if ops.is_empty() {
+        return;
+    }
+
+    match store.apply(&ops).await {
+        Ok(()) => info!(count = ops.len(), "Replayed parked usage ledger writes"),
+        Err(e) => {
+            warn!(error = %e, count = ops.len(), "Ledger replay failed, re-parking");
+            park_failed(redis, &ops).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::usage::ledger::hash_user;
+
+    fn entry(request_id: &str, created_at: i64) -> LedgerEntry {
+        LedgerEntry {
+            request_id: request_id.to_string(),
+            user_hash: hash_user("test@test.com"),
+            model: Some("gpt-4o".to_string()),
+            input_tokens: 100,
+            output_tokens: 50,
+            requests: 1,
+            created_at,
+            delivery_status: DeliveryStatus::Pending,
+            upstream_user: None,
+        }
+    }
+
+    async fn memory_store() -> LedgerStore {
+        LedgerStore::connect("sqlite::memory:").await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_append_and_export() {
+        let store = memory_store().await;
+        store
+            .apply(&[
+                LedgerOp::Append(entry("req-1", 1_000)),
+                LedgerOp::Append(entry("req-2", 2_000)),
+                LedgerOp::Append(LedgerEntry {
+                    model: None,
+                    upstream_user: Some("5e8f".to_string()),
+                    ..entry("req-3", 3_000)
+                }),
+            ])
+            .await
+            .unwrap();
+
+        let all = store.export(0, i64::MAX, 100).await.unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0], entry("req-1", 1_000));
+        assert!(all[2].model.is_none());
+        assert_eq!(all[2].upstream_user.as_deref(), Some("5e8f"));
+
+        // Range is half-open: [from, to)
+        let range = store.export(1_000, 3_000, 100).await.unwrap();
+        let ids: Vec<&str> = range.iter().map(|e| e.request_id.as_str()).collect();
+        assert_eq!(ids, vec!["req-1", "req-2"]);
+
+        let limited = store.export(0, i64::MAX, 1).await.unwrap();
+        assert_eq!(limited.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_mark_delivery_updates_status() {
+        let store = memory_store().await;
+        store
+            .apply(&[
+                LedgerOp::Append(entry("req-1", 1_000)),
+                LedgerOp::Append(entry("req-2", 1_001)),
+                LedgerOp::MarkDelivery {
+                    request_ids: vec!["req-1".to_string()],
+                    status: DeliveryStatus::Delivered,
+                },
+                LedgerOp::MarkDelivery {
+                    request_ids: vec!["req-2".to_string()],
+                    status: DeliveryStatus::Failed,
+                },
+            ])
+            .await
+            .unwrap();
+
+        let rows = store.export(0, i64::MAX, 100).await.unwrap();
+        assert_eq!(rows[0].delivery_status, DeliveryStatus::Delivered);
+        assert_eq!(rows[1].delivery_status, DeliveryStatus::Failed);
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_append_is_ignored() {
+        let store = memory_store().await;
+        store
+            .apply(&[
+                LedgerOp::Append(entry("req-1", 1_000)),
+                LedgerOp::Append(entry("req-1", 9_000)),
+            ])
+            .await
+            .unwrap();
+
+        let rows = store.export(0, i64::MAX, 100).await.unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].created_at, 1_000);
+    }
+
+    #[tokio::test]
+    async fn test_writer_batches_handle_writes() {
+        let store = Arc::new(memory_store().await);
+        let config = LedgerWriterConfig {
+            flush_interval: Duration::from_millis(10),
+            ..Default::default()
+        };
+        let handle = spawn_ledger_writer(store.clone(), None, config);
+
+        handle.append(entry("req-1", 1_000));
+        handle.mark(vec!["req-1".to_string()], DeliveryStatus::Delivered);
+
+        let mut rows = Vec::new();
+        for _ in 0..50 {
+            rows = store.export(0, i64::MAX, 100).await.unwrap();
+            if rows.first().map(|r| r.delivery_status) == Some(DeliveryStatus::Delivered) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].delivery_status, DeliveryStatus::Delivered);
+    }
+}
@@ -3,7 +3,22 @@
 //! Tracks and reports AI usage to Zion.
 
 pub mod batching;
+pub mod exact;
+pub mod ledger;
+pub mod queue;
+pub mod recent;
+pub mod report;
+pub mod retry_lease;
 pub mod tracker;
+pub mod weights;
+pub mod workflow;
 
-pub use batching::{BatchingConfig, BatchingUsageTracker};
+pub use batching::{BatchingConfig, BatchingUsageTracker, UsageTrackerStatus};
+pub use exact::ExactUsageMode;
+pub use ledger::LedgerHandle;
+pub use queue::FailedQueue;
+pub use recent::{ModelDailyUsage, RecentUsage, RecentUsageStore};
+pub use retry_lease::{RetryLease, RetryLeaseStatus};
 pub use tracker::{limits, UsageData, UsageTracker};
+pub use weights::{RequestWeightTable, RequestWeights, RequestWeightsStatus};
+pub use workflow::{validate_workflow_id, workflow_id_from_headers, WORKFLOW_ID_HEADER};
@@ -0,0 +1,525 @@
+//! Failed usage increments queue
+//!
+//! Increments Zion rejected (or that couldn't be sent) are parked in the
+//! `sentinel:usage:failed` Redis list and retried by the batching tracker.
+//! During a long Zion outage operators inspect, export, flush or purge the
+//! queue with `sentinel usage-queue` (see `crate::cli`).
+//!
+//! Anything that pops or removes entries holds `sentinel:usage:failed:lock`
+//! (SET NX with a TTL, refreshed while working): each replica's retry loop
+//! skips a cycle while a manual flush or purge runs, and vice versa. Stats and
+//! exports only read the list and never take the lock.
+//!
+//! Entries carry their schema version (see `crate::cache::schema`). Entries
+//! written by a newer release are put back for it instead of being dropped,
+//! so a rollback or a mixed-version rollout never loses usage.
+
+use std::collections::BTreeMap;
+use std::num::NonZeroU32;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use governor::{Quota, RateLimiter};
+use redis::AsyncCommands;
+use serde::Serialize;
+use tracing::{debug, warn};
+
+use super::batching::UsageIncrement;
+use super::ledger::{DeliveryStatus, LedgerHandle};
+use crate::cache::schema::{self, Unreadable};
+use crate::error::AppResult;
+use crate::zion::ZionClient;
+
+/// Redis list holding failed usage increments (FIFO)
+pub const REDIS_FAILED_INCREMENTS_KEY: &str = "sentinel:usage:failed";
+
+/// Lock held while entries are popped or removed from the queue
+const REDIS_FAILED_LOCK_KEY: &str = "sentinel:usage:failed:lock";
+
+/// Lifetime of the lock unless refreshed
+const LOCK_TTL_SECONDS: u64 = 120;
+
+/// Delete the lock only if we still own it
+const RELEASE_LOCK_SCRIPT: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('DEL', KEYS[1])
+end
+return 0
+"#;
+
+/// Extend the lock only if we still own it
+const REFRESH_LOCK_SCRIPT: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('EXPIRE', KEYS[1], ARGV[2])
+end
+return 0
+"#;
+
+/// Queued totals for one user
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserTotals {
+    pub email: String,
+    pub increments: u64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub requests: i64,
+}
+
+/// Summary of the queue's contents
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueStats {
+    /// Entries in the list, including unreadable ones
+    pub length: usize,
+    /// Entries that aren't valid increments
+    pub unreadable: usize,
+    /// Earliest increment timestamp
+    pub oldest: Option<String>,
+    /// Latest increment timestamp
+    pub newest: Option<String>,
+    /// Totals per user, by email
+    pub users: Vec<UserTotals>,
+}
+
+impl QueueStats {
+    /// Summarize raw queue entries
+    pub fn from_entries(entries: &[String]) -> Self {
+        let mut stats = QueueStats {
+            length: entries.len(),
+            ..Default::default()
+        };
+        let mut users: BTreeMap<&str, UserTotals> = BTreeMap::new();
+        let increments = decode(entries, &mut stats.unreadable);
+        for increment in &increments {
+            let totals = users.entry(&increment.email).or_default();
+            totals.increments += 1;
+            totals.input_tokens += increment.input_tokens;
+            totals.output_tokens += increment.output_tokens;
+            totals.requests += increment.requests;
+
+            let timestamp = &increment.timestamp;
+            if stats
+                .oldest
+                .as_ref()
+                .is_none_or(|oldest| timestamp < oldest)
+            {
+                stats.oldest = Some(timestamp.clone());
+            }
+            if stats
+                .newest
+                .as_ref()
+                .is_none_or(|newest| timestamp > newest)
+            {
+                stats.newest = Some(timestamp.clone());
+            }
+        }
+        stats.users = users
+            .into_iter()
+            .map(|(email, totals)| UserTotals {
+                email: email.to_string(),
+                ..totals
+            })
+            .collect();
+        stats
+    }
+}
+
+/// Export format for `usage-queue export`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+}
+
+/// Render raw queue entries in an export format
+///
+/// Unreadable entries are left out.
+pub fn export(entries: &[String], format: ExportFormat) -> String {
+    let increments = decode(entries, &mut 0);
+    match format {
+        ExportFormat::Json => serde_json::to_string_pretty(&increments).unwrap_or_default(),
+        ExportFormat::Csv => {
+            let mut csv = String::from(
+                "timestamp,email,model,input_tokens,output_tokens,requests,organization_id,external_id\n",
+            );
+            for increment in &increments {
+                let row = [
+                    csv_field(&increment.timestamp),
+                    csv_field(&increment.email),
+                    csv_field(increment.model.as_deref().unwrap_or_default()),
+                    increment.input_tokens.to_string(),
+                    increment.output_tokens.to_string(),
+                    increment.requests.to_string(),
+                    csv_field(increment.organization_id.as_deref().unwrap_or_default()),
+                    csv_field(increment.external_id.as_deref().unwrap_or_default()),
+                ];
+                csv.push_str(&row.join(","));
+                csv.push('\n');
+            }
+            csv
+        }
+    }
+}
+
+/// Quote a CSV field when it contains a separator, quote or newline
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Parse entries, counting the ones that aren't increments
+fn decode(entries: &[String], unreadable: &mut usize) -> Vec<UsageIncrement> {
+    entries
+        .iter()
+        .filter_map(|json| match schema::decode(json) {
+            Ok(increment) => Some(increment),
+            Err(_) => {
+                *unreadable += 1;
+                None
+            }
+        })
+        .collect()
+}
+
+/// Whether an entry's increment is older than the cutoff
+///
+/// Unreadable entries and timestamps are never considered old.
+fn is_older_than(json: &str, cutoff: DateTime<Utc>) -> bool {
+    schema::decode::<UsageIncrement>(json)
+        .ok()
+        .and_then(|increment| DateTime::parse_from_rfc3339(&increment.timestamp).ok())
+        .is_some_and(|timestamp| timestamp < cutoff)
+}
+
+/// Result of a manual flush
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlushReport {
+    pub delivered: usize,
+    /// Entries that failed and were re-queued (the flush stops at the first)
+    pub failed: usize,
+    /// Entries that couldn't be parsed and were dropped
+    pub dropped: usize,
+    /// Entries written by a newer release, re-queued for it
+    pub deferred: usize,
+    /// Entries left in the queue
+    pub remaining: usize,
+}
+
+/// Exclusive hold on the queue; release it when done
+#[derive(Debug)]
+pub struct QueueLock {
+    token: String,
+}
+
+/// Handle on the failed increments queue
+#[derive(Clone)]
+pub struct FailedQueue {
+    redis: redis::aio::ConnectionManager,
+    key: String,
+    lock_key: String,
+}
+
+impl FailedQueue {
+    /// Queue in the given Redis
+    pub fn new(redis: redis::aio::ConnectionManager) -> Self {
+        Self {
+            redis,
+            key: REDIS_FAILED_INCREMENTS_KEY.to_string(),
+            lock_key: REDIS_FAILED_LOCK_KEY.to_string(),
+        }
+    }
+
+    /// Queue under a different key (`BatchingConfig::failed_queue_key`, or
+    /// tests that mustn't touch the real queue)
+    pub fn with_key(redis: redis::aio::ConnectionManager, key: &str) -> Self {
+        Self {
+            redis,
+            key: key.to_string(),
+            lock_key: format!("{}:lock", key),
+        }
+    }
+
+    /// Number of queued entries
+    pub async fn len(&self) -> AppResult<usize> {
+        let mut conn = self.redis.clone();
+        Ok(conn.llen(&self.key).await?)
+    }
+
+    /// Whether the queue is empty
+    pub async fn is_empty(&self) -> AppResult<bool> {
+        Ok(self.len().await? == 0)
+    }
+
+    /// All raw entries, oldest first
+    pub async fn entries(&self) -> AppResult<Vec<String>> {
+        let mut conn = self.redis.clone();
+        Ok(conn.lrange(&self.key, 0, -1).await?)
+    }
+
+    /// Summarize the queue
+    pub async fn stats(&self) -> AppResult<QueueStats> {
+        Ok(QueueStats::from_entries(&self.entries().await?))
+    }
+
+    /// Append an increment to the back of the queue
+    pub(crate) async fn push(&self, increment: &UsageIncrement) -> AppResult<()> {
+        let mut conn = self.redis.clone();
+        conn.rpush::<_, _, ()>(&self.key, schema::encode(increment)?).await?;
+        Ok(())
+    }
+
+    /// Put a popped entry back at the end of the queue as it was
+    pub(crate) async fn requeue(&self, json: &str) -> AppResult<()> {
+        let mut conn = self.redis.clone();
+        conn.rpush::<_, _, ()>(&self.key, json).await?;
+        Ok(())
+    }
+
+    /// Pop the entry at the front of the queue
+    pub(crate) async fn pop(&self) -> AppResult<Option<String>> {
+        let mut conn = self.redis.clone();
+        Ok(conn.lpop(&self.key, None).await?)
+    }
+
+    /// Take the queue lock, or None when someone else holds it
+    pub async fn try_lock(&self) -> AppResult<Option<QueueLock>> {
+        let mut conn = self.redis.clone();
+        let token = uuid::Uuid::new_v4().to_string();
+        let acquired: Option<String> = redis::cmd("SET")
+            .arg(&self.lock_key)
+            .arg(&token)
+            .arg("NX")
+            .arg("EX")
+            .arg(LOCK_TTL_SECONDS)
+            .query_async(&mut conn)
+            .await?;
+        Ok(acquired.map(|_| QueueLock { token }))
+    }
+
+    /// Push the lock's expiry out again during long work
+    pub async fn refresh_lock(&self, lock: &QueueLock) -> AppResult<()> {
+        let mut conn = self.redis.clone();
+        let _: i64 = redis::Script::new(REFRESH_LOCK_SCRIPT)
+            .key(&self.lock_key)
+            .arg(&lock.token)
+            .arg(LOCK_TTL_SECONDS)
+            .invoke_async(&mut conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Release the lock (a no-op if it already expired)
+    pub async fn unlock(&self, lock: QueueLock) -> AppResult<()> {
+        let mut conn = self.redis.clone();
+        let _: i64 = redis::Script::new(RELEASE_LOCK_SCRIPT)
+            .key(&self.lock_key)
+            .arg(&lock.token)
+            .invoke_async(&mut conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Send the entries queued now to Zion, at most `rate_per_second` per second
+    ///
+    /// Stops at the first failure, which is re-queued, so a flush during an
+    /// outage doesn't cycle through the whole queue. The caller must hold the
+    /// lock.
+    pub async fn flush(
+        &self,
+        lock: &QueueLock,
+        zion_client: &ZionClient,
+        rate_per_second: u32,
+        ledger: &LedgerHandle,
+    ) -> AppResult<FlushReport> {
+        let rate = NonZeroU32::new(rate_per_second).unwrap_or(NonZeroU32::MIN);
+        let rate_limiter = RateLimiter::direct(Quota::per_second(rate));
+        let mut report = FlushReport::default();
+
+        for _ in 0..self.len().await? {
+            let Some(json) = self.pop().await? else {
+                break;
+            };
+            let increment = match schema::decode::<UsageIncrement>(&json) {
+                Ok(increment) => increment,
+                Err(Unreadable::Newer(_)) => {
+                    report.deferred += 1;
+                    self.requeue(&json).await?;
+                    continue;
+                }
+                Err(Unreadable::Invalid) => {
+                    warn!(json = %json, "Dropping unreadable queued increment");
+                    report.dropped += 1;
+                    continue;
+                }
+            };
+
+            rate_limiter.until_ready().await;
+            match increment.deliver(zion_client).await {
+                Ok(_) => {
+                    report.delivered += 1;
+                    ledger.mark(increment.request_ids.clone(), DeliveryStatus::Delivered);
+                    debug!(email = %increment.email, "Flushed queued increment");
+                }
+                Err(e) => {
+                    warn!(email = %increment.email, error = %e, "Flush failed, re-queuing");
+                    report.failed += 1;
+                    self.push(&increment).await?;
+                    break;
+                }
+            }
+            self.refresh_lock(lock).await?;
+        }
+
+        report.remaining = self.len().await?;
+        Ok(report)
+    }
+
+    /// Remove entries whose increment is older than `max_age`
+    ///
+    /// Removes each matching entry by value, so increments re-queued while the
+    /// purge runs are kept. The caller must hold the lock.
+    pub async fn purge_older_than(&self, lock: &QueueLock, max_age: Duration) -> AppResult<usize> {
+        let cutoff =
+            Utc::now() - chrono::Duration::from_std(max_age).unwrap_or(chrono::Duration::MAX);
+        let mut conn = self.redis.clone();
+        let mut purged = 0;
+        for (index, json) in self.entries().await?.iter().enumerate() {
+            if !is_older_than(json, cutoff) {
+                continue;
+            }
+            let removed: usize = conn.lrem(&self.key, 1, json).await?;
+            purged += removed;
+            if index % 1000 == 999 {
+                self.refresh_lock(lock).await?;
+            }
+        }
+        Ok(purged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn seeded_queue() -> Vec<String> {
+        vec![
+            json!({
+                "email": "alice@example.com", "input_tokens": 100, "output_tokens": 20,
+                "requests": 1, "model": "gpt-4o", "timestamp": "2026-10-01T10:00:00.000Z"
+            })
+            .to_string(),
+            json!({
+                "email": "bob@example.com", "input_tokens": 5, "output_tokens": 5,
+                "requests": 1, "model": null, "timestamp": "2026-09-30T08:00:00.000Z",
+                "organization_id": "org, \"quoted\""
+            })
+            .to_string(),
+            "not json".to_string(),
+            json!({
+                "email": "alice@example.com", "input_tokens": 50, "output_tokens": 10,
+                "requests": 2, "model": "gpt-4o", "timestamp": "2026-10-02T12:00:00.000Z"
+            })
+            .to_string(),
+        ]
+    }
+
+    #[test]
+    fn test_stats_from_seeded_queue() {
+        let stats = QueueStats::from_entries(&seeded_queue());
+
+        assert_eq!(stats.length, 4);
+        assert_eq!(stats.unreadable, 1);
+        assert_eq!(stats.oldest.as_deref(), Some("2026-09-30T08:00:00.000Z"));
+        assert_eq!(stats.newest.as_deref(), Some("2026-10-02T12:00:00.000Z"));
+        assert_eq!(
+            stats.users,
+            vec![
+                UserTotals {
+                    email: "alice@example.com".to_string(),
+                    increments: 2,
+                    input_tokens: 150,
+                    output_tokens: 30,
+                    requests: 3,
+                },
+                UserTotals {
+                    email: "bob@example.com".to_string(),
+                    increments: 1,
+                    input_tokens: 5,
+                    output_tokens: 5,
+                    requests: 1,
+                },
+            ]
+        );
+        assert_eq!(QueueStats::from_entries(&[]), QueueStats::default());
+    }
+
+    #[test]
+    fn test_export_json_and_csv() {
+        let entries = seeded_queue();
+
+        let exported: serde_json::Value =
+            serde_json::from_str(&export(&entries, ExportFormat::Json)).unwrap();
+        let rows = exported.as_array().unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0]["email"], "alice@example.com");
+        assert_eq!(rows[1]["organization_id"], "org, \"quoted\"");
+
+        let csv = export(&entries, ExportFormat::Csv);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("timestamp,email,model,"));
+        assert_eq!(
+            lines[1],
+            "2026-10-01T10:00:00.000Z,alice@example.com,gpt-4o,100,20,1,,"
+        );
+        assert_eq!(
+            lines[2],
+            "2026-09-30T08:00:00.000Z,bob@example.com,,5,5,1,\"org, \"\"quoted\"\"\","
+        );
+    }
+
+    #[test]
+    fn test_is_older_than() {
+        let entries = seeded_queue();
+        let cutoff = DateTime::parse_from_rfc3339("2026-10-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        let old: Vec<bool> = entries
+            .iter()
+            .map(|json| is_older_than(json, cutoff))
+            .collect();
+        assert_eq!(old, vec![false, true, false, false]);
+    }
+
+    #[test]
+    fn test_entries_written_by_other_releases() {
+        let increment = json!({
+            "email": "carol@example.com", "input_tokens": 7, "output_tokens": 3,
+            "requests": 1, "model": "gpt-4o", "timestamp": "2026-09-01T00:00:00.000Z"
+        });
+        let entries = vec![
+            increment.to_string(),
+            format!("v1|{}", increment),
+            format!("v2|{}", increment),
+        ];
+
+        // Unprefixed and v1 entries count; the newer one isn't readable here
+        let stats = QueueStats::from_entries(&entries);
+        assert_eq!(stats.users[0].increments, 2);
+        assert_eq!(stats.unreadable, 1);
+
+        let cutoff = Utc::now();
+        let old: Vec<bool> = entries
+            .iter()
+            .map(|json| is_older_than(json, cutoff))
+            .collect();
+        assert_eq!(old, vec![true, true, false]);
+    }
+}
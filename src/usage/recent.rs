@@ -0,0 +1,580 @@
+//! Local rolling usage aggregates
+//!
+//! Support needs "how much did this user consume lately" without waiting on
+//! Zion's reporting API. The batching worker adds each flush's per-user totals
+//! to daily counters (`sentinel:usage:daily:{external_id}:{YYYY-MM-DD}:{field}`)
+//! in a single pipeline, so requests never wait on these writes. Keys expire
+//! after the retention window.
+//!
+//! Each day also keeps the set of users that had usage
+//! (`sentinel:usage:active:{YYYY-MM-DD}`), which cache warming uses to find
+//! recently active users.
+//!
+//! Usage tagged with a workflow id is also added to per-workflow counters
+//! (`sentinel:usage:workflow:{external_id}:{workflow_id}:{field}`) that are
+//! not split by day; their expiry is refreshed on every write, so a workflow
+//! is forgotten once it has been idle for the retention window.
+//!
+//! For usage reports, every flush is also added to per-(day, model, tier)
+//! counters (`sentinel:usage:model:{YYYY-MM-DD}:{tier}:{model}:{field}`),
+//! kept for `USAGE_REPORT_DAYS` and listed per day in
+//! `sentinel:usage:models:{YYYY-MM-DD}`. These hold no user identity, so
+//! they include users who opted out of request logging. A day holds at most
+//! `USAGE_REPORT_MAX_GROUPS` (model, tier) pairs; usage of further models is
+//! counted under the model `other`.
+
+use std::collections::{BTreeMap, HashSet};
+
+use chrono::{Duration, NaiveDate, Utc};
+use serde::Serialize;
+use tracing::warn;
+
+use crate::{cache::redis::keys, error::AppResult};
+
+#[cfg(any(test, feature = "test-utils"))]
+use std::sync::Arc;
+
+#[cfg(any(test, feature = "test-utils"))]
+use crate::cache::InMemoryCache;
+
+/// Days of aggregates kept when `USAGE_AGGREGATE_DAYS` is not set
+pub const DEFAULT_RETENTION_DAYS: u32 = 30;
+
+/// Days of model aggregates kept when `USAGE_REPORT_DAYS` is not set
+pub const DEFAULT_REPORT_DAYS: u32 = 400;
+
+/// (model, tier) pairs recorded per day when `USAGE_REPORT_MAX_GROUPS` is not set
+pub const DEFAULT_REPORT_MAX_GROUPS: usize = 200;
+
+/// Model recorded for usage tracked without one (pass-through request weights)
+pub const NO_MODEL: &str = "none";
+
+/// Model that usage is counted under once a day's (model, tier) pairs are used up
+pub const OTHER_MODEL: &str = "other";
+
+/// Counter fields stored per user and day
+const FIELDS: [&str; 3] = ["requests", "input_tokens", "output_tokens"];
+
+/// Usage counters for one user over some period
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct UsageCounts {
+    pub requests: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+}
+
+impl UsageCounts {
+    /// Add another set of counters to this one
+    pub fn add(&mut self, other: &UsageCounts) {
+        self.requests += other.requests;
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+    }
+
+    fn values(&self) -> [i64; 3] {
+        [self.requests, self.input_tokens, self.output_tokens]
+    }
+}
+
+/// Usage for a single UTC day
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DailyUsage {
+    /// UTC date (YYYY-MM-DD)
+    pub date: String,
+    #[serde(flatten)]
+    pub usage: UsageCounts,
+}
+
+/// Recent usage for one user, newest day first
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RecentUsage {
+    /// Number of days covered, including today
+    pub days: u32,
+    /// Sum over all covered days
+    pub total: UsageCounts,
+    pub daily: Vec<DailyUsage>,
+}
+
+/// Usage of one model in one tier on one UTC day
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ModelDailyUsage {
+    /// UTC date (YYYY-MM-DD)
+    pub date: String,
+    pub model: String,
+    /// Native routing tier, `none` for the OpenAI-compatible endpoints
+    pub tier: String,
+    #[serde(flatten)]
+    pub usage: UsageCounts,
+}
+
+/// Storage backend for the aggregates
+enum RecentUsageBackend {
+    Redis(redis::aio::ConnectionManager),
+    #[cfg(any(test, feature = "test-utils"))]
+    InMemory(Arc<InMemoryCache>),
+}
+
+/// Daily per-user usage counters with automatic expiry
+pub struct RecentUsageStore {
+    backend: RecentUsageBackend,
+    retention_days: u32,
+    report_days: u32,
+    report_max_groups: usize,
+}
+
+impl RecentUsageStore {
+    /// Create a store backed by Redis
+    pub fn new(redis: redis::aio::ConnectionManager, retention_days: u32) -> Self {
+        Self {
+            backend: RecentUsageBackend::Redis(redis),
+            retention_days: retention_days.max(1),
+            report_days: DEFAULT_REPORT_DAYS,
+            report_max_groups: DEFAULT_REPORT_MAX_GROUPS,
+        }
+    }
+
+    /// Create a store with in-memory backend for testing
+    #[cfg(any(test, feature = "test-utils"))]
+    pub fn new_for_testing(cache: Arc<InMemoryCache>, retention_days: u32) -> Self {
+        Self {
+            backend: RecentUsageBackend::InMemory(cache),
+            retention_days: retention_days.max(1),
+            report_days: DEFAULT_REPORT_DAYS,
+            report_max_groups: DEFAULT_REPORT_MAX_GROUPS,
+        }
+    }
+
+    /// Keep model aggregates for `days`, with at most `max_groups` (model, tier) pairs a day
+    pub fn with_report_limits(mut self, days: u32, max_groups: usize) -> Self {
+        self.report_days = days.max(1);
+        self.report_max_groups = max_groups.max(1);
+        self
+    }
+
+    /// Number of days aggregates are kept
+    pub fn retention_days(&self) -> u32 {
+        self.retention_days
+    }
+
+    /// Number of days model aggregates are kept
+    pub fn report_days(&self) -> u32 {
+        self.report_days
+    }
+
+    /// Add flushed per-user totals to the counters for `day`
+    ///
+    /// Errors are logged and swallowed; the aggregates are best-effort and
+    /// must never hold up delivery to Zion.
+    pub async fn record(&self, day: NaiveDate, totals: &[(String, UsageCounts)]) {
+        if totals.is_empty() {
+            return;
+        }
+        if let Err(e) = self.try_record(day, totals).await {
+            warn!(error = %e, users = totals.len(), "Failed to update local usage aggregates");
+        }
+    }
+
+    async fn try_record(&self, day: NaiveDate, totals: &[(String, UsageCounts)]) -> AppResult<()> {
+        // Keep a day's keys until it falls out of the window
+        let ttl_seconds = self.ttl_seconds();
+        let date = day.format("%Y-%m-%d").to_string();
+
+        match &self.backend {
+            RecentUsageBackend::Redis(conn) => {
+                let mut pipe = redis::pipe();
+                let active_key = keys::usage_active(&date);
+                for (external_id, usage) in totals {
+                    pipe.sadd(&active_key, external_id).ignore();
+                    for (field, value) in FIELDS.iter().zip(usage.values()) {
+                        let key = keys::usage_daily(external_id, &date, field);
+                        pipe.incr(&key, value).ignore();
+                        pipe.expire(&key, ttl_seconds as i64).ignore();
+                    }
+                }
+                pipe.expire(&active_key, ttl_seconds as i64).ignore();
+                let mut conn = conn.clone();
+                let _: () = pipe.query_async(&mut conn).await?;
+            }
+            #[cfg(any(test, feature = "test-utils"))]
+            RecentUsageBackend::InMemory(cache) => {
+                for (external_id, usage) in totals {
+                    cache.sadd(&keys::usage_active(&date), external_id, ttl_seconds).await?;
+                    for (field, value) in FIELDS.iter().zip(usage.values()) {
+                        let key = keys::usage_daily(external_id, &date, field);
+                        cache.incr(&key, value).await?;
+                        cache.expire(&key, ttl_seconds).await?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Add flushed per-workflow totals, keyed by `(external_id, workflow_id)`
+    ///
+    /// Best-effort like [`record`](Self::record).
+    pub async fn record_workflows(&self, totals: &[(String, String, UsageCounts)]) {
+        if totals.is_empty() {
+            return;
+        }
+        if let Err(e) = self.try_record_workflows(totals).await {
+            warn!(error = %e, workflows = totals.len(), "Failed to update workflow usage aggregates");
+        }
+    }
+
+    async fn try_record_workflows(&self, totals: &[(String, String, UsageCounts)]) -> AppResult<()> {
+        let ttl_seconds = self.ttl_seconds();
+
+        match &self.backend {
+            RecentUsageBackend::Redis(conn) => {
+                let mut pipe = redis::pipe();
+                for (external_id, workflow_id, usage) in totals {
+                    for (field, value) in FIELDS.iter().zip(usage.values()) {
+                        let key = keys::usage_workflow(external_id, workflow_id, field);
+                        pipe.incr(&key, value).ignore();
+                        pipe.expire(&key, ttl_seconds as i64).ignore();
+                    }
+                }
+                let mut conn = conn.clone();
+                let _: () = pipe.query_async(&mut conn).await?;
+            }
+            #[cfg(any(test, feature = "test-utils"))]
+            RecentUsageBackend::InMemory(cache) => {
+                for (external_id, workflow_id, usage) in totals {
+                    for (field, value) in FIELDS.iter().zip(usage.values()) {
+                        let key = keys::usage_workflow(external_id, workflow_id, field);
+                        cache.incr(&key, value).await?;
+                        cache.expire(&key, ttl_seconds).await?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Add flushed per-(model, tier) totals to the model counters for `day`
+    ///
+    /// Best-effort like [`record`](Self::record). Pairs that are new for the
+    /// day once it holds `USAGE_REPORT_MAX_GROUPS` are counted as `other`.
+    pub async fn record_models(&self, day: NaiveDate, totals: &[(String, String, UsageCounts)]) {
+        if totals.is_empty() {
+            return;
+        }
+        if let Err(e) = self.try_record_models(day, totals).await {
+            warn!(error = %e, groups = totals.len(), "Failed to update model usage aggregates");
+        }
+    }
+
+    async fn try_record_models(
+        &self,
+        day: NaiveDate,
+        totals: &[(String, String, UsageCounts)],
+    ) -> AppResult<()> {
+        let ttl_seconds = (self.report_days as u64 + 1) * 86_400;
+        let date = day.format("%Y-%m-%d").to_string();
+        let groups_key = keys::usage_models(&date);
+
+        // Pairs already recorded today don't count against the limit again
+        let mut known: HashSet<String> = self.members(&groups_key).await?.into_iter().collect();
+        let mut groups: BTreeMap<(String, String), UsageCounts> = BTreeMap::new();
+        for (model, tier, usage) in totals {
+            let mut model = model.as_str();
+            if !known.contains(&group_member(tier, model)) {
+                if known.len() >= self.report_max_groups {
+                    model = OTHER_MODEL;
+                }
+                known.insert(group_member(tier, model));
+            }
+            groups.entry((tier.clone(), model.to_string())).or_default().add(usage);
+        }
+
+        match &self.backend {
+            RecentUsageBackend::Redis(conn) => {
+                let mut pipe = redis::pipe();
+                for ((tier, model), usage) in &groups {
+                    pipe.sadd(&groups_key, group_member(tier, model)).ignore();
+                    for (field, value) in FIELDS.iter().zip(usage.values()) {
+                        let key = keys::usage_model_daily(&date, tier, model, field);
+                        pipe.incr(&key, value).ignore();
+                        pipe.expire(&key, ttl_seconds as i64).ignore();
+                    }
+                }
+                pipe.expire(&groups_key, ttl_seconds as i64).ignore();
+                let mut conn = conn.clone();
+                let _: () = pipe.query_async(&mut conn).await?;
+            }
+            #[cfg(any(test, feature = "test-utils"))]
+            RecentUsageBackend::InMemory(cache) => {
+                for ((tier, model), usage) in &groups {
+                    cache.sadd(&groups_key, &group_member(tier, model), ttl_seconds).await?;
+                    for (field, value) in FIELDS.iter().zip(usage.values()) {
+                        let key = keys::usage_model_daily(&date, tier, model, field);
+                        cache.incr(&key, value).await?;
+                        cache.expire(&key, ttl_seconds).await?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Usage per model and tier for each UTC day from `from` to `to`, both included
+    ///
+    /// Days before the report retention window are skipped. Ordered by day,
+    /// then tier and model.
+    pub async fn model_usage(&self, from: NaiveDate, to: NaiveDate) -> AppResult<Vec<ModelDailyUsage>> {
+        let oldest = Utc::now().date_naive() - Duration::days(self.report_days as i64);
+        let mut rows = Vec::new();
+        for day in from.max(oldest).iter_days().take_while(|day| *day <= to) {
+            let date = day.format("%Y-%m-%d").to_string();
+            let mut groups: Vec<(String, String)> = self
+                .members(&keys::usage_models(&date))
+                .await?
+                .iter()
+                .filter_map(|member| member.split_once(':'))
+                .map(|(tier, model)| (tier.to_string(), model.to_string()))
+                .collect();
+            if groups.is_empty() {
+                continue;
+            }
+            groups.sort();
+            let keys: Vec<String> = groups
+                .iter()
+                .flat_map(|(tier, model)| {
+                    FIELDS.iter().map(|field| keys::usage_model_daily(&date, tier, model, field))
+                })
+                .collect();
+            let values = self.get_counters(&keys).await?;
+            rows.extend(groups.into_iter().zip(values.chunks(FIELDS.len())).map(
+                |((tier, model), counters)| ModelDailyUsage {
+                    date: date.clone(),
+                    model,
+                    tier,
+                    usage: UsageCounts {
+                        requests: counters[0],
+                        input_tokens: counters[1],
+                        output_tokens: counters[2],
+                    },
+                },
+            ));
+        }
+        Ok(rows)
+    }
+
+    /// Accumulated usage of `external_id` in `workflow_id`; zero for unknown workflows
+    pub async fn workflow(&self, external_id: &str, workflow_id: &str) -> AppResult<UsageCounts> {
+        let keys: Vec<String> = FIELDS
+            .iter()
+            .map(|field| keys::usage_workflow(external_id, workflow_id, field))
+            .collect();
+        let counters = self.get_counters(&keys).await?;
+        Ok(UsageCounts {
+            requests: counters[0],
+            input_tokens: counters[1],
+            output_tokens: counters[2],
+        })
+    }
+
+    /// Usage for `external_id` over the last `days` UTC days, including today
+    ///
+    /// `days` is clamped to the retention window.
+    pub async fn recent(&self, external_id: &str, days: u32) -> AppResult<RecentUsage> {
+        let days = days.clamp(1, self.retention_days);
+        let today = Utc::now().date_naive();
+        let dates: Vec<String> = (0..days)
+            .map(|offset| (today - Duration::days(offset as i64)).format("%Y-%m-%d").to_string())
+            .collect();
+        let keys: Vec<String> = dates
+            .iter()
+            .flat_map(|date| FIELDS.iter().map(move |field| keys::usage_daily(external_id, date, field)))
+            .collect();
+
+        let values = self.get_counters(&keys).await?;
+
+        let mut total = UsageCounts::default();
+        let daily = dates
+            .into_iter()
+            .zip(values.chunks(FIELDS.len()))
+            .map(|(date, counters)| {
+                let usage = UsageCounts {
+                    requests: counters[0],
+                    input_tokens: counters[1],
+                    output_tokens: counters[2],
+                };
+                total.add(&usage);
+                DailyUsage { date, usage }
+            })
+            .collect();
+
+        Ok(RecentUsage { days, total, daily })
+    }
+
+    /// External IDs with usage recorded over the last `days` UTC days, including today
+    ///
+    /// `days` is clamped to the retention window. Sorted and deduplicated.
+    pub async fn active_users(&self, days: u32) -> AppResult<Vec<String>> {
+        let days = days.clamp(1, self.retention_days);
+        let today = Utc::now().date_naive();
+        let mut users = Vec::new();
+        for offset in 0..days {
+            let date = (today - Duration::days(offset as i64)).format("%Y-%m-%d").to_string();
+            users.extend(self.members(&keys::usage_active(&date)).await?);
+        }
+        users.sort();
+        users.dedup();
+        Ok(users)
+    }
+
+    /// Members of a set, empty when it doesn't exist
+    async fn members(&self, key: &str) -> AppResult<Vec<String>> {
+        match &self.backend {
+            RecentUsageBackend::Redis(conn) => {
+                let mut conn = conn.clone();
+                Ok(redis::cmd("SMEMBERS").arg(key).query_async(&mut conn).await?)
+            }
+            #[cfg(any(test, feature = "test-utils"))]
+            RecentUsageBackend::InMemory(cache) => cache.smembers(key).await,
+        }
+    }
+
+    /// Expiry of aggregate keys: the retention window plus the current day
+    fn ttl_seconds(&self) -> u64 {
+        (self.retention_days as u64 + 1) * 86_400
+    }
+
+    /// Read counters, treating missing keys as zero
+    async fn get_counters(&self, keys: &[String]) -> AppResult<Vec<i64>> {
+        match &self.backend {
+            RecentUsageBackend::Redis(conn) => {
+                let mut conn = conn.clone();
+                let values: Vec<Option<i64>> = redis::cmd("MGET").arg(keys).query_async(&mut conn).await?;
+                Ok(values.into_iter().map(|v| v.unwrap_or(0)).collect())
+            }
+            #[cfg(any(test, feature = "test-utils"))]
+            RecentUsageBackend::InMemory(cache) => {
+                let mut values = Vec::with_capacity(keys.len());
+                for key in keys {
+                    values.push(cache.get::<i64>(key).await?.unwrap_or(0));
+                }
+                Ok(values)
+            }
+        }
+    }
+}
+
+/// Member of a day's model set; tiers never contain `:`, models may
+fn group_member(tier: &str, model: &str) -> String {
+    format!("{}:{}", tier, model)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> RecentUsageStore {
+        RecentUsageStore::new_for_testing(Arc::new(InMemoryCache::new(60)), 7)
+    }
+
+    fn counts(requests: i64, input_tokens: i64, output_tokens: i64) -> UsageCounts {
+        UsageCounts {
+            requests,
+            input_tokens,
+            output_tokens,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_record_accumulates_per_day() {
+        let store = store();
+        let today = Utc::now().date_naive();
+        let yesterday = today - Duration::days(1);
+
+        store.record(today, &[("user-1".to_string(), counts(2, 100, 50))]).await;
+        store.record(today, &[("user-1".to_string(), counts(1, 10, 5))]).await;
+        store.record(yesterday, &[("user-1".to_string(), counts(4, 400, 200))]).await;
+
+        let recent = store.recent("user-1", 2).await.unwrap();
+        assert_eq!(recent.days, 2);
+        assert_eq!(recent.daily[0].usage, counts(3, 110, 55));
+        assert_eq!(recent.daily[1].usage, counts(4, 400, 200));
+        assert_eq!(recent.total, counts(7, 510, 255));
+    }
+
+    #[tokio::test]
+    async fn test_recent_clamps_days_and_isolates_users() {
+        let store = store();
+        let today = Utc::now().date_naive();
+        store.record(today, &[("user-1".to_string(), counts(1, 10, 5))]).await;
+
+        let recent = store.recent("user-2", 90).await.unwrap();
+        assert_eq!(recent.days, 7);
+        assert_eq!(recent.daily.len(), 7);
+        assert_eq!(recent.total, UsageCounts::default());
+    }
+
+    #[tokio::test]
+    async fn test_active_users_covers_requested_days() {
+        let store = store();
+        let today = Utc::now().date_naive();
+        store.record(today, &[("user-b".to_string(), counts(1, 10, 5))]).await;
+        store
+            .record(
+                today - Duration::days(1),
+                &[("user-a".to_string(), counts(1, 10, 5)), ("user-b".to_string(), counts(1, 1, 1))],
+            )
+            .await;
+        store.record(today - Duration::days(3), &[("user-c".to_string(), counts(1, 10, 5))]).await;
+
+        assert_eq!(store.active_users(1).await.unwrap(), vec!["user-b"]);
+        assert_eq!(store.active_users(2).await.unwrap(), vec!["user-a", "user-b"]);
+        assert_eq!(store.active_users(90).await.unwrap(), vec!["user-a", "user-b", "user-c"]);
+    }
+
+    #[tokio::test]
+    async fn test_model_usage_caps_groups_per_day() {
+        let store = store().with_report_limits(30, 2);
+        let today = Utc::now().date_naive();
+        let group = |model: &str, tier: &str, usage| (model.to_string(), tier.to_string(), usage);
+
+        store
+            .record_models(today, &[group("gpt-4o", "complex", counts(1, 10, 5)), group("o3:mini", "none", counts(1, 1, 1))])
+            .await;
+        // New pairs past the limit become `other`; known ones keep counting
+        store
+            .record_models(
+                today,
+                &[group("gpt-4o", "complex", counts(2, 20, 10)), group("gpt-4o-mini", "simple", counts(1, 3, 3))],
+            )
+            .await;
+        store.record_models(today - Duration::days(1), &[group("gpt-4o", "simple", counts(1, 7, 7))]).await;
+
+        let rows = store.model_usage(today - Duration::days(1), today).await.unwrap();
+        let rows: Vec<(&str, &str, UsageCounts)> =
+            rows.iter().map(|row| (row.model.as_str(), row.tier.as_str(), row.usage)).collect();
+        assert_eq!(
+            rows,
+            vec![
+                ("gpt-4o", "simple", counts(1, 7, 7)),
+                ("gpt-4o", "complex", counts(3, 30, 15)),
+                ("o3:mini", "none", counts(1, 1, 1)),
+                ("other", "simple", counts(1, 3, 3)),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_workflow_totals_accumulate_per_user() {
+        let store = store();
+        store
+            .record_workflows(&[
+                ("user-1".to_string(), "wf-1".to_string(), counts(1, 100, 20)),
+                ("user-2".to_string(), "wf-1".to_string(), counts(1, 7, 7)),
+            ])
+            .await;
+        store.record_workflows(&[("user-1".to_string(), "wf-1".to_string(), counts(2, 50, 10))]).await;
+
+        assert_eq!(store.workflow("user-1", "wf-1").await.unwrap(), counts(3, 150, 30));
+        assert_eq!(store.workflow("user-2", "wf-1").await.unwrap(), counts(1, 7, 7));
+        assert_eq!(store.workflow("user-1", "wf-2").await.unwrap(), UsageCounts::default());
+    }
+}
@@ -0,0 +1,237 @@
+//! Aggregate usage reports (`GET /admin/reports/usage`)
+//!
+//! Groups the per-(day, model, tier) counters kept by `RecentUsageStore` by
+//! model, tier or day. Costs are estimated from the tier config's per-million
+//! prices; a group containing a model without a price (including `other` and
+//! `none`) has no estimate rather than an understated one.
+
+use std::collections::BTreeMap;
+
+use axum::http::{header, HeaderMap};
+use serde::{Deserialize, Serialize};
+
+use super::recent::{ModelDailyUsage, UsageCounts};
+use crate::tiers::TierConfig;
+
+/// What report rows are grouped by
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupBy {
+    #[default]
+    Model,
+    Tier,
+    Day,
+}
+
+impl GroupBy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            GroupBy::Model => "model",
+            GroupBy::Tier => "tier",
+            GroupBy::Day => "day",
+        }
+    }
+
+    fn key(self, row: &ModelDailyUsage) -> &str {
+        match self {
+            GroupBy::Model => &row.model,
+            GroupBy::Tier => &row.tier,
+            GroupBy::Day => &row.date,
+        }
+    }
+}
+
+/// Usage of a group or of the whole report
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct UsageTotals {
+    #[serde(flatten)]
+    pub usage: UsageCounts,
+    /// Cost from the tier config's prices; null when a model has no price
+    pub estimated_cost: Option<f64>,
+}
+
+impl UsageTotals {
+    fn add(&mut self, usage: &UsageCounts, cost: Option<f64>) {
+        self.usage.add(usage);
+        self.estimated_cost = self.estimated_cost.zip(cost).map(|(total, cost)| total + cost);
+    }
+}
+
+/// One row of a report
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UsageReportGroup {
+    /// Model, tier or UTC date (YYYY-MM-DD), per `group_by`
+    pub group: String,
+    #[serde(flatten)]
+    pub totals: UsageTotals,
+}
+
+/// Usage between two UTC days, grouped
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UsageReport {
+    pub from: String,
+    pub to: String,
+    pub group_by: GroupBy,
+    /// Ordered by group
+    pub groups: Vec<UsageReportGroup>,
+    pub total: UsageTotals,
+}
+
+impl UsageReport {
+    /// Group `rows` and price them with `tier_config`, when one is available
+    pub fn build(
+        from: String,
+        to: String,
+        group_by: GroupBy,
+        rows: &[ModelDailyUsage],
+        tier_config: Option<&TierConfig>,
+    ) -> Self {
+        // Without prices nothing is estimated, not even an empty report
+        let empty = UsageTotals {
+            usage: UsageCounts::default(),
+            estimated_cost: tier_config.map(|_| 0.0),
+        };
+        let mut groups: BTreeMap<&str, UsageTotals> = BTreeMap::new();
+        let mut total = empty;
+        for row in rows {
+            let cost = tier_config.and_then(|config| estimated_cost(config, &row.model, &row.usage));
+            groups.entry(group_by.key(row)).or_insert(empty).add(&row.usage, cost);
+            total.add(&row.usage, cost);
+        }
+
+        Self {
+            from,
+            to,
+            group_by,
+            groups: groups
+                .into_iter()
+                .map(|(group, totals)| UsageReportGroup {
+                    group: group.to_string(),
+                    totals,
+                })
+                .collect(),
+            total,
+        }
+    }
+}
+
+/// Cost of `usage` at `model`'s per-million prices
+fn estimated_cost(config: &TierConfig, model: &str, usage: &UsageCounts) -> Option<f64> {
+    let prices = config.model_config(model)?;
+    Some(
+        (usage.input_tokens as f64 * prices.input_price_per_million
+            + usage.output_tokens as f64 * prices.output_price_per_million)
+            / 1_000_000.0,
+    )
+}
+
+/// Whether the `Accept` header prefers `text/csv` to JSON
+///
+/// The highest `q` wins, ties go to JSON, as does a missing header.
+pub fn wants_csv(headers: &HeaderMap) -> bool {
+    let mut csv = 0.0_f32;
+    let mut json = 0.0_f32;
+    let ranges = headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','));
+    for range in ranges {
+        let mut parts = range.split(';').map(str::trim);
+        let media_type = parts.next().unwrap_or_default().to_ascii_lowercase();
+        let q = parts
+            .filter_map(|param| param.strip_prefix("q="))
+            .find_map(|q| q.parse::<f32>().ok())
+            .unwrap_or(1.0);
+        match media_type.as_str() {
+            "text/csv" => csv = csv.max(q),
+            "application/json" | "*/*" => json = json.max(q),
+            _ => {}
+        }
+    }
+    csv > json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zion::models::{ModelConfig, TierConfigData, TierMapping};
+
+    fn row(date: &str, model: &str, tier: &str, input_tokens: i64, output_tokens: i64) -> ModelDailyUsage {
+        ModelDailyUsage {
+            date: date.to_string(),
+            model: model.to_string(),
+            tier: tier.to_string(),
+            usage: UsageCounts {
+                requests: 1,
+                input_tokens,
+                output_tokens,
+            },
+        }
+    }
+
+    fn tier_config() -> TierConfig {
+        TierConfigData {
+            version: "1".to_string(),
+            updated_at: "2026-01-01T00:00:00Z".to_string(),
+            tiers: TierMapping {
+                simple: vec![ModelConfig {
+                    provider: "openai".to_string(),
+                    model: "gpt-4o-mini".to_string(),
+                    relative_cost: 1,
+                    input_price_per_million: 1.0,
+                    output_price_per_million: 2.0,
+                    reasoning: false,
+                    strip_reasoning: false,
+                }],
+                moderate: vec![],
+                complex: vec![],
+            },
+            system_prompts: None,
+            long_context_models: None,
+            embedding_models: None,
+        }
+    }
+
+    #[test]
+    fn test_groups_and_prices_rows() {
+        let rows = vec![
+            row("2026-10-01", "gpt-4o-mini", "simple", 1_000_000, 500_000),
+            row("2026-10-02", "gpt-4o-mini", "none", 1_000_000, 0),
+            row("2026-10-02", "gpt-4o", "moderate", 10, 5),
+        ];
+        let config = tier_config();
+
+        let report = UsageReport::build("a".into(), "b".into(), GroupBy::Model, &rows, Some(&config));
+        let groups: Vec<&str> = report.groups.iter().map(|group| group.group.as_str()).collect();
+        assert_eq!(groups, ["gpt-4o", "gpt-4o-mini"]);
+        assert_eq!(report.groups[0].totals.estimated_cost, None);
+        assert_eq!(report.groups[1].totals.usage.requests, 2);
+        assert_eq!(report.groups[1].totals.usage.input_tokens, 2_000_000);
+        assert_eq!(report.groups[1].totals.estimated_cost, Some(3.0));
+        assert_eq!(report.total.usage.requests, 3);
+        assert_eq!(report.total.estimated_cost, None);
+
+        let by_day = UsageReport::build("a".into(), "b".into(), GroupBy::Day, &rows[..2], Some(&config));
+        assert_eq!(by_day.groups.len(), 2);
+        assert_eq!(by_day.groups[0].totals.estimated_cost, Some(2.0));
+        assert_eq!(by_day.total.estimated_cost, Some(3.0));
+
+        let unpriced = UsageReport::build("a".into(), "b".into(), GroupBy::Tier, &rows, None);
+        assert!(unpriced.groups.iter().all(|group| group.totals.estimated_cost.is_none()));
+    }
+
+    #[test]
+    fn test_wants_csv() {
+        let accept = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::ACCEPT, value.parse().unwrap());
+            headers
+        };
+        assert!(wants_csv(&accept("text/csv")));
+        assert!(wants_csv(&accept("application/json;q=0.5, text/csv")));
+        assert!(!wants_csv(&accept("text/csv, application/json")));
+        assert!(!wants_csv(&accept("*/*")));
+        assert!(!wants_csv(&HeaderMap::new()));
+    }
+}
@@ -0,0 +1,189 @@
+//! Retry leadership across replicas
+//!
+//! Every replica's batching tracker would otherwise run its retry cycle
+//! against the shared failed increments queue, and together they would retry
+//! at a multiple of the intended Zion rate. Only the replica holding the
+//! lease (`<queue key>:retry-leader`, taken with SET NX PX and a per-process
+//! token) runs retry cycles. The holder renews it every cycle and after each
+//! retried increment; if it dies the lease expires and the next replica to
+//! try takes over. A replica shutting down releases it right away.
+//!
+//! The lease is separate from the queue lock (see `queue`): the lock only
+//! excludes concurrent drains (a manual flush or purge against the retry
+//! loop) and is released after every cycle.
+
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::error::AppResult;
+
+/// Suffix of the lease key, after the queue key
+const LEASE_KEY_SUFFIX: &str = ":retry-leader";
+
+/// Extend the lease only if we still hold it
+const RENEW_LEASE_SCRIPT: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('PEXPIRE', KEYS[1], ARGV[2])
+end
+return 0
+"#;
+
+/// Delete the lease only if we still hold it
+const RELEASE_LEASE_SCRIPT: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('DEL', KEYS[1])
+end
+return 0
+"#;
+
+/// Identifier of this process for the lease: the configured
+/// `SENTINEL_REPLICA_ID`, else `HOSTNAME` (the pod name on Kubernetes), else
+/// a random id
+pub fn resolve_replica_id(configured: Option<&str>) -> String {
+    configured
+        .map(str::to_string)
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .filter(|id| !id.trim().is_empty())
+        .unwrap_or_else(|| {
+            format!(
+                "sentinel-{}",
+                &uuid::Uuid::new_v4().simple().to_string()[..8]
+            )
+        })
+}
+
+/// Who runs the retry loop, as returned by `GET /admin/usage/retry`
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct RetryLeaseStatus {
+    /// Replica answering the request
+    pub replica_id: String,
+    /// Replica holding the lease (None when no replica is retrying)
+    pub leader: Option<String>,
+    /// Whether the answering replica holds the lease
+    pub is_leader: bool,
+    /// Time left before the lease expires unless renewed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_in_ms: Option<u64>,
+}
+
+/// This replica's claim on the retry loop
+pub struct RetryLease {
+    redis: redis::aio::ConnectionManager,
+    key: String,
+    replica_id: String,
+    /// `<replica id>/<random>`, so a restarted replica never renews its old lease
+    token: String,
+    ttl: Duration,
+}
+
+impl RetryLease {
+    /// Lease for the retry loop of the queue at `queue_key`
+    pub fn new(
+        redis: redis::aio::ConnectionManager,
+        queue_key: &str,
+        replica_id: &str,
+        ttl: Duration,
+    ) -> Self {
+        Self {
+            redis,
+            key: format!("{}{}", queue_key, LEASE_KEY_SUFFIX),
+            replica_id: replica_id.to_string(),
+            token: format!("{}/{}", replica_id, uuid::Uuid::new_v4().simple()),
+            ttl,
+        }
+    }
+
+    pub fn replica_id(&self) -> &str {
+        &self.replica_id
+    }
+
+    /// Take the lease if it is free, or renew it if we hold it
+    ///
+    /// Returns whether this replica holds the lease afterwards.
+    pub async fn acquire(&self) -> AppResult<bool> {
+        let mut conn = self.redis.clone();
+        let acquired: Option<String> = redis::cmd("SET")
+            .arg(&self.key)
+            .arg(&self.token)
+            .arg("NX")
+            .arg("PX")
+            .arg(self.ttl_ms())
+            .query_async(&mut conn)
+            .await?;
+        if acquired.is_some() {
+            return Ok(true);
+        }
+        self.renew().await
+    }
+
+    /// Push the lease's expiry out again; false when we no longer hold it
+    pub async fn renew(&self) -> AppResult<bool> {
+        let mut conn = self.redis.clone();
+        let renewed: i64 = redis::Script::new(RENEW_LEASE_SCRIPT)
+            .key(&self.key)
+            .arg(&self.token)
+            .arg(self.ttl_ms())
+            .invoke_async(&mut conn)
+            .await?;
+        Ok(renewed == 1)
+    }
+
+    /// Give the lease up (a no-op unless we hold it)
+    pub async fn release(&self) -> AppResult<()> {
+        let mut conn = self.redis.clone();
+        let _: i64 = redis::Script::new(RELEASE_LEASE_SCRIPT)
+            .key(&self.key)
+            .arg(&self.token)
+            .invoke_async(&mut conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Current holder of the lease
+    pub async fn status(&self) -> AppResult<RetryLeaseStatus> {
+        let mut conn = self.redis.clone();
+        let (token, ttl_ms): (Option<String>, i64) = redis::pipe()
+            .cmd("GET")
+            .arg(&self.key)
+            .cmd("PTTL")
+            .arg(&self.key)
+            .query_async(&mut conn)
+            .await?;
+        Ok(RetryLeaseStatus {
+            replica_id: self.replica_id.clone(),
+            leader: token
+                .as_deref()
+                .map(|token| token_replica(token).to_string()),
+            is_leader: token.as_deref() == Some(self.token.as_str()),
+            expires_in_ms: u64::try_from(ttl_ms).ok().filter(|_| token.is_some()),
+        })
+    }
+
+    fn ttl_ms(&self) -> u64 {
+        self.ttl.as_millis().max(1) as u64
+    }
+}
+
+/// Replica id part of a lease token
+fn token_replica(token: &str) -> &str {
+    token.rsplit_once('/').map_or(token, |(replica, _)| replica)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_replica() {
+        assert_eq!(token_replica("sentinel-7d9f/0a1b2c"), "sentinel-7d9f");
+        assert_eq!(token_replica("ns/pod-1/0a1b2c"), "ns/pod-1");
+        assert_eq!(token_replica("legacy"), "legacy");
+    }
+
+    #[test]
+    fn test_resolve_replica_id() {
+        assert_eq!(resolve_replica_id(Some("replica-a")), "replica-a");
+        assert!(!resolve_replica_id(None).trim().is_empty());
+    }
+}
@@ -0,0 +1,229 @@
+//! Request weights for pass-through usage
+//!
+//! Pass-through endpoints have no token counts, so their usage is the request
+//! count alone. The weight table (`USAGE_REQUEST_WEIGHTS_JSON`) scales that
+//! count by path: an image generation can cost 5 requests while a model
+//! lookup costs nothing. Weight 0 means the request isn't counted; paths
+//! without a matching pattern (and every typed endpoint) weigh 1.
+//!
+//! Patterns are provider paths without the `/v1` prefix. A pattern matches
+//! that path and everything below it, `*` matches a single segment, and the
+//! most specific matching pattern wins. `PUT /admin/usage/request-weights`
+//! replaces the table on a replica without a restart.
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::RwLock;
+
+use serde::Serialize;
+use tracing::info;
+
+/// Table used when `USAGE_REQUEST_WEIGHTS_JSON` is unset
+pub const DEFAULT_REQUEST_WEIGHTS: &str = r#"{
+    "/images/generations": 5,
+    "/images/edits": 5,
+    "/images/variations": 5,
+    "/models": 0
+}"#;
+
+/// Weight of requests no pattern matches
+pub const DEFAULT_REQUEST_WEIGHT: u32 = 1;
+
+/// Path patterns and the request weight of the paths they match
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestWeightTable {
+    /// Patterns split into segments, most specific first
+    rules: Vec<(Vec<String>, u32)>,
+}
+
+impl RequestWeightTable {
+    /// Table from pattern → weight pairs
+    pub fn new(weights: BTreeMap<String, u32>) -> Result<Self, String> {
+        let mut rules = weights
+            .into_iter()
+            .map(|(pattern, weight)| {
+                if !pattern.starts_with('/') {
+                    return Err(format!(
+                        "request weight pattern '{}' must start with '/'",
+                        pattern
+                    ));
+                }
+                Ok((segments(&pattern), weight))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        // Longer patterns first; among equal lengths, fewer wildcards first
+        rules.sort_by_key(|(pattern, _)| {
+            let wildcards = pattern.iter().filter(|segment| *segment == "*").count();
+            (std::cmp::Reverse(pattern.len()), wildcards)
+        });
+        Ok(Self { rules })
+    }
+
+    /// Weight of a request to `path` (without the `/v1` prefix)
+    pub fn weight_for(&self, path: &str) -> u32 {
+        let path = segments(path);
+        self.rules
+            .iter()
+            .find(|(pattern, _)| matches(pattern, &path))
+            .map(|(_, weight)| *weight)
+            .unwrap_or(DEFAULT_REQUEST_WEIGHT)
+    }
+
+    /// Pattern → weight pairs, as configured
+    pub fn to_map(&self) -> BTreeMap<String, u32> {
+        self.rules
+            .iter()
+            .map(|(pattern, weight)| (format!("/{}", pattern.join("/")), *weight))
+            .collect()
+    }
+}
+
+impl Default for RequestWeightTable {
+    fn default() -> Self {
+        DEFAULT_REQUEST_WEIGHTS
+            .parse()
+            .expect("default request weights are valid")
+    }
+}
+
+impl FromStr for RequestWeightTable {
+    type Err = String;
+
+    fn from_str(json: &str) -> Result<Self, Self::Err> {
+        let weights: BTreeMap<String, u32> = serde_json::from_str(json)
+            .map_err(|e| format!("invalid request weights JSON: {}", e))?;
+        Self::new(weights)
+    }
+}
+
+fn segments(path: &str) -> Vec<String> {
+    path.split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Whether `pattern` matches `path` or one of its ancestors
+fn matches(pattern: &[String], path: &[String]) -> bool {
+    pattern.len() <= path.len()
+        && pattern
+            .iter()
+            .zip(path)
+            .all(|(expected, actual)| expected == "*" || expected == actual)
+}
+
+/// Current table, as returned by `GET /admin/usage/request-weights`
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct RequestWeightsStatus {
+    /// Active pattern → weight pairs
+    pub weights: BTreeMap<String, u32>,
+    /// Table the process started with
+    pub startup_weights: BTreeMap<String, u32>,
+    /// Weight of paths no pattern matches
+    pub default_weight: u32,
+}
+
+/// Runtime control of the request weight table
+pub struct RequestWeights {
+    startup: RequestWeightTable,
+    active: RwLock<RequestWeightTable>,
+}
+
+impl RequestWeights {
+    pub fn new(table: RequestWeightTable) -> Self {
+        Self {
+            active: RwLock::new(table.clone()),
+            startup: table,
+        }
+    }
+
+    /// Weight of a request to `path` under the active table
+    pub fn weight_for(&self, path: &str) -> u32 {
+        self.active.read().unwrap().weight_for(path)
+    }
+
+    /// Active and startup tables
+    pub fn status(&self) -> RequestWeightsStatus {
+        RequestWeightsStatus {
+            weights: self.active.read().unwrap().to_map(),
+            startup_weights: self.startup.to_map(),
+            default_weight: DEFAULT_REQUEST_WEIGHT,
+        }
+    }
+
+    /// Replace the active table
+    pub fn set(&self, table: RequestWeightTable) -> RequestWeightsStatus {
+        let mut active = self.active.write().unwrap();
+        info!(
+            from = ?active.to_map(),
+            to = ?table.to_map(),
+            "Changing usage request weights"
+        );
+        *active = table;
+        drop(active);
+        self.status()
+    }
+
+    /// Restore the startup table
+    pub fn reset(&self) -> RequestWeightsStatus {
+        self.set(self.startup.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(json: &str) -> RequestWeightTable {
+        json.parse().unwrap()
+    }
+
+    #[test]
+    fn test_default_table() {
+        let weights = RequestWeightTable::default();
+        assert_eq!(weights.weight_for("/images/generations"), 5);
+        assert_eq!(weights.weight_for("/models"), 0);
+        assert_eq!(weights.weight_for("/models/gpt-4o"), 0);
+        assert_eq!(weights.weight_for("/audio/speech"), DEFAULT_REQUEST_WEIGHT);
+    }
+
+    #[test]
+    fn test_most_specific_pattern_wins() {
+        let weights = table(r#"{"/files": 2, "/files/*/content": 7, "/files/abc": 3}"#);
+        assert_eq!(weights.weight_for("/files"), 2);
+        assert_eq!(weights.weight_for("/files/xyz"), 2);
+        assert_eq!(weights.weight_for("/files/abc"), 3);
+        assert_eq!(weights.weight_for("/files/xyz/content"), 7);
+        // A literal segment beats a wildcard of the same length
+        assert_eq!(
+            table(r#"{"/files/*": 4, "/files/abc": 3}"#).weight_for("/files/abc"),
+            3
+        );
+        // Patterns match whole segments only
+        assert_eq!(weights.weight_for("/filesystem"), DEFAULT_REQUEST_WEIGHT);
+    }
+
+    #[test]
+    fn test_invalid_tables() {
+        assert!("not json".parse::<RequestWeightTable>().is_err());
+        assert!(r#"{"/images": -1}"#.parse::<RequestWeightTable>().is_err());
+        assert!(r#"{"images": 2}"#.parse::<RequestWeightTable>().is_err());
+    }
+
+    #[test]
+    fn test_set_replaces_active_table() {
+        let weights = RequestWeights::new(RequestWeightTable::default());
+        let status = weights.set(table(r#"{"/audio": 3}"#));
+        assert_eq!(status.weights, BTreeMap::from([("/audio".to_string(), 3)]));
+        assert_eq!(status.startup_weights.get("/models"), Some(&0));
+        assert_eq!(weights.weight_for("/audio/speech"), 3);
+        assert_eq!(
+            weights.weight_for("/images/generations"),
+            DEFAULT_REQUEST_WEIGHT
+        );
+
+        let status = weights.reset();
+        assert_eq!(status.weights, status.startup_weights);
+        assert_eq!(weights.weight_for("/images/generations"), 5);
+    }
+}
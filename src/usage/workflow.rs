@@ -0,0 +1,86 @@
+//! Workflow attribution
+//!
+//! Agent workflows make many tool call roundtrips on behalf of one task. A
+//! client tags each request with the same workflow id (the
+//! `X-Sentinel-Workflow-Id` header, or `workflow_id` in a native request),
+//! and the batching worker adds the usage to per-workflow counters next to
+//! the daily aggregates. Zion is still billed per user; the workflow only
+//! exists locally.
+
+use axum::http::HeaderMap;
+
+/// Header carrying the workflow id on `/v1` and native requests
+pub const WORKFLOW_ID_HEADER: &str = "x-sentinel-workflow-id";
+
+/// Maximum length of a workflow id, in characters
+pub const MAX_WORKFLOW_ID_CHARS: usize = 128;
+
+const INVALID_CHARACTERS: &str =
+    "workflow_id may only contain letters, digits, '-', '_', '.' and ':'";
+
+/// Validate a workflow id: non-empty, within the length cap, and limited to
+/// ASCII letters, digits and `-`, `_`, `.`, `:`
+///
+/// The id becomes part of a Redis key, so anything else is rejected rather
+/// than escaped.
+pub fn validate_workflow_id(workflow_id: &str) -> Result<(), String> {
+    if workflow_id.is_empty() {
+        return Err("workflow_id must not be empty".to_string());
+    }
+    if workflow_id.len() > MAX_WORKFLOW_ID_CHARS {
+        return Err(format!(
+            "workflow_id exceeds {} characters",
+            MAX_WORKFLOW_ID_CHARS
+        ));
+    }
+    if !workflow_id
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+    {
+        return Err(INVALID_CHARACTERS.to_string());
+    }
+    Ok(())
+}
+
+/// Workflow id from the `X-Sentinel-Workflow-Id` header, validated
+pub fn workflow_id_from_headers(headers: &HeaderMap) -> Result<Option<String>, String> {
+    let Some(value) = headers.get(WORKFLOW_ID_HEADER) else {
+        return Ok(None);
+    };
+    let workflow_id = value.to_str().map_err(|_| INVALID_CHARACTERS.to_string())?;
+    validate_workflow_id(workflow_id)?;
+    Ok(Some(workflow_id.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_validate_workflow_id() {
+        assert!(validate_workflow_id("wf-2024.10:build_1").is_ok());
+        assert!(validate_workflow_id(&"a".repeat(MAX_WORKFLOW_ID_CHARS)).is_ok());
+
+        for invalid in ["", "has space", "slash/id", "ümlaut", "star*"] {
+            assert!(validate_workflow_id(invalid).is_err(), "{:?}", invalid);
+        }
+        let long = "a".repeat(MAX_WORKFLOW_ID_CHARS + 1);
+        assert!(validate_workflow_id(&long).unwrap_err().contains("128"));
+    }
+
+    #[test]
+    fn test_workflow_id_from_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(workflow_id_from_headers(&headers), Ok(None));
+
+        headers.insert(WORKFLOW_ID_HEADER, HeaderValue::from_static("wf-1"));
+        assert_eq!(
+            workflow_id_from_headers(&headers),
+            Ok(Some("wf-1".to_string()))
+        );
+
+        headers.insert(WORKFLOW_ID_HEADER, HeaderValue::from_static(""));
+        assert!(workflow_id_from_headers(&headers).is_err());
+    }
+}
@@ -2,17 +2,25 @@
 //!
 //! HTTP client for communicating with the Zion governance API.
 
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
 use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
-use tracing::{debug, error, instrument, warn};
+use tracing::{debug, error, info, instrument, warn};
 
+#[cfg(feature = "chaos")]
+use crate::chaos::{ChaosInjector, Target};
 use crate::{
     config::Config,
     error::{AppError, AppResult},
     zion::models::{
-        BatchIncrementData, BatchIncrementItem, BatchIncrementRequest, BatchIncrementResponse,
+        parse_response, BatchIncrementData, BatchIncrementItem, BatchIncrementRequest, BatchIncrementResponse,
         ExternalLimitsResponse, IncrementUsageData, IncrementUsageRequest, IncrementUsageResponse,
-        TierConfigData, TierConfigResponse, UserLimit, UserProfile, UserProfileResponse,
+        MetaData, MetaResponse, PayloadCase, TierConfigData, TierConfigResponse, UserLimit, UserProfile,
+        UserProfileResponse,
     },
+    zion::negotiation::ZionCapabilities,
 };
 
 /// Zion API client
@@ -20,6 +28,16 @@ pub struct ZionClient {
     client: reqwest::Client,
     base_url: String,
     api_key: String,
+    /// Negotiated batch capabilities and when they were fetched
+    capabilities: RwLock<Option<(Instant, Arc<ZionCapabilities>)>>,
+    meta_ttl: Duration,
+    /// Field casing of the increment payloads
+    payload_case: PayloadCase,
+    /// Set once a downgraded batch payload has been logged for the current set
+    downgrade_logged: AtomicBool,
+    /// Fault rules consulted before each call (`CHAOS_ENABLED`)
+    #[cfg(feature = "chaos")]
+    chaos: Option<Arc<ChaosInjector>>,
 }
 
 impl ZionClient {
@@ -27,14 +45,48 @@ impl ZionClient {
     pub fn new(client: reqwest::Client, config: &Config) -> Self {
         Self {
             client,
-            base_url: config.zion_api_url.clone(),
-            api_key: config.zion_api_key.clone(),
+            base_url: config.zion.api_url.clone(),
+            api_key: config.zion.api_key.clone(),
+            capabilities: RwLock::new(None),
+            meta_ttl: Duration::from_secs(config.zion.meta_ttl_seconds),
+            payload_case: config.zion.payload_case,
+            downgrade_logged: AtomicBool::new(false),
+            #[cfg(feature = "chaos")]
+            chaos: None,
+        }
+    }
+
+    /// Run every call through the `zion` fault rules of `chaos` first
+    #[cfg(feature = "chaos")]
+    pub fn with_chaos(mut self, chaos: Arc<ChaosInjector>) -> Self {
+        self.chaos = Some(chaos);
+        self
+    }
+
+    /// Fault rules this client consults, if any
+    #[cfg(feature = "chaos")]
+    pub fn chaos(&self) -> Option<&Arc<ChaosInjector>> {
+        self.chaos.as_ref()
+    }
+
+    /// Fail or delay a call as the `zion` fault rules say (`chaos` feature)
+    async fn inject_fault(&self, operation: &str) -> AppResult<()> {
+        #[cfg(feature = "chaos")]
+        if let Some(chaos) = &self.chaos {
+            chaos
+                .intercept(Target::Zion, operation)
+                .await
+                .map_err(|injected| injected.upstream_error("Zion API"))?;
         }
+        #[cfg(not(feature = "chaos"))]
+        let _ = operation;
+        Ok(())
     }
 
     /// Get user limits by external ID
     #[instrument(skip(self), fields(external_id = %external_id))]
     pub async fn get_limits(&self, external_id: &str) -> AppResult<Vec<UserLimit>> {
+        self.inject_fault("get_limits").await?;
         let url = format!(
             "{}/api/v1/limits/external/{}",
             self.base_url, external_id
@@ -72,7 +124,7 @@ impl ZionClient {
         let body = response.text().await?;
         debug!(body = %body, "Zion limits response body");
 
-        let result: ExternalLimitsResponse = match serde_json::from_str(&body) {
+        let result: ExternalLimitsResponse = match parse_response("limits", &body) {
             Ok(r) => r,
             Err(e) => {
                 error!(error = %e, body = %body, "Failed to parse Zion limits response");
@@ -91,6 +143,7 @@ impl ZionClient {
     ///
     /// Sends a single request to increment input tokens, output tokens, and request count.
     /// The limit is auto-detected from the user's subscription plan by the Zion API.
+    #[allow(clippy::too_many_arguments)]
     #[instrument(skip(self), fields(email = %email, input_tokens, output_tokens, requests))]
     pub async fn increment_usage(
         &self,
@@ -99,8 +152,10 @@ impl ZionClient {
         output_tokens: i64,
         requests: i64,
         model: Option<&str>,
+        organization_id: Option<&str>,
         timestamp: Option<&str>,
     ) -> AppResult<IncrementUsageData> {
+        self.inject_fault("increment_usage").await?;
         let url = format!("{}/api/v1/usage/external/increment", self.base_url);
 
         let request = IncrementUsageRequest {
@@ -110,7 +165,9 @@ impl ZionClient {
             ai_requests: if requests > 0 { Some(requests) } else { None },
             model: model.map(|s| s.to_string()),
             timestamp: timestamp.map(|s| s.to_string()),
+            organization_id: organization_id.map(|s| s.to_string()),
         };
+        let payload = self.payload_case.to_value(&request)?;
 
         debug!(url = %url, "Incrementing usage via Zion");
 
@@ -118,7 +175,7 @@ impl ZionClient {
             .client
             .post(&url)
             .headers(self.api_key_headers())
-            .json(&request)
+            .json(&payload)
             .send()
             .await?;
 
@@ -137,7 +194,7 @@ impl ZionClient {
         let body = response.text().await?;
         debug!(body = %body, "Zion increment response body");
 
-        let result: IncrementUsageResponse = match serde_json::from_str(&body) {
+        let result: IncrementUsageResponse = match parse_response("increment", &body) {
             Ok(r) => r,
             Err(e) => {
                 error!(error = %e, body = %body, "Failed to parse Zion increment response");
@@ -177,22 +234,31 @@ impl ZionClient {
             ));
         }
 
+        self.inject_fault("batch_increment").await?;
+        let capabilities = self.negotiated_capabilities().await;
+        let mut items = items;
+        let dropped = capabilities.shape_batch(&mut items);
+        if !dropped.is_empty() && !self.downgrade_logged.swap(true, Ordering::Relaxed) {
+            warn!(
+                dropped = ?dropped,
+                source = ?capabilities.source,
+                "Zion does not advertise some batch fields; sending a reduced payload"
+            );
+        }
+
         let url = format!("{}/api/v1/usage/external/batch-increment", self.base_url);
 
         let request = BatchIncrementRequest { increments: items };
+        let payload = self.payload_case.to_value(&request)?;
 
         // Log the full request payload for debugging
-        if let Ok(payload) = serde_json::to_string(&request) {
-            debug!(url = %url, payload = %payload, "Sending batch increment to Zion");
-        } else {
-            debug!(url = %url, "Sending batch increment to Zion");
-        }
+        debug!(url = %url, payload = %payload, "Sending batch increment to Zion");
 
         let response = self
             .client
             .post(&url)
             .headers(self.api_key_headers())
-            .json(&request)
+            .json(&payload)
             .send()
             .await?;
 
@@ -211,7 +277,7 @@ impl ZionClient {
         let body = response.text().await?;
         debug!(body = %body, "Zion batch increment response body");
 
-        let result: BatchIncrementResponse = match serde_json::from_str(&body) {
+        let result: BatchIncrementResponse = match parse_response("batch-increment", &body) {
             Ok(r) => r,
             Err(e) => {
                 error!(error = %e, body = %body, "Failed to parse Zion batch response");
@@ -229,6 +295,7 @@ impl ZionClient {
     /// Validate a JWT and get user profile
     #[instrument(skip(self, jwt), fields(jwt_prefix = %jwt.chars().take(20).collect::<String>()))]
     pub async fn validate_jwt(&self, jwt: &str) -> AppResult<UserProfile> {
+        self.inject_fault("validate_jwt").await?;
         let url = format!("{}/api/v1/users/me", self.base_url);
 
         debug!(url = %url, jwt_len = jwt.len(), "Validating JWT with Zion");
@@ -265,7 +332,7 @@ impl ZionClient {
         let body = response.text().await?;
         debug!(body = %body, "Zion JWT validation response body");
 
-        let result: UserProfileResponse = match serde_json::from_str(&body) {
+        let result: UserProfileResponse = match parse_response("users/me", &body) {
             Ok(r) => r,
             Err(e) => {
                 error!(error = %e, body = %body, "Failed to parse Zion user profile response");
@@ -291,6 +358,7 @@ impl ZionClient {
     /// is global (same for all users) and changes infrequently.
     #[instrument(skip(self))]
     pub async fn get_tier_config(&self) -> AppResult<TierConfigData> {
+        self.inject_fault("get_tier_config").await?;
         let url = format!("{}/api/v1/tiers/config", self.base_url);
 
         debug!(url = %url, "Fetching tier config from Zion");
@@ -317,7 +385,7 @@ impl ZionClient {
         let body = response.text().await?;
         debug!(body = %body, "Zion tier config response body");
 
-        let result: TierConfigResponse = match serde_json::from_str(&body) {
+        let result: TierConfigResponse = match parse_response("tiers/config", &body) {
             Ok(r) => r,
             Err(e) => {
                 error!(error = %e, body = %body, "Failed to parse Zion tier config response");
@@ -332,6 +400,89 @@ impl ZionClient {
         Ok(result.data)
     }
 
+    /// Get API metadata, including advertised capabilities
+    #[instrument(skip(self))]
+    pub async fn get_meta(&self) -> AppResult<MetaData> {
+        self.inject_fault("get_meta").await?;
+        let url = format!("{}/api/v1/meta", self.base_url);
+
+        debug!(url = %url, "Fetching API metadata from Zion");
+
+        let response = self
+            .client
+            .get(&url)
+            .headers(self.api_key_headers())
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(AppError::UpstreamError(format!(
+                "Zion meta API error {}: {}",
+                status, text
+            )));
+        }
+
+        let body = response.text().await?;
+        let result: MetaResponse = parse_response("meta", &body).map_err(|e| {
+            AppError::UpstreamError(format!("Failed to parse Zion meta response: {}", e))
+        })?;
+        Ok(result.data)
+    }
+
+    /// Re-read the meta endpoint and replace the negotiated capabilities
+    ///
+    /// Never fails: if the meta endpoint can't be read the legacy minimal
+    /// payload is used until the next refresh.
+    pub async fn refresh_capabilities(&self) -> Arc<ZionCapabilities> {
+        let capabilities = match self.get_meta().await {
+            Ok(meta) => {
+                info!(api_version = %meta.api_version, capabilities = ?meta.capabilities, "Negotiated Zion capabilities");
+                ZionCapabilities::from_meta(meta)
+            }
+            Err(e) => {
+                warn!(error = %e, "Zion meta endpoint unavailable; using legacy batch payload");
+                ZionCapabilities::legacy(e.to_string())
+            }
+        };
+        let capabilities = Arc::new(capabilities);
+
+        let mut cached = self.capabilities.write().unwrap();
+        let changed = cached
+            .as_ref()
+            .is_none_or(|(_, previous)| previous.batch_fields != capabilities.batch_fields);
+        if changed {
+            self.downgrade_logged.store(false, Ordering::Relaxed);
+        }
+        *cached = Some((Instant::now(), capabilities.clone()));
+        capabilities
+    }
+
+    /// Last negotiated capabilities, if the meta endpoint has been read yet
+    pub fn capabilities(&self) -> Option<Arc<ZionCapabilities>> {
+        self.capabilities
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|(_, capabilities)| capabilities.clone())
+    }
+
+    /// Negotiated capabilities, refreshed once they are older than `ZION_META_TTL_SECONDS`
+    async fn negotiated_capabilities(&self) -> Arc<ZionCapabilities> {
+        let fresh = self
+            .capabilities
+            .read()
+            .unwrap()
+            .as_ref()
+            .filter(|(fetched, _)| fetched.elapsed() < self.meta_ttl)
+            .map(|(_, capabilities)| capabilities.clone());
+        match fresh {
+            Some(capabilities) => capabilities,
+            None => self.refresh_capabilities().await,
+        }
+    }
+
     /// Build headers with API key authentication
     fn api_key_headers(&self) -> HeaderMap {
         let mut headers = HeaderMap::new();
@@ -0,0 +1,341 @@
+//! Local JWT verification against Zion's JWKS (`ZION_JWKS_URL`)
+//!
+//! Validating an unknown token with Zion (`/api/v1/users/me`) costs a round
+//! trip on every cold auth and makes Zion a hard dependency of the hot path.
+//! With `ZION_JWKS_URL` set, tokens signed with a key from Zion's JWKS are
+//! verified locally (signature, `exp`, and `iss` when `ZION_JWT_ISSUER` is
+//! set) and the user is read from the `sub`, `externalId`, `email` and
+//! `scopes` claims.
+//!
+//! The key set is fetched on first use, again when it is older than
+//! [`MAX_KEY_SET_AGE`], and when a token names a `kid` it doesn't contain (at
+//! most once per [`REFRESH_COOLDOWN`], so made-up key ids can't flood Zion).
+//! Tokens that aren't JWTs, have no `kid`, name a key Zion doesn't publish or
+//! lack one of the claims above are validated with Zion as before, and so is
+//! every token while the key set can't be fetched. A token that fails
+//! verification (bad signature, expired, wrong issuer) is rejected without
+//! asking Zion.
+//!
+//! Locally verified tokens are not checked for revocation: a token revoked in
+//! Zion keeps working until it expires.
+
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use jsonwebtoken::{
+    decode, decode_header,
+    errors::ErrorKind,
+    jwk::{Jwk, JwkSet},
+    DecodingKey, Validation,
+};
+use serde::Deserialize;
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+use crate::{
+    config::ZionConfig,
+    error::{AppError, AppResult},
+    zion::UserProfile,
+};
+
+/// Oldest key set used before it is fetched again
+pub const MAX_KEY_SET_AGE: Duration = Duration::from_secs(3600);
+
+/// Shortest time between fetches for unknown key ids, or after a failed fetch
+pub const REFRESH_COOLDOWN: Duration = Duration::from_secs(10);
+
+/// Longest a key set fetch may take
+const FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Claims the user is read from; all but `scopes` are required
+#[derive(Deserialize)]
+struct Claims {
+    sub: Option<String>,
+    #[serde(rename = "externalId", alias = "external_id")]
+    external_id: Option<String>,
+    email: Option<String>,
+    scopes: Option<Vec<String>>,
+}
+
+impl Claims {
+    /// The profile these claims describe, if they are complete
+    fn into_profile(self) -> Option<UserProfile> {
+        let non_empty = |value: Option<String>| value.filter(|v| !v.is_empty());
+        Some(UserProfile {
+            id: non_empty(self.sub)?,
+            email: non_empty(self.email)?,
+            name: None,
+            external_id: Some(non_empty(self.external_id)?),
+            email_verified: false,
+            created_at: String::new(),
+            last_login_at: None,
+            scopes: self.scopes,
+        })
+    }
+}
+
+/// Outcome of verifying a token locally
+#[derive(Debug)]
+pub enum Verification {
+    /// Signed by Zion, valid and complete: the profile its claims describe
+    Verified(UserProfile),
+    /// Invalid (bad signature, expired, wrong issuer); don't ask Zion
+    Rejected(String),
+    /// Can't be decided locally; validate with Zion
+    Remote(String),
+}
+
+/// Fetched key set
+struct KeySet {
+    keys: JwkSet,
+    fetched_at: Instant,
+}
+
+impl KeySet {
+    fn is_fresh(&self) -> bool {
+        self.fetched_at.elapsed() < MAX_KEY_SET_AGE
+    }
+}
+
+/// When fetches were last limited by [`REFRESH_COOLDOWN`]
+#[derive(Default)]
+struct FetchState {
+    /// Last refetch for a key id the fresh set didn't contain
+    unknown_kid_at: Option<Instant>,
+    /// Last failed fetch
+    failed_at: Option<Instant>,
+}
+
+fn cooling_down(at: Option<Instant>) -> bool {
+    at.is_some_and(|at| at.elapsed() < REFRESH_COOLDOWN)
+}
+
+/// Verifies Zion JWTs with the keys published at `ZION_JWKS_URL`
+pub struct JwksVerifier {
+    client: reqwest::Client,
+    url: String,
+    issuer: Option<String>,
+    keys: RwLock<Option<Arc<KeySet>>>,
+    /// Held while fetching, so concurrent requests share one fetch
+    fetch: Mutex<FetchState>,
+}
+
+impl JwksVerifier {
+    pub fn new(client: reqwest::Client, url: String, issuer: Option<String>) -> Self {
+        Self {
+            client,
+            url,
+            issuer,
+            keys: RwLock::new(None),
+            fetch: Mutex::new(FetchState::default()),
+        }
+    }
+
+    /// A verifier when `ZION_JWKS_URL` is set
+    pub fn from_config(client: &reqwest::Client, config: &ZionConfig) -> Option<Self> {
+        let url = config.jwks_url.clone()?;
+        info!(url = %url, "Verifying Zion tokens locally");
+        Some(Self::new(client.clone(), url, config.jwt_issuer.clone()))
+    }
+
+    /// Verify `token` locally, or say why Zion has to
+    pub async fn verify(&self, token: &str) -> Verification {
+        let header = match decode_header(token) {
+            Ok(header) => header,
+            Err(e) => return Verification::Remote(format!("not a JWT: {}", e)),
+        };
+        let Some(kid) = header.kid.as_deref() else {
+            return Verification::Remote("no kid in token header".to_string());
+        };
+        let jwk = match self.key(kid).await {
+            Ok(Some(jwk)) => jwk,
+            Ok(None) => return Verification::Remote(format!("unknown key id '{}'", kid)),
+            Err(e) => return Verification::Remote(format!("JWKS unavailable: {}", e)),
+        };
+        let key = match DecodingKey::from_jwk(&jwk) {
+            Ok(key) => key,
+            Err(e) => return Verification::Remote(format!("unusable key '{}': {}", kid, e)),
+        };
+
+        // The algorithm must suit the key: an HS256 header can't turn an RSA
+        // public key into a shared secret
+        let mut validation = Validation::new(header.alg);
+        validation.validate_aud = false;
+        if let Some(issuer) = &self.issuer {
+            validation.set_issuer(&[issuer]);
+        }
+
+        match decode::<Claims>(token, &key, &validation) {
+            Ok(data) => match data.claims.into_profile() {
+                Some(profile) => Verification::Verified(profile),
+                None => Verification::Remote(
+                    "token lacks the sub, externalId or email claim".to_string(),
+                ),
+            },
+            Err(e) => match e.kind() {
+                ErrorKind::MissingRequiredClaim(claim) => {
+                    Verification::Remote(format!("token lacks the {} claim", claim))
+                }
+                _ => Verification::Rejected(e.to_string()),
+            },
+        }
+    }
+
+    fn current(&self) -> Option<Arc<KeySet>> {
+        self.keys.read().unwrap().clone()
+    }
+
+    /// The key `kid`, fetching the key set when needed
+    async fn key(&self, kid: &str) -> AppResult<Option<Jwk>> {
+        if let Some(set) = self.current().filter(|set| set.is_fresh()) {
+            if let Some(jwk) = set.keys.find(kid) {
+                return Ok(Some(jwk.clone()));
+            }
+        }
+
+        let mut state = self.fetch.lock().await;
+        // Another request may have fetched the set while this one waited
+        if let Some(set) = self.current().filter(|set| set.is_fresh()) {
+            if let Some(jwk) = set.keys.find(kid) {
+                return Ok(Some(jwk.clone()));
+            }
+            if cooling_down(state.unknown_kid_at) {
+                return Ok(None);
+            }
+            state.unknown_kid_at = Some(Instant::now());
+        }
+        if cooling_down(state.failed_at) {
+            return Err(AppError::ServiceUnavailable {
+                message: "JWKS fetch failed recently".to_string(),
+                retry_after: None,
+            });
+        }
+
+        let keys = match self.fetch_keys().await {
+            Ok(keys) => keys,
+            Err(e) => {
+                warn!(url = %self.url, error = %e, "Failed to fetch Zion JWKS");
+                state.failed_at = Some(Instant::now());
+                return Err(e);
+            }
+        };
+        state.failed_at = None;
+        debug!(keys = keys.keys.len(), "Fetched Zion JWKS");
+        let jwk = keys.find(kid).cloned();
+        *self.keys.write().unwrap() = Some(Arc::new(KeySet {
+            keys,
+            fetched_at: Instant::now(),
+        }));
+        Ok(jwk)
+    }
+
+    async fn fetch_keys(&self) -> AppResult<JwkSet> {
+        let response = self
+            .client
+            .get(&self.url)
+            .timeout(FETCH_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(response.json::<JwkSet>().await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::jwks::{jwks_body, sign_token, TEST_JWKS_KID};
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    async fn verifier(issuer: Option<&str>) -> (JwksVerifier, MockServer) {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/.well-known/jwks.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(jwks_body(TEST_JWKS_KID)))
+            .mount(&server)
+            .await;
+        let url = format!("{}/.well-known/jwks.json", server.uri());
+        let verifier = JwksVerifier::new(reqwest::Client::new(), url, issuer.map(str::to_string));
+        (verifier, server)
+    }
+
+    fn claims(exp_offset: i64) -> serde_json::Value {
+        json!({
+            "sub": "user_1",
+            "externalId": "ext_1",
+            "email": "user@example.com",
+            "iss": "https://zion.example",
+            "exp": chrono::Utc::now().timestamp() + exp_offset,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_verified_claims_become_a_profile() {
+        let (verifier, _server) = verifier(Some("https://zion.example")).await;
+        let mut claims = claims(300);
+        claims["scopes"] = json!(["chat"]);
+
+        let Verification::Verified(profile) = verifier.verify(&sign_token(TEST_JWKS_KID, &claims)).await
+        else {
+            panic!("token not verified");
+        };
+        assert_eq!(profile.id, "user_1");
+        assert_eq!(profile.effective_external_id(), "ext_1");
+        assert_eq!(profile.email, "user@example.com");
+        assert_eq!(profile.scopes, Some(vec!["chat".to_string()]));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_tokens_are_rejected() {
+        let (verifier, _server) = verifier(Some("https://zion.example")).await;
+
+        let expired = sign_token(TEST_JWKS_KID, &claims(-3600));
+        assert!(matches!(verifier.verify(&expired).await, Verification::Rejected(_)));
+
+        let mut other_issuer = claims(300);
+        other_issuer["iss"] = json!("https://elsewhere.example");
+        let token = sign_token(TEST_JWKS_KID, &other_issuer);
+        assert!(matches!(verifier.verify(&token).await, Verification::Rejected(_)));
+
+        // Signature no longer matches the payload
+        let token = sign_token(TEST_JWKS_KID, &claims(300));
+        let (head, signature) = token.rsplit_once('.').unwrap();
+        let tampered = format!("{}.{}", head, signature.replace(&signature[..4], "AAAA"));
+        assert!(matches!(verifier.verify(&tampered).await, Verification::Rejected(_)));
+    }
+
+    #[tokio::test]
+    async fn test_undecidable_tokens_go_to_zion() {
+        let (verifier, server) = verifier(None).await;
+
+        assert!(matches!(verifier.verify("opaque-token").await, Verification::Remote(_)));
+        assert!(matches!(
+            verifier.verify(crate::testing::constants::TEST_JWT_TOKEN).await,
+            Verification::Remote(_)
+        ));
+
+        let mut incomplete = claims(300);
+        incomplete.as_object_mut().unwrap().remove("externalId");
+        let token = sign_token(TEST_JWKS_KID, &incomplete);
+        assert!(matches!(verifier.verify(&token).await, Verification::Remote(_)));
+
+        // An unknown key id refetches the set once, then waits out the cooldown
+        let token = sign_token("rotated-away", &claims(300));
+        assert!(matches!(verifier.verify(&token).await, Verification::Remote(_)));
+        assert!(matches!(verifier.verify(&token).await, Verification::Remote(_)));
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_jwks_goes_to_zion() {
+        let verifier = JwksVerifier::new(
+            reqwest::Client::new(),
+            "http://127.0.0.1:1/jwks.json".to_string(),
+            None,
+        );
+        let token = sign_token(TEST_JWKS_KID, &claims(300));
+        assert!(matches!(verifier.verify(&token).await, Verification::Remote(_)));
+    }
+}
@@ -3,7 +3,18 @@
 //! Provides client for interacting with the Zion governance API.
 
 pub mod client;
+pub mod jwks;
 pub mod models;
+pub mod negotiation;
 
 pub use client::ZionClient;
-pub use models::*;
+pub use models::{
+    find_limit, parse_response, resolve_limit, BatchIncrementData, BatchIncrementItem,
+    BatchIncrementMetricResult, BatchIncrementRequest, BatchIncrementResponse,
+    BatchIncrementResult, ExternalLimitsData, ExternalLimitsResponse, IncrementUsageData,
+    IncrementUsageRequest, IncrementUsageResponse, LimitMetric, MetaData, MetaResponse,
+    MissingLimitPolicy, ModelConfig, PayloadCase, ResetPeriod, TierConfigData, TierConfigResponse,
+    TierLongContextModels, TierMapping, TierSystemPrompts, UserLimit, UserProfile,
+    UserProfileResponse, unknown_fields,
+};
+pub use negotiation::{CapabilitySource, ZionCapabilities};
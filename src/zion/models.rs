@@ -2,7 +2,13 @@
 //!
 //! Data structures for Zion API requests and responses.
 
-use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use tracing::warn;
+
+use crate::error::RetryAfter;
 
 /// Reset period for limits
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -12,6 +18,9 @@ pub enum ResetPeriod {
     Weekly,
     Monthly,
     Never,
+    /// Any period Sentinel doesn't know about yet
+    #[serde(other)]
+    Unknown,
 }
 
 /// A single metric within a unified limit (e.g., aiInputTokens, aiOutputTokens, aiRequests)
@@ -28,17 +37,290 @@ pub struct LimitMetric {
 #[serde(rename_all = "camelCase")]
 pub struct UserLimit {
     pub name: String,
+    #[serde(default, alias = "display_name")]
     pub display_name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub unit: Option<String>,
+    #[serde(alias = "ai_input_tokens")]
     pub ai_input_tokens: LimitMetric,
+    #[serde(alias = "ai_output_tokens")]
     pub ai_output_tokens: LimitMetric,
+    #[serde(alias = "ai_requests")]
     pub ai_requests: LimitMetric,
+    #[serde(alias = "reset_period")]
     pub reset_period: Option<ResetPeriod>,
+    #[serde(alias = "period_start")]
     pub period_start: Option<String>,
+    #[serde(alias = "period_end")]
     pub period_end: Option<String>,
+    /// Exempts the user from Sentinel's request rate limiting (internal service accounts)
+    #[serde(default, alias = "rate_limit_exempt")]
+    pub rate_limit_exempt: bool,
+    /// Organization the user belongs to (org-scoped rate limiting and usage attribution)
+    #[serde(default, skip_serializing_if = "Option::is_none", alias = "organization_id")]
+    pub organization_id: Option<String>,
+    /// Per-organization request ceiling override (requests per rate-limit window)
+    #[serde(default, skip_serializing_if = "Option::is_none", alias = "organization_rate_limit")]
+    pub organization_rate_limit: Option<i64>,
+    /// Keeps the user's requests out of optional logs and sinks (contractual privacy tier)
+    #[serde(default, alias = "logging_opt_out")]
+    pub logging_opt_out: bool,
+    /// Glob patterns of the models the user may request (absent: every model)
+    #[serde(default, skip_serializing_if = "Option::is_none", alias = "allowed_models")]
+    pub allowed_models: Option<Vec<String>>,
+}
+
+impl LimitMetric {
+    /// Metric with no effective limit
+    pub fn unlimited() -> Self {
+        Self {
+            limit: i64::MAX,
+            used: 0,
+            remaining: i64::MAX,
+        }
+    }
+
+    /// Metric that allows nothing
+    pub fn zero() -> Self {
+        Self {
+            limit: 0,
+            used: 0,
+            remaining: 0,
+        }
+    }
+}
+
+/// How to treat a limits payload that lacks the expected limit entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MissingLimitPolicy {
+    /// No limit configured means no limit enforced
+    #[default]
+    Unlimited,
+    /// No limit configured means nothing is allowed
+    Zero,
+}
+
+impl FromStr for MissingLimitPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "unlimited" => Ok(MissingLimitPolicy::Unlimited),
+            "zero" => Ok(MissingLimitPolicy::Zero),
+            other => Err(format!(
+                "unknown missing limit policy '{}' (expected unlimited or zero)",
+                other
+            )),
+        }
+    }
+}
+
+/// Field casing of the payloads Sentinel sends to Zion
+///
+/// Responses are read in either casing (every multi-word field has a
+/// snake_case alias); requests are written in the one the deployed Zion expects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PayloadCase {
+    /// `aiInputTokens`, `organizationId`
+    #[default]
+    Camel,
+    /// `ai_input_tokens`, `organization_id`
+    Snake,
+}
+
+impl FromStr for PayloadCase {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "camel" => Ok(PayloadCase::Camel),
+            "snake" => Ok(PayloadCase::Snake),
+            other => Err(format!(
+                "unknown payload case '{}' (expected camel or snake)",
+                other
+            )),
+        }
+    }
+}
+
+impl PayloadCase {
+    /// Serialize an outbound payload with this casing
+    ///
+    /// The models serialize as camelCase; for snake_case every object key is
+    /// rewritten, so the payloads must not carry maps with data keys.
+    pub fn to_value<T: Serialize>(self, payload: &T) -> serde_json::Result<Value> {
+        let mut value = serde_json::to_value(payload)?;
+        if self == PayloadCase::Snake {
+            snake_case_keys(&mut value);
+        }
+        Ok(value)
+    }
+}
+
+fn snake_case_keys(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            *object = std::mem::take(object)
+                .into_iter()
+                .map(|(key, mut value)| {
+                    snake_case_keys(&mut value);
+                    (snake_case(&key), value)
+                })
+                .collect();
+        }
+        Value::Array(values) => values.iter_mut().for_each(snake_case_keys),
+        _ => {}
+    }
+}
+
+fn snake_case(name: &str) -> String {
+    let mut snake = String::with_capacity(name.len() + 4);
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            snake.push('_');
+            snake.push(c.to_ascii_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+fn camel_case(name: &str) -> String {
+    let mut parts = name.split('_');
+    let mut camel = parts.next().unwrap_or_default().to_string();
+    for part in parts {
+        let mut chars = part.chars();
+        if let Some(first) = chars.next() {
+            camel.push(first.to_ascii_uppercase());
+            camel.push_str(chars.as_str());
+        }
+    }
+    camel
+}
+
+impl ResetPeriod {
+    /// Longest a period can last, in seconds (None if it never resets)
+    pub fn max_seconds(&self) -> Option<u64> {
+        match self {
+            ResetPeriod::Daily => Some(86_400),
+            ResetPeriod::Weekly => Some(7 * 86_400),
+            ResetPeriod::Monthly => Some(31 * 86_400),
+            ResetPeriod::Never | ResetPeriod::Unknown => None,
+        }
+    }
+}
+
+impl UserLimit {
+    /// Wait until the period ends, for `Retry-After` once the quota is used up
+    ///
+    /// Capped at the period's length (from `reset_period`, else from the
+    /// period bounds). None when the end or the length is unknown.
+    pub fn retry_after(&self, now: i64) -> Option<RetryAfter> {
+        let timestamp = |value: &Option<String>| {
+            chrono::DateTime::parse_from_rfc3339(value.as_deref()?)
+                .ok()
+                .map(|dt| dt.timestamp())
+        };
+        let end = timestamp(&self.period_end)?;
+        let window = match self.reset_period.as_ref().and_then(ResetPeriod::max_seconds) {
+            Some(window) => window,
+            None => u64::try_from(end - timestamp(&self.period_start)?).ok()?,
+        };
+        Some(RetryAfter::until(end, now, window))
+    }
+
+    /// The first metric with nothing remaining, if the allowance is used up
+    pub fn exhausted_metric(&self) -> Option<&LimitMetric> {
+        [&self.ai_requests, &self.ai_input_tokens, &self.ai_output_tokens]
+            .into_iter()
+            .find(|metric| metric.remaining <= 0)
+    }
+
+    /// Smallest remaining value across all metrics
+    fn min_remaining(&self) -> i64 {
+        self.ai_input_tokens
+            .remaining
+            .min(self.ai_output_tokens.remaining)
+            .min(self.ai_requests.remaining)
+    }
+
+    /// Placeholder used when Zion doesn't return the expected limit entry
+    pub fn not_configured(name: &str, policy: MissingLimitPolicy) -> Self {
+        let metric = match policy {
+            MissingLimitPolicy::Unlimited => LimitMetric::unlimited(),
+            MissingLimitPolicy::Zero => LimitMetric::zero(),
+        };
+        Self {
+            name: name.to_string(),
+            display_name: name.to_string(),
+            description: None,
+            unit: None,
+            ai_input_tokens: metric.clone(),
+            ai_output_tokens: metric.clone(),
+            ai_requests: metric,
+            reset_period: None,
+            period_start: None,
+            period_end: None,
+            rate_limit_exempt: false,
+            organization_id: None,
+            organization_rate_limit: None,
+            logging_opt_out: false,
+            allowed_models: None,
+        }
+    }
+}
+
+/// Find the effective limit entry with the given name
+///
+/// When several entries share the name (e.g. daily and monthly periods), the
+/// most restrictive one - lowest remaining across metrics - wins.
+pub fn find_limit<'a>(limits: &'a [UserLimit], name: &str) -> Option<&'a UserLimit> {
+    limits
+        .iter()
+        .filter(|limit| limit.name == name)
+        .min_by_key(|limit| limit.min_remaining())
+}
+
+/// Resolve the effective limit, applying the policy when the entry is missing
+pub fn resolve_limit(limits: &[UserLimit], name: &str, policy: MissingLimitPolicy) -> UserLimit {
+    find_limit(limits, name)
+        .cloned()
+        .unwrap_or_else(|| UserLimit::not_configured(name, policy))
+}
+
+/// Deserialize a limits array, skipping entries that don't match `UserLimit`
+///
+/// Zion may add limit types Sentinel doesn't understand; one odd entry must not
+/// make the whole payload (and with it auth) fail.
+fn deserialize_limits_lenient<'de, D>(deserializer: D) -> Result<Vec<UserLimit>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let raw: Vec<serde_json::Value> = Vec::deserialize(deserializer)?;
+    let mut limits = Vec::with_capacity(raw.len());
+
+    for entry in raw {
+        match serde_json::from_value::<UserLimit>(entry.clone()) {
+            Ok(limit) => limits.push(limit),
+            Err(e) => {
+                let keys: Vec<&str> = entry
+                    .as_object()
+                    .map(|obj| obj.keys().map(String::as_str).collect())
+                    .unwrap_or_default();
+                warn!(
+                    error = %e,
+                    name = ?entry.get("name").and_then(|n| n.as_str()),
+                    keys = ?keys,
+                    "Skipping unrecognized Zion limit entry"
+                );
+            }
+        }
+    }
+
+    Ok(limits)
 }
 
 /// Response from external limits endpoint
@@ -53,8 +335,11 @@ pub struct ExternalLimitsResponse {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ExternalLimitsData {
+    #[serde(alias = "user_id")]
     pub user_id: String,
+    #[serde(alias = "external_id")]
     pub external_id: String,
+    #[serde(default, deserialize_with = "deserialize_limits_lenient")]
     pub limits: Vec<UserLimit>,
 }
 
@@ -64,16 +349,18 @@ pub struct ExternalLimitsData {
 #[serde(rename_all = "camelCase")]
 pub struct IncrementUsageRequest {
     pub email: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(skip_serializing_if = "Option::is_none", alias = "ai_input_tokens")]
     pub ai_input_tokens: Option<i64>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(skip_serializing_if = "Option::is_none", alias = "ai_output_tokens")]
     pub ai_output_tokens: Option<i64>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(skip_serializing_if = "Option::is_none", alias = "ai_requests")]
     pub ai_requests: Option<i64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub model: Option<String>,      // AI model name (e.g., "gpt-4o")
     #[serde(skip_serializing_if = "Option::is_none")]
     pub timestamp: Option<String>,  // ISO 8601 UTC timestamp
+    #[serde(default, skip_serializing_if = "Option::is_none", alias = "organization_id")]
+    pub organization_id: Option<String>, // Zion organization, when known
 }
 
 /// Response data from single increment endpoint
@@ -81,9 +368,13 @@ pub struct IncrementUsageRequest {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct IncrementUsageData {
+    #[serde(alias = "can_use")]
     pub can_use: bool,
+    #[serde(alias = "ai_input_tokens")]
     pub ai_input_tokens: LimitMetric,
+    #[serde(alias = "ai_output_tokens")]
     pub ai_output_tokens: LimitMetric,
+    #[serde(alias = "ai_requests")]
     pub ai_requests: LimitMetric,
 }
 
@@ -95,22 +386,45 @@ pub struct IncrementUsageResponse {
     pub data: IncrementUsageData,
 }
 
+/// API metadata from `GET /api/v1/meta`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetaData {
+    #[serde(alias = "api_version")]
+    pub api_version: String,
+    /// Optional features this deployment accepts (e.g. "batch.model")
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+}
+
+/// Response from the meta endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetaResponse {
+    pub success: bool,
+    pub data: MetaData,
+}
+
 /// Single item in a batch increment request
 /// Note: limit_name is not sent - it's auto-detected from user's subscription plan
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct BatchIncrementItem {
     pub email: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(skip_serializing_if = "Option::is_none", alias = "ai_input_tokens")]
     pub ai_input_tokens: Option<i64>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(skip_serializing_if = "Option::is_none", alias = "ai_output_tokens")]
     pub ai_output_tokens: Option<i64>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(skip_serializing_if = "Option::is_none", alias = "ai_requests")]
     pub ai_requests: Option<i64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub model: Option<String>,      // AI model name (e.g., "gpt-4o")
     #[serde(skip_serializing_if = "Option::is_none")]
     pub timestamp: Option<String>,  // ISO 8601 UTC timestamp
+    #[serde(default, skip_serializing_if = "Option::is_none", alias = "organization_id")]
+    pub organization_id: Option<String>, // Zion organization, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimated: Option<bool>,    // Some tokens were estimated, not reported by the provider
 }
 
 /// Batch increment request (up to 1000 items)
@@ -125,13 +439,15 @@ pub struct BatchIncrementRequest {
 #[serde(rename_all = "camelCase")]
 pub struct BatchIncrementResult {
     pub email: String,
+    /// Absent on items that failed before a limit was resolved
+    #[serde(default, alias = "limit_name")]
     pub limit_name: String,
     pub success: bool,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(skip_serializing_if = "Option::is_none", alias = "ai_input_tokens")]
     pub ai_input_tokens: Option<BatchIncrementMetricResult>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(skip_serializing_if = "Option::is_none", alias = "ai_output_tokens")]
     pub ai_output_tokens: Option<BatchIncrementMetricResult>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(skip_serializing_if = "Option::is_none", alias = "ai_requests")]
     pub ai_requests: Option<BatchIncrementMetricResult>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
@@ -141,6 +457,7 @@ pub struct BatchIncrementResult {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct BatchIncrementMetricResult {
+    #[serde(alias = "new_value")]
     pub new_value: i64,
     pub limit: i64,
 }
@@ -169,10 +486,24 @@ pub struct UserProfile {
     pub id: String,
     pub email: String,
     pub name: Option<String>,
+    #[serde(alias = "external_id")]
     pub external_id: Option<String>,
+    #[serde(alias = "email_verified")]
     pub email_verified: bool,
+    #[serde(alias = "created_at")]
     pub created_at: String,
+    #[serde(alias = "last_login_at")]
     pub last_login_at: Option<String>,
+    /// Scopes of the token the profile was fetched with (None for unscoped tokens)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scopes: Option<Vec<String>>,
+}
+
+impl UserProfile {
+    /// External id usage is tracked under, the user id when Zion sent none
+    pub fn effective_external_id(&self) -> String {
+        self.external_id.clone().unwrap_or_else(|| self.id.clone())
+    }
 }
 
 /// Response from user profile endpoint
@@ -198,11 +529,22 @@ pub struct ModelConfig {
     /// Relative cost score (1-10, lower is cheaper)
     /// Used for weighted selection - lower cost = higher probability
     /// Must be >= 1 to avoid division by zero in weight calculation
+    #[serde(alias = "relative_cost")]
     pub relative_cost: u8,
     /// Input token price per million (for cost reporting)
+    #[serde(alias = "input_price_per_million")]
     pub input_price_per_million: f64,
     /// Output token price per million (for cost reporting)
+    #[serde(alias = "output_price_per_million")]
     pub output_price_per_million: f64,
+    /// Reasoning model (o1/o3 family): takes `developer` instead of `system`
+    /// messages, rejects sampling parameters and wants `max_completion_tokens`
+    #[serde(default)]
+    pub reasoning: bool,
+    /// Remove reasoning blocks and fields from this model's responses
+    /// (see `provider.response_strip_tags` / `provider.response_drop_fields`)
+    #[serde(default, alias = "strip_reasoning")]
+    pub strip_reasoning: bool,
 }
 
 /// Tier-to-model mapping configuration
@@ -224,9 +566,46 @@ pub struct TierConfigData {
     /// Config version for cache invalidation
     pub version: String,
     /// When this config was last updated
+    #[serde(alias = "updated_at")]
     pub updated_at: String,
     /// Tier-to-model mappings
     pub tiers: TierMapping,
+    /// Optional per-tier system prompt injection overrides
+    #[serde(default, skip_serializing_if = "Option::is_none", alias = "system_prompts")]
+    pub system_prompts: Option<TierSystemPrompts>,
+    /// Optional per-tier long-context models used by `CONTEXT_FALLBACK`
+    #[serde(default, skip_serializing_if = "Option::is_none", alias = "long_context_models")]
+    pub long_context_models: Option<TierLongContextModels>,
+    /// Optional per-tier models for `POST /native/v1/embeddings`
+    #[serde(default, skip_serializing_if = "Option::is_none", alias = "embedding_models")]
+    pub embedding_models: Option<TierEmbeddingModels>,
+}
+
+/// Per-tier system prompt overrides (take precedence over SYSTEM_PROMPT_INJECTION)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TierSystemPrompts {
+    pub simple: Option<String>,
+    pub moderate: Option<String>,
+    pub complex: Option<String>,
+}
+
+/// Per-tier models to retry on when a request exceeds the selected model's context
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TierLongContextModels {
+    pub simple: Option<String>,
+    pub moderate: Option<String>,
+    pub complex: Option<String>,
+}
+
+/// Per-tier embedding models
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TierEmbeddingModels {
+    pub simple: Option<String>,
+    pub moderate: Option<String>,
+    pub complex: Option<String>,
 }
 
 /// Response wrapper from tier config endpoint
@@ -237,9 +616,77 @@ pub struct TierConfigResponse {
     pub data: TierConfigData,
 }
 
+// ===========================================
+// Response Parsing
+// ===========================================
+
+/// Parse a Zion response body into its typed model
+///
+/// Unknown fields are ignored so Zion can add fields without breaking
+/// Sentinel. Debug builds log them, which surfaces renamed or unmodeled
+/// fields while developing against a new Zion version. Fields are accepted
+/// in camelCase and snake_case, so a casing change alone isn't reported.
+pub fn parse_response<T>(endpoint: &str, body: &str) -> serde_json::Result<T>
+where
+    T: DeserializeOwned + Serialize,
+{
+    let parsed: T = serde_json::from_str(body)?;
+    if cfg!(debug_assertions) {
+        if let (Ok(raw), Ok(typed)) = (
+            serde_json::from_str::<Value>(body),
+            serde_json::to_value(&parsed),
+        ) {
+            let fields = unknown_fields(&raw, &typed);
+            if !fields.is_empty() {
+                warn!(endpoint, fields = ?fields, "Zion response has fields Sentinel doesn't model");
+            }
+        }
+    }
+    Ok(parsed)
+}
+
+/// Paths of non-null fields in `raw` that are missing from `typed`
+///
+/// `typed` is the parsed model serialized back. Arrays are compared element
+/// by element only if their lengths match (the lenient limits parser drops
+/// entries it doesn't understand).
+pub fn unknown_fields(raw: &Value, typed: &Value) -> Vec<String> {
+    let mut fields = Vec::new();
+    collect_unknown_fields(raw, typed, "", &mut fields);
+    fields
+}
+
+fn collect_unknown_fields(raw: &Value, typed: &Value, path: &str, fields: &mut Vec<String>) {
+    match (raw, typed) {
+        (Value::Object(raw), Value::Object(typed)) => {
+            for (key, value) in raw {
+                let field = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                // snake_case keys were read through their aliases
+                match typed.get(key).or_else(|| typed.get(&camel_case(key))) {
+                    Some(typed) => collect_unknown_fields(value, typed, &field, fields),
+                    None if !value.is_null() => fields.push(field),
+                    None => {}
+                }
+            }
+        }
+        (Value::Array(raw), Value::Array(typed)) if raw.len() == typed.len() => {
+            for (index, (raw, typed)) in raw.iter().zip(typed).enumerate() {
+                collect_unknown_fields(raw, typed, &format!("{}[{}]", path, index), fields);
+            }
+        }
+        _ => {}
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::zion as fixtures;
+    use serde_json::json;
 
     // ===========================================
     // ResetPeriod Serialization Tests
@@ -421,6 +868,137 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_retry_after_until_period_end() {
+        let mut limit = UserLimit::not_configured("ai_usage", MissingLimitPolicy::Zero);
+        let now = chrono::DateTime::parse_from_rfc3339("2026-01-31T23:00:00Z")
+            .unwrap()
+            .timestamp();
+        assert_eq!(limit.retry_after(now), None);
+
+        limit.reset_period = Some(ResetPeriod::Daily);
+        limit.period_end = Some("2026-02-01T00:00:00Z".to_string());
+        assert_eq!(limit.retry_after(now), Some(RetryAfter::seconds(3600)));
+
+        // An end further out than the period (stale or skewed data) is capped
+        limit.period_end = Some("2026-03-01T00:00:00Z".to_string());
+        assert_eq!(limit.retry_after(now), Some(RetryAfter::seconds(86_400)));
+
+        // Without a known period, the bounds give the length
+        limit.reset_period = Some(ResetPeriod::Never);
+        assert_eq!(limit.retry_after(now), None);
+        limit.period_start = Some("2026-02-28T00:00:00Z".to_string());
+        assert_eq!(limit.retry_after(now), Some(RetryAfter::seconds(86_400)));
+
+        // Already past: retry shortly, never immediately
+        limit.period_end = Some("2026-01-01T00:00:00Z".to_string());
+        limit.reset_period = Some(ResetPeriod::Monthly);
+        assert_eq!(limit.retry_after(now), Some(RetryAfter::MIN));
+    }
+
+    // ===========================================
+    // Partial / Malformed Limits Payload Tests
+    // ===========================================
+
+    fn limits_payload(limits: &str) -> String {
+        format!(
+            r#"{{"success": true, "data": {{"userId": "user_123", "externalId": "ext_456", "limits": {}}}}}"#,
+            limits
+        )
+    }
+
+    #[test]
+    fn test_limits_without_ai_usage_entry() {
+        // Seen during plan migration: only a non-AI limit came back
+        let json = limits_payload(
+            r#"[{
+                "name": "storage",
+                "displayName": "Storage",
+                "aiInputTokens": {"limit": 0, "used": 0, "remaining": 0},
+                "aiOutputTokens": {"limit": 0, "used": 0, "remaining": 0},
+                "aiRequests": {"limit": 0, "used": 0, "remaining": 0},
+                "resetPeriod": "NEVER"
+            }]"#,
+        );
+
+        let response: ExternalLimitsResponse = serde_json::from_str(&json).unwrap();
+        let limits = &response.data.limits;
+        assert!(find_limit(limits, "ai_usage").is_none());
+
+        let unlimited = resolve_limit(limits, "ai_usage", MissingLimitPolicy::Unlimited);
+        assert_eq!(unlimited.ai_requests, LimitMetric::unlimited());
+
+        let zero = resolve_limit(limits, "ai_usage", MissingLimitPolicy::Zero);
+        assert_eq!(zero.ai_input_tokens.remaining, 0);
+    }
+
+    #[test]
+    fn test_limits_skip_unrecognized_entries() {
+        // An entry in a shape Sentinel doesn't know must not break parsing
+        let json = limits_payload(
+            r#"[
+                {"name": "seats", "limit": 5, "used": 2},
+                {
+                    "name": "ai_usage",
+                    "displayName": "AI Usage",
+                    "aiInputTokens": {"limit": 100, "used": 0, "remaining": 100},
+                    "aiOutputTokens": {"limit": 50, "used": 0, "remaining": 50},
+                    "aiRequests": {"limit": 10, "used": 0, "remaining": 10},
+                    "resetPeriod": "MONTHLY"
+                }
+            ]"#,
+        );
+
+        let response: ExternalLimitsResponse = serde_json::from_str(&json).unwrap();
+        assert_eq!(response.data.limits.len(), 1);
+        assert_eq!(response.data.limits[0].name, "ai_usage");
+    }
+
+    #[test]
+    fn test_limits_missing_limits_field() {
+        let json = r#"{"success": true, "data": {"userId": "user_123", "externalId": "ext_456"}}"#;
+        let response: ExternalLimitsResponse = serde_json::from_str(json).unwrap();
+        assert!(response.data.limits.is_empty());
+    }
+
+    #[test]
+    fn test_find_limit_picks_most_restrictive_period() {
+        let json = limits_payload(
+            r#"[
+                {
+                    "name": "ai_usage",
+                    "displayName": "AI Usage (monthly)",
+                    "aiInputTokens": {"limit": 100000, "used": 10, "remaining": 99990},
+                    "aiOutputTokens": {"limit": 50000, "used": 0, "remaining": 50000},
+                    "aiRequests": {"limit": 1000, "used": 1, "remaining": 999},
+                    "resetPeriod": "MONTHLY"
+                },
+                {
+                    "name": "ai_usage",
+                    "displayName": "AI Usage (daily)",
+                    "aiInputTokens": {"limit": 5000, "used": 10, "remaining": 4990},
+                    "aiOutputTokens": {"limit": 2000, "used": 0, "remaining": 2000},
+                    "aiRequests": {"limit": 50, "used": 1, "remaining": 49},
+                    "resetPeriod": "DAILY"
+                }
+            ]"#,
+        );
+
+        let response: ExternalLimitsResponse = serde_json::from_str(&json).unwrap();
+        let limit = find_limit(&response.data.limits, "ai_usage").unwrap();
+        assert_eq!(limit.reset_period, Some(ResetPeriod::Daily));
+    }
+
+    #[test]
+    fn test_missing_limit_policy_from_str() {
+        assert_eq!(
+            "unlimited".parse::<MissingLimitPolicy>().unwrap(),
+            MissingLimitPolicy::Unlimited
+        );
+        assert_eq!("ZERO".parse::<MissingLimitPolicy>().unwrap(), MissingLimitPolicy::Zero);
+        assert!("maybe".parse::<MissingLimitPolicy>().is_err());
+    }
+
     #[test]
     fn test_serialize_user_limit() {
         let limit = UserLimit {
@@ -434,6 +1012,11 @@ mod tests {
             reset_period: Some(ResetPeriod::Daily),
             period_start: Some("2024-01-01T00:00:00Z".to_string()),
             period_end: Some("2024-01-01T23:59:59Z".to_string()),
+            rate_limit_exempt: false,
+            organization_id: None,
+            organization_rate_limit: None,
+            logging_opt_out: false,
+            allowed_models: None,
         };
 
         let json = serde_json::to_string(&limit).unwrap();
@@ -458,6 +1041,11 @@ mod tests {
             reset_period: None,
             period_start: None,
             period_end: None,
+            rate_limit_exempt: false,
+            organization_id: None,
+            organization_rate_limit: None,
+            logging_opt_out: false,
+            allowed_models: None,
         };
 
         let cloned = limit.clone();
@@ -496,6 +1084,7 @@ mod tests {
             ai_requests: Some(1),
             model: None,
             timestamp: None,
+            organization_id: None,
         };
 
         let json = serde_json::to_string(&request).unwrap();
@@ -516,6 +1105,7 @@ mod tests {
             ai_requests: None,
             model: None,
             timestamp: None,
+            organization_id: None,
         };
 
         let json = serde_json::to_string(&request).unwrap();
@@ -549,6 +1139,7 @@ mod tests {
             ai_requests: Some(1),
             model: Some("gpt-4o".to_string()),
             timestamp: Some("2024-01-15T10:30:00Z".to_string()),
+            organization_id: None,
         };
 
         let cloned = request.clone();
@@ -567,6 +1158,7 @@ mod tests {
             ai_requests: Some(1),
             model: Some("gpt-4o".to_string()),
             timestamp: Some("2024-01-15T10:30:00Z".to_string()),
+            organization_id: None,
         };
 
         let json = serde_json::to_string(&request).unwrap();
@@ -611,6 +1203,8 @@ mod tests {
             ai_requests: Some(1),
             model: None,
             timestamp: None,
+            organization_id: None,
+            estimated: None,
         };
 
         let json = serde_json::to_string(&item).unwrap();
@@ -631,6 +1225,8 @@ mod tests {
                     ai_requests: Some(1),
                     model: Some("gpt-4o".to_string()),
                     timestamp: Some("2024-01-15T10:30:00Z".to_string()),
+                    organization_id: None,
+                    estimated: None,
                 },
                 BatchIncrementItem {
                     email: "user2@example.com".to_string(),
@@ -639,6 +1235,8 @@ mod tests {
                     ai_requests: Some(1),
                     model: None,
                     timestamp: None,
+                    organization_id: None,
+                    estimated: None,
                 },
             ],
         };
@@ -730,6 +1328,73 @@ mod tests {
         assert_eq!(response.data.limits[0].ai_input_tokens.limit, 100000);
         assert_eq!(response.data.limits[0].ai_output_tokens.limit, 50000);
         assert_eq!(response.data.limits[0].ai_requests.limit, 1000);
+        assert!(!response.data.limits[0].rate_limit_exempt);
+    }
+
+    #[test]
+    fn test_deserialize_user_limit_rate_limit_exempt() {
+        let json = r#"{
+            "name": "ai_usage",
+            "displayName": "AI Usage",
+            "aiInputTokens": {"limit": 100000, "used": 0, "remaining": 100000},
+            "aiOutputTokens": {"limit": 50000, "used": 0, "remaining": 50000},
+            "aiRequests": {"limit": 1000, "used": 0, "remaining": 1000},
+            "resetPeriod": "DAILY",
+            "periodStart": null,
+            "periodEnd": null,
+            "rateLimitExempt": true
+        }"#;
+
+        let limit: UserLimit = serde_json::from_str(json).unwrap();
+        assert!(limit.rate_limit_exempt);
+    }
+
+    #[test]
+    fn test_deserialize_user_limit_logging_opt_out() {
+        let json = r#"{
+            "name": "ai_usage",
+            "aiInputTokens": {"limit": 100000, "used": 0, "remaining": 100000},
+            "aiOutputTokens": {"limit": 50000, "used": 0, "remaining": 50000},
+            "aiRequests": {"limit": 1000, "used": 0, "remaining": 1000},
+            "resetPeriod": "MONTHLY",
+            "periodStart": null,
+            "periodEnd": null,
+            "loggingOptOut": true
+        }"#;
+
+        let limit: UserLimit = serde_json::from_str(json).unwrap();
+        assert!(limit.logging_opt_out);
+
+        // Kept when the limits are cached with the subscription
+        let cached: UserLimit =
+            serde_json::from_value(serde_json::to_value(&limit).unwrap()).unwrap();
+        assert!(cached.logging_opt_out);
+        assert!(!UserLimit::not_configured("ai_usage", MissingLimitPolicy::Unlimited).logging_opt_out);
+    }
+
+    #[test]
+    fn test_deserialize_user_limit_organization() {
+        let json = r#"{
+            "name": "ai_usage",
+            "aiInputTokens": {"limit": 100000, "used": 0, "remaining": 100000},
+            "aiOutputTokens": {"limit": 50000, "used": 0, "remaining": 50000},
+            "aiRequests": {"limit": 1000, "used": 0, "remaining": 1000},
+            "resetPeriod": "MONTHLY",
+            "periodStart": null,
+            "periodEnd": null,
+            "organizationId": "org_acme",
+            "organizationRateLimit": 500
+        }"#;
+
+        let limit: UserLimit = serde_json::from_str(json).unwrap();
+        assert_eq!(limit.organization_id.as_deref(), Some("org_acme"));
+        assert_eq!(limit.organization_rate_limit, Some(500));
+
+        // Absent by default and omitted when re-serialized for the cache
+        let limit = UserLimit::not_configured("ai_usage", MissingLimitPolicy::Unlimited);
+        assert!(limit.organization_id.is_none());
+        let value = serde_json::to_value(&limit).unwrap();
+        assert!(value.get("organizationId").is_none());
     }
 
     #[test]
@@ -894,6 +1559,7 @@ mod tests {
             email_verified: true,
             created_at: "2024-01-01".to_string(),
             last_login_at: None,
+            scopes: None,
         };
 
         let cloned = profile.clone();
@@ -953,7 +1619,7 @@ mod tests {
     // ===========================================
 
     #[test]
-    fn test_invalid_reset_period_fails() {
+    fn test_unknown_reset_period_tolerated() {
         let json = r#"{
             "name": "ai_usage",
             "displayName": "AI Usage",
@@ -965,8 +1631,9 @@ mod tests {
             "periodEnd": null
         }"#;
 
-        let result: Result<UserLimit, _> = serde_json::from_str(json);
-        assert!(result.is_err(), "Invalid reset period should fail deserialization");
+        // New periods added in Zion must not break limits parsing
+        let limit: UserLimit = serde_json::from_str(json).unwrap();
+        assert_eq!(limit.reset_period, Some(ResetPeriod::Unknown));
     }
 
     #[test]
@@ -999,6 +1666,11 @@ mod tests {
             reset_period: Some(ResetPeriod::Monthly),
             period_start: Some("2024-01-01".to_string()),
             period_end: Some("2024-01-31".to_string()),
+            rate_limit_exempt: false,
+            organization_id: None,
+            organization_rate_limit: None,
+            logging_opt_out: false,
+            allowed_models: None,
         };
 
         let json = serde_json::to_string(&original).unwrap();
@@ -1021,6 +1693,7 @@ mod tests {
             ai_requests: Some(1),
             model: Some("gpt-4o".to_string()),
             timestamp: Some("2024-01-15T10:30:00Z".to_string()),
+            organization_id: None,
         };
 
         let json = serde_json::to_string(&original).unwrap();
@@ -1044,6 +1717,7 @@ mod tests {
             email_verified: true,
             created_at: "2024-01-01T00:00:00Z".to_string(),
             last_login_at: Some("2024-06-15T10:00:00Z".to_string()),
+            scopes: None,
         };
 
         let json = serde_json::to_string(&original).unwrap();
@@ -1073,6 +1747,11 @@ mod tests {
             reset_period: None,
             period_start: None,
             period_end: None,
+            rate_limit_exempt: false,
+            organization_id: None,
+            organization_rate_limit: None,
+            logging_opt_out: false,
+            allowed_models: None,
         };
 
         let debug_str = format!("{:?}", limit);
@@ -1090,6 +1769,7 @@ mod tests {
             email_verified: false,
             created_at: "2024-01-01".to_string(),
             last_login_at: None,
+            scopes: None,
         };
 
         let debug_str = format!("{:?}", profile);
@@ -1109,6 +1789,8 @@ mod tests {
             relative_cost: 1,
             input_price_per_million: 0.15,
             output_price_per_million: 0.60,
+            reasoning: false,
+            strip_reasoning: false,
         };
 
         let json = serde_json::to_string(&config).unwrap();
@@ -1128,6 +1810,8 @@ mod tests {
             relative_cost: 5,
             input_price_per_million: 3.0,
             output_price_per_million: 15.0,
+            reasoning: false,
+            strip_reasoning: false,
         };
 
         let json = serde_json::to_string(&config).unwrap();
@@ -1217,6 +1901,22 @@ mod tests {
         assert_eq!(response.data.version, "1.0.0");
     }
 
+    #[test]
+    fn test_tier_config_system_prompts_deserialization() {
+        let json = r#"{
+            "version": "1.0.0",
+            "updatedAt": "2024-01-15T10:30:00Z",
+            "tiers": { "simple": [], "moderate": [], "complex": [] },
+            "systemPrompts": { "moderate": "Cite sources." }
+        }"#;
+
+        let config: TierConfigData = serde_json::from_str(json).unwrap();
+        let prompts = config.system_prompts.unwrap();
+        assert_eq!(prompts.moderate.as_deref(), Some("Cite sources."));
+        assert!(prompts.simple.is_none());
+        assert!(prompts.complex.is_none());
+    }
+
     #[test]
     fn test_tier_config_data_roundtrip() {
         let original = TierConfigData {
@@ -1230,6 +1930,8 @@ mod tests {
                         relative_cost: 1,
                         input_price_per_million: 0.15,
                         output_price_per_million: 0.60,
+                        reasoning: false,
+                        strip_reasoning: false,
                     },
                 ],
                 moderate: vec![
@@ -1239,10 +1941,24 @@ mod tests {
                         relative_cost: 5,
                         input_price_per_million: 2.50,
                         output_price_per_million: 10.0,
+                        reasoning: false,
+                        strip_reasoning: false,
                     },
                 ],
                 complex: vec![],
             },
+            system_prompts: Some(TierSystemPrompts {
+                complex: Some("Think step by step.".to_string()),
+                ..Default::default()
+            }),
+            long_context_models: Some(TierLongContextModels {
+                moderate: Some("gpt-4.1".to_string()),
+                ..Default::default()
+            }),
+            embedding_models: Some(TierEmbeddingModels {
+                simple: Some("text-embedding-3-small".to_string()),
+                ..Default::default()
+            }),
         };
 
         let json = serde_json::to_string(&original).unwrap();
@@ -1284,4 +2000,331 @@ mod tests {
         assert!(mapping.moderate.is_empty());
         assert!(mapping.complex.is_empty());
     }
+
+    // ===========================================
+    // Fixture and Forward-Compatibility Tests
+    // ===========================================
+
+    /// Parse a fixture, asserting every field it carries is modeled
+    fn parse_fixture<T: DeserializeOwned + Serialize>(endpoint: &str, body: Value) -> T {
+        let parsed: T = parse_response(endpoint, &body.to_string()).unwrap();
+        assert_eq!(
+            unknown_fields(&body, &serde_json::to_value(&parsed).unwrap()),
+            Vec::<String>::new(),
+            "{} fixture has unmodeled fields",
+            endpoint
+        );
+        parsed
+    }
+
+    /// Add an unknown field to every object in a payload
+    fn with_extra_fields(value: &mut Value) {
+        match value {
+            Value::Object(object) => {
+                object.values_mut().for_each(with_extra_fields);
+                object.insert("futureField".to_string(), json!({"nested": [1, 2]}));
+            }
+            Value::Array(items) => items.iter_mut().for_each(with_extra_fields),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn test_stub_fixtures_deserialize() {
+        let profile: UserProfileResponse = parse_fixture("users/me", fixtures::profile_body());
+        assert_eq!(profile.data.email, crate::testing::constants::TEST_EMAIL);
+        assert_eq!(profile.data.name.as_deref(), Some("Test User"));
+        // Unscoped legacy token
+        assert_eq!(profile.data.scopes, None);
+        let mut scoped = fixtures::profile_body();
+        scoped["data"]["scopes"] = json!(["embeddings"]);
+        let profile: UserProfileResponse = parse_fixture("users/me", scoped);
+        assert_eq!(profile.data.scopes, Some(vec!["embeddings".to_string()]));
+
+        let limits: ExternalLimitsResponse = parse_fixture("limits", fixtures::limits_body());
+        let limit = &limits.data.limits[0];
+        assert_eq!(limit.name, "ai_usage");
+        assert_eq!(limit.ai_input_tokens.limit, 1_000_000);
+        assert_eq!(limit.ai_output_tokens.remaining, 1_000_000);
+        assert_eq!(limit.ai_requests.limit, 10_000);
+        assert_eq!(limit.reset_period, Some(ResetPeriod::Monthly));
+        assert_eq!(limit.period_start.as_deref(), Some("2024-01-01T00:00:00Z"));
+        assert_eq!(limit.period_end.as_deref(), Some("2024-01-31T23:59:59Z"));
+
+        let increment: IncrementUsageResponse =
+            parse_fixture("increment", fixtures::increment_body());
+        assert!(increment.data.can_use);
+        assert_eq!(increment.data.ai_requests.remaining, 10_000);
+
+        let batch: BatchIncrementResponse =
+            parse_fixture("batch-increment", fixtures::batch_increment_body());
+        assert_eq!((batch.data.processed, batch.data.failed), (1, 0));
+
+        let tiers: TierConfigResponse = parse_fixture("tiers/config", fixtures::tier_config_body());
+        assert_eq!(tiers.data.tiers.simple[0].model, "gpt-4o-mini");
+        assert_eq!(tiers.data.tiers.complex[0].relative_cost, 5);
+
+        let meta: MetaResponse = parse_fixture("meta", fixtures::meta_body());
+        assert_eq!(meta.data.capabilities.len(), 4);
+    }
+
+    #[test]
+    fn test_stub_fixtures_tolerate_extra_fields() {
+        fn check<T: DeserializeOwned + Serialize>(endpoint: &str, body: Value) {
+            let mut extended = body.clone();
+            with_extra_fields(&mut extended);
+
+            let parsed: T = parse_response(endpoint, &extended.to_string())
+                .unwrap_or_else(|e| panic!("{} rejected extra fields: {}", endpoint, e));
+            // Same model as without the extra fields, which are all reported
+            let typed = serde_json::to_value(&parsed).unwrap();
+            let plain = serde_json::to_value(parse_response::<T>(endpoint, &body.to_string()).unwrap())
+                .unwrap();
+            assert_eq!(typed, plain, "{}", endpoint);
+            let unknown = unknown_fields(&extended, &typed);
+            assert!(unknown.contains(&"futureField".to_string()), "{:?}", unknown);
+            assert!(unknown.contains(&"data.futureField".to_string()), "{:?}", unknown);
+        }
+
+        check::<UserProfileResponse>("users/me", fixtures::profile_body());
+        check::<ExternalLimitsResponse>("limits", fixtures::limits_body());
+        check::<IncrementUsageResponse>("increment", fixtures::increment_body());
+        check::<BatchIncrementResponse>("batch-increment", fixtures::batch_increment_body());
+        check::<TierConfigResponse>("tiers/config", fixtures::tier_config_body());
+        check::<MetaResponse>("meta", fixtures::meta_body());
+    }
+
+    #[test]
+    fn test_unknown_fields_paths() {
+        let raw = json!({
+            "success": true,
+            "data": {
+                "limits": [{"name": "ai_usage", "quota": 5}],
+                "legacyField": null,
+                "snake_case": 1
+            }
+        });
+        let typed = json!({
+            "success": true,
+            "data": {"limits": [{"name": "ai_usage"}], "snakeCase": 1}
+        });
+        // Null fields are ignored: optional fields skipped when serializing.
+        // snake_case keys match their camelCase field (read through the alias)
+        assert_eq!(unknown_fields(&raw, &typed), vec!["data.limits[0].quota"]);
+        let typed = json!({"success": true, "data": {"limits": [{"name": "ai_usage"}]}});
+        assert_eq!(
+            unknown_fields(&raw, &typed),
+            vec!["data.limits[0].quota", "data.snake_case"]
+        );
+
+        // Dropped limit entries change the array length; no per-entry report
+        let typed = json!({"success": true, "data": {"limits": [], "snake_case": 1}});
+        assert!(unknown_fields(&raw, &typed).is_empty());
+    }
+
+    #[test]
+    fn test_batch_result_without_limit_name() {
+        // Items that fail before a limit is resolved carry no limitName
+        let json = r#"{
+            "success": true,
+            "data": {
+                "processed": 0,
+                "failed": 1,
+                "results": [{"email": "a@b.com", "success": false, "error": "User not found"}]
+            }
+        }"#;
+        let response: BatchIncrementResponse = parse_response("batch-increment", json).unwrap();
+        let result = &response.data.results[0];
+        assert!(!result.success);
+        assert!(result.limit_name.is_empty());
+        assert_eq!(result.error.as_deref(), Some("User not found"));
+    }
+
+    // ===========================================
+    // Payload Casing and Contract Tests
+    // ===========================================
+
+    #[test]
+    fn test_payload_case_from_str() {
+        assert_eq!("camel".parse::<PayloadCase>().unwrap(), PayloadCase::Camel);
+        assert_eq!(" Snake ".parse::<PayloadCase>().unwrap(), PayloadCase::Snake);
+        assert_eq!(PayloadCase::default(), PayloadCase::Camel);
+        assert!("kebab".parse::<PayloadCase>().is_err());
+    }
+
+    #[test]
+    fn test_casing_helpers() {
+        assert_eq!(snake_case("aiInputTokens"), "ai_input_tokens");
+        assert_eq!(snake_case("email"), "email");
+        assert_eq!(camel_case("organization_rate_limit"), "organizationRateLimit");
+        assert_eq!(camel_case("limits"), "limits");
+    }
+
+    #[test]
+    fn test_snake_case_fixtures_deserialize() {
+        // Every response a snake_case Zion could send reads as the camelCase one
+        fn check<T: DeserializeOwned + Serialize>(endpoint: &str, body: Value) {
+            let mut snake = body.clone();
+            snake_case_keys(&mut snake);
+
+            let parsed: T = parse_response(endpoint, &snake.to_string())
+                .unwrap_or_else(|e| panic!("{} rejected snake_case: {}", endpoint, e));
+            let typed = serde_json::to_value(&parsed).unwrap();
+            let camel = serde_json::to_value(parse_response::<T>(endpoint, &body.to_string()).unwrap())
+                .unwrap();
+            assert_eq!(typed, camel, "{}", endpoint);
+            assert_eq!(unknown_fields(&snake, &typed), Vec::<String>::new(), "{}", endpoint);
+        }
+
+        check::<UserProfileResponse>("users/me", fixtures::profile_body());
+        check::<ExternalLimitsResponse>("limits", fixtures::limits_body());
+        check::<IncrementUsageResponse>("increment", fixtures::increment_body());
+        check::<BatchIncrementResponse>("batch-increment", fixtures::batch_increment_body());
+        check::<TierConfigResponse>("tiers/config", fixtures::tier_config_body());
+        check::<MetaResponse>("meta", fixtures::meta_body());
+    }
+
+    #[test]
+    fn test_snake_case_limit_entry_not_skipped() {
+        // The lenient limits parser must not drop snake_case entries
+        let json = r#"{
+            "success": true,
+            "data": {
+                "user_id": "user_123",
+                "external_id": "ext_123",
+                "limits": [{
+                    "name": "ai_usage",
+                    "display_name": "AI Usage",
+                    "ai_input_tokens": {"limit": 100, "used": 10, "remaining": 90},
+                    "ai_output_tokens": {"limit": 50, "used": 5, "remaining": 45},
+                    "ai_requests": {"limit": 10, "used": 1, "remaining": 9},
+                    "reset_period": "DAILY",
+                    "period_start": null,
+                    "period_end": null,
+                    "rate_limit_exempt": true,
+                    "organization_id": "org_1"
+                }]
+            }
+        }"#;
+        let response: ExternalLimitsResponse = parse_response("limits", json).unwrap();
+        assert_eq!(response.data.external_id, "ext_123");
+        let limit = &response.data.limits[0];
+        assert_eq!(limit.ai_input_tokens.remaining, 90);
+        assert_eq!(limit.reset_period, Some(ResetPeriod::Daily));
+        assert!(limit.rate_limit_exempt);
+        assert_eq!(limit.organization_id.as_deref(), Some("org_1"));
+    }
+
+    /// Every field of a batch item set
+    fn full_batch_item() -> BatchIncrementItem {
+        BatchIncrementItem {
+            email: "user@example.com".to_string(),
+            ai_input_tokens: Some(100),
+            ai_output_tokens: Some(50),
+            ai_requests: Some(1),
+            model: Some("gpt-4o".to_string()),
+            timestamp: Some("2024-01-15T10:30:00Z".to_string()),
+            organization_id: Some("org_1".to_string()),
+            estimated: Some(true),
+        }
+    }
+
+    /// Serialize an outbound payload the way `ZionClient` sends it
+    fn wire<T: Serialize>(case: PayloadCase, payload: &T) -> String {
+        serde_json::to_string(&case.to_value(payload).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_increment_request_contract() {
+        let full = IncrementUsageRequest {
+            email: "user@example.com".to_string(),
+            ai_input_tokens: Some(100),
+            ai_output_tokens: Some(50),
+            ai_requests: Some(1),
+            model: Some("gpt-4o".to_string()),
+            timestamp: Some("2024-01-15T10:30:00Z".to_string()),
+            organization_id: Some("org_1".to_string()),
+        };
+        let minimal = IncrementUsageRequest {
+            email: "user@example.com".to_string(),
+            ai_input_tokens: None,
+            ai_output_tokens: None,
+            ai_requests: Some(1),
+            model: None,
+            timestamp: None,
+            organization_id: None,
+        };
+
+        assert_eq!(
+            wire(PayloadCase::Camel, &full),
+            r#"{"aiInputTokens":100,"aiOutputTokens":50,"aiRequests":1,"email":"user@example.com","model":"gpt-4o","organizationId":"org_1","timestamp":"2024-01-15T10:30:00Z"}"#
+        );
+        assert_eq!(
+            wire(PayloadCase::Snake, &full),
+            r#"{"ai_input_tokens":100,"ai_output_tokens":50,"ai_requests":1,"email":"user@example.com","model":"gpt-4o","organization_id":"org_1","timestamp":"2024-01-15T10:30:00Z"}"#
+        );
+        assert_eq!(
+            wire(PayloadCase::Camel, &minimal),
+            r#"{"aiRequests":1,"email":"user@example.com"}"#
+        );
+        assert_eq!(
+            wire(PayloadCase::Snake, &minimal),
+            r#"{"ai_requests":1,"email":"user@example.com"}"#
+        );
+    }
+
+    #[test]
+    fn test_batch_increment_request_contract() {
+        let request = BatchIncrementRequest {
+            increments: vec![
+                full_batch_item(),
+                BatchIncrementItem {
+                    email: "other@example.com".to_string(),
+                    ai_input_tokens: Some(7),
+                    ai_output_tokens: None,
+                    ai_requests: None,
+                    model: None,
+                    timestamp: None,
+                    organization_id: None,
+                    estimated: None,
+                },
+            ],
+        };
+
+        assert_eq!(
+            wire(PayloadCase::Camel, &request),
+            concat!(
+                r#"{"increments":["#,
+                r#"{"aiInputTokens":100,"aiOutputTokens":50,"aiRequests":1,"email":"user@example.com","estimated":true,"model":"gpt-4o","organizationId":"org_1","timestamp":"2024-01-15T10:30:00Z"},"#,
+                r#"{"aiInputTokens":7,"email":"other@example.com"}"#,
+                r#"]}"#
+            )
+        );
+        assert_eq!(
+            wire(PayloadCase::Snake, &request),
+            concat!(
+                r#"{"increments":["#,
+                r#"{"ai_input_tokens":100,"ai_output_tokens":50,"ai_requests":1,"email":"user@example.com","estimated":true,"model":"gpt-4o","organization_id":"org_1","timestamp":"2024-01-15T10:30:00Z"},"#,
+                r#"{"ai_input_tokens":7,"email":"other@example.com"}"#,
+                r#"]}"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_outbound_payloads_read_back_in_either_case() {
+        let request = BatchIncrementRequest {
+            increments: vec![full_batch_item()],
+        };
+        for case in [PayloadCase::Camel, PayloadCase::Snake] {
+            let parsed: BatchIncrementRequest =
+                serde_json::from_value(case.to_value(&request).unwrap()).unwrap();
+            assert_eq!(
+                serde_json::to_value(&parsed).unwrap(),
+                serde_json::to_value(&request).unwrap(),
+                "{:?}",
+                case
+            );
+        }
+    }
 }
@@ -0,0 +1,171 @@
+//! Batch-increment payload negotiation
+//!
+//! Zion advertises the optional batch item fields it accepts at
+//! `GET /api/v1/meta`. Fields it doesn't list are stripped before sending, so
+//! a Sentinel release that records more detail can still report usage to an
+//! older Zion. When the meta endpoint can't be read we assume the legacy
+//! minimal payload (email and the three counters).
+
+use chrono::Utc;
+use serde::Serialize;
+
+use crate::zion::models::{BatchIncrementItem, MetaData};
+
+/// Capability for `model` on batch items
+pub const BATCH_MODEL: &str = "batch.model";
+/// Capability for `timestamp` on batch items
+pub const BATCH_TIMESTAMP: &str = "batch.timestamp";
+/// Capability for `organizationId` on batch items
+pub const BATCH_ORGANIZATION: &str = "batch.organization";
+/// Capability for the `estimated` marker on batch items
+pub const BATCH_ESTIMATED: &str = "batch.estimated";
+
+/// Clears a field on a batch item, returning whether it was set
+type StripField = fn(&mut BatchIncrementItem) -> bool;
+
+/// Optional batch fields: (capability, wire name, strip)
+const OPTIONAL_BATCH_FIELDS: [(&str, &str, StripField); 4] = [
+    (BATCH_MODEL, "model", |item| item.model.take().is_some()),
+    (BATCH_TIMESTAMP, "timestamp", |item| item.timestamp.take().is_some()),
+    (BATCH_ORGANIZATION, "organizationId", |item| {
+        item.organization_id.take().is_some()
+    }),
+    (BATCH_ESTIMATED, "estimated", |item| item.estimated.take().is_some()),
+];
+
+/// Where a capability set came from
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CapabilitySource {
+    /// Read from Zion's meta endpoint
+    Meta,
+    /// Meta endpoint unavailable; legacy minimal payload
+    Legacy,
+}
+
+/// Capabilities negotiated with Zion
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ZionCapabilities {
+    pub source: CapabilitySource,
+    /// When the meta endpoint was read (RFC 3339)
+    pub fetched_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_version: Option<String>,
+    /// Capabilities as advertised, including ones Sentinel doesn't use
+    pub capabilities: Vec<String>,
+    /// Optional batch item fields that will be sent
+    pub batch_fields: Vec<&'static str>,
+    /// Why the meta endpoint couldn't be read
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ZionCapabilities {
+    /// Capabilities advertised by the meta endpoint
+    pub fn from_meta(meta: MetaData) -> Self {
+        Self::new(CapabilitySource::Meta, Some(meta.api_version), meta.capabilities, None)
+    }
+
+    /// Legacy minimal payload, used when the meta endpoint fails
+    pub fn legacy(error: String) -> Self {
+        Self::new(CapabilitySource::Legacy, None, Vec::new(), Some(error))
+    }
+
+    fn new(
+        source: CapabilitySource,
+        api_version: Option<String>,
+        capabilities: Vec<String>,
+        error: Option<String>,
+    ) -> Self {
+        let batch_fields = OPTIONAL_BATCH_FIELDS
+            .iter()
+            .filter(|(capability, _, _)| capabilities.iter().any(|c| c == capability))
+            .map(|(_, field, _)| *field)
+            .collect();
+        Self {
+            source,
+            fetched_at: Utc::now().to_rfc3339(),
+            api_version,
+            capabilities,
+            batch_fields,
+            error,
+        }
+    }
+
+    /// Strip optional fields Zion didn't advertise
+    ///
+    /// Returns the wire names of fields that were set on some item and removed.
+    pub fn shape_batch(&self, items: &mut [BatchIncrementItem]) -> Vec<&'static str> {
+        let mut dropped = Vec::new();
+        for (_, field, strip) in &OPTIONAL_BATCH_FIELDS {
+            if self.batch_fields.contains(field) {
+                continue;
+            }
+            let mut any = false;
+            for item in items.iter_mut() {
+                any |= strip(item);
+            }
+            if any {
+                dropped.push(*field);
+            }
+        }
+        dropped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item() -> BatchIncrementItem {
+        BatchIncrementItem {
+            email: "user@example.com".to_string(),
+            ai_input_tokens: Some(10),
+            ai_output_tokens: Some(5),
+            ai_requests: Some(1),
+            model: Some("gpt-4o".to_string()),
+            timestamp: Some("2024-01-01T00:00:00Z".to_string()),
+            organization_id: None,
+            estimated: Some(true),
+        }
+    }
+
+    #[test]
+    fn test_shape_keeps_advertised_fields() {
+        let capabilities = ZionCapabilities::from_meta(MetaData {
+            api_version: "2".to_string(),
+            capabilities: vec![BATCH_MODEL.to_string(), "batch.future".to_string()],
+        });
+        assert_eq!(capabilities.batch_fields, vec!["model"]);
+
+        let mut items = vec![item()];
+        // organizationId was never set, so it isn't reported as dropped
+        assert_eq!(capabilities.shape_batch(&mut items), vec!["timestamp", "estimated"]);
+        assert_eq!(items[0].model.as_deref(), Some("gpt-4o"));
+        assert!(items[0].timestamp.is_none());
+        assert!(items[0].estimated.is_none());
+        assert_eq!(items[0].ai_input_tokens, Some(10));
+    }
+
+    #[test]
+    fn test_legacy_sends_minimal_payload() {
+        let capabilities = ZionCapabilities::legacy("404".to_string());
+        assert!(capabilities.batch_fields.is_empty());
+
+        let mut items = vec![item()];
+        assert_eq!(
+            capabilities.shape_batch(&mut items),
+            vec!["model", "timestamp", "estimated"]
+        );
+        let payload = serde_json::to_value(&items[0]).unwrap();
+        assert_eq!(
+            payload,
+            serde_json::json!({
+                "email": "user@example.com",
+                "aiInputTokens": 10,
+                "aiOutputTokens": 5,
+                "aiRequests": 1
+            })
+        );
+    }
+}
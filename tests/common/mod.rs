@@ -14,52 +14,10 @@ use wiremock::{MockServer, Mock, ResponseTemplate};
 use wiremock::matchers::{method, path, path_regex, header};
 
 
-/// Test configuration constants
-pub mod constants {
-    /// Default test API key for Zion
-    pub const TEST_ZION_API_KEY: &str = "test-zion-api-key";
-    /// Default test API key for OpenAI
-    pub const TEST_OPENAI_API_KEY: &str = "test-openai-api-key";
-    /// Default test JWT token
-    pub const TEST_JWT_TOKEN: &str = "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9.eyJzdWIiOiJ1c2VyXzEyMyIsImVtYWlsIjoidGVzdEB0ZXN0LmNvbSJ9.test";
-    /// Test user ID
-    pub const TEST_USER_ID: &str = "user_123";
-    /// Test external ID
-    pub const TEST_EXTERNAL_ID: &str = "ext_123";
-    /// Test email
-    pub const TEST_EMAIL: &str = "test@test.com";
-}
+/// Test configuration constants (shared with the library's `testing` module)
+pub use sentinel::testing::constants;
 
-/// Test configuration that mirrors the real Config
-#[derive(Debug, Clone)]
-pub struct TestConfig {
-    pub host: String,
-    pub port: u16,
-    pub redis_url: String,
-    pub zion_api_url: String,
-    pub zion_api_key: String,
-    pub openai_api_url: String,
-    pub openai_api_key: String,
-    pub cache_ttl_seconds: u64,
-    pub jwt_cache_ttl_seconds: u64,
-}
-
-impl TestConfig {
-    /// Create a test config with mock server URLs
-    pub fn new(zion_url: &str, openai_url: &str) -> Self {
-        Self {
-            host: "127.0.0.1".to_string(),
-            port: 0, // Let OS assign port
-            redis_url: "redis://localhost:6379".to_string(),
-            zion_api_url: zion_url.to_string(),
-            zion_api_key: constants::TEST_ZION_API_KEY.to_string(),
-            openai_api_url: openai_url.to_string(),
-            openai_api_key: constants::TEST_OPENAI_API_KEY.to_string(),
-            cache_ttl_seconds: 300,
-            jwt_cache_ttl_seconds: 300,
-        }
-    }
-}
+use sentinel::testing::StreamScript;
 
 /// Mock Zion API responses
 pub mod zion_mocks {
@@ -210,23 +168,9 @@ pub mod openai_mocks {
 
     /// Create a mock for streaming chat completions
     pub async fn mock_chat_completions_streaming(server: &MockServer) {
-        // SSE format streaming response
-        let stream_data = concat!(
-            "data: {\"id\":\"chatcmpl-test123\",\"object\":\"chat.completion.chunk\",\"created\":1706745600,\"model\":\"gpt-4o\",\"choices\":[{\"index\":0,\"delta\":{\"role\":\"assistant\"},\"finish_reason\":null}]}\n\n",
-            "data: {\"id\":\"chatcmpl-test123\",\"object\":\"chat.completion.chunk\",\"created\":1706745600,\"model\":\"gpt-4o\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hello\"},\"finish_reason\":null}]}\n\n",
-            "data: {\"id\":\"chatcmpl-test123\",\"object\":\"chat.completion.chunk\",\"created\":1706745600,\"model\":\"gpt-4o\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"!\"},\"finish_reason\":null}]}\n\n",
-            "data: {\"id\":\"chatcmpl-test123\",\"object\":\"chat.completion.chunk\",\"created\":1706745600,\"model\":\"gpt-4o\",\"choices\":[{\"index\":0,\"delta\":{},\"finish_reason\":\"stop\"}]}\n\n",
-            "data: [DONE]\n\n"
-        );
-
         Mock::given(method("POST"))
             .and(path("/chat/completions"))
-            .respond_with(
-                ResponseTemplate::new(200)
-                    .set_body_string(stream_data)
-                    .insert_header("content-type", "text/event-stream")
-                    .insert_header("cache-control", "no-cache")
-            )
+            .respond_with(StreamScript::new("gpt-4o").text("Hello!").response())
             .mount(server)
             .await;
     }
@@ -288,8 +232,8 @@ pub mod test_data {
 // =============================================================================
 
 use sentinel::{
-    AppState, Config, ZionClient, OpenAIProvider, BatchingUsageTracker,
-    proxy::AiProvider, routes,
+    OpenAIProvider,
+    proxy::AiProvider, routes, testing,
 };
 use crate::mocks::{openai::MockOpenAI, zion::MockZionServer};
 use tokio::time::Instant;
@@ -340,44 +284,15 @@ impl TokenTrackingTestHarness {
 
         // Create config pointing to mocks
         // Note: OpenAI URL needs /v1 suffix to match real API structure
-        let config = Config {
-            host: "127.0.0.1".to_string(),
-            port: 0,
-            redis_url: "redis://localhost:6379".to_string(), // Not used in test mode
-            zion_api_url: zion.uri(),
-            zion_api_key: constants::TEST_ZION_API_KEY.to_string(),
-            openai_api_url: format!("{}/v1", openai.uri()),
-            openai_api_key: Some(constants::TEST_OPENAI_API_KEY.to_string()),
-            cache_ttl_seconds: 60,
-            jwt_cache_ttl_seconds: 60,
-            session_ttl_seconds: 86400, // 24 hours for session stickiness
-            tier_config_ttl_seconds: 60, // 1 minute for tests
-            debug_enabled: false,
-        };
-
-        // Create HTTP client
-        let http_client = reqwest::Client::new();
-
-        // Create Zion client pointing to mock
-        let zion_client = Arc::new(ZionClient::new(http_client.clone(), &config));
-
-        // Create batching tracker (test version without Redis retry)
-        let batching_tracker = Arc::new(BatchingUsageTracker::new_for_testing(zion_client.clone()));
+        let config = testing::test_config(&zion.uri(), &format!("{}/v1", openai.uri()));
 
         // Create AI provider pointing to mock
         let ai_provider: Arc<dyn AiProvider> = Arc::new(
-            OpenAIProvider::new(http_client, &config)
+            OpenAIProvider::new(reqwest::Client::new(), &config)
         );
 
         // Create app state with in-memory cache (no Redis required)
-        let state = Arc::new(
-            AppState::new_for_testing(
-                config,
-                zion_client,
-                ai_provider,
-                batching_tracker,
-            ).await
-        );
+        let state = testing::test_state(config, ai_provider).await;
 
         // Create router
         let app = routes::create_router(state);
@@ -414,21 +329,13 @@ impl TokenTrackingTestHarness {
     ///
     /// Extracts the increments array from the request body.
     pub fn parse_batch_payload(request: &wiremock::Request) -> Vec<serde_json::Value> {
-        let body: serde_json::Value = serde_json::from_slice(&request.body)
-            .expect("Failed to parse batch increment request body");
-        body["increments"]
-            .as_array()
-            .cloned()
-            .unwrap_or_default()
+        testing::parse_batch_payload(request)
     }
 
     /// Extract token counts from a batch increment item
     ///
     /// Returns (input_tokens, output_tokens, requests)
     pub fn extract_token_counts(item: &serde_json::Value) -> (i64, i64, i64) {
-        let input = item["aiInputTokens"].as_i64().unwrap_or(0);
-        let output = item["aiOutputTokens"].as_i64().unwrap_or(0);
-        let requests = item["aiRequests"].as_i64().unwrap_or(0);
-        (input, output, requests)
+        testing::extract_token_counts(item)
     }
 }
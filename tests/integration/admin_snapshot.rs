@@ -0,0 +1,114 @@
+//! Admin traffic snapshot tests
+//!
+//! A slow mock stream is held open while `/admin/snapshot` is called from a
+//! second client; the stream must be listed with its endpoint and a
+//! plausible elapsed time, and must disappear once its body has been sent.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::header;
+use axum_test::TestServer;
+use serde_json::{json, Value};
+
+use sentinel::testing::{constants, MockAiProvider, MockEndpoint, StreamScript, TestHarness};
+
+const ADMIN_KEY: &str = "admin-secret";
+const CHUNK_DELAY: Duration = Duration::from_millis(150);
+
+async fn snapshot(harness: &TestHarness) -> Value {
+    let server = TestServer::new(harness.router()).unwrap();
+    let response = server
+        .get("/admin/snapshot")
+        .add_header("X-Admin-Key".parse().unwrap(), ADMIN_KEY.parse().unwrap())
+        .await;
+    response.assert_status_ok();
+    response.json()
+}
+
+#[tokio::test]
+async fn test_snapshot_lists_open_stream() {
+    let provider = Arc::new(
+        MockAiProvider::new()
+            .with_chunk_delay(CHUNK_DELAY)
+            .with_reply(
+                MockEndpoint::ChatCompletions,
+                StreamScript::new("gpt-4o-mini")
+                    .text("one two three four five six")
+                    .reply(),
+            ),
+    );
+    let harness = TestHarness::with_config(provider, |config| {
+        config.server.admin_api_key = Some(ADMIN_KEY.to_string());
+    })
+    .await;
+
+    let client = TestServer::new(harness.router()).unwrap();
+    let stream = async {
+        client
+            .post("/v1/chat/completions")
+            .add_header(
+                header::AUTHORIZATION,
+                format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                    .parse()
+                    .unwrap(),
+            )
+            .json(&json!({
+                "model": "gpt-4o-mini",
+                "stream": true,
+                "messages": [{"role": "user", "content": "Count to six"}]
+            }))
+            .await
+    };
+    let during = async {
+        tokio::time::sleep(CHUNK_DELAY * 3).await;
+        snapshot(&harness).await
+    };
+    let (response, report) = tokio::join!(stream, during);
+    response.assert_status_ok();
+
+    assert_eq!(report["in_flight"]["total"], 1);
+    assert_eq!(
+        report["in_flight"]["by_endpoint"]["/v1/chat/completions"],
+        1
+    );
+    let streams = report["in_flight"]["streams"].as_array().unwrap();
+    assert_eq!(streams.len(), 1);
+    assert_eq!(streams[0]["endpoint"], "/v1/chat/completions");
+    assert_eq!(streams[0]["streaming"], true);
+    assert_eq!(streams[0]["user_hash"].as_str().unwrap().len(), 64);
+    let elapsed = streams[0]["elapsed_ms"].as_u64().unwrap();
+    assert!(
+        (CHUNK_DELAY.as_millis() as u64..5_000).contains(&elapsed),
+        "elapsed_ms = {elapsed}"
+    );
+    assert_eq!(
+        report["in_flight"]["slowest"][0]["request_id"],
+        streams[0]["request_id"]
+    );
+
+    assert_eq!(report["rate_limit"]["window_seconds"], 60);
+    assert_eq!(report["rate_limit"]["checked"], 1);
+    assert_eq!(report["rate_limit"]["rejected"], 0);
+    assert_eq!(report["usage_tracker"]["circuit"], "closed");
+    assert_eq!(report["upstream_circuits"], json!([]));
+    assert_eq!(report["redis_degraded"], false);
+
+    let report = snapshot(&harness).await;
+    assert_eq!(report["in_flight"]["total"], 0);
+    assert_eq!(report["in_flight"]["streams"], json!([]));
+}
+
+#[tokio::test]
+async fn test_snapshot_hidden_without_admin_key() {
+    let harness = TestHarness::with_config(Arc::new(MockAiProvider::new()), |config| {
+        config.server.admin_api_key = Some(ADMIN_KEY.to_string());
+    })
+    .await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    server
+        .get("/admin/snapshot")
+        .await
+        .assert_status_not_found();
+}
@@ -0,0 +1,223 @@
+//! Anthropic provider tests
+//!
+//! Point a real `AnthropicProvider` at a wiremock server speaking the
+//! Messages API and verify `/v1/chat/completions` answers in the OpenAI
+//! format, streaming included, with Anthropic errors rewritten to the OpenAI
+//! error shape and token usage still reported to Zion.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::{header, StatusCode};
+use axum_test::{TestResponse, TestServer};
+use serde_json::{json, Value};
+use wiremock::matchers::{body_partial_json, header as header_eq, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use sentinel::proxy::{redirect, AiProvider, AnthropicProvider};
+use sentinel::routes;
+use sentinel::testing::{
+    constants, extract_token_counts, parse_batch_payload, test_config, test_state,
+    wait_for_batch_requests, zion_stub,
+};
+
+const MODEL: &str = "claude-sonnet-4-5";
+
+struct AnthropicHarness {
+    server: TestServer,
+    anthropic: MockServer,
+    zion: MockServer,
+}
+
+async fn harness() -> AnthropicHarness {
+    let zion = zion_stub().await;
+    let anthropic = MockServer::start().await;
+
+    let mut config = test_config(&zion.uri(), "http://openai.invalid/v1");
+    config.provider.anthropic_api_url = format!("{}/v1", anthropic.uri());
+    config.provider.anthropic_api_key = Some("sk-ant-test".to_string());
+    let provider: Arc<dyn AiProvider> = Arc::new(AnthropicProvider::new(
+        redirect::provider_client().unwrap(),
+        &config,
+    ));
+    let state = test_state(config, provider).await;
+    let server = TestServer::new(routes::create_router(state)).unwrap();
+
+    AnthropicHarness {
+        server,
+        anthropic,
+        zion,
+    }
+}
+
+async fn chat(harness: &AnthropicHarness, body: Value) -> TestResponse {
+    harness
+        .server
+        .post("/v1/chat/completions")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .json(&body)
+        .await
+}
+
+fn request(stream: bool) -> Value {
+    json!({
+        "model": MODEL,
+        "stream": stream,
+        "messages": [
+            {"role": "system", "content": "Be brief."},
+            {"role": "user", "content": "Hi"}
+        ]
+    })
+}
+
+fn sse(events: &[Value]) -> String {
+    events
+        .iter()
+        .map(|event| {
+            format!(
+                "event: {}\ndata: {}\n\n",
+                event["type"].as_str().unwrap(),
+                event
+            )
+        })
+        .collect()
+}
+
+async fn assert_usage_reported(zion: &MockServer, input: i64, output: i64) {
+    let requests = wait_for_batch_requests(zion, 1, Duration::from_secs(5)).await;
+    assert!(!requests.is_empty(), "Expected a batch-increment request");
+    let (input_tokens, output_tokens, _) =
+        extract_token_counts(&parse_batch_payload(&requests[0])[0]);
+    assert_eq!((input_tokens, output_tokens), (input, output));
+}
+
+#[tokio::test]
+async fn test_chat_completion_through_messages_api() {
+    let harness = harness().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .and(header_eq("x-api-key", "sk-ant-test"))
+        .and(header_eq("anthropic-version", "2023-06-01"))
+        .and(body_partial_json(json!({
+            "model": MODEL,
+            "system": "Be brief.",
+            "max_tokens": 4096,
+            "messages": [{"role": "user", "content": [{"type": "text", "text": "Hi"}]}]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_1",
+            "type": "message",
+            "role": "assistant",
+            "model": MODEL,
+            "content": [{"type": "text", "text": "Hello!"}],
+            "stop_reason": "end_turn",
+            "usage": {"input_tokens": 12, "output_tokens": 4}
+        })))
+        .expect(1)
+        .mount(&harness.anthropic)
+        .await;
+
+    let response = chat(&harness, request(false)).await;
+    response.assert_status_ok();
+    let body: Value = response.json();
+    assert_eq!(body["object"], "chat.completion");
+    assert_eq!(body["choices"][0]["message"]["content"], "Hello!");
+    assert_eq!(body["choices"][0]["finish_reason"], "stop");
+    assert_eq!(body["usage"]["total_tokens"], 16);
+
+    assert_usage_reported(&harness.zion, 12, 4).await;
+}
+
+#[tokio::test]
+async fn test_streaming_is_translated_to_chunks() {
+    let harness = harness().await;
+    let events = sse(&[
+        json!({"type": "message_start", "message": {
+            "id": "msg_1", "model": MODEL, "usage": {"input_tokens": 12, "output_tokens": 1}
+        }}),
+        json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+        json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Hel"}}),
+        json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "lo!"}}),
+        json!({"type": "content_block_stop", "index": 0}),
+        json!({"type": "message_delta", "delta": {"stop_reason": "end_turn"}, "usage": {"output_tokens": 6}}),
+        json!({"type": "message_stop"}),
+    ]);
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .and(body_partial_json(json!({"stream": true})))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(events),
+        )
+        .mount(&harness.anthropic)
+        .await;
+
+    let response = chat(&harness, request(true)).await;
+    response.assert_status_ok();
+    let body = response.text();
+    let chunks: Vec<Value> = body
+        .split("\n\n")
+        .filter_map(|event| event.strip_prefix("data: "))
+        .filter(|data| *data != "[DONE]")
+        .map(|data| serde_json::from_str(data).unwrap())
+        .collect();
+    let text: String = chunks
+        .iter()
+        .filter_map(|chunk| chunk["choices"][0]["delta"]["content"].as_str())
+        .collect();
+    assert_eq!(text, "Hello!");
+    assert!(chunks
+        .iter()
+        .any(|chunk| chunk["choices"][0]["finish_reason"] == "stop"));
+    assert!(body.trim_end().ends_with("data: [DONE]"));
+
+    assert_usage_reported(&harness.zion, 12, 6).await;
+}
+
+#[tokio::test]
+async fn test_anthropic_errors_use_openai_shape() {
+    let harness = harness().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "type": "error",
+            "error": {"type": "invalid_request_error", "message": "max_tokens: too large"}
+        })))
+        .mount(&harness.anthropic)
+        .await;
+
+    let response = chat(&harness, request(false)).await;
+    response.assert_status(StatusCode::BAD_GATEWAY);
+    let body = response.text();
+    assert!(
+        body.contains("Anthropic error 400"),
+        "unexpected body: {}",
+        body
+    );
+    assert!(
+        body.contains(r#"\"type\":\"invalid_request_error\""#),
+        "unexpected body: {}",
+        body
+    );
+    assert!(body.contains("max_tokens: too large"));
+
+    // Endpoints without an Anthropic equivalent are refused
+    let response = harness
+        .server
+        .post("/v1/embeddings")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .json(&json!({"model": "text-embedding-3-small", "input": "Hi"}))
+        .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+}
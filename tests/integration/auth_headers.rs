@@ -0,0 +1,132 @@
+//! Credentials header parsing tests
+//!
+//! The bearer scheme is matched case-insensitively and whitespace is ignored;
+//! duplicate or empty credentials are rejected with specific codes before
+//! Zion is asked to validate anything.
+
+use std::sync::Arc;
+
+use axum::http::{header, HeaderValue, StatusCode};
+use axum_test::{TestResponse, TestServer};
+use serde_json::{json, Value};
+
+use sentinel::testing::{constants, MockAiProvider, MockEndpoint, MockReply, TestHarness};
+
+fn provider() -> Arc<MockAiProvider> {
+    Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_completion("gpt-4o-mini", "Hello", 10, 5),
+    ))
+}
+
+fn body() -> Value {
+    json!({
+        "model": "gpt-4o-mini",
+        "messages": [{"role": "user", "content": "Hi"}]
+    })
+}
+
+fn error_code(response: &TestResponse) -> String {
+    response.json::<Value>()["error"]["code"].as_str().unwrap_or_default().to_string()
+}
+
+/// Number of token validations Zion was asked for
+async fn zion_validations(harness: &TestHarness) -> usize {
+    harness
+        .zion
+        .received_requests()
+        .await
+        .unwrap_or_default()
+        .iter()
+        .filter(|r| r.url.path() == "/api/v1/users/me")
+        .count()
+}
+
+#[tokio::test]
+async fn test_lowercase_bearer_with_whitespace_is_accepted() {
+    let harness = TestHarness::with_provider(provider()).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = server
+        .post("/v1/chat/completions")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("bearer   {} ", constants::TEST_JWT_TOKEN).parse().unwrap(),
+        )
+        .json(&body())
+        .await;
+
+    response.assert_status_ok();
+    let requests = harness.zion.received_requests().await.unwrap();
+    let validation = requests.iter().find(|r| r.url.path() == "/api/v1/users/me").unwrap();
+    assert_eq!(
+        validation.headers.get("authorization").unwrap(),
+        &format!("Bearer {}", constants::TEST_JWT_TOKEN)
+    );
+}
+
+#[tokio::test]
+async fn test_malformed_headers_are_rejected_before_zion() {
+    let harness = TestHarness::with_provider(provider()).await;
+    let server = TestServer::new(harness.router()).unwrap();
+    let bearer: HeaderValue = format!("Bearer {}", constants::TEST_JWT_TOKEN).parse().unwrap();
+
+    let response = server
+        .post("/v1/chat/completions")
+        .add_header(header::AUTHORIZATION, bearer.clone())
+        .add_header(header::AUTHORIZATION, bearer)
+        .json(&body())
+        .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+    assert_eq!(error_code(&response), "MULTIPLE_AUTHORIZATION_HEADERS");
+
+    let response = server
+        .post("/v1/chat/completions")
+        .add_header(header::AUTHORIZATION, "Bearer   ".parse().unwrap())
+        .json(&body())
+        .await;
+    response.assert_status(StatusCode::UNAUTHORIZED);
+    assert_eq!(error_code(&response), "EMPTY_TOKEN");
+
+    let response = server
+        .post("/v1/chat/completions")
+        .add_header(header::AUTHORIZATION, "Basic dXNlcjpwdw==".parse().unwrap())
+        .json(&body())
+        .await;
+    response.assert_status(StatusCode::UNAUTHORIZED);
+    assert_eq!(error_code(&response), "UNSUPPORTED_AUTH_SCHEME");
+
+    let response = server.post("/v1/chat/completions").json(&body()).await;
+    response.assert_status(StatusCode::UNAUTHORIZED);
+    assert_eq!(error_code(&response), "UNAUTHORIZED");
+
+    assert_eq!(zion_validations(&harness).await, 0);
+    assert!(harness.provider.requests().is_empty());
+}
+
+#[tokio::test]
+async fn test_x_api_key_requires_config_flag() {
+    let api_key: HeaderValue = constants::TEST_JWT_TOKEN.parse().unwrap();
+
+    let harness = TestHarness::with_provider(provider()).await;
+    let server = TestServer::new(harness.router()).unwrap();
+    let response = server
+        .post("/v1/chat/completions")
+        .add_header("x-api-key".parse().unwrap(), api_key.clone())
+        .json(&body())
+        .await;
+    response.assert_status(StatusCode::UNAUTHORIZED);
+    assert_eq!(error_code(&response), "UNAUTHORIZED");
+
+    let harness = TestHarness::with_config(provider(), |config| {
+        config.server.allow_x_api_key = true;
+    })
+    .await;
+    let server = TestServer::new(harness.router()).unwrap();
+    let response = server
+        .post("/v1/chat/completions")
+        .add_header("x-api-key".parse().unwrap(), api_key)
+        .json(&body())
+        .await;
+    response.assert_status_ok();
+}
@@ -0,0 +1,149 @@
+//! Auth critical path tests
+//!
+//! Zion is made deliberately slow to check that a known token's validation
+//! and limits fetch overlap without an invalid token waiting for its limits,
+//! and that `AUTH_PATH_BUDGET_MS` cuts a slow lookup off with a 503 while
+//! cached tokens keep working.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::http::{header, StatusCode};
+use axum_test::{TestResponse, TestServer};
+use serde_json::{json, Value};
+use wiremock::matchers::{method, path, path_regex};
+use wiremock::{Mock, ResponseTemplate};
+
+use sentinel::middleware::auth::hash_jwt;
+use sentinel::testing::zion::{limits_body, profile_body};
+use sentinel::testing::{constants, MockAiProvider, MockEndpoint, MockReply, TestHarness};
+
+/// Delay of each slow Zion endpoint
+const ZION_DELAY: Duration = Duration::from_millis(500);
+
+fn provider() -> Arc<MockAiProvider> {
+    Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_completion("gpt-4o-mini", "Hello", 10, 5),
+    ))
+}
+
+async fn chat(server: &TestServer) -> (TestResponse, Duration) {
+    let started = Instant::now();
+    let response = server
+        .post("/v1/chat/completions")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN).parse().unwrap(),
+        )
+        .json(&json!({
+            "model": "gpt-4o-mini",
+            "messages": [{"role": "user", "content": "Hi"}]
+        }))
+        .await;
+    (response, started.elapsed())
+}
+
+/// Answer token validation with `profile` and limits with `limits`, over the stubs
+async fn mount_zion(harness: &TestHarness, profile: ResponseTemplate, limits: ResponseTemplate) {
+    Mock::given(method("GET"))
+        .and(path("/api/v1/users/me"))
+        .respond_with(profile)
+        .with_priority(1)
+        .mount(&harness.zion)
+        .await;
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/api/v1/limits/external/.+$"))
+        .respond_with(limits)
+        .with_priority(1)
+        .mount(&harness.zion)
+        .await;
+}
+
+fn slow(body: Value) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(body).set_delay(ZION_DELAY)
+}
+
+/// Drop the cached profile and limits, as when both expire
+async fn expire_cache(harness: &TestHarness) {
+    let cache = &harness.state.subscription_cache;
+    cache.invalidate_jwt(&hash_jwt(constants::TEST_JWT_TOKEN)).await.unwrap();
+    cache.invalidate_user_limits(constants::TEST_EXTERNAL_ID).await.unwrap();
+}
+
+fn error_code(response: &TestResponse) -> String {
+    response.json::<Value>()["error"]["code"].as_str().unwrap_or_default().to_string()
+}
+
+#[tokio::test]
+async fn test_known_token_validates_and_fetches_limits_together() {
+    let harness = TestHarness::with_provider(provider()).await;
+    let server = TestServer::new(harness.router()).unwrap();
+    mount_zion(&harness, slow(profile_body()), slow(limits_body())).await;
+
+    // A token Sentinel hasn't seen needs its profile before the limits
+    let (response, elapsed) = chat(&server).await;
+    response.assert_status_ok();
+    assert!(elapsed >= ZION_DELAY * 2, "{elapsed:?}");
+
+    // Known now: both lookups overlap
+    expire_cache(&harness).await;
+    let (response, elapsed) = chat(&server).await;
+    response.assert_status_ok();
+    assert!(elapsed >= ZION_DELAY, "{elapsed:?}");
+    assert!(elapsed < ZION_DELAY * 2, "{elapsed:?}");
+}
+
+#[tokio::test]
+async fn test_invalid_token_beats_slow_limits() {
+    let harness = TestHarness::with_provider(provider()).await;
+    let server = TestServer::new(harness.router()).unwrap();
+    chat(&server).await.0.assert_status_ok();
+
+    // The token was revoked, and the limits fetch started alongside hangs
+    expire_cache(&harness).await;
+    mount_zion(
+        &harness,
+        ResponseTemplate::new(401),
+        ResponseTemplate::new(500).set_delay(ZION_DELAY * 4),
+    )
+    .await;
+
+    let (response, elapsed) = chat(&server).await;
+    response.assert_status(StatusCode::UNAUTHORIZED);
+    assert_eq!(error_code(&response), "INVALID_TOKEN");
+    assert!(elapsed < ZION_DELAY * 2, "{elapsed:?}");
+}
+
+#[tokio::test]
+async fn test_budget_cuts_off_slow_zion() {
+    let harness = TestHarness::with_config(provider(), |config| {
+        config.zion.auth_path_budget_ms = 200;
+    })
+    .await;
+    let server = TestServer::new(harness.router()).unwrap();
+    mount_zion(&harness, slow(profile_body()), slow(limits_body())).await;
+
+    let (response, elapsed) = chat(&server).await;
+    response.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(error_code(&response), "auth_backend_slow");
+    assert_eq!(response.json::<Value>()["error"]["retryable"], true);
+    assert_eq!(response.headers()[header::RETRY_AFTER], "1");
+    assert!(elapsed < ZION_DELAY, "{elapsed:?}");
+    assert_eq!(harness.provider.requests_for(MockEndpoint::ChatCompletions).len(), 0);
+}
+
+#[tokio::test]
+async fn test_budget_spares_cached_tokens() {
+    let harness = TestHarness::with_config(provider(), |config| {
+        config.zion.auth_path_budget_ms = 200;
+    })
+    .await;
+    let server = TestServer::new(harness.router()).unwrap();
+    chat(&server).await.0.assert_status_ok();
+
+    // Zion slows down, but the profile and limits are cached
+    mount_zion(&harness, slow(profile_body()), slow(limits_body())).await;
+    let (response, _) = chat(&server).await;
+    response.assert_status_ok();
+}
@@ -0,0 +1,196 @@
+//! Limits cache warming tests
+//!
+//! `POST /admin/cache/warm` loads users' limits through the subscription cache
+//! in the background; once the job completes, those users' requests must not
+//! reach Zion's limits endpoint.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::{header, StatusCode};
+use axum_test::TestServer;
+use chrono::Utc;
+use serde_json::{json, Value};
+use wiremock::matchers::{header as header_matcher, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use sentinel::testing::{MockAiProvider, TestHarness};
+use sentinel::usage::recent::UsageCounts;
+
+const ADMIN_KEY: &str = "admin-secret";
+
+async fn harness() -> TestHarness {
+    TestHarness::with_config(Arc::new(MockAiProvider::new()), |config| {
+        config.server.admin_api_key = Some(ADMIN_KEY.to_string());
+    })
+    .await
+}
+
+fn external_id(i: usize) -> String {
+    format!("warm-user-{}", i)
+}
+
+/// Map `Bearer token-{i}` to `warm-user-{i}` on the Zion profile endpoint
+async fn mount_users(harness: &TestHarness, count: usize) {
+    for i in 0..count {
+        Mock::given(method("GET"))
+            .and(path("/api/v1/users/me"))
+            .and(header_matcher(
+                "Authorization",
+                format!("Bearer token-{}", i).as_str(),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "success": true,
+                "data": {
+                    "id": format!("user-{}", i),
+                    "email": format!("user-{}@test.com", i),
+                    "externalId": external_id(i),
+                    "emailVerified": true,
+                    "createdAt": "2024-01-01T00:00:00Z"
+                }
+            })))
+            .mount(&harness.zion)
+            .await;
+    }
+}
+
+async fn limits_calls(harness: &TestHarness) -> usize {
+    harness
+        .zion
+        .received_requests()
+        .await
+        .unwrap_or_default()
+        .iter()
+        .filter(|r| r.url.path().starts_with("/api/v1/limits/external/"))
+        .count()
+}
+
+async fn start_warm(server: &TestServer, body: Value) -> Value {
+    let response = server
+        .post("/admin/cache/warm")
+        .add_header(
+            header::HeaderName::from_static("x-admin-key"),
+            ADMIN_KEY.parse().unwrap(),
+        )
+        .json(&body)
+        .await;
+    response.assert_status(StatusCode::ACCEPTED);
+    response.json()
+}
+
+async fn wait_for_job(server: &TestServer, job_id: &str) -> Value {
+    for _ in 0..200 {
+        let response = server
+            .get(&format!("/admin/cache/warm/{}", job_id))
+            .add_header(
+                header::HeaderName::from_static("x-admin-key"),
+                ADMIN_KEY.parse().unwrap(),
+            )
+            .await;
+        response.assert_status_ok();
+        let report: Value = response.json();
+        if report["status"] == "completed" {
+            return report;
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    panic!("Warm job {} did not complete", job_id);
+}
+
+#[tokio::test]
+async fn test_warmed_users_make_no_limits_calls() {
+    let harness = harness().await;
+    mount_users(&harness, 50).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let ids: Vec<String> = (0..50).map(external_id).collect();
+    let started = start_warm(&server, json!({"external_ids": ids})).await;
+    assert_eq!(started["total"], 50);
+
+    let report = wait_for_job(&server, started["job_id"].as_str().unwrap()).await;
+    assert_eq!(report["done"], 50);
+    assert_eq!(report["warmed"], 50);
+    assert_eq!(report["failed"], 0);
+    assert_eq!(report["results"][0]["external_id"], "warm-user-0");
+    assert_eq!(report["results"][0]["status"], "warmed");
+    assert_eq!(limits_calls(&harness).await, 50);
+
+    // Auth, rate limiting and /v1/usage all read the cached limits
+    for i in 0..50 {
+        server
+            .get("/v1/usage")
+            .add_header(
+                header::AUTHORIZATION,
+                format!("Bearer token-{}", i).parse().unwrap(),
+            )
+            .await
+            .assert_status_ok();
+    }
+    assert_eq!(limits_calls(&harness).await, 50);
+
+    // Resubmitting the same users is served from the cache
+    let rerun = start_warm(&server, json!({"external_ids": ids})).await;
+    assert_eq!(rerun["job_id"], started["job_id"]);
+    let report = wait_for_job(&server, rerun["job_id"].as_str().unwrap()).await;
+    assert_eq!(report["cached"], 50);
+    assert_eq!(limits_calls(&harness).await, 50);
+}
+
+#[tokio::test]
+async fn test_warm_recent_users_from_local_aggregates() {
+    let harness = harness().await;
+    let server = TestServer::new(harness.router()).unwrap();
+    let usage = UsageCounts {
+        requests: 1,
+        input_tokens: 10,
+        output_tokens: 5,
+    };
+    harness
+        .state
+        .batching_tracker
+        .recent_usage()
+        .record(
+            Utc::now().date_naive(),
+            &[
+                ("active-a".to_string(), usage),
+                ("active-b".to_string(), usage),
+            ],
+        )
+        .await;
+
+    let started = start_warm(&server, json!({"source": "recent", "hours": 24})).await;
+    let report = wait_for_job(&server, started["job_id"].as_str().unwrap()).await;
+    let warmed: Vec<&str> = report["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["external_id"].as_str().unwrap())
+        .collect();
+    assert_eq!(warmed, vec!["active-a", "active-b"]);
+}
+
+#[tokio::test]
+async fn test_warm_rejects_empty_list_and_unknown_job() {
+    let harness = harness().await;
+    let server = TestServer::new(harness.router()).unwrap();
+    let admin_key = || ADMIN_KEY.parse().unwrap();
+
+    server
+        .post("/admin/cache/warm")
+        .add_header(header::HeaderName::from_static("x-admin-key"), admin_key())
+        .json(&json!({"external_ids": []}))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    server
+        .get("/admin/cache/warm/warm_unknown")
+        .add_header(header::HeaderName::from_static("x-admin-key"), admin_key())
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+
+    // Hidden without the admin key
+    server
+        .get("/admin/cache/warm/warm_unknown")
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,225 @@
+//! Fault injection tests (`chaos` feature)
+//!
+//! Rules posted to `/admin/chaos/faults` fail or delay calls to the provider,
+//! Zion or Redis until their TTL runs out. A 100% Zion failure must open the
+//! usage tracker's circuit breaker, and the breaker must close again once the
+//! rule has expired.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::http::{header, HeaderName, HeaderValue};
+use axum_test::{TestResponse, TestServer};
+use serde_json::{json, Value};
+
+use sentinel::testing::{
+    batch_increment_requests, capture_logs, constants, test_config, zion_stub, MockAiProvider,
+    MockEndpoint, MockReply, TestHarness,
+};
+use sentinel::{routes, AppState, BatchingConfig, BatchingUsageTracker, ZionClient};
+
+const ADMIN_KEY: &str = "admin-secret";
+
+fn admin_key() -> (HeaderName, HeaderValue) {
+    (
+        HeaderName::from_static("x-admin-key"),
+        HeaderValue::from_static(ADMIN_KEY),
+    )
+}
+
+fn chat_provider() -> Arc<MockAiProvider> {
+    Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_completion("gpt-4o-mini", "Hello!", 10, 5),
+    ))
+}
+
+async fn send_chat(server: &TestServer) -> TestResponse {
+    server
+        .post("/v1/chat/completions")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .json(&json!({
+            "model": "gpt-4o-mini",
+            "messages": [{"role": "user", "content": "Hi"}]
+        }))
+        .await
+}
+
+async fn add_fault(server: &TestServer, rule: Value) -> TestResponse {
+    let (name, value) = admin_key();
+    server
+        .post("/admin/chaos/faults")
+        .add_header(name, value)
+        .json(&rule)
+        .await
+}
+
+async fn list_faults(server: &TestServer) -> Vec<Value> {
+    let (name, value) = admin_key();
+    let response = server.get("/admin/chaos/faults").add_header(name, value).await;
+    response.assert_status_ok();
+    response.json::<Vec<Value>>()
+}
+
+/// Send chat requests until the tracker's circuit is in `state`
+async fn drive_circuit_to(server: &TestServer, tracker: &BatchingUsageTracker, state: &str) {
+    let start = Instant::now();
+    while tracker.status().circuit != state {
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "circuit stayed {} instead of {}",
+            tracker.status().circuit,
+            state
+        );
+        send_chat(server).await.assert_status_ok();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
+
+#[tokio::test]
+async fn test_zion_outage_opens_breaker_until_rule_expires() {
+    let (_, logs, _guard) = capture_logs("info");
+
+    let zion = zion_stub().await;
+    let mut config = test_config(&zion.uri(), "http://mock-provider.invalid/v1");
+    config.server.chaos_enabled = true;
+    config.server.admin_api_key = Some(ADMIN_KEY.to_string());
+
+    let chaos = Arc::new(sentinel::chaos::ChaosInjector::new());
+    let zion_client =
+        Arc::new(ZionClient::new(reqwest::Client::new(), &config).with_chaos(chaos.clone()));
+    let tracker = Arc::new(BatchingUsageTracker::new_for_testing_with_config(
+        zion_client.clone(),
+        BatchingConfig {
+            circuit_breaker_threshold: 2,
+            circuit_breaker_reset: Duration::from_millis(200),
+            ..BatchingUsageTracker::testing_config()
+        },
+    ));
+    let state = Arc::new(
+        AppState::new_for_testing(config, zion_client, chat_provider(), tracker.clone()).await,
+    );
+    assert!(Arc::ptr_eq(state.chaos.as_ref().unwrap(), &chaos));
+    let server = TestServer::new(routes::create_router(state)).unwrap();
+
+    // Warm the auth and limits caches, so requests keep working while Zion is down
+    send_chat(&server).await.assert_status_ok();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let delivered = batch_increment_requests(&zion).await.len();
+    assert!(delivered >= 1, "Expected a batch-increment request");
+    assert_eq!(tracker.status().circuit, "closed");
+
+    let response = add_fault(
+        &server,
+        json!({"target": "zion", "fault": "error", "rate": 1.0, "ttl_seconds": 1}),
+    )
+    .await;
+    response.assert_status(axum::http::StatusCode::CREATED);
+    let fault: Value = response.json();
+    assert_eq!(fault["target"], "zion");
+    assert_eq!(fault["status"], 503);
+
+    drive_circuit_to(&server, &tracker, "open").await;
+    assert_eq!(
+        batch_increment_requests(&zion).await.len(),
+        delivered,
+        "injected failures must not reach Zion"
+    );
+
+    // Rule expires after its TTL; the next flush probes Zion and closes the circuit
+    let start = Instant::now();
+    while !list_faults(&server).await.is_empty() {
+        assert!(start.elapsed() < Duration::from_secs(3), "rule did not expire");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    drive_circuit_to(&server, &tracker, "closed").await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(batch_increment_requests(&zion).await.len() > delivered);
+
+    // Injected faults are tagged apart from real failures
+    let logs = logs.text();
+    assert!(logs.contains("Injecting chaos fault"));
+    assert!(logs.contains("chaos=true"));
+    assert!(logs.contains("operation=batch_increment"));
+    assert!(logs.contains("injected fault"));
+}
+
+#[tokio::test]
+async fn test_provider_fault_fails_requests_until_removed() {
+    let provider = chat_provider();
+    let harness = TestHarness::with_config(provider.clone(), |config| {
+        config.server.chaos_enabled = true;
+        config.server.admin_api_key = Some(ADMIN_KEY.to_string());
+    })
+    .await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = add_fault(
+        &server,
+        json!({"target": "provider", "fault": "reset", "ttl_seconds": 60}),
+    )
+    .await;
+    response.assert_status(axum::http::StatusCode::CREATED);
+    let id = response.json::<Value>()["id"].as_str().unwrap().to_string();
+    assert_eq!(list_faults(&server).await.len(), 1);
+
+    let response = send_chat(&server).await;
+    assert!(response.status_code().is_server_error());
+    assert!(provider.requests_for(MockEndpoint::ChatCompletions).is_empty());
+
+    let (name, value) = admin_key();
+    let response = server
+        .delete(&format!("/admin/chaos/faults/{}", id))
+        .add_header(name, value)
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.json::<Value>()["removed"], 1);
+    assert!(list_faults(&server).await.is_empty());
+
+    send_chat(&server).await.assert_status_ok();
+    assert_eq!(provider.requests_for(MockEndpoint::ChatCompletions).len(), 1);
+}
+
+#[tokio::test]
+async fn test_fault_endpoints_validate_and_require_enabling() {
+    let harness = TestHarness::with_config(chat_provider(), |config| {
+        config.server.chaos_enabled = true;
+        config.server.admin_api_key = Some(ADMIN_KEY.to_string());
+    })
+    .await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    for rule in [
+        json!({"target": "zion", "fault": "error", "rate": 2.0, "ttl_seconds": 10}),
+        json!({"target": "redis", "fault": "reset", "ttl_seconds": 0}),
+        json!({"target": "provider", "fault": "latency", "ttl_seconds": 10}),
+    ] {
+        add_fault(&server, rule).await.assert_status_bad_request();
+    }
+    assert!(list_faults(&server).await.is_empty());
+
+    let (name, value) = admin_key();
+    server
+        .delete("/admin/chaos/faults/unknown")
+        .add_header(name, value)
+        .await
+        .assert_status_not_found();
+
+    // Without CHAOS_ENABLED there is nothing to inject into
+    let harness = TestHarness::with_config(chat_provider(), |config| {
+        config.server.admin_api_key = Some(ADMIN_KEY.to_string());
+    })
+    .await;
+    let server = TestServer::new(harness.router()).unwrap();
+    add_fault(
+        &server,
+        json!({"target": "zion", "fault": "error", "ttl_seconds": 10}),
+    )
+    .await
+    .assert_status_not_found();
+}
@@ -0,0 +1,127 @@
+//! Strict OpenAI compatibility tests (`COMPAT_MODE=strict`)
+//!
+//! Conformance-style checks: in strict mode `/v1` responses carry no
+//! `X-Sentinel-*` or `X-RateLimit-*` headers, Sentinel extension fields are
+//! refused like unknown OpenAI arguments and error bodies have exactly
+//! OpenAI's fields. `/native` and the default mode keep the extensions.
+
+use std::sync::Arc;
+
+use axum::http::{header, HeaderMap, StatusCode};
+use axum_test::{TestResponse, TestServer};
+use serde_json::{json, Value};
+
+use sentinel::middleware::CompatMode;
+use sentinel::testing::{constants, MockAiProvider, MockEndpoint, MockReply, TestHarness};
+
+async fn start(compat_mode: CompatMode) -> (TestHarness, TestServer) {
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_completion("gpt-4o-mini", "Hello", 10, 5),
+    ));
+    let harness = TestHarness::with_config(provider, |config| {
+        config.server.compat_mode = compat_mode;
+    })
+    .await;
+    let server = TestServer::new(harness.router()).unwrap();
+    (harness, server)
+}
+
+async fn post(server: &TestServer, path: &str, body: Value) -> TestResponse {
+    server
+        .post(path)
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN).parse().unwrap(),
+        )
+        .json(&body)
+        .await
+}
+
+fn chat(key: &str, value: &str) -> Value {
+    json!({key: value, "messages": [{"role": "user", "content": "Hi"}]})
+}
+
+/// Sentinel's extension headers among `headers`
+fn extension_headers(headers: &HeaderMap) -> Vec<String> {
+    headers
+        .keys()
+        .map(|name| name.as_str().to_string())
+        .filter(|name| name.starts_with("x-sentinel-") || name.starts_with("x-ratelimit-"))
+        .collect()
+}
+
+/// Field names of a response's `error` object, sorted
+fn error_fields(response: &TestResponse) -> Vec<String> {
+    let mut fields: Vec<String> = response.json::<Value>()["error"]
+        .as_object()
+        .unwrap()
+        .keys()
+        .cloned()
+        .collect();
+    fields.sort();
+    fields
+}
+
+#[tokio::test]
+async fn test_strict_mode_sends_no_extension_headers() {
+    let (_harness, server) = start(CompatMode::Strict).await;
+
+    let response = post(&server, "/v1/chat/completions", chat("model", "gpt-4o-mini")).await;
+    response.assert_status_ok();
+    assert_eq!(extension_headers(response.headers()), Vec::<String>::new());
+
+    // Sentinel's own API keeps them
+    let response = post(&server, "/native/v1/chat/completions", chat("tier", "simple")).await;
+    response.assert_status_ok();
+    assert!(response.headers().contains_key("x-sentinel-model"));
+    assert!(response.headers().contains_key("x-ratelimit-remaining"));
+}
+
+#[tokio::test]
+async fn test_standard_mode_keeps_extension_headers() {
+    let (_harness, server) = start(CompatMode::Standard).await;
+
+    let response = post(&server, "/v1/chat/completions", chat("model", "gpt-4o-mini")).await;
+    response.assert_status_ok();
+    assert!(response.headers().contains_key("x-ratelimit-remaining"));
+}
+
+#[tokio::test]
+async fn test_strict_mode_refuses_extension_fields() {
+    let (harness, server) = start(CompatMode::Strict).await;
+
+    let mut body = chat("model", "gpt-4o-mini");
+    body["models"] = json!(["gpt-4o"]);
+    let response = post(&server, "/v1/chat/completions", body).await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+    let error = &response.json::<Value>()["error"];
+    assert_eq!(error["message"], "Unrecognized request argument supplied: models");
+    assert_eq!(error["type"], "invalid_request_error");
+    assert_eq!(error["param"], Value::Null);
+    assert_eq!(error_fields(&response), ["code", "message", "param", "type"]);
+    assert_eq!(harness.provider.requests_for(MockEndpoint::ChatCompletions).len(), 0);
+}
+
+#[tokio::test]
+async fn test_strict_mode_error_envelope() {
+    let (_harness, server) = start(CompatMode::Strict).await;
+
+    let response = server
+        .post("/v1/chat/completions")
+        .json(&chat("model", "gpt-4o-mini"))
+        .await;
+    response.assert_status(StatusCode::UNAUTHORIZED);
+    assert_eq!(error_fields(&response), ["code", "message", "param", "type"]);
+    assert_eq!(response.json::<Value>()["error"]["type"], "authentication_error");
+    assert_eq!(extension_headers(response.headers()), Vec::<String>::new());
+
+    // The default mode keeps Sentinel's fields
+    let (_harness, server) = start(CompatMode::Standard).await;
+    let response = server
+        .post("/v1/chat/completions")
+        .json(&chat("model", "gpt-4o-mini"))
+        .await;
+    response.assert_status(StatusCode::UNAUTHORIZED);
+    assert!(error_fields(&response).contains(&"retryable".to_string()));
+}
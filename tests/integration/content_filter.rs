@@ -0,0 +1,145 @@
+//! Response blocklist tests
+//!
+//! With `RESPONSE_BLOCKLIST_JSON` set, responses containing a blocked term
+//! are rejected with `content_blocked`: non-streaming responses outright,
+//! streams with an error event in place of the chunk completing the term.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::{header, StatusCode};
+use axum_test::{TestResponse, TestServer};
+use serde_json::{json, Value};
+
+use sentinel::testing::{
+    constants, extract_token_counts, parse_batch_payload, MockAiProvider, MockEndpoint, MockReply,
+    TestHarness,
+};
+
+const MODEL: &str = "gpt-4o-mini";
+
+const BLOCKLIST: &str =
+    r#"{"block": ["(?i)miracle cure"], "allow": ["(?i)no miracle cure exists"]}"#;
+
+async fn harness(reply: MockReply) -> TestHarness {
+    let provider = Arc::new(MockAiProvider::new().with_reply(MockEndpoint::ChatCompletions, reply));
+    TestHarness::with_config(provider, |config| {
+        config.provider.response_blocklist = Some(BLOCKLIST.parse().unwrap());
+    })
+    .await
+}
+
+/// Stream whose content deltas are sent as separate chunks
+fn stream_of(deltas: &[&str]) -> MockReply {
+    let chunk = |delta: Value, finish_reason: Value| {
+        json!({
+            "id": "chatcmpl-mock",
+            "object": "chat.completion.chunk",
+            "created": 1700000000,
+            "model": MODEL,
+            "choices": [{"index": 0, "delta": delta, "finish_reason": finish_reason}]
+        })
+    };
+    let mut events: Vec<Value> = deltas
+        .iter()
+        .map(|content| chunk(json!({"content": content}), Value::Null))
+        .collect();
+    events.push(chunk(json!({}), json!("stop")));
+    MockReply::sse(events)
+}
+
+async fn post(server: &TestServer, path: &str, body: Value) -> TestResponse {
+    server
+        .post(path)
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .json(&body)
+        .await
+}
+
+fn chat(stream: bool) -> Value {
+    json!({"model": MODEL, "stream": stream, "messages": [{"role": "user", "content": "Hi"}]})
+}
+
+/// Data payloads of an SSE response body
+fn events(body: &str) -> Vec<Value> {
+    body.lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .filter_map(|data| serde_json::from_str::<Value>(data).ok())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_stream_term_split_across_chunks_is_blocked() {
+    let harness = harness(stream_of(&["Try this mira", "cle cure", " today"])).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = post(&server, "/v1/chat/completions", chat(true)).await;
+
+    response.assert_status_ok();
+    let body = response.text();
+    let events = events(&body);
+    assert_eq!(events.len(), 2, "unexpected events: {}", body);
+    assert_eq!(events[0]["choices"][0]["delta"]["content"], "Try this mira");
+    assert_eq!(events[1]["error"]["code"], "content_blocked");
+    assert!(!body.contains("cle cure"));
+    assert!(!body.contains("[DONE]"));
+
+    // Partial usage is still tracked, estimated from what was received
+    let requests = harness
+        .wait_for_batch_requests(1, Duration::from_secs(5))
+        .await;
+    assert!(!requests.is_empty(), "Expected a batch-increment request");
+    assert!(extract_token_counts(&parse_batch_payload(&requests[0])[0]).1 > 0);
+}
+
+#[tokio::test]
+async fn test_native_stream_is_blocked() {
+    let harness = harness(stream_of(&["A miracle ", "cure!"])).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = post(
+        &server,
+        "/native/v1/chat/completions",
+        json!({"tier": "simple", "stream": true, "messages": [{"role": "user", "content": "Hi"}]}),
+    )
+    .await;
+
+    response.assert_status_ok();
+    let events = events(&response.text());
+    assert_eq!(events.last().unwrap()["error"]["code"], "content_blocked");
+}
+
+#[tokio::test]
+async fn test_non_streaming_match_is_blocked() {
+    let reply = MockReply::chat_completion(MODEL, "Here is a Miracle Cure for you", 10, 8);
+    let harness = harness(reply).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = post(&server, "/v1/chat/completions", chat(false)).await;
+
+    response.assert_status(StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS);
+    let body: Value = response.json();
+    assert_eq!(body["error"]["code"], "content_blocked");
+    assert!(!response.text().contains("Miracle Cure"));
+}
+
+#[tokio::test]
+async fn test_allowed_context_passes() {
+    let reply = MockReply::chat_completion(MODEL, "Sadly, no miracle cure exists.", 10, 8);
+    let harness = harness(reply).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = post(&server, "/v1/chat/completions", chat(false)).await;
+
+    response.assert_status_ok();
+    let body: Value = response.json();
+    assert_eq!(
+        body["choices"][0]["message"]["content"],
+        "Sadly, no miracle cure exists."
+    );
+}
@@ -0,0 +1,105 @@
+//! Request content sanitization tests
+//!
+//! NUL bytes and lone surrogate escapes in a client body are removed or
+//! replaced with U+FFFD before the body is parsed, so the provider receives
+//! clean text instead of the client getting a 400. With
+//! `CONTENT_NORMALIZE_NFC`, message text is also normalized to NFC.
+
+use std::sync::Arc;
+
+use axum::http::header;
+use axum_test::{TestRequest, TestServer};
+use serde_json::json;
+
+use sentinel::testing::{constants, MockAiProvider, MockEndpoint, MockReply, TestHarness};
+
+async fn sanitizing_server(normalize_nfc: bool) -> (TestHarness, TestServer) {
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_completion("gpt-4o-mini", "Hello", 10, 5),
+    ));
+    let harness = TestHarness::with_config(provider, |config| {
+        config.provider.content_normalize_nfc = normalize_nfc;
+    })
+    .await;
+    let server = TestServer::new(harness.router()).unwrap();
+    (harness, server)
+}
+
+fn authorized(request: TestRequest) -> TestRequest {
+    request.add_header(
+        header::AUTHORIZATION,
+        format!("Bearer {}", constants::TEST_JWT_TOKEN)
+            .parse()
+            .unwrap(),
+    )
+}
+
+/// Content of the last message the provider received
+fn forwarded_content(harness: &TestHarness) -> serde_json::Value {
+    let requests = harness.provider.requests_for(MockEndpoint::ChatCompletions);
+    assert_eq!(requests.len(), 1);
+    requests[0]["messages"][0]["content"].clone()
+}
+
+#[tokio::test]
+async fn test_bad_bytes_are_cleaned_before_forwarding() {
+    let (harness, server) = sanitizing_server(false).await;
+
+    // serde_json alone rejects the lone surrogate; the NULs would reach the provider
+    let body = "{\"model\": \"gpt-4o-mini\", \"messages\": [{\"role\": \"user\", \
+                \"content\": \"a\\u0000b\u{0}c \\ud800 \\ud83d\\ude00\"}]}";
+    authorized(server.post("/v1/chat/completions"))
+        .content_type("application/json")
+        .bytes(body.to_string().into())
+        .await
+        .assert_status_ok();
+
+    assert_eq!(forwarded_content(&harness), "abc \u{FFFD} \u{1F600}");
+}
+
+#[tokio::test]
+async fn test_nfc_normalization_when_enabled() {
+    let request = json!({
+        "model": "gpt-4o-mini",
+        "messages": [{"role": "user", "content": "Cafe\u{301}"}]
+    });
+
+    let (harness, server) = sanitizing_server(true).await;
+    authorized(server.post("/v1/chat/completions"))
+        .json(&request)
+        .await
+        .assert_status_ok();
+    assert_eq!(forwarded_content(&harness), "Caf\u{e9}");
+
+    // Off by default: the decomposed text is forwarded as sent
+    let (harness, server) = sanitizing_server(false).await;
+    authorized(server.post("/v1/chat/completions"))
+        .json(&request)
+        .await
+        .assert_status_ok();
+    assert_eq!(forwarded_content(&harness), "Cafe\u{301}");
+}
+
+#[tokio::test]
+async fn test_native_content_parts_are_normalized() {
+    let (harness, server) = sanitizing_server(true).await;
+
+    authorized(server.post("/native/v1/chat/completions"))
+        .json(&json!({
+            "tier": "simple",
+            "messages": [{
+                "role": "user",
+                "content": [{"type": "text", "text": "A\u{30a}ngstro\u{308}m\u{0}"}]
+            }]
+        }))
+        .await
+        .assert_status_ok();
+
+    let content = forwarded_content(&harness);
+    let text = content
+        .as_str()
+        .map(str::to_string)
+        .unwrap_or_else(|| content[0]["text"].as_str().unwrap().to_string());
+    assert_eq!(text, "\u{c5}ngstr\u{f6}m");
+}
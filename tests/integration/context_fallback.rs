@@ -0,0 +1,168 @@
+//! Context-length fallback tests
+//!
+//! The mock provider rejects the first request with `context_length_exceeded`;
+//! with `CONTEXT_FALLBACK` enabled the native API must re-issue it once to the
+//! tier's long-context model, flag the response and attribute usage to it.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::{header, StatusCode};
+use axum_test::TestServer;
+use serde_json::json;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use sentinel::testing::{
+    constants, parse_batch_payload, MockAiProvider, MockEndpoint, MockReply, TestHarness,
+};
+
+const PRIMARY: &str = "gpt-4o-mini";
+const LONG_CONTEXT: &str = "gpt-4.1";
+
+fn context_error() -> MockReply {
+    MockReply::Error {
+        status: 400,
+        message: r#"{"error":{"message":"maximum context length exceeded","code":"context_length_exceeded"}}"#
+            .to_string(),
+    }
+}
+
+/// Harness whose simple tier designates `LONG_CONTEXT` as its long-context model
+async fn fallback_harness(provider: Arc<MockAiProvider>, enabled: bool) -> TestHarness {
+    let harness = TestHarness::with_config(provider, |config| {
+        config.provider.context_fallback = enabled;
+    })
+    .await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/v1/tiers/config"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "success": true,
+            "data": {
+                "version": "1.0.0",
+                "updatedAt": "2024-01-01T00:00:00Z",
+                "tiers": {
+                    "simple": [{
+                        "provider": "openai",
+                        "model": PRIMARY,
+                        "relativeCost": 1,
+                        "inputPricePerMillion": 0.15,
+                        "outputPricePerMillion": 0.60
+                    }],
+                    "moderate": [],
+                    "complex": []
+                },
+                "longContextModels": {"simple": LONG_CONTEXT}
+            }
+        })))
+        .mount(&harness.zion)
+        .await;
+
+    harness
+}
+
+async fn send_native(server: &TestServer, stream: bool) -> axum_test::TestResponse {
+    server
+        .post("/native/v1/chat/completions")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN).parse().unwrap(),
+        )
+        .json(&json!({
+            "tier": "simple",
+            "messages": [{"role": "user", "content": "A very long prompt"}],
+            "stream": stream
+        }))
+        .await
+}
+
+/// Model reported on the first batch-increment item
+async fn tracked_model(harness: &TestHarness) -> String {
+    let requests = harness.wait_for_batch_requests(1, Duration::from_secs(2)).await;
+    assert!(!requests.is_empty(), "Expected a batch-increment request");
+    parse_batch_payload(&requests[0])[0]["model"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string()
+}
+
+#[tokio::test]
+async fn test_context_error_falls_back_to_long_context_model() {
+    let provider = Arc::new(
+        MockAiProvider::new()
+            .with_reply(MockEndpoint::ChatCompletions, context_error())
+            .with_reply(
+                MockEndpoint::ChatCompletions,
+                MockReply::chat_completion(LONG_CONTEXT, "Summary", 90_000, 20),
+            ),
+    );
+    let harness = fallback_harness(provider, true).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = send_native(&server, false).await;
+    response.assert_status_ok();
+    assert_eq!(response.header("X-Sentinel-Fallback-Reason"), "context_length");
+    assert_eq!(response.header("X-Sentinel-Model"), LONG_CONTEXT);
+
+    // The identical request was re-issued to the long-context model
+    let requests = harness.provider.requests_for(MockEndpoint::ChatCompletions);
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[1]["model"], LONG_CONTEXT);
+    assert_eq!(requests[1]["messages"], requests[0]["messages"]);
+
+    assert_eq!(tracked_model(&harness).await, LONG_CONTEXT);
+}
+
+#[tokio::test]
+async fn test_streaming_context_error_falls_back_before_any_bytes() {
+    let provider = Arc::new(
+        MockAiProvider::new()
+            .with_reply(MockEndpoint::ChatCompletions, context_error())
+            .with_reply(
+                MockEndpoint::ChatCompletions,
+                MockReply::chat_stream(LONG_CONTEXT, "Summary", Some((90_000, 20))),
+            ),
+    );
+    let harness = fallback_harness(provider, true).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = send_native(&server, true).await;
+    response.assert_status_ok();
+    assert_eq!(response.header("X-Sentinel-Fallback-Reason"), "context_length");
+    assert!(response.text().contains("Summary"));
+
+    assert_eq!(tracked_model(&harness).await, LONG_CONTEXT);
+}
+
+#[tokio::test]
+async fn test_second_context_error_propagates() {
+    let provider = Arc::new(MockAiProvider::new().with_reply(MockEndpoint::ChatCompletions, context_error()));
+    let harness = fallback_harness(provider, true).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = send_native(&server, false).await;
+    response.assert_status(StatusCode::BAD_GATEWAY);
+    assert!(response.text().contains("context_length_exceeded"));
+
+    // One fallback attempt, no loop
+    assert_eq!(harness.provider.requests_for(MockEndpoint::ChatCompletions).len(), 2);
+}
+
+#[tokio::test]
+async fn test_fallback_disabled_by_default() {
+    let provider = Arc::new(
+        MockAiProvider::new()
+            .with_reply(MockEndpoint::ChatCompletions, context_error())
+            .with_reply(
+                MockEndpoint::ChatCompletions,
+                MockReply::chat_completion(LONG_CONTEXT, "Summary", 90_000, 20),
+            ),
+    );
+    let harness = fallback_harness(provider, false).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = send_native(&server, false).await;
+    response.assert_status(StatusCode::BAD_GATEWAY);
+    assert_eq!(harness.provider.requests_for(MockEndpoint::ChatCompletions).len(), 1);
+}
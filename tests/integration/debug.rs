@@ -10,11 +10,9 @@ use serde_json::Value;
 use std::sync::Arc;
 
 use sentinel::{
-    routes, AiProvider, AppState, BatchingUsageTracker, Config, OpenAIProvider,
-    ZionClient,
+    routes, AiProvider, AppState, BatchingUsageTracker, Config, OpenAIProvider, ZionClient,
 };
 
-use crate::common::constants;
 use crate::mocks::{MockOpenAI, MockZionServer};
 
 /// Test harness for debug endpoint tests
@@ -37,20 +35,10 @@ impl DebugTestHarness {
         let zion = MockZionServer::start().await;
 
         // Create config with specified debug_enabled
-        let config = Config {
-            host: "127.0.0.1".to_string(),
-            port: 0,
-            redis_url: "redis://localhost:6379".to_string(), // Not used in test mode
-            zion_api_url: zion.uri(),
-            zion_api_key: constants::TEST_ZION_API_KEY.to_string(),
-            openai_api_url: format!("{}/v1", openai.uri()),
-            openai_api_key: Some(constants::TEST_OPENAI_API_KEY.to_string()),
-            cache_ttl_seconds: 60,
-            jwt_cache_ttl_seconds: 60,
-            session_ttl_seconds: 86400,
-            tier_config_ttl_seconds: 60,
-            debug_enabled,
-        };
+        let mut config = Config::for_tests();
+        config.zion.api_url = zion.uri();
+        config.provider.openai_api_url = format!("{}/v1", openai.uri());
+        config.server.debug_enabled = debug_enabled;
 
         // Create HTTP client
         let http_client = reqwest::Client::new();
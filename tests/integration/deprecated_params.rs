@@ -0,0 +1,210 @@
+//! Deprecated OpenAI parameter tests
+//!
+//! `/v1/chat/completions` requests using `functions`, `function_call` or
+//! `max_tokens` on a reasoning model get `X-Sentinel-Deprecated`, and
+//! `DEPRECATED_PARAMS` decides whether the parameters are forwarded as sent
+//! (`warn`), rewritten to `tools`/`tool_choice`/`max_completion_tokens`
+//! (`translate`) or refused with a 400 (`reject`).
+
+use std::sync::Arc;
+
+use axum::http::{header, StatusCode};
+use axum_test::{TestResponse, TestServer};
+use serde_json::{json, Value};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use sentinel::proxy::deprecated::DeprecatedParams;
+use sentinel::testing::{constants, MockAiProvider, MockEndpoint, MockReply, TestHarness};
+
+const HEADER: &str = "X-Sentinel-Deprecated";
+const REASONING: &str = "o3-mini";
+
+async fn harness(mode: DeprecatedParams) -> (TestHarness, TestServer) {
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_completion("gpt-4o", "Hello!", 10, 5),
+    ));
+    let harness =
+        TestHarness::with_config(provider, |config| config.provider.deprecated_params = mode).await;
+    let server = TestServer::new(harness.router()).unwrap();
+    (harness, server)
+}
+
+/// Flag `o3-mini` as a reasoning model in the tier config
+async fn with_reasoning_model(harness: &TestHarness) {
+    let model = |model: &str, reasoning: bool| {
+        json!({
+            "provider": "openai", "model": model, "relativeCost": 1,
+            "inputPricePerMillion": 1.0, "outputPricePerMillion": 1.0, "reasoning": reasoning
+        })
+    };
+    Mock::given(method("GET"))
+        .and(path("/api/v1/tiers/config"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "success": true,
+            "data": {
+                "version": "1.0.0",
+                "updatedAt": "2024-01-01T00:00:00Z",
+                "tiers": {
+                    "simple": [model(REASONING, true)],
+                    "moderate": [model("gpt-4o", false)],
+                    "complex": []
+                }
+            }
+        })))
+        .mount(&harness.zion)
+        .await;
+}
+
+async fn chat(server: &TestServer, body: Value) -> TestResponse {
+    server
+        .post("/v1/chat/completions")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .json(&body)
+        .await
+}
+
+fn weather() -> Value {
+    json!({"name": "get_weather", "parameters": {"type": "object", "properties": {}}})
+}
+
+fn function_request() -> Value {
+    json!({
+        "model": "gpt-4o",
+        "messages": [{"role": "user", "content": "Weather in Paris?"}],
+        "functions": [weather()],
+        "function_call": {"name": "get_weather"}
+    })
+}
+
+fn forwarded(harness: &TestHarness) -> Vec<Value> {
+    harness.provider.requests_for(MockEndpoint::ChatCompletions)
+}
+
+#[tokio::test]
+async fn test_warn_forwards_functions_as_sent() {
+    let (harness, server) = harness(DeprecatedParams::Warn).await;
+
+    let response = chat(&server, function_request()).await;
+    response.assert_status_ok();
+    assert_eq!(
+        response.header(HEADER),
+        "functions->tools, function_call->tool_choice"
+    );
+
+    let request = &forwarded(&harness)[0];
+    assert_eq!(request["functions"], json!([weather()]));
+    assert_eq!(request["function_call"], json!({"name": "get_weather"}));
+    assert!(request.get("tools").is_none());
+
+    // Modern requests get no header
+    let response = chat(
+        &server,
+        json!({"model": "gpt-4o", "max_tokens": 50, "messages": [{"role": "user", "content": "Hi"}]}),
+    )
+    .await;
+    response.assert_status_ok();
+    assert!(response.maybe_header(HEADER).is_none());
+}
+
+#[tokio::test]
+async fn test_translate_rewrites_to_tools() {
+    let (harness, server) = harness(DeprecatedParams::Translate).await;
+
+    let response = chat(&server, function_request()).await;
+    response.assert_status_ok();
+    assert_eq!(
+        response.header(HEADER),
+        "functions->tools, function_call->tool_choice"
+    );
+
+    let request = &forwarded(&harness)[0];
+    assert!(request.get("functions").is_none());
+    assert!(request.get("function_call").is_none());
+    assert_eq!(
+        request["tools"],
+        json!([{"type": "function", "function": weather()}])
+    );
+    assert_eq!(
+        request["tool_choice"],
+        json!({"type": "function", "function": {"name": "get_weather"}})
+    );
+
+    // `function_call` modes carry over as is
+    let mut body = function_request();
+    body["function_call"] = json!("none");
+    chat(&server, body).await.assert_status_ok();
+    assert_eq!(forwarded(&harness)[1]["tool_choice"], "none");
+
+    // A shape OpenAI never accepted can't be translated
+    let mut body = function_request();
+    body["function_call"] = json!(["get_weather"]);
+    let response = chat(&server, body).await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+    assert!(response.text().contains("function_call"));
+    assert_eq!(forwarded(&harness).len(), 2);
+}
+
+#[tokio::test]
+async fn test_reject_refuses_deprecated_params() {
+    let (harness, server) = harness(DeprecatedParams::Reject).await;
+
+    let response = chat(&server, function_request()).await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.header(HEADER),
+        "functions->tools, function_call->tool_choice"
+    );
+    assert!(response.text().contains("functions (use tools)"));
+
+    let mut body = function_request();
+    body.as_object_mut().unwrap().remove("functions");
+    let response = chat(&server, body).await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+    assert_eq!(response.header(HEADER), "function_call->tool_choice");
+
+    assert!(forwarded(&harness).is_empty());
+}
+
+#[tokio::test]
+async fn test_max_tokens_on_reasoning_models() {
+    let body = json!({
+        "model": REASONING,
+        "max_tokens": 200,
+        "messages": [{"role": "user", "content": "2 + 2?"}]
+    });
+
+    // Still rewritten when warning, as before
+    for mode in [DeprecatedParams::Warn, DeprecatedParams::Translate] {
+        let (harness, server) = harness(mode).await;
+        with_reasoning_model(&harness).await;
+
+        let response = chat(&server, body.clone()).await;
+        response.assert_status_ok();
+        assert_eq!(response.header(HEADER), "max_tokens->max_completion_tokens");
+        let request = &forwarded(&harness)[0];
+        assert_eq!(request["max_completion_tokens"], 200);
+        assert!(request.get("max_tokens").is_none());
+    }
+
+    let (harness, server) = harness(DeprecatedParams::Reject).await;
+    with_reasoning_model(&harness).await;
+    let response = chat(&server, body.clone()).await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+    assert_eq!(response.header(HEADER), "max_tokens->max_completion_tokens");
+    assert!(forwarded(&harness).is_empty());
+
+    // `max_tokens` is still current for other models
+    let mut body = body;
+    body["model"] = json!("gpt-4o");
+    let response = chat(&server, body).await;
+    response.assert_status_ok();
+    assert!(response.maybe_header(HEADER).is_none());
+    assert_eq!(forwarded(&harness)[0]["max_tokens"], 200);
+}
@@ -0,0 +1,168 @@
+//! Exact usage accounting tests
+//!
+//! Accounts in `REQUIRE_EXACT_USAGE` stream natively only as long as the
+//! provider reports usage: a stream ending without it is served but flagged
+//! (metric, audit field and, with `REPORT_ESTIMATED_USAGE`, an `estimated`
+//! marker on the Zion increment). In `strict` mode streaming is refused.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::{header, StatusCode};
+use axum_test::{TestResponse, TestServer};
+use serde_json::{json, Value};
+
+use sentinel::routes::metrics::init_metrics;
+use sentinel::testing::{
+    constants, extract_token_counts, parse_batch_payload, MockAiProvider, MockEndpoint, MockReply,
+    TestHarness,
+};
+use sentinel::usage::ExactUsageMode;
+
+async fn harness(
+    reply: MockReply,
+    mode: ExactUsageMode,
+    report_estimated: bool,
+) -> (TestHarness, TestServer) {
+    init_metrics();
+    let provider = Arc::new(MockAiProvider::new().with_reply(MockEndpoint::ChatCompletions, reply));
+    let harness = TestHarness::with_config(provider, |config| {
+        config.usage.require_exact_usage = vec![constants::TEST_EXTERNAL_ID.to_string()];
+        config.usage.exact_usage_mode = mode;
+        config.usage.report_estimated_usage = report_estimated;
+    })
+    .await;
+    let server = TestServer::new(harness.router()).unwrap();
+    (harness, server)
+}
+
+async fn native_chat(server: &TestServer, stream: bool) -> TestResponse {
+    server
+        .post("/native/v1/chat/completions")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .json(&json!({
+            "messages": [{"role": "user", "content": "Hi"}],
+            "stream": stream
+        }))
+        .await
+}
+
+/// The single batch item Zion received
+async fn tracked_item(harness: &TestHarness) -> Value {
+    let batches = harness
+        .wait_for_batch_requests(1, Duration::from_secs(3))
+        .await;
+    assert!(!batches.is_empty(), "Expected batch-increment request");
+    parse_batch_payload(&batches[0]).remove(0)
+}
+
+async fn estimated_total(server: &TestServer) -> f64 {
+    server
+        .get("/metrics")
+        .await
+        .text()
+        .lines()
+        .filter(|line| line.starts_with("sentinel_estimated_usage_total{"))
+        .filter_map(|line| line.rsplit(' ').next()?.parse::<f64>().ok())
+        .sum()
+}
+
+#[tokio::test]
+async fn test_stream_with_provider_usage_is_exact() {
+    let (harness, server) = harness(
+        MockReply::chat_stream("gpt-4o-mini", "Hello there", Some((20, 9))),
+        ExactUsageMode::Flag,
+        true,
+    )
+    .await;
+
+    native_chat(&server, true).await.assert_status_ok();
+
+    let item = tracked_item(&harness).await;
+    assert_eq!(extract_token_counts(&item), (20, 9, 1));
+    assert!(
+        item.get("estimated").is_none(),
+        "exact usage flagged: {}",
+        item
+    );
+
+    // Usage was requested from the provider
+    let requests = harness.provider.requests_for(MockEndpoint::ChatCompletions);
+    assert_eq!(requests[0]["stream_options"]["include_usage"], true);
+}
+
+#[tokio::test]
+async fn test_stream_without_usage_is_flagged() {
+    let (harness, server) = harness(
+        MockReply::chat_stream("gpt-4o-mini", "Hello there", None),
+        ExactUsageMode::Flag,
+        true,
+    )
+    .await;
+
+    let before = estimated_total(&server).await;
+    let response = native_chat(&server, true).await;
+    response.assert_status_ok();
+    assert!(response.text().contains("[DONE]"));
+
+    let item = tracked_item(&harness).await;
+    assert_eq!(item["estimated"], true);
+    assert_eq!(extract_token_counts(&item).2, 1);
+    assert!(estimated_total(&server).await >= before + 1.0);
+}
+
+#[tokio::test]
+async fn test_estimated_marker_requires_opt_in() {
+    let (harness, server) = harness(
+        MockReply::chat_stream("gpt-4o-mini", "Hello there", None),
+        ExactUsageMode::Flag,
+        false,
+    )
+    .await;
+
+    let before = estimated_total(&server).await;
+    native_chat(&server, true).await.assert_status_ok();
+
+    let item = tracked_item(&harness).await;
+    assert!(
+        item.get("estimated").is_none(),
+        "marker sent without opt-in: {}",
+        item
+    );
+    // Still counted
+    assert!(estimated_total(&server).await >= before + 1.0);
+}
+
+#[tokio::test]
+async fn test_strict_mode_refuses_streaming() {
+    let (harness, server) = harness(
+        MockReply::chat_completion("gpt-4o-mini", "Hello!", 10, 5),
+        ExactUsageMode::Strict,
+        true,
+    )
+    .await;
+
+    let response = native_chat(&server, true).await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+    let body: Value = response.json();
+    assert_eq!(body["error"]["type"], "invalid_request_error");
+    assert!(body["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("exact usage"));
+    assert!(harness
+        .provider
+        .requests_for(MockEndpoint::ChatCompletions)
+        .is_empty());
+
+    // Non-streaming responses always carry usage and are served
+    native_chat(&server, false).await.assert_status_ok();
+    let item = tracked_item(&harness).await;
+    assert_eq!(extract_token_counts(&item), (10, 5, 1));
+    assert!(item.get("estimated").is_none());
+}
@@ -0,0 +1,136 @@
+//! finish_reason tracking tests
+//!
+//! Completed responses are counted in `sentinel_finish_reasons_total` by
+//! endpoint, model and reason, from the choices of non-streaming responses
+//! and the final chunk of streams. A `content_filter` share above
+//! `FINISH_REASON_ALERT_PERCENT` raises the spike alert.
+
+use std::sync::Arc;
+
+use axum::http::header;
+use axum_test::TestServer;
+use serde_json::json;
+
+use sentinel::routes::metrics::init_metrics;
+use sentinel::testing::{
+    constants, MockAiProvider, MockEndpoint, MockReply, StreamScript, TestHarness,
+};
+
+fn completion(model: &str, finish_reason: &str) -> MockReply {
+    MockReply::Json(json!({
+        "id": "chatcmpl-mock",
+        "object": "chat.completion",
+        "created": 1700000000,
+        "model": model,
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": "Hello"},
+            "finish_reason": finish_reason
+        }],
+        "usage": {"prompt_tokens": 5, "completion_tokens": 1, "total_tokens": 6}
+    }))
+}
+
+fn stream(model: &str, finish_reason: &str) -> MockReply {
+    StreamScript::new(model)
+        .text("Hello")
+        .finish_reason(finish_reason)
+        .reply()
+}
+
+async fn harness(replies: Vec<MockReply>) -> TestHarness {
+    init_metrics();
+    let provider = Arc::new(MockAiProvider::new());
+    for reply in replies {
+        provider.push_reply(MockEndpoint::ChatCompletions, reply);
+    }
+    TestHarness::with_config(provider, |config| {
+        config.provider.finish_reason_alert_percent = 40.0;
+        config.provider.finish_reason_min_samples = 4;
+    })
+    .await
+}
+
+async fn chat(server: &TestServer, model: &str, stream: bool) {
+    server
+        .post("/v1/chat/completions")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .json(&json!({
+            "model": model,
+            "stream": stream,
+            "messages": [{"role": "user", "content": "Hi"}]
+        }))
+        .await
+        .assert_status_ok();
+}
+
+/// Value of the finish_reason counter for a model and reason
+fn counter(metrics: &str, model: &str, reason: &str) -> u64 {
+    metrics
+        .lines()
+        .find(|line| {
+            line.starts_with("sentinel_finish_reasons_total{")
+                && line.contains("endpoint=\"chat\"")
+                && line.contains(&format!("model=\"{}\"", model))
+                && line.contains(&format!("reason=\"{}\"", reason))
+        })
+        .and_then(|line| line.rsplit(' ').next())
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(0)
+}
+
+#[tokio::test]
+async fn test_finish_reasons_counted_from_both_modes() {
+    const MODEL: &str = "finish-reason-count-model";
+    let harness = harness(vec![
+        completion(MODEL, "stop"),
+        completion(MODEL, "length"),
+        stream(MODEL, "length"),
+        stream(MODEL, "stop"),
+    ])
+    .await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    chat(&server, MODEL, false).await;
+    chat(&server, MODEL, false).await;
+    // Streams are counted once fully consumed
+    chat(&server, MODEL, true).await;
+    chat(&server, MODEL, true).await;
+
+    let metrics = server.get("/metrics").await.text();
+    assert_eq!(counter(&metrics, MODEL, "stop"), 2, "{}", metrics);
+    assert_eq!(counter(&metrics, MODEL, "length"), 2, "{}", metrics);
+    assert_eq!(counter(&metrics, MODEL, "content_filter"), 0);
+    assert!(!harness.state.finish_reasons.is_alerting());
+}
+
+#[tokio::test]
+async fn test_content_filter_spike_raises_alert() {
+    const MODEL: &str = "finish-reason-spike-model";
+    let harness = harness(vec![
+        completion(MODEL, "stop"),
+        completion(MODEL, "stop"),
+        stream(MODEL, "content_filter"),
+        completion(MODEL, "content_filter"),
+    ])
+    .await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    // 1 of 3 is below the sample minimum
+    for stream in [false, false, true] {
+        chat(&server, MODEL, stream).await;
+    }
+    assert!(!harness.state.finish_reasons.is_alerting());
+
+    // 2 of 4 (50%) is over the 40% threshold
+    chat(&server, MODEL, false).await;
+    assert!(harness.state.finish_reasons.is_alerting());
+
+    let metrics = server.get("/metrics").await.text();
+    assert_eq!(counter(&metrics, MODEL, "content_filter"), 2, "{}", metrics);
+}
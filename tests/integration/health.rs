@@ -12,6 +12,8 @@ use axum::{
 use axum_test::TestServer;
 use serde_json::Value;
 
+use sentinel::testing::TestHarness;
+
 /// Create a minimal router for health endpoint testing.
 ///
 /// Health endpoints don't require Redis for the liveness check,
@@ -274,3 +276,50 @@ async fn test_health_endpoints_accept_get_only() {
     let response = server.post("/health/live").await;
     response.assert_status(StatusCode::METHOD_NOT_ALLOWED);
 }
+
+// =============================================================================
+// Application router
+// =============================================================================
+
+#[tokio::test]
+async fn test_health_reports_build_metadata() {
+    let harness = TestHarness::new().await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = server.get("/health").await;
+    response.assert_status_ok();
+    let json: Value = response.json();
+
+    assert_eq!(json["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(json["build"]["version"], env!("CARGO_PKG_VERSION"));
+    assert!(!json["build"]["git_sha"].as_str().unwrap().is_empty());
+    let built_at = json["build"]["built_at"].as_str().unwrap();
+    assert!(chrono::DateTime::parse_from_rfc3339(built_at).is_ok());
+    assert!(json["build"]["features"].is_array());
+    assert_eq!(json["provider"], "mock");
+    // Nothing has fetched the tier config yet
+    assert!(json["tier_config_version"].is_null());
+}
+
+#[tokio::test]
+async fn test_health_uptime_increases() {
+    let harness = TestHarness::new().await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let first: Value = server.get("/health").await.json();
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+    let second: Value = server.get("/health").await.json();
+
+    assert!(
+        second["uptime_seconds"].as_u64().unwrap() > first["uptime_seconds"].as_u64().unwrap()
+    );
+}
+
+#[tokio::test]
+async fn test_liveness_stays_minimal() {
+    let harness = TestHarness::new().await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let json: Value = server.get("/health/live").await.json();
+    assert_eq!(json, serde_json::json!({"status": "healthy"}));
+}
@@ -0,0 +1,107 @@
+//! Request body rejection tests
+//!
+//! Malformed bodies are rejected with the OpenAI error envelope: `code`
+//! separates syntax errors from schema mismatches, `param` points at the
+//! offending field, and a missing JSON `Content-Type` is a 415.
+
+use axum::http::{header, StatusCode};
+use axum_test::{TestResponse, TestServer};
+use serde_json::{json, Value};
+
+use sentinel::testing::{constants, TestHarness};
+
+async fn post_bytes(server: &TestServer, path: &str, content_type: Option<&str>, body: &str) -> TestResponse {
+    let mut request = server.post(path).add_header(
+        header::AUTHORIZATION,
+        format!("Bearer {}", constants::TEST_JWT_TOKEN).parse().unwrap(),
+    );
+    if let Some(content_type) = content_type {
+        request = request.content_type(content_type);
+    }
+    request.bytes(body.to_string().into()).await
+}
+
+fn error(response: &TestResponse) -> Value {
+    let body: Value = response.json();
+    let error = body["error"].clone();
+    assert_eq!(error["type"], "invalid_request_error");
+    error
+}
+
+#[tokio::test]
+async fn test_syntax_error_reports_invalid_json() {
+    let harness = TestHarness::new().await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = post_bytes(
+        &server,
+        "/v1/chat/completions",
+        Some("application/json"),
+        r#"{"model": "gpt-4o-mini", "messages": ["#,
+    )
+    .await;
+
+    response.assert_status(StatusCode::BAD_REQUEST);
+    let error = error(&response);
+    assert_eq!(error["code"], "invalid_json");
+    assert_eq!(error["param"], Value::Null);
+    assert!(harness.provider.requests().is_empty());
+}
+
+#[tokio::test]
+async fn test_nested_type_error_points_at_field() {
+    let harness = TestHarness::new().await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let body = json!({
+        "model": "gpt-4o-mini",
+        "messages": [
+            {"role": "user", "content": "Hi"},
+            {"role": 5, "content": "Hello"}
+        ]
+    });
+    let response = post_bytes(&server, "/v1/chat/completions", Some("application/json"), &body.to_string()).await;
+
+    response.assert_status(StatusCode::BAD_REQUEST);
+    let error = error(&response);
+    assert_eq!(error["code"], "invalid_type");
+    assert_eq!(error["param"], "messages[1].role");
+    assert!(harness.provider.requests().is_empty());
+}
+
+#[tokio::test]
+async fn test_native_type_error_points_at_field() {
+    let harness = TestHarness::new().await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let body = json!({"messages": [{"role": "user", "content": "Hi"}], "temperature": "warm"});
+    let response = post_bytes(
+        &server,
+        "/native/v1/chat/completions",
+        Some("application/json"),
+        &body.to_string(),
+    )
+    .await;
+
+    response.assert_status(StatusCode::BAD_REQUEST);
+    let error = error(&response);
+    assert_eq!(error["code"], "invalid_type");
+    assert_eq!(error["param"], "temperature");
+}
+
+#[tokio::test]
+async fn test_missing_content_type_is_unsupported_media_type() {
+    let harness = TestHarness::new().await;
+    let server = TestServer::new(harness.router()).unwrap();
+    let body = json!({"model": "gpt-4o-mini", "messages": [{"role": "user", "content": "Hi"}]}).to_string();
+
+    for path in ["/v1/chat/completions", "/native/v1/chat/completions"] {
+        let response = post_bytes(&server, path, None, &body).await;
+        response.assert_status(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(error(&response)["code"], "unsupported_media_type", "{}", path);
+
+        let response = post_bytes(&server, path, Some("text/plain"), &body).await;
+        response.assert_status(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+    assert!(harness.provider.requests().is_empty());
+}
@@ -0,0 +1,88 @@
+//! JSON structural limit tests
+//!
+//! Bodies nested deeper than `JSON_MAX_DEPTH`, with more than `JSON_MAX_KEYS`
+//! object keys or a string over `JSON_MAX_STRING_BYTES` are rejected with a
+//! 400 before they are parsed or forwarded.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::http::{header, StatusCode};
+use axum_test::{TestResponse, TestServer};
+use serde_json::Value;
+
+use sentinel::testing::{constants, MockAiProvider, TestHarness};
+
+async fn harness() -> TestHarness {
+    TestHarness::with_config(Arc::new(MockAiProvider::new()), |config| {
+        config.server.json_max_string_bytes = 1_048_576;
+    })
+    .await
+}
+
+async fn post_raw(server: &TestServer, path: &str, body: String) -> TestResponse {
+    server
+        .post(path)
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .content_type("application/json")
+        .bytes(body.into())
+        .await
+}
+
+fn assert_rejected(response: &TestResponse, code: &str) {
+    response.assert_status(StatusCode::BAD_REQUEST);
+    let body: Value = response.json();
+    assert_eq!(body["error"]["code"], code);
+    assert_eq!(body["error"]["type"], "invalid_request_error");
+}
+
+#[tokio::test]
+async fn test_deeply_nested_tool_parameters_are_rejected() {
+    let harness = harness().await;
+    let server = TestServer::new(harness.router()).unwrap();
+    let nested = format!("{}{}", "[".repeat(10_000), "]".repeat(10_000));
+    let body = format!(
+        r#"{{"model": "gpt-4o", "messages": [{{"role": "user", "content": "Hi"}}],
+            "tools": [{{"type": "function", "function": {{"name": "f", "parameters": {{"x": {}}}}}}}]}}"#,
+        nested
+    );
+
+    let started = Instant::now();
+    let response = post_raw(&server, "/v1/chat/completions", body).await;
+
+    assert_rejected(&response, "json_too_deep");
+    assert!(started.elapsed() < Duration::from_secs(2));
+    assert!(harness.provider.requests().is_empty());
+}
+
+#[tokio::test]
+async fn test_too_many_keys_and_long_strings_are_rejected() {
+    let harness = harness().await;
+    let server = TestServer::new(harness.router()).unwrap();
+    let keys = (0..100_000)
+        .map(|i| format!(r#""k{}": 0"#, i))
+        .collect::<Vec<_>>()
+        .join(",");
+    let many_keys = format!(
+        r#"{{"model": "gpt-4o", "messages": [{{"role": "user", "content": "Hi"}}], "metadata": {{{}}}}}"#,
+        keys
+    );
+    let long_string = format!(
+        r#"{{"model": "gpt-4o", "messages": [{{"role": "user", "content": "{}"}}]}}"#,
+        "a".repeat(4 * 1_048_576)
+    );
+
+    let started = Instant::now();
+    let response = post_raw(&server, "/native/v1/chat/completions", many_keys).await;
+    assert_rejected(&response, "json_too_many_keys");
+    let response = post_raw(&server, "/v1/chat/completions", long_string).await;
+    assert_rejected(&response, "json_string_too_long");
+
+    assert!(started.elapsed() < Duration::from_secs(2));
+    assert!(harness.provider.requests().is_empty());
+}
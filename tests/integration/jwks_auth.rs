@@ -0,0 +1,155 @@
+//! Local JWT verification tests (`ZION_JWKS_URL`)
+//!
+//! The Zion stub also serves a JWKS with the test signing key. Signed tokens
+//! with complete claims must be accepted or rejected without Zion's
+//! `/users/me`; everything else still goes through Zion and its cache.
+
+use std::sync::Arc;
+
+use axum::http::{header, StatusCode};
+use axum_test::{TestResponse, TestServer};
+use serde_json::{json, Value};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use sentinel::middleware::auth::hash_jwt;
+use sentinel::testing::jwks::{jwks_body, sign_token, TEST_JWKS_KID};
+use sentinel::testing::{constants, MockAiProvider, MockEndpoint, MockReply, TestHarness};
+use sentinel::zion::UserProfile;
+
+const JWKS_PATH: &str = "/.well-known/jwks.json";
+
+fn provider() -> Arc<MockAiProvider> {
+    Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_completion("gpt-4o-mini", "Hello", 10, 5),
+    ))
+}
+
+/// Harness verifying tokens against the stub's JWKS, which publishes the test key
+async fn jwks_harness() -> TestHarness {
+    let harness = TestHarness::with_config(provider(), |config| {
+        config.zion.jwks_url = Some(format!("{}{}", config.zion.api_url, JWKS_PATH));
+    })
+    .await;
+    Mock::given(method("GET"))
+        .and(path(JWKS_PATH))
+        .respond_with(ResponseTemplate::new(200).set_body_json(jwks_body(TEST_JWKS_KID)))
+        .mount(&harness.zion)
+        .await;
+    harness
+}
+
+/// Claims of the test user, expiring `exp_offset` seconds from now
+fn claims(exp_offset: i64) -> Value {
+    json!({
+        "sub": constants::TEST_USER_ID,
+        "externalId": constants::TEST_EXTERNAL_ID,
+        "email": constants::TEST_EMAIL,
+        "exp": chrono::Utc::now().timestamp() + exp_offset,
+    })
+}
+
+async fn chat(server: &TestServer, token: &str) -> TestResponse {
+    server
+        .post("/v1/chat/completions")
+        .add_header(header::AUTHORIZATION, format!("Bearer {}", token).parse().unwrap())
+        .json(&json!({
+            "model": "gpt-4o-mini",
+            "messages": [{"role": "user", "content": "Hi"}]
+        }))
+        .await
+}
+
+/// Requests Zion received for `path`
+async fn zion_calls(harness: &TestHarness, path: &str) -> usize {
+    harness
+        .zion
+        .received_requests()
+        .await
+        .unwrap_or_default()
+        .iter()
+        .filter(|r| r.url.path() == path)
+        .count()
+}
+
+#[tokio::test]
+async fn test_signed_token_is_verified_without_zion() {
+    let harness = jwks_harness().await;
+    let server = TestServer::new(harness.router()).unwrap();
+    let token = sign_token(TEST_JWKS_KID, &claims(300));
+
+    chat(&server, &token).await.assert_status_ok();
+    chat(&server, &token).await.assert_status_ok();
+
+    assert_eq!(zion_calls(&harness, "/api/v1/users/me").await, 0);
+    assert_eq!(zion_calls(&harness, JWKS_PATH).await, 1);
+    let limits = format!("/api/v1/limits/external/{}", constants::TEST_EXTERNAL_ID);
+    assert_eq!(zion_calls(&harness, &limits).await, 1);
+}
+
+#[tokio::test]
+async fn test_expired_token_is_rejected_locally() {
+    let harness = jwks_harness().await;
+    let server = TestServer::new(harness.router()).unwrap();
+    let token = sign_token(TEST_JWKS_KID, &claims(-3600));
+
+    // Even with a profile cached from an earlier remote validation
+    let profile: UserProfile =
+        serde_json::from_value(sentinel::testing::zion::profile_body()["data"].clone()).unwrap();
+    harness.state.subscription_cache.set_profile(&hash_jwt(&token), &profile).await.unwrap();
+
+    let response = chat(&server, &token).await;
+    response.assert_status(StatusCode::UNAUTHORIZED);
+    assert_eq!(response.json::<Value>()["error"]["code"], "INVALID_TOKEN");
+    assert_eq!(zion_calls(&harness, "/api/v1/users/me").await, 0);
+    assert_eq!(harness.provider.requests_for(MockEndpoint::ChatCompletions).len(), 0);
+}
+
+#[tokio::test]
+async fn test_unverifiable_tokens_fall_back_to_zion() {
+    let harness = jwks_harness().await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    // No externalId claim: Zion validates, and its profile is cached as before
+    let mut incomplete = claims(300);
+    incomplete.as_object_mut().unwrap().remove("externalId");
+    let token = sign_token(TEST_JWKS_KID, &incomplete);
+    chat(&server, &token).await.assert_status_ok();
+    chat(&server, &token).await.assert_status_ok();
+    assert_eq!(zion_calls(&harness, "/api/v1/users/me").await, 1);
+
+    // Tokens without a key id never reach the JWKS
+    chat(&server, constants::TEST_JWT_TOKEN).await.assert_status_ok();
+    assert_eq!(zion_calls(&harness, "/api/v1/users/me").await, 2);
+    assert_eq!(zion_calls(&harness, JWKS_PATH).await, 1);
+}
+
+#[tokio::test]
+async fn test_unknown_key_id_refetches_jwks() {
+    let harness = TestHarness::with_config(provider(), |config| {
+        config.zion.jwks_url = Some(format!("{}{}", config.zion.api_url, JWKS_PATH));
+    })
+    .await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    // The first fetch still has the key under its previous id
+    Mock::given(method("GET"))
+        .and(path(JWKS_PATH))
+        .respond_with(ResponseTemplate::new(200).set_body_json(jwks_body("previous-key")))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&harness.zion)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(JWKS_PATH))
+        .respond_with(ResponseTemplate::new(200).set_body_json(jwks_body(TEST_JWKS_KID)))
+        .mount(&harness.zion)
+        .await;
+
+    chat(&server, &sign_token("previous-key", &claims(300))).await.assert_status_ok();
+    chat(&server, &sign_token(TEST_JWKS_KID, &claims(300))).await.assert_status_ok();
+
+    assert_eq!(zion_calls(&harness, JWKS_PATH).await, 2);
+    assert_eq!(zion_calls(&harness, "/api/v1/users/me").await, 0);
+}
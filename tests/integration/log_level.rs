@@ -0,0 +1,135 @@
+//! Runtime log level tests
+//!
+//! `PUT /admin/log-level` swaps the reloadable filter in front of every
+//! output layer, so debug events appear as soon as the change is accepted and
+//! disappear again when it reverts. `GET` reports the active filter, invalid
+//! directives are rejected before anything changes, and the endpoints stay
+//! behind the admin key.
+
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use axum_test::{TestResponse, TestServer};
+use serde_json::{json, Value};
+use tracing::subscriber::DefaultGuard;
+
+use sentinel::testing::{capture_logs, test_config, zion_stub, CapturedLogs, MockAiProvider};
+use sentinel::{routes, AppState, BatchingUsageTracker, ZionClient};
+
+const ADMIN_KEY: &str = "admin-secret";
+
+struct LogHarness {
+    server: TestServer,
+    captured: CapturedLogs,
+    _guard: DefaultGuard,
+}
+
+/// Server whose log filter starts at `sentinel=info` and is captured in memory
+async fn harness() -> LogHarness {
+    let zion = zion_stub().await;
+    let mut config = test_config(&zion.uri(), "http://mock-provider.invalid/v1");
+    config.server.admin_api_key = Some(ADMIN_KEY.to_string());
+
+    let zion_client = Arc::new(ZionClient::new(reqwest::Client::new(), &config));
+    let tracker = Arc::new(BatchingUsageTracker::new_for_testing(zion_client.clone()));
+    let provider = Arc::new(MockAiProvider::new());
+    let mut state = AppState::new_for_testing(config, zion_client, provider, tracker).await;
+
+    let (log_level, captured, guard) = capture_logs("sentinel=info");
+    state.log_level = log_level;
+
+    LogHarness {
+        server: TestServer::new(routes::create_router(Arc::new(state))).unwrap(),
+        captured,
+        _guard: guard,
+    }
+}
+
+async fn put_filter(server: &TestServer, body: Value) -> TestResponse {
+    server
+        .put("/admin/log-level")
+        .add_header("X-Admin-Key".parse().unwrap(), ADMIN_KEY.parse().unwrap())
+        .json(&body)
+        .await
+}
+
+async fn get_filter(server: &TestServer) -> Value {
+    let response = server
+        .get("/admin/log-level")
+        .add_header("X-Admin-Key".parse().unwrap(), ADMIN_KEY.parse().unwrap())
+        .await;
+    response.assert_status_ok();
+    response.json()
+}
+
+#[tokio::test]
+async fn test_put_changes_filter_immediately() {
+    let harness = harness().await;
+    assert_eq!(get_filter(&harness.server).await["filter"], "sentinel=info");
+
+    tracing::debug!(target: "sentinel::probe", "before change");
+    let response = put_filter(
+        &harness.server,
+        json!({"filter": "sentinel=debug", "revert_after_seconds": 0}),
+    )
+    .await;
+    response.assert_status_ok();
+    let status: Value = response.json();
+    assert_eq!(status["filter"], "sentinel=debug");
+    assert_eq!(status["startup_filter"], "sentinel=info");
+    assert!(status.get("reverts_at").is_none());
+    tracing::debug!(target: "sentinel::probe", "after change");
+
+    put_filter(&harness.server, json!({"filter": "sentinel=info"}))
+        .await
+        .assert_status_ok();
+    tracing::debug!(target: "sentinel::probe", "after reset");
+
+    let output = harness.captured.text();
+    assert!(!output.contains("before change"));
+    assert!(output.contains("after change"));
+    assert!(!output.contains("after reset"));
+    assert!(output.contains("Changing log filter"));
+}
+
+#[tokio::test]
+async fn test_default_revert_comes_from_config() {
+    let harness = harness().await;
+
+    let status: Value = put_filter(&harness.server, json!({"filter": "sentinel=debug"}))
+        .await
+        .json();
+    // LOG_LEVEL_REVERT_SECONDS defaults to 15 minutes
+    assert!(status["reverts_at"].is_string());
+    assert_eq!(get_filter(&harness.server).await, status);
+}
+
+#[tokio::test]
+async fn test_invalid_filter_is_rejected() {
+    let harness = harness().await;
+
+    let response = put_filter(&harness.server, json!({"filter": "sentinel=loud"})).await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+    assert_eq!(get_filter(&harness.server).await["filter"], "sentinel=info");
+
+    tracing::debug!(target: "sentinel::probe", "still hidden");
+    assert!(!harness.captured.text().contains("still hidden"));
+}
+
+#[tokio::test]
+async fn test_requires_admin_key() {
+    let harness = harness().await;
+
+    harness
+        .server
+        .get("/admin/log-level")
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+    harness
+        .server
+        .put("/admin/log-level")
+        .json(&json!({"filter": "sentinel=debug"}))
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+    assert_eq!(get_filter(&harness.server).await["filter"], "sentinel=info");
+}
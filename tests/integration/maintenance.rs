@@ -0,0 +1,150 @@
+//! Maintenance mode tests
+//!
+//! While maintenance mode is on, model endpoints answer 503 `maintenance` with
+//! `Retry-After` (a single SSE error event for streaming requests) without
+//! reaching the provider, while the health probes keep reporting the instance
+//! as alive and ready. Toggling via `/admin/maintenance` applies to the next
+//! request without a restart.
+
+use std::sync::Arc;
+
+use axum::http::{header, StatusCode};
+use axum_test::TestServer;
+use serde_json::{json, Value};
+
+use sentinel::testing::{constants, MockAiProvider, MockEndpoint, MockReply, TestHarness};
+
+const ADMIN_KEY: &str = "admin-secret";
+const MESSAGE: &str = "Back at 14:00 UTC";
+
+async fn harness(maintenance_mode: bool) -> TestHarness {
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_completion("gpt-4o-mini", "Hello!", 10, 5),
+    ));
+    TestHarness::with_config(provider, |config| {
+        config.server.admin_api_key = Some(ADMIN_KEY.to_string());
+        config.server.maintenance_mode = maintenance_mode;
+        config.server.maintenance_retry_after_seconds = 120;
+    })
+    .await
+}
+
+fn auth() -> header::HeaderValue {
+    format!("Bearer {}", constants::TEST_JWT_TOKEN).parse().unwrap()
+}
+
+async fn chat(server: &TestServer, stream: bool) -> axum_test::TestResponse {
+    server
+        .post("/v1/chat/completions")
+        .add_header(header::AUTHORIZATION, auth())
+        .json(&json!({
+            "model": "gpt-4o-mini",
+            "messages": [{"role": "user", "content": "Hi"}],
+            "stream": stream
+        }))
+        .await
+}
+
+async fn set_maintenance(server: &TestServer, body: Value) -> Value {
+    let response = server
+        .put("/admin/maintenance")
+        .add_header("X-Admin-Key".parse().unwrap(), ADMIN_KEY.parse().unwrap())
+        .json(&body)
+        .await;
+    response.assert_status_ok();
+    response.json()
+}
+
+fn assert_maintenance(response: &axum_test::TestResponse) {
+    response.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.header(header::RETRY_AFTER), "120");
+}
+
+#[tokio::test]
+async fn test_admin_toggle_flips_model_endpoints_without_restart() {
+    let harness = harness(false).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    chat(&server, false).await.assert_status_ok();
+
+    let status = set_maintenance(&server, json!({"enabled": true, "message": MESSAGE})).await;
+    assert_eq!(status["enabled"], true);
+    assert_eq!(status["source"], "override");
+
+    let response = chat(&server, false).await;
+    assert_maintenance(&response);
+    let body: Value = response.json();
+    assert_eq!(body["error"]["code"], "maintenance");
+    assert_eq!(body["error"]["message"], MESSAGE);
+    assert_eq!(body["error"]["retryable"], true);
+
+    let response = server
+        .post("/v1/embeddings")
+        .add_header(header::AUTHORIZATION, auth())
+        .json(&json!({"model": "text-embedding-3-small", "input": "Hi"}))
+        .await;
+    assert_maintenance(&response);
+
+    let response = server
+        .post("/native/v1/chat/completions")
+        .add_header(header::AUTHORIZATION, auth())
+        .json(&json!({"messages": [{"role": "user", "content": "Hi"}]}))
+        .await;
+    assert_maintenance(&response);
+    assert_eq!(response.json::<Value>()["error"]["code"], "maintenance");
+
+    // Only the request sent before maintenance reached the provider
+    assert_eq!(harness.provider.requests_for(MockEndpoint::ChatCompletions).len(), 1);
+
+    set_maintenance(&server, json!({"enabled": false})).await;
+    chat(&server, false).await.assert_status_ok();
+    assert_eq!(harness.provider.requests_for(MockEndpoint::ChatCompletions).len(), 2);
+}
+
+#[tokio::test]
+async fn test_streaming_request_gets_single_sse_error_event() {
+    let harness = harness(true).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = chat(&server, true).await;
+    assert_maintenance(&response);
+    assert_eq!(
+        response.header(header::CONTENT_TYPE),
+        "text/event-stream; charset=utf-8"
+    );
+
+    let text = response.text();
+    let events: Vec<&str> = text.lines().filter(|line| line.starts_with("data: ")).collect();
+    assert_eq!(events.len(), 1);
+    let event: Value = serde_json::from_str(events[0].trim_start_matches("data: ")).unwrap();
+    assert_eq!(event["error"]["code"], "maintenance");
+    assert_eq!(event["error"]["message"], "Scheduled maintenance");
+    assert_eq!(event["error"]["retryable"], true);
+    assert!(event["error"]["retry_after_ms"].as_u64().is_some());
+}
+
+#[tokio::test]
+async fn test_health_probes_report_maintenance_distinctly() {
+    let harness = harness(true).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let live = server.get("/health/live").await;
+    live.assert_status_ok();
+    assert_eq!(live.json::<Value>()["status"], "healthy");
+
+    let ready = server.get("/health/ready").await;
+    ready.assert_status_ok();
+    assert_eq!(ready.json::<Value>()["status"], "maintenance");
+
+    // Clearing the override falls back to MAINTENANCE_MODE, which is on here
+    set_maintenance(&server, json!({"enabled": false})).await;
+    assert_eq!(server.get("/health/ready").await.json::<Value>()["status"], "healthy");
+
+    let response = server
+        .delete("/admin/maintenance")
+        .add_header("X-Admin-Key".parse().unwrap(), ADMIN_KEY.parse().unwrap())
+        .await;
+    assert_eq!(response.json::<Value>()["source"], "config");
+    assert_eq!(server.get("/health/ready").await.json::<Value>()["status"], "maintenance");
+}
@@ -0,0 +1,59 @@
+//! /metrics access tests
+//!
+//! `/metrics` is public unless `METRICS_TOKEN` is set, in which case scrapes
+//! need `Authorization: Bearer <token>`. Constant `METRICS_LABELS` are
+//! covered by the unit tests in `routes::metrics`, since the recorder is
+//! process-wide.
+
+use std::sync::Arc;
+
+use axum::http::{header, StatusCode};
+use axum_test::TestServer;
+
+use sentinel::routes::metrics::{init_metrics, record_request};
+use sentinel::testing::{MockAiProvider, TestHarness};
+
+async fn server(metrics_token: Option<&str>) -> TestServer {
+    init_metrics();
+    record_request("200", "gpt-4o-mini", 0.1);
+    let token = metrics_token.map(str::to_string);
+    let harness = TestHarness::with_config(Arc::new(MockAiProvider::new()), |config| {
+        config.server.metrics_token = token;
+    })
+    .await;
+    TestServer::new(harness.router()).unwrap()
+}
+
+#[tokio::test]
+async fn test_metrics_public_without_token() {
+    let server = server(None).await;
+
+    let response = server.get("/metrics").await;
+    response.assert_status_ok();
+    assert!(response.text().contains("sentinel_requests_total"));
+}
+
+#[tokio::test]
+async fn test_metrics_token_required_when_configured() {
+    let server = server(Some("scrape-token")).await;
+
+    let response = server.get("/metrics").await;
+    response.assert_status(StatusCode::UNAUTHORIZED);
+    assert_eq!(response.header(header::WWW_AUTHENTICATE), "Bearer");
+
+    let response = server
+        .get("/metrics")
+        .add_header(header::AUTHORIZATION, "Bearer wrong-token".parse().unwrap())
+        .await;
+    response.assert_status(StatusCode::UNAUTHORIZED);
+
+    let response = server
+        .get("/metrics")
+        .add_header(
+            header::AUTHORIZATION,
+            "Bearer scrape-token".parse().unwrap(),
+        )
+        .await;
+    response.assert_status_ok();
+    assert!(response.text().contains("sentinel_requests_total"));
+}
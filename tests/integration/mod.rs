@@ -4,11 +4,95 @@
 //! flow through the proxy, including authentication, rate limiting, and AI provider
 //! interactions.
 
+pub mod admin_snapshot;
+pub mod anthropic_provider;
+pub mod auth_headers;
+pub mod auth_path;
+pub mod cache_warm;
 pub mod chat_completions;
+pub mod compat_mode;
+pub mod content_filter;
+pub mod content_sanitization;
+pub mod context_fallback;
 pub mod debug;
+pub mod deprecated_params;
+pub mod exact_usage;
+pub mod finish_reasons;
 pub mod health;
+pub mod json_errors;
+pub mod json_limits;
+pub mod jwks_auth;
+pub mod log_level;
+pub mod logging_opt_out;
+pub mod maintenance;
+pub mod metrics_endpoint;
+pub mod model_allowlist;
+pub mod model_fallback;
+pub mod model_snapshots;
 pub mod models;
 pub mod rate_limiting;
+pub mod reasoning_models;
+pub mod request_complexity;
+pub mod request_conflicts;
+pub mod request_decompression;
+pub mod request_mirror;
+pub mod request_weights;
+pub mod response_headers;
+pub mod session_redis;
+pub mod responses_streaming;
 pub mod token_estimation_accuracy;
+pub mod sse_line_limit;
+pub mod stop_sequences;
+pub mod stream_lock;
+pub mod stream_chunking;
+pub mod stream_options;
+pub mod stream_accept;
+pub mod synthetic_traffic;
+pub mod system_prompt_injection;
 pub mod token_tracking;
+pub mod native_batch;
 pub mod native_chat;
+pub mod native_embeddings;
+pub mod native_fingerprint;
+pub mod native_msgpack;
+pub mod native_routing;
+pub mod native_tokenize;
+pub mod param_bounds;
+pub mod passthrough_headers;
+pub mod payload_sizes;
+pub mod progress_sse;
+pub mod provider_check;
+pub mod provider_failover;
+pub mod provider_override;
+pub mod quarantine;
+pub mod quiet_logs;
+pub mod reasoning_filter;
+pub mod secret_redaction;
+pub mod session_affinity;
+pub mod session_export;
+pub mod sessions;
+pub mod testing_utils;
+pub mod tier_standby;
+pub mod tier_state;
+pub mod token_scopes;
+pub mod upstream_circuit;
+pub mod upstream_headers;
+pub mod upstream_pools;
+pub mod upstream_redirects;
+pub mod upstream_timeout;
+pub mod upstream_user;
+pub mod upstream_validation;
+pub mod usage_aggregates;
+pub mod usage_queue;
+pub mod usage_report;
+pub mod usage_retry_lease;
+pub mod workflow_usage;
+pub mod zion_capabilities;
+pub mod zion_limits;
+pub mod zion_payloads;
+#[cfg(feature = "sigv4")]
+pub mod upstream_signing;
+#[cfg(feature = "ledger")]
+pub mod usage_ledger;
+#[cfg(feature = "chaos")]
+pub mod chaos;
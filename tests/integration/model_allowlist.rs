@@ -0,0 +1,133 @@
+//! Model allowlist tests (Zion `allowedModels`)
+//!
+//! A user whose limits carry `allowedModels` may only request matching
+//! models: the typed `/v1` handlers and the pass-through handler (for JSON
+//! bodies) answer 403 `model_not_allowed` without reaching the provider.
+//! Limits without the field allow every model.
+
+use std::sync::Arc;
+
+use axum::http::{header, StatusCode};
+use axum_test::{TestResponse, TestServer};
+use serde_json::{json, Value};
+use wiremock::matchers::{method, path_regex};
+use wiremock::{Mock, ResponseTemplate};
+
+use sentinel::testing::zion::limits_body;
+use sentinel::testing::{
+    constants, MockAiProvider, MockEndpoint, MockReply, TestHarness, STUB_PRIORITY,
+};
+
+/// Harness whose Zion limits carry `allowed_models` (None: the field is absent)
+async fn start(allowed_models: Option<Value>) -> (TestHarness, TestServer) {
+    let provider = Arc::new(
+        MockAiProvider::new()
+            .with_reply(
+                MockEndpoint::ChatCompletions,
+                MockReply::chat_completion("gpt-4o-mini", "Hello", 10, 5),
+            )
+            .with_reply(MockEndpoint::Passthrough, MockReply::Json(json!({"ok": true}))),
+    );
+    let harness = TestHarness::with_provider(provider).await;
+
+    let mut limits = limits_body();
+    if let Some(allowed_models) = allowed_models {
+        limits["data"]["limits"][0]["allowedModels"] = allowed_models;
+    }
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/api/v1/limits/external/.+$"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(limits))
+        .with_priority(STUB_PRIORITY - 1)
+        .mount(&harness.zion)
+        .await;
+
+    let server = TestServer::new(harness.router()).unwrap();
+    (harness, server)
+}
+
+async fn post(server: &TestServer, path: &str, body: Value) -> TestResponse {
+    server
+        .post(path)
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN).parse().unwrap(),
+        )
+        .json(&body)
+        .await
+}
+
+fn chat(model: &str) -> Value {
+    json!({"model": model, "messages": [{"role": "user", "content": "Hi"}]})
+}
+
+fn assert_not_allowed(response: &TestResponse, model: &str) {
+    response.assert_status(StatusCode::FORBIDDEN);
+    let error = &response.json::<Value>()["error"];
+    assert_eq!(error["code"], "model_not_allowed");
+    assert_eq!(error["retryable"], false);
+    assert!(error["message"].as_str().unwrap().contains(model), "{error}");
+}
+
+#[tokio::test]
+async fn test_typed_endpoints_refuse_other_models() {
+    let (harness, server) = start(Some(json!(["gpt-4o-mini*"]))).await;
+
+    post(&server, "/v1/chat/completions", chat("gpt-4o-mini")).await.assert_status_ok();
+    assert_not_allowed(&post(&server, "/v1/chat/completions", chat("gpt-4o")).await, "gpt-4o");
+    assert_eq!(harness.provider.requests_for(MockEndpoint::ChatCompletions).len(), 1);
+
+    let completion = json!({"model": "gpt-3.5-turbo-instruct", "prompt": "Hi"});
+    let response = post(&server, "/v1/completions", completion).await;
+    assert_not_allowed(&response, "gpt-3.5-turbo-instruct");
+
+    let embedding = json!({"model": "text-embedding-3-large", "input": "Hi"});
+    let response = post(&server, "/v1/embeddings", embedding).await;
+    assert_not_allowed(&response, "text-embedding-3-large");
+
+    let responses = json!({"model": "gpt-4o", "input": [{"role": "user", "content": "Hi"}]});
+    assert_not_allowed(&post(&server, "/v1/responses", responses).await, "gpt-4o");
+
+    assert_eq!(harness.provider.requests_for(MockEndpoint::Completions).len(), 0);
+    assert_eq!(harness.provider.requests_for(MockEndpoint::Embeddings).len(), 0);
+    assert_eq!(harness.provider.requests_for(MockEndpoint::Responses).len(), 0);
+}
+
+#[tokio::test]
+async fn test_fallback_models_are_checked_too() {
+    let (harness, server) = start(Some(json!(["gpt-4o-mini*"]))).await;
+
+    let mut body = chat("gpt-4o-mini");
+    body["models"] = json!(["gpt-4o"]);
+    assert_not_allowed(&post(&server, "/v1/chat/completions", body).await, "gpt-4o");
+    assert_eq!(harness.provider.requests_for(MockEndpoint::ChatCompletions).len(), 0);
+}
+
+#[tokio::test]
+async fn test_passthrough_checks_json_model() {
+    let (harness, server) = start(Some(json!(["gpt-4o-mini*", "dall-e-2"]))).await;
+
+    let image = json!({"model": "dall-e-3", "prompt": "A cat"});
+    assert_not_allowed(&post(&server, "/v1/images/generations", image).await, "dall-e-3");
+
+    // Allowed models and bodies without one are forwarded unchanged
+    let image = json!({"model": "dall-e-2", "prompt": "A cat"});
+    post(&server, "/v1/images/generations", image.clone()).await.assert_status_ok();
+    let moderation = json!({"input": "Hi"});
+    post(&server, "/v1/moderations", moderation).await.assert_status_ok();
+
+    let forwarded = harness.provider.requests_for(MockEndpoint::Passthrough);
+    assert_eq!(forwarded.len(), 2);
+    assert_eq!(forwarded[0]["body"], image);
+}
+
+#[tokio::test]
+async fn test_limits_without_allowed_models_allow_everything() {
+    let (harness, server) = start(None).await;
+
+    post(&server, "/v1/chat/completions", chat("gpt-4o")).await.assert_status_ok();
+    let image = json!({"model": "dall-e-3", "prompt": "A cat"});
+    post(&server, "/v1/images/generations", image).await.assert_status_ok();
+
+    assert_eq!(harness.provider.requests_for(MockEndpoint::ChatCompletions).len(), 1);
+    assert_eq!(harness.provider.requests_for(MockEndpoint::Passthrough).len(), 1);
+}
@@ -0,0 +1,169 @@
+//! Client fallback model tests
+//!
+//! A `/v1/chat/completions` request may list fallback `models`. When the
+//! provider fails the first model with a retryable error the request is
+//! re-issued to the next one; the serving model is reported in
+//! `X-Sentinel-Model`, usage is attributed to it alone, and the failed model
+//! is put in backoff by the health tracker.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::{header, StatusCode};
+use axum_test::{TestResponse, TestServer};
+use serde_json::{json, Value};
+
+use sentinel::testing::{
+    constants, parse_batch_payload, sse_events, MockAiProvider, MockEndpoint, MockReply,
+    TestHarness,
+};
+
+const PRIMARY: &str = "gpt-4o";
+const FALLBACK: &str = "gpt-4o-mini";
+
+fn unavailable() -> MockReply {
+    MockReply::Error {
+        status: 503,
+        message: "Service Unavailable".to_string(),
+    }
+}
+
+async fn harness(first: MockReply, second: MockReply) -> (TestHarness, TestServer) {
+    let provider = Arc::new(
+        MockAiProvider::new()
+            .with_reply(MockEndpoint::ChatCompletions, first)
+            .with_reply(MockEndpoint::ChatCompletions, second),
+    );
+    let harness = TestHarness::with_provider(provider).await;
+    let server = TestServer::new(harness.router()).unwrap();
+    (harness, server)
+}
+
+async fn chat(server: &TestServer, body: Value) -> TestResponse {
+    server
+        .post("/v1/chat/completions")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .json(&body)
+        .await
+}
+
+fn request(stream: bool) -> Value {
+    json!({
+        "model": PRIMARY,
+        "models": [PRIMARY, FALLBACK],
+        "messages": [{"role": "user", "content": "Hi"}],
+        "stream": stream
+    })
+}
+
+/// Models the provider was asked for, in order
+fn requested_models(harness: &TestHarness) -> Vec<String> {
+    harness
+        .provider
+        .requests_for(MockEndpoint::ChatCompletions)
+        .iter()
+        .map(|request| {
+            assert!(request.get("models").is_none(), "models was forwarded");
+            request["model"].as_str().unwrap().to_string()
+        })
+        .collect()
+}
+
+/// Model reported on the first batch-increment item
+async fn tracked_model(harness: &TestHarness) -> String {
+    let requests = harness
+        .wait_for_batch_requests(1, Duration::from_secs(2))
+        .await;
+    assert!(!requests.is_empty(), "Expected a batch-increment request");
+    parse_batch_payload(&requests[0])[0]["model"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string()
+}
+
+#[tokio::test]
+async fn test_failing_model_falls_back_to_next() {
+    let (harness, server) = harness(
+        unavailable(),
+        MockReply::chat_completion(FALLBACK, "Hello!", 10, 5),
+    )
+    .await;
+
+    let response = chat(&server, request(false)).await;
+    response.assert_status_ok();
+    assert_eq!(response.header("X-Sentinel-Model"), FALLBACK);
+    assert_eq!(
+        response.json::<Value>()["choices"][0]["message"]["content"],
+        "Hello!"
+    );
+
+    assert_eq!(requested_models(&harness), vec![PRIMARY, FALLBACK]);
+    assert_eq!(tracked_model(&harness).await, FALLBACK);
+    assert!(!harness.state.health_tracker.is_available("openai", PRIMARY));
+    assert!(harness
+        .state
+        .health_tracker
+        .is_available("openai", FALLBACK));
+}
+
+#[tokio::test]
+async fn test_stream_falls_back_before_it_opens() {
+    let (harness, server) = harness(
+        unavailable(),
+        MockReply::chat_stream(FALLBACK, "Hello world", Some((7, 2))),
+    )
+    .await;
+
+    let response = chat(&server, request(true)).await;
+    response.assert_status_ok();
+    assert_eq!(response.header("X-Sentinel-Model"), FALLBACK);
+    assert!(!sse_events(&response.text()).is_empty());
+
+    assert_eq!(requested_models(&harness), vec![PRIMARY, FALLBACK]);
+    assert_eq!(tracked_model(&harness).await, FALLBACK);
+}
+
+#[tokio::test]
+async fn test_client_errors_are_not_retried() {
+    let (harness, server) = harness(
+        MockReply::Error {
+            status: 400,
+            message: "Invalid request".to_string(),
+        },
+        MockReply::chat_completion(FALLBACK, "Hello!", 10, 5),
+    )
+    .await;
+
+    let response = chat(&server, request(false)).await;
+    assert!(response.status_code().is_server_error());
+    assert_eq!(requested_models(&harness), vec![PRIMARY]);
+    assert!(harness.state.health_tracker.is_available("openai", PRIMARY));
+}
+
+#[tokio::test]
+async fn test_unknown_fallback_models_are_rejected() {
+    let (harness, server) = harness(
+        MockReply::chat_completion(PRIMARY, "Hello!", 10, 5),
+        MockReply::chat_completion(PRIMARY, "Hello!", 10, 5),
+    )
+    .await;
+
+    let mut body = request(false);
+    body["models"] = json!([FALLBACK, "not-a-model"]);
+    let response = chat(&server, body).await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+    assert!(response.text().contains("not-a-model"));
+
+    let mut body = request(false);
+    body["models"] = json!([]);
+    chat(&server, body)
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    assert!(requested_models(&harness).is_empty());
+}
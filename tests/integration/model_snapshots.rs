@@ -0,0 +1,103 @@
+//! Upstream model snapshot tests
+//!
+//! The mock provider reports a dated snapshot for an aliased model; verify the
+//! snapshot is exposed in `X-Sentinel-Upstream-Model`, counted in metrics,
+//! recorded once, and used for usage attribution.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::header;
+use axum_test::TestServer;
+use serde_json::json;
+
+use sentinel::routes::metrics::init_metrics;
+use sentinel::testing::{constants, parse_batch_payload, MockAiProvider, MockEndpoint, MockReply, TestHarness};
+
+const REQUESTED: &str = "gpt-4o";
+const SERVED: &str = "gpt-4o-2024-11-20";
+
+async fn send_chat(server: &TestServer, stream: bool) -> axum_test::TestResponse {
+    let response = server
+        .post("/v1/chat/completions")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN).parse().unwrap(),
+        )
+        .json(&json!({
+            "model": REQUESTED,
+            "messages": [{"role": "user", "content": "Hi"}],
+            "stream": stream
+        }))
+        .await;
+    response.assert_status_ok();
+    response
+}
+
+/// Model reported on the first batch-increment item
+async fn tracked_model(harness: &TestHarness) -> String {
+    let requests = harness.wait_for_batch_requests(1, Duration::from_secs(2)).await;
+    assert!(!requests.is_empty(), "Expected a batch-increment request");
+    let increments = parse_batch_payload(&requests[0]);
+    increments[0]["model"].as_str().unwrap_or_default().to_string()
+}
+
+#[tokio::test]
+async fn test_served_snapshot_exposed_counted_and_tracked() {
+    init_metrics();
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_completion(SERVED, "Hello!", 10, 5),
+    ));
+    let harness = TestHarness::with_provider(provider).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = send_chat(&server, false).await;
+    assert_eq!(response.header("X-Sentinel-Upstream-Model"), SERVED);
+    assert_eq!(tracked_model(&harness).await, SERVED);
+
+    // The sighting was recorded, so it won't be logged again
+    assert!(!harness.state.model_snapshots.observe(REQUESTED, SERVED).await);
+
+    let metrics = server.get("/metrics").await.text();
+    assert!(
+        metrics
+            .lines()
+            .any(|line| line.starts_with("sentinel_model_snapshot{")
+                && line.contains(&format!("requested=\"{}\"", REQUESTED))
+                && line.contains(&format!("served=\"{}\"", SERVED))),
+        "Missing sentinel_model_snapshot series in:\n{}",
+        metrics
+    );
+}
+
+#[tokio::test]
+async fn test_new_snapshot_reported_only_once() {
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_completion(SERVED, "Hello!", 10, 5),
+    ));
+    let harness = TestHarness::with_provider(provider).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    send_chat(&server, false).await;
+    send_chat(&server, false).await;
+
+    // Both requests were served by the same snapshot; a different one is still new
+    assert!(!harness.state.model_snapshots.observe(REQUESTED, SERVED).await);
+    assert!(harness.state.model_snapshots.observe(REQUESTED, "gpt-4o-2025-01-01").await);
+}
+
+#[tokio::test]
+async fn test_streaming_usage_attributed_to_served_snapshot() {
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_stream(SERVED, "Hello!", Some((10, 5))),
+    ));
+    let harness = TestHarness::with_provider(provider).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    send_chat(&server, true).await;
+    assert_eq!(tracked_model(&harness).await, SERVED);
+    assert!(!harness.state.model_snapshots.observe(REQUESTED, SERVED).await);
+}
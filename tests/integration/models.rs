@@ -3,6 +3,7 @@
 //! Tests for the models endpoints:
 //! - GET /v1/models - List available models
 //! - GET /v1/models/:id - Get specific model
+//! - `MODELS_SOURCE` (upstream, static, merged) against the real router
 
 use axum::{
     http::StatusCode,
@@ -332,3 +333,133 @@ async fn test_list_models_includes_anthropic_models() {
 
     assert!(!anthropic_models.is_empty(), "Should have Anthropic models");
 }
+
+mod source {
+    use std::sync::Arc;
+
+    use axum::http::header;
+    use axum_test::{TestResponse, TestServer};
+    use serde_json::{json, Value};
+
+    use sentinel::proxy::static_models::ModelsSource;
+    use sentinel::testing::{constants, MockAiProvider, MockEndpoint, MockReply, TestHarness};
+
+    const STATIC_MODELS: &str = r#"{"object": "list", "data": [
+        {"id": "llama-3-70b", "owned_by": "vllm", "created": 1717000000},
+        {"id": "gpt-4o", "owned_by": "on-prem"}
+    ]}"#;
+
+    fn upstream_listing() -> MockReply {
+        MockReply::Json(json!({
+            "object": "list",
+            "data": [
+                {"id": "gpt-4o", "object": "model", "created": 1706745600, "owned_by": "openai"},
+                {"id": "gpt-4o-mini", "object": "model", "created": 1706745600, "owned_by": "openai"}
+            ]
+        }))
+    }
+
+    async fn start(source: ModelsSource, reply: MockReply) -> (TestServer, Arc<MockAiProvider>) {
+        let provider = Arc::new(MockAiProvider::new().with_reply(MockEndpoint::Models, reply));
+        let harness = TestHarness::with_config(provider.clone(), |config| {
+            config.provider.models_source = source;
+            config.provider.static_models = Some(STATIC_MODELS.parse().unwrap());
+        })
+        .await;
+        (TestServer::new(harness.router()).unwrap(), provider)
+    }
+
+    async fn get(server: &TestServer, path: &str) -> TestResponse {
+        server
+            .get(path)
+            .add_header(
+                header::AUTHORIZATION,
+                format!("Bearer {}", constants::TEST_JWT_TOKEN).parse().unwrap(),
+            )
+            .await
+    }
+
+    fn ids(response: &TestResponse) -> Vec<String> {
+        let body: Value = response.json();
+        assert_eq!(body["object"], "list");
+        body["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|model| model["id"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_static_source_serves_configured_list() {
+        // An upstream without /models
+        let (server, provider) = start(
+            ModelsSource::Static,
+            MockReply::Error {
+                status: 404,
+                message: "Not Found".to_string(),
+            },
+        )
+        .await;
+
+        let response = get(&server, "/v1/models").await;
+        response.assert_status_ok();
+        assert_eq!(ids(&response), vec!["llama-3-70b", "gpt-4o"]);
+
+        let response = get(&server, "/v1/models/llama-3-70b").await;
+        response.assert_status_ok();
+        let model: Value = response.json();
+        assert_eq!(model["object"], "model");
+        assert_eq!(model["owned_by"], "vllm");
+        assert_eq!(model["created"], 1717000000);
+
+        // Unknown ids are a 404, not the built-in fallback list
+        get(&server, "/v1/models/gpt-4o-mini").await.assert_status_not_found();
+        let response = get(&server, "/v1/models/unknown-model").await;
+        response.assert_status_not_found();
+        let body: Value = response.json();
+        assert!(body["error"]["message"].as_str().unwrap().contains("unknown-model"));
+
+        assert!(provider.requests_for(MockEndpoint::Models).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_merged_source_overlays_upstream_list() {
+        let (server, provider) = start(ModelsSource::Merged, upstream_listing()).await;
+
+        let response = get(&server, "/v1/models").await;
+        response.assert_status_ok();
+        assert_eq!(ids(&response), vec!["gpt-4o", "gpt-4o-mini", "llama-3-70b"]);
+        let body: Value = response.json();
+        assert_eq!(body["data"][0]["owned_by"], "on-prem");
+        assert_eq!(provider.requests_for(MockEndpoint::Models).len(), 1);
+
+        // Configured ids resolve locally, the rest through the provider
+        let response = get(&server, "/v1/models/llama-3-70b").await;
+        response.assert_status_ok();
+        assert_eq!(response.json::<Value>()["owned_by"], "vllm");
+        assert_eq!(provider.requests_for(MockEndpoint::Models).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_upstream_source_ignores_static_list() {
+        let (server, provider) = start(ModelsSource::Upstream, upstream_listing()).await;
+
+        let response = get(&server, "/v1/models").await;
+        response.assert_status_ok();
+        assert_eq!(ids(&response), vec!["gpt-4o", "gpt-4o-mini"]);
+        assert_eq!(response.json::<Value>()["data"][0]["owned_by"], "openai");
+
+        // Falls back to the built-in list, which doesn't know the static model
+        let (server, _) = start(
+            ModelsSource::Upstream,
+            MockReply::Error {
+                status: 404,
+                message: "Not Found".to_string(),
+            },
+        )
+        .await;
+        get(&server, "/v1/models/llama-3-70b").await.assert_status_not_found();
+        assert_eq!(provider.requests_for(MockEndpoint::Models).len(), 1);
+    }
+}
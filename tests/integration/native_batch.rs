@@ -0,0 +1,206 @@
+//! Native chat completion batch tests
+//!
+//! `POST /native/v1/chat/completions/batch` answers every item on its own:
+//! valid items get completions, invalid and streaming items get the error
+//! envelope they would have gotten as single requests, in request order.
+//! Each completed item is tracked for usage individually, and the batch
+//! counts once per item for rate limiting.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::{header, StatusCode};
+use axum_test::{TestResponse, TestServer};
+use serde_json::{json, Value};
+
+use sentinel::testing::{
+    constants, extract_token_counts, parse_batch_payload, MockAiProvider, MockEndpoint, MockReply,
+    TestHarness,
+};
+
+async fn harness(max_items: usize) -> (TestHarness, TestServer) {
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_completion("gpt-4o-mini", "Hello!", 10, 5),
+    ));
+    let harness = TestHarness::with_config(provider, |config| {
+        config.provider.native_batch_max_items = max_items;
+        config.provider.native_batch_concurrency = 2;
+    })
+    .await;
+    let server = TestServer::new(harness.router()).unwrap();
+    (harness, server)
+}
+
+async fn post_batch(server: &TestServer, requests: Value) -> TestResponse {
+    server
+        .post("/native/v1/chat/completions/batch")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .json(&json!({ "requests": requests }))
+        .await
+}
+
+fn prompt(text: &str) -> Value {
+    json!({"messages": [{"role": "user", "content": text}]})
+}
+
+fn header_number(response: &TestResponse, name: &str) -> i64 {
+    response.header(name).to_str().unwrap().parse().unwrap()
+}
+
+/// Wait until Zion has received `requests` requests' worth of increments, returning the totals
+async fn zion_totals(harness: &TestHarness, requests: i64) -> (i64, i64, i64) {
+    let deadline = std::time::Instant::now() + Duration::from_secs(5);
+    loop {
+        let totals = harness
+            .wait_for_batch_requests(1, Duration::from_secs(2))
+            .await
+            .iter()
+            .flat_map(parse_batch_payload)
+            .map(|item| extract_token_counts(&item))
+            .fold((0, 0, 0), |acc, (i, o, r)| {
+                (acc.0 + i, acc.1 + o, acc.2 + r)
+            });
+        if totals.2 >= requests || std::time::Instant::now() > deadline {
+            return totals;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
+
+#[tokio::test]
+async fn test_mixed_batch_partial_success() {
+    let (harness, server) = harness(10).await;
+
+    let response = post_batch(
+        &server,
+        json!([
+            prompt("First"),
+            {"messages": [{"role": "user", "content": "Typo"}], "temprature": 0.2},
+            prompt("Third"),
+            {"messages": [{"role": "user", "content": "Stream"}], "stream": true}
+        ]),
+    )
+    .await;
+    response.assert_status_ok();
+
+    let results = response.json::<Value>()["results"]
+        .as_array()
+        .unwrap()
+        .clone();
+    let statuses: Vec<u64> = results
+        .iter()
+        .map(|result| result["status"].as_u64().unwrap())
+        .collect();
+    assert_eq!(statuses, vec![200, 400, 200, 400]);
+
+    for index in [0, 2] {
+        assert_eq!(
+            results[index]["response"]["choices"][0]["message"]["content"],
+            "Hello!"
+        );
+        assert!(results[index].get("error").is_none());
+    }
+    assert_eq!(results[1]["error"]["type"], "invalid_request_error");
+    assert!(results[1]["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("temprature"));
+    assert!(results[3]["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("Streaming is not supported"));
+    assert!(results[3].get("response").is_none());
+
+    // Only the valid items reached the provider, and each is tracked on its own
+    assert_eq!(
+        harness
+            .provider
+            .requests_for(MockEndpoint::ChatCompletions)
+            .len(),
+        2
+    );
+    assert_eq!(zion_totals(&harness, 2).await, (20, 10, 2));
+
+    // The batch counted as four requests
+    assert_eq!(
+        header_number(&response, "x-ratelimit-remaining"),
+        header_number(&response, "x-ratelimit-limit") - 4
+    );
+}
+
+#[tokio::test]
+async fn test_results_keep_request_order() {
+    let (_harness, server) = harness(10).await;
+
+    let requests: Vec<Value> = (0..6)
+        .map(|i| {
+            if i % 2 == 0 {
+                prompt(&format!("Prompt {}", i))
+            } else {
+                json!({"tier": "enormous", "messages": [{"role": "user", "content": "Hi"}]})
+            }
+        })
+        .collect();
+    let response = post_batch(&server, json!(requests)).await;
+    response.assert_status_ok();
+
+    let body: Value = response.json();
+    let statuses: Vec<u64> = body["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|result| result["status"].as_u64().unwrap())
+        .collect();
+    assert_eq!(statuses, vec![200, 400, 200, 400, 200, 400]);
+}
+
+#[tokio::test]
+async fn test_batch_size_is_bounded() {
+    let (harness, server) = harness(2).await;
+
+    let response = post_batch(&server, json!([])).await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json::<Value>()["error"]["type"],
+        "invalid_request_error"
+    );
+
+    let response = post_batch(
+        &server,
+        json!([prompt("One"), prompt("Two"), prompt("Three")]),
+    )
+    .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+    assert!(response.json::<Value>()["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("at most 2"));
+
+    assert!(harness
+        .provider
+        .requests_for(MockEndpoint::ChatCompletions)
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_batch_route_rejects_other_methods() {
+    let (_harness, server) = harness(10).await;
+
+    let response = server
+        .get("/native/v1/chat/completions/batch")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .await;
+    response.assert_status(StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(response.header("allow"), "OPTIONS, POST");
+}
@@ -13,11 +13,11 @@
 use std::time::Duration;
 
 use axum::http::{header, StatusCode};
-use serde_json::json;
+use serde_json::{json, Value};
 
 use crate::common::{constants, TokenTrackingTestHarness};
-use crate::mocks::openai::OpenAITestData;
 use crate::mocks::zion::{UserProfileMock, ZionTestData};
+use sentinel::testing::{sse_events, StreamScript};
 
 // =============================================================================
 // Test Helpers
@@ -372,8 +372,13 @@ async fn test_native_chat_completions_streaming() {
     harness.zion.mock_batch_increment_success(1, 0).await;
 
     // Mock streaming response with usage in final chunk
-    let chunks = OpenAITestData::streaming_chunks("Hello world this is a streaming response");
-    harness.openai.mock_chat_completion_stream(chunks).await;
+    let script = StreamScript::new("gpt-4")
+        .text("Hello world this is a streaming response")
+        .usage(50, 14);
+    harness
+        .openai
+        .mock_chat_completion_response(script.response())
+        .await;
 
     // Send streaming request
     let response = harness
@@ -414,10 +419,13 @@ async fn test_native_chat_completions_streaming() {
         "Streaming should have X-Sentinel-Tier header"
     );
 
-    // Consume the stream and verify format
+    // Chunks pass through in the OpenAI format
     let body = response.text();
-    assert!(body.contains("data:"), "Response should be SSE format");
-    assert!(body.contains("[DONE]"), "Stream should end with [DONE]");
+    assert_eq!(sse_events(&body), script.events());
+    assert!(
+        body.ends_with("data: [DONE]\n\n"),
+        "Stream should end with [DONE]"
+    );
 }
 
 #[tokio::test]
@@ -437,8 +445,11 @@ async fn test_native_chat_completions_streaming_usage_tracked() {
     harness.zion.mock_batch_increment_success(1, 0).await;
 
     // Mock streaming response with usage
-    let chunks = OpenAITestData::streaming_chunks("Test response");
-    harness.openai.mock_chat_completion_stream(chunks).await;
+    let script = StreamScript::new("gpt-4").text("Test response").usage(50, 4);
+    harness
+        .openai
+        .mock_chat_completion_response(script.response())
+        .await;
 
     // Send streaming request
     let response = harness
@@ -473,13 +484,151 @@ async fn test_native_chat_completions_streaming_usage_tracked() {
     let increments = TokenTrackingTestHarness::parse_batch_payload(&requests[0]);
     let (input, output, req_count) = TokenTrackingTestHarness::extract_token_counts(&increments[0]);
 
-    assert!(
-        input > 0 || output > 0,
-        "Should have tracked some tokens for streaming"
-    );
+    assert_eq!((input, output), (50, 4));
     assert_eq!(req_count, 1, "Request count should be 1");
 }
 
+/// Stream `script` through the native endpoint, returning the SSE events
+async fn stream_native(harness: &TokenTrackingTestHarness, script: &StreamScript) -> Vec<Value> {
+    harness
+        .zion
+        .mock_get_user_profile_success(make_test_profile())
+        .await;
+    harness
+        .zion
+        .mock_get_limits_success(constants::TEST_EXTERNAL_ID, ZionTestData::free_tier_limits())
+        .await;
+    harness.zion.mock_tier_config_success().await;
+    harness.zion.mock_batch_increment_success(1, 0).await;
+    harness
+        .openai
+        .mock_chat_completion_response(script.response())
+        .await;
+
+    let response = harness
+        .server
+        .post("/native/v1/chat/completions")
+        .add_header(header::AUTHORIZATION, auth_header().parse().unwrap())
+        .json(&json!({
+            "tier": "simple",
+            "messages": [
+                {"role": "user", "content": "What's the weather in Paris?"}
+            ],
+            "stream": true
+        }))
+        .await;
+    response.assert_status_ok();
+    sse_events(&response.text())
+}
+
+/// Tool call deltas of `events` at `index`, in order
+fn tool_call_deltas(events: &[Value], index: u64) -> Vec<&Value> {
+    events
+        .iter()
+        .filter_map(|event| event["choices"][0]["delta"]["tool_calls"].as_array())
+        .flatten()
+        .filter(|delta| delta["index"] == index)
+        .collect()
+}
+
+#[tokio::test]
+async fn test_native_chat_streaming_tool_call_ids_translated() {
+    let harness = TokenTrackingTestHarness::new().await;
+    let arguments = r#"{"location":"Paris, France","unit":"celsius"}"#;
+    let script = StreamScript::new("gpt-4")
+        .tool_call("call_provider_1", "get_weather", arguments)
+        .usage(50, 12);
+
+    let events = stream_native(&harness, &script).await;
+
+    // The provider ID is replaced with a Sentinel ID in the call_{uuid} format
+    let deltas = tool_call_deltas(&events, 0);
+    let ids: Vec<&str> = deltas.iter().filter_map(|d| d["id"].as_str()).collect();
+    assert_eq!(ids.len(), 1, "Only the first delta carries the ID");
+    let id = ids[0];
+    assert!(id.starts_with("call_"), "Expected call_ prefix, got {}", id);
+    assert_ne!(id, "call_provider_1");
+    assert!(uuid::Uuid::parse_str(id.trim_start_matches("call_")).is_ok());
+    assert_eq!(deltas[0]["function"]["name"], "get_weather");
+
+    // Arguments are forwarded fragment by fragment
+    let fragments: Vec<&str> = deltas
+        .iter()
+        .filter_map(|d| d["function"]["arguments"].as_str())
+        .filter(|fragment| !fragment.is_empty())
+        .collect();
+    assert!(fragments.len() > 1, "Arguments should stream incrementally");
+    assert_eq!(fragments.concat(), arguments);
+
+    let finish_reasons: Vec<&Value> = events
+        .iter()
+        .map(|event| &event["choices"][0]["finish_reason"])
+        .filter(|reason| !reason.is_null())
+        .collect();
+    assert_eq!(finish_reasons, vec!["tool_calls"]);
+
+    // The usage chunk still reaches the client and is tracked
+    assert_eq!(events.last().unwrap()["usage"]["completion_tokens"], 12);
+    let requests = harness
+        .wait_for_batch_requests(1, Duration::from_secs(3))
+        .await;
+    assert!(!requests.is_empty(), "Expected batch-increment request");
+    let increments = TokenTrackingTestHarness::parse_batch_payload(&requests[0]);
+    let (input, output, _) = TokenTrackingTestHarness::extract_token_counts(&increments[0]);
+    assert_eq!((input, output), (50, 12));
+}
+
+#[tokio::test]
+async fn test_native_streaming_tool_call_only_estimates_output_tokens() {
+    let harness = TokenTrackingTestHarness::new().await;
+    let script = StreamScript::new("gpt-4")
+        .tool_call("call_provider_1", "get_weather", r#"{"location":"Paris, France"}"#);
+
+    stream_native(&harness, &script).await;
+
+    // Without provider usage the streamed tool call is what gets estimated
+    let requests = harness
+        .wait_for_batch_requests(1, Duration::from_secs(3))
+        .await;
+    assert!(!requests.is_empty(), "Expected batch-increment request");
+    let increments = TokenTrackingTestHarness::parse_batch_payload(&requests[0]);
+    let (input, output, _) = TokenTrackingTestHarness::extract_token_counts(&increments[0]);
+    assert!(input > 0, "Input tokens should be > 0, got {}", input);
+    assert!(output > 0, "Tool call arguments should count as output tokens, got {}", output);
+}
+
+#[tokio::test]
+async fn test_native_chat_streaming_parallel_tool_calls_get_distinct_ids() {
+    let harness = TokenTrackingTestHarness::new().await;
+    let script = StreamScript::new("gpt-4")
+        .tool_call("toolu_01A", "get_weather", r#"{"location":"Paris"}"#)
+        .tool_call("toolu_01B", "get_time", r#"{"timezone":"Europe/Paris"}"#)
+        .finish_reason("tool_use")
+        .usage(60, 20);
+
+    let events = stream_native(&harness, &script).await;
+
+    let ids: Vec<&str> = [0, 1]
+        .into_iter()
+        .map(|index| {
+            let deltas = tool_call_deltas(&events, index);
+            let ids: Vec<&str> = deltas.iter().filter_map(|d| d["id"].as_str()).collect();
+            assert_eq!(ids.len(), 1, "tool call {}", index);
+            ids[0]
+        })
+        .collect();
+    assert!(ids.iter().all(|id| id.starts_with("call_")), "{:?}", ids);
+    assert_ne!(ids[0], ids[1]);
+
+    // A provider's own stop reason for tool use is reported as tool_calls
+    let last_choice = events
+        .iter()
+        .rev()
+        .find(|event| !event["choices"][0]["finish_reason"].is_null())
+        .unwrap();
+    assert_eq!(last_choice["choices"][0]["finish_reason"], "tool_calls");
+}
+
 // =============================================================================
 // Regression Test
 // =============================================================================
@@ -1457,3 +1606,74 @@ async fn test_native_chat_tool_result_missing_history() {
         "Error should indicate missing tool call in history"
     );
 }
+
+// =============================================================================
+// Session Concurrency Tests
+// =============================================================================
+
+/// Parallel requests in one conversation must end with the highest requested tier
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_session_updates_keep_highest_tier() {
+    use std::sync::Arc;
+
+    use axum::body::Body;
+    use axum_test::TestServer;
+    use sentinel::testing::{MockAiProvider, MockEndpoint, MockReply, TestHarness};
+    use tower::ServiceExt;
+
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_completion("gpt-4o", "Hello!", 10, 5),
+    ));
+    let harness = TestHarness::with_provider(provider).await;
+    let router = harness.router();
+
+    let tiers = ["simple", "complex", "moderate", "simple", "moderate", "simple"];
+    let handles: Vec<_> = tiers
+        .iter()
+        .cycle()
+        .take(24)
+        .map(|tier| {
+            let request = axum::http::Request::post("/native/v1/chat/completions")
+                .header(header::AUTHORIZATION, auth_header())
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(
+                    json!({
+                        "tier": tier,
+                        "messages": [{"role": "user", "content": "Hello!"}],
+                        "conversation_id": "test-session-race"
+                    })
+                    .to_string(),
+                ))
+                .unwrap();
+            tokio::spawn(router.clone().oneshot(request))
+        })
+        .collect();
+    for handle in handles {
+        let response = handle.await.unwrap().unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let session = harness
+        .state
+        .session_manager
+        .get("test-session-race")
+        .await
+        .unwrap()
+        .expect("Session should exist");
+    assert_eq!(session.tier.to_string(), "complex");
+
+    // Later requests are pinned to the upgraded session
+    let server = TestServer::new(router).unwrap();
+    let response = server
+        .post("/native/v1/chat/completions")
+        .add_header(header::AUTHORIZATION, auth_header().parse().unwrap())
+        .json(&json!({
+            "tier": "simple",
+            "messages": [{"role": "user", "content": "Hello again!"}],
+            "conversation_id": "test-session-race"
+        }))
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.header("X-Sentinel-Tier"), "complex");
+}
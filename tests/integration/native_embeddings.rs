@@ -0,0 +1,195 @@
+//! Native embeddings endpoint tests
+//!
+//! `POST /native/v1/embeddings` embeds with the tier's embedding model, names
+//! it in `X-Sentinel-Model`, tracks input tokens and refuses inputs above
+//! `NATIVE_EMBEDDINGS_MAX_INPUT_TOKENS` before calling the provider.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::{header, StatusCode};
+use axum_test::{TestResponse, TestServer};
+use serde_json::{json, Value};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use sentinel::native::error::NativeErrorResponse;
+use sentinel::testing::{
+    constants, extract_token_counts, parse_batch_payload, MockAiProvider, MockEndpoint, MockReply,
+    TestHarness,
+};
+
+const SIMPLE_MODEL: &str = "text-embedding-3-small";
+const COMPLEX_MODEL: &str = "text-embedding-3-large";
+
+fn embeddings_reply(model: &str, count: usize, prompt_tokens: u32) -> MockReply {
+    let data: Vec<Value> = (0..count)
+        .map(|index| json!({"object": "embedding", "index": index, "embedding": [0.25, -0.5]}))
+        .collect();
+    MockReply::Json(json!({
+        "object": "list",
+        "data": data,
+        "model": model,
+        "usage": {"prompt_tokens": prompt_tokens, "total_tokens": prompt_tokens}
+    }))
+}
+
+/// Harness whose simple and complex tiers have embedding models
+async fn harness(provider: MockAiProvider, max_input_tokens: usize) -> (TestHarness, TestServer) {
+    let harness = TestHarness::with_config(Arc::new(provider), |config| {
+        config.provider.native_embeddings_max_input_tokens = max_input_tokens;
+    })
+    .await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/v1/tiers/config"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "success": true,
+            "data": {
+                "version": "1.0.0",
+                "updatedAt": "2024-01-01T00:00:00Z",
+                "tiers": {
+                    "simple": [{
+                        "provider": "openai",
+                        "model": "gpt-4o-mini",
+                        "relativeCost": 1,
+                        "inputPricePerMillion": 0.15,
+                        "outputPricePerMillion": 0.60
+                    }],
+                    "moderate": [],
+                    "complex": []
+                },
+                "embeddingModels": {"simple": SIMPLE_MODEL, "complex": COMPLEX_MODEL}
+            }
+        })))
+        .mount(&harness.zion)
+        .await;
+
+    let server = TestServer::new(harness.router()).unwrap();
+    (harness, server)
+}
+
+async fn embed(server: &TestServer, body: Value) -> TestResponse {
+    server
+        .post("/native/v1/embeddings")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .json(&body)
+        .await
+}
+
+#[tokio::test]
+async fn test_embeds_with_tier_model_and_tracks_usage() {
+    let provider = MockAiProvider::new().with_reply(
+        MockEndpoint::Embeddings,
+        embeddings_reply(SIMPLE_MODEL, 1, 4),
+    );
+    let (harness, server) = harness(provider, 300_000).await;
+
+    let response = embed(&server, json!({"input": "Hello world"})).await;
+    response.assert_status_ok();
+    assert_eq!(response.header("X-Sentinel-Model"), SIMPLE_MODEL);
+    assert_eq!(response.header("X-Sentinel-Tier"), "simple");
+    let body: Value = response.json();
+    assert_eq!(body["object"], "list");
+    assert_eq!(body["data"][0]["embedding"], json!([0.25, -0.5]));
+    assert_eq!(body["usage"]["prompt_tokens"], 4);
+
+    let requests = harness.provider.requests_for(MockEndpoint::Embeddings);
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0]["model"], SIMPLE_MODEL);
+    assert_eq!(requests[0]["input"], "Hello world");
+
+    let batches = harness
+        .wait_for_batch_requests(1, Duration::from_secs(2))
+        .await;
+    assert!(!batches.is_empty(), "Expected a batch-increment request");
+    let item = &parse_batch_payload(&batches[0])[0];
+    assert_eq!(item["model"], SIMPLE_MODEL);
+    assert_eq!(extract_token_counts(item), (4, 0, 1));
+}
+
+#[tokio::test]
+async fn test_array_input_uses_requested_tier() {
+    let texts: Vec<String> = (0..2000).map(|i| format!("document {}", i)).collect();
+    let provider = MockAiProvider::new().with_reply(
+        MockEndpoint::Embeddings,
+        embeddings_reply(COMPLEX_MODEL, texts.len(), 6000),
+    );
+    let (harness, server) = harness(provider, 300_000).await;
+
+    let response = embed(
+        &server,
+        json!({"input": texts, "tier": "complex", "dimensions": 256}),
+    )
+    .await;
+    response.assert_status_ok();
+    assert_eq!(response.header("X-Sentinel-Model"), COMPLEX_MODEL);
+    let body: Value = response.json();
+    assert_eq!(body["data"].as_array().unwrap().len(), 2000);
+
+    let requests = harness.provider.requests_for(MockEndpoint::Embeddings);
+    assert_eq!(requests[0]["input"].as_array().unwrap().len(), 2000);
+    assert_eq!(requests[0]["dimensions"], 256);
+}
+
+#[tokio::test]
+async fn test_oversized_input_is_rejected_before_the_provider() {
+    let provider = MockAiProvider::new();
+    let (harness, server) = harness(provider, 10).await;
+
+    // Two tokens per text, 12 in total
+    let texts = vec!["Hello world"; 6];
+    let response = embed(&server, json!({"input": texts})).await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+    let error: NativeErrorResponse = response.json();
+    assert_eq!(error.error.error_type, "invalid_request_error");
+    assert!(
+        error.error.message.contains("12 tokens"),
+        "{}",
+        error.error.message
+    );
+    assert!(harness
+        .provider
+        .requests_for(MockEndpoint::Embeddings)
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_invalid_requests() {
+    let (harness, server) = harness(MockAiProvider::new(), 300_000).await;
+
+    for body in [
+        json!({"input": []}),
+        json!({"input": ["Hello", ""]}),
+        json!({"input": "Hello", "model": "text-embedding-3-small"}),
+    ] {
+        let response = embed(&server, body.clone()).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let error: NativeErrorResponse = response.json();
+        assert_eq!(error.error.error_type, "invalid_request_error", "{}", body);
+    }
+
+    // No embedding model for the moderate tier
+    let response = embed(&server, json!({"input": "Hello", "tier": "moderate"})).await;
+    response.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+    assert!(harness
+        .provider
+        .requests_for(MockEndpoint::Embeddings)
+        .is_empty());
+
+    let response = server
+        .get("/native/v1/embeddings")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .await;
+    response.assert_status(StatusCode::METHOD_NOT_ALLOWED);
+}
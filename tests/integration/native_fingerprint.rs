@@ -0,0 +1,115 @@
+//! System fingerprint and seed tests
+//!
+//! Native responses carry the provider's `system_fingerprint` (non-streaming
+//! and on every streamed chunk), and a request `seed` is forwarded upstream so
+//! clients can pair the two for reproducibility.
+
+use std::sync::Arc;
+
+use axum::http::header;
+use axum_test::{TestResponse, TestServer};
+use serde_json::{json, Value};
+
+use sentinel::testing::{
+    constants, sse_events, MockAiProvider, MockEndpoint, MockReply, TestHarness,
+};
+
+const FINGERPRINT: &str = "fp_44709d6fcb";
+
+async fn harness(reply: MockReply) -> (TestHarness, TestServer) {
+    let provider = Arc::new(MockAiProvider::new().with_reply(MockEndpoint::ChatCompletions, reply));
+    let harness = TestHarness::with_provider(provider).await;
+    let server = TestServer::new(harness.router()).unwrap();
+    (harness, server)
+}
+
+async fn native_chat(server: &TestServer, body: Value) -> TestResponse {
+    server
+        .post("/native/v1/chat/completions")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .json(&body)
+        .await
+}
+
+/// Completion reply from a provider that reports its fingerprint
+fn fingerprinted_completion() -> MockReply {
+    let MockReply::Json(mut body) = MockReply::chat_completion("gpt-4o-mini", "Hello!", 10, 5)
+    else {
+        unreachable!("chat_completion is a JSON reply");
+    };
+    body["system_fingerprint"] = json!(FINGERPRINT);
+    MockReply::Json(body)
+}
+
+#[tokio::test]
+async fn test_non_streaming_response_carries_fingerprint_and_forwards_seed() {
+    let (harness, server) = harness(fingerprinted_completion()).await;
+
+    let response = native_chat(
+        &server,
+        json!({"messages": [{"role": "user", "content": "Hi"}], "seed": 42}),
+    )
+    .await;
+    response.assert_status_ok();
+    assert_eq!(response.json::<Value>()["system_fingerprint"], FINGERPRINT);
+
+    let requests = harness.provider.requests_for(MockEndpoint::ChatCompletions);
+    assert_eq!(requests[0]["seed"], 42);
+}
+
+#[tokio::test]
+async fn test_fingerprint_omitted_when_provider_does_not_report_it() {
+    let (harness, server) =
+        harness(MockReply::chat_completion("gpt-4o-mini", "Hello!", 10, 5)).await;
+
+    let response = native_chat(
+        &server,
+        json!({"messages": [{"role": "user", "content": "Hi"}]}),
+    )
+    .await;
+    response.assert_status_ok();
+    assert!(response.json::<Value>().get("system_fingerprint").is_none());
+
+    let requests = harness.provider.requests_for(MockEndpoint::ChatCompletions);
+    assert!(requests[0].get("seed").is_none());
+}
+
+#[tokio::test]
+async fn test_streamed_chunks_carry_fingerprint() {
+    let chunk = |delta: Value, finish_reason: Value| {
+        json!({
+            "id": "chatcmpl-mock",
+            "object": "chat.completion.chunk",
+            "created": 1700000000,
+            "model": "gpt-4o-mini",
+            "system_fingerprint": FINGERPRINT,
+            "choices": [{"index": 0, "delta": delta, "finish_reason": finish_reason}]
+        })
+    };
+    let (_harness, server) = harness(MockReply::sse(vec![
+        chunk(
+            json!({"role": "assistant", "content": "Hello"}),
+            Value::Null,
+        ),
+        chunk(json!({}), json!("stop")),
+    ]))
+    .await;
+
+    let response = native_chat(
+        &server,
+        json!({"messages": [{"role": "user", "content": "Hi"}], "stream": true, "seed": 7}),
+    )
+    .await;
+    response.assert_status_ok();
+
+    let events = sse_events(&response.text());
+    assert_eq!(events.len(), 2);
+    for event in &events {
+        assert_eq!(event["system_fingerprint"], FINGERPRINT);
+    }
+}
@@ -0,0 +1,129 @@
+//! MessagePack native response tests
+//!
+//! A non-streaming native request that prefers `application/msgpack` gets
+//! the same response (or error) as MessagePack; everything else stays JSON.
+
+use std::sync::Arc;
+
+use axum::http::header;
+use axum_test::{TestResponse, TestServer};
+use serde_json::{json, Value};
+
+use sentinel::native::encoding::MSGPACK_CONTENT_TYPE;
+use sentinel::native::error::NativeErrorResponse;
+use sentinel::native::ChatCompletionResponse;
+use sentinel::proxy::stream_accept::StreamAcceptMismatch;
+use sentinel::testing::{constants, MockAiProvider, MockEndpoint, MockReply, TestHarness};
+
+async fn server() -> TestServer {
+    server_with(MockReply::chat_completion("gpt-4o-mini", "Hello", 10, 5)).await
+}
+
+async fn server_with(reply: MockReply) -> TestServer {
+    let provider = Arc::new(MockAiProvider::new().with_reply(MockEndpoint::ChatCompletions, reply));
+    let harness = TestHarness::with_config(provider, |_| {}).await;
+    TestServer::new(harness.router()).unwrap()
+}
+
+async fn send(server: &TestServer, accept: Option<&str>, body: Value) -> TestResponse {
+    let mut request = server
+        .post("/native/v1/chat/completions")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .json(&body);
+    if let Some(accept) = accept {
+        request = request.add_header(header::ACCEPT, accept.parse().unwrap());
+    }
+    request.await
+}
+
+fn chat(stream: bool) -> Value {
+    json!({
+        "tier": "simple",
+        "stream": stream,
+        "messages": [{"role": "user", "content": "Hello"}]
+    })
+}
+
+#[tokio::test]
+async fn test_msgpack_response_matches_json() {
+    let server = server().await;
+
+    let json_response = send(&server, None, chat(false)).await;
+    json_response.assert_status_ok();
+    let from_json: ChatCompletionResponse = json_response.json();
+
+    let response = send(&server, Some("application/msgpack"), chat(false)).await;
+    response.assert_status_ok();
+    assert_eq!(response.header(header::CONTENT_TYPE), MSGPACK_CONTENT_TYPE);
+    let from_msgpack: ChatCompletionResponse = rmp_serde::from_slice(response.as_bytes()).unwrap();
+
+    // Only the generated id and timestamp differ between the two requests
+    assert_eq!(
+        from_msgpack,
+        ChatCompletionResponse {
+            id: from_msgpack.id.clone(),
+            created: from_msgpack.created,
+            ..from_json
+        }
+    );
+    assert_eq!(
+        from_msgpack.choices[0].message.content.as_deref(),
+        Some("Hello")
+    );
+    // Sentinel headers are set whatever the encoding
+    assert!(response.maybe_header("X-Sentinel-Tier").is_some());
+}
+
+#[tokio::test]
+async fn test_msgpack_error() {
+    let server = server().await;
+    let mut body = chat(false);
+    body["temperature"] = json!(3.0);
+
+    let response = send(&server, Some("application/msgpack"), body).await;
+    response.assert_status_bad_request();
+    assert_eq!(response.header(header::CONTENT_TYPE), MSGPACK_CONTENT_TYPE);
+    let error: NativeErrorResponse = rmp_serde::from_slice(response.as_bytes()).unwrap();
+    assert!(error.error.message.contains("temperature"), "{:?}", error);
+}
+
+#[tokio::test]
+async fn test_json_when_msgpack_not_preferred() {
+    let server = server().await;
+    for accept in [
+        "application/json, application/msgpack",
+        "application/x-protobuf",
+        "text/html",
+    ] {
+        let response = send(&server, Some(accept), chat(false)).await;
+        response.assert_status_ok();
+        assert_eq!(response.header(header::CONTENT_TYPE), "application/json");
+        let _: ChatCompletionResponse = response.json();
+    }
+}
+
+#[tokio::test]
+async fn test_stream_stays_sse() {
+    // STREAM_ACCEPT_MISMATCH=reject would refuse a stream to a MessagePack-only client
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_stream("gpt-4o-mini", "Hello", None),
+    ));
+    let harness = TestHarness::with_config(provider, |config| {
+        config.provider.stream_accept_mismatch = StreamAcceptMismatch::Warn;
+    })
+    .await;
+    let server = TestServer::new(harness.router()).unwrap();
+    let response = send(&server, Some("application/msgpack"), chat(true)).await;
+    response.assert_status_ok();
+    assert!(response
+        .header(header::CONTENT_TYPE)
+        .to_str()
+        .unwrap()
+        .starts_with("text/event-stream"));
+}
@@ -0,0 +1,95 @@
+//! Native routing tests
+//!
+//! Requests under `/native` that no route serves get native-format errors:
+//! a 405 with `Allow` for the wrong method on the chat route, and a 404
+//! listing the native endpoints for unknown paths. `OPTIONS` requests are
+//! answered by the global CORS layer without credentials.
+
+use axum::http::{header, HeaderValue, Method, StatusCode};
+use axum_test::{TestResponse, TestServer};
+
+use sentinel::native::error::NativeErrorResponse;
+use sentinel::testing::{constants, TestHarness};
+
+fn bearer() -> HeaderValue {
+    format!("Bearer {}", constants::TEST_JWT_TOKEN)
+        .parse()
+        .unwrap()
+}
+
+async fn send(server: &TestServer, method: Method, path: &str) -> TestResponse {
+    server
+        .method(method, path)
+        .add_header(header::AUTHORIZATION, bearer())
+        .await
+}
+
+#[tokio::test]
+async fn test_wrong_method_on_chat_route() {
+    let harness = TestHarness::new().await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    for method in [Method::GET, Method::DELETE] {
+        let response = send(&server, method, "/native/v1/chat/completions").await;
+        response.assert_status(StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(response.header(header::ALLOW), "OPTIONS, POST");
+        let error: NativeErrorResponse = response.json();
+        assert_eq!(error.error.code, "method_not_allowed");
+    }
+}
+
+#[tokio::test]
+async fn test_unknown_native_path_lists_native_endpoints() {
+    let harness = TestHarness::new().await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = send(&server, Method::POST, "/native/v1/images/generations").await;
+    response.assert_status(StatusCode::NOT_FOUND);
+    let error: NativeErrorResponse = response.json();
+    assert_eq!(error.error.error_type, "not_found_error");
+    assert_eq!(error.error.code, "endpoint_not_found");
+    assert!(error.error.message.contains("/native/v1/images/generations"));
+    assert!(error
+        .error
+        .message
+        .contains("POST /native/v1/chat/completions"));
+    assert!(!error.error.message.contains("/v1/ and"));
+
+    // Behind the same auth as the routes, like the /v1 pass-through
+    server
+        .get("/native/v2/anything")
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_preflight_follows_cors_policy() {
+    let harness = TestHarness::new().await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = server
+        .method(Method::OPTIONS, "/native/v1/chat/completions")
+        .add_header(
+            header::ORIGIN,
+            HeaderValue::from_static("https://app.example"),
+        )
+        .add_header(
+            header::ACCESS_CONTROL_REQUEST_METHOD,
+            HeaderValue::from_static("POST"),
+        )
+        .add_header(
+            header::ACCESS_CONTROL_REQUEST_HEADERS,
+            HeaderValue::from_static("authorization, content-type"),
+        )
+        .await;
+
+    response.assert_status_ok();
+    assert_eq!(response.header(header::ACCESS_CONTROL_ALLOW_ORIGIN), "*");
+    assert_eq!(response.header(header::ACCESS_CONTROL_ALLOW_METHODS), "*");
+
+    // Also without credentials or CORS headers, like every other path
+    server
+        .method(Method::OPTIONS, "/native/v1/chat/completions")
+        .await
+        .assert_status_ok();
+}
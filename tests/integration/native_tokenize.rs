@@ -0,0 +1,140 @@
+//! Native tokenize endpoint tests
+//!
+//! `POST /native/v1/tokenize` counts tokens with the proxy's own counter and
+//! suggests which messages to drop for a budget, without calling the
+//! provider or tracking usage.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::{header, StatusCode};
+use axum_test::{TestResponse, TestServer};
+use serde_json::{json, Value};
+
+use sentinel::testing::{constants, MockAiProvider, TestHarness};
+
+async fn harness() -> (TestHarness, TestServer) {
+    let harness = TestHarness::with_provider(Arc::new(MockAiProvider::new())).await;
+    let server = TestServer::new(harness.router()).unwrap();
+    (harness, server)
+}
+
+async fn tokenize(server: &TestServer, body: Value) -> TestResponse {
+    server
+        .post("/native/v1/tokenize")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .json(&body)
+        .await
+}
+
+fn message(role: &str, content: &str) -> Value {
+    json!({"role": role, "content": content})
+}
+
+#[tokio::test]
+async fn test_counts_text_and_messages() {
+    let (harness, server) = harness().await;
+
+    // "Hello world" is two tokens
+    let response = tokenize(&server, json!({"model": "gpt-4o", "text": "Hello world"})).await;
+    response.assert_status_ok();
+    let body: Value = response.json();
+    assert_eq!(body["model"], "gpt-4o");
+    assert_eq!(body["total_tokens"], 2);
+    assert!(body.get("message_tokens").is_none());
+
+    // Each message: 3 overhead + role + content; plus 3 to prime the reply
+    let response = tokenize(
+        &server,
+        json!({"messages": [message("system", "Hello world"), message("user", "Hello world")]}),
+    )
+    .await;
+    response.assert_status_ok();
+    let body: Value = response.json();
+    assert_eq!(body["model"], "gpt-4o-mini", "simple tier by default");
+    assert_eq!(body["message_tokens"], json!([6, 6]));
+    assert_eq!(body["total_tokens"], 15);
+    assert!(body.get("truncation").is_none());
+
+    // Nothing went upstream and nothing was charged
+    assert!(harness.provider.requests().is_empty());
+    assert!(harness
+        .wait_for_batch_requests(1, Duration::from_millis(300))
+        .await
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_truncation_suggestion() {
+    let (_harness, server) = harness().await;
+
+    let messages = json!([
+        message("system", "Hello world"),
+        message("user", "Hello world"),
+        message("assistant", "Hello world"),
+        message("user", "Hello world")
+    ]);
+    let response = tokenize(&server, json!({"messages": messages, "max_tokens": 16})).await;
+    response.assert_status_ok();
+    assert_eq!(response.json::<Value>()["total_tokens"], 27);
+    assert_eq!(
+        response.json::<Value>()["truncation"],
+        json!({"max_tokens": 16, "drop": [1, 2], "total_tokens": 15, "fits": true})
+    );
+
+    // A system prompt over the budget stays; everything but the last message goes
+    let response = tokenize(&server, json!({"messages": messages, "max_tokens": 5})).await;
+    assert_eq!(
+        response.json::<Value>()["truncation"],
+        json!({"max_tokens": 5, "drop": [1, 2], "total_tokens": 15, "fits": false})
+    );
+
+    // A single message too large for the budget is never dropped
+    let huge = "Hello world ".repeat(500);
+    let response = tokenize(
+        &server,
+        json!({"messages": [message("user", &huge)], "max_tokens": 100}),
+    )
+    .await;
+    let truncation = &response.json::<Value>()["truncation"];
+    assert_eq!(truncation["drop"], json!([]));
+    assert_eq!(truncation["fits"], false);
+}
+
+#[tokio::test]
+async fn test_invalid_requests_are_rejected() {
+    let (_harness, server) = harness().await;
+
+    for body in [
+        json!({"model": "gpt-4o"}),
+        json!({"text": "Hi", "messages": [message("user", "Hi")]}),
+        json!({"model": "gpt-4o", "tier": "simple", "text": "Hi"}),
+        json!({"messages": []}),
+        json!({"text": "Hi", "max_tokens": 10, "temperature": 0.2}),
+    ] {
+        let response = tokenize(&server, body.clone()).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        assert_eq!(
+            response.json::<Value>()["error"]["type"],
+            "invalid_request_error",
+            "{}",
+            body
+        );
+    }
+
+    let response = server
+        .get("/native/v1/tokenize")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .await;
+    response.assert_status(StatusCode::METHOD_NOT_ALLOWED);
+}
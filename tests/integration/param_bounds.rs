@@ -0,0 +1,96 @@
+//! Sampling parameter bound tests for the native API
+//!
+//! Out-of-range `temperature`/`top_p`/`max_tokens` are rejected with a 400
+//! naming the parameter (`PARAM_OUT_OF_RANGE=reject`, the default) or clamped
+//! to the provider's bounds before forwarding (`clamp`).
+
+use std::sync::Arc;
+
+use axum::http::{header, StatusCode};
+use axum_test::{TestResponse, TestServer};
+use serde_json::{json, Value};
+
+use sentinel::native::translate::ParamOutOfRange;
+use sentinel::testing::{constants, MockAiProvider, MockEndpoint, MockReply, TestHarness};
+
+async fn harness(mode: ParamOutOfRange) -> TestHarness {
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_completion("gpt-4o-mini", "Hello", 10, 5),
+    ));
+    TestHarness::with_config(provider, |config| {
+        config.provider.param_out_of_range = mode;
+    })
+    .await
+}
+
+async fn send(server: &TestServer, params: Value) -> TestResponse {
+    let mut body = json!({
+        "tier": "simple",
+        "messages": [{"role": "user", "content": "Hello"}]
+    });
+    body.as_object_mut()
+        .unwrap()
+        .extend(params.as_object().unwrap().clone());
+    server
+        .post("/native/v1/chat/completions")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .json(&body)
+        .await
+}
+
+#[tokio::test]
+async fn test_reject_mode_returns_400_naming_the_parameter() {
+    let harness = harness(ParamOutOfRange::Reject).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    for (params, name) in [
+        (json!({"temperature": 3.0}), "temperature"),
+        (json!({"top_p": 1.5}), "top_p"),
+        (json!({"max_tokens": 0}), "max_tokens"),
+    ] {
+        let response = send(&server, params).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let message = response.json::<Value>()["error"]["message"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        assert!(message.contains(name), "unexpected message: {}", message);
+    }
+    assert!(harness
+        .provider
+        .requests_for(MockEndpoint::ChatCompletions)
+        .is_empty());
+
+    // In-range values are forwarded unchanged
+    send(&server, json!({"temperature": 1.5, "top_p": 0.9}))
+        .await
+        .assert_status_ok();
+    let requests = harness.provider.requests_for(MockEndpoint::ChatCompletions);
+    assert_eq!(requests[0]["temperature"], 1.5);
+    assert_eq!(requests[0]["top_p"], 0.9);
+}
+
+#[tokio::test]
+async fn test_clamp_mode_forwards_nearest_bound() {
+    let harness = harness(ParamOutOfRange::Clamp).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    send(
+        &server,
+        json!({"temperature": 3.0, "top_p": 1.5, "max_tokens": 0}),
+    )
+    .await
+    .assert_status_ok();
+
+    let requests = harness.provider.requests_for(MockEndpoint::ChatCompletions);
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0]["temperature"], 2.0);
+    assert_eq!(requests[0]["top_p"], 1.0);
+    assert_eq!(requests[0]["max_tokens"], 1);
+}
@@ -0,0 +1,172 @@
+//! Pass-through response framing tests
+//!
+//! A raw TCP upstream sends hand-written responses (chunked bodies, a
+//! `Connection` header nominating extra hop-by-hop headers, a stale
+//! `Content-Length`) that a regular mock server would normalize. Sentinel is
+//! served over real HTTP so the client sees exactly the framing hyper writes
+//! from the headers we copy, or drop (cookies, headers past the limits).
+
+use std::sync::Arc;
+
+use axum::http::{header, StatusCode};
+use axum_test::{TestResponse, TestServer, TestServerConfig};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+use sentinel::proxy::{redirect, AiProvider};
+use sentinel::testing::{constants, test_config, test_state, zion_stub};
+use sentinel::{routes, OpenAIProvider};
+
+/// Start an upstream that answers every request with `response` verbatim
+async fn raw_upstream(response: &'static [u8]) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let Ok((mut socket, _)) = listener.accept().await else {
+                return;
+            };
+            tokio::spawn(async move {
+                // Requests in these tests are small; one read covers the head and body
+                let mut request = vec![0u8; 16 * 1024];
+                let _ = socket.read(&mut request).await;
+                let _ = socket.write_all(response).await;
+                let _ = socket.shutdown().await;
+            });
+        }
+    });
+    format!("http://{}/v1", address)
+}
+
+/// Proxy a GET to `path` through Sentinel, backed by the raw upstream
+async fn passthrough(response: &'static [u8], path: &str) -> TestResponse {
+    let zion = zion_stub().await;
+    let config = test_config(&zion.uri(), &raw_upstream(response).await);
+    let provider: Arc<dyn AiProvider> = Arc::new(OpenAIProvider::new(
+        redirect::provider_client().unwrap(),
+        &config,
+    ));
+    let state = test_state(config, provider).await;
+    let server = TestServer::new_with_config(
+        routes::create_router(state),
+        TestServerConfig::builder().http_transport().build(),
+    )
+    .unwrap();
+
+    server
+        .get(path)
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN).parse().unwrap(),
+        )
+        .await
+}
+
+/// `Content-Length`, when present, must match the body the client received
+fn assert_coherent_length(response: &TestResponse) {
+    if let Some(length) = response.maybe_header(header::CONTENT_LENGTH) {
+        assert_eq!(
+            length.to_str().unwrap(),
+            response.as_bytes().len().to_string(),
+            "Content-Length must describe the forwarded body"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_chunked_passthrough_drops_connection_nominated_headers() {
+    let response = passthrough(
+        b"HTTP/1.1 200 OK\r\n\
+          Content-Type: application/octet-stream\r\n\
+          Transfer-Encoding: chunked\r\n\
+          Connection: keep-alive, x-custom-hop\r\n\
+          Keep-Alive: timeout=5\r\n\
+          X-Custom-Hop: internal\r\n\
+          X-Request-Id: req_123\r\n\
+          \r\n\
+          5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n",
+        "/v1/files/file-abc/content",
+    )
+    .await;
+
+    response.assert_status_ok();
+    assert_eq!(response.as_bytes().as_ref(), b"hello world");
+    assert!(response.maybe_header("x-custom-hop").is_none());
+    assert!(response.maybe_header("keep-alive").is_none());
+    assert_eq!(response.header("x-request-id"), "req_123");
+    assert_coherent_length(&response);
+}
+
+#[tokio::test]
+async fn test_stale_content_length_is_not_forwarded_with_streamed_body() {
+    // Content-Length alongside chunked framing is ignored by the upstream
+    // client; copying it would promise the client 999 bytes
+    let response = passthrough(
+        b"HTTP/1.1 200 OK\r\n\
+          Content-Type: audio/mpeg\r\n\
+          Transfer-Encoding: chunked\r\n\
+          Content-Length: 999\r\n\
+          \r\n\
+          4\r\nmp3!\r\n0\r\n\r\n",
+        "/v1/audio/speech",
+    )
+    .await;
+
+    response.assert_status_ok();
+    assert_eq!(response.as_bytes().as_ref(), b"mp3!");
+    assert_coherent_length(&response);
+}
+
+#[tokio::test]
+async fn test_buffered_error_body_gets_recomputed_length() {
+    // The error body isn't valid UTF-8; it must reach the client byte for byte
+    let response = passthrough(
+        b"HTTP/1.1 404 Not Found\r\n\
+          Content-Type: application/json\r\n\
+          Content-Length: 13\r\n\
+          Connection: close\r\n\
+          \r\n\
+          {\"error\":\"\xff\"}",
+        "/v1/files/missing",
+    )
+    .await;
+
+    response.assert_status(StatusCode::NOT_FOUND);
+    assert_eq!(response.as_bytes().as_ref(), b"{\"error\":\"\xff\"}");
+    assert_eq!(response.header(header::CONTENT_LENGTH), "13");
+}
+
+#[tokio::test]
+async fn test_pathological_header_set_is_capped_and_cookies_dropped() {
+    let mut head = String::from(
+        "HTTP/1.1 200 OK\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: 2\r\n\
+         Set-Cookie: session=abc; Path=/\r\n",
+    );
+    // hyper rejects upstream responses with over 100 headers outright
+    for i in 0..80 {
+        head.push_str(&format!("X-Tracking-{}: {}\r\n", i, i));
+    }
+    head.push_str("\r\n{}");
+    let response = passthrough(
+        Box::leak(head.into_bytes().into_boxed_slice()),
+        "/v1/moderations",
+    )
+    .await;
+
+    response.assert_status_ok();
+    assert_eq!(response.as_bytes().as_ref(), b"{}");
+    assert!(response.maybe_header(header::SET_COOKIE).is_none());
+    assert_eq!(response.header("x-sentinel-headers-truncated"), "true");
+    assert_eq!(response.header(header::CONTENT_TYPE), "application/json");
+    assert_eq!(response.header("x-tracking-0"), "0");
+    assert!(response.maybe_header("x-tracking-79").is_none());
+    // 64 upstream headers at most: Content-Type and the first 63 others
+    let tracking = response
+        .headers()
+        .keys()
+        .filter(|name| name.as_str().starts_with("x-tracking-"))
+        .count();
+    assert_eq!(tracking, 63);
+}
@@ -0,0 +1,118 @@
+//! Payload size tests
+//!
+//! Verify request/response byte histograms are exported per endpoint and that
+//! the payload warning fires once a request crosses the configured thresholds.
+//! Metrics are process-global, so assertions compare before/after values.
+
+use std::sync::Arc;
+
+use axum::http::header;
+use axum_test::TestServer;
+use serde_json::json;
+
+use sentinel::routes::metrics::init_metrics;
+use sentinel::testing::{constants, MockAiProvider, MockEndpoint, MockReply, TestHarness};
+
+const CHAT: &str = "endpoint=\"/v1/chat/completions\"";
+
+/// Sum of all series of `name` carrying every label in `labels`
+fn metric_value(metrics: &str, name: &str, labels: &[&str]) -> f64 {
+    metrics
+        .lines()
+        .filter(|line| line.starts_with(&format!("{}{{", name)))
+        .filter(|line| labels.iter().all(|label| line.contains(label)))
+        .filter_map(|line| line.rsplit(' ').next()?.parse::<f64>().ok())
+        .sum()
+}
+
+async fn scrape(server: &TestServer) -> String {
+    server.get("/metrics").await.text()
+}
+
+async fn send_chat(server: &TestServer, content: &str, stream: bool) {
+    server
+        .post("/v1/chat/completions")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN).parse().unwrap(),
+        )
+        .json(&json!({
+            "model": "gpt-4o-mini",
+            "messages": [{"role": "user", "content": content}],
+            "stream": stream
+        }))
+        .await
+        .assert_status_ok();
+}
+
+fn chat_provider(stream: bool) -> Arc<MockAiProvider> {
+    let reply = if stream {
+        MockReply::chat_stream("gpt-4o-mini", "Hello!", Some((10, 5)))
+    } else {
+        MockReply::chat_completion("gpt-4o-mini", "Hello!", 10, 5)
+    };
+    Arc::new(MockAiProvider::new().with_reply(MockEndpoint::ChatCompletions, reply))
+}
+
+#[tokio::test]
+async fn test_payload_histogram_buckets_move() {
+    init_metrics();
+    let harness = TestHarness::with_provider(chat_provider(false)).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let request_bucket = [CHAT, "stage=\"client\"", "le=\"1024\""];
+    let forwarded_bucket = [CHAT, "stage=\"forwarded\"", "le=\"1024\""];
+    let response_bucket = [CHAT, "le=\"1024\""];
+
+    let before = scrape(&server).await;
+    send_chat(&server, "Hi", false).await;
+    let after = scrape(&server).await;
+
+    for (name, labels) in [
+        ("sentinel_request_bytes_bucket", &request_bucket[..]),
+        ("sentinel_request_bytes_bucket", &forwarded_bucket[..]),
+        ("sentinel_response_bytes_bucket", &response_bucket[..]),
+    ] {
+        assert!(
+            metric_value(&after, name, labels) >= metric_value(&before, name, labels) + 1.0,
+            "{} {:?} did not move in:\n{}",
+            name,
+            labels,
+            after
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_large_request_triggers_payload_warning() {
+    init_metrics();
+    let harness = TestHarness::with_config(chat_provider(false), |config| {
+        config.server.payload_warn_request_bytes = 256;
+    })
+    .await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let warnings = [CHAT, "direction=\"request\""];
+    let before = metric_value(&scrape(&server).await, "sentinel_payload_warnings_total", &warnings);
+    send_chat(&server, &"x".repeat(1024), false).await;
+    let after = metric_value(&scrape(&server).await, "sentinel_payload_warnings_total", &warnings);
+
+    assert!(after >= before + 1.0, "Expected a request payload warning");
+}
+
+#[tokio::test]
+async fn test_streamed_bytes_counted_against_response_threshold() {
+    init_metrics();
+    let harness = TestHarness::with_config(chat_provider(true), |config| {
+        config.server.payload_warn_response_bytes = 16;
+    })
+    .await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let warnings = [CHAT, "direction=\"response\""];
+    let before = metric_value(&scrape(&server).await, "sentinel_payload_warnings_total", &warnings);
+    send_chat(&server, "Hi", true).await;
+    let after = metric_value(&scrape(&server).await, "sentinel_payload_warnings_total", &warnings);
+
+    assert!(after >= before + 1.0, "Expected a response payload warning for the stream");
+}
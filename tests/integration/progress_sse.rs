@@ -0,0 +1,225 @@
+//! Progress SSE tests (`X-Sentinel-Progress: sse`)
+//!
+//! A slow wiremock upstream answers a non-streaming request; the client gets
+//! `progress` heartbeats while it waits, then the complete response as a
+//! `result` event (or the error envelope as an `error` event) and `[DONE]`.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::header;
+use axum_test::{TestResponse, TestServer};
+use serde_json::{json, Value};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use sentinel::proxy::AiProvider;
+use sentinel::testing::{
+    constants, extract_token_counts, parse_batch_payload, test_config, test_state,
+    wait_for_batch_requests, zion_stub,
+};
+use sentinel::{routes, OpenAIProvider};
+
+/// Heartbeat interval configured for these tests
+const INTERVAL_MS: u64 = 100;
+
+struct ProgressHarness {
+    server: TestServer,
+    openai: MockServer,
+    zion: MockServer,
+}
+
+async fn progress_harness() -> ProgressHarness {
+    let zion = zion_stub().await;
+    let openai = MockServer::start().await;
+
+    let mut config = test_config(&zion.uri(), &format!("{}/v1", openai.uri()));
+    config.provider.progress_interval_ms = INTERVAL_MS;
+
+    let provider: Arc<dyn AiProvider> =
+        Arc::new(OpenAIProvider::new(reqwest::Client::new(), &config));
+    let state = test_state(config, provider).await;
+    let server = TestServer::new(routes::create_router(state)).unwrap();
+
+    ProgressHarness {
+        server,
+        openai,
+        zion,
+    }
+}
+
+fn chat_completion_body() -> Value {
+    json!({
+        "id": "chatcmpl-progress",
+        "object": "chat.completion",
+        "created": 1700000000,
+        "model": "gpt-4o-mini",
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": "Hello!"},
+            "finish_reason": "stop"
+        }],
+        "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+    })
+}
+
+async fn mount_chat(harness: &ProgressHarness, template: ResponseTemplate) {
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(template)
+        .mount(&harness.openai)
+        .await;
+}
+
+async fn send(harness: &ProgressHarness, path: &str, body: Value) -> TestResponse {
+    harness
+        .server
+        .post(path)
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .add_header(
+            "X-Sentinel-Progress".parse().unwrap(),
+            "sse".parse().unwrap(),
+        )
+        .json(&body)
+        .await
+}
+
+/// `(event name, data)` pairs of an SSE body
+fn events(body: &str) -> Vec<(String, String)> {
+    body.split("\n\n")
+        .filter(|block| !block.is_empty())
+        .map(|block| {
+            let mut name = String::new();
+            let mut data = String::new();
+            for line in block.lines() {
+                if let Some(value) = line.strip_prefix("event: ") {
+                    name = value.to_string();
+                } else if let Some(value) = line.strip_prefix("data: ") {
+                    data = value.to_string();
+                }
+            }
+            (name, data)
+        })
+        .collect()
+}
+
+// Multi-threaded, so a slow first token count can't hold up the heartbeats
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_heartbeats_then_result_and_usage_tracked() {
+    let harness = progress_harness().await;
+    mount_chat(
+        &harness,
+        ResponseTemplate::new(200)
+            .set_body_json(chat_completion_body())
+            .set_delay(Duration::from_millis(550)),
+    )
+    .await;
+
+    let response = send(
+        &harness,
+        "/v1/chat/completions",
+        json!({"model": "gpt-4o-mini", "messages": [{"role": "user", "content": "Hi"}]}),
+    )
+    .await;
+
+    response.assert_status_ok();
+    assert_eq!(
+        response.header(header::CONTENT_TYPE),
+        "text/event-stream; charset=utf-8"
+    );
+    let events = events(&response.text());
+
+    // Heartbeats every interval while the upstream is slow
+    let elapsed: Vec<u64> = events
+        .iter()
+        .filter(|(name, _)| name == "progress")
+        .map(|(_, data)| {
+            serde_json::from_str::<Value>(data).unwrap()["elapsed_ms"]
+                .as_u64()
+                .unwrap()
+        })
+        .collect();
+    assert!(elapsed.len() >= 4, "Unexpected heartbeats: {:?}", elapsed);
+    assert!(elapsed[0] >= INTERVAL_MS);
+    for pair in elapsed.windows(2) {
+        assert!(
+            pair[1] - pair[0] >= INTERVAL_MS / 2,
+            "Heartbeats too close: {:?}",
+            elapsed
+        );
+    }
+
+    // Then the full response, then [DONE]
+    let (name, data) = &events[events.len() - 2];
+    assert_eq!(name, "result");
+    let result: Value = serde_json::from_str(data).unwrap();
+    assert_eq!(result["id"], "chatcmpl-progress");
+    assert_eq!(result["choices"][0]["message"]["content"], "Hello!");
+    assert_eq!(events.last().unwrap().1, "[DONE]");
+
+    // Usage is tracked as for a plain non-streaming request
+    let requests = wait_for_batch_requests(&harness.zion, 1, Duration::from_secs(3)).await;
+    assert!(!requests.is_empty(), "Expected batch-increment request");
+    let items = parse_batch_payload(&requests[0]);
+    assert_eq!(extract_token_counts(&items[0]), (10, 5, 1));
+}
+
+#[tokio::test]
+async fn test_upstream_error_becomes_error_event() {
+    let harness = progress_harness().await;
+    mount_chat(
+        &harness,
+        ResponseTemplate::new(500)
+            .set_body_json(json!({"error": {"message": "boom"}}))
+            .set_delay(Duration::from_millis(150)),
+    )
+    .await;
+
+    let response = send(
+        &harness,
+        "/v1/chat/completions",
+        json!({"model": "gpt-4o-mini", "messages": [{"role": "user", "content": "Hi"}]}),
+    )
+    .await;
+
+    response.assert_status_ok();
+    let events = events(&response.text());
+    assert_eq!(events[0].0, "progress");
+    let (name, data) = &events[events.len() - 2];
+    assert_eq!(name, "error");
+    let error: Value = serde_json::from_str(data).unwrap();
+    assert!(error["error"]["message"].is_string(), "{}", data);
+    assert_eq!(events.last().unwrap().1, "[DONE]");
+}
+
+#[tokio::test]
+async fn test_native_request_gets_result_event() {
+    let harness = progress_harness().await;
+    mount_chat(
+        &harness,
+        ResponseTemplate::new(200)
+            .set_body_json(chat_completion_body())
+            .set_delay(Duration::from_millis(250)),
+    )
+    .await;
+
+    let response = send(
+        &harness,
+        "/native/v1/chat/completions",
+        json!({"tier": "complex", "messages": [{"role": "user", "content": "Hi"}]}),
+    )
+    .await;
+
+    response.assert_status_ok();
+    let events = events(&response.text());
+    assert!(events.iter().any(|(name, _)| name == "progress"));
+    let (name, data) = &events[events.len() - 2];
+    assert_eq!(name, "result");
+    let result: Value = serde_json::from_str(data).unwrap();
+    assert_eq!(result["choices"][0]["message"]["content"], "Hello!");
+}
@@ -0,0 +1,206 @@
+//! Startup provider capability check tests
+//!
+//! The tier config references `gpt-4o-mini` and `gpt-4o`, but the provider's
+//! `/models` only lists `gpt-4o-mini`. In `warn` mode startup continues and
+//! the missing model is reported; in `fail` mode startup is refused. The
+//! report is served at `/admin/providers/status`. With a static model list
+//! (`MODELS_SOURCE=static|merged`) the check runs against that list.
+
+use std::sync::Arc;
+
+use axum_test::TestServer;
+use serde_json::{json, Value};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use sentinel::config::Config;
+use sentinel::proxy::capabilities::{self, ProviderCheckMode};
+use sentinel::proxy::static_models::ModelsSource;
+use sentinel::testing::{MockAiProvider, MockEndpoint, MockReply, TestHarness};
+
+const ADMIN_KEY: &str = "admin-secret";
+
+async fn harness(mode: ProviderCheckMode, models_reply: MockReply) -> TestHarness {
+    harness_with(mode, models_reply, |_| {}).await
+}
+
+async fn harness_with(
+    mode: ProviderCheckMode,
+    models_reply: MockReply,
+    configure: impl FnOnce(&mut Config),
+) -> TestHarness {
+    let provider = Arc::new(MockAiProvider::new().with_reply(MockEndpoint::Models, models_reply));
+    let harness = TestHarness::with_config(provider, |config| {
+        config.server.admin_api_key = Some(ADMIN_KEY.to_string());
+        config.provider.startup_provider_check = mode;
+        configure(config);
+    })
+    .await;
+
+    let model = |name: &str| {
+        json!({
+            "provider": "openai",
+            "model": name,
+            "relativeCost": 1,
+            "inputPricePerMillion": 0.15,
+            "outputPricePerMillion": 0.60
+        })
+    };
+    Mock::given(method("GET"))
+        .and(path("/api/v1/tiers/config"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "success": true,
+            "data": {
+                "version": "2.0.0",
+                "updatedAt": "2024-01-01T00:00:00Z",
+                "tiers": {
+                    "simple": [model("gpt-4o-mini")],
+                    "moderate": [model("gpt-4o-mini")],
+                    "complex": [model("gpt-4o")]
+                }
+            }
+        })))
+        .mount(&harness.zion)
+        .await;
+
+    harness
+}
+
+fn listing_without_gpt_4o() -> MockReply {
+    MockReply::Json(json!({
+        "object": "list",
+        "data": [
+            {"id": "gpt-4o-mini", "object": "model"},
+            {"id": "text-embedding-3-small", "object": "model"}
+        ]
+    }))
+}
+
+async fn admin_status(harness: &TestHarness) -> Value {
+    let server = TestServer::new(harness.router()).unwrap();
+    let response = server
+        .get("/admin/providers/status")
+        .add_header("X-Admin-Key".parse().unwrap(), ADMIN_KEY.parse().unwrap())
+        .await;
+    response.assert_status_ok();
+    response.json()
+}
+
+#[tokio::test]
+async fn test_warn_mode_reports_missing_model_and_starts() {
+    let harness = harness(ProviderCheckMode::Warn, listing_without_gpt_4o()).await;
+
+    capabilities::startup_check(&harness.state).await.unwrap();
+
+    let status = admin_status(&harness).await;
+    assert_eq!(status["mode"], "warn");
+    assert_eq!(status["ok"], false);
+    assert_eq!(status["tier_config_version"], "2.0.0");
+    let provider = &status["providers"][0];
+    assert_eq!(provider["provider"], "mock");
+    assert_eq!(provider["auth_ok"], true);
+    assert_eq!(provider["model_count"], 2);
+    assert_eq!(provider["missing_models"], json!(["gpt-4o"]));
+
+    // The stored report is served without probing again
+    assert_eq!(harness.provider.requests_for(MockEndpoint::Models).len(), 1);
+}
+
+#[tokio::test]
+async fn test_fail_mode_refuses_to_start_on_missing_model() {
+    let harness = harness(ProviderCheckMode::Fail, listing_without_gpt_4o()).await;
+
+    let error = capabilities::startup_check(&harness.state).await.unwrap_err();
+    assert!(error.to_string().contains("missing models gpt-4o"), "{}", error);
+
+    let status = admin_status(&harness).await;
+    assert_eq!(status["mode"], "fail");
+    assert_eq!(status["providers"][0]["missing_models"], json!(["gpt-4o"]));
+}
+
+#[tokio::test]
+async fn test_fail_mode_reports_auth_failure() {
+    let harness = harness(
+        ProviderCheckMode::Fail,
+        MockReply::Error {
+            status: 401,
+            message: "Incorrect API key provided".to_string(),
+        },
+    )
+    .await;
+
+    let error = capabilities::startup_check(&harness.state).await.unwrap_err();
+    assert!(error.to_string().contains("mock: authentication failed"), "{}", error);
+
+    let status = admin_status(&harness).await;
+    assert_eq!(status["providers"][0]["auth_ok"], false);
+    assert_eq!(status["providers"][0]["reachable"], false);
+}
+
+#[tokio::test]
+async fn test_off_mode_skips_check_until_requested() {
+    let harness = harness(ProviderCheckMode::Off, listing_without_gpt_4o()).await;
+
+    capabilities::startup_check(&harness.state).await.unwrap();
+    assert!(harness.provider.requests_for(MockEndpoint::Models).is_empty());
+
+    // The admin endpoint probes on demand
+    let status = admin_status(&harness).await;
+    assert_eq!(status["mode"], "off");
+    assert_eq!(status["providers"][0]["missing_models"], json!(["gpt-4o"]));
+}
+
+fn static_models(source: ModelsSource, json: &'static str) -> impl FnOnce(&mut Config) {
+    move |config| {
+        config.provider.models_source = source;
+        config.provider.static_models = Some(json.parse().unwrap());
+    }
+}
+
+#[tokio::test]
+async fn test_static_source_checks_static_list_without_probing() {
+    let harness = harness_with(
+        ProviderCheckMode::Fail,
+        MockReply::Error {
+            status: 404,
+            message: "Not Found".to_string(),
+        },
+        static_models(ModelsSource::Static, r#"[{"id": "gpt-4o-mini"}]"#),
+    )
+    .await;
+
+    let error = capabilities::startup_check(&harness.state).await.unwrap_err();
+    assert!(error.to_string().contains("missing models gpt-4o"), "{}", error);
+    assert!(harness.provider.requests_for(MockEndpoint::Models).is_empty());
+
+    let harness = harness_with(
+        ProviderCheckMode::Fail,
+        MockReply::Error {
+            status: 404,
+            message: "Not Found".to_string(),
+        },
+        static_models(ModelsSource::Static, r#"[{"id": "gpt-4o-mini"}, {"id": "gpt-4o"}]"#),
+    )
+    .await;
+    capabilities::startup_check(&harness.state).await.unwrap();
+    let status = admin_status(&harness).await;
+    assert_eq!(status["ok"], true);
+    assert_eq!(status["providers"][0]["model_count"], 2);
+    assert!(harness.provider.requests_for(MockEndpoint::Models).is_empty());
+}
+
+#[tokio::test]
+async fn test_merged_source_counts_static_models_as_listed() {
+    let harness = harness_with(
+        ProviderCheckMode::Fail,
+        listing_without_gpt_4o(),
+        static_models(ModelsSource::Merged, r#"[{"id": "gpt-4o"}]"#),
+    )
+    .await;
+
+    capabilities::startup_check(&harness.state).await.unwrap();
+    let status = admin_status(&harness).await;
+    assert_eq!(status["ok"], true);
+    assert_eq!(status["providers"][0]["model_count"], 3);
+    assert_eq!(harness.provider.requests_for(MockEndpoint::Models).len(), 1);
+}
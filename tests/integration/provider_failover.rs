@@ -0,0 +1,150 @@
+//! Provider failover tests
+//!
+//! With failover providers configured, `/v1/chat/completions` requests the
+//! primary fails with a 5xx are served by the next provider, asked for the
+//! model `FAILOVER_MODEL_MAP` maps the request's to and named in
+//! `X-Sentinel-Provider`. Client errors are returned as they are, and so is
+//! the primary's error when failover doesn't help.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::http::{header, StatusCode};
+use axum_test::{TestResponse, TestServer};
+use serde_json::{json, Value};
+use wiremock::MockServer;
+
+use sentinel::proxy::AiProvider;
+use sentinel::routes;
+use sentinel::testing::{
+    constants, test_config, test_state, zion_stub, MockAiProvider, MockEndpoint, MockReply,
+};
+
+const HEADER: &str = "X-Sentinel-Provider";
+
+fn error(status: u16) -> MockReply {
+    MockReply::Error {
+        status,
+        message: "upstream trouble".to_string(),
+    }
+}
+
+fn provider(name: &'static str, replies: Vec<MockReply>) -> Arc<MockAiProvider> {
+    let provider = MockAiProvider::new().with_name(name);
+    for reply in replies {
+        provider.push_reply(MockEndpoint::ChatCompletions, reply);
+    }
+    Arc::new(provider)
+}
+
+async fn server(
+    primary: &Arc<MockAiProvider>,
+    secondary: &Arc<MockAiProvider>,
+) -> (TestServer, MockServer) {
+    let zion = zion_stub().await;
+    let mut config = test_config(&zion.uri(), "http://mock-provider.invalid/v1");
+    config.provider.failover_model_map =
+        HashMap::from([("gpt-4o".to_string(), "claude-sonnet-4-5".to_string())]);
+    let mut state = test_state(config, primary.clone()).await;
+    Arc::get_mut(&mut state).unwrap().failover_providers =
+        vec![secondary.clone() as Arc<dyn AiProvider>];
+    (TestServer::new(routes::create_router(state)).unwrap(), zion)
+}
+
+async fn chat(server: &TestServer, stream: bool) -> TestResponse {
+    server
+        .post("/v1/chat/completions")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .json(&json!({
+            "model": "gpt-4o",
+            "stream": stream,
+            "messages": [{"role": "user", "content": "Hi"}]
+        }))
+        .await
+}
+
+#[tokio::test]
+async fn test_5xx_fails_over_to_secondary() {
+    let primary = provider(
+        "primary",
+        vec![
+            error(503),
+            MockReply::chat_completion("gpt-4o", "From primary", 10, 5),
+        ],
+    );
+    let secondary = provider(
+        "secondary",
+        vec![MockReply::chat_completion(
+            "gpt-4o",
+            "From secondary",
+            10,
+            5,
+        )],
+    );
+    let (server, _zion) = server(&primary, &secondary).await;
+
+    let response = chat(&server, false).await;
+    response.assert_status_ok();
+    assert_eq!(response.header(HEADER), "secondary");
+    let body: Value = response.json();
+    assert_eq!(body["choices"][0]["message"]["content"], "From secondary");
+    assert_eq!(secondary.requests()[0].body["model"], "claude-sonnet-4-5");
+
+    // A healthy primary answers itself
+    let response = chat(&server, false).await;
+    response.assert_status_ok();
+    assert_eq!(response.header(HEADER), "primary");
+    assert_eq!(secondary.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_stream_fails_over_before_first_chunk() {
+    let primary = provider("primary", vec![error(502)]);
+    let secondary = provider(
+        "secondary",
+        vec![MockReply::chat_stream("gpt-4o", "Hello!", Some((10, 5)))],
+    );
+    let (server, _zion) = server(&primary, &secondary).await;
+
+    let response = chat(&server, true).await;
+    response.assert_status_ok();
+    assert_eq!(response.header(HEADER), "secondary");
+    assert!(response.text().contains("Hello!"));
+}
+
+#[tokio::test]
+async fn test_client_errors_do_not_fail_over() {
+    let primary = provider("primary", vec![error(400)]);
+    let secondary = provider(
+        "secondary",
+        vec![MockReply::chat_completion(
+            "gpt-4o",
+            "From secondary",
+            10,
+            5,
+        )],
+    );
+    let (server, _zion) = server(&primary, &secondary).await;
+
+    let response = chat(&server, false).await;
+    assert_ne!(response.status_code(), StatusCode::OK);
+    assert!(response.maybe_header(HEADER).is_none());
+    assert!(secondary.requests().is_empty());
+}
+
+#[tokio::test]
+async fn test_primary_error_when_failover_fails() {
+    let primary = provider("primary", vec![error(503)]);
+    let secondary = provider("secondary", vec![error(400)]);
+    let (server, _zion) = server(&primary, &secondary).await;
+
+    let response = chat(&server, false).await;
+    assert!(response.status_code().is_server_error(), "{}", response.status_code());
+    assert!(response.text().contains("503"), "{}", response.text());
+    assert_eq!(secondary.requests().len(), 1);
+}
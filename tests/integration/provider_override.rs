@@ -0,0 +1,149 @@
+//! Canary provider override tests
+//!
+//! A second mock provider is registered as `anthropic`. Users on the canary
+//! list can route a request to it with `X-Sentinel-Provider`; everyone else is
+//! rejected with 403. On the native API the tier config still chooses the
+//! model, only the provider is swapped.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::header;
+use axum_test::{TestResponse, TestServer};
+use serde_json::{json, Value};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use sentinel::testing::{constants, MockAiProvider, MockEndpoint, MockReply, TestHarness};
+
+const CANARY_PROVIDER: &str = "anthropic";
+
+/// Harness with a canary provider registered; `canary` puts the test user on the list
+async fn harness(canary: bool) -> (TestHarness, Arc<MockAiProvider>) {
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_completion("gpt-4o-mini", "From default", 10, 5),
+    ));
+    let harness = TestHarness::with_config(provider, |config| {
+        if canary {
+            config.provider.canary_external_ids = vec![constants::TEST_EXTERNAL_ID.to_string()];
+        }
+    })
+    .await;
+
+    let alternate = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_completion("gpt-4o-mini", "From canary", 10, 5),
+    ));
+    harness.state.providers.register(CANARY_PROVIDER, alternate.clone());
+
+    (harness, alternate)
+}
+
+async fn post(server: &TestServer, path: &str, provider: &str, body: Value) -> TestResponse {
+    server
+        .post(path)
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN).parse().unwrap(),
+        )
+        .add_header("X-Sentinel-Provider".parse().unwrap(), provider.parse().unwrap())
+        .json(&body)
+        .await
+}
+
+fn chat_body() -> Value {
+    json!({
+        "model": "gpt-4o-mini",
+        "messages": [{"role": "user", "content": "Hello"}]
+    })
+}
+
+#[tokio::test]
+async fn test_canary_user_override_routes_to_requested_provider() {
+    let (harness, alternate) = harness(true).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = post(&server, "/v1/chat/completions", "Anthropic", chat_body()).await;
+
+    response.assert_status_ok();
+    assert_eq!(response.header("x-sentinel-provider"), CANARY_PROVIDER);
+    let body: Value = response.json();
+    assert_eq!(body["choices"][0]["message"]["content"], "From canary");
+    assert_eq!(alternate.requests_for(MockEndpoint::ChatCompletions).len(), 1);
+    assert!(harness.provider.requests().is_empty());
+
+    // Usage is still tracked for overridden requests
+    let batches = harness.wait_for_batch_requests(1, Duration::from_secs(5)).await;
+    assert!(!batches.is_empty(), "usage should be tracked");
+}
+
+#[tokio::test]
+async fn test_override_rejected_for_users_not_on_canary_list() {
+    let (harness, alternate) = harness(false).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = post(&server, "/v1/chat/completions", CANARY_PROVIDER, chat_body()).await;
+
+    response.assert_status(axum::http::StatusCode::FORBIDDEN);
+    let body: Value = response.json();
+    assert_eq!(body["error"]["code"], "provider_override_forbidden");
+    assert!(alternate.requests().is_empty());
+    assert!(harness.provider.requests().is_empty());
+}
+
+#[tokio::test]
+async fn test_unknown_provider_is_rejected() {
+    let (harness, _) = harness(true).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = post(&server, "/v1/chat/completions", "bedrock", chat_body()).await;
+
+    response.assert_status_bad_request();
+    let body: Value = response.json();
+    assert_eq!(body["error"]["code"], "unknown_provider");
+    assert!(harness.provider.requests().is_empty());
+}
+
+#[tokio::test]
+async fn test_native_override_keeps_tier_model_selection() {
+    let (harness, alternate) = harness(true).await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/tiers/config"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "success": true,
+            "data": {
+                "version": "1.0.0",
+                "updatedAt": "2024-01-01T00:00:00Z",
+                "tiers": {
+                    "simple": [{
+                        "provider": "openai",
+                        "model": "gpt-4o-mini",
+                        "relativeCost": 1,
+                        "inputPricePerMillion": 0.15,
+                        "outputPricePerMillion": 0.60
+                    }],
+                    "moderate": [],
+                    "complex": []
+                }
+            }
+        })))
+        .mount(&harness.zion)
+        .await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = post(
+        &server,
+        "/native/v1/chat/completions",
+        CANARY_PROVIDER,
+        json!({"tier": "simple", "messages": [{"role": "user", "content": "Hello"}]}),
+    )
+    .await;
+
+    response.assert_status_ok();
+    assert_eq!(response.header("x-sentinel-provider"), CANARY_PROVIDER);
+    let forwarded = alternate.requests_for(MockEndpoint::ChatCompletions);
+    assert_eq!(forwarded.len(), 1);
+    assert_eq!(forwarded[0]["model"], "gpt-4o-mini");
+    assert!(harness.provider.requests().is_empty());
+}
@@ -0,0 +1,131 @@
+//! Malformed-request quarantine tests
+//!
+//! Repeated invalid bodies push a user over `QUARANTINE_MALFORMED_THRESHOLD`;
+//! from then on every request is rejected with 429 `too_many_malformed_requests`
+//! without reaching the provider, until the quarantine expires or an admin
+//! clears it.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::{header, StatusCode};
+use axum_test::TestServer;
+use serde_json::{json, Value};
+
+use sentinel::testing::{constants, MockAiProvider, MockEndpoint, MockReply, TestHarness};
+
+const THRESHOLD: i64 = 3;
+const ADMIN_KEY: &str = "admin-secret";
+
+async fn harness(duration_seconds: u64) -> TestHarness {
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_completion("gpt-4o-mini", "Hello!", 10, 5),
+    ));
+    TestHarness::with_config(provider, |config| {
+        config.rate_limit.quarantine_malformed_threshold = THRESHOLD;
+        config.rate_limit.quarantine_duration_seconds = duration_seconds;
+        config.server.admin_api_key = Some(ADMIN_KEY.to_string());
+    })
+    .await
+}
+
+fn auth() -> header::HeaderValue {
+    format!("Bearer {}", constants::TEST_JWT_TOKEN).parse().unwrap()
+}
+
+async fn send_malformed(server: &TestServer) -> axum_test::TestResponse {
+    server
+        .post("/v1/chat/completions")
+        .add_header(header::AUTHORIZATION, auth())
+        .content_type("application/json")
+        .bytes(axum::body::Bytes::from_static(b"{not json"))
+        .await
+}
+
+async fn send_valid(server: &TestServer) -> axum_test::TestResponse {
+    server
+        .post("/v1/chat/completions")
+        .add_header(header::AUTHORIZATION, auth())
+        .json(&json!({
+            "model": "gpt-4o-mini",
+            "messages": [{"role": "user", "content": "Hi"}]
+        }))
+        .await
+}
+
+/// Send malformed bodies until the threshold is reached
+async fn trip_quarantine(server: &TestServer) {
+    for _ in 0..THRESHOLD {
+        send_malformed(server).await.assert_status(StatusCode::BAD_REQUEST);
+    }
+}
+
+fn assert_quarantined(response: &axum_test::TestResponse) {
+    response.assert_status(StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(
+        response.json::<Value>()["error"]["code"],
+        "too_many_malformed_requests"
+    );
+    let retry_after: u64 = response.header(header::RETRY_AFTER).to_str().unwrap().parse().unwrap();
+    assert!(retry_after >= 1);
+}
+
+#[tokio::test]
+async fn test_quarantine_rejects_requests_until_it_expires() {
+    let harness = harness(1).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    trip_quarantine(&server).await;
+
+    // Valid requests are rejected too, without reaching the provider
+    assert_quarantined(&send_valid(&server).await);
+    assert_quarantined(&send_malformed(&server).await);
+    assert!(harness.provider.requests_for(MockEndpoint::ChatCompletions).is_empty());
+
+    tokio::time::sleep(Duration::from_millis(2100)).await;
+
+    send_valid(&server).await.assert_status_ok();
+    assert_eq!(harness.provider.requests_for(MockEndpoint::ChatCompletions).len(), 1);
+}
+
+#[tokio::test]
+async fn test_quarantine_applies_to_native_api() {
+    let harness = harness(300).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    trip_quarantine(&server).await;
+
+    let response = server
+        .post("/native/v1/chat/completions")
+        .add_header(header::AUTHORIZATION, auth())
+        .json(&json!({"messages": [{"role": "user", "content": "Hi"}]}))
+        .await;
+    assert_quarantined(&response);
+}
+
+#[tokio::test]
+async fn test_admin_throttle_endpoint_clears_quarantine() {
+    let harness = harness(300).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    trip_quarantine(&server).await;
+    assert_quarantined(&send_valid(&server).await);
+
+    let path = format!("/admin/users/{}/throttle", constants::TEST_EXTERNAL_ID);
+    let response = server
+        .delete(&path)
+        .add_header("X-Admin-Key".parse().unwrap(), ADMIN_KEY.parse().unwrap())
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.json::<Value>()["quarantine_cleared"], true);
+
+    send_valid(&server).await.assert_status_ok();
+
+    // Nothing left to clear
+    let response = server
+        .delete(&path)
+        .add_header("X-Admin-Key".parse().unwrap(), ADMIN_KEY.parse().unwrap())
+        .await;
+    assert_eq!(response.json::<Value>()["quarantine_cleared"], false);
+}
@@ -0,0 +1,92 @@
+//! Quiet request logging tests
+//!
+//! Requests to `QUIET_LOG_PATHS` (the health endpoints by default) are
+//! served without request logging, so load balancer probes don't flood the
+//! logs; every other request still gets its span and request/response events.
+
+use std::sync::Arc;
+
+use axum::http::{header, Method};
+use axum_test::TestServer;
+use serde_json::json;
+use tracing::subscriber::DefaultGuard;
+
+use sentinel::testing::{
+    capture_logs, constants, CapturedLogs, MockAiProvider, MockEndpoint, MockReply, TestHarness,
+};
+
+/// Server whose request logs (`tower_http=debug`) are captured in memory
+async fn server(quiet_log_paths: Option<Vec<String>>) -> (TestServer, CapturedLogs, DefaultGuard) {
+    let (_log_level, captured, guard) = capture_logs("sentinel=info,tower_http=debug");
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_completion("gpt-4o-mini", "Hello!", 10, 5),
+    ));
+    let harness = TestHarness::with_config(provider, |config| {
+        if let Some(paths) = quiet_log_paths {
+            config.server.quiet_log_paths = paths;
+        }
+    })
+    .await;
+    (TestServer::new(harness.router()).unwrap(), captured, guard)
+}
+
+/// Captured lines in the request span of `path`
+fn request_lines(captured: &CapturedLogs, path: &str) -> Vec<String> {
+    captured
+        .text()
+        .lines()
+        .filter(|line| line.contains(&format!("{} version=", path)))
+        .map(str::to_string)
+        .collect()
+}
+
+#[tokio::test]
+async fn test_health_probes_are_not_logged() {
+    let (server, captured, _guard) = server(None).await;
+
+    server.get("/health/live").await.assert_status_ok();
+    server
+        .method(Method::HEAD, "/health/live")
+        .await
+        .assert_status_ok();
+    // Full checks still run (Redis is unavailable in tests, so only the status code varies)
+    assert!(server.get("/health").await.text().contains("\"checks\""));
+    for path in ["/health", "/health/live"] {
+        assert!(
+            request_lines(&captured, path).is_empty(),
+            "{} was logged",
+            path
+        );
+    }
+
+    server
+        .post("/v1/chat/completions")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .json(&json!({
+            "model": "gpt-4o-mini",
+            "messages": [{"role": "user", "content": "Hi"}]
+        }))
+        .await
+        .assert_status_ok();
+    let chat_lines = request_lines(&captured, "/v1/chat/completions");
+    assert!(chat_lines
+        .iter()
+        .any(|line| line.contains("finished processing request")));
+}
+
+#[tokio::test]
+async fn test_quiet_paths_are_configurable() {
+    let (server, captured, _guard) = server(Some(vec!["/metrics".to_string()])).await;
+
+    server.get("/health/live").await.assert_status_ok();
+    server.get("/metrics").await.assert_status_ok();
+
+    assert!(!request_lines(&captured, "/health/live").is_empty());
+    assert!(request_lines(&captured, "/metrics").is_empty());
+}
@@ -6,6 +6,7 @@
 //! - 429 Too Many Requests responses with Retry-After header
 //! - Sliding window algorithm behavior
 //! - Per-user rate limit isolation
+//! - Token limits (X-RateLimit-Tokens-*), estimated up front and reconciled with actual usage
 
 use axum::{
     body::Body,
@@ -1109,3 +1110,607 @@ async fn test_many_rapid_requests() {
     // Cleanup
     cleanup_rate_limit_keys(&mut conn, &prefix).await;
 }
+
+// =============================================================================
+// Rate Limit Exemption Tests (real middleware via TestHarness)
+// =============================================================================
+
+mod exemption {
+    use super::*;
+    use std::time::Duration;
+
+    use sentinel::testing::{
+        constants, parse_batch_payload, MockAiProvider, MockEndpoint, MockReply, TestHarness,
+    };
+    use wiremock::matchers::{method, path_regex};
+    use wiremock::{Mock, ResponseTemplate};
+
+    /// Default limit of `RateLimitConfig::for_ai_requests()`
+    const AI_REQUEST_LIMIT: usize = 100;
+
+    fn provider() -> Arc<MockAiProvider> {
+        Arc::new(MockAiProvider::new().with_reply(
+            MockEndpoint::ChatCompletions,
+            MockReply::chat_completion("gpt-4o-mini", "Hello!", 5, 2),
+        ))
+    }
+
+    async fn send_chat(server: &TestServer) -> axum_test::TestResponse {
+        server
+            .post("/v1/chat/completions")
+            .add_header(
+                header::AUTHORIZATION,
+                format!("Bearer {}", constants::TEST_JWT_TOKEN).parse().unwrap(),
+            )
+            .json(&json!({
+                "model": "gpt-4o-mini",
+                "messages": [{"role": "user", "content": "Hi"}]
+            }))
+            .await
+    }
+
+    /// Build a harness backed by real Redis so the rate limiter is enforced
+    async fn redis_harness(
+        redis: redis::aio::ConnectionManager,
+        exempt_ids: Vec<String>,
+    ) -> TestHarness {
+        let mut harness = TestHarness::with_config(provider(), |config| {
+            config.rate_limit.exempt_ids = exempt_ids;
+        })
+        .await;
+        Arc::get_mut(&mut harness.state)
+            .expect("harness state should not be shared yet")
+            .redis = Some(redis);
+        harness
+    }
+
+    #[tokio::test]
+    async fn test_exempt_user_passes_limit_that_blocks_normal_user() {
+        let redis = match get_test_redis().await {
+            Some(r) => r,
+            None => {
+                eprintln!("Skipping test: Redis not available");
+                return;
+            }
+        };
+
+        let prefix = format!("sentinel:ratelimit:ai:{}", constants::TEST_EXTERNAL_ID);
+        let mut conn = redis.clone();
+        cleanup_rate_limit_keys(&mut conn, &prefix).await;
+
+        // Exempt user: never rate limited, counters untouched
+        let exempt = redis_harness(redis.clone(), vec![constants::TEST_EXTERNAL_ID.to_string()]).await;
+        let server = TestServer::new(exempt.router()).unwrap();
+        for _ in 0..=AI_REQUEST_LIMIT {
+            let response = send_chat(&server).await;
+            response.assert_status_ok();
+            assert_eq!(response.header("x-ratelimit-exempt"), "true");
+        }
+
+        // Normal user: the request after the limit is rejected
+        let normal = redis_harness(redis, Vec::new()).await;
+        let server = TestServer::new(normal.router()).unwrap();
+        let mut last_status = StatusCode::OK;
+        for _ in 0..=AI_REQUEST_LIMIT {
+            last_status = send_chat(&server).await.status_code();
+        }
+        assert_eq!(last_status, StatusCode::TOO_MANY_REQUESTS);
+
+        cleanup_rate_limit_keys(&mut conn, &prefix).await;
+    }
+
+    #[tokio::test]
+    async fn test_config_exempt_response_has_header_and_tracks_usage() {
+        let harness = TestHarness::with_config(provider(), |config| {
+            config.rate_limit.exempt_ids = vec![constants::TEST_EXTERNAL_ID.to_string()];
+        })
+        .await;
+        let server = TestServer::new(harness.router()).unwrap();
+
+        let response = send_chat(&server).await;
+        response.assert_status_ok();
+        assert_eq!(response.header("x-ratelimit-exempt"), "true");
+        assert!(response.maybe_header("x-ratelimit-limit").is_none());
+
+        // Usage is still tracked for exempt users
+        let requests = harness.wait_for_batch_requests(1, Duration::from_secs(2)).await;
+        assert!(!requests.is_empty(), "Expected a batch-increment request");
+        let increments = parse_batch_payload(&requests[0]);
+        assert_eq!(increments[0]["email"], constants::TEST_EMAIL);
+    }
+
+    #[tokio::test]
+    async fn test_exempt_ids_change_at_runtime() {
+        let harness = TestHarness::with_config(provider(), |config| {
+            config.server.admin_api_key = Some("admin-secret".to_string());
+        })
+        .await;
+        let server = TestServer::new(harness.router()).unwrap();
+        let admin_key = header::HeaderName::from_static("x-admin-key");
+
+        assert!(send_chat(&server).await.maybe_header("x-ratelimit-exempt").is_none());
+
+        let response = server
+            .put("/admin/rate-limit/exempt-ids")
+            .add_header(admin_key.clone(), "admin-secret".parse().unwrap())
+            .json(&json!({"external_ids": [constants::TEST_EXTERNAL_ID]}))
+            .await;
+        response.assert_status_ok();
+        let status: Value = response.json();
+        assert_eq!(status["source"], "override");
+        let response = send_chat(&server).await;
+        response.assert_status_ok();
+        assert_eq!(response.header("x-ratelimit-exempt"), "true");
+
+        let response = server
+            .delete("/admin/rate-limit/exempt-ids")
+            .add_header(admin_key, "admin-secret".parse().unwrap())
+            .await;
+        response.assert_status_ok();
+        let status: Value = response.json();
+        assert_eq!(status["source"], "config");
+        assert!(send_chat(&server).await.maybe_header("x-ratelimit-exempt").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_zion_flag_exempts_user() {
+        let harness = TestHarness::with_provider(provider()).await;
+        Mock::given(method("GET"))
+            .and(path_regex(r"^/api/v1/limits/external/.+$"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "success": true,
+                "data": {
+                    "userId": constants::TEST_USER_ID,
+                    "externalId": constants::TEST_EXTERNAL_ID,
+                    "limits": [{
+                        "name": "ai_usage",
+                        "displayName": "AI Usage",
+                        "aiInputTokens": {"limit": 1000000, "used": 0, "remaining": 1000000},
+                        "aiOutputTokens": {"limit": 1000000, "used": 0, "remaining": 1000000},
+                        "aiRequests": {"limit": 10000, "used": 0, "remaining": 10000},
+                        "resetPeriod": "MONTHLY",
+                        "periodStart": null,
+                        "periodEnd": null,
+                        "rateLimitExempt": true
+                    }]
+                }
+            })))
+            .mount(&harness.zion)
+            .await;
+        let server = TestServer::new(harness.router()).unwrap();
+
+        let response = send_chat(&server).await;
+        response.assert_status_ok();
+        assert_eq!(response.header("x-ratelimit-exempt"), "true");
+    }
+
+    #[tokio::test]
+    async fn test_normal_user_not_marked_exempt() {
+        let harness = TestHarness::with_provider(provider()).await;
+        let server = TestServer::new(harness.router()).unwrap();
+
+        let response = send_chat(&server).await;
+        response.assert_status_ok();
+        assert!(response.maybe_header("x-ratelimit-exempt").is_none());
+        assert!(response.maybe_header("x-ratelimit-limit").is_some());
+    }
+}
+
+// =============================================================================
+// Organization Rate Limit Tests (real middleware via TestHarness)
+// =============================================================================
+
+mod organization {
+    use super::*;
+    use std::time::Duration;
+
+    use sentinel::testing::{
+        constants, parse_batch_payload, MockAiProvider, MockEndpoint, MockReply, TestHarness,
+        STUB_PRIORITY,
+    };
+    use wiremock::matchers::{header as header_eq, method, path, path_regex};
+    use wiremock::{Mock, ResponseTemplate};
+
+    const ORG_ID: &str = "org_rate_limit_test";
+    const SECOND_JWT: &str = "second-user-jwt";
+    const SECOND_EXTERNAL_ID: &str = "ext_second_user";
+
+    fn provider() -> Arc<MockAiProvider> {
+        Arc::new(MockAiProvider::new().with_reply(
+            MockEndpoint::ChatCompletions,
+            MockReply::chat_completion("gpt-4o-mini", "Hello!", 5, 2),
+        ))
+    }
+
+    async fn send_chat(server: &TestServer, jwt: &str) -> axum_test::TestResponse {
+        server
+            .post("/v1/chat/completions")
+            .add_header(
+                header::AUTHORIZATION,
+                format!("Bearer {}", jwt).parse().unwrap(),
+            )
+            .json(&json!({
+                "model": "gpt-4o-mini",
+                "messages": [{"role": "user", "content": "Hi"}]
+            }))
+            .await
+    }
+
+    /// Put every user in `ORG_ID` and authenticate `SECOND_JWT` as a second user
+    async fn mount_org_mocks(harness: &TestHarness, organization_rate_limit: Option<i64>) {
+        Mock::given(method("GET"))
+            .and(path_regex(r"^/api/v1/limits/external/.+$"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "success": true,
+                "data": {
+                    "userId": constants::TEST_USER_ID,
+                    "externalId": constants::TEST_EXTERNAL_ID,
+                    "limits": [{
+                        "name": "ai_usage",
+                        "displayName": "AI Usage",
+                        "aiInputTokens": {"limit": 1000000, "used": 0, "remaining": 1000000},
+                        "aiOutputTokens": {"limit": 1000000, "used": 0, "remaining": 1000000},
+                        "aiRequests": {"limit": 10000, "used": 0, "remaining": 10000},
+                        "resetPeriod": "MONTHLY",
+                        "periodStart": null,
+                        "periodEnd": null,
+                        "organizationId": ORG_ID,
+                        "organizationRateLimit": organization_rate_limit
+                    }]
+                }
+            })))
+            .with_priority(STUB_PRIORITY - 1)
+            .mount(&harness.zion)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/api/v1/users/me"))
+            .and(header_eq("Authorization", format!("Bearer {}", SECOND_JWT).as_str()))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "success": true,
+                "data": {
+                    "id": "user_second",
+                    "email": "second@example.com",
+                    "name": "Second User",
+                    "externalId": SECOND_EXTERNAL_ID,
+                    "emailVerified": true,
+                    "createdAt": "2024-01-01T00:00:00Z",
+                    "lastLoginAt": "2024-01-15T12:00:00Z"
+                }
+            })))
+            .with_priority(STUB_PRIORITY - 1)
+            .mount(&harness.zion)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_users_in_same_org_share_org_budget() {
+        let redis = match get_test_redis().await {
+            Some(r) => r,
+            None => {
+                eprintln!("Skipping test: Redis not available");
+                return;
+            }
+        };
+
+        let mut conn = redis.clone();
+        let org_prefix = format!("sentinel:ratelimit:org:{}", ORG_ID);
+        cleanup_rate_limit_keys(&mut conn, &org_prefix).await;
+        for external_id in [constants::TEST_EXTERNAL_ID, SECOND_EXTERNAL_ID] {
+            cleanup_rate_limit_keys(&mut conn, &format!("sentinel:ratelimit:ai:{}", external_id))
+                .await;
+        }
+
+        let mut harness = TestHarness::with_provider(provider()).await;
+        Arc::get_mut(&mut harness.state)
+            .expect("harness state should not be shared yet")
+            .redis = Some(redis);
+        mount_org_mocks(&harness, Some(3)).await;
+        let server = TestServer::new(harness.router()).unwrap();
+
+        // Two requests from the first user, one from the second: budget of 3 used up
+        for jwt in [constants::TEST_JWT_TOKEN, constants::TEST_JWT_TOKEN, SECOND_JWT] {
+            let response = send_chat(&server, jwt).await;
+            response.assert_status_ok();
+            assert_eq!(response.header("x-ratelimit-org-limit"), "3");
+        }
+
+        // Neither user is near their own limit, but the organization is exhausted
+        for jwt in [SECOND_JWT, constants::TEST_JWT_TOKEN] {
+            let response = send_chat(&server, jwt).await;
+            response.assert_status(StatusCode::TOO_MANY_REQUESTS);
+            assert_eq!(response.header("x-ratelimit-scope"), "org");
+            assert_eq!(response.header("x-ratelimit-org-remaining"), "0");
+            let body: Value = response.json();
+            assert_eq!(body["error"]["code"], "ORG_RATE_LIMIT_EXCEEDED");
+            assert_eq!(body["error"]["retryable"], true);
+            assert!(body["error"]["retry_after_ms"].as_u64().unwrap() >= 1000);
+        }
+
+        cleanup_rate_limit_keys(&mut conn, &org_prefix).await;
+        for external_id in [constants::TEST_EXTERNAL_ID, SECOND_EXTERNAL_ID] {
+            cleanup_rate_limit_keys(&mut conn, &format!("sentinel:ratelimit:ai:{}", external_id))
+                .await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_org_headers_use_config_override() {
+        let harness = TestHarness::with_config(provider(), |config| {
+            config.rate_limit.org_overrides.insert(ORG_ID.to_string(), 250);
+        })
+        .await;
+        mount_org_mocks(&harness, None).await;
+        let server = TestServer::new(harness.router()).unwrap();
+
+        let response = send_chat(&server, constants::TEST_JWT_TOKEN).await;
+        response.assert_status_ok();
+        assert_eq!(response.header("x-ratelimit-org-limit"), "250");
+        assert_eq!(response.header("x-ratelimit-limit"), "100");
+    }
+
+    #[tokio::test]
+    async fn test_usage_carries_organization_id() {
+        let harness = TestHarness::with_provider(provider()).await;
+        mount_org_mocks(&harness, None).await;
+        let server = TestServer::new(harness.router()).unwrap();
+
+        send_chat(&server, constants::TEST_JWT_TOKEN)
+            .await
+            .assert_status_ok();
+
+        let requests = harness.wait_for_batch_requests(1, Duration::from_secs(2)).await;
+        assert!(!requests.is_empty(), "Expected a batch-increment request");
+        let increments = parse_batch_payload(&requests[0]);
+        assert_eq!(increments[0]["organizationId"], ORG_ID);
+    }
+
+    #[tokio::test]
+    async fn test_user_without_org_has_no_org_scope() {
+        let harness = TestHarness::with_provider(provider()).await;
+        let server = TestServer::new(harness.router()).unwrap();
+
+        let response = send_chat(&server, constants::TEST_JWT_TOKEN).await;
+        response.assert_status_ok();
+        assert!(response.maybe_header("x-ratelimit-org-limit").is_none());
+
+        let requests = harness.wait_for_batch_requests(1, Duration::from_secs(2)).await;
+        let increments = parse_batch_payload(&requests[0]);
+        assert!(increments[0].get("organizationId").is_none());
+    }
+}
+
+mod tokens {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    use sentinel::testing::{
+        constants, MockAiProvider, MockEndpoint, MockReply, TestHarness, STUB_PRIORITY,
+    };
+    use wiremock::matchers::{method, path_regex};
+    use wiremock::{Mock, ResponseTemplate};
+
+    fn chat(text: &str, stream: bool) -> Value {
+        json!({
+            "model": "gpt-4o-mini",
+            "messages": [{"role": "user", "content": text}],
+            "stream": stream
+        })
+    }
+
+    async fn send(server: &TestServer, body: Value) -> axum_test::TestResponse {
+        server
+            .post("/v1/chat/completions")
+            .add_header(
+                header::AUTHORIZATION,
+                format!("Bearer {}", constants::TEST_JWT_TOKEN).parse().unwrap(),
+            )
+            .json(&body)
+            .await
+    }
+
+    async fn harness(provider: Arc<MockAiProvider>, max_tokens_per_window: i64) -> TestHarness {
+        TestHarness::with_config(provider, |config| {
+            config.rate_limit.max_tokens_per_window = max_tokens_per_window;
+        })
+        .await
+    }
+
+    fn chat_provider() -> Arc<MockAiProvider> {
+        Arc::new(MockAiProvider::new().with_reply(
+            MockEndpoint::ChatCompletions,
+            MockReply::chat_completion("gpt-4o-mini", "Hello!", 10, 5),
+        ))
+    }
+
+    /// Report `remaining` input tokens for the test user's period
+    async fn mount_input_remaining(harness: &TestHarness, remaining: i64) {
+        Mock::given(method("GET"))
+            .and(path_regex(r"^/api/v1/limits/external/.+$"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "success": true,
+                "data": {
+                    "userId": constants::TEST_USER_ID,
+                    "externalId": constants::TEST_EXTERNAL_ID,
+                    "limits": [{
+                        "name": "ai_usage",
+                        "displayName": "AI Usage",
+                        "aiInputTokens": {"limit": 1000000, "used": 1000000 - remaining, "remaining": remaining},
+                        "aiOutputTokens": {"limit": 1000000, "used": 0, "remaining": 1000000},
+                        "aiRequests": {"limit": 10000, "used": 0, "remaining": 10000},
+                        "resetPeriod": "MONTHLY",
+                        "periodStart": null,
+                        "periodEnd": null
+                    }]
+                }
+            })))
+            .with_priority(STUB_PRIORITY - 1)
+            .mount(&harness.zion)
+            .await;
+    }
+
+    async fn harness_with_remaining(remaining: i64) -> TestHarness {
+        let harness = harness(chat_provider(), 20_000).await;
+        mount_input_remaining(&harness, remaining).await;
+        harness
+    }
+
+    #[tokio::test]
+    async fn test_token_limit_off_by_default() {
+        let harness = harness(chat_provider(), 0).await;
+        let server = TestServer::new(harness.router()).unwrap();
+
+        let response = send(&server, chat("Hi", false)).await;
+        response.assert_status_ok();
+        assert!(response.maybe_header("x-ratelimit-tokens-limit").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_token_headers_use_default_or_zion_remaining() {
+        let harness = harness(chat_provider(), 20_000).await;
+        let server = TestServer::new(harness.router()).unwrap();
+        let response = send(&server, chat("Hi", false)).await;
+        response.assert_status_ok();
+        assert_eq!(response.header("x-ratelimit-tokens-limit"), "20000");
+        let remaining: i64 = response
+            .header("x-ratelimit-tokens-remaining")
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(remaining > 0 && remaining < 20_000, "remaining was {}", remaining);
+        assert!(response.maybe_header("x-ratelimit-tokens-reset").is_some());
+
+        let harness = harness_with_remaining(2_500).await;
+        let server = TestServer::new(harness.router()).unwrap();
+        let response = send(&server, chat("Hi", false)).await;
+        response.assert_status_ok();
+        assert_eq!(response.header("x-ratelimit-tokens-limit"), "2500");
+    }
+
+    #[tokio::test]
+    async fn test_prompt_over_limit_rejected_before_forwarding() {
+        let provider = chat_provider();
+        let harness = harness(provider.clone(), 50).await;
+        let server = TestServer::new(harness.router()).unwrap();
+
+        let prompt = "Please summarize the following paragraph for me. ".repeat(20);
+        let response = send(&server, chat(&prompt, false)).await;
+        response.assert_status(StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.header("x-ratelimit-scope"), "tokens");
+        assert_eq!(response.header("x-ratelimit-tokens-limit"), "50");
+        assert_eq!(response.header("x-ratelimit-tokens-remaining"), "0");
+        assert!(response.maybe_header("retry-after").is_some());
+        let body: Value = response.json();
+        assert_eq!(body["error"]["code"], "TOKEN_RATE_LIMIT_EXCEEDED");
+        assert_eq!(body["error"]["retryable"], true);
+        assert_eq!(body["error"]["details"]["limit"], 50);
+        assert!(provider.requests_for(MockEndpoint::ChatCompletions).is_empty());
+
+        // A short prompt still fits
+        send(&server, chat("Hi", false)).await.assert_status_ok();
+        assert_eq!(provider.requests_for(MockEndpoint::ChatCompletions).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_passthrough_not_estimated_and_oversized_bodies_refused() {
+        let provider = Arc::new(
+            MockAiProvider::new()
+                .with_reply(MockEndpoint::Passthrough, MockReply::Json(json!({"ok": true}))),
+        );
+        let harness = TestHarness::with_config(provider.clone(), |config| {
+            config.rate_limit.max_tokens_per_window = 50;
+            config.server.max_request_body_bytes = 4096;
+        })
+        .await;
+        let server = TestServer::new(harness.router()).unwrap();
+
+        // Pass-through bodies are forwarded without a token estimate
+        let prompt = "Please summarize the following paragraph for me. ".repeat(20);
+        let response = server
+            .post("/v1/moderations")
+            .add_header(
+                header::AUTHORIZATION,
+                format!("Bearer {}", constants::TEST_JWT_TOKEN).parse().unwrap(),
+            )
+            .json(&json!({"input": prompt}))
+            .await;
+        response.assert_status_ok();
+        assert!(response.maybe_header("x-ratelimit-tokens-limit").is_none());
+        assert_eq!(provider.requests_for(MockEndpoint::Passthrough).len(), 1);
+
+        // Bodies over MAX_REQUEST_BODY_BYTES aren't buffered for the estimate
+        let response = send(&server, chat(&"word ".repeat(2000), false)).await;
+        response.assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+        let body: Value = response.json();
+        assert_eq!(body["error"]["code"], "request_too_large");
+    }
+
+    /// Sliding-window token count of the test user, once it stops changing
+    async fn settled_tokens(conn: &mut redis::aio::ConnectionManager, prefix: &str) -> i64 {
+        let start = Instant::now();
+        let mut last = None;
+        loop {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            let keys: Vec<String> = redis::cmd("KEYS")
+                .arg(format!("{}*", prefix))
+                .query_async(conn)
+                .await
+                .unwrap_or_default();
+            let mut total = 0;
+            for key in keys {
+                total += conn.get::<_, Option<i64>>(&key).await.unwrap().unwrap_or(0);
+            }
+            if last == Some(total) || start.elapsed() > Duration::from_secs(3) {
+                return total;
+            }
+            last = Some(total);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_window_reconciles_to_actual_usage() {
+        let redis = match get_test_redis().await {
+            Some(r) => r,
+            None => {
+                eprintln!("Skipping test: Redis not available");
+                return;
+            }
+        };
+        let mut conn = redis.clone();
+        let prefix = format!("sentinel:ratelimit:tokens:{}", constants::TEST_EXTERNAL_ID);
+        cleanup_rate_limit_keys(&mut conn, &prefix).await;
+
+        let provider = Arc::new(
+            MockAiProvider::new()
+                .with_reply(
+                    MockEndpoint::ChatCompletions,
+                    MockReply::chat_completion("gpt-4o-mini", "Hello!", 40, 5),
+                )
+                .with_reply(
+                    MockEndpoint::ChatCompletions,
+                    MockReply::chat_stream("gpt-4o-mini", "Hi there", Some((30, 20))),
+                ),
+        );
+        let mut harness = harness(provider, 100).await;
+        Arc::get_mut(&mut harness.state)
+            .expect("harness state should not be shared yet")
+            .redis = Some(redis);
+        let server = TestServer::new(harness.router()).unwrap();
+
+        // The estimate is replaced by the provider's 40 + 5
+        send(&server, chat("Hello", false)).await.assert_status_ok();
+        assert_eq!(settled_tokens(&mut conn, &prefix).await, 45);
+
+        // Streams are reconciled once they complete: 30 + 20 more
+        send(&server, chat("Hello", true)).await.assert_status_ok();
+        assert_eq!(settled_tokens(&mut conn, &prefix).await, 95);
+
+        // The window is nearly spent, so a prompt estimated above what's left is rejected
+        let response = send(&server, chat(&"word ".repeat(20), false)).await;
+        response.assert_status(StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.header("x-ratelimit-scope"), "tokens");
+
+        cleanup_rate_limit_keys(&mut conn, &prefix).await;
+    }
+}
@@ -0,0 +1,185 @@
+//! Reasoning content filter tests
+//!
+//! The tier config flags `gpt-4o-mini` with `stripReasoning`, so `<thinking>`
+//! blocks and `reasoning_content` are removed from its responses on both the
+//! /v1 and native APIs. Usage is still tracked from the unfiltered output.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::header;
+use axum_test::{TestResponse, TestServer};
+use serde_json::{json, Value};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use sentinel::testing::{
+    constants, extract_token_counts, parse_batch_payload, MockAiProvider, MockEndpoint, MockReply,
+    TestHarness,
+};
+
+const MODEL: &str = "gpt-4o-mini";
+
+/// Harness whose tier config flags the model for reasoning filtering
+async fn harness(reply: MockReply) -> TestHarness {
+    let provider = Arc::new(MockAiProvider::new().with_reply(MockEndpoint::ChatCompletions, reply));
+    let harness = TestHarness::with_provider(provider).await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/tiers/config"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "success": true,
+            "data": {
+                "version": "1.0.0",
+                "updatedAt": "2024-01-01T00:00:00Z",
+                "tiers": {
+                    "simple": [{
+                        "provider": "openai",
+                        "model": MODEL,
+                        "relativeCost": 1,
+                        "inputPricePerMillion": 0.15,
+                        "outputPricePerMillion": 0.60,
+                        "stripReasoning": true
+                    }],
+                    "moderate": [],
+                    "complex": []
+                }
+            }
+        })))
+        .mount(&harness.zion)
+        .await;
+    harness
+}
+
+/// Stream whose `<thinking>` tags are split across three content deltas
+fn split_tag_stream() -> MockReply {
+    let chunk = |delta: Value, finish_reason: Value| {
+        json!({
+            "id": "chatcmpl-mock",
+            "object": "chat.completion.chunk",
+            "created": 1700000000,
+            "model": MODEL,
+            "choices": [{"index": 0, "delta": delta, "finish_reason": finish_reason}]
+        })
+    };
+    MockReply::sse(vec![
+        chunk(
+            json!({"role": "assistant", "content": "<thin"}),
+            Value::Null,
+        ),
+        chunk(
+            json!({"content": "king>Let me weigh every option carefully before I reply</thinki"}),
+            Value::Null,
+        ),
+        chunk(json!({"content": "ng>Answer"}), Value::Null),
+        chunk(json!({}), json!("stop")),
+    ])
+}
+
+async fn post(server: &TestServer, path: &str, body: Value) -> TestResponse {
+    server
+        .post(path)
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .json(&body)
+        .await
+}
+
+/// Concatenated delta content of an SSE response body
+fn streamed_content(body: &str) -> String {
+    body.lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .filter(|data| *data != "[DONE]")
+        .filter_map(|data| serde_json::from_str::<Value>(data).ok())
+        .filter_map(|chunk| {
+            chunk["choices"][0]["delta"]["content"]
+                .as_str()
+                .map(str::to_string)
+        })
+        .collect()
+}
+
+async fn tracked_output_tokens(harness: &TestHarness) -> i64 {
+    let requests = harness
+        .wait_for_batch_requests(1, Duration::from_secs(5))
+        .await;
+    assert!(!requests.is_empty(), "Expected a batch-increment request");
+    extract_token_counts(&parse_batch_payload(&requests[0])[0]).1
+}
+
+#[tokio::test]
+async fn test_streaming_tag_split_across_chunks_is_stripped() {
+    let harness = harness(split_tag_stream()).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = post(
+        &server,
+        "/v1/chat/completions",
+        json!({"model": MODEL, "stream": true, "messages": [{"role": "user", "content": "Hi"}]}),
+    )
+    .await;
+
+    response.assert_status_ok();
+    let body = response.text();
+    assert_eq!(streamed_content(&body), "Answer");
+    assert!(body.ends_with("data: [DONE]\n\n"));
+
+    // No upstream usage: output is estimated from the unfiltered deltas
+    assert!(tracked_output_tokens(&harness).await > 5);
+}
+
+#[tokio::test]
+async fn test_native_streaming_is_filtered() {
+    let harness = harness(split_tag_stream()).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = post(
+        &server,
+        "/native/v1/chat/completions",
+        json!({"tier": "simple", "stream": true, "messages": [{"role": "user", "content": "Hi"}]}),
+    )
+    .await;
+
+    response.assert_status_ok();
+    assert_eq!(streamed_content(&response.text()), "Answer");
+}
+
+#[tokio::test]
+async fn test_non_streaming_drops_reasoning_field_and_tags() {
+    let harness = harness(MockReply::Json(json!({
+        "id": "chatcmpl-mock",
+        "object": "chat.completion",
+        "created": 1700000000,
+        "model": MODEL,
+        "choices": [{
+            "index": 0,
+            "message": {
+                "role": "assistant",
+                "content": "<thinking>Short plan</thinking>Answer",
+                "reasoning_content": "Long hidden reasoning"
+            },
+            "finish_reason": "stop"
+        }],
+        "usage": {"prompt_tokens": 10, "completion_tokens": 40, "total_tokens": 50}
+    })))
+    .await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = post(
+        &server,
+        "/v1/chat/completions",
+        json!({"model": MODEL, "messages": [{"role": "user", "content": "Hi"}]}),
+    )
+    .await;
+
+    response.assert_status_ok();
+    let body: Value = response.json();
+    let message = &body["choices"][0]["message"];
+    assert_eq!(message["content"], "Answer");
+    assert!(message.get("reasoning_content").is_none());
+    // The provider billed for the reasoning; so do we
+    assert_eq!(tracked_output_tokens(&harness).await, 40);
+}
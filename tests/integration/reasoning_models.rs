@@ -0,0 +1,183 @@
+//! Reasoning model adaptation tests
+//!
+//! The tier config flags `o3-mini` as a reasoning model. Requests routed to it
+//! must be forwarded with `developer` instead of `system` messages, without
+//! sampling parameters and with `max_completion_tokens`; requests for other
+//! models must be forwarded unchanged.
+
+use std::sync::Arc;
+
+use axum::http::header;
+use axum_test::TestServer;
+use serde_json::{json, Value};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use sentinel::testing::{constants, MockAiProvider, MockEndpoint, MockReply, TestHarness};
+
+const REASONING: &str = "o3-mini";
+const STANDARD: &str = "gpt-4o-mini";
+
+/// Harness whose simple tier serves the reasoning model and moderate tier a standard one
+async fn harness() -> TestHarness {
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_completion(REASONING, "Done", 10, 5),
+    ));
+    let harness = TestHarness::with_provider(provider).await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/v1/tiers/config"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "success": true,
+            "data": {
+                "version": "1.0.0",
+                "updatedAt": "2024-01-01T00:00:00Z",
+                "tiers": {
+                    "simple": [{
+                        "provider": "openai",
+                        "model": REASONING,
+                        "relativeCost": 3,
+                        "inputPricePerMillion": 1.10,
+                        "outputPricePerMillion": 4.40,
+                        "reasoning": true
+                    }],
+                    "moderate": [{
+                        "provider": "openai",
+                        "model": STANDARD,
+                        "relativeCost": 1,
+                        "inputPricePerMillion": 0.15,
+                        "outputPricePerMillion": 0.60
+                    }],
+                    "complex": []
+                }
+            }
+        })))
+        .mount(&harness.zion)
+        .await;
+
+    harness
+}
+
+async fn post(server: &TestServer, path: &str, body: Value) {
+    server
+        .post(path)
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN).parse().unwrap(),
+        )
+        .json(&body)
+        .await
+        .assert_status_ok();
+}
+
+fn messages() -> Value {
+    json!([
+        {"role": "system", "content": "Think step by step."},
+        {"role": "user", "content": "What is 2 + 2?"}
+    ])
+}
+
+fn forwarded(harness: &TestHarness) -> Value {
+    let requests = harness.provider.requests_for(MockEndpoint::ChatCompletions);
+    assert_eq!(requests.len(), 1);
+    requests[0].clone()
+}
+
+fn assert_adapted(request: &Value) {
+    assert_eq!(request["messages"][0]["role"], "developer");
+    assert_eq!(request["messages"][1]["role"], "user");
+    assert!(request.get("temperature").is_none());
+    assert!(request.get("top_p").is_none());
+    assert!(request.get("max_tokens").is_none());
+    assert_eq!(request["max_completion_tokens"], 200);
+}
+
+#[tokio::test]
+async fn test_v1_reasoning_model_request_is_adapted() {
+    let harness = harness().await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    post(
+        &server,
+        "/v1/chat/completions",
+        json!({
+            "model": REASONING,
+            "messages": messages(),
+            "temperature": 0.7,
+            "top_p": 0.9,
+            "max_tokens": 200
+        }),
+    )
+    .await;
+
+    assert_adapted(&forwarded(&harness));
+}
+
+#[tokio::test]
+async fn test_v1_standard_model_request_is_untouched() {
+    let harness = harness().await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    post(
+        &server,
+        "/v1/chat/completions",
+        json!({
+            "model": STANDARD,
+            "messages": messages(),
+            "temperature": 0.7,
+            "max_tokens": 200
+        }),
+    )
+    .await;
+
+    let request = forwarded(&harness);
+    assert_eq!(request["messages"][0]["role"], "system");
+    assert_eq!(request["temperature"], 0.7);
+    assert_eq!(request["max_tokens"], 200);
+    assert!(request.get("max_completion_tokens").is_none());
+}
+
+#[tokio::test]
+async fn test_native_reasoning_tier_request_is_adapted() {
+    let harness = harness().await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    post(
+        &server,
+        "/native/v1/chat/completions",
+        json!({
+            "tier": "simple",
+            "messages": messages(),
+            "temperature": 0.7,
+            "top_p": 0.9,
+            "max_tokens": 200
+        }),
+    )
+    .await;
+
+    assert_adapted(&forwarded(&harness));
+}
+
+#[tokio::test]
+async fn test_native_standard_tier_request_is_untouched() {
+    let harness = harness().await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    post(
+        &server,
+        "/native/v1/chat/completions",
+        json!({
+            "tier": "moderate",
+            "messages": messages(),
+            "temperature": 0.7,
+            "max_tokens": 200
+        }),
+    )
+    .await;
+
+    let request = forwarded(&harness);
+    assert_eq!(request["messages"][0]["role"], "system");
+    assert_eq!(request["temperature"], 0.7);
+    assert_eq!(request["max_tokens"], 200);
+}
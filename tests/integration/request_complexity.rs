@@ -0,0 +1,199 @@
+//! Request complexity metrics tests
+//!
+//! Message count and content characters are exported as histograms by
+//! endpoint and tier, with counters for images, tools and streaming.
+//! Metrics are process-global, so assertions compare before/after values.
+
+use std::sync::Arc;
+
+use axum::http::header;
+use axum_test::TestServer;
+use serde_json::{json, Value};
+
+use sentinel::routes::metrics::init_metrics;
+use sentinel::testing::{constants, MockAiProvider, MockEndpoint, MockReply, TestHarness};
+
+const CHAT: &str = "endpoint=\"/v1/chat/completions\"";
+const NATIVE: &str = "endpoint=\"/native/v1/chat/completions\"";
+
+/// Sum of all series of `name` carrying every label in `labels`
+fn metric_value(metrics: &str, name: &str, labels: &[&str]) -> f64 {
+    metrics
+        .lines()
+        .filter(|line| line.starts_with(&format!("{}{{", name)))
+        .filter(|line| labels.iter().all(|label| line.contains(label)))
+        .filter_map(|line| line.rsplit(' ').next()?.parse::<f64>().ok())
+        .sum()
+}
+
+/// Increase of a metric between two scrapes
+fn delta(before: &str, after: &str, name: &str, labels: &[&str]) -> f64 {
+    metric_value(after, name, labels) - metric_value(before, name, labels)
+}
+
+async fn harness() -> (TestHarness, TestServer) {
+    init_metrics();
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_completion("gpt-4o-mini", "Hello!", 10, 5),
+    ));
+    let harness = TestHarness::with_provider(provider).await;
+    let server = TestServer::new(harness.router()).unwrap();
+    (harness, server)
+}
+
+async fn scrape(server: &TestServer) -> String {
+    server.get("/metrics").await.text()
+}
+
+async fn post(server: &TestServer, path: &str, body: Value) {
+    server
+        .post(path)
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .json(&body)
+        .await
+        .assert_status_ok();
+}
+
+/// `count` user messages of `chars` characters each
+fn messages(count: usize, chars: usize) -> Value {
+    (0..count)
+        .map(|_| json!({"role": "user", "content": "x".repeat(chars)}))
+        .collect()
+}
+
+#[tokio::test]
+async fn test_histogram_buckets_follow_request_size() {
+    let (_harness, server) = harness().await;
+    let tier = "tier=\"none\"";
+    let bucket =
+        |metrics: &str, name: &str, le: &str| metric_value(metrics, name, &[CHAT, tier, le]);
+
+    // One short message lands in the lowest buckets
+    let before = scrape(&server).await;
+    post(
+        &server,
+        "/v1/chat/completions",
+        json!({"model": "gpt-4o-mini", "messages": messages(1, 10)}),
+    )
+    .await;
+    let after = scrape(&server).await;
+    for (name, le) in [
+        ("sentinel_request_messages_bucket", "le=\"1\""),
+        ("sentinel_request_content_chars_bucket", "le=\"100\""),
+    ] {
+        assert!(
+            bucket(&after, name, le) >= bucket(&before, name, le) + 1.0,
+            "{} {} did not move in:\n{}",
+            name,
+            le,
+            after
+        );
+    }
+
+    // 40 messages of 500 characters: above the 32-message and 16000-char buckets
+    let before = scrape(&server).await;
+    post(
+        &server,
+        "/v1/chat/completions",
+        json!({"model": "gpt-4o-mini", "messages": messages(40, 500)}),
+    )
+    .await;
+    let after = scrape(&server).await;
+    for (name, below, above) in [
+        ("sentinel_request_messages_bucket", "le=\"32\"", "le=\"64\""),
+        (
+            "sentinel_request_content_chars_bucket",
+            "le=\"16000\"",
+            "le=\"64000\"",
+        ),
+    ] {
+        let moved_above = bucket(&after, name, above) - bucket(&before, name, above);
+        let moved_below = bucket(&after, name, below) - bucket(&before, name, below);
+        assert!(
+            moved_above - moved_below >= 1.0,
+            "{} has no observation between {} and {} in:\n{}",
+            name,
+            below,
+            above,
+            after
+        );
+    }
+    assert!(
+        delta(
+            &before,
+            &after,
+            "sentinel_request_content_chars_sum",
+            &[CHAT, tier]
+        ) >= 20_000.0
+    );
+}
+
+#[tokio::test]
+async fn test_native_features_counted_by_tier() {
+    let (_harness, server) = harness().await;
+    let tier = "tier=\"simple\"";
+    let feature = |name: &'static str| [NATIVE, tier, name];
+
+    let before = scrape(&server).await;
+    post(
+        &server,
+        "/native/v1/chat/completions",
+        json!({
+            "tier": "simple",
+            "messages": [{
+                "role": "user",
+                "content": [
+                    {"type": "text", "text": "What is in this image?"},
+                    {"type": "image_url", "image_url": {"url": "https://example.com/a.png"}}
+                ]
+            }],
+            "tools": [{
+                "type": "function",
+                "function": {
+                    "name": "describe",
+                    "description": "Describe an image",
+                    "parameters": {"type": "object", "properties": {}}
+                }
+            }]
+        }),
+    )
+    .await;
+    let after = scrape(&server).await;
+
+    for name in ["feature=\"images\"", "feature=\"tools\""] {
+        assert!(
+            delta(
+                &before,
+                &after,
+                "sentinel_request_features_total",
+                &feature(name)
+            ) >= 1.0,
+            "{} not counted in:\n{}",
+            name,
+            after
+        );
+    }
+    assert!(
+        delta(
+            &before,
+            &after,
+            "sentinel_request_messages_count",
+            &[NATIVE, tier]
+        ) >= 1.0
+    );
+    // Image parts don't count as characters: only the 22 of the text part
+    assert!(
+        delta(
+            &before,
+            &after,
+            "sentinel_request_content_chars_bucket",
+            &[NATIVE, tier, "le=\"100\""]
+        ) >= 1.0
+    );
+}
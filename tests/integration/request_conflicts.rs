@@ -0,0 +1,188 @@
+//! Body/query conflict tests for `/v1` endpoints
+//!
+//! Duplicate top-level keys in a request body are rejected with a 400 naming
+//! the key instead of silently keeping the last value. A `stream` query
+//! parameter takes precedence over the body's `stream` flag.
+
+use std::sync::Arc;
+
+use axum::http::{header, StatusCode};
+use axum_test::TestServer;
+use serde_json::{json, Value};
+
+use sentinel::testing::{constants, MockAiProvider, MockEndpoint, MockReply, TestHarness};
+
+fn auth() -> header::HeaderValue {
+    format!("Bearer {}", constants::TEST_JWT_TOKEN).parse().unwrap()
+}
+
+async fn post_raw(server: &TestServer, path: &str, body: &str) -> axum_test::TestResponse {
+    server
+        .post(path)
+        .add_header(header::AUTHORIZATION, auth())
+        .content_type("application/json")
+        .bytes(body.to_string().into())
+        .await
+}
+
+fn assert_duplicate(response: &axum_test::TestResponse, key: &str) {
+    response.assert_status(StatusCode::BAD_REQUEST);
+    let message = response.json::<Value>()["error"]["message"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    assert!(
+        message.contains(&format!("duplicate field `{}`", key)),
+        "unexpected message: {}",
+        message
+    );
+}
+
+#[tokio::test]
+async fn test_duplicate_top_level_keys_are_rejected() {
+    let harness = TestHarness::with_provider(Arc::new(MockAiProvider::new())).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = post_raw(
+        &server,
+        "/v1/chat/completions",
+        r#"{"model": "gpt-4o-mini",
+            "messages": [{"role": "user", "content": "first"}],
+            "messages": [{"role": "user", "content": "second"}]}"#,
+    )
+    .await;
+    assert_duplicate(&response, "messages");
+
+    let response = post_raw(
+        &server,
+        "/v1/chat/completions",
+        r#"{"model": "gpt-4o-mini", "stream": true, "stream": false,
+            "messages": [{"role": "user", "content": "Hi"}]}"#,
+    )
+    .await;
+    assert_duplicate(&response, "stream");
+
+    let response = post_raw(
+        &server,
+        "/v1/completions",
+        r#"{"model": "gpt-3.5-turbo-instruct", "prompt": "a", "prompt": "b"}"#,
+    )
+    .await;
+    assert_duplicate(&response, "prompt");
+
+    let response = post_raw(
+        &server,
+        "/v1/embeddings",
+        r#"{"model": "text-embedding-3-small", "model": "text-embedding-3-large", "input": "Hi"}"#,
+    )
+    .await;
+    assert_duplicate(&response, "model");
+
+    let response = post_raw(
+        &server,
+        "/v1/responses",
+        r#"{"model": "gpt-4o", "input": "a", "input": "b"}"#,
+    )
+    .await;
+    assert_duplicate(&response, "input");
+
+    assert!(harness.provider.requests().is_empty());
+}
+
+#[tokio::test]
+async fn test_nested_keys_may_repeat_top_level_names() {
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_completion("gpt-4o-mini", "Hello!", 10, 5),
+    ));
+    let harness = TestHarness::with_provider(provider).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    post_raw(
+        &server,
+        "/v1/chat/completions",
+        r#"{"model": "gpt-4o-mini",
+            "messages": [{"role": "user", "content": "Hi"}],
+            "metadata": {"model": "x", "messages": "y"}}"#,
+    )
+    .await
+    .assert_status_ok();
+}
+
+async fn chat_with_query(
+    server: &TestServer,
+    query: (&str, &str),
+    body_stream: bool,
+) -> axum_test::TestResponse {
+    server
+        .post("/v1/chat/completions")
+        .add_query_param(query.0, query.1)
+        .add_header(header::AUTHORIZATION, auth())
+        .json(&json!({
+            "model": "gpt-4o-mini",
+            "messages": [{"role": "user", "content": "Hi"}],
+            "stream": body_stream
+        }))
+        .await
+}
+
+fn forwarded_stream_flag(harness: &TestHarness) -> bool {
+    let requests = harness.provider.requests_for(MockEndpoint::ChatCompletions);
+    requests.last().unwrap()["stream"].as_bool().unwrap_or(false)
+}
+
+#[tokio::test]
+async fn test_query_stream_true_overrides_body_false() {
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_stream("gpt-4o-mini", "Hello!", Some((10, 5))),
+    ));
+    let harness = TestHarness::with_provider(provider).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = chat_with_query(&server, ("stream", "true"), false).await;
+    response.assert_status_ok();
+    assert!(response.text().contains("data: "));
+    assert!(forwarded_stream_flag(&harness));
+}
+
+#[tokio::test]
+async fn test_query_stream_false_overrides_body_true() {
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_completion("gpt-4o-mini", "Hello!", 10, 5),
+    ));
+    let harness = TestHarness::with_provider(provider).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = chat_with_query(&server, ("stream", "false"), true).await;
+    response.assert_status_ok();
+    assert_eq!(response.json::<Value>()["choices"][0]["message"]["content"], "Hello!");
+    assert!(!forwarded_stream_flag(&harness));
+}
+
+#[tokio::test]
+async fn test_body_stream_applies_without_query() {
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_stream("gpt-4o-mini", "Hello!", Some((10, 5))),
+    ));
+    let harness = TestHarness::with_provider(provider).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    // Unrelated query parameters leave the body's flag alone
+    chat_with_query(&server, ("api-version", "2024-01-01"), true)
+        .await
+        .assert_status_ok();
+    assert!(forwarded_stream_flag(&harness));
+}
+
+#[tokio::test]
+async fn test_invalid_stream_query_is_rejected() {
+    let harness = TestHarness::with_provider(Arc::new(MockAiProvider::new())).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = chat_with_query(&server, ("stream", "maybe"), false).await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+    assert!(harness.provider.requests().is_empty());
+}
@@ -0,0 +1,148 @@
+//! Compressed request body tests
+//!
+//! Gzipped bodies are inflated before the handlers see them, capped at
+//! `MAX_REQUEST_BODY_BYTES` after decompression. Other encodings are a 415.
+//! Uncompressed bodies are held to the same limit. Bodies are only inflated
+//! once the caller is authenticated.
+
+use std::io::Write;
+use std::sync::Arc;
+
+use axum::body::Bytes;
+use axum::http::{header, StatusCode};
+use axum_test::{TestResponse, TestServer};
+use flate2::{write::GzEncoder, Compression};
+use serde_json::{json, Value};
+
+use sentinel::testing::{constants, MockAiProvider, MockEndpoint, MockReply, TestHarness};
+
+const LIMIT: usize = 64 * 1024;
+
+async fn harness() -> TestHarness {
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::Embeddings,
+        MockReply::Json(json!({
+            "object": "list",
+            "data": [{"object": "embedding", "index": 0, "embedding": [0.1, 0.2]}],
+            "model": "text-embedding-3-small",
+            "usage": {"prompt_tokens": 4, "total_tokens": 4}
+        })),
+    ));
+    TestHarness::with_config(provider, |config| {
+        config.server.max_request_body_bytes = LIMIT;
+    })
+    .await
+}
+
+fn gzip(data: &[u8]) -> Bytes {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap().into()
+}
+
+async fn post_encoded(server: &TestServer, encoding: &str, body: Bytes) -> TestResponse {
+    server
+        .post("/v1/embeddings")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .add_header(header::CONTENT_ENCODING, encoding.parse().unwrap())
+        .content_type("application/json")
+        .bytes(body)
+        .await
+}
+
+#[tokio::test]
+async fn test_gzipped_embeddings_request() {
+    let harness = harness().await;
+    let server = TestServer::new(harness.router()).unwrap();
+    let inputs: Vec<String> = (0..200).map(|i| format!("document number {}", i)).collect();
+    let body = json!({"model": "text-embedding-3-small", "input": inputs});
+
+    let response = post_encoded(&server, "gzip", gzip(body.to_string().as_bytes())).await;
+
+    response.assert_status_ok();
+    let forwarded = harness.provider.requests_for(MockEndpoint::Embeddings);
+    assert_eq!(forwarded.len(), 1);
+    assert_eq!(forwarded[0]["input"], body["input"]);
+}
+
+#[tokio::test]
+async fn test_expansion_past_limit_is_rejected() {
+    let harness = harness().await;
+    let server = TestServer::new(harness.router()).unwrap();
+    // ~10 MiB of padding compresses to a few KiB
+    let padding = " ".repeat(10 * 1024 * 1024);
+    let body = format!(
+        r#"{{"model": "text-embedding-3-small", "input": "hi"{}}}"#,
+        padding
+    );
+    let compressed = gzip(body.as_bytes());
+    assert!(compressed.len() < LIMIT);
+
+    let response = post_encoded(&server, "gzip", compressed).await;
+
+    response.assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+    let body: Value = response.json();
+    assert_eq!(body["error"]["code"], "request_too_large");
+    assert!(harness.provider.requests().is_empty());
+}
+
+#[tokio::test]
+async fn test_unsupported_encoding_and_corrupt_gzip() {
+    let harness = harness().await;
+    let server = TestServer::new(harness.router()).unwrap();
+    let body = Bytes::from_static(br#"{"model": "text-embedding-3-small", "input": "hi"}"#);
+
+    let response = post_encoded(&server, "br", body.clone()).await;
+    response.assert_status(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    let error: Value = response.json();
+    assert_eq!(error["error"]["code"], "unsupported_encoding");
+
+    let response = post_encoded(&server, "gzip", body.clone()).await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+    let error: Value = response.json();
+    assert_eq!(error["error"]["code"], "invalid_encoding");
+
+    // identity is the same as no encoding
+    post_encoded(&server, "identity", body)
+        .await
+        .assert_status_ok();
+    assert_eq!(harness.provider.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_uncompressed_body_past_limit_is_rejected() {
+    let harness = harness().await;
+    let server = TestServer::new(harness.router()).unwrap();
+    let body = json!({"model": "text-embedding-3-small", "input": "x".repeat(LIMIT)});
+
+    let response = post_encoded(&server, "identity", body.to_string().into()).await;
+
+    response.assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+    let body: Value = response.json();
+    assert_eq!(body["error"]["code"], "request_too_large");
+    assert!(harness.provider.requests().is_empty());
+}
+
+#[tokio::test]
+async fn test_unauthenticated_gzip_is_not_inflated() {
+    let harness = harness().await;
+    let server = TestServer::new(harness.router()).unwrap();
+    // Not valid gzip: inflating it would answer 400 `invalid_encoding`
+    let body = Bytes::from_static(br#"{"model": "text-embedding-3-small", "input": "hi"}"#);
+
+    for path in ["/v1/embeddings", "/native/v1/embeddings"] {
+        let response = server
+            .post(path)
+            .add_header(header::CONTENT_ENCODING, "gzip".parse().unwrap())
+            .content_type("application/json")
+            .bytes(body.clone())
+            .await;
+        response.assert_status(StatusCode::UNAUTHORIZED);
+    }
+    assert!(harness.provider.requests().is_empty());
+}
@@ -0,0 +1,143 @@
+//! Request mirroring tests
+//!
+//! Sampled requests are copied to `MIRROR_URL` with the client's credentials
+//! replaced by `MIRROR_AUTH_TOKEN`, streaming turned off, and the mirror's
+//! status compared with the primary response.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::header;
+use axum_test::TestServer;
+use serde_json::{json, Value};
+use wiremock::{matchers::method, Mock, MockServer, Request, ResponseTemplate};
+
+use sentinel::routes::metrics::init_metrics;
+use sentinel::testing::{constants, MockAiProvider, MockEndpoint, StreamScript, TestHarness};
+
+async fn harness(mirror: &MockServer, sample_rate: f64) -> TestHarness {
+    init_metrics();
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        StreamScript::new("gpt-4o").text("Hi").reply(),
+    ));
+    let mirror_url = mirror.uri();
+    TestHarness::with_config(provider, move |config| {
+        config.server.mirror_url = Some(mirror_url);
+        config.server.mirror_auth_token = Some("staging-token".to_string());
+        config.server.mirror_sample_rate = sample_rate;
+    })
+    .await
+}
+
+async fn stream_chat(server: &TestServer) {
+    server
+        .post("/v1/chat/completions")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .json(&json!({
+            "model": "gpt-4o",
+            "stream": true,
+            "messages": [{"role": "user", "content": "Hi"}]
+        }))
+        .await
+        .assert_status_ok();
+}
+
+/// Wait for the background mirror request to arrive
+async fn mirrored_requests(mirror: &MockServer, expected: usize) -> Vec<Request> {
+    for _ in 0..50 {
+        let requests = mirror.received_requests().await.unwrap_or_default();
+        if requests.len() >= expected {
+            return requests;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    mirror.received_requests().await.unwrap_or_default()
+}
+
+fn mismatches(metrics: &str) -> u64 {
+    metrics
+        .lines()
+        .find(|line| line.starts_with("sentinel_mirror_requests_total{result=\"mismatch\"}"))
+        .and_then(|line| line.rsplit(' ').next())
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(0)
+}
+
+#[tokio::test]
+async fn test_sampled_request_is_mirrored_with_staging_token() {
+    let mirror = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .mount(&mirror)
+        .await;
+    let harness = harness(&mirror, 1.0).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    stream_chat(&server).await;
+
+    let requests = mirrored_requests(&mirror, 1).await;
+    assert_eq!(requests.len(), 1);
+    let copy = &requests[0];
+    assert_eq!(copy.url.path(), "/v1/chat/completions");
+    assert_eq!(
+        copy.headers.get("authorization").unwrap(),
+        "Bearer staging-token"
+    );
+    assert_eq!(copy.headers.get("x-sentinel-mirror").unwrap(), "1");
+    let body: Value = serde_json::from_slice(&copy.body).unwrap();
+    assert_eq!(body["stream"], false);
+    assert_eq!(body["model"], "gpt-4o");
+    // The primary request is unaffected
+    assert_eq!(harness.provider.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_status_mismatch_is_counted() {
+    let mirror = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&mirror)
+        .await;
+    let harness = harness(&mirror, 1.0).await;
+    let server = TestServer::new(harness.router()).unwrap();
+    let before = mismatches(&server.get("/metrics").await.text());
+
+    stream_chat(&server).await;
+    assert_eq!(mirrored_requests(&mirror, 1).await.len(), 1);
+
+    // The counter is recorded after the mirror's response is read
+    let mut after = before;
+    for _ in 0..50 {
+        after = mismatches(&server.get("/metrics").await.text());
+        if after > before {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert!(after > before);
+}
+
+#[tokio::test]
+async fn test_zero_sample_rate_mirrors_nothing() {
+    let mirror = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&mirror)
+        .await;
+    let harness = harness(&mirror, 0.0).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    for _ in 0..3 {
+        stream_chat(&server).await;
+    }
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    assert!(mirror.received_requests().await.unwrap().is_empty());
+    assert_eq!(harness.provider.requests().len(), 3);
+}
@@ -0,0 +1,155 @@
+//! Pass-through request weight tests
+//!
+//! Pass-through usage is counted as the path's weight from the request weight
+//! table (`aiRequests` in the batch payload): weighted paths multiply the
+//! count, weight 0 isn't counted, and unmatched paths and typed endpoints
+//! count 1. The table can be replaced through `/admin/usage/request-weights`.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::{header, StatusCode};
+use axum_test::{TestRequest, TestServer};
+use serde_json::{json, Value};
+
+use sentinel::testing::{
+    constants, extract_token_counts, parse_batch_payload, MockAiProvider, MockEndpoint, MockReply,
+    TestHarness,
+};
+
+const ADMIN_KEY: &str = "admin-secret";
+
+async fn server() -> (TestHarness, TestServer) {
+    let provider = Arc::new(
+        MockAiProvider::new()
+            .with_reply(
+                MockEndpoint::Passthrough,
+                MockReply::Json(json!({"ok": true})),
+            )
+            .with_reply(
+                MockEndpoint::ChatCompletions,
+                MockReply::chat_completion("gpt-4o-mini", "Hello", 10, 5),
+            ),
+    );
+    let harness = TestHarness::with_config(provider, |config| {
+        config.server.admin_api_key = Some(ADMIN_KEY.to_string());
+    })
+    .await;
+    let server = TestServer::new(harness.router()).unwrap();
+    (harness, server)
+}
+
+fn authorized(request: TestRequest) -> TestRequest {
+    request.add_header(
+        header::AUTHORIZATION,
+        format!("Bearer {}", constants::TEST_JWT_TOKEN)
+            .parse()
+            .unwrap(),
+    )
+}
+
+async fn post(server: &TestServer, path: &str) {
+    authorized(server.post(path))
+        .json(&json!({"input": "Hi"}))
+        .await
+        .assert_status_ok();
+}
+
+/// `aiRequests` of every increment received so far, waiting for at least one
+async fn reported_requests(harness: &TestHarness) -> Vec<i64> {
+    let requests = harness
+        .wait_for_batch_requests(1, Duration::from_secs(3))
+        .await;
+    assert!(!requests.is_empty(), "Expected batch-increment request");
+    requests
+        .iter()
+        .flat_map(parse_batch_payload)
+        .map(|item| extract_token_counts(&item).2)
+        .collect()
+}
+
+#[tokio::test]
+async fn test_weighted_path_multiplies_requests() {
+    let (harness, server) = server().await;
+
+    post(&server, "/v1/images/generations").await;
+
+    assert_eq!(reported_requests(&harness).await, vec![5]);
+}
+
+#[tokio::test]
+async fn test_zero_weight_is_not_counted() {
+    let (harness, server) = server().await;
+
+    authorized(server.get("/v1/models/gpt-4o-mini/details"))
+        .await
+        .assert_status_ok();
+    // Followed by a counted request, so the batch is known to have flushed
+    post(&server, "/v1/audio/speech").await;
+
+    assert_eq!(reported_requests(&harness).await, vec![1]);
+    assert_eq!(
+        harness
+            .provider
+            .requests_for(MockEndpoint::Passthrough)
+            .len(),
+        2
+    );
+}
+
+#[tokio::test]
+async fn test_unmatched_path_counts_once() {
+    let (harness, server) = server().await;
+
+    post(&server, "/v1/moderations").await;
+
+    assert_eq!(reported_requests(&harness).await, vec![1]);
+}
+
+#[tokio::test]
+async fn test_typed_endpoints_count_once() {
+    let (harness, server) = server().await;
+
+    authorized(server.post("/v1/chat/completions"))
+        .json(&json!({
+            "model": "gpt-4o-mini",
+            "messages": [{"role": "user", "content": "Hi"}]
+        }))
+        .await
+        .assert_status_ok();
+
+    assert_eq!(reported_requests(&harness).await, vec![1]);
+}
+
+#[tokio::test]
+async fn test_weights_reload_through_admin() {
+    let (harness, server) = server().await;
+    let admin = |request: TestRequest| {
+        request.add_header("X-Admin-Key".parse().unwrap(), ADMIN_KEY.parse().unwrap())
+    };
+
+    let response = admin(server.put("/admin/usage/request-weights"))
+        .json(&json!({"weights": {"/audio": 3, "/images/generations": 0}}))
+        .await;
+    response.assert_status_ok();
+    let status: Value = response.json();
+    assert_eq!(
+        status["weights"],
+        json!({"/audio": 3, "/images/generations": 0})
+    );
+    assert_eq!(status["startup_weights"]["/images/generations"], 5);
+
+    post(&server, "/v1/images/generations").await;
+    post(&server, "/v1/audio/speech").await;
+    assert_eq!(reported_requests(&harness).await, vec![3]);
+
+    admin(server.put("/admin/usage/request-weights"))
+        .json(&json!({"weights": {"audio": 3}}))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    let response = admin(server.delete("/admin/usage/request-weights")).await;
+    response.assert_status_ok();
+    let status: Value = response.json();
+    assert_eq!(status["weights"], status["startup_weights"]);
+}
@@ -0,0 +1,200 @@
+//! Response header tests
+//!
+//! Responses Sentinel builds carry one `Content-Type` per class: JSON bodies
+//! (results, error envelopes, health and usage) `application/json`, with
+//! `; charset=utf-8` under `JSON_RESPONSE_CHARSET`, and event streams the full
+//! SSE set. Pass-through responses keep the provider's headers.
+
+use std::sync::Arc;
+
+use axum::http::{header, HeaderName};
+use axum_test::{TestResponse, TestServer};
+use serde_json::{json, Value};
+
+use sentinel::testing::{constants, MockAiProvider, MockEndpoint, MockReply, TestHarness};
+
+const SSE_HEADERS: [(HeaderName, &str); 4] = [
+    (header::CONTENT_TYPE, "text/event-stream; charset=utf-8"),
+    (header::CACHE_CONTROL, "no-cache"),
+    (header::CONNECTION, "keep-alive"),
+    (HeaderName::from_static("x-accel-buffering"), "no"),
+];
+
+/// Server whose provider answers chat with `chat` and pass-through with `passthrough`
+async fn start(json_charset: bool, chat: MockReply, passthrough: MockReply) -> TestServer {
+    let provider = Arc::new(
+        MockAiProvider::new()
+            .with_reply(MockEndpoint::ChatCompletions, chat)
+            .with_reply(MockEndpoint::Passthrough, passthrough),
+    );
+    let harness = TestHarness::with_config(provider, |config| {
+        config.server.json_response_charset = json_charset;
+    })
+    .await;
+    TestServer::new(harness.router()).unwrap()
+}
+
+fn auth() -> (HeaderName, axum::http::HeaderValue) {
+    (
+        header::AUTHORIZATION,
+        format!("Bearer {}", constants::TEST_JWT_TOKEN)
+            .parse()
+            .unwrap(),
+    )
+}
+
+async fn post(server: &TestServer, path: &str, body: Value) -> TestResponse {
+    let (name, value) = auth();
+    server.post(path).add_header(name, value).json(&body).await
+}
+
+async fn get(server: &TestServer, path: &str) -> TestResponse {
+    let (name, value) = auth();
+    server.get(path).add_header(name, value).await
+}
+
+fn v1_chat(stream: bool) -> Value {
+    json!({
+        "model": "gpt-4o-mini",
+        "messages": [{"role": "user", "content": "Hello"}],
+        "stream": stream
+    })
+}
+
+fn native_chat(stream: bool) -> Value {
+    json!({
+        "tier": "simple",
+        "messages": [{"role": "user", "content": "Hello"}],
+        "stream": stream
+    })
+}
+
+/// JSON response: exactly `content_type`, none of the SSE headers
+fn assert_json(response: &TestResponse, content_type: &str) {
+    assert_eq!(response.header(header::CONTENT_TYPE), content_type);
+    assert_eq!(
+        response
+            .headers()
+            .get_all(header::CONTENT_TYPE)
+            .iter()
+            .count(),
+        1
+    );
+    for (name, _) in &SSE_HEADERS[1..] {
+        assert!(
+            response.maybe_header(name).is_none(),
+            "unexpected {} on a JSON response",
+            name
+        );
+    }
+}
+
+fn assert_sse(response: &TestResponse) {
+    for (name, value) in &SSE_HEADERS {
+        assert_eq!(response.header(name), *value, "{}", name);
+        assert_eq!(
+            response.headers().get_all(name).iter().count(),
+            1,
+            "{}",
+            name
+        );
+    }
+}
+
+/// Every synthesized JSON class against one server
+async fn assert_json_classes(server: &TestServer, content_type: &str) {
+    // Results
+    let response = post(server, "/v1/chat/completions", v1_chat(false)).await;
+    response.assert_status_ok();
+    assert_json(&response, content_type);
+    let response = post(server, "/native/v1/chat/completions", native_chat(false)).await;
+    response.assert_status_ok();
+    assert_json(&response, content_type);
+
+    // Usage and health
+    let response = get(server, "/v1/usage").await;
+    response.assert_status_ok();
+    assert_json(&response, content_type);
+    let response = server.get("/health/live").await;
+    response.assert_status_ok();
+    assert_json(&response, content_type);
+
+    // Error envelopes: auth failure, unknown route, bad request body
+    let response = server
+        .post("/v1/chat/completions")
+        .json(&v1_chat(false))
+        .await;
+    response.assert_status_unauthorized();
+    assert_json(&response, content_type);
+    let response = server.get("/not-a-route").await;
+    response.assert_status_not_found();
+    assert_json(&response, content_type);
+    let response = post(server, "/v1/chat/completions", json!({"model": 1})).await;
+    response.assert_status_bad_request();
+    assert_json(&response, content_type);
+}
+
+#[tokio::test]
+async fn test_json_responses_without_charset_by_default() {
+    let server = start(
+        false,
+        MockReply::chat_completion("gpt-4o-mini", "Hi!", 10, 5),
+        MockReply::Json(json!({"results": []})),
+    )
+    .await;
+    assert_json_classes(&server, "application/json").await;
+}
+
+#[tokio::test]
+async fn test_json_responses_with_charset_when_configured() {
+    let server = start(
+        true,
+        MockReply::chat_completion("gpt-4o-mini", "Hi!", 10, 5),
+        MockReply::Json(json!({"results": []})),
+    )
+    .await;
+    assert_json_classes(&server, "application/json; charset=utf-8").await;
+}
+
+#[tokio::test]
+async fn test_streams_carry_full_sse_header_set() {
+    for json_charset in [false, true] {
+        let server = start(
+            json_charset,
+            MockReply::chat_stream("gpt-4o-mini", "Hi there", Some((10, 2))),
+            MockReply::Json(json!({"results": []})),
+        )
+        .await;
+
+        let response = post(&server, "/v1/chat/completions", v1_chat(true)).await;
+        response.assert_status_ok();
+        assert_sse(&response);
+        let response = post(&server, "/native/v1/chat/completions", native_chat(true)).await;
+        response.assert_status_ok();
+        assert_sse(&response);
+    }
+}
+
+#[tokio::test]
+async fn test_pass_through_keeps_provider_content_type() {
+    let server = start(
+        true,
+        MockReply::chat_completion("gpt-4o-mini", "Hi!", 10, 5),
+        MockReply::Json(json!({"results": []})),
+    )
+    .await;
+    let response = post(&server, "/v1/moderations", json!({"input": "Hello"})).await;
+    response.assert_status_ok();
+    assert_json(&response, "application/json");
+
+    let server = start(
+        true,
+        MockReply::chat_completion("gpt-4o-mini", "Hi!", 10, 5),
+        MockReply::sse(vec![json!({"type": "speech.audio.done"})]),
+    )
+    .await;
+    let response = post(&server, "/v1/audio/speech", json!({"input": "Hello"})).await;
+    response.assert_status_ok();
+    assert_eq!(response.header(header::CONTENT_TYPE), "text/event-stream");
+    assert!(response.maybe_header("x-accel-buffering").is_none());
+}
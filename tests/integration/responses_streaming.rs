@@ -0,0 +1,130 @@
+//! Responses API streaming tests
+//!
+//! Replay captured `/v1/responses` SSE transcripts through the mock provider
+//! and verify the stream reaches the client untouched while usage and
+//! failures are accounted from the Responses event set.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::header;
+use axum_test::TestServer;
+use bytes::Bytes;
+use serde_json::json;
+
+use sentinel::testing::{
+    constants, extract_token_counts, parse_batch_payload, MockAiProvider, MockEndpoint,
+    MockReply, TestHarness,
+};
+
+/// Captured Responses stream with a function call, keep-alive comment and `event:` lines
+const TOOL_CALL_TRANSCRIPT: &str = concat!(
+    "event: response.created\n",
+    "data: {\"type\":\"response.created\",\"sequence_number\":0,\"response\":{\"id\":\"resp_abc\",\"object\":\"response\",\"status\":\"in_progress\",\"model\":\"gpt-4o-mini\",\"output\":[],\"usage\":null}}\n\n",
+    "event: response.in_progress\n",
+    "data: {\"type\":\"response.in_progress\",\"sequence_number\":1,\"response\":{\"id\":\"resp_abc\",\"status\":\"in_progress\",\"usage\":null}}\n\n",
+    ": keep-alive\n\n",
+    "event: response.output_item.added\n",
+    "data: {\"type\":\"response.output_item.added\",\"sequence_number\":2,\"output_index\":0,\"item\":{\"id\":\"fc_1\",\"type\":\"function_call\",\"status\":\"in_progress\",\"call_id\":\"call_1\",\"name\":\"get_weather\",\"arguments\":\"\"}}\n\n",
+    "event: response.function_call_arguments.delta\n",
+    "data: {\"type\":\"response.function_call_arguments.delta\",\"sequence_number\":3,\"item_id\":\"fc_1\",\"output_index\":0,\"delta\":\"{\\\"city\\\":\"}\n\n",
+    "event: response.function_call_arguments.delta\n",
+    "data: {\"type\":\"response.function_call_arguments.delta\",\"sequence_number\":4,\"item_id\":\"fc_1\",\"output_index\":0,\"delta\":\"\\\"Paris\\\"}\"}\n\n",
+    "event: response.function_call_arguments.done\n",
+    "data: {\"type\":\"response.function_call_arguments.done\",\"sequence_number\":5,\"item_id\":\"fc_1\",\"output_index\":0,\"arguments\":\"{\\\"city\\\":\\\"Paris\\\"}\"}\n\n",
+    "event: response.output_item.done\n",
+    "data: {\"type\":\"response.output_item.done\",\"sequence_number\":6,\"output_index\":0,\"item\":{\"id\":\"fc_1\",\"type\":\"function_call\",\"status\":\"completed\",\"call_id\":\"call_1\",\"name\":\"get_weather\",\"arguments\":\"{\\\"city\\\":\\\"Paris\\\"}\"}}\n\n",
+    "event: response.completed\n",
+    "data: {\"type\":\"response.completed\",\"sequence_number\":7,\"response\":{\"id\":\"resp_abc\",\"status\":\"completed\",\"usage\":{\"input_tokens\":57,\"input_tokens_details\":{\"cached_tokens\":0},\"output_tokens\":18,\"output_tokens_details\":{\"reasoning_tokens\":0},\"total_tokens\":75}}}\n\n",
+);
+
+/// Captured Responses stream that fails after partial output
+const FAILED_TRANSCRIPT: &str = concat!(
+    "event: response.created\n",
+    "data: {\"type\":\"response.created\",\"sequence_number\":0,\"response\":{\"id\":\"resp_bad\",\"status\":\"in_progress\",\"usage\":null}}\n\n",
+    "event: response.output_text.delta\n",
+    "data: {\"type\":\"response.output_text.delta\",\"sequence_number\":1,\"item_id\":\"msg_1\",\"output_index\":0,\"content_index\":0,\"delta\":\"Partial answer\"}\n\n",
+    "event: response.failed\n",
+    "data: {\"type\":\"response.failed\",\"sequence_number\":2,\"response\":{\"id\":\"resp_bad\",\"status\":\"failed\",\"error\":{\"code\":\"server_error\",\"message\":\"The server had an error\"},\"usage\":null}}\n\n",
+);
+
+/// Split a transcript into small chunks that cut across SSE lines
+fn chunked(transcript: &str, size: usize) -> MockReply {
+    MockReply::Stream(
+        transcript
+            .as_bytes()
+            .chunks(size)
+            .map(Bytes::copy_from_slice)
+            .collect(),
+    )
+}
+
+async fn stream_responses(harness: &TestHarness) -> String {
+    let server = TestServer::new(harness.router()).unwrap();
+    let response = server
+        .post("/v1/responses")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN).parse().unwrap(),
+        )
+        .json(&json!({
+            "model": "gpt-4o-mini",
+            "stream": true,
+            "input": [{"role": "user", "content": "What's the weather in Paris?"}],
+            "tools": [{"type": "function", "name": "get_weather", "parameters": {"type": "object"}}]
+        }))
+        .await;
+    response.assert_status_ok();
+    assert_eq!(
+        response.header(header::CONTENT_TYPE).to_str().unwrap(),
+        "text/event-stream; charset=utf-8"
+    );
+    response.text()
+}
+
+#[tokio::test]
+async fn test_responses_stream_forwarded_untouched_with_exact_usage() {
+    let provider = Arc::new(
+        MockAiProvider::new().with_reply(MockEndpoint::Responses, chunked(TOOL_CALL_TRANSCRIPT, 37)),
+    );
+    let harness = TestHarness::with_provider(provider).await;
+
+    let body = stream_responses(&harness).await;
+    assert_eq!(body, TOOL_CALL_TRANSCRIPT, "Stream must reach the client byte-for-byte");
+
+    let requests = harness.wait_for_batch_requests(1, Duration::from_secs(2)).await;
+    assert!(!requests.is_empty(), "Expected a batch-increment request");
+    let increments = parse_batch_payload(&requests[0]);
+    assert_eq!(increments.len(), 1);
+    let (input_tokens, output_tokens, requests_count) = extract_token_counts(&increments[0]);
+    assert_eq!(input_tokens, 57);
+    assert_eq!(output_tokens, 18);
+    assert_eq!(requests_count, 1);
+
+    assert!(harness.state.health_tracker.is_available("mock", "gpt-4o-mini"));
+}
+
+#[tokio::test]
+async fn test_responses_stream_failure_reported_to_health_tracker() {
+    let provider = Arc::new(
+        MockAiProvider::new().with_reply(MockEndpoint::Responses, chunked(FAILED_TRANSCRIPT, 64)),
+    );
+    let harness = TestHarness::with_provider(provider).await;
+
+    let body = stream_responses(&harness).await;
+    assert_eq!(body, FAILED_TRANSCRIPT);
+
+    let unavailable = harness.state.health_tracker.get_unavailable_providers();
+    assert_eq!(
+        unavailable,
+        vec![("mock".to_string(), "gpt-4o-mini".to_string(), 1)]
+    );
+
+    // Usage is still tracked (estimated, since the failure carried none)
+    let requests = harness.wait_for_batch_requests(1, Duration::from_secs(2)).await;
+    assert!(!requests.is_empty(), "Expected a batch-increment request");
+    let increments = parse_batch_payload(&requests[0]);
+    let (input_tokens, output_tokens, _) = extract_token_counts(&increments[0]);
+    assert!(input_tokens > 0);
+    assert!(output_tokens > 0);
+}
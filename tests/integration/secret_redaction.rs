@@ -0,0 +1,158 @@
+//! Secret redaction tests
+//!
+//! An upstream echoing credentials in its error bodies must not reach the
+//! client: typed handler errors, pass-through error bodies and SSE error
+//! events all come back with the secrets replaced by `[redacted:...]`.
+
+use std::sync::Arc;
+
+use axum::http::{header, StatusCode};
+use axum_test::{TestResponse, TestServer};
+use serde_json::{json, Value};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use sentinel::proxy::{redirect, AiProvider};
+use sentinel::testing::{constants, test_config, test_state, zion_stub};
+use sentinel::{routes, OpenAIProvider};
+
+/// Key-shaped string echoed by the upstream
+const LEAKED_KEY: &str = "sk-proj-Abc123Def456Ghi789Jkl012Mno345";
+
+struct RedactionHarness {
+    server: TestServer,
+    upstream: MockServer,
+    _zion: MockServer,
+}
+
+async fn harness() -> RedactionHarness {
+    let zion = zion_stub().await;
+    let upstream = MockServer::start().await;
+    let config = test_config(&zion.uri(), &format!("{}/v1", upstream.uri()));
+    let provider: Arc<dyn AiProvider> = Arc::new(OpenAIProvider::new(
+        redirect::provider_client().unwrap(),
+        &config,
+    ));
+    let state = test_state(config, provider).await;
+
+    RedactionHarness {
+        server: TestServer::new(routes::create_router(state)).unwrap(),
+        upstream,
+        _zion: zion,
+    }
+}
+
+/// An OpenAI-style error quoting both the leaked key and the Zion key
+fn leaky_error() -> Value {
+    json!({
+        "error": {
+            "message": format!(
+                "Incorrect API key provided: {} (forwarded with {})",
+                LEAKED_KEY,
+                constants::TEST_ZION_API_KEY
+            ),
+            "type": "invalid_request_error",
+            "code": "invalid_api_key"
+        }
+    })
+}
+
+fn assert_redacted(body: &str) {
+    assert!(!body.contains(LEAKED_KEY), "key leaked: {}", body);
+    assert!(
+        !body.contains(constants::TEST_ZION_API_KEY),
+        "Zion key leaked: {}",
+        body
+    );
+    assert!(body.contains("[redacted:"), "nothing redacted: {}", body);
+}
+
+async fn chat(harness: &RedactionHarness, stream: bool) -> TestResponse {
+    harness
+        .server
+        .post("/v1/chat/completions")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .json(&json!({
+            "model": "gpt-4o",
+            "stream": stream,
+            "messages": [{"role": "user", "content": "Hi"}]
+        }))
+        .await
+}
+
+#[tokio::test]
+async fn test_typed_handler_errors_are_redacted() {
+    let harness = harness().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(401).set_body_json(leaky_error()))
+        .mount(&harness.upstream)
+        .await;
+
+    let response = chat(&harness, false).await;
+    response.assert_status(StatusCode::BAD_GATEWAY);
+    let body = response.text();
+    assert_redacted(&body);
+    assert!(body.contains("invalid_api_key"));
+}
+
+#[tokio::test]
+async fn test_passthrough_error_bodies_are_redacted() {
+    let harness = harness().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/files"))
+        .respond_with(ResponseTemplate::new(401).set_body_json(leaky_error()))
+        .mount(&harness.upstream)
+        .await;
+
+    let response = harness
+        .server
+        .get("/v1/files")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .await;
+    response.assert_status(StatusCode::UNAUTHORIZED);
+    let body = response.text();
+    assert_redacted(&body);
+    assert_eq!(
+        response.header(header::CONTENT_LENGTH).to_str().unwrap(),
+        body.len().to_string()
+    );
+}
+
+#[tokio::test]
+async fn test_sse_error_events_are_redacted() {
+    let harness = harness().await;
+    let events = format!(
+        "data: {}\n\ndata: {}\n\n",
+        json!({
+            "id": "chatcmpl-1", "object": "chat.completion.chunk", "model": "gpt-4o",
+            "choices": [{"index": 0, "delta": {"content": "Hel"}, "finish_reason": null}]
+        }),
+        leaky_error()
+    );
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(events),
+        )
+        .mount(&harness.upstream)
+        .await;
+
+    let response = chat(&harness, true).await;
+    response.assert_status_ok();
+    let body = response.text();
+    assert_redacted(&body);
+    assert!(body.contains("Hel"));
+}
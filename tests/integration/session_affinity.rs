@@ -0,0 +1,108 @@
+//! Affinity hint tests
+//!
+//! With `AFFINITY_SECRET` set, native responses carry `X-Sentinel-Affinity`
+//! for the conversation; echoing it back lets the replica use its local
+//! session copy instead of reading the session store.
+
+use std::sync::Arc;
+
+use axum::http::header;
+use axum_test::{TestResponse, TestServer};
+use serde_json::json;
+
+use sentinel::native::affinity::{self, AFFINITY_HEADER};
+use sentinel::testing::{constants, MockAiProvider, MockEndpoint, MockReply, TestHarness};
+
+const SECRET: &str = "affinity-secret";
+
+async fn harness(secret: Option<&str>) -> TestHarness {
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_completion("gpt-4o-mini", "Hello", 10, 5),
+    ));
+    TestHarness::with_config(provider, |config| {
+        config.provider.affinity_secret = secret.map(str::to_string);
+    })
+    .await
+}
+
+async fn send(server: &TestServer, conversation_id: &str, hint: Option<&str>) -> TestResponse {
+    let mut request = server
+        .post("/native/v1/chat/completions")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .json(&json!({
+            "tier": "simple",
+            "conversation_id": conversation_id,
+            "messages": [{"role": "user", "content": "Hello"}]
+        }));
+    if let Some(hint) = hint {
+        request = request.add_header(AFFINITY_HEADER.parse().unwrap(), hint.parse().unwrap());
+    }
+    request.await
+}
+
+#[tokio::test]
+async fn test_hint_round_trips_and_second_request_uses_local_copy() {
+    let harness = harness(Some(SECRET)).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let first = send(&server, "conv-1", None).await;
+    first.assert_status_ok();
+    let hint = first.header(AFFINITY_HEADER).to_str().unwrap().to_string();
+    assert_eq!(hint, affinity::hint(SECRET, "conv-1"));
+    assert_eq!(harness.state.session_manager.local_hits(), 0);
+
+    let second = send(&server, "conv-1", Some(&hint)).await;
+    second.assert_status_ok();
+    assert_eq!(second.header(AFFINITY_HEADER).to_str().unwrap(), hint);
+    assert_eq!(harness.state.session_manager.local_hits(), 1);
+
+    // The session is still durable in the shared store
+    assert!(harness
+        .state
+        .session_manager
+        .get("conv-1")
+        .await
+        .unwrap()
+        .is_some());
+}
+
+#[tokio::test]
+async fn test_invalid_hint_reads_the_session_store() {
+    let harness = harness(Some(SECRET)).await;
+    let server = TestServer::new(harness.router()).unwrap();
+    send(&server, "conv-1", None).await.assert_status_ok();
+
+    // A hint for another conversation, or from another secret, isn't trusted
+    for hint in [
+        affinity::hint(SECRET, "conv-2"),
+        affinity::hint("other-secret", "conv-1"),
+        "not-a-hint".to_string(),
+    ] {
+        send(&server, "conv-1", Some(&hint))
+            .await
+            .assert_status_ok();
+    }
+    assert_eq!(harness.state.session_manager.local_hits(), 0);
+}
+
+#[tokio::test]
+async fn test_no_hint_without_secret() {
+    let harness = harness(None).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = send(&server, "conv-1", None).await;
+    response.assert_status_ok();
+    assert!(response.maybe_header(AFFINITY_HEADER).is_none());
+
+    let hint = affinity::hint(SECRET, "conv-1");
+    send(&server, "conv-1", Some(&hint))
+        .await
+        .assert_status_ok();
+    assert_eq!(harness.state.session_manager.local_hits(), 0);
+}
@@ -0,0 +1,246 @@
+//! Session export tests
+//!
+//! `GET /native/v1/sessions/{conversation_id}` returns the caller's session
+//! with its usage and, with `SESSION_HISTORY_MAX_MESSAGES` set, the stored
+//! messages a page at a time. Other users' sessions 404 exactly like
+//! sessions that don't exist.
+
+use std::sync::Arc;
+
+use axum::http::{header, HeaderValue, StatusCode};
+use axum_test::{TestResponse, TestServer};
+use serde_json::{json, Value};
+
+use wiremock::matchers::{header as header_eq, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use sentinel::native::types::Tier;
+use sentinel::testing::zion::profile_body;
+use sentinel::testing::{
+    constants, MockAiProvider, MockEndpoint, MockReply, TestHarness, STUB_PRIORITY,
+};
+
+async fn harness(history_max_messages: usize) -> (TestHarness, TestServer) {
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_completion("gpt-4o-mini", "Hello", 10, 5),
+    ));
+    let harness = TestHarness::with_config(provider, |config| {
+        config.provider.session_history_max_messages = history_max_messages;
+    })
+    .await;
+    let server = TestServer::new(harness.router()).unwrap();
+    (harness, server)
+}
+
+fn auth() -> HeaderValue {
+    format!("Bearer {}", constants::TEST_JWT_TOKEN)
+        .parse()
+        .unwrap()
+}
+
+async fn chat(server: &TestServer, conversation_id: &str, messages: Value) {
+    server
+        .post("/native/v1/chat/completions")
+        .add_header(header::AUTHORIZATION, auth())
+        .json(&json!({
+            "tier": "simple",
+            "conversation_id": conversation_id,
+            "messages": messages
+        }))
+        .await
+        .assert_status_ok();
+}
+
+async fn export(
+    server: &TestServer,
+    conversation_id: &str,
+    query: &[(&str, &str)],
+) -> TestResponse {
+    server
+        .get(&format!("/native/v1/sessions/{}", conversation_id))
+        .add_query_params(query)
+        .add_header(header::AUTHORIZATION, auth())
+        .await
+}
+
+#[tokio::test]
+async fn test_owner_exports_session_with_usage() {
+    let (_harness, server) = harness(0).await;
+    chat(
+        &server,
+        "conv-1",
+        json!([{"role": "user", "content": "Hi"}]),
+    )
+    .await;
+    chat(
+        &server,
+        "conv-1",
+        json!([{"role": "user", "content": "Hi again"}]),
+    )
+    .await;
+
+    let response = export(&server, "conv-1", &[]).await;
+    response.assert_status_ok();
+    let body: Value = response.json();
+    assert_eq!(body["conversation_id"], "conv-1");
+    assert_eq!(body["tier"], "simple");
+    assert_eq!(body["model"], "gpt-4o-mini");
+    assert_eq!(
+        body["usage"],
+        json!({"requests": 2, "input_tokens": 20, "output_tokens": 10})
+    );
+    assert!(body["last_used_at"].as_i64().unwrap() >= body["created_at"].as_i64().unwrap());
+    // No history without SESSION_HISTORY_MAX_MESSAGES
+    assert!(body.get("history").is_none());
+}
+
+#[tokio::test]
+async fn test_other_users_sessions_look_nonexistent() {
+    let (harness, server) = harness(10).await;
+    harness
+        .state
+        .session_manager
+        .create(
+            "conv-x",
+            "openai",
+            "gpt-4o-mini",
+            Tier::Simple,
+            "someone-else",
+        )
+        .await
+        .unwrap();
+
+    let foreign = export(&server, "conv-x", &[]).await;
+    foreign.assert_status(StatusCode::NOT_FOUND);
+    let missing = export(&server, "conv-y", &[]).await;
+    missing.assert_status(StatusCode::NOT_FOUND);
+
+    let foreign: Value = foreign.json();
+    assert_eq!(foreign["error"]["code"], "session_not_found");
+    assert_eq!(
+        foreign.to_string().replace("conv-x", "conv-y"),
+        missing.json::<Value>().to_string()
+    );
+
+    // Unauthenticated requests don't get that far
+    server
+        .get("/native/v1/sessions/conv-x")
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_shared_conversation_id_keeps_owner_history() {
+    let (harness, server) = harness(10).await;
+    // A second user, authenticated by its own token
+    let other_token = "other-user-token";
+    let mut other = profile_body();
+    other["data"]["externalId"] = json!("other-user");
+    Mock::given(method("GET"))
+        .and(path("/api/v1/users/me"))
+        .and(header_eq("authorization", format!("Bearer {}", other_token).as_str()))
+        .respond_with(ResponseTemplate::new(200).set_body_json(other))
+        .with_priority(STUB_PRIORITY - 1)
+        .mount(&harness.zion)
+        .await;
+
+    chat(&server, "conv-1", json!([{"role": "user", "content": "Mine"}])).await;
+
+    // The other user's request under the same conversation_id is refused
+    let response = server
+        .post("/native/v1/chat/completions")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", other_token).parse::<HeaderValue>().unwrap(),
+        )
+        .json(&json!({
+            "tier": "simple",
+            "conversation_id": "conv-1",
+            "messages": [{"role": "user", "content": "Theirs"}]
+        }))
+        .await;
+    response.assert_status(StatusCode::NOT_FOUND);
+    assert_eq!(response.json::<Value>()["error"]["code"], "session_not_found");
+    assert_eq!(harness.provider.requests_for(MockEndpoint::ChatCompletions).len(), 1);
+
+    let body: Value = export(&server, "conv-1", &[]).await.json();
+    assert_eq!(body["usage"]["requests"], 1);
+    assert_eq!(
+        body["history"]["messages"],
+        json!([
+            {"role": "user", "content": "Mine"},
+            {"role": "assistant", "content": "Hello"}
+        ])
+    );
+}
+
+#[tokio::test]
+async fn test_history_is_capped_and_paginated() {
+    let (_harness, server) = harness(4).await;
+    chat(
+        &server,
+        "conv-1",
+        json!([{"role": "user", "content": "One"}]),
+    )
+    .await;
+    chat(
+        &server,
+        "conv-1",
+        json!([
+            {"role": "system", "content": "Be brief."},
+            {"role": "user", "content": "One"},
+            {"role": "assistant", "content": "Hello"},
+            {"role": "user", "content": "Two"}
+        ]),
+    )
+    .await;
+
+    // 4 client messages plus the reply: the oldest is dropped
+    let response = export(&server, "conv-1", &[("limit", "3")]).await;
+    response.assert_status_ok();
+    let history = &response.json::<Value>()["history"];
+    assert_eq!(history["truncated"], true);
+    assert_eq!(history["total_messages"], 4);
+    assert_eq!(history["first_index"], 0);
+    assert_eq!(history["next_after"], 2);
+    assert_eq!(
+        history["messages"],
+        json!([
+            {"role": "user", "content": "One"},
+            {"role": "assistant", "content": "Hello"},
+            {"role": "user", "content": "Two"}
+        ])
+    );
+
+    let response = export(&server, "conv-1", &[("after", "2"), ("limit", "3")]).await;
+    response.assert_status_ok();
+    let history = &response.json::<Value>()["history"];
+    assert_eq!(history["first_index"], 3);
+    assert_eq!(
+        history["messages"],
+        json!([{"role": "assistant", "content": "Hello"}])
+    );
+    assert!(history.get("next_after").is_none());
+
+    // Past the end is an empty page
+    let response = export(&server, "conv-1", &[("after", "10")]).await;
+    response.assert_status_ok();
+    assert_eq!(response.json::<Value>()["history"]["messages"], json!([]));
+
+    for query in [
+        ("limit", "0"),
+        ("limit", "1001"),
+        ("after", "-1"),
+        ("limit", "many"),
+    ] {
+        let response = export(&server, "conv-1", &[query]).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        assert_eq!(
+            response.json::<Value>()["error"]["type"],
+            "invalid_request_error",
+            "{:?}",
+            query
+        );
+    }
+}
@@ -0,0 +1,131 @@
+//! Native sessions against a real Redis
+//!
+//! Sessions live in Redis, so a conversation's tier binding survives a
+//! restart and is shared by every replica. Each test builds separate
+//! `SessionManager`s (standing in for replicas, or for a process before and
+//! after a restart) over one Redis and checks the compare-and-set script
+//! keeps tier upgrades. Skipped when Redis isn't available.
+
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+use sentinel::cache::RedisCache;
+use sentinel::native::types::Tier;
+use sentinel::native::SessionManager;
+
+const SESSION_TTL: u64 = 60;
+
+/// Test helper to connect to Redis (skips test if unavailable)
+async fn get_test_redis() -> Option<redis::aio::ConnectionManager> {
+    let client = redis::Client::open("redis://127.0.0.1:6379").ok()?;
+    client.get_connection_manager().await.ok()
+}
+
+/// A session manager as one replica would build it
+fn replica(redis: &redis::aio::ConnectionManager) -> Arc<SessionManager> {
+    Arc::new(SessionManager::new(
+        Arc::new(RedisCache::new(redis.clone(), SESSION_TTL)),
+        SESSION_TTL,
+    ))
+}
+
+/// Unique user and conversation IDs, so parallel runs don't share sessions
+fn ids() -> (String, String) {
+    let suffix = Uuid::new_v4();
+    (
+        format!("test-user-{}", suffix),
+        format!("test-conv-{}", suffix),
+    )
+}
+
+#[tokio::test]
+async fn test_session_survives_restart_and_is_shared_by_replicas() {
+    let redis = match get_test_redis().await {
+        Some(r) => r,
+        None => {
+            eprintln!("Skipping test: Redis not available");
+            return;
+        }
+    };
+    let (user, conversation) = ids();
+
+    let created = replica(&redis)
+        .create(&conversation, "openai", "gpt-4o", Tier::Moderate, &user)
+        .await
+        .unwrap();
+
+    // A fresh manager (restarted process or another replica) sees the binding
+    let other = replica(&redis);
+    assert_eq!(
+        other.get(&conversation).await.unwrap(),
+        Some(created.clone())
+    );
+
+    // ...and keeps it against a lower tier
+    let kept = other
+        .upgrade_tier(&conversation, &user, "openai", "gpt-4o-mini", Tier::Simple)
+        .await
+        .unwrap();
+    assert_eq!(kept, created);
+
+    let upgraded = other
+        .upgrade_tier(&conversation, &user, "openai", "o3", Tier::Complex)
+        .await
+        .unwrap();
+    assert_eq!(upgraded.tier, Tier::Complex);
+    assert_eq!(upgraded.version, created.version + 1);
+    assert_eq!(
+        replica(&redis).get(&conversation).await.unwrap(),
+        Some(upgraded)
+    );
+
+    assert_eq!(replica(&redis).delete_for_user(&user).await.unwrap(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_replicas_keep_highest_tier() {
+    let redis = match get_test_redis().await {
+        Some(r) => r,
+        None => {
+            eprintln!("Skipping test: Redis not available");
+            return;
+        }
+    };
+    let (user, conversation) = ids();
+    let replicas = [replica(&redis), replica(&redis), replica(&redis)];
+    let tiers = [Tier::Simple, Tier::Complex, Tier::Moderate, Tier::Simple];
+
+    let handles: Vec<_> = (0..32)
+        .map(|i| {
+            let manager = replicas[i % replicas.len()].clone();
+            let tier = tiers[i % tiers.len()];
+            let (user, conversation) = (user.clone(), conversation.clone());
+            tokio::spawn(async move {
+                let model = format!("model-{:?}", tier);
+                match manager.get(&conversation).await.unwrap() {
+                    Some(_) => {
+                        manager
+                            .upgrade_tier(&conversation, &user, "openai", &model, tier)
+                            .await
+                    }
+                    None => {
+                        manager
+                            .create(&conversation, "openai", &model, tier, &user)
+                            .await
+                    }
+                }
+                .unwrap()
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.await.unwrap();
+    }
+
+    let session = replica(&redis).get(&conversation).await.unwrap().unwrap();
+    assert_eq!(session.tier, Tier::Complex);
+    assert_eq!(session.model, "model-Complex");
+
+    replica(&redis).delete_for_user(&user).await.unwrap();
+}
@@ -0,0 +1,138 @@
+//! Session deletion tests
+//!
+//! Native chat requests with a `conversation_id` create sessions; the user's
+//! session index lets `DELETE /v1/sessions` and the admin variant remove them
+//! (or, with `?mode=redact`, only their history) without touching other
+//! users' sessions.
+
+use std::sync::Arc;
+
+use axum::http::header;
+use axum_test::TestServer;
+use serde_json::{json, Value};
+
+use sentinel::native::types::Tier;
+use sentinel::testing::{constants, MockAiProvider, MockEndpoint, MockReply, TestHarness};
+
+const ADMIN_KEY: &str = "admin-secret";
+
+async fn harness() -> TestHarness {
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_completion("gpt-4o-mini", "Hello", 10, 5),
+    ));
+    TestHarness::with_config(provider, |config| {
+        config.server.admin_api_key = Some(ADMIN_KEY.to_string());
+        config.provider.session_history_max_messages = 10;
+    })
+    .await
+}
+
+fn auth() -> axum::http::HeaderValue {
+    format!("Bearer {}", constants::TEST_JWT_TOKEN).parse().unwrap()
+}
+
+async fn start_conversation(server: &TestServer, conversation_id: &str) {
+    server
+        .post("/native/v1/chat/completions")
+        .add_header(header::AUTHORIZATION, auth())
+        .json(&json!({
+            "tier": "simple",
+            "conversation_id": conversation_id,
+            "messages": [{"role": "user", "content": "Hello"}]
+        }))
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_delete_sessions_removes_callers_sessions() {
+    let harness = harness().await;
+    let server = TestServer::new(harness.router()).unwrap();
+    start_conversation(&server, "conv-a").await;
+    start_conversation(&server, "conv-b").await;
+    // Another user's session is left alone
+    harness
+        .state
+        .session_manager
+        .create("conv-other", "openai", "gpt-4o-mini", Tier::Simple, "someone-else")
+        .await
+        .unwrap();
+
+    let response = server.delete("/v1/sessions").add_header(header::AUTHORIZATION, auth()).await;
+    response.assert_status_ok();
+    let body: Value = response.json();
+    assert_eq!(body["external_id"], constants::TEST_EXTERNAL_ID);
+    assert_eq!(body["deleted"], 2);
+
+    let sessions = &harness.state.session_manager;
+    assert!(sessions.get("conv-a").await.unwrap().is_none());
+    assert!(sessions.get("conv-b").await.unwrap().is_none());
+    assert!(sessions.get("conv-other").await.unwrap().is_some());
+
+    // Idempotent
+    let response = server.delete("/v1/sessions").add_header(header::AUTHORIZATION, auth()).await;
+    response.assert_status_ok();
+    assert_eq!(response.json::<Value>()["deleted"], 0);
+}
+
+#[tokio::test]
+async fn test_admin_deletes_user_sessions() {
+    let harness = harness().await;
+    let server = TestServer::new(harness.router()).unwrap();
+    start_conversation(&server, "conv-a").await;
+
+    let path = format!("/admin/users/{}/sessions", constants::TEST_EXTERNAL_ID);
+    let response = server
+        .delete(&path)
+        .add_header("X-Admin-Key".parse().unwrap(), ADMIN_KEY.parse().unwrap())
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.json::<Value>()["deleted"], 1);
+    assert!(harness.state.session_manager.get("conv-a").await.unwrap().is_none());
+
+    // A new conversation afterwards is indexed again
+    start_conversation(&server, "conv-a").await;
+    let sessions = harness
+        .state
+        .session_manager
+        .list_for_user(constants::TEST_EXTERNAL_ID)
+        .await
+        .unwrap();
+    assert_eq!(sessions.len(), 1);
+}
+
+#[tokio::test]
+async fn test_redact_keeps_sessions_without_history() {
+    let harness = harness().await;
+    let server = TestServer::new(harness.router()).unwrap();
+    start_conversation(&server, "conv-a").await;
+    let sessions = &harness.state.session_manager;
+    assert!(sessions.history("conv-a").await.unwrap().is_some());
+
+    let response = server
+        .delete("/v1/sessions")
+        .add_query_param("mode", "redact")
+        .add_header(header::AUTHORIZATION, auth())
+        .await;
+    response.assert_status_ok();
+    let body: Value = response.json();
+    assert_eq!(body["redacted"], 1);
+    assert!(body.get("deleted").is_none());
+
+    assert!(sessions.history("conv-a").await.unwrap().is_none());
+    let session = sessions.get("conv-a").await.unwrap().unwrap();
+    assert_eq!(session.external_id, constants::TEST_EXTERNAL_ID);
+    assert_eq!(session.usage.requests, 1);
+
+    // Idempotent, for the admin variant too
+    let path = format!("/admin/users/{}/sessions", constants::TEST_EXTERNAL_ID);
+    let response = server
+        .delete(&path)
+        .add_query_param("mode", "redact")
+        .add_header("X-Admin-Key".parse().unwrap(), ADMIN_KEY.parse().unwrap())
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.json::<Value>()["redacted"], 0);
+    assert!(sessions.get("conv-a").await.unwrap().is_some());
+}
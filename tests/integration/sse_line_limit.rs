@@ -0,0 +1,95 @@
+//! SSE line limit tests
+//!
+//! The mock provider streams one valid chunk followed by data that never
+//! contains a newline; the handler must stop forwarding once the configured
+//! line limit is crossed and finish with an `sse_line_too_long` error event.
+
+use std::sync::Arc;
+
+use axum::http::header;
+use axum_test::TestServer;
+use bytes::Bytes;
+use serde_json::json;
+
+use sentinel::testing::{constants, MockAiProvider, MockEndpoint, MockReply, TestHarness};
+
+const LIMIT: usize = 4096;
+const CHUNK: usize = 1024;
+
+fn runaway_stream() -> MockReply {
+    let first = json!({
+        "id": "chatcmpl-runaway",
+        "object": "chat.completion.chunk",
+        "created": 1700000000,
+        "model": "gpt-4o-mini",
+        "choices": [{"index": 0, "delta": {"content": "Hello"}, "finish_reason": null}]
+    });
+    let mut chunks = vec![Bytes::from(format!("data: {}\n\n", first))];
+    // 64 KiB of newline-free data, far past the limit
+    chunks.extend((0..64).map(|_| Bytes::from(vec![b'x'; CHUNK])));
+    MockReply::Stream(chunks)
+}
+
+async fn harness() -> TestHarness {
+    let provider = Arc::new(MockAiProvider::new().with_reply(MockEndpoint::ChatCompletions, runaway_stream()));
+    TestHarness::with_config(provider, |config| {
+        config.provider.sse_max_line_bytes = LIMIT;
+    })
+    .await
+}
+
+async fn stream_chat(server: &TestServer, path: &str, body: serde_json::Value) -> String {
+    let response = server
+        .post(path)
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN).parse().unwrap(),
+        )
+        .json(&body)
+        .await;
+    response.assert_status_ok();
+    response.text()
+}
+
+fn assert_aborted(text: &str) {
+    assert!(text.contains("Hello"), "first chunk missing: {}", &text[..text.len().min(200)]);
+    assert!(text.trim_end().ends_with('}'), "stream did not end with an event");
+    assert!(text.contains("sse_line_too_long"));
+    // Nothing past the chunk that crossed the limit was forwarded
+    assert!(text.len() < LIMIT + 2 * CHUNK, "forwarded {} bytes", text.len());
+}
+
+#[tokio::test]
+async fn test_v1_stream_aborted_on_oversized_line() {
+    let harness = harness().await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let text = stream_chat(
+        &server,
+        "/v1/chat/completions",
+        json!({
+            "model": "gpt-4o-mini",
+            "messages": [{"role": "user", "content": "Hi"}],
+            "stream": true
+        }),
+    )
+    .await;
+    assert_aborted(&text);
+}
+
+#[tokio::test]
+async fn test_native_stream_aborted_on_oversized_line() {
+    let harness = harness().await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let text = stream_chat(
+        &server,
+        "/native/v1/chat/completions",
+        json!({
+            "messages": [{"role": "user", "content": "Hi"}],
+            "stream": true
+        }),
+    )
+    .await;
+    assert_aborted(&text);
+}
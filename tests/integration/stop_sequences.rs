@@ -0,0 +1,99 @@
+//! Stop sequence validation parity tests
+//!
+//! The same `stop` payloads are sent to `/v1/chat/completions` and
+//! `/native/v1/chat/completions`; both must reject them with a 400 naming the
+//! offending entry, without calling the provider.
+
+use std::sync::Arc;
+
+use axum::http::{header, StatusCode};
+use axum_test::TestServer;
+use serde_json::{json, Value};
+
+use sentinel::testing::{constants, MockAiProvider, MockEndpoint, MockReply, TestHarness};
+
+async fn harness() -> TestHarness {
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_completion("gpt-4o-mini", "Hello!", 10, 5),
+    ));
+    TestHarness::with_provider(provider).await
+}
+
+/// POST the same stop value to both APIs, returning (status, error message) for each
+async fn send_both(server: &TestServer, stop: Value) -> [(StatusCode, String); 2] {
+    let auth = format!("Bearer {}", constants::TEST_JWT_TOKEN);
+    let bodies = [
+        (
+            "/v1/chat/completions",
+            json!({
+                "model": "gpt-4o-mini",
+                "messages": [{"role": "user", "content": "Hi"}],
+                "stop": stop
+            }),
+        ),
+        (
+            "/native/v1/chat/completions",
+            json!({
+                "messages": [{"role": "user", "content": "Hi"}],
+                "stop": stop
+            }),
+        ),
+    ];
+
+    let mut results = Vec::new();
+    for (path, body) in bodies {
+        let response = server
+            .post(path)
+            .add_header(header::AUTHORIZATION, auth.parse().unwrap())
+            .json(&body)
+            .await;
+        let status = response.status_code();
+        let message = response.json::<Value>()["error"]["message"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        results.push((status, message));
+    }
+    results.try_into().unwrap()
+}
+
+async fn assert_rejected_by_both(stop: Value, expected: &str) {
+    let harness = harness().await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    for (status, message) in send_both(&server, stop).await {
+        assert_eq!(status, StatusCode::BAD_REQUEST, "message: {}", message);
+        assert!(message.contains(expected), "'{}' missing from '{}'", expected, message);
+    }
+    assert!(harness.provider.requests().is_empty());
+}
+
+#[tokio::test]
+async fn test_too_many_stop_sequences_rejected_by_both() {
+    assert_rejected_by_both(json!(["a", "b", "c", "d", "e"]), "at most 4 sequences, got 5").await;
+}
+
+#[tokio::test]
+async fn test_empty_stop_entry_rejected_by_both() {
+    assert_rejected_by_both(json!(["STOP", ""]), "stop[1] must not be empty").await;
+}
+
+#[tokio::test]
+async fn test_overlong_stop_entry_rejected_by_both() {
+    assert_rejected_by_both(json!(["END", "x".repeat(300)]), "stop[1] exceeds").await;
+}
+
+#[tokio::test]
+async fn test_valid_stop_sequences_accepted_by_both() {
+    let harness = harness().await;
+    let server = TestServer::new(harness.router()).unwrap();
+    harness.provider.push_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_completion("gpt-4o-mini", "Hello!", 10, 5),
+    );
+
+    for (status, message) in send_both(&server, json!(["STOP", "END"])).await {
+        assert_eq!(status, StatusCode::OK, "message: {}", message);
+    }
+}
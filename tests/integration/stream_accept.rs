@@ -0,0 +1,126 @@
+//! `Accept` header checks for chat streams
+//!
+//! A `stream: true` request whose `Accept` header excludes
+//! `text/event-stream` is refused with a 406 (`STREAM_ACCEPT_MISMATCH=reject`)
+//! or streamed anyway (`warn`); a non-streaming request accepting only
+//! `text/event-stream` gets `X-Sentinel-Stream-Hint` in either mode.
+
+use std::sync::Arc;
+
+use axum::http::{header, StatusCode};
+use axum_test::{TestResponse, TestServer};
+use serde_json::{json, Value};
+
+use sentinel::proxy::stream_accept::{StreamAcceptMismatch, STREAM_HINT_HEADER};
+use sentinel::testing::{constants, MockAiProvider, MockEndpoint, MockReply, TestHarness};
+
+const MODES: [StreamAcceptMismatch; 2] = [StreamAcceptMismatch::Reject, StreamAcceptMismatch::Warn];
+
+/// Harness in `mode` whose provider answers streams or JSON, as `stream` asks
+async fn start(mode: StreamAcceptMismatch, stream: bool) -> (TestHarness, TestServer) {
+    let reply = if stream {
+        MockReply::chat_stream("gpt-4o-mini", "Hello", None)
+    } else {
+        MockReply::chat_completion("gpt-4o-mini", "Hello", 10, 5)
+    };
+    let provider =
+        Arc::new(MockAiProvider::new().with_reply(MockEndpoint::ChatCompletions, reply));
+    let harness = TestHarness::with_config(provider, |config| {
+        config.provider.stream_accept_mismatch = mode;
+    })
+    .await;
+    let server = TestServer::new(harness.router()).unwrap();
+    (harness, server)
+}
+
+async fn chat(server: &TestServer, path: &str, stream: bool, accept: &str) -> TestResponse {
+    let mut body = json!({"stream": stream, "messages": [{"role": "user", "content": "Hi"}]});
+    // The native API picks the model from the tier
+    if path.starts_with("/v1") {
+        body["model"] = json!("gpt-4o-mini");
+    }
+    server
+        .post(path)
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN).parse().unwrap(),
+        )
+        .add_header(header::ACCEPT, accept.parse().unwrap())
+        .json(&body)
+        .await
+}
+
+fn content_type(response: &TestResponse) -> String {
+    response.header(header::CONTENT_TYPE).to_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn test_stream_to_json_client() {
+    for mode in MODES {
+        let (harness, server) = start(mode, true).await;
+        let response = chat(&server, "/v1/chat/completions", true, "application/json").await;
+
+        if mode == StreamAcceptMismatch::Reject {
+            response.assert_status(StatusCode::NOT_ACCEPTABLE);
+            let error = &response.json::<Value>()["error"];
+            assert_eq!(error["code"], "not_acceptable");
+            assert_eq!(error["retryable"], false);
+            assert!(error["message"].as_str().unwrap().contains("text/event-stream"));
+            assert_eq!(harness.provider.requests_for(MockEndpoint::ChatCompletions).len(), 0);
+        } else {
+            response.assert_status_ok();
+            assert!(content_type(&response).starts_with("text/event-stream"), "{mode:?}");
+        }
+        assert!(response.maybe_header(STREAM_HINT_HEADER).is_none());
+    }
+}
+
+#[tokio::test]
+async fn test_stream_to_sse_client() {
+    for mode in MODES {
+        let (_harness, server) = start(mode, true).await;
+        let response = chat(&server, "/v1/chat/completions", true, "text/event-stream").await;
+
+        response.assert_status_ok();
+        assert!(content_type(&response).starts_with("text/event-stream"), "{mode:?}");
+        assert!(response.maybe_header(STREAM_HINT_HEADER).is_none());
+    }
+}
+
+#[tokio::test]
+async fn test_json_to_sse_client_gets_hint() {
+    for mode in MODES {
+        let (_harness, server) = start(mode, false).await;
+        let response = chat(&server, "/v1/chat/completions", false, "text/event-stream").await;
+
+        response.assert_status_ok();
+        assert!(content_type(&response).starts_with("application/json"), "{mode:?}");
+        assert!(response.header(STREAM_HINT_HEADER).to_str().unwrap().contains("\"stream\": true"));
+    }
+}
+
+#[tokio::test]
+async fn test_json_to_json_client() {
+    for mode in MODES {
+        let (_harness, server) = start(mode, false).await;
+        let response = chat(&server, "/v1/chat/completions", false, "application/json").await;
+
+        response.assert_status_ok();
+        assert!(content_type(&response).starts_with("application/json"), "{mode:?}");
+        assert!(response.maybe_header(STREAM_HINT_HEADER).is_none());
+    }
+}
+
+#[tokio::test]
+async fn test_native_chat_negotiates_the_same() {
+    let (harness, server) = start(StreamAcceptMismatch::Reject, true).await;
+    let response = chat(&server, "/native/v1/chat/completions", true, "application/json").await;
+    response.assert_status(StatusCode::NOT_ACCEPTABLE);
+    assert_eq!(response.json::<Value>()["error"]["code"], "not_acceptable");
+    assert_eq!(harness.provider.requests_for(MockEndpoint::ChatCompletions).len(), 0);
+
+    let (_harness, server) = start(StreamAcceptMismatch::Reject, false).await;
+    let response = chat(&server, "/native/v1/chat/completions", false, "text/event-stream").await;
+    response.assert_status_ok();
+    assert!(response.maybe_header(STREAM_HINT_HEADER).is_some());
+}
@@ -0,0 +1,172 @@
+//! Upstream chunk boundary tests
+//!
+//! Providers cut their SSE streams wherever the network does: inside the JSON
+//! of an event, inside a multi-byte character, between the two newlines that
+//! end an event. Whatever the chunking, the client must receive the same
+//! events on `/v1` and `/native/v1`, and the same tokens must be billed, both
+//! from the provider's usage chunk and from the content-based estimate.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::header;
+use serde_json::{json, Value};
+
+use sentinel::testing::{
+    constants, extract_token_counts, parse_batch_payload, sse_events, Chunking, MockAiProvider,
+    MockEndpoint, StreamScript, TestHarness,
+};
+
+const CHUNKINGS: [Chunking; 4] = [
+    Chunking::PerToken,
+    Chunking::FixedBytes(1),
+    Chunking::FixedBytes(7),
+    Chunking::MidJson,
+];
+
+/// Text with multi-byte characters, so `MidJson` cuts inside them
+const TEXT: &str = "Grüße aus dem Café — naïve façade";
+
+/// Endpoint under test and the streaming request it takes
+#[derive(Debug, Clone, Copy)]
+enum Api {
+    OpenAi,
+    Native,
+}
+
+impl Api {
+    fn path(self) -> &'static str {
+        match self {
+            Api::OpenAi => "/v1/chat/completions",
+            Api::Native => "/native/v1/chat/completions",
+        }
+    }
+
+    fn request(self) -> Value {
+        let messages = json!([{"role": "user", "content": "Say hello"}]);
+        match self {
+            // The usage chunk only reaches `/v1` clients that ask for it
+            Api::OpenAi => json!({
+                "model": "gpt-4o-mini",
+                "stream": true,
+                "stream_options": {"include_usage": true},
+                "messages": messages
+            }),
+            Api::Native => json!({"tier": "simple", "stream": true, "messages": messages}),
+        }
+    }
+}
+
+/// Stream `script` through `api`, returning the response body and the billed (input, output)
+async fn stream(api: Api, script: &StreamScript) -> (String, (i64, i64)) {
+    let provider =
+        Arc::new(MockAiProvider::new().with_reply(MockEndpoint::ChatCompletions, script.reply()));
+    let harness = TestHarness::with_provider(provider).await;
+    let server = axum_test::TestServer::new(harness.router()).unwrap();
+
+    let response = server
+        .post(api.path())
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .json(&api.request())
+        .await;
+    response.assert_status_ok();
+    let body = response.text();
+
+    let requests = harness
+        .wait_for_batch_requests(1, Duration::from_secs(3))
+        .await;
+    assert!(!requests.is_empty(), "Expected batch-increment request");
+    let items = parse_batch_payload(&requests[0]);
+    let (input, output, _) = extract_token_counts(&items[0]);
+    (body, (input, output))
+}
+
+/// Put `provider_id` back where a native stream sent its Sentinel tool call ID
+///
+/// The re-encoded chunk also omits its null `finish_reason`.
+fn restore_provider_id(events: &mut [Value], provider_id: &str) {
+    let event = events
+        .iter_mut()
+        .find(|event| !event["choices"][0]["delta"]["tool_calls"][0]["id"].is_null())
+        .expect("a tool call delta with an ID");
+    let choice = &mut event["choices"][0];
+    let id = choice["delta"]["tool_calls"][0]["id"].as_str().unwrap();
+    assert!(id.starts_with("call_") && id != provider_id, "{}", id);
+    choice["delta"]["tool_calls"][0]["id"] = json!(provider_id);
+    choice["finish_reason"] = Value::Null;
+}
+
+#[tokio::test]
+async fn test_events_survive_any_chunking() {
+    for api in [Api::OpenAi, Api::Native] {
+        for chunking in CHUNKINGS {
+            let script = StreamScript::new("gpt-4o-mini")
+                .text(TEXT)
+                .usage(12, 6)
+                .chunking(chunking);
+            let (body, billed) = stream(api, &script).await;
+
+            assert_eq!(body, script.body(), "{:?} {:?}", api, chunking);
+            assert_eq!(
+                sse_events(&body),
+                script.events(),
+                "{:?} {:?}",
+                api,
+                chunking
+            );
+            assert_eq!(billed, (12, 6), "{:?} {:?}", api, chunking);
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_estimate_does_not_depend_on_chunking() {
+    for api in [Api::OpenAi, Api::Native] {
+        let baseline = StreamScript::new("gpt-4o-mini").text(TEXT);
+        let (_, expected) = stream(api, &baseline).await;
+        assert!(expected.1 > 0, "{:?}", api);
+
+        for chunking in [Chunking::FixedBytes(1), Chunking::MidJson] {
+            let (body, billed) = stream(api, &baseline.clone().chunking(chunking)).await;
+            assert_eq!(
+                sse_events(&body),
+                baseline.events(),
+                "{:?} {:?}",
+                api,
+                chunking
+            );
+            assert_eq!(billed, expected, "{:?} {:?}", api, chunking);
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_mid_json_tool_call_arguments() {
+    let script = StreamScript::new("gpt-4o-mini")
+        .tool_call("call_1", "get_weather", r#"{"city":"Zürich","unit":"°C"}"#)
+        .chunking(Chunking::MidJson);
+
+    for api in [Api::OpenAi, Api::Native] {
+        let (body, _) = stream(api, &script).await;
+        let mut events = sse_events(&body);
+        if let Api::Native = api {
+            restore_provider_id(&mut events, "call_1");
+        }
+        assert_eq!(events, script.events(), "{:?}", api);
+        assert_eq!(
+            events.last().unwrap()["choices"][0]["finish_reason"],
+            "tool_calls"
+        );
+    }
+
+    // /v1 estimates output from the streamed arguments when usage is missing
+    let (_, expected) = stream(Api::OpenAi, &script.clone().chunking(Chunking::PerToken)).await;
+    let (_, billed) = stream(Api::OpenAi, &script).await;
+    assert!(expected.1 > 0);
+    assert_eq!(billed, expected);
+}
@@ -0,0 +1,206 @@
+//! Per-conversation stream lock tests
+//!
+//! Only one streaming request runs per `conversation_id`. A slow wiremock
+//! upstream keeps the first stream open while a second one arrives; the
+//! second gets 409 `conversation_busy` (or waits, with `STREAM_LOCK_WAIT_MS`).
+//! Another user's request for the conversation never touches the lock.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::{header, StatusCode};
+use axum_test::{TestResponse, TestServer};
+use serde_json::{json, Value};
+use wiremock::matchers::{header as header_eq, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use sentinel::config::Config;
+use sentinel::native::types::Tier;
+use sentinel::proxy::AiProvider;
+use sentinel::testing::zion::profile_body;
+use sentinel::testing::{
+    constants, test_config, test_state, zion_stub, StreamScript, STUB_PRIORITY,
+};
+use sentinel::{routes, AppState, OpenAIProvider};
+
+/// Time the upstream takes to start streaming
+const UPSTREAM_DELAY: Duration = Duration::from_millis(400);
+
+struct LockHarness {
+    state: Arc<AppState>,
+    #[allow(dead_code)]
+    openai: MockServer,
+    #[allow(dead_code)]
+    zion: MockServer,
+}
+
+async fn lock_harness(configure: impl FnOnce(&mut Config)) -> LockHarness {
+    let zion = zion_stub().await;
+    let openai = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(
+            StreamScript::new("gpt-4o-mini")
+                .text("Hello!")
+                .response()
+                .set_delay(UPSTREAM_DELAY),
+        )
+        .mount(&openai)
+        .await;
+
+    let mut config = test_config(&zion.uri(), &format!("{}/v1", openai.uri()));
+    configure(&mut config);
+    let provider: Arc<dyn AiProvider> =
+        Arc::new(OpenAIProvider::new(reqwest::Client::new(), &config));
+    let state = test_state(config, provider).await;
+
+    LockHarness {
+        state,
+        openai,
+        zion,
+    }
+}
+
+/// Send one request, on its own `TestServer` (a server handles one request at a time)
+async fn send(harness: &LockHarness, conversation_id: &str, stream: bool) -> TestResponse {
+    send_as(harness, constants::TEST_JWT_TOKEN, conversation_id, stream).await
+}
+
+/// [`send`] authenticated with `token`
+async fn send_as(
+    harness: &LockHarness,
+    token: &str,
+    conversation_id: &str,
+    stream: bool,
+) -> TestResponse {
+    TestServer::new(routes::create_router(harness.state.clone()))
+        .unwrap()
+        .post("/native/v1/chat/completions")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", token).parse().unwrap(),
+        )
+        .json(&json!({
+            "tier": "simple",
+            "stream": stream,
+            "conversation_id": conversation_id,
+            "messages": [{"role": "user", "content": "Hello"}]
+        }))
+        .await
+}
+
+/// Send a second request for the conversation while the first one is still open
+async fn send_overlapping(harness: &LockHarness) -> (TestResponse, TestResponse) {
+    let second = async {
+        tokio::time::sleep(UPSTREAM_DELAY / 4).await;
+        send(harness, "conv-1", true).await
+    };
+    tokio::join!(send(harness, "conv-1", true), second)
+}
+
+#[tokio::test]
+async fn test_concurrent_stream_is_rejected() {
+    let harness = lock_harness(|_| {}).await;
+
+    let (first, second) = send_overlapping(&harness).await;
+    first.assert_status_ok();
+    assert!(first.text().contains("Hello!"));
+    assert_eq!(second.status_code(), StatusCode::CONFLICT);
+    let error: Value = second.json();
+    assert_eq!(error["error"]["code"], "conversation_busy");
+
+    // The finished stream released the lock
+    send(&harness, "conv-1", true).await.assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_other_conversations_and_non_streaming_are_not_locked() {
+    let harness = lock_harness(|_| {}).await;
+
+    let other = async {
+        tokio::time::sleep(UPSTREAM_DELAY / 4).await;
+        send(&harness, "conv-2", true).await
+    };
+    let (first, other) = tokio::join!(send(&harness, "conv-1", true), other);
+    first.assert_status_ok();
+    other.assert_status_ok();
+
+    // A held lock only blocks streams
+    let lock = harness
+        .state
+        .session_manager
+        .lock_stream("conv-3", 60, Duration::ZERO)
+        .await
+        .unwrap();
+    assert!(lock.is_some());
+    assert_eq!(
+        send(&harness, "conv-3", true).await.status_code(),
+        StatusCode::CONFLICT
+    );
+    assert_ne!(
+        send(&harness, "conv-3", false).await.status_code(),
+        StatusCode::CONFLICT
+    );
+}
+
+#[tokio::test]
+async fn test_waiting_stream_gets_the_lock() {
+    let harness = lock_harness(|config| config.provider.stream_lock_wait_ms = 2_000).await;
+
+    let (first, second) = send_overlapping(&harness).await;
+    first.assert_status_ok();
+    second.assert_status_ok();
+    assert!(second.text().contains("Hello!"));
+}
+
+#[tokio::test]
+async fn test_crashed_stream_lock_expires() {
+    let harness = lock_harness(|config| config.provider.stream_lock_ttl_seconds = 1).await;
+
+    // A replica that died mid-stream never releases its lock
+    let lock = harness
+        .state
+        .session_manager
+        .lock_stream("conv-1", 1, Duration::ZERO)
+        .await
+        .unwrap();
+    std::mem::forget(lock);
+    assert_eq!(
+        send(&harness, "conv-1", true).await.status_code(),
+        StatusCode::CONFLICT
+    );
+
+    tokio::time::sleep(Duration::from_millis(1_100)).await;
+    send(&harness, "conv-1", true).await.assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_other_users_never_take_the_lock() {
+    let harness = lock_harness(|_| {}).await;
+    let other_token = "other-user-token";
+    let mut other = profile_body();
+    other["data"]["externalId"] = json!("other-user");
+    Mock::given(method("GET"))
+        .and(path("/api/v1/users/me"))
+        .and(header_eq("authorization", format!("Bearer {}", other_token).as_str()))
+        .respond_with(ResponseTemplate::new(200).set_body_json(other))
+        .with_priority(STUB_PRIORITY - 1)
+        .mount(&harness.zion)
+        .await;
+    harness
+        .state
+        .session_manager
+        .create("conv-1", "openai", "gpt-4o-mini", Tier::Simple, constants::TEST_EXTERNAL_ID)
+        .await
+        .unwrap();
+
+    // Refused as a missing session while the owner streams, not as busy
+    let other = async {
+        tokio::time::sleep(UPSTREAM_DELAY / 4).await;
+        send_as(&harness, other_token, "conv-1", true).await
+    };
+    let (first, other) = tokio::join!(send(&harness, "conv-1", true), other);
+    first.assert_status_ok();
+    assert_eq!(other.status_code(), StatusCode::NOT_FOUND);
+    assert_eq!(other.json::<Value>()["error"]["code"], "session_not_found");
+}
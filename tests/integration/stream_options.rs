@@ -0,0 +1,157 @@
+//! Client `stream_options` tests
+//!
+//! `/v1/chat/completions` streams the usage chunk to clients that set
+//! `stream_options.include_usage`, exactly once and as received. When the
+//! client didn't, Sentinel asks for it anyway (`STREAM_USAGE_INJECTION`) and
+//! consumes it before the client sees it. Options Sentinel doesn't interpret
+//! reach the provider untouched.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::header;
+use serde_json::{json, Value};
+
+use sentinel::testing::{
+    constants, extract_token_counts, parse_batch_payload, sse_events, Chunking, MockAiProvider,
+    MockEndpoint, StreamScript, TestHarness,
+};
+
+const TEXT: &str = "Hello from the stream";
+
+fn script() -> StreamScript {
+    StreamScript::new("gpt-4o-mini")
+        .text(TEXT)
+        .chunking(Chunking::FixedBytes(7))
+}
+
+struct Streamed {
+    body: String,
+    /// `stream_options` the provider received
+    upstream_options: Value,
+    /// Billed (input, output)
+    billed: (i64, i64),
+}
+
+/// Stream `upstream` to a client sending `stream_options`
+async fn stream(
+    upstream: &StreamScript,
+    stream_options: Option<Value>,
+    inject_usage: bool,
+) -> Streamed {
+    let provider =
+        Arc::new(MockAiProvider::new().with_reply(MockEndpoint::ChatCompletions, upstream.reply()));
+    let harness = TestHarness::with_config(provider, |config| {
+        config.provider.stream_usage_injection = inject_usage;
+    })
+    .await;
+    let server = axum_test::TestServer::new(harness.router()).unwrap();
+
+    let mut request = json!({
+        "model": "gpt-4o-mini",
+        "stream": true,
+        "messages": [{"role": "user", "content": "Say hello"}]
+    });
+    if let Some(options) = stream_options {
+        request["stream_options"] = options;
+    }
+    let response = server
+        .post("/v1/chat/completions")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .json(&request)
+        .await;
+    response.assert_status_ok();
+    let body = response.text();
+
+    let forwarded = harness.provider.requests_for(MockEndpoint::ChatCompletions);
+    assert_eq!(forwarded.len(), 1);
+
+    let requests = harness
+        .wait_for_batch_requests(1, Duration::from_secs(3))
+        .await;
+    assert!(!requests.is_empty(), "Expected batch-increment request");
+    let items = parse_batch_payload(&requests[0]);
+    let (input, output, _) = extract_token_counts(&items[0]);
+
+    Streamed {
+        body,
+        upstream_options: forwarded[0]["stream_options"].clone(),
+        billed: (input, output),
+    }
+}
+
+#[tokio::test]
+async fn test_requested_usage_passes_through_once() {
+    let upstream = script().usage(12, 6);
+
+    let streamed = stream(&upstream, Some(json!({"include_usage": true})), true).await;
+
+    assert_eq!(streamed.upstream_options, json!({"include_usage": true}));
+    assert_eq!(streamed.body, upstream.body());
+    assert_eq!(sse_events(&streamed.body), upstream.events());
+    assert_eq!(streamed.billed, (12, 6));
+}
+
+#[tokio::test]
+async fn test_requested_usage_without_injection() {
+    let upstream = script().usage(12, 6);
+
+    let streamed = stream(&upstream, Some(json!({"include_usage": true})), false).await;
+
+    assert_eq!(streamed.upstream_options, json!({"include_usage": true}));
+    assert_eq!(streamed.body, upstream.body());
+    assert_eq!(streamed.billed, (12, 6));
+}
+
+#[tokio::test]
+async fn test_injected_usage_is_consumed() {
+    let upstream = script().usage(12, 6);
+
+    let streamed = stream(&upstream, None, true).await;
+
+    assert_eq!(streamed.upstream_options, json!({"include_usage": true}));
+    // Every event but the usage chunk, byte for byte
+    let mut events = upstream.events();
+    let usage = events.pop().unwrap();
+    assert_eq!(usage["choices"], json!([]));
+    assert_eq!(sse_events(&streamed.body), events);
+    assert_eq!(streamed.body, script().body());
+    // Still billed from the consumed chunk
+    assert_eq!(streamed.billed, (12, 6));
+}
+
+#[tokio::test]
+async fn test_no_usage_without_injection() {
+    let upstream = script();
+
+    let streamed = stream(&upstream, None, false).await;
+
+    assert_eq!(streamed.upstream_options, Value::Null);
+    assert_eq!(streamed.body, upstream.body());
+    // Estimated from the content
+    let (input, output) = streamed.billed;
+    assert!(input > 0 && output > 0, "{:?}", streamed.billed);
+}
+
+#[tokio::test]
+async fn test_unknown_options_forwarded_untouched() {
+    let upstream = script().usage(12, 6);
+    let options = json!({"include_obfuscation": false, "chunk_hint": {"size": 3}});
+
+    let streamed = stream(&upstream, Some(options), true).await;
+    assert_eq!(
+        streamed.upstream_options,
+        json!({"include_usage": true, "include_obfuscation": false, "chunk_hint": {"size": 3}})
+    );
+    assert_eq!(streamed.body, script().body());
+
+    let options = json!({"include_usage": true, "include_obfuscation": true});
+    let streamed = stream(&upstream, Some(options.clone()), false).await;
+    assert_eq!(streamed.upstream_options, options);
+    assert_eq!(streamed.body, upstream.body());
+}
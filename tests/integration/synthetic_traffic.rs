@@ -0,0 +1,104 @@
+//! Synthetic traffic tests
+//!
+//! `X-Sentinel-Synthetic: true` from a user in `SYNTHETIC_EXTERNAL_IDS`
+//! keeps the request out of Zion usage increments and the local aggregates,
+//! while it is still rate-limited. From anyone else the header is ignored
+//! and the usage is tracked as usual.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::header;
+use axum_test::TestServer;
+use serde_json::json;
+
+use sentinel::testing::{
+    constants, extract_token_counts, parse_batch_payload, MockAiProvider, MockEndpoint, MockReply,
+    TestHarness,
+};
+
+async fn harness(synthetic_external_ids: &[&str]) -> (TestHarness, TestServer) {
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_completion("gpt-4o-mini", "Hello!", 10, 5),
+    ));
+    let harness = TestHarness::with_config(provider, |config| {
+        config.usage.synthetic_external_ids = synthetic_external_ids
+            .iter()
+            .map(|id| id.to_string())
+            .collect();
+    })
+    .await;
+    let server = TestServer::new(harness.router()).unwrap();
+    (harness, server)
+}
+
+async fn synthetic_chat(server: &TestServer) -> axum_test::TestResponse {
+    server
+        .post("/v1/chat/completions")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .add_header(
+            "x-sentinel-synthetic".parse().unwrap(),
+            "true".parse().unwrap(),
+        )
+        .json(&json!({
+            "model": "gpt-4o-mini",
+            "messages": [{"role": "user", "content": "Hi"}]
+        }))
+        .await
+}
+
+async fn recent_requests(harness: &TestHarness) -> i64 {
+    harness
+        .state
+        .batching_tracker
+        .recent_usage()
+        .recent(constants::TEST_EXTERNAL_ID, 1)
+        .await
+        .unwrap()
+        .total
+        .requests
+}
+
+#[tokio::test]
+async fn test_allow_listed_synthetic_request_is_not_tracked() {
+    let (harness, server) = harness(&[constants::TEST_EXTERNAL_ID]).await;
+
+    let response = synthetic_chat(&server).await;
+    response.assert_status_ok();
+    // Still rate-limited
+    assert!(response.maybe_header("x-ratelimit-remaining").is_some());
+
+    let batches = harness
+        .wait_for_batch_requests(1, Duration::from_millis(500))
+        .await;
+    assert!(batches.is_empty(), "synthetic usage reached Zion");
+    assert_eq!(recent_requests(&harness).await, 0);
+    assert_eq!(
+        harness
+            .provider
+            .requests_for(MockEndpoint::ChatCompletions)
+            .len(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn test_spoofed_synthetic_header_is_tracked() {
+    let (harness, server) = harness(&["monitor-1"]).await;
+
+    synthetic_chat(&server).await.assert_status_ok();
+
+    let batches = harness
+        .wait_for_batch_requests(1, Duration::from_secs(3))
+        .await;
+    assert!(!batches.is_empty(), "Expected batch-increment request");
+    let items = parse_batch_payload(&batches[0]);
+    assert_eq!(extract_token_counts(&items[0]), (10, 5, 1));
+    assert_eq!(recent_requests(&harness).await, 1);
+}
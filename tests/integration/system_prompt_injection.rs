@@ -0,0 +1,212 @@
+//! System prompt injection tests
+//!
+//! Verify the configured preamble is forwarded first to the provider and is
+//! included in the fallback prompt token estimate.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::header;
+use axum_test::TestServer;
+use serde_json::{json, Value};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use sentinel::injection::InjectionMode;
+use sentinel::testing::{
+    constants, extract_token_counts, parse_batch_payload, MockAiProvider, MockEndpoint,
+    MockReply, TestHarness,
+};
+
+const PREAMBLE: &str = "You are a support assistant for Acme Corp. Never give legal advice.";
+
+fn auth_header() -> String {
+    format!("Bearer {}", constants::TEST_JWT_TOKEN)
+}
+
+/// Chat completion reply without a usage field, forcing token estimation
+fn reply_without_usage() -> MockReply {
+    MockReply::Json(json!({
+        "id": "chatcmpl-test",
+        "object": "chat.completion",
+        "created": 1700000000,
+        "model": "gpt-4o-mini",
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": "Hello!"},
+            "finish_reason": "stop"
+        }]
+    }))
+}
+
+/// Send a `/v1` chat request and return the forwarded body and tracked input tokens
+async fn run_v1_chat(
+    configure: impl FnOnce(&mut sentinel::Config),
+    messages: Value,
+) -> (Value, i64) {
+    let provider = Arc::new(
+        MockAiProvider::new().with_reply(MockEndpoint::ChatCompletions, reply_without_usage()),
+    );
+    let harness = TestHarness::with_config(provider.clone(), |config| {
+        // A reply without usage only passes with validation off
+        config.provider.validate_upstream_responses = false;
+        configure(config);
+    })
+    .await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = server
+        .post("/v1/chat/completions")
+        .add_header(header::AUTHORIZATION, auth_header().parse().unwrap())
+        .json(&json!({ "model": "gpt-4o-mini", "messages": messages }))
+        .await;
+    response.assert_status_ok();
+
+    let body: Value = response.json();
+    assert!(
+        !body.to_string().contains(PREAMBLE),
+        "Injected preamble must not leak into the response"
+    );
+
+    let sent = provider.requests_for(MockEndpoint::ChatCompletions);
+    assert_eq!(sent.len(), 1);
+
+    let requests = harness.wait_for_batch_requests(1, Duration::from_secs(2)).await;
+    assert!(!requests.is_empty(), "Expected a batch-increment request");
+    let increments = parse_batch_payload(&requests[0]);
+    let (input_tokens, _, _) = extract_token_counts(&increments[0]);
+
+    (sent[0].clone(), input_tokens)
+}
+
+#[tokio::test]
+async fn test_v1_prepend_forwards_preamble_and_counts_it() {
+    let messages = json!([
+        {"role": "system", "content": "Be brief."},
+        {"role": "user", "content": "Hi"}
+    ]);
+
+    let (baseline_body, baseline_input) = run_v1_chat(|_| {}, messages.clone()).await;
+    let (injected_body, injected_input) = run_v1_chat(
+        |config| config.provider.system_prompt_injection = Some(PREAMBLE.to_string()),
+        messages,
+    )
+    .await;
+
+    assert_eq!(baseline_body["messages"].as_array().unwrap().len(), 2);
+
+    let forwarded = injected_body["messages"].as_array().unwrap();
+    assert_eq!(forwarded.len(), 3);
+    assert_eq!(forwarded[0]["role"], "system");
+    assert_eq!(forwarded[0]["content"], PREAMBLE);
+    assert_eq!(forwarded[1]["content"], "Be brief.");
+
+    assert!(
+        injected_input > baseline_input,
+        "Estimate should include the preamble ({} vs {})",
+        injected_input,
+        baseline_input
+    );
+}
+
+#[tokio::test]
+async fn test_v1_replace_empty_keeps_client_system_prompt() {
+    let (body, _) = run_v1_chat(
+        |config| {
+            config.provider.system_prompt_injection = Some(PREAMBLE.to_string());
+            config.provider.system_prompt_injection_mode = InjectionMode::ReplaceEmpty;
+        },
+        json!([
+            {"role": "system", "content": "Be brief."},
+            {"role": "user", "content": "Hi"}
+        ]),
+    )
+    .await;
+
+    let forwarded = body["messages"].as_array().unwrap();
+    assert_eq!(forwarded.len(), 2);
+    assert_eq!(forwarded[0]["content"], "Be brief.");
+}
+
+#[tokio::test]
+async fn test_v1_off_mode_disables_injection() {
+    let (body, _) = run_v1_chat(
+        |config| {
+            config.provider.system_prompt_injection = Some(PREAMBLE.to_string());
+            config.provider.system_prompt_injection_mode = InjectionMode::Off;
+        },
+        json!([{"role": "user", "content": "Hi"}]),
+    )
+    .await;
+
+    let forwarded = body["messages"].as_array().unwrap();
+    assert_eq!(forwarded.len(), 1);
+    assert_eq!(forwarded[0]["role"], "user");
+}
+
+#[tokio::test]
+async fn test_native_streaming_uses_tier_override_and_counts_it() {
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_stream("gpt-4o-mini", "Hello there", None),
+    ));
+    let harness = TestHarness::with_config(provider.clone(), |config| {
+        config.provider.system_prompt_injection = Some("Global preamble.".to_string());
+    })
+    .await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/v1/tiers/config"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "success": true,
+            "data": {
+                "version": "1.0.0",
+                "updatedAt": "2024-01-01T00:00:00Z",
+                "tiers": {
+                    "simple": [{
+                        "provider": "openai",
+                        "model": "gpt-4o-mini",
+                        "relativeCost": 1,
+                        "inputPricePerMillion": 0.15,
+                        "outputPricePerMillion": 0.60
+                    }],
+                    "moderate": [],
+                    "complex": []
+                },
+                "systemPrompts": { "simple": PREAMBLE }
+            }
+        })))
+        .mount(&harness.zion)
+        .await;
+
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = server
+        .post("/native/v1/chat/completions")
+        .add_header(header::AUTHORIZATION, auth_header().parse().unwrap())
+        .json(&json!({
+            "stream": true,
+            "messages": [{"role": "user", "content": "Hi"}]
+        }))
+        .await;
+    response.assert_status_ok();
+    assert!(!response.text().contains(PREAMBLE));
+
+    let sent = provider.requests_for(MockEndpoint::ChatCompletions);
+    assert_eq!(sent.len(), 1);
+    let forwarded = sent[0]["messages"].as_array().unwrap();
+    assert_eq!(forwarded.len(), 2);
+    assert_eq!(forwarded[0]["role"], "system");
+    assert_eq!(forwarded[0]["content"], PREAMBLE);
+
+    // No usage in the stream, so the input count is the tiktoken estimate
+    let requests = harness.wait_for_batch_requests(1, Duration::from_secs(2)).await;
+    assert!(!requests.is_empty(), "Expected a batch-increment request");
+    let increments = parse_batch_payload(&requests[0]);
+    let (input_tokens, _, _) = extract_token_counts(&increments[0]);
+    assert!(
+        input_tokens > 10,
+        "Estimate should include the preamble, got {}",
+        input_tokens
+    );
+}
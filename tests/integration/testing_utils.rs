@@ -0,0 +1,129 @@
+//! Tests for the library's `testing` module
+//!
+//! These tests only use `sentinel::testing` (plus axum-test as the HTTP driver),
+//! proving an external crate can run Sentinel end-to-end with nothing more than
+//! the `test-utils` feature.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::{header, StatusCode};
+use axum_test::TestServer;
+use serde_json::json;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use sentinel::testing::{
+    constants, extract_token_counts, parse_batch_payload, MockAiProvider, MockEndpoint,
+    MockReply, TestHarness,
+};
+
+fn auth_header() -> String {
+    format!("Bearer {}", constants::TEST_JWT_TOKEN)
+}
+
+#[tokio::test]
+async fn test_harness_chat_completion_tracks_usage() {
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_completion("gpt-4o-mini", "Hello!", 12, 3),
+    ));
+    let harness = TestHarness::with_provider(provider.clone()).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = server
+        .post("/v1/chat/completions")
+        .add_header(header::AUTHORIZATION, auth_header().parse().unwrap())
+        .json(&json!({
+            "model": "gpt-4o-mini",
+            "messages": [{"role": "user", "content": "Hi"}]
+        }))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["choices"][0]["message"]["content"], "Hello!");
+
+    let sent = provider.requests_for(MockEndpoint::ChatCompletions);
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0]["model"], "gpt-4o-mini");
+
+    let requests = harness.wait_for_batch_requests(1, Duration::from_secs(2)).await;
+    assert!(!requests.is_empty(), "Expected a batch-increment request");
+    let increments = parse_batch_payload(&requests[0]);
+    assert_eq!(increments[0]["email"], constants::TEST_EMAIL);
+    assert_eq!(extract_token_counts(&increments[0]), (12, 3, 1));
+}
+
+#[tokio::test]
+async fn test_harness_streaming_chat_completion() {
+    let harness = TestHarness::new().await;
+    harness.provider.push_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_stream("gpt-4o-mini", "Hello there", Some((8, 2))),
+    );
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = server
+        .post("/v1/chat/completions")
+        .add_header(header::AUTHORIZATION, auth_header().parse().unwrap())
+        .json(&json!({
+            "model": "gpt-4o-mini",
+            "stream": true,
+            "messages": [{"role": "user", "content": "Hi"}]
+        }))
+        .await;
+
+    response.assert_status_ok();
+    let text = response.text();
+    assert!(text.contains("Hello"));
+    assert!(text.contains("[DONE]"));
+
+    let requests = harness.wait_for_batch_requests(1, Duration::from_secs(2)).await;
+    assert!(!requests.is_empty(), "Expected a batch-increment request");
+    let increments = parse_batch_payload(&requests[0]);
+    assert_eq!(extract_token_counts(&increments[0]), (8, 2, 1));
+}
+
+#[tokio::test]
+async fn test_harness_upstream_error() {
+    let harness = TestHarness::new().await;
+    harness.provider.push_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::Error { status: 500, message: "boom".to_string() },
+    );
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = server
+        .post("/v1/chat/completions")
+        .add_header(header::AUTHORIZATION, auth_header().parse().unwrap())
+        .json(&json!({
+            "model": "gpt-4o-mini",
+            "messages": [{"role": "user", "content": "Hi"}]
+        }))
+        .await;
+
+    response.assert_status(StatusCode::BAD_GATEWAY);
+}
+
+#[tokio::test]
+async fn test_harness_zion_stub_overridable() {
+    let harness = TestHarness::new().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/users/me"))
+        .respond_with(ResponseTemplate::new(401))
+        .mount(&harness.zion)
+        .await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = server
+        .post("/v1/chat/completions")
+        .add_header(header::AUTHORIZATION, auth_header().parse().unwrap())
+        .json(&json!({
+            "model": "gpt-4o-mini",
+            "messages": [{"role": "user", "content": "Hi"}]
+        }))
+        .await;
+
+    response.assert_status(StatusCode::UNAUTHORIZED);
+}
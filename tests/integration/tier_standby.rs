@@ -0,0 +1,170 @@
+//! Standby tier config tests
+//!
+//! The Zion stub answers the tier config with 503, as during an outage at
+//! pod startup. A standby copy seeded in the cache (Redis in production) or
+//! in `TIER_CONFIG_STANDBY_PATH` keeps native chat working while it is
+//! younger than `TIER_CONFIG_MAX_STALENESS_HOURS`, and `/health/ready`
+//! reports its age.
+
+use std::sync::Arc;
+
+use axum::http::header;
+use axum_test::TestServer;
+use serde_json::{json, Value};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use sentinel::config::Config;
+use sentinel::testing::zion::tier_config_body;
+use sentinel::testing::{
+    constants, MockAiProvider, MockEndpoint, MockReply, TestHarness, STUB_PRIORITY,
+};
+use sentinel::tiers::standby::{self, StandbyTierConfig};
+
+const TWO_HOURS: i64 = 2 * 3600;
+
+async fn harness(configure: impl FnOnce(&mut Config)) -> TestHarness {
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_completion("gpt-4o-mini", "Hello", 10, 5),
+    ));
+    TestHarness::with_config(provider, configure).await
+}
+
+/// Answer the tier config with `response`, overriding earlier answers
+async fn zion_tier_config(harness: &TestHarness, response: ResponseTemplate, priority: u8) {
+    Mock::given(method("GET"))
+        .and(path("/api/v1/tiers/config"))
+        .respond_with(response)
+        .with_priority(priority)
+        .mount(&harness.zion)
+        .await;
+}
+
+async fn zion_outage(harness: &TestHarness) {
+    zion_tier_config(harness, ResponseTemplate::new(503), STUB_PRIORITY - 1).await;
+}
+
+/// The stub's tier config, fetched `age_seconds` ago
+fn standby_copy(age_seconds: i64) -> StandbyTierConfig {
+    StandbyTierConfig {
+        config: serde_json::from_value(tier_config_body()["data"].clone()).unwrap(),
+        fetched_at: chrono::Utc::now().timestamp() - age_seconds,
+    }
+}
+
+async fn native_chat(server: &TestServer) -> axum_test::TestResponse {
+    server
+        .post("/native/v1/chat/completions")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .json(&json!({
+            "tier": "simple",
+            "messages": [{"role": "user", "content": "Hello"}]
+        }))
+        .await
+}
+
+async fn readiness(server: &TestServer) -> Value {
+    let response = server.get("/health/ready").await;
+    response.assert_status_ok();
+    response.json()
+}
+
+#[tokio::test]
+async fn test_standby_config_served_during_outage() {
+    let harness = harness(|_| {}).await;
+    let cache = harness.state.tier_config_cache.clone();
+    zion_outage(&harness).await;
+    cache.store_standby(&standby_copy(TWO_HOURS)).await;
+
+    // Startup falls back to the standby copy and keeps retrying Zion
+    let retry = standby::load_at_startup(cache.clone()).await;
+    assert!(retry.is_some());
+    retry.unwrap().abort();
+
+    let server = TestServer::new(harness.router()).unwrap();
+    native_chat(&server).await.assert_status_ok();
+    let forwarded = harness.provider.requests_for(MockEndpoint::ChatCompletions);
+    assert_eq!(forwarded[0]["model"], "gpt-4o-mini");
+
+    let ready = readiness(&server).await;
+    assert_eq!(ready["status"], "degraded");
+    let standby = &ready["tier_config_standby"];
+    assert_eq!(standby["source"], "redis");
+    assert_eq!(standby["version"], "1.0.0");
+    let age = standby["age_seconds"].as_i64().unwrap();
+    assert!(
+        (TWO_HOURS..TWO_HOURS + 60).contains(&age),
+        "age_seconds = {age}"
+    );
+
+    // Zion answers again: the fresh config replaces the standby copy
+    let recovered = ResponseTemplate::new(200).set_body_json(tier_config_body());
+    zion_tier_config(&harness, recovered, STUB_PRIORITY - 2).await;
+    cache.refresh().await.unwrap();
+    let ready = readiness(&server).await;
+    assert_eq!(ready["status"], "healthy");
+    assert!(ready.get("tier_config_standby").is_none());
+}
+
+#[tokio::test]
+async fn test_stale_standby_config_is_refused() {
+    let harness = harness(|config| {
+        config.zion.tier_config_max_staleness_hours = 1;
+    })
+    .await;
+    let cache = harness.state.tier_config_cache.clone();
+    zion_outage(&harness).await;
+    cache.store_standby(&standby_copy(TWO_HOURS)).await;
+
+    let server = TestServer::new(harness.router()).unwrap();
+    let status = native_chat(&server).await.status_code();
+    assert!(status.is_server_error(), "status = {status}");
+    assert!(harness
+        .provider
+        .requests_for(MockEndpoint::ChatCompletions)
+        .is_empty());
+    assert!(readiness(&server)
+        .await
+        .get("tier_config_standby")
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_standby_config_read_from_file() {
+    let file = std::env::temp_dir().join(format!("sentinel-tiers-{}.json", uuid::Uuid::new_v4()));
+    standby::write_file(&file, &standby_copy(60)).await.unwrap();
+    let harness = harness(|config| {
+        config.zion.tier_config_standby_path = Some(file.display().to_string());
+    })
+    .await;
+    zion_outage(&harness).await;
+
+    let server = TestServer::new(harness.router()).unwrap();
+    native_chat(&server).await.assert_status_ok();
+    let ready = readiness(&server).await;
+    assert_eq!(ready["tier_config_standby"]["source"], "file");
+
+    tokio::fs::remove_file(&file).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_fresh_config_is_kept_as_standby() {
+    let file = std::env::temp_dir().join(format!("sentinel-tiers-{}.json", uuid::Uuid::new_v4()));
+    let harness = harness(|config| {
+        config.zion.tier_config_standby_path = Some(file.display().to_string());
+    })
+    .await;
+
+    let config = harness.state.tier_config_cache.refresh().await.unwrap();
+    let stored = standby::read_file(&file).await.unwrap();
+    assert_eq!(stored.config, config);
+    assert!(chrono::Utc::now().timestamp() - stored.fetched_at < 60);
+
+    tokio::fs::remove_file(&file).await.unwrap();
+}
@@ -0,0 +1,140 @@
+//! Tier state pruning tests
+//!
+//! The Zion stub's tier config changes to drop a model; once the cached
+//! config expires, the model's health entry is pruned only after it has been
+//! absent for the retention window. A test clock drives both the cache TTL
+//! and the window. `/admin/tiers/state` reports the result.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum_test::TestServer;
+use serde_json::Value;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use sentinel::cache::InMemoryCache;
+use sentinel::testing::zion::tier_config_body;
+use sentinel::testing::{
+    test_config, zion_stub, MockAiProvider, TestClock, TestHarness, STUB_PRIORITY,
+};
+use sentinel::tiers::prune::prune_once;
+use sentinel::tiers::{ProviderHealthTracker, TierConfigCache};
+use sentinel::ZionClient;
+
+const ADMIN_KEY: &str = "admin-secret";
+const RETENTION: Duration = Duration::from_secs(3600);
+
+#[tokio::test]
+async fn test_removed_model_is_pruned_after_the_window() {
+    let zion = zion_stub().await;
+    let config = test_config(&zion.uri(), "http://mock-provider.invalid/v1");
+    let clock = TestClock::new(1_700_000_000);
+    let cache = TierConfigCache::new_for_testing(
+        Arc::new(InMemoryCache::new(60).with_clock(clock.clone())),
+        Arc::new(ZionClient::new(reqwest::Client::new(), &config)),
+        60,
+    );
+    let tracker = ProviderHealthTracker::new().with_clock(clock.clone());
+    tracker.record_failure("openai", "gpt-4o");
+    tracker.record_latency("openai", "gpt-4o-mini", Duration::from_millis(100));
+
+    // Both models are configured
+    let stats = prune_once(&cache, &tracker, RETENTION).await.unwrap();
+    assert_eq!((stats.last_pruned, stats.pending), (0, 0));
+
+    // Zion drops gpt-4o; the change is seen once the cached config expires
+    let mut changed = tier_config_body();
+    changed["data"]["version"] = "2.0.0".into();
+    changed["data"]["tiers"]["moderate"] = changed["data"]["tiers"]["simple"].clone();
+    changed["data"]["tiers"]["complex"] = changed["data"]["tiers"]["simple"].clone();
+    Mock::given(method("GET"))
+        .and(path("/api/v1/tiers/config"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(changed))
+        .with_priority(STUB_PRIORITY - 1)
+        .mount(&zion)
+        .await;
+    clock.advance(Duration::from_secs(61));
+
+    let stats = prune_once(&cache, &tracker, RETENTION).await.unwrap();
+    assert_eq!((stats.last_pruned, stats.pending), (0, 1));
+    assert_eq!(cache.cached_version().await.as_deref(), Some("2.0.0"));
+    assert_eq!(tracker.footprint().health_entries, 1);
+
+    // Still inside the window
+    clock.advance(RETENTION - Duration::from_secs(120));
+    let stats = prune_once(&cache, &tracker, RETENTION).await.unwrap();
+    assert_eq!((stats.last_pruned, stats.pending), (0, 1));
+
+    clock.advance(Duration::from_secs(120));
+    let stats = prune_once(&cache, &tracker, RETENTION).await.unwrap();
+    assert_eq!(
+        (stats.last_pruned, stats.pending, stats.total_pruned),
+        (1, 0, 1)
+    );
+    let models: Vec<String> = tracker
+        .snapshots()
+        .into_iter()
+        .map(|snapshot| snapshot.model)
+        .collect();
+    assert_eq!(models, vec!["gpt-4o-mini"]);
+}
+
+#[tokio::test]
+async fn test_prune_skipped_without_tier_config() {
+    let zion = wiremock::MockServer::start().await;
+    let config = test_config(&zion.uri(), "http://mock-provider.invalid/v1");
+    let cache = TierConfigCache::new_for_testing(
+        Arc::new(InMemoryCache::new(60)),
+        Arc::new(ZionClient::new(reqwest::Client::new(), &config)),
+        60,
+    );
+    let tracker = ProviderHealthTracker::new();
+    tracker.record_failure("openai", "gpt-4o");
+
+    // Zion can't serve the config: nothing is treated as removed
+    assert!(prune_once(&cache, &tracker, Duration::ZERO).await.is_none());
+    assert_eq!(tracker.footprint().health_entries, 1);
+}
+
+#[tokio::test]
+async fn test_admin_tier_state() {
+    let harness = TestHarness::with_config(Arc::new(MockAiProvider::new()), |config| {
+        config.server.admin_api_key = Some(ADMIN_KEY.to_string());
+        config.provider.tier_health_retention_hours = 0;
+    })
+    .await;
+    let state = &harness.state;
+    state.health_tracker.record_failure("openai", "gpt-4o");
+    state
+        .health_tracker
+        .record_failure("openai", "gpt-3.5-turbo");
+    prune_once(
+        &state.tier_config_cache,
+        &state.health_tracker,
+        Duration::ZERO,
+    )
+    .await
+    .unwrap();
+
+    let server = TestServer::new(harness.router()).unwrap();
+    let response = server
+        .get("/admin/tiers/state")
+        .add_header("X-Admin-Key".parse().unwrap(), ADMIN_KEY.parse().unwrap())
+        .await;
+    response.assert_status_ok();
+    let report: Value = response.json();
+
+    assert_eq!(report["config_version"], "1.0.0");
+    assert_eq!(report["config_models"], 2);
+    assert!(report["config_bytes"].as_u64().unwrap() > 0);
+    assert_eq!(report["health"]["health_entries"], 1);
+    let models = report["models"].as_array().unwrap();
+    assert_eq!(models.len(), 1);
+    assert_eq!(models[0]["model"], "gpt-4o");
+    assert_eq!(models[0]["available"], false);
+    assert_eq!(models[0]["consecutive_failures"], 1);
+    assert_eq!(report["prune"]["runs"], 1);
+    assert_eq!(report["prune"]["total_pruned"], 1);
+    assert_eq!(report["prune"]["retention_hours"], 0);
+}
@@ -0,0 +1,210 @@
+//! Token scope tests
+//!
+//! Zion profiles with a `scopes` claim restrict which model endpoints the
+//! token may call: chat-like routes need `chat`, embeddings need
+//! `embeddings`, pass-through endpoints need `passthrough`, and `*` grants
+//! all of them. Profiles without the claim keep full access unless
+//! `AUTH_UNSCOPED_FULL_ACCESS` is turned off. Admin endpoints accept a token
+//! that claims `admin`, and never an unscoped one.
+
+use std::sync::Arc;
+
+use axum::http::{header, StatusCode};
+use axum_test::{TestRequest, TestResponse, TestServer};
+use serde_json::{json, Value};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use sentinel::config::Config;
+use sentinel::testing::{constants, zion, MockAiProvider, MockEndpoint, MockReply, TestHarness};
+
+fn provider() -> Arc<MockAiProvider> {
+    Arc::new(
+        MockAiProvider::new()
+            .with_reply(
+                MockEndpoint::ChatCompletions,
+                MockReply::chat_completion("gpt-4o-mini", "Hello", 10, 5),
+            )
+            .with_reply(
+                MockEndpoint::Embeddings,
+                MockReply::Json(json!({
+                    "object": "list",
+                    "data": [],
+                    "model": "text-embedding-3-small",
+                    "usage": {"prompt_tokens": 1, "total_tokens": 1}
+                })),
+            )
+            .with_reply(
+                MockEndpoint::Models,
+                MockReply::Json(json!({"object": "list", "data": []})),
+            )
+            .with_reply(
+                MockEndpoint::Passthrough,
+                MockReply::Json(json!({"created": 1, "data": []})),
+            ),
+    )
+}
+
+/// Server whose Zion profile carries `scopes` (omitted when None)
+async fn scoped_server(
+    scopes: Option<Value>,
+    configure: impl FnOnce(&mut Config),
+) -> (TestHarness, TestServer) {
+    let harness = TestHarness::with_config(provider(), configure).await;
+    if let Some(scopes) = scopes {
+        let mut profile = zion::profile_body();
+        profile["data"]["scopes"] = scopes;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/users/me"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(profile))
+            .mount(&harness.zion)
+            .await;
+    }
+    let server = TestServer::new(harness.router()).unwrap();
+    (harness, server)
+}
+
+fn authorized(request: TestRequest) -> TestRequest {
+    request.add_header(
+        header::AUTHORIZATION,
+        format!("Bearer {}", constants::TEST_JWT_TOKEN)
+            .parse()
+            .unwrap(),
+    )
+}
+
+async fn chat(server: &TestServer) -> TestResponse {
+    authorized(server.post("/v1/chat/completions"))
+        .json(&json!({
+            "model": "gpt-4o-mini",
+            "messages": [{"role": "user", "content": "Hi"}]
+        }))
+        .await
+}
+
+async fn native_chat(server: &TestServer) -> TestResponse {
+    authorized(server.post("/native/v1/chat/completions"))
+        .json(&json!({
+            "tier": "simple",
+            "messages": [{"role": "user", "content": "Hi"}]
+        }))
+        .await
+}
+
+async fn embeddings(server: &TestServer) -> TestResponse {
+    authorized(server.post("/v1/embeddings"))
+        .json(&json!({"model": "text-embedding-3-small", "input": "Hi"}))
+        .await
+}
+
+async fn images(server: &TestServer) -> TestResponse {
+    authorized(server.post("/v1/images/generations"))
+        .json(&json!({"model": "dall-e-3", "prompt": "A cat"}))
+        .await
+}
+
+async fn admin(server: &TestServer) -> TestResponse {
+    authorized(server.get("/admin/maintenance")).await
+}
+
+fn assert_insufficient_scope(response: &TestResponse, scope: &str) {
+    response.assert_status(StatusCode::FORBIDDEN);
+    let body: Value = response.json();
+    assert_eq!(body["error"]["code"], "insufficient_scope");
+    let message = body["error"]["message"].as_str().unwrap();
+    assert!(message.contains(&format!("'{}'", scope)), "{}", message);
+}
+
+#[tokio::test]
+async fn test_embeddings_token_cannot_chat() {
+    let (harness, server) = scoped_server(Some(json!(["embeddings"])), |_| {}).await;
+
+    embeddings(&server).await.assert_status_ok();
+    assert_insufficient_scope(&chat(&server).await, "chat");
+    assert_insufficient_scope(&native_chat(&server).await, "chat");
+    // Routes without a scope requirement accept any token
+    authorized(server.get("/v1/models"))
+        .await
+        .assert_status_ok();
+
+    // Rejected requests never reach the provider
+    assert!(harness
+        .provider
+        .requests_for(MockEndpoint::ChatCompletions)
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_chat_token_cannot_embed() {
+    let (_harness, server) = scoped_server(Some(json!(["chat"])), |_| {}).await;
+
+    chat(&server).await.assert_status_ok();
+    native_chat(&server).await.assert_status_ok();
+    assert_insufficient_scope(&embeddings(&server).await, "embeddings");
+}
+
+#[tokio::test]
+async fn test_wildcard_scope_grants_everything() {
+    let (_harness, server) = scoped_server(Some(json!(["*"])), |_| {}).await;
+
+    chat(&server).await.assert_status_ok();
+    embeddings(&server).await.assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_unscoped_token_keeps_full_access() {
+    let (_harness, server) = scoped_server(None, |_| {}).await;
+
+    chat(&server).await.assert_status_ok();
+    native_chat(&server).await.assert_status_ok();
+    embeddings(&server).await.assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_unscoped_token_restricted_when_configured() {
+    let (_harness, server) = scoped_server(None, |config| {
+        config.server.unscoped_full_access = false;
+    })
+    .await;
+
+    assert_insufficient_scope(&chat(&server).await, "chat");
+    assert_insufficient_scope(&embeddings(&server).await, "embeddings");
+    authorized(server.get("/v1/models"))
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_passthrough_needs_its_scope() {
+    let (harness, server) = scoped_server(Some(json!(["embeddings"])), |_| {}).await;
+    assert_insufficient_scope(&images(&server).await, "passthrough");
+    assert!(harness
+        .provider
+        .requests_for(MockEndpoint::Passthrough)
+        .is_empty());
+
+    let (_harness, server) = scoped_server(Some(json!(["passthrough"])), |_| {}).await;
+    images(&server).await.assert_status_ok();
+    let (_harness, server) = scoped_server(Some(json!(["*"])), |_| {}).await;
+    images(&server).await.assert_status_ok();
+    let (_harness, server) = scoped_server(None, |_| {}).await;
+    images(&server).await.assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_admin_needs_the_admin_scope() {
+    let (_harness, server) = scoped_server(Some(json!(["admin"])), |_| {}).await;
+    admin(&server).await.assert_status_ok();
+
+    let (_harness, server) = scoped_server(Some(json!(["chat", "embeddings"])), |_| {}).await;
+    assert_insufficient_scope(&admin(&server).await, "admin");
+
+    // Unscoped tokens never get admin access, and callers without a valid
+    // token don't learn it exists
+    let (_harness, server) = scoped_server(None, |_| {}).await;
+    assert_insufficient_scope(&admin(&server).await, "admin");
+    server
+        .get("/admin/maintenance")
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
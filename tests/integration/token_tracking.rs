@@ -14,13 +14,15 @@
 //!
 //! Note: These tests require Redis to be running locally.
 
+use std::sync::Arc;
 use std::time::Duration;
 use axum::http::header;
 use serde_json::json;
 
 use crate::common::{constants, TokenTrackingTestHarness};
 use crate::mocks::zion::{ZionTestData, UserProfileMock};
-use crate::mocks::openai::OpenAITestData;
+use axum_test::TestServer;
+use sentinel::testing::{sse_events, MockAiProvider, MockEndpoint, MockReply, StreamScript, TestHarness};
 
 /// Helper to create authorization header value
 fn auth_header() -> String {
@@ -148,15 +150,18 @@ async fn test_chat_completion_streaming_tracks_tokens() {
     harness.zion.mock_batch_increment_success(1, 0).await;
 
     // Mock streaming response with usage in final chunk
-    let chunks = OpenAITestData::streaming_chunks("Hello world this is a streaming response");
-    harness.openai.mock_chat_completion_stream(chunks).await;
+    let script = StreamScript::new("gpt-4")
+        .text("Hello world this is a streaming response")
+        .usage(50, 14);
+    harness.openai.mock_chat_completion_response(script.response()).await;
 
     let request = json!({
         "model": "gpt-4",
         "messages": [
             {"role": "user", "content": "Hello!"}
         ],
-        "stream": true
+        "stream": true,
+        "stream_options": {"include_usage": true}
     });
 
     let response = harness.server
@@ -170,22 +175,123 @@ async fn test_chat_completion_streaming_tracks_tokens() {
 
     // Consume the stream by reading the body
     let body = response.text();
-    assert!(body.contains("data:"), "Response should be SSE format");
-    assert!(body.contains("[DONE]"), "Stream should complete");
+    assert_eq!(sse_events(&body), script.events());
+    assert!(body.ends_with("data: [DONE]\n\n"), "Stream should complete");
 
     // Wait for Zion batch-increment (streaming may take a bit longer)
     let requests = harness.wait_for_batch_requests(1, Duration::from_secs(3)).await;
     assert!(!requests.is_empty(), "Expected batch-increment request after streaming");
 
-    // Parse and verify the payload
+    // The provider's usage chunk is what gets billed
     let increments = TokenTrackingTestHarness::parse_batch_payload(&requests[0]);
     let (input, output, req_count) = TokenTrackingTestHarness::extract_token_counts(&increments[0]);
 
-    assert!(input > 0, "Input tokens should be > 0 for streaming, got {}", input);
-    assert!(output > 0, "Output tokens should be > 0 for streaming, got {}", output);
+    assert_eq!((input, output), (50, 14));
     assert_eq!(req_count, 1, "Request count should be 1");
 }
 
+// =============================================================================
+// Tool Call Token Accounting Tests
+// =============================================================================
+
+/// Streaming tool-call-only turn (null content) without provider usage
+fn tool_call_stream_without_usage() -> MockReply {
+    StreamScript::new("gpt-4")
+        .tool_call("call_1", "get_weather", r#"{"location":"Paris, France"}"#)
+        .reply()
+}
+
+#[tokio::test]
+async fn test_streaming_tool_call_only_estimates_output_tokens() {
+    let harness = TestHarness::new().await;
+    harness
+        .provider
+        .push_reply(MockEndpoint::ChatCompletions, tool_call_stream_without_usage());
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let request = json!({
+        "model": "gpt-4",
+        "messages": [{"role": "user", "content": "What's the weather in Paris?"}],
+        "tools": [{"type": "function", "function": {"name": "get_weather", "parameters": {"type": "object"}}}],
+        "tool_choice": "auto",
+        "stream": true
+    });
+
+    let response = server
+        .post("/v1/chat/completions")
+        .add_header(header::AUTHORIZATION, auth_header().parse().unwrap())
+        .json(&request)
+        .await;
+
+    response.assert_status_ok();
+    assert!(response.text().contains("[DONE]"), "Stream should complete");
+
+    // Tool fields are forwarded upstream unchanged
+    let sent = harness.provider.requests_for(MockEndpoint::ChatCompletions);
+    assert_eq!(sent[0]["tools"][0]["function"]["name"], "get_weather");
+    assert_eq!(sent[0]["tool_choice"], "auto");
+
+    let requests = harness.wait_for_batch_requests(1, Duration::from_secs(3)).await;
+    assert!(!requests.is_empty(), "Expected batch-increment request after streaming");
+
+    let increments = TokenTrackingTestHarness::parse_batch_payload(&requests[0]);
+    let (input, output, req_count) = TokenTrackingTestHarness::extract_token_counts(&increments[0]);
+
+    assert!(input > 0, "Input tokens should be > 0, got {}", input);
+    assert!(output > 0, "Tool call arguments should count as output tokens, got {}", output);
+    assert_eq!(req_count, 1, "Request count should be 1");
+}
+
+#[tokio::test]
+async fn test_non_streaming_tool_call_only_estimates_output_tokens() {
+    // No usage in the reply, which only passes with validation off
+    let harness = TestHarness::with_config(Arc::new(MockAiProvider::new()), |config| {
+        config.provider.validate_upstream_responses = false;
+    })
+    .await;
+    harness.provider.push_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::Json(json!({
+            "id": "chatcmpl-tools",
+            "object": "chat.completion",
+            "created": 1700000000,
+            "model": "gpt-4",
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "content": null,
+                    "tool_calls": [{
+                        "id": "call_1",
+                        "type": "function",
+                        "function": {"name": "get_weather", "arguments": "{\"location\":\"Paris, France\"}"}
+                    }]
+                },
+                "finish_reason": "tool_calls"
+            }]
+        })),
+    );
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = server
+        .post("/v1/chat/completions")
+        .add_header(header::AUTHORIZATION, auth_header().parse().unwrap())
+        .json(&json!({
+            "model": "gpt-4",
+            "messages": [{"role": "user", "content": "What's the weather in Paris?"}]
+        }))
+        .await;
+
+    response.assert_status_ok();
+
+    let requests = harness.wait_for_batch_requests(1, Duration::from_secs(2)).await;
+    assert!(!requests.is_empty(), "Expected batch-increment request");
+
+    let increments = TokenTrackingTestHarness::parse_batch_payload(&requests[0]);
+    let (_, output, _) = TokenTrackingTestHarness::extract_token_counts(&increments[0]);
+    assert!(output > 0, "Tool call arguments should count as output tokens, got {}", output);
+}
+
 // =============================================================================
 // Completions Endpoint Tests
 // =============================================================================
@@ -0,0 +1,160 @@
+//! Upstream circuit breaker tests
+//!
+//! Drive the mock provider into consecutive 5xx failures and verify that
+//! further requests fail fast with 503 `upstream_unavailable` (without
+//! reaching the provider), that `/health/ready` reports the open circuit, and
+//! that a successful half-open probe closes it again.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::{header, StatusCode};
+use axum_test::{TestRequest, TestServer};
+use serde_json::{json, Value};
+
+use sentinel::testing::{constants, MockAiProvider, MockEndpoint, MockReply, TestHarness};
+
+fn authorized(request: TestRequest) -> TestRequest {
+    request.add_header(
+        header::AUTHORIZATION,
+        format!("Bearer {}", constants::TEST_JWT_TOKEN)
+            .parse()
+            .unwrap(),
+    )
+}
+
+fn server_error() -> MockReply {
+    MockReply::Error {
+        status: 503,
+        message: "upstream overloaded".to_string(),
+    }
+}
+
+/// Harness whose chat endpoint fails `failures` times, then answers normally
+async fn failing_harness(failures: usize, reset_seconds: u64) -> (TestHarness, TestServer) {
+    let mut provider = MockAiProvider::new();
+    for _ in 0..failures {
+        provider = provider.with_reply(MockEndpoint::ChatCompletions, server_error());
+    }
+    let provider = Arc::new(
+        provider
+            .with_reply(
+                MockEndpoint::ChatCompletions,
+                MockReply::chat_completion("gpt-4o-mini", "Back up.", 10, 3),
+            )
+            .with_reply(
+                MockEndpoint::Embeddings,
+                MockReply::Json(json!({
+                    "object": "list",
+                    "data": [],
+                    "model": "text-embedding-3-small",
+                    "usage": {"prompt_tokens": 1, "total_tokens": 1}
+                })),
+            ),
+    );
+    let harness = TestHarness::with_config(provider, |config| {
+        config.provider.circuit_breaker_threshold = 3;
+        config.provider.circuit_breaker_reset_seconds = reset_seconds;
+    })
+    .await;
+    let server = TestServer::new(harness.router()).unwrap();
+    (harness, server)
+}
+
+async fn chat(server: &TestServer) -> axum_test::TestResponse {
+    authorized(server.post("/v1/chat/completions"))
+        .json(&json!({
+            "model": "gpt-4o-mini",
+            "messages": [{"role": "user", "content": "Hi"}]
+        }))
+        .await
+}
+
+#[tokio::test]
+async fn test_open_circuit_fails_fast() {
+    let (harness, server) = failing_harness(10, 30).await;
+
+    for _ in 0..3 {
+        chat(&server).await.assert_status(StatusCode::BAD_GATEWAY);
+    }
+
+    let response = chat(&server).await;
+    response.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+    let retry_after: u64 = response
+        .header("retry-after")
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!((1..=30).contains(&retry_after));
+    let body: Value = response.json();
+    assert_eq!(body["error"]["code"], "upstream_unavailable");
+    assert!(body["error"]["details"]["reset_at"].is_string());
+
+    // Native requests are stopped by the same breaker, in native format
+    let response = authorized(server.post("/native/v1/chat/completions"))
+        .json(&json!({
+            "tier": "simple",
+            "messages": [{"role": "user", "content": "Hi"}]
+        }))
+        .await;
+    response.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+    assert!(response.maybe_header("retry-after").is_some());
+    let body: Value = response.json();
+    assert_eq!(body["error"]["code"], "upstream_unavailable");
+    assert_eq!(body["error"]["provider"], "mock");
+
+    // Only the failures that opened the circuit reached the provider
+    assert_eq!(
+        harness
+            .provider
+            .requests_for(MockEndpoint::ChatCompletions)
+            .len(),
+        3
+    );
+
+    // Other endpoints have their own breaker
+    authorized(server.post("/v1/embeddings"))
+        .json(&json!({"model": "text-embedding-3-small", "input": "Hi"}))
+        .await
+        .assert_status_ok();
+
+    let ready = server.get("/health/ready").await;
+    ready.assert_status_ok();
+    let ready: Value = ready.json();
+    assert_eq!(ready["status"], "degraded");
+    let circuit = &ready["upstream_circuits"][0];
+    assert_eq!(circuit["provider"], "mock");
+    assert_eq!(circuit["endpoint"], "chat/completions");
+    assert_eq!(circuit["state"], "open");
+    assert!(circuit["retry_after_seconds"].as_u64().unwrap() <= 30);
+}
+
+#[tokio::test]
+async fn test_successful_probe_closes_circuit() {
+    let (harness, server) = failing_harness(3, 1).await;
+
+    for _ in 0..3 {
+        chat(&server).await.assert_status(StatusCode::BAD_GATEWAY);
+    }
+    chat(&server)
+        .await
+        .assert_status(StatusCode::SERVICE_UNAVAILABLE);
+
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+
+    // The half-open probe goes through and closes the circuit
+    chat(&server).await.assert_status_ok();
+    chat(&server).await.assert_status_ok();
+    assert_eq!(
+        harness
+            .provider
+            .requests_for(MockEndpoint::ChatCompletions)
+            .len(),
+        5
+    );
+
+    let ready: Value = server.get("/health/ready").await.json();
+    assert_eq!(ready["status"], "healthy");
+    assert!(ready.get("upstream_circuits").is_none());
+}
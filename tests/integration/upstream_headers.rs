@@ -0,0 +1,173 @@
+//! Upstream header capture tests
+//!
+//! Point a real OpenAI provider at a wiremock gateway that injects request-id
+//! and processing-time headers, then verify they reach the completion log and
+//! that the request id is exposed to the client as `X-Upstream-Request-Id`.
+
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use axum::http::header;
+use axum_test::TestServer;
+use serde_json::json;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use sentinel::proxy::{redirect, AiProvider};
+use sentinel::testing::{constants, test_config, test_state, zion_stub, StreamScript};
+use sentinel::{routes, OpenAIProvider};
+
+/// Log sink shared between the subscriber and the test
+#[derive(Clone, Default)]
+struct LogBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl LogBuffer {
+    fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.lock().unwrap()).into_owned()
+    }
+}
+
+impl Write for LogBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+struct HeaderHarness {
+    server: TestServer,
+    gateway: MockServer,
+    #[allow(dead_code)]
+    zion: MockServer,
+}
+
+async fn header_harness() -> HeaderHarness {
+    let zion = zion_stub().await;
+    let gateway = MockServer::start().await;
+
+    let config = test_config(&zion.uri(), &format!("{}/v1", gateway.uri()));
+    let provider: Arc<dyn AiProvider> = Arc::new(OpenAIProvider::new(
+        redirect::provider_client().unwrap(),
+        &config,
+    ));
+    let state = test_state(config, provider).await;
+    let server = TestServer::new(routes::create_router(state)).unwrap();
+
+    HeaderHarness { server, gateway, zion }
+}
+
+fn with_upstream_headers(template: ResponseTemplate) -> ResponseTemplate {
+    template
+        .insert_header("x-request-id", "req_upstream_123")
+        .insert_header("openai-processing-ms", "845")
+        .insert_header("x-internal-secret", "do-not-log")
+}
+
+async fn send_chat(harness: &HeaderHarness, stream: bool) -> axum_test::TestResponse {
+    harness
+        .server
+        .post("/v1/chat/completions")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN).parse().unwrap(),
+        )
+        .json(&json!({
+            "model": "gpt-4o-mini",
+            "messages": [{"role": "user", "content": "Hi"}],
+            "stream": stream
+        }))
+        .await
+}
+
+/// Run `send_chat` with a subscriber capturing everything that gets logged
+async fn send_chat_logged(harness: &HeaderHarness, stream: bool) -> (axum_test::TestResponse, String) {
+    let logs = LogBuffer::default();
+    let writer = logs.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::INFO)
+        .with_ansi(false)
+        .with_writer(move || writer.clone())
+        .finish();
+
+    let _guard = tracing::subscriber::set_default(subscriber);
+    let response = send_chat(harness, stream).await;
+    (response, logs.contents())
+}
+
+fn assert_headers_logged(logs: &str) {
+    assert!(logs.contains("x-request-id=req_upstream_123"), "logs:\n{}", logs);
+    assert!(logs.contains("openai-processing-ms=845"), "logs:\n{}", logs);
+    assert!(!logs.contains("do-not-log"), "non-allow-listed header logged:\n{}", logs);
+}
+
+#[tokio::test]
+async fn test_buffered_response_captures_upstream_headers() {
+    let harness = header_harness().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(with_upstream_headers(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-headers",
+            "object": "chat.completion",
+            "created": 1700000000,
+            "model": "gpt-4o-mini",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Hello!"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+        }))))
+        .mount(&harness.gateway)
+        .await;
+
+    let (response, logs) = send_chat_logged(&harness, false).await;
+    response.assert_status_ok();
+    assert_eq!(response.header("X-Upstream-Request-Id"), "req_upstream_123");
+    assert_headers_logged(&logs);
+}
+
+#[tokio::test]
+async fn test_streaming_response_captures_upstream_headers() {
+    let harness = header_harness().await;
+    let script = StreamScript::new("gpt-4o-mini").text("Hello!").usage(10, 5);
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(with_upstream_headers(script.response()))
+        .mount(&harness.gateway)
+        .await;
+
+    let (response, logs) = send_chat_logged(&harness, true).await;
+    response.assert_status_ok();
+    assert_eq!(response.header("X-Upstream-Request-Id"), "req_upstream_123");
+    assert!(response.text().contains("Hello!"));
+    assert_headers_logged(&logs);
+}
+
+#[tokio::test]
+async fn test_missing_request_id_omits_client_header() {
+    let harness = header_harness().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-plain",
+            "object": "chat.completion",
+            "created": 1700000000,
+            "model": "gpt-4o-mini",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Hello!"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+        })))
+        .mount(&harness.gateway)
+        .await;
+
+    let response = send_chat(&harness, false).await;
+    response.assert_status_ok();
+    assert!(response.maybe_header("X-Upstream-Request-Id").is_none());
+}
@@ -0,0 +1,182 @@
+//! Upstream connection pool tests
+//!
+//! A load of slow streams is held open against a wiremock gateway while
+//! non-streaming calls run alongside. The streams are counted on the
+//! streaming client and the short calls on the short one, and the short
+//! calls finish while every stream is still waiting on its upstream.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::http::header;
+use axum::Router;
+use axum_test::TestServer;
+use futures::future::join_all;
+use serde_json::json;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use sentinel::proxy::pool::{ProviderClients, UpstreamPool};
+use sentinel::proxy::AiProvider;
+use sentinel::testing::{constants, test_config, test_state, zion_stub, StreamScript};
+use sentinel::{routes, OpenAIProvider};
+
+/// Streams held open at once
+const STREAMS: usize = 32;
+/// Non-streaming calls sent while the streams are open
+const SHORT_CALLS: usize = 16;
+/// How long the gateway holds each stream before answering
+const STREAM_DELAY: Duration = Duration::from_millis(1500);
+
+struct PoolHarness {
+    router: Router,
+    clients: ProviderClients,
+    gateway: MockServer,
+    #[allow(dead_code)]
+    zion: MockServer,
+}
+
+async fn pool_harness() -> PoolHarness {
+    let zion = zion_stub().await;
+    let gateway = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(body_partial_json(json!({"stream": true})))
+        .respond_with(
+            StreamScript::new("gpt-4o-mini")
+                .text("Hello!")
+                .usage(10, 5)
+                .response()
+                .set_delay(STREAM_DELAY),
+        )
+        .mount(&gateway)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-short",
+            "object": "chat.completion",
+            "created": 1700000000,
+            "model": "gpt-4o-mini",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Hello!"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+        })))
+        .mount(&gateway)
+        .await;
+
+    let config = test_config(&zion.uri(), &format!("{}/v1", gateway.uri()));
+    let clients = ProviderClients::from_config(&config.provider).unwrap();
+    let provider: Arc<dyn AiProvider> = Arc::new(OpenAIProvider::new(clients.clone(), &config));
+    let state = test_state(config, provider).await;
+
+    PoolHarness {
+        router: routes::create_router(state),
+        clients,
+        gateway,
+        zion,
+    }
+}
+
+/// Send a chat completion on its own test server, so requests run concurrently
+async fn chat(router: &Router, stream: bool) -> axum_test::TestResponse {
+    TestServer::new(router.clone())
+        .unwrap()
+        .post("/v1/chat/completions")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .json(&json!({
+            "model": "gpt-4o-mini",
+            "messages": [{"role": "user", "content": "Hi"}],
+            "stream": stream
+        }))
+        .await
+}
+
+#[tokio::test]
+async fn test_short_calls_are_not_held_up_by_streams() {
+    let harness = pool_harness().await;
+    let router = &harness.router;
+    let clients = &harness.clients;
+
+    let streams = join_all((0..STREAMS).map(|_| chat(router, true)));
+    let short_calls = async {
+        // Wait until every stream is waiting on the gateway
+        while clients.in_flight(UpstreamPool::Streaming) < STREAMS {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(clients.in_flight(UpstreamPool::Short), 0);
+
+        let start = Instant::now();
+        for response in join_all((0..SHORT_CALLS).map(|_| chat(router, false))).await {
+            response.assert_status_ok();
+        }
+        let elapsed = start.elapsed();
+        // The streams are still open upstream
+        assert_eq!(clients.in_flight(UpstreamPool::Streaming), STREAMS);
+        elapsed
+    };
+    let (streams, short_elapsed) = tokio::join!(streams, short_calls);
+
+    assert!(
+        short_elapsed < STREAM_DELAY / 2,
+        "short calls took {short_elapsed:?} behind {STREAMS} streams"
+    );
+    for response in streams {
+        response.assert_status_ok();
+        assert!(response.text().contains("Hello!"));
+    }
+    assert_eq!(clients.requests(UpstreamPool::Streaming), STREAMS as u64);
+    assert_eq!(clients.requests(UpstreamPool::Short), SHORT_CALLS as u64);
+    assert_eq!(clients.in_flight(UpstreamPool::Streaming), 0);
+    assert_eq!(clients.in_flight(UpstreamPool::Short), 0);
+}
+
+#[tokio::test]
+async fn test_passthrough_pool_follows_stream_flag() {
+    let harness = pool_harness().await;
+    let clients = &harness.clients;
+    Mock::given(method("POST"))
+        .and(path("/v1/threads/runs"))
+        .respond_with(StreamScript::new("gpt-4o-mini").text("Hello!").response())
+        .mount(&harness.gateway)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/moderations"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"results": []})))
+        .mount(&harness.gateway)
+        .await;
+
+    let server = TestServer::new(harness.router.clone()).unwrap();
+    let passthrough = |path: &'static str, body: serde_json::Value| {
+        server
+            .post(path)
+            .add_header(
+                header::AUTHORIZATION,
+                format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                    .parse()
+                    .unwrap(),
+            )
+            .json(&body)
+    };
+    passthrough(
+        "/v1/threads/runs",
+        json!({"assistant_id": "asst_1", "stream": true}),
+    )
+    .await
+    .assert_status_ok();
+    passthrough("/v1/moderations", json!({"input": "Hi"}))
+        .await
+        .assert_status_ok();
+
+    assert_eq!(clients.requests(UpstreamPool::Streaming), 1);
+    assert_eq!(clients.requests(UpstreamPool::Short), 1);
+    assert_eq!(clients.in_flight(UpstreamPool::Streaming), 0);
+}
@@ -0,0 +1,157 @@
+//! Upstream redirect tests
+//!
+//! Point a real OpenAI provider at a wiremock gateway that answers with
+//! 307/308 redirects and verify same-origin hops are followed with auth
+//! intact, while cross-origin, streaming and looping redirects become 502s.
+
+use std::sync::Arc;
+
+use axum::http::{header, StatusCode};
+use axum_test::TestServer;
+use serde_json::{json, Value};
+use wiremock::matchers::{header as header_matcher, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use sentinel::proxy::{redirect, AiProvider};
+use sentinel::testing::{constants, test_config, test_state, zion_stub};
+use sentinel::{routes, OpenAIProvider};
+
+struct RedirectHarness {
+    server: TestServer,
+    gateway: MockServer,
+    #[allow(dead_code)]
+    zion: MockServer,
+}
+
+/// Build a test server whose provider talks to the gateway mock
+async fn redirect_harness() -> RedirectHarness {
+    let zion = zion_stub().await;
+    let gateway = MockServer::start().await;
+
+    let config = test_config(&zion.uri(), &format!("{}/v1", gateway.uri()));
+    let provider: Arc<dyn AiProvider> = Arc::new(OpenAIProvider::new(
+        redirect::provider_client().unwrap(),
+        &config,
+    ));
+    let state = test_state(config, provider).await;
+    let server = TestServer::new(routes::create_router(state)).unwrap();
+
+    RedirectHarness { server, gateway, zion }
+}
+
+async fn redirect_chat(gateway: &MockServer, status: u16, location: &str) {
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(status).insert_header("location", location))
+        .mount(gateway)
+        .await;
+}
+
+async fn send_chat(harness: &RedirectHarness, stream: bool) -> axum_test::TestResponse {
+    harness
+        .server
+        .post("/v1/chat/completions")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN).parse().unwrap(),
+        )
+        .json(&json!({
+            "model": "gpt-4o-mini",
+            "messages": [{"role": "user", "content": "Hi"}],
+            "stream": stream
+        }))
+        .await
+}
+
+#[tokio::test]
+async fn test_same_origin_redirect_followed_with_auth() {
+    let harness = redirect_harness().await;
+    redirect_chat(&harness.gateway, 308, "/v2/chat/completions").await;
+    Mock::given(method("POST"))
+        .and(path("/v2/chat/completions"))
+        .and(header_matcher(
+            "authorization",
+            format!("Bearer {}", constants::TEST_OPENAI_API_KEY).as_str(),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-redirected",
+            "object": "chat.completion",
+            "created": 1700000000,
+            "model": "gpt-4o-mini",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Hello!"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+        })))
+        .expect(1)
+        .mount(&harness.gateway)
+        .await;
+
+    let response = send_chat(&harness, false).await;
+    response.assert_status_ok();
+    let body: Value = response.json();
+    assert_eq!(body["id"], "chatcmpl-redirected");
+
+    // The redirected request kept the POST body
+    let requests = harness.gateway.received_requests().await.unwrap();
+    let redirected = requests
+        .iter()
+        .find(|r| r.url.path() == "/v2/chat/completions")
+        .unwrap();
+    let forwarded: Value = serde_json::from_slice(&redirected.body).unwrap();
+    assert_eq!(forwarded["model"], "gpt-4o-mini");
+}
+
+#[tokio::test]
+async fn test_cross_origin_redirect_refused() {
+    let harness = redirect_harness().await;
+    let elsewhere = MockServer::start().await;
+    redirect_chat(
+        &harness.gateway,
+        307,
+        &format!("{}/v1/chat/completions", elsewhere.uri()),
+    )
+    .await;
+
+    let response = send_chat(&harness, false).await;
+    response.assert_status(StatusCode::BAD_GATEWAY);
+    let body: Value = response.json();
+    assert!(body["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("different origin"));
+
+    assert!(elsewhere.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_streaming_redirect_returns_502() {
+    let harness = redirect_harness().await;
+    redirect_chat(&harness.gateway, 308, "/v2/chat/completions").await;
+
+    let response = send_chat(&harness, true).await;
+    response.assert_status(StatusCode::BAD_GATEWAY);
+    let body: Value = response.json();
+    assert!(body["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("streaming request"));
+
+    // Only the original request reached the gateway
+    assert_eq!(harness.gateway.received_requests().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_redirect_loop_stops_after_max_hops() {
+    let harness = redirect_harness().await;
+    redirect_chat(&harness.gateway, 307, "/v1/chat/completions").await;
+
+    send_chat(&harness, false)
+        .await
+        .assert_status(StatusCode::BAD_GATEWAY);
+
+    let requests = harness.gateway.received_requests().await.unwrap();
+    assert_eq!(requests.len(), redirect::MAX_REDIRECTS + 1);
+}
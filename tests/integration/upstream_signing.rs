@@ -0,0 +1,153 @@
+//! Upstream request signing tests
+//!
+//! Point a SigV4-signing OpenAI provider at a wiremock gateway and verify that
+//! buffered and streaming requests arrive with the signature headers instead
+//! of a bearer key, and that each signature matches the body the gateway
+//! received.
+
+use std::sync::Arc;
+
+use axum::http::header;
+use axum_test::TestServer;
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+use serde_json::json;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use sentinel::clock::TestClock;
+use sentinel::proxy::signing::{body_sha256, RequestSigner, SigV4Credentials, SigV4Signer};
+use sentinel::proxy::{redirect, AiProvider};
+use sentinel::testing::{constants, test_config, test_state, zion_stub, StreamScript};
+use sentinel::{routes, OpenAIProvider};
+
+/// 2023-11-14T22:13:20Z
+const SIGNING_TIME: i64 = 1_700_000_000;
+
+fn signer() -> SigV4Signer {
+    SigV4Signer::new(
+        SigV4Credentials {
+            access_key_id: "AKIDTEST".to_string(),
+            secret_access_key: "gateway-secret".to_string(),
+            session_token: Some("session-token".to_string()),
+        },
+        "us-east-1",
+        "execute-api",
+        TestClock::new(SIGNING_TIME),
+    )
+}
+
+/// Server whose provider signs for `gateway`, and the Zion stub it uses
+async fn signed_server(gateway: &MockServer) -> (TestServer, MockServer) {
+    let zion = zion_stub().await;
+    let mut config = test_config(&zion.uri(), &format!("{}/v1", gateway.uri()));
+    config.provider.openai_api_key = None;
+    let provider: Arc<dyn AiProvider> = Arc::new(OpenAIProvider::with_signer(
+        redirect::provider_client().unwrap(),
+        &config,
+        Arc::new(signer()),
+    ));
+    let state = test_state(config, provider).await;
+    (TestServer::new(routes::create_router(state)).unwrap(), zion)
+}
+
+async fn send_chat(server: &TestServer, stream: bool) {
+    server
+        .post("/v1/chat/completions")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .json(&json!({
+            "model": "gpt-4o-mini",
+            "messages": [{"role": "user", "content": "Hi"}],
+            "stream": stream
+        }))
+        .await
+        .assert_status_ok();
+}
+
+/// The gateway's view of the request carries a signature over its body
+fn assert_signed(request: &wiremock::Request) {
+    let authorization = request.headers["authorization"].to_str().unwrap();
+    assert!(
+        authorization.starts_with(
+            "AWS4-HMAC-SHA256 Credential=AKIDTEST/20231114/us-east-1/execute-api/aws4_request, \
+             SignedHeaders=content-type;host;x-amz-date;x-amz-security-token, Signature="
+        ),
+        "{}",
+        authorization
+    );
+    assert_eq!(request.headers["x-amz-date"], "20231114T221320Z");
+    assert_eq!(request.headers["x-amz-security-token"], "session-token");
+
+    // Verify the way the gateway would, against the Host it was sent to
+    // (wiremock's `request.url` always says localhost)
+    let url = reqwest::Url::parse(&format!(
+        "http://{}{}",
+        request.headers["host"].to_str().unwrap(),
+        request.url.path()
+    ))
+    .unwrap();
+    let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    let expected = signer()
+        .sign(
+            &reqwest::Method::POST,
+            &url,
+            &headers,
+            &body_sha256(Some(&request.body)),
+        )
+        .unwrap();
+    assert_eq!(request.headers["authorization"], expected["authorization"]);
+}
+
+#[tokio::test]
+async fn test_requests_are_signed_instead_of_bearer() {
+    let gateway = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-signed",
+            "object": "chat.completion",
+            "created": 1700000000,
+            "model": "gpt-4o-mini",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Hello!"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 5, "completion_tokens": 2, "total_tokens": 7}
+        })))
+        .mount(&gateway)
+        .await;
+    let (server, _zion) = signed_server(&gateway).await;
+
+    send_chat(&server, false).await;
+
+    let requests = gateway.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 1);
+    assert_signed(&requests[0]);
+    assert!(!requests[0].body.is_empty());
+}
+
+#[tokio::test]
+async fn test_streaming_requests_are_signed_over_the_full_body() {
+    let gateway = MockServer::start().await;
+    let script = StreamScript::new("gpt-4o-mini").text("Hello!").usage(10, 5);
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(script.response())
+        .mount(&gateway)
+        .await;
+    let (server, _zion) = signed_server(&gateway).await;
+
+    send_chat(&server, true).await;
+
+    let requests = gateway.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 1);
+    assert_signed(&requests[0]);
+    let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!(body["stream"], true);
+}
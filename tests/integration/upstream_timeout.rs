@@ -0,0 +1,248 @@
+//! Per-request upstream timeout tests
+//!
+//! Point a real OpenAI provider at a deliberately slow wiremock upstream and
+//! verify the client-requested timeout is clamped, applied, and echoed back,
+//! for both non-streaming (504) and streaming (SSE error event) requests.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::{header, StatusCode};
+use axum_test::TestServer;
+use serde_json::{json, Value};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use sentinel::proxy::AiProvider;
+use sentinel::testing::{constants, test_config, test_state, zion_stub, StreamScript};
+use sentinel::{routes, OpenAIProvider};
+
+/// Upstream delay, well past every timeout used below
+const UPSTREAM_DELAY: Duration = Duration::from_secs(3);
+
+struct TimeoutHarness {
+    server: TestServer,
+    openai: MockServer,
+    #[allow(dead_code)]
+    zion: MockServer,
+}
+
+/// Build a test server with a real provider pointed at a wiremock upstream
+async fn timeout_harness() -> TimeoutHarness {
+    let zion = zion_stub().await;
+    let openai = MockServer::start().await;
+
+    let mut config = test_config(&zion.uri(), &format!("{}/v1", openai.uri()));
+    config.provider.upstream_timeout_min_ms = 100;
+    config.provider.upstream_timeout_max_ms = 1_000;
+
+    let provider: Arc<dyn AiProvider> =
+        Arc::new(OpenAIProvider::new(reqwest::Client::new(), &config));
+    let state = test_state(config, provider).await;
+    let server = TestServer::new(routes::create_router(state)).unwrap();
+
+    TimeoutHarness { server, openai, zion }
+}
+
+fn chat_completion_body() -> Value {
+    json!({
+        "id": "chatcmpl-timeout",
+        "object": "chat.completion",
+        "created": 1700000000,
+        "model": "gpt-4o-mini",
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": "Hello!"},
+            "finish_reason": "stop"
+        }],
+        "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+    })
+}
+
+fn stream_script() -> StreamScript {
+    StreamScript::new("gpt-4o-mini").text("Hello!")
+}
+
+/// Mount a chat completions mock answering after `delay`
+async fn mount_chat(harness: &TimeoutHarness, streaming: bool, delay: Duration) {
+    let template = if streaming {
+        stream_script().response()
+    } else {
+        ResponseTemplate::new(200).set_body_json(chat_completion_body())
+    };
+
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(template.set_delay(delay))
+        .mount(&harness.openai)
+        .await;
+}
+
+fn auth_header() -> axum::http::HeaderValue {
+    format!("Bearer {}", constants::TEST_JWT_TOKEN).parse().unwrap()
+}
+
+fn chat_request(stream: bool) -> Value {
+    json!({
+        "model": "gpt-4o-mini",
+        "messages": [{"role": "user", "content": "Hi"}],
+        "stream": stream
+    })
+}
+
+fn native_request(stream: bool, timeout_ms: u64) -> Value {
+    json!({
+        "messages": [{"role": "user", "content": "Hi"}],
+        "stream": stream,
+        "timeout_ms": timeout_ms
+    })
+}
+
+#[tokio::test]
+async fn test_non_streaming_timeout_returns_504() {
+    let harness = timeout_harness().await;
+    mount_chat(&harness, false, UPSTREAM_DELAY).await;
+
+    let response = harness
+        .server
+        .post("/v1/chat/completions")
+        .add_header(header::AUTHORIZATION, auth_header())
+        .add_header("X-Sentinel-Timeout-Ms".parse().unwrap(), "200".parse().unwrap())
+        .json(&chat_request(false))
+        .await;
+
+    response.assert_status(StatusCode::GATEWAY_TIMEOUT);
+    assert_eq!(response.header("X-Sentinel-Timeout-Ms"), "200");
+    let body: Value = response.json();
+    assert_eq!(body["error"]["code"], "upstream_timeout");
+    assert_eq!(body["error"]["retryable"], true);
+}
+
+#[tokio::test]
+async fn test_timeout_is_clamped_and_echoed() {
+    let harness = timeout_harness().await;
+    mount_chat(&harness, false, Duration::ZERO).await;
+
+    // Below the minimum
+    let response = harness
+        .server
+        .post("/v1/chat/completions")
+        .add_header(header::AUTHORIZATION, auth_header())
+        .add_header("X-Sentinel-Timeout-Ms".parse().unwrap(), "5".parse().unwrap())
+        .json(&chat_request(false))
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.header("X-Sentinel-Timeout-Ms"), "100");
+
+    // Above the maximum
+    let response = harness
+        .server
+        .post("/v1/chat/completions")
+        .add_header(header::AUTHORIZATION, auth_header())
+        .add_header("X-Sentinel-Timeout-Ms".parse().unwrap(), "600000".parse().unwrap())
+        .json(&chat_request(false))
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.header("X-Sentinel-Timeout-Ms"), "1000");
+
+    // Without a requested timeout nothing is echoed
+    let response = harness
+        .server
+        .post("/v1/chat/completions")
+        .add_header(header::AUTHORIZATION, auth_header())
+        .json(&chat_request(false))
+        .await;
+    response.assert_status_ok();
+    assert!(response.maybe_header("X-Sentinel-Timeout-Ms").is_none());
+}
+
+#[tokio::test]
+async fn test_invalid_timeout_header_rejected() {
+    let harness = timeout_harness().await;
+    mount_chat(&harness, false, Duration::ZERO).await;
+
+    harness
+        .server
+        .post("/v1/chat/completions")
+        .add_header(header::AUTHORIZATION, auth_header())
+        .add_header("X-Sentinel-Timeout-Ms".parse().unwrap(), "soon".parse().unwrap())
+        .json(&chat_request(false))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_streaming_timeout_before_first_byte_sends_error_event() {
+    let harness = timeout_harness().await;
+    mount_chat(&harness, true, UPSTREAM_DELAY).await;
+
+    let response = harness
+        .server
+        .post("/v1/chat/completions")
+        .add_header(header::AUTHORIZATION, auth_header())
+        .add_header("X-Sentinel-Timeout-Ms".parse().unwrap(), "200".parse().unwrap())
+        .json(&chat_request(true))
+        .await;
+
+    response.assert_status_ok();
+    assert_eq!(response.header("X-Sentinel-Timeout-Ms"), "200");
+    let body = response.text();
+    assert!(body.starts_with("data: "), "Unexpected stream body: {}", body);
+    assert!(body.contains("\"code\":\"upstream_timeout\""));
+    assert!(body.contains("\"retryable\":true"));
+    assert!(!body.contains("Hello!"));
+}
+
+#[tokio::test]
+async fn test_streaming_within_timeout_is_untouched() {
+    let harness = timeout_harness().await;
+    mount_chat(&harness, true, Duration::ZERO).await;
+
+    let response = harness
+        .server
+        .post("/v1/chat/completions")
+        .add_header(header::AUTHORIZATION, auth_header())
+        .add_header("X-Sentinel-Timeout-Ms".parse().unwrap(), "1000".parse().unwrap())
+        .json(&chat_request(true))
+        .await;
+
+    response.assert_status_ok();
+    assert_eq!(response.text(), stream_script().body());
+}
+
+#[tokio::test]
+async fn test_native_non_streaming_timeout_returns_504() {
+    let harness = timeout_harness().await;
+    mount_chat(&harness, false, UPSTREAM_DELAY).await;
+
+    let response = harness
+        .server
+        .post("/native/v1/chat/completions")
+        .add_header(header::AUTHORIZATION, auth_header())
+        .json(&native_request(false, 200))
+        .await;
+
+    response.assert_status(StatusCode::GATEWAY_TIMEOUT);
+    assert_eq!(response.header("X-Sentinel-Timeout-Ms"), "200");
+    let body: Value = response.json();
+    assert_eq!(body["error"]["code"], "upstream_timeout");
+    assert_eq!(body["error"]["type"], "timeout_error");
+}
+
+#[tokio::test]
+async fn test_native_streaming_timeout_sends_error_event() {
+    let harness = timeout_harness().await;
+    mount_chat(&harness, true, UPSTREAM_DELAY).await;
+
+    let response = harness
+        .server
+        .post("/native/v1/chat/completions")
+        .add_header(header::AUTHORIZATION, auth_header())
+        .json(&native_request(true, 200))
+        .await;
+
+    response.assert_status_ok();
+    assert_eq!(response.header("X-Sentinel-Timeout-Ms"), "200");
+    let body = response.text();
+    assert!(body.contains("\"code\":\"upstream_timeout\""), "Unexpected stream body: {}", body);
+}
@@ -0,0 +1,229 @@
+//! Upstream `user` field tests
+//!
+//! `UPSTREAM_USER_FIELD` decides the `user` value the provider receives on
+//! `/v1` (pass-through included) and native requests: the client's own, a
+//! keyed hash of it, a keyed hash of the authenticated external ID, or none.
+//! In the hashing modes the client's value never reaches the provider. The
+//! ledger row records the forwarded value, hashed; logs never carry it.
+
+use std::sync::Arc;
+
+use axum::http::header;
+use axum_test::TestServer;
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+
+use sentinel::proxy::upstream_user::{self, UpstreamUserField};
+use sentinel::testing::{
+    capture_logs, constants, test_config, zion_stub, MockAiProvider, MockEndpoint, MockReply,
+};
+use sentinel::usage::ledger::{LedgerEntry, LedgerHandle, LedgerOp};
+use sentinel::{routes, AppState, BatchingUsageTracker, ZionClient};
+
+/// PII a client might put in `user`
+const CLIENT_USER: &str = "jane.doe@example.com";
+const HASH_KEY: &str = "user-hash-key";
+
+/// Provider bodies of each request, and the ledger rows they were tracked in
+struct Forwarded {
+    v1_chat: Value,
+    native_chat: Value,
+    embeddings: Value,
+    responses: Value,
+    passthrough: Value,
+    ledger: Vec<LedgerEntry>,
+}
+
+impl Forwarded {
+    fn bodies(&self) -> [&Value; 5] {
+        [
+            &self.v1_chat,
+            &self.native_chat,
+            &self.embeddings,
+            &self.responses,
+            &self.passthrough,
+        ]
+    }
+
+    fn users(&self) -> [&Value; 5] {
+        self.bodies().map(|body| &body["user"])
+    }
+
+    /// The `upstream_user` of every ledger row
+    fn recorded(&self) -> Vec<Option<&str>> {
+        assert_eq!(self.ledger.len(), 5, "{:?}", self.ledger);
+        self.ledger
+            .iter()
+            .map(|entry| entry.upstream_user.as_deref())
+            .collect()
+    }
+}
+
+/// Send each request with `client_user` under `mode` and capture what the provider got
+async fn send_all(mode: UpstreamUserField, client_user: Option<&str>) -> Forwarded {
+    let provider = Arc::new(
+        MockAiProvider::new()
+            .with_reply(
+                MockEndpoint::ChatCompletions,
+                MockReply::chat_completion("gpt-4o-mini", "Hi!", 10, 5),
+            )
+            .with_reply(
+                MockEndpoint::Embeddings,
+                MockReply::Json(json!({
+                    "object": "list",
+                    "data": [{"object": "embedding", "index": 0, "embedding": [0.5]}],
+                    "model": "text-embedding-3-small",
+                    "usage": {"prompt_tokens": 2, "total_tokens": 2}
+                })),
+            )
+            .with_reply(
+                MockEndpoint::Responses,
+                MockReply::Json(json!({
+                    "id": "resp_1",
+                    "model": "gpt-4o-mini",
+                    "output": [],
+                    "usage": {"input_tokens": 4, "output_tokens": 2, "total_tokens": 6}
+                })),
+            )
+            .with_reply(MockEndpoint::Passthrough, MockReply::Json(json!({"data": []}))),
+    );
+    let zion = zion_stub().await;
+    let mut config = test_config(&zion.uri(), "http://mock-provider.invalid/v1");
+    config.provider.upstream_user_field = mode;
+    config.provider.upstream_user_hash_key = Some(HASH_KEY.to_string());
+
+    // Read the ledger's write queue directly (the store itself needs the ledger feature)
+    let (sender, mut ledger) = mpsc::channel(16);
+    let zion_client = Arc::new(ZionClient::new(reqwest::Client::new(), &config));
+    let tracker = Arc::new(BatchingUsageTracker::new_for_testing_with_ledger(
+        zion_client.clone(),
+        LedgerHandle::new(sender),
+    ));
+    let state =
+        Arc::new(AppState::new_for_testing(config, zion_client, provider.clone(), tracker).await);
+    let server = TestServer::new(routes::create_router(state)).unwrap();
+
+    let with_user = |mut body: Value| {
+        if let Some(user) = client_user {
+            body["user"] = json!(user);
+        }
+        body
+    };
+    let messages = json!([{"role": "user", "content": "Hello"}]);
+
+    for (path, body) in [
+        (
+            "/v1/chat/completions",
+            json!({"model": "gpt-4o-mini", "messages": messages}),
+        ),
+        (
+            "/native/v1/chat/completions",
+            json!({"tier": "simple", "messages": messages}),
+        ),
+        (
+            "/v1/embeddings",
+            json!({"model": "text-embedding-3-small", "input": "Hello"}),
+        ),
+        (
+            "/v1/responses",
+            json!({"model": "gpt-4o-mini", "input": messages}),
+        ),
+        (
+            "/v1/images/generations",
+            json!({"model": "dall-e-3", "prompt": "A cat"}),
+        ),
+    ] {
+        server
+            .post(path)
+            .add_header(
+                header::AUTHORIZATION,
+                format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                    .parse()
+                    .unwrap(),
+            )
+            .json(&with_user(body))
+            .await
+            .assert_status_ok();
+    }
+
+    let mut rows = Vec::new();
+    while let Ok(op) = ledger.try_recv() {
+        if let LedgerOp::Append(entry) = op {
+            rows.push(entry);
+        }
+    }
+    let chats = provider.requests_for(MockEndpoint::ChatCompletions);
+    assert_eq!(chats.len(), 2);
+    Forwarded {
+        v1_chat: chats[0].clone(),
+        native_chat: chats[1].clone(),
+        embeddings: provider.requests_for(MockEndpoint::Embeddings)[0].clone(),
+        responses: provider.requests_for(MockEndpoint::Responses)[0].clone(),
+        passthrough: provider.requests_for(MockEndpoint::Passthrough)[0]["body"].clone(),
+        ledger: rows,
+    }
+}
+
+fn assert_no_pii(forwarded: &Forwarded) {
+    for body in forwarded.bodies() {
+        assert!(
+            !body.to_string().contains(CLIENT_USER),
+            "client user leaked: {}",
+            body
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_passthrough_forwards_the_client_value() {
+    let (_, logs, _guard) = capture_logs("info");
+    let forwarded = send_all(UpstreamUserField::Passthrough, Some(CLIENT_USER)).await;
+    assert_eq!(forwarded.users(), [&json!(CLIENT_USER); 5]);
+
+    // The ledger keeps a hash of the value, never the value itself
+    let hashed = upstream_user::hash(HASH_KEY, CLIENT_USER);
+    assert_eq!(forwarded.recorded(), [Some(hashed.as_str()); 5]);
+    assert!(!logs.text().contains(CLIENT_USER));
+
+    let forwarded = send_all(UpstreamUserField::Passthrough, None).await;
+    assert_eq!(forwarded.users(), [&Value::Null; 5]);
+    assert_eq!(forwarded.recorded(), [None; 5]);
+}
+
+#[tokio::test]
+async fn test_hash_replaces_the_client_value() {
+    let (_, logs, _guard) = capture_logs("info");
+    let forwarded = send_all(UpstreamUserField::Hash, Some(CLIENT_USER)).await;
+    let hashed = upstream_user::hash(HASH_KEY, CLIENT_USER);
+    let expected = json!(hashed);
+    assert_eq!(forwarded.users(), [&expected; 5]);
+    assert_no_pii(&forwarded);
+
+    // The ledger row names the value the provider saw, for matching abuse reports
+    assert_eq!(forwarded.recorded(), [Some(hashed.as_str()); 5]);
+    assert!(!logs.text().contains(CLIENT_USER));
+
+    // Nothing to hash, nothing sent
+    let forwarded = send_all(UpstreamUserField::Hash, None).await;
+    assert_eq!(forwarded.users(), [&Value::Null; 5]);
+}
+
+#[tokio::test]
+async fn test_external_id_hash_ignores_the_client_value() {
+    let expected = json!(upstream_user::hash(HASH_KEY, constants::TEST_EXTERNAL_ID));
+
+    let forwarded = send_all(UpstreamUserField::ExternalIdHash, Some(CLIENT_USER)).await;
+    assert_eq!(forwarded.users(), [&expected; 5]);
+    assert_no_pii(&forwarded);
+
+    let forwarded = send_all(UpstreamUserField::ExternalIdHash, None).await;
+    assert_eq!(forwarded.users(), [&expected; 5]);
+}
+
+#[tokio::test]
+async fn test_omit_drops_the_field() {
+    let forwarded = send_all(UpstreamUserField::Omit, Some(CLIENT_USER)).await;
+    assert_eq!(forwarded.users(), [&Value::Null; 5]);
+    assert_no_pii(&forwarded);
+    assert_eq!(forwarded.recorded(), [None; 5]);
+}
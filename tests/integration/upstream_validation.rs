@@ -0,0 +1,148 @@
+//! Upstream response validation tests
+//!
+//! Non-streaming chat completions that come back 200 but structurally broken
+//! are returned as 502 `upstream_invalid_response` and counted in
+//! `sentinel_upstream_invalid_responses_total`, unless
+//! `VALIDATE_UPSTREAM_RESPONSES` is off.
+
+use std::sync::Arc;
+
+use axum::http::{header, StatusCode};
+use axum_test::{TestResponse, TestServer};
+use serde_json::{json, Value};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use sentinel::proxy::{redirect, AiProvider};
+use sentinel::routes::metrics::init_metrics;
+use sentinel::testing::{
+    constants, test_config, test_state, zion_stub, MockAiProvider, MockEndpoint, MockReply,
+    TestHarness,
+};
+use sentinel::{routes, OpenAIProvider};
+
+async fn send_chat(server: &TestServer) -> TestResponse {
+    server
+        .post("/v1/chat/completions")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .json(&json!({
+            "model": "gpt-4o",
+            "messages": [{"role": "user", "content": "Hi"}]
+        }))
+        .await
+}
+
+fn invalid_count(metrics: &str, provider: &str) -> u64 {
+    metrics
+        .lines()
+        .find(|line| {
+            line.starts_with("sentinel_upstream_invalid_responses_total{")
+                && line.contains(&format!("provider=\"{}\"", provider))
+        })
+        .and_then(|line| line.rsplit(' ').next())
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(0)
+}
+
+fn malformed_bodies() -> Vec<Value> {
+    let message = json!({"role": "assistant", "content": "Hi"});
+    let usage = json!({"prompt_tokens": 5, "completion_tokens": 1, "total_tokens": 6});
+    vec![
+        // Empty choices
+        json!({"id": "c1", "object": "chat.completion", "created": 1700000000, "model": "gpt-4o", "choices": [], "usage": usage}),
+        // Missing usage
+        json!({"id": "c2", "object": "chat.completion", "created": 1700000000, "model": "gpt-4o",
+               "choices": [{"index": 0, "message": message, "finish_reason": "stop"}]}),
+        // Message of the wrong type
+        json!({"id": "c3", "object": "chat.completion", "created": 1700000000, "model": "gpt-4o",
+               "choices": [{"index": 0, "message": "Hi", "finish_reason": "stop"}], "usage": usage}),
+        // No finish_reason
+        json!({"id": "c4", "object": "chat.completion", "created": 1700000000, "model": "gpt-4o",
+               "choices": [{"index": 0, "message": message}], "usage": usage}),
+    ]
+}
+
+#[tokio::test]
+async fn test_malformed_bodies_become_502() {
+    init_metrics();
+    let provider = Arc::new(MockAiProvider::new());
+    for body in malformed_bodies() {
+        provider.push_reply(MockEndpoint::ChatCompletions, MockReply::Json(body));
+    }
+    let harness = TestHarness::with_config(provider, |_| {}).await;
+    let server = TestServer::new(harness.router()).unwrap();
+    let before = invalid_count(&server.get("/metrics").await.text(), "mock");
+
+    for expected in [
+        "choices is empty",
+        "usage is missing",
+        "choices[0].message is not an object",
+        "choices[0].finish_reason is missing",
+    ] {
+        let response = send_chat(&server).await;
+        response.assert_status(StatusCode::BAD_GATEWAY);
+        let body: Value = response.json();
+        assert_eq!(body["error"]["code"], "upstream_invalid_response");
+        let message = body["error"]["message"].as_str().unwrap();
+        assert!(message.contains(expected), "{}", message);
+    }
+
+    let after = invalid_count(&server.get("/metrics").await.text(), "mock");
+    assert_eq!(after - before, 4);
+}
+
+#[tokio::test]
+async fn test_validation_can_be_disabled() {
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::Json(malformed_bodies().remove(1)),
+    ));
+    let harness = TestHarness::with_config(provider, |config| {
+        config.provider.validate_upstream_responses = false;
+    })
+    .await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    // Without usage the tokens are estimated, as before
+    let response = send_chat(&server).await;
+    response.assert_status_ok();
+    let body: Value = response.json();
+    assert_eq!(body["choices"][0]["message"]["content"], "Hi");
+}
+
+#[tokio::test]
+async fn test_error_names_upstream_request_id() {
+    let zion = zion_stub().await;
+    let gateway = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("x-request-id", "req_broken_42")
+                .set_body_json(json!({"id": "c1", "object": "chat.completion", "created": 1700000000, "model": "gpt-4o", "choices": []})),
+        )
+        .mount(&gateway)
+        .await;
+    let config = test_config(&zion.uri(), &format!("{}/v1", gateway.uri()));
+    let provider: Arc<dyn AiProvider> = Arc::new(OpenAIProvider::new(
+        redirect::provider_client().unwrap(),
+        &config,
+    ));
+    let server =
+        TestServer::new(routes::create_router(test_state(config, provider).await)).unwrap();
+
+    let response = send_chat(&server).await;
+
+    response.assert_status(StatusCode::BAD_GATEWAY);
+    let body: Value = response.json();
+    assert_eq!(body["error"]["code"], "upstream_invalid_response");
+    assert!(body["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("req_broken_42"));
+}
@@ -0,0 +1,141 @@
+//! Local usage aggregate tests
+//!
+//! Traffic goes through the harness; the daily counters exposed by
+//! `/admin/users/{external_id}/usage` and `/v1/usage` must match what the
+//! batching worker sent to mock Zion.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::{header, StatusCode};
+use axum_test::TestServer;
+use serde_json::{json, Value};
+
+use sentinel::testing::{
+    constants, extract_token_counts, parse_batch_payload, MockAiProvider, MockEndpoint, MockReply,
+    TestHarness,
+};
+
+const ADMIN_KEY: &str = "test-admin-key";
+
+async fn harness() -> TestHarness {
+    let provider = Arc::new(
+        MockAiProvider::new()
+            .with_reply(
+                MockEndpoint::ChatCompletions,
+                MockReply::chat_completion("gpt-4o-mini", "Hello!", 12, 7),
+            )
+            .with_reply(
+                MockEndpoint::ChatCompletions,
+                MockReply::chat_stream("gpt-4o-mini", "Hello there", Some((20, 9))),
+            ),
+    );
+    TestHarness::with_config(provider, |config| {
+        config.server.admin_api_key = Some(ADMIN_KEY.to_string());
+    })
+    .await
+}
+
+async fn send_chat(server: &TestServer, stream: bool) {
+    server
+        .post("/v1/chat/completions")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN).parse().unwrap(),
+        )
+        .json(&json!({
+            "model": "gpt-4o-mini",
+            "messages": [{"role": "user", "content": "Hi"}],
+            "stream": stream
+        }))
+        .await
+        .assert_status_ok();
+}
+
+/// Wait until Zion has received `requests` requests' worth of increments, returning the totals
+async fn zion_totals(harness: &TestHarness, requests: i64) -> (i64, i64, i64) {
+    let deadline = std::time::Instant::now() + Duration::from_secs(5);
+    loop {
+        let totals = harness
+            .wait_for_batch_requests(1, Duration::from_secs(2))
+            .await
+            .iter()
+            .flat_map(parse_batch_payload)
+            .map(|item| extract_token_counts(&item))
+            .fold((0, 0, 0), |acc, (i, o, r)| (acc.0 + i, acc.1 + o, acc.2 + r));
+        if totals.2 >= requests || std::time::Instant::now() > deadline {
+            return totals;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
+
+fn counters(section: &Value) -> (i64, i64, i64) {
+    (
+        section["input_tokens"].as_i64().unwrap(),
+        section["output_tokens"].as_i64().unwrap(),
+        section["requests"].as_i64().unwrap(),
+    )
+}
+
+#[tokio::test]
+async fn test_daily_counters_match_batch_increments() {
+    let harness = harness().await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    send_chat(&server, false).await;
+    send_chat(&server, true).await;
+    send_chat(&server, true).await;
+
+    let sent = zion_totals(&harness, 3).await;
+    assert_eq!(sent.2, 3);
+
+    let response = server
+        .get(&format!("/admin/users/{}/usage", constants::TEST_EXTERNAL_ID))
+        .add_query_param("days", 7)
+        .add_header("X-Admin-Key".parse().unwrap(), ADMIN_KEY.parse().unwrap())
+        .await;
+    response.assert_status_ok();
+    let body: Value = response.json();
+
+    assert_eq!(body["days"], 7);
+    assert_eq!(body["daily"].as_array().unwrap().len(), 7);
+    // All traffic happened today (the first entry)
+    assert_eq!(counters(&body["daily"][0]), sent);
+    assert_eq!(counters(&body["total"]), sent);
+}
+
+#[tokio::test]
+async fn test_v1_usage_includes_recent_section() {
+    let harness = harness().await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    send_chat(&server, false).await;
+    let sent = zion_totals(&harness, 1).await;
+
+    let response = server
+        .get("/v1/usage")
+        .add_query_param("days", 1)
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN).parse().unwrap(),
+        )
+        .await;
+    response.assert_status_ok();
+    let body: Value = response.json();
+
+    assert!(body["limits"].is_array());
+    assert_eq!(body["recent"]["days"], 1);
+    assert_eq!(counters(&body["recent"]["total"]), sent);
+}
+
+#[tokio::test]
+async fn test_admin_usage_hidden_without_key() {
+    let harness = harness().await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    server
+        .get(&format!("/admin/users/{}/usage", constants::TEST_EXTERNAL_ID))
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,194 @@
+//! Usage ledger tests
+//!
+//! Verify tracked requests land in the local ledger with their Zion delivery
+//! status, and that the admin export is hidden without the admin key.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::{header, StatusCode};
+use axum_test::TestServer;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use sentinel::testing::{
+    constants, test_config, zion_stub, MockAiProvider, MockEndpoint, MockReply,
+};
+use sentinel::usage::ledger::{spawn_ledger_writer, LedgerStore, LedgerWriterConfig};
+use sentinel::{routes, AppState, BatchingUsageTracker, ZionClient};
+
+const ADMIN_KEY: &str = "test-admin-key";
+
+struct LedgerHarness {
+    server: TestServer,
+    #[allow(dead_code)]
+    zion: MockServer,
+}
+
+/// Build a test server whose batching tracker dual-writes to an in-memory ledger
+async fn ledger_harness(zion: MockServer) -> LedgerHarness {
+    let mut config = test_config(&zion.uri(), "http://mock-provider.invalid/v1");
+    config.server.admin_api_key = Some(ADMIN_KEY.to_string());
+
+    let store = Arc::new(LedgerStore::connect("sqlite::memory:").await.unwrap());
+    let ledger = spawn_ledger_writer(
+        store.clone(),
+        None,
+        LedgerWriterConfig {
+            flush_interval: Duration::from_millis(10),
+            ..Default::default()
+        },
+    );
+
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_completion("gpt-4o-mini", "Hello!", 10, 5),
+    ));
+    let zion_client = Arc::new(ZionClient::new(reqwest::Client::new(), &config));
+    let tracker = Arc::new(BatchingUsageTracker::new_for_testing_with_ledger(
+        zion_client.clone(),
+        ledger,
+    ));
+
+    let mut state = AppState::new_for_testing(config, zion_client, provider, tracker).await;
+    state.ledger = Some(store);
+
+    let server = TestServer::new(routes::create_router(Arc::new(state))).unwrap();
+    LedgerHarness { server, zion }
+}
+
+async fn send_chat(server: &TestServer) {
+    server
+        .post("/v1/chat/completions")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN).parse().unwrap(),
+        )
+        .json(&json!({
+            "model": "gpt-4o-mini",
+            "messages": [{"role": "user", "content": "Hi"}],
+            "user": constants::TEST_EMAIL
+        }))
+        .await
+        .assert_status_ok();
+}
+
+/// Poll the JSON export until a row reaches `status` or the timeout elapses
+async fn wait_for_status(server: &TestServer, status: &str) -> Vec<Value> {
+    for _ in 0..100 {
+        let rows: Vec<Value> = server
+            .get("/admin/ledger/export")
+            .add_query_param("from", "2000-01-01T00:00:00Z")
+            .add_query_param("to", "2100-01-01T00:00:00Z")
+            .add_header("X-Admin-Key".parse().unwrap(), ADMIN_KEY.parse().unwrap())
+            .await
+            .json();
+        if rows.iter().any(|r| r["delivery_status"] == status) {
+            return rows;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("No ledger row reached status '{}'", status);
+}
+
+#[tokio::test]
+async fn test_tracked_request_is_recorded_as_delivered() {
+    let harness = ledger_harness(zion_stub().await).await;
+    send_chat(&harness.server).await;
+
+    let rows = wait_for_status(&harness.server, "delivered").await;
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0]["model"], "gpt-4o-mini");
+    assert_eq!(rows[0]["input_tokens"], 10);
+    assert_eq!(rows[0]["output_tokens"], 5);
+    assert_eq!(rows[0]["requests"], 1);
+    assert!(
+        !rows[0]["user_hash"]
+            .as_str()
+            .unwrap()
+            .contains(constants::TEST_EMAIL),
+        "Ledger must not store raw emails"
+    );
+}
+
+#[tokio::test]
+async fn test_zion_failure_is_recorded_as_failed() {
+    let zion = zion_stub().await;
+    Mock::given(method("POST"))
+        .and(path("/api/v1/usage/external/batch-increment"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&zion)
+        .await;
+
+    let harness = ledger_harness(zion).await;
+    send_chat(&harness.server).await;
+
+    let rows = wait_for_status(&harness.server, "failed").await;
+    assert_eq!(rows.len(), 1);
+}
+
+#[tokio::test]
+async fn test_export_csv() {
+    let harness = ledger_harness(zion_stub().await).await;
+    send_chat(&harness.server).await;
+    wait_for_status(&harness.server, "delivered").await;
+
+    let response = harness
+        .server
+        .get("/admin/ledger/export")
+        .add_query_param("from", "2000-01-01T00:00:00Z")
+        .add_query_param("to", "2100-01-01T00:00:00Z")
+        .add_query_param("format", "csv")
+        .add_header("X-Admin-Key".parse().unwrap(), ADMIN_KEY.parse().unwrap())
+        .await;
+    response.assert_status_ok();
+    assert!(response
+        .header(header::CONTENT_TYPE)
+        .to_str()
+        .unwrap()
+        .starts_with("text/csv"));
+
+    let body = response.text();
+    let lines: Vec<&str> = body.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].ends_with(",delivery_status,upstream_user"));
+    assert!(lines[1].contains(",gpt-4o-mini,10,5,1,"));
+    // The `user` passed through as sent is recorded as its hash (no hash key set)
+    let upstream_user = hex::encode(Sha256::digest(constants::TEST_EMAIL.as_bytes()));
+    assert!(lines[1].ends_with(&format!(",delivered,{}", upstream_user)));
+    assert!(!lines[1].contains(constants::TEST_EMAIL));
+}
+
+#[tokio::test]
+async fn test_export_rejects_bad_range() {
+    let harness = ledger_harness(zion_stub().await).await;
+
+    let response = harness
+        .server
+        .get("/admin/ledger/export")
+        .add_query_param("from", "2100-01-01T00:00:00Z")
+        .add_query_param("to", "2000-01-01T00:00:00Z")
+        .add_header("X-Admin-Key".parse().unwrap(), ADMIN_KEY.parse().unwrap())
+        .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_export_hidden_without_admin_key() {
+    let harness = ledger_harness(zion_stub().await).await;
+
+    let query = "/admin/ledger/export?from=2000-01-01T00:00:00Z&to=2100-01-01T00:00:00Z";
+    harness
+        .server
+        .get(query)
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+    harness
+        .server
+        .get(query)
+        .add_header("X-Admin-Key".parse().unwrap(), "wrong".parse().unwrap())
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,98 @@
+//! Failed usage increments queue tests (`sentinel usage-queue`)
+//!
+//! Seed a queue in Redis under a test key and check stats, export, purge and
+//! the lock shared with the retry loop. Skipped when Redis isn't available.
+
+use std::time::Duration;
+
+use chrono::Utc;
+use redis::AsyncCommands;
+use serde_json::{json, Value};
+
+use sentinel::usage::queue::{export, ExportFormat};
+use sentinel::usage::FailedQueue;
+
+/// Test helper to connect to Redis (skips test if unavailable)
+async fn get_test_redis() -> Option<redis::aio::ConnectionManager> {
+    let client = redis::Client::open("redis://127.0.0.1:6379").ok()?;
+    client.get_connection_manager().await.ok()
+}
+
+/// Push increments to a fresh test queue
+async fn seed(redis: &mut redis::aio::ConnectionManager, key: &str) {
+    let _: () = redis.del(&[key, &format!("{}:lock", key)]).await.unwrap();
+    let old = (Utc::now() - chrono::Duration::days(10)).to_rfc3339();
+    let recent = Utc::now().to_rfc3339();
+    for (email, tokens, timestamp) in [
+        ("alice@example.com", 100, &old),
+        ("bob@example.com", 5, &recent),
+        ("alice@example.com", 50, &recent),
+    ] {
+        let entry = json!({
+            "email": email, "input_tokens": tokens, "output_tokens": 1,
+            "requests": 1, "model": "gpt-4o", "timestamp": timestamp
+        });
+        let _: () = redis.rpush(key, entry.to_string()).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_stats_and_export_of_seeded_queue() {
+    let mut redis = match get_test_redis().await {
+        Some(r) => r,
+        None => {
+            eprintln!("Skipping test: Redis not available");
+            return;
+        }
+    };
+    let key = "sentinel:test:usage:failed:stats";
+    seed(&mut redis, key).await;
+    let queue = FailedQueue::with_key(redis.clone(), key);
+
+    let stats = queue.stats().await.unwrap();
+    assert_eq!(stats.length, 3);
+    assert_eq!(stats.users.len(), 2);
+    assert_eq!(stats.users[0].email, "alice@example.com");
+    assert_eq!(stats.users[0].input_tokens, 150);
+    assert!(stats.oldest < stats.newest);
+
+    let exported: Value =
+        serde_json::from_str(&export(&queue.entries().await.unwrap(), ExportFormat::Json)).unwrap();
+    assert_eq!(exported.as_array().unwrap().len(), 3);
+    let csv = export(&queue.entries().await.unwrap(), ExportFormat::Csv);
+    assert_eq!(csv.lines().count(), 4);
+
+    // Reading never drains the queue
+    assert_eq!(queue.len().await.unwrap(), 3);
+    let _: () = redis.del(key).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_purge_holds_the_lock() {
+    let mut redis = match get_test_redis().await {
+        Some(r) => r,
+        None => {
+            eprintln!("Skipping test: Redis not available");
+            return;
+        }
+    };
+    let key = "sentinel:test:usage:failed:purge";
+    seed(&mut redis, key).await;
+    let queue = FailedQueue::with_key(redis.clone(), key);
+
+    let lock = queue.try_lock().await.unwrap().expect("lock is free");
+    // A retry loop (or a second operator) can't take it meanwhile
+    assert!(queue.try_lock().await.unwrap().is_none());
+
+    let purged = queue
+        .purge_older_than(&lock, Duration::from_secs(7 * 86_400))
+        .await
+        .unwrap();
+    assert_eq!(purged, 1);
+    assert_eq!(queue.stats().await.unwrap().users[0].input_tokens, 50);
+
+    queue.unlock(lock).await.unwrap();
+    let lock = queue.try_lock().await.unwrap().expect("lock was released");
+    queue.unlock(lock).await.unwrap();
+    let _: () = redis.del(key).await.unwrap();
+}
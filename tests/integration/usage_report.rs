@@ -0,0 +1,174 @@
+//! Aggregate usage report tests (`GET /admin/reports/usage`)
+//!
+//! Traffic across two tiers (and so two models) plus the OpenAI-compatible
+//! API must show up in the report with the same totals Zion received in its
+//! batch increments, grouped by model, tier or day, as JSON or CSV.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::{header, StatusCode};
+use axum_test::{TestResponse, TestServer};
+use chrono::Utc;
+use serde_json::{json, Value};
+
+use sentinel::testing::{
+    constants, extract_token_counts, parse_batch_payload, MockAiProvider, MockEndpoint, MockReply,
+    TestHarness,
+};
+
+const ADMIN_KEY: &str = "test-admin-key";
+
+async fn start() -> (TestHarness, TestServer) {
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_completion("gpt-4o-mini", "Hello", 10, 5),
+    ));
+    let harness = TestHarness::with_config(provider, |config| {
+        config.server.admin_api_key = Some(ADMIN_KEY.to_string());
+    })
+    .await;
+    let server = TestServer::new(harness.router()).unwrap();
+    (harness, server)
+}
+
+/// Chat request body for `/native` (`tier`) or `/v1` (`model`)
+fn chat_body(key: &str, value: &str) -> Value {
+    json!({key: value, "messages": [{"role": "user", "content": "Hi"}]})
+}
+
+/// Send one chat request and wait until it's the `batches`th batch Zion received
+async fn chat_and_flush(
+    harness: &TestHarness,
+    server: &TestServer,
+    path: &str,
+    body: Value,
+    batches: usize,
+) {
+    server
+        .post(path)
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN).parse().unwrap(),
+        )
+        .json(&body)
+        .await
+        .assert_status_ok();
+    let received = harness.wait_for_batch_requests(batches, Duration::from_secs(3)).await;
+    assert_eq!(received.len(), batches, "Expected {} batch-increment requests", batches);
+}
+
+async fn report(server: &TestServer, query: &str, accept: &str) -> TestResponse {
+    server
+        .get("/admin/reports/usage")
+        .add_raw_query_param(query)
+        .add_header("X-Admin-Key".parse().unwrap(), ADMIN_KEY.parse().unwrap())
+        .add_header(header::ACCEPT, accept.parse().unwrap())
+        .await
+}
+
+fn today() -> String {
+    Utc::now().format("%Y-%m-%d").to_string()
+}
+
+/// (requests, input_tokens, output_tokens) per group of a JSON report
+fn groups(report: &Value) -> BTreeMap<String, (i64, i64, i64)> {
+    report["groups"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|group| {
+            (
+                group["group"].as_str().unwrap().to_string(),
+                (
+                    group["requests"].as_i64().unwrap(),
+                    group["input_tokens"].as_i64().unwrap(),
+                    group["output_tokens"].as_i64().unwrap(),
+                ),
+            )
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_report_matches_batch_increments() {
+    let (harness, server) = start().await;
+    let native = "/native/v1/chat/completions";
+    chat_and_flush(&harness, &server, native, chat_body("tier", "simple"), 1).await;
+    chat_and_flush(&harness, &server, native, chat_body("tier", "moderate"), 2).await;
+    chat_and_flush(&harness, &server, native, chat_body("tier", "moderate"), 3).await;
+    let openai = "/v1/chat/completions";
+    chat_and_flush(&harness, &server, openai, chat_body("model", "gpt-4o-mini"), 4).await;
+
+    // One request per flush, so every batch item is a single model's usage
+    let mut expected: BTreeMap<String, (i64, i64, i64)> = BTreeMap::new();
+    for batch in harness.wait_for_batch_requests(4, Duration::from_secs(3)).await {
+        for item in parse_batch_payload(&batch) {
+            let (input, output, requests) = extract_token_counts(&item);
+            let totals = expected.entry(item["model"].as_str().unwrap().to_string()).or_default();
+            *totals = (totals.0 + requests, totals.1 + input, totals.2 + output);
+        }
+    }
+    assert_eq!(expected.len(), 2, "{expected:?}");
+
+    let day = today();
+    let by_model = report(&server, &format!("from={day}&to={day}"), "application/json").await;
+    by_model.assert_status_ok();
+    let by_model: Value = by_model.json();
+    assert_eq!(by_model["group_by"], "model");
+    assert_eq!(groups(&by_model), expected);
+    assert_eq!(by_model["total"]["requests"], 4);
+    // Stub tier config prices: gpt-4o-mini 0.15/0.60, gpt-4o 2.50/10.0 per million
+    let cost = by_model["total"]["estimated_cost"].as_f64().unwrap();
+    let expected_cost = (2.0 * (10.0 * 0.15 + 5.0 * 0.60) + 2.0 * (10.0 * 2.50 + 5.0 * 10.0)) / 1e6;
+    assert!((cost - expected_cost).abs() < 1e-12, "{cost}");
+
+    let query = format!("from={day}&to={day}&group_by=tier");
+    let by_tier = groups(&report(&server, &query, "*/*").await.json());
+    // gpt-4o-mini served the simple tier and the OpenAI-compatible request
+    let (simple, none) = (by_tier["simple"], by_tier["none"]);
+    assert_eq!((simple.0 + none.0, simple.1 + none.1, simple.2 + none.2), expected["gpt-4o-mini"]);
+    assert_eq!(by_tier["moderate"], expected["gpt-4o"]);
+
+    let query = format!("from={day}&to={day}&group_by=day");
+    let by_day = groups(&report(&server, &query, "*/*").await.json());
+    assert_eq!(by_day[&day].0, 4);
+}
+
+#[tokio::test]
+async fn test_report_as_csv() {
+    let (harness, server) = start().await;
+    let body = chat_body("tier", "simple");
+    chat_and_flush(&harness, &server, "/native/v1/chat/completions", body, 1).await;
+
+    let day = today();
+    let response = report(&server, &format!("from={day}&to={day}&group_by=tier"), "text/csv").await;
+    response.assert_status_ok();
+    assert!(response.header(header::CONTENT_TYPE).to_str().unwrap().starts_with("text/csv"));
+    let csv = response.text();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], "tier,requests,input_tokens,output_tokens,estimated_cost");
+    assert_eq!(lines[1], "simple,1,10,5,0.0000045");
+    assert_eq!(lines.len(), 2);
+}
+
+#[tokio::test]
+async fn test_report_rejects_bad_ranges() {
+    let (_harness, server) = start().await;
+    for query in [
+        "from=2026-10-02&to=2026-10-01",
+        "from=yesterday&to=2026-10-01",
+        "to=2026-10-01",
+    ] {
+        let response = report(&server, query, "application/json").await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+    }
+
+    // An empty range is a report without groups
+    let empty: Value = report(&server, "from=2020-01-01&to=2020-01-31", "application/json")
+        .await
+        .json();
+    assert_eq!(empty["groups"], json!([]));
+    assert_eq!(empty["total"]["requests"], 0);
+}
@@ -0,0 +1,202 @@
+//! Usage retry lease tests
+//!
+//! Two batching trackers share one failed increments queue in Redis, each
+//! reporting to its own Zion stub: only the replica holding the retry lease
+//! drains the queue, and the other takes over when it shuts down or its lease
+//! expires. The Redis tests are skipped when Redis isn't available.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum_test::TestServer;
+use chrono::Utc;
+use redis::AsyncCommands;
+use serde_json::{json, Value};
+use wiremock::MockServer;
+
+use sentinel::testing::{test_config, zion_stub, MockAiProvider, TestHarness};
+use sentinel::usage::{BatchingConfig, BatchingUsageTracker, RetryLease};
+use sentinel::zion::ZionClient;
+
+const ADMIN_KEY: &str = "admin-secret";
+
+/// Test helper to connect to Redis (skips test if unavailable)
+async fn get_test_redis() -> Option<redis::aio::ConnectionManager> {
+    let client = redis::Client::open("redis://127.0.0.1:6379").ok()?;
+    client.get_connection_manager().await.ok()
+}
+
+/// Fresh test queue holding `count` increments
+async fn seed(redis: &mut redis::aio::ConnectionManager, key: &str, count: usize) {
+    for index in 0..count {
+        let entry = json!({
+            "email": format!("user{}@example.com", index), "input_tokens": 10,
+            "output_tokens": 1, "requests": 1, "model": "gpt-4o",
+            "timestamp": Utc::now().to_rfc3339()
+        });
+        let _: () = redis.rpush(key, entry.to_string()).await.unwrap();
+    }
+}
+
+async fn clear(redis: &mut redis::aio::ConnectionManager, key: &str) {
+    let _: () = redis
+        .del(&[
+            key.to_string(),
+            format!("{}:lock", key),
+            format!("{}:retry-leader", key),
+        ])
+        .await
+        .unwrap();
+}
+
+/// Tracker for `replica` retrying every 100ms against the queue at `key`
+fn tracker(
+    redis: &redis::aio::ConnectionManager,
+    zion: &MockServer,
+    key: &str,
+    replica: &str,
+) -> BatchingUsageTracker {
+    let config = test_config(&zion.uri(), "http://openai.invalid/v1");
+    BatchingUsageTracker::new(
+        Arc::new(ZionClient::new(reqwest::Client::new(), &config)),
+        redis.clone(),
+        BatchingConfig {
+            retry_interval: Duration::from_millis(100),
+            max_retry_batch: 1,
+            retry_lease_ttl: Duration::from_secs(5),
+            replica_id: replica.to_string(),
+            failed_queue_key: key.to_string(),
+            ..Default::default()
+        },
+    )
+}
+
+/// Single increments a Zion stub received from retries
+async fn retried(zion: &MockServer) -> usize {
+    zion.received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .filter(|request| request.url.path() == "/api/v1/usage/external/increment")
+        .count()
+}
+
+/// Wait until the queue at `key` is empty
+async fn wait_for_drain(redis: &mut redis::aio::ConnectionManager, key: &str) {
+    let deadline = std::time::Instant::now() + Duration::from_secs(10);
+    while std::time::Instant::now() < deadline {
+        let len: usize = redis.llen(key).await.unwrap();
+        if len == 0 {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("queue {} was not drained", key);
+}
+
+#[tokio::test]
+async fn test_only_the_lease_holder_drains() {
+    let mut redis = match get_test_redis().await {
+        Some(r) => r,
+        None => {
+            eprintln!("Skipping test: Redis not available");
+            return;
+        }
+    };
+    let key = "sentinel:test:usage:failed:lease";
+    clear(&mut redis, key).await;
+    seed(&mut redis, key, 6).await;
+
+    let (zion_a, zion_b) = (zion_stub().await, zion_stub().await);
+    let tracker_a = tracker(&redis, &zion_a, key, "replica-a");
+    let tracker_b = tracker(&redis, &zion_b, key, "replica-b");
+
+    // One increment per cycle, so the drain spans several cycles of both
+    wait_for_drain(&mut redis, key).await;
+    let (from_a, from_b) = (retried(&zion_a).await, retried(&zion_b).await);
+    assert_eq!(from_a + from_b, 6);
+    assert!(
+        from_a == 0 || from_b == 0,
+        "both replicas retried: {} / {}",
+        from_a,
+        from_b
+    );
+
+    let (leader, follower, follower_zion) = if from_a > 0 {
+        (tracker_a, tracker_b, &zion_b)
+    } else {
+        (tracker_b, tracker_a, &zion_a)
+    };
+    let status = leader.retry_status().await.unwrap();
+    assert!(status.is_leader);
+    assert_eq!(status.leader.as_deref(), Some(status.replica_id.as_str()));
+    let status = follower.retry_status().await.unwrap();
+    assert!(!status.is_leader);
+    assert_ne!(status.leader.as_deref(), Some(status.replica_id.as_str()));
+
+    // A leader shutting down releases the lease and the other replica takes over
+    drop(leader);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    seed(&mut redis, key, 2).await;
+    wait_for_drain(&mut redis, key).await;
+    assert_eq!(retried(follower_zion).await, 2);
+    assert!(follower.retry_status().await.unwrap().is_leader);
+
+    drop(follower);
+    clear(&mut redis, key).await;
+}
+
+#[tokio::test]
+async fn test_lease_expires_when_its_holder_dies() {
+    let mut redis = match get_test_redis().await {
+        Some(r) => r,
+        None => {
+            eprintln!("Skipping test: Redis not available");
+            return;
+        }
+    };
+    let key = "sentinel:test:usage:failed:lease-expiry";
+    clear(&mut redis, key).await;
+
+    let ttl = Duration::from_millis(300);
+    let a = RetryLease::new(redis.clone(), key, "replica-a", ttl);
+    let b = RetryLease::new(redis.clone(), key, "replica-b", ttl);
+
+    assert!(a.acquire().await.unwrap());
+    assert!(!b.acquire().await.unwrap());
+    assert!(a.acquire().await.unwrap(), "the holder renews");
+    let status = b.status().await.unwrap();
+    assert_eq!(status.leader.as_deref(), Some("replica-a"));
+    assert!(status.expires_in_ms.unwrap() <= 300);
+
+    // replica-a stops renewing
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    assert!(b.acquire().await.unwrap());
+    assert!(!a.renew().await.unwrap());
+    a.release().await.unwrap();
+    assert_eq!(
+        b.status().await.unwrap().leader.as_deref(),
+        Some("replica-b")
+    );
+
+    clear(&mut redis, key).await;
+}
+
+#[tokio::test]
+async fn test_admin_retry_status_without_redis() {
+    let harness = TestHarness::with_config(Arc::new(MockAiProvider::new()), |config| {
+        config.server.admin_api_key = Some(ADMIN_KEY.to_string());
+    })
+    .await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let response = server
+        .get("/admin/usage/retry")
+        .add_header("X-Admin-Key".parse().unwrap(), ADMIN_KEY.parse().unwrap())
+        .await;
+    response.assert_status_ok();
+    let status: Value = response.json();
+    assert!(!status["replica_id"].as_str().unwrap().is_empty());
+    assert!(status["leader"].is_null());
+    assert_eq!(status["is_leader"], false);
+}
@@ -0,0 +1,181 @@
+//! Workflow usage tests
+//!
+//! Requests tagged with a workflow id (the `X-Sentinel-Workflow-Id` header or
+//! the native `workflow_id` field) are summed per workflow and reported by
+//! `GET /v1/usage/workflows/{id}`. Zion still receives plain per-user
+//! increments.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::{header, HeaderValue, StatusCode};
+use axum_test::{TestRequest, TestServer};
+use serde_json::{json, Value};
+
+use sentinel::testing::{
+    constants, extract_token_counts, parse_batch_payload, MockAiProvider, MockEndpoint, MockReply,
+    StreamScript, TestHarness,
+};
+use sentinel::usage::WORKFLOW_ID_HEADER;
+
+const WORKFLOW: &str = "wf-agent-42";
+
+fn authorized(request: TestRequest) -> TestRequest {
+    request.add_header(
+        header::AUTHORIZATION,
+        format!("Bearer {}", constants::TEST_JWT_TOKEN)
+            .parse()
+            .unwrap(),
+    )
+}
+
+fn tagged(request: TestRequest, workflow_id: &'static str) -> TestRequest {
+    authorized(request).add_header(
+        WORKFLOW_ID_HEADER.parse().unwrap(),
+        HeaderValue::from_static(workflow_id),
+    )
+}
+
+async fn workflow_usage(server: &TestServer, workflow_id: &str) -> Value {
+    let response = authorized(server.get(&format!("/v1/usage/workflows/{}", workflow_id))).await;
+    response.assert_status_ok();
+    response.json()
+}
+
+/// Wait until Zion has received `requests` requests' worth of increments
+async fn wait_for_requests(harness: &TestHarness, requests: i64) -> Vec<Value> {
+    let deadline = std::time::Instant::now() + Duration::from_secs(5);
+    loop {
+        let items: Vec<Value> = harness
+            .wait_for_batch_requests(1, Duration::from_secs(2))
+            .await
+            .iter()
+            .flat_map(parse_batch_payload)
+            .collect();
+        let sent: i64 = items.iter().map(|item| extract_token_counts(item).2).sum();
+        if sent >= requests || std::time::Instant::now() > deadline {
+            return items;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
+
+#[tokio::test]
+async fn test_tool_call_roundtrip_totals_per_workflow() {
+    let tool_call = StreamScript::new("gpt-4o-mini")
+        .tool_call("call_1", "get_weather", r#"{"city":"Paris"}"#)
+        .usage(20, 9);
+    let provider = Arc::new(
+        MockAiProvider::new()
+            .with_reply(MockEndpoint::ChatCompletions, tool_call.reply())
+            .with_reply(
+                MockEndpoint::ChatCompletions,
+                MockReply::chat_completion("gpt-4o-mini", "It is sunny.", 35, 6),
+            )
+            .with_reply(
+                MockEndpoint::ChatCompletions,
+                MockReply::chat_completion("gpt-4o-mini", "Done.", 11, 2),
+            )
+            .with_reply(
+                MockEndpoint::ChatCompletions,
+                MockReply::chat_completion("gpt-4o-mini", "Untagged.", 100, 100),
+            ),
+    );
+    let harness = TestHarness::with_provider(provider).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    // The agent asks, runs the tool, and sends the result back
+    tagged(server.post("/v1/chat/completions"), WORKFLOW)
+        .json(&json!({
+            "model": "gpt-4o-mini",
+            "stream": true,
+            "messages": [{"role": "user", "content": "Weather in Paris?"}]
+        }))
+        .await
+        .assert_status_ok();
+    tagged(server.post("/v1/chat/completions"), WORKFLOW)
+        .json(&json!({
+            "model": "gpt-4o-mini",
+            "messages": [
+                {"role": "user", "content": "Weather in Paris?"},
+                {"role": "assistant", "content": null, "tool_calls": [{
+                    "id": "call_1",
+                    "type": "function",
+                    "function": {"name": "get_weather", "arguments": "{\"city\":\"Paris\"}"}
+                }]},
+                {"role": "tool", "tool_call_id": "call_1", "content": "sunny"}
+            ]
+        }))
+        .await
+        .assert_status_ok();
+    // A native step of the same workflow, tagged in the body
+    authorized(server.post("/native/v1/chat/completions"))
+        .json(&json!({
+            "tier": "simple",
+            "workflow_id": WORKFLOW,
+            "messages": [{"role": "user", "content": "Summarize"}]
+        }))
+        .await
+        .assert_status_ok();
+    // Not part of any workflow
+    authorized(server.post("/v1/chat/completions"))
+        .json(&json!({
+            "model": "gpt-4o-mini",
+            "messages": [{"role": "user", "content": "Hi"}]
+        }))
+        .await
+        .assert_status_ok();
+
+    let items = wait_for_requests(&harness, 4).await;
+    let sent = items
+        .iter()
+        .map(extract_token_counts)
+        .fold((0, 0, 0), |acc, (i, o, r)| {
+            (acc.0 + i, acc.1 + o, acc.2 + r)
+        });
+    assert_eq!(sent, (166, 117, 4));
+    // Zion is billed per user only
+    assert!(items.iter().all(|item| item.get("workflow_id").is_none()));
+
+    let usage = workflow_usage(&server, WORKFLOW).await;
+    assert_eq!(usage["workflow_id"], WORKFLOW);
+    assert_eq!(usage["requests"], 3);
+    assert_eq!(usage["input_tokens"], 66);
+    assert_eq!(usage["output_tokens"], 17);
+
+    let other = workflow_usage(&server, "wf-unknown").await;
+    assert_eq!(other["requests"], 0);
+    assert_eq!(other["input_tokens"], 0);
+}
+
+#[tokio::test]
+async fn test_invalid_workflow_ids_are_rejected() {
+    let provider = Arc::new(MockAiProvider::new());
+    let harness = TestHarness::with_provider(provider).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    tagged(server.post("/v1/chat/completions"), "not valid!")
+        .json(&json!({
+            "model": "gpt-4o-mini",
+            "messages": [{"role": "user", "content": "Hi"}]
+        }))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    let response = authorized(server.post("/native/v1/chat/completions"))
+        .json(&json!({
+            "workflow_id": "a/b",
+            "messages": [{"role": "user", "content": "Hi"}]
+        }))
+        .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+    let error: Value = response.json();
+    assert!(error["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("workflow_id"));
+
+    authorized(server.get(&format!("/v1/usage/workflows/{}", "x".repeat(129))))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
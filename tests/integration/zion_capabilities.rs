@@ -0,0 +1,144 @@
+//! Zion batch payload negotiation tests
+//!
+//! Each test points Sentinel at a Zion mock advertising a different set of
+//! capabilities at `/api/v1/meta` and checks which optional fields the
+//! batch-increment payload carries. The negotiated set is served at
+//! `/admin/zion/capabilities`.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::header;
+use axum_test::TestServer;
+use serde_json::{json, Value};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use sentinel::testing::{
+    constants, parse_batch_payload, MockAiProvider, MockEndpoint, MockReply, TestHarness,
+};
+
+const ADMIN_KEY: &str = "admin-secret";
+
+/// Harness whose Zion answers the meta endpoint with `meta`
+async fn harness(meta: ResponseTemplate) -> TestHarness {
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_completion("gpt-4o-mini", "Hello", 10, 5),
+    ));
+    let harness = TestHarness::with_config(provider, |config| {
+        config.server.admin_api_key = Some(ADMIN_KEY.to_string());
+    })
+    .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/meta"))
+        .respond_with(meta)
+        .mount(&harness.zion)
+        .await;
+    harness
+}
+
+fn advertising(capabilities: &[&str]) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({
+        "success": true,
+        "data": {"apiVersion": "2.1.0", "capabilities": capabilities}
+    }))
+}
+
+/// Send one chat request and return the single batch item it produced
+async fn tracked_item(harness: &TestHarness, server: &TestServer) -> Value {
+    server
+        .post("/v1/chat/completions")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN).parse().unwrap(),
+        )
+        .json(&json!({
+            "model": "gpt-4o-mini",
+            "messages": [{"role": "user", "content": "Hi"}]
+        }))
+        .await
+        .assert_status_ok();
+
+    let requests = harness.wait_for_batch_requests(1, Duration::from_secs(5)).await;
+    assert!(!requests.is_empty(), "Expected a batch-increment request");
+    parse_batch_payload(&requests[0])[0].clone()
+}
+
+async fn admin_capabilities(server: &TestServer) -> Value {
+    let response = server
+        .get("/admin/zion/capabilities")
+        .add_header("X-Admin-Key".parse().unwrap(), ADMIN_KEY.parse().unwrap())
+        .await;
+    response.assert_status_ok();
+    response.json()
+}
+
+#[tokio::test]
+async fn test_full_capabilities_send_optional_fields() {
+    let harness = harness(advertising(&["batch.model", "batch.timestamp", "batch.organization"])).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let item = tracked_item(&harness, &server).await;
+    assert_eq!(item["model"], "gpt-4o-mini");
+    assert!(item["timestamp"].is_string());
+    assert_eq!(item["aiRequests"], 1);
+
+    let capabilities = admin_capabilities(&server).await;
+    assert_eq!(capabilities["source"], "meta");
+    assert_eq!(capabilities["api_version"], "2.1.0");
+    assert_eq!(capabilities["batch_fields"], json!(["model", "timestamp", "organizationId"]));
+}
+
+#[tokio::test]
+async fn test_partial_capabilities_downgrade_payload() {
+    let harness = harness(advertising(&["batch.model", "batch.rollups"])).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let item = tracked_item(&harness, &server).await;
+    assert_eq!(item["model"], "gpt-4o-mini");
+    assert!(item.get("timestamp").is_none());
+
+    let capabilities = admin_capabilities(&server).await;
+    assert_eq!(capabilities["capabilities"], json!(["batch.model", "batch.rollups"]));
+    assert_eq!(capabilities["batch_fields"], json!(["model"]));
+}
+
+#[tokio::test]
+async fn test_meta_failure_sends_legacy_payload() {
+    let harness = harness(ResponseTemplate::new(404)).await;
+    let server = TestServer::new(harness.router()).unwrap();
+
+    let item = tracked_item(&harness, &server).await;
+    let mut fields: Vec<&str> = item.as_object().unwrap().keys().map(String::as_str).collect();
+    fields.sort();
+    assert_eq!(fields, vec!["aiInputTokens", "aiOutputTokens", "aiRequests", "email"]);
+
+    let capabilities = admin_capabilities(&server).await;
+    assert_eq!(capabilities["source"], "legacy");
+    assert_eq!(capabilities["batch_fields"], json!([]));
+    assert!(capabilities["error"].as_str().unwrap().contains("404"));
+}
+
+#[tokio::test]
+async fn test_admin_refresh_picks_up_new_capabilities() {
+    let harness = harness(ResponseTemplate::new(503)).await;
+    let server = TestServer::new(harness.router()).unwrap();
+    assert_eq!(admin_capabilities(&server).await["source"], "legacy");
+
+    harness.zion.reset().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/meta"))
+        .respond_with(advertising(&["batch.timestamp"]))
+        .mount(&harness.zion)
+        .await;
+
+    let response = server
+        .get("/admin/zion/capabilities")
+        .add_query_param("refresh", "true")
+        .add_header("X-Admin-Key".parse().unwrap(), ADMIN_KEY.parse().unwrap())
+        .await;
+    let capabilities: Value = response.json();
+    assert_eq!(capabilities["source"], "meta");
+    assert_eq!(capabilities["batch_fields"], json!(["timestamp"]));
+}
@@ -0,0 +1,126 @@
+//! Zion limits payload robustness tests
+//!
+//! Partial or unexpected limits payloads (e.g. during a plan migration) must
+//! not break request handling. A missing `ai_usage` entry admits or refuses
+//! the user according to `MISSING_LIMIT_POLICY`.
+
+use std::sync::Arc;
+
+use axum::http::{header, StatusCode};
+use axum_test::{TestResponse, TestServer};
+use serde_json::{json, Value};
+use wiremock::matchers::{method, path_regex};
+use wiremock::{Mock, ResponseTemplate};
+
+use sentinel::testing::{constants, MockAiProvider, MockEndpoint, MockReply, TestHarness};
+use sentinel::usage::limits;
+use sentinel::zion::{resolve_limit, MissingLimitPolicy};
+
+#[tokio::test]
+async fn test_limits_without_ai_usage_do_not_break_requests() {
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_completion("gpt-4o-mini", "Hello!", 5, 2),
+    ));
+    let harness = TestHarness::with_provider(provider).await;
+
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/api/v1/limits/external/.+$"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "success": true,
+            "data": {
+                "userId": constants::TEST_USER_ID,
+                "externalId": constants::TEST_EXTERNAL_ID,
+                "limits": [
+                    {"name": "seats", "limit": 5, "used": 1},
+                    {
+                        "name": "legacy_usage",
+                        "displayName": "Legacy",
+                        "aiInputTokens": {"limit": 10, "used": 0, "remaining": 10},
+                        "aiOutputTokens": {"limit": 10, "used": 0, "remaining": 10},
+                        "aiRequests": {"limit": 1, "used": 0, "remaining": 1},
+                        "resetPeriod": "QUARTERLY"
+                    }
+                ]
+            }
+        })))
+        .mount(&harness.zion)
+        .await;
+
+    let server = TestServer::new(harness.router()).unwrap();
+    let response = server
+        .post("/v1/chat/completions")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN).parse().unwrap(),
+        )
+        .json(&json!({
+            "model": "gpt-4o-mini",
+            "messages": [{"role": "user", "content": "Hi"}]
+        }))
+        .await;
+    response.assert_status_ok();
+
+    // The missing entry resolves through the configured policy
+    let user_limits = harness
+        .state
+        .subscription_cache
+        .get_user_limits(constants::TEST_EXTERNAL_ID)
+        .await
+        .unwrap();
+    let limit = resolve_limit(
+        &user_limits,
+        limits::AI_USAGE,
+        harness.state.config.zion.missing_limit_policy,
+    );
+    assert_eq!(harness.state.config.zion.missing_limit_policy, MissingLimitPolicy::Unlimited);
+    assert_eq!(limit.ai_requests.remaining, i64::MAX);
+}
+
+async fn chat_with_policy(policy: MissingLimitPolicy) -> TestResponse {
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_completion("gpt-4o-mini", "Hello!", 5, 2),
+    ));
+    let harness = TestHarness::with_config(provider, |config| {
+        config.zion.missing_limit_policy = policy;
+    })
+    .await;
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/api/v1/limits/external/.+$"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "success": true,
+            "data": {
+                "userId": constants::TEST_USER_ID,
+                "externalId": constants::TEST_EXTERNAL_ID,
+                "limits": [{"name": "seats", "limit": 5, "used": 1}]
+            }
+        })))
+        .mount(&harness.zion)
+        .await;
+
+    TestServer::new(harness.router())
+        .unwrap()
+        .post("/v1/chat/completions")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN).parse().unwrap(),
+        )
+        .json(&json!({
+            "model": "gpt-4o-mini",
+            "messages": [{"role": "user", "content": "Hi"}]
+        }))
+        .await
+}
+
+#[tokio::test]
+async fn test_missing_ai_usage_follows_policy() {
+    chat_with_policy(MissingLimitPolicy::Unlimited)
+        .await
+        .assert_status_ok();
+
+    let response = chat_with_policy(MissingLimitPolicy::Zero).await;
+    response.assert_status(StatusCode::TOO_MANY_REQUESTS);
+    let body: Value = response.json();
+    assert_eq!(body["error"]["code"], "QUOTA_EXCEEDED");
+}
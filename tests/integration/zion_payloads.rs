@@ -0,0 +1,116 @@
+//! Zion payload casing tests
+//!
+//! Usage increments are sent in the casing set by `ZION_PAYLOAD_CASE`, and
+//! Zion responses are read whether their fields are camelCase or snake_case.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::header;
+use axum_test::TestServer;
+use serde_json::json;
+use wiremock::matchers::{method, path_regex};
+use wiremock::{Mock, ResponseTemplate};
+
+use sentinel::testing::{
+    constants, parse_batch_payload, MockAiProvider, MockEndpoint, MockReply, TestHarness,
+};
+use sentinel::usage::limits;
+use sentinel::zion::{resolve_limit, PayloadCase};
+
+async fn harness(payload_case: PayloadCase) -> (TestHarness, TestServer) {
+    let provider = Arc::new(MockAiProvider::new().with_reply(
+        MockEndpoint::ChatCompletions,
+        MockReply::chat_completion("gpt-4o-mini", "Hello!", 10, 5),
+    ));
+    let harness = TestHarness::with_config(provider, |config| {
+        config.zion.payload_case = payload_case;
+    })
+    .await;
+    let server = TestServer::new(harness.router()).unwrap();
+    (harness, server)
+}
+
+async fn chat(server: &TestServer) -> axum_test::TestResponse {
+    server
+        .post("/v1/chat/completions")
+        .add_header(
+            header::AUTHORIZATION,
+            format!("Bearer {}", constants::TEST_JWT_TOKEN)
+                .parse()
+                .unwrap(),
+        )
+        .json(&json!({
+            "model": "gpt-4o-mini",
+            "messages": [{"role": "user", "content": "Hi"}]
+        }))
+        .await
+}
+
+#[tokio::test]
+async fn test_snake_case_batch_payload() {
+    let (harness, server) = harness(PayloadCase::Snake).await;
+
+    chat(&server).await.assert_status_ok();
+
+    let batches = harness
+        .wait_for_batch_requests(1, Duration::from_secs(3))
+        .await;
+    assert!(!batches.is_empty(), "Expected batch-increment request");
+    let items = parse_batch_payload(&batches[0]);
+    let item = items[0].as_object().unwrap();
+    assert_eq!(item["email"], constants::TEST_EMAIL);
+    assert_eq!(item["ai_input_tokens"], 10);
+    assert_eq!(item["ai_output_tokens"], 5);
+    assert_eq!(item["ai_requests"], 1);
+    assert!(
+        item.keys()
+            .all(|key| key.chars().all(|c| !c.is_ascii_uppercase())),
+        "camelCase key in {:?}",
+        item
+    );
+}
+
+#[tokio::test]
+async fn test_snake_case_limits_response() {
+    let (harness, server) = harness(PayloadCase::Camel).await;
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/api/v1/limits/external/.+$"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "success": true,
+            "data": {
+                "user_id": constants::TEST_USER_ID,
+                "external_id": constants::TEST_EXTERNAL_ID,
+                "limits": [{
+                    "name": "ai_usage",
+                    "display_name": "AI Usage",
+                    "ai_input_tokens": {"limit": 1000, "used": 0, "remaining": 1000},
+                    "ai_output_tokens": {"limit": 1000, "used": 0, "remaining": 1000},
+                    "ai_requests": {"limit": 10, "used": 1, "remaining": 9},
+                    "reset_period": "DAILY",
+                    "period_start": null,
+                    "period_end": null
+                }]
+            }
+        })))
+        .mount(&harness.zion)
+        .await;
+
+    chat(&server).await.assert_status_ok();
+
+    // The snake_case entry is read rather than skipped as unrecognized
+    let user_limits = harness
+        .state
+        .subscription_cache
+        .get_user_limits(constants::TEST_EXTERNAL_ID)
+        .await
+        .unwrap();
+    let limit = resolve_limit(
+        &user_limits,
+        limits::AI_USAGE,
+        harness.state.config.zion.missing_limit_policy,
+    );
+    assert_eq!(limit.display_name, "AI Usage");
+    assert_eq!(limit.ai_requests.limit, 10);
+    assert_eq!(limit.ai_requests.remaining, 9);
+}
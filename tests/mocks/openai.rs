@@ -111,6 +111,16 @@ impl MockOpenAI {
             .await;
     }
 
+    /// Mock chat completions answering with a prebuilt response (e.g. `StreamScript::response()`)
+    pub async fn mock_chat_completion_response(&self, response: ResponseTemplate) {
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .and(header_exists("Authorization"))
+            .respond_with(response)
+            .mount(&self.server)
+            .await;
+    }
+
     /// Mock chat completion with custom token usage
     pub async fn mock_chat_completion_with_usage(
         &self,
@@ -149,7 +149,7 @@ impl MockZionServer {
     pub async fn mock_increment_usage_success(&self, updated_limit: UserLimitMock) {
         let response = IncrementUsageResponseMock {
             success: true,
-            data: updated_limit,
+            data: IncrementUsageDataMock::from_limit(&updated_limit, true),
         };
 
         Mock::given(method("POST"))
@@ -165,7 +165,7 @@ impl MockZionServer {
     pub async fn mock_increment_usage_limit_exceeded(&self, limit: UserLimitMock) {
         let response = IncrementUsageResponseMock {
             success: true,
-            data: limit,
+            data: IncrementUsageDataMock::from_limit(&limit, false),
         };
 
         Mock::given(method("POST"))
@@ -489,12 +489,33 @@ pub struct IncrementUsageRequestMock {
     pub ai_requests: Option<i64>,
 }
 
+/// Increment usage response data (metrics after the increment)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IncrementUsageDataMock {
+    pub can_use: bool,
+    pub ai_input_tokens: LimitMetricMock,
+    pub ai_output_tokens: LimitMetricMock,
+    pub ai_requests: LimitMetricMock,
+}
+
+impl IncrementUsageDataMock {
+    pub fn from_limit(limit: &UserLimitMock, can_use: bool) -> Self {
+        Self {
+            can_use,
+            ai_input_tokens: limit.ai_input_tokens.clone(),
+            ai_output_tokens: limit.ai_output_tokens.clone(),
+            ai_requests: limit.ai_requests.clone(),
+        }
+    }
+}
+
 /// Increment usage response
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct IncrementUsageResponseMock {
     pub success: bool,
-    pub data: UserLimitMock,
+    pub data: IncrementUsageDataMock,
 }
 
 /// Batch increment item
@@ -895,4 +916,111 @@ mod tests {
         assert_eq!(limit.ai_requests.limit, 100);
         assert_eq!(limit.ai_requests.remaining, 90);
     }
+
+    /// Serialize a mock payload and parse it as Sentinel does
+    fn parse_as<T>(endpoint: &str, mock: &impl Serialize) -> T
+    where
+        T: serde::de::DeserializeOwned + Serialize,
+    {
+        let body = serde_json::to_value(mock).unwrap();
+        let parsed: T = sentinel::zion::parse_response(endpoint, &body.to_string())
+            .unwrap_or_else(|e| panic!("{} mock doesn't match the Zion models: {}", endpoint, e));
+        let typed = serde_json::to_value(&parsed).unwrap();
+        assert_eq!(
+            sentinel::zion::unknown_fields(&body, &typed),
+            Vec::<String>::new(),
+            "{} mock has fields the Zion models don't know",
+            endpoint
+        );
+        parsed
+    }
+
+    #[test]
+    fn test_payloads_match_sentinel_models() {
+        use sentinel::zion::{
+            BatchIncrementResponse, ExternalLimitsResponse, IncrementUsageResponse,
+            TierConfigResponse, UserProfileResponse,
+        };
+
+        for limits in [
+            ZionTestData::free_tier_limits(),
+            ZionTestData::pro_tier_limits(),
+            ZionTestData::nearly_exhausted_limits(),
+            ZionTestData::exhausted_limits(),
+        ] {
+            let response: ExternalLimitsResponse = parse_as(
+                "limits",
+                &ExternalLimitsResponseMock {
+                    success: true,
+                    data: ExternalLimitsDataMock {
+                        user_id: "usr_1".to_string(),
+                        external_id: "ext_1".to_string(),
+                        limits: limits.clone(),
+                    },
+                },
+            );
+            assert_eq!(response.data.limits.len(), limits.len());
+            assert_eq!(
+                response.data.limits[0].ai_requests.remaining,
+                limits[0].ai_requests.remaining
+            );
+        }
+
+        let limit = ZionTestData::exhausted_limits().remove(0);
+        for can_use in [true, false] {
+            let response: IncrementUsageResponse = parse_as(
+                "increment",
+                &IncrementUsageResponseMock {
+                    success: true,
+                    data: IncrementUsageDataMock::from_limit(&limit, can_use),
+                },
+            );
+            assert_eq!(response.data.can_use, can_use);
+        }
+
+        let response: BatchIncrementResponse = parse_as(
+            "batch-increment",
+            &BatchIncrementResponseMock {
+                success: true,
+                data: BatchIncrementDataMock {
+                    processed: 1,
+                    failed: 1,
+                    results: vec![BatchIncrementResultMock {
+                        email: "a@example.com".to_string(),
+                        success: false,
+                        error: Some("Failed to increment".to_string()),
+                    }],
+                },
+            },
+        );
+        assert_eq!(response.data.results[0].email, "a@example.com");
+
+        for profile in [
+            ZionTestData::default_profile("ext_1"),
+            ZionTestData::unverified_profile("ext_1"),
+        ] {
+            let response: UserProfileResponse = parse_as(
+                "users/me",
+                &UserProfileResponseMock {
+                    success: true,
+                    data: profile.clone(),
+                },
+            );
+            assert_eq!(response.data.email_verified, profile.email_verified);
+        }
+
+        for config in [
+            ZionTestData::default_tier_config(),
+            ZionTestData::tier_config_with("a", "b", "c"),
+        ] {
+            let response: TierConfigResponse = parse_as(
+                "tiers/config",
+                &TierConfigResponseMock {
+                    success: true,
+                    data: config,
+                },
+            );
+            assert_eq!(response.data.tiers.simple.len(), 1);
+        }
+    }
 }